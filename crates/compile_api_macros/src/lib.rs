@@ -29,7 +29,7 @@
 // ! Notice:
 // ! - The function is now `async` and `pub`
 // ! - Return type is `Result<HttpResponse, NanoServiceError>`
-// ! - Original arguments and body are preserved
+// ! - Original arguments, body, doc comments, and attributes are preserved
 // ! 
 // ! ## Endpoint with DAL but no token
 // ! If your endpoint requires database traits but you do not need token checks, specify `db_traits`:
@@ -97,7 +97,8 @@
 // ! where
 // !     X: One + Two + Three,
 // !     Y: utils::config::GetConfigVariable + Send,
-// !     Z: kernel::token::session_cache::traits::GetAuthCacheSession,
+// !     Z: kernel::token::session_cache::traits::GetAuthCacheSession
+// !         + kernel::token::session_cache::traits::TouchAuthCacheSession,
 // ! {
 // !     let user_session = match Z::get_auth_cache_session(&jwt).await {
 // !         Ok(Some(session)) => session,
@@ -117,40 +118,183 @@
 // ! Here the `jwt` is passed in and the session is extracted from the cache. This means that on top
 // ! of the `X` dal handle, the developer also has access to the `jwt` and the `user_session` extracted
 // ! from the cache when using the macro.
+// ! 
+// ! The session lookup and the body actually run inside `utils::telemetry::instrument_endpoint`, in an
+// ! `api_endpoint` span tagged with the handler name, `jwt.role` and `jwt.org_id`, so traces can be
+// ! sliced by role and tenant. The user ID is deliberately left off the span.
+// ! 
+// ! They also run inside `kernel::tenancy::with_tenant` with `jwt.org_id`, so the DAL scopes the
+// ! to-do items it reads and changes to the caller's organization.
+// ! 
+// ! The check's `check_permissions` is then run against `user_session.permissions`. This is a no-op for
+// ! the role checks, but `token=PermissionCheck<TodoAssignPermission>` rejects sessions without the
+// ! `todo:assign` permission with a 403.
+// ! 
+// ! ## Sliding expiration
+// ! Once the session is loaded `kernel::token::sliding::slide_session` refuses it with a 401 if it
+// ! has been idle for longer than `SESSION_IDLE_TIMEOUT_MINUTES`, records the request as its
+// ! `last_seen`, and re-issues the token when it is close to expiring. The body runs inside
+// ! `kernel::token::sliding::with_refreshed_token`, which sends the re-issued token back in the
+// ! `X-Refreshed-Token` header of a successful response, and in the token cookie for browser
+// ! sessions. This is why `Z` is also bound by `TouchAuthCacheSession`.
+// ! 
+// ! ## Optional sessions
+// ! By default a token whose session is no longer in the cache is rejected with a 401. Passing
+// ! `session_optional=true` binds `user_session: Option<AuthCacheSession>` instead so the endpoint
+// ! can decide what an expired or flushed session means:
+// ! ```no_run
+// ! #[api_endpoint(token=NoRoleCheck, session_optional=true)]
+// ! fn maybe_session() {
+// !     match user_session {
+// !         Some(session) => { /* full response */ },
+// !         None => { /* degraded response */ }
+// !     }
+// ! }
+// ! ```
+// ! Errors from the cache itself are still returned, and a session that is there still slides.
+// ! 
+// ! ## API keys
+// ! Endpoints called by cron jobs and integrations rather than users can take an API key in the
+// ! `X-Api-Key` header instead of a token with `auth=ApiKey`:
+// ! ```no_run
+// ! #[api_endpoint(auth=ApiKey, db_traits=[One])]
+// ! fn for_services() {
+// !     let name = api_key.name;
+// ! }
+// ! ```
+// ! This expands to:
+// ! ```no_run
+// ! pub async fn for_services<X>(
+// !     api_key_token: kernel::api_keys::ApiKeyToken,
+// ! ) -> Result<actix_web::HttpResponse, utils::errors::NanoServiceError>
+// ! where
+// !     X: dal::api_keys::tx_definitions::UseApiKey + One,
+// ! {
+// !     let api_key = kernel::api_keys::check_api_key(
+// !         <X as dal::api_keys::tx_definitions::UseApiKey>::use_api_key(api_key_token.key_hash).await?
+// !     )?;
+// !     let name = api_key.name;
+// ! }
+// ! ```
+// ! Unknown and revoked keys are rejected with a 401. There is no `jwt`, `user_session` or `Z` cache
+// ! handle, and `Y` is only added with `env_variable_trait=true`. It cannot be used with a `token`.
+// ! 
+// ! ## Confirmed users
+// ! A token outlives changes to the account it was issued for, so every endpoint with a `token` loads the
+// ! user row after the session check and refuses blocked and unconfirmed users with a 403, and users
+// ! deleted since the token was issued with a 401:
+// ! ```no_run
+// ! #[api_endpoint(token=NoRoleCheck, db_traits=[One])]
+// ! fn confirmed_only() {
+// !     let email = confirmed_user.email;
+// ! }
+// ! ```
+// ! This adds `dal::users::tx_definitions::GetUser` to the `X` bounds, introducing `X` if there are no
+// ! `db_traits`, and binds the row as `confirmed_user`. The endpoints that confirm a user or resend the
+// ! confirmation email opt out with `confirmed_user=false`. `confirmed_user=true` needs a `token`.
+// ! 
+// ! ## Extra session cache traits
+// ! The `Z` cache handle is always bound by `GetAuthCacheSession` and `TouchAuthCacheSession`. Endpoints that need more from the cache
+// ! can add bounds with `cache_traits`:
+// ! ```no_run
+// ! #[api_endpoint(token=SuperAdminRoleCheck, cache_traits=[FlushAuthCacheSessions])]
+// ! fn flush() {
+// !     let removed = Z::flush_auth_cache_sessions().await?;
+// ! }
+// ! ```
+// ! This gives `Z: kernel::token::session_cache::traits::GetAuthCacheSession + kernel::token::session_cache::traits::TouchAuthCacheSession + FlushAuthCacheSessions`.
+// ! 
+// ! ## Generics of your own
+// ! Generic parameters declared on the function are kept after the generated ones, with their
+// ! bounds, for collaborators that are not a DAL, email, config, or cache trait:
+// ! ```no_run
+// ! #[api_endpoint(token=AdminRoleCheck, db_traits=[One])]
+// ! fn store_func<O: StoreObject>(val: i32) {
+// !     O::put_object::<Y>("key", Vec::new(), "text/plain").await?;
+// ! }
+// ! ```
+// ! This expands to `pub async fn store_func<X, Y, Z, O: StoreObject>(...)`, so the route names
+// ! the store last, such as `store_func::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>, ConfiguredObjectStore>`.
+// ! 
+// ! ## Naming the generic parameters
+// ! The `W`, `X`, `Y`, and `Z` letters are only defaults. They can be renamed with `email_param`,
+// ! `db_param`, `config_param`, and `cache_param` so the handler body reads better or avoids clashing
+// ! with your own generics:
+// ! ```no_run
+// ! #[api_endpoint(token=AdminRoleCheck, db_traits=[One], db_param=Db, config_param=Config, cache_param=Cache)]
+// ! fn named_func(val: i32) {
+// !     let outcome = Db::one(val).await?;
+// ! }
+// ! ```
+// ! This expands to:
+// ! ```no_run
+// ! pub async fn named_func<Db, Config, Cache>(
+// !     jwt: kernel::token::token::HeaderToken<Config, kernel::token::checks::AdminRoleCheck>,
+// !     val: i32,
+// ! ) -> Result<actix_web::HttpResponse, utils::errors::NanoServiceError>
+// ! where
+// !     Db: One,
+// !     Config: utils::config::GetConfigVariable + Send,
+// !     Cache: kernel::token::session_cache::traits::GetAuthCacheSession
+// !         + kernel::token::session_cache::traits::TouchAuthCacheSession,
+// ! {
+// !     // session extraction and function body
+// ! }
+// ! ```
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 use quote::quote;
 use syn::{
     parse_macro_input, parse::Parse, parse::ParseStream,
-    ItemFn, Ident, Token, Result, bracketed, LitBool
+    ItemFn, Ident, Token, Result, bracketed, LitBool, Type
 };
 
 
 // Struct to parse macro attributes
 struct ApiEndpointArgs {
-    token_type: Option<Ident>,
+    token_type: Option<Type>,
+    auth: Option<Ident>,
     db_traits: Vec<Ident>,
     email_traits: Vec<Ident>,
+    cache_traits: Vec<Ident>,
     env_variable_trait: bool,
+    session_optional: bool,
+    confirmed_user: Option<bool>,
+    email_param: Ident,
+    db_param: Ident,
+    config_param: Ident,
+    cache_param: Ident,
 }
 
 impl Parse for ApiEndpointArgs {
     fn parse(input: ParseStream) -> Result<Self> {
         let mut token_type = None;
+        let mut auth = None;
         let mut db_traits = Vec::new();
         let mut email_traits = Vec::new();
+        let mut cache_traits = Vec::new();
         let mut env_variable_trait = false;
+        let mut session_optional = false;
+        let mut confirmed_user = None;
+        let mut email_param = Ident::new("W", Span::call_site());
+        let mut db_param = Ident::new("X", Span::call_site());
+        let mut config_param = Ident::new("Y", Span::call_site());
+        let mut cache_param = Ident::new("Z", Span::call_site());
 
         while !input.is_empty() {
             let key: Ident = input.parse()?; // Read key (e.g., "token" or "traits")
             input.parse::<Token![=]>()?; // Expect '='
 
             if key == "token" {
-                // Read token type (e.g., "SomeThing")
+                // Read token type (e.g., "SomeThing" or "PermissionCheck<SomePermission>")
                 if input.peek(Ident) {
                     token_type = Some(input.parse()?);
                 }
+            } else if key == "auth" {
+                // Read the alternative to a token (e.g., "ApiKey")
+                auth = Some(input.parse()?);
             } else if key == "db_traits" {
                 // Read traits inside brackets `[Trait1, Trait2]`
                 let content;
@@ -171,12 +315,36 @@ impl Parse for ApiEndpointArgs {
                         content.parse::<Token![,]>()?; // Consume comma
                     }
                 }
+            } else if key == "cache_traits" {
+                // Read traits inside brackets `[Trait1, Trait2]`
+                let content;
+                bracketed!(content in input);
+                while !content.is_empty() {
+                    cache_traits.push(content.parse()?); // Read each trait
+                    if content.peek(Token![,]) {
+                        content.parse::<Token![,]>()?; // Consume comma
+                    }
+                }
             } else if key == "env_variable_trait" {
                 // Parse next token as a boolean literal
                 let bool_lit: LitBool = input.parse()?;
                 if bool_lit.value() {
                     env_variable_trait = bool_lit.value();
                 }
+            } else if key == "session_optional" {
+                let bool_lit: LitBool = input.parse()?;
+                session_optional = bool_lit.value();
+            } else if key == "confirmed_user" {
+                let bool_lit: LitBool = input.parse()?;
+                confirmed_user = Some(bool_lit.value());
+            } else if key == "email_param" {
+                email_param = input.parse()?;
+            } else if key == "db_param" {
+                db_param = input.parse()?;
+            } else if key == "config_param" {
+                config_param = input.parse()?;
+            } else if key == "cache_param" {
+                cache_param = input.parse()?;
             }
 
             if input.peek(Token![,]) {
@@ -184,13 +352,19 @@ impl Parse for ApiEndpointArgs {
             }
         }
 
-        Ok(ApiEndpointArgs { token_type, db_traits, email_traits, env_variable_trait })
+        Ok(ApiEndpointArgs {
+            token_type, auth, db_traits, email_traits, cache_traits, env_variable_trait, session_optional,
+            confirmed_user, email_param, db_param, config_param, cache_param
+        })
     }
 }
 
 #[proc_macro_attribute]
 pub fn api_endpoint(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let ApiEndpointArgs { token_type, db_traits, email_traits, env_variable_trait } = parse_macro_input!(attr as ApiEndpointArgs);
+    let ApiEndpointArgs {
+        token_type, auth, db_traits, email_traits, cache_traits, env_variable_trait, session_optional,
+        confirmed_user, email_param, db_param, config_param, cache_param
+    } = parse_macro_input!(attr as ApiEndpointArgs);
 
     // define the status
     let mut token = false;
@@ -202,12 +376,40 @@ pub fn api_endpoint(attr: TokenStream, item: TokenStream) -> TokenStream {
     let fn_inputs = &input_fn.sig.inputs;
     let fn_body = &input_fn.block.stmts;
     let fn_name = &input_fn.sig.ident;
+    let fn_attrs = &input_fn.attrs;
+
+    if confirmed_user == Some(true) && token_type.is_none() {
+        return syn::Error::new(fn_name.span(), "`confirmed_user=true` needs a `token`")
+            .to_compile_error()
+            .into();
+    }
+    // token endpoints check the user is confirmed unless they opt out
+    let confirmed_user = confirmed_user.unwrap_or(token_type.is_some());
+    let api_key = match &auth {
+        Some(auth) if auth != "ApiKey" => {
+            return syn::Error::new(auth.span(), "`auth` only supports `ApiKey`")
+                .to_compile_error()
+                .into();
+        }
+        Some(auth) if token_type.is_some() => {
+            return syn::Error::new(auth.span(), "`auth=ApiKey` cannot be used with a `token`")
+                .to_compile_error()
+                .into();
+        }
+        Some(_) => true,
+        None => false
+    };
 
     let processed_inputs = match token_type.clone() {
         Some(token_type) => {
             token = true;
             quote! {
-                jwt: kernel::token::token::HeaderToken<Y, kernel::token::checks::#token_type>, #fn_inputs
+                jwt: kernel::token::token::HeaderToken<#config_param, kernel::token::checks::#token_type>, #fn_inputs
+            }
+        }
+        None if api_key => {
+            quote! {
+                api_key_token: kernel::api_keys::ApiKeyToken, #fn_inputs
             }
         }
         None => {
@@ -217,9 +419,24 @@ pub fn api_endpoint(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
     let session_call = match token_type {
-        Some(_) => {
+        Some(token_type) if session_optional => {
+            quote! {
+                let user_session: Option<kernel::token::session_cache::structs::AuthCacheSession> =
+                    #cache_param::get_auth_cache_session(&jwt).await?;
+                <kernel::token::checks::#token_type as kernel::token::checks::CheckUserRole>::check_permissions(
+                    user_session.as_ref().map(|session| session.permissions.as_slice()).unwrap_or(&[])
+                )?;
+                let refreshed_token = match &user_session {
+                    Some(session) => kernel::token::sliding::slide_session::<
+                        #config_param, kernel::token::checks::#token_type, #cache_param
+                    >(&jwt, session).await?,
+                    None => None
+                };
+            }
+        }
+        Some(token_type) => {
             quote! {
-                let user_session = match Z::get_auth_cache_session(&jwt).await {
+                let user_session = match #cache_param::get_auth_cache_session(&jwt).await {
                     Ok(Some(session)) => {session},
                     Ok(None) => {
                         return Err(utils::errors::NanoServiceError::new(
@@ -231,63 +448,117 @@ pub fn api_endpoint(attr: TokenStream, item: TokenStream) -> TokenStream {
                         return Err(e)
                     }
                 };
+                <kernel::token::checks::#token_type as kernel::token::checks::CheckUserRole>::check_permissions(
+                    &user_session.permissions
+                )?;
+                let refreshed_token = kernel::token::sliding::slide_session::<
+                    #config_param, kernel::token::checks::#token_type, #cache_param
+                >(&jwt, &user_session).await?;
             }
         }
         None => {
             quote! {}
         }
     };
-
-
-    let (email_trait_stub, email_trait_bounds) = if email_traits.is_empty() {
-        (quote! { }, quote! { })
-    } else {
-        if db_traits.is_empty() {
-            (quote! {W}, quote! { W: #(#email_traits)+* })
-        } else {
-            (quote! {W,}, quote! { W: #(#email_traits)+* , })
+    let confirmed_user_call = if confirmed_user {
+        quote! {
+            let confirmed_user = kernel::users::check_confirmed_user(
+                <#db_param as dal::users::tx_definitions::GetUser>::get_user(jwt.user_id).await
+            )?;
         }
-    };
-
-    let (dal_trait_stub, dal_trait_bounds) = if db_traits.is_empty() {
-        (quote! { }, quote! { })
     } else {
-        if token == false && env_variable_trait == false {
-            (quote! {X}, quote! { X: #(#db_traits)+* })
-        } else {
-            (quote! {X,}, quote! { X: #(#db_traits)+* , })
+        quote! {}
+    };
+    let api_key_call = if api_key {
+        quote! {
+            let api_key = kernel::api_keys::check_api_key(
+                <#db_param as dal::api_keys::tx_definitions::UseApiKey>::use_api_key(api_key_token.key_hash).await?
+            )?;
         }
+    } else {
+        quote! {}
     };
 
-    let (config_trait_stub, config_trait_bounds) = if token == false && env_variable_trait == false {
-        (quote! { }, quote! { })
-    } else {
-        if token == false {
-            (quote! {Y}, quote! { Y: utils::config::GetConfigVariable + Send })
-        } else {
-            (quote! {Y,}, quote! { Y: utils::config::GetConfigVariable + Send, })
+
+    // Collect the generic parameters and their bounds in W, X, Y, Z order
+    let mut generic_params = Vec::new();
+    let mut generic_bounds = Vec::new();
+    if !email_traits.is_empty() {
+        generic_params.push(email_param.clone());
+        generic_bounds.push(quote! { #email_param: #(#email_traits)+* });
+    }
+    if confirmed_user {
+        generic_params.push(db_param.clone());
+        generic_bounds.push(quote! { #db_param: dal::users::tx_definitions::GetUser #(+ #db_traits)* });
+    } else if api_key {
+        generic_params.push(db_param.clone());
+        generic_bounds.push(quote! { #db_param: dal::api_keys::tx_definitions::UseApiKey #(+ #db_traits)* });
+    } else if !db_traits.is_empty() {
+        generic_params.push(db_param.clone());
+        generic_bounds.push(quote! { #db_param: #(#db_traits)+* });
+    }
+    if token || env_variable_trait {
+        generic_params.push(config_param.clone());
+        generic_bounds.push(quote! { #config_param: utils::config::GetConfigVariable + Send });
+    }
+    if token {
+        generic_params.push(cache_param.clone());
+        generic_bounds.push(quote! {
+            #cache_param: kernel::token::session_cache::traits::GetAuthCacheSession
+                + kernel::token::session_cache::traits::TouchAuthCacheSession #(+ #cache_traits)*
+        });
+    }
+
+    // The handler's own generics, such as a store to swap out in tests, follow W, X, Y and Z
+    let generic_params: Vec<proc_macro2::TokenStream> = generic_params.iter()
+        .map(|param| quote! { #param })
+        .chain(input_fn.sig.generics.params.iter().map(|param| quote! { #param }))
+        .collect();
+    if let Some(where_clause) = &input_fn.sig.generics.where_clause {
+        for predicate in &where_clause.predicates {
+            generic_bounds.push(quote! { #predicate });
         }
-    };
+    }
 
-    let (cache_trait_stub, cache_trait_bounds) = if token == false {
-        (quote! { }, quote! { })
+    // Handlers with a token run inside a span tagged with the caller's role and organization, never their identity,
+    // acting in the caller's organization, and handlers taking an API key inside one tagged `api_key`
+    let handler_body = if token {
+        quote! {
+            let endpoint_span = utils::telemetry::endpoint_span(
+                stringify!(#fn_name), &jwt.role.to_string(), Some(jwt.org_id)
+            );
+            utils::telemetry::instrument_endpoint(endpoint_span, kernel::tenancy::with_tenant(jwt.org_id, async move {
+                #session_call
+                #confirmed_user_call
+                kernel::token::sliding::with_refreshed_token::<#config_param, _>(refreshed_token, async move {
+                    #(#fn_body)*
+                }).await
+            })).await
+        }
+    } else if api_key {
+        quote! {
+            let endpoint_span = utils::telemetry::endpoint_span(stringify!(#fn_name), "api_key", None);
+            utils::telemetry::instrument_endpoint(endpoint_span, async move {
+                #api_key_call
+                #(#fn_body)*
+            }).await
+        }
     } else {
-        (quote! {Z}, quote! { Z: kernel::token::session_cache::traits::GetAuthCacheSession })
+        quote! {
+            #(#fn_body)*
+        }
     };
 
     // Generate the expanded code
     let expanded = quote! {
-        pub async fn #fn_name <#email_trait_stub #dal_trait_stub #config_trait_stub #cache_trait_stub>(
+        #(#fn_attrs)*
+        pub async fn #fn_name <#(#generic_params),*>(
             #processed_inputs
         ) -> Result<actix_web::HttpResponse, utils::errors::NanoServiceError> 
         where
-            #email_trait_bounds
-            #dal_trait_bounds
-            #config_trait_bounds
-            #cache_trait_bounds
+            #(#generic_bounds),*
         {
-            #session_call
-            #(#fn_body)*
+            #handler_body
         }
     };
     TokenStream::from(expanded)
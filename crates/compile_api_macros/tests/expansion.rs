@@ -0,0 +1,16 @@
+//! Compile tests for the `api_endpoint` expansion.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/preserves_docs.rs");
+    t.pass("tests/ui/preserves_attributes.rs");
+    t.pass("tests/ui/session_optional.rs");
+    t.pass("tests/ui/permission_check.rs");
+    t.pass("tests/ui/api_key.rs");
+    t.pass("tests/ui/early_return.rs");
+    t.pass("tests/ui/own_generics.rs");
+    t.pass("tests/ui/confirmed_user.rs");
+    t.pass("tests/ui/endpoint_span.rs");
+    t.compile_fail("tests/ui/forwards_deprecated.rs");
+}
@@ -0,0 +1,14 @@
+//! With `auth=ApiKey` the body receives the `api_key` the request was sent with and no token.
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+
+
+#[api_endpoint(auth=ApiKey, env_variable_trait=true)]
+fn for_services() {
+    let api_key: kernel::api_keys::ApiKey = api_key;
+    Ok(HttpResponse::Ok().json(api_key.name))
+}
+
+fn main() {
+    let _ = for_services::<dal::connections::sqlx_postgres::SqlxPostGresDescriptor, utils::config::EnvConfig>;
+}
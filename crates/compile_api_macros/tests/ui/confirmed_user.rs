@@ -0,0 +1,20 @@
+//! Token endpoints receive the `confirmed_user` row, unless they opt out with `confirmed_user=false`.
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+
+
+#[api_endpoint(token=NoRoleCheck)]
+fn confirmed_only() {
+    let user: kernel::users::User = confirmed_user;
+    Ok(HttpResponse::Ok().json(user.email))
+}
+
+#[api_endpoint(token=SuperAdminRoleCheck, confirmed_user=false)]
+fn any_user() {
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn main() {
+    let _ = confirmed_only::<dal::connections::sqlx_postgres::SqlxPostGresDescriptor, utils::config::EnvConfig, kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem>;
+    let _ = any_user::<utils::config::EnvConfig, kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem>;
+}
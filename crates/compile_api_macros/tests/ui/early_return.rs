@@ -0,0 +1,18 @@
+//! The body runs in its own async block so `return` and `?` still reach the refreshed token wrapper.
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+#[api_endpoint(token=NoRoleCheck)]
+fn early_return(value: i32) {
+    if value < 0 {
+        return Err(NanoServiceError::new("negative".to_string(), NanoServiceErrorStatus::BadRequest));
+    }
+    let parsed: i32 = "2".parse().map_err(|_| NanoServiceError::new("not a number".to_string(), NanoServiceErrorStatus::BadRequest))?;
+    Ok(HttpResponse::Ok().json(value + parsed))
+}
+
+fn main() {
+    let _ = early_return::<dal::connections::sqlx_postgres::SqlxPostGresDescriptor, utils::config::EnvConfig, kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem>;
+}
@@ -0,0 +1,60 @@
+//! Token endpoints run in a span tagged with the caller's role and organization.
+use std::sync::{Arc, Mutex};
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+use kernel::token::checks::NoRoleCheck;
+use test_support::{FakeConfig, MissingSessionMock, TokenBuilder};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+
+#[api_endpoint(token=NoRoleCheck, session_optional=true, confirmed_user=false)]
+fn tagged() {
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Records the fields of every span created.
+#[derive(Clone, Default)]
+struct RecordingSubscriber {
+    fields: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+impl Visit for RecordingSubscriber {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.fields.lock().unwrap().push((field.name().to_string(), format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.lock().unwrap().push((field.name().to_string(), value.to_string()));
+    }
+}
+
+impl Subscriber for RecordingSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool { true }
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        span.record(&mut self.clone());
+        Id::from_u64(1)
+    }
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+    fn event(&self, _event: &Event<'_>) {}
+    fn enter(&self, _span: &Id) {}
+    fn exit(&self, _span: &Id) {}
+}
+
+fn main() {
+    let subscriber = RecordingSubscriber::default();
+    let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+    let jwt = TokenBuilder::<FakeConfig, NoRoleCheck>::new().org_id(7).build();
+    let response = actix_web::rt::System::new()
+        .block_on(tagged::<FakeConfig, MissingSessionMock>(jwt))
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    let fields = subscriber.fields.lock().unwrap();
+    assert!(fields.contains(&("code.function".to_string(), "tagged".to_string())));
+    assert!(fields.contains(&("enduser.role".to_string(), "Worker".to_string())));
+    assert!(fields.contains(&("enduser.org_id".to_string(), "7".to_string())));
+}
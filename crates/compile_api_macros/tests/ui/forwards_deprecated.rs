@@ -0,0 +1,15 @@
+//! The `#[deprecated]` attribute has to reach the generated function for the call below to be rejected.
+#![deny(deprecated)]
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+
+
+#[api_endpoint]
+#[deprecated(note = "use the v2 endpoint")]
+fn old_endpoint() {
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn main() {
+    let _ = old_endpoint();
+}
@@ -0,0 +1,39 @@
+//! Generics declared on the handler are kept after the generated ones, with their bounds.
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+
+
+trait Greeting {
+    fn greeting() -> String;
+}
+
+struct Hello;
+
+impl Greeting for Hello {
+    fn greeting() -> String {
+        "hello".to_string()
+    }
+}
+
+trait Punctuation {
+    fn mark() -> char;
+}
+
+impl Punctuation for Hello {
+    fn mark() -> char {
+        '!'
+    }
+}
+
+
+#[api_endpoint(env_variable_trait=true)]
+fn greet<G: Greeting, P>(name: String)
+where
+    P: Punctuation,
+{
+    Ok(HttpResponse::Ok().body(format!("{} {}{}", G::greeting(), name, P::mark())))
+}
+
+fn main() {
+    let _ = greet::<utils::config::EnvConfig, Hello, Hello>;
+}
@@ -0,0 +1,15 @@
+//! A generic check such as `PermissionCheck<T>` can be passed as the token type.
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+use kernel::token::checks::TodoAssignPermission;
+
+
+#[api_endpoint(token=PermissionCheck<TodoAssignPermission>)]
+fn assign() {
+    let _permissions: &Vec<String> = &user_session.permissions;
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn main() {
+    let _ = assign::<dal::connections::sqlx_postgres::SqlxPostGresDescriptor, utils::config::EnvConfig, kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem>;
+}
@@ -0,0 +1,15 @@
+//! `unused_variables` is denied, so this only compiles if the `#[allow]` survives the expansion.
+#![deny(unused_variables)]
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+
+
+#[api_endpoint]
+#[allow(unused_variables)]
+fn ignores_input(value: i32) {
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn main() {
+    let _ = ignores_input(1);
+}
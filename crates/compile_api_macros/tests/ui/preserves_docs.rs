@@ -0,0 +1,15 @@
+//! `missing_docs` is denied, so this only compiles if the doc comment survives the expansion.
+#![deny(missing_docs)]
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+
+
+/// Returns an empty 200 response.
+#[api_endpoint]
+fn documented() {
+    Ok(HttpResponse::Ok().finish())
+}
+
+fn main() {
+    let _ = documented();
+}
@@ -0,0 +1,17 @@
+//! With `session_optional=true` the body receives `user_session` as an `Option`.
+use actix_web::HttpResponse;
+use compile_api_macros::api_endpoint;
+
+
+#[api_endpoint(token=NoRoleCheck, session_optional=true)]
+fn maybe_session() {
+    let session: Option<kernel::token::session_cache::structs::AuthCacheSession> = user_session;
+    match session {
+        Some(_) => Ok(HttpResponse::Ok().finish()),
+        None => Ok(HttpResponse::NoContent().finish())
+    }
+}
+
+fn main() {
+    let _ = maybe_session::<dal::connections::sqlx_postgres::SqlxPostGresDescriptor, utils::config::EnvConfig, kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem>;
+}
@@ -55,11 +55,11 @@ pub fn impl_transaction(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
-    // Generate the expanded code
+    // Generate the expanded code, timing the body against the trait name for slow query logging
     let expanded = quote! {
         impl #trait_name for #struct_name {
             fn #fn_name #fn_generics (#fn_inputs) -> impl std::future::Future<Output = #fn_output> + Send {
-                async move #fn_body
+                utils::transaction_metrics::instrument_transaction(stringify!(#trait_name), async move #fn_body)
             }
         }
     };
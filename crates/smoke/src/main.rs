@@ -0,0 +1,266 @@
+//! End-to-end smoke test run against a deployed environment after a deploy.
+//!
+//! The run signs in as a super admin, creates a throwaway worker on a sandbox email address,
+//! confirms it, logs in as the worker, creates and completes a to-do item, then deletes the
+//! worker (which cascades to the to-do item). Any failed step exits with a nonzero code.
+//!
+//! # Variables
+//! * `SMOKE_BASE_URL` - The base url of the deployment, e.g. `https://staging.example.com`
+//! * `SMOKE_ADMIN_EMAIL` - The email of an existing super admin
+//! * `SMOKE_ADMIN_PASSWORD` - The password of that super admin
+//! * `SMOKE_EMAIL_DOMAIN` - The sandbox domain the temp user is created on, defaults to `example.com`
+use reqwest::{Client, Response};
+use serde::Deserialize;
+use serde_json::json;
+use std::process::ExitCode;
+use utils::config::{EnvConfig, GetConfigVariable};
+
+
+/// The user agent sent with every request, logins are tied to it.
+const USER_AGENT: &str = "smoke-test";
+
+/// The password given to the temp user.
+const SMOKE_PASSWORD: &str = "Smoke-test-password-1";
+
+
+/// The config for a smoke run.
+struct SmokeConfig {
+    base_url: String,
+    admin_email: String,
+    admin_password: String,
+    email_domain: String,
+}
+
+
+impl SmokeConfig {
+
+    fn from_config<X: GetConfigVariable>() -> Result<Self, String> {
+        let read = |name: &str| X::get_config_variable(name.to_string()).map_err(|e| e.message);
+        Ok(SmokeConfig {
+            base_url: read("SMOKE_BASE_URL")?.trim_end_matches('/').to_string(),
+            admin_email: read("SMOKE_ADMIN_EMAIL")?,
+            admin_password: read("SMOKE_ADMIN_PASSWORD")?,
+            email_domain: read("SMOKE_EMAIL_DOMAIN").unwrap_or("example.com".to_string()),
+        })
+    }
+}
+
+
+#[derive(Deserialize)]
+struct LoginResponse {
+    token: String,
+}
+
+
+#[derive(Deserialize)]
+struct UserResponse {
+    id: i32,
+    uuid: String,
+    confirmed: bool,
+}
+
+
+#[derive(Deserialize)]
+struct ProfileResponse {
+    user: UserResponse,
+}
+
+
+#[derive(Deserialize)]
+struct TodoResponse {
+    id: i32,
+    name: String,
+    finished: bool,
+}
+
+
+/// Builds a unique sandbox address so repeated runs never collide.
+fn sandbox_email(domain: &str) -> String {
+    format!("smoke+{}@{}", uuid::Uuid::new_v4().simple(), domain)
+}
+
+
+/// Fails the step if the response is not a success, including the body in the message.
+async fn expect_success(step: &str, response: Result<Response, reqwest::Error>) -> Result<Response, String> {
+    let response = response.map_err(|e| format!("{}: request failed: {}", step, e))?;
+    if response.status().is_success() {
+        return Ok(response)
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("{}: returned {} {}", step, status, body))
+}
+
+
+/// Talks to the deployment for a single smoke run.
+struct SmokeRun {
+    client: Client,
+    config: SmokeConfig,
+}
+
+
+impl SmokeRun {
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.config.base_url, path)
+    }
+
+    async fn login(&self, email: &str, password: &str, role: &str) -> Result<String, String> {
+        let response = self.client.post(self.url("/api/auth/v1/auth/login"))
+            .basic_auth(email, Some(password))
+            .header("User-Agent", USER_AGENT)
+            .json(&json!({"role": role}))
+            .send()
+            .await;
+        let response = expect_success(&format!("login as {}", role), response).await?;
+        let login: LoginResponse = response.json().await.map_err(|e| format!("login: {}", e))?;
+        Ok(login.token)
+    }
+
+    async fn post(&self, step: &str, path: &str, token: Option<&str>, body: serde_json::Value) -> Result<Response, String> {
+        let mut request = self.client.post(self.url(path))
+            .header("User-Agent", USER_AGENT)
+            .json(&body);
+        if let Some(token) = token {
+            request = request.header("token", token);
+        }
+        expect_success(step, request.send().await).await
+    }
+
+    async fn get_user(&self, admin_token: &str, email: &str) -> Result<UserResponse, String> {
+        let response = self.client.get(self.url(&format!("/api/auth/v1/users/get-by-email/{}", email)))
+            .header("User-Agent", USER_AGENT)
+            .header("token", admin_token)
+            .send()
+            .await;
+        let response = expect_success("get user", response).await?;
+        let profile: ProfileResponse = response.json().await.map_err(|e| format!("get user: {}", e))?;
+        Ok(profile.user)
+    }
+
+    /// Runs every step after the temp user exists, so the caller can always clean it up.
+    async fn exercise_user(&self, admin_token: &str, email: &str, user: &UserResponse) -> Result<(), String> {
+        self.post("confirm user", "/api/auth/v1/users/confirm", None, json!({"unique_id": user.uuid})).await?;
+        if !self.get_user(admin_token, email).await?.confirmed {
+            return Err("confirm user: user is still unconfirmed".to_string())
+        }
+        self.post(
+            "set password",
+            "/api/auth/v1/users/reset-password",
+            None,
+            json!({"unique_id": user.uuid, "new_password": SMOKE_PASSWORD})
+        ).await?;
+        let worker_token = self.login(email, SMOKE_PASSWORD, "Worker").await?;
+        println!("ok: logged in as temp user");
+
+        let name = format!("smoke test {}", uuid::Uuid::new_v4().simple());
+        let response = self.post("create todo", "/api/todo/v1/basic_actions/create", Some(admin_token), json!({
+            "name": name,
+            "due_date": null,
+            "assigned_by": user.id,
+            "assigned_to": user.id,
+            "description": "created by the smoke test",
+            "date_assigned": null
+        })).await?;
+        let items: Vec<TodoResponse> = response.json().await.map_err(|e| format!("create todo: {}", e))?;
+        let item = items.into_iter()
+            .find(|item| item.name == name)
+            .ok_or("create todo: item missing from the user's items")?;
+        println!("ok: created todo {}", item.id);
+
+        let response = self.post(
+            "complete todo",
+            "/api/todo/v1/basic_actions/complete",
+            Some(&worker_token),
+            json!({"todo_id": item.id})
+        ).await?;
+        let item: TodoResponse = response.json().await.map_err(|e| format!("complete todo: {}", e))?;
+        if !item.finished {
+            return Err("complete todo: item is not finished".to_string())
+        }
+        println!("ok: completed todo {}", item.id);
+        Ok(())
+    }
+
+    async fn run(&self) -> Result<(), String> {
+        let admin_token = self.login(&self.config.admin_email, &self.config.admin_password, "Super Admin").await?;
+        println!("ok: logged in as admin");
+
+        let email = sandbox_email(&self.config.email_domain);
+        self.post("create user", "/api/auth/v1/users/create", Some(&admin_token), json!({
+            "username": email,
+            "email": email,
+            "first_name": "Smoke",
+            "last_name": "Test",
+            "user_role": "Worker"
+        })).await?;
+        let user = self.get_user(&admin_token, &email).await?;
+        println!("ok: created temp user {}", email);
+
+        let outcome = self.exercise_user(&admin_token, &email, &user).await;
+        let cleanup = self.post("delete user", "/api/auth/v1/users/delete", Some(&admin_token), json!({"id": user.id})).await;
+        outcome?;
+        cleanup?;
+        println!("ok: deleted temp user");
+        Ok(())
+    }
+}
+
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let config = match SmokeConfig::from_config::<EnvConfig>() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("smoke test misconfigured: {}", e);
+            return ExitCode::from(2)
+        }
+    };
+    let run = SmokeRun { client: Client::new(), config };
+    match run.run().await {
+        Ok(()) => {
+            println!("smoke test passed");
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("smoke test failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SMOKE_BASE_URL" => Ok("https://staging.example.com/".to_string()),
+                "SMOKE_EMAIL_DOMAIN" => Err(NanoServiceError::new(
+                    "not set".to_string(), NanoServiceErrorStatus::Unknown
+                )),
+                _ => Ok("admin".to_string())
+            }
+        }
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config = SmokeConfig::from_config::<FakeConfig>().unwrap();
+        assert_eq!(config.base_url, "https://staging.example.com");
+        assert_eq!(config.email_domain, "example.com");
+    }
+
+    #[test]
+    fn test_sandbox_email_is_unique() {
+        let email = sandbox_email("sandbox.example.com");
+        assert!(email.starts_with("smoke+"));
+        assert!(email.ends_with("@sandbox.example.com"));
+        assert_ne!(email, sandbox_email("sandbox.example.com"));
+    }
+}
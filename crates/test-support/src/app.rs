@@ -0,0 +1,48 @@
+//! Sends a request to a single endpoint mounted on a test app.
+use actix_web::dev::ServiceResponse;
+use actix_web::http::Method;
+use actix_web::test::{self, TestRequest};
+use actix_web::{web, App, FromRequest, Handler, Responder};
+
+
+/// Mounts an endpoint on a test app and sends it a request.
+///
+/// # Arguments
+/// * `method` - The method the endpoint is mounted for.
+/// * `pattern` - The path the endpoint is mounted at, such as `/get/{id}`.
+/// * `handler` - The endpoint with its generics filled in, such as `search_users::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>`.
+/// * `request` - The request to send, see `TokenBuilder::request` to add a token to it.
+///
+/// # Returns
+/// * `ServiceResponse` - The endpoint's response.
+pub async fn call_endpoint<F, Args>(method: Method, pattern: &str, handler: F, request: TestRequest) -> ServiceResponse
+where
+    F: Handler<Args>,
+    Args: FromRequest + 'static,
+    F::Output: Responder + 'static,
+{
+    let app = test::init_service(App::new().route(pattern, web::method(method).to(handler))).await;
+    test::call_service(&app, request.to_request()).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::HttpResponse;
+
+    async fn echo(path: web::Path<i32>) -> HttpResponse {
+        HttpResponse::Ok().json(path.into_inner())
+    }
+
+    #[tokio::test]
+    async fn test_call_endpoint() {
+        let resp = call_endpoint(Method::GET, "/get/{id}", echo, TestRequest::get().uri("/get/3")).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body, 3);
+
+        let resp = call_endpoint(Method::POST, "/get/{id}", echo, TestRequest::get().uri("/get/3")).await;
+        assert_eq!(resp.status(), 404);
+    }
+}
@@ -0,0 +1,70 @@
+//! Configs returning fixed values in place of the environment or the secrets backend.
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// A config returning `"secret"` for every variable, enough to sign and verify tokens.
+pub struct FakeConfig;
+
+impl GetConfigVariable for FakeConfig {
+    fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+        Ok("secret".to_string())
+    }
+}
+
+
+/// A config with no variables set, for code that falls back to its defaults or skips what is not configured.
+pub struct MissingConfig;
+
+impl GetConfigVariable for MissingConfig {
+    fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+        Err(NanoServiceError::new(format!("{} not found", variable), NanoServiceErrorStatus::NotFound))
+    }
+}
+
+
+/// Defines a config returning the given values, and `"secret"` for every other variable.
+///
+/// # Example
+/// ```rust
+/// test_support::fake_config!(RetentionConfig, {
+///     "AUDIT_LOG_RETENTION_DAYS" => "0",
+///     "LOGIN_EVENTS_RETENTION_DAYS" => "14",
+/// });
+/// ```
+#[macro_export]
+macro_rules! fake_config {
+    ($name:ident, { $($variable:expr => $value:expr),* $(,)? }) => {
+        struct $name;
+
+        impl $crate::utils::config::GetConfigVariable for $name {
+            fn get_config_variable(variable: String) -> Result<String, $crate::utils::errors::NanoServiceError> {
+                match variable.as_str() {
+                    $($variable => Ok($value.to_string()),)*
+                    _ => Ok("secret".to_string())
+                }
+            }
+        }
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::config::TypedConfig;
+
+    fake_config!(RetentionConfig, {
+        "AUDIT_LOG_RETENTION_DAYS" => "0",
+        "LOGIN_EVENTS_RETENTION_DAYS" => 14,
+    });
+
+    #[test]
+    fn test_fake_config() {
+        assert_eq!(FakeConfig::get_config_variable("SECRET_KEY".to_string()).unwrap(), "secret");
+        assert_eq!(RetentionConfig::get_int("AUDIT_LOG_RETENTION_DAYS").unwrap(), 0);
+        assert_eq!(RetentionConfig::get_int("LOGIN_EVENTS_RETENTION_DAYS").unwrap(), 14);
+        assert_eq!(RetentionConfig::get_config_variable("SECRET_KEY".to_string()).unwrap(), "secret");
+        assert_eq!(MissingConfig::get_config_variable("SECRET_KEY".to_string()).unwrap_err().status, NanoServiceErrorStatus::NotFound);
+    }
+}
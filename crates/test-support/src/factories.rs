@@ -0,0 +1,125 @@
+//! Records for mocked transactions to return.
+//!
+//! # Overview
+//! Each factory fills every field with a plausible value derived from the ID, so a test only sets
+//! the fields it is about with struct update syntax:
+//! ```rust
+//! let blocked = User { blocked: true, ..user(2) };
+//! ```
+use chrono::Utc;
+use kernel::devices::{device_fingerprint, Device};
+use kernel::projects::Project;
+use kernel::to_do_items::{NewTodo, Todo, TodoPriority, TodoStatus};
+use kernel::todo_events::{NewTodoEvent, TodoEvent};
+use kernel::users::{PublicUser, TrimmedUser, User, UserRole};
+
+
+/// A confirmed worker named `user{id}`, with an unhashed `password` as the password.
+pub fn user(id: i32) -> User {
+    let now = Utc::now().naive_utc();
+    User {
+        id,
+        confirmed: true,
+        username: format!("user{}", id),
+        email: format!("user{}@example.com", id),
+        password: "password".to_string(),
+        first_name: "Test".to_string(),
+        last_name: format!("User{}", id),
+        user_role: UserRole::Worker,
+        date_created: now,
+        last_logged_in: now,
+        blocked: false,
+        uuid: format!("uuid-{}", id),
+        avatar_version: None,
+    }
+}
+
+
+/// The `user` as returned to clients.
+pub fn trimmed_user(id: i32) -> TrimmedUser {
+    TrimmedUser::from(user(id))
+}
+
+
+/// The `user` as other users see them.
+pub fn public_user(id: i32) -> PublicUser {
+    PublicUser::from(trimmed_user(id))
+}
+
+
+/// An untrusted, unnamed device of user 2.
+pub fn device(id: i32) -> Device {
+    let now = Utc::now().naive_utc();
+    let user_agent = format!("Mozilla/5.0 (device {})", id);
+    Device {
+        id,
+        user_id: 2,
+        fingerprint: device_fingerprint(&user_agent),
+        name: None,
+        user_agent,
+        trusted_until: None,
+        date_created: now,
+        last_seen_at: now,
+    }
+}
+
+
+/// An unfinished item named `Task {id}`, assigned by user 1 to user 2.
+pub fn todo(id: i32) -> Todo {
+    Todo {
+        id,
+        name: format!("Task {}", id),
+        due_date: None,
+        assigned_by: 1,
+        assigned_to: 2,
+        description: None,
+        date_assigned: Utc::now().naive_utc(),
+        date_finished: None,
+        finished: false,
+        requires_review: false,
+        pending_review: false,
+        review_comment: None,
+        priority: TodoPriority::Medium,
+        status: TodoStatus::Backlog,
+    }
+}
+
+
+/// An item as a client would send it, assigned by user 1 to user 2.
+pub fn new_todo(name: &str) -> NewTodo {
+    NewTodo {
+        name: name.to_string(),
+        due_date: None,
+        assigned_by: 1,
+        assigned_to: 2,
+        description: None,
+        date_assigned: None,
+        requires_review: false,
+        priority: TodoPriority::Medium,
+    }
+}
+
+
+/// The change as it is recorded in the history of its item, with the ID given.
+pub fn todo_event(id: i32, event: NewTodoEvent) -> TodoEvent {
+    TodoEvent {
+        id,
+        todo_id: event.todo_id,
+        actor_id: Some(event.actor_id),
+        kind: event.kind,
+        details: sqlx::types::Json(event.details),
+        date_created: Utc::now().naive_utc(),
+    }
+}
+
+
+/// A project named `Project {id}` without a description, owned by user 1.
+pub fn project(id: i32) -> Project {
+    Project {
+        id,
+        name: format!("Project {}", id),
+        description: None,
+        owner_id: 1,
+        date_created: Utc::now().naive_utc(),
+    }
+}
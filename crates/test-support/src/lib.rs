@@ -0,0 +1,25 @@
+//! Shared mocks, factories and helpers for the test blocks of the nanoservices.
+//!
+//! # Overview
+//! Endpoint tests all need the same pieces: a config returning a secret to sign tokens with, a
+//! session cache that lets the request through, a token for some role, some records for the mocked
+//! transactions to return, and an actix app routing to the endpoint under test. This crate holds
+//! them once so a test block only defines the transactions it is testing.
+//!
+//! # Notes
+//! The crate is only for `[dev-dependencies]`, nothing in it is fit to run against real data.
+pub mod app;
+pub mod config;
+pub mod factories;
+pub mod sessions;
+pub mod token;
+pub mod users;
+
+pub use app::call_endpoint;
+pub use config::{FakeConfig, MissingConfig};
+pub use sessions::{MissingSessionMock, PassAuthSessionCheckMock};
+pub use token::TokenBuilder;
+
+// the crates the macros expand to, so a test block does not need them as dependencies itself
+#[doc(hidden)]
+pub use {chrono, dal, kernel, utils};
@@ -0,0 +1,92 @@
+//! Session caches for endpoint tests.
+//!
+//! # Overview
+//! `PassAuthSessionCheckMock` finds a session for every token, `MissingSessionMock` finds none, and
+//! `session_mock!` defines a cache returning a session with a given user, role and permissions for
+//! the endpoints checking permissions.
+use kernel::token::session_cache::structs::{AuthCacheSession, IntoAuthCacheKey};
+use kernel::chrono::{DateTime, Utc};
+use kernel::token::session_cache::traits::{GetAuthCacheSession, TouchAuthCacheSession};
+use utils::errors::NanoServiceError;
+use std::future::Future;
+
+pub use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+
+
+/// A session cache without any sessions, as if every token had been logged out.
+pub struct MissingSessionMock;
+
+impl GetAuthCacheSession for MissingSessionMock {
+    fn get_auth_cache_session<X: IntoAuthCacheKey + Send>(_key: &X)
+    -> impl Future<Output = Result<Option<AuthCacheSession>, NanoServiceError>> + Send {
+        std::future::ready(Ok(None))
+    }
+}
+
+impl TouchAuthCacheSession for MissingSessionMock {
+    #[allow(clippy::manual_async_fn)]
+    fn touch_auth_cache_session<X: IntoAuthCacheKey + Send>(_key: &X, _last_seen: DateTime<Utc>, _time_expire: DateTime<Utc>)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send {
+        async move {
+            Ok(())
+        }
+    }
+}
+
+
+/// Defines a session cache returning a session for the given user, role and permissions.
+///
+/// # Example
+/// ```rust
+/// test_support::session_mock!(CompleteSessionMock, 2, UserRole::Worker, ["todo:complete"]);
+/// ```
+#[macro_export]
+macro_rules! session_mock {
+    ($name:ident, $user_id:expr, $role:expr, [$($permission:expr),* $(,)?]) => {
+        struct $name;
+
+        impl $crate::kernel::token::session_cache::traits::GetAuthCacheSession for $name {
+            fn get_auth_cache_session<X: $crate::kernel::token::session_cache::structs::IntoAuthCacheKey + Send>(_key: &X)
+            -> impl std::future::Future<Output = Result<Option<$crate::kernel::token::session_cache::structs::AuthCacheSession>, $crate::utils::errors::NanoServiceError>> + Send {
+                std::future::ready(Ok(Some($crate::kernel::token::session_cache::structs::AuthCacheSession {
+                    user_id: $user_id,
+                    role: $role,
+                    time_started: $crate::chrono::Utc::now(),
+                    time_expire: $crate::chrono::Utc::now(),
+                    user_agent: "test".to_string(),
+                    permissions: vec![$($permission.to_string()),*],
+                    last_seen: $crate::chrono::Utc::now(),
+                    device_fingerprint: None
+                })))
+            }
+        }
+
+        impl $crate::kernel::token::session_cache::traits::TouchAuthCacheSession for $name {
+            fn touch_auth_cache_session<X: $crate::kernel::token::session_cache::structs::IntoAuthCacheKey + Send>(
+                _key: &X,
+                _last_seen: $crate::chrono::DateTime<$crate::chrono::Utc>,
+                _time_expire: $crate::chrono::DateTime<$crate::chrono::Utc>
+            ) -> impl std::future::Future<Output = Result<(), $crate::utils::errors::NanoServiceError>> + Send {
+                std::future::ready(Ok(()))
+            }
+        }
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::UserRole;
+
+    session_mock!(CompleteSessionMock, 2, UserRole::Worker, ["todo:complete"]);
+
+    #[tokio::test]
+    async fn test_session_mocks() {
+        let key = "some-token".to_string();
+        let session = CompleteSessionMock::get_auth_cache_session(&key).await.unwrap().unwrap();
+        assert_eq!((session.user_id, session.role), (2, UserRole::Worker));
+        assert_eq!(session.permissions, vec!["todo:complete".to_string()]);
+        assert!(MissingSessionMock::get_auth_cache_session(&key).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,147 @@
+//! Builds the tokens that test requests are sent with.
+use std::marker::PhantomData;
+use actix_web::http::header::USER_AGENT;
+use actix_web::test::TestRequest;
+use chrono::{Duration, Utc};
+use kernel::token::checks::{CheckUserRole, WorkerRoleCheck};
+use kernel::token::token::HeaderToken;
+use kernel::organizations::DEFAULT_ORG_ID;
+use kernel::users::UserRole;
+use utils::config::GetConfigVariable;
+use crate::config::FakeConfig;
+
+
+/// The user agent every built token and request carries, unless another is set.
+pub const TEST_USER_AGENT: &str = "some-agent";
+
+
+/// Builds a token for any user, role and agent, signed with the config `X`.
+///
+/// # Generics
+/// * `X` - The config the token is signed with, which has to match the endpoint's config.
+/// * `Y` - The role check the token is typed with, it does not limit the role given to `role`.
+///
+/// # Example
+/// ```rust
+/// let req = TokenBuilder::<FakeConfig, AdminRoleCheck>::new()
+///     .user_id(7)
+///     .role(UserRole::Admin)
+///     .request(TestRequest::get().uri("/search?q=ada"));
+/// ```
+pub struct TokenBuilder<X: GetConfigVariable = FakeConfig, Y: CheckUserRole = WorkerRoleCheck> {
+    user_id: i32,
+    org_id: i32,
+    role: UserRole,
+    user_agent: String,
+    expired: bool,
+    impersonator_id: Option<i32>,
+    handles: PhantomData<(X, Y)>,
+}
+
+impl<X: GetConfigVariable, Y: CheckUserRole> Default for TokenBuilder<X, Y> {
+    fn default() -> Self {
+        TokenBuilder {
+            user_id: 1,
+            org_id: DEFAULT_ORG_ID,
+            role: UserRole::Worker,
+            user_agent: TEST_USER_AGENT.to_string(),
+            expired: false,
+            impersonator_id: None,
+            handles: PhantomData,
+        }
+    }
+}
+
+impl<X: GetConfigVariable, Y: CheckUserRole> TokenBuilder<X, Y> {
+
+    /// A token for user 1 as a worker in the default organization, from `TEST_USER_AGENT`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the ID of the user the token is for.
+    pub fn user_id(mut self, user_id: i32) -> Self {
+        self.user_id = user_id;
+        self
+    }
+
+    /// Sets the ID of the organization the user is acting in.
+    pub fn org_id(mut self, org_id: i32) -> Self {
+        self.org_id = org_id;
+        self
+    }
+
+    /// Sets the role of the user the token is for.
+    pub fn role(mut self, role: UserRole) -> Self {
+        self.role = role;
+        self
+    }
+
+    /// Sets the agent the token was issued to, the request still sends `TEST_USER_AGENT`.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    /// Makes the token one that expired a minute ago.
+    pub fn expired(mut self) -> Self {
+        self.expired = true;
+        self
+    }
+
+    /// Makes the token one issued to a super admin impersonating the user.
+    pub fn impersonated_by(mut self, impersonator_id: i32) -> Self {
+        self.impersonator_id = Some(impersonator_id);
+        self
+    }
+
+    /// Builds the token.
+    pub fn build(self) -> HeaderToken<X, Y> {
+        let mut token = HeaderToken::new(self.user_agent, self.user_id, self.role).in_org(self.org_id);
+        token.impersonator_id = self.impersonator_id;
+        if self.expired {
+            token.time_expire = Utc::now() - Duration::minutes(1);
+        }
+        token
+    }
+
+    /// Builds and signs the token.
+    ///
+    /// # Panics
+    /// - If `X` does not hold what is needed to sign it.
+    pub fn encode(self) -> String {
+        self.build().encode().expect("the test token could not be signed")
+    }
+
+    /// Adds the signed token and `TEST_USER_AGENT` to a request's headers.
+    pub fn request(self, request: TestRequest) -> TestRequest {
+        request
+            .insert_header(("token", self.encode()))
+            .insert_header((USER_AGENT, TEST_USER_AGENT))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::token::checks::AdminRoleCheck;
+
+    #[test]
+    fn test_build() {
+        let token = TokenBuilder::<FakeConfig, AdminRoleCheck>::new().user_id(7).role(UserRole::Admin).build();
+        assert_eq!((token.user_id, &token.role), (7, &UserRole::Admin));
+        assert_eq!(token.user_agent, TEST_USER_AGENT);
+        assert!(token.check_if_expired().is_ok());
+        assert_eq!(token.impersonator_id, None);
+        assert_eq!(token.org_id, DEFAULT_ORG_ID);
+
+        let token: HeaderToken<FakeConfig, WorkerRoleCheck> = TokenBuilder::new().user_agent("other-agent").expired().build();
+        assert_eq!(token.user_agent, "other-agent");
+        assert!(token.check_if_expired().is_err());
+
+        let token: HeaderToken<FakeConfig, WorkerRoleCheck> = TokenBuilder::new().impersonated_by(2).org_id(3).build();
+        assert_eq!(token.impersonator_id, Some(2));
+        assert_eq!(token.org_id, 3);
+    }
+}
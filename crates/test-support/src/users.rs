@@ -0,0 +1,44 @@
+//! User lookups for the confirmed user check of endpoint tests.
+//!
+//! # Overview
+//! Every endpoint with a token loads the user behind it before running, so the mocked database of
+//! a token endpoint has to implement `GetUser`. `confirmed_users!` implements it with the
+//! `factories::user` rows, which are confirmed and not blocked.
+
+
+/// Implements `GetUser` for a mocked database, returning a confirmed user for every ID.
+///
+/// # Example
+/// ```rust
+/// struct MockPostgres;
+///
+/// test_support::confirmed_users!(MockPostgres);
+/// ```
+#[macro_export]
+macro_rules! confirmed_users {
+    ($name:ty) => {
+        impl $crate::dal::users::tx_definitions::GetUser for $name {
+            fn get_user(id: i32)
+            -> impl std::future::Future<Output = Result<$crate::kernel::users::User, $crate::utils::errors::NanoServiceError>> + Send {
+                std::future::ready(Ok($crate::factories::user(id)))
+            }
+        }
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use dal::users::tx_definitions::GetUser;
+
+    struct MockPostgres;
+
+    confirmed_users!(MockPostgres);
+
+    #[tokio::test]
+    async fn test_confirmed_users() {
+        let user = MockPostgres::get_user(3).await.unwrap();
+        assert_eq!(user.id, 3);
+        assert!(user.confirmed && !user.blocked);
+    }
+}
@@ -1,8 +1,29 @@
 //! Defines extracting config variables.
+//!
+//! # Overview
+//! Config is resolved in layers, the first layer holding the variable wins:
+//! 1. environment variables
+//! 2. the TOML or YAML file at `CONFIG_PATH`, if set
+//! 3. the defaults in `CONFIG_DEFAULTS`
+//!
+//! Nested tables in the file are flattened into upper case names joined with `_`, so
+//! `[mailchimp] api_key = "..."` is read as `MAILCHIMP_API_KEY`.
+use std::collections::HashMap;
 use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
 use crate::errors::{NanoServiceError, NanoServiceErrorStatus};
 
 
+/// The values used when a variable is in neither the environment nor the config file.
+pub const CONFIG_DEFAULTS: [(&str, &str); 4] = [
+    ("EMAIL_OUTBOX_POLL_SECONDS", "10"),
+    ("EMAIL_OUTBOX_BATCH_SIZE", "20"),
+    ("SMOKE_EMAIL_DOMAIN", "example.com"),
+    ("TO_DO_MAX_CONNECTIONS", "5"),
+];
+
+
 /// Defines the trait for getting config variables
 pub trait GetConfigVariable {
 
@@ -17,13 +38,188 @@ pub trait GetConfigVariable {
 }
 
 
-/// Defines the struct for getting config variables from the environment
+fn invalid_variable(variable: &str, value: &str, expected: &str) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("{} is set to '{}' which is not {}", variable, value, expected),
+        NanoServiceErrorStatus::Unknown
+    )
+}
+
+
+/// Typed accessors for config variables, available on every `GetConfigVariable`.
+pub trait TypedConfig: GetConfigVariable {
+
+    /// Gets the config variable as an integer.
+    fn get_int(variable: &str) -> Result<i64, NanoServiceError> {
+        let value = Self::get_config_variable(variable.to_string())?;
+        value.trim().parse::<i64>().map_err(|_| invalid_variable(variable, &value, "an integer"))
+    }
+
+    /// Gets the config variable as an integer above zero, such as a poll interval or a batch size.
+    fn get_positive_int(variable: &str) -> Result<i64, NanoServiceError> {
+        let value = Self::get_int(variable)?;
+        if value <= 0 {
+            return Err(invalid_variable(variable, &value.to_string(), "above zero"))
+        }
+        Ok(value)
+    }
+
+    /// Gets the config variable as a bool, accepting `true`/`false`, `1`/`0` and `yes`/`no` in any case.
+    fn get_bool(variable: &str) -> Result<bool, NanoServiceError> {
+        let value = Self::get_config_variable(variable.to_string())?;
+        parse_bool(&value).ok_or_else(|| invalid_variable(variable, &value, "a bool"))
+    }
+
+    /// Gets the config variable as a duration, e.g. `250ms`, `30s`, `5m`, `2h` or `1d`.
+    /// A plain number is read as seconds.
+    fn get_duration(variable: &str) -> Result<Duration, NanoServiceError> {
+        let value = Self::get_config_variable(variable.to_string())?;
+        parse_duration(&value).ok_or_else(|| invalid_variable(variable, &value, "a duration"))
+    }
+}
+
+impl<T: GetConfigVariable> TypedConfig for T {}
+
+
+/// Parses a bool config value.
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None
+    }
+}
+
+
+/// Parses a duration config value.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount = amount.parse::<u64>().ok()?;
+    match unit.trim() {
+        "ms" => Some(Duration::from_millis(amount)),
+        "" | "s" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_secs(amount * 60)),
+        "h" => Some(Duration::from_secs(amount * 60 * 60)),
+        "d" => Some(Duration::from_secs(amount * 60 * 60 * 24)),
+        _ => None
+    }
+}
+
+
+fn file_error(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::Unknown)
+}
+
+
+fn flatten_toml(prefix: &str, value: &toml::Value, out: &mut HashMap<String, String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                flatten_toml(&join_key(prefix, key), value, out);
+            }
+        },
+        toml::Value::String(value) => { out.insert(prefix.to_string(), value.clone()); },
+        other => { out.insert(prefix.to_string(), other.to_string()); }
+    }
+}
+
+
+fn flatten_yaml(prefix: &str, value: &serde_yaml::Value, out: &mut HashMap<String, String>) {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                if let Some(key) = key.as_str() {
+                    flatten_yaml(&join_key(prefix, key), value, out);
+                }
+            }
+        },
+        serde_yaml::Value::String(value) => { out.insert(prefix.to_string(), value.clone()); },
+        serde_yaml::Value::Bool(value) => { out.insert(prefix.to_string(), value.to_string()); },
+        serde_yaml::Value::Number(value) => { out.insert(prefix.to_string(), value.to_string()); },
+        _ => {}
+    }
+}
+
+
+fn join_key(prefix: &str, key: &str) -> String {
+    let key = key.to_uppercase();
+    if prefix.is_empty() { key } else { format!("{}_{}", prefix, key) }
+}
+
+
+/// Parses the contents of a config file into flattened variables.
+///
+/// # Arguments
+/// * `path` - The path of the file, the extension picks the format (`.toml`, `.yaml` or `.yml`)
+/// * `contents` - The contents of the file
+///
+/// # Returns
+/// * `Ok(HashMap<String, String>)` - The variables in the file
+/// * `Err(NanoServiceError)` - If the format is not supported or the file cannot be parsed
+pub fn parse_config_file(path: &str, contents: &str) -> Result<HashMap<String, String>, NanoServiceError> {
+    let mut variables = HashMap::new();
+    if path.ends_with(".toml") {
+        let value: toml::Value = toml::from_str(contents)
+            .map_err(|e| file_error(format!("Failed to parse config file {}: {}", path, e)))?;
+        flatten_toml("", &value, &mut variables);
+    } else if path.ends_with(".yaml") || path.ends_with(".yml") {
+        let value: serde_yaml::Value = serde_yaml::from_str(contents)
+            .map_err(|e| file_error(format!("Failed to parse config file {}: {}", path, e)))?;
+        flatten_yaml("", &value, &mut variables);
+    } else {
+        return Err(file_error(format!("Config file {} is not .toml, .yaml or .yml", path)))
+    }
+    Ok(variables)
+}
+
+
+/// The variables from the file at `CONFIG_PATH`, read once.
+///
+/// # Panics
+/// If `CONFIG_PATH` is set but the file cannot be read or parsed, as running with half of the
+/// intended config is worse than not starting.
+fn config_file() -> &'static HashMap<String, String> {
+    static CONFIG_FILE: OnceLock<HashMap<String, String>> = OnceLock::new();
+    CONFIG_FILE.get_or_init(|| {
+        let path = match env::var("CONFIG_PATH") {
+            Ok(path) => path,
+            Err(_) => return HashMap::new()
+        };
+        let contents = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read config file {}: {}", path, e));
+        parse_config_file(&path, &contents).unwrap_or_else(|e| panic!("{}", e.message))
+    })
+}
+
+
+/// Resolves a variable through the layers, the first layer holding it wins.
+///
+/// # Arguments
+/// * `variable` - The name of the variable
+/// * `env_value` - The value from the environment, if set
+/// * `file` - The variables from the config file
+/// * `defaults` - The default values
+pub fn resolve_layered(
+    variable: &str,
+    env_value: Option<String>,
+    file: &HashMap<String, String>,
+    defaults: &[(&str, &str)],
+) -> Option<String> {
+    env_value
+        .or_else(|| file.get(variable).cloned())
+        .or_else(|| defaults.iter().find(|(name, _)| *name == variable).map(|(_, value)| value.to_string()))
+}
+
+
+/// Defines the struct for getting config variables from the environment, the config file and the defaults
 pub struct EnvConfig;
 
 
 impl GetConfigVariable for EnvConfig {
 
-    /// Gets the config variable from the environment
+    /// Gets the config variable from the environment, falling back to the config file and then the defaults
     /// 
     /// # Arguments
     /// * `variable` - The name of the config variable to get
@@ -31,9 +227,9 @@ impl GetConfigVariable for EnvConfig {
     /// # Returns
     /// * `Result<String, NanoServiceError>` - The result of getting the config variable
     fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
-        match env::var(&variable) {
-            Ok(val) => Ok(val),
-            Err(_) => Err(
+        match resolve_layered(&variable, env::var(&variable).ok(), config_file(), &CONFIG_DEFAULTS) {
+            Some(val) => Ok(val),
+            None => Err(
                 NanoServiceError::new(
                     format!("{} not found in environment", variable),
                     NanoServiceErrorStatus::Unknown
@@ -42,3 +238,104 @@ impl GetConfigVariable for EnvConfig {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULTS: [(&str, &str); 2] = [("PORT", "8001"), ("PRODUCTION", "FALSE")];
+
+    fn file() -> HashMap<String, String> {
+        HashMap::from([
+            ("PORT".to_string(), "9000".to_string()),
+            ("DB_URL".to_string(), "postgres://file".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_env_overrides_file_and_defaults() {
+        let value = resolve_layered("PORT", Some("7000".to_string()), &file(), &DEFAULTS);
+        assert_eq!(value, Some("7000".to_string()));
+    }
+
+    #[test]
+    fn test_file_overrides_defaults() {
+        assert_eq!(resolve_layered("PORT", None, &file(), &DEFAULTS), Some("9000".to_string()));
+        assert_eq!(resolve_layered("DB_URL", None, &file(), &DEFAULTS), Some("postgres://file".to_string()));
+    }
+
+    #[test]
+    fn test_defaults_used_last() {
+        assert_eq!(resolve_layered("PRODUCTION", None, &file(), &DEFAULTS), Some("FALSE".to_string()));
+        assert_eq!(resolve_layered("MISSING", None, &file(), &DEFAULTS), None);
+    }
+
+    #[test]
+    fn test_parse_toml_flattens_tables() {
+        let contents = r#"
+            production = true
+            port = 8001
+
+            [mailchimp]
+            api_key = "key"
+        "#;
+        let variables = parse_config_file("config.toml", contents).unwrap();
+        assert_eq!(variables["PRODUCTION"], "true");
+        assert_eq!(variables["PORT"], "8001");
+        assert_eq!(variables["MAILCHIMP_API_KEY"], "key");
+    }
+
+    #[test]
+    fn test_parse_yaml_flattens_mappings() {
+        let contents = "production: false\nemail_outbox:\n  poll_seconds: 5\n";
+        let variables = parse_config_file("config.yml", contents).unwrap();
+        assert_eq!(variables["PRODUCTION"], "false");
+        assert_eq!(variables["EMAIL_OUTBOX_POLL_SECONDS"], "5");
+    }
+
+    #[test]
+    fn test_parse_unsupported_file() {
+        assert!(parse_config_file("config.json", "{}").is_err());
+        assert!(parse_config_file("config.toml", "not = = toml").is_err());
+    }
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "COUNT" => Ok(" 42 ".to_string()),
+                "ENABLED" => Ok("Yes".to_string()),
+                "TIMEOUT" => Ok("5m".to_string()),
+                "BROKEN" => Ok("soon".to_string()),
+                "ZERO" => Ok("0".to_string()),
+                _ => Err(NanoServiceError::new(format!("{} not found", variable), NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        assert_eq!(FakeConfig::get_int("COUNT").unwrap(), 42);
+        assert!(FakeConfig::get_bool("ENABLED").unwrap());
+        assert_eq!(FakeConfig::get_duration("TIMEOUT").unwrap(), Duration::from_secs(300));
+        assert!(FakeConfig::get_int("BROKEN").is_err());
+        assert!(FakeConfig::get_bool("BROKEN").is_err());
+        assert!(FakeConfig::get_duration("BROKEN").is_err());
+        assert!(FakeConfig::get_int("MISSING").is_err());
+        assert_eq!(FakeConfig::get_positive_int("COUNT").unwrap(), 42);
+        assert!(FakeConfig::get_positive_int("ZERO").is_err());
+        assert!(FakeConfig::get_positive_int("BROKEN").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(86400)));
+        assert_eq!(parse_duration("1w"), None);
+        assert_eq!(parse_duration("m"), None);
+    }
+}
@@ -3,6 +3,8 @@ use thiserror::Error;
 use std::fmt;
 
 use actix_web::{HttpResponse, error::ResponseError, http::StatusCode};
+use crate::i18n::{translate, LocalizedMessage};
+use crate::locale::Locale;
 
 
 #[derive(Error, Debug, Serialize, Deserialize, PartialEq)]
@@ -18,7 +20,15 @@ pub enum NanoServiceErrorStatus {
     #[error("Conflict")]
     Conflict,
     #[error("Unauthorized")]
-    Unauthorized
+    Unauthorized,
+    #[error("Unprocessable Entity")]
+    UnprocessableEntity,
+    #[error("Upgrade Required")]
+    UpgradeRequired,
+    #[error("Payload Too Large")]
+    PayloadTooLarge,
+    #[error("Unsupported Media Type")]
+    UnsupportedMediaType
 }
 
 impl NanoServiceErrorStatus {
@@ -36,6 +46,10 @@ impl NanoServiceErrorStatus {
             400 => NanoServiceErrorStatus::BadRequest,
             409 => NanoServiceErrorStatus::Conflict,
             401 => NanoServiceErrorStatus::Unauthorized,
+            422 => NanoServiceErrorStatus::UnprocessableEntity,
+            402 => NanoServiceErrorStatus::UpgradeRequired,
+            413 => NanoServiceErrorStatus::PayloadTooLarge,
+            415 => NanoServiceErrorStatus::UnsupportedMediaType,
             _ => NanoServiceErrorStatus::Unknown,
         }
     }
@@ -47,10 +61,16 @@ impl NanoServiceErrorStatus {
 /// # Fields
 /// * `message` - The message of the error.
 /// * `status` - The status of the error.
+/// * `details` - Structured context for the caller, such as the fields that failed validation.
+/// * `localized` - The message to show the caller in their own language, see `crate::i18n`.
 #[derive(Serialize, Deserialize, Debug, Error)]
 pub struct NanoServiceError {
     pub message: String,
-    pub status: NanoServiceErrorStatus
+    pub status: NanoServiceErrorStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+    #[serde(skip)]
+    pub localized: Option<LocalizedMessage>
 }
 
 impl NanoServiceError {
@@ -66,7 +86,55 @@ impl NanoServiceError {
     pub fn new(message: String, status: NanoServiceErrorStatus) -> NanoServiceError {
         NanoServiceError {
             message,
-            status
+            status,
+            details: None,
+            localized: None
+        }
+    }
+
+    /// Attaches structured context to the error, returned to the caller alongside the message.
+    /// 
+    /// # Arguments
+    /// * `details` - The context, such as the violations for each field.
+    /// 
+    /// # Returns
+    /// * `NanoServiceError` - The error with the details attached.
+    pub fn with_details(mut self, details: serde_json::Value) -> NanoServiceError {
+        self.details = Some(details);
+        self
+    }
+
+    /// Gives the error a message from the catalog in `crate::i18n`, shown in the caller's language.
+    /// 
+    /// # Arguments
+    /// * `key` - The key of the message, such as `auth.invalid_password`.
+    /// * `args` - The values for the message's named arguments.
+    /// 
+    /// # Returns
+    /// * `NanoServiceError` - The error with the message key attached.
+    pub fn with_message_key(mut self, key: &'static str, args: Vec<(&'static str, String)>) -> NanoServiceError {
+        self.localized = Some(LocalizedMessage { key, args });
+        self
+    }
+
+    /// The message in the locale's language, or the untranslated message if it has no key.
+    pub fn localized_message(&self, locale: Locale) -> String {
+        self.localized.as_ref().and_then(|localized| translate(locale, localized)).unwrap_or_else(|| self.message.clone())
+    }
+
+    /// Constructs the HTTP response for the error with its message in the locale's language.
+    pub fn localized_response(&self, locale: Locale) -> HttpResponse {
+        self.response_with_message(self.localized_message(locale))
+    }
+
+    fn response_with_message(&self, message: String) -> HttpResponse {
+        let status_code = self.status_code();
+        match &self.details {
+            Some(details) => HttpResponse::build(status_code).json(serde_json::json!({
+                "message": message,
+                "details": details
+            })),
+            None => HttpResponse::build(status_code).json(message)
         }
     }
 }
@@ -98,17 +166,28 @@ impl ResponseError for NanoServiceError {
             NanoServiceErrorStatus::Conflict => 
                 StatusCode::CONFLICT,
             NanoServiceErrorStatus::Unauthorized => 
-                StatusCode::UNAUTHORIZED
+                StatusCode::UNAUTHORIZED,
+            NanoServiceErrorStatus::UnprocessableEntity => 
+                StatusCode::UNPROCESSABLE_ENTITY,
+            NanoServiceErrorStatus::UpgradeRequired => 
+                StatusCode::PAYMENT_REQUIRED,
+            NanoServiceErrorStatus::PayloadTooLarge => 
+                StatusCode::PAYLOAD_TOO_LARGE,
+            NanoServiceErrorStatus::UnsupportedMediaType => 
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
         }
     }
 
     /// Constructs a HTTP response for the error.
     /// 
+    /// # Notes
+    /// The body is the message as a JSON string, or an object with the message and details if
+    /// details are attached.
+    /// 
     /// # Returns
     /// * `HttpResponse` - The HTTP response for the error.
     fn error_response(&self) -> HttpResponse {
-        let status_code = self.status_code();
-        HttpResponse::build(status_code).json(self.message.clone())
+        self.response_with_message(self.message.clone())
     }
 }
 
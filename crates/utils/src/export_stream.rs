@@ -0,0 +1,531 @@
+//! Streams large exports as CSV or JSON, gzipped on the fly when the client accepts it.
+//!
+//! # Overview
+//! An export reads the rows a page at a time with a keyset query and encodes each page into the
+//! response as it goes, so only one page and the compressor's window are held in memory however
+//! many rows are exported. The stream is pulled by the response body, so the next page is only
+//! read once the client has taken the bytes of the last one, and a slow client slows the reads
+//! down rather than building up a backlog in memory.
+//!
+//! `export_rows` encodes rows from a database cursor instead, for reads that stream rows with
+//! `fetch` rather than paging them. The rows are still pulled as the client takes the bytes, but
+//! the cursor holds its connection for the whole export.
+//!
+//! # Notes
+//! - An error part way through an export aborts the response, as the status has already been sent.
+//! - Gzip is only used when `Accept-Encoding` allows it, the response then carries
+//!   `Content-Encoding: gzip` so it is not compressed a second time.
+use std::future::Future;
+use std::io::Write;
+use std::pin::Pin;
+use actix_web::HttpResponse;
+use actix_web::http::header::{HeaderMap, ACCEPT_ENCODING, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_TYPE, VARY};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use futures_util::{stream, StreamExt};
+use serde::{Serialize, Deserialize};
+use crate::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+// re-exported so the crates returning an export do not need their own dependency on actix or futures
+pub use actix_web::web::Bytes;
+pub use futures_util::stream::Stream;
+
+
+/// The rows read for each page of an export when not configured.
+pub const DEFAULT_EXPORT_PAGE_SIZE: usize = 1000;
+
+
+/// Rows read from a database cursor, see `export_rows`.
+pub type RowStream<T> = Pin<Box<dyn Stream<Item = Result<T, NanoServiceError>> + Send>>;
+
+
+/// The format an export is returned in.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+
+/// How the bytes of an export are encoded on the wire.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportEncoding {
+    Identity,
+    Gzip,
+}
+
+impl ExportEncoding {
+
+    /// Picks gzip if the `Accept-Encoding` header allows it.
+    ///
+    /// # Notes
+    /// `gzip;q=0` refuses gzip, and `*` accepts it unless gzip is refused by name.
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let accept_encoding = headers.get(ACCEPT_ENCODING).and_then(|value| value.to_str().ok()).unwrap_or_default();
+        let mut wildcard = false;
+        for coding in accept_encoding.split(',') {
+            let mut parts = coding.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default().to_ascii_lowercase();
+            let refused = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .any(|quality| quality.parse::<f32>().map(|quality| quality <= 0.0).unwrap_or(false));
+            match name.as_str() {
+                "gzip" | "x-gzip" => return if refused { ExportEncoding::Identity } else { ExportEncoding::Gzip },
+                "*" => wildcard = !refused,
+                _ => {}
+            }
+        }
+        if wildcard { ExportEncoding::Gzip } else { ExportEncoding::Identity }
+    }
+}
+
+
+/// A row that can be written to a CSV export.
+pub trait CsvRow {
+
+    /// The header row, without the trailing newline.
+    const CSV_HEADER: &'static str;
+
+    /// The row, without the trailing newline, with fields escaped by `csv_field`.
+    fn csv_row(&self) -> String;
+}
+
+
+/// Quotes a CSV field if it holds a comma, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+
+enum ChunkEncoder {
+    Identity(Vec<u8>),
+    Gzip(GzEncoder<Vec<u8>>),
+}
+
+impl ChunkEncoder {
+
+    fn new(encoding: ExportEncoding) -> Self {
+        match encoding {
+            ExportEncoding::Identity => ChunkEncoder::Identity(Vec::new()),
+            ExportEncoding::Gzip => ChunkEncoder::Gzip(GzEncoder::new(Vec::new(), Compression::default())),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), NanoServiceError> {
+        match self {
+            ChunkEncoder::Identity(buffer) => buffer.extend_from_slice(data),
+            ChunkEncoder::Gzip(encoder) => encoder.write_all(data).map_err(compression_error)?,
+        }
+        Ok(())
+    }
+
+    /// Takes the bytes encoded so far, gzip holds some back until it has enough to compress.
+    fn take(&mut self) -> Bytes {
+        match self {
+            ChunkEncoder::Identity(buffer) => Bytes::from(std::mem::take(buffer)),
+            ChunkEncoder::Gzip(encoder) => Bytes::from(std::mem::take(encoder.get_mut())),
+        }
+    }
+
+    fn finish(self) -> Result<Bytes, NanoServiceError> {
+        match self {
+            ChunkEncoder::Identity(buffer) => Ok(Bytes::from(buffer)),
+            ChunkEncoder::Gzip(encoder) => encoder.finish().map(Bytes::from).map_err(compression_error),
+        }
+    }
+}
+
+
+fn compression_error(e: std::io::Error) -> NanoServiceError {
+    NanoServiceError::new(format!("Failed to compress export: {}", e), NanoServiceErrorStatus::Unknown)
+}
+
+
+struct ExportState<T, F> {
+    fetch_page: F,
+    key: fn(&T) -> i64,
+    page_size: usize,
+    format: ExportFormat,
+    cursor: Option<i64>,
+    rows_written: u64,
+    encoder: Option<ChunkEncoder>,
+}
+
+/// Encodes a row, JSON rows after the first are preceded by the separator.
+fn encode_row<T: Serialize + CsvRow>(format: ExportFormat, rows_written: u64, row: &T) -> Result<String, NanoServiceError> {
+    match format {
+        ExportFormat::Csv => Ok(format!("{}\n", row.csv_row())),
+        ExportFormat::Json => {
+            let separator = if rows_written == 0 { "" } else { "," };
+            let json = serde_json::to_string(row).map_err(|e| NanoServiceError::new(
+                format!("Failed to serialize export row: {}", e),
+                NanoServiceErrorStatus::Unknown
+            ))?;
+            Ok(format!("{}{}", separator, json))
+        }
+    }
+}
+
+
+/// Writes the end of the export and takes the last of its bytes.
+fn finish_export(mut encoder: ChunkEncoder, format: ExportFormat) -> Result<Bytes, NanoServiceError> {
+    let closing: &[u8] = if format == ExportFormat::Json { b"]" } else { b"" };
+    encoder.write(closing)?;
+    encoder.finish()
+}
+
+
+/// The encoder with the opening of the export written, `None` if it could not be written.
+fn start_export<T: CsvRow>(format: ExportFormat, encoding: ExportEncoding) -> Option<ChunkEncoder> {
+    let mut encoder = ChunkEncoder::new(encoding);
+    let opening = match format {
+        ExportFormat::Csv => format!("{}\n", T::CSV_HEADER),
+        ExportFormat::Json => "[".to_string(),
+    };
+    encoder.write(opening.as_bytes()).ok().map(|_| encoder)
+}
+
+
+impl<T: Serialize + CsvRow, F> ExportState<T, F> {
+
+    fn write_rows(&mut self, rows: &[T]) -> Result<(), NanoServiceError> {
+        let encoder = self.encoder.as_mut().expect("rows are only written before the export finishes");
+        for row in rows {
+            encoder.write(encode_row(self.format, self.rows_written, row)?.as_bytes())?;
+            self.rows_written += 1;
+        }
+        Ok(())
+    }
+
+    /// Reads pages until there are bytes to send, returning `None` once the export is finished.
+    async fn next_chunk<Fut>(&mut self) -> Option<Result<Bytes, NanoServiceError>>
+    where
+        F: FnMut(Option<i64>) -> Fut,
+        Fut: Future<Output = Result<Vec<T>, NanoServiceError>>
+    {
+        self.encoder.as_ref()?;
+        loop {
+            let outcome = match (self.fetch_page)(self.cursor).await {
+                Ok(rows) => self.write_rows(&rows).map(|_| rows),
+                Err(e) => Err(e)
+            };
+            let rows = match outcome {
+                Ok(rows) => rows,
+                Err(e) => {
+                    self.encoder = None;
+                    return Some(Err(e))
+                }
+            };
+            self.cursor = rows.last().map(self.key).or(self.cursor);
+            if rows.len() < self.page_size {
+                return Some(finish_export(self.encoder.take()?, self.format))
+            }
+            let chunk = self.encoder.as_mut()?.take();
+            if !chunk.is_empty() {
+                return Some(Ok(chunk))
+            }
+        }
+    }
+}
+
+
+/// Streams every row of an export, reading it a page at a time.
+///
+/// # Arguments
+/// * `format` - CSV or JSON, JSON being a single array.
+/// * `encoding` - Whether to gzip the bytes.
+/// * `page_size` - The rows read for each page, a shorter page ends the export.
+/// * `key` - The keyset position of a row, the next page is read after the last row's.
+/// * `fetch_page` - Reads up to `page_size` rows after the position, `None` for the first page.
+pub fn export_stream<T, F, Fut>(
+    format: ExportFormat,
+    encoding: ExportEncoding,
+    page_size: usize,
+    key: fn(&T) -> i64,
+    fetch_page: F
+) -> impl Stream<Item = Result<Bytes, NanoServiceError>>
+where
+    T: Serialize + CsvRow,
+    F: FnMut(Option<i64>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, NanoServiceError>>
+{
+    let state = ExportState {
+        fetch_page,
+        key,
+        page_size: page_size.max(1),
+        format,
+        cursor: None,
+        rows_written: 0,
+        // an encoder that cannot take the opening ends the export before it starts
+        encoder: start_export::<T>(format, encoding),
+    };
+    stream::unfold(state, |mut state| async move {
+        state.next_chunk().await.map(|chunk| (chunk, state))
+    })
+}
+
+
+struct RowExportState<T> {
+    rows: RowStream<T>,
+    chunk_rows: usize,
+    format: ExportFormat,
+    rows_written: u64,
+    encoder: Option<ChunkEncoder>,
+}
+
+impl<T: Serialize + CsvRow> RowExportState<T> {
+
+    /// Reads rows until `chunk_rows` have been encoded into bytes to send, returning `None` once
+    /// the export is finished.
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, NanoServiceError>> {
+        self.encoder.as_ref()?;
+        let mut chunk_written = 0;
+        loop {
+            let row = match self.rows.next().await {
+                Some(row) => row,
+                None => return Some(finish_export(self.encoder.take()?, self.format))
+            };
+            let encoder = self.encoder.as_mut()?;
+            let outcome = row.and_then(|row| encode_row(self.format, self.rows_written, &row))
+                .and_then(|encoded| encoder.write(encoded.as_bytes()));
+            if let Err(e) = outcome {
+                self.encoder = None;
+                return Some(Err(e))
+            }
+            self.rows_written += 1;
+            chunk_written += 1;
+            if chunk_written >= self.chunk_rows {
+                chunk_written = 0;
+                let chunk = self.encoder.as_mut()?.take();
+                if !chunk.is_empty() {
+                    return Some(Ok(chunk))
+                }
+            }
+        }
+    }
+}
+
+
+/// Streams every row of an export from a database cursor.
+///
+/// # Arguments
+/// * `format` - CSV or JSON, JSON being a single array.
+/// * `encoding` - Whether to gzip the bytes.
+/// * `chunk_rows` - The rows encoded into each chunk sent.
+/// * `rows` - The rows, read as the client takes the bytes.
+pub fn export_rows<T>(
+    format: ExportFormat,
+    encoding: ExportEncoding,
+    chunk_rows: usize,
+    rows: RowStream<T>
+) -> impl Stream<Item = Result<Bytes, NanoServiceError>>
+where
+    T: Serialize + CsvRow
+{
+    let state = RowExportState {
+        rows,
+        chunk_rows: chunk_rows.max(1),
+        format,
+        rows_written: 0,
+        encoder: start_export::<T>(format, encoding),
+    };
+    stream::unfold(state, |mut state| async move {
+        state.next_chunk().await.map(|chunk| (chunk, state))
+    })
+}
+
+
+/// Builds the response for an export, named `<name>.csv` or `<name>.json`.
+///
+/// # Arguments
+/// * `format` - The format the stream was written in.
+/// * `encoding` - The encoding the stream was written with.
+/// * `name` - The file name offered to the client, without the extension.
+/// * `body` - The stream from `export_stream`.
+pub fn export_response<S>(format: ExportFormat, encoding: ExportEncoding, name: &str, body: S) -> HttpResponse
+where
+    S: Stream<Item = Result<Bytes, NanoServiceError>> + 'static
+{
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header((CONTENT_TYPE, format.content_type()))
+        .insert_header((CONTENT_DISPOSITION, format!("attachment; filename=\"{}.{}\"", name, format.extension())))
+        .insert_header((VARY, "Accept-Encoding"));
+    if encoding == ExportEncoding::Gzip {
+        response.insert_header((CONTENT_ENCODING, "gzip"));
+    }
+    response.streaming(body)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use actix_web::http::header::HeaderValue;
+    use flate2::read::GzDecoder;
+    use futures_util::StreamExt;
+
+    #[derive(Serialize)]
+    struct Row {
+        id: i64,
+        name: String,
+    }
+
+    impl CsvRow for Row {
+        const CSV_HEADER: &'static str = "id,name";
+
+        fn csv_row(&self) -> String {
+            format!("{},{}", self.id, csv_field(&self.name))
+        }
+    }
+
+    /// Serves rows 1 to `total` a page at a time, counting the pages read.
+    fn rows(total: i64, pages: Arc<AtomicUsize>) -> impl FnMut(Option<i64>) -> std::future::Ready<Result<Vec<Row>, NanoServiceError>> {
+        move |after| {
+            pages.fetch_add(1, Ordering::SeqCst);
+            let start = after.unwrap_or(0) + 1;
+            std::future::ready(Ok((start..=total).take(100).map(|id| Row { id, name: format!("row, {}", id) }).collect()))
+        }
+    }
+
+    fn gunzip(bytes: &[u8]) -> String {
+        let mut decoded = String::new();
+        GzDecoder::new(bytes).read_to_string(&mut decoded).unwrap();
+        decoded
+    }
+
+    async fn collect<S: Stream<Item = Result<Bytes, NanoServiceError>>>(stream: S) -> Vec<u8> {
+        let chunks: Vec<Result<Bytes, NanoServiceError>> = stream.collect().await;
+        chunks.into_iter().flat_map(|chunk| chunk.unwrap().to_vec()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_csv_export() {
+        let pages = Arc::new(AtomicUsize::new(0));
+        let body = collect(export_stream(ExportFormat::Csv, ExportEncoding::Identity, 100, |row: &Row| row.id, rows(250, pages.clone()))).await;
+        let csv = String::from_utf8(body).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 251);
+        assert_eq!(lines[0], "id,name");
+        assert_eq!(lines[250], "250,\"row, 250\"");
+        assert_eq!(pages.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gzipped_json_export() {
+        let pages = Arc::new(AtomicUsize::new(0));
+        let body = collect(export_stream(ExportFormat::Json, ExportEncoding::Gzip, 100, |row: &Row| row.id, rows(200, pages.clone()))).await;
+        let json: serde_json::Value = serde_json::from_str(&gunzip(&body)).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 200);
+        assert_eq!(json[199]["id"], 200);
+        // a full last page is only known to be last once the empty page after it is read
+        assert_eq!(pages.load(Ordering::SeqCst), 3);
+
+        let empty = collect(export_stream(ExportFormat::Json, ExportEncoding::Gzip, 100, |row: &Row| row.id, rows(0, pages))).await;
+        assert_eq!(gunzip(&empty), "[]");
+    }
+
+    #[tokio::test]
+    async fn test_slow_client_applies_backpressure() {
+        let pages = Arc::new(AtomicUsize::new(0));
+        let mut stream = Box::pin(export_stream(ExportFormat::Csv, ExportEncoding::Identity, 100, |row: &Row| row.id, rows(10_000, pages.clone())));
+
+        for taken in 1..=3 {
+            let chunk = stream.next().await.unwrap().unwrap();
+            assert!(chunk.len() < 2500, "a chunk holds at most one page");
+            // the client is slow to take the next chunk, nothing is read ahead in the meantime
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            assert_eq!(pages.load(Ordering::SeqCst), taken);
+        }
+
+        // the client going away drops the stream, and no more pages are read
+        drop(stream);
+        assert_eq!(pages.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_error_ends_export() {
+        let mut calls = 0;
+        let stream = export_stream(ExportFormat::Csv, ExportEncoding::Identity, 1, |row: &Row| row.id, move |_| {
+            calls += 1;
+            std::future::ready(match calls {
+                1 => Ok(vec![Row { id: 1, name: "a".to_string() }]),
+                _ => Err(NanoServiceError::new("connection lost".to_string(), NanoServiceErrorStatus::Unknown))
+            })
+        });
+        let chunks: Vec<Result<Bytes, NanoServiceError>> = stream.collect().await;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].as_ref().unwrap().as_ref(), b"id,name\n1,a\n");
+        assert_eq!(chunks[1].as_ref().unwrap_err().message, "connection lost");
+    }
+
+    #[tokio::test]
+    async fn test_export_rows() {
+        let rows: RowStream<Row> = Box::pin(stream::iter((1..=250).map(|id| Ok(Row { id, name: format!("row, {}", id) }))));
+        let mut chunks = Box::pin(export_rows(ExportFormat::Csv, ExportEncoding::Identity, 100, rows));
+        let first = chunks.next().await.unwrap().unwrap();
+        assert_eq!(String::from_utf8(first.to_vec()).unwrap().lines().count(), 101);
+        let rest = collect(chunks).await;
+        let csv = String::from_utf8(rest).unwrap();
+        assert_eq!(csv.lines().count(), 150);
+        assert_eq!(csv.lines().last().unwrap(), "250,\"row, 250\"");
+
+        let rows: RowStream<Row> = Box::pin(stream::iter(vec![
+            Ok(Row { id: 1, name: "a".to_string() }),
+            Err(NanoServiceError::new("connection lost".to_string(), NanoServiceErrorStatus::Unknown))
+        ]));
+        let chunks: Vec<Result<Bytes, NanoServiceError>> = export_rows(ExportFormat::Json, ExportEncoding::Identity, 100, rows).collect().await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].as_ref().unwrap_err().message, "connection lost");
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let negotiate = |accept_encoding: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT_ENCODING, HeaderValue::from_str(accept_encoding).unwrap());
+            ExportEncoding::negotiate(&headers)
+        };
+        assert_eq!(negotiate("gzip, deflate, br"), ExportEncoding::Gzip);
+        assert_eq!(negotiate("br;q=1.0, gzip;q=0.5"), ExportEncoding::Gzip);
+        assert_eq!(negotiate("gzip;q=0"), ExportEncoding::Identity);
+        assert_eq!(negotiate("*"), ExportEncoding::Gzip);
+        assert_eq!(negotiate("*, gzip;q=0"), ExportEncoding::Identity);
+        assert_eq!(negotiate("identity"), ExportEncoding::Identity);
+        assert_eq!(ExportEncoding::negotiate(&HeaderMap::new()), ExportEncoding::Identity);
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,233 @@
+//! Randomly delays or fails calls to the database, email provider and session cache.
+//!
+//! # Overview
+//! Staging rarely sees a deadlock, a failed send or a slow cache, so the code that handles them
+//! goes unexercised. With fault injection turned on, the calls wrapped by `inject_fault` are
+//! delayed or failed at the configured rates before they are made:
+//! - Database statements run through `retry_transient` fail with a dropped connection, so they are retried,
+//!   and those run through `retry_write` return the error.
+//! - Emails sent through Mailchimp fail with an `Unknown` error, so the outbox backs off and sends them again.
+//! - Session cache lookups fail with an `Unknown` error, so the request is rejected.
+//!
+//! # Configuration
+//! - `FAULT_INJECTION`: Set to `true` to turn fault injection on, it is off by default.
+//! - `FAULT_INJECTION_TARGETS`: The comma separated targets, from `dal`, `email` and `cache`, defaults to all of them.
+//! - `FAULT_INJECTION_FAILURE_PERCENT`: The percentage of calls that fail, defaults to 10.
+//! - `FAULT_INJECTION_DELAY_PERCENT`: The percentage of calls that are delayed, defaults to 10.
+//! - `FAULT_INJECTION_MAX_DELAY_MS`: The most a call is delayed by, defaults to 2000.
+//!
+//! # Notes
+//! Fault injection only exists in debug builds, in a release build `inject_fault` does nothing
+//! whatever the environment says.
+use std::fmt;
+use std::sync::LazyLock;
+use std::time::Duration;
+use rand::Rng;
+
+
+/// A kind of call that faults can be injected into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultTarget {
+    Dal,
+    Email,
+    Cache,
+}
+
+impl FaultTarget {
+
+    /// Every target, in the order they are listed.
+    pub const ALL: [FaultTarget; 3] = [FaultTarget::Dal, FaultTarget::Email, FaultTarget::Cache];
+
+    /// The name the target is configured and logged by.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FaultTarget::Dal => "dal",
+            FaultTarget::Email => "email",
+            FaultTarget::Cache => "cache",
+        }
+    }
+}
+
+
+/// The failure made in place of a call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InjectedFault(pub FaultTarget);
+
+impl fmt::Display for InjectedFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "injected {} fault", self.0.as_str())
+    }
+}
+
+
+/// Which calls get faults and how often.
+///
+/// # Fields
+/// * `targets` - The kinds of call faults are injected into.
+/// * `failure_percent` - The percentage of calls that fail.
+/// * `delay_percent` - The percentage of calls that are delayed, a delayed call can still fail.
+/// * `max_delay` - The most a call is delayed by, the delay is random up to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultConfig {
+    pub targets: Vec<FaultTarget>,
+    pub failure_percent: f64,
+    pub delay_percent: f64,
+    pub max_delay: Duration,
+}
+
+impl FaultConfig {
+
+    /// Reads the config with `lookup`, returning `None` if fault injection is not turned on.
+    ///
+    /// # Panics
+    /// - If a target is unknown, or a percentage or the delay is not a number.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        if !lookup("FAULT_INJECTION").is_some_and(|enabled| enabled.trim().eq_ignore_ascii_case("true")) {
+            return None
+        }
+        let targets = match lookup("FAULT_INJECTION_TARGETS") {
+            Some(targets) => targets.split(',').map(str::trim).filter(|target| !target.is_empty()).map(|target| {
+                FaultTarget::ALL.into_iter()
+                    .find(|known| known.as_str().eq_ignore_ascii_case(target))
+                    .unwrap_or_else(|| panic!("Unknown fault injection target {}", target))
+            }).collect(),
+            None => FaultTarget::ALL.to_vec()
+        };
+        let percent = |key: &str, default: f64| -> f64 {
+            match lookup(key) {
+                Some(value) => value.trim().parse::<f64>().unwrap_or_else(|_| panic!("Could not parse {}", key)).clamp(0.0, 100.0),
+                None => default
+            }
+        };
+        let max_delay_ms = match lookup("FAULT_INJECTION_MAX_DELAY_MS") {
+            Some(value) => value.trim().parse::<u64>().unwrap_or_else(|_| panic!("Could not parse FAULT_INJECTION_MAX_DELAY_MS")),
+            None => 2000
+        };
+        Some(FaultConfig {
+            targets,
+            failure_percent: percent("FAULT_INJECTION_FAILURE_PERCENT", 10.0),
+            delay_percent: percent("FAULT_INJECTION_DELAY_PERCENT", 10.0),
+            max_delay: Duration::from_millis(max_delay_ms),
+        })
+    }
+
+    /// Picks the fault for a call.
+    ///
+    /// # Arguments
+    /// * `target` - The kind of call.
+    /// * `rng` - The source of the rolls.
+    ///
+    /// # Returns
+    /// * `(Option<Duration>, bool)` - How long to delay the call, if at all, and whether it fails.
+    pub fn roll(&self, target: FaultTarget, rng: &mut impl Rng) -> (Option<Duration>, bool) {
+        if !self.targets.contains(&target) {
+            return (None, false)
+        }
+        let delay = (rng.gen_range(0.0..100.0) < self.delay_percent).then(|| {
+            let max_micros = self.max_delay.as_micros().min(u64::MAX as u128) as u64;
+            Duration::from_micros(rng.gen_range(0..=max_micros))
+        });
+        (delay, rng.gen_range(0.0..100.0) < self.failure_percent)
+    }
+}
+
+
+/// The fault injection config, read from the environment on first use.
+static FAULT_CONFIG: LazyLock<Option<FaultConfig>> = LazyLock::new(|| {
+    let config = FaultConfig::from_lookup(|key| std::env::var(key).ok());
+    if let Some(config) = &config {
+        println!("fault injection is on: {:?}", config);
+    }
+    config
+});
+
+
+/// Delays or fails a call before it is made, at the rates in the environment.
+///
+/// # Arguments
+/// * `target` - The kind of call about to be made.
+///
+/// # Returns
+/// * `Ok(())` - If the call should be made, after any delay has passed.
+/// * `Err(InjectedFault)` - If the call should fail instead.
+pub async fn inject_fault(target: FaultTarget) -> Result<(), InjectedFault> {
+    if !cfg!(debug_assertions) {
+        return Ok(())
+    }
+    let Some(config) = FAULT_CONFIG.as_ref() else {
+        return Ok(())
+    };
+    let (delay, fail) = config.roll(target, &mut rand::thread_rng());
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    if fail {
+        println!("{}", InjectedFault(target));
+        return Err(InjectedFault(target))
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> Option<FaultConfig> {
+        let vars: HashMap<String, String> = vars.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        FaultConfig::from_lookup(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn test_from_lookup() {
+        assert_eq!(config(&[]), None);
+        assert_eq!(config(&[("FAULT_INJECTION", "false"), ("FAULT_INJECTION_FAILURE_PERCENT", "50")]), None);
+        assert_eq!(config(&[("FAULT_INJECTION", "true")]), Some(FaultConfig {
+            targets: FaultTarget::ALL.to_vec(),
+            failure_percent: 10.0,
+            delay_percent: 10.0,
+            max_delay: Duration::from_millis(2000),
+        }));
+        assert_eq!(config(&[
+            ("FAULT_INJECTION", "TRUE"),
+            ("FAULT_INJECTION_TARGETS", "email, Cache"),
+            ("FAULT_INJECTION_FAILURE_PERCENT", "150"),
+            ("FAULT_INJECTION_DELAY_PERCENT", "2.5"),
+            ("FAULT_INJECTION_MAX_DELAY_MS", "50"),
+        ]), Some(FaultConfig {
+            targets: vec![FaultTarget::Email, FaultTarget::Cache],
+            failure_percent: 100.0,
+            delay_percent: 2.5,
+            max_delay: Duration::from_millis(50),
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown fault injection target redis")]
+    fn test_from_lookup_unknown_target() {
+        config(&[("FAULT_INJECTION", "true"), ("FAULT_INJECTION_TARGETS", "dal,redis")]);
+    }
+
+    #[test]
+    fn test_roll() {
+        let config = FaultConfig {
+            targets: vec![FaultTarget::Dal],
+            failure_percent: 100.0,
+            delay_percent: 0.0,
+            max_delay: Duration::from_millis(5),
+        };
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            assert_eq!(config.roll(FaultTarget::Dal, &mut rng), (None, true));
+            assert_eq!(config.roll(FaultTarget::Email, &mut rng), (None, false));
+        }
+
+        let config = FaultConfig { failure_percent: 0.0, delay_percent: 100.0, ..config };
+        for _ in 0..50 {
+            let (delay, fail) = config.roll(FaultTarget::Dal, &mut rng);
+            assert!(delay.unwrap() <= Duration::from_millis(5));
+            assert!(!fail);
+        }
+    }
+}
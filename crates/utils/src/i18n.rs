@@ -0,0 +1,313 @@
+//! Translates user-facing error messages and email copy into the reader's language.
+//!
+//! # Overview
+//! Messages are looked up by key, such as `auth.invalid_password`, in a catalog of templates per
+//! language. A template names its arguments in braces, such as `Missing required permission: {permission}`.
+//! A key missing from a language falls back to English, so a new message only needs an English
+//! template to ship.
+//!
+//! The reader's language comes from where they are read:
+//! - Errors are returned with `NanoServiceError::with_message_key` and rendered in the language of
+//!   the request's `Accept-Language` header by the `localize_errors` middleware. Errors without a
+//!   key keep their message.
+//! - Emails are written in the recipient's stored locale, `email_merge_vars` gives the copy for a
+//!   template as merge variables.
+//!
+//! # Notes
+//! The message passed to `NanoServiceError::new` stays in English, it is what gets logged and what
+//! callers that never pass through the middleware see.
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error
+};
+use crate::errors::NanoServiceError;
+use crate::locale::Locale;
+
+
+/// A language with its own catalog.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    English,
+    German,
+    Spanish,
+    French,
+}
+
+impl Language {
+
+    /// The language a locale is written in, locales without their own language use English.
+    pub fn of(locale: Locale) -> Self {
+        match locale {
+            Locale::Neutral | Locale::EnGb | Locale::EnUs => Language::English,
+            Locale::De => Language::German,
+            Locale::Es => Language::Spanish,
+            Locale::Fr => Language::French,
+        }
+    }
+
+    fn catalog(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => ENGLISH,
+            Language::German => GERMAN,
+            Language::Spanish => SPANISH,
+            Language::French => FRENCH,
+        }
+    }
+}
+
+
+static ENGLISH: &[(&str, &str)] = &[
+    ("auth.user_blocked", "Your account has been blocked"),
+    ("auth.user_not_confirmed", "Please confirm your email address before logging in"),
+    ("auth.invalid_password", "The password is incorrect"),
+    ("auth.missing_role", "Your account does not have the {role} role"),
+    ("auth.missing_token", "You need to log in to do this"),
+    ("auth.session_idle", "Your session has timed out, please log in again"),
+    ("auth.csrf_mismatch", "Your session could not be checked, please reload the page and try again"),
+    ("auth.session_limit", "You are logged in on {max} devices, log out of one to log in here"),
+    ("auth.insufficient_role", "Your role does not allow this"),
+    ("auth.missing_permission", "You need the {permission} permission to do this"),
+    ("email.confirmation-email.subject", "Confirm your email address"),
+    ("email.confirmation-email.heading", "Welcome"),
+    ("email.confirmation-email.body", "Confirm your email address to start using your account."),
+    ("email.confirmation-email.action", "Confirm email"),
+    ("email.password-reset.subject", "Reset your password"),
+    ("email.password-reset.heading", "Reset your password"),
+    ("email.password-reset.body", "Choose a new password with the link below. If you did not ask to reset it you can ignore this email."),
+    ("email.password-reset.action", "Reset password"),
+    ("email.invite.subject", "You have been invited"),
+    ("email.invite.heading", "You have been invited"),
+    ("email.invite.body", "You have been invited to join your team. Accept the invite to set up your account."),
+    ("email.invite.action", "Accept invite"),
+];
+
+static GERMAN: &[(&str, &str)] = &[
+    ("auth.user_blocked", "Ihr Konto wurde gesperrt"),
+    ("auth.user_not_confirmed", "Bitte bestätigen Sie Ihre E-Mail-Adresse, bevor Sie sich anmelden"),
+    ("auth.invalid_password", "Das Passwort ist falsch"),
+    ("auth.missing_role", "Ihr Konto hat nicht die Rolle {role}"),
+    ("auth.missing_token", "Sie müssen sich dafür anmelden"),
+    ("auth.session_idle", "Ihre Sitzung ist abgelaufen, bitte melden Sie sich erneut an"),
+    ("auth.csrf_mismatch", "Ihre Sitzung konnte nicht geprüft werden, bitte laden Sie die Seite neu und versuchen Sie es erneut"),
+    ("auth.session_limit", "Sie sind auf {max} Geräten angemeldet, melden Sie sich auf einem ab, um sich hier anzumelden"),
+    ("auth.insufficient_role", "Ihre Rolle erlaubt das nicht"),
+    ("auth.missing_permission", "Dafür benötigen Sie die Berechtigung {permission}"),
+    ("email.confirmation-email.subject", "Bestätigen Sie Ihre E-Mail-Adresse"),
+    ("email.confirmation-email.heading", "Willkommen"),
+    ("email.confirmation-email.body", "Bestätigen Sie Ihre E-Mail-Adresse, um Ihr Konto zu nutzen."),
+    ("email.confirmation-email.action", "E-Mail bestätigen"),
+    ("email.password-reset.subject", "Passwort zurücksetzen"),
+    ("email.password-reset.heading", "Passwort zurücksetzen"),
+    ("email.password-reset.body", "Wählen Sie über den Link unten ein neues Passwort. Wenn Sie das nicht angefordert haben, können Sie diese E-Mail ignorieren."),
+    ("email.password-reset.action", "Passwort zurücksetzen"),
+    ("email.invite.subject", "Sie wurden eingeladen"),
+    ("email.invite.heading", "Sie wurden eingeladen"),
+    ("email.invite.body", "Sie wurden eingeladen, Ihrem Team beizutreten. Nehmen Sie die Einladung an, um Ihr Konto einzurichten."),
+    ("email.invite.action", "Einladung annehmen"),
+];
+
+static SPANISH: &[(&str, &str)] = &[
+    ("auth.user_blocked", "Tu cuenta ha sido bloqueada"),
+    ("auth.user_not_confirmed", "Confirma tu dirección de correo antes de iniciar sesión"),
+    ("auth.invalid_password", "La contraseña es incorrecta"),
+    ("auth.missing_role", "Tu cuenta no tiene el rol {role}"),
+    ("auth.missing_token", "Tienes que iniciar sesión para hacer esto"),
+    ("auth.session_idle", "Tu sesión ha caducado, vuelve a iniciar sesión"),
+    ("auth.csrf_mismatch", "No se ha podido comprobar tu sesión, recarga la página y vuelve a intentarlo"),
+    ("auth.session_limit", "Has iniciado sesión en {max} dispositivos, cierra sesión en uno para iniciar sesión aquí"),
+    ("auth.insufficient_role", "Tu rol no permite hacer esto"),
+    ("auth.missing_permission", "Necesitas el permiso {permission} para hacer esto"),
+    ("email.confirmation-email.subject", "Confirma tu dirección de correo"),
+    ("email.confirmation-email.heading", "Bienvenido"),
+    ("email.confirmation-email.body", "Confirma tu dirección de correo para empezar a usar tu cuenta."),
+    ("email.confirmation-email.action", "Confirmar correo"),
+    ("email.password-reset.subject", "Restablece tu contraseña"),
+    ("email.password-reset.heading", "Restablece tu contraseña"),
+    ("email.password-reset.body", "Elige una contraseña nueva con el enlace de abajo. Si no lo has pedido puedes ignorar este correo."),
+    ("email.password-reset.action", "Restablecer contraseña"),
+    ("email.invite.subject", "Te han invitado"),
+    ("email.invite.heading", "Te han invitado"),
+    ("email.invite.body", "Te han invitado a unirte a tu equipo. Acepta la invitación para crear tu cuenta."),
+    ("email.invite.action", "Aceptar invitación"),
+];
+
+static FRENCH: &[(&str, &str)] = &[
+    ("auth.user_blocked", "Votre compte a été bloqué"),
+    ("auth.user_not_confirmed", "Veuillez confirmer votre adresse e-mail avant de vous connecter"),
+    ("auth.invalid_password", "Le mot de passe est incorrect"),
+    ("auth.missing_role", "Votre compte n'a pas le rôle {role}"),
+    ("auth.missing_token", "Vous devez vous connecter pour faire cela"),
+    ("auth.session_idle", "Votre session a expiré, veuillez vous reconnecter"),
+    ("auth.csrf_mismatch", "Votre session n'a pas pu être vérifiée, veuillez recharger la page et réessayer"),
+    ("auth.session_limit", "Vous êtes connecté sur {max} appareils, déconnectez-vous de l'un d'eux pour vous connecter ici"),
+    ("auth.insufficient_role", "Votre rôle ne le permet pas"),
+    ("auth.missing_permission", "Il vous faut la permission {permission} pour faire cela"),
+    ("email.confirmation-email.subject", "Confirmez votre adresse e-mail"),
+    ("email.confirmation-email.heading", "Bienvenue"),
+    ("email.confirmation-email.body", "Confirmez votre adresse e-mail pour commencer à utiliser votre compte."),
+    ("email.confirmation-email.action", "Confirmer l'e-mail"),
+    ("email.password-reset.subject", "Réinitialisez votre mot de passe"),
+    ("email.password-reset.heading", "Réinitialisez votre mot de passe"),
+    ("email.password-reset.body", "Choisissez un nouveau mot de passe avec le lien ci-dessous. Si vous ne l'avez pas demandé, vous pouvez ignorer cet e-mail."),
+    ("email.password-reset.action", "Réinitialiser le mot de passe"),
+    ("email.invite.subject", "Vous avez été invité"),
+    ("email.invite.heading", "Vous avez été invité"),
+    ("email.invite.body", "Vous avez été invité à rejoindre votre équipe. Acceptez l'invitation pour configurer votre compte."),
+    ("email.invite.action", "Accepter l'invitation"),
+];
+
+
+/// The merge variables an email's copy is given as, and the part of the key each is looked up by.
+const EMAIL_PARTS: [(&str, &str); 4] = [
+    ("SUBJECT", "subject"),
+    ("HEADING", "heading"),
+    ("BODY", "body"),
+    ("ACTION", "action"),
+];
+
+
+/// A message to render in the reader's language.
+///
+/// # Fields
+/// * `key` - The key of the message in the catalog.
+/// * `args` - The values for the template's named arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalizedMessage {
+    pub key: &'static str,
+    pub args: Vec<(&'static str, String)>,
+}
+
+
+/// Finds the template for a key, falling back to English.
+///
+/// # Returns
+/// * `Some(&str)` - The template in the locale's language, or in English if it has not been translated.
+/// * `None` - If the key is not in the catalog at all.
+pub fn lookup(locale: Locale, key: &str) -> Option<&'static str> {
+    let find = |language: Language| language.catalog().iter().find(|(found, _)| *found == key).map(|(_, template)| *template);
+    find(Language::of(locale)).or_else(|| find(Language::English))
+}
+
+
+/// Fills the named arguments of a template, arguments that are not given are left as they are.
+pub fn render(template: &str, args: &[(&str, String)]) -> String {
+    args.iter().fold(template.to_string(), |rendered, (name, value)| rendered.replace(&format!("{{{}}}", name), value))
+}
+
+
+/// Renders a message in the locale's language.
+///
+/// # Returns
+/// * `Some(String)` - The rendered message.
+/// * `None` - If the key is not in the catalog.
+pub fn translate(locale: Locale, message: &LocalizedMessage) -> Option<String> {
+    lookup(locale, message.key).map(|template| render(template, &message.args))
+}
+
+
+/// The copy of an email template in the locale's language, as merge variables.
+///
+/// # Arguments
+/// * `template_name` - The name of the email template, such as `password-reset`.
+/// * `locale` - The recipient's locale.
+///
+/// # Returns
+/// * `Vec<(&str, String)>` - The `SUBJECT`, `HEADING`, `BODY` and `ACTION` the template has copy for.
+pub fn email_merge_vars(template_name: &str, locale: Locale) -> Vec<(&'static str, String)> {
+    EMAIL_PARTS.iter().filter_map(|(name, part)| {
+        lookup(locale, &format!("email.{}.{}", template_name, part)).map(|copy| (*name, copy.to_string()))
+    }).collect()
+}
+
+
+/// Rewrites the message of an error response in the language of the request's `Accept-Language` header.
+///
+/// # Notes
+/// Only `NanoServiceError`s given a message key are rewritten, the status and details are kept.
+///
+/// # Arguments
+/// * `req` - The incoming request
+/// * `next` - The rest of the middleware chain
+pub async fn localize_errors<B: MessageBody + 'static>(
+    req: ServiceRequest,
+    next: Next<B>
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let locale = Locale::from_accept_language(req.headers());
+    let response = next.call(req).await?;
+    let localized = response.response().error()
+        .and_then(|error| error.as_error::<NanoServiceError>())
+        .filter(|error| error.localized.is_some())
+        .map(|error| error.localized_response(locale));
+    match localized {
+        Some(localized) => Ok(response.into_response(localized).map_into_right_body()),
+        None => Ok(response.map_into_left_body())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::NanoServiceErrorStatus;
+    use actix_web::{test as actix_test, web, App, HttpResponse, middleware::from_fn};
+
+    #[test]
+    fn test_catalogs_are_complete() {
+        for language in [Language::German, Language::Spanish, Language::French] {
+            for (key, template) in ENGLISH {
+                let translated = language.catalog().iter().find(|(found, _)| found == key);
+                let (_, translated) = translated.unwrap_or_else(|| panic!("{} is not translated into {:?}", key, language));
+                for arg in ["{role}", "{permission}"] {
+                    assert_eq!(template.contains(arg), translated.contains(arg), "{} in {:?}", key, language);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_translate() {
+        let message = LocalizedMessage { key: "auth.missing_permission", args: vec![("permission", "view_reports".to_string())] };
+        assert_eq!(translate(Locale::Fr, &message).unwrap(), "Il vous faut la permission view_reports pour faire cela");
+        assert_eq!(translate(Locale::EnUs, &message).unwrap(), "You need the view_reports permission to do this");
+        assert_eq!(translate(Locale::Neutral, &LocalizedMessage { key: "unknown", args: vec![] }), None);
+        assert_eq!(render("{a} and {b}", &[("a", "1".to_string())]), "1 and {b}");
+    }
+
+    #[test]
+    fn test_email_merge_vars() {
+        let copy = email_merge_vars("password-reset", Locale::De);
+        assert_eq!(copy.iter().map(|(name, _)| *name).collect::<Vec<_>>(), vec!["SUBJECT", "HEADING", "BODY", "ACTION"]);
+        assert_eq!(copy[0].1, "Passwort zurücksetzen");
+        assert!(email_merge_vars("todo-sla-warning", Locale::De).is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_localize_errors() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(localize_errors))
+                .route("/keyed", web::get().to(|| async {
+                    Err::<HttpResponse, _>(NanoServiceError::new("Invalid password".to_string(), NanoServiceErrorStatus::Unauthorized)
+                        .with_message_key("auth.invalid_password", vec![]))
+                }))
+                .route("/plain", web::get().to(|| async {
+                    Err::<HttpResponse, _>(NanoServiceError::new("Invalid password".to_string(), NanoServiceErrorStatus::Unauthorized))
+                }))
+        ).await;
+
+        let call = |path: &str| actix_test::TestRequest::get().uri(path).insert_header(("Accept-Language", "es-ES,en;q=0.5")).to_request();
+        let response = actix_test::call_service(&app, call("/keyed")).await;
+        assert_eq!(response.status(), 401);
+        let body: String = actix_test::read_body_json(response).await;
+        assert_eq!(body, "La contraseña es incorrecta");
+
+        let body: String = actix_test::read_body_json(actix_test::call_service(&app, call("/plain")).await).await;
+        assert_eq!(body, "Invalid password");
+
+        let request = actix_test::TestRequest::get().uri("/keyed").to_request();
+        let body: String = actix_test::read_body_json(actix_test::call_service(&app, request).await).await;
+        assert_eq!(body, "The password is incorrect");
+    }
+}
@@ -1,5 +1,15 @@
 pub mod errors;
 pub mod config;
+pub mod secrets;
+pub mod telemetry;
+pub mod transaction_metrics;
+pub mod log_sampling;
+pub mod validation;
+pub mod export_stream;
+pub mod locale;
+pub mod i18n;
+pub mod fault_injection;
+pub mod webhooks;
 pub mod compile_api;
 pub use compile_api_macros::api_endpoint;
 pub mod test_api_endpoint;
@@ -0,0 +1,195 @@
+//! Formats dates and counts for people reading them in their own language.
+//!
+//! # Overview
+//! The few surfaces rendered on the server, such as emails, format values through a `Locale`
+//! rather than their own chrono patterns. A locale comes from a stored language tag such as
+//! `fr-CA`, or from the `Accept-Language` header of a request. Languages without their own
+//! conventions, and missing tags, fall back to `Locale::Neutral`, which writes ISO dates.
+//!
+//! # Notes
+//! Exports are read back in by other tools, so they always write `iso_datetime` and plain counts
+//! whatever the reader's language.
+use actix_web::http::header::{HeaderMap, ACCEPT_LANGUAGE};
+use chrono::{NaiveDate, NaiveDateTime};
+
+
+/// The conventions used to write dates and counts.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Locale {
+    /// `2025-02-10 17:30` and `1,234`.
+    #[default]
+    Neutral,
+    /// `10/02/2025 17:30` and `1,234`, used for every English region but the US.
+    EnGb,
+    /// `02/10/2025 5:30 PM` and `1,234`.
+    EnUs,
+    /// `10.02.2025 17:30` and `1.234`.
+    De,
+    /// `10/02/2025 17:30` and `1.234`.
+    Es,
+    /// `10/02/2025 17:30` and `1 234` with a narrow no-break space.
+    Fr,
+}
+
+impl Locale {
+
+    /// Finds the locale for a language tag such as `en`, `en-US` or `fr-CA`.
+    ///
+    /// # Returns
+    /// * `Some(Locale)` - If the language has its own conventions.
+    /// * `None` - If it does not, or the tag is empty.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let mut parts = tag.trim().split(['-', '_']);
+        let language = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let region = parts.next().map(|region| region.to_ascii_uppercase());
+        match (language.as_str(), region.as_deref()) {
+            ("en", Some("US")) => Some(Locale::EnUs),
+            ("en", _) => Some(Locale::EnGb),
+            ("de", _) => Some(Locale::De),
+            ("es", _) => Some(Locale::Es),
+            ("fr", _) => Some(Locale::Fr),
+            _ => None
+        }
+    }
+
+    /// Finds the locale for a stored language tag, falling back to `Neutral`.
+    pub fn from_tag_or_default(tag: &str) -> Self {
+        Self::from_tag(tag).unwrap_or_default()
+    }
+
+    /// Picks the most preferred language in the `Accept-Language` header that has its own conventions.
+    ///
+    /// # Notes
+    /// Languages with `q=0` are skipped and ties keep the order of the header.
+    pub fn from_accept_language(headers: &HeaderMap) -> Self {
+        let accept_language = headers.get(ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok()).unwrap_or_default();
+        let mut languages: Vec<(f32, Locale)> = accept_language
+            .split(',')
+            .filter_map(|language| {
+                let mut parts = language.split(';').map(str::trim);
+                let locale = Self::from_tag(parts.next().unwrap_or_default())?;
+                let quality = parts
+                    .filter_map(|param| param.strip_prefix("q="))
+                    .find_map(|quality| quality.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (quality > 0.0).then_some((quality, locale))
+            })
+            .collect();
+        // a stable sort so equal qualities keep the order of the header
+        languages.sort_by(|a, b| b.0.total_cmp(&a.0));
+        languages.first().map(|(_, locale)| *locale).unwrap_or_default()
+    }
+
+    fn date_pattern(&self) -> &'static str {
+        match self {
+            Locale::Neutral => "%Y-%m-%d",
+            Locale::EnUs => "%m/%d/%Y",
+            Locale::De => "%d.%m.%Y",
+            Locale::EnGb | Locale::Es | Locale::Fr => "%d/%m/%Y",
+        }
+    }
+
+    fn time_pattern(&self) -> &'static str {
+        match self {
+            Locale::EnUs => "%-I:%M %p",
+            _ => "%H:%M",
+        }
+    }
+
+    fn group_separator(&self) -> &'static str {
+        match self {
+            Locale::Neutral | Locale::EnGb | Locale::EnUs => ",",
+            Locale::De | Locale::Es => ".",
+            Locale::Fr => "\u{202F}",
+        }
+    }
+
+    /// Writes a day, such as `10/02/2025`.
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.date_pattern()).to_string()
+    }
+
+    /// Writes a day and time to the minute, such as `10/02/2025 17:30`.
+    pub fn format_datetime(&self, datetime: NaiveDateTime) -> String {
+        format!("{} {}", self.format_date(datetime.date()), datetime.format(self.time_pattern()))
+    }
+
+    /// Writes a count with its thousands grouped, such as `1,234,567`.
+    pub fn format_count(&self, count: i64) -> String {
+        let digits = count.unsigned_abs().to_string();
+        let mut grouped = String::new();
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                grouped.push_str(self.group_separator());
+            }
+            grouped.push(digit);
+        }
+        if count < 0 {
+            grouped.insert(0, '-');
+        }
+        grouped
+    }
+}
+
+
+/// Writes a timestamp to the second for exports, such as `2025-02-10T17:30:00`.
+pub fn iso_datetime(datetime: NaiveDateTime) -> String {
+    datetime.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::HeaderValue;
+
+    fn due() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 2, 10).unwrap().and_hms_opt(17, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn test_from_tag() {
+        assert_eq!(Locale::from_tag("en-US"), Some(Locale::EnUs));
+        assert_eq!(Locale::from_tag("en_us"), Some(Locale::EnUs));
+        assert_eq!(Locale::from_tag("en"), Some(Locale::EnGb));
+        assert_eq!(Locale::from_tag("fr-CA"), Some(Locale::Fr));
+        assert_eq!(Locale::from_tag("pt-BR"), None);
+        assert_eq!(Locale::from_tag(""), None);
+        assert_eq!(Locale::from_tag_or_default("pt-BR"), Locale::Neutral);
+    }
+
+    #[test]
+    fn test_from_accept_language() {
+        let negotiate = |accept_language: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_str(accept_language).unwrap());
+            Locale::from_accept_language(&headers)
+        };
+        assert_eq!(negotiate("fr-CA,fr;q=0.9,en;q=0.8"), Locale::Fr);
+        assert_eq!(negotiate("pt-BR, de;q=0.5, en-US;q=0.7"), Locale::EnUs);
+        assert_eq!(negotiate("es, de"), Locale::Es);
+        assert_eq!(negotiate("de;q=0, pt"), Locale::Neutral);
+        assert_eq!(negotiate("*"), Locale::Neutral);
+        assert_eq!(Locale::from_accept_language(&HeaderMap::new()), Locale::Neutral);
+    }
+
+    #[test]
+    fn test_format_datetime() {
+        assert_eq!(Locale::Neutral.format_datetime(due()), "2025-02-10 17:30");
+        assert_eq!(Locale::EnGb.format_datetime(due()), "10/02/2025 17:30");
+        assert_eq!(Locale::EnUs.format_datetime(due()), "02/10/2025 5:30 PM");
+        assert_eq!(Locale::De.format_datetime(due()), "10.02.2025 17:30");
+        assert_eq!(Locale::De.format_date(due().date()), "10.02.2025");
+        assert_eq!(iso_datetime(due()), "2025-02-10T17:30:00");
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(Locale::Neutral.format_count(0), "0");
+        assert_eq!(Locale::EnUs.format_count(999), "999");
+        assert_eq!(Locale::EnUs.format_count(1_234_567), "1,234,567");
+        assert_eq!(Locale::De.format_count(-1234), "-1.234");
+        assert_eq!(Locale::Fr.format_count(123_456), "123\u{202F}456");
+        assert_eq!(Locale::Es.format_count(i64::MIN), "-9.223.372.036.854.775.808");
+    }
+}
@@ -0,0 +1,311 @@
+//! Collapses repeated log lines so an incident, such as the database going down, does not flood the
+//! logs with the same error from every request.
+//!
+//! # Overview
+//! Lines logged with `log_limited!` are counted against their module and the line's format string,
+//! so the same error from the same place is counted together whatever its IDs. Each module prints
+//! a number of lines from a place per window and only counts the rest. The count is printed with
+//! the first line of the next window, and `report_suppressed` prints the counts of windows that
+//! ended without another line, so a burst that stops is still accounted for.
+//!
+//! # Variables
+//! * `LOG_RATE_LIMIT` - The lines from one place a module prints per window, as `<count>/<window>`
+//!   such as `5/1m`, or `off`, defaults to `5/1m`
+//! * `LOG_RATE_LIMIT_MODULES` - Limits for single modules that replace `LOG_RATE_LIMIT`, such as
+//!   `dal=20/1m,analytics=off`
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use crate::config::{parse_duration, GetConfigVariable};
+use crate::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The limit used for every module when not configured.
+pub const DEFAULT_LOG_LIMIT: LogLimit = LogLimit { count: 5, window: Duration::from_secs(60) };
+
+/// The most places counted at once, lines from any more are printed without being limited.
+const MAX_TRACKED: usize = 4096;
+
+/// How often the counts of ended windows are printed by `spawn_suppressed_log_report`.
+const REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+static LIMITER: LazyLock<Mutex<LogLimiter>> = LazyLock::new(|| Mutex::new(LogLimiter::new(LogLimits::default())));
+
+
+/// How many lines from one place are printed per window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogLimit {
+    pub count: u64,
+    pub window: Duration,
+}
+
+impl LogLimit {
+
+    /// Parses `<count>/<window>`, such as `5/1m`, or `off` for no limit.
+    ///
+    /// # Returns
+    /// * `Some(Some(LogLimit))` - The limit
+    /// * `Some(None)` - If the value is `off`
+    /// * `None` - If the value is not valid
+    pub fn parse(value: &str) -> Option<Option<LogLimit>> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("off") {
+            return Some(None)
+        }
+        let (count, window) = value.split_once('/')?;
+        let count = count.trim().parse::<u64>().ok().filter(|count| *count > 0)?;
+        let window = parse_duration(window).filter(|window| !window.is_zero())?;
+        Some(Some(LogLimit { count, window }))
+    }
+}
+
+
+/// The limits for every module.
+///
+/// # Fields
+/// * `default` - The limit for a module without its own, `None` for no limit.
+/// * `modules` - The limits of single modules, `None` for no limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLimits {
+    pub default: Option<LogLimit>,
+    pub modules: HashMap<String, Option<LogLimit>>,
+}
+
+impl Default for LogLimits {
+    fn default() -> Self {
+        LogLimits { default: Some(DEFAULT_LOG_LIMIT), modules: HashMap::new() }
+    }
+}
+
+impl LogLimits {
+
+    /// Reads the limits from config.
+    ///
+    /// # Returns
+    /// * `Ok(LogLimits)` - The limits with the default applied for anything not set
+    /// * `Err(NanoServiceError)` - If a limit is set but is not valid
+    pub fn from_config<Y: GetConfigVariable>() -> Result<Self, NanoServiceError> {
+        let invalid = |variable: &str, value: &str| NanoServiceError::new(
+            format!("{} must be <count>/<window> or off, got '{}'", variable, value),
+            NanoServiceErrorStatus::BadRequest
+        );
+        let default = match Y::get_config_variable("LOG_RATE_LIMIT".to_string()) {
+            Ok(value) => LogLimit::parse(&value).ok_or_else(|| invalid("LOG_RATE_LIMIT", &value))?,
+            Err(_) => Some(DEFAULT_LOG_LIMIT)
+        };
+        let mut modules = HashMap::new();
+        if let Ok(value) = Y::get_config_variable("LOG_RATE_LIMIT_MODULES".to_string()) {
+            for module_limit in value.split(',').filter(|module_limit| !module_limit.trim().is_empty()) {
+                let (module, limit) = module_limit.split_once('=')
+                    .ok_or_else(|| invalid("LOG_RATE_LIMIT_MODULES", module_limit))?;
+                let limit = LogLimit::parse(limit).ok_or_else(|| invalid("LOG_RATE_LIMIT_MODULES", module_limit))?;
+                modules.insert(module.trim().to_string(), limit);
+            }
+        }
+        Ok(LogLimits { default, modules })
+    }
+
+    fn for_module(&self, module: &str) -> Option<LogLimit> {
+        self.modules.get(module).copied().unwrap_or(self.default)
+    }
+}
+
+
+/// Whether a line is printed.
+#[derive(Debug, PartialEq)]
+pub enum LogDecision {
+    /// Print the line, with the count of lines from the same place not printed in the window before.
+    Print { suppressed: u64 },
+    Suppress,
+}
+
+
+/// The lines seen from one place in the current window.
+struct LogWindow {
+    started: Instant,
+    window: Duration,
+    printed: u64,
+    suppressed: u64,
+    last_suppressed: Option<String>,
+}
+
+
+/// A line that was not printed, with how many times it was not printed.
+#[derive(Debug, PartialEq)]
+pub struct SuppressedLines {
+    pub module: &'static str,
+    pub message: String,
+    pub suppressed: u64,
+}
+
+
+/// Counts the lines from each place against its module's limit.
+pub struct LogLimiter {
+    limits: LogLimits,
+    windows: HashMap<(&'static str, &'static str), LogWindow>,
+}
+
+impl LogLimiter {
+
+    pub fn new(limits: LogLimits) -> Self {
+        LogLimiter { limits, windows: HashMap::new() }
+    }
+
+    /// Counts a line and decides whether it is printed.
+    ///
+    /// # Arguments
+    /// * `module` - The module logging the line.
+    /// * `place` - The line's format string, which lines are counted together by.
+    /// * `message` - The line, kept so a count can be reported with it.
+    /// * `now` - When the line was logged.
+    pub fn check(&mut self, module: &'static str, place: &'static str, message: &str, now: Instant) -> LogDecision {
+        let Some(limit) = self.limits.for_module(module) else {
+            return LogDecision::Print { suppressed: 0 }
+        };
+        if self.windows.len() >= MAX_TRACKED && !self.windows.contains_key(&(module, place)) {
+            return LogDecision::Print { suppressed: 0 }
+        }
+        let window = self.windows.entry((module, place)).or_insert(LogWindow {
+            started: now,
+            window: limit.window,
+            printed: 0,
+            suppressed: 0,
+            last_suppressed: None,
+        });
+        if now.duration_since(window.started) >= window.window {
+            let suppressed = window.suppressed;
+            *window = LogWindow { started: now, window: limit.window, printed: 1, suppressed: 0, last_suppressed: None };
+            return LogDecision::Print { suppressed }
+        }
+        if window.printed < limit.count {
+            window.printed += 1;
+            return LogDecision::Print { suppressed: 0 }
+        }
+        window.suppressed += 1;
+        window.last_suppressed = Some(message.to_string());
+        LogDecision::Suppress
+    }
+
+    /// Stops counting the windows that have ended, returning the lines they did not print.
+    pub fn take_ended(&mut self, now: Instant) -> Vec<SuppressedLines> {
+        let ended: Vec<(&'static str, &'static str)> = self.windows.iter()
+            .filter(|(_, window)| now.duration_since(window.started) >= window.window)
+            .map(|(key, _)| *key)
+            .collect();
+        ended.into_iter()
+            .filter_map(|key| {
+                let window = self.windows.remove(&key)?;
+                window.last_suppressed.map(|message| SuppressedLines { module: key.0, message, suppressed: window.suppressed })
+            })
+            .collect()
+    }
+}
+
+
+/// Replaces the limits lines are counted against.
+pub fn set_log_limits(limits: LogLimits) {
+    *LIMITER.lock().unwrap() = LogLimiter::new(limits);
+}
+
+
+/// Prints a line unless its module has printed its limit of lines from the same place in the window.
+/// Use `log_limited!` rather than calling this directly.
+pub fn log_limited(module: &'static str, place: &'static str, message: String) {
+    let decision = LIMITER.lock().unwrap().check(module, place, &message, Instant::now());
+    match decision {
+        LogDecision::Print { suppressed: 0 } => println!("{}", message),
+        LogDecision::Print { suppressed } => println!("{} ({} similar lines suppressed)", message, suppressed),
+        LogDecision::Suppress => {}
+    }
+}
+
+
+/// Prints the lines not printed in windows that have ended without another line from the same place.
+pub fn report_suppressed() {
+    let ended = LIMITER.lock().unwrap().take_ended(Instant::now());
+    for lines in ended {
+        println!("{} ({} similar lines suppressed in {})", lines.message, lines.suppressed, lines.module);
+    }
+}
+
+
+/// Runs `report_suppressed` every `REPORT_INTERVAL` in the background for the life of the runtime.
+pub fn spawn_suppressed_log_report() {
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(REPORT_INTERVAL).await;
+            report_suppressed();
+        }
+    });
+}
+
+
+/// Prints a line like `println!`, collapsing repeats from the same place with `log_sampling::log_limited`.
+///
+/// # Arguments
+/// * `module` - The module logging the line, which its limit is looked up by.
+/// * The format string and its arguments, as for `println!`.
+#[macro_export]
+macro_rules! log_limited {
+    ($module:expr, $format:literal $(, $argument:expr)* $(,)?) => {
+        $crate::log_sampling::log_limited($module, $format, format!($format $(, $argument)*))
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct LimitsConfig;
+
+    impl GetConfigVariable for LimitsConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "LOG_RATE_LIMIT" => Ok("2/10s".to_string()),
+                "LOG_RATE_LIMIT_MODULES" => Ok("dal=20/1m, analytics=off".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(LogLimit::parse("5/1m"), Some(Some(DEFAULT_LOG_LIMIT)));
+        assert_eq!(LogLimit::parse(" OFF "), Some(None));
+        assert_eq!(LogLimit::parse("0/1m"), None);
+        assert_eq!(LogLimit::parse("5/0s"), None);
+        assert_eq!(LogLimit::parse("5"), None);
+
+        let limits = LogLimits::from_config::<LimitsConfig>().unwrap();
+        assert_eq!(limits.for_module("auth"), Some(LogLimit { count: 2, window: Duration::from_secs(10) }));
+        assert_eq!(limits.for_module("dal"), Some(LogLimit { count: 20, window: Duration::from_secs(60) }));
+        assert_eq!(limits.for_module("analytics"), None);
+    }
+
+    #[test]
+    fn test_collapses_repeats() {
+        let limits = LogLimits { default: Some(LogLimit { count: 2, window: Duration::from_secs(10) }), modules: HashMap::new() };
+        let mut limiter = LogLimiter::new(limits);
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+        assert_eq!(limiter.check("dal", "failed {}", "failed 1", at(0)), LogDecision::Print { suppressed: 0 });
+        assert_eq!(limiter.check("dal", "failed {}", "failed 2", at(1)), LogDecision::Print { suppressed: 0 });
+        assert_eq!(limiter.check("dal", "failed {}", "failed 3", at(2)), LogDecision::Suppress);
+        assert_eq!(limiter.check("dal", "failed {}", "failed 4", at(3)), LogDecision::Suppress);
+        // another place, and the same place in another module, have their own counts
+        assert_eq!(limiter.check("dal", "other {}", "other 1", at(3)), LogDecision::Print { suppressed: 0 });
+        assert_eq!(limiter.check("auth", "failed {}", "failed 1", at(3)), LogDecision::Print { suppressed: 0 });
+
+        assert_eq!(limiter.check("dal", "failed {}", "failed 5", at(10)), LogDecision::Print { suppressed: 2 });
+        assert_eq!(limiter.check("dal", "failed {}", "failed 6", at(11)), LogDecision::Print { suppressed: 0 });
+        assert_eq!(limiter.check("dal", "failed {}", "failed 7", at(12)), LogDecision::Suppress);
+
+        assert!(limiter.take_ended(at(15)).is_empty());
+        assert_eq!(limiter.take_ended(at(20)), vec![
+            SuppressedLines { module: "dal", message: "failed 7".to_string(), suppressed: 1 }
+        ]);
+        assert!(limiter.windows.is_empty());
+    }
+}
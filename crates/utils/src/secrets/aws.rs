@@ -0,0 +1,182 @@
+//! Fetches secrets from an AWS Secrets Manager secret holding a JSON object of key value pairs.
+//!
+//! # Overview
+//! The `GetSecretValue` request is signed with AWS Signature Version 4 using the credentials in
+//! config, so no AWS SDK is needed.
+//!
+//! # Variables
+//! * `AWS_REGION` - The region of the secret
+//! * `AWS_SECRET_ID` - The name or ARN of the secret
+//! * `AWS_ACCESS_KEY_ID` - The access key used to sign the request
+//! * `AWS_SECRET_ACCESS_KEY` - The secret key used to sign the request
+//! * `AWS_SESSION_TOKEN` - The session token for temporary credentials, optional
+use std::collections::HashMap;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use serde_json::{json, Value};
+use crate::config::GetConfigVariable;
+use crate::errors::NanoServiceError;
+use super::secrets_error;
+
+
+const SERVICE: &str = "secretsmanager";
+const TARGET: &str = "secretsmanager.GetSecretValue";
+const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+
+/// The Secrets Manager secret the managed secrets are read from.
+pub struct AwsSource {
+    pub region: String,
+    pub secret_id: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsSource {
+
+    pub fn from_config<Y: GetConfigVariable>() -> Result<Self, NanoServiceError> {
+        Ok(AwsSource {
+            region: Y::get_config_variable("AWS_REGION".to_string())?,
+            secret_id: Y::get_config_variable("AWS_SECRET_ID".to_string())?,
+            access_key_id: Y::get_config_variable("AWS_ACCESS_KEY_ID".to_string())?,
+            secret_access_key: Y::get_config_variable("AWS_SECRET_ACCESS_KEY".to_string())?,
+            session_token: Y::get_config_variable("AWS_SESSION_TOKEN".to_string()).ok().filter(|token| !token.is_empty()),
+        })
+    }
+
+    fn host(&self) -> String {
+        format!("{}.{}.amazonaws.com", SERVICE, self.region)
+    }
+
+    /// Builds the signed headers for a request sent at `amz_date` (`YYYYMMDDTHHMMSSZ`).
+    fn signed_headers(&self, amz_date: &str, body: &str) -> Vec<(String, String)> {
+        let mut headers = vec![
+            ("content-type".to_string(), CONTENT_TYPE.to_string()),
+            ("host".to_string(), self.host()),
+            ("x-amz-date".to_string(), amz_date.to_string()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        headers.push(("x-amz-target".to_string(), TARGET.to_string()));
+
+        let names = headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+        let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+        let canonical_request = format!(
+            "POST\n/\n\n{}\n{}\n{}",
+            canonical_headers, names, hex::encode(Sha256::digest(body.as_bytes()))
+        );
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, SERVICE);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date, scope, hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(&self.secret_access_key, date, &self.region, SERVICE);
+        let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+
+        headers.push((
+            "authorization".to_string(),
+            format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}", self.access_key_id, scope, names, signature)
+        ));
+        headers
+    }
+
+    /// Reads the key value pairs held in the secret.
+    pub async fn fetch(&self) -> Result<HashMap<String, String>, NanoServiceError> {
+        let body = json!({"SecretId": self.secret_id}).to_string();
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let mut request = reqwest::Client::new().post(format!("https://{}/", self.host()));
+        for (name, value) in self.signed_headers(&amz_date, &body) {
+            if name != "host" {
+                request = request.header(name, value);
+            }
+        }
+        let response = request.body(body)
+            .send()
+            .await
+            .map_err(|e| secrets_error(format!("Failed to reach AWS Secrets Manager: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(secrets_error(format!("AWS Secrets Manager returned {} for {}", response.status(), self.secret_id)))
+        }
+        let body: Value = response.json()
+            .await
+            .map_err(|e| secrets_error(format!("Failed to read AWS Secrets Manager response: {}", e)))?;
+        parse_secret_value(&body)
+    }
+}
+
+
+/// Signs `data` with `key`, the building block of Signature Version 4.
+pub fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+
+/// Derives the Signature Version 4 signing key for a day, region and service.
+pub fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret_access_key).as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    hmac_sha256(&key, "aws4_request")
+}
+
+
+/// Reads the key value pairs from the JSON object in `SecretString`.
+pub(crate) fn parse_secret_value(body: &Value) -> Result<HashMap<String, String>, NanoServiceError> {
+    let secret = body.get("SecretString")
+        .and_then(Value::as_str)
+        .ok_or_else(|| secrets_error("AWS secret has no SecretString".to_string()))?;
+    let secret: HashMap<String, Value> = serde_json::from_str(secret)
+        .map_err(|_| secrets_error("AWS SecretString is not a JSON object".to_string()))?;
+    Ok(secret.into_iter()
+        .map(|(name, value)| {
+            let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            (name, value)
+        })
+        .collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example from the AWS Signature Version 4 documentation.
+    #[test]
+    fn test_signing_key() {
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex::encode(key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn test_signed_headers() {
+        let source = AwsSource {
+            region: "eu-west-2".to_string(),
+            secret_id: "web-server".to_string(),
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: Some("session".to_string()),
+        };
+        let headers = source.signed_headers("20250101T120000Z", r#"{"SecretId":"web-server"}"#);
+        let authorization = &headers.last().unwrap().1;
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20250101/eu-west-2/secretsmanager/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date;x-amz-security-token;x-amz-target, Signature="
+        ));
+        assert_eq!(headers[1], ("host".to_string(), "secretsmanager.eu-west-2.amazonaws.com".to_string()));
+    }
+
+    #[test]
+    fn test_parse_secret_value() {
+        let body = json!({"Name": "web-server", "SecretString": "{\"DB_URL\": \"postgres://secret\", \"PORT\": 8001}"});
+        let secrets = parse_secret_value(&body).unwrap();
+        assert_eq!(secrets["DB_URL"], "postgres://secret");
+        assert_eq!(secrets["PORT"], "8001");
+        assert!(parse_secret_value(&json!({"SecretString": "not json"})).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Reads the secrets the server holds from a secrets manager rather than the environment.
+//!
+//! # Overview
+//! `SECRETS_BACKEND` picks where the secrets in `MANAGED_SECRETS` come from when the server starts:
+//! * `env` - nothing is fetched and `SecretsConfig` behaves exactly like `EnvConfig`, the default
+//! * `vault` - a HashiCorp Vault KV version 2 secret, see `vault`
+//! * `aws` - an AWS Secrets Manager secret holding a JSON object, see `aws`
+//!
+//! The fetched secrets are cached in process and refreshed in the background, a failed refresh keeps
+//! the last values that were fetched. Any variable that is not managed, or is missing from the
+//! secret, is read through `EnvConfig`.
+//!
+//! # Variables
+//! * `SECRETS_BACKEND` - `env`, `vault` or `aws`, defaults to `env`
+//! * `SECRETS_REFRESH_SECONDS` - How often the secrets are fetched again, defaults to 300
+pub mod vault;
+pub mod aws;
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+use crate::config::{EnvConfig, GetConfigVariable, TypedConfig};
+use crate::errors::{NanoServiceError, NanoServiceErrorStatus};
+use vault::VaultSource;
+use aws::AwsSource;
+
+
+/// The variables read from the secrets backend when one is configured.
+pub const MANAGED_SECRETS: [&str; 4] = ["SECRET_KEY", "JWT_PRIVATE_KEY", "MAILCHIMP_API_KEY", "DB_URL"];
+
+/// The refresh interval used when not configured.
+const DEFAULT_REFRESH_SECONDS: i64 = 300;
+
+/// The secrets fetched from the backend.
+static SECRETS: LazyLock<RwLock<HashMap<String, String>>> = LazyLock::new(|| RwLock::new(HashMap::new()));
+
+
+pub(crate) fn secrets_error(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::Unknown)
+}
+
+
+/// Where the managed secrets are read from.
+pub enum SecretsBackend {
+    Env,
+    Vault(VaultSource),
+    Aws(AwsSource),
+}
+
+impl SecretsBackend {
+
+    /// Builds the backend named by `SECRETS_BACKEND`.
+    ///
+    /// # Returns
+    /// * `Ok(SecretsBackend)` - The backend
+    /// * `Err(NanoServiceError)` - If the backend is unknown or its settings are missing
+    pub fn from_config<Y: GetConfigVariable>() -> Result<Self, NanoServiceError> {
+        let backend = Y::get_config_variable("SECRETS_BACKEND".to_string()).unwrap_or_default();
+        match backend.trim().to_lowercase().as_str() {
+            "" | "env" => Ok(SecretsBackend::Env),
+            "vault" => Ok(SecretsBackend::Vault(VaultSource::from_config::<Y>()?)),
+            "aws" => Ok(SecretsBackend::Aws(AwsSource::from_config::<Y>()?)),
+            other => Err(secrets_error(format!("SECRETS_BACKEND '{}' is not env, vault or aws", other)))
+        }
+    }
+
+    /// The name of the backend for logging.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SecretsBackend::Env => "env",
+            SecretsBackend::Vault(_) => "vault",
+            SecretsBackend::Aws(_) => "aws",
+        }
+    }
+
+    /// Fetches the secrets held by the backend.
+    pub async fn fetch(&self) -> Result<HashMap<String, String>, NanoServiceError> {
+        match self {
+            SecretsBackend::Env => Ok(HashMap::new()),
+            SecretsBackend::Vault(source) => source.fetch().await,
+            SecretsBackend::Aws(source) => source.fetch().await,
+        }
+    }
+}
+
+
+/// Replaces the cached secrets with the managed secrets in `fetched`.
+fn store_secrets(fetched: HashMap<String, String>) -> usize {
+    let managed: HashMap<String, String> = fetched
+        .into_iter()
+        .filter(|(name, _)| MANAGED_SECRETS.contains(&name.as_str()))
+        .collect();
+    let count = managed.len();
+    *SECRETS.write().unwrap() = managed;
+    count
+}
+
+
+/// Fetches the secrets from the backend and caches them.
+///
+/// # Returns
+/// * `Ok(usize)` - The number of managed secrets the backend held
+/// * `Err(NanoServiceError)` - If the secrets could not be fetched, the cache is left unchanged
+pub async fn load_secrets(backend: &SecretsBackend) -> Result<usize, NanoServiceError> {
+    let fetched = backend.fetch().await?;
+    Ok(store_secrets(fetched))
+}
+
+
+/// Fetches the secrets again in the background for the life of the runtime.
+///
+/// # Notes
+/// Nothing is spawned for the `env` backend. A failed refresh is logged and the cached secrets are kept.
+pub fn spawn_secrets_refresh<Y: GetConfigVariable>(backend: SecretsBackend) {
+    if let SecretsBackend::Env = backend {
+        return
+    }
+    let seconds = Y::get_int("SECRETS_REFRESH_SECONDS")
+        .ok()
+        .filter(|seconds| *seconds > 0)
+        .unwrap_or(DEFAULT_REFRESH_SECONDS);
+    let interval = std::time::Duration::from_secs(seconds as u64);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(e) = load_secrets(&backend).await {
+                println!("failed to refresh secrets from {}: {}", backend.name(), e.message);
+            }
+        }
+    });
+}
+
+
+/// Reads the managed secrets from the secrets backend and everything else from `EnvConfig`.
+pub struct SecretsConfig;
+
+impl GetConfigVariable for SecretsConfig {
+
+    /// Gets the config variable from the cached secrets if it is managed and was fetched, otherwise from `EnvConfig`.
+    ///
+    /// # Arguments
+    /// * `variable` - The name of the config variable to get
+    ///
+    /// # Returns
+    /// * `Result<String, NanoServiceError>` - The result of getting the config variable
+    fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+        if let Some(secret) = SECRETS.read().unwrap().get(&variable) {
+            return Ok(secret.clone())
+        }
+        EnvConfig::get_config_variable(variable)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SECRETS_BACKEND" => Ok("consul".to_string()),
+                _ => Err(secrets_error(format!("{} not found", variable)))
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_backend_rejected() {
+        assert!(SecretsBackend::from_config::<FakeConfig>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_secrets_config_prefers_managed_secrets() {
+        assert_eq!(load_secrets(&SecretsBackend::Env).await.unwrap(), 0);
+
+        let count = store_secrets(HashMap::from([
+            ("SECRET_KEY".to_string(), "from-backend".to_string()),
+            ("CARGO_PKG_NAME".to_string(), "not-managed".to_string()),
+        ]));
+        assert_eq!(count, 1);
+        assert_eq!(SecretsConfig::get_config_variable("SECRET_KEY".to_string()).unwrap(), "from-backend");
+        // not managed, so read from the environment cargo sets for tests
+        assert_eq!(SecretsConfig::get_config_variable("CARGO_PKG_NAME".to_string()).unwrap(), "utils");
+        store_secrets(HashMap::new());
+    }
+}
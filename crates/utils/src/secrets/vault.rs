@@ -0,0 +1,90 @@
+//! Fetches secrets from a HashiCorp Vault KV version 2 secret.
+//!
+//! # Variables
+//! * `VAULT_ADDR` - The address of the Vault server, e.g. `https://vault.internal:8200`
+//! * `VAULT_TOKEN` - The token used to read the secret
+//! * `VAULT_SECRET_PATH` - The API path of the secret, e.g. `secret/data/web-server`
+use std::collections::HashMap;
+use serde_json::Value;
+use crate::config::GetConfigVariable;
+use crate::errors::NanoServiceError;
+use super::secrets_error;
+
+
+/// The Vault secret the managed secrets are read from.
+pub struct VaultSource {
+    pub address: String,
+    pub token: String,
+    pub path: String,
+}
+
+impl VaultSource {
+
+    pub fn from_config<Y: GetConfigVariable>() -> Result<Self, NanoServiceError> {
+        Ok(VaultSource {
+            address: Y::get_config_variable("VAULT_ADDR".to_string())?,
+            token: Y::get_config_variable("VAULT_TOKEN".to_string())?,
+            path: Y::get_config_variable("VAULT_SECRET_PATH".to_string())?,
+        })
+    }
+
+    /// The URL of the secret.
+    fn url(&self) -> String {
+        format!("{}/v1/{}", self.address.trim_end_matches('/'), self.path.trim_start_matches('/'))
+    }
+
+    /// Reads the key value pairs held in the secret.
+    pub async fn fetch(&self) -> Result<HashMap<String, String>, NanoServiceError> {
+        let response = reqwest::Client::new()
+            .get(self.url())
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .await
+            .map_err(|e| secrets_error(format!("Failed to reach Vault: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(secrets_error(format!("Vault returned {} for {}", response.status(), self.path)))
+        }
+        let body: Value = response.json()
+            .await
+            .map_err(|e| secrets_error(format!("Failed to read Vault response: {}", e)))?;
+        parse_kv_response(&body)
+    }
+}
+
+
+/// Reads the key value pairs from a KV version 2 read, where they are nested under `data.data`.
+pub(crate) fn parse_kv_response(body: &Value) -> Result<HashMap<String, String>, NanoServiceError> {
+    let data = body.get("data")
+        .and_then(|data| data.get("data"))
+        .and_then(Value::as_object)
+        .ok_or_else(|| secrets_error("Vault response has no data.data object".to_string()))?;
+    Ok(data.iter()
+        .map(|(name, value)| (name.clone(), value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string())))
+        .collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_kv_response() {
+        let body = json!({"data": {"data": {"SECRET_KEY": "key", "PORT": 8001}, "metadata": {"version": 3}}});
+        let secrets = parse_kv_response(&body).unwrap();
+        assert_eq!(secrets["SECRET_KEY"], "key");
+        assert_eq!(secrets["PORT"], "8001");
+        assert!(parse_kv_response(&json!({"data": {}})).is_err());
+    }
+
+    #[test]
+    fn test_url() {
+        let source = VaultSource {
+            address: "https://vault:8200/".to_string(),
+            token: "token".to_string(),
+            path: "/secret/data/web-server".to_string(),
+        };
+        assert_eq!(source.url(), "https://vault:8200/v1/secret/data/web-server");
+    }
+}
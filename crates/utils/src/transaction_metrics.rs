@@ -0,0 +1,180 @@
+//! Times every transaction implemented with `impl_transaction` and logs the slow ones.
+//!
+//! # Overview
+//! `impl_transaction` runs each transaction body through `instrument_transaction`, which counts the
+//! run against the transaction's trait name in a latency histogram held in memory for the life of
+//! the process. A run slower than the threshold is logged with its trait name so a slow query can
+//! be traced back to the transaction that issued it. The counts are read with `transaction_metrics`
+//! for the ops endpoints to expose.
+//!
+//! ## Notes
+//! - Mocks are implemented with `impl_transaction` too, so tests are timed in the same way.
+//! - The threshold defaults to `DEFAULT_SLOW_TRANSACTION_MS` until `set_slow_transaction_threshold` is called.
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{LazyLock, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::errors::NanoServiceError;
+
+
+/// The slow transaction threshold used when not configured.
+pub const DEFAULT_SLOW_TRANSACTION_MS: u64 = 500;
+
+/// The upper bounds, in milliseconds, of the histogram buckets, slower runs are counted over the last.
+pub const LATENCY_BUCKETS_MS: [u64; 11] = [1, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+
+static SLOW_TRANSACTION_MS: AtomicU64 = AtomicU64::new(DEFAULT_SLOW_TRANSACTION_MS);
+
+static TRANSACTION_STATS: LazyLock<Mutex<BTreeMap<&'static str, TransactionCounts>>> = LazyLock::new(Default::default);
+
+
+#[derive(Default)]
+struct TransactionCounts {
+    count: u64,
+    errors: u64,
+    slow: u64,
+    total_micros: u64,
+    max_micros: u64,
+    buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+
+/// A histogram bucket.
+///
+/// # Fields
+/// * `le_ms` - The upper bound of the bucket, `None` for the runs slower than every bound.
+/// * `count` - The runs that took at most `le_ms` and more than the bound before it.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LatencyBucket {
+    pub le_ms: Option<u64>,
+    pub count: u64,
+}
+
+
+/// The timings of a transaction since the process started.
+///
+/// # Fields
+/// * `transaction` - The trait name of the transaction.
+/// * `count` - How many times it has run.
+/// * `errors` - How many runs returned an error.
+/// * `slow` - How many runs were over the slow transaction threshold.
+/// * `total_ms` - The time spent in every run together.
+/// * `max_ms` - The slowest run.
+/// * `buckets` - The runs by how long they took.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TransactionStats {
+    pub transaction: &'static str,
+    pub count: u64,
+    pub errors: u64,
+    pub slow: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+    pub buckets: Vec<LatencyBucket>,
+}
+
+
+/// Sets how long, in milliseconds, a transaction can run before it is logged as slow.
+pub fn set_slow_transaction_threshold(threshold_ms: u64) {
+    SLOW_TRANSACTION_MS.store(threshold_ms, Ordering::Relaxed);
+}
+
+
+/// Counts a run of a transaction, logging it if it was slow.
+///
+/// # Arguments
+/// * `transaction` - The trait name of the transaction.
+/// * `elapsed` - How long the run took.
+/// * `succeeded` - Whether the run returned `Ok`.
+pub fn record_transaction(transaction: &'static str, elapsed: Duration, succeeded: bool) {
+    let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+    let slow = elapsed.as_millis() > SLOW_TRANSACTION_MS.load(Ordering::Relaxed) as u128;
+    if slow {
+        crate::log_limited!("dal", "slow transaction {} took {}ms", transaction, elapsed.as_millis());
+    }
+    let bucket = LATENCY_BUCKETS_MS.iter()
+        .position(|bound| micros <= bound * 1000)
+        .unwrap_or(LATENCY_BUCKETS_MS.len());
+
+    let mut stats = TRANSACTION_STATS.lock().unwrap();
+    let counts = stats.entry(transaction).or_default();
+    counts.count += 1;
+    counts.errors += u64::from(!succeeded);
+    counts.slow += u64::from(slow);
+    counts.total_micros = counts.total_micros.saturating_add(micros);
+    counts.max_micros = counts.max_micros.max(micros);
+    counts.buckets[bucket] += 1;
+}
+
+
+/// Runs a transaction body, counting how long it took.
+///
+/// # Arguments
+/// * `transaction` - The trait name of the transaction.
+/// * `body` - The transaction body.
+pub async fn instrument_transaction<T, F>(transaction: &'static str, body: F) -> Result<T, NanoServiceError>
+where
+    F: Future<Output = Result<T, NanoServiceError>>
+{
+    let started = Instant::now();
+    let outcome = body.await;
+    record_transaction(transaction, started.elapsed(), outcome.is_ok());
+    outcome
+}
+
+
+/// The timings of every transaction that has run, by trait name.
+pub fn transaction_metrics() -> Vec<TransactionStats> {
+    TRANSACTION_STATS.lock().unwrap().iter().map(|(transaction, counts)| TransactionStats {
+        transaction,
+        count: counts.count,
+        errors: counts.errors,
+        slow: counts.slow,
+        total_ms: counts.total_micros as f64 / 1000.0,
+        max_ms: counts.max_micros as f64 / 1000.0,
+        buckets: counts.buckets.iter().enumerate().map(|(index, count)| LatencyBucket {
+            le_ms: LATENCY_BUCKETS_MS.get(index).copied(),
+            count: *count,
+        }).collect(),
+    }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::NanoServiceErrorStatus;
+
+    fn stats(transaction: &str) -> TransactionStats {
+        transaction_metrics().into_iter().find(|stats| stats.transaction == transaction).unwrap()
+    }
+
+    #[test]
+    fn test_record_transaction() {
+        record_transaction("RecordTest", Duration::from_micros(800), true);
+        record_transaction("RecordTest", Duration::from_millis(30), false);
+        record_transaction("RecordTest", Duration::from_secs(6), true);
+
+        let stats = stats("RecordTest");
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.slow, 1);
+        assert_eq!(stats.max_ms, 6000.0);
+        let counted: Vec<(Option<u64>, u64)> = stats.buckets.iter()
+            .filter(|bucket| bucket.count > 0)
+            .map(|bucket| (bucket.le_ms, bucket.count))
+            .collect();
+        assert_eq!(counted, vec![(Some(1), 1), (Some(50), 1), (None, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_instrument_transaction() {
+        let outcome: Result<i32, NanoServiceError> = instrument_transaction("InstrumentTest", async {
+            Err(NanoServiceError::new("failed".to_string(), NanoServiceErrorStatus::Unknown))
+        }).await;
+        assert!(outcome.is_err());
+        let stats = stats("InstrumentTest");
+        assert_eq!((stats.count, stats.errors), (1, 1));
+    }
+}
@@ -0,0 +1,124 @@
+//! Turns `validator` violations into a 400 that lists what is wrong with each field.
+//!
+//! # Overview
+//! Request schemas derive `validator::Validate` and handlers call `validate_body` before acting on
+//! them. A body that fails is rejected with a `BadRequest` whose details map each field, dotted for
+//! nested structs and indexed for lists, to its violations:
+//! ```json
+//! {"message": "Request body failed validation", "details": {"email": ["must be a valid email address"]}}
+//! ```
+use std::collections::BTreeMap;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+use crate::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// Collects the violations for every field, keyed by the path to the field.
+///
+/// # Notes
+/// A violation without a message is reported by its code, such as `length`.
+pub fn field_violations(errors: &ValidationErrors) -> BTreeMap<String, Vec<String>> {
+    let mut violations = BTreeMap::new();
+    collect_violations(errors, "", &mut violations);
+    violations
+}
+
+
+fn collect_violations(errors: &ValidationErrors, prefix: &str, violations: &mut BTreeMap<String, Vec<String>>) {
+    for (field, kind) in errors.errors() {
+        let path = format!("{}{}", prefix, field);
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                let messages = field_errors.iter().map(|error| match &error.message {
+                    Some(message) => message.to_string(),
+                    None => error.code.to_string()
+                });
+                violations.entry(path).or_default().extend(messages);
+            },
+            ValidationErrorsKind::Struct(nested) => {
+                collect_violations(nested, &format!("{}.", path), violations);
+            },
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_violations(nested, &format!("{}[{}].", path, index), violations);
+                }
+            }
+        }
+    }
+}
+
+
+/// Validates a request body.
+///
+/// # Arguments
+/// * `body` - The deserialized request body
+///
+/// # Returns
+/// * `Ok(())` - If the body is valid
+/// * `Err(NanoServiceError)` - A `BadRequest` listing the violations for each field
+pub fn validate_body<T: Validate>(body: &T) -> Result<(), NanoServiceError> {
+    body.validate().map_err(|errors| {
+        NanoServiceError::new(
+            "Request body failed validation".to_string(),
+            NanoServiceErrorStatus::BadRequest
+        ).with_details(serde_json::json!(field_violations(&errors)))
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::ResponseError;
+    use validator::ValidationError;
+
+    struct Address {
+        city: String,
+    }
+
+    impl Validate for Address {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+            if self.city.is_empty() {
+                errors.add("city", ValidationError::new("length"));
+            }
+            match errors.is_empty() {
+                true => Ok(()),
+                false => Err(errors)
+            }
+        }
+    }
+
+    struct Signup {
+        email: String,
+        address: Address,
+    }
+
+    impl Validate for Signup {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+            if !self.email.contains('@') {
+                errors.add("email", ValidationError::new("email").with_message("must be a valid email address".into()));
+            }
+            let nested = self.address.validate();
+            ValidationErrors::merge(match errors.is_empty() {
+                true => Ok(()),
+                false => Err(errors)
+            }, "address", nested)
+        }
+    }
+
+    #[test]
+    fn test_validate_body() {
+        let valid = Signup { email: "ada@example.com".to_string(), address: Address { city: "London".to_string() } };
+        assert!(validate_body(&valid).is_ok());
+
+        let invalid = Signup { email: "ada".to_string(), address: Address { city: String::new() } };
+        let error = validate_body(&invalid).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        assert_eq!(error.details, Some(serde_json::json!({
+            "address.city": ["length"],
+            "email": ["must be a valid email address"]
+        })));
+        assert_eq!(error.error_response().status(), 400);
+    }
+}
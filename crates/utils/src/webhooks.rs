@@ -0,0 +1,195 @@
+//! Shared replay protection for the webhooks posted to us by other services.
+//!
+//! # Overview
+//! A webhook is authenticated by a signature rather than a token, so a captured webhook is as good
+//! as a real one. Every receiver protects against that in the same three ways:
+//! - The signature is an HMAC of the payload, checked with `verify_hmac_sha256_hex` or `constant_time_eq`
+//!   so the time taken does not give away how much of it matched.
+//! - Providers that sign a timestamp have it checked with `within_tolerance`, so an old webhook is rejected.
+//! - Each delivery is processed once, `process_once` claims its idempotency key in the webhook
+//!   delivery ledger first and skips deliveries that were already claimed. Providers without a
+//!   delivery ID are keyed by `payload_digest`.
+//!
+//! The ledger traits are implemented for Postgres in `dal::webhook_deliveries`.
+use std::future::Future;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use crate::errors::NanoServiceError;
+
+
+/// How far, in seconds, a signed timestamp can be from now unless a provider needs otherwise.
+pub const DEFAULT_TOLERANCE_SECONDS: i64 = 300;
+
+
+/// Claims the idempotency key of a webhook delivery before it is processed.
+pub trait ClaimWebhookDelivery {
+
+    /// Records the delivery, returning `false` if it had already been recorded.
+    ///
+    /// # Arguments
+    /// * `source` - The integration the webhook is for, such as `stripe`, keys are unique per source.
+    /// * `delivery_id` - The idempotency key of the delivery.
+    fn claim_webhook_delivery(source: String, delivery_id: String) -> impl Future<Output = Result<bool, NanoServiceError>> + Send;
+}
+
+
+/// Releases a claimed delivery that could not be processed, so the provider's retry is processed.
+pub trait ReleaseWebhookDelivery {
+    fn release_webhook_delivery(source: String, delivery_id: String) -> impl Future<Output = Result<(), NanoServiceError>> + Send;
+}
+
+
+/// Signs a message with HMAC-SHA256 as lowercase hex.
+///
+/// # Arguments
+/// * `secret` - The signing secret.
+/// * `parts` - The parts of the signed message, in order, such as a timestamp and the payload.
+pub fn hmac_sha256_hex(secret: &[u8], parts: &[&[u8]]) -> String {
+    hex::encode(hmac_sha256(secret, parts).finalize().into_bytes())
+}
+
+
+fn hmac_sha256(secret: &[u8], parts: &[&[u8]]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+
+/// Checks a hex HMAC-SHA256 signature of a message in constant time.
+///
+/// # Returns
+/// * `bool` - `false` if the signature is not hex or does not match.
+pub fn verify_hmac_sha256_hex(secret: &[u8], parts: &[&[u8]], signature: &str) -> bool {
+    match hex::decode(signature) {
+        Ok(signature) => hmac_sha256(secret, parts).verify_slice(&signature).is_ok(),
+        Err(_) => false
+    }
+}
+
+
+/// Compares two byte strings in time that does not depend on where they differ.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+
+/// Whether a signed unix timestamp is within `tolerance_seconds` of `now`, either side.
+pub fn within_tolerance(timestamp: i64, now: i64, tolerance_seconds: i64) -> bool {
+    now.abs_diff(timestamp) <= tolerance_seconds.unsigned_abs()
+}
+
+
+/// The idempotency key of a payload, for providers that do not give each delivery an ID.
+pub fn payload_digest(payload: &[u8]) -> String {
+    hex::encode(Sha256::digest(payload))
+}
+
+
+/// What happened to a webhook delivery.
+///
+/// # Variants
+/// * `Processed` - The delivery was new and this is what processing it returned.
+/// * `Replayed` - The delivery had already been claimed, so it was not processed again.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeliveryOutcome<T> {
+    Processed(T),
+    Replayed,
+}
+
+
+/// Processes a webhook delivery unless it has already been claimed.
+///
+/// # Arguments
+/// * `source` - The integration the webhook is for.
+/// * `delivery_id` - The idempotency key of the delivery.
+/// * `process` - Processes the delivery.
+///
+/// # Returns
+/// * `Ok(DeliveryOutcome)` - What processing returned, or `Replayed` if it was skipped.
+/// * `Err(NanoServiceError)` - If the claim or processing failed. A delivery that fails to process
+///   is released again so the provider's retry is processed.
+pub async fn process_once<X, T, F, Fut>(source: &str, delivery_id: &str, process: F) -> Result<DeliveryOutcome<T>, NanoServiceError>
+where
+    X: ClaimWebhookDelivery + ReleaseWebhookDelivery,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, NanoServiceError>>
+{
+    if !X::claim_webhook_delivery(source.to_string(), delivery_id.to_string()).await? {
+        return Ok(DeliveryOutcome::Replayed)
+    }
+    match process().await {
+        Ok(output) => Ok(DeliveryOutcome::Processed(output)),
+        Err(error) => {
+            if let Err(release_error) = X::release_webhook_delivery(source.to_string(), delivery_id.to_string()).await {
+                crate::log_limited!("webhooks", "could not release {} webhook delivery {}: {}", source, delivery_id, release_error.message);
+            }
+            Err(error)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::NanoServiceErrorStatus;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    static CLAIMED: Mutex<Option<HashSet<(String, String)>>> = Mutex::new(None);
+
+    struct MockLedger;
+
+    impl ClaimWebhookDelivery for MockLedger {
+        async fn claim_webhook_delivery(source: String, delivery_id: String) -> Result<bool, NanoServiceError> {
+            Ok(CLAIMED.lock().unwrap().get_or_insert_with(HashSet::new).insert((source, delivery_id)))
+        }
+    }
+
+    impl ReleaseWebhookDelivery for MockLedger {
+        async fn release_webhook_delivery(source: String, delivery_id: String) -> Result<(), NanoServiceError> {
+            CLAIMED.lock().unwrap().get_or_insert_with(HashSet::new).remove(&(source, delivery_id));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hmac_sha256_hex() {
+        let signature = hmac_sha256_hex(b"secret", &[b"1000.", b"payload"]);
+        assert_eq!(signature, hmac_sha256_hex(b"secret", &[b"1000.payload"]));
+        assert!(verify_hmac_sha256_hex(b"secret", &[b"1000.", b"payload"], &signature));
+        assert!(!verify_hmac_sha256_hex(b"secret", &[b"1001.", b"payload"], &signature));
+        assert!(!verify_hmac_sha256_hex(b"other", &[b"1000.", b"payload"], &signature));
+        assert!(!verify_hmac_sha256_hex(b"secret", &[b"1000.", b"payload"], "not hex"));
+    }
+
+    #[test]
+    fn test_within_tolerance() {
+        assert!(within_tolerance(1_000, 1_300, DEFAULT_TOLERANCE_SECONDS));
+        assert!(within_tolerance(1_300, 1_000, DEFAULT_TOLERANCE_SECONDS));
+        assert!(!within_tolerance(1_000, 1_301, DEFAULT_TOLERANCE_SECONDS));
+        assert!(!within_tolerance(i64::MIN, i64::MAX, DEFAULT_TOLERANCE_SECONDS));
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+    }
+
+    #[tokio::test]
+    async fn test_process_once() {
+        let outcome = process_once::<MockLedger, _, _, _>("stripe", "evt_1", || async { Ok(7) }).await.unwrap();
+        assert_eq!(outcome, DeliveryOutcome::Processed(7));
+        let replayed = process_once::<MockLedger, i32, _, _>("stripe", "evt_1", || async { panic!("a replay is not processed") }).await.unwrap();
+        assert_eq!(replayed, DeliveryOutcome::Replayed);
+        let other_source = process_once::<MockLedger, _, _, _>("scim", "evt_1", || async { Ok(8) }).await.unwrap();
+        assert_eq!(other_source, DeliveryOutcome::Processed(8));
+
+        let failed = process_once::<MockLedger, i32, _, _>("stripe", "evt_2", || async {
+            Err(NanoServiceError::new("down".to_string(), NanoServiceErrorStatus::Unknown))
+        }).await.unwrap_err();
+        assert_eq!(failed.message, "down");
+        let retried = process_once::<MockLedger, _, _, _>("stripe", "evt_2", || async { Ok(9) }).await.unwrap();
+        assert_eq!(retried, DeliveryOutcome::Processed(9));
+    }
+}
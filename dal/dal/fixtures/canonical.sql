@@ -0,0 +1,49 @@
+-- Canonical integration test dataset, restore with `dal::fixtures::restore_canonical_fixtures`.
+-- Every user has the password `fixture-password`.
+
+-- users
+INSERT INTO users SELECT * FROM jsonb_populate_record(NULL::users, '{"id": 1, "uuid": "00000000-0000-4000-8000-000000000001", "email": "super_admin@fixtures.example.com", "blocked": false, "password": "$argon2id$v=19$m=19456,t=2,p=1$SEGRxqHBjNfKmw7KonF59w$mAteUP3DDCvRLTdXFa1KWtLia41XohC9dv2GCywarTQ", "username": "super_admin", "confirmed": true, "last_name": "Fixture", "user_role": "Super Admin", "first_name": "Super", "date_created": "2025-01-01T09:00:00", "last_logged_in": "2025-01-01T09:00:00", "locale": "en", "timezone": "UTC", "updated_at": "2025-01-01T09:00:00"}');
+INSERT INTO users SELECT * FROM jsonb_populate_record(NULL::users, '{"id": 2, "uuid": "00000000-0000-4000-8000-000000000002", "email": "admin@fixtures.example.com", "blocked": false, "password": "$argon2id$v=19$m=19456,t=2,p=1$SEGRxqHBjNfKmw7KonF59w$mAteUP3DDCvRLTdXFa1KWtLia41XohC9dv2GCywarTQ", "username": "admin", "confirmed": true, "last_name": "Fixture", "user_role": "Admin", "first_name": "Admin", "date_created": "2025-01-01T09:00:00", "last_logged_in": "2025-01-01T09:00:00", "locale": "en", "timezone": "UTC", "updated_at": "2025-01-01T09:00:00"}');
+INSERT INTO users SELECT * FROM jsonb_populate_record(NULL::users, '{"id": 3, "uuid": "00000000-0000-4000-8000-000000000003", "email": "worker@fixtures.example.com", "blocked": false, "password": "$argon2id$v=19$m=19456,t=2,p=1$SEGRxqHBjNfKmw7KonF59w$mAteUP3DDCvRLTdXFa1KWtLia41XohC9dv2GCywarTQ", "username": "worker", "confirmed": true, "last_name": "Fixture", "user_role": "Worker", "first_name": "Worker", "date_created": "2025-01-01T09:00:00", "last_logged_in": "2025-01-01T09:00:00", "locale": "en", "timezone": "UTC", "updated_at": "2025-01-01T09:00:00"}');
+INSERT INTO users SELECT * FROM jsonb_populate_record(NULL::users, '{"id": 4, "uuid": "00000000-0000-4000-8000-000000000004", "email": "unconfirmed_worker@fixtures.example.com", "blocked": false, "password": "$argon2id$v=19$m=19456,t=2,p=1$SEGRxqHBjNfKmw7KonF59w$mAteUP3DDCvRLTdXFa1KWtLia41XohC9dv2GCywarTQ", "username": "unconfirmed_worker", "confirmed": false, "last_name": "Fixture", "user_role": "Worker", "first_name": "Unconfirmed", "date_created": "2025-01-01T09:00:00", "last_logged_in": "2025-01-01T09:00:00", "locale": "en", "timezone": "UTC", "updated_at": "2025-01-01T09:00:00"}');
+INSERT INTO users SELECT * FROM jsonb_populate_record(NULL::users, '{"id": 5, "uuid": "00000000-0000-4000-8000-000000000005", "email": "blocked_worker@fixtures.example.com", "blocked": true, "password": "$argon2id$v=19$m=19456,t=2,p=1$SEGRxqHBjNfKmw7KonF59w$mAteUP3DDCvRLTdXFa1KWtLia41XohC9dv2GCywarTQ", "username": "blocked_worker", "confirmed": true, "last_name": "Fixture", "user_role": "Worker", "first_name": "Blocked", "date_created": "2025-01-01T09:00:00", "last_logged_in": "2025-01-01T09:00:00", "locale": "en", "timezone": "UTC", "updated_at": "2025-01-01T09:00:00"}');
+
+-- org_memberships
+INSERT INTO org_memberships SELECT * FROM jsonb_populate_record(NULL::org_memberships, '{"id": 1, "org_id": 1, "user_id": 1, "org_role": "owner", "date_joined": "2025-01-01T09:00:00"}');
+INSERT INTO org_memberships SELECT * FROM jsonb_populate_record(NULL::org_memberships, '{"id": 2, "org_id": 1, "user_id": 2, "org_role": "member", "date_joined": "2025-01-01T09:00:00"}');
+INSERT INTO org_memberships SELECT * FROM jsonb_populate_record(NULL::org_memberships, '{"id": 3, "org_id": 1, "user_id": 3, "org_role": "member", "date_joined": "2025-01-01T09:00:00"}');
+INSERT INTO org_memberships SELECT * FROM jsonb_populate_record(NULL::org_memberships, '{"id": 4, "org_id": 1, "user_id": 4, "org_role": "member", "date_joined": "2025-01-01T09:00:00"}');
+INSERT INTO org_memberships SELECT * FROM jsonb_populate_record(NULL::org_memberships, '{"id": 5, "org_id": 1, "user_id": 5, "org_role": "member", "date_joined": "2025-01-01T09:00:00"}');
+
+-- role_permissions
+INSERT INTO role_permissions SELECT * FROM jsonb_populate_record(NULL::role_permissions, '{"id": 1, "role": "Super Admin", "user_id": 1}');
+INSERT INTO role_permissions SELECT * FROM jsonb_populate_record(NULL::role_permissions, '{"id": 2, "role": "Admin", "user_id": 1}');
+INSERT INTO role_permissions SELECT * FROM jsonb_populate_record(NULL::role_permissions, '{"id": 3, "role": "Admin", "user_id": 2}');
+INSERT INTO role_permissions SELECT * FROM jsonb_populate_record(NULL::role_permissions, '{"id": 4, "role": "Worker", "user_id": 3}');
+INSERT INTO role_permissions SELECT * FROM jsonb_populate_record(NULL::role_permissions, '{"id": 5, "role": "Worker", "user_id": 4}');
+INSERT INTO role_permissions SELECT * FROM jsonb_populate_record(NULL::role_permissions, '{"id": 6, "role": "Worker", "user_id": 5}');
+
+-- user_onboarding
+INSERT INTO user_onboarding SELECT * FROM jsonb_populate_record(NULL::user_onboarding, '{"id": 1, "user_id": 1, "terms_version": "1", "password_set_at": "2025-01-01T09:00:00", "terms_accepted_at": "2025-01-01T09:00:00", "profile_completed_at": "2025-01-01T09:00:00"}');
+INSERT INTO user_onboarding SELECT * FROM jsonb_populate_record(NULL::user_onboarding, '{"id": 2, "user_id": 2, "terms_version": "1", "password_set_at": "2025-01-01T09:00:00", "terms_accepted_at": "2025-01-01T09:00:00", "profile_completed_at": "2025-01-01T09:00:00"}');
+INSERT INTO user_onboarding SELECT * FROM jsonb_populate_record(NULL::user_onboarding, '{"id": 3, "user_id": 3, "terms_version": "1", "password_set_at": "2025-01-01T09:00:00", "terms_accepted_at": "2025-01-01T09:00:00", "profile_completed_at": "2025-01-01T09:00:00"}');
+INSERT INTO user_onboarding SELECT * FROM jsonb_populate_record(NULL::user_onboarding, '{"id": 4, "user_id": 5, "terms_version": "1", "password_set_at": "2025-01-01T09:00:00", "terms_accepted_at": "2025-01-01T09:00:00", "profile_completed_at": "2025-01-01T09:00:00"}');
+
+-- rate_limit_entries
+INSERT INTO rate_limit_entries SELECT * FROM jsonb_populate_record(NULL::rate_limit_entries, '{"id": 1, "count": 1, "email": "worker@fixtures.example.com", "rate_limit_period_start": "2025-01-01T09:00:00"}');
+INSERT INTO rate_limit_entries SELECT * FROM jsonb_populate_record(NULL::rate_limit_entries, '{"id": 2, "count": 5, "email": "unconfirmed_worker@fixtures.example.com", "rate_limit_period_start": "2025-01-01T09:00:00"}');
+
+-- todos
+INSERT INTO todos SELECT * FROM jsonb_populate_record(NULL::todos, '{"id": 1, "name": "pending task", "due_date": "2025-02-01T09:00:00", "finished": false, "assigned_by": 2, "assigned_to": 3, "description": "A task that is still to do", "date_assigned": "2025-01-01T09:00:00", "date_finished": null, "requires_review": false, "pending_review": false, "review_comment": null, "priority": "medium", "status": "backlog", "org_id": 1, "updated_at": "2025-01-01T09:00:00"}');
+INSERT INTO todos SELECT * FROM jsonb_populate_record(NULL::todos, '{"id": 2, "name": "finished task", "due_date": "2025-02-01T09:00:00", "finished": true, "assigned_by": 2, "assigned_to": 3, "description": "A task that has been completed", "date_assigned": "2025-01-01T09:00:00", "date_finished": "2025-01-02T09:00:00", "requires_review": false, "pending_review": false, "review_comment": null, "priority": "medium", "status": "done", "org_id": 1, "updated_at": "2025-01-01T09:00:00"}');
+INSERT INTO todos SELECT * FROM jsonb_populate_record(NULL::todos, '{"id": 3, "name": "overdue task", "due_date": "2025-01-01T12:00:00", "finished": false, "assigned_by": 2, "assigned_to": 3, "description": null, "date_assigned": "2025-01-01T09:00:00", "date_finished": null, "requires_review": false, "pending_review": false, "review_comment": null, "priority": "medium", "status": "backlog", "org_id": 1, "updated_at": "2025-01-01T09:00:00"}');
+INSERT INTO todos SELECT * FROM jsonb_populate_record(NULL::todos, '{"id": 4, "name": "undated task", "due_date": null, "finished": false, "assigned_by": 1, "assigned_to": 2, "description": null, "date_assigned": "2025-01-01T09:00:00", "date_finished": null, "requires_review": false, "pending_review": false, "review_comment": null, "priority": "medium", "status": "backlog", "org_id": 1, "updated_at": "2025-01-01T09:00:00"}');
+
+-- tags
+INSERT INTO tags SELECT * FROM jsonb_populate_record(NULL::tags, '{"id": 1, "name": "billing"}');
+INSERT INTO tags SELECT * FROM jsonb_populate_record(NULL::tags, '{"id": 2, "name": "urgent"}');
+
+-- todo_tags
+INSERT INTO todo_tags SELECT * FROM jsonb_populate_record(NULL::todo_tags, '{"id": 1, "tag_id": 2, "todo_id": 1}');
+INSERT INTO todo_tags SELECT * FROM jsonb_populate_record(NULL::todo_tags, '{"id": 2, "tag_id": 1, "todo_id": 3}');
+INSERT INTO todo_tags SELECT * FROM jsonb_populate_record(NULL::todo_tags, '{"id": 3, "tag_id": 2, "todo_id": 3}');
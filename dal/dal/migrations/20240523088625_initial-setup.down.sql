@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS todos;
+DROP TABLE IF EXISTS rate_limit_entries;
+DROP TABLE IF EXISTS role_permissions;
+DROP TABLE IF EXISTS users;
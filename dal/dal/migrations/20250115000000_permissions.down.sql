@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS role_permission_grants;
+DROP TABLE IF EXISTS permissions;
@@ -0,0 +1,36 @@
+-- Fine-grained permissions granted to roles
+CREATE TABLE IF NOT EXISTS permissions (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL UNIQUE,
+    description TEXT
+);
+
+
+CREATE TABLE IF NOT EXISTS role_permission_grants (
+    id SERIAL PRIMARY KEY,
+    role VARCHAR NOT NULL,
+    permission_id INTEGER NOT NULL REFERENCES permissions(id) ON DELETE CASCADE,
+    CONSTRAINT unique_role_permission_grant UNIQUE (role, permission_id)  -- A role is granted a permission once
+);
+
+
+INSERT INTO permissions (name, description) VALUES
+    ('todo:create', 'Create to-do items'),
+    ('todo:assign', 'Assign to-do items to other users'),
+    ('todo:complete', 'Complete to-do items'),
+    ('user:block', 'Block and unblock users'),
+    ('user:manage_roles', 'Assign and remove user roles')
+ON CONFLICT (name) DO NOTHING;
+
+
+INSERT INTO role_permission_grants (role, permission_id)
+SELECT 'Super Admin', id FROM permissions
+ON CONFLICT ON CONSTRAINT unique_role_permission_grant DO NOTHING;
+
+INSERT INTO role_permission_grants (role, permission_id)
+SELECT 'Admin', id FROM permissions WHERE name IN ('todo:create', 'todo:assign', 'todo:complete', 'user:block')
+ON CONFLICT ON CONSTRAINT unique_role_permission_grant DO NOTHING;
+
+INSERT INTO role_permission_grants (role, permission_id)
+SELECT 'Worker', id FROM permissions WHERE name IN ('todo:complete')
+ON CONFLICT ON CONSTRAINT unique_role_permission_grant DO NOTHING;
@@ -0,0 +1,15 @@
+-- Labels that can be attached to to-do items
+CREATE TABLE IF NOT EXISTS tags (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL UNIQUE
+);
+
+
+CREATE TABLE IF NOT EXISTS todo_tags (
+    id SERIAL PRIMARY KEY,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    CONSTRAINT unique_todo_tag UNIQUE (todo_id, tag_id)  -- A tag is attached to a to-do item once
+);
+
+CREATE INDEX IF NOT EXISTS todo_tags_tag_id_idx ON todo_tags (tag_id);
@@ -0,0 +1,11 @@
+-- Record of privileged actions, such as overriding a to-do capacity limit
+CREATE TABLE IF NOT EXISTS audit_log (
+    id SERIAL PRIMARY KEY,
+    actor_id INTEGER NOT NULL,
+    action VARCHAR NOT NULL,
+    subject_id INTEGER,
+    details JSONB NOT NULL DEFAULT '{}',
+    date_created TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_action_idx ON audit_log (action, date_created);
@@ -0,0 +1,3 @@
+ALTER TABLE todos DROP COLUMN IF EXISTS review_comment;
+ALTER TABLE todos DROP COLUMN IF EXISTS pending_review;
+ALTER TABLE todos DROP COLUMN IF EXISTS requires_review;
@@ -0,0 +1,4 @@
+-- To-do items that need the assigner to sign off before they are finished
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS requires_review BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS pending_review BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS review_comment TEXT;
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS sla_warnings;
+DROP TABLE IF EXISTS sla_policies;
+ALTER TABLE todos DROP COLUMN IF EXISTS priority;
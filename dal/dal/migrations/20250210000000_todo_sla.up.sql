@@ -0,0 +1,20 @@
+-- Target completion times per priority, with warnings sent to the assignee before an item breaches
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS priority VARCHAR NOT NULL DEFAULT 'medium';
+
+CREATE TABLE IF NOT EXISTS sla_policies (
+    priority VARCHAR PRIMARY KEY,
+    target_minutes INTEGER NOT NULL CHECK (target_minutes > 0),
+    warn_minutes INTEGER NOT NULL DEFAULT 0 CHECK (warn_minutes >= 0)
+);
+
+INSERT INTO sla_policies (priority, target_minutes, warn_minutes) VALUES
+    ('low', 10080, 1440),
+    ('medium', 4320, 720),
+    ('high', 1440, 240)
+ON CONFLICT (priority) DO NOTHING;
+
+-- One row per to-do item that has been warned about, so the warning is only sent once
+CREATE TABLE IF NOT EXISTS sla_warnings (
+    todo_id INTEGER PRIMARY KEY REFERENCES todos(id) ON DELETE CASCADE,
+    date_sent TIMESTAMP NOT NULL DEFAULT NOW()
+);
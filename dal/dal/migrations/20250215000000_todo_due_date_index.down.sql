@@ -0,0 +1 @@
+DROP INDEX IF EXISTS todos_assigned_to_due_date_idx;
@@ -0,0 +1,2 @@
+-- The calendar view reads a user's to-do items within a range of due dates
+CREATE INDEX IF NOT EXISTS todos_assigned_to_due_date_idx ON todos (assigned_to, due_date);
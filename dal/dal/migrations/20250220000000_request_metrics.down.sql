@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS availability_rollups;
+DROP TABLE IF EXISTS request_metrics;
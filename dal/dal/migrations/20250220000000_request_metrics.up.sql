@@ -0,0 +1,18 @@
+-- Request counts per minute, flushed by each server and summed when several servers share a minute
+CREATE TABLE IF NOT EXISTS request_metrics (
+    bucket_start TIMESTAMP PRIMARY KEY,
+    total_requests BIGINT NOT NULL DEFAULT 0,
+    server_errors BIGINT NOT NULL DEFAULT 0,
+    client_errors BIGINT NOT NULL DEFAULT 0
+);
+
+-- Daily availability computed from request_metrics, kept after the raw minutes are purged
+CREATE TABLE IF NOT EXISTS availability_rollups (
+    day DATE PRIMARY KEY,
+    total_requests BIGINT NOT NULL,
+    server_errors BIGINT NOT NULL,
+    client_errors BIGINT NOT NULL,
+    availability DOUBLE PRECISION NOT NULL,
+    error_rate DOUBLE PRECISION NOT NULL,
+    date_computed TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,2 @@
+-- The language emails to the user are written in, as a BCP 47 tag such as `en` or `fr-CA`
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locale VARCHAR NOT NULL DEFAULT 'en';
@@ -0,0 +1,2 @@
+ALTER TABLE email_outbox DROP COLUMN IF EXISTS send_at;
+ALTER TABLE users DROP COLUMN IF EXISTS timezone;
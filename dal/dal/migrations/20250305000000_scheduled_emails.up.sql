@@ -0,0 +1,5 @@
+-- The IANA timezone of the user, such as `Europe/London`, used to send emails at a sensible local time
+ALTER TABLE users ADD COLUMN IF NOT EXISTS timezone VARCHAR NOT NULL DEFAULT 'UTC';
+
+-- The earliest time a queued email may be sent, emails are sent as soon as they are due by default
+ALTER TABLE email_outbox ADD COLUMN IF NOT EXISTS send_at TIMESTAMP NOT NULL DEFAULT NOW();
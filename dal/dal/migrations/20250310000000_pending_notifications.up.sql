@@ -0,0 +1,11 @@
+-- Notifications waiting to be batched into a single summary email per user and type
+CREATE TABLE IF NOT EXISTS pending_notifications (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    notification_type VARCHAR NOT NULL,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    todo_name VARCHAR NOT NULL,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS pending_notifications_batch_idx ON pending_notifications (notification_type, user_id, date_created);
@@ -0,0 +1,12 @@
+-- The branding for the organization running the deployment, held in a single row
+CREATE TABLE IF NOT EXISTS org_branding (
+    id INTEGER PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    product_name VARCHAR NOT NULL,
+    logo_url VARCHAR,
+    accent_color VARCHAR NOT NULL,
+    date_updated TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO org_branding (id, product_name, logo_url, accent_color)
+VALUES (1, 'To Do', NULL, '#2563eb')
+ON CONFLICT (id) DO NOTHING;
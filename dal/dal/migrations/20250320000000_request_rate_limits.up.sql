@@ -0,0 +1,6 @@
+-- Request counts per client IP or account for the rate limited auth endpoints
+CREATE TABLE IF NOT EXISTS request_rate_limits (
+    key VARCHAR PRIMARY KEY,
+    window_start TIMESTAMP NOT NULL DEFAULT NOW(),
+    count INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,14 @@
+-- Onboarding progress per user, email confirmation is read from `users.confirmed`
+CREATE TABLE IF NOT EXISTS user_onboarding (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+    terms_version VARCHAR,
+    terms_accepted_at TIMESTAMP,
+    password_set_at TIMESTAMP,
+    profile_completed_at TIMESTAMP
+);
+
+-- Users who were confirmed before onboarding existed already set a password and gave their names
+INSERT INTO user_onboarding (user_id, password_set_at, profile_completed_at)
+SELECT id, NOW(), NOW() FROM users WHERE confirmed = TRUE
+ON CONFLICT (user_id) DO NOTHING;
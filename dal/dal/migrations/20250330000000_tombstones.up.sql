@@ -0,0 +1,9 @@
+-- A record of every deleted user and to-do item so sync clients can remove them too
+CREATE TABLE IF NOT EXISTS tombstones (
+    id SERIAL PRIMARY KEY,
+    entity_type VARCHAR NOT NULL,
+    entity_id INTEGER NOT NULL,
+    deleted_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS tombstones_deleted_at_idx ON tombstones (deleted_at, id);
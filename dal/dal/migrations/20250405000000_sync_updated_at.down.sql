@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS todos_updated_at_idx;
+DROP INDEX IF EXISTS users_updated_at_idx;
+ALTER TABLE todos DROP COLUMN IF EXISTS updated_at;
+ALTER TABLE users DROP COLUMN IF EXISTS updated_at;
@@ -0,0 +1,7 @@
+-- When a user or to-do item last changed, read by the incremental sync API
+ALTER TABLE users ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP NOT NULL DEFAULT NOW();
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP NOT NULL DEFAULT NOW();
+
+-- Sync clients page through changes in (updated_at, id) order
+CREATE INDEX IF NOT EXISTS users_updated_at_idx ON users (updated_at, id);
+CREATE INDEX IF NOT EXISTS todos_updated_at_idx ON todos (updated_at, id);
@@ -0,0 +1,2 @@
+-- the pg_trgm extension is left installed as other databases on the server may use it
+DROP INDEX IF EXISTS users_search_idx;
@@ -0,0 +1,5 @@
+-- Trigram index so admins can search users by any part of their username, email or name with ILIKE
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS users_search_idx ON users
+    USING GIN ((username || ' ' || email || ' ' || first_name || ' ' || last_name) gin_trgm_ops);
@@ -0,0 +1,18 @@
+-- Text that moderation flagged or rejected, kept for admins to review
+CREATE TABLE IF NOT EXISTS moderation_decisions (
+    id SERIAL PRIMARY KEY,
+    content VARCHAR NOT NULL,
+    entity_id INTEGER,
+    author_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    action VARCHAR NOT NULL,
+    reasons TEXT[] NOT NULL DEFAULT '{}',
+    text TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    reviewed_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMP,
+    upheld BOOLEAN
+);
+
+CREATE INDEX IF NOT EXISTS moderation_decisions_pending_idx
+    ON moderation_decisions (created_at, id)
+    WHERE reviewed_at IS NULL;
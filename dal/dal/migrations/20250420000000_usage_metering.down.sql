@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS usage_rollups;
+DROP TABLE IF EXISTS usage_active_users;
+DROP TABLE IF EXISTS usage_counters;
@@ -0,0 +1,23 @@
+-- Billable usage counted per day by the subsystems that cause it
+CREATE TABLE IF NOT EXISTS usage_counters (
+    metric VARCHAR NOT NULL,
+    day DATE NOT NULL,
+    quantity BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (metric, day)
+);
+
+-- The users who signed in each month, kept when a user is deleted as they were still billed for
+CREATE TABLE IF NOT EXISTS usage_active_users (
+    month DATE NOT NULL,
+    user_id INTEGER NOT NULL,
+    PRIMARY KEY (month, user_id)
+);
+
+-- One row per month rolled up from the counts for billing
+CREATE TABLE IF NOT EXISTS usage_rollups (
+    month DATE PRIMARY KEY,
+    active_users BIGINT NOT NULL,
+    emails_sent BIGINT NOT NULL,
+    storage_bytes BIGINT NOT NULL,
+    rolled_up_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,15 @@
+-- The plan for the organization running the deployment and its overrides, held in a single row
+CREATE TABLE IF NOT EXISTS org_plan (
+    id INTEGER PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    plan VARCHAR NOT NULL,
+    max_users BIGINT,
+    max_todos BIGINT,
+    mfa BOOLEAN,
+    api_access BOOLEAN,
+    date_updated TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- existing deployments keep everything they have until a plan is chosen
+INSERT INTO org_plan (id, plan)
+VALUES (1, 'enterprise')
+ON CONFLICT (id) DO NOTHING;
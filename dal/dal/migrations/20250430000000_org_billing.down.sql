@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS billing_events;
+DROP TABLE IF EXISTS org_billing;
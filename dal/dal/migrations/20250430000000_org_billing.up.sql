@@ -0,0 +1,21 @@
+-- The Stripe subscription of the organization running the deployment, held in a single row
+CREATE TABLE IF NOT EXISTS org_billing (
+    id INTEGER PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    status VARCHAR NOT NULL DEFAULT 'none',
+    customer_id VARCHAR,
+    subscription_id VARCHAR,
+    current_period_end TIMESTAMP,
+    last_event_at TIMESTAMP,
+    date_updated TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO org_billing (id)
+VALUES (1)
+ON CONFLICT (id) DO NOTHING;
+
+-- Every Stripe event applied, so a webhook Stripe retries is only applied once
+CREATE TABLE IF NOT EXISTS billing_events (
+    event_id VARCHAR PRIMARY KEY,
+    event_type VARCHAR NOT NULL,
+    date_received TIMESTAMP NOT NULL DEFAULT NOW()
+);
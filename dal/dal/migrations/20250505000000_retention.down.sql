@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS pending_notifications_date_created_idx;
+DROP INDEX IF EXISTS email_outbox_date_created_idx;
+DROP INDEX IF EXISTS audit_log_date_created_idx;
+DROP TABLE IF EXISTS login_events;
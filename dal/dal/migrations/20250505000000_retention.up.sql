@@ -0,0 +1,12 @@
+-- Every successful login, kept for as long as the login events retention allows
+CREATE TABLE IF NOT EXISTS login_events (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- The retention purge deletes the oldest rows of each table first
+CREATE INDEX IF NOT EXISTS login_events_date_created_idx ON login_events (date_created);
+CREATE INDEX IF NOT EXISTS audit_log_date_created_idx ON audit_log (date_created);
+CREATE INDEX IF NOT EXISTS email_outbox_date_created_idx ON email_outbox (date_created);
+CREATE INDEX IF NOT EXISTS pending_notifications_date_created_idx ON pending_notifications (date_created);
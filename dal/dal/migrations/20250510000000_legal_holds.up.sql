@@ -0,0 +1,8 @@
+-- Users whose data is kept until a super admin releases the hold, the reference to users refuses
+-- deleting a user on hold even if a query forgets to check
+CREATE TABLE IF NOT EXISTS legal_holds (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id),
+    placed_by INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,10 @@
+-- The idempotency keys of the webhook deliveries that have been processed, so a replayed or
+-- retried delivery is only processed once
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    source VARCHAR(64) NOT NULL,
+    delivery_id VARCHAR(255) NOT NULL,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (source, delivery_id)
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_date_created_idx ON webhook_deliveries (date_created);
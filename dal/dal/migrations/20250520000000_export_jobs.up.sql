@@ -0,0 +1,19 @@
+-- Exports that are too large to stream in a request, written to the object store by a worker
+-- and downloaded from a presigned link until they expire
+CREATE TABLE IF NOT EXISTS export_jobs (
+    id SERIAL PRIMARY KEY,
+    requested_by INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(32) NOT NULL,
+    format VARCHAR(8) NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    total_rows BIGINT,
+    exported_rows BIGINT NOT NULL DEFAULT 0,
+    object_key VARCHAR(255),
+    error TEXT,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP,
+    expires_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS export_jobs_status_idx ON export_jobs (status, updated_at);
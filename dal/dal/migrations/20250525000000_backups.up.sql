@@ -0,0 +1,11 @@
+-- The logical backups written to the object store, kept out of the backups themselves and with no
+-- foreign key so restoring a backup never removes the record of another
+CREATE TABLE IF NOT EXISTS backups (
+    id SERIAL PRIMARY KEY,
+    object_key VARCHAR(255) NOT NULL UNIQUE,
+    size_bytes BIGINT NOT NULL,
+    row_count BIGINT NOT NULL,
+    schema_version BIGINT NOT NULL,
+    triggered_by INTEGER,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW()
+);
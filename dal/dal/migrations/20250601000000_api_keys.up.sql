@@ -0,0 +1,12 @@
+-- The keys services send instead of a user's token, only the hash of each key is stored and there
+-- is no foreign key on `created_by` so a key outlives the super admin who created it
+CREATE TABLE IF NOT EXISTS api_keys (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash VARCHAR(64) NOT NULL UNIQUE,
+    created_by INTEGER NOT NULL,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP,
+    revoked_at TIMESTAMP
+);
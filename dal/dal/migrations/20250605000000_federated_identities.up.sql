@@ -0,0 +1,12 @@
+-- The subjects users are known by at OpenID Connect providers, a subject is linked to one user
+CREATE TABLE IF NOT EXISTS federated_identities (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(32) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    email VARCHAR NOT NULL,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, subject)
+);
+
+CREATE INDEX IF NOT EXISTS federated_identities_user_idx ON federated_identities (user_id);
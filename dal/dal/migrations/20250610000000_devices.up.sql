@@ -0,0 +1,13 @@
+-- The devices users have logged in from, keyed by a fingerprint of the user agent. A device the user
+-- has marked as trusted stays trusted until `trusted_until`
+CREATE TABLE IF NOT EXISTS devices (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    fingerprint VARCHAR(64) NOT NULL,
+    name VARCHAR(100),
+    user_agent VARCHAR NOT NULL,
+    trusted_until TIMESTAMP,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, fingerprint)
+);
@@ -0,0 +1,3 @@
+-- The version of each user's avatar, a hash of the stored image that changes with every upload so
+-- the avatar's URL can be cached. NULL until the user uploads an avatar
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar_version VARCHAR(32);
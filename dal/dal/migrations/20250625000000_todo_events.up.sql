@@ -0,0 +1,11 @@
+-- The history of changes to each to-do item, written by the core layer as the item changes
+CREATE TABLE IF NOT EXISTS todo_events (
+    id SERIAL PRIMARY KEY,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    actor_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    kind VARCHAR(32) NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    date_created TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS todo_events_todo_id_idx ON todo_events (todo_id, id);
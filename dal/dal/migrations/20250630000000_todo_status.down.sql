@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS todos_assigned_to_status_idx;
+ALTER TABLE todos DROP COLUMN IF EXISTS status;
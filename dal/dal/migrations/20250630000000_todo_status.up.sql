@@ -0,0 +1,6 @@
+-- Where each to-do item is in its workflow, kept in step with `finished` as items move in and out of 'done'
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS status VARCHAR(16) NOT NULL DEFAULT 'backlog';
+
+UPDATE todos SET status = 'done' WHERE finished;
+
+CREATE INDEX IF NOT EXISTS todos_assigned_to_status_idx ON todos (assigned_to, status);
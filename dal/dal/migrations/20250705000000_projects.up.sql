@@ -0,0 +1,30 @@
+-- Boards that group to-do items, removed with the user who owns them
+CREATE TABLE IF NOT EXISTS projects (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+
+-- The users who can see each project
+CREATE TABLE IF NOT EXISTS project_members (
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    date_added TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (project_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS project_members_user_id_idx ON project_members (user_id);
+
+
+-- The project each to-do item belongs to, an item is in one project at most
+CREATE TABLE IF NOT EXISTS project_todos (
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    date_added TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (todo_id)
+);
+
+CREATE INDEX IF NOT EXISTS project_todos_project_id_idx ON project_todos (project_id);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS todos_org_id_assigned_to_idx;
+ALTER TABLE todos DROP COLUMN IF EXISTS org_id;
+DROP TABLE IF EXISTS org_memberships;
+DROP TABLE IF EXISTS organizations;
@@ -0,0 +1,36 @@
+-- The tenants sharing the deployment, existing deployments keep everything in the default organization
+CREATE TABLE IF NOT EXISTS organizations (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    date_created TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO organizations (id, name)
+VALUES (1, 'Default')
+ON CONFLICT (id) DO NOTHING;
+
+SELECT setval(pg_get_serial_sequence('organizations', 'id'), (SELECT MAX(id) FROM organizations));
+
+
+-- The organizations each user belongs to, with their role in each
+CREATE TABLE IF NOT EXISTS org_memberships (
+    id SERIAL PRIMARY KEY,
+    org_id INTEGER NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    org_role VARCHAR(16) NOT NULL DEFAULT 'member',
+    date_joined TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique_org_membership UNIQUE (org_id, user_id)  -- A user joins an organization once
+);
+
+CREATE INDEX IF NOT EXISTS org_memberships_user_id_idx ON org_memberships (user_id);
+
+INSERT INTO org_memberships (org_id, user_id, org_role)
+SELECT 1, id, CASE WHEN user_role = 'Super Admin' THEN 'owner' ELSE 'member' END
+FROM users
+ON CONFLICT ON CONSTRAINT unique_org_membership DO NOTHING;
+
+
+-- The organization each to-do item belongs to
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS org_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS todos_org_id_assigned_to_idx ON todos (org_id, assigned_to);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS tombstones_org_id_idx;
+ALTER TABLE tombstones DROP COLUMN IF EXISTS org_id;
@@ -0,0 +1,5 @@
+-- The organization each tombstone belongs to, so sync clients only learn about deletions in their own
+-- organization. A deleted user leaves a tombstone in every organization they were a member of.
+ALTER TABLE tombstones ADD COLUMN IF NOT EXISTS org_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS tombstones_org_id_idx ON tombstones (org_id, id);
@@ -0,0 +1,79 @@
+//! Implements the API key transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::api_keys::{NewApiKey, ApiKey};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::api_keys::tx_definitions::{CreateApiKey, ListApiKeys, RevokeApiKey, UseApiKey};
+
+
+fn api_key_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(format!("Failed to {}: {}", action, e), NanoServiceErrorStatus::Unknown)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CreateApiKey, create_api_key)]
+async fn create_api_key(api_key: NewApiKey) -> Result<ApiKey, NanoServiceError> {
+    let query = r#"
+        INSERT INTO api_keys (name, key_prefix, key_hash, created_by)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, ApiKey>(query)
+            .bind(&api_key.name)
+            .bind(&api_key.key_prefix)
+            .bind(&api_key.key_hash)
+            .bind(api_key.created_by)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| api_key_error("create API key", e))
+}
+
+
+/// Lists every key, revoked ones included, newest first.
+#[impl_transaction(SqlxPostGresDescriptor, ListApiKeys, list_api_keys)]
+async fn list_api_keys() -> Result<Vec<ApiKey>, NanoServiceError> {
+    retry_transient(|| {
+        sqlx::query_as::<_, ApiKey>("SELECT * FROM api_keys ORDER BY id DESC")
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| api_key_error("list API keys", e))
+}
+
+
+/// Revokes a key, `false` if there is no such key or it was already revoked.
+#[impl_transaction(SqlxPostGresDescriptor, RevokeApiKey, revoke_api_key)]
+async fn revoke_api_key(id: i32) -> Result<bool, NanoServiceError> {
+    let result = retry_write(|| {
+        sqlx::query("UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| api_key_error("revoke API key", e))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+
+/// Finds the key with the hash that has not been revoked, recording that it was used.
+#[impl_transaction(SqlxPostGresDescriptor, UseApiKey, use_api_key)]
+async fn use_api_key(key_hash: String) -> Result<Option<ApiKey>, NanoServiceError> {
+    let query = r#"
+        UPDATE api_keys SET last_used_at = NOW()
+        WHERE key_hash = $1 AND revoked_at IS NULL
+        RETURNING *
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, ApiKey>(query)
+            .bind(&key_hash)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| api_key_error("use API key", e))
+}
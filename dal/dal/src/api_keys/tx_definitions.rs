@@ -0,0 +1,15 @@
+//! Defines transaction traits for interacting with the `api_keys` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `UseApiKey` only finds keys that have not been revoked, and records that the key was used.
+use kernel::api_keys::{NewApiKey, ApiKey};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreateApiKey => create_api_key(api_key: NewApiKey) -> ApiKey,
+    ListApiKeys => list_api_keys() -> Vec<ApiKey>,
+    RevokeApiKey => revoke_api_key(id: i32) -> bool,
+    UseApiKey => use_api_key(key_hash: String) -> Option<ApiKey>
+);
@@ -0,0 +1,2 @@
+pub mod tx_definitions;
+pub mod postgres_txs;
@@ -0,0 +1,93 @@
+//! Implements the attachment transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::attachments::{Attachment, NewAttachment};
+use kernel::tenancy::current_tenant;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::attachments::tx_definitions::{CreateAttachment, DeleteAttachment, GetAttachment, ListAttachments};
+
+
+fn attachment_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(format!("Failed to {}: {}", action, e), NanoServiceErrorStatus::Unknown)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CreateAttachment, create_attachment)]
+async fn create_attachment(attachment: NewAttachment) -> Result<Attachment, NanoServiceError> {
+    let query = r#"
+        INSERT INTO attachments (todo_id, uploaded_by, file_name, content_type, size_bytes, storage_key)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, Attachment>(query)
+            .bind(attachment.todo_id)
+            .bind(attachment.uploaded_by)
+            .bind(&attachment.file_name)
+            .bind(&attachment.content_type)
+            .bind(attachment.size_bytes)
+            .bind(&attachment.storage_key)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| attachment_error("create attachment", e))
+}
+
+
+/// Lists the attachments of a to-do item, the oldest first.
+#[impl_transaction(SqlxPostGresDescriptor, ListAttachments, list_attachments)]
+async fn list_attachments(todo_id: i32) -> Result<Vec<Attachment>, NanoServiceError> {
+    let tenant = current_tenant()?;
+    retry_transient(|| {
+        sqlx::query_as::<_, Attachment>(r#"
+            SELECT * FROM attachments
+            WHERE todo_id = $1 AND ($2::INTEGER IS NULL OR todo_id IN (SELECT id FROM todos WHERE org_id = $2))
+            ORDER BY id
+        "#)
+            .bind(todo_id)
+            .bind(tenant)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| attachment_error("list attachments", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetAttachment, get_attachment)]
+async fn get_attachment(todo_id: i32, id: i32) -> Result<Option<Attachment>, NanoServiceError> {
+    let tenant = current_tenant()?;
+    retry_transient(|| {
+        sqlx::query_as::<_, Attachment>(r#"
+            SELECT * FROM attachments
+            WHERE todo_id = $1 AND id = $2 AND ($3::INTEGER IS NULL OR todo_id IN (SELECT id FROM todos WHERE org_id = $3))
+        "#)
+            .bind(todo_id)
+            .bind(id)
+            .bind(tenant)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| attachment_error("get attachment", e))
+}
+
+
+/// Deletes the attachment's row, returning it so its file can be deleted from the object store.
+#[impl_transaction(SqlxPostGresDescriptor, DeleteAttachment, delete_attachment)]
+async fn delete_attachment(todo_id: i32, id: i32) -> Result<Option<Attachment>, NanoServiceError> {
+    let tenant = current_tenant()?;
+    retry_write(|| {
+        sqlx::query_as::<_, Attachment>(r#"
+            DELETE FROM attachments
+            WHERE todo_id = $1 AND id = $2 AND ($3::INTEGER IS NULL OR todo_id IN (SELECT id FROM todos WHERE org_id = $3))
+            RETURNING *
+        "#)
+            .bind(todo_id)
+            .bind(id)
+            .bind(tenant)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| attachment_error("delete attachment", e))
+}
@@ -0,0 +1,16 @@
+//! Defines transaction traits for interacting with the `attachments` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `GetAttachment` and `DeleteAttachment` only find an attachment of the given to-do item, so an
+//!   attachment cannot be reached through another item.
+use kernel::attachments::{Attachment, NewAttachment};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreateAttachment => create_attachment(attachment: NewAttachment) -> Attachment,
+    ListAttachments => list_attachments(todo_id: i32) -> Vec<Attachment>,
+    GetAttachment => get_attachment(todo_id: i32, id: i32) -> Option<Attachment>,
+    DeleteAttachment => delete_attachment(todo_id: i32, id: i32) -> Option<Attachment>
+);
@@ -0,0 +1,57 @@
+//! Implements the audit log transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::audit_log::{NewAuditEntry, AuditEntry};
+use sqlx::types::Json;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::audit_log::tx_definitions::{CreateAuditEntry, ListAuditEntries};
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CreateAuditEntry, create_audit_entry)]
+async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+    let query = r#"
+        INSERT INTO audit_log (actor_id, action, subject_id, details)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, actor_id, action, subject_id, details, date_created
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, AuditEntry>(query)
+            .bind(entry.actor_id)
+            .bind(&entry.action)
+            .bind(entry.subject_id)
+            .bind(Json(&entry.details))
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to create audit entry: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+/// Lists the entries newest first, starting after `before_id` when paging.
+#[impl_transaction(SqlxPostGresDescriptor, ListAuditEntries, list_audit_entries)]
+async fn list_audit_entries(before_id: Option<i32>, limit: i64) -> Result<Vec<AuditEntry>, NanoServiceError> {
+    let query = r#"
+        SELECT id, actor_id, action, subject_id, details, date_created
+        FROM audit_log
+        WHERE $1::INTEGER IS NULL OR id < $1
+        ORDER BY id DESC
+        LIMIT $2
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, AuditEntry>(query)
+            .bind(before_id)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to list audit entries: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
@@ -0,0 +1,12 @@
+//! Defines transaction traits for interacting with the `audit_log` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::audit_log::{NewAuditEntry, AuditEntry};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreateAuditEntry => create_audit_entry(entry: NewAuditEntry) -> AuditEntry,
+    ListAuditEntries => list_audit_entries(before_id: Option<i32>, limit: i64) -> Vec<AuditEntry>
+);
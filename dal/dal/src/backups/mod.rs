@@ -0,0 +1,112 @@
+//! Logical backups of the application tables for self-hosted installs.
+//!
+//! # Overview
+//! A backup uses the same format as a fixture snapshot, one `INSERT` per row built from the row's
+//! JSON, but covers every table in `BACKUP_TABLES` and starts with a header naming the schema
+//! version it was taken at. `runner` gzips it into the object store and records it in `backups`,
+//! and restores a recorded backup by replacing the contents of every table in one transaction.
+//!
+//! ## Notes
+//! - A backup can only be restored to a database at the schema version it was taken at, run the
+//!   migrations to that version first.
+//! - The whole backup is built in memory, which suits the small installs it is meant for.
+//! - `attachments` only records the attached files, the files themselves stay in the object store.
+pub mod tx_definitions;
+pub mod postgres_txs;
+pub mod runner;
+
+
+/// The tables held in a backup, ordered so that rows are inserted after the rows they reference.
+pub const BACKUP_TABLES: [&str; 41] = [
+    "organizations",
+    "users",
+    "org_memberships",
+    "role_permissions",
+    "permissions",
+    "role_permission_grants",
+    "user_onboarding",
+    "rate_limit_entries",
+    "todos",
+    "tags",
+    "todo_tags",
+    "todo_comments",
+    "sla_policies",
+    "sla_warnings",
+    "pending_notifications",
+    "email_outbox",
+    "audit_log",
+    "tombstones",
+    "moderation_decisions",
+    "login_events",
+    "legal_holds",
+    "org_branding",
+    "org_plan",
+    "org_billing",
+    "billing_events",
+    "usage_counters",
+    "usage_active_users",
+    "usage_rollups",
+    "request_metrics",
+    "availability_rollups",
+    "webhook_deliveries",
+    "webhooks",
+    "webhook_outbox",
+    "api_keys",
+    "federated_identities",
+    "devices",
+    "attachments",
+    "todo_events",
+    "projects",
+    "project_members",
+    "project_todos",
+];
+
+/// The tables left out of a backup and left alone by a restore.
+///
+/// * `backups` - The record of the backups themselves.
+/// * `request_rate_limits` - Short lived request counts.
+/// * `export_jobs` - Exports whose files expire, emptied by the cascade when `users` is restored.
+pub const NOT_BACKED_UP: [&str; 3] = ["backups", "request_rate_limits", "export_jobs"];
+
+
+/// The first line of a backup, naming the schema version it was taken at.
+pub fn backup_header(schema_version: i64) -> String {
+    format!("-- backup at schema version {}\n", schema_version)
+}
+
+
+/// Reads the schema version from the first line of a backup.
+///
+/// # Returns
+/// * `Some(i64)` - The version the backup was taken at
+/// * `None` - If the SQL does not start with a backup header
+pub fn backup_schema_version(sql: &str) -> Option<i64> {
+    sql.lines().next()?.strip_prefix("-- backup at schema version ")?.trim().parse().ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema_compat::load_schema_manifest;
+
+    #[test]
+    fn test_every_table_is_backed_up_or_left_out() {
+        let manifest = load_schema_manifest().unwrap();
+        for table in manifest.keys() {
+            assert!(
+                BACKUP_TABLES.contains(&table.as_str()) != NOT_BACKED_UP.contains(&table.as_str()),
+                "{} must be in exactly one of BACKUP_TABLES and NOT_BACKED_UP", table
+            );
+        }
+        assert_eq!(manifest.len(), BACKUP_TABLES.len() + NOT_BACKED_UP.len());
+    }
+
+    #[test]
+    fn test_backup_schema_version() {
+        let sql = format!("{}-- users\nINSERT INTO users SELECT 1;\n", backup_header(20250525000000));
+        assert_eq!(backup_schema_version(&sql), Some(20250525000000));
+        assert_eq!(backup_schema_version("-- users\n"), None);
+        assert_eq!(backup_schema_version(""), None);
+    }
+}
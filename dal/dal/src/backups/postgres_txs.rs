@@ -0,0 +1,213 @@
+//! Implements the backup transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::backups::{Backup, DatabaseDump, NewBackup};
+use sqlx::{Postgres, Row, Transaction};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::backups::{backup_header, backup_schema_version, BACKUP_TABLES};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::fixtures::insert_statement;
+use crate::schema_compat::load_schema_manifest;
+use crate::backups::tx_definitions::{
+    DumpBackup,
+    RestoreBackup,
+    RecordBackup,
+    GetBackup,
+    ListBackups,
+    DeleteBackupRecord
+};
+
+
+fn backup_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+/// The backed up tables with an `id` column, whose sequences are reset after a restore.
+fn tables_with_id() -> Result<Vec<&'static str>, NanoServiceError> {
+    let manifest = load_schema_manifest()?;
+    Ok(BACKUP_TABLES.into_iter()
+        .filter(|table| manifest.get(*table).is_some_and(|columns| columns.iter().any(|column| column == "id")))
+        .collect())
+}
+
+
+async fn schema_version(transaction: &mut Transaction<'_, Postgres>) -> Result<i64, NanoServiceError> {
+    sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success")
+        .fetch_one(&mut **transaction)
+        .await
+        .map_err(|e| backup_error("read the schema version", e))
+}
+
+
+/// Dumps every table in `BACKUP_TABLES` from one read only snapshot, so the rows of each table are
+/// consistent with the rows they reference.
+#[impl_transaction(SqlxPostGresDescriptor, DumpBackup, dump_backup)]
+async fn dump_backup() -> Result<DatabaseDump, NanoServiceError> {
+    let tables_with_id = tables_with_id()?;
+    let mut transaction = SQLX_POSTGRES_POOL.begin()
+        .await
+        .map_err(|e| backup_error("start backup transaction", e))?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| backup_error("start backup snapshot", e))?;
+
+    let schema_version = schema_version(&mut transaction).await?;
+    let mut sql = backup_header(schema_version);
+    let mut row_count = 0;
+    for table in BACKUP_TABLES {
+        let order = if tables_with_id.contains(&table) { "id" } else { "1" };
+        let query = format!("SELECT to_jsonb(t)::text AS row FROM {} t ORDER BY {}", table, order);
+        let rows = sqlx::query(&query)
+            .fetch_all(&mut *transaction)
+            .await
+            .map_err(|e| backup_error(&format!("back up {}", table), e))?;
+        sql.push_str(&format!("-- {}\n", table));
+        for row in rows {
+            let row: String = row.get("row");
+            sql.push_str(&insert_statement(table, &row));
+            sql.push('\n');
+            row_count += 1;
+        }
+        sql.push('\n');
+    }
+
+    transaction.commit()
+        .await
+        .map_err(|e| backup_error("finish backup", e))?;
+    Ok(DatabaseDump { sql, row_count, schema_version })
+}
+
+
+/// Replaces the contents of every table in `BACKUP_TABLES` with a backup in one transaction, so a
+/// backup that fails part way leaves the database as it was.
+#[impl_transaction(SqlxPostGresDescriptor, RestoreBackup, restore_backup)]
+async fn restore_backup(sql: String) -> Result<i64, NanoServiceError> {
+    let Some(backup_version) = backup_schema_version(&sql) else {
+        return Err(NanoServiceError::new("The file is not a backup".to_string(), NanoServiceErrorStatus::BadRequest))
+    };
+    let tables_with_id = tables_with_id()?;
+    let mut transaction = SQLX_POSTGRES_POOL.begin()
+        .await
+        .map_err(|e| backup_error("start restore transaction", e))?;
+
+    let schema_version = schema_version(&mut transaction).await?;
+    if schema_version != backup_version {
+        return Err(NanoServiceError::new(
+            format!("The backup was taken at schema version {} but the database is at {}", backup_version, schema_version),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    }
+
+    let truncate = format!("TRUNCATE {} RESTART IDENTITY CASCADE", BACKUP_TABLES.join(", "));
+    sqlx::query(&truncate)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| backup_error("truncate backed up tables", e))?;
+
+    // every row is its own statement on its own line, `raw_sql` cannot be held across the awaits of a `Send` future
+    let mut restored = 0;
+    for statement in sql.lines().filter(|line| line.starts_with("INSERT INTO")) {
+        sqlx::query(statement)
+            .persistent(false)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| backup_error("restore backup", e))?;
+        restored += 1;
+    }
+
+    // explicit ids do not advance the sequences, so they are moved past the restored rows
+    for table in tables_with_id {
+        let reset = format!(
+            "SELECT setval(pg_get_serial_sequence('{}', 'id'), COALESCE(MAX(id), 1), MAX(id) IS NOT NULL) FROM {}",
+            table, table
+        );
+        sqlx::query(&reset)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| backup_error(&format!("reset {} sequence", table), e))?;
+    }
+
+    transaction.commit()
+        .await
+        .map_err(|e| backup_error("commit restore", e))?;
+    Ok(restored)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RecordBackup, record_backup)]
+async fn record_backup(backup: NewBackup) -> Result<Backup, NanoServiceError> {
+    let query = r#"
+        INSERT INTO backups (object_key, size_bytes, row_count, schema_version, triggered_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, object_key, size_bytes, row_count, schema_version, triggered_by, date_created
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, Backup>(query)
+            .bind(&backup.object_key)
+            .bind(backup.size_bytes)
+            .bind(backup.row_count)
+            .bind(backup.schema_version)
+            .bind(backup.triggered_by)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| backup_error("record backup", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetBackup, get_backup)]
+async fn get_backup(id: i32) -> Result<Option<Backup>, NanoServiceError> {
+    let query = r#"
+        SELECT id, object_key, size_bytes, row_count, schema_version, triggered_by, date_created
+        FROM backups
+        WHERE id = $1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Backup>(query)
+            .bind(id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| backup_error("get backup", e))
+}
+
+
+/// Lists the backups newest first.
+#[impl_transaction(SqlxPostGresDescriptor, ListBackups, list_backups)]
+async fn list_backups(limit: i64) -> Result<Vec<Backup>, NanoServiceError> {
+    let query = r#"
+        SELECT id, object_key, size_bytes, row_count, schema_version, triggered_by, date_created
+        FROM backups
+        ORDER BY date_created DESC, id DESC
+        LIMIT $1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Backup>(query)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| backup_error("list backups", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, DeleteBackupRecord, delete_backup_record)]
+async fn delete_backup_record(id: i32) -> Result<bool, NanoServiceError> {
+    let result = retry_write(|| {
+        sqlx::query("DELETE FROM backups WHERE id = $1")
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| backup_error("delete backup record", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
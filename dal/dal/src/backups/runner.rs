@@ -0,0 +1,205 @@
+//! Takes backups into the object store, restores them, and prunes the old ones.
+use std::io::{Read, Write};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use kernel::backups::{Backup, NewBackup};
+use kernel::chrono::Utc;
+use kernel::object_store::ObjectStore;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::backups::tx_definitions::{DumpBackup, RestoreBackup, RecordBackup, GetBackup, ListBackups, DeleteBackupRecord};
+
+
+fn compression_error(e: std::io::Error) -> NanoServiceError {
+    NanoServiceError::new(format!("Failed to compress backup: {}", e), NanoServiceErrorStatus::Unknown)
+}
+
+
+/// Dumps the database, puts it gzipped in the object store and records it.
+///
+/// # Arguments
+/// * `triggered_by` - The ID of the super admin who asked for the backup, `None` for a scheduled one.
+///
+/// # Returns
+/// * `Ok(Backup)` - The recorded backup
+/// * `Err(NanoServiceError)` - If the dump or the upload failed, nothing is recorded
+pub async fn create_backup<X, O, Y>(triggered_by: Option<i32>) -> Result<Backup, NanoServiceError>
+where
+    X: DumpBackup + RecordBackup,
+    O: ObjectStore,
+    Y: GetConfigVariable,
+{
+    let dump = X::dump_backup().await?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(dump.sql.as_bytes()).map_err(compression_error)?;
+    let body = encoder.finish().map_err(compression_error)?;
+
+    let object_key = Backup::storage_key(Utc::now().naive_utc());
+    let size_bytes = body.len() as i64;
+    O::put_object::<Y>(&object_key, body, "application/gzip").await?;
+    X::record_backup(NewBackup {
+        object_key,
+        size_bytes,
+        row_count: dump.row_count,
+        schema_version: dump.schema_version,
+        triggered_by,
+    }).await
+}
+
+
+/// Replaces the contents of the database with a recorded backup.
+///
+/// # Returns
+/// * `Ok(i64)` - The rows restored
+/// * `Err(NanoServiceError)` - `NotFound` if there is no such backup, `BadRequest` if it was taken
+///   at another schema version, in which case nothing is changed
+pub async fn restore_from_backup<X, O, Y>(id: i32) -> Result<i64, NanoServiceError>
+where
+    X: GetBackup + RestoreBackup,
+    O: ObjectStore,
+    Y: GetConfigVariable,
+{
+    let backup = X::get_backup(id).await?.ok_or_else(|| NanoServiceError::new(
+        format!("Backup {} not found", id),
+        NanoServiceErrorStatus::NotFound
+    ))?;
+    let body = O::get_object::<Y>(&backup.object_key).await?;
+    let mut sql = String::new();
+    GzDecoder::new(body.as_slice()).read_to_string(&mut sql).map_err(|e| NanoServiceError::new(
+        format!("Backup {} could not be decompressed: {}", id, e),
+        NanoServiceErrorStatus::Unknown
+    ))?;
+    X::restore_backup(sql).await
+}
+
+
+/// Deletes all but the `keep` newest backups from the object store and their records.
+///
+/// # Returns
+/// * `usize` - The backups deleted, one whose file could not be deleted is kept for the next run
+pub async fn prune_backups<X, O, Y>(keep: usize) -> Result<usize, NanoServiceError>
+where
+    X: ListBackups + DeleteBackupRecord,
+    O: ObjectStore,
+    Y: GetConfigVariable,
+{
+    let backups = X::list_backups(i64::MAX).await?;
+    let mut deleted = 0;
+    for backup in backups.into_iter().skip(keep) {
+        if let Err(e) = O::delete_object::<Y>(&backup.object_key).await {
+            println!("failed to delete backup {}: {}", backup.id, e.message);
+            continue
+        }
+        X::delete_backup_record(backup.id).await?;
+        deleted += 1;
+    }
+    Ok(deleted)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::backups::DatabaseDump;
+    use kernel::chrono::NaiveDateTime;
+    use kernel::object_store::engine_mock::{FailingObjectStoreMock, MemoryObjectStoreMock, STORED_OBJECTS};
+    use std::sync::Mutex;
+    use crate::backups::backup_header;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    static RECORDED: Mutex<Vec<NewBackup>> = Mutex::new(Vec::new());
+    static RESTORED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    static DELETED: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+
+    fn backup(id: i32, object_key: &str) -> Backup {
+        Backup {
+            id,
+            object_key: object_key.to_string(),
+            size_bytes: 10,
+            row_count: 2,
+            schema_version: 7,
+            triggered_by: None,
+            date_created: NaiveDateTime::default(),
+        }
+    }
+
+    /// Has backups `3`, `2` and `1` newest first, backup `1` is stored as `backups/runner-1.sql.gz`.
+    struct MockPostgres;
+
+    #[impl_transaction(MockPostgres, DumpBackup, dump_backup)]
+    async fn dump_backup() -> Result<DatabaseDump, NanoServiceError> {
+        let sql = format!("{}-- users\nINSERT INTO users SELECT 1;\nINSERT INTO users SELECT 2;\n", backup_header(7));
+        Ok(DatabaseDump { sql, row_count: 2, schema_version: 7 })
+    }
+
+    #[impl_transaction(MockPostgres, RecordBackup, record_backup)]
+    async fn record_backup(new_backup: NewBackup) -> Result<Backup, NanoServiceError> {
+        RECORDED.lock().unwrap().push(new_backup.clone());
+        Ok(backup(4, &new_backup.object_key))
+    }
+
+    #[impl_transaction(MockPostgres, GetBackup, get_backup)]
+    async fn get_backup(id: i32) -> Result<Option<Backup>, NanoServiceError> {
+        Ok((1..=3).contains(&id).then(|| backup(id, &format!("backups/runner-{}.sql.gz", id))))
+    }
+
+    #[impl_transaction(MockPostgres, RestoreBackup, restore_backup)]
+    async fn restore_backup(sql: String) -> Result<i64, NanoServiceError> {
+        RESTORED.lock().unwrap().push(sql);
+        Ok(2)
+    }
+
+    #[impl_transaction(MockPostgres, ListBackups, list_backups)]
+    async fn list_backups(_limit: i64) -> Result<Vec<Backup>, NanoServiceError> {
+        Ok((1..=3).rev().map(|id| backup(id, &format!("backups/runner-{}.sql.gz", id))).collect())
+    }
+
+    #[impl_transaction(MockPostgres, DeleteBackupRecord, delete_backup_record)]
+    async fn delete_backup_record(id: i32) -> Result<bool, NanoServiceError> {
+        DELETED.lock().unwrap().push(id);
+        Ok(true)
+    }
+
+    #[tokio::test]
+    async fn test_create_and_restore_backup() {
+        let created = create_backup::<MockPostgres, MemoryObjectStoreMock, FakeConfig>(Some(1)).await.unwrap();
+        let recorded = RECORDED.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(recorded.object_key, created.object_key);
+        assert!(recorded.object_key.starts_with("backups/") && recorded.object_key.ends_with(".sql.gz"));
+        assert_eq!((recorded.row_count, recorded.schema_version, recorded.triggered_by), (2, 7, Some(1)));
+        let stored = STORED_OBJECTS.lock().unwrap().get(&created.object_key).cloned().unwrap();
+        assert_eq!(recorded.size_bytes, stored.len() as i64);
+
+        // the stored backup is restored as it was dumped
+        STORED_OBJECTS.lock().unwrap().insert("backups/runner-1.sql.gz".to_string(), stored);
+        assert_eq!(restore_from_backup::<MockPostgres, MemoryObjectStoreMock, FakeConfig>(1).await.unwrap(), 2);
+        let dumped = MockPostgres::dump_backup().await.unwrap().sql;
+        assert_eq!(RESTORED.lock().unwrap()[0], dumped);
+
+        let error = restore_from_backup::<MockPostgres, MemoryObjectStoreMock, FakeConfig>(9).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+        STORED_OBJECTS.lock().unwrap().insert("backups/runner-2.sql.gz".to_string(), b"not gzip".to_vec());
+        assert!(restore_from_backup::<MockPostgres, MemoryObjectStoreMock, FakeConfig>(2).await.is_err());
+        assert_eq!(RESTORED.lock().unwrap().len(), 1);
+
+        assert!(create_backup::<MockPostgres, FailingObjectStoreMock, FakeConfig>(None).await.is_err());
+        assert_eq!(RECORDED.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_prune_backups() {
+        assert_eq!(prune_backups::<MockPostgres, FailingObjectStoreMock, FakeConfig>(1).await.unwrap(), 0);
+        assert!(DELETED.lock().unwrap().is_empty());
+        assert_eq!(prune_backups::<MockPostgres, MemoryObjectStoreMock, FakeConfig>(1).await.unwrap(), 2);
+        assert_eq!(*DELETED.lock().unwrap(), vec![2, 1]);
+    }
+}
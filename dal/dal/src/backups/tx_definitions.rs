@@ -0,0 +1,21 @@
+//! Defines transaction traits for taking, restoring and recording backups.
+//!
+//! # Overview
+//! This file uses the `define_dal_transactions` macro to create traits for dumping and restoring the
+//! tables in `BACKUP_TABLES`, and for keeping the record of the backups in the `backups` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `RestoreBackup` replaces every row in the backed up tables, it is only meant for the CLI.
+use kernel::backups::{Backup, DatabaseDump, NewBackup};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    DumpBackup => dump_backup() -> DatabaseDump,
+    RestoreBackup => restore_backup(sql: String) -> i64,
+    RecordBackup => record_backup(backup: NewBackup) -> Backup,
+    GetBackup => get_backup(id: i32) -> Option<Backup>,
+    ListBackups => list_backups(limit: i64) -> Vec<Backup>,
+    DeleteBackupRecord => delete_backup_record(id: i32) -> bool
+);
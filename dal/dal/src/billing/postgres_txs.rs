@@ -0,0 +1,92 @@
+//! Implements the billing transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::billing::{BillingChange, OrgBilling};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::retry_transient;
+use crate::connections::unit_of_work::WithTransaction;
+use crate::billing::tx_definitions::{GetOrgBilling, ApplyBillingChange};
+
+
+fn billing_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetOrgBilling, get_org_billing)]
+async fn get_org_billing() -> Result<OrgBilling, NanoServiceError> {
+    let query = r#"
+        SELECT status, customer_id, subscription_id, current_period_end, last_event_at, date_updated
+        FROM org_billing
+        WHERE id = 1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, OrgBilling>(query)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| billing_error("get billing", e))
+}
+
+
+/// Records the event and applies its change to the billing status and plan in one transaction.
+///
+/// # Returns
+/// - `Ok(true)`: If the event had not been seen before.
+/// - `Ok(false)`: If the event has already been recorded, in which case nothing is changed.
+/// - `Err(NanoServiceError)`: If a statement fails, in which case the event is not recorded so
+///   Stripe's retry applies it.
+///
+/// # Notes
+/// - A change from an event older than the last one applied is recorded but not applied, so a late
+///   renewal cannot reactivate a subscription that has since been deleted.
+/// - Moving plan keeps the overrides a super admin has set.
+#[impl_transaction(SqlxPostGresDescriptor, ApplyBillingChange, apply_billing_change)]
+async fn apply_billing_change(change: BillingChange) -> Result<bool, NanoServiceError> {
+    SqlxPostGresDescriptor::with_transaction(|transaction| Box::pin(async move {
+        let recorded = sqlx::query(
+            "INSERT INTO billing_events (event_id, event_type) VALUES ($1, $2) ON CONFLICT (event_id) DO NOTHING"
+        )
+            .bind(&change.event_id)
+            .bind(&change.event_type)
+            .execute(&mut **transaction)
+            .await
+            .map_err(|e| billing_error("record billing event", e))?;
+        if recorded.rows_affected() == 0 {
+            return Ok(false)
+        }
+
+        let query = r#"
+            UPDATE org_billing
+            SET status = $1,
+                customer_id = COALESCE($2, customer_id),
+                subscription_id = COALESCE($3, subscription_id),
+                current_period_end = COALESCE($4, current_period_end),
+                last_event_at = $5,
+                date_updated = NOW()
+            WHERE id = 1 AND (last_event_at IS NULL OR last_event_at <= $5)
+        "#;
+        let updated = sqlx::query(query)
+            .bind(change.status)
+            .bind(&change.customer_id)
+            .bind(&change.subscription_id)
+            .bind(change.current_period_end)
+            .bind(change.event_created)
+            .execute(&mut **transaction)
+            .await
+            .map_err(|e| billing_error("update billing", e))?;
+
+        if let (1, Some(plan)) = (updated.rows_affected(), change.plan) {
+            sqlx::query("UPDATE org_plan SET plan = $1, date_updated = NOW() WHERE id = 1")
+                .bind(plan)
+                .execute(&mut **transaction)
+                .await
+                .map_err(|e| billing_error("update plan", e))?;
+        }
+        Ok(true)
+    })).await
+}
@@ -0,0 +1,13 @@
+//! Defines transaction traits for interacting with the `org_billing` and `billing_events` tables.
+//!
+//! ## Notes
+//! - `org_billing` holds a single row seeded by its migration, so there is no create or delete.
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::billing::{BillingChange, OrgBilling};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    GetOrgBilling => get_org_billing() -> OrgBilling,
+    ApplyBillingChange => apply_billing_change(change: BillingChange) -> bool
+);
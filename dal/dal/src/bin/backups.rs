@@ -0,0 +1,54 @@
+//! Takes, lists and restores the backups of the database pointed to by `DB_URL`.
+//!
+//! # Usage
+//! * `backups list` - Lists the backups in the object store, newest first
+//! * `backups create` - Takes a backup now
+//! * `backups restore <id> --yes` - Replaces every backed up row with the backup `<id>`
+//!
+//! The object store is configured with the `OBJECT_STORE_*` variables the server uses.
+use dal::backups::runner::{create_backup, restore_from_backup};
+use dal::backups::tx_definitions::ListBackups;
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use kernel::object_store::engine_s3::S3ObjectStore;
+use std::process::ExitCode;
+use utils::secrets::SecretsConfig;
+
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let outcome = match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["list"] => SqlxPostGresDescriptor::list_backups(i64::MAX).await.map(|backups| {
+            println!("{:<8}{:<21}{:<12}{:<14}{:<16}TRIGGERED BY", "ID", "TAKEN AT", "ROWS", "BYTES", "SCHEMA");
+            for backup in backups {
+                println!(
+                    "{:<8}{:<21}{:<12}{:<14}{:<16}{}",
+                    backup.id,
+                    backup.date_created.format("%Y-%m-%d %H:%M:%S"),
+                    backup.row_count,
+                    backup.size_bytes,
+                    backup.schema_version,
+                    backup.triggered_by.map(|id| format!("user {}", id)).unwrap_or_else(|| "schedule".to_string())
+                );
+            }
+        }).map_err(|e| e.message),
+        ["create"] => create_backup::<SqlxPostGresDescriptor, S3ObjectStore, SecretsConfig>(None).await
+            .map(|backup| println!("backed up {} rows to {}", backup.row_count, backup.object_key))
+            .map_err(|e| e.message),
+        ["restore", id, "--yes"] => match id.parse::<i32>() {
+            Ok(id) => restore_from_backup::<SqlxPostGresDescriptor, S3ObjectStore, SecretsConfig>(id).await
+                .map(|rows| println!("restored {} rows from backup {}", rows, id))
+                .map_err(|e| e.message),
+            Err(_) => Err(format!("{} is not a backup ID", id))
+        },
+        ["restore", _] => Err("restoring replaces every backed up row in the database, pass --yes to restore".to_string()),
+        _ => Err("usage: backups list | backups create | backups restore <id> --yes".to_string())
+    };
+    match outcome {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Dumps or restores a snapshot of the test database pointed to by `DB_URL`.
+//!
+//! # Usage
+//! * `fixtures dump <path>` - Writes the current fixture tables to `<path>`
+//! * `fixtures restore [path]` - Restores `<path>`, or the canonical dataset if no path is given
+use dal::fixtures::{dump_snapshot, restore_snapshot, CANONICAL_FIXTURES};
+use std::process::ExitCode;
+
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let outcome = match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["dump", path] => match dump_snapshot().await {
+            Ok(snapshot) => std::fs::write(path, snapshot).map_err(|e| e.to_string()),
+            Err(e) => Err(e.message)
+        },
+        ["restore"] => restore_snapshot(CANONICAL_FIXTURES).await.map_err(|e| e.message),
+        ["restore", path] => match std::fs::read_to_string(path) {
+            Ok(snapshot) => restore_snapshot(&snapshot).await.map_err(|e| e.message),
+            Err(e) => Err(e.to_string())
+        },
+        _ => Err("usage: fixtures dump <path> | fixtures restore [path]".to_string())
+    };
+    match outcome {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
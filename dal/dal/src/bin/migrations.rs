@@ -0,0 +1,33 @@
+//! Runs, reverts or reports the migrations of the database pointed to by `DB_URL`.
+//!
+//! # Usage
+//! * `migrations status` - Lists every migration and whether it has been applied
+//! * `migrations run` - Applies the pending migrations
+//! * `migrations revert <version>` - Reverts the migrations newer than `<version>`, `0` reverts them all
+use dal::migrations::{migration_status, revert_migrations, run_migrations};
+use std::process::ExitCode;
+
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let outcome = match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["status"] => migration_status().await.map(|statuses| {
+            println!("{:<16}{:<10}{:<21}DESCRIPTION", "VERSION", "STATE", "INSTALLED ON");
+            statuses.iter().for_each(|status| println!("{}", status));
+        }).map_err(|e| e.message),
+        ["run"] => run_migrations().await.map_err(|e| e.message),
+        ["revert", version] => match version.parse::<i64>() {
+            Ok(target) => revert_migrations(target).await.map_err(|e| e.message),
+            Err(_) => Err(format!("{} is not a migration version", version))
+        },
+        _ => Err("usage: migrations status | migrations run | migrations revert <version>".to_string())
+    };
+    match outcome {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
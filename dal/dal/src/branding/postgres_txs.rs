@@ -0,0 +1,56 @@
+//! Implements the branding transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::branding::{NewOrgBranding, OrgBranding};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::branding::tx_definitions::{GetOrgBranding, UpdateOrgBranding};
+
+
+fn branding_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetOrgBranding, get_org_branding)]
+async fn get_org_branding() -> Result<OrgBranding, NanoServiceError> {
+    let query = r#"
+        SELECT product_name, logo_url, accent_color, date_updated
+        FROM org_branding
+        WHERE id = 1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, OrgBranding>(query)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| branding_error("get branding", e))
+}
+
+
+/// Replaces the branding, recreating the row if it has been removed by hand.
+#[impl_transaction(SqlxPostGresDescriptor, UpdateOrgBranding, update_org_branding)]
+async fn update_org_branding(branding: NewOrgBranding) -> Result<OrgBranding, NanoServiceError> {
+    let query = r#"
+        INSERT INTO org_branding (id, product_name, logo_url, accent_color)
+        VALUES (1, $1, $2, $3)
+        ON CONFLICT (id) DO UPDATE
+        SET product_name = EXCLUDED.product_name, logo_url = EXCLUDED.logo_url,
+            accent_color = EXCLUDED.accent_color, date_updated = NOW()
+        RETURNING product_name, logo_url, accent_color, date_updated
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, OrgBranding>(query)
+            .bind(&branding.product_name)
+            .bind(&branding.logo_url)
+            .bind(&branding.accent_color)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| branding_error("save branding", e))
+}
@@ -0,0 +1,13 @@
+//! Defines transaction traits for interacting with the `org_branding` table.
+//!
+//! ## Notes
+//! - The table holds a single row seeded by its migration, so there is no create or delete.
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::branding::{NewOrgBranding, OrgBranding};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    GetOrgBranding => get_org_branding() -> OrgBranding,
+    UpdateOrgBranding => update_org_branding(branding: NewOrgBranding) -> OrgBranding
+);
@@ -1 +1,3 @@
 pub mod sqlx_postgres;
+pub mod unit_of_work;
+pub mod retry;
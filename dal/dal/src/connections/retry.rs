@@ -0,0 +1,388 @@
+//! Retries statements that fail for reasons that go away on their own.
+//!
+//! # Overview
+//! A statement run against the pool can fail because Postgres cancelled it to resolve a
+//! serialization failure or a deadlock, or because the connection it was given had dropped. Running
+//! it again usually succeeds, so the transaction implementations run their pool statements again
+//! after a jittered backoff when the error is one of these, up to the attempts allowed by
+//! `RETRY_POLICY`. Any other error is returned straight away.
+//!
+//! Reads go through `retry_transient`, which retries all of these errors. Writes go through
+//! `retry_write`, which only retries serialization failures and deadlocks, as Postgres rolled the
+//! statement back for those. A connection that drops mid-statement hides whether a write was
+//! committed, and running it again could queue an email or create an item twice.
+//!
+//! # Configuration
+//! - `DAL_RETRY_MAX_ATTEMPTS`: How many times a statement is run in total, defaults to 3, 1 turns retries off.
+//! - `DAL_RETRY_BASE_DELAY_MS`: The backoff before the first retry, doubling for each retry after it, defaults to 25.
+//! - `DAL_RETRY_MAX_DELAY_MS`: The most a single backoff can grow to, defaults to 1000.
+//!
+//! With fault injection turned on, see `utils::fault_injection`, a statement can fail with a
+//! dropped connection before it is run, exercising the retries of reads and the errors of writes.
+//!
+//! # Notes
+//! - Statements run inside `with_transaction` are not retried on their own, as a failed statement
+//!   aborts the rest of the transaction.
+use std::future::Future;
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use utils::fault_injection::{inject_fault, FaultTarget};
+use utils::log_limited;
+
+
+/// The SQLSTATE codes of errors where running the statement again could succeed.
+///
+/// # Codes
+/// - `40001`: serialization failure.
+/// - `40P01`: deadlock detected.
+/// - `57P01`, `57P02`, `57P03`: the server is shutting down, crashed or is not accepting connections yet.
+/// - `08000`, `08001`, `08003`, `08004`, `08006`: the connection could not be made or was lost.
+pub const TRANSIENT_SQLSTATES: [&str; 10] = [
+    "40001", "40P01", "57P01", "57P02", "57P03", "08000", "08001", "08003", "08004", "08006",
+];
+
+
+/// The SQLSTATE codes of errors where Postgres rolled the statement back, so a write did not take
+/// effect and can be run again.
+///
+/// # Codes
+/// - `40001`: serialization failure.
+/// - `40P01`: deadlock detected.
+pub const ROLLED_BACK_SQLSTATES: [&str; 2] = ["40001", "40P01"];
+
+
+/// Whether an error is worth retrying.
+pub trait TransientError {
+
+    /// Returns `true` if running the same statement again could succeed.
+    fn is_transient(&self) -> bool;
+
+    /// Returns `true` if the statement did not take effect, so running it again cannot repeat a write.
+    fn is_rolled_back(&self) -> bool;
+}
+
+impl TransientError for sqlx::Error {
+
+    fn is_transient(&self) -> bool {
+        match self {
+            sqlx::Error::Database(error) => error.code()
+                .map(|code| TRANSIENT_SQLSTATES.contains(&code.as_ref()))
+                .unwrap_or(false),
+            sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+            _ => false
+        }
+    }
+
+    fn is_rolled_back(&self) -> bool {
+        match self {
+            sqlx::Error::Database(error) => error.code()
+                .map(|code| ROLLED_BACK_SQLSTATES.contains(&code.as_ref()))
+                .unwrap_or(false),
+            _ => false
+        }
+    }
+}
+
+
+/// How many times a statement is run and how long to wait between runs.
+///
+/// # Fields
+/// * `max_attempts` - How many times a statement is run in total.
+/// * `base_delay` - The backoff before the first retry, doubling for each retry after it.
+/// * `max_delay` - The most a single backoff can grow to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(25),
+            max_delay: Duration::from_millis(1000),
+        }
+    }
+}
+
+impl RetryPolicy {
+
+    /// Reads the policy from the `DAL_RETRY_*` environment variables, using the defaults for any
+    /// that are not set.
+    ///
+    /// # Panics
+    /// - If a variable is set but is not a whole number, or `DAL_RETRY_MAX_ATTEMPTS` is 0.
+    pub fn from_env() -> Self {
+        let defaults = RetryPolicy::default();
+        let read = |key: &str, default: u64| -> u64 {
+            match std::env::var(key) {
+                Ok(val) => val.trim().parse::<u64>().unwrap_or_else(|_| panic!("Could not parse {}", key)),
+                Err(_) => default
+            }
+        };
+        let max_attempts = read("DAL_RETRY_MAX_ATTEMPTS", defaults.max_attempts as u64);
+        assert!(max_attempts > 0, "DAL_RETRY_MAX_ATTEMPTS has to be at least 1");
+        RetryPolicy {
+            max_attempts: u32::try_from(max_attempts).unwrap_or(u32::MAX),
+            base_delay: Duration::from_millis(read("DAL_RETRY_BASE_DELAY_MS", defaults.base_delay.as_millis() as u64)),
+            max_delay: Duration::from_millis(read("DAL_RETRY_MAX_DELAY_MS", defaults.max_delay.as_millis() as u64)),
+        }
+    }
+
+    /// The backoff before a retry, a random duration up to the base delay doubled for each
+    /// earlier retry, so statements that failed together do not all run again together.
+    ///
+    /// # Arguments
+    /// * `retry` - The retry about to be made, starting at 1.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        let ceiling_micros = ceiling.as_micros().min(u64::MAX as u128) as u64;
+        Duration::from_micros(rand::thread_rng().gen_range(0..=ceiling_micros))
+    }
+
+    /// Runs a statement that only reads, running it again after a backoff while it fails with a
+    /// transient error.
+    ///
+    /// # Arguments
+    /// * `statement` - Builds and runs the statement, called once for each attempt.
+    ///
+    /// # Returns
+    /// * `Ok(T)` - The output of the first attempt that succeeded.
+    /// * `Err(E)` - The error of the first attempt that failed with an error that is not transient,
+    ///   or of the last attempt.
+    pub async fn run<T, E, F, Fut>(&self, statement: F) -> Result<T, E>
+    where
+        E: TransientError + std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>
+    {
+        self.run_while(E::is_transient, statement).await
+    }
+
+    /// Runs a statement that writes, running it again after a backoff only while Postgres rolls it
+    /// back, so a write that may have been committed is never repeated.
+    ///
+    /// # Arguments
+    /// * `statement` - Builds and runs the statement, called once for each attempt.
+    ///
+    /// # Returns
+    /// * `Ok(T)` - The output of the first attempt that succeeded.
+    /// * `Err(E)` - The error of the first attempt that failed with an error that was not rolled back,
+    ///   or of the last attempt.
+    pub async fn run_write<T, E, F, Fut>(&self, statement: F) -> Result<T, E>
+    where
+        E: TransientError + std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>
+    {
+        self.run_while(E::is_rolled_back, statement).await
+    }
+
+    async fn run_while<T, E, F, Fut>(&self, retryable: fn(&E) -> bool, mut statement: F) -> Result<T, E>
+    where
+        E: std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>
+    {
+        let mut attempt = 1;
+        loop {
+            match statement().await {
+                Err(error) if retryable(&error) && attempt < self.max_attempts => {
+                    let backoff = self.backoff(attempt);
+                    log_limited!(
+                        "dal",
+                        "statement attempt {} of {} failed, retrying in {}ms: {}",
+                        attempt, self.max_attempts, backoff.as_millis(), error
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                },
+                outcome => return outcome
+            }
+        }
+    }
+}
+
+
+/// The retry policy for statements run against `SQLX_POSTGRES_POOL`, read from the environment on first use.
+pub static RETRY_POLICY: Lazy<RetryPolicy> = Lazy::new(RetryPolicy::from_env);
+
+
+/// Runs a statement that only reads against the pool with `RETRY_POLICY`.
+///
+/// # Arguments
+/// * `statement` - Builds and runs the statement, called once for each attempt, so the values bound
+///   to the statement are borrowed rather than moved into it.
+pub async fn retry_transient<T, F, Fut>(mut statement: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>
+{
+    RETRY_POLICY.run(|| with_fault(statement())).await
+}
+
+
+/// Runs a statement that writes against the pool with `RETRY_POLICY`, only retrying serialization
+/// failures and deadlocks.
+///
+/// # Arguments
+/// * `statement` - Builds and runs the statement, called once for each attempt, so the values bound
+///   to the statement are borrowed rather than moved into it.
+pub async fn retry_write<T, F, Fut>(mut statement: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>
+{
+    RETRY_POLICY.run_write(|| with_fault(statement())).await
+}
+
+
+/// Fails an attempt with a dropped connection before it is run when a fault is injected.
+async fn with_fault<T>(attempt: impl Future<Output = Result<T, sqlx::Error>>) -> Result<T, sqlx::Error> {
+    inject_fault(FaultTarget::Dal).await.map_err(|fault| sqlx::Error::Io(std::io::Error::other(fault.to_string())))?;
+    attempt.await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use std::collections::VecDeque;
+    use std::error::Error as StdError;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    #[derive(Debug)]
+    struct MockDatabaseError(&'static str);
+
+    impl std::fmt::Display for MockDatabaseError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "mock database error {}", self.0)
+        }
+    }
+
+    impl StdError for MockDatabaseError {}
+
+    impl DatabaseError for MockDatabaseError {
+        fn message(&self) -> &str {
+            "mock database error"
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+
+        fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn StdError + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    fn database_error(code: &'static str) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(MockDatabaseError(code)))
+    }
+
+    /// Fails each statement with the queued errors before succeeding.
+    struct MockPool {
+        failures: Mutex<VecDeque<sqlx::Error>>,
+        calls: AtomicU32,
+    }
+
+    impl MockPool {
+        fn new(failures: Vec<sqlx::Error>) -> Self {
+            MockPool { failures: Mutex::new(failures.into()), calls: AtomicU32::new(0) }
+        }
+
+        async fn fetch_one(&self, id: &i32) -> Result<i32, sqlx::Error> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            match self.failures.lock().unwrap().pop_front() {
+                Some(error) => Err(error),
+                None => Ok(*id)
+            }
+        }
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy { max_attempts, base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(2) }
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(database_error("40001").is_transient());
+        assert!(database_error("40P01").is_transient());
+        assert!(database_error("08006").is_transient());
+        assert!(sqlx::Error::PoolTimedOut.is_transient());
+        assert!(!database_error("23505").is_transient());
+        assert!(!sqlx::Error::RowNotFound.is_transient());
+    }
+
+    #[test]
+    fn test_is_rolled_back() {
+        assert!(database_error("40001").is_rolled_back());
+        assert!(database_error("40P01").is_rolled_back());
+        assert!(!database_error("08006").is_rolled_back());
+        assert!(!sqlx::Error::Io(std::io::Error::other("connection reset")).is_rolled_back());
+        assert!(!sqlx::Error::PoolTimedOut.is_rolled_back());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy { max_attempts: 5, base_delay: Duration::from_millis(10), max_delay: Duration::from_millis(30) };
+        for _ in 0..50 {
+            assert!(policy.backoff(1) <= Duration::from_millis(10));
+            assert!(policy.backoff(2) <= Duration::from_millis(20));
+            assert!(policy.backoff(4) <= Duration::from_millis(30));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let pool = MockPool::new(vec![database_error("40001"), sqlx::Error::PoolTimedOut]);
+        let id = 7;
+        let outcome = policy(3).run(|| pool.fetch_one(&id)).await;
+        assert_eq!(outcome.unwrap(), 7);
+        assert_eq!(pool.calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let pool = MockPool::new(vec![database_error("40P01"), database_error("40P01"), database_error("40P01")]);
+        let outcome = policy(2).run(|| pool.fetch_one(&1)).await;
+        assert_eq!(outcome.unwrap_err().as_database_error().unwrap().code().unwrap(), "40P01");
+        assert_eq!(pool.calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_writes_only_retry_rolled_back_errors() {
+        let pool = MockPool::new(vec![database_error("40001"), sqlx::Error::Io(std::io::Error::other("connection reset"))]);
+        let outcome = policy(3).run_write(|| pool.fetch_one(&1)).await;
+        assert!(matches!(outcome.unwrap_err(), sqlx::Error::Io(_)));
+        assert_eq!(pool.calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_other_errors() {
+        let pool = MockPool::new(vec![database_error("23505")]);
+        let outcome = policy(3).run(|| pool.fetch_one(&1)).await;
+        assert!(outcome.is_err());
+        assert_eq!(pool.calls.load(Ordering::Relaxed), 1);
+    }
+}
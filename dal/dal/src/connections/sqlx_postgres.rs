@@ -3,14 +3,28 @@
 //! # Overview
 //! - Establishes a connection pool for a PostgreSQL database using the `sqlx` library.
 //! - Provides the `SqlxPostGresDescriptor` struct to serve as a handle for database-related operations.
-//! - Configures the connection pool using environment variables for flexibility and scalability.
+//! - Configures the connection pool from config variables, see `PoolConfig`.
 //!
 //! # Features
-//! - The `SQLX_POSTGRES_POOL` is a lazily-initialized static instance for managing database connections.
-//! - The `SqlxPostGresDescriptor` is used for dependency injection and applying database traits for transaction handling.
-use sqlx::postgres::{PgPool, PgPoolOptions};
-use once_cell::sync::Lazy;
-use std::env;
+//! - Servers call `init_pool` at startup so a bad config or an unreachable database stops the
+//!   server with a clear error, and `close_pool` on shutdown so the connections are closed cleanly.
+//! - Binaries that never call `init_pool` get a pool created lazily on first use of `SQLX_POSTGRES_POOL`.
+//!
+//! # Variables
+//! * `DB_URL` - The connection string, read through `SecretsConfig` so it can come from the secrets backend
+//! * `TO_DO_MAX_CONNECTIONS` - The most connections the pool opens, defaults to 5
+//! * `DB_ACQUIRE_TIMEOUT` - How long a statement waits for a free connection, defaults to `30s`
+//! * `DB_IDLE_TIMEOUT` - How long a connection can sit idle before it is closed, defaults to `10m`, `0` keeps idle connections open
+//! * `DB_MAX_LIFETIME` - How long a connection is used before it is replaced, defaults to `30m`, `0` never replaces them
+//! * `DB_STATEMENT_CACHE_SIZE` - The prepared statements cached per connection, defaults to 100, `0` disables the cache
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use utils::config::{GetConfigVariable, parse_duration};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::secrets::SecretsConfig;
 
 /// A descriptor struct used for applying database traits and dependency injection.
 ///
@@ -19,34 +33,259 @@ use std::env;
 /// that define transactions or other interactions with the database.
 pub struct SqlxPostGresDescriptor;
 
-/// A lazily-initialized static instance of the PostgreSQL connection pool.
+
+/// The acquire timeout used when not configured.
+const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The idle timeout used when not configured.
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// The connection lifetime used when not configured.
+const DEFAULT_MAX_LIFETIME: Duration = Duration::from_secs(30 * 60);
+
+/// The statement cache size used when not configured.
+const DEFAULT_STATEMENT_CACHE_SIZE: usize = 100;
+
+
+/// The resolved pool options.
+///
+/// # Fields
+/// * `max_connections` - The most connections the pool opens.
+/// * `acquire_timeout` - How long a statement waits for a free connection.
+/// * `idle_timeout` - How long a connection can sit idle, `None` to keep it open.
+/// * `max_lifetime` - How long a connection is used before it is replaced, `None` to never replace it.
+/// * `statement_cache_size` - The prepared statements cached per connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    pub acquire_timeout: Duration,
+    pub idle_timeout: Option<Duration>,
+    pub max_lifetime: Option<Duration>,
+    pub statement_cache_size: usize,
+}
+
+
+fn invalid_pool_variable(variable: &str, value: &str, expected: &str) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("{} is set to '{}' which is not {}", variable, value, expected),
+        NanoServiceErrorStatus::Unknown
+    )
+}
+
+
+/// Reads a duration, `None` if it is not set.
+fn read_duration<Y: GetConfigVariable>(variable: &str) -> Result<Option<Duration>, NanoServiceError> {
+    match Y::get_config_variable(variable.to_string()) {
+        Ok(value) => parse_duration(&value).map(Some).ok_or_else(|| invalid_pool_variable(variable, &value, "a duration")),
+        Err(_) => Ok(None)
+    }
+}
+
+
+/// Reads a number, `None` if it is not set.
+fn read_count<Y: GetConfigVariable>(variable: &str) -> Result<Option<u32>, NanoServiceError> {
+    match Y::get_config_variable(variable.to_string()) {
+        Ok(value) => value.trim().parse::<u32>().map(Some).map_err(|_| invalid_pool_variable(variable, &value, "a whole number")),
+        Err(_) => Ok(None)
+    }
+}
+
+
+impl PoolConfig {
+
+    /// Reads the pool options from config.
+    ///
+    /// # Returns
+    /// * `Ok(PoolConfig)` - The options with defaults applied for anything not set
+    /// * `Err(NanoServiceError)` - If a variable is set but is not valid
+    pub fn from_config<Y: GetConfigVariable>() -> Result<Self, NanoServiceError> {
+        let max_connections = match read_count::<Y>("TO_DO_MAX_CONNECTIONS")? {
+            Some(0) => return Err(invalid_pool_variable("TO_DO_MAX_CONNECTIONS", "0", "at least 1")),
+            Some(max_connections) => max_connections,
+            None => 5
+        };
+        let acquire_timeout = read_duration::<Y>("DB_ACQUIRE_TIMEOUT")?.unwrap_or(DEFAULT_ACQUIRE_TIMEOUT);
+        let idle_timeout = read_duration::<Y>("DB_IDLE_TIMEOUT")?.unwrap_or(DEFAULT_IDLE_TIMEOUT);
+        let max_lifetime = read_duration::<Y>("DB_MAX_LIFETIME")?.unwrap_or(DEFAULT_MAX_LIFETIME);
+        let statement_cache_size = read_count::<Y>("DB_STATEMENT_CACHE_SIZE")?
+            .map(|size| size as usize)
+            .unwrap_or(DEFAULT_STATEMENT_CACHE_SIZE);
+        Ok(PoolConfig {
+            max_connections,
+            acquire_timeout,
+            idle_timeout: Some(idle_timeout).filter(|timeout| !timeout.is_zero()),
+            max_lifetime: Some(max_lifetime).filter(|lifetime| !lifetime.is_zero()),
+            statement_cache_size,
+        })
+    }
+
+    /// The pool options for these settings.
+    pub fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .acquire_timeout(self.acquire_timeout)
+            .idle_timeout(self.idle_timeout)
+            .max_lifetime(self.max_lifetime)
+    }
+}
+
+
+/// Reads the connection string and pool options through `Y`.
+fn pool_settings<Y: GetConfigVariable>() -> Result<(PgConnectOptions, PoolConfig), NanoServiceError> {
+    let connection_string = Y::get_config_variable("DB_URL".to_string()).map_err(|_| NanoServiceError::new(
+        "DB_URL is not set so the database cannot be reached".to_string(),
+        NanoServiceErrorStatus::Unknown
+    ))?;
+    let pool_config = PoolConfig::from_config::<Y>()?;
+    let connect_options = PgConnectOptions::from_str(&connection_string)
+        .map_err(|e| NanoServiceError::new(
+            format!("DB_URL is not a valid PostgreSQL connection string: {}", e),
+            NanoServiceErrorStatus::Unknown
+        ))?
+        .statement_cache_capacity(pool_config.statement_cache_size);
+    Ok((connect_options, pool_config))
+}
+
+
+static POOL: OnceLock<PgPool> = OnceLock::new();
+
+
+/// Creates the pool and opens its first connection.
 ///
-/// # Details
-/// - Uses the `DB_URL` environment variable to determine the connection string.
-/// - Allows configuring the maximum number of connections via the `TO_DO_MAX_CONNECTIONS` environment variable.
-/// - Falls back to a default of 5 maximum connections if the environment variable is not set.
+/// # Returns
+/// * `Ok(PoolConfig)` - The options the pool was created with
+/// * `Err(NanoServiceError)` - If the config is not valid, the database cannot be reached, or the
+///   pool was already created
+pub async fn init_pool<Y: GetConfigVariable>() -> Result<PoolConfig, NanoServiceError> {
+    let (connect_options, pool_config) = pool_settings::<Y>()?;
+    let pool = pool_config.pool_options()
+        .connect_with(connect_options)
+        .await
+        .map_err(|e| NanoServiceError::new(
+            format!("Failed to connect to the database: {}", e),
+            NanoServiceErrorStatus::Unknown
+        ))?;
+    POOL.set(pool).map_err(|_| NanoServiceError::new(
+        "The database pool was already created, init_pool has to be called before the pool is used".to_string(),
+        NanoServiceErrorStatus::Unknown
+    ))?;
+    Ok(pool_config)
+}
+
+
+/// Closes the pool, waiting for the connections in use to be returned.
+///
+/// # Notes
+/// Statements run after the pool is closed fail, so this is only called once the server has stopped.
+pub async fn close_pool() {
+    if let Some(pool) = POOL.get() {
+        pool.close().await;
+    }
+}
+
+
+/// A handle to the PostgreSQL connection pool, dereferenced as a `PgPool`.
 ///
 /// # Panics
-/// - If the `DB_URL` environment variable is not set or the connection pool cannot be created.
-pub static SQLX_POSTGRES_POOL: Lazy<PgPool> = Lazy::new(|| {
-    // Retrieve the database connection string from the environment.
-    let connection_string = env::var("DB_URL").unwrap();
-
-    // Determine the maximum number of connections from the environment.
-    let max_connections = match std::env::var("TO_DO_MAX_CONNECTIONS") {
-        Ok(val) => val,
-        Err(_) => "5".to_string(), // Default to 5 if not set.
-    }
-    .trim()
-    .parse::<u32>()
-    .map_err(|_e| "Could not parse max connections".to_string())
-    .unwrap();
-
-    // Configure the connection pool.
-    let pool = PgPoolOptions::new()
-        .max_connections(max_connections);
-
-    // Establish the connection pool lazily.
-    pool.connect_lazy(&connection_string)
-        .expect("Failed to create pool")
-});
+/// - If the pool was not created with `init_pool` and `DB_URL` or the pool options are not valid.
+pub struct SqlxPostgresPool;
+
+impl Deref for SqlxPostgresPool {
+    type Target = PgPool;
+
+    fn deref(&self) -> &PgPool {
+        POOL.get_or_init(|| {
+            let (connect_options, pool_config) = pool_settings::<SecretsConfig>().unwrap_or_else(|e| panic!("{}", e.message));
+            pool_config.pool_options().connect_lazy_with(connect_options)
+        })
+    }
+}
+
+
+/// The PostgreSQL connection pool every transaction runs against.
+pub static SQLX_POSTGRES_POOL: SqlxPostgresPool = SqlxPostgresPool;
+
+
+/// Builds an `ILIKE` pattern matching any value containing `text`, with `%`, `_` and `\` in the
+/// text matched literally.
+pub fn contains_pattern(text: &str) -> String {
+    format!("%{}%", text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "TO_DO_MAX_CONNECTIONS" => Ok("20".to_string()),
+                "DB_ACQUIRE_TIMEOUT" => Ok("5s".to_string()),
+                "DB_IDLE_TIMEOUT" => Ok("0".to_string()),
+                "DB_STATEMENT_CACHE_SIZE" => Ok("0".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    struct EmptyConfig;
+
+    impl GetConfigVariable for EmptyConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            Err(NanoServiceError::new(variable, NanoServiceErrorStatus::Unknown))
+        }
+    }
+
+    struct BadConfig;
+
+    impl GetConfigVariable for BadConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "DB_URL" => Ok("postgres://localhost/to_do".to_string()),
+                "DB_ACQUIRE_TIMEOUT" => Ok("soon".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    #[test]
+    fn test_pool_config() {
+        assert_eq!(PoolConfig::from_config::<EmptyConfig>().unwrap(), PoolConfig {
+            max_connections: 5,
+            acquire_timeout: DEFAULT_ACQUIRE_TIMEOUT,
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+            max_lifetime: Some(DEFAULT_MAX_LIFETIME),
+            statement_cache_size: DEFAULT_STATEMENT_CACHE_SIZE,
+        });
+        assert_eq!(PoolConfig::from_config::<MockConfig>().unwrap(), PoolConfig {
+            max_connections: 20,
+            acquire_timeout: Duration::from_secs(5),
+            idle_timeout: None,
+            max_lifetime: Some(DEFAULT_MAX_LIFETIME),
+            statement_cache_size: 0,
+        });
+        assert_eq!(
+            PoolConfig::from_config::<BadConfig>().unwrap_err().message,
+            "DB_ACQUIRE_TIMEOUT is set to 'soon' which is not a duration"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_init_pool_reports_bad_config() {
+        assert_eq!(
+            init_pool::<EmptyConfig>().await.unwrap_err().message,
+            "DB_URL is not set so the database cannot be reached"
+        );
+        assert!(init_pool::<BadConfig>().await.unwrap_err().message.starts_with("DB_ACQUIRE_TIMEOUT"));
+        assert!(POOL.get().is_none());
+    }
+
+    #[test]
+    fn test_contains_pattern() {
+        assert_eq!(contains_pattern("ada"), "%ada%");
+        assert_eq!(contains_pattern("100%_\\"), "%100\\%\\_\\\\%");
+    }
+}
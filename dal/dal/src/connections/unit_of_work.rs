@@ -0,0 +1,79 @@
+//! Runs several statements as a single database transaction.
+//!
+//! # Overview
+//! Each transaction trait normally runs its statement straight against the pool, so a crash between
+//! two calls can leave half of an operation behind, e.g. a user without a role. `WithTransaction`
+//! hands a `sqlx::Transaction` to a closure, committing if the closure succeeds and rolling back if
+//! it fails, so combined transactions such as `CreateUserWithRolePermission` are atomic.
+//!
+//! # Notes
+//! - The statements used by combined transactions are written against a generic `PgExecutor` so the
+//!   same SQL runs against the pool for the single step traits and against the transaction here.
+use std::future::Future;
+use std::pin::Pin;
+use sqlx::{Postgres, Transaction};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::log_limited;
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+
+
+/// An open PostgreSQL transaction.
+pub type PgTransaction = Transaction<'static, Postgres>;
+
+/// The future returned by the work run inside a transaction, borrowing the transaction for `'c`.
+pub type TransactionFuture<'c, T> = Pin<Box<dyn Future<Output = Result<T, NanoServiceError>> + Send + 'c>>;
+
+
+/// Provides a transaction handle to a unit of work.
+pub trait WithTransaction {
+
+    /// Runs `work` inside a transaction.
+    ///
+    /// # Arguments
+    /// - `work`: The statements to run, given the transaction to run them against.
+    ///
+    /// # Returns
+    /// - `Ok(T)`: The output of `work` once the transaction is committed.
+    /// - `Err(NanoServiceError)`: The error from `work`, after rolling back, or a failure to begin or commit.
+    fn with_transaction<T, F>(work: F) -> impl Future<Output = Result<T, NanoServiceError>> + Send
+    where
+        T: Send,
+        F: for<'c> FnOnce(&'c mut PgTransaction) -> TransactionFuture<'c, T> + Send;
+}
+
+
+fn transaction_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {} transaction: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+impl WithTransaction for SqlxPostGresDescriptor {
+
+    async fn with_transaction<T, F>(work: F) -> Result<T, NanoServiceError>
+    where
+        T: Send,
+        F: for<'c> FnOnce(&'c mut PgTransaction) -> TransactionFuture<'c, T> + Send
+    {
+        let mut transaction = SQLX_POSTGRES_POOL.begin()
+            .await
+            .map_err(|e| transaction_error("begin", e))?;
+        match work(&mut transaction).await {
+            Ok(output) => {
+                transaction.commit()
+                    .await
+                    .map_err(|e| transaction_error("commit", e))?;
+                Ok(output)
+            },
+            Err(e) => {
+                // dropping the transaction would also roll it back, this just surfaces failures sooner
+                if let Err(rollback_error) = transaction.rollback().await {
+                    log_limited!("dal", "{}", transaction_error("roll back", rollback_error).message);
+                }
+                Err(e)
+            }
+        }
+    }
+}
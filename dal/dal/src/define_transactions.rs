@@ -64,7 +64,7 @@ mod tests {
         assert_eq!(outcome, 1);
 
         let outcome = PostgresHandle::delete(1).await.unwrap();
-        assert_eq!(outcome, true);
+        assert!(outcome);
 
     }
 
@@ -0,0 +1,90 @@
+//! Implements the device transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::chrono::NaiveDateTime;
+use kernel::devices::{Device, NewDevice};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::devices::tx_definitions::{ListDevices, RecordDeviceLogin, RenameDevice, RevokeDevice, TrustDevice};
+
+
+fn device_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(format!("Failed to {}: {}", action, e), NanoServiceErrorStatus::Unknown)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RecordDeviceLogin, record_device_login)]
+async fn record_device_login(device: NewDevice) -> Result<Device, NanoServiceError> {
+    let query = r#"
+        INSERT INTO devices (user_id, fingerprint, user_agent)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, fingerprint) DO UPDATE SET last_seen_at = NOW()
+        RETURNING *
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, Device>(query)
+            .bind(device.user_id)
+            .bind(&device.fingerprint)
+            .bind(&device.user_agent)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| device_error("record device login", e))
+}
+
+
+/// Lists the user's devices, the most recently used first.
+#[impl_transaction(SqlxPostGresDescriptor, ListDevices, list_devices)]
+async fn list_devices(user_id: i32) -> Result<Vec<Device>, NanoServiceError> {
+    retry_transient(|| {
+        sqlx::query_as::<_, Device>("SELECT * FROM devices WHERE user_id = $1 ORDER BY last_seen_at DESC, id DESC")
+            .bind(user_id)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| device_error("list devices", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RenameDevice, rename_device)]
+async fn rename_device(user_id: i32, id: i32, name: Option<String>) -> Result<Option<Device>, NanoServiceError> {
+    retry_write(|| {
+        sqlx::query_as::<_, Device>("UPDATE devices SET name = $3 WHERE user_id = $1 AND id = $2 RETURNING *")
+            .bind(user_id)
+            .bind(id)
+            .bind(&name)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| device_error("rename device", e))
+}
+
+
+/// Trusts the device until `trusted_until`, or stops trusting it with `None`.
+#[impl_transaction(SqlxPostGresDescriptor, TrustDevice, trust_device)]
+async fn trust_device(user_id: i32, id: i32, trusted_until: Option<NaiveDateTime>) -> Result<Option<Device>, NanoServiceError> {
+    retry_write(|| {
+        sqlx::query_as::<_, Device>("UPDATE devices SET trusted_until = $3 WHERE user_id = $1 AND id = $2 RETURNING *")
+            .bind(user_id)
+            .bind(id)
+            .bind(trusted_until)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| device_error("trust device", e))
+}
+
+
+/// Deletes the device, returning it so its sessions can be ended.
+#[impl_transaction(SqlxPostGresDescriptor, RevokeDevice, revoke_device)]
+async fn revoke_device(user_id: i32, id: i32) -> Result<Option<Device>, NanoServiceError> {
+    retry_write(|| {
+        sqlx::query_as::<_, Device>("DELETE FROM devices WHERE user_id = $1 AND id = $2 RETURNING *")
+            .bind(user_id)
+            .bind(id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| device_error("revoke device", e))
+}
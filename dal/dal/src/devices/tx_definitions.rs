@@ -0,0 +1,19 @@
+//! Defines transaction traits for interacting with the `devices` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `RecordDeviceLogin` inserts the device the first time the user logs in from it, and otherwise
+//!   records the login on the existing row, keeping its name and trust.
+//! - The other transactions only touch the user's own devices, so a device of another user is not found.
+use kernel::chrono::NaiveDateTime;
+use kernel::devices::{Device, NewDevice};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    RecordDeviceLogin => record_device_login(device: NewDevice) -> Device,
+    ListDevices => list_devices(user_id: i32) -> Vec<Device>,
+    RenameDevice => rename_device(user_id: i32, id: i32, name: Option<String>) -> Option<Device>,
+    TrustDevice => trust_device(user_id: i32, id: i32, trusted_until: Option<NaiveDateTime>) -> Option<Device>,
+    RevokeDevice => revoke_device(user_id: i32, id: i32) -> Option<Device>
+);
@@ -0,0 +1,146 @@
+//! Implements the email outbox transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::email_outbox::{NewOutboxEmail, OutboxEmail, OutboxStatus};
+use kernel::metering::UsageMetric;
+use kernel::chrono::NaiveDateTime;
+use sqlx::PgExecutor;
+use sqlx::types::Json;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::retry_write;
+use crate::email_outbox::tx_definitions::{
+    EnqueueEmail,
+    ClaimDueEmails,
+    MarkEmailSent,
+    RecordEmailFailure
+};
+
+
+#[impl_transaction(SqlxPostGresDescriptor, EnqueueEmail, enqueue_email)]
+async fn enqueue_email(email: NewOutboxEmail) -> Result<OutboxEmail, NanoServiceError> {
+    retry_write(|| insert_outbox_email(&*SQLX_POSTGRES_POOL, &email))
+        .await
+        .map_err(queue_error)
+}
+
+
+/// Queues an email using the given executor, either the pool or an open transaction, so the
+/// email can be written together with the rows it is about.
+pub(crate) async fn insert_outbox_email<'e, E: PgExecutor<'e>>(executor: E, email: &NewOutboxEmail) -> Result<OutboxEmail, sqlx::Error> {
+    let query = r#"
+        INSERT INTO email_outbox (template, send_at, next_attempt_at)
+        VALUES ($1, COALESCE($2, NOW()), COALESCE($2, NOW()))
+        RETURNING id, template, status, attempts, next_attempt_at, last_error, date_created, date_sent, send_at
+    "#;
+
+    sqlx::query_as::<_, OutboxEmail>(query)
+        .bind(Json(&email.template))
+        .bind(email.send_at)
+        .fetch_one(executor)
+        .await
+}
+
+
+/// Maps a failed insert into the outbox.
+pub(crate) fn queue_error(e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to queue email: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+/// Claims up to `limit` due emails by pushing their `next_attempt_at` forward, so another worker
+/// polling at the same time skips them. If the worker dies mid-send the email is retried once the
+/// lease runs out.
+#[impl_transaction(SqlxPostGresDescriptor, ClaimDueEmails, claim_due_emails)]
+async fn claim_due_emails(limit: i64) -> Result<Vec<OutboxEmail>, NanoServiceError> {
+    let query = r#"
+        UPDATE email_outbox
+        SET next_attempt_at = NOW() + INTERVAL '5 minutes'
+        WHERE id IN (
+            SELECT id FROM email_outbox
+            WHERE status = $1 AND next_attempt_at <= NOW() AND send_at <= NOW()
+            ORDER BY next_attempt_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, template, status, attempts, next_attempt_at, last_error, date_created, date_sent, send_at
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, OutboxEmail>(query)
+            .bind(OutboxStatus::Pending)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to claim due emails: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+/// Marks an email as sent and counts it in today's `emails_sent` usage in the same statement.
+#[impl_transaction(SqlxPostGresDescriptor, MarkEmailSent, mark_email_sent)]
+async fn mark_email_sent(id: i32) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        WITH sent AS (
+            UPDATE email_outbox
+            SET status = $1, attempts = attempts + 1, date_sent = NOW(), last_error = NULL
+            WHERE id = $2
+            RETURNING id
+        ), counted AS (
+            INSERT INTO usage_counters (metric, day, quantity)
+            SELECT $3, NOW()::DATE, COUNT(*) FROM sent HAVING COUNT(*) > 0
+            ON CONFLICT (metric, day) DO UPDATE SET quantity = usage_counters.quantity + EXCLUDED.quantity
+        )
+        SELECT COUNT(*) FROM sent
+    "#;
+
+    let updated: i64 = retry_write(|| {
+        sqlx::query_scalar(query)
+            .bind(OutboxStatus::Sent)
+            .bind(id)
+            .bind(UsageMetric::EmailsSent)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to mark email as sent: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(updated > 0)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RecordEmailFailure, record_email_failure)]
+async fn record_email_failure(id: i32, error: String, next_attempt_at: Option<NaiveDateTime>) -> Result<bool, NanoServiceError> {
+    let status = match next_attempt_at {
+        Some(_) => OutboxStatus::Pending,
+        None => OutboxStatus::Failed
+    };
+    let query = r#"
+        UPDATE email_outbox
+        SET status = $1, attempts = attempts + 1, last_error = $2, next_attempt_at = COALESCE($3, next_attempt_at)
+        WHERE id = $4
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&status)
+            .bind(&error)
+            .bind(next_attempt_at)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to record email failure: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,20 @@
+//! Defines transaction traits for interacting with the `email_outbox` table.
+//!
+//! # Overview
+//! This file uses the `define_dal_transactions` macro to create traits for queueing emails,
+//! claiming the emails that are due to be sent, and recording the outcome of each attempt.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `next_attempt_at` of `None` in `RecordEmailFailure` marks the email as permanently failed.
+use kernel::email_outbox::{NewOutboxEmail, OutboxEmail};
+use kernel::chrono::NaiveDateTime;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    EnqueueEmail => enqueue_email(email: NewOutboxEmail) -> OutboxEmail,
+    ClaimDueEmails => claim_due_emails(limit: i64) -> Vec<OutboxEmail>,
+    MarkEmailSent => mark_email_sent(id: i32) -> bool,
+    RecordEmailFailure => record_email_failure(id: i32, error: String, next_attempt_at: Option<NaiveDateTime>) -> bool
+);
@@ -0,0 +1,295 @@
+//! Implements the export job transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::export_jobs::{ExportJob, ExportJobStatus, ExportKind, ExportedUser, NewExportJob};
+use kernel::to_do_items::Todo;
+use kernel::chrono::NaiveDateTime;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::export_jobs::tx_definitions::{
+    CreateExportJob,
+    GetExportJob,
+    ClaimExportJob,
+    RecordExportProgress,
+    CompleteExportJob,
+    FailExportJob,
+    ListExpiredExports,
+    MarkExportExpired,
+    CountExportRows,
+    ListUsersForExport,
+    ListTodosForExport
+};
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CreateExportJob, create_export_job)]
+async fn create_export_job(job: NewExportJob) -> Result<ExportJob, NanoServiceError> {
+    let query = r#"
+        INSERT INTO export_jobs (requested_by, kind, format)
+        VALUES ($1, $2, $3)
+        RETURNING id, requested_by, kind, format, status, total_rows, exported_rows, object_key, error,
+                  date_created, updated_at, completed_at, expires_at
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, ExportJob>(query)
+            .bind(job.requested_by)
+            .bind(job.kind)
+            .bind(&job.format)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to queue export: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetExportJob, get_export_job)]
+async fn get_export_job(id: i32) -> Result<Option<ExportJob>, NanoServiceError> {
+    let query = r#"
+        SELECT id, requested_by, kind, format, status, total_rows, exported_rows, object_key, error,
+               date_created, updated_at, completed_at, expires_at
+        FROM export_jobs
+        WHERE id = $1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, ExportJob>(query)
+            .bind(id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to get export: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+/// Claims the oldest pending export, or a running export that has not recorded any progress for
+/// ten minutes because its worker died, and starts it again from the beginning.
+#[impl_transaction(SqlxPostGresDescriptor, ClaimExportJob, claim_export_job)]
+async fn claim_export_job() -> Result<Option<ExportJob>, NanoServiceError> {
+    let query = r#"
+        UPDATE export_jobs
+        SET status = $1, exported_rows = 0, updated_at = NOW()
+        WHERE id IN (
+            SELECT id FROM export_jobs
+            WHERE status = $2 OR (status = $1 AND updated_at <= NOW() - INTERVAL '10 minutes')
+            ORDER BY date_created
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, requested_by, kind, format, status, total_rows, exported_rows, object_key, error,
+                  date_created, updated_at, completed_at, expires_at
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, ExportJob>(query)
+            .bind(ExportJobStatus::Running)
+            .bind(ExportJobStatus::Pending)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to claim export: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RecordExportProgress, record_export_progress)]
+async fn record_export_progress(id: i32, total_rows: i64, exported_rows: i64) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        UPDATE export_jobs
+        SET total_rows = $1, exported_rows = $2, updated_at = NOW()
+        WHERE id = $3 AND status = $4
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(total_rows)
+            .bind(exported_rows)
+            .bind(id)
+            .bind(ExportJobStatus::Running)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to record export progress: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CompleteExportJob, complete_export_job)]
+async fn complete_export_job(id: i32, object_key: String, expires_at: NaiveDateTime) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        UPDATE export_jobs
+        SET status = $1, object_key = $2, expires_at = $3, completed_at = NOW(), updated_at = NOW(), error = NULL
+        WHERE id = $4
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(ExportJobStatus::Complete)
+            .bind(&object_key)
+            .bind(expires_at)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to complete export: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, FailExportJob, fail_export_job)]
+async fn fail_export_job(id: i32, error: String) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        UPDATE export_jobs
+        SET status = $1, error = $2, updated_at = NOW()
+        WHERE id = $3
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(ExportJobStatus::Failed)
+            .bind(&error)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to record export failure: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+/// Lists up to `limit` complete exports whose `expires_at` has passed, oldest first.
+#[impl_transaction(SqlxPostGresDescriptor, ListExpiredExports, list_expired_exports)]
+async fn list_expired_exports(limit: i64) -> Result<Vec<ExportJob>, NanoServiceError> {
+    let query = r#"
+        SELECT id, requested_by, kind, format, status, total_rows, exported_rows, object_key, error,
+               date_created, updated_at, completed_at, expires_at
+        FROM export_jobs
+        WHERE status = $1 AND expires_at <= NOW()
+        ORDER BY expires_at
+        LIMIT $2
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, ExportJob>(query)
+            .bind(ExportJobStatus::Complete)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to list expired exports: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, MarkExportExpired, mark_export_expired)]
+async fn mark_export_expired(id: i32) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        UPDATE export_jobs
+        SET status = $1, object_key = NULL, updated_at = NOW()
+        WHERE id = $2
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(ExportJobStatus::Expired)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to mark export as expired: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CountExportRows, count_export_rows)]
+async fn count_export_rows(kind: ExportKind) -> Result<i64, NanoServiceError> {
+    let query = match kind {
+        ExportKind::Users => "SELECT COUNT(*) FROM users",
+        ExportKind::Todos => "SELECT COUNT(*) FROM todos",
+        ExportKind::AuditLog => "SELECT COUNT(*) FROM audit_log",
+    };
+
+    retry_transient(|| {
+        sqlx::query_scalar(query)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to count {} to export: {}", kind.as_str(), e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, ListUsersForExport, list_users_for_export)]
+async fn list_users_for_export(after_id: Option<i32>, limit: i64) -> Result<Vec<ExportedUser>, NanoServiceError> {
+    let query = r#"
+        SELECT id, username, email, first_name, last_name, user_role, confirmed, blocked, date_created, last_logged_in
+        FROM users
+        WHERE $1::INTEGER IS NULL OR id > $1
+        ORDER BY id
+        LIMIT $2
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, ExportedUser>(query)
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to list users to export: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, ListTodosForExport, list_todos_for_export)]
+async fn list_todos_for_export(after_id: Option<i32>, limit: i64) -> Result<Vec<Todo>, NanoServiceError> {
+    let query = r#"
+        SELECT id, name, due_date, assigned_by, assigned_to, description, date_assigned, date_finished, finished,
+               requires_review, pending_review, review_comment, priority, status
+        FROM todos
+        WHERE $1::INTEGER IS NULL OR id > $1
+        ORDER BY id
+        LIMIT $2
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Todo>(query)
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to list to-do items to export: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
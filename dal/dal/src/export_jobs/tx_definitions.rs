@@ -0,0 +1,30 @@
+//! Defines transaction traits for interacting with the `export_jobs` table.
+//!
+//! # Overview
+//! This file uses the `define_dal_transactions` macro to create traits for queueing exports, claiming
+//! them for a worker, recording their progress and outcome, and expiring them. It also defines the
+//! paged reads the worker writes exports from.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - The paged reads are keyset paged on `id` ascending, so rows added during an export are picked up
+//!   rather than shifting the pages.
+use kernel::export_jobs::{ExportJob, ExportKind, ExportedUser, NewExportJob};
+use kernel::to_do_items::Todo;
+use kernel::chrono::NaiveDateTime;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreateExportJob => create_export_job(job: NewExportJob) -> ExportJob,
+    GetExportJob => get_export_job(id: i32) -> Option<ExportJob>,
+    ClaimExportJob => claim_export_job() -> Option<ExportJob>,
+    RecordExportProgress => record_export_progress(id: i32, total_rows: i64, exported_rows: i64) -> bool,
+    CompleteExportJob => complete_export_job(id: i32, object_key: String, expires_at: NaiveDateTime) -> bool,
+    FailExportJob => fail_export_job(id: i32, error: String) -> bool,
+    ListExpiredExports => list_expired_exports(limit: i64) -> Vec<ExportJob>,
+    MarkExportExpired => mark_export_expired(id: i32) -> bool,
+    CountExportRows => count_export_rows(kind: ExportKind) -> i64,
+    ListUsersForExport => list_users_for_export(after_id: Option<i32>, limit: i64) -> Vec<ExportedUser>,
+    ListTodosForExport => list_todos_for_export(after_id: Option<i32>, limit: i64) -> Vec<Todo>
+);
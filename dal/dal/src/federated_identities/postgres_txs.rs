@@ -0,0 +1,74 @@
+//! Implements the federated identity transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::oidc::{FederatedIdentity, FederatedSubject};
+use kernel::role_permissions::NewRolePermission;
+use kernel::users::{NewUser, User};
+use sqlx::PgExecutor;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::retry_transient;
+use crate::connections::unit_of_work::WithTransaction;
+use crate::role_permissions::postgres_tsx::insert_role_permission;
+use crate::users::postgres_txs::insert_user;
+use crate::federated_identities::tx_definitions::{GetFederatedIdentity, LinkFederatedIdentity, ProvisionFederatedUser};
+
+
+fn federated_identity_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(format!("Failed to {}: {}", action, e), NanoServiceErrorStatus::Unknown)
+}
+
+
+/// Links the subject to the user using the given executor, either the pool or an open transaction.
+async fn insert_federated_identity<'e, E: PgExecutor<'e>>(
+    executor: E,
+    user_id: i32,
+    subject: FederatedSubject
+) -> Result<FederatedIdentity, NanoServiceError> {
+    let query = r#"
+        INSERT INTO federated_identities (user_id, provider, subject, email)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+    "#;
+
+    sqlx::query_as::<_, FederatedIdentity>(query)
+        .bind(user_id)
+        .bind(&subject.provider)
+        .bind(&subject.subject)
+        .bind(&subject.email)
+        .fetch_one(executor)
+        .await
+        .map_err(|e| federated_identity_error("link federated identity", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetFederatedIdentity, get_federated_identity)]
+async fn get_federated_identity(provider: String, subject: String) -> Result<Option<FederatedIdentity>, NanoServiceError> {
+    retry_transient(|| {
+        sqlx::query_as::<_, FederatedIdentity>("SELECT * FROM federated_identities WHERE provider = $1 AND subject = $2")
+            .bind(&provider)
+            .bind(&subject)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| federated_identity_error("get federated identity", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, LinkFederatedIdentity, link_federated_identity)]
+async fn link_federated_identity(user_id: i32, subject: FederatedSubject) -> Result<FederatedIdentity, NanoServiceError> {
+    insert_federated_identity(&*SQLX_POSTGRES_POOL, user_id, subject).await
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, ProvisionFederatedUser, provision_federated_user)]
+async fn provision_federated_user(user: NewUser, subject: FederatedSubject) -> Result<User, NanoServiceError> {
+    SqlxPostGresDescriptor::with_transaction(|transaction| Box::pin(async move {
+        let user = insert_user(&mut **transaction, user).await?;
+        insert_role_permission(&mut **transaction, NewRolePermission {
+            user_id: user.id,
+            role: user.user_role.clone(),
+        }).await?;
+        insert_federated_identity(&mut **transaction, user.id, subject).await?;
+        Ok(user)
+    })).await
+}
@@ -0,0 +1,16 @@
+//! Defines transaction traits for interacting with the `federated_identities` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `ProvisionFederatedUser` creates the user, their role permission and the link in one
+//!   transaction, so a failed login does not leave a user that cannot log in.
+use kernel::oidc::{FederatedIdentity, FederatedSubject};
+use kernel::users::{NewUser, User};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    GetFederatedIdentity => get_federated_identity(provider: String, subject: String) -> Option<FederatedIdentity>,
+    LinkFederatedIdentity => link_federated_identity(user_id: i32, subject: FederatedSubject) -> FederatedIdentity,
+    ProvisionFederatedUser => provision_federated_user(user: NewUser, subject: FederatedSubject) -> User
+);
@@ -0,0 +1,204 @@
+//! Dumps and restores deterministic snapshots of the test database.
+//!
+//! # Overview
+//! A snapshot is a plain SQL file with one `INSERT` per row, in id order, for every table in
+//! `FIXTURE_TABLES`. Restoring truncates those tables, replays the file and resets the id
+//! sequences, all in one transaction, so every integration test run starts from the same state.
+//! The canonical dataset lives in `fixtures/canonical.sql` and is loaded with `restore_canonical_fixtures`.
+//!
+//! ## Notes
+//! - `permissions`, `role_permission_grants`, `sla_policies`, `org_branding`, `org_plan`, `org_billing` and `organizations` are seeded by migrations so they are left alone.
+//! - `sla_warnings` is emptied by the cascade when `todos` is truncated and is never part of a snapshot.
+//! - `request_metrics` and `availability_rollups` hold server telemetry rather than test data and are never part of a snapshot.
+//! - `request_rate_limits` only holds short lived request counts and is never part of a snapshot.
+//! - `usage_counters`, `usage_active_users` and `usage_rollups` hold billing records rather than test data and are never part of a snapshot.
+//! - `billing_events` records the Stripe events already applied and is never part of a snapshot.
+//! - `login_events` is emptied by the cascade when `users` is truncated and is never part of a snapshot.
+//! - `legal_holds` is emptied by the cascade when `users` is truncated and is never part of a snapshot.
+//! - `webhook_deliveries` records the webhook deliveries already processed and is never part of a snapshot.
+//! - `export_jobs` is emptied by the cascade when `users` is truncated and is never part of a snapshot.
+//! - `backups` records the backups in the object store and is never part of a snapshot.
+//! - `webhooks` and `webhook_outbox` hold the registered webhooks and their deliveries rather than test data and are never part of a snapshot.
+//! - `federated_identities` is emptied by the cascade when `users` is truncated and is never part of a snapshot.
+//! - `devices` is emptied by the cascade when `users` is truncated and is never part of a snapshot.
+//! - `attachments` is emptied by the cascade when `todos` is truncated and is never part of a snapshot.
+//! - `todo_events` is emptied by the cascade when `todos` is truncated and is never part of a snapshot.
+//! - `projects`, `project_members` and `project_todos` are emptied by the cascade when `users` is truncated and are never part of a snapshot.
+//! - `api_keys` holds the keys issued to services rather than test data and is never part of a snapshot.
+//! - Every fixture user has the password `fixture-password`.
+use sqlx::Row;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::SQLX_POSTGRES_POOL;
+
+
+/// The tables held in a snapshot, ordered so that rows are inserted after the rows they reference.
+pub const FIXTURE_TABLES: [&str; 14] = [
+    "users",
+    "org_memberships",
+    "role_permissions",
+    "user_onboarding",
+    "rate_limit_entries",
+    "todos",
+    "tags",
+    "todo_tags",
+    "todo_comments",
+    "pending_notifications",
+    "email_outbox",
+    "audit_log",
+    "tombstones",
+    "moderation_decisions",
+];
+
+/// The canonical test dataset.
+pub const CANONICAL_FIXTURES: &str = include_str!("../fixtures/canonical.sql");
+
+/// The password shared by every user in the canonical dataset.
+pub const FIXTURE_PASSWORD: &str = "fixture-password";
+
+
+fn fixture_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+/// Builds the insert statement for a single row serialized as JSON.
+///
+/// # Notes
+/// `jsonb_populate_record` maps the JSON back onto the table's column types, so the statement
+/// does not need to know the columns and survives additive migrations.
+pub fn insert_statement(table: &str, row: &str) -> String {
+    format!(
+        "INSERT INTO {} SELECT * FROM jsonb_populate_record(NULL::{}, '{}');",
+        table, table, row.replace('\'', "''")
+    )
+}
+
+
+/// Dumps the current contents of the fixture tables as a snapshot.
+///
+/// # Returns
+/// * `Ok(String)` - The snapshot SQL, identical for identical data
+/// * `Err(NanoServiceError)` - If a table could not be read
+pub async fn dump_snapshot() -> Result<String, NanoServiceError> {
+    let mut snapshot = String::new();
+    for table in FIXTURE_TABLES {
+        let query = format!("SELECT to_jsonb(t)::text AS row FROM {} t ORDER BY id", table);
+        let rows = sqlx::query(&query)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+            .await
+            .map_err(|e| fixture_error(&format!("dump {}", table), e))?;
+        snapshot.push_str(&format!("-- {}\n", table));
+        for row in rows {
+            let row: String = row.get("row");
+            snapshot.push_str(&insert_statement(table, &row));
+            snapshot.push('\n');
+        }
+        snapshot.push('\n');
+    }
+    Ok(snapshot)
+}
+
+
+/// Replaces the contents of the fixture tables with a snapshot.
+///
+/// # Arguments
+/// * `snapshot` - The snapshot SQL produced by `dump_snapshot`
+///
+/// # Returns
+/// * `Ok(())` - If the snapshot was restored
+/// * `Err(NanoServiceError)` - If any statement failed, in which case nothing is changed
+pub async fn restore_snapshot(snapshot: &str) -> Result<(), NanoServiceError> {
+    let mut transaction = SQLX_POSTGRES_POOL.begin()
+        .await
+        .map_err(|e| fixture_error("start fixture transaction", e))?;
+
+    let truncate = format!("TRUNCATE {} RESTART IDENTITY CASCADE", FIXTURE_TABLES.join(", "));
+    sqlx::query(&truncate)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| fixture_error("truncate fixture tables", e))?;
+
+    sqlx::raw_sql(snapshot)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| fixture_error("restore snapshot", e))?;
+
+    // explicit ids do not advance the sequences, so they are moved past the restored rows
+    for table in FIXTURE_TABLES {
+        let reset = format!(
+            "SELECT setval(pg_get_serial_sequence('{}', 'id'), COALESCE(MAX(id), 1), MAX(id) IS NOT NULL) FROM {}",
+            table, table
+        );
+        sqlx::query(&reset)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| fixture_error(&format!("reset {} sequence", table), e))?;
+    }
+
+    transaction.commit()
+        .await
+        .map_err(|e| fixture_error("commit fixtures", e))
+}
+
+
+/// Restores the canonical test dataset.
+pub async fn restore_canonical_fixtures() -> Result<(), NanoServiceError> {
+    restore_snapshot(CANONICAL_FIXTURES).await
+}
+
+
+/// Runs a test against the database in `DB_URL`, one at a time on a runtime shared by every such
+/// test, as the tests restore the same fixtures and the pool's connections only work on the runtime
+/// they were opened on.
+#[cfg(test)]
+pub(crate) fn run_database_test<F: std::future::Future<Output = ()>>(test: F) {
+    static RUNTIME: once_cell::sync::Lazy<tokio::runtime::Runtime> = once_cell::sync::Lazy::new(|| {
+        tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap()
+    });
+    static RUNNING: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _running = RUNNING.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    RUNTIME.block_on(test)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_statement_escapes_quotes() {
+        let statement = insert_statement("todos", r#"{"id": 1, "name": "Bob's task"}"#);
+        assert_eq!(
+            statement,
+            r#"INSERT INTO todos SELECT * FROM jsonb_populate_record(NULL::todos, '{"id": 1, "name": "Bob''s task"}');"#
+        );
+    }
+
+    #[test]
+    fn test_canonical_fixtures_only_touch_fixture_tables() {
+        let statements: Vec<&str> = CANONICAL_FIXTURES.lines()
+            .filter(|line| line.starts_with("INSERT"))
+            .collect();
+        assert!(!statements.is_empty());
+        for statement in statements {
+            let table = statement.split_whitespace().nth(2).unwrap();
+            assert!(FIXTURE_TABLES.contains(&table), "{} is not a fixture table", table);
+            assert!(statement.ends_with("');"));
+        }
+    }
+
+    #[test]
+    fn test_canonical_fixtures_are_in_table_order() {
+        let order: Vec<usize> = CANONICAL_FIXTURES.lines()
+            .filter(|line| line.starts_with("INSERT"))
+            .map(|line| {
+                let table = line.split_whitespace().nth(2).unwrap();
+                FIXTURE_TABLES.iter().position(|fixture| *fixture == table).unwrap()
+            })
+            .collect();
+        assert!(order.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+}
@@ -0,0 +1,86 @@
+//! Implements the legal hold transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+//!
+//! # Notes
+//! The holds are enforced where the data is removed, `delete_user` and the retention purge both
+//! check `legal_holds` in the same statement that deletes the rows.
+use dal_tx_impl::impl_transaction;
+use kernel::legal_holds::{NewLegalHold, LegalHold};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::legal_holds::tx_definitions::{PlaceLegalHold, ReleaseLegalHold, GetLegalHolds};
+
+
+fn legal_hold_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+/// Places a hold on the user, replacing the reason of a hold already in place.
+///
+/// # Returns
+/// - `Ok(Some(LegalHold))`: The hold.
+/// - `Ok(None)`: If there is no user with the ID.
+#[impl_transaction(SqlxPostGresDescriptor, PlaceLegalHold, place_legal_hold)]
+async fn place_legal_hold(hold: NewLegalHold) -> Result<Option<LegalHold>, NanoServiceError> {
+    let query = r#"
+        WITH placed AS (
+            INSERT INTO legal_holds (user_id, placed_by, reason)
+            SELECT id, $2, $3 FROM users WHERE id = $1
+            ON CONFLICT (user_id) DO UPDATE
+            SET placed_by = EXCLUDED.placed_by, reason = EXCLUDED.reason, date_created = NOW()
+            RETURNING user_id, placed_by, reason, date_created
+        )
+        SELECT placed.user_id, users.username, users.email, placed.placed_by, placed.reason, placed.date_created
+        FROM placed
+        JOIN users ON users.id = placed.user_id
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, LegalHold>(query)
+            .bind(hold.user_id)
+            .bind(hold.placed_by)
+            .bind(&hold.reason)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| legal_hold_error("place legal hold", e))
+}
+
+
+/// Releases the hold on the user, `false` if there was none.
+#[impl_transaction(SqlxPostGresDescriptor, ReleaseLegalHold, release_legal_hold)]
+async fn release_legal_hold(user_id: i32) -> Result<bool, NanoServiceError> {
+    let result = retry_write(|| {
+        sqlx::query("DELETE FROM legal_holds WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| legal_hold_error("release legal hold", e))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+
+/// Lists every hold in place, oldest first.
+#[impl_transaction(SqlxPostGresDescriptor, GetLegalHolds, get_legal_holds)]
+async fn get_legal_holds() -> Result<Vec<LegalHold>, NanoServiceError> {
+    let query = r#"
+        SELECT legal_holds.user_id, users.username, users.email, legal_holds.placed_by,
+               legal_holds.reason, legal_holds.date_created
+        FROM legal_holds
+        JOIN users ON users.id = legal_holds.user_id
+        ORDER BY legal_holds.date_created, legal_holds.user_id
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, LegalHold>(query)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| legal_hold_error("get legal holds", e))
+}
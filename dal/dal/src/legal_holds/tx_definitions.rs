@@ -0,0 +1,13 @@
+//! Defines transaction traits for interacting with the `legal_holds` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::legal_holds::{NewLegalHold, LegalHold};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    PlaceLegalHold => place_legal_hold(hold: NewLegalHold) -> Option<LegalHold>,
+    ReleaseLegalHold => release_legal_hold(user_id: i32) -> bool,
+    GetLegalHolds => get_legal_holds() -> Vec<LegalHold>
+);
@@ -1,7 +1,39 @@
 pub mod migrations;
+pub mod schema_compat;
+pub mod fixtures;
 pub mod connections;
 pub mod users;
 pub mod rate_limit_entries;
 pub mod role_permissions;
+pub mod permissions;
 pub mod define_transactions;
 pub mod to_do_items;
+pub mod tags;
+pub mod email_outbox;
+pub mod audit_log;
+pub mod sla;
+pub mod request_metrics;
+pub mod todo_comments;
+pub mod notifications;
+pub mod branding;
+pub mod request_rate_limits;
+pub mod onboarding;
+pub mod tombstones;
+pub mod sync;
+pub mod moderation;
+pub mod metering;
+pub mod plans;
+pub mod billing;
+pub mod retention;
+pub mod legal_holds;
+pub mod export_jobs;
+pub mod backups;
+pub mod webhook_deliveries;
+pub mod webhooks;
+pub mod api_keys;
+pub mod federated_identities;
+pub mod devices;
+pub mod attachments;
+pub mod todo_events;
+pub mod projects;
+pub mod organizations;
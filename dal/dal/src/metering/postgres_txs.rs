@@ -0,0 +1,114 @@
+//! Implements the metering transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::metering::{UsageMetric, UsageRollup};
+use kernel::chrono::NaiveDate;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::metering::tx_definitions::{RecordUsage, RecordActiveUser, RollupUsage, GetUsageRollups};
+
+
+fn metering_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+/// Adds to today's count of a metric, a negative quantity takes away from it.
+#[impl_transaction(SqlxPostGresDescriptor, RecordUsage, record_usage)]
+async fn record_usage(metric: UsageMetric, quantity: i64) -> Result<(), NanoServiceError> {
+    let query = r#"
+        INSERT INTO usage_counters (metric, day, quantity)
+        VALUES ($1, NOW()::DATE, $2)
+        ON CONFLICT (metric, day) DO UPDATE SET quantity = usage_counters.quantity + EXCLUDED.quantity
+    "#;
+
+    retry_write(|| {
+        sqlx::query(query)
+            .bind(metric)
+            .bind(quantity)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| metering_error("record usage", e))?;
+    Ok(())
+}
+
+
+/// Marks a user as active this month.
+#[impl_transaction(SqlxPostGresDescriptor, RecordActiveUser, record_active_user)]
+async fn record_active_user(user_id: i32) -> Result<(), NanoServiceError> {
+    let query = r#"
+        INSERT INTO usage_active_users (month, user_id)
+        VALUES (DATE_TRUNC('month', NOW())::DATE, $1)
+        ON CONFLICT DO NOTHING
+    "#;
+
+    retry_write(|| {
+        sqlx::query(query)
+            .bind(user_id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| metering_error("record active user", e))?;
+    Ok(())
+}
+
+
+/// Recomputes the rollup for the month starting on `month`.
+///
+/// # Notes
+/// Storage is the running total of every change up to the end of the month, the other metrics
+/// only count the month itself.
+#[impl_transaction(SqlxPostGresDescriptor, RollupUsage, rollup_usage)]
+async fn rollup_usage(month: NaiveDate) -> Result<UsageRollup, NanoServiceError> {
+    let query = r#"
+        INSERT INTO usage_rollups (month, active_users, emails_sent, storage_bytes, rolled_up_at)
+        SELECT
+            $1,
+            (SELECT COUNT(*) FROM usage_active_users WHERE month = $1),
+            (SELECT COALESCE(SUM(quantity), 0)::BIGINT FROM usage_counters
+                WHERE metric = $2 AND day >= $1 AND day < $1 + INTERVAL '1 month'),
+            (SELECT COALESCE(SUM(quantity), 0)::BIGINT FROM usage_counters
+                WHERE metric = $3 AND day < $1 + INTERVAL '1 month'),
+            NOW()
+        ON CONFLICT (month) DO UPDATE SET
+            active_users = EXCLUDED.active_users,
+            emails_sent = EXCLUDED.emails_sent,
+            storage_bytes = EXCLUDED.storage_bytes,
+            rolled_up_at = EXCLUDED.rolled_up_at
+        RETURNING month, active_users, emails_sent, storage_bytes, rolled_up_at
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, UsageRollup>(query)
+            .bind(month)
+            .bind(UsageMetric::EmailsSent)
+            .bind(UsageMetric::StorageBytes)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| metering_error("roll up usage", e))
+}
+
+
+/// Gets the monthly rollups from `since` onwards, oldest first.
+#[impl_transaction(SqlxPostGresDescriptor, GetUsageRollups, get_usage_rollups)]
+async fn get_usage_rollups(since: NaiveDate) -> Result<Vec<UsageRollup>, NanoServiceError> {
+    let query = r#"
+        SELECT month, active_users, emails_sent, storage_bytes, rolled_up_at
+        FROM usage_rollups
+        WHERE month >= $1
+        ORDER BY month
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, UsageRollup>(query)
+            .bind(since)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| metering_error("get usage rollups", e))
+}
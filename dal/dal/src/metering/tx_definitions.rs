@@ -0,0 +1,16 @@
+//! Defines transaction traits for the `usage_counters`, `usage_active_users` and `usage_rollups` tables.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - Emails sent are counted by `MarkEmailSent` in the same statement that marks the email as sent.
+use kernel::metering::{UsageMetric, UsageRollup};
+use kernel::chrono::NaiveDate;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    RecordUsage => record_usage(metric: UsageMetric, quantity: i64) -> (),
+    RecordActiveUser => record_active_user(user_id: i32) -> (),
+    RollupUsage => rollup_usage(month: NaiveDate) -> UsageRollup,
+    GetUsageRollups => get_usage_rollups(since: NaiveDate) -> Vec<UsageRollup>
+);
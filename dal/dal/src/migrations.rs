@@ -1,12 +1,251 @@
-//! Defines the migration functions for databases.
+//! Runs, reverts and reports the versioned migrations for the database.
+//!
+//! # Overview
+//! Each migration in `migrations/` is a pair of scripts, `<version>_<description>.up.sql` and
+//! `<version>_<description>.down.sql`, embedded into the binary by `sqlx::migrate!`. sqlx records
+//! every version it applies in `_sqlx_migrations` with a checksum of the up script, so
+//! `migration_status` can report which migrations are applied, pending, or were edited after they
+//! were applied.
+//!
+//! # Startup
+//! Servers apply pending migrations on startup unless `AUTO_MIGRATE` is `false`. Production turns
+//! it off and runs `migrations run` as a deploy step, so a server never changes the schema itself
+//! and refuses to start with `ensure_migrations_applied` if the deploy step was missed.
+//!
+//! # Notes
+//! - Versions in `_sqlx_migrations` that this binary does not know are ignored, so an older binary
+//!   can start against a database migrated by a newer one.
+//! - Down scripts drop what their up script created, including the data in it.
+use std::fmt;
+use kernel::chrono::{DateTime, Utc};
+use sqlx::migrate::Migrator;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 use crate::connections::sqlx_postgres::SQLX_POSTGRES_POOL;
 
 
-/// Runs a set of embedded migrations for a specific database.
-pub async fn run_migrations() {
+/// The migrations embedded from `migrations/`.
+pub static MIGRATOR: Migrator = Migrator {
+    ignore_missing: true,
+    ..sqlx::migrate!("./migrations")
+};
+
+
+fn migration_error(action: &str, e: impl fmt::Display) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown
+    )
+}
+
+
+/// Whether a migration has been applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrationState {
+    /// Applied with the up script this binary has.
+    Applied,
+    /// Not applied yet.
+    Pending,
+    /// Applied, but the up script has changed since.
+    Modified,
+    /// Applied, but this binary does not have the migration.
+    Unknown,
+}
+
+impl MigrationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "modified",
+            MigrationState::Unknown => "unknown",
+        }
+    }
+}
+
+
+/// A migration as recorded in `_sqlx_migrations`.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub checksum: Vec<u8>,
+    pub installed_on: DateTime<Utc>,
+}
+
+
+/// The state of a migration in the database.
+///
+/// # Fields
+/// * `version` - The version in the file name.
+/// * `description` - The description in the file name.
+/// * `state` - Whether it has been applied.
+/// * `installed_on` - When it was applied, `None` if it is pending.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+    pub installed_on: Option<DateTime<Utc>>,
+}
+
+impl fmt::Display for MigrationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let installed_on = self.installed_on
+            .map(|installed_on| installed_on.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        write!(f, "{:<16}{:<10}{:<21}{}", self.version, self.state.as_str(), installed_on, self.description)
+    }
+}
+
+
+/// Compares the migrations in `migrator` with those recorded as applied.
+///
+/// # Arguments
+/// * `migrator` - The migrations this binary has.
+/// * `applied` - The rows of `_sqlx_migrations`.
+///
+/// # Returns
+/// * `Vec<MigrationStatus>` - Every migration in either, ordered by version.
+pub fn migration_statuses(migrator: &Migrator, applied: &[AppliedMigration]) -> Vec<MigrationStatus> {
+    let mut statuses: Vec<MigrationStatus> = migrator.iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| {
+            let record = applied.iter().find(|record| record.version == migration.version);
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state: match record {
+                    None => MigrationState::Pending,
+                    Some(record) if record.checksum == migration.checksum.as_ref() => MigrationState::Applied,
+                    Some(_) => MigrationState::Modified,
+                },
+                installed_on: record.map(|record| record.installed_on),
+            }
+        })
+        .collect();
+    statuses.extend(applied.iter()
+        .filter(|record| !migrator.version_exists(record.version))
+        .map(|record| MigrationStatus {
+            version: record.version,
+            description: record.description.clone(),
+            state: MigrationState::Unknown,
+            installed_on: Some(record.installed_on),
+        }));
+    statuses.sort_by_key(|status| status.version);
+    statuses
+}
+
+
+/// Applies every pending migration.
+pub async fn run_migrations() -> Result<(), NanoServiceError> {
     println!("Migrating database...");
-    let mut migrations = sqlx::migrate!("./migrations");
-    migrations.ignore_missing = true;
-    let result = migrations.run(&*SQLX_POSTGRES_POOL).await.unwrap();
-    println!("to-do database migrations completed: {:?}", result);
+    MIGRATOR.run(&*SQLX_POSTGRES_POOL).await.map_err(|e| migration_error("run migrations", e))?;
+    println!("to-do database migrations completed");
+    Ok(())
+}
+
+
+/// Reverts the applied migrations newer than `target`, newest first, with their down scripts.
+///
+/// # Arguments
+/// * `target` - The version to go back to, `0` reverts every migration.
+pub async fn revert_migrations(target: i64) -> Result<(), NanoServiceError> {
+    if target != 0 && !MIGRATOR.version_exists(target) {
+        return Err(NanoServiceError::new(
+            format!("There is no migration with version {}", target),
+            NanoServiceErrorStatus::NotFound
+        ))
+    }
+    MIGRATOR.undo(&*SQLX_POSTGRES_POOL, target).await.map_err(|e| migration_error("revert migrations", e))
+}
+
+
+/// Reads the state of every migration from the database.
+pub async fn migration_status() -> Result<Vec<MigrationStatus>, NanoServiceError> {
+    let table_exists: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(&*SQLX_POSTGRES_POOL)
+        .await
+        .map_err(|e| migration_error("read migrations", e))?;
+    let applied = match table_exists {
+        true => sqlx::query_as::<_, AppliedMigration>(
+                "SELECT version, description, checksum, installed_on FROM _sqlx_migrations WHERE success ORDER BY version"
+            )
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+            .await
+            .map_err(|e| migration_error("read migrations", e))?,
+        false => Vec::new()
+    };
+    Ok(migration_statuses(&MIGRATOR, &applied))
+}
+
+
+/// Checks every migration this binary has was applied, for servers started with `AUTO_MIGRATE=false`.
+///
+/// # Returns
+/// * `Err(NanoServiceError)` - Listing the pending and modified migrations, if there are any.
+pub async fn ensure_migrations_applied() -> Result<(), NanoServiceError> {
+    let outstanding: Vec<String> = migration_status().await?
+        .into_iter()
+        .filter(|status| matches!(status.state, MigrationState::Pending | MigrationState::Modified))
+        .map(|status| format!("{} ({})", status.version, status.state.as_str()))
+        .collect();
+    if !outstanding.is_empty() {
+        return Err(NanoServiceError::new(
+            format!("Migrations have to be run before starting: {}", outstanding.join(", ")),
+            NanoServiceErrorStatus::Unknown
+        ))
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use sqlx::migrate::MigrationType;
+
+    fn applied(version: i64, checksum: &[u8]) -> AppliedMigration {
+        AppliedMigration {
+            version,
+            description: "applied".to_string(),
+            checksum: checksum.to_vec(),
+            installed_on: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_every_migration_is_reversible() {
+        let ups: HashSet<i64> = MIGRATOR.iter()
+            .filter(|migration| migration.migration_type == MigrationType::ReversibleUp)
+            .map(|migration| migration.version)
+            .collect();
+        let downs: HashSet<i64> = MIGRATOR.iter()
+            .filter(|migration| migration.migration_type == MigrationType::ReversibleDown)
+            .map(|migration| migration.version)
+            .collect();
+        assert!(!ups.is_empty());
+        assert_eq!(ups, downs);
+        assert_eq!(ups.len() * 2, MIGRATOR.iter().count());
+    }
+
+    #[test]
+    fn test_migration_statuses() {
+        let mut migrations = MIGRATOR.iter().filter(|migration| migration.migration_type == MigrationType::ReversibleUp);
+        let first = migrations.next().unwrap();
+        let second = migrations.next().unwrap();
+        let records = vec![
+            applied(first.version, &first.checksum),
+            applied(second.version, b"edited"),
+            applied(1, b"from a newer binary"),
+        ];
+
+        let statuses = migration_statuses(&MIGRATOR, &records);
+        assert_eq!(statuses.len(), MIGRATOR.iter().count() / 2 + 1);
+        assert_eq!((statuses[0].version, statuses[0].state), (1, MigrationState::Unknown));
+        assert_eq!((statuses[1].version, statuses[1].state), (first.version, MigrationState::Applied));
+        assert_eq!((statuses[2].version, statuses[2].state), (second.version, MigrationState::Modified));
+        assert!(statuses[3..].iter().all(|status| status.state == MigrationState::Pending && status.installed_on.is_none()));
+        assert!(statuses[1].to_string().starts_with(&format!("{}  applied", first.version)));
+    }
 }
@@ -0,0 +1,94 @@
+//! Implements the moderation transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::moderation::{NewModerationDecision, ModerationDecision};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::moderation::tx_definitions::{
+    RecordModerationDecision,
+    GetModerationDecisions,
+    ReviewModerationDecision
+};
+
+
+const DECISION_COLUMNS: &str = "id, content, entity_id, author_id, action, reasons, text, created_at, reviewed_by, reviewed_at, upheld";
+
+
+fn moderation_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+/// Records that a piece of text was flagged or rejected.
+#[impl_transaction(SqlxPostGresDescriptor, RecordModerationDecision, record_moderation_decision)]
+async fn record_moderation_decision(decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+    let query = format!(r#"
+        INSERT INTO moderation_decisions (content, entity_id, author_id, action, reasons, text)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING {}
+    "#, DECISION_COLUMNS);
+
+    retry_write(|| {
+        sqlx::query_as::<_, ModerationDecision>(&query)
+            .bind(decision.content)
+            .bind(decision.entity_id)
+            .bind(decision.author_id)
+            .bind(decision.action)
+            .bind(&decision.reasons)
+            .bind(&decision.text)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| moderation_error("record moderation decision", e))
+}
+
+
+/// Gets the most recent decisions, only those not yet reviewed if `pending_only` is set.
+#[impl_transaction(SqlxPostGresDescriptor, GetModerationDecisions, get_moderation_decisions)]
+async fn get_moderation_decisions(pending_only: bool, limit: i64) -> Result<Vec<ModerationDecision>, NanoServiceError> {
+    let query = format!(r#"
+        SELECT {}
+        FROM moderation_decisions
+        WHERE NOT $1 OR reviewed_at IS NULL
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+    "#, DECISION_COLUMNS);
+
+    retry_transient(|| {
+        sqlx::query_as::<_, ModerationDecision>(&query)
+            .bind(pending_only)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| moderation_error("get moderation decisions", e))
+}
+
+
+/// Records an admin's review of a decision, a later review replaces an earlier one.
+#[impl_transaction(SqlxPostGresDescriptor, ReviewModerationDecision, review_moderation_decision)]
+async fn review_moderation_decision(id: i32, reviewer_id: i32, upheld: bool) -> Result<ModerationDecision, NanoServiceError> {
+    let query = format!(r#"
+        UPDATE moderation_decisions
+        SET reviewed_by = $2, reviewed_at = NOW(), upheld = $3
+        WHERE id = $1
+        RETURNING {}
+    "#, DECISION_COLUMNS);
+
+    retry_write(|| {
+        sqlx::query_as::<_, ModerationDecision>(&query)
+            .bind(id)
+            .bind(reviewer_id)
+            .bind(upheld)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| moderation_error("review moderation decision", e))?
+    .ok_or_else(|| NanoServiceError::new(
+        format!("Moderation decision with id {} not found", id),
+        NanoServiceErrorStatus::NotFound,
+    ))
+}
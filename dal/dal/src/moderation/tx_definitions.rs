@@ -0,0 +1,13 @@
+//! Defines transaction traits for interacting with the `moderation_decisions` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::moderation::{NewModerationDecision, ModerationDecision};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    RecordModerationDecision => record_moderation_decision(decision: NewModerationDecision) -> ModerationDecision,
+    GetModerationDecisions => get_moderation_decisions(pending_only: bool, limit: i64) -> Vec<ModerationDecision>,
+    ReviewModerationDecision => review_moderation_decision(id: i32, reviewer_id: i32, upheld: bool) -> ModerationDecision
+);
@@ -0,0 +1,93 @@
+//! Implements the notification batching transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::notifications::{NewNotification, NotificationType, PendingNotification};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::notifications::tx_definitions::{QueueNotification, TakeDueNotifications, ListPendingNotifications};
+
+
+fn notification_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, QueueNotification, queue_notification)]
+async fn queue_notification(notification: NewNotification) -> Result<PendingNotification, NanoServiceError> {
+    let query = r#"
+        INSERT INTO pending_notifications (user_id, notification_type, todo_id, todo_name)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, user_id, notification_type, todo_id, todo_name, date_created
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, PendingNotification>(query)
+            .bind(notification.user_id)
+            .bind(notification.notification_type)
+            .bind(notification.todo_id)
+            .bind(&notification.todo_name)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| notification_error("queue notification", e))
+}
+
+
+/// Removes and returns every notification of the type for the users whose oldest notification is
+/// at least `window_seconds` old, oldest first. Rows locked by another worker are skipped.
+#[impl_transaction(SqlxPostGresDescriptor, TakeDueNotifications, take_due_notifications)]
+async fn take_due_notifications(notification_type: NotificationType, window_seconds: i64) -> Result<Vec<PendingNotification>, NanoServiceError> {
+    let query = r#"
+        WITH due_users AS (
+            SELECT user_id FROM pending_notifications
+            WHERE notification_type = $1
+            GROUP BY user_id
+            HAVING MIN(date_created) <= NOW() - make_interval(secs => $2)
+        ), taken AS (
+            DELETE FROM pending_notifications
+            WHERE id IN (
+                SELECT id FROM pending_notifications
+                WHERE notification_type = $1 AND user_id IN (SELECT user_id FROM due_users)
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, user_id, notification_type, todo_id, todo_name, date_created
+        )
+        SELECT * FROM taken ORDER BY date_created, id
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, PendingNotification>(query)
+            .bind(notification_type)
+            .bind(window_seconds as f64)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| notification_error("take due notifications", e))
+}
+
+
+/// Lists a user's notifications still waiting to be batched, oldest first, starting after
+/// `after_id` when paging.
+#[impl_transaction(SqlxPostGresDescriptor, ListPendingNotifications, list_pending_notifications)]
+async fn list_pending_notifications(user_id: i32, after_id: Option<i32>, limit: i64) -> Result<Vec<PendingNotification>, NanoServiceError> {
+    let query = r#"
+        SELECT id, user_id, notification_type, todo_id, todo_name, date_created
+        FROM pending_notifications
+        WHERE user_id = $1 AND ($2::INTEGER IS NULL OR id > $2)
+        ORDER BY id
+        LIMIT $3
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, PendingNotification>(query)
+            .bind(user_id)
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| notification_error("list notifications", e))
+}
@@ -0,0 +1,14 @@
+//! Defines transaction traits for interacting with the `pending_notifications` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `TakeDueNotifications` removes the notifications it returns, so each is only summarised once.
+use kernel::notifications::{NewNotification, NotificationType, PendingNotification};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    QueueNotification => queue_notification(notification: NewNotification) -> PendingNotification,
+    TakeDueNotifications => take_due_notifications(notification_type: NotificationType, window_seconds: i64) -> Vec<PendingNotification>,
+    ListPendingNotifications => list_pending_notifications(user_id: i32, after_id: Option<i32>, limit: i64) -> Vec<PendingNotification>
+);
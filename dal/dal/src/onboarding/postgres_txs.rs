@@ -0,0 +1,176 @@
+//! Implements the onboarding transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::onboarding::{OnboardingState, OnboardingProfile, OnboardingFunnelCount};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::onboarding::tx_definitions::{
+    GetOnboardingState,
+    GetOnboardingStateByUuid,
+    AcceptTerms,
+    RecordPasswordSet,
+    CompleteOnboardingProfile,
+    GetOnboardingFunnel,
+};
+
+
+fn onboarding_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetOnboardingState, get_onboarding_state)]
+async fn get_onboarding_state(user_id: i32) -> Result<Option<OnboardingState>, NanoServiceError> {
+    let query = r#"
+        SELECT u.id AS user_id, u.confirmed, o.terms_version, o.terms_accepted_at,
+               o.password_set_at, o.profile_completed_at
+        FROM users u
+        LEFT JOIN user_onboarding o ON o.user_id = u.id
+        WHERE u.id = $1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, OnboardingState>(query)
+            .bind(user_id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| onboarding_error("get onboarding state", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetOnboardingStateByUuid, get_onboarding_state_by_uuid)]
+async fn get_onboarding_state_by_uuid(uuid: String) -> Result<Option<OnboardingState>, NanoServiceError> {
+    let query = r#"
+        SELECT u.id AS user_id, u.confirmed, o.terms_version, o.terms_accepted_at,
+               o.password_set_at, o.profile_completed_at
+        FROM users u
+        LEFT JOIN user_onboarding o ON o.user_id = u.id
+        WHERE u.uuid = $1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, OnboardingState>(query)
+            .bind(&uuid)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| onboarding_error("get onboarding state", e))
+}
+
+
+/// Records the version of the terms the user accepted, replacing any earlier acceptance.
+#[impl_transaction(SqlxPostGresDescriptor, AcceptTerms, accept_terms)]
+async fn accept_terms(user_id: i32, terms_version: String) -> Result<OnboardingState, NanoServiceError> {
+    let query = r#"
+        WITH saved AS (
+            INSERT INTO user_onboarding (user_id, terms_version, terms_accepted_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (user_id) DO UPDATE
+            SET terms_version = EXCLUDED.terms_version, terms_accepted_at = EXCLUDED.terms_accepted_at
+            RETURNING *
+        )
+        SELECT u.id AS user_id, u.confirmed, saved.terms_version, saved.terms_accepted_at,
+               saved.password_set_at, saved.profile_completed_at
+        FROM saved
+        JOIN users u ON u.id = saved.user_id
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, OnboardingState>(query)
+            .bind(user_id)
+            .bind(&terms_version)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| onboarding_error("accept terms", e))
+}
+
+
+/// Records that the user with the uuid has set their own password.
+#[impl_transaction(SqlxPostGresDescriptor, RecordPasswordSet, record_password_set)]
+async fn record_password_set(uuid: String) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        INSERT INTO user_onboarding (user_id, password_set_at)
+        SELECT id, NOW() FROM users WHERE uuid = $1
+        ON CONFLICT (user_id) DO UPDATE
+        SET password_set_at = EXCLUDED.password_set_at
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&uuid)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| onboarding_error("record password set", e))?;
+    Ok(result.rows_affected() == 1)
+}
+
+
+/// Saves the profile on the user and marks the profile step as complete in one statement.
+#[impl_transaction(SqlxPostGresDescriptor, CompleteOnboardingProfile, complete_onboarding_profile)]
+async fn complete_onboarding_profile(user_id: i32, profile: OnboardingProfile) -> Result<OnboardingState, NanoServiceError> {
+    let query = r#"
+        WITH updated AS (
+            UPDATE users
+            SET first_name = $2, last_name = $3, locale = $4, timezone = $5, updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, confirmed
+        ), saved AS (
+            INSERT INTO user_onboarding (user_id, profile_completed_at)
+            SELECT id, NOW() FROM updated
+            ON CONFLICT (user_id) DO UPDATE
+            SET profile_completed_at = EXCLUDED.profile_completed_at
+            RETURNING *
+        )
+        SELECT updated.id AS user_id, updated.confirmed, saved.terms_version, saved.terms_accepted_at,
+               saved.password_set_at, saved.profile_completed_at
+        FROM updated
+        JOIN saved ON saved.user_id = updated.id
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, OnboardingState>(query)
+            .bind(user_id)
+            .bind(&profile.first_name)
+            .bind(&profile.last_name)
+            .bind(&profile.locale)
+            .bind(&profile.timezone)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| onboarding_error("complete profile", e))
+}
+
+
+/// Counts the users waiting on each step, the steps mirror `OnboardingState::next_step`.
+#[impl_transaction(SqlxPostGresDescriptor, GetOnboardingFunnel, get_onboarding_funnel)]
+async fn get_onboarding_funnel(terms_version: String) -> Result<Vec<OnboardingFunnelCount>, NanoServiceError> {
+    let query = r#"
+        SELECT step, COUNT(*) AS users
+        FROM (
+            SELECT CASE
+                WHEN NOT u.confirmed THEN 'confirm_email'
+                WHEN o.terms_version IS DISTINCT FROM $1 THEN 'accept_terms'
+                WHEN o.password_set_at IS NULL THEN 'set_password'
+                WHEN o.profile_completed_at IS NULL THEN 'complete_profile'
+                ELSE 'complete'
+            END AS step
+            FROM users u
+            LEFT JOIN user_onboarding o ON o.user_id = u.id
+        ) steps
+        GROUP BY step
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, OnboardingFunnelCount>(query)
+            .bind(&terms_version)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| onboarding_error("get onboarding funnel", e))
+}
@@ -0,0 +1,18 @@
+//! Defines transaction traits for recording onboarding progress in the `user_onboarding` table.
+//!
+//! ## Notes
+//! - Email confirmation is read from `users.confirmed`, a user without a `user_onboarding` row has
+//!   not completed any of the other steps.
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::onboarding::{OnboardingState, OnboardingProfile, OnboardingFunnelCount};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    GetOnboardingState => get_onboarding_state(user_id: i32) -> Option<OnboardingState>,
+    GetOnboardingStateByUuid => get_onboarding_state_by_uuid(uuid: String) -> Option<OnboardingState>,
+    AcceptTerms => accept_terms(user_id: i32, terms_version: String) -> OnboardingState,
+    RecordPasswordSet => record_password_set(uuid: String) -> bool,
+    CompleteOnboardingProfile => complete_onboarding_profile(user_id: i32, profile: OnboardingProfile) -> OnboardingState,
+    GetOnboardingFunnel => get_onboarding_funnel(terms_version: String) -> Vec<OnboardingFunnelCount>
+);
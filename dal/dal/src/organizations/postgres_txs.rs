@@ -0,0 +1,178 @@
+//! Implements the organization transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::organizations::{NewOrganization, Organization, OrgMembership, OrgRole, UserOrganization};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::organizations::tx_definitions::{
+    CreateOrganization,
+    GetOrganization,
+    GetUserOrganizations,
+    GetOrgMembership,
+    AddOrgMember,
+    RemoveOrgMember,
+    GetOrgMembers
+};
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CreateOrganization, create_organization)]
+async fn create_organization(owner_id: i32, org: NewOrganization) -> Result<Organization, NanoServiceError> {
+    let query = r#"
+        WITH created AS (
+            INSERT INTO organizations (name)
+            VALUES ($1)
+            RETURNING id, name, date_created
+        ), owner AS (
+            INSERT INTO org_memberships (org_id, user_id, org_role)
+            SELECT id, $2, 'owner' FROM created
+        )
+        SELECT id, name, date_created
+        FROM created
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, Organization>(query)
+            .bind(&org.name)
+            .bind(owner_id)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to create organization: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetOrganization, get_organization)]
+async fn get_organization(id: i32) -> Result<Organization, NanoServiceError> {
+    let query = r#"
+        SELECT id, name, date_created
+        FROM organizations
+        WHERE id = $1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Organization>(query)
+            .bind(id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(format!("Failed to get organization: {}", e), NanoServiceErrorStatus::Unknown))?
+    .ok_or_else(|| NanoServiceError::new(format!("Organization {} not found", id), NanoServiceErrorStatus::NotFound))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetUserOrganizations, get_user_organizations)]
+async fn get_user_organizations(user_id: i32) -> Result<Vec<UserOrganization>, NanoServiceError> {
+    let query = r#"
+        SELECT organizations.id, organizations.name, org_memberships.org_role
+        FROM organizations
+        JOIN org_memberships ON org_memberships.org_id = organizations.id
+        WHERE org_memberships.user_id = $1
+        ORDER BY organizations.id
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, UserOrganization>(query)
+            .bind(user_id)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to get organizations for user: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetOrgMembership, get_org_membership)]
+async fn get_org_membership(org_id: i32, user_id: i32) -> Result<Option<OrgMembership>, NanoServiceError> {
+    let query = r#"
+        SELECT org_id, user_id, org_role, date_joined
+        FROM org_memberships
+        WHERE org_id = $1 AND user_id = $2
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, OrgMembership>(query)
+            .bind(org_id)
+            .bind(user_id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to get organization membership: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, AddOrgMember, add_org_member)]
+async fn add_org_member(org_id: i32, user_id: i32, org_role: OrgRole) -> Result<OrgMembership, NanoServiceError> {
+    let query = r#"
+        INSERT INTO org_memberships (org_id, user_id, org_role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT ON CONSTRAINT unique_org_membership
+        DO UPDATE SET org_role = EXCLUDED.org_role
+        RETURNING org_id, user_id, org_role, date_joined
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, OrgMembership>(query)
+            .bind(org_id)
+            .bind(user_id)
+            .bind(org_role)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to add organization member: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RemoveOrgMember, remove_org_member)]
+async fn remove_org_member(org_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        DELETE FROM org_memberships
+        WHERE org_id = $1 AND user_id = $2
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(org_id)
+            .bind(user_id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to remove organization member: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetOrgMembers, get_org_members)]
+async fn get_org_members(org_id: i32) -> Result<Vec<OrgMembership>, NanoServiceError> {
+    let query = r#"
+        SELECT org_id, user_id, org_role, date_joined
+        FROM org_memberships
+        WHERE org_id = $1
+        ORDER BY date_joined, user_id
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, OrgMembership>(query)
+            .bind(org_id)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to get organization members: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
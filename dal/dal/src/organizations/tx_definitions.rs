@@ -0,0 +1,22 @@
+//! Defines transaction traits for interacting with the `organizations` and `org_memberships` tables.
+//!
+//! # Overview
+//! This file uses the `define_dal_transactions` macro to create traits for creating organizations,
+//! reading the organizations a user belongs to and managing their members.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `CreateOrganization` adds the user creating it as its owner in the same statement.
+use kernel::organizations::{NewOrganization, Organization, OrgMembership, OrgRole, UserOrganization};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreateOrganization => create_organization(owner_id: i32, org: NewOrganization) -> Organization,
+    GetOrganization => get_organization(id: i32) -> Organization,
+    GetUserOrganizations => get_user_organizations(user_id: i32) -> Vec<UserOrganization>,
+    GetOrgMembership => get_org_membership(org_id: i32, user_id: i32) -> Option<OrgMembership>,
+    AddOrgMember => add_org_member(org_id: i32, user_id: i32, org_role: OrgRole) -> OrgMembership,
+    RemoveOrgMember => remove_org_member(org_id: i32, user_id: i32) -> bool,
+    GetOrgMembers => get_org_members(org_id: i32) -> Vec<OrgMembership>
+);
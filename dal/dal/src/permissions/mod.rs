@@ -0,0 +1,2 @@
+pub mod postgres_txs;
+pub mod tx_definitions;
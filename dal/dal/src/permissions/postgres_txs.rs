@@ -0,0 +1,131 @@
+//! Implements the permission transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::permissions::{Permission, NewPermission, RolePermissionGrant};
+use kernel::users::UserRole;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::permissions::tx_definitions::{
+    CreatePermission,
+    GetPermissions,
+    GrantRolePermission,
+    RevokeRolePermission,
+    GetEffectivePermissions
+};
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CreatePermission, create_permission)]
+async fn create_permission(permission: NewPermission) -> Result<Permission, NanoServiceError> {
+    let query = r#"
+        INSERT INTO permissions (name, description)
+        VALUES ($1, $2)
+        RETURNING id, name, description
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, Permission>(query)
+            .bind(&permission.name)
+            .bind(&permission.description)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to create permission: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetPermissions, get_permissions)]
+async fn get_permissions() -> Result<Vec<Permission>, NanoServiceError> {
+    let query = r#"
+        SELECT id, name, description
+        FROM permissions
+        ORDER BY name
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Permission>(query)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to fetch permissions: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GrantRolePermission, grant_role_permission)]
+async fn grant_role_permission(role: UserRole, permission_id: i32) -> Result<RolePermissionGrant, NanoServiceError> {
+    let query = r#"
+        INSERT INTO role_permission_grants (role, permission_id)
+        VALUES ($1, $2)
+        ON CONFLICT ON CONSTRAINT unique_role_permission_grant
+        DO UPDATE SET role = EXCLUDED.role
+        RETURNING id, role, permission_id
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, RolePermissionGrant>(query)
+            .bind(role.to_string())
+            .bind(permission_id)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to grant permission to role: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RevokeRolePermission, revoke_role_permission)]
+async fn revoke_role_permission(role: UserRole, permission_id: i32) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        DELETE FROM role_permission_grants
+        WHERE role = $1 AND permission_id = $2
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(role.to_string())
+            .bind(permission_id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to revoke permission from role: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+/// The effective permissions are the grants for the user's own role and every role in `role_permissions`.
+#[impl_transaction(SqlxPostGresDescriptor, GetEffectivePermissions, get_effective_permissions)]
+async fn get_effective_permissions(user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+    let query = r#"
+        SELECT DISTINCT permissions.name
+        FROM permissions
+        JOIN role_permission_grants ON role_permission_grants.permission_id = permissions.id
+        WHERE role_permission_grants.role IN (
+            SELECT user_role FROM users WHERE id = $1
+            UNION
+            SELECT role FROM role_permissions WHERE user_id = $1
+        )
+        ORDER BY permissions.name
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_scalar::<_, String>(query)
+            .bind(user_id)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to fetch effective permissions: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
@@ -0,0 +1,20 @@
+//! Defines transaction traits for interacting with the `permissions` and `role_permission_grants` tables.
+//!
+//! # Overview
+//! This file uses the `define_dal_transactions` macro to create traits for creating permissions,
+//! granting and revoking them for roles, and resolving the effective permissions of a user.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::permissions::{Permission, NewPermission, RolePermissionGrant};
+use kernel::users::UserRole;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreatePermission => create_permission(permission: NewPermission) -> Permission,
+    GetPermissions => get_permissions() -> Vec<Permission>,
+    GrantRolePermission => grant_role_permission(role: UserRole, permission_id: i32) -> RolePermissionGrant,
+    RevokeRolePermission => revoke_role_permission(role: UserRole, permission_id: i32) -> bool,
+    GetEffectivePermissions => get_effective_permissions(user_id: i32) -> Vec<String>
+);
@@ -0,0 +1,58 @@
+//! Implements the plan transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::plans::{NewOrgPlan, OrgPlan};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::plans::tx_definitions::{GetOrgPlan, UpdateOrgPlan};
+
+
+fn plan_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetOrgPlan, get_org_plan)]
+async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+    let query = r#"
+        SELECT plan, max_users, max_todos, mfa, api_access, date_updated
+        FROM org_plan
+        WHERE id = 1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, OrgPlan>(query)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| plan_error("get plan", e))
+}
+
+
+/// Replaces the plan and its overrides, recreating the row if it has been removed by hand.
+#[impl_transaction(SqlxPostGresDescriptor, UpdateOrgPlan, update_org_plan)]
+async fn update_org_plan(plan: NewOrgPlan) -> Result<OrgPlan, NanoServiceError> {
+    let query = r#"
+        INSERT INTO org_plan (id, plan, max_users, max_todos, mfa, api_access)
+        VALUES (1, $1, $2, $3, $4, $5)
+        ON CONFLICT (id) DO UPDATE
+        SET plan = EXCLUDED.plan, max_users = EXCLUDED.max_users, max_todos = EXCLUDED.max_todos,
+            mfa = EXCLUDED.mfa, api_access = EXCLUDED.api_access, date_updated = NOW()
+        RETURNING plan, max_users, max_todos, mfa, api_access, date_updated
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, OrgPlan>(query)
+            .bind(plan.plan)
+            .bind(plan.max_users)
+            .bind(plan.max_todos)
+            .bind(plan.mfa)
+            .bind(plan.api_access)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| plan_error("save plan", e))
+}
@@ -0,0 +1,14 @@
+//! Defines transaction traits for interacting with the `org_plan` table.
+//!
+//! ## Notes
+//! - The table holds a single row seeded by its migration, so there is no create or delete.
+//! - The usage the limits are checked against is counted by `CountUsers` and `CountToDoItems`.
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::plans::{NewOrgPlan, OrgPlan};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    GetOrgPlan => get_org_plan() -> OrgPlan,
+    UpdateOrgPlan => update_org_plan(plan: NewOrgPlan) -> OrgPlan
+);
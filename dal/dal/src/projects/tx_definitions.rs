@@ -0,0 +1,31 @@
+//! Defines transaction traits for interacting with the `projects`, `project_members` and `project_todos` tables.
+//!
+//! # Overview
+//! This file uses the `define_dal_transactions` macro to create traits for managing projects,
+//! the users who can see them and the to-do items added to them.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `CreateProject` adds the owner as the first member in the same statement.
+//! - `AddToDoItemToProject` moves an item that is already in another project.
+//! - `GetToDoItemsForProject` only reaches the items of `kernel::tenancy::current_tenant` and errors
+//!   if no tenant is set, callers working across organizations use `kernel::tenancy::without_tenant`.
+use kernel::projects::{NewProject, Project, ProjectItemFilter, ProjectMember, ProjectPatch, ProjectTodo};
+use kernel::to_do_items::Todo;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreateProject => create_project(owner_id: i32, project: NewProject) -> Project,
+    GetProject => get_project(id: i32) -> Project,
+    GetProjectsForUser => get_projects_for_user(user_id: i32) -> Vec<Project>,
+    UpdateProject => update_project(id: i32, patch: ProjectPatch) -> Project,
+    DeleteProject => delete_project(id: i32) -> bool,
+    IsProjectMember => is_project_member(project_id: i32, user_id: i32) -> bool,
+    AddProjectMember => add_project_member(project_id: i32, user_id: i32) -> ProjectMember,
+    RemoveProjectMember => remove_project_member(project_id: i32, user_id: i32) -> bool,
+    GetProjectMembers => get_project_members(project_id: i32) -> Vec<ProjectMember>,
+    AddToDoItemToProject => add_to_do_item_to_project(project_id: i32, todo_id: i32) -> ProjectTodo,
+    RemoveToDoItemFromProject => remove_to_do_item_from_project(project_id: i32, todo_id: i32) -> bool,
+    GetToDoItemsForProject => get_to_do_items_for_project(project_id: i32, filter: ProjectItemFilter) -> Vec<Todo>
+);
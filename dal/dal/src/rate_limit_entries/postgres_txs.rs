@@ -8,6 +8,7 @@ use dal_tx_impl::impl_transaction;
 use kernel::rate_limit_entries::{RateLimitEntry, NewRateLimitEntry};
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
 use crate::rate_limit_entries::tx_definitions::{CreateRateLimitEntry, GetRateLimitEntry, UpdateRateLimitEntry};
 
 /// Implements the `CreateRateLimitEntry` trait for the `SqlxPostGresDescriptor`.
@@ -21,12 +22,14 @@ async fn create_rate_limit_entry(email: NewRateLimitEntry) -> Result<RateLimitEn
         RETURNING id, email, rate_limit_period_start, count
     "#;
 
-    sqlx::query_as::<_, RateLimitEntry>(query)
-        .bind(email.email)
-        .bind(1)
-        .fetch_one(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(format!("Failed to create rate limit entry: {}", e), NanoServiceErrorStatus::Unknown))
+    retry_write(|| {
+        sqlx::query_as::<_, RateLimitEntry>(query)
+            .bind(&email.email)
+            .bind(1)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(format!("Failed to create rate limit entry: {}", e), NanoServiceErrorStatus::Unknown))
 }
 
 /// Implements the `GetRateLimitEntry` trait for the `SqlxPostGresDescriptor`.
@@ -40,14 +43,16 @@ async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, N
         WHERE email = $1
     "#;
 
-    let result = sqlx::query_as::<_, RateLimitEntry>(query)
-        .bind(email)
-        .fetch_optional(&*SQLX_POSTGRES_POOL) // Use fetch_optional here
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to fetch rate limit entry: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_transient(|| {
+        sqlx::query_as::<_, RateLimitEntry>(query)
+            .bind(&email)
+            .fetch_optional(&*SQLX_POSTGRES_POOL) // Use fetch_optional here
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to fetch rate limit entry: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     Ok(result)
 }
@@ -63,18 +68,20 @@ async fn update_rate_limit_entry(updated_entry: RateLimitEntry) -> Result<bool,
         WHERE id = $3
     "#;
 
-    let result = sqlx::query(query)
-        .bind(updated_entry.rate_limit_period_start)
-        .bind(updated_entry.count)
-        .bind(updated_entry.id)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| {
-            NanoServiceError::new(
-                format!("Failed to update rate limit entry: {}", e),
-                NanoServiceErrorStatus::Unknown,
-            )
-        })?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(updated_entry.rate_limit_period_start)
+            .bind(updated_entry.count)
+            .bind(updated_entry.id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| {
+        NanoServiceError::new(
+            format!("Failed to update rate limit entry: {}", e),
+            NanoServiceErrorStatus::Unknown,
+        )
+    })?;
 
     Ok(result.rows_affected() > 0)
 }
@@ -0,0 +1,122 @@
+//! Implements the request metrics transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+//!
+//! # Overview
+//! Each server adds its counts to the minute they were served in, so servers flushing the same
+//! minute are summed. Rolling up recomputes each day from the minutes, which makes it safe to roll
+//! up a day again as late minutes arrive.
+use dal_tx_impl::impl_transaction;
+use kernel::request_metrics::{RequestMetricsBucket, AvailabilityRollup};
+use kernel::chrono::{NaiveDate, NaiveDateTime};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::request_metrics::tx_definitions::{
+    RecordRequestMetrics, RollupAvailability, GetAvailabilityRollups, PurgeRequestMetrics
+};
+
+
+fn metrics_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RecordRequestMetrics, record_request_metrics)]
+async fn record_request_metrics(bucket: RequestMetricsBucket) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        INSERT INTO request_metrics (bucket_start, total_requests, server_errors, client_errors)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (bucket_start) DO UPDATE
+        SET total_requests = request_metrics.total_requests + EXCLUDED.total_requests,
+            server_errors = request_metrics.server_errors + EXCLUDED.server_errors,
+            client_errors = request_metrics.client_errors + EXCLUDED.client_errors
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(bucket.bucket_start)
+            .bind(bucket.total_requests)
+            .bind(bucket.server_errors)
+            .bind(bucket.client_errors)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| metrics_error("record request metrics", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+/// Recomputes the rollups for every day from `since` that has request metrics.
+#[impl_transaction(SqlxPostGresDescriptor, RollupAvailability, rollup_availability)]
+async fn rollup_availability(since: NaiveDate) -> Result<Vec<AvailabilityRollup>, NanoServiceError> {
+    let query = r#"
+        INSERT INTO availability_rollups
+            (day, total_requests, server_errors, client_errors, availability, error_rate, date_computed)
+        SELECT day, total_requests, server_errors, client_errors,
+               CASE WHEN total_requests = 0 THEN 1.0 ELSE 1.0 - server_errors::DOUBLE PRECISION / total_requests END,
+               CASE WHEN total_requests = 0 THEN 0.0 ELSE server_errors::DOUBLE PRECISION / total_requests END,
+               NOW()
+        FROM (
+            SELECT bucket_start::DATE AS day,
+                   SUM(total_requests)::BIGINT AS total_requests,
+                   SUM(server_errors)::BIGINT AS server_errors,
+                   SUM(client_errors)::BIGINT AS client_errors
+            FROM request_metrics
+            WHERE bucket_start >= $1
+            GROUP BY bucket_start::DATE
+        ) AS days
+        ON CONFLICT (day) DO UPDATE
+        SET total_requests = EXCLUDED.total_requests,
+            server_errors = EXCLUDED.server_errors,
+            client_errors = EXCLUDED.client_errors,
+            availability = EXCLUDED.availability,
+            error_rate = EXCLUDED.error_rate,
+            date_computed = EXCLUDED.date_computed
+        RETURNING day, total_requests, server_errors, client_errors, availability, error_rate
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, AvailabilityRollup>(query)
+            .bind(since.and_hms_opt(0, 0, 0).unwrap())
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| metrics_error("roll up availability", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetAvailabilityRollups, get_availability_rollups)]
+async fn get_availability_rollups(since: NaiveDate) -> Result<Vec<AvailabilityRollup>, NanoServiceError> {
+    let query = r#"
+        SELECT day, total_requests, server_errors, client_errors, availability, error_rate
+        FROM availability_rollups
+        WHERE day >= $1
+        ORDER BY day
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, AvailabilityRollup>(query)
+            .bind(since)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| metrics_error("get availability rollups", e))
+}
+
+
+/// Deletes the minutes before `before`, the rollups they were counted in are kept.
+#[impl_transaction(SqlxPostGresDescriptor, PurgeRequestMetrics, purge_request_metrics)]
+async fn purge_request_metrics(before: NaiveDateTime) -> Result<u64, NanoServiceError> {
+    let result = retry_write(|| {
+        sqlx::query("DELETE FROM request_metrics WHERE bucket_start < $1")
+            .bind(before)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| metrics_error("purge request metrics", e))?;
+
+    Ok(result.rows_affected())
+}
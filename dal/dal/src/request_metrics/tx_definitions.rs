@@ -0,0 +1,15 @@
+//! Defines transaction traits for the `request_metrics` and `availability_rollups` tables.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::request_metrics::{RequestMetricsBucket, AvailabilityRollup};
+use kernel::chrono::{NaiveDate, NaiveDateTime};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    RecordRequestMetrics => record_request_metrics(bucket: RequestMetricsBucket) -> bool,
+    RollupAvailability => rollup_availability(since: NaiveDate) -> Vec<AvailabilityRollup>,
+    GetAvailabilityRollups => get_availability_rollups(since: NaiveDate) -> Vec<AvailabilityRollup>,
+    PurgeRequestMetrics => purge_request_metrics(before: NaiveDateTime) -> u64
+);
@@ -0,0 +1,39 @@
+//! Implements the request rate limit transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::request_rate_limits::RateLimitHit;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::retry_write;
+use crate::request_rate_limits::tx_definitions::HitRateLimit;
+
+
+/// Counts a request against a key, starting a new window if the current one is over.
+#[impl_transaction(SqlxPostGresDescriptor, HitRateLimit, hit_rate_limit)]
+async fn hit_rate_limit(key: String, window_seconds: i64) -> Result<RateLimitHit, NanoServiceError> {
+    let query = r#"
+        INSERT INTO request_rate_limits (key, window_start, count)
+        VALUES ($1, NOW(), 1)
+        ON CONFLICT (key) DO UPDATE
+        SET count = CASE
+                WHEN request_rate_limits.window_start <= NOW() - make_interval(secs => $2) THEN 1
+                ELSE request_rate_limits.count + 1
+            END,
+            window_start = CASE
+                WHEN request_rate_limits.window_start <= NOW() - make_interval(secs => $2) THEN NOW()
+                ELSE request_rate_limits.window_start
+            END
+        RETURNING count, window_start
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, RateLimitHit>(query)
+            .bind(&key)
+            .bind(window_seconds as f64)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to count request: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
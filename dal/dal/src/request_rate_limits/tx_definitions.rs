@@ -0,0 +1,13 @@
+//! Defines transaction traits for counting requests against the `request_rate_limits` table.
+//!
+//! ## Notes
+//! - Counting and resetting an expired window happen in one statement, so concurrent requests for
+//!   the same key are never lost.
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::request_rate_limits::RateLimitHit;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    HitRateLimit => hit_rate_limit(key: String, window_seconds: i64) -> RateLimitHit
+);
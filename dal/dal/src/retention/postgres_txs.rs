@@ -0,0 +1,98 @@
+//! Implements the retention transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+//!
+//! # Overview
+//! Each call deletes at most `limit` of the oldest expired rows, so the purge job can work through
+//! a backlog a batch at a time without holding a lock on the whole table. Users under a legal hold
+//! keep their rows however old they are.
+use dal_tx_impl::impl_transaction;
+use kernel::retention::RetentionTable;
+use kernel::chrono::NaiveDateTime;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::retry_write;
+use crate::retention::tx_definitions::PurgeExpiredRows;
+
+
+/// The delete for a batch of a table's rows created before `$1`, at most `$2` of them.
+///
+/// # Notes
+/// Rows about a user under a legal hold are skipped: the audit entries they performed or that were
+/// performed on them, their logins, the emails sent to them and their notifications.
+fn purge_query(table: RetentionTable) -> &'static str {
+    match table {
+        RetentionTable::AuditLog => r#"
+            DELETE FROM audit_log
+            WHERE id IN (
+                SELECT id FROM audit_log
+                WHERE date_created < $1
+                AND actor_id NOT IN (SELECT user_id FROM legal_holds)
+                AND NOT (action LIKE 'user:%' AND subject_id IN (SELECT user_id FROM legal_holds))
+                ORDER BY date_created
+                LIMIT $2
+            )
+        "#,
+        RetentionTable::LoginEvents => r#"
+            DELETE FROM login_events
+            WHERE id IN (
+                SELECT id FROM login_events
+                WHERE date_created < $1
+                AND user_id NOT IN (SELECT user_id FROM legal_holds)
+                ORDER BY date_created
+                LIMIT $2
+            )
+        "#,
+        RetentionTable::EmailLog => r#"
+            DELETE FROM email_outbox
+            WHERE id IN (
+                SELECT id FROM email_outbox
+                WHERE status <> 'pending' AND date_created < $1
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM jsonb_array_elements(email_outbox.template->'message'->'to') AS recipient
+                    JOIN users ON users.email = recipient->>'email'
+                    JOIN legal_holds ON legal_holds.user_id = users.id
+                )
+                ORDER BY date_created
+                LIMIT $2
+            )
+        "#,
+        RetentionTable::Notifications => r#"
+            DELETE FROM pending_notifications
+            WHERE id IN (
+                SELECT id FROM pending_notifications
+                WHERE date_created < $1
+                AND user_id NOT IN (SELECT user_id FROM legal_holds)
+                ORDER BY date_created
+                LIMIT $2
+            )
+        "#,
+        RetentionTable::WebhookDeliveries => r#"
+            DELETE FROM webhook_deliveries
+            WHERE (source, delivery_id) IN (
+                SELECT source, delivery_id FROM webhook_deliveries
+                WHERE date_created < $1
+                ORDER BY date_created
+                LIMIT $2
+            )
+        "#,
+    }
+}
+
+
+/// Deletes up to `limit` of the rows created before `before`, oldest first.
+#[impl_transaction(SqlxPostGresDescriptor, PurgeExpiredRows, purge_expired_rows)]
+async fn purge_expired_rows(table: RetentionTable, before: NaiveDateTime, limit: i64) -> Result<u64, NanoServiceError> {
+    let result = retry_write(|| {
+        sqlx::query(purge_query(table))
+            .bind(before)
+            .bind(limit)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to purge {}: {}", table.as_str(), e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(result.rows_affected())
+}
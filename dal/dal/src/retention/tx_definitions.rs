@@ -0,0 +1,12 @@
+//! Defines transaction traits for purging the tables with a retention period.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::retention::RetentionTable;
+use kernel::chrono::NaiveDateTime;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    PurgeExpiredRows => purge_expired_rows(table: RetentionTable, before: NaiveDateTime, limit: i64) -> u64
+);
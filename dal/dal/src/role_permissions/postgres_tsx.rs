@@ -8,9 +8,11 @@
 use dal_tx_impl::impl_transaction;
 use kernel::role_permissions::{RolePermission, NewRolePermission};
 use kernel::users::UserRole;
-use sqlx::Result;
+use sqlx::{PgExecutor, Result};
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::connections::unit_of_work::WithTransaction;
 use crate::role_permissions::tx_definitions::{CreateRolePermission, GetRolePermissions, DeleteRolePermission, UpdateRolePermissions};
 
 /// Implements the `CreateRolePermission` trait for the `SqlxPostGresDescriptor`.
@@ -18,6 +20,14 @@ use crate::role_permissions::tx_definitions::{CreateRolePermission, GetRolePermi
 /// Inserts a new role permission entry into the PostgreSQL database and returns the created entry.
 #[impl_transaction(SqlxPostGresDescriptor, CreateRolePermission, create_role_permission)]
 async fn create_role_permission(role_permission: NewRolePermission) -> Result<RolePermission, NanoServiceError> {
+    insert_role_permission(&*SQLX_POSTGRES_POOL, role_permission).await
+}
+
+/// Inserts a role permission entry using the given executor, either the pool or an open transaction.
+pub(crate) async fn insert_role_permission<'e, E: PgExecutor<'e>>(
+    executor: E,
+    role_permission: NewRolePermission
+) -> Result<RolePermission, NanoServiceError> {
     let query = r#"
         INSERT INTO role_permissions (user_id, role)
         VALUES ($1, $2)
@@ -27,7 +37,7 @@ async fn create_role_permission(role_permission: NewRolePermission) -> Result<Ro
     sqlx::query_as::<_, RolePermission>(query)
         .bind(role_permission.user_id)
         .bind(role_permission.role.to_string())
-        .fetch_one(&*SQLX_POSTGRES_POOL)
+        .fetch_one(executor)
         .await
         .map_err(|e| NanoServiceError::new(
             format!("Failed to create role permission entry: {}", e),
@@ -46,14 +56,16 @@ async fn get_role_permissions(user_id: i32) -> Result<Vec<RolePermission>, NanoS
         WHERE user_id = $1
     "#;
 
-    let role_permissions = sqlx::query_as::<_, RolePermission>(query)
-        .bind(user_id)
-        .fetch_all(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to fetch role permission entries: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let role_permissions = retry_transient(|| {
+        sqlx::query_as::<_, RolePermission>(query)
+            .bind(user_id)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to fetch role permission entries: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
     
     Ok(role_permissions)
 }
@@ -68,55 +80,63 @@ async fn delete_role_permission(user_id: i32, role: UserRole) -> Result<bool, Na
         WHERE user_id = $1 AND role = $2
     "#;
 
-    let result = sqlx::query(query)
-        .bind(user_id)
-        .bind(role.to_string())
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to delete role permission entry: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(user_id)
+            .bind(role.to_string())
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to delete role permission entry: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     Ok(result.rows_affected() > 0)
 }
 
 
+/// Implements the `UpdateRolePermissions` trait for the `SqlxPostGresDescriptor`.
+///
+/// Replaces all of a user's roles in one transaction, so a failure part way through does not
+/// leave the user with no roles.
 #[impl_transaction(SqlxPostGresDescriptor, UpdateRolePermissions, update_role_permissions)]
 async fn update_role_permissions(user_id: i32, roles: Vec<UserRole>) -> Result<(), NanoServiceError> {
-    // wipe all roles for user
-    let query = r#"
-        DELETE FROM role_permissions
-        WHERE user_id = $1
-    "#;
-    let _ = sqlx::query(query)
-        .bind(user_id)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to delete all role permissions for user: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
-    
-    let user_ids = vec![user_id; roles.len()];
-    let roles = roles.iter().map(|r| r.to_string()).collect::<Vec<String>>();
+    SqlxPostGresDescriptor::with_transaction(|transaction| Box::pin(async move {
+        // wipe all roles for user
+        let query = r#"
+            DELETE FROM role_permissions
+            WHERE user_id = $1
+        "#;
+        let _ = sqlx::query(query)
+            .bind(user_id)
+            .execute(&mut **transaction)
+            .await
+            .map_err(|e| NanoServiceError::new(
+                format!("Failed to delete all role permissions for user: {}", e),
+                NanoServiceErrorStatus::Unknown,
+            ))?;
 
-    // insert new roles
-    let query = r#"
-        INSERT INTO role_permissions (user_id, role)
-        SELECT * FROM UNNEST(
-            $1::INT4[],  -- Array of user IDs
-            $2::VARCHAR(128)[]  -- Array of roles
-        ) RETURNING id;
-    "#;
-    let _ = sqlx::query(query)
-        .bind(user_ids)
-        .bind(roles)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to update role permissions for user: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
-    Ok(())
+        let user_ids = vec![user_id; roles.len()];
+        let roles = roles.iter().map(|r| r.to_string()).collect::<Vec<String>>();
+
+        // insert new roles
+        let query = r#"
+            INSERT INTO role_permissions (user_id, role)
+            SELECT * FROM UNNEST(
+                $1::INT4[],  -- Array of user IDs
+                $2::VARCHAR(128)[]  -- Array of roles
+            ) RETURNING id;
+        "#;
+        let _ = sqlx::query(query)
+            .bind(user_ids)
+            .bind(roles)
+            .execute(&mut **transaction)
+            .await
+            .map_err(|e| NanoServiceError::new(
+                format!("Failed to update role permissions for user: {}", e),
+                NanoServiceErrorStatus::Unknown,
+            ))?;
+        Ok(())
+    })).await
 }
\ No newline at end of file
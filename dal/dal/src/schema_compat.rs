@@ -0,0 +1,121 @@
+//! Checks that the live database has every column this binary's queries reference.
+//!
+//! # Overview
+//! `schema_manifest.json` lists, per table, the columns the DAL reads or writes. When the
+//! `SCHEMA_COMPATIBILITY_MODE` flag is set the binary checks the manifest against
+//! `information_schema` at startup instead of running migrations. This allows a rolling
+//! (blue/green) deploy against a shared database: the old and new binaries both have to pass the
+//! check against the same schema, so migrations have to be additive until the old binary is gone.
+//!
+//! # Notes
+//! Add a column to the manifest in the same change that starts using it in a query.
+use crate::connections::sqlx_postgres::SQLX_POSTGRES_POOL;
+use std::collections::{BTreeMap, HashSet};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The manifest of the columns this binary needs, embedded at compile time.
+pub const SCHEMA_MANIFEST: &str = include_str!("../schema_manifest.json");
+
+
+/// Parses the embedded manifest into a map of table name to required columns.
+///
+/// # Returns
+/// * `BTreeMap<String, Vec<String>>` - The columns required for each table
+pub fn load_schema_manifest() -> Result<BTreeMap<String, Vec<String>>, NanoServiceError> {
+    serde_json::from_str(SCHEMA_MANIFEST).map_err(|e| {
+        NanoServiceError::new(
+            format!("Failed to parse schema manifest: {}", e),
+            NanoServiceErrorStatus::Unknown
+        )
+    })
+}
+
+
+/// Finds the manifest columns that are not in the live schema.
+///
+/// # Arguments
+/// * `manifest` - The columns required for each table
+/// * `live_columns` - The `(table, column)` pairs present in the database
+///
+/// # Returns
+/// * `Vec<String>` - The missing columns as `table.column`, in manifest order
+pub fn missing_columns(
+    manifest: &BTreeMap<String, Vec<String>>,
+    live_columns: &HashSet<(String, String)>
+) -> Vec<String> {
+    manifest.iter()
+        .flat_map(|(table, columns)| columns.iter().map(move |column| (table, column)))
+        .filter(|(table, column)| !live_columns.contains(&(table.to_string(), column.to_string())))
+        .map(|(table, column)| format!("{}.{}", table, column))
+        .collect()
+}
+
+
+/// Checks the manifest against the columns in the current schema of the database.
+///
+/// # Returns
+/// * `Ok(())` - If every column in the manifest exists
+/// * `Err(NanoServiceError)` - Listing the missing columns, or if the schema could not be read
+pub async fn check_schema_compatibility() -> Result<(), NanoServiceError> {
+    let manifest = load_schema_manifest()?;
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT table_name::TEXT, column_name::TEXT
+         FROM information_schema.columns
+         WHERE table_schema = current_schema()"
+    )
+        .fetch_all(&*SQLX_POSTGRES_POOL)
+        .await
+        .map_err(|e| {
+            NanoServiceError::new(
+                format!("Failed to read database schema: {}", e),
+                NanoServiceErrorStatus::Unknown
+            )
+        })?;
+    let live_columns: HashSet<(String, String)> = rows.into_iter().collect();
+    let missing = missing_columns(&manifest, &live_columns);
+    if !missing.is_empty() {
+        return Err(NanoServiceError::new(
+            format!("Database schema is missing columns required by this build: {}", missing.join(", ")),
+            NanoServiceErrorStatus::Unknown
+        ))
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_columns_exist_in_migrations() {
+        let manifest = load_schema_manifest().unwrap();
+        let migrations_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+        let migrations: String = std::fs::read_dir(migrations_dir).unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        for (table, columns) in manifest.iter() {
+            assert!(migrations.contains(&format!("CREATE TABLE IF NOT EXISTS {}", table)), "{} not migrated", table);
+            for column in columns {
+                assert!(migrations.contains(&format!("{} ", column)), "{}.{} not migrated", table, column);
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_columns() {
+        let mut manifest = BTreeMap::new();
+        manifest.insert("todos".to_string(), vec!["id".to_string(), "name".to_string()]);
+        manifest.insert("users".to_string(), vec!["id".to_string()]);
+
+        let mut live_columns = HashSet::new();
+        live_columns.insert(("todos".to_string(), "id".to_string()));
+        live_columns.insert(("users".to_string(), "id".to_string()));
+
+        assert_eq!(missing_columns(&manifest, &live_columns), vec!["todos.name".to_string()]);
+
+        live_columns.insert(("todos".to_string(), "name".to_string()));
+        assert!(missing_columns(&manifest, &live_columns).is_empty());
+    }
+}
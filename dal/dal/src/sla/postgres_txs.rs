@@ -0,0 +1,195 @@
+//! Implements the SLA transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+//!
+//! # Overview
+//! An item is due `target_minutes` after it was assigned. An unfinished item is breached once it is
+//! past due, a finished item is breached if it was finished after it was due.
+//!
+//! `GetBreachedToDoItems` and `GetToDoCounts` only reach the items of `kernel::tenancy::current_tenant`
+//! and error if no tenant is set. `ClaimSlaWarnings` is run by the SLA monitor for every organization.
+use dal_tx_impl::impl_transaction;
+use kernel::sla::{SlaPolicy, TodoSlaStatus, ToDoCounts};
+use kernel::tenancy::current_tenant;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::sla::tx_definitions::{
+    GetSlaPolicies, UpsertSlaPolicy, GetBreachedToDoItems, GetToDoCounts, ClaimSlaWarnings
+};
+
+
+/// Selects every to-do item that has an SLA along with its deadline and breach status, and the
+/// `org_id` of the item to filter on.
+const SLA_STATUS_QUERY: &str = r#"
+    SELECT todo_id, name, assigned_by, assigned_to, priority, date_assigned, sla_due, warn_at, finished,
+           CASE WHEN finished THEN COALESCE(date_finished > sla_due, FALSE) ELSE NOW() > sla_due END AS breached
+    FROM (
+        SELECT todos.id AS todo_id, todos.name, todos.assigned_by, todos.assigned_to, todos.priority,
+               todos.date_assigned, todos.date_finished, todos.finished, todos.org_id,
+               todos.date_assigned + make_interval(mins => sla_policies.target_minutes) AS sla_due,
+               todos.date_assigned + make_interval(mins => sla_policies.target_minutes - sla_policies.warn_minutes) AS warn_at
+        FROM todos
+        JOIN sla_policies ON sla_policies.priority = todos.priority
+    ) AS sla_status
+"#;
+
+
+fn sla_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetSlaPolicies, get_sla_policies)]
+async fn get_sla_policies() -> Result<Vec<SlaPolicy>, NanoServiceError> {
+    let query = r#"
+        SELECT priority, target_minutes, warn_minutes
+        FROM sla_policies
+        ORDER BY target_minutes DESC
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, SlaPolicy>(query)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| sla_error("get SLA policies", e))
+}
+
+
+/// Creates the policy for a priority, or replaces it if there already is one.
+#[impl_transaction(SqlxPostGresDescriptor, UpsertSlaPolicy, upsert_sla_policy)]
+async fn upsert_sla_policy(policy: SlaPolicy) -> Result<SlaPolicy, NanoServiceError> {
+    let query = r#"
+        INSERT INTO sla_policies (priority, target_minutes, warn_minutes)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (priority) DO UPDATE
+        SET target_minutes = EXCLUDED.target_minutes, warn_minutes = EXCLUDED.warn_minutes
+        RETURNING priority, target_minutes, warn_minutes
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, SlaPolicy>(query)
+            .bind(&policy.priority)
+            .bind(policy.target_minutes)
+            .bind(policy.warn_minutes)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| sla_error("save SLA policy", e))
+}
+
+
+/// Gets the unfinished items that are past their SLA, the most overdue first.
+#[impl_transaction(SqlxPostGresDescriptor, GetBreachedToDoItems, get_breached_to_do_items)]
+async fn get_breached_to_do_items() -> Result<Vec<TodoSlaStatus>, NanoServiceError> {
+    let tenant = current_tenant()?;
+    let query = format!(
+        "{} WHERE NOT finished AND NOW() > sla_due AND ($1::INTEGER IS NULL OR org_id = $1) ORDER BY sla_due, todo_id",
+        SLA_STATUS_QUERY
+    );
+
+    retry_transient(|| {
+        sqlx::query_as::<_, TodoSlaStatus>(&query)
+            .bind(tenant)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| sla_error("get breached to-do items", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetToDoCounts, get_to_do_counts)]
+async fn get_to_do_counts() -> Result<ToDoCounts, NanoServiceError> {
+    let tenant = current_tenant()?;
+    let query = r#"
+        SELECT COUNT(*) FILTER (WHERE NOT finished) AS open_items,
+               COUNT(*) FILTER (WHERE finished) AS finished_items,
+               COUNT(*) FILTER (WHERE pending_review) AS pending_review_items
+        FROM todos
+        WHERE ($1::INTEGER IS NULL OR org_id = $1)
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, ToDoCounts>(query)
+            .bind(tenant)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| sla_error("count to-do items", e))
+}
+
+
+/// Claims the unfinished items that have reached their warning time but are not yet breached.
+///
+/// # Notes
+/// Claiming records the item in `sla_warnings` in the same statement, so an item is only ever
+/// returned once, even with several servers polling.
+#[impl_transaction(SqlxPostGresDescriptor, ClaimSlaWarnings, claim_sla_warnings)]
+async fn claim_sla_warnings(limit: i64) -> Result<Vec<TodoSlaStatus>, NanoServiceError> {
+    let query = format!(r#"
+        WITH due AS (
+            {}
+            WHERE NOT finished AND NOW() >= warn_at AND NOW() <= sla_due
+              AND NOT EXISTS (SELECT 1 FROM sla_warnings WHERE sla_warnings.todo_id = sla_status.todo_id)
+            ORDER BY sla_due, todo_id
+            LIMIT $1
+        ),
+        claimed AS (
+            INSERT INTO sla_warnings (todo_id)
+            SELECT todo_id FROM due
+            ON CONFLICT (todo_id) DO NOTHING
+            RETURNING todo_id
+        )
+        SELECT due.* FROM due JOIN claimed ON claimed.todo_id = due.todo_id
+        ORDER BY due.sla_due, due.todo_id
+    "#, SLA_STATUS_QUERY);
+
+    retry_write(|| {
+        sqlx::query_as::<_, TodoSlaStatus>(&query)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| sla_error("claim SLA warnings", e))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::tenancy::{with_tenant, without_tenant};
+    use crate::fixtures::{restore_canonical_fixtures, run_database_test};
+
+    #[tokio::test]
+    async fn test_sla_stats_need_a_tenant() {
+        // refused before a connection is taken, so no database is needed
+        assert!(SqlxPostGresDescriptor::get_breached_to_do_items().await.is_err());
+        assert!(SqlxPostGresDescriptor::get_to_do_counts().await.is_err());
+    }
+
+    /// Every item of the canonical fixtures belongs to organization 1, and the unfinished ones are past their SLA.
+    #[test]
+    #[ignore = "needs the database in DB_URL"]
+    fn test_sla_stats_of_another_org_are_excluded() {
+        run_database_test(async {
+            restore_canonical_fixtures().await.unwrap();
+
+            with_tenant(2, async {
+                assert!(SqlxPostGresDescriptor::get_breached_to_do_items().await.unwrap().is_empty());
+                let counts = SqlxPostGresDescriptor::get_to_do_counts().await.unwrap();
+                assert_eq!((counts.open_items, counts.finished_items), (0, 0));
+            }).await;
+
+            with_tenant(1, async {
+                assert_eq!(SqlxPostGresDescriptor::get_breached_to_do_items().await.unwrap().len(), 3);
+                let counts = SqlxPostGresDescriptor::get_to_do_counts().await.unwrap();
+                assert_eq!((counts.open_items, counts.finished_items), (3, 1));
+            }).await;
+            without_tenant(async {
+                assert_eq!(SqlxPostGresDescriptor::get_breached_to_do_items().await.unwrap().len(), 3);
+            }).await;
+        });
+    }
+}
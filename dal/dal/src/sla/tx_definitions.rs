@@ -0,0 +1,17 @@
+//! Defines transaction traits for tracking to-do items against the `sla_policies` table.
+//!
+//! ## Notes
+//! - The SLA deadline and breach status are computed in the queries, an item whose priority has no
+//!   policy has no SLA and is left out.
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::sla::{SlaPolicy, TodoSlaStatus, ToDoCounts};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    GetSlaPolicies => get_sla_policies() -> Vec<SlaPolicy>,
+    UpsertSlaPolicy => upsert_sla_policy(policy: SlaPolicy) -> SlaPolicy,
+    GetBreachedToDoItems => get_breached_to_do_items() -> Vec<TodoSlaStatus>,
+    GetToDoCounts => get_to_do_counts() -> ToDoCounts,
+    ClaimSlaWarnings => claim_sla_warnings(limit: i64) -> Vec<TodoSlaStatus>
+);
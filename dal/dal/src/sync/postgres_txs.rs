@@ -0,0 +1,117 @@
+//! Implements the sync transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::sync::{SyncPosition, SyncedUser, SyncedTodo};
+use kernel::tenancy::current_tenant;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::retry_transient;
+use crate::sync::tx_definitions::{
+    GetUsersChangedSince, GetToDoItemsChangedSince, GetSyncedUser, GetSyncedToDoItem
+};
+
+
+fn sync_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+/// Gets the users changed after the position, only the user with `user_id` and only the members
+/// of the organization with `org_id` if they are given.
+#[impl_transaction(SqlxPostGresDescriptor, GetUsersChangedSince, get_users_changed_since)]
+async fn get_users_changed_since(user_id: Option<i32>, org_id: Option<i32>, after: SyncPosition, limit: i64) -> Result<Vec<SyncedUser>, NanoServiceError> {
+    let query = r#"
+        SELECT id, confirmed, username, email, first_name, last_name, user_role, date_created,
+               last_logged_in, blocked, '/avatars/' || id || '?v=' || avatar_version AS avatar_url, updated_at
+        FROM users
+        WHERE (updated_at, id) > ($1, $2) AND ($3::INTEGER IS NULL OR id = $3)
+          AND ($5::INTEGER IS NULL OR id IN (SELECT user_id FROM org_memberships WHERE org_id = $5))
+        ORDER BY updated_at, id
+        LIMIT $4
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, SyncedUser>(query)
+            .bind(after.updated_at)
+            .bind(after.id)
+            .bind(user_id)
+            .bind(limit)
+            .bind(org_id)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| sync_error("get changed users", e))
+}
+
+
+/// Gets the to-do items assigned to or by the user that changed after the position.
+#[impl_transaction(SqlxPostGresDescriptor, GetToDoItemsChangedSince, get_to_do_items_changed_since)]
+async fn get_to_do_items_changed_since(user_id: i32, after: SyncPosition, limit: i64) -> Result<Vec<SyncedTodo>, NanoServiceError> {
+    let tenant = current_tenant()?;
+    let query = r#"
+        SELECT id, name, due_date, assigned_by, assigned_to, description, date_assigned, date_finished, finished,
+               requires_review, pending_review, review_comment, priority, status, updated_at
+        FROM todos
+        WHERE (updated_at, id) > ($1, $2) AND (assigned_to = $3 OR assigned_by = $3) AND ($5::INTEGER IS NULL OR org_id = $5)
+        ORDER BY updated_at, id
+        LIMIT $4
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, SyncedTodo>(query)
+            .bind(after.updated_at)
+            .bind(after.id)
+            .bind(user_id)
+            .bind(limit)
+            .bind(tenant)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| sync_error("get changed to-do items", e))
+}
+
+
+/// Gets a user with its version.
+#[impl_transaction(SqlxPostGresDescriptor, GetSyncedUser, get_synced_user)]
+async fn get_synced_user(id: i32) -> Result<SyncedUser, NanoServiceError> {
+    let query = r#"
+        SELECT id, confirmed, username, email, first_name, last_name, user_role, date_created,
+               last_logged_in, blocked, '/avatars/' || id || '?v=' || avatar_version AS avatar_url, updated_at
+        FROM users
+        WHERE id = $1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, SyncedUser>(query)
+            .bind(id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| sync_error("get user", e))?
+    .ok_or_else(|| NanoServiceError::new(format!("User {} not found", id), NanoServiceErrorStatus::NotFound))
+}
+
+
+/// Gets a to-do item with its version.
+#[impl_transaction(SqlxPostGresDescriptor, GetSyncedToDoItem, get_synced_to_do_item)]
+async fn get_synced_to_do_item(todo_id: i32) -> Result<SyncedTodo, NanoServiceError> {
+    let tenant = current_tenant()?;
+    let query = r#"
+        SELECT id, name, due_date, assigned_by, assigned_to, description, date_assigned, date_finished, finished,
+               requires_review, pending_review, review_comment, priority, status, updated_at
+        FROM todos
+        WHERE id = $1 AND ($2::INTEGER IS NULL OR org_id = $2)
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, SyncedTodo>(query)
+            .bind(todo_id)
+            .bind(tenant)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| sync_error("get to-do item", e))?
+    .ok_or_else(|| NanoServiceError::new(format!("To-do item {} not found", todo_id), NanoServiceErrorStatus::NotFound))
+}
@@ -0,0 +1,19 @@
+//! Defines transaction traits for reading the users and to-do items changed since a sync position.
+//!
+//! ## Notes
+//! - Rows come back in `(updated_at, id)` order starting after the position, so a client can page
+//!   through them without missing rows that changed in the same instant.
+//! - Deletions are read from the `tombstones` table with `GetTombstones`.
+//! - `GetSyncedUser` and `GetSyncedToDoItem` read a single row with its version, to report the
+//!   current state when an update is refused because of an old `If-Match` version.
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::sync::{SyncPosition, SyncedUser, SyncedTodo};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    GetUsersChangedSince => get_users_changed_since(user_id: Option<i32>, org_id: Option<i32>, after: SyncPosition, limit: i64) -> Vec<SyncedUser>,
+    GetToDoItemsChangedSince => get_to_do_items_changed_since(user_id: i32, after: SyncPosition, limit: i64) -> Vec<SyncedTodo>,
+    GetSyncedUser => get_synced_user(id: i32) -> SyncedUser,
+    GetSyncedToDoItem => get_synced_to_do_item(todo_id: i32) -> SyncedTodo
+);
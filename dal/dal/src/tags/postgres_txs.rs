@@ -0,0 +1,158 @@
+//! Implements the tag transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::tags::{NewTag, Tag, TodoTag};
+use kernel::to_do_items::Todo;
+use kernel::tenancy::current_tenant;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::tags::tx_definitions::{
+    CreateTag,
+    GetTags,
+    AttachTag,
+    DetachTag,
+    GetTagsForToDoItem,
+    GetToDoItemsForUserByTag
+};
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CreateTag, create_tag)]
+async fn create_tag(tag: NewTag) -> Result<Tag, NanoServiceError> {
+    let query = r#"
+        INSERT INTO tags (name)
+        VALUES ($1)
+        RETURNING id, name
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, Tag>(query)
+            .bind(&tag.name)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to create tag: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetTags, get_tags)]
+async fn get_tags() -> Result<Vec<Tag>, NanoServiceError> {
+    let query = r#"
+        SELECT id, name
+        FROM tags
+        ORDER BY name
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Tag>(query)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to fetch tags: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, AttachTag, attach_tag)]
+async fn attach_tag(todo_id: i32, tag_id: i32) -> Result<TodoTag, NanoServiceError> {
+    let query = r#"
+        INSERT INTO todo_tags (todo_id, tag_id)
+        VALUES ($1, $2)
+        ON CONFLICT ON CONSTRAINT unique_todo_tag
+        DO UPDATE SET tag_id = EXCLUDED.tag_id
+        RETURNING id, todo_id, tag_id
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, TodoTag>(query)
+            .bind(todo_id)
+            .bind(tag_id)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to attach tag to to-do item: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, DetachTag, detach_tag)]
+async fn detach_tag(todo_id: i32, tag_id: i32) -> Result<bool, NanoServiceError> {
+    let tenant = current_tenant()?;
+    let query = r#"
+        DELETE FROM todo_tags
+        WHERE todo_id = $1 AND tag_id = $2 AND ($3::INTEGER IS NULL OR todo_id IN (SELECT id FROM todos WHERE org_id = $3))
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(todo_id)
+            .bind(tag_id)
+            .bind(tenant)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to detach tag from to-do item: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetTagsForToDoItem, get_tags_for_to_do_item)]
+async fn get_tags_for_to_do_item(todo_id: i32) -> Result<Vec<Tag>, NanoServiceError> {
+    let tenant = current_tenant()?;
+    let query = r#"
+        SELECT tags.id, tags.name
+        FROM tags
+        JOIN todo_tags ON todo_tags.tag_id = tags.id
+        WHERE todo_tags.todo_id = $1 AND ($2::INTEGER IS NULL OR todo_tags.todo_id IN (SELECT id FROM todos WHERE org_id = $2))
+        ORDER BY tags.name
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Tag>(query)
+            .bind(todo_id)
+            .bind(tenant)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to fetch tags for to-do item: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetToDoItemsForUserByTag, get_to_do_items_for_user_by_tag)]
+async fn get_to_do_items_for_user_by_tag(user_id: i32, org_id: i32, tag: String) -> Result<Vec<Todo>, NanoServiceError> {
+    let query = r#"
+        SELECT todos.id, todos.name, todos.due_date, todos.assigned_by, todos.assigned_to,
+               todos.description, todos.date_assigned, todos.date_finished, todos.finished,
+               todos.requires_review, todos.pending_review, todos.review_comment, todos.priority, todos.status
+        FROM todos
+        JOIN todo_tags ON todo_tags.todo_id = todos.id
+        JOIN tags ON tags.id = todo_tags.tag_id
+        WHERE todos.assigned_to = $1 AND tags.name = $2 AND todos.org_id = $3
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Todo>(query)
+            .bind(user_id)
+            .bind(&tag)
+            .bind(org_id)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to get to-do items by tag: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
@@ -0,0 +1,21 @@
+//! Defines transaction traits for interacting with the `tags` and `todo_tags` tables.
+//!
+//! # Overview
+//! This file uses the `define_dal_transactions` macro to create traits for creating tags,
+//! attaching and detaching them from to-do items, and listing to-do items by tag.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::tags::{NewTag, Tag, TodoTag};
+use kernel::to_do_items::Todo;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreateTag => create_tag(tag: NewTag) -> Tag,
+    GetTags => get_tags() -> Vec<Tag>,
+    AttachTag => attach_tag(todo_id: i32, tag_id: i32) -> TodoTag,
+    DetachTag => detach_tag(todo_id: i32, tag_id: i32) -> bool,
+    GetTagsForToDoItem => get_tags_for_to_do_item(todo_id: i32) -> Vec<Tag>,
+    GetToDoItemsForUserByTag => get_to_do_items_for_user_by_tag(user_id: i32, org_id: i32, tag: String) -> Vec<Todo>
+);
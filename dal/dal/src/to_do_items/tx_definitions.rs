@@ -12,15 +12,35 @@
 //! ## Notes
 //! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
 //! - Adding a new database backend requires implementing these traits for the corresponding descriptor.
-use kernel::to_do_items::{NewTodo, Todo};
+//! - `ImportToDoItems` creates every item in one transaction, so a failed import leaves nothing behind.
+//! - `TransitionToDoItem` only moves an item that is still in the status it was read in.
+//! - The items created by `CreateToDoItem` and `ImportToDoItems` belong to the organization given,
+//!   and `GetToDoItemsForUser` and `SearchToDoItems` only return the items of that organization.
+//! - The other transactions reading or changing items, apart from `CountToDoItems` and
+//!   `ListExportedToDoItems`, only reach the items of `kernel::tenancy::current_tenant` and error
+//!   if no tenant is set, callers working across organizations use `kernel::tenancy::without_tenant`.
+use kernel::to_do_items::{NewTodo, Todo, ExportedTodo, ToDoItemPatch, ToDoSearch, TodoStatus};
+use kernel::sync::SyncedTodo;
+use kernel::chrono::NaiveDateTime;
 use crate::define_dal_transactions;
 
 
 define_dal_transactions!(
-    CreateToDoItem => create_to_do_item(todo: NewTodo) -> Todo,
+    CreateToDoItem => create_to_do_item(todo: NewTodo, org_id: i32) -> Todo,
     DeleteToDoItem => delete_to_do_item(id: i32) -> bool,
-    GetToDoItemsForUser => get_to_do_items_for_user(user_id: i32) -> Vec<Todo>,
+    GetToDoItemsForUser => get_to_do_items_for_user(user_id: i32, org_id: i32) -> Vec<Todo>,
     GetPendingToDoItemsForUser => get_pending_to_do_items_for_user(user_id: i32) -> Vec<Todo>,
     ReAssignToDoItem => re_assign_to_do_item(todo_id: i32, new_assigned_to: i32) -> Todo,
-    CompleteToDoItem => complete_to_do_item(todo_id: i32) -> Todo
+    CompleteToDoItem => complete_to_do_item(todo_id: i32) -> Todo,
+    CountOpenToDoItemsForUser => count_open_to_do_items_for_user(user_id: i32) -> i64,
+    CountToDoItems => count_to_do_items() -> i64,
+    GetToDoItem => get_to_do_item(todo_id: i32) -> Todo,
+    ApproveToDoItem => approve_to_do_item(todo_id: i32) -> Todo,
+    RejectToDoItem => reject_to_do_item(todo_id: i32, comment: String) -> Todo,
+    GetToDoItemsDueBetween => get_to_do_items_due_between(user_id: i32, from: NaiveDateTime, to: NaiveDateTime) -> Vec<Todo>,
+    UpdateToDoItem => update_to_do_item(todo_id: i32, patch: ToDoItemPatch, if_match: Option<NaiveDateTime>) -> Option<SyncedTodo>,
+    SearchToDoItems => search_to_do_items(user_id: i32, org_id: i32, search: ToDoSearch, limit: i64) -> Vec<Todo>,
+    ImportToDoItems => import_to_do_items(todos: Vec<NewTodo>, org_id: i32) -> Vec<Todo>,
+    ListExportedToDoItems => list_exported_to_do_items(after_id: Option<i32>, limit: i64) -> Vec<ExportedTodo>,
+    TransitionToDoItem => transition_to_do_item(todo_id: i32, from: TodoStatus, to: TodoStatus) -> Option<Todo>
 );
@@ -0,0 +1,61 @@
+//! Implements the to-do comment transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::todo_comments::{NewToDoComment, ToDoComment};
+use kernel::tenancy::current_tenant;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::todo_comments::tx_definitions::{CreateToDoComment, GetToDoComments};
+
+
+fn comment_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+/// Adds a comment, returning `None` if a comment from the same email was already added.
+#[impl_transaction(SqlxPostGresDescriptor, CreateToDoComment, create_to_do_comment)]
+async fn create_to_do_comment(comment: NewToDoComment) -> Result<Option<ToDoComment>, NanoServiceError> {
+    let query = r#"
+        INSERT INTO todo_comments (todo_id, author_id, body, message_id)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (message_id) DO NOTHING
+        RETURNING id, todo_id, author_id, body, message_id, date_created
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, ToDoComment>(query)
+            .bind(comment.todo_id)
+            .bind(comment.author_id)
+            .bind(&comment.body)
+            .bind(&comment.message_id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| comment_error("add comment", e))
+}
+
+
+/// Gets the comments on a to-do item, oldest first.
+#[impl_transaction(SqlxPostGresDescriptor, GetToDoComments, get_to_do_comments)]
+async fn get_to_do_comments(todo_id: i32) -> Result<Vec<ToDoComment>, NanoServiceError> {
+    let tenant = current_tenant()?;
+    let query = r#"
+        SELECT id, todo_id, author_id, body, message_id, date_created
+        FROM todo_comments
+        WHERE todo_id = $1 AND ($2::INTEGER IS NULL OR todo_id IN (SELECT id FROM todos WHERE org_id = $2))
+        ORDER BY date_created, id
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, ToDoComment>(query)
+            .bind(todo_id)
+            .bind(tenant)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| comment_error("get comments", e))
+}
@@ -0,0 +1,12 @@
+//! Defines transaction traits for interacting with the `todo_comments` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::todo_comments::{NewToDoComment, ToDoComment};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreateToDoComment => create_to_do_comment(comment: NewToDoComment) -> Option<ToDoComment>,
+    GetToDoComments => get_to_do_comments(todo_id: i32) -> Vec<ToDoComment>
+);
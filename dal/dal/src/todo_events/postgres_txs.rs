@@ -0,0 +1,58 @@
+//! Implements the to-do history transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::todo_events::{NewTodoEvent, TodoEvent};
+use sqlx::types::Json;
+use kernel::tenancy::current_tenant;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::todo_events::tx_definitions::{RecordToDoEvent, ListToDoEvents};
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RecordToDoEvent, record_to_do_event)]
+async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+    let query = r#"
+        INSERT INTO todo_events (todo_id, actor_id, kind, details)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, todo_id, actor_id, kind, details, date_created
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, TodoEvent>(query)
+            .bind(event.todo_id)
+            .bind(event.actor_id)
+            .bind(event.kind)
+            .bind(Json(&event.details))
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to record to-do event: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+/// Lists the history of a to-do item, the oldest change first.
+#[impl_transaction(SqlxPostGresDescriptor, ListToDoEvents, list_to_do_events)]
+async fn list_to_do_events(todo_id: i32) -> Result<Vec<TodoEvent>, NanoServiceError> {
+    let tenant = current_tenant()?;
+    let query = r#"
+        SELECT id, todo_id, actor_id, kind, details, date_created
+        FROM todo_events
+        WHERE todo_id = $1 AND ($2::INTEGER IS NULL OR todo_id IN (SELECT id FROM todos WHERE org_id = $2))
+        ORDER BY id
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, TodoEvent>(query)
+            .bind(todo_id)
+            .bind(tenant)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to list to-do events: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
@@ -0,0 +1,12 @@
+//! Defines transaction traits for interacting with the `todo_events` table.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::todo_events::{NewTodoEvent, TodoEvent};
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    RecordToDoEvent => record_to_do_event(event: NewTodoEvent) -> TodoEvent,
+    ListToDoEvents => list_to_do_events(todo_id: i32) -> Vec<TodoEvent>
+);
@@ -0,0 +1,36 @@
+//! Implements the tombstone transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::tombstones::Tombstone;
+use kernel::chrono::NaiveDateTime;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::retry_transient;
+use crate::tombstones::tx_definitions::GetTombstones;
+
+
+/// Gets up to `limit` tombstones written at or after `since` with an ID above `after_id`, oldest first,
+/// only those of the organization with `org_id` if it is given.
+#[impl_transaction(SqlxPostGresDescriptor, GetTombstones, get_tombstones)]
+async fn get_tombstones(org_id: Option<i32>, since: NaiveDateTime, after_id: i32, limit: i64) -> Result<Vec<Tombstone>, NanoServiceError> {
+    let query = r#"
+        SELECT id, entity_type, entity_id, deleted_at
+        FROM tombstones
+        WHERE deleted_at >= $1 AND id > $2 AND ($4::INTEGER IS NULL OR org_id = $4)
+        ORDER BY id
+        LIMIT $3
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Tombstone>(query)
+            .bind(since)
+            .bind(after_id)
+            .bind(limit)
+            .bind(org_id)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to get tombstones: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
@@ -0,0 +1,13 @@
+//! Defines transaction traits for reading the `tombstones` table.
+//!
+//! ## Notes
+//! - Tombstones are written by the delete transactions of the entities they record, so there is no create.
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+use kernel::tombstones::Tombstone;
+use kernel::chrono::NaiveDateTime;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    GetTombstones => get_tombstones(org_id: Option<i32>, since: NaiveDateTime, after_id: i32, limit: i64) -> Vec<Tombstone>
+);
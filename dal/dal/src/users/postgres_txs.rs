@@ -1,20 +1,32 @@
 //! Implements transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
 //!
 //! # Overview
-//! This file implements user-related transaction traits (`CreateUser`, `ConfirmUser`, `GetUser`)
+//! This file implements user-related transaction traits (`CreateUser`, `CreateUserWithRolePermission`, `ConfirmUser`, `GetUser`)
 //! for PostgreSQL using `SqlxPostGresDescriptor`. Each implementation maps to a specific database operation.
 
 use dal_tx_impl::impl_transaction;
-use kernel::users::{NewUser, User, UserProfile, TrimmedUser, UserRole};
-use kernel::role_permissions::RolePermission;
+use kernel::users::{NewUser, User, UserProfile, UserProfilePatch, PublicUser, TrimmedUser, UserRole, RecipientProfile, ExportedUserProfile};
+use kernel::sync::SyncedUser;
+use kernel::chrono::NaiveDateTime;
+use kernel::role_permissions::{RolePermission, NewRolePermission};
+use kernel::avatars::avatar_url;
+use kernel::organizations::DEFAULT_ORG_ID;
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
-use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor, contains_pattern};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::connections::unit_of_work::WithTransaction;
+use crate::role_permissions::postgres_tsx::insert_role_permission;
+use crate::email_outbox::postgres_txs::{insert_outbox_email, queue_error};
+use kernel::email_outbox::NewOutboxEmail;
 use crate::users::tx_definitions::{
-    CreateUser, ConfirmUser, GetUser, GetUserByEmail, GetUserProfileByEmail, GetAllUserProfiles, BlockUser, 
+    CreateUser, CreateUserWithRolePermission, CreateUserWithConfirmationEmail, ConfirmUser, GetUser, GetUserByEmail, GetUserProfileByEmail, GetRecipientProfile, GetAllUserProfiles, BlockUser, 
     UnblockUser, GetUserByUuid, ResetPassword, UpdateUuid, UpdateUserUsername, 
-    UpdateUserEmail, UpdateUserFirstName, UpdateUserLasttName, DeleteUser
+    UpdateUserEmail, UpdateUserFirstName, UpdateUserLasttName, UpdateUserProfile, SearchUsers, CountUsers, UpdateLastLoggedIn, SetUserAvatar, DeleteUser,
+    StreamUserProfiles
 };
-use sqlx::Row;
+use utils::export_stream::RowStream;
+use futures_util::StreamExt;
+use sqlx::{PgExecutor, Row};
 use std::collections::HashMap;
 
 /// Implements the `CreateUser` trait for the `SqlxPostGresDescriptor`.
@@ -29,13 +41,28 @@ use std::collections::HashMap;
 /// - `Err(NanoServiceError)`: If the insert operation fails.
 #[impl_transaction(SqlxPostGresDescriptor, CreateUser, create_user)]
 async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
+    insert_user(&*SQLX_POSTGRES_POOL, user).await
+}
+
+/// Inserts a new user using the given executor, either the pool or an open transaction. The user
+/// joins the default organization in the same statement.
+pub(crate) async fn insert_user<'e, E: PgExecutor<'e>>(executor: E, user: NewUser) -> Result<User, NanoServiceError> {
     let query = r#"
-        INSERT INTO users (
-            username, email, first_name, last_name, user_role, password, uuid, date_created, last_logged_in, blocked, confirmed
-        ) VALUES (
-            $1, $2, $3, $4, $5, $6, $7, NOW(), NOW(), $8, $9
+        WITH created AS (
+            INSERT INTO users (
+                username, email, first_name, last_name, user_role, password, uuid, date_created, last_logged_in, blocked, confirmed
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, NOW(), NOW(), $8, $9
+            )
+            RETURNING id, username, email, first_name, last_name, user_role, password, uuid, date_created, last_logged_in, blocked, confirmed,
+                      avatar_version
+        ), joined AS (
+            INSERT INTO org_memberships (org_id, user_id)
+            SELECT $10, id FROM created
         )
-        RETURNING id, username, email, first_name, last_name, user_role, password, uuid, date_created, last_logged_in, blocked, confirmed
+        SELECT id, username, email, first_name, last_name, user_role, password, uuid, date_created, last_logged_in, blocked, confirmed,
+               avatar_version
+        FROM created
     "#;
 
     sqlx::query_as::<_, User>(query)
@@ -48,7 +75,8 @@ async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
         .bind(user.uuid)
         .bind(user.blocked)
         .bind(user.confirmed)
-        .fetch_one(&*SQLX_POSTGRES_POOL)
+        .bind(DEFAULT_ORG_ID)
+        .fetch_one(executor)
         .await
         .map_err(|e| NanoServiceError::new(
             format!("Failed to create user: {}", e),
@@ -56,6 +84,56 @@ async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
         ))
 }
 
+/// Implements the `CreateUserWithRolePermission` trait for the `SqlxPostGresDescriptor`.
+///
+/// Inserts a new user and the role permission for their role in one transaction, so a failure
+/// part way through does not leave a user without a role.
+///
+/// # Arguments
+/// - `user`: The new user details.
+///
+/// # Returns
+/// - `Ok(User)`: The created user record.
+/// - `Err(NanoServiceError)`: If either insert fails, in which case neither is kept.
+#[impl_transaction(SqlxPostGresDescriptor, CreateUserWithRolePermission, create_user_with_role_permission)]
+async fn create_user_with_role_permission(user: NewUser) -> Result<User, NanoServiceError> {
+    SqlxPostGresDescriptor::with_transaction(|transaction| Box::pin(async move {
+        let user = insert_user(&mut **transaction, user).await?;
+        let role_permission = NewRolePermission {
+            user_id: user.id,
+            role: user.user_role.clone(),
+        };
+        insert_role_permission(&mut **transaction, role_permission).await?;
+        Ok(user)
+    })).await
+}
+
+/// Implements the `CreateUserWithConfirmationEmail` trait for the `SqlxPostGresDescriptor`.
+///
+/// Inserts a new user, the role permission for their role and their confirmation email in the
+/// outbox in one transaction, so the user is only kept if the email will be sent.
+///
+/// # Arguments
+/// - `user`: The new user details.
+/// - `email`: The confirmation email to queue.
+///
+/// # Returns
+/// - `Ok(User)`: The created user record.
+/// - `Err(NanoServiceError)`: If any insert fails, in which case none are kept.
+#[impl_transaction(SqlxPostGresDescriptor, CreateUserWithConfirmationEmail, create_user_with_confirmation_email)]
+async fn create_user_with_confirmation_email(user: NewUser, email: NewOutboxEmail) -> Result<User, NanoServiceError> {
+    SqlxPostGresDescriptor::with_transaction(|transaction| Box::pin(async move {
+        let user = insert_user(&mut **transaction, user).await?;
+        let role_permission = NewRolePermission {
+            user_id: user.id,
+            role: user.user_role.clone(),
+        };
+        insert_role_permission(&mut **transaction, role_permission).await?;
+        insert_outbox_email(&mut **transaction, &email).await.map_err(queue_error)?;
+        Ok(user)
+    })).await
+}
+
 /// Implements the `ConfirmUser` trait for the `SqlxPostGresDescriptor`.
 ///
 /// Marks a user as confirmed based on their UUID.
@@ -70,18 +148,20 @@ async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
 async fn confirm_user(uuid: String) -> Result<bool, NanoServiceError> {
     let query = r#"
         UPDATE users
-        SET confirmed = true
+        SET confirmed = true, updated_at = NOW()
         WHERE uuid = $1
     "#;
 
-    let result = sqlx::query(query)
-        .bind(uuid)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to confirm user: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&uuid)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to confirm user: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     Ok(result.rows_affected() > 0)
 }
@@ -99,19 +179,22 @@ async fn confirm_user(uuid: String) -> Result<bool, NanoServiceError> {
 #[impl_transaction(SqlxPostGresDescriptor, GetUser, get_user)]
 async fn get_user(id: i32) -> Result<User, NanoServiceError> {
     let query = r#"
-        SELECT id, confirmed, username, email, first_name, last_name, user_role, password, uuid, date_created, last_logged_in, blocked
+        SELECT id, confirmed, username, email, first_name, last_name, user_role, password, uuid, date_created, last_logged_in, blocked,
+               avatar_version
         FROM users
         WHERE id = $1
     "#;
 
-    sqlx::query_as::<_, User>(query)
-        .bind(id)
-        .fetch_one(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to retrieve user: {}", e),
-            NanoServiceErrorStatus::NotFound,
-        ))
+    retry_transient(|| {
+        sqlx::query_as::<_, User>(query)
+            .bind(id)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to retrieve user: {}", e),
+        NanoServiceErrorStatus::NotFound,
+    ))
 }
 
 
@@ -127,19 +210,54 @@ async fn get_user(id: i32) -> Result<User, NanoServiceError> {
 #[impl_transaction(SqlxPostGresDescriptor, GetUserByEmail, get_user_by_email)]
 async fn get_user_by_email(email: String) -> Result<User, NanoServiceError> {
     let query = r#"
-        SELECT id, confirmed, username, email, first_name, last_name, user_role, password, uuid, date_created, last_logged_in, blocked
+        SELECT id, confirmed, username, email, first_name, last_name, user_role, password, uuid, date_created, last_logged_in, blocked,
+               avatar_version
         FROM users
         WHERE email = $1
     "#;
 
-    sqlx::query_as::<_, User>(query)
-        .bind(email)
-        .fetch_one(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to retrieve user: {}", e),
-            NanoServiceErrorStatus::NotFound,
-        ))
+    retry_transient(|| {
+        sqlx::query_as::<_, User>(query)
+            .bind(&email)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to retrieve user: {}", e),
+        NanoServiceErrorStatus::NotFound,
+    ))
+}
+
+
+
+/// Implements the `GetRecipientProfile` trait for the `SqlxPostGresDescriptor`.
+/// 
+/// Retrieves the details used to personalise emails sent to an address.
+/// 
+/// # Arguments
+/// - `email`: The email the message is sent to.
+/// 
+/// # Returns
+/// - `Ok(Some(RecipientProfile))`: The details of the user with the email.
+/// - `Ok(None)`: If no user has the email.
+#[impl_transaction(SqlxPostGresDescriptor, GetRecipientProfile, get_recipient_profile)]
+async fn get_recipient_profile(email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+    let query = r#"
+        SELECT first_name, username, locale, timezone
+        FROM users
+        WHERE email = $1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, RecipientProfile>(query)
+            .bind(&email)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to retrieve recipient profile: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
 }
 
 
@@ -160,21 +278,23 @@ pub async fn get_user_profile_by_email(
     let query = r#"
         SELECT 
             users.id, users.username, users.email, users.first_name, users.last_name, users.user_role, 
-            users.date_created, users.last_logged_in, users.blocked, users.uuid,
+            users.date_created, users.last_logged_in, users.blocked, users.uuid, users.confirmed, users.avatar_version,
             role_permissions.id AS role_id, role_permissions.user_id, role_permissions.role
         FROM users
         LEFT JOIN role_permissions ON users.id = role_permissions.user_id
         WHERE users.email = $1
     "#;
 
-    let rows = sqlx::query(query)
-        .bind(&email)
-        .fetch_all(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to retrieve user: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let rows = retry_transient(|| {
+        sqlx::query(query)
+            .bind(&email)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to retrieve user: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     if rows.is_empty() {
         return Err(NanoServiceError::new(
@@ -202,7 +322,8 @@ pub async fn get_user_profile_by_email(
                     last_logged_in: row.get("last_logged_in"),
                     blocked: row.get("blocked"),
                     uuid: row.get("uuid"),
-                    confirmed: row.get("confirmed")
+                    confirmed: row.get("confirmed"),
+                    avatar_url: row.get::<Option<String>, _>("avatar_version").map(|version| avatar_url(user_id, &version))
                 },
                 role_permissions: vec![],
             });
@@ -242,24 +363,57 @@ pub async fn get_user_profile_by_email(
 }
 
 
+/// Implements the `StreamUserProfiles` trait for the `SqlxPostGresDescriptor`.
+///
+/// Reads the users with a cursor, so rows are only read from the database as the stream is polled.
+///
+/// # Notes
+/// The stream is not retried on a transient error as rows may already have been sent.
+impl StreamUserProfiles for SqlxPostGresDescriptor {
+    fn stream_user_profiles() -> RowStream<ExportedUserProfile> {
+        let query = r#"
+            SELECT
+                users.id, users.username, users.email, users.first_name, users.last_name, users.user_role,
+                COALESCE(
+                    array_agg(role_permissions.role::TEXT ORDER BY role_permissions.role) FILTER (WHERE role_permissions.role IS NOT NULL),
+                    '{}'
+                ) AS roles,
+                users.confirmed, users.blocked, users.date_created, users.last_logged_in
+            FROM users
+            LEFT JOIN role_permissions ON users.id = role_permissions.user_id
+            GROUP BY users.id
+            ORDER BY users.id
+        "#;
+        Box::pin(sqlx::query_as::<_, ExportedUserProfile>(query)
+            .fetch(&*SQLX_POSTGRES_POOL)
+            .map(|row| row.map_err(|e| NanoServiceError::new(
+                format!("Failed to stream user profiles: {}", e),
+                NanoServiceErrorStatus::Unknown,
+            ))))
+    }
+}
+
+
 #[impl_transaction(SqlxPostGresDescriptor, GetAllUserProfiles, get_all_user_profiles)]
 pub async fn get_all_user_profiles() -> Result<Vec<UserProfile>, NanoServiceError> {
     let query = r#"
         SELECT 
             users.id, users.username, users.email, users.first_name, users.last_name, users.user_role, 
-            users.date_created, users.last_logged_in, users.blocked, users.uuid, users.confirmed,
+            users.date_created, users.last_logged_in, users.blocked, users.uuid, users.confirmed, users.avatar_version,
             role_permissions.id AS role_id, role_permissions.user_id, role_permissions.role
         FROM users
         LEFT JOIN role_permissions ON users.id = role_permissions.user_id
     "#;
     
-    let rows = sqlx::query(query)
-        .fetch_all(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to retrieve user profiles: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let rows = retry_transient(|| {
+        sqlx::query(query)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to retrieve user profiles: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
     let mut user_profiles: Vec<UserProfile> = vec![];
     let mut user_profiles_map: HashMap<i32, UserProfile> = HashMap::new();
 
@@ -283,7 +437,8 @@ pub async fn get_all_user_profiles() -> Result<Vec<UserProfile>, NanoServiceErro
                     last_logged_in: row.get("last_logged_in"),
                     blocked: row.get("blocked"),
                     uuid: row.get("uuid"),
-                    confirmed: row.get("confirmed")
+                    confirmed: row.get("confirmed"),
+                    avatar_url: row.get::<Option<String>, _>("avatar_version").map(|version| avatar_url(user_id, &version))
                 },
                 role_permissions: vec![],
             };
@@ -334,18 +489,20 @@ pub async fn get_all_user_profiles() -> Result<Vec<UserProfile>, NanoServiceErro
 pub async fn block_user(user_id: i32) -> Result<bool, NanoServiceError> {
     let query = r#"
         UPDATE users
-        SET blocked = true
+        SET blocked = true, updated_at = NOW()
         WHERE id = $1
     "#;
 
-    let result = sqlx::query(query)
-        .bind(user_id)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to block user: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(user_id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to block user: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     if result.rows_affected() > 1 {
         return Err(NanoServiceError::new(
@@ -371,18 +528,20 @@ pub async fn block_user(user_id: i32) -> Result<bool, NanoServiceError> {
 pub async fn unblock_user(user_id: i32) -> Result<bool, NanoServiceError> {
     let query = r#"
         UPDATE users
-        SET blocked = false
+        SET blocked = false, updated_at = NOW()
         WHERE id = $1
     "#;
 
-    let result = sqlx::query(query)
-        .bind(user_id)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to unblock user: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(user_id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to unblock user: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     if result.rows_affected() > 1 {
         return Err(NanoServiceError::new(
@@ -410,19 +569,21 @@ async fn get_user_by_uuid(uuid: String) -> Result<User, NanoServiceError> {
     let query = r#"
         SELECT id, confirmed, username, email, password, 
                first_name, last_name, user_role, 
-               date_created, last_logged_in, blocked, uuid
+               date_created, last_logged_in, blocked, uuid, avatar_version
         FROM users
         WHERE uuid = $1
     "#;
 
-    sqlx::query_as::<_, User>(query)
-        .bind(uuid)
-        .fetch_one(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to retrieve user by UUID: {}", e),
-            NanoServiceErrorStatus::NotFound,
-        ))
+    retry_transient(|| {
+        sqlx::query_as::<_, User>(query)
+            .bind(&uuid)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to retrieve user by UUID: {}", e),
+        NanoServiceErrorStatus::NotFound,
+    ))
 }
 
 
@@ -440,19 +601,21 @@ async fn get_user_by_uuid(uuid: String) -> Result<User, NanoServiceError> {
 pub async fn update_uuid(email: String, new_uuid: String) -> Result<bool, NanoServiceError> {
     let query = r#"
         UPDATE users
-        SET uuid = $1
+        SET uuid = $1, updated_at = NOW()
         WHERE email = $2
     "#;
 
-    let result = sqlx::query(query)
-        .bind(new_uuid)
-        .bind(email)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to update uuid: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&new_uuid)
+            .bind(&email)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to update uuid: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     if result.rows_affected() > 1 {
         return Err(NanoServiceError::new(
@@ -483,15 +646,17 @@ pub async fn reset_password(uuid: String, new_password: String) -> Result<bool,
         WHERE uuid = $2
     "#;
 
-    let result = sqlx::query(query)
-        .bind(new_password)
-        .bind(uuid)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to reset password: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&new_password)
+            .bind(&uuid)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to reset password: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     if result.rows_affected() > 1 {
         return Err(NanoServiceError::new(
@@ -517,19 +682,21 @@ pub async fn reset_password(uuid: String, new_password: String) -> Result<bool,
 async fn update_user_username(id: i32, username: String) -> Result<bool, NanoServiceError> {
     let query = r#"
         UPDATE users
-        SET username = $1
+        SET username = $1, updated_at = NOW()
         WHERE id = $2
     "#;
 
-    let result = sqlx::query(query)
-        .bind(username)
-        .bind(id)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to update username: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&username)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to update username: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     Ok(result.rows_affected() > 0)
 }
@@ -547,19 +714,21 @@ async fn update_user_username(id: i32, username: String) -> Result<bool, NanoSer
 async fn update_user_email(id: i32, email: String) -> Result<bool, NanoServiceError> {
     let query = r#"
         UPDATE users
-        SET email = $1
+        SET email = $1, updated_at = NOW()
         WHERE id = $2
     "#;
 
-    let result = sqlx::query(query)
-        .bind(email)
-        .bind(id)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to update email: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&email)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to update email: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     Ok(result.rows_affected() > 0)
 }
@@ -577,19 +746,21 @@ async fn update_user_email(id: i32, email: String) -> Result<bool, NanoServiceEr
 async fn update_user_first_name(id: i32, first_name: String) -> Result<bool, NanoServiceError> {
     let query = r#"
         UPDATE users
-        SET first_name = $1
+        SET first_name = $1, updated_at = NOW()
         WHERE id = $2
     "#;
 
-    let result = sqlx::query(query)
-        .bind(first_name)
-        .bind(id)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to update first name: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&first_name)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to update first name: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     Ok(result.rows_affected() > 0)
 }
@@ -607,24 +778,203 @@ async fn update_user_first_name(id: i32, first_name: String) -> Result<bool, Nan
 async fn update_user_last_name(id: i32, last_name: String) -> Result<bool, NanoServiceError> {
     let query = r#"
         UPDATE users
-        SET last_name = $1
+        SET last_name = $1, updated_at = NOW()
         WHERE id = $2
     "#;
 
-    let result = sqlx::query(query)
-        .bind(last_name)
-        .bind(id)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to update last name: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&last_name)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to update last name: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
 
     Ok(result.rows_affected() > 0)
 }
 
 
+/// Implements `UpdateUserProfile` to change the given profile fields in one statement.
+///
+/// # Arguments
+/// - `id`: The ID of the user to update.
+/// - `patch`: The fields to change, fields left as `None` keep their current value.
+/// - `if_match`: The version the user has to be at, any version if `None`.
+///
+/// # Returns
+/// - `Ok(Some(SyncedUser))`: The updated user with their new version.
+/// - `Ok(None)`: If there is no such user or they are not at the `if_match` version.
+/// - `Err(NanoServiceError)`: `Conflict` if the username or email belongs to another user, or if the operation fails.
+#[impl_transaction(SqlxPostGresDescriptor, UpdateUserProfile, update_user_profile)]
+async fn update_user_profile(id: i32, patch: UserProfilePatch, if_match: Option<NaiveDateTime>) -> Result<Option<SyncedUser>, NanoServiceError> {
+    let query = r#"
+        UPDATE users
+        SET username = COALESCE($2, username),
+            email = COALESCE($3, email),
+            first_name = COALESCE($4, first_name),
+            last_name = COALESCE($5, last_name),
+            updated_at = NOW()
+        WHERE id = $1 AND ($6::TIMESTAMP IS NULL OR updated_at = $6)
+        RETURNING id, confirmed, username, email, first_name, last_name, user_role, date_created,
+                  last_logged_in, blocked, '/avatars/' || id || '?v=' || avatar_version AS avatar_url, updated_at
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, SyncedUser>(query)
+            .bind(id)
+            .bind(&patch.username)
+            .bind(&patch.email)
+            .bind(&patch.first_name)
+            .bind(&patch.last_name)
+            .bind(if_match)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| match e.as_database_error().is_some_and(|e| e.is_unique_violation()) {
+        true => NanoServiceError::new(
+            "The username or email is already taken".to_string(),
+            NanoServiceErrorStatus::Conflict,
+        ),
+        false => NanoServiceError::new(
+            format!("Failed to update user profile: {}", e),
+            NanoServiceErrorStatus::Unknown,
+        )
+    })
+}
+
+
+/// Implements the `UpdateLastLoggedIn` trait for the `SqlxPostGresDescriptor`.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user who has just logged in.
+///
+/// # Returns
+/// - `Ok(bool)`: `true` if the user exists.
+/// - `Err(NanoServiceError)`: If the operation fails.
+///
+/// # Notes
+/// - `updated_at` is left alone so logging in does not send the user to every sync client.
+/// - The login is also added to `login_events`, which is purged by the retention job.
+#[impl_transaction(SqlxPostGresDescriptor, UpdateLastLoggedIn, update_last_logged_in)]
+async fn update_last_logged_in(user_id: i32) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        WITH login_event AS (
+            INSERT INTO login_events (user_id)
+            SELECT id FROM users WHERE id = $1
+        )
+        UPDATE users SET last_logged_in = NOW() WHERE id = $1
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(user_id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to update last login: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+    Ok(result.rows_affected() == 1)
+}
+
+
+/// Implements the `SetUserAvatar` trait for the `SqlxPostGresDescriptor`.
+///
+/// # Arguments
+/// - `id`: The ID of the user.
+/// - `avatar_version`: The version of the user's new avatar, `None` if they no longer have one.
+///
+/// # Returns
+/// - `Ok(bool)`: `true` if the user exists.
+/// - `Err(NanoServiceError)`: If the operation fails.
+///
+/// # Notes
+/// `updated_at` is moved on so sync clients pick up the new `avatar_url`.
+#[impl_transaction(SqlxPostGresDescriptor, SetUserAvatar, set_user_avatar)]
+async fn set_user_avatar(id: i32, avatar_version: Option<String>) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        UPDATE users SET avatar_version = $2, updated_at = NOW() WHERE id = $1
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(id)
+            .bind(&avatar_version)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to set avatar: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+    Ok(result.rows_affected() == 1)
+}
+
+
+/// Implements the `CountUsers` trait for the `SqlxPostGresDescriptor`.
+///
+/// # Returns
+/// - `Ok(i64)`: The number of users, checked against the plan's limit.
+/// - `Err(NanoServiceError)`: If the operation fails.
+#[impl_transaction(SqlxPostGresDescriptor, CountUsers, count_users)]
+async fn count_users() -> Result<i64, NanoServiceError> {
+    retry_transient(|| {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users")
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(format!("Failed to count users: {}", e), NanoServiceErrorStatus::Unknown))
+}
+
+
+/// Implements `SearchUsers` to find the members of an organization whose username, email or name
+/// contains the query.
+///
+/// # Arguments
+/// - `org_id`: The ID of the organization to search the members of.
+/// - `query`: The text to find, case insensitive.
+/// - `limit`: The most users to return.
+/// - `offset`: The number of matching users to skip.
+///
+/// # Returns
+/// - `Ok(Vec<PublicUser>)`: The matching users ordered by username, without their `uuid`.
+/// - `Err(NanoServiceError)`: If the operation fails.
+///
+/// # Notes
+/// The expression matched has to stay the same as `users_search_idx` for the index to be used.
+#[impl_transaction(SqlxPostGresDescriptor, SearchUsers, search_users)]
+async fn search_users(org_id: i32, query: String, limit: i64, offset: i64) -> Result<Vec<PublicUser>, NanoServiceError> {
+    let sql = r#"
+        SELECT id, confirmed, username, email, first_name, last_name, user_role, date_created,
+               last_logged_in, blocked, '/avatars/' || id || '?v=' || avatar_version AS avatar_url
+        FROM users
+        WHERE (username || ' ' || email || ' ' || first_name || ' ' || last_name) ILIKE $1
+          AND id IN (SELECT user_id FROM org_memberships WHERE org_id = $4)
+        ORDER BY username, id
+        LIMIT $2 OFFSET $3
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, PublicUser>(sql)
+            .bind(contains_pattern(&query))
+            .bind(limit)
+            .bind(offset)
+            .bind(org_id)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to search users: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
 /// Implements the `DeleteUser` transaction to delete a user by ID.
 ///
 /// # Arguments
@@ -633,25 +983,52 @@ async fn update_user_last_name(id: i32, last_name: String) -> Result<bool, NanoS
 /// # Returns
 /// - `Ok(true)`: If the deletion was successful (a row was deleted).
 /// - `Ok(false)`: If no user with the given ID was found.
-/// - `Err(NanoServiceError)`: If the operation fails.
+/// - `Err(NanoServiceError)`: `Conflict` if the user is under a legal hold, or if the operation fails.
 ///
 /// # Notes
 /// - The deletion is a hard delete (removes the user entirely).
+/// - Tombstones are written for the user in each of their organizations and for the to-do items
+///   removed with them by the cascade.
+/// - The hold is checked in the same statement as the delete, so a hold placed at the same time is not missed.
 #[impl_transaction(SqlxPostGresDescriptor, DeleteUser, delete_user)]
 async fn delete_user(id: i32) -> Result<bool, NanoServiceError> {
     let query = r#"
-        DELETE FROM users
-        WHERE id = $1
+        WITH held AS (
+            SELECT user_id FROM legal_holds WHERE user_id = $1
+        ), deleted AS (
+            DELETE FROM users
+            WHERE id = $1 AND NOT EXISTS (SELECT 1 FROM held)
+            RETURNING id
+        ), todo_tombstones AS (
+            INSERT INTO tombstones (entity_type, entity_id, org_id)
+            SELECT 'todo', todos.id, todos.org_id
+            FROM todos
+            JOIN deleted ON todos.assigned_by = deleted.id OR todos.assigned_to = deleted.id
+        ), user_tombstones AS (
+            INSERT INTO tombstones (entity_type, entity_id, org_id)
+            SELECT 'user', deleted.id, org_memberships.org_id
+            FROM deleted
+            JOIN org_memberships ON org_memberships.user_id = deleted.id
+        )
+        SELECT (SELECT COUNT(*) FROM deleted), (SELECT COUNT(*) FROM held)
     "#;
 
-    let result = sqlx::query(query)
-        .bind(id)
-        .execute(&*SQLX_POSTGRES_POOL)
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to delete user: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
-
-    Ok(result.rows_affected() > 0)
+    let (deleted, held): (i64, i64) = retry_write(|| {
+        sqlx::query_as(query)
+            .bind(id)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| NanoServiceError::new(
+        format!("Failed to delete user: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+
+    if held > 0 {
+        return Err(NanoServiceError::new(
+            format!("User {} is under a legal hold and cannot be deleted", id),
+            NanoServiceErrorStatus::Conflict,
+        ))
+    }
+    Ok(deleted > 0)
 }
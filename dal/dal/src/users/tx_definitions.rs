@@ -11,18 +11,32 @@
 //! - Provides a consistent interface for interacting with `User` entities in the database.
 //! - Supports dependency injection and ensures flexibility when passing these traits to core 
 //!   functions or services.
+//!
+//! # Notes
+//! `CreateUserWithConfirmationEmail` writes the user and their confirmation email to the outbox in
+//! one transaction, so a user is never created without the email that confirms them.
+//!
+//! `StreamUserProfiles` is written out by hand as it returns the rows as a stream rather than a
+//! future, so the user export never holds the whole table in memory.
 use crate::define_dal_transactions;
-use kernel::users::{NewUser, User, UserProfile};
+use kernel::users::{NewUser, User, UserProfile, UserProfilePatch, RecipientProfile, PublicUser, ExportedUserProfile};
+use kernel::sync::SyncedUser;
+use kernel::email_outbox::NewOutboxEmail;
+use kernel::chrono::NaiveDateTime;
+use utils::export_stream::RowStream;
 
 
 define_dal_transactions!(
     CreateUser => create_user(user: NewUser) -> User,
+    CreateUserWithRolePermission => create_user_with_role_permission(user: NewUser) -> User,
+    CreateUserWithConfirmationEmail => create_user_with_confirmation_email(user: NewUser, email: NewOutboxEmail) -> User,
     GetUser => get_user(id: i32) -> User,
     GetUserByEmail => get_user_by_email(email: String) -> User,
     GetUserByUuid => get_user_by_uuid(uuid: String) -> User,
     DeleteUser => delete_user(id: i32) -> bool,
     ConfirmUser => confirm_user(uuid: String) -> bool,
     GetUserProfileByEmail => get_user_profile_by_email(email: String) -> UserProfile,
+    GetRecipientProfile => get_recipient_profile(email: String) -> Option<RecipientProfile>,
     GetAllUserProfiles => get_all_user_profiles() -> Vec<UserProfile>,
     BlockUser => block_user(id: i32) -> bool,
     UnblockUser => unblock_user(id: i32) -> bool,
@@ -32,4 +46,15 @@ define_dal_transactions!(
     UpdateUserEmail => update_user_email(id: i32, email: String) -> bool,
     UpdateUserFirstName => update_user_first_name(id: i32, first_name: String) -> bool,
     UpdateUserLasttName => update_user_last_name(id: i32, last_name: String) -> bool,
+    UpdateUserProfile => update_user_profile(id: i32, patch: UserProfilePatch, if_match: Option<NaiveDateTime>) -> Option<SyncedUser>,
+    SearchUsers => search_users(org_id: i32, query: String, limit: i64, offset: i64) -> Vec<PublicUser>,
+    CountUsers => count_users() -> i64,
+    UpdateLastLoggedIn => update_last_logged_in(id: i32) -> bool,
+    SetUserAvatar => set_user_avatar(id: i32, avatar_version: Option<String>) -> bool,
 );
+
+
+/// Streams every user's profile ordered by ID, with their roles flattened.
+pub trait StreamUserProfiles {
+    fn stream_user_profiles() -> RowStream<ExportedUserProfile>;
+}
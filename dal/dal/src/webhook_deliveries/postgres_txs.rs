@@ -0,0 +1,46 @@
+//! Implements the webhook delivery ledger for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::retry_write;
+use crate::webhook_deliveries::tx_definitions::{ClaimWebhookDelivery, ReleaseWebhookDelivery};
+
+
+fn webhook_delivery_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("Failed to {}: {}", action, e),
+        NanoServiceErrorStatus::Unknown,
+    )
+}
+
+
+/// Records the delivery, returning `false` if the source already recorded the same ID.
+#[impl_transaction(SqlxPostGresDescriptor, ClaimWebhookDelivery, claim_webhook_delivery)]
+async fn claim_webhook_delivery(source: String, delivery_id: String) -> Result<bool, NanoServiceError> {
+    let result = retry_write(|| {
+        sqlx::query("INSERT INTO webhook_deliveries (source, delivery_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+            .bind(&source)
+            .bind(&delivery_id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_delivery_error("claim webhook delivery", e))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+
+/// Removes the delivery so it can be claimed again.
+#[impl_transaction(SqlxPostGresDescriptor, ReleaseWebhookDelivery, release_webhook_delivery)]
+async fn release_webhook_delivery(source: String, delivery_id: String) -> Result<(), NanoServiceError> {
+    retry_write(|| {
+        sqlx::query("DELETE FROM webhook_deliveries WHERE source = $1 AND delivery_id = $2")
+            .bind(&source)
+            .bind(&delivery_id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_delivery_error("release webhook delivery", e))?;
+
+    Ok(())
+}
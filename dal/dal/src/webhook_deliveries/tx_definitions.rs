@@ -0,0 +1,6 @@
+//! Defines transaction traits for interacting with the `webhook_deliveries` table.
+//!
+//! ## Notes
+//! - The traits are defined in `utils::webhooks` so `process_once` can claim deliveries without
+//!   depending on the DAL, they are re-exported here next to the other transaction traits.
+pub use utils::webhooks::{ClaimWebhookDelivery, ReleaseWebhookDelivery};
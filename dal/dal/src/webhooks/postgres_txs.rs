@@ -0,0 +1,263 @@
+//! Implements the webhook transaction traits for PostgreSQL using the `SqlxPostGresDescriptor`.
+use dal_tx_impl::impl_transaction;
+use kernel::webhooks::{
+    DueWebhookDelivery,
+    NewWebhook,
+    Webhook,
+    WebhookDelivery,
+    WebhookDeliveryStatus,
+    WebhookEvent,
+    WebhookUpdate
+};
+use kernel::chrono::NaiveDateTime;
+use sqlx::types::Json;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::connections::sqlx_postgres::{SQLX_POSTGRES_POOL, SqlxPostGresDescriptor};
+use crate::connections::retry::{retry_transient, retry_write};
+use crate::webhooks::tx_definitions::{
+    CreateWebhook,
+    ListWebhooks,
+    GetWebhook,
+    UpdateWebhook,
+    DeleteWebhook,
+    EnqueueWebhookDeliveries,
+    ClaimDueWebhookDeliveries,
+    MarkWebhookDelivered,
+    RecordWebhookFailure,
+    ListWebhookDeliveries
+};
+
+
+fn webhook_error(action: &str, e: sqlx::Error) -> NanoServiceError {
+    NanoServiceError::new(format!("Failed to {}: {}", action, e), NanoServiceErrorStatus::Unknown)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, CreateWebhook, create_webhook)]
+async fn create_webhook(webhook: NewWebhook) -> Result<Webhook, NanoServiceError> {
+    let query = r#"
+        INSERT INTO webhooks (url, secret, events, created_by)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, url, secret, events, active, created_by, date_created, date_updated
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, Webhook>(query)
+            .bind(&webhook.url)
+            .bind(&webhook.secret)
+            .bind(Json(&webhook.events))
+            .bind(webhook.created_by)
+            .fetch_one(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("register webhook", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, ListWebhooks, list_webhooks)]
+async fn list_webhooks() -> Result<Vec<Webhook>, NanoServiceError> {
+    let query = r#"
+        SELECT id, url, secret, events, active, created_by, date_created, date_updated
+        FROM webhooks
+        ORDER BY id
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Webhook>(query).fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("list webhooks", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, GetWebhook, get_webhook)]
+async fn get_webhook(id: i32) -> Result<Option<Webhook>, NanoServiceError> {
+    let query = r#"
+        SELECT id, url, secret, events, active, created_by, date_created, date_updated
+        FROM webhooks
+        WHERE id = $1
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, Webhook>(query)
+            .bind(id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("get webhook", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, UpdateWebhook, update_webhook)]
+async fn update_webhook(id: i32, update: WebhookUpdate) -> Result<Option<Webhook>, NanoServiceError> {
+    let query = r#"
+        UPDATE webhooks
+        SET url = COALESCE($1, url),
+            events = COALESCE($2, events),
+            active = COALESCE($3, active),
+            date_updated = NOW()
+        WHERE id = $4
+        RETURNING id, url, secret, events, active, created_by, date_created, date_updated
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, Webhook>(query)
+            .bind(update.url.as_deref())
+            .bind(update.events.as_ref().map(Json))
+            .bind(update.active)
+            .bind(id)
+            .fetch_optional(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("update webhook", e))
+}
+
+
+/// Deletes a webhook along with its deliveries.
+#[impl_transaction(SqlxPostGresDescriptor, DeleteWebhook, delete_webhook)]
+async fn delete_webhook(id: i32) -> Result<bool, NanoServiceError> {
+    let result = retry_write(|| {
+        sqlx::query("DELETE FROM webhooks WHERE id = $1")
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("delete webhook", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+/// Queues the event for every active webhook subscribed to it in one statement.
+#[impl_transaction(SqlxPostGresDescriptor, EnqueueWebhookDeliveries, enqueue_webhook_deliveries)]
+async fn enqueue_webhook_deliveries(event: WebhookEvent, event_id: String, payload: serde_json::Value) -> Result<i64, NanoServiceError> {
+    let query = r#"
+        INSERT INTO webhook_outbox (webhook_id, event_id, event, payload)
+        SELECT id, $1, $2, $3 FROM webhooks
+        WHERE active AND events ? $4
+        ON CONFLICT (webhook_id, event_id) DO NOTHING
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&event_id)
+            .bind(event.as_str())
+            .bind(Json(&payload))
+            .bind(event.as_str())
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("queue webhook deliveries", e))?;
+
+    Ok(result.rows_affected() as i64)
+}
+
+
+/// Claims up to `limit` due deliveries by pushing their `next_attempt_at` forward, so another worker
+/// polling at the same time skips them. If the worker dies mid-post the delivery is retried once
+/// the lease runs out. The deliveries of a paused webhook wait until it is resumed.
+#[impl_transaction(SqlxPostGresDescriptor, ClaimDueWebhookDeliveries, claim_due_webhook_deliveries)]
+async fn claim_due_webhook_deliveries(limit: i64) -> Result<Vec<DueWebhookDelivery>, NanoServiceError> {
+    let query = r#"
+        WITH claimed AS (
+            UPDATE webhook_outbox
+            SET next_attempt_at = NOW() + INTERVAL '5 minutes'
+            WHERE id IN (
+                SELECT webhook_outbox.id FROM webhook_outbox
+                JOIN webhooks ON webhooks.id = webhook_outbox.webhook_id
+                WHERE webhook_outbox.status = $1 AND webhook_outbox.next_attempt_at <= NOW() AND webhooks.active
+                ORDER BY webhook_outbox.next_attempt_at
+                LIMIT $2
+                FOR UPDATE OF webhook_outbox SKIP LOCKED
+            )
+            RETURNING id, webhook_id, event_id, event, payload, attempts
+        )
+        SELECT claimed.id, claimed.webhook_id, webhooks.url, webhooks.secret, claimed.event_id,
+               claimed.event, claimed.payload, claimed.attempts
+        FROM claimed
+        JOIN webhooks ON webhooks.id = claimed.webhook_id
+    "#;
+
+    retry_write(|| {
+        sqlx::query_as::<_, DueWebhookDelivery>(query)
+            .bind(WebhookDeliveryStatus::Pending)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("claim due webhook deliveries", e))
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, MarkWebhookDelivered, mark_webhook_delivered)]
+async fn mark_webhook_delivered(id: i32, status_code: i32) -> Result<bool, NanoServiceError> {
+    let query = r#"
+        UPDATE webhook_outbox
+        SET status = $1, attempts = attempts + 1, last_status_code = $2, last_error = NULL, date_delivered = NOW()
+        WHERE id = $3
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(WebhookDeliveryStatus::Delivered)
+            .bind(status_code)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("mark webhook as delivered", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+#[impl_transaction(SqlxPostGresDescriptor, RecordWebhookFailure, record_webhook_failure)]
+async fn record_webhook_failure(id: i32, status_code: Option<i32>, error: String, next_attempt_at: Option<NaiveDateTime>) -> Result<bool, NanoServiceError> {
+    let status = match next_attempt_at {
+        Some(_) => WebhookDeliveryStatus::Pending,
+        None => WebhookDeliveryStatus::Failed
+    };
+    let query = r#"
+        UPDATE webhook_outbox
+        SET status = $1, attempts = attempts + 1, last_status_code = $2, last_error = $3,
+            next_attempt_at = COALESCE($4, next_attempt_at)
+        WHERE id = $5
+    "#;
+
+    let result = retry_write(|| {
+        sqlx::query(query)
+            .bind(&status)
+            .bind(status_code)
+            .bind(&error)
+            .bind(next_attempt_at)
+            .bind(id)
+            .execute(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("record webhook failure", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+
+/// Lists a webhook's deliveries newest first.
+#[impl_transaction(SqlxPostGresDescriptor, ListWebhookDeliveries, list_webhook_deliveries)]
+async fn list_webhook_deliveries(webhook_id: i32, limit: i64) -> Result<Vec<WebhookDelivery>, NanoServiceError> {
+    let query = r#"
+        SELECT id, webhook_id, event_id, event, payload, status, attempts, next_attempt_at,
+               last_status_code, last_error, date_created, date_delivered
+        FROM webhook_outbox
+        WHERE webhook_id = $1
+        ORDER BY id DESC
+        LIMIT $2
+    "#;
+
+    retry_transient(|| {
+        sqlx::query_as::<_, WebhookDelivery>(query)
+            .bind(webhook_id)
+            .bind(limit)
+            .fetch_all(&*SQLX_POSTGRES_POOL)
+    })
+    .await
+    .map_err(|e| webhook_error("list webhook deliveries", e))
+}
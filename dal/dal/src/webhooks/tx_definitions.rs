@@ -0,0 +1,29 @@
+//! Defines transaction traits for interacting with the `webhooks` and `webhook_outbox` tables.
+//!
+//! # Overview
+//! This file uses the `define_dal_transactions` macro to create traits for managing the registered
+//! webhooks, queueing an event for every webhook subscribed to it, claiming the deliveries that are
+//! due and recording the outcome of each attempt.
+//!
+//! ## Notes
+//! - These traits are designed to be implemented by database descriptor structs, such as `SqlxPostGresDescriptor`.
+//! - `next_attempt_at` of `None` in `RecordWebhookFailure` marks the delivery as permanently failed.
+//! - `EnqueueWebhookDeliveries` skips a webhook that already has a delivery of the event, so an event
+//!   seen twice is only delivered once.
+use kernel::webhooks::{DueWebhookDelivery, NewWebhook, Webhook, WebhookDelivery, WebhookEvent, WebhookUpdate};
+use kernel::chrono::NaiveDateTime;
+use crate::define_dal_transactions;
+
+
+define_dal_transactions!(
+    CreateWebhook => create_webhook(webhook: NewWebhook) -> Webhook,
+    ListWebhooks => list_webhooks() -> Vec<Webhook>,
+    GetWebhook => get_webhook(id: i32) -> Option<Webhook>,
+    UpdateWebhook => update_webhook(id: i32, update: WebhookUpdate) -> Option<Webhook>,
+    DeleteWebhook => delete_webhook(id: i32) -> bool,
+    EnqueueWebhookDeliveries => enqueue_webhook_deliveries(event: WebhookEvent, event_id: String, payload: serde_json::Value) -> i64,
+    ClaimDueWebhookDeliveries => claim_due_webhook_deliveries(limit: i64) -> Vec<DueWebhookDelivery>,
+    MarkWebhookDelivered => mark_webhook_delivered(id: i32, status_code: i32) -> bool,
+    RecordWebhookFailure => record_webhook_failure(id: i32, status_code: Option<i32>, error: String, next_attempt_at: Option<NaiveDateTime>) -> bool,
+    ListWebhookDeliveries => list_webhook_deliveries(webhook_id: i32, limit: i64) -> Vec<WebhookDelivery>
+);
@@ -0,0 +1,24 @@
+//! Picks the analytics sink to use from the config.
+//!
+//! # Variables
+//! * `ANALYTICS_SINK` - `log` for `LogAnalyticsSink`, `segment` for `SegmentAnalyticsSink`, anything
+//!   else or unset turns analytics off
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::analytics::{AnalyticsEvent, AnalyticsSink};
+use crate::analytics::engine_log::LogAnalyticsSink;
+use crate::analytics::engine_segment::SegmentAnalyticsSink;
+
+
+/// Sends events to the sink named by `ANALYTICS_SINK`.
+pub struct ConfiguredAnalyticsSink;
+
+impl AnalyticsSink for ConfiguredAnalyticsSink {
+    async fn track<Y: GetConfigVariable>(event: &AnalyticsEvent) -> Result<(), NanoServiceError> {
+        match Y::get_config_variable("ANALYTICS_SINK".to_string()).as_deref().map(str::trim) {
+            Ok("log") => LogAnalyticsSink::track::<Y>(event).await,
+            Ok("segment") => SegmentAnalyticsSink::track::<Y>(event).await,
+            _ => Ok(()),
+        }
+    }
+}
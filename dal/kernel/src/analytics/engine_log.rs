@@ -0,0 +1,19 @@
+//! Writes events to the log, for local development and for shipping with the rest of the logs.
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::analytics::{AnalyticsEvent, AnalyticsSink};
+
+
+/// Prints each event as a line of JSON prefixed with `analytics:`.
+pub struct LogAnalyticsSink;
+
+impl AnalyticsSink for LogAnalyticsSink {
+    async fn track<Y: GetConfigVariable>(event: &AnalyticsEvent) -> Result<(), NanoServiceError> {
+        let line = serde_json::to_string(event).map_err(|e| NanoServiceError::new(
+            format!("Failed to serialize analytics event: {}", e),
+            NanoServiceErrorStatus::Unknown
+        ))?;
+        println!("analytics: {}", line);
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+//! Sinks for tests.
+use std::sync::Mutex;
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::analytics::{AnalyticsEvent, AnalyticsSink};
+
+
+/// Every event sent to `RecordAnalyticsMock`, shared by the tests of a crate.
+pub static RECORDED_EVENTS: Mutex<Vec<AnalyticsEvent>> = Mutex::new(Vec::new());
+
+
+/// Drops every event.
+pub struct NoAnalyticsMock;
+
+impl AnalyticsSink for NoAnalyticsMock {
+    async fn track<Y: GetConfigVariable>(_event: &AnalyticsEvent) -> Result<(), NanoServiceError> {
+        Ok(())
+    }
+}
+
+
+/// Keeps every event in `RECORDED_EVENTS`.
+pub struct RecordAnalyticsMock;
+
+impl AnalyticsSink for RecordAnalyticsMock {
+    async fn track<Y: GetConfigVariable>(event: &AnalyticsEvent) -> Result<(), NanoServiceError> {
+        RECORDED_EVENTS.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
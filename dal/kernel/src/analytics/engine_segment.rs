@@ -0,0 +1,89 @@
+//! Sends events to a Segment-style HTTP tracking API.
+//!
+//! # Overview
+//! Each event is posted as a `track` call authenticated with the write key as the basic auth
+//! username, which Segment and the providers compatible with it such as RudderStack accept. The
+//! post is made in the background so a request never waits on the provider, and a failed post is
+//! logged and dropped.
+//!
+//! # Variables
+//! * `ANALYTICS_WRITE_KEY` - The write key of the source events are sent to, required
+//! * `ANALYTICS_ENDPOINT` - The track endpoint, defaults to `DEFAULT_SEGMENT_ENDPOINT`
+use serde_json::{json, Value};
+use std::sync::LazyLock;
+use std::time::Duration;
+use utils::config::GetConfigVariable;
+use utils::log_limited;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::analytics::{AnalyticsEvent, AnalyticsSink};
+
+
+/// The track endpoint used when `ANALYTICS_ENDPOINT` is not set.
+pub const DEFAULT_SEGMENT_ENDPOINT: &str = "https://api.segment.io/v1/track";
+
+static SEGMENT_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .expect("the analytics client can be built")
+});
+
+
+/// The body of the `track` call for an event.
+pub fn segment_payload(event: &AnalyticsEvent) -> Value {
+    json!({
+        "type": "track",
+        "userId": event.user_id.to_string(),
+        "event": event.event.as_str(),
+        "properties": event.properties,
+        "timestamp": event.timestamp.to_rfc3339(),
+    })
+}
+
+
+/// Posts each event to the track endpoint in `ANALYTICS_ENDPOINT`.
+pub struct SegmentAnalyticsSink;
+
+impl AnalyticsSink for SegmentAnalyticsSink {
+    async fn track<Y: GetConfigVariable>(event: &AnalyticsEvent) -> Result<(), NanoServiceError> {
+        let write_key = Y::get_config_variable("ANALYTICS_WRITE_KEY".to_string()).map_err(|_| NanoServiceError::new(
+            "ANALYTICS_WRITE_KEY is not set".to_string(),
+            NanoServiceErrorStatus::Unknown
+        ))?;
+        let endpoint = Y::get_config_variable("ANALYTICS_ENDPOINT".to_string())
+            .unwrap_or_else(|_| DEFAULT_SEGMENT_ENDPOINT.to_string());
+        let payload = segment_payload(event);
+        let event_name = event.event.as_str();
+        tokio::spawn(async move {
+            let outcome = SEGMENT_CLIENT
+                .post(&endpoint)
+                .basic_auth(write_key, Some(""))
+                .json(&payload)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = outcome {
+                log_limited!("analytics", "failed to send {} event to {}: {}", event_name, endpoint, e);
+            }
+        });
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::ProductEvent;
+
+    #[test]
+    fn test_segment_payload() {
+        let event = AnalyticsEvent::new(ProductEvent::Login, 3, json!({"role": "Admin", "email": "ada@example.com"}));
+        let payload = segment_payload(&event);
+        assert_eq!(payload["type"], "track");
+        assert_eq!(payload["userId"], "3");
+        assert_eq!(payload["event"], "login");
+        assert_eq!(payload["properties"], json!({"role": "Admin"}));
+        assert_eq!(payload["timestamp"], event.timestamp.to_rfc3339());
+    }
+}
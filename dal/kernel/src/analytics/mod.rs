@@ -0,0 +1,220 @@
+//! Emits product events to an analytics sink.
+//!
+//! ## Purpose
+//! - The core functions emit an `AnalyticsEvent` when a user is created, logs in or completes a
+//!   to-do item, through an `AnalyticsSink` so where events go is chosen by config, see
+//!   `engine_configured`.
+//! - Every event has the same shape whatever the sink: the event name, the ID of the user it is
+//!   about, its properties and when it happened. Properties are scrubbed of personal data before
+//!   they leave the service, so names, emails and free text never reach the analytics provider.
+//! - Analytics never fails a request, `emit` logs a sink's error and carries on.
+pub mod engine_log;
+pub mod engine_segment;
+pub mod engine_configured;
+pub mod engine_mock;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use utils::config::GetConfigVariable;
+use utils::log_limited;
+use utils::errors::NanoServiceError;
+use crate::to_do_items::Todo;
+use crate::users::User;
+
+
+/// The property names never sent to a sink, whatever the event.
+pub const PII_PROPERTIES: [&str; 10] = [
+    "email", "username", "first_name", "last_name", "name", "password",
+    "description", "ip", "user_agent", "token",
+];
+
+
+/// A product event.
+///
+/// # Variants
+/// * `UserCreated` - A user signed up or was created by an admin.
+/// * `Login` - A user logged in.
+/// * `TodoCompleted` - A to-do item was completed, or sent for review.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductEvent {
+    UserCreated,
+    Login,
+    TodoCompleted,
+}
+
+impl ProductEvent {
+
+    /// The name the event is sent by.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProductEvent::UserCreated => "user_created",
+            ProductEvent::Login => "login",
+            ProductEvent::TodoCompleted => "todo_completed",
+        }
+    }
+}
+
+
+/// An event about a user, ready to send.
+///
+/// # Fields
+/// * `event` - What happened.
+/// * `user_id` - The ID of the user it happened to.
+/// * `properties` - The details of the event, scrubbed of personal data.
+/// * `timestamp` - When it happened.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AnalyticsEvent {
+    pub event: ProductEvent,
+    pub user_id: i32,
+    pub properties: Map<String, Value>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl AnalyticsEvent {
+
+    /// Builds an event happening now, scrubbing its properties.
+    ///
+    /// # Arguments
+    /// * `event` - What happened.
+    /// * `user_id` - The ID of the user it happened to.
+    /// * `properties` - A JSON object of the details, anything else is sent without properties.
+    pub fn new(event: ProductEvent, user_id: i32, properties: Value) -> Self {
+        let properties = match properties {
+            Value::Object(properties) => scrub_properties(properties),
+            _ => Map::new()
+        };
+        AnalyticsEvent { event, user_id, properties, timestamp: Utc::now() }
+    }
+
+    /// A user was created.
+    pub fn user_created(user: &User) -> Self {
+        Self::new(ProductEvent::UserCreated, user.id, serde_json::json!({
+            "role": user.user_role,
+            "confirmed": user.confirmed,
+        }))
+    }
+
+    /// A user logged in.
+    pub fn login(user: &User) -> Self {
+        Self::new(ProductEvent::Login, user.id, serde_json::json!({
+            "role": user.user_role,
+        }))
+    }
+
+    /// A to-do item was completed by its assignee.
+    pub fn todo_completed(todo: &Todo) -> Self {
+        Self::new(ProductEvent::TodoCompleted, todo.assigned_to, serde_json::json!({
+            "todo_id": todo.id,
+            "priority": todo.priority,
+            "pending_review": todo.pending_review,
+            "overdue": todo.due_date.is_some_and(|due| due < Utc::now().naive_utc()),
+        }))
+    }
+}
+
+
+/// Removes the personal data from an event's properties.
+///
+/// # Notes
+/// Properties named in `PII_PROPERTIES` are dropped, at any depth, and any string that looks like
+/// an email address is replaced with `[redacted]`.
+pub fn scrub_properties(properties: Map<String, Value>) -> Map<String, Value> {
+    properties.into_iter()
+        .filter(|(name, _)| !PII_PROPERTIES.contains(&name.to_ascii_lowercase().as_str()))
+        .map(|(name, value)| (name, scrub_value(value)))
+        .collect()
+}
+
+
+fn scrub_value(value: Value) -> Value {
+    match value {
+        Value::Object(properties) => Value::Object(scrub_properties(properties)),
+        Value::Array(values) => Value::Array(values.into_iter().map(scrub_value).collect()),
+        Value::String(text) if looks_like_email(&text) => Value::String("[redacted]".to_string()),
+        value => value
+    }
+}
+
+
+fn looks_like_email(text: &str) -> bool {
+    text.split_whitespace().any(|word| {
+        matches!(word.split_once('@'), Some((local, domain)) if !local.is_empty() && domain.contains('.'))
+    })
+}
+
+
+/// Defines the contract for sending product events somewhere they can be analysed.
+pub trait AnalyticsSink {
+
+    /// Sends an event.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the event was sent, or queued to be sent
+    /// * `Err(NanoServiceError)` - If the sink is misconfigured
+    fn track<Y: GetConfigVariable>(event: &AnalyticsEvent)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send;
+}
+
+
+/// Sends an event with `A`, logging rather than returning a failure so analytics never fails a request.
+pub async fn emit<A: AnalyticsSink, Y: GetConfigVariable>(event: AnalyticsEvent) {
+    if let Err(e) = A::track::<Y>(&event).await {
+        log_limited!("analytics", "failed to track {} event for user {}: {}", event.event.as_str(), event.user_id, e.message);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_do_items::TodoStatus;
+    use serde_json::json;
+
+    #[test]
+    fn test_scrub_properties() {
+        let event = AnalyticsEvent::new(ProductEvent::Login, 3, json!({
+            "role": "Worker",
+            "Email": "ada@example.com",
+            "source": {"username": "ada", "referrer": "sent by ada@example.com"},
+            "tags": ["admin", "bob@example.org"],
+            "count": 2,
+        }));
+        assert_eq!(Value::Object(event.properties), json!({
+            "role": "Worker",
+            "source": {"referrer": "[redacted]"},
+            "tags": ["admin", "[redacted]"],
+            "count": 2,
+        }));
+        assert!(AnalyticsEvent::new(ProductEvent::Login, 3, json!("not an object")).properties.is_empty());
+        assert!(!looks_like_email("@handle on example.com"));
+    }
+
+    #[test]
+    fn test_todo_completed() {
+        let todo = Todo {
+            id: 4,
+            name: "Call ada@example.com".to_string(),
+            due_date: None,
+            assigned_by: 1,
+            assigned_to: 2,
+            description: Some("private".to_string()),
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: true,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: Default::default(),
+            status: TodoStatus::Done,
+        };
+        let event = AnalyticsEvent::todo_completed(&todo);
+        assert_eq!((event.event, event.user_id), (ProductEvent::TodoCompleted, 2));
+        assert_eq!(Value::Object(event.properties), json!({
+            "todo_id": 4, "priority": "medium", "pending_review": false, "overdue": false
+        }));
+        assert_eq!(serde_json::to_value(ProductEvent::TodoCompleted).unwrap(), "todo_completed");
+    }
+}
@@ -0,0 +1,219 @@
+//! Defines the structs for API keys used by services instead of a user's token.
+//!
+//! ## Purpose
+//! - A super admin creates a key for a cron job or an integration, which then sends it in the
+//!   `X-Api-Key` header to the endpoints that accept keys, `api_endpoint(auth=ApiKey)`.
+//! - Only a SHA-256 hash of the key is stored, the key itself is shown once when it is created. The
+//!   keys are long and random so a fast hash is enough, unlike passwords.
+//! - A revoked key is refused from the next request, every use of a key records when it was last used.
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use chrono::NaiveDateTime;
+use futures::future::{err, ok, Ready};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The header an API key is sent in.
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// The audit log action recorded when a key is created.
+pub const API_KEY_CREATED_ACTION: &str = "api_key:created";
+
+/// The audit log action recorded when a key is revoked.
+pub const API_KEY_REVOKED_ACTION: &str = "api_key:revoked";
+
+/// The start of every generated key, so a leaked key is easy to recognise.
+const KEY_PREFIX: &str = "ak_";
+
+/// The length of the random part of a generated key.
+const KEY_LENGTH: usize = 40;
+
+/// The characters of a key kept to tell keys apart in the list.
+const DISPLAY_PREFIX_LENGTH: usize = 11;
+
+
+/// Represents the schema for storing a new API key.
+///
+/// # Fields
+/// * name - What the key is used for, such as `nightly-backup`.
+/// * key_prefix - The start of the key, shown in the list so keys can be told apart.
+/// * key_hash - The SHA-256 hash of the key.
+/// * created_by - The ID of the super admin who created the key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewApiKey {
+    pub name: String,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub created_by: i32,
+}
+
+
+/// Represents an API key.
+///
+/// # Fields
+/// * id - The unique identifier for the key.
+/// * name - What the key is used for.
+/// * key_prefix - The start of the key.
+/// * key_hash - The SHA-256 hash of the key, never returned.
+/// * created_by - The ID of the super admin who created the key.
+/// * date_created - When the key was created.
+/// * last_used_at - When the key was last accepted, if it has been used.
+/// * revoked_at - When the key was revoked, if it has been.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub key_prefix: String,
+    #[serde(skip_serializing, default)]
+    pub key_hash: String,
+    pub created_by: i32,
+    pub date_created: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+
+/// A key that has just been created, the only time the key itself is returned.
+///
+/// # Fields
+/// * api_key - The stored key.
+/// * key - The key to send in the `X-Api-Key` header.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CreatedApiKey {
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+
+/// A new random API key.
+pub fn generate_api_key() -> String {
+    let key: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(KEY_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("{}{}", KEY_PREFIX, key)
+}
+
+
+/// The hash an API key is stored and looked up by.
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+
+impl NewApiKey {
+
+    /// Hashes a generated key for storing.
+    ///
+    /// # Arguments
+    /// * `name` - What the key is used for.
+    /// * `key` - The generated key.
+    /// * `created_by` - The ID of the super admin creating the key.
+    pub fn from_key(name: String, key: &str, created_by: i32) -> Self {
+        NewApiKey {
+            name,
+            key_prefix: key.chars().take(DISPLAY_PREFIX_LENGTH).collect(),
+            key_hash: hash_api_key(key),
+            created_by,
+        }
+    }
+}
+
+
+/// The key sent with a request, hashed as soon as it is read.
+///
+/// # Notes
+/// This only reads the header, the key is checked against the `api_keys` table by the endpoint,
+/// see `check_api_key`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKeyToken {
+    pub key_hash: String,
+}
+
+impl FromRequest for ApiKeyToken {
+    type Error = NanoServiceError;
+    type Future = Ready<Result<ApiKeyToken, NanoServiceError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match req.headers().get(API_KEY_HEADER).and_then(|value| value.to_str().ok()) {
+            Some(key) if !key.trim().is_empty() => ok(ApiKeyToken { key_hash: hash_api_key(key.trim()) }),
+            _ => err(NanoServiceError::new(
+                format!("API key not in header under key '{}'", API_KEY_HEADER),
+                NanoServiceErrorStatus::Unauthorized
+            ))
+        }
+    }
+}
+
+
+/// Checks the key looked up for a request was found and has not been revoked.
+///
+/// # Arguments
+/// * `api_key` - The key with the hash sent, `None` if there is no key with that hash that is not revoked
+///
+/// # Returns
+/// * `Ok(ApiKey)` - The key the request was sent with
+/// * `Err(NanoServiceError)` - `Unauthorized` if the key is unknown or revoked
+pub fn check_api_key(api_key: Option<ApiKey>) -> Result<ApiKey, NanoServiceError> {
+    api_key.ok_or_else(|| NanoServiceError::new(
+        "API key is not valid".to_string(),
+        NanoServiceErrorStatus::Unauthorized
+    ))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_generate_api_key() {
+        let key = generate_api_key();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(key.len(), KEY_PREFIX.len() + KEY_LENGTH);
+        assert_ne!(key, generate_api_key());
+    }
+
+    #[test]
+    fn test_new_api_key_from_key() {
+        let key = "ak_0123456789abcdef";
+        let new_key = NewApiKey::from_key("nightly-backup".to_string(), key, 1);
+        assert_eq!(new_key.key_prefix, "ak_01234567");
+        assert_eq!(new_key.key_hash, hash_api_key(key));
+        assert_eq!(new_key.key_hash.len(), 64);
+        assert_ne!(new_key.key_hash, hash_api_key("ak_0123456789abcdeF"));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_token_from_request() {
+        let req = TestRequest::default().insert_header((API_KEY_HEADER, "ak_test")).to_http_request();
+        let token = ApiKeyToken::extract(&req).await.unwrap();
+        assert_eq!(token.key_hash, hash_api_key("ak_test"));
+
+        for req in [TestRequest::default(), TestRequest::default().insert_header((API_KEY_HEADER, " "))] {
+            let error = ApiKeyToken::extract(&req.to_http_request()).await.unwrap_err();
+            assert_eq!(error.status, NanoServiceErrorStatus::Unauthorized);
+        }
+    }
+
+    #[test]
+    fn test_api_key_is_not_serialized_with_its_hash() {
+        let api_key = ApiKey {
+            id: 1,
+            name: "nightly-backup".to_string(),
+            key_prefix: "ak_01234567".to_string(),
+            key_hash: hash_api_key("ak_0123456789"),
+            created_by: 1,
+            date_created: chrono::Utc::now().naive_utc(),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        let value = serde_json::to_value(&api_key).unwrap();
+        assert!(value.get("key_hash").is_none());
+        assert_eq!(value["key_prefix"], "ak_01234567");
+    }
+}
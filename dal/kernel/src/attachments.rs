@@ -0,0 +1,241 @@
+//! Defines the structs for files attached to to-do items.
+//!
+//! ## Purpose
+//! - The assigner and assignee of a to-do item, and admins, can attach files to it and download them.
+//! - The file is kept in the object store under `storage_key` and the row records what it is, so
+//!   the file can be served with its name and type.
+//! - Uploads are limited in size by `ATTACHMENT_MAX_BYTES` and in type by `ATTACHMENT_ALLOWED_TYPES`.
+use chrono::NaiveDateTime;
+use serde::{Serialize, Deserialize};
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::to_do_items::Todo;
+use crate::users::UserRole;
+
+
+/// The largest attachment when `ATTACHMENT_MAX_BYTES` is not set, 10 MiB.
+pub const DEFAULT_ATTACHMENT_MAX_BYTES: usize = 10 * 1024 * 1024;
+
+/// The largest attachment `ATTACHMENT_MAX_BYTES` can allow, 100 MiB.
+pub const MAX_ATTACHMENT_MAX_BYTES: usize = 100 * 1024 * 1024;
+
+/// The types that can be attached when `ATTACHMENT_ALLOWED_TYPES` is not set.
+pub const DEFAULT_ATTACHMENT_TYPES: [&str; 6] = [
+    "image/png", "image/jpeg", "image/gif", "application/pdf", "text/plain", "text/csv"
+];
+
+/// The longest file name kept for an attachment.
+pub const MAX_ATTACHMENT_NAME_LENGTH: usize = 255;
+
+
+/// The limits on what can be attached.
+///
+/// # Fields
+/// * `max_bytes` - The largest file that can be attached.
+/// * `allowed_types` - The content types that can be attached, a type ending in `/*` allows every
+///   subtype, such as `image/*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentLimits {
+    pub max_bytes: usize,
+    pub allowed_types: Vec<String>,
+}
+
+impl AttachmentLimits {
+
+    /// Reads the limits from `ATTACHMENT_MAX_BYTES`, capped at `MAX_ATTACHMENT_MAX_BYTES`, and the
+    /// comma separated `ATTACHMENT_ALLOWED_TYPES`.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let max_bytes = Y::get_int("ATTACHMENT_MAX_BYTES").ok()
+            .filter(|max| *max > 0)
+            .map(|max| (max as usize).min(MAX_ATTACHMENT_MAX_BYTES))
+            .unwrap_or(DEFAULT_ATTACHMENT_MAX_BYTES);
+        let allowed_types: Vec<String> = Y::get_config_variable("ATTACHMENT_ALLOWED_TYPES".to_string())
+            .map(|types| types.split(',')
+                .map(|content_type| content_type.trim().to_ascii_lowercase())
+                .filter(|content_type| !content_type.is_empty())
+                .collect())
+            .unwrap_or_default();
+        let allowed_types = match allowed_types.is_empty() {
+            true => DEFAULT_ATTACHMENT_TYPES.iter().map(|content_type| content_type.to_string()).collect(),
+            false => allowed_types
+        };
+        AttachmentLimits { max_bytes, allowed_types }
+    }
+
+    /// Whether a file of the type can be attached, any parameters such as `charset` are ignored.
+    pub fn allows_type(&self, content_type: &str) -> bool {
+        let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        self.allowed_types.iter().any(|allowed| match allowed.strip_suffix("/*") {
+            Some(top_level) => essence.split('/').next() == Some(top_level),
+            None => *allowed == essence
+        })
+    }
+
+    /// Refuses a file that is too large or of a type that cannot be attached.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the file can be attached
+    /// * `Err(NanoServiceError)` - `PayloadTooLarge` if it is too large, `UnsupportedMediaType` if
+    ///   its type is not allowed
+    pub fn check(&self, content_type: &str, size_bytes: usize) -> Result<(), NanoServiceError> {
+        if size_bytes > self.max_bytes {
+            return Err(self.too_large())
+        }
+        if !self.allows_type(content_type) {
+            return Err(NanoServiceError::new(
+                format!("Files of type {} cannot be attached", content_type),
+                NanoServiceErrorStatus::UnsupportedMediaType
+            ))
+        }
+        Ok(())
+    }
+
+    /// The error for a file over `max_bytes`.
+    pub fn too_large(&self) -> NanoServiceError {
+        NanoServiceError::new(
+            format!("An attachment can be at most {} bytes", self.max_bytes),
+            NanoServiceErrorStatus::PayloadTooLarge
+        )
+    }
+}
+
+
+/// Whether a user can see and change the attachments of a to-do item, only its assigner, its
+/// assignee and admins can.
+pub fn can_access_attachments(todo: &Todo, user_id: i32, role: &UserRole) -> bool {
+    todo.assigned_by == user_id
+        || todo.assigned_to == user_id
+        || matches!(role, UserRole::SuperAdmin | UserRole::Admin)
+}
+
+
+/// The name an attachment is kept under, the last part of the uploaded path with control
+/// characters removed, or `attachment` if nothing is left.
+pub fn attachment_file_name(file_name: &str) -> String {
+    let base = file_name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = base.chars().filter(|c| !c.is_control()).take(MAX_ATTACHMENT_NAME_LENGTH).collect();
+    match name.trim() {
+        "" | "." | ".." => "attachment".to_string(),
+        name => name.to_string()
+    }
+}
+
+
+/// The key a new attachment of a to-do item is stored under, unique so an upload never replaces
+/// another file.
+pub fn attachment_storage_key(todo_id: i32) -> String {
+    format!("attachments/{}/{}", todo_id, uuid::Uuid::new_v4())
+}
+
+
+/// Represents the schema for attaching a file to a to-do item.
+///
+/// # Fields
+/// * todo_id - The ID of the to-do item the file is attached to.
+/// * uploaded_by - The ID of the user who attached the file.
+/// * file_name - The name of the file.
+/// * content_type - The content type of the file.
+/// * size_bytes - The size of the file.
+/// * storage_key - The key the file is kept under in the object store.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewAttachment {
+    pub todo_id: i32,
+    pub uploaded_by: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub storage_key: String,
+}
+
+
+/// Represents a file attached to a to-do item.
+///
+/// # Fields
+/// * id - The unique identifier for the attachment.
+/// * todo_id - The ID of the to-do item the file is attached to.
+/// * uploaded_by - The ID of the user who attached the file.
+/// * file_name - The name of the file.
+/// * content_type - The content type of the file.
+/// * size_bytes - The size of the file.
+/// * storage_key - The key the file is kept under in the object store, never returned.
+/// * date_created - When the file was attached.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct Attachment {
+    pub id: i32,
+    pub todo_id: i32,
+    pub uploaded_by: i32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    #[serde(skip_serializing, default)]
+    pub storage_key: String,
+    pub date_created: NaiveDateTime,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "ATTACHMENT_MAX_BYTES" => Ok("1000000000".to_string()),
+                "ATTACHMENT_ALLOWED_TYPES" => Ok(" image/* , Application/PDF,".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    struct UnsetConfig;
+
+    impl GetConfigVariable for UnsetConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Err(NanoServiceError::new("not set".to_string(), NanoServiceErrorStatus::Unknown))
+        }
+    }
+
+    #[test]
+    fn test_limits_from_config() {
+        let limits = AttachmentLimits::from_config::<MockConfig>();
+        assert_eq!(limits.max_bytes, MAX_ATTACHMENT_MAX_BYTES);
+        assert_eq!(limits.allowed_types, vec!["image/*".to_string(), "application/pdf".to_string()]);
+
+        let limits = AttachmentLimits::from_config::<UnsetConfig>();
+        assert_eq!(limits.max_bytes, DEFAULT_ATTACHMENT_MAX_BYTES);
+        assert_eq!(limits.allowed_types.len(), DEFAULT_ATTACHMENT_TYPES.len());
+    }
+
+    #[test]
+    fn test_check() {
+        let limits = AttachmentLimits::from_config::<MockConfig>();
+        assert!(limits.check("image/webp", 10).is_ok());
+        assert!(limits.check("application/pdf; name=report", 10).is_ok());
+        assert!(limits.allows_type("IMAGE/PNG"));
+        assert!(!limits.allows_type("imagex/png"));
+
+        let wrong_type = limits.check("text/html", 10).unwrap_err();
+        assert_eq!(wrong_type.status, NanoServiceErrorStatus::UnsupportedMediaType);
+        let too_large = limits.check("image/png", MAX_ATTACHMENT_MAX_BYTES + 1).unwrap_err();
+        assert_eq!(too_large.status, NanoServiceErrorStatus::PayloadTooLarge);
+    }
+
+    #[test]
+    fn test_attachment_file_name() {
+        assert_eq!(attachment_file_name("report.pdf"), "report.pdf");
+        assert_eq!(attachment_file_name("C:\\Users\\me\\report.pdf"), "report.pdf");
+        assert_eq!(attachment_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(attachment_file_name("bad\nname.txt"), "badname.txt");
+        assert_eq!(attachment_file_name("folder/"), "attachment");
+        assert_eq!(attachment_file_name(&"a".repeat(300)).len(), MAX_ATTACHMENT_NAME_LENGTH);
+    }
+
+    #[test]
+    fn test_storage_keys_are_unique() {
+        let key = attachment_storage_key(3);
+        assert!(key.starts_with("attachments/3/"));
+        assert_ne!(key, attachment_storage_key(3));
+    }
+}
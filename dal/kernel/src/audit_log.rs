@@ -0,0 +1,62 @@
+//! Defines the structs for the audit log of privileged actions.
+//!
+//! ## Purpose
+//! - Actions that bypass a normal rule, such as assigning past a capacity limit, are recorded with
+//!   who did it, what they did and to which record, so they can be reviewed later.
+use serde::{Serialize, Deserialize};
+use sqlx::types::Json;
+use chrono::NaiveDateTime;
+use utils::export_stream::{CsvRow, csv_field};
+use utils::locale::iso_datetime;
+
+
+/// Represents the schema for a new audit entry.
+///
+/// # Fields
+/// * actor_id - The ID of the user who performed the action.
+/// * action - The name of the action, e.g. `todo:capacity_override`.
+/// * subject_id - The ID of the record the action was performed on, if any.
+/// * details - Any context needed to understand the action later.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewAuditEntry {
+    pub actor_id: i32,
+    pub action: String,
+    pub subject_id: Option<i32>,
+    pub details: serde_json::Value,
+}
+
+
+/// Represents an entry in the audit log.
+///
+/// # Fields
+/// * id - The unique identifier for the entry.
+/// * actor_id - The ID of the user who performed the action.
+/// * action - The name of the action.
+/// * subject_id - The ID of the record the action was performed on, if any.
+/// * details - Any context needed to understand the action later.
+/// * date_created - When the action was performed.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub actor_id: i32,
+    pub action: String,
+    pub subject_id: Option<i32>,
+    pub details: Json<serde_json::Value>,
+    pub date_created: NaiveDateTime,
+}
+
+impl CsvRow for AuditEntry {
+    const CSV_HEADER: &'static str = "id,actor_id,action,subject_id,details,date_created";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.id,
+            self.actor_id,
+            csv_field(&self.action),
+            self.subject_id.map(|subject_id| subject_id.to_string()).unwrap_or_default(),
+            csv_field(&self.details.0.to_string()),
+            iso_datetime(self.date_created)
+        )
+    }
+}
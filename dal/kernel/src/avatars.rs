@@ -0,0 +1,97 @@
+//! Defines how the avatars users upload for their profiles are versioned and found.
+//!
+//! ## Purpose
+//! - An upload is processed into each size it is served at by `auth_core::avatars` and every size
+//!   is stored under a key of its own for the user, replacing the last avatar.
+//! - The users row records `avatar_version`, a hash of the stored avatar, so a new avatar always has
+//!   a new `avatar_url` and the ingress can let a versioned URL be cached forever.
+//! - Uploads are limited in size by `AVATAR_MAX_BYTES` before they are processed.
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The largest avatar upload when `AVATAR_MAX_BYTES` is not set, 5 MiB.
+pub const DEFAULT_AVATAR_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+/// The largest avatar upload `AVATAR_MAX_BYTES` can allow, 20 MiB.
+pub const MAX_AVATAR_MAX_BYTES: usize = 20 * 1024 * 1024;
+
+/// The number of hex characters of the avatar's hash kept as its version.
+const AVATAR_VERSION_LENGTH: usize = 16;
+
+
+/// A user's avatar as returned once it is changed.
+///
+/// # Fields
+/// * `avatar_url` - The URL the avatar is served from, `None` if the user has no avatar.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UserAvatar {
+    pub avatar_url: Option<String>,
+}
+
+
+/// The largest avatar upload, from `AVATAR_MAX_BYTES` capped at `MAX_AVATAR_MAX_BYTES`.
+pub fn avatar_max_bytes<Y: GetConfigVariable>() -> usize {
+    Y::get_int("AVATAR_MAX_BYTES").ok()
+        .filter(|max| *max > 0)
+        .map(|max| (max as usize).min(MAX_AVATAR_MAX_BYTES))
+        .unwrap_or(DEFAULT_AVATAR_MAX_BYTES)
+}
+
+
+/// The error for an avatar upload over `max_bytes`.
+pub fn avatar_too_large(max_bytes: usize) -> NanoServiceError {
+    NanoServiceError::new(
+        format!("An avatar can be at most {} bytes", max_bytes),
+        NanoServiceErrorStatus::PayloadTooLarge
+    )
+}
+
+
+/// The version of a stored avatar, the start of its SHA-256 in hex.
+pub fn avatar_version(avatar: &[u8]) -> String {
+    hex::encode(Sha256::digest(avatar))[..AVATAR_VERSION_LENGTH].to_string()
+}
+
+
+/// The URL a user's avatar is served from by the ingress, `/avatars/{user_id}?v={version}`. A client
+/// can add `&size=64` or `&size=128` for a smaller avatar.
+///
+/// # Notes
+/// The queries that read a `TrimmedUser` straight from a row build the same URL in SQL.
+pub fn avatar_url(user_id: i32, version: &str) -> String {
+    format!("/avatars/{}?v={}", user_id, version)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "AVATAR_MAX_BYTES" => Ok("999999999".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    #[test]
+    fn test_avatar_max_bytes() {
+        assert_eq!(avatar_max_bytes::<MockConfig>(), MAX_AVATAR_MAX_BYTES);
+        assert_eq!(avatar_too_large(10).status, NanoServiceErrorStatus::PayloadTooLarge);
+    }
+
+    #[test]
+    fn test_avatar_url() {
+        let version = avatar_version(b"avatar");
+        assert_eq!(version.len(), 16);
+        assert_ne!(version, avatar_version(b"another avatar"));
+        assert_eq!(avatar_url(7, &version), format!("/avatars/7?v={}", version));
+    }
+}
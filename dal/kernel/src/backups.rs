@@ -0,0 +1,71 @@
+//! Defines the structs for logical backups of the database.
+//!
+//! ## Purpose
+//! - A backup is a dump of the application tables written gzipped to the object store, for
+//!   self-hosted installs without a managed database that backs itself up.
+//! - Each backup is recorded in `backups` so they can be listed and restored by ID.
+use serde::{Serialize, Deserialize};
+use chrono::NaiveDateTime;
+
+
+/// A dump of the application tables.
+///
+/// # Fields
+/// * sql - The statements that restore the rows, one `INSERT` per row.
+/// * row_count - The rows dumped.
+/// * schema_version - The latest migration applied when the dump was taken, it can only be
+///   restored to a database at the same version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseDump {
+    pub sql: String,
+    pub row_count: i64,
+    pub schema_version: i64,
+}
+
+
+/// Represents the schema for recording a backup.
+///
+/// # Fields
+/// * object_key - Where the backup is stored.
+/// * size_bytes - The size of the gzipped backup.
+/// * row_count - The rows in the backup.
+/// * schema_version - The latest migration applied when the backup was taken.
+/// * triggered_by - The ID of the super admin who asked for the backup, `None` for a scheduled one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewBackup {
+    pub object_key: String,
+    pub size_bytes: i64,
+    pub row_count: i64,
+    pub schema_version: i64,
+    pub triggered_by: Option<i32>,
+}
+
+
+/// Represents a backup in the object store.
+///
+/// # Fields
+/// * id - The unique identifier for the backup.
+/// * object_key - Where the backup is stored.
+/// * size_bytes - The size of the gzipped backup.
+/// * row_count - The rows in the backup.
+/// * schema_version - The latest migration applied when the backup was taken.
+/// * triggered_by - The ID of the super admin who asked for the backup, `None` for a scheduled one.
+/// * date_created - When the backup was taken.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct Backup {
+    pub id: i32,
+    pub object_key: String,
+    pub size_bytes: i64,
+    pub row_count: i64,
+    pub schema_version: i64,
+    pub triggered_by: Option<i32>,
+    pub date_created: NaiveDateTime,
+}
+
+impl Backup {
+
+    /// The key a backup taken at `taken_at` is stored under, such as `backups/20250525T020000.sql.gz`.
+    pub fn storage_key(taken_at: NaiveDateTime) -> String {
+        format!("backups/{}.sql.gz", taken_at.format("%Y%m%dT%H%M%S"))
+    }
+}
@@ -0,0 +1,352 @@
+//! Defines the Stripe webhook events that move the organization's subscription through its lifecycle.
+//!
+//! ## Purpose
+//! - Stripe signs each webhook in the `Stripe-Signature` header with a hex HMAC-SHA256, keyed with
+//!   the endpoint's signing secret, of the timestamp, a `.` and the raw body. Webhooks with a
+//!   signature that does not match, or a timestamp too far from now, are rejected so a captured
+//!   webhook cannot be replayed later.
+//! - A completed checkout moves the organization onto the plan named in the checkout's `plan`
+//!   metadata, a paid invoice renews the subscription, a failed payment marks it past due and a
+//!   deleted subscription moves the organization back onto the free plan.
+//! - The billing status is held in a single row like the plan, and every event applied is recorded
+//!   by its id so a webhook Stripe retries is only applied once.
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
+use chrono::{DateTime, NaiveDateTime};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::webhooks::{hmac_sha256_hex, verify_hmac_sha256_hex, within_tolerance, DEFAULT_TOLERANCE_SECONDS};
+use std::error::Error;
+use std::str::FromStr;
+use crate::plans::Plan;
+
+
+/// The header Stripe signs webhooks in.
+pub const STRIPE_SIGNATURE_HEADER: &str = "Stripe-Signature";
+
+/// How far, in seconds, the signed timestamp can be from now.
+pub const SIGNATURE_TOLERANCE_SECONDS: i64 = DEFAULT_TOLERANCE_SECONDS;
+
+
+/// Computes the hex signature Stripe sends for a webhook.
+///
+/// # Arguments
+/// * `secret` - The endpoint's signing secret.
+/// * `timestamp` - The `t` of the signature header.
+/// * `payload` - The raw body of the webhook.
+pub fn stripe_signature(secret: &str, timestamp: i64, payload: &[u8]) -> String {
+    hmac_sha256_hex(secret.as_bytes(), &[format!("{}.", timestamp).as_bytes(), payload])
+}
+
+
+fn invalid_signature(message: &str) -> NanoServiceError {
+    NanoServiceError::new(message.to_string(), NanoServiceErrorStatus::Unauthorized)
+}
+
+
+/// Checks the `Stripe-Signature` header of a webhook.
+///
+/// # Arguments
+/// * `secret` - The endpoint's signing secret.
+/// * `header` - The signature header, e.g. `t=1492774577,v1=5257a8...`.
+/// * `payload` - The raw body of the webhook.
+/// * `now` - The current time as a unix timestamp.
+///
+/// # Returns
+/// * `Ok(())` - If one of the `v1` signatures matches and the timestamp is within the tolerance.
+/// * `Err(NanoServiceError)` - `Unauthorized` otherwise.
+///
+/// # Notes
+/// Stripe sends more than one `v1` signature while a signing secret is being rolled.
+pub fn verify_stripe_signature(secret: &str, header: &str, payload: &[u8], now: i64) -> Result<(), NanoServiceError> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or_else(|| invalid_signature("Billing webhook signature has no timestamp"))?;
+    if !within_tolerance(timestamp, now, SIGNATURE_TOLERANCE_SECONDS) {
+        return Err(invalid_signature("Billing webhook signature has expired"))
+    }
+
+    let signed_timestamp = format!("{}.", timestamp);
+    let matched = signatures.iter()
+        .any(|signature| verify_hmac_sha256_hex(secret.as_bytes(), &[signed_timestamp.as_bytes(), payload], signature));
+    match matched {
+        true => Ok(()),
+        false => Err(invalid_signature("Invalid billing webhook signature"))
+    }
+}
+
+
+/// The state of the organization's subscription.
+///
+/// # Variants
+/// * `None` - The organization has never subscribed.
+/// * `Active` - The subscription is paid up.
+/// * `PastDue` - The last payment failed and Stripe is retrying it.
+/// * `Canceled` - The subscription has ended.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BillingStatus {
+    None,
+    Active,
+    PastDue,
+    Canceled,
+}
+
+impl BillingStatus {
+
+    /// The value stored in the `status` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillingStatus::None => "none",
+            BillingStatus::Active => "active",
+            BillingStatus::PastDue => "past_due",
+            BillingStatus::Canceled => "canceled",
+        }
+    }
+}
+
+impl FromStr for BillingStatus {
+    type Err = String;
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.trim() {
+            "none" => Ok(BillingStatus::None),
+            "active" => Ok(BillingStatus::Active),
+            "past_due" => Ok(BillingStatus::PastDue),
+            "canceled" => Ok(BillingStatus::Canceled),
+            _ => Err(format!("Invalid billing status: {}", status)),
+        }
+    }
+}
+
+impl Type<Postgres> for BillingStatus {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for BillingStatus {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for BillingStatus {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        BillingStatus::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// The parts of a Stripe event that are read.
+///
+/// # Fields
+/// * id - The id of the event, the same for every retry of it.
+/// * event_type - The type of the event, e.g. `invoice.paid`.
+/// * created - When the event happened as a unix timestamp.
+/// * data - The object the event is about.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StripeEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub created: i64,
+    pub data: StripeEventData,
+}
+
+
+/// The object a Stripe event is about.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StripeEventData {
+    pub object: Value,
+}
+
+
+fn bad_event(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::BadRequest)
+}
+
+
+fn timestamp(seconds: i64) -> Result<NaiveDateTime, NanoServiceError> {
+    DateTime::from_timestamp(seconds, 0)
+        .map(|time| time.naive_utc())
+        .ok_or_else(|| bad_event(format!("Invalid timestamp: {}", seconds)))
+}
+
+
+impl StripeEvent {
+
+    /// Reads an event from the body of a webhook.
+    pub fn from_payload(payload: &[u8]) -> Result<Self, NanoServiceError> {
+        serde_json::from_slice(payload).map_err(|e| bad_event(format!("Invalid billing event: {}", e)))
+    }
+
+    fn object_str(&self, field: &str) -> Option<String> {
+        self.data.object.get(field).and_then(Value::as_str).map(str::to_string)
+    }
+
+    /// Works out what the event changes.
+    ///
+    /// # Returns
+    /// * `Ok(Some(BillingChange))` - For checkout, invoice and subscription deletion events.
+    /// * `Ok(None)` - For any other event, which is acknowledged and ignored.
+    /// * `Err(NanoServiceError)` - `BadRequest` if a completed checkout does not name a valid plan.
+    pub fn to_billing_change(&self) -> Result<Option<BillingChange>, NanoServiceError> {
+        let (plan, status, subscription_id, current_period_end) = match self.event_type.as_str() {
+            "checkout.session.completed" => {
+                let plan = self.data.object.pointer("/metadata/plan")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| bad_event("Checkout has no plan in its metadata".to_string()))?;
+                let plan = Plan::from_str(plan).map_err(bad_event)?;
+                (Some(plan), BillingStatus::Active, self.object_str("subscription"), None)
+            },
+            "invoice.paid" => {
+                // the line of a subscription invoice covers the period that has just been paid for
+                let period_end = self.data.object.pointer("/lines/data/0/period/end")
+                    .or_else(|| self.data.object.get("period_end"))
+                    .and_then(Value::as_i64)
+                    .map(timestamp)
+                    .transpose()?;
+                (None, BillingStatus::Active, self.object_str("subscription"), period_end)
+            },
+            "invoice.payment_failed" => (None, BillingStatus::PastDue, self.object_str("subscription"), None),
+            "customer.subscription.deleted" => (Some(Plan::Free), BillingStatus::Canceled, self.object_str("id"), None),
+            _ => return Ok(None)
+        };
+        Ok(Some(BillingChange {
+            event_id: self.id.clone(),
+            event_type: self.event_type.clone(),
+            event_created: timestamp(self.created)?,
+            plan,
+            status,
+            customer_id: self.object_str("customer"),
+            subscription_id,
+            current_period_end,
+        }))
+    }
+}
+
+
+/// A change to the organization's billing made by a Stripe event.
+///
+/// # Fields
+/// * event_id - The id of the event, recorded so it is only applied once.
+/// * event_type - The type of the event.
+/// * event_created - When the event happened, an event older than the last one applied does not
+///   change the status as Stripe does not deliver events in order.
+/// * plan - The plan the organization moves onto, `None` keeps the current plan.
+/// * status - The status of the subscription after the event.
+/// * customer_id - The Stripe customer, `None` keeps the one held.
+/// * subscription_id - The Stripe subscription, `None` keeps the one held.
+/// * current_period_end - When the paid period ends, `None` keeps the one held.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BillingChange {
+    pub event_id: String,
+    pub event_type: String,
+    pub event_created: NaiveDateTime,
+    pub plan: Option<Plan>,
+    pub status: BillingStatus,
+    pub customer_id: Option<String>,
+    pub subscription_id: Option<String>,
+    pub current_period_end: Option<NaiveDateTime>,
+}
+
+
+/// The billing status held in the `org_billing` table.
+///
+/// # Fields
+/// * status - The state of the subscription.
+/// * customer_id - The Stripe customer, if the organization has subscribed.
+/// * subscription_id - The Stripe subscription, if the organization has subscribed.
+/// * current_period_end - When the paid period ends, if an invoice has been paid.
+/// * last_event_at - When the last event applied happened.
+/// * date_updated - When the billing status was last changed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct OrgBilling {
+    pub status: BillingStatus,
+    pub customer_id: Option<String>,
+    pub subscription_id: Option<String>,
+    pub current_period_end: Option<NaiveDateTime>,
+    pub last_event_at: Option<NaiveDateTime>,
+    pub date_updated: NaiveDateTime,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SECRET: &str = "whsec_test";
+
+    fn event(event_type: &str, object: Value) -> StripeEvent {
+        StripeEvent {
+            id: "evt_1".to_string(),
+            event_type: event_type.to_string(),
+            created: 1_745_000_000,
+            data: StripeEventData { object },
+        }
+    }
+
+    #[test]
+    fn test_status_round_trip() {
+        for status in [BillingStatus::None, BillingStatus::Active, BillingStatus::PastDue, BillingStatus::Canceled] {
+            assert_eq!(BillingStatus::from_str(status.as_str()).unwrap(), status);
+        }
+    }
+
+    #[test]
+    fn test_verify_stripe_signature() {
+        let payload = br#"{"id":"evt_1"}"#;
+        let signature = stripe_signature(SECRET, 1_000, payload);
+        let header = format!("t=1000,v1=deadbeef,v1={}", signature);
+        assert!(verify_stripe_signature(SECRET, &header, payload, 1_100).is_ok());
+
+        let expired = verify_stripe_signature(SECRET, &header, payload, 1_000 + SIGNATURE_TOLERANCE_SECONDS + 1).unwrap_err();
+        assert_eq!(expired.status, NanoServiceErrorStatus::Unauthorized);
+        assert!(verify_stripe_signature(SECRET, &header, br#"{"id":"evt_2"}"#, 1_100).is_err());
+        assert!(verify_stripe_signature("whsec_other", &header, payload, 1_100).is_err());
+        assert!(verify_stripe_signature(SECRET, &format!("v1={}", signature), payload, 1_100).is_err());
+    }
+
+    #[test]
+    fn test_checkout_change() {
+        let checkout = event("checkout.session.completed", json!({
+            "customer": "cus_1", "subscription": "sub_1", "metadata": {"plan": "pro"}
+        }));
+        let change = checkout.to_billing_change().unwrap().unwrap();
+        assert_eq!(change.plan, Some(Plan::Pro));
+        assert_eq!(change.status, BillingStatus::Active);
+        assert_eq!(change.customer_id, Some("cus_1".to_string()));
+        assert_eq!(change.subscription_id, Some("sub_1".to_string()));
+
+        let no_plan = event("checkout.session.completed", json!({"customer": "cus_1"}));
+        assert_eq!(no_plan.to_billing_change().unwrap_err().status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[test]
+    fn test_renewal_and_cancellation_changes() {
+        let renewal = event("invoice.paid", json!({
+            "customer": "cus_1", "subscription": "sub_1",
+            "lines": {"data": [{"period": {"start": 1_745_000_000, "end": 1_747_592_000}}]}
+        }));
+        let change = renewal.to_billing_change().unwrap().unwrap();
+        assert_eq!(change.plan, None);
+        assert_eq!(change.current_period_end, Some(timestamp(1_747_592_000).unwrap()));
+
+        let cancellation = event("customer.subscription.deleted", json!({"id": "sub_1", "customer": "cus_1"}));
+        let change = cancellation.to_billing_change().unwrap().unwrap();
+        assert_eq!(change.plan, Some(Plan::Free));
+        assert_eq!(change.status, BillingStatus::Canceled);
+        assert_eq!(change.subscription_id, Some("sub_1".to_string()));
+
+        assert!(event("customer.created", json!({})).to_billing_change().unwrap().is_none());
+    }
+}
@@ -0,0 +1,104 @@
+//! Defines the branding settings used for white-label deployments.
+//!
+//! ## Purpose
+//! - The product name, logo and accent colour are stored once for the organization running the
+//!   deployment, so emails and the frontend are branded without a rebuild.
+//! - The row is seeded by a migration, so there is always branding to read.
+use serde::{Serialize, Deserialize};
+use chrono::NaiveDateTime;
+use validator::{Validate, ValidationError};
+use crate::users::validate_name;
+
+
+/// The branding settings as they are submitted.
+///
+/// # Fields
+/// * product_name - The name the product is shown under.
+/// * logo_url - The HTTPS URL of the logo, `None` shows no logo.
+/// * accent_color - The accent colour as a hex code such as `#2563eb`.
+///
+/// # Validation
+/// * `product_name` - 1 to 64 characters with no control characters.
+/// * `logo_url` - An `https` URL of at most 2048 characters.
+/// * `accent_color` - A 6 digit hex colour with a leading `#`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Validate)]
+pub struct NewOrgBranding {
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_name")
+    )]
+    pub product_name: String,
+    #[validate(
+        length(max = 2048, message = "must be at most 2048 characters"),
+        url(message = "must be a valid URL"),
+        custom(function = "validate_https")
+    )]
+    pub logo_url: Option<String>,
+    #[validate(custom(function = "validate_hex_color"))]
+    pub accent_color: String,
+}
+
+
+/// The branding settings held in the `org_branding` table.
+///
+/// # Fields
+/// * product_name - The name the product is shown under.
+/// * logo_url - The HTTPS URL of the logo, if there is one.
+/// * accent_color - The accent colour as a hex code.
+/// * date_updated - When the branding was last changed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct OrgBranding {
+    pub product_name: String,
+    pub logo_url: Option<String>,
+    pub accent_color: String,
+    pub date_updated: NaiveDateTime,
+}
+
+
+/// Checks a URL is served over HTTPS so it can be embedded without mixed content warnings.
+pub fn validate_https(url: &str) -> Result<(), ValidationError> {
+    match url.starts_with("https://") {
+        true => Ok(()),
+        false => Err(ValidationError::new("https").with_message("must use https".into()))
+    }
+}
+
+
+/// Checks a colour is a 6 digit hex code with a leading `#`.
+pub fn validate_hex_color(color: &str) -> Result<(), ValidationError> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    match valid {
+        true => Ok(()),
+        false => Err(ValidationError::new("hex_color").with_message("must be a hex colour such as #2563eb".into()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branding(logo_url: Option<&str>, accent_color: &str) -> NewOrgBranding {
+        NewOrgBranding {
+            product_name: "Acme Tasks".to_string(),
+            logo_url: logo_url.map(str::to_string),
+            accent_color: accent_color.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_new_org_branding_validation() {
+        assert!(branding(Some("https://cdn.example.com/logo.png"), "#2563eb").validate().is_ok());
+        assert!(branding(None, "#ABCDEF").validate().is_ok());
+
+        let errors = branding(Some("http://cdn.example.com/logo.png"), "#2563eb").validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("logo_url"));
+
+        for accent_color in ["2563eb", "#2563e", "#2563eg", "#2563ebff"] {
+            let errors = branding(None, accent_color).validate().unwrap_err();
+            assert!(errors.field_errors().contains_key("accent_color"));
+        }
+    }
+}
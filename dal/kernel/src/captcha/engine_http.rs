@@ -0,0 +1,69 @@
+//! Verifies solved CAPTCHAs with the `siteverify` endpoint of hCaptcha or reCAPTCHA.
+//!
+//! # Overview
+//! Both providers take the site's secret, the token and optionally the client's IP as a form, and
+//! answer with a JSON object whose `success` field tells whether the CAPTCHA was solved.
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::Duration;
+use serde::Deserialize;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::captcha::{CaptchaProvider, VerifyHumanChallenge};
+
+
+/// How long the provider has to answer a verification.
+pub const TIMEOUT_SECONDS: u64 = 10;
+
+static CAPTCHA_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(TIMEOUT_SECONDS))
+        .build()
+        .expect("the CAPTCHA client can be built")
+});
+
+
+/// The part of the verification response that is used.
+#[derive(Deserialize)]
+struct VerifyResponse {
+    success: bool,
+}
+
+
+/// Verifies tokens with the provider set in `CAPTCHA_PROVIDER`.
+pub struct HttpCaptchaVerifier;
+
+impl VerifyHumanChallenge for HttpCaptchaVerifier {
+    fn verify_human_challenge<Y: GetConfigVariable>(token: String, remote_ip: Option<String>)
+    -> impl Future<Output = Result<bool, NanoServiceError>> + Send {
+        let provider = CaptchaProvider::from_config::<Y>();
+        let secret = Y::get_config_variable("CAPTCHA_SECRET".to_string());
+        async move {
+            let (provider, secret) = (provider?, secret?);
+            let mut form = vec![("secret", secret), ("response", token)];
+            if let Some(remote_ip) = remote_ip {
+                form.push(("remoteip", remote_ip));
+            }
+            let response = CAPTCHA_CLIENT
+                .post(provider.verify_endpoint())
+                .form(&form)
+                .send()
+                .await
+                .map_err(|e| NanoServiceError::new(
+                    format!("Failed to reach the CAPTCHA provider: {}", e),
+                    NanoServiceErrorStatus::Unknown
+                ))?;
+            if !response.status().is_success() {
+                return Err(NanoServiceError::new(
+                    format!("The CAPTCHA provider failed to verify the token with {}", response.status()),
+                    NanoServiceErrorStatus::Unknown
+                ))
+            }
+            let body: VerifyResponse = response.json().await.map_err(|e| NanoServiceError::new(
+                format!("Failed to read the CAPTCHA verification: {}", e),
+                NanoServiceErrorStatus::Unknown
+            ))?;
+            Ok(body.success)
+        }
+    }
+}
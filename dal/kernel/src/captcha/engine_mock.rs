@@ -0,0 +1,14 @@
+//! CAPTCHA verifiers for tests.
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::captcha::VerifyHumanChallenge;
+
+
+/// Passes the token `solved` and fails every other token, so a test sends the outcome it wants.
+pub struct SolvedTokenMock;
+
+impl VerifyHumanChallenge for SolvedTokenMock {
+    async fn verify_human_challenge<Y: GetConfigVariable>(token: String, _remote_ip: Option<String>) -> Result<bool, NanoServiceError> {
+        Ok(token == "solved")
+    }
+}
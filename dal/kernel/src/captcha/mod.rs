@@ -0,0 +1,146 @@
+//! Asks clients that look like they are guessing credentials to prove they are human.
+//!
+//! ## Purpose
+//! - Once an account has been tried too often on the login or password reset endpoints, requests
+//!   for it have to carry a solved CAPTCHA in the `X-Captcha-Token` header, see
+//!   `auth_networking::rate_limit`.
+//! - The check is behind `VerifyHumanChallenge` so hCaptcha, reCAPTCHA or a mock in tests can be
+//!   plugged in.
+//! - A request without a solved CAPTCHA is refused with a `403` and a `captcha_required` code, so
+//!   clients know to show the challenge and try again.
+//!
+//! ## Variables
+//! * `CAPTCHA_PROVIDER` - `hcaptcha` or `recaptcha`, defaults to `hcaptcha`
+//! * `CAPTCHA_SECRET` - The secret key of the site, challenges cannot be passed when not set
+pub mod engine_http;
+pub mod engine_mock;
+
+use serde_json::json;
+use std::future::Future;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The header a client sends its solved CAPTCHA in.
+pub const CAPTCHA_HEADER: &str = "X-Captcha-Token";
+
+/// The code in the error details when a request has to carry a solved CAPTCHA.
+pub const CAPTCHA_REQUIRED_CODE: &str = "captcha_required";
+
+
+/// The services that can verify a solved CAPTCHA.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptchaProvider {
+    HCaptcha,
+    ReCaptcha,
+}
+
+impl CaptchaProvider {
+
+    /// Reads the provider from `CAPTCHA_PROVIDER`, hCaptcha if it is not set.
+    ///
+    /// # Returns
+    /// * `Ok(CaptchaProvider)` - The configured provider
+    /// * `Err(NanoServiceError)` - `Unknown` if the variable names a provider that is not supported
+    pub fn from_config<Y: GetConfigVariable>() -> Result<Self, NanoServiceError> {
+        let name = Y::get_config_variable("CAPTCHA_PROVIDER".to_string()).unwrap_or_default();
+        match name.trim().to_lowercase().as_str() {
+            "" | "hcaptcha" => Ok(CaptchaProvider::HCaptcha),
+            "recaptcha" => Ok(CaptchaProvider::ReCaptcha),
+            other => Err(NanoServiceError::new(
+                format!("{} is not a supported CAPTCHA provider", other),
+                NanoServiceErrorStatus::Unknown
+            )),
+        }
+    }
+
+    /// The endpoint solved CAPTCHAs are verified at.
+    pub fn verify_endpoint(&self) -> &'static str {
+        match self {
+            CaptchaProvider::HCaptcha => "https://api.hcaptcha.com/siteverify",
+            CaptchaProvider::ReCaptcha => "https://www.google.com/recaptcha/api/siteverify",
+        }
+    }
+}
+
+
+/// Defines the contract for checking a solved CAPTCHA.
+pub trait VerifyHumanChallenge {
+
+    /// Checks the token a client got by solving a CAPTCHA.
+    ///
+    /// # Arguments
+    /// * `token` - The token from the `X-Captcha-Token` header.
+    /// * `remote_ip` - The IP of the client, if known, for the provider to check it solved the CAPTCHA.
+    ///
+    /// # Returns
+    /// * `Ok(bool)` - Whether the CAPTCHA was solved
+    /// * `Err(NanoServiceError)` - If the provider could not be reached or is not configured
+    fn verify_human_challenge<Y: GetConfigVariable>(token: String, remote_ip: Option<String>)
+    -> impl Future<Output = Result<bool, NanoServiceError>> + Send;
+}
+
+
+/// The error returned when a request has to carry a solved CAPTCHA and does not.
+pub fn captcha_required() -> NanoServiceError {
+    NanoServiceError::new(
+        "Solve the CAPTCHA and try again".to_string(),
+        NanoServiceErrorStatus::Forbidden
+    ).with_details(json!({"code": CAPTCHA_REQUIRED_CODE, "header": CAPTCHA_HEADER}))
+}
+
+
+/// Refuses a request unless it carries a solved CAPTCHA.
+///
+/// # Arguments
+/// * `token` - The token from the `X-Captcha-Token` header, if the request has one.
+/// * `remote_ip` - The IP of the client, if known.
+///
+/// # Returns
+/// * `Ok(())` - If the CAPTCHA was solved
+/// * `Err(NanoServiceError)` - `Forbidden` with the `captcha_required` code if there is no token
+///   or it was not solved, or the error from the provider if it could not be checked
+pub async fn require_human<C: VerifyHumanChallenge, Y: GetConfigVariable>(token: Option<String>, remote_ip: Option<String>) -> Result<(), NanoServiceError> {
+    let token = match token.map(|token| token.trim().to_string()).filter(|token| !token.is_empty()) {
+        Some(token) => token,
+        None => return Err(captcha_required()),
+    };
+    match C::verify_human_challenge::<Y>(token, remote_ip).await? {
+        true => Ok(()),
+        false => Err(captcha_required()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::engine_mock::SolvedTokenMock;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(key: String) -> Result<String, NanoServiceError> {
+            match key.as_str() {
+                "CAPTCHA_PROVIDER" => Ok("reCAPTCHA".to_string()),
+                _ => Ok("secret".to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_provider_from_config() {
+        assert_eq!(CaptchaProvider::from_config::<MockConfig>().unwrap(), CaptchaProvider::ReCaptcha);
+    }
+
+    #[tokio::test]
+    async fn test_require_human() {
+        assert!(require_human::<SolvedTokenMock, MockConfig>(Some("solved".to_string()), None).await.is_ok());
+
+        for token in [None, Some(" ".to_string()), Some("guessed".to_string())] {
+            let error = require_human::<SolvedTokenMock, MockConfig>(token, None).await.unwrap_err();
+            assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+            assert_eq!(error.details.unwrap()["code"], "captcha_required");
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Implements `ScanContent` with the ClamAV daemon.
+//!
+//! # Overview
+//! The file is streamed to clamd over TCP with the `INSTREAM` command, in chunks each prefixed
+//! with their length as a big endian `u32` and ended with a zero length chunk. clamd replies with
+//! `stream: OK`, `stream: <signature> FOUND` or `<reason> ERROR`.
+//!
+//! # Variables
+//! * `CLAMD_ADDRESS` - The address clamd listens on, such as `127.0.0.1:3310`, uploads are refused when not set
+//! * `CLAMD_TIMEOUT_SECS` - How long a scan can take before the upload is refused, defaults to 30
+use std::future::Future;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::content_scan::{ScanContent, ScanVerdict};
+
+
+/// The size of the chunks the file is streamed in, well under clamd's default `StreamMaxLength`.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// How long a scan can take when `CLAMD_TIMEOUT_SECS` is not set.
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+
+/// Scans files with the ClamAV daemon at `CLAMD_ADDRESS`.
+pub struct ClamdScanner;
+
+impl ScanContent for ClamdScanner {
+    fn scan_content<Y: GetConfigVariable>(content: &[u8])
+    -> impl Future<Output = Result<ScanVerdict, NanoServiceError>> + Send {
+        let address = Y::get_config_variable("CLAMD_ADDRESS".to_string());
+        let timeout = Y::get_config_variable("CLAMD_TIMEOUT_SECS".to_string())
+            .ok()
+            .and_then(|secs| secs.trim().parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+        async move {
+            let address = address?;
+            let reply = tokio::time::timeout(Duration::from_secs(timeout), scan_stream(&address, content))
+                .await
+                .map_err(|_| scan_error(format!("clamd did not reply within {} seconds", timeout)))?
+                .map_err(|e| scan_error(format!("Failed to scan with clamd at {}: {}", address, e)))?;
+            parse_reply(&reply)
+        }
+    }
+}
+
+
+/// Streams the content to clamd and reads its reply.
+async fn scan_stream(address: &str, content: &[u8]) -> std::io::Result<String> {
+    let mut stream = TcpStream::connect(address).await?;
+    stream.write_all(b"zINSTREAM\0").await?;
+    for chunk in content.chunks(CHUNK_SIZE) {
+        stream.write_all(&(chunk.len() as u32).to_be_bytes()).await?;
+        stream.write_all(chunk).await?;
+    }
+    stream.write_all(&0u32.to_be_bytes()).await?;
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).await?;
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
+
+
+/// Reads the verdict from a clamd reply.
+fn parse_reply(reply: &str) -> Result<ScanVerdict, NanoServiceError> {
+    let reply = reply.trim_end_matches(['\0', '\n']).trim();
+    let result = reply.strip_prefix("stream:").map(str::trim).unwrap_or(reply);
+    if result == "OK" {
+        return Ok(ScanVerdict::Clean)
+    }
+    if let Some(signature) = result.strip_suffix(" FOUND") {
+        return Ok(ScanVerdict::Infected { signature: signature.trim().to_string() })
+    }
+    Err(scan_error(format!("clamd could not scan the file: {}", reply)))
+}
+
+
+fn scan_error(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::Unknown)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply("stream: OK\0").unwrap(), ScanVerdict::Clean);
+        assert_eq!(
+            parse_reply("stream: Win.Test.EICAR_HDB-1 FOUND\0").unwrap(),
+            ScanVerdict::Infected { signature: "Win.Test.EICAR_HDB-1".to_string() }
+        );
+        let error = parse_reply("INSTREAM size limit exceeded. ERROR\0").unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Unknown);
+    }
+
+    /// Runs a fake clamd that checks the stream and replies with `reply`.
+    async fn fake_clamd(reply: &'static str, expected: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut command = [0u8; 10];
+            socket.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+            let mut received = Vec::new();
+            loop {
+                let mut length = [0u8; 4];
+                socket.read_exact(&mut length).await.unwrap();
+                let length = u32::from_be_bytes(length) as usize;
+                if length == 0 {
+                    break
+                }
+                let mut chunk = vec![0u8; length];
+                socket.read_exact(&mut chunk).await.unwrap();
+                received.extend(chunk);
+            }
+            assert_eq!(received, expected);
+            socket.write_all(reply.as_bytes()).await.unwrap();
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_scan_with_clamd() {
+        // larger than a chunk so the file is streamed in several
+        let content = vec![7u8; CHUNK_SIZE + 10];
+        let address = fake_clamd("stream: Eicar-Signature FOUND\0", content.clone()).await;
+        let reply = scan_stream(&address, &content).await.unwrap();
+        assert_eq!(parse_reply(&reply).unwrap(), ScanVerdict::Infected { signature: "Eicar-Signature".to_string() });
+    }
+}
@@ -0,0 +1,24 @@
+//! Scanners that do not scan, for tests and local development.
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::content_scan::{ScanContent, ScanVerdict};
+
+
+/// Passes every file as clean.
+pub struct PassScanMock;
+
+impl ScanContent for PassScanMock {
+    async fn scan_content<Y: GetConfigVariable>(_content: &[u8]) -> Result<ScanVerdict, NanoServiceError> {
+        Ok(ScanVerdict::Clean)
+    }
+}
+
+
+/// Finds `Test-Signature` in every file, for testing how upload paths refuse infected files.
+pub struct InfectedScanMock;
+
+impl ScanContent for InfectedScanMock {
+    async fn scan_content<Y: GetConfigVariable>(_content: &[u8]) -> Result<ScanVerdict, NanoServiceError> {
+        Ok(ScanVerdict::Infected { signature: "Test-Signature".to_string() })
+    }
+}
@@ -0,0 +1,86 @@
+//! Scans uploaded files for viruses before they are stored.
+//!
+//! ## Purpose
+//! - Every upload path passes the file through `reject_infected` so infected files never reach storage.
+//! - The scanner is behind `ScanContent` so the ClamAV daemon can be swapped for a mock in tests
+//!   and for local development.
+//! - An infected file is refused with a `422` and an `infected_file` code so clients can tell it
+//!   apart from a file that is simply not valid.
+pub mod engine_clamd;
+pub mod engine_mock;
+
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+use std::future::Future;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The code in the error details when a file is refused for being infected.
+pub const INFECTED_FILE_CODE: &str = "infected_file";
+
+
+/// The outcome of scanning a file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+pub enum ScanVerdict {
+    Clean,
+    Infected { signature: String },
+}
+
+
+/// Defines the contract for scanning the content of an uploaded file.
+pub trait ScanContent {
+
+    /// Scans the content of a file.
+    ///
+    /// # Returns
+    /// * `Ok(ScanVerdict)` - Whether the file is clean or infected
+    /// * `Err(NanoServiceError)` - If the scanner could not be reached or could not scan the file
+    fn scan_content<Y: GetConfigVariable>(content: &[u8])
+    -> impl Future<Output = Result<ScanVerdict, NanoServiceError>> + Send;
+}
+
+
+/// Scans a file and refuses it if it is infected.
+///
+/// # Arguments
+/// * `content` - The content of the uploaded file.
+///
+/// # Returns
+/// * `Ok(())` - If the file is clean
+/// * `Err(NanoServiceError)` - `UnprocessableEntity` with the `infected_file` code and the signature
+///   found if the file is infected, or the error from the scanner if it could not be scanned
+pub async fn reject_infected<S: ScanContent, Y: GetConfigVariable>(content: &[u8]) -> Result<(), NanoServiceError> {
+    match S::scan_content::<Y>(content).await? {
+        ScanVerdict::Clean => Ok(()),
+        ScanVerdict::Infected { signature } => Err(NanoServiceError::new(
+            "The file was rejected because it contains a virus".to_string(),
+            NanoServiceErrorStatus::UnprocessableEntity
+        ).with_details(json!({"code": INFECTED_FILE_CODE, "signature": signature}))),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::engine_mock::{PassScanMock, InfectedScanMock};
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reject_infected() {
+        assert!(reject_infected::<PassScanMock, MockConfig>(b"hello").await.is_ok());
+
+        let error = reject_infected::<InfectedScanMock, MockConfig>(b"hello").await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::UnprocessableEntity);
+        assert_eq!(error.details.unwrap(), json!({"code": "infected_file", "signature": "Test-Signature"}));
+    }
+}
@@ -0,0 +1,168 @@
+//! Defines the structs for the devices users log in from.
+//!
+//! ## Purpose
+//! - Every login records the device it was made from, keyed by a fingerprint of the `User-Agent`,
+//!   the same device info tokens are bound to. The session stores the fingerprint so a device's
+//!   sessions can be ended together.
+//! - Users name, trust and revoke their own devices. Revoking a device forgets it and logs it out.
+//! - Trust is a label a user puts on a device until `trusted_until`, after which it has to be trusted
+//!   again. The login response reports whether the device is trusted, nothing is skipped for it.
+//!
+//! ## Notes
+//! The fingerprint only tells devices with different `User-Agent`s apart, it is not a secret and
+//! does not prove which device a login came from. Logging in again from a revoked device records
+//! it again as a new, untrusted device.
+use chrono::{Duration, NaiveDateTime};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use utils::config::{GetConfigVariable, TypedConfig};
+
+
+/// How long a device stays trusted when not configured.
+pub const DEFAULT_TRUSTED_DEVICE_DAYS: i64 = 30;
+
+/// The longest a device can be configured to stay trusted.
+pub const MAX_TRUSTED_DEVICE_DAYS: i64 = 90;
+
+/// The longest name a device can be given.
+pub const MAX_DEVICE_NAME_LENGTH: usize = 100;
+
+
+/// The fingerprint a device is recorded by.
+///
+/// # Arguments
+/// * `user_agent` - The `User-Agent` of the requests made from the device.
+pub fn device_fingerprint(user_agent: &str) -> String {
+    hex::encode(Sha256::digest(user_agent.as_bytes()))
+}
+
+
+/// How long a device stays trusted, from `TRUSTED_DEVICE_DAYS`.
+pub fn trusted_device_lifetime<Y: GetConfigVariable>() -> Duration {
+    let days = Y::get_int("TRUSTED_DEVICE_DAYS").ok()
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_TRUSTED_DEVICE_DAYS)
+        .min(MAX_TRUSTED_DEVICE_DAYS);
+    Duration::days(days)
+}
+
+
+/// Represents the schema for recording a login from a device.
+///
+/// # Fields
+/// * user_id - The ID of the user logging in.
+/// * fingerprint - The fingerprint of the device.
+/// * user_agent - The `User-Agent` of the device, shown so users can tell their devices apart.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewDevice {
+    pub user_id: i32,
+    pub fingerprint: String,
+    pub user_agent: String,
+}
+
+impl NewDevice {
+
+    /// The device a user is logging in from.
+    pub fn new(user_id: i32, user_agent: &str) -> Self {
+        NewDevice {
+            user_id,
+            fingerprint: device_fingerprint(user_agent),
+            user_agent: user_agent.to_string(),
+        }
+    }
+}
+
+
+/// Represents a device a user has logged in from.
+///
+/// # Fields
+/// * id - The unique identifier for the device.
+/// * user_id - The ID of the user the device belongs to.
+/// * fingerprint - The fingerprint of the device, never returned.
+/// * name - The name the user gave the device, if they have named it.
+/// * user_agent - The `User-Agent` of the device.
+/// * trusted_until - When the device stops being trusted, `None` if it is not trusted.
+/// * date_created - When the user first logged in from the device.
+/// * last_seen_at - When the user last logged in from the device.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct Device {
+    pub id: i32,
+    pub user_id: i32,
+    #[serde(skip_serializing, default)]
+    pub fingerprint: String,
+    pub name: Option<String>,
+    pub user_agent: String,
+    pub trusted_until: Option<NaiveDateTime>,
+    pub date_created: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+}
+
+impl Device {
+
+    /// Whether the user has marked the device as trusted at `now`.
+    pub fn is_trusted(&self, now: NaiveDateTime) -> bool {
+        self.trusted_until.is_some_and(|trusted_until| trusted_until > now)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use utils::errors::NanoServiceError;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "TRUSTED_DEVICE_DAYS" => Ok("400".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    struct UnsetConfig;
+
+    impl GetConfigVariable for UnsetConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    #[test]
+    fn test_device_fingerprint() {
+        let device = NewDevice::new(2, "Mozilla/5.0");
+        assert_eq!(device.fingerprint, device_fingerprint("Mozilla/5.0"));
+        assert_eq!(device.fingerprint.len(), 64);
+        assert_ne!(device.fingerprint, device_fingerprint("curl/8.0"));
+    }
+
+    #[test]
+    fn test_trusted_device_lifetime() {
+        assert_eq!(trusted_device_lifetime::<MockConfig>(), Duration::days(MAX_TRUSTED_DEVICE_DAYS));
+        assert_eq!(trusted_device_lifetime::<UnsetConfig>(), Duration::days(DEFAULT_TRUSTED_DEVICE_DAYS));
+    }
+
+    #[test]
+    fn test_is_trusted() {
+        let now = Utc::now().naive_utc();
+        let mut device = Device {
+            id: 1,
+            user_id: 2,
+            fingerprint: device_fingerprint("Mozilla/5.0"),
+            name: None,
+            user_agent: "Mozilla/5.0".to_string(),
+            trusted_until: None,
+            date_created: now,
+            last_seen_at: now,
+        };
+        assert!(!device.is_trusted(now));
+        device.trusted_until = Some(now + Duration::days(1));
+        assert!(device.is_trusted(now));
+        device.trusted_until = Some(now - Duration::days(1));
+        assert!(!device.is_trusted(now));
+        assert!(serde_json::to_value(&device).unwrap().get("fingerprint").is_none());
+    }
+}
@@ -0,0 +1,122 @@
+//! Defines the structs for the email outbox.
+//!
+//! ## Purpose
+//! - Outgoing emails are written to the `email_outbox` table instead of being sent inside the request.
+//! - A worker sends the pending emails, retrying failures with backoff until they are sent or
+//!   marked as permanently failed.
+use serde::{Serialize, Deserialize};
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
+use sqlx::types::Json;
+use chrono::NaiveDateTime;
+use std::error::Error;
+use std::str::FromStr;
+
+
+/// The delivery status of an email in the outbox.
+///
+/// # Variants
+/// * `Pending` - The email is waiting to be sent or retried.
+/// * `Sent` - The provider accepted the email.
+/// * `Failed` - The email will not be retried.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboxStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+impl OutboxStatus {
+
+    /// The value stored in the `status` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Sent => "sent",
+            OutboxStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for OutboxStatus {
+    type Err = String;
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.trim() {
+            "pending" => Ok(OutboxStatus::Pending),
+            "sent" => Ok(OutboxStatus::Sent),
+            "failed" => Ok(OutboxStatus::Failed),
+            _ => Err(format!("Invalid outbox status: {}", status)),
+        }
+    }
+}
+
+impl Type<Postgres> for OutboxStatus {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for OutboxStatus {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for OutboxStatus {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        OutboxStatus::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// Represents the schema for queueing an email.
+///
+/// # Fields
+/// * template - The provider payload, stored as JSON so the outbox does not depend on the provider.
+/// * send_at - The earliest time the email may be sent, `None` sends it as soon as possible.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewOutboxEmail {
+    pub template: serde_json::Value,
+    pub send_at: Option<NaiveDateTime>,
+}
+
+
+/// Represents an email in the outbox.
+///
+/// # Fields
+/// * id - The unique identifier for the email.
+/// * template - The provider payload.
+/// * status - The delivery status.
+/// * attempts - How many times sending has been tried.
+/// * next_attempt_at - When the email is next due to be tried.
+/// * last_error - The error from the most recent failed attempt.
+/// * date_created - When the email was queued.
+/// * date_sent - When the provider accepted the email.
+/// * send_at - The earliest time the email may be sent.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct OutboxEmail {
+    pub id: i32,
+    pub template: Json<serde_json::Value>,
+    pub status: OutboxStatus,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+    pub date_created: NaiveDateTime,
+    pub date_sent: Option<NaiveDateTime>,
+    pub send_at: NaiveDateTime,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outbox_status_round_trip() {
+        for status in [OutboxStatus::Pending, OutboxStatus::Sent, OutboxStatus::Failed] {
+            assert_eq!(OutboxStatus::from_str(status.as_str()).unwrap(), status);
+        }
+        assert!(OutboxStatus::from_str("queued").is_err());
+    }
+}
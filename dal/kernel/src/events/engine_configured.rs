@@ -0,0 +1,27 @@
+//! Picks where events go from the config.
+//!
+//! Events always go to the subscribers in the process, so the features that react to them work
+//! whatever the config, and can also be published to NATS for other processes.
+//!
+//! # Variables
+//! * `EVENT_BUS` - `nats` to also publish to `NatsEventBus`, anything else or unset keeps events in
+//!   the process
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::events::{EventBus, EventEnvelope};
+use crate::events::engine_in_process::InProcessEventBus;
+use crate::events::engine_nats::NatsEventBus;
+
+
+/// Sends events to the subscribers in the process and the bus named by `EVENT_BUS`.
+pub struct ConfiguredEventBus;
+
+impl EventBus for ConfiguredEventBus {
+    async fn publish<Y: GetConfigVariable>(envelope: &EventEnvelope) -> Result<(), NanoServiceError> {
+        InProcessEventBus::publish::<Y>(envelope).await?;
+        match Y::get_config_variable("EVENT_BUS".to_string()).as_deref().map(str::trim) {
+            Ok("nats") => NatsEventBus::publish::<Y>(envelope).await,
+            _ => Ok(()),
+        }
+    }
+}
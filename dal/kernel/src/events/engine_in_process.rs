@@ -0,0 +1,67 @@
+//! Hands events to subscribers in the same process, for features such as the audit log that react
+//! to events published by another nanoservice in the same binary.
+use futures::future::BoxFuture;
+use std::sync::{LazyLock, RwLock};
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::events::{EventBus, EventEnvelope};
+
+
+/// A subscriber, called with every event published in the process and ignoring those it does not need.
+pub type Subscriber = fn(EventEnvelope) -> BoxFuture<'static, ()>;
+
+
+static SUBSCRIBERS: LazyLock<RwLock<Vec<Subscriber>>> = LazyLock::new(|| RwLock::new(Vec::new()));
+
+
+/// Adds a subscriber for every event published with `InProcessEventBus` from now on.
+pub fn subscribe(subscriber: Subscriber) {
+    SUBSCRIBERS.write().unwrap().push(subscriber);
+}
+
+
+/// Spawns each subscriber with the event so a slow subscriber never holds up the request that published it.
+pub struct InProcessEventBus;
+
+impl EventBus for InProcessEventBus {
+    async fn publish<Y: GetConfigVariable>(envelope: &EventEnvelope) -> Result<(), NanoServiceError> {
+        let subscribers = SUBSCRIBERS.read().unwrap().clone();
+        for subscriber in subscribers {
+            tokio::spawn(subscriber(envelope.clone()));
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use crate::events::DomainEvent;
+    use test_support::MissingConfig;
+
+    static RECEIVED: Mutex<Vec<EventEnvelope>> = Mutex::new(Vec::new());
+
+    fn record(envelope: EventEnvelope) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            RECEIVED.lock().unwrap().push(envelope);
+        })
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_events() {
+        subscribe(record);
+        subscribe(record);
+        let envelope = EventEnvelope::new(DomainEvent::UserBlocked { user_id: 3, blocked_by: 1 });
+        InProcessEventBus::publish::<MissingConfig>(&envelope).await.unwrap();
+        for _ in 0..50 {
+            if RECEIVED.lock().unwrap().len() == 2 {
+                break
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(*RECEIVED.lock().unwrap(), vec![envelope.clone(), envelope]);
+    }
+}
@@ -0,0 +1,30 @@
+//! Buses for tests.
+use std::sync::Mutex;
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::events::{EventBus, EventEnvelope};
+
+
+/// Every event sent to `RecordEventsMock`, shared by the tests of a crate.
+pub static PUBLISHED_EVENTS: Mutex<Vec<EventEnvelope>> = Mutex::new(Vec::new());
+
+
+/// Drops every event.
+pub struct NoEventsMock;
+
+impl EventBus for NoEventsMock {
+    async fn publish<Y: GetConfigVariable>(_envelope: &EventEnvelope) -> Result<(), NanoServiceError> {
+        Ok(())
+    }
+}
+
+
+/// Keeps every event in `PUBLISHED_EVENTS`.
+pub struct RecordEventsMock;
+
+impl EventBus for RecordEventsMock {
+    async fn publish<Y: GetConfigVariable>(envelope: &EventEnvelope) -> Result<(), NanoServiceError> {
+        PUBLISHED_EVENTS.lock().unwrap().push(envelope.clone());
+        Ok(())
+    }
+}
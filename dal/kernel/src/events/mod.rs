@@ -0,0 +1,127 @@
+//! Publishes domain events so one nanoservice can react to what happens in another without
+//! calling it.
+//!
+//! ## Purpose
+//! - The core functions publish a `DomainEvent` when a user is created or blocked, or a to-do item
+//!   is completed, through an `EventBus` so where events go is chosen by config, see
+//!   `engine_configured`.
+//! - Every event is sent in an `EventEnvelope` with its own ID and the time it happened, so a
+//!   subscriber that sees an event twice can tell.
+//! - Publishing never fails a request, `publish` logs a bus's error and carries on.
+pub mod engine_in_process;
+pub mod engine_nats;
+pub mod engine_configured;
+pub mod engine_mock;
+
+use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use uuid::Uuid;
+use utils::config::GetConfigVariable;
+use utils::log_limited;
+use utils::errors::NanoServiceError;
+use crate::to_do_items::Todo;
+use crate::users::{User, UserRole};
+
+
+/// Something that happened in a nanoservice that others may want to react to.
+///
+/// # Variants
+/// * `UserCreated` - A user signed up or was created by an admin.
+/// * `UserBlocked` - A super admin blocked a user.
+/// * `TodoCompleted` - A to-do item was completed, or sent for review.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum DomainEvent {
+    UserCreated { user_id: i32, role: UserRole },
+    UserBlocked { user_id: i32, blocked_by: i32 },
+    TodoCompleted { todo_id: i32, assigned_to: i32, pending_review: bool },
+}
+
+impl DomainEvent {
+
+    /// The name the event is published under.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DomainEvent::UserCreated { .. } => "user_created",
+            DomainEvent::UserBlocked { .. } => "user_blocked",
+            DomainEvent::TodoCompleted { .. } => "todo_completed",
+        }
+    }
+
+    /// A user was created.
+    pub fn user_created(user: &User) -> Self {
+        DomainEvent::UserCreated { user_id: user.id, role: user.user_role.clone() }
+    }
+
+    /// A to-do item was completed by its assignee.
+    pub fn todo_completed(todo: &Todo) -> Self {
+        DomainEvent::TodoCompleted { todo_id: todo.id, assigned_to: todo.assigned_to, pending_review: todo.pending_review }
+    }
+}
+
+
+/// An event as it is published.
+///
+/// # Fields
+/// * `id` - The ID of this publication of the event.
+/// * `occurred_at` - When it happened.
+/// * `event` - What happened, flattened in as `event` and `data`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EventEnvelope {
+    pub id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: DomainEvent,
+}
+
+impl EventEnvelope {
+
+    /// Wraps an event happening now.
+    pub fn new(event: DomainEvent) -> Self {
+        EventEnvelope { id: Uuid::new_v4(), occurred_at: Utc::now(), event }
+    }
+}
+
+
+/// Defines the contract for sending domain events to whoever subscribes to them.
+pub trait EventBus {
+
+    /// Sends an event.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the event was sent, or handed to the subscribers
+    /// * `Err(NanoServiceError)` - If the bus is misconfigured or could not be reached
+    fn publish<Y: GetConfigVariable>(envelope: &EventEnvelope)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send;
+}
+
+
+/// Sends an event with `E`, logging rather than returning a failure so publishing never fails a request.
+pub async fn publish<E: EventBus, Y: GetConfigVariable>(event: DomainEvent) {
+    let envelope = EventEnvelope::new(event);
+    if let Err(e) = E::publish::<Y>(&envelope).await {
+        log_limited!("events", "failed to publish {} event {}: {}", envelope.event.name(), envelope.id, e.message);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_envelope_json() {
+        let envelope = EventEnvelope::new(DomainEvent::UserBlocked { user_id: 4, blocked_by: 1 });
+        let value = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(value["event"], "user_blocked");
+        assert_eq!(value["data"], json!({"user_id": 4, "blocked_by": 1}));
+        assert_eq!(value["id"], envelope.id.to_string());
+        assert_eq!(serde_json::from_value::<EventEnvelope>(value).unwrap(), envelope);
+
+        let created = DomainEvent::UserCreated { user_id: 2, role: UserRole::Worker };
+        assert_eq!(serde_json::to_value(&created).unwrap()["data"]["role"], "Worker");
+        assert_eq!(created.name(), "user_created");
+    }
+}
@@ -0,0 +1,289 @@
+//! Defines the structs for exports run in the background.
+//!
+//! ## Purpose
+//! - An export too large to stream inside a request is queued as a job in `export_jobs`.
+//! - A worker writes the export to the object store, recording how far it has got so the
+//!   requester can poll its progress, and the requester downloads it from a presigned link.
+//! - The file is deleted from the object store once the job expires.
+use serde::{Serialize, Deserialize};
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
+use chrono::NaiveDateTime;
+use std::error::Error;
+use std::str::FromStr;
+use utils::export_stream::{CsvRow, ExportFormat, csv_field};
+use utils::locale::iso_datetime;
+use crate::users::UserRole;
+
+
+/// What an export job exports.
+///
+/// # Variants
+/// * `Users` - Every user, without their password or confirmation token.
+/// * `Todos` - Every to-do item.
+/// * `AuditLog` - Every entry in the audit log, newest first.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportKind {
+    Users,
+    Todos,
+    AuditLog,
+}
+
+impl ExportKind {
+
+    /// The value stored in the `kind` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportKind::Users => "users",
+            ExportKind::Todos => "todos",
+            ExportKind::AuditLog => "audit_log",
+        }
+    }
+}
+
+impl FromStr for ExportKind {
+    type Err = String;
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind.trim() {
+            "users" => Ok(ExportKind::Users),
+            "todos" => Ok(ExportKind::Todos),
+            "audit_log" => Ok(ExportKind::AuditLog),
+            _ => Err(format!("Invalid export kind: {}", kind)),
+        }
+    }
+}
+
+impl Type<Postgres> for ExportKind {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for ExportKind {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for ExportKind {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        ExportKind::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// Where an export job has got to.
+///
+/// # Variants
+/// * `Pending` - The job is waiting for a worker.
+/// * `Running` - A worker is writing the export.
+/// * `Complete` - The export can be downloaded until the job expires.
+/// * `Failed` - The export could not be written, see the job's `error`.
+/// * `Expired` - The export has been deleted from the object store.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportJobStatus {
+    Pending,
+    Running,
+    Complete,
+    Failed,
+    Expired,
+}
+
+impl ExportJobStatus {
+
+    /// The value stored in the `status` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportJobStatus::Pending => "pending",
+            ExportJobStatus::Running => "running",
+            ExportJobStatus::Complete => "complete",
+            ExportJobStatus::Failed => "failed",
+            ExportJobStatus::Expired => "expired",
+        }
+    }
+}
+
+impl FromStr for ExportJobStatus {
+    type Err = String;
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.trim() {
+            "pending" => Ok(ExportJobStatus::Pending),
+            "running" => Ok(ExportJobStatus::Running),
+            "complete" => Ok(ExportJobStatus::Complete),
+            "failed" => Ok(ExportJobStatus::Failed),
+            "expired" => Ok(ExportJobStatus::Expired),
+            _ => Err(format!("Invalid export job status: {}", status)),
+        }
+    }
+}
+
+impl Type<Postgres> for ExportJobStatus {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for ExportJobStatus {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for ExportJobStatus {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        ExportJobStatus::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// Represents the schema for queueing an export.
+///
+/// # Fields
+/// * requested_by - The ID of the user who asked for the export, the only user who can download it.
+/// * kind - What to export.
+/// * format - `json` or `csv`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewExportJob {
+    pub requested_by: i32,
+    pub kind: ExportKind,
+    pub format: String,
+}
+
+
+/// Represents an export job.
+///
+/// # Fields
+/// * id - The unique identifier for the job.
+/// * requested_by - The ID of the user who asked for the export.
+/// * kind - What is exported.
+/// * format - `json` or `csv`.
+/// * status - Where the job has got to.
+/// * total_rows - The rows there were to export when the job started, `None` until it has.
+/// * exported_rows - The rows written so far.
+/// * object_key - Where the export is stored once it is complete.
+/// * error - Why the job failed, if it did.
+/// * date_created - When the export was asked for.
+/// * updated_at - When the job last changed, a running job is touched as each page is written.
+/// * completed_at - When the export was written.
+/// * expires_at - When the export is deleted.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct ExportJob {
+    pub id: i32,
+    pub requested_by: i32,
+    pub kind: ExportKind,
+    pub format: String,
+    pub status: ExportJobStatus,
+    pub total_rows: Option<i64>,
+    pub exported_rows: i64,
+    pub object_key: Option<String>,
+    pub error: Option<String>,
+    pub date_created: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl ExportJob {
+
+    /// The format the export is written in, JSON if the stored value is not known.
+    pub fn export_format(&self) -> ExportFormat {
+        match self.format.as_str() {
+            "csv" => ExportFormat::Csv,
+            _ => ExportFormat::Json,
+        }
+    }
+
+    /// The key the export is stored under, such as `exports/12/users.csv.gz`.
+    pub fn storage_key(&self) -> String {
+        format!("exports/{}/{}.{}.gz", self.id, self.kind.as_str(), self.export_format().extension())
+    }
+
+    /// How far through the export the job is, only `100` once it is complete.
+    pub fn progress_percent(&self) -> u8 {
+        match (self.status, self.total_rows) {
+            (ExportJobStatus::Complete, _) => 100,
+            (_, Some(total)) if total > 0 => ((self.exported_rows.max(0) * 100 / total).min(99)) as u8,
+            _ => 0,
+        }
+    }
+}
+
+
+/// A user as written to an export, without their password or confirmation token.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct ExportedUser {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub user_role: UserRole,
+    pub confirmed: bool,
+    pub blocked: bool,
+    pub date_created: NaiveDateTime,
+    pub last_logged_in: NaiveDateTime,
+}
+
+impl CsvRow for ExportedUser {
+    const CSV_HEADER: &'static str = "id,username,email,first_name,last_name,user_role,confirmed,blocked,date_created,last_logged_in";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            self.id,
+            csv_field(&self.username),
+            csv_field(&self.email),
+            csv_field(&self.first_name),
+            csv_field(&self.last_name),
+            self.user_role.as_str(),
+            self.confirmed,
+            self.blocked,
+            iso_datetime(self.date_created),
+            iso_datetime(self.last_logged_in)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn job(status: ExportJobStatus, total_rows: Option<i64>, exported_rows: i64) -> ExportJob {
+        let now = Utc::now().naive_utc();
+        ExportJob {
+            id: 12,
+            requested_by: 1,
+            kind: ExportKind::AuditLog,
+            format: "csv".to_string(),
+            status,
+            total_rows,
+            exported_rows,
+            object_key: None,
+            error: None,
+            date_created: now,
+            updated_at: now,
+            completed_at: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_progress_percent() {
+        assert_eq!(job(ExportJobStatus::Pending, None, 0).progress_percent(), 0);
+        assert_eq!(job(ExportJobStatus::Running, Some(400), 100).progress_percent(), 25);
+        assert_eq!(job(ExportJobStatus::Running, Some(400), 400).progress_percent(), 99);
+        assert_eq!(job(ExportJobStatus::Running, Some(0), 0).progress_percent(), 0);
+        assert_eq!(job(ExportJobStatus::Complete, Some(0), 0).progress_percent(), 100);
+    }
+
+    #[test]
+    fn test_storage_key() {
+        assert_eq!(job(ExportJobStatus::Pending, None, 0).storage_key(), "exports/12/audit_log.csv.gz");
+        assert_eq!(serde_json::to_string(&ExportKind::AuditLog).unwrap(), "\"audit_log\"");
+        assert_eq!(ExportKind::from_str("todos"), Ok(ExportKind::Todos));
+    }
+}
@@ -0,0 +1,124 @@
+//! Prunes responses down to the fields a client asks for with `?fields=`.
+//!
+//! ## Purpose
+//! - Mobile clients listing many users or to-do items only need a few fields of each, so a GET can
+//!   take `?fields=id,name,due_date` and each object comes back with only those fields.
+//! - Each endpoint names the fields that can be picked, e.g. `USER_FIELDS`, and any other field is
+//!   refused so clients cannot rely on fields that are not part of the API.
+//! - Without `fields` the response is unchanged.
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The `fields` query parameter, a comma separated list of field names.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct FieldsQuery {
+    pub fields: Option<String>,
+}
+
+
+/// The fields a client has picked, or every field.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldSelection {
+    fields: Option<Vec<String>>,
+}
+
+impl FieldSelection {
+
+    /// Reads the fields a client has picked.
+    ///
+    /// # Arguments
+    /// * `query` - The `fields` query parameter, every field is kept if it is missing or blank.
+    /// * `allowed` - The fields that can be picked.
+    ///
+    /// # Returns
+    /// * `Err(NanoServiceError)` - `BadRequest` naming the allowed fields if any field is not one of them.
+    pub fn parse(query: &FieldsQuery, allowed: &[&str]) -> Result<Self, NanoServiceError> {
+        let fields: Vec<String> = query.fields.as_deref().unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(str::to_string)
+            .collect();
+        if fields.is_empty() {
+            return Ok(FieldSelection { fields: None })
+        }
+        let unknown: Vec<&String> = fields.iter().filter(|field| !allowed.contains(&field.as_str())).collect();
+        if !unknown.is_empty() {
+            return Err(NanoServiceError::new(
+                format!("Unknown fields: {}", unknown.iter().map(|field| field.as_str()).collect::<Vec<_>>().join(", ")),
+                NanoServiceErrorStatus::BadRequest
+            ).with_details(serde_json::json!({"allowed": allowed})))
+        }
+        Ok(FieldSelection { fields: Some(fields) })
+    }
+
+    /// Serializes a response, pruning the objects found at `path` down to the picked fields.
+    ///
+    /// # Arguments
+    /// * `value` - The response.
+    /// * `path` - The keys leading to the objects to prune, arrays on the way are pruned element by
+    ///   element, e.g. `&["items", "user"]` prunes the user of every profile on a page.
+    pub fn select<T: Serialize>(&self, value: &T, path: &[&str]) -> Result<Value, NanoServiceError> {
+        let mut value = serde_json::to_value(value).map_err(|e| NanoServiceError::new(
+            format!("Failed to serialize response: {}", e),
+            NanoServiceErrorStatus::Unknown
+        ))?;
+        if let Some(fields) = &self.fields {
+            prune(&mut value, path, fields);
+        }
+        Ok(value)
+    }
+}
+
+
+fn prune(value: &mut Value, path: &[&str], fields: &[String]) {
+    match (value, path.split_first()) {
+        (Value::Array(items), _) => items.iter_mut().for_each(|item| prune(item, path, fields)),
+        (Value::Object(object), None) => object.retain(|key, _| fields.contains(key)),
+        (Value::Object(object), Some((key, rest))) => {
+            if let Some(child) = object.get_mut(*key) {
+                prune(child, rest, fields);
+            }
+        },
+        _ => {}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ALLOWED: [&str; 3] = ["id", "name", "due_date"];
+
+    fn selection(fields: &str) -> FieldSelection {
+        FieldSelection::parse(&FieldsQuery { fields: Some(fields.to_string()) }, &ALLOWED).unwrap()
+    }
+
+    #[test]
+    fn test_select() {
+        let page = json!({"items": [{"id": 1, "name": "a", "due_date": null}], "next_cursor": null});
+        assert_eq!(
+            selection(" id , name").select(&page, &["items"]).unwrap(),
+            json!({"items": [{"id": 1, "name": "a"}], "next_cursor": null})
+        );
+
+        let profiles = json!([{"user": {"id": 1, "name": "a"}, "roles": []}]);
+        assert_eq!(
+            selection("name").select(&profiles, &["user"]).unwrap(),
+            json!([{"user": {"name": "a"}, "roles": []}])
+        );
+        assert_eq!(selection("").select(&profiles, &["user"]).unwrap(), profiles);
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let error = FieldSelection::parse(&FieldsQuery { fields: Some("id,password".to_string()) }, &ALLOWED).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        assert_eq!(error.message, "Unknown fields: password");
+        assert_eq!(error.details.unwrap()["allowed"], json!(ALLOWED));
+    }
+}
@@ -0,0 +1,49 @@
+//! Defines the structs for super admins impersonating other users.
+//!
+//! ## Purpose
+//! - A super admin can get a short-lived token for another user to see the app as they do, such
+//!   as to reproduce a support issue. The token carries the `impersonator_id` claim.
+//! - Starting an impersonation and every request made with the token are written to the audit log
+//!   with the super admin as the actor, so what was done on the user's behalf can be reviewed.
+//! - Responses about the current user report the impersonation, so the frontend can show a banner.
+use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Utc};
+use utils::config::GetConfigVariable;
+use crate::token::checks::CheckUserRole;
+use crate::token::token::HeaderToken;
+
+
+/// The audit log action recorded when a super admin starts impersonating a user.
+pub const IMPERSONATION_STARTED_ACTION: &str = "user:impersonation_started";
+
+/// The audit log action recorded for each request made with an impersonation token.
+pub const IMPERSONATED_REQUEST_ACTION: &str = "user:impersonated_request";
+
+/// How long an impersonation token lasts when not configured.
+pub const DEFAULT_IMPERSONATION_MINUTES: i64 = 15;
+
+/// The longest an impersonation token can be configured to last.
+pub const MAX_IMPERSONATION_MINUTES: i64 = 60;
+
+
+/// The impersonation a token was issued for.
+///
+/// # Fields
+/// * impersonator_id - The ID of the super admin acting as the user.
+/// * expires_at - When the impersonation token expires.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Impersonation {
+    pub impersonator_id: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Impersonation {
+
+    /// The impersonation of a token, `None` if it is the user's own token.
+    pub fn from_token<X: GetConfigVariable, Y: CheckUserRole>(token: &HeaderToken<X, Y>) -> Option<Self> {
+        token.impersonator_id.map(|impersonator_id| Impersonation {
+            impersonator_id,
+            expires_at: token.time_expire,
+        })
+    }
+}
@@ -0,0 +1,50 @@
+//! Defines the structs for legal holds on users.
+//!
+//! ## Purpose
+//! - A super admin places a hold on a user when their data has to be preserved, such as for a
+//!   dispute or an investigation.
+//! - While the hold is in place the user cannot be deleted and the retention purge skips the rows
+//!   about them, until the hold is released.
+use serde::{Serialize, Deserialize};
+use chrono::NaiveDateTime;
+
+
+/// The audit log action recorded when a hold is placed.
+pub const LEGAL_HOLD_PLACED_ACTION: &str = "user:legal_hold_placed";
+
+/// The audit log action recorded when a hold is released.
+pub const LEGAL_HOLD_RELEASED_ACTION: &str = "user:legal_hold_released";
+
+
+/// Represents the schema for placing a legal hold.
+///
+/// # Fields
+/// * user_id - The ID of the user whose data is held.
+/// * placed_by - The ID of the super admin placing the hold.
+/// * reason - Why the data is held, such as a case reference.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewLegalHold {
+    pub user_id: i32,
+    pub placed_by: i32,
+    pub reason: String,
+}
+
+
+/// Represents a legal hold with the user it is on.
+///
+/// # Fields
+/// * user_id - The ID of the user whose data is held.
+/// * username - The username of the user.
+/// * email - The email address of the user.
+/// * placed_by - The ID of the super admin who placed the hold.
+/// * reason - Why the data is held.
+/// * date_created - When the hold was placed, or last updated.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct LegalHold {
+    pub user_id: i32,
+    pub username: String,
+    pub email: String,
+    pub placed_by: i32,
+    pub reason: String,
+    pub date_created: NaiveDateTime,
+}
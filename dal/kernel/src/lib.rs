@@ -2,6 +2,48 @@ pub mod users;
 pub mod email_invites;
 pub mod rate_limit_entries;
 pub mod role_permissions;
+pub mod permissions;
 pub mod token;
 pub mod to_do_items;
+pub mod tags;
+pub mod email_outbox;
+pub mod audit_log;
+pub mod sla;
+pub mod request_metrics;
+pub mod todo_comments;
+pub mod notifications;
+pub mod branding;
+pub mod request_rate_limits;
+pub mod onboarding;
+pub mod tombstones;
+pub mod sync;
+pub mod content_scan;
+pub mod moderation;
+pub mod metering;
+pub mod plans;
+pub mod billing;
+pub mod retention;
+pub mod legal_holds;
+pub mod analytics;
+pub mod events;
+pub mod export_jobs;
+pub mod object_store;
+pub mod attachments;
+pub mod todo_events;
+pub mod todo_workflow;
+pub mod projects;
+pub mod organizations;
+pub mod tenancy;
+pub mod avatars;
+pub mod backups;
+pub mod webhooks;
+pub mod impersonation;
+pub mod api_keys;
+pub mod devices;
+pub mod oidc;
+pub mod captcha;
+#[cfg(feature = "saml")]
+pub mod saml;
+pub mod pagination;
+pub mod fields;
 pub use chrono;
\ No newline at end of file
@@ -0,0 +1,122 @@
+//! Defines the structs for metering billable usage.
+//!
+//! ## Purpose
+//! - The subsystems that cause billable usage count it as it happens: a sign in marks the user as
+//!   active for the month, a sent email adds to the emails sent that day and stored files add or
+//!   remove bytes of storage.
+//! - A rollup job turns the counts into one row per month which finance exports for billing.
+//! - A deployment serves a single organization, so usage is metered for the deployment as a whole.
+use serde::{Serialize, Deserialize};
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime};
+use std::error::Error;
+use std::str::FromStr;
+
+
+/// A quantity counted per day.
+///
+/// # Variants
+/// * `EmailsSent` - Emails the provider accepted.
+/// * `StorageBytes` - Bytes stored, removed files are counted as negative bytes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageMetric {
+    EmailsSent,
+    StorageBytes,
+}
+
+impl UsageMetric {
+
+    /// The value stored in the `metric` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageMetric::EmailsSent => "emails_sent",
+            UsageMetric::StorageBytes => "storage_bytes",
+        }
+    }
+}
+
+impl FromStr for UsageMetric {
+    type Err = String;
+    fn from_str(metric: &str) -> Result<Self, Self::Err> {
+        match metric.trim() {
+            "emails_sent" => Ok(UsageMetric::EmailsSent),
+            "storage_bytes" => Ok(UsageMetric::StorageBytes),
+            _ => Err(format!("Invalid usage metric: {}", metric)),
+        }
+    }
+}
+
+impl Type<Postgres> for UsageMetric {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for UsageMetric {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for UsageMetric {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        UsageMetric::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// The billable usage for one month.
+///
+/// # Fields
+/// * month - The first day of the month.
+/// * active_users - The users who signed in during the month.
+/// * emails_sent - The emails sent during the month.
+/// * storage_bytes - The bytes stored at the end of the month, or so far for the current month.
+/// * rolled_up_at - When the month was last rolled up.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct UsageRollup {
+    pub month: NaiveDate,
+    pub active_users: i64,
+    pub emails_sent: i64,
+    pub storage_bytes: i64,
+    pub rolled_up_at: NaiveDateTime,
+}
+
+
+/// The first day of the month a day is in.
+pub fn month_start(day: NaiveDate) -> NaiveDate {
+    day - Days::new(u64::from(day.day0()))
+}
+
+
+/// The first day of the month `months` months before the month a day is in.
+pub fn months_before(day: NaiveDate, months: u32) -> NaiveDate {
+    month_start(day).checked_sub_months(Months::new(months)).unwrap_or(NaiveDate::MIN)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_months() {
+        assert_eq!(month_start(date("2025-03-31")), date("2025-03-01"));
+        assert_eq!(month_start(date("2025-03-01")), date("2025-03-01"));
+        assert_eq!(months_before(date("2025-03-15"), 1), date("2025-02-01"));
+        assert_eq!(months_before(date("2025-01-31"), 12), date("2024-01-01"));
+    }
+
+    #[test]
+    fn test_metric_round_trip() {
+        for metric in [UsageMetric::EmailsSent, UsageMetric::StorageBytes] {
+            assert_eq!(UsageMetric::from_str(metric.as_str()).unwrap(), metric);
+        }
+    }
+}
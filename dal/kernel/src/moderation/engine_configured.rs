@@ -0,0 +1,22 @@
+//! Picks the moderator to use from the config.
+//!
+//! # Variables
+//! * `MODERATION_PROVIDER` - `external` for `ExternalModerator`, anything else or unset for `WordlistModerator`
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::moderation::{ModerateText, ModerationVerdict};
+use crate::moderation::engine_wordlist::WordlistModerator;
+use crate::moderation::engine_external::ExternalModerator;
+
+
+/// Moderates text with the moderator named by `MODERATION_PROVIDER`.
+pub struct ConfiguredModerator;
+
+impl ModerateText for ConfiguredModerator {
+    async fn moderate_text<Y: GetConfigVariable>(text: &str) -> Result<ModerationVerdict, NanoServiceError> {
+        match Y::get_config_variable("MODERATION_PROVIDER".to_string()).as_deref().map(str::trim) {
+            Ok("external") => ExternalModerator::moderate_text::<Y>(text).await,
+            _ => WordlistModerator::moderate_text::<Y>(text).await,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Implements `ModerateText` with an external moderation API.
+//!
+//! # Overview
+//! The text is posted as `{"text": "..."}` and the API has to answer with a verdict in the same
+//! shape as `ModerationVerdict`, such as `{"verdict": "flag", "reasons": ["spam"]}`.
+//!
+//! # Variables
+//! * `MODERATION_API_URL` - The URL the text is posted to
+//! * `MODERATION_API_KEY` - Sent as a bearer token when set
+//! * `MODERATION_API_TIMEOUT_SECS` - How long the API can take to answer, defaults to 5
+use std::future::Future;
+use std::time::Duration;
+use reqwest::Client;
+use serde_json::json;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::moderation::{ModerateText, ModerationVerdict};
+
+
+/// How long the API can take when `MODERATION_API_TIMEOUT_SECS` is not set.
+const DEFAULT_TIMEOUT_SECS: u64 = 5;
+
+
+/// Moderates text with the API at `MODERATION_API_URL`.
+pub struct ExternalModerator;
+
+impl ModerateText for ExternalModerator {
+    fn moderate_text<Y: GetConfigVariable>(text: &str)
+    -> impl Future<Output = Result<ModerationVerdict, NanoServiceError>> + Send {
+        let url = Y::get_config_variable("MODERATION_API_URL".to_string());
+        let key = Y::get_config_variable("MODERATION_API_KEY".to_string()).ok();
+        let timeout = Y::get_config_variable("MODERATION_API_TIMEOUT_SECS".to_string())
+            .ok()
+            .and_then(|secs| secs.trim().parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+        let body = json!({"text": text});
+        async move {
+            let mut request = Client::new()
+                .post(url?)
+                .timeout(Duration::from_secs(timeout))
+                .json(&body);
+            if let Some(key) = key {
+                request = request.bearer_auth(key);
+            }
+            let response = request.send().await.map_err(|e| moderation_error(
+                format!("Failed to reach the moderation API: {}", e)
+            ))?;
+            if !response.status().is_success() {
+                return Err(moderation_error(
+                    format!("Failed to moderate text. HTTP Status: {}", response.status())
+                ))
+            }
+            response.json::<ModerationVerdict>().await.map_err(|e| moderation_error(
+                format!("Failed to read the moderation API response: {}", e)
+            ))
+        }
+    }
+}
+
+
+fn moderation_error(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::Unknown)
+}
@@ -0,0 +1,34 @@
+//! Moderators with a fixed verdict, for tests and local development.
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::moderation::{ModerateText, ModerationVerdict};
+
+
+/// Allows all text.
+pub struct AllowTextMock;
+
+impl ModerateText for AllowTextMock {
+    async fn moderate_text<Y: GetConfigVariable>(_text: &str) -> Result<ModerationVerdict, NanoServiceError> {
+        Ok(ModerationVerdict::Allow)
+    }
+}
+
+
+/// Flags all text as `test-flag`.
+pub struct FlagTextMock;
+
+impl ModerateText for FlagTextMock {
+    async fn moderate_text<Y: GetConfigVariable>(_text: &str) -> Result<ModerationVerdict, NanoServiceError> {
+        Ok(ModerationVerdict::Flag { reasons: vec!["test-flag".to_string()] })
+    }
+}
+
+
+/// Rejects all text as `test-reject`.
+pub struct RejectTextMock;
+
+impl ModerateText for RejectTextMock {
+    async fn moderate_text<Y: GetConfigVariable>(_text: &str) -> Result<ModerationVerdict, NanoServiceError> {
+        Ok(ModerationVerdict::Reject { reasons: vec!["test-reject".to_string()] })
+    }
+}
@@ -0,0 +1,94 @@
+//! Implements `ModerateText` with configurable lists of words and phrases.
+//!
+//! # Overview
+//! Text and list entries are lowercased and split on anything that is not a letter or digit before
+//! they are compared, so `Spam!` matches `spam` but `spammer` does not. An entry of several words
+//! matches those words in order.
+//!
+//! # Variables
+//! * `MODERATION_BLOCKED_WORDS` - Comma separated words and phrases that get text rejected
+//! * `MODERATION_FLAGGED_WORDS` - Comma separated words and phrases that get text flagged for review
+//!
+//! Text is allowed when neither list is set.
+use std::future::Future;
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::moderation::{ModerateText, ModerationVerdict};
+
+
+/// Moderates text against `MODERATION_BLOCKED_WORDS` and `MODERATION_FLAGGED_WORDS`.
+pub struct WordlistModerator;
+
+impl ModerateText for WordlistModerator {
+    fn moderate_text<Y: GetConfigVariable>(text: &str)
+    -> impl Future<Output = Result<ModerationVerdict, NanoServiceError>> + Send {
+        let blocked = Y::get_config_variable("MODERATION_BLOCKED_WORDS".to_string()).unwrap_or_default();
+        let flagged = Y::get_config_variable("MODERATION_FLAGGED_WORDS".to_string()).unwrap_or_default();
+        let verdict = check_wordlists(text, &blocked, &flagged);
+        async move { Ok(verdict) }
+    }
+}
+
+
+/// Lowercases text and puts a single space around each of its words.
+fn normalise(text: &str) -> String {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    format!(" {} ", words.join(" "))
+}
+
+
+/// Finds the entries of a comma separated list that appear in normalised text.
+fn matches(normalised: &str, list: &str) -> Vec<String> {
+    list.split(',')
+        .map(normalise)
+        .filter(|entry| !entry.trim().is_empty() && normalised.contains(entry.as_str()))
+        .map(|entry| entry.trim().to_string())
+        .collect()
+}
+
+
+/// Rejects text containing a blocked entry, flags text containing a flagged entry and allows the rest.
+pub fn check_wordlists(text: &str, blocked: &str, flagged: &str) -> ModerationVerdict {
+    let normalised = normalise(text);
+    let found = matches(&normalised, blocked);
+    if !found.is_empty() {
+        return ModerationVerdict::Reject { reasons: found.into_iter().map(|entry| format!("blocked: {}", entry)).collect() }
+    }
+    let found = matches(&normalised, flagged);
+    if !found.is_empty() {
+        return ModerationVerdict::Flag { reasons: found.into_iter().map(|entry| format!("flagged: {}", entry)).collect() }
+    }
+    ModerationVerdict::Allow
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_wordlists() {
+        let blocked = "scam, wire transfer";
+        let flagged = "urgent";
+        assert_eq!(check_wordlists("Tidy the office", blocked, flagged), ModerationVerdict::Allow);
+        assert_eq!(
+            check_wordlists("This is a SCAM!", blocked, flagged),
+            ModerationVerdict::Reject { reasons: vec!["blocked: scam".to_string()] }
+        );
+        assert_eq!(
+            check_wordlists("Send a wire   transfer, urgent", blocked, flagged),
+            ModerationVerdict::Reject { reasons: vec!["blocked: wire transfer".to_string()] }
+        );
+        assert_eq!(
+            check_wordlists("Urgent: call back", blocked, flagged),
+            ModerationVerdict::Flag { reasons: vec!["flagged: urgent".to_string()] }
+        );
+        // only whole words match
+        assert_eq!(check_wordlists("The scammer list", blocked, flagged), ModerationVerdict::Allow);
+        assert_eq!(check_wordlists("anything", "", " , "), ModerationVerdict::Allow);
+    }
+}
@@ -0,0 +1,237 @@
+//! Moderates the text users write before it is stored.
+//!
+//! ## Purpose
+//! - To-do descriptions and comments are passed through a `ModerateText` implementation before
+//!   they are saved. Rejected text is refused with a `422` and a `content_rejected` code, flagged
+//!   text is saved but held for an admin to look at.
+//! - Every rejection and flag is recorded as a `ModerationDecision` so admins can review it and
+//!   uphold or overturn it, which is how the wordlist gets tuned.
+pub mod engine_wordlist;
+pub mod engine_external;
+pub mod engine_configured;
+pub mod engine_mock;
+
+use serde::{Serialize, Deserialize};
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
+use chrono::NaiveDateTime;
+use std::error::Error;
+use std::future::Future;
+use std::str::FromStr;
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+
+
+/// The code in the error details when text is refused by moderation.
+pub const CONTENT_REJECTED_CODE: &str = "content_rejected";
+
+
+/// The outcome of moderating a piece of text.
+///
+/// # Variants
+/// * `Allow` - The text can be stored.
+/// * `Flag` - The text can be stored but an admin should look at it.
+/// * `Reject` - The text cannot be stored.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+pub enum ModerationVerdict {
+    Allow,
+    Flag { reasons: Vec<String> },
+    Reject { reasons: Vec<String> },
+}
+
+
+/// Defines the contract for moderating text written by users.
+pub trait ModerateText {
+
+    /// Moderates a piece of text.
+    ///
+    /// # Returns
+    /// * `Ok(ModerationVerdict)` - Whether the text is allowed, flagged or rejected
+    /// * `Err(NanoServiceError)` - If the moderator could not be reached or is misconfigured
+    fn moderate_text<Y: GetConfigVariable>(text: &str)
+    -> impl Future<Output = Result<ModerationVerdict, NanoServiceError>> + Send;
+}
+
+
+/// The kind of text a moderation decision was made on.
+///
+/// # Variants
+/// * `TodoDescription` - The description of a to-do item.
+/// * `Comment` - A comment on a to-do item.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModeratedContent {
+    TodoDescription,
+    Comment,
+}
+
+impl ModeratedContent {
+
+    /// The value stored in the `content` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModeratedContent::TodoDescription => "todo_description",
+            ModeratedContent::Comment => "comment",
+        }
+    }
+}
+
+impl FromStr for ModeratedContent {
+    type Err = String;
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        match content.trim() {
+            "todo_description" => Ok(ModeratedContent::TodoDescription),
+            "comment" => Ok(ModeratedContent::Comment),
+            _ => Err(format!("Invalid moderated content: {}", content)),
+        }
+    }
+}
+
+impl Type<Postgres> for ModeratedContent {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for ModeratedContent {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for ModeratedContent {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        ModeratedContent::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// What was done with text that was not allowed.
+///
+/// # Variants
+/// * `Flagged` - The text was stored and held for review.
+/// * `Rejected` - The text was refused.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    Flagged,
+    Rejected,
+}
+
+impl ModerationAction {
+
+    /// The value stored in the `action` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationAction::Flagged => "flagged",
+            ModerationAction::Rejected => "rejected",
+        }
+    }
+}
+
+impl FromStr for ModerationAction {
+    type Err = String;
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action.trim() {
+            "flagged" => Ok(ModerationAction::Flagged),
+            "rejected" => Ok(ModerationAction::Rejected),
+            _ => Err(format!("Invalid moderation action: {}", action)),
+        }
+    }
+}
+
+impl Type<Postgres> for ModerationAction {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for ModerationAction {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for ModerationAction {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        ModerationAction::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// Represents the schema for recording a moderation decision.
+///
+/// # Fields
+/// * content - The kind of text moderated.
+/// * entity_id - The ID of the to-do item or comment the text belongs to, `None` if it was rejected before it was stored.
+/// * author_id - The ID of the user who wrote the text.
+/// * action - Whether the text was flagged or rejected.
+/// * reasons - Why the moderator did not allow the text.
+/// * text - The text that was moderated.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewModerationDecision {
+    pub content: ModeratedContent,
+    pub entity_id: Option<i32>,
+    pub author_id: i32,
+    pub action: ModerationAction,
+    pub reasons: Vec<String>,
+    pub text: String,
+}
+
+
+/// Represents a moderation decision stored in the system.
+///
+/// # Fields
+/// * id - The unique identifier for the decision.
+/// * content - The kind of text moderated.
+/// * entity_id - The ID of the to-do item or comment the text belongs to, `None` if it was rejected before it was stored.
+/// * author_id - The ID of the user who wrote the text.
+/// * action - Whether the text was flagged or rejected.
+/// * reasons - Why the moderator did not allow the text.
+/// * text - The text that was moderated.
+/// * created_at - When the decision was made.
+/// * reviewed_by - The ID of the admin who reviewed the decision, `None` until it is reviewed.
+/// * reviewed_at - When the decision was reviewed.
+/// * upheld - Whether the admin agreed with the moderator.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ModerationDecision {
+    pub id: i32,
+    pub content: ModeratedContent,
+    pub entity_id: Option<i32>,
+    pub author_id: i32,
+    pub action: ModerationAction,
+    pub reasons: Vec<String>,
+    pub text: String,
+    pub created_at: NaiveDateTime,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<NaiveDateTime>,
+    pub upheld: Option<bool>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict_serialization() {
+        assert_eq!(serde_json::to_value(ModerationVerdict::Allow).unwrap(), serde_json::json!({"verdict": "allow"}));
+        let verdict: ModerationVerdict = serde_json::from_value(serde_json::json!({
+            "verdict": "reject", "reasons": ["harassment"]
+        })).unwrap();
+        assert_eq!(verdict, ModerationVerdict::Reject { reasons: vec!["harassment".to_string()] });
+    }
+
+    #[test]
+    fn test_content_and_action_round_trip() {
+        for content in [ModeratedContent::TodoDescription, ModeratedContent::Comment] {
+            assert_eq!(ModeratedContent::from_str(content.as_str()).unwrap(), content);
+        }
+        for action in [ModerationAction::Flagged, ModerationAction::Rejected] {
+            assert_eq!(ModerationAction::from_str(action.as_str()).unwrap(), action);
+        }
+        assert!(ModerationAction::from_str("deleted").is_err());
+    }
+}
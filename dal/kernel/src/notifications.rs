@@ -0,0 +1,119 @@
+//! Defines the structs for batching notifications.
+//!
+//! ## Purpose
+//! - Events worth telling a user about are queued in the `pending_notifications` table rather than
+//!   emailed one by one.
+//! - Once the batch window for the type has passed, every notification of that type queued for the
+//!   user is sent as a single summary email.
+use serde::{Serialize, Deserialize};
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
+use chrono::NaiveDateTime;
+use std::error::Error;
+use std::str::FromStr;
+
+
+/// The kind of event a notification is about.
+///
+/// # Variants
+/// * `Assignment` - A to-do item was assigned to the user.
+/// * `Comment` - A comment was added to a to-do item the user assigned or is assigned.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationType {
+    Assignment,
+    Comment,
+}
+
+impl NotificationType {
+
+    /// Every notification type, in the order they are batched.
+    pub const ALL: [NotificationType; 2] = [NotificationType::Assignment, NotificationType::Comment];
+
+    /// The value stored in the `notification_type` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationType::Assignment => "assignment",
+            NotificationType::Comment => "comment",
+        }
+    }
+}
+
+impl FromStr for NotificationType {
+    type Err = String;
+    fn from_str(notification_type: &str) -> Result<Self, Self::Err> {
+        match notification_type.trim() {
+            "assignment" => Ok(NotificationType::Assignment),
+            "comment" => Ok(NotificationType::Comment),
+            _ => Err(format!("Invalid notification type: {}", notification_type)),
+        }
+    }
+}
+
+impl Type<Postgres> for NotificationType {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for NotificationType {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for NotificationType {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        NotificationType::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// Represents the schema for queueing a notification.
+///
+/// # Fields
+/// * user_id - The user to notify.
+/// * notification_type - The kind of event.
+/// * todo_id - The to-do item the event happened to.
+/// * todo_name - The name of the to-do item, listed in the summary email.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewNotification {
+    pub user_id: i32,
+    pub notification_type: NotificationType,
+    pub todo_id: i32,
+    pub todo_name: String,
+}
+
+
+/// Represents a notification waiting to be batched.
+///
+/// # Fields
+/// * id - The unique identifier for the notification.
+/// * user_id - The user to notify.
+/// * notification_type - The kind of event.
+/// * todo_id - The to-do item the event happened to.
+/// * todo_name - The name of the to-do item.
+/// * date_created - When the event happened.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct PendingNotification {
+    pub id: i32,
+    pub user_id: i32,
+    pub notification_type: NotificationType,
+    pub todo_id: i32,
+    pub todo_name: String,
+    pub date_created: NaiveDateTime,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_type_round_trip() {
+        for notification_type in NotificationType::ALL {
+            assert_eq!(NotificationType::from_str(notification_type.as_str()).unwrap(), notification_type);
+        }
+        assert!(NotificationType::from_str("digest").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+//! Picks the object store to use from the config.
+//!
+//! # Variables
+//! * `OBJECT_STORE_PROVIDER` - `local` for `LocalObjectStore`, anything else or unset for `S3ObjectStore`
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::object_store::{DeleteObject, GetObject, ObjectStream, StoreObject};
+use crate::object_store::engine_local::LocalObjectStore;
+use crate::object_store::engine_s3::S3ObjectStore;
+
+
+fn is_local<Y: GetConfigVariable>() -> bool {
+    matches!(Y::get_config_variable("OBJECT_STORE_PROVIDER".to_string()).as_deref().map(str::trim), Ok("local"))
+}
+
+
+/// Stores files in the store named by `OBJECT_STORE_PROVIDER`.
+pub struct ConfiguredObjectStore;
+
+impl StoreObject for ConfiguredObjectStore {
+    async fn put_object<Y: GetConfigVariable>(key: &str, body: Vec<u8>, content_type: &str) -> Result<(), NanoServiceError> {
+        match is_local::<Y>() {
+            true => LocalObjectStore::put_object::<Y>(key, body, content_type).await,
+            false => S3ObjectStore::put_object::<Y>(key, body, content_type).await,
+        }
+    }
+}
+
+impl GetObject for ConfiguredObjectStore {
+    async fn get_object<Y: GetConfigVariable>(key: &str) -> Result<Vec<u8>, NanoServiceError> {
+        match is_local::<Y>() {
+            true => LocalObjectStore::get_object::<Y>(key).await,
+            false => S3ObjectStore::get_object::<Y>(key).await,
+        }
+    }
+
+    async fn stream_object<Y: GetConfigVariable>(key: &str) -> Result<ObjectStream, NanoServiceError> {
+        match is_local::<Y>() {
+            true => LocalObjectStore::stream_object::<Y>(key).await,
+            false => S3ObjectStore::stream_object::<Y>(key).await,
+        }
+    }
+}
+
+impl DeleteObject for ConfiguredObjectStore {
+    async fn delete_object<Y: GetConfigVariable>(key: &str) -> Result<(), NanoServiceError> {
+        match is_local::<Y>() {
+            true => LocalObjectStore::delete_object::<Y>(key).await,
+            false => S3ObjectStore::delete_object::<Y>(key).await,
+        }
+    }
+}
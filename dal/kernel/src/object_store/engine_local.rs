@@ -0,0 +1,188 @@
+//! Stores files in a directory on the server's disk, for deployments without an S3 compatible store.
+//!
+//! # Overview
+//! A file is kept at `<OBJECT_STORE_DIR>/<key>`, with each `/` in the key a directory. A file is
+//! written to a temporary name and renamed into place, so a read never sees half a file.
+//!
+//! # Notes
+//! Every server has to see the same directory, such as a shared volume, or a file stored through
+//! one server cannot be read through another. There are no presigned links, so the local store is
+//! not an `ObjectStore` and cannot hold exports.
+//!
+//! # Variables
+//! * `OBJECT_STORE_DIR` - The directory files are stored in, required
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use futures::stream;
+use tokio::io::AsyncReadExt;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::export_stream::Bytes;
+use crate::object_store::{DeleteObject, GetObject, ObjectStream, StoreObject};
+
+
+/// How many bytes of a file are read into each chunk of a stream.
+const READ_CHUNK_BYTES: usize = 64 * 1024;
+
+
+fn store_error(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::Unknown)
+}
+
+
+fn not_found(key: &str) -> NanoServiceError {
+    NanoServiceError::new(format!("{} is not in the object store", key), NanoServiceErrorStatus::NotFound)
+}
+
+
+/// The path a file is kept at, refusing keys that would reach outside `OBJECT_STORE_DIR`.
+///
+/// # Arguments
+/// * `key` - The key of the file, such as `attachments/3/report.pdf`.
+pub fn object_path<Y: GetConfigVariable>(key: &str) -> Result<PathBuf, NanoServiceError> {
+    let dir = Y::get_config_variable("OBJECT_STORE_DIR".to_string())
+        .map_err(|_| store_error("OBJECT_STORE_DIR is not set".to_string()))?;
+    let mut path = PathBuf::from(dir);
+    for part in key.split('/') {
+        if part.is_empty() || part == "." || part == ".." || part.contains('\\') {
+            return Err(NanoServiceError::new(
+                format!("{} is not a valid object key", key),
+                NanoServiceErrorStatus::BadRequest
+            ))
+        }
+        path.push(part);
+    }
+    Ok(path)
+}
+
+
+/// Stores files under the directory in `OBJECT_STORE_DIR`.
+pub struct LocalObjectStore;
+
+impl StoreObject for LocalObjectStore {
+    async fn put_object<Y: GetConfigVariable>(key: &str, body: Vec<u8>, _content_type: &str) -> Result<(), NanoServiceError> {
+        let path = object_path::<Y>(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await
+                .map_err(|e| store_error(format!("Failed to store {}: {}", key, e)))?;
+        }
+        let partial = path.with_extension(format!("partial-{}", uuid::Uuid::new_v4()));
+        let written = match tokio::fs::write(&partial, body).await {
+            Ok(()) => tokio::fs::rename(&partial, &path).await,
+            Err(e) => Err(e)
+        };
+        if let Err(e) = written {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(store_error(format!("Failed to store {}: {}", key, e)))
+        }
+        Ok(())
+    }
+}
+
+impl GetObject for LocalObjectStore {
+    async fn get_object<Y: GetConfigVariable>(key: &str) -> Result<Vec<u8>, NanoServiceError> {
+        match tokio::fs::read(object_path::<Y>(key)?).await {
+            Ok(body) => Ok(body),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(not_found(key)),
+            Err(e) => Err(store_error(format!("Failed to read {}: {}", key, e)))
+        }
+    }
+
+    async fn stream_object<Y: GetConfigVariable>(key: &str) -> Result<ObjectStream, NanoServiceError> {
+        let file = match tokio::fs::File::open(object_path::<Y>(key)?).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(not_found(key)),
+            Err(e) => return Err(store_error(format!("Failed to read {}: {}", key, e)))
+        };
+        let key = key.to_string();
+        let body = stream::unfold(Some(file), move |file| {
+            let key = key.clone();
+            async move {
+                let mut file = file?;
+                let mut chunk = vec![0; READ_CHUNK_BYTES];
+                match file.read(&mut chunk).await {
+                    Ok(0) => None,
+                    Ok(read) => {
+                        chunk.truncate(read);
+                        Some((Ok(Bytes::from(chunk)), Some(file)))
+                    },
+                    // the stream ends after reporting the error
+                    Err(e) => Some((Err(store_error(format!("Failed to read {}: {}", key, e))), None))
+                }
+            }
+        });
+        Ok(Box::pin(body))
+    }
+}
+
+impl DeleteObject for LocalObjectStore {
+    async fn delete_object<Y: GetConfigVariable>(key: &str) -> Result<(), NanoServiceError> {
+        match tokio::fs::remove_file(object_path::<Y>(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(store_error(format!("Failed to delete {}: {}", key, e)))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::sync::LazyLock;
+
+    /// A directory of its own for this run of the tests.
+    static TEST_DIR: LazyLock<String> = LazyLock::new(|| {
+        std::env::temp_dir().join(format!("object-store-{}", uuid::Uuid::new_v4())).to_string_lossy().to_string()
+    });
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "OBJECT_STORE_DIR" => Ok(TEST_DIR.clone()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    #[test]
+    fn test_object_path() {
+        let path = object_path::<MockConfig>("attachments/3/report.pdf").unwrap();
+        assert_eq!(path, PathBuf::from(TEST_DIR.as_str()).join("attachments").join("3").join("report.pdf"));
+        for key in ["../secrets", "attachments/../../secrets", "/etc/passwd", "attachments//report.pdf", "a\\b"] {
+            assert_eq!(object_path::<MockConfig>(key).unwrap_err().status, NanoServiceErrorStatus::BadRequest);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_put_get_and_delete() {
+        LocalObjectStore::put_object::<MockConfig>("attachments/1/notes.txt", b"first".to_vec(), "text/plain").await.unwrap();
+        LocalObjectStore::put_object::<MockConfig>("attachments/1/notes.txt", b"second".to_vec(), "text/plain").await.unwrap();
+        assert_eq!(LocalObjectStore::get_object::<MockConfig>("attachments/1/notes.txt").await.unwrap(), b"second");
+
+        LocalObjectStore::delete_object::<MockConfig>("attachments/1/notes.txt").await.unwrap();
+        let missing = LocalObjectStore::get_object::<MockConfig>("attachments/1/notes.txt").await.unwrap_err();
+        assert_eq!(missing.status, NanoServiceErrorStatus::NotFound);
+        // deleting it again is not an error
+        LocalObjectStore::delete_object::<MockConfig>("attachments/1/notes.txt").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stream_object() {
+        let body: Vec<u8> = (0..READ_CHUNK_BYTES * 2 + 10).map(|n| (n % 251) as u8).collect();
+        LocalObjectStore::put_object::<MockConfig>("attachments/2/large.bin", body.clone(), "application/octet-stream").await.unwrap();
+
+        let chunks: Vec<Bytes> = LocalObjectStore::stream_object::<MockConfig>("attachments/2/large.bin").await.unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), body);
+
+        let missing = LocalObjectStore::stream_object::<MockConfig>("attachments/2/missing.bin").await.err().unwrap();
+        assert_eq!(missing.status, NanoServiceErrorStatus::NotFound);
+    }
+}
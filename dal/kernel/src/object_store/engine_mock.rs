@@ -0,0 +1,69 @@
+//! Stores for tests.
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::object_store::{DeleteObject, GetObject, ObjectStore, StoreObject};
+
+
+/// Every file put in `MemoryObjectStoreMock` by key, shared by the tests of a crate.
+pub static STORED_OBJECTS: LazyLock<Mutex<HashMap<String, Vec<u8>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+
+/// Keeps every file in `STORED_OBJECTS` and links to them as `memory://<key>?expires_in=<seconds>`.
+pub struct MemoryObjectStoreMock;
+
+impl StoreObject for MemoryObjectStoreMock {
+    async fn put_object<Y: GetConfigVariable>(key: &str, body: Vec<u8>, _content_type: &str) -> Result<(), NanoServiceError> {
+        STORED_OBJECTS.lock().unwrap().insert(key.to_string(), body);
+        Ok(())
+    }
+}
+
+impl GetObject for MemoryObjectStoreMock {
+    async fn get_object<Y: GetConfigVariable>(key: &str) -> Result<Vec<u8>, NanoServiceError> {
+        STORED_OBJECTS.lock().unwrap().get(key).cloned()
+            .ok_or_else(|| NanoServiceError::new(format!("{} is not stored", key), NanoServiceErrorStatus::NotFound))
+    }
+}
+
+impl ObjectStore for MemoryObjectStoreMock {
+    fn presigned_url<Y: GetConfigVariable>(key: &str, expires_in: u64) -> Result<String, NanoServiceError> {
+        Ok(format!("memory://{}?expires_in={}", key, expires_in))
+    }
+}
+
+impl DeleteObject for MemoryObjectStoreMock {
+    async fn delete_object<Y: GetConfigVariable>(key: &str) -> Result<(), NanoServiceError> {
+        STORED_OBJECTS.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+
+/// Refuses every file, for testing what happens when the store is down.
+pub struct FailingObjectStoreMock;
+
+impl StoreObject for FailingObjectStoreMock {
+    async fn put_object<Y: GetConfigVariable>(key: &str, _body: Vec<u8>, _content_type: &str) -> Result<(), NanoServiceError> {
+        Err(NanoServiceError::new(format!("failed to store {}", key), NanoServiceErrorStatus::Unknown))
+    }
+}
+
+impl GetObject for FailingObjectStoreMock {
+    async fn get_object<Y: GetConfigVariable>(key: &str) -> Result<Vec<u8>, NanoServiceError> {
+        Err(NanoServiceError::new(format!("failed to read {}", key), NanoServiceErrorStatus::Unknown))
+    }
+}
+
+impl ObjectStore for FailingObjectStoreMock {
+    fn presigned_url<Y: GetConfigVariable>(key: &str, _expires_in: u64) -> Result<String, NanoServiceError> {
+        Err(NanoServiceError::new(format!("failed to sign {}", key), NanoServiceErrorStatus::Unknown))
+    }
+}
+
+impl DeleteObject for FailingObjectStoreMock {
+    async fn delete_object<Y: GetConfigVariable>(key: &str) -> Result<(), NanoServiceError> {
+        Err(NanoServiceError::new(format!("failed to delete {}", key), NanoServiceErrorStatus::Unknown))
+    }
+}
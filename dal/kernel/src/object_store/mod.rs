@@ -0,0 +1,94 @@
+//! Stores files too large to hand back in a request, such as exports and attachments.
+//!
+//! ## Purpose
+//! - A file is put in the store under a key, read back or handed out as a presigned link that
+//!   expires, and deleted when it is no longer needed. Each operation is a trait so tests can swap
+//!   the store out and code only asks for what it uses.
+//! - Clients download exports from the store directly, so a large file never passes back through the
+//!   server. Attachments are streamed through the server, so the store can be a local disk.
+//!
+//! ## Stores
+//! - `engine_s3` keeps files in an S3 compatible bucket and is the only store with presigned links.
+//! - `engine_local` keeps files in a directory on the server's disk.
+//! - `engine_configured` picks between them with `OBJECT_STORE_PROVIDER`.
+pub mod engine_s3;
+pub mod engine_local;
+pub mod engine_configured;
+pub mod engine_mock;
+
+use std::future::Future;
+use std::pin::Pin;
+use futures::stream;
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use utils::export_stream::{Bytes, Stream};
+
+
+/// The longest a presigned link can be valid for.
+pub const MAX_PRESIGNED_SECONDS: u64 = 604_800;
+
+
+/// The bytes of a file read from the store as they arrive.
+pub type ObjectStream = Pin<Box<dyn Stream<Item = Result<Bytes, NanoServiceError>> + Send>>;
+
+
+/// Defines the contract for putting files in a store.
+pub trait StoreObject {
+
+    /// Puts a file in the store, replacing any file already under its key.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the file was stored
+    /// * `Err(NanoServiceError)` - If the store is misconfigured or refused the file
+    fn put_object<Y: GetConfigVariable>(key: &str, body: Vec<u8>, content_type: &str)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send;
+}
+
+
+/// Defines the contract for reading files from a store.
+pub trait GetObject {
+
+    /// Reads a file from the store.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - The file
+    /// * `Err(NanoServiceError)` - `NotFound` if there is no file under the key
+    fn get_object<Y: GetConfigVariable>(key: &str)
+    -> impl Future<Output = Result<Vec<u8>, NanoServiceError>> + Send;
+
+    /// Reads a file from the store a chunk at a time, so it is never held in memory whole. Stores
+    /// that cannot stream read the whole file and hand it back as one chunk.
+    ///
+    /// # Returns
+    /// * `Ok(ObjectStream)` - The bytes of the file
+    /// * `Err(NanoServiceError)` - `NotFound` if there is no file under the key
+    fn stream_object<Y: GetConfigVariable>(key: &str)
+    -> impl Future<Output = Result<ObjectStream, NanoServiceError>> + Send {
+        async move {
+            let body = Self::get_object::<Y>(key).await?;
+            let stream: ObjectStream = Box::pin(stream::once(async move { Ok(Bytes::from(body)) }));
+            Ok(stream)
+        }
+    }
+}
+
+
+/// Defines the contract for deleting files from a store.
+pub trait DeleteObject {
+
+    /// Deletes a file from the store, a key with no file is not an error.
+    fn delete_object<Y: GetConfigVariable>(key: &str)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send;
+}
+
+
+/// Defines the contract for a store of files that clients download with presigned links.
+pub trait ObjectStore: StoreObject + GetObject + DeleteObject {
+
+    /// A link anyone holding it can download the file with until it expires.
+    ///
+    /// # Arguments
+    /// * `key` - The key the file is stored under.
+    /// * `expires_in` - How many seconds the link is valid for, capped at `MAX_PRESIGNED_SECONDS`.
+    fn presigned_url<Y: GetConfigVariable>(key: &str, expires_in: u64) -> Result<String, NanoServiceError>;
+}
@@ -0,0 +1,66 @@
+//! Exchanges authorization codes with the provider's token endpoint over HTTP.
+use std::sync::LazyLock;
+use std::time::Duration;
+use serde::Deserialize;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::oidc::{OidcClient, OidcSettings};
+
+
+/// How long the provider has to answer an exchange.
+pub const TIMEOUT_SECONDS: u64 = 10;
+
+static OIDC_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(TIMEOUT_SECONDS))
+        .build()
+        .expect("the OIDC client can be built")
+});
+
+
+/// The part of the token endpoint's response that is used.
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+
+/// Posts the code to the provider's token endpoint with the client's credentials.
+pub struct HttpOidcClient;
+
+impl OidcClient for HttpOidcClient {
+    async fn exchange_code(settings: &OidcSettings, code: &str) -> Result<String, NanoServiceError> {
+        let response = OIDC_CLIENT
+            .post(settings.provider.token_endpoint())
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("client_id", settings.client_id.as_str()),
+                ("client_secret", settings.client_secret.as_str()),
+                ("redirect_uri", settings.redirect_uri.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| NanoServiceError::new(
+                format!("Failed to reach {}: {}", settings.provider.as_str(), e),
+                NanoServiceErrorStatus::Unknown
+            ))?;
+        let status = response.status();
+        if status.is_client_error() {
+            return Err(NanoServiceError::new(
+                format!("{} refused the login code with {}", settings.provider.as_str(), status),
+                NanoServiceErrorStatus::Unauthorized
+            ))
+        }
+        if !status.is_success() {
+            return Err(NanoServiceError::new(
+                format!("{} failed to exchange the login code with {}", settings.provider.as_str(), status),
+                NanoServiceErrorStatus::Unknown
+            ))
+        }
+        let body: TokenResponse = response.json().await.map_err(|e| NanoServiceError::new(
+            format!("Failed to read the {} token response: {}", settings.provider.as_str(), e),
+            NanoServiceErrorStatus::Unknown
+        ))?;
+        Ok(body.id_token)
+    }
+}
@@ -0,0 +1,30 @@
+//! OIDC clients for tests.
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::oidc::{OidcClient, OidcSettings};
+
+
+/// An ID token with the claims and a signature that is not checked, see `IdTokenClaims::from_id_token`.
+pub fn unsigned_id_token(claims: &serde_json::Value) -> String {
+    let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"RS256","typ":"JWT"}"#);
+    let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+    format!("{}.{}.c2lnbmF0dXJl", header, payload)
+}
+
+
+/// Hands the code back as the ID token, so a test sends the ID token it wants as the code. The code
+/// `refused` is refused as the provider would an unknown code.
+pub struct CodeIsIdTokenMock;
+
+impl OidcClient for CodeIsIdTokenMock {
+    async fn exchange_code(_settings: &OidcSettings, code: &str) -> Result<String, NanoServiceError> {
+        match code {
+            "refused" => Err(NanoServiceError::new(
+                "The login code was refused".to_string(),
+                NanoServiceErrorStatus::Unauthorized
+            )),
+            _ => Ok(code.to_string())
+        }
+    }
+}
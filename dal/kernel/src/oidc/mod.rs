@@ -0,0 +1,420 @@
+//! Defines the structs and checks for logging in with an OpenID Connect provider such as Google.
+//!
+//! ## Purpose
+//! - A user can log in with their account at a provider instead of a password. The start endpoint
+//!   redirects them to the provider with a signed `state`, and the callback exchanges the code the
+//!   provider sends back for an ID token through an `OidcClient`.
+//! - The subject the provider knows the user by is linked to the user in `federated_identities`. The
+//!   first login links a user with the same verified email, or provisions a new confirmed user with
+//!   `OIDC_DEFAULT_ROLE`.
+//! - The `state` is signed with the token keys and carries the `nonce` the ID token has to repeat,
+//!   so a callback can only complete a login this server started, within `STATE_MINUTES`.
+//!
+//! ## Notes
+//! The ID token comes straight from the provider's token endpoint over TLS, so as the OpenID Connect
+//! spec allows its signature is not checked, only its issuer, audience, expiry and nonce.
+//!
+//! ## Variables
+//! * `GOOGLE_OIDC_CLIENT_ID` - The OAuth client ID, Google login is off when it is not set
+//! * `GOOGLE_OIDC_CLIENT_SECRET` - The OAuth client secret
+//! * `GOOGLE_OIDC_REDIRECT_URI` - The callback URL registered with Google, ending in `/oidc/google/callback`
+//! * `OIDC_DEFAULT_ROLE` - The role of provisioned users, `Worker` or `Admin`, defaults to `Worker`
+pub mod engine_http;
+pub mod engine_mock;
+
+use std::future::Future;
+use std::str::FromStr;
+use chrono::{Duration, NaiveDateTime, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, Validation};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::token::signing::token_keys;
+use crate::users::UserRole;
+
+
+/// How long a user has to log in at the provider before the `state` expires.
+pub const STATE_MINUTES: i64 = 10;
+
+/// The length of a generated nonce.
+const NONCE_LENGTH: usize = 32;
+
+
+/// The providers users can log in with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OidcProvider {
+    Google,
+}
+
+impl OidcProvider {
+
+    /// The name of the provider in paths and the `federated_identities` table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OidcProvider::Google => "google",
+        }
+    }
+
+    /// The prefix of the provider's config variables.
+    fn config_prefix(&self) -> &'static str {
+        match self {
+            OidcProvider::Google => "GOOGLE_OIDC",
+        }
+    }
+
+    /// Where users are sent to log in.
+    pub fn authorization_endpoint(&self) -> &'static str {
+        match self {
+            OidcProvider::Google => "https://accounts.google.com/o/oauth2/v2/auth",
+        }
+    }
+
+    /// Where the code is exchanged for an ID token.
+    pub fn token_endpoint(&self) -> &'static str {
+        match self {
+            OidcProvider::Google => "https://oauth2.googleapis.com/token",
+        }
+    }
+
+    /// The issuers the provider's ID tokens can name.
+    pub fn issuers(&self) -> &'static [&'static str] {
+        match self {
+            OidcProvider::Google => &["https://accounts.google.com", "accounts.google.com"],
+        }
+    }
+}
+
+impl FromStr for OidcProvider {
+    type Err = NanoServiceError;
+
+    fn from_str(provider: &str) -> Result<Self, Self::Err> {
+        match provider {
+            "google" => Ok(OidcProvider::Google),
+            _ => Err(NanoServiceError::new(
+                format!("Unknown login provider: {}", provider),
+                NanoServiceErrorStatus::NotFound
+            ))
+        }
+    }
+}
+
+
+/// The configured client for a provider.
+///
+/// # Fields
+/// * provider - The provider the client is registered with.
+/// * client_id - The OAuth client ID.
+/// * client_secret - The OAuth client secret.
+/// * redirect_uri - The callback URL registered with the provider.
+/// * default_role - The role given to users provisioned on their first login.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OidcSettings {
+    pub provider: OidcProvider,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    pub default_role: UserRole,
+}
+
+impl OidcSettings {
+
+    /// Reads the client for a provider from config.
+    ///
+    /// # Returns
+    /// * `Ok(OidcSettings)` - The client
+    /// * `Err(NanoServiceError)` - `NotFound` if the provider is not configured, `Unknown` if the
+    ///   secret or redirect is missing or the default role is not `Worker` or `Admin`
+    pub fn from_config<Y: GetConfigVariable>(provider: OidcProvider) -> Result<Self, NanoServiceError> {
+        let prefix = provider.config_prefix();
+        let client_id = Y::get_config_variable(format!("{}_CLIENT_ID", prefix)).unwrap_or_default();
+        if client_id.trim().is_empty() {
+            return Err(NanoServiceError::new(
+                format!("Login with {} is not configured", provider.as_str()),
+                NanoServiceErrorStatus::NotFound
+            ))
+        }
+        let default_role = match Y::get_config_variable("OIDC_DEFAULT_ROLE".to_string()) {
+            Ok(role) if !role.trim().is_empty() => UserRole::from_string(role.trim())?,
+            _ => UserRole::Worker
+        };
+        if default_role == UserRole::SuperAdmin {
+            return Err(NanoServiceError::new(
+                "OIDC_DEFAULT_ROLE cannot be Super Admin".to_string(),
+                NanoServiceErrorStatus::Unknown
+            ))
+        }
+        Ok(OidcSettings {
+            provider,
+            client_id: client_id.trim().to_string(),
+            client_secret: Y::get_config_variable(format!("{}_CLIENT_SECRET", prefix))?,
+            redirect_uri: Y::get_config_variable(format!("{}_REDIRECT_URI", prefix))?,
+            default_role,
+        })
+    }
+
+    /// The URL the user is redirected to, to log in at the provider.
+    pub fn authorization_url(&self, state: &str, nonce: &str) -> Result<String, NanoServiceError> {
+        let url = reqwest::Url::parse_with_params(self.provider.authorization_endpoint(), &[
+            ("response_type", "code"),
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", self.redirect_uri.as_str()),
+            ("scope", "openid email profile"),
+            ("state", state),
+            ("nonce", nonce),
+        ]).map_err(|e| NanoServiceError::new(format!("Failed to build the login URL: {}", e), NanoServiceErrorStatus::Unknown))?;
+        Ok(url.to_string())
+    }
+}
+
+
+/// The claims of a signed `state`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct OidcStateClaims {
+    provider: OidcProvider,
+    nonce: String,
+    exp: i64,
+}
+
+
+/// Starts a login, returning the signed `state` and the `nonce` it carries.
+pub fn issue_state<Y: GetConfigVariable>(provider: OidcProvider) -> Result<(String, String), NanoServiceError> {
+    let nonce: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(NONCE_LENGTH)
+        .map(char::from)
+        .collect();
+    let claims = OidcStateClaims {
+        provider,
+        nonce: nonce.clone(),
+        exp: (Utc::now() + Duration::minutes(STATE_MINUTES)).timestamp(),
+    };
+    let keys = token_keys::<Y>()?;
+    let state = encode(&keys.header(), &claims, &keys.encoding)
+        .map_err(|e| NanoServiceError::new(format!("Failed to sign the login state: {}", e), NanoServiceErrorStatus::Unknown))?;
+    Ok((state, nonce))
+}
+
+
+/// Checks a `state` was signed by this server for the provider and has not expired.
+///
+/// # Returns
+/// * `Ok(String)` - The nonce the ID token has to repeat
+/// * `Err(NanoServiceError)` - `Unauthorized` if the state is not valid
+pub fn verify_state<Y: GetConfigVariable>(state: &str, provider: OidcProvider) -> Result<String, NanoServiceError> {
+    let keys = token_keys::<Y>()?;
+    let mut validation = keys.validation();
+    validation.set_required_spec_claims(&["exp"]);
+    let claims = decode::<OidcStateClaims>(state, &keys.decoding, &validation)
+        .map_err(|e| NanoServiceError::new(format!("Login state is not valid: {}", e), NanoServiceErrorStatus::Unauthorized))?
+        .claims;
+    if claims.provider != provider {
+        return Err(NanoServiceError::new(
+            "Login state is for another provider".to_string(),
+            NanoServiceErrorStatus::Unauthorized
+        ))
+    }
+    Ok(claims.nonce)
+}
+
+
+/// The claims of an ID token that are used.
+///
+/// # Fields
+/// * sub - The ID the provider knows the user by, which never changes.
+/// * email - The user's email address at the provider.
+/// * email_verified - Whether the provider has verified the user owns the email.
+/// * given_name - The user's first name, if they shared it.
+/// * family_name - The user's last name, if they shared it.
+/// * nonce - The nonce from the `state` the login was started with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: String,
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub given_name: Option<String>,
+    #[serde(default)]
+    pub family_name: Option<String>,
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+impl IdTokenClaims {
+
+    /// Reads an ID token received from the provider's token endpoint.
+    ///
+    /// # Arguments
+    /// * `id_token` - The ID token.
+    /// * `settings` - The client the token was issued to.
+    /// * `nonce` - The nonce from the `state`.
+    ///
+    /// # Returns
+    /// * `Ok(IdTokenClaims)` - The claims
+    /// * `Err(NanoServiceError)` - `Unauthorized` if the issuer, audience, expiry or nonce do not match
+    pub fn from_id_token(id_token: &str, settings: &OidcSettings, nonce: &str) -> Result<Self, NanoServiceError> {
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.insecure_disable_signature_validation();
+        validation.set_audience(&[&settings.client_id]);
+        validation.set_issuer(settings.provider.issuers());
+        validation.set_required_spec_claims(&["exp", "aud", "iss", "sub"]);
+        let claims = decode::<Self>(id_token, &DecodingKey::from_secret(&[]), &validation)
+            .map_err(|e| NanoServiceError::new(format!("ID token is not valid: {}", e), NanoServiceErrorStatus::Unauthorized))?
+            .claims;
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(NanoServiceError::new(
+                "ID token was not issued for this login".to_string(),
+                NanoServiceErrorStatus::Unauthorized
+            ))
+        }
+        Ok(claims)
+    }
+}
+
+
+/// The subject a user is known by at a provider.
+///
+/// # Fields
+/// * provider - The name of the provider, such as `google`.
+/// * subject - The `sub` of the provider's ID tokens.
+/// * email - The email the provider gave when the identity was linked.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FederatedSubject {
+    pub provider: String,
+    pub subject: String,
+    pub email: String,
+}
+
+impl FederatedSubject {
+
+    /// The subject of an ID token from a provider.
+    pub fn from_claims(provider: OidcProvider, claims: &IdTokenClaims) -> Self {
+        FederatedSubject {
+            provider: provider.as_str().to_string(),
+            subject: claims.sub.clone(),
+            email: claims.email.clone(),
+        }
+    }
+}
+
+
+/// A provider's subject linked to a user.
+///
+/// # Fields
+/// * id - The unique identifier for the link.
+/// * user_id - The ID of the user.
+/// * provider - The name of the provider.
+/// * subject - The `sub` of the provider's ID tokens.
+/// * email - The email the provider gave when the identity was linked.
+/// * date_created - When the identity was linked.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct FederatedIdentity {
+    pub id: i32,
+    pub user_id: i32,
+    pub provider: String,
+    pub subject: String,
+    pub email: String,
+    pub date_created: NaiveDateTime,
+}
+
+
+/// Defines the contract for exchanging the code a provider sends back for an ID token.
+pub trait OidcClient {
+
+    /// Exchanges an authorization code for an ID token.
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The ID token
+    /// * `Err(NanoServiceError)` - `Unauthorized` if the provider refused the code
+    fn exchange_code(settings: &OidcSettings, code: &str) -> impl Future<Output = Result<String, NanoServiceError>> + Send;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oidc::engine_mock::unsigned_id_token;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SECRET_KEY" => Ok("secret".to_string()),
+                "GOOGLE_OIDC_CLIENT_ID" => Ok("client-id".to_string()),
+                "GOOGLE_OIDC_CLIENT_SECRET" => Ok("client-secret".to_string()),
+                "GOOGLE_OIDC_REDIRECT_URI" => Ok("https://app.example.com/api/auth/v1/auth/oidc/google/callback".to_string()),
+                _ => Err(NanoServiceError::new(format!("{} not set", variable), NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    struct UnconfiguredConfig;
+
+    impl GetConfigVariable for UnconfiguredConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            Err(NanoServiceError::new(format!("{} not set", variable), NanoServiceErrorStatus::Unknown))
+        }
+    }
+
+    fn id_token_claims(nonce: &str) -> serde_json::Value {
+        serde_json::json!({
+            "iss": "https://accounts.google.com",
+            "aud": "client-id",
+            "sub": "10769150350006150715113082367",
+            "email": "ada@example.com",
+            "email_verified": true,
+            "given_name": "Ada",
+            "nonce": nonce,
+            "exp": (Utc::now() + Duration::minutes(5)).timestamp(),
+        })
+    }
+
+    #[test]
+    fn test_settings_from_config() {
+        let settings = OidcSettings::from_config::<MockConfig>(OidcProvider::Google).unwrap();
+        assert_eq!(settings.client_id, "client-id");
+        assert_eq!(settings.default_role, UserRole::Worker);
+
+        let url = settings.authorization_url("the-state", "the-nonce").unwrap();
+        assert!(url.starts_with("https://accounts.google.com/o/oauth2/v2/auth?response_type=code&client_id=client-id"));
+        assert!(url.contains("scope=openid+email+profile&state=the-state&nonce=the-nonce"));
+
+        let error = OidcSettings::from_config::<UnconfiguredConfig>(OidcProvider::Google).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let (state, nonce) = issue_state::<MockConfig>(OidcProvider::Google).unwrap();
+        assert_eq!(nonce.len(), NONCE_LENGTH);
+        assert_eq!(verify_state::<MockConfig>(&state, OidcProvider::Google).unwrap(), nonce);
+
+        let tampered = format!("{}x", state);
+        assert_eq!(verify_state::<MockConfig>(&tampered, OidcProvider::Google).unwrap_err().status, NanoServiceErrorStatus::Unauthorized);
+    }
+
+    #[test]
+    fn test_id_token_claims() {
+        let settings = OidcSettings::from_config::<MockConfig>(OidcProvider::Google).unwrap();
+        let claims = IdTokenClaims::from_id_token(&unsigned_id_token(&id_token_claims("n1")), &settings, "n1").unwrap();
+        assert_eq!(claims.sub, "10769150350006150715113082367");
+        assert!(claims.email_verified);
+        assert_eq!(claims.family_name, None);
+
+        // a token replayed from another login, for another client or from another issuer is refused
+        let replayed = IdTokenClaims::from_id_token(&unsigned_id_token(&id_token_claims("n2")), &settings, "n1").unwrap_err();
+        assert_eq!(replayed.status, NanoServiceErrorStatus::Unauthorized);
+        let mut other_client = id_token_claims("n1");
+        other_client["aud"] = "other-client".into();
+        assert!(IdTokenClaims::from_id_token(&unsigned_id_token(&other_client), &settings, "n1").is_err());
+        let mut other_issuer = id_token_claims("n1");
+        other_issuer["iss"] = "https://evil.example.com".into();
+        assert!(IdTokenClaims::from_id_token(&unsigned_id_token(&other_issuer), &settings, "n1").is_err());
+        let mut expired = id_token_claims("n1");
+        expired["exp"] = (Utc::now() - Duration::minutes(5)).timestamp().into();
+        assert!(IdTokenClaims::from_id_token(&unsigned_id_token(&expired), &settings, "n1").is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! Defines the onboarding steps a new user works through and the state recorded for each user.
+//!
+//! ## Purpose
+//! - A user confirms their email, accepts the terms, sets a password and completes their profile,
+//!   in that order. The next step is worked out from the recorded state so the frontend and the
+//!   server always agree on where a user is.
+//! - The terms are versioned, a user who accepted an older version has to accept the current one again.
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
+use validator::{Validate, ValidationError};
+use crate::users::validate_name;
+
+
+/// A step of onboarding, in the order they have to be completed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    ConfirmEmail,
+    AcceptTerms,
+    SetPassword,
+    CompleteProfile,
+    Complete,
+}
+
+impl OnboardingStep {
+
+    /// Every step that has to be completed, in order.
+    pub const REQUIRED: [OnboardingStep; 4] = [
+        OnboardingStep::ConfirmEmail,
+        OnboardingStep::AcceptTerms,
+        OnboardingStep::SetPassword,
+        OnboardingStep::CompleteProfile,
+    ];
+
+    /// The name of the step as it is reported by the API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OnboardingStep::ConfirmEmail => "confirm_email",
+            OnboardingStep::AcceptTerms => "accept_terms",
+            OnboardingStep::SetPassword => "set_password",
+            OnboardingStep::CompleteProfile => "complete_profile",
+            OnboardingStep::Complete => "complete",
+        }
+    }
+}
+
+impl FromStr for OnboardingStep {
+    type Err = String;
+    fn from_str(step: &str) -> Result<Self, Self::Err> {
+        match step.trim() {
+            "confirm_email" => Ok(OnboardingStep::ConfirmEmail),
+            "accept_terms" => Ok(OnboardingStep::AcceptTerms),
+            "set_password" => Ok(OnboardingStep::SetPassword),
+            "complete_profile" => Ok(OnboardingStep::CompleteProfile),
+            "complete" => Ok(OnboardingStep::Complete),
+            _ => Err(format!("Invalid onboarding step: {}", step)),
+        }
+    }
+}
+
+
+/// The onboarding state recorded for a user.
+///
+/// # Fields
+/// * user_id - The ID of the user.
+/// * confirmed - Whether the user has confirmed their email.
+/// * terms_version - The version of the terms the user last accepted, if any.
+/// * terms_accepted_at - When the user last accepted the terms.
+/// * password_set_at - When the user last set their password.
+/// * profile_completed_at - When the user completed their profile.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct OnboardingState {
+    pub user_id: i32,
+    pub confirmed: bool,
+    pub terms_version: Option<String>,
+    pub terms_accepted_at: Option<NaiveDateTime>,
+    pub password_set_at: Option<NaiveDateTime>,
+    pub profile_completed_at: Option<NaiveDateTime>,
+}
+
+impl OnboardingState {
+
+    /// Whether a step has been completed, accepting the terms only counts for the current version.
+    pub fn is_done(&self, step: OnboardingStep, current_terms_version: &str) -> bool {
+        match step {
+            OnboardingStep::ConfirmEmail => self.confirmed,
+            OnboardingStep::AcceptTerms => self.terms_version.as_deref() == Some(current_terms_version),
+            OnboardingStep::SetPassword => self.password_set_at.is_some(),
+            OnboardingStep::CompleteProfile => self.profile_completed_at.is_some(),
+            OnboardingStep::Complete => self.next_step(current_terms_version) == OnboardingStep::Complete,
+        }
+    }
+
+    /// The first step the user has not completed, `Complete` once they have done them all.
+    pub fn next_step(&self, current_terms_version: &str) -> OnboardingStep {
+        OnboardingStep::REQUIRED
+            .into_iter()
+            .find(|step| !self.is_done(*step, current_terms_version))
+            .unwrap_or(OnboardingStep::Complete)
+    }
+}
+
+
+/// Where a user is in onboarding, as reported to the frontend.
+///
+/// # Fields
+/// * next_step - The step the user has to complete next.
+/// * completed_steps - The steps the user has completed.
+/// * terms_version - The version of the terms the user has to have accepted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OnboardingStatus {
+    pub next_step: OnboardingStep,
+    pub completed_steps: Vec<OnboardingStep>,
+    pub terms_version: String,
+}
+
+impl OnboardingStatus {
+
+    /// Builds the status for a user against the current terms.
+    pub fn new(state: &OnboardingState, current_terms_version: &str) -> Self {
+        OnboardingStatus {
+            next_step: state.next_step(current_terms_version),
+            completed_steps: OnboardingStep::REQUIRED
+                .into_iter()
+                .filter(|step| state.is_done(*step, current_terms_version))
+                .collect(),
+            terms_version: current_terms_version.to_string(),
+        }
+    }
+}
+
+
+/// The number of users waiting on a step.
+///
+/// # Fields
+/// * step - The step, `complete` for users who have finished onboarding.
+/// * users - The number of users whose next step it is.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct OnboardingFunnelCount {
+    pub step: String,
+    pub users: i64,
+}
+
+
+/// The profile a user fills in to finish onboarding.
+///
+/// # Validation
+/// * `first_name` and `last_name` - 1 to 64 characters with no control characters.
+/// * `locale` - A language tag such as `en` or `fr-CA`.
+/// * `timezone` - An IANA timezone such as `Europe/London`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Validate)]
+pub struct OnboardingProfile {
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_name")
+    )]
+    pub first_name: String,
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_name")
+    )]
+    pub last_name: String,
+    #[validate(custom(function = "validate_locale"))]
+    pub locale: String,
+    #[validate(custom(function = "validate_timezone"))]
+    pub timezone: String,
+}
+
+
+/// Checks a locale is a language tag made of a 2 or 3 letter language and an optional region.
+pub fn validate_locale(locale: &str) -> Result<(), ValidationError> {
+    let mut parts = locale.split('-');
+    let language = parts.next().unwrap_or_default();
+    let region = parts.next();
+    let valid = (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && region.is_none_or(|region| (2..=3).contains(&region.len()) && region.chars().all(|c| c.is_ascii_alphanumeric()))
+        && parts.next().is_none();
+    match valid {
+        true => Ok(()),
+        false => Err(ValidationError::new("locale").with_message("must be a language tag such as en or fr-CA".into()))
+    }
+}
+
+
+/// Checks a timezone is a known IANA timezone.
+pub fn validate_timezone(timezone: &str) -> Result<(), ValidationError> {
+    match timezone.parse::<Tz>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ValidationError::new("timezone").with_message("must be an IANA timezone such as Europe/London".into()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn state() -> OnboardingState {
+        OnboardingState {
+            user_id: 1,
+            confirmed: false,
+            terms_version: None,
+            terms_accepted_at: None,
+            password_set_at: None,
+            profile_completed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_next_step() {
+        let now = Utc::now().naive_utc();
+        let mut state = state();
+        assert_eq!(state.next_step("2"), OnboardingStep::ConfirmEmail);
+
+        state.confirmed = true;
+        assert_eq!(state.next_step("2"), OnboardingStep::AcceptTerms);
+
+        state.terms_version = Some("1".to_string());
+        state.terms_accepted_at = Some(now);
+        state.password_set_at = Some(now);
+        // the old terms do not count once the version has moved on
+        assert_eq!(state.next_step("2"), OnboardingStep::AcceptTerms);
+        assert_eq!(state.next_step("1"), OnboardingStep::CompleteProfile);
+
+        state.profile_completed_at = Some(now);
+        assert_eq!(state.next_step("1"), OnboardingStep::Complete);
+        assert_eq!(OnboardingStatus::new(&state, "2").completed_steps, vec![
+            OnboardingStep::ConfirmEmail, OnboardingStep::SetPassword, OnboardingStep::CompleteProfile
+        ]);
+    }
+
+    #[test]
+    fn test_step_round_trip() {
+        for step in OnboardingStep::REQUIRED.into_iter().chain([OnboardingStep::Complete]) {
+            assert_eq!(OnboardingStep::from_str(step.as_str()).unwrap(), step);
+            assert_eq!(serde_json::to_value(step).unwrap(), step.as_str());
+        }
+    }
+
+    #[test]
+    fn test_profile_validation() {
+        let mut profile = OnboardingProfile {
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            locale: "en-GB".to_string(),
+            timezone: "Europe/London".to_string(),
+        };
+        assert!(profile.validate().is_ok());
+
+        profile.locale = "English".to_string();
+        profile.timezone = "Mars/Olympus".to_string();
+        let errors = profile.validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("locale"));
+        assert!(errors.field_errors().contains_key("timezone"));
+    }
+}
@@ -0,0 +1,182 @@
+//! Defines the structs for organizations, the tenants a deployment is shared between.
+//!
+//! ## Purpose
+//! - Users belong to organizations through the `org_memberships` table, with a role in each
+//!   organization that is separate from their `UserRole`.
+//! - A token carries the `org_id` of the organization the user is acting in, and user listings and
+//!   to-do queries only return the rows of that organization.
+//! - Deployments that were running before organizations existed keep every user and item in the
+//!   default organization, `DEFAULT_ORG_ID`.
+use std::error::Error;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use sqlx::{Decode, Encode, Postgres, Type};
+use sqlx::postgres::PgTypeInfo;
+use chrono::NaiveDateTime;
+use validator::Validate;
+use crate::users::{validate_name, UserRole};
+
+
+/// The organization every user and item belongs to when no other is given.
+pub const DEFAULT_ORG_ID: i32 = 1;
+
+
+/// The role of a user within one organization.
+///
+/// # Variants
+/// * `Owner` - Created the organization, can manage it and cannot be removed from it.
+/// * `Admin` - Can add and remove the members of the organization.
+/// * `Member` - Can act in the organization.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Owner,
+    Admin,
+    #[default]
+    Member,
+}
+
+impl OrgRole {
+
+    /// The value stored in the `org_role` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrgRole::Owner => "owner",
+            OrgRole::Admin => "admin",
+            OrgRole::Member => "member",
+        }
+    }
+}
+
+impl FromStr for OrgRole {
+    type Err = String;
+    fn from_str(org_role: &str) -> Result<Self, Self::Err> {
+        match org_role {
+            "owner" => Ok(OrgRole::Owner),
+            "admin" => Ok(OrgRole::Admin),
+            "member" => Ok(OrgRole::Member),
+            _ => Err(format!("Invalid organization role: {}", org_role)),
+        }
+    }
+}
+
+impl Type<Postgres> for OrgRole {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for OrgRole {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for OrgRole {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        OrgRole::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// Represents the schema for a new organization.
+///
+/// # Fields
+/// * name - The name of the organization.
+///
+/// # Validation
+/// * `name` - 1 to 255 characters with no control characters.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Validate)]
+pub struct NewOrganization {
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        custom(function = "validate_name")
+    )]
+    pub name: String,
+}
+
+
+/// Represents an organization stored in the system.
+///
+/// # Fields
+/// * id - The unique identifier for the organization.
+/// * name - The name of the organization.
+/// * date_created - When the organization was created.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct Organization {
+    pub id: i32,
+    pub name: String,
+    pub date_created: NaiveDateTime,
+}
+
+
+/// Represents a user's membership of an organization.
+///
+/// # Fields
+/// * org_id - The ID of the organization.
+/// * user_id - The ID of the member.
+/// * org_role - The role of the member within the organization.
+/// * date_joined - When the user joined the organization.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct OrgMembership {
+    pub org_id: i32,
+    pub user_id: i32,
+    pub org_role: OrgRole,
+    pub date_joined: NaiveDateTime,
+}
+
+
+/// Represents an organization a user belongs to, as listed for that user.
+///
+/// # Fields
+/// * id - The ID of the organization.
+/// * name - The name of the organization.
+/// * org_role - The role of the user within the organization.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct UserOrganization {
+    pub id: i32,
+    pub name: String,
+    pub org_role: OrgRole,
+}
+
+
+/// Whether a user can add and remove the members of an organization, only its owner, its admins
+/// and super admins can.
+///
+/// # Arguments
+/// * `membership` - The user's membership of the organization, `None` if they are not a member.
+/// * `role` - The user's role across the deployment.
+pub fn can_manage_org(membership: Option<&OrgMembership>, role: &UserRole) -> bool {
+    matches!(role, UserRole::SuperAdmin)
+        || membership.is_some_and(|membership| matches!(membership.org_role, OrgRole::Owner | OrgRole::Admin))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_org_role_round_trip() {
+        for org_role in [OrgRole::Owner, OrgRole::Admin, OrgRole::Member] {
+            assert_eq!(OrgRole::from_str(org_role.as_str()).unwrap(), org_role);
+            assert_eq!(serde_json::to_value(org_role).unwrap(), org_role.as_str());
+        }
+        assert!(OrgRole::from_str("guest").is_err());
+    }
+
+    #[test]
+    fn test_can_manage_org() {
+        let membership = |org_role| OrgMembership {
+            org_id: 2,
+            user_id: 3,
+            org_role,
+            date_joined: chrono::Utc::now().naive_utc(),
+        };
+        assert!(can_manage_org(Some(&membership(OrgRole::Admin)), &UserRole::Worker));
+        assert!(!can_manage_org(Some(&membership(OrgRole::Member)), &UserRole::Admin));
+        assert!(can_manage_org(None, &UserRole::SuperAdmin));
+        assert!(!can_manage_org(None, &UserRole::Admin));
+    }
+}
@@ -0,0 +1,204 @@
+//! Defines the envelope every v2 list endpoint returns.
+//!
+//! ## Purpose
+//! - A list comes back as `{"items": [...], "next_cursor": "...", "total": 42}` rather than a bare
+//!   array, so clients page through every list in the same way.
+//! - `next_cursor` is opaque and `null` on the last page, passing it back as `cursor` returns the
+//!   next page. `total` is only included when the list was counted anyway.
+//! - The cursor holds a single position, the offset for lists paged in memory or the last ID for
+//!   lists read with a keyset query, so each endpoint only ever decodes the cursors it issued.
+use serde::{Serialize, Deserialize};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The page size used when the client does not ask for one.
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+
+/// The largest page a client can ask for.
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+
+fn bad_request(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::BadRequest)
+}
+
+
+/// How far through a list a client has got, handed to clients as an opaque string.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PageCursor {
+    pub after: i64,
+}
+
+impl PageCursor {
+
+    /// Encodes the cursor as a URL safe string.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Decodes a cursor returned by `encode`.
+    ///
+    /// # Returns
+    /// * `Err(NanoServiceError)` - `BadRequest` if the cursor was not produced by `encode`.
+    pub fn decode(cursor: &str) -> Result<Self, NanoServiceError> {
+        URL_SAFE_NO_PAD.decode(cursor.trim())
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| bad_request("Invalid cursor".to_string()))
+    }
+}
+
+
+/// The page a client asks for, read from the query string.
+///
+/// # Fields
+/// * `cursor` - The `next_cursor` of the previous page, the first page if `None`.
+/// * `limit` - The most items to return, `DEFAULT_PAGE_SIZE` if `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PageQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl PageQuery {
+
+    /// The page size asked for.
+    ///
+    /// # Returns
+    /// * `Err(NanoServiceError)` - `BadRequest` if `limit` is not between 1 and `MAX_PAGE_SIZE`.
+    pub fn limit(&self) -> Result<i64, NanoServiceError> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            return Err(bad_request(format!("limit has to be between 1 and {}", MAX_PAGE_SIZE)))
+        }
+        Ok(limit)
+    }
+
+    /// The position in the cursor, `None` for the first page.
+    pub fn after(&self) -> Result<Option<i64>, NanoServiceError> {
+        self.cursor.as_deref()
+            .map(|cursor| PageCursor::decode(cursor).map(|cursor| cursor.after))
+            .transpose()
+    }
+
+    /// The position in the cursor as a row ID, `None` for the first page.
+    pub fn after_id(&self) -> Result<Option<i32>, NanoServiceError> {
+        self.after()?
+            .map(|after| i32::try_from(after).map_err(|_| bad_request("Invalid cursor".to_string())))
+            .transpose()
+    }
+}
+
+
+/// A page of a list.
+///
+/// # Fields
+/// * `items` - The items on this page.
+/// * `next_cursor` - The cursor of the next page, `None` on the last page.
+/// * `total` - How many items the whole list holds, left out when it was not counted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+impl<T> Page<T> {
+
+    /// Pages a list already held in full, the cursor being the offset of the next page.
+    ///
+    /// # Arguments
+    /// * `items` - The whole list.
+    /// * `query` - The page asked for.
+    pub fn from_items(items: Vec<T>, query: &PageQuery) -> Result<Self, NanoServiceError> {
+        let limit = query.limit()?;
+        let offset = query.after()?.unwrap_or(0);
+        if offset < 0 {
+            return Err(bad_request("Invalid cursor".to_string()))
+        }
+        let total = items.len() as i64;
+        let items: Vec<T> = items.into_iter().skip(offset as usize).take(limit as usize).collect();
+        let next_offset = offset + items.len() as i64;
+        Ok(Page {
+            items,
+            next_cursor: (next_offset < total).then(|| PageCursor { after: next_offset }.encode()),
+            total: Some(total),
+        })
+    }
+
+    /// Pages the rows of a keyset query that read one more row than the page.
+    ///
+    /// # Arguments
+    /// * `rows` - Up to `limit + 1` rows after the cursor, in the order they are listed.
+    /// * `limit` - The page size.
+    /// * `key` - The position of a row, the next page starts after the last row's.
+    pub fn from_lookahead(mut rows: Vec<T>, limit: i64, key: impl Fn(&T) -> i64) -> Self {
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+        let next_cursor = match (has_more, rows.last()) {
+            (true, Some(last)) => Some(PageCursor { after: key(last) }.encode()),
+            _ => None
+        };
+        Page { items: rows, next_cursor, total: None }
+    }
+
+    /// Converts the items, keeping the cursor and total.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            total: self.total,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(cursor: Option<String>, limit: i64) -> PageQuery {
+        PageQuery { cursor, limit: Some(limit) }
+    }
+
+    #[test]
+    fn test_from_items() {
+        let first = Page::from_items((1..=5).collect(), &query(None, 2)).unwrap();
+        assert_eq!(first.items, vec![1, 2]);
+        assert_eq!(first.total, Some(5));
+
+        let last = Page::from_items((1..=5).collect(), &query(first.next_cursor.clone(), 3)).unwrap();
+        assert_eq!(last.items, vec![3, 4, 5]);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn test_from_lookahead() {
+        let page = Page::from_lookahead(vec![10, 8, 7], 2, |row| *row as i64);
+        assert_eq!(page.items, vec![10, 8]);
+        let next = query(page.next_cursor, 2);
+        assert_eq!(next.after_id().unwrap(), Some(8));
+
+        let last = Page::from_lookahead(vec![3], 2, |row| *row as i64);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        assert!(query(None, 0).limit().is_err());
+        assert!(query(None, MAX_PAGE_SIZE + 1).limit().is_err());
+        assert_eq!(PageQuery::default().limit().unwrap(), DEFAULT_PAGE_SIZE);
+        let error = query(Some("not-a-cursor".to_string()), 2).after().unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[test]
+    fn test_envelope() {
+        let page = Page { items: vec!["a"], next_cursor: None, total: None };
+        assert_eq!(serde_json::to_value(&page).unwrap(), serde_json::json!({"items": ["a"], "next_cursor": null}));
+    }
+}
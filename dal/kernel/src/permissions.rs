@@ -0,0 +1,48 @@
+//! Defines the fine-grained permission model layered on top of user roles.
+//!
+//! ## Purpose
+//! - Roles are granted named permissions such as `todo:assign` through `role_permission_grants`.
+//! - A user's effective permissions are the union of the grants for every role they hold, and are
+//!   stored in the auth cache session at login so `PermissionCheck` can be enforced per request.
+use serde::{Serialize, Deserialize};
+use crate::users::UserRole;
+
+
+/// Represents the schema for a new permission.
+/// 
+/// # Fields
+/// * name - The unique name of the permission, e.g. `todo:assign`.
+/// * description - An optional description of what the permission allows.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewPermission {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+
+/// Represents a permission stored in the system.
+/// 
+/// # Fields
+/// * id - The unique identifier for the permission.
+/// * name - The unique name of the permission, e.g. `todo:assign`.
+/// * description - An optional description of what the permission allows.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct Permission {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+
+/// Represents a permission granted to a role.
+/// 
+/// # Fields
+/// * id - The unique identifier for the grant.
+/// * role - The role the permission is granted to.
+/// * permission_id - The ID of the granted permission.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct RolePermissionGrant {
+    pub id: i32,
+    pub role: UserRole,
+    pub permission_id: i32,
+}
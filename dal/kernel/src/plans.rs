@@ -0,0 +1,260 @@
+//! Defines the plans and the entitlements they grant.
+//!
+//! ## Purpose
+//! - The organization running the deployment is on a free, pro or enterprise plan, held in a single
+//!   row like the branding. Each plan grants a set of entitlements and a super admin can override
+//!   any of them for the organization, for example to raise the user limit for a trial.
+//! - Core functions check the entitlement they need and refuse with `UpgradeRequired`, a 402, and
+//!   details naming the entitlement so the frontend can offer an upgrade.
+//! - The plan row is seeded as enterprise so existing deployments keep everything they have.
+use serde::{Serialize, Deserialize};
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
+use chrono::NaiveDateTime;
+use validator::Validate;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use std::error::Error;
+use std::str::FromStr;
+
+
+/// A plan an organization can be on.
+///
+/// # Variants
+/// * `Free` - A small team trying the product.
+/// * `Pro` - A paying team.
+/// * `Enterprise` - No limits.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Plan {
+    Free,
+    Pro,
+    Enterprise,
+}
+
+impl Plan {
+
+    /// The value stored in the `plan` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Plan::Free => "free",
+            Plan::Pro => "pro",
+            Plan::Enterprise => "enterprise",
+        }
+    }
+
+    /// The entitlements the plan grants before any overrides.
+    pub fn entitlements(&self) -> Entitlements {
+        match self {
+            Plan::Free => Entitlements { max_users: Some(5), max_todos: Some(100), mfa: false, api_access: false },
+            Plan::Pro => Entitlements { max_users: Some(50), max_todos: Some(10_000), mfa: true, api_access: true },
+            Plan::Enterprise => Entitlements { max_users: None, max_todos: None, mfa: true, api_access: true },
+        }
+    }
+}
+
+impl FromStr for Plan {
+    type Err = String;
+    fn from_str(plan: &str) -> Result<Self, Self::Err> {
+        match plan.trim() {
+            "free" => Ok(Plan::Free),
+            "pro" => Ok(Plan::Pro),
+            "enterprise" => Ok(Plan::Enterprise),
+            _ => Err(format!("Invalid plan: {}", plan)),
+        }
+    }
+}
+
+impl Type<Postgres> for Plan {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for Plan {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Plan {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        Plan::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// What an organization is allowed to do.
+///
+/// # Fields
+/// * max_users - The most users the organization can have, `None` is unlimited.
+/// * max_todos - The most to-do items the organization can have, `None` is unlimited.
+/// * mfa - Whether users can turn on multi-factor authentication.
+/// * api_access - Whether the API can be used with API keys.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Entitlements {
+    pub max_users: Option<i64>,
+    pub max_todos: Option<i64>,
+    pub mfa: bool,
+    pub api_access: bool,
+}
+
+impl Entitlements {
+
+    /// Checks another user can be added to the `users` the organization already has.
+    pub fn check_max_users(&self, users: i64) -> Result<(), NanoServiceError> {
+        check_limit("max_users", self.max_users, users)
+    }
+
+    /// Checks another to-do item can be added to the `todos` the organization already has.
+    pub fn check_max_todos(&self, todos: i64) -> Result<(), NanoServiceError> {
+        check_limit("max_todos", self.max_todos, todos)
+    }
+
+    /// Checks users can turn on multi-factor authentication.
+    pub fn check_mfa(&self) -> Result<(), NanoServiceError> {
+        check_granted("mfa", self.mfa)
+    }
+
+    /// Checks the API can be used with API keys.
+    pub fn check_api_access(&self) -> Result<(), NanoServiceError> {
+        check_granted("api_access", self.api_access)
+    }
+}
+
+
+/// The error returned when the plan does not grant an entitlement.
+///
+/// # Arguments
+/// * `entitlement` - The name of the entitlement, given in the details.
+/// * `message` - What the plan does not allow.
+pub fn upgrade_required(entitlement: &str, message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::UpgradeRequired)
+        .with_details(serde_json::json!({"entitlement": entitlement}))
+}
+
+
+fn check_limit(entitlement: &str, limit: Option<i64>, current: i64) -> Result<(), NanoServiceError> {
+    match limit {
+        Some(limit) if current >= limit => Err(upgrade_required(
+            entitlement,
+            format!("The plan allows at most {} for {}", limit, entitlement)
+        )),
+        _ => Ok(())
+    }
+}
+
+
+fn check_granted(entitlement: &str, granted: bool) -> Result<(), NanoServiceError> {
+    match granted {
+        true => Ok(()),
+        false => Err(upgrade_required(entitlement, format!("The plan does not include {}", entitlement)))
+    }
+}
+
+
+/// The plan and overrides as they are submitted.
+///
+/// # Fields
+/// * plan - The plan the organization is on.
+/// * max_users - Overrides the plan's user limit, `None` keeps the plan's.
+/// * max_todos - Overrides the plan's to-do item limit, `None` keeps the plan's.
+/// * mfa - Overrides whether the plan includes multi-factor authentication.
+/// * api_access - Overrides whether the plan includes API access.
+///
+/// # Validation
+/// * `max_users` and `max_todos` - Zero or more.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Validate)]
+pub struct NewOrgPlan {
+    pub plan: Plan,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "must be zero or more"))]
+    pub max_users: Option<i64>,
+    #[serde(default)]
+    #[validate(range(min = 0, message = "must be zero or more"))]
+    pub max_todos: Option<i64>,
+    #[serde(default)]
+    pub mfa: Option<bool>,
+    #[serde(default)]
+    pub api_access: Option<bool>,
+}
+
+
+/// The plan held in the `org_plan` table.
+///
+/// # Fields
+/// * plan - The plan the organization is on.
+/// * max_users - The override of the plan's user limit, if there is one.
+/// * max_todos - The override of the plan's to-do item limit, if there is one.
+/// * mfa - The override of whether the plan includes multi-factor authentication, if there is one.
+/// * api_access - The override of whether the plan includes API access, if there is one.
+/// * date_updated - When the plan was last changed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct OrgPlan {
+    pub plan: Plan,
+    pub max_users: Option<i64>,
+    pub max_todos: Option<i64>,
+    pub mfa: Option<bool>,
+    pub api_access: Option<bool>,
+    pub date_updated: NaiveDateTime,
+}
+
+impl OrgPlan {
+
+    /// The plan's entitlements with the organization's overrides applied.
+    pub fn entitlements(&self) -> Entitlements {
+        let defaults = self.plan.entitlements();
+        Entitlements {
+            max_users: self.max_users.or(defaults.max_users),
+            max_todos: self.max_todos.or(defaults.max_todos),
+            mfa: self.mfa.unwrap_or(defaults.mfa),
+            api_access: self.api_access.unwrap_or(defaults.api_access),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn org_plan(plan: Plan) -> OrgPlan {
+        OrgPlan {
+            plan,
+            max_users: None,
+            max_todos: None,
+            mfa: None,
+            api_access: None,
+            date_updated: NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn test_plan_round_trip() {
+        for plan in [Plan::Free, Plan::Pro, Plan::Enterprise] {
+            assert_eq!(Plan::from_str(plan.as_str()).unwrap(), plan);
+        }
+    }
+
+    #[test]
+    fn test_overrides() {
+        let entitlements = OrgPlan { max_users: Some(8), api_access: Some(true), ..org_plan(Plan::Free) }.entitlements();
+        assert_eq!(entitlements.max_users, Some(8));
+        assert_eq!(entitlements.max_todos, Some(100));
+        assert!(!entitlements.mfa);
+        assert!(entitlements.api_access);
+    }
+
+    #[test]
+    fn test_checks() {
+        let free = org_plan(Plan::Free).entitlements();
+        assert!(free.check_max_users(4).is_ok());
+        let error = free.check_max_users(5).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::UpgradeRequired);
+        assert_eq!(error.details.unwrap()["entitlement"], "max_users");
+        assert!(free.check_mfa().is_err());
+
+        let enterprise = org_plan(Plan::Enterprise).entitlements();
+        assert!(enterprise.check_max_todos(i64::MAX).is_ok());
+        assert!(enterprise.check_api_access().is_ok());
+    }
+}
@@ -0,0 +1,161 @@
+//! Defines the structs for grouping to-do items into projects.
+//!
+//! ## Purpose
+//! - A project is a named board that to-do items can be added to, each item belongs to at most one
+//!   project and the `project_todos` table links it to that project.
+//! - The `project_members` table records which users can see a project. The user who creates a
+//!   project owns it and is its first member, only the owner and admins can change the project or
+//!   its members.
+//! - A project is removed with its owner, and removing a project leaves its items in place.
+use serde::{Serialize, Deserialize};
+use chrono::NaiveDateTime;
+use validator::Validate;
+use crate::to_do_items::{deserialize_some, TodoStatus};
+use crate::users::{validate_name, UserRole};
+
+
+/// Represents the schema for a new project.
+///
+/// # Fields
+/// * name - The name of the project.
+/// * description - What the project is for (optional).
+///
+/// # Validation
+/// * `name` - 1 to 255 characters with no control characters.
+/// * `description` - At most 5000 characters.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Validate)]
+pub struct NewProject {
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        custom(function = "validate_name")
+    )]
+    pub name: String,
+    #[validate(length(max = 5000, message = "must be at most 5000 characters"))]
+    pub description: Option<String>,
+}
+
+
+/// Represents a project stored in the system.
+///
+/// # Fields
+/// * id - The unique identifier for the project.
+/// * name - The name of the project.
+/// * description - What the project is for.
+/// * owner_id - The ID of the user who created the project.
+/// * date_created - When the project was created.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct Project {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub owner_id: i32,
+    pub date_created: NaiveDateTime,
+}
+
+
+/// The changes to make to a project, fields that are left out are not changed.
+///
+/// # Fields
+/// * `name`: The new name of the project.
+/// * `description`: The new description, `Some(None)` clears it.
+///
+/// # Validation
+/// * `name` - 1 to 255 characters with no control characters.
+/// * `description` - At most 5000 characters.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, Validate)]
+pub struct ProjectPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        custom(function = "validate_name")
+    )]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    #[validate(length(max = 5000, message = "must be at most 5000 characters"))]
+    pub description: Option<Option<String>>,
+}
+
+impl ProjectPatch {
+
+    /// Whether the patch does not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.description.is_none()
+    }
+}
+
+
+/// Represents a user who can see a project.
+///
+/// # Fields
+/// * project_id - The ID of the project.
+/// * user_id - The ID of the member.
+/// * date_added - When the user was added to the project.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct ProjectMember {
+    pub project_id: i32,
+    pub user_id: i32,
+    pub date_added: NaiveDateTime,
+}
+
+
+/// Represents a to-do item added to a project.
+///
+/// # Fields
+/// * project_id - The ID of the project.
+/// * todo_id - The ID of the to-do item.
+/// * date_added - When the item was added to the project.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct ProjectTodo {
+    pub project_id: i32,
+    pub todo_id: i32,
+    pub date_added: NaiveDateTime,
+}
+
+
+/// The optional filters for listing the to-do items of a project.
+///
+/// # Fields
+/// * `status` - Only return items in this status.
+/// * `assigned_to` - Only return items assigned to this user.
+/// * `finished` - Only return finished or unfinished items.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ProjectItemFilter {
+    pub status: Option<TodoStatus>,
+    pub assigned_to: Option<i32>,
+    pub finished: Option<bool>,
+}
+
+
+/// Whether a user can change a project and its members, only its owner and admins can.
+pub fn can_manage_project(project: &Project, user_id: i32, role: &UserRole) -> bool {
+    project.owner_id == user_id || matches!(role, UserRole::SuperAdmin | UserRole::Admin)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_clears_description() {
+        let patch: ProjectPatch = serde_json::from_str(r#"{"description": null}"#).unwrap();
+        assert_eq!(patch.description, Some(None));
+        assert!(!patch.is_empty());
+        assert!(ProjectPatch::default().is_empty());
+        assert!(ProjectPatch { name: Some("  ".to_string()), description: None }.validate().is_err());
+    }
+
+    #[test]
+    fn test_can_manage_project() {
+        let project = Project {
+            id: 1,
+            name: "Launch".to_string(),
+            description: None,
+            owner_id: 2,
+            date_created: chrono::Utc::now().naive_utc(),
+        };
+        assert!(can_manage_project(&project, 2, &UserRole::Worker));
+        assert!(can_manage_project(&project, 5, &UserRole::Admin));
+        assert!(!can_manage_project(&project, 5, &UserRole::Worker));
+    }
+}
@@ -31,7 +31,7 @@ impl NewRateLimitEntry {
     /// * `Ok(NewRateLimitEntry)` - If the email is valid.
     /// * `Err(NanoServiceError)` - If the email is empty or invalid.
     pub fn new(email: String) -> NewRateLimitEntry {
-        return NewRateLimitEntry { email }
+        NewRateLimitEntry { email }
     }
 }
 
@@ -0,0 +1,43 @@
+//! Defines the structs for measuring how available the server is.
+//!
+//! ## Purpose
+//! - Requests are counted per minute, a server error (5xx) counts against availability.
+//! - The minutes are rolled up into one row per day that is kept for the SLO report.
+use serde::{Serialize, Deserialize};
+use chrono::{NaiveDate, NaiveDateTime};
+
+
+/// The requests served in one minute.
+///
+/// # Fields
+/// * bucket_start - The start of the minute.
+/// * total_requests - Every request served in the minute.
+/// * server_errors - The requests that got a 5xx response.
+/// * client_errors - The requests that got a 4xx response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct RequestMetricsBucket {
+    pub bucket_start: NaiveDateTime,
+    pub total_requests: i64,
+    pub server_errors: i64,
+    pub client_errors: i64,
+}
+
+
+/// The availability of the server for one day.
+///
+/// # Fields
+/// * day - The UTC day.
+/// * total_requests - Every request served in the day.
+/// * server_errors - The requests that got a 5xx response.
+/// * client_errors - The requests that got a 4xx response.
+/// * availability - The fraction of requests that did not get a 5xx response, 1 for a day without requests.
+/// * error_rate - The fraction of requests that got a 5xx response.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct AvailabilityRollup {
+    pub day: NaiveDate,
+    pub total_requests: i64,
+    pub server_errors: i64,
+    pub client_errors: i64,
+    pub availability: f64,
+    pub error_rate: f64,
+}
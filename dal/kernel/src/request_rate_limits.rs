@@ -0,0 +1,20 @@
+//! Defines the structs for limiting how often sensitive endpoints can be called.
+//!
+//! ## Purpose
+//! - Requests are counted per key, such as a client IP or an account email, in fixed windows.
+//! - Once a key's count passes the limit for its window, further requests are refused until the
+//!   window resets.
+use serde::{Serialize, Deserialize};
+use chrono::NaiveDateTime;
+
+
+/// The state of a key's window after counting a request against it.
+///
+/// # Fields
+/// * count - The requests made in the current window, including this one.
+/// * window_start - When the current window started.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct RateLimitHit {
+    pub count: i32,
+    pub window_start: NaiveDateTime,
+}
@@ -0,0 +1,79 @@
+//! Defines the tables that are purged once their rows are older than the retention period.
+//!
+//! ## Purpose
+//! - Records that grow with every login, email or event are only kept for a configured number of
+//!   days, keeping storage bounded and meeting the retention rules for personal data.
+//! - Each table is purged in batches so a large backlog never holds a long lock.
+//! - Rows about a user under a legal hold are never purged, see `crate::legal_holds`.
+use serde::Serialize;
+
+
+/// A table with a retention period.
+///
+/// # Variants
+/// * `AuditLog` - The `audit_log` of privileged actions.
+/// * `LoginEvents` - The `login_events` recorded for every successful login.
+/// * `EmailLog` - The emails in the `email_outbox` that have been sent or have failed, pending emails are never purged.
+/// * `Notifications` - The `pending_notifications` that were never batched into a summary.
+/// * `WebhookDeliveries` - The `webhook_deliveries` recorded to process each webhook once, kept past the providers' retries.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionTable {
+    AuditLog,
+    LoginEvents,
+    EmailLog,
+    Notifications,
+    WebhookDeliveries,
+}
+
+impl RetentionTable {
+
+    /// Every table with a retention period, in the order they are purged.
+    pub const ALL: [RetentionTable; 5] = [
+        RetentionTable::AuditLog,
+        RetentionTable::LoginEvents,
+        RetentionTable::EmailLog,
+        RetentionTable::Notifications,
+        RetentionTable::WebhookDeliveries,
+    ];
+
+    /// The name the table is reported and configured by.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetentionTable::AuditLog => "audit_log",
+            RetentionTable::LoginEvents => "login_events",
+            RetentionTable::EmailLog => "email_log",
+            RetentionTable::Notifications => "notifications",
+            RetentionTable::WebhookDeliveries => "webhook_deliveries",
+        }
+    }
+
+    /// The config variable holding the days to keep, such as `AUDIT_LOG_RETENTION_DAYS`.
+    pub fn config_variable(&self) -> String {
+        format!("{}_RETENTION_DAYS", self.as_str().to_uppercase())
+    }
+
+    /// The days kept when not configured.
+    pub fn default_days(&self) -> u64 {
+        match self {
+            RetentionTable::AuditLog => 365,
+            RetentionTable::LoginEvents => 90,
+            RetentionTable::EmailLog => 30,
+            RetentionTable::Notifications => 30,
+            RetentionTable::WebhookDeliveries => 30,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_variable() {
+        assert_eq!(RetentionTable::AuditLog.config_variable(), "AUDIT_LOG_RETENTION_DAYS");
+        assert_eq!(RetentionTable::EmailLog.config_variable(), "EMAIL_LOG_RETENTION_DAYS");
+        assert_eq!(serde_json::to_string(&RetentionTable::LoginEvents).unwrap(), "\"login_events\"");
+    }
+}
@@ -0,0 +1,198 @@
+//! Exclusive XML canonicalization without comments, the form SAML signatures are computed over.
+//!
+//! # Notes
+//! Follows https://www.w3.org/TR/xml-exc-c14n/ for documents `roxmltree` has parsed, which has
+//! already normalized line endings and attribute values and refuses DTDs. Prefixes are read from the
+//! input as `roxmltree` only keeps the namespace they are bound to.
+use std::collections::BTreeMap;
+use roxmltree::{Node, NodeId};
+
+
+/// The name of the element or attribute at the start of the input, as written.
+fn written_qname(input: &str, start: usize) -> &str {
+    let rest = &input[start..];
+    let end = rest.find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '=')).unwrap_or(rest.len());
+    &rest[..end]
+}
+
+
+/// The prefix of a name as written, `""` if it has none.
+fn prefix_of(qname: &str) -> &str {
+    qname.split_once(':').map(|(prefix, _)| prefix).unwrap_or("")
+}
+
+
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c)
+        }
+    }
+}
+
+
+fn escape_attribute(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c)
+        }
+    }
+}
+
+
+/// Canonicalizes an element and its descendants.
+///
+/// # Arguments
+/// * `element` - The element to canonicalize.
+/// * `excluded` - An element left out with its descendants, the signature of an enveloped signature.
+/// * `inclusive_prefixes` - The `InclusiveNamespaces` prefixes rendered wherever they are in scope,
+///   `#default` for the default namespace.
+pub fn canonicalize(element: Node, excluded: Option<NodeId>, inclusive_prefixes: &[&str]) -> String {
+    let mut out = String::new();
+    write_element(element, excluded, inclusive_prefixes, &BTreeMap::new(), &mut out);
+    out
+}
+
+
+fn write_element(
+    element: Node,
+    excluded: Option<NodeId>,
+    inclusive_prefixes: &[&str],
+    rendered: &BTreeMap<String, String>,
+    out: &mut String
+) {
+    let input = element.document().input_text();
+    let qname = written_qname(input, element.range().start + 1);
+    let attributes: Vec<_> = element.attributes()
+        .map(|attribute| (written_qname(input, attribute.range_qname().start), attribute))
+        .collect();
+
+    // the namespaces the element and its attributes use, and the inclusive ones
+    let mut utilized: Vec<&str> = vec![prefix_of(qname)];
+    utilized.extend(attributes.iter().map(|(name, _)| prefix_of(name)).filter(|prefix| !prefix.is_empty()));
+    utilized.extend(inclusive_prefixes.iter().map(|prefix| if *prefix == "#default" { "" } else { prefix }));
+    let mut declarations = BTreeMap::new();
+    for prefix in utilized {
+        if prefix == "xml" || prefix == "xmlns" {
+            continue
+        }
+        let uri = match prefix {
+            "" => element.default_namespace().unwrap_or(""),
+            _ => match element.lookup_namespace_uri(Some(prefix)) {
+                Some(uri) => uri,
+                None => continue
+            }
+        };
+        // an empty default namespace only needs declaring to undo one declared above
+        if rendered.get(prefix).map(String::as_str).unwrap_or("") != uri {
+            declarations.insert(prefix.to_string(), uri.to_string());
+        }
+    }
+
+    out.push('<');
+    out.push_str(qname);
+    for (prefix, uri) in &declarations {
+        match prefix.as_str() {
+            "" => out.push_str(" xmlns=\""),
+            _ => {
+                out.push_str(" xmlns:");
+                out.push_str(prefix);
+                out.push_str("=\"");
+            }
+        }
+        escape_attribute(uri, out);
+        out.push('"');
+    }
+    let mut sorted = attributes;
+    sorted.sort_by(|(_, a), (_, b)| {
+        (a.namespace().unwrap_or(""), a.name()).cmp(&(b.namespace().unwrap_or(""), b.name()))
+    });
+    for (name, attribute) in sorted {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        escape_attribute(attribute.value(), out);
+        out.push('"');
+    }
+    out.push('>');
+
+    let mut in_scope = rendered.clone();
+    in_scope.extend(declarations);
+    for child in element.children() {
+        if child.is_element() {
+            if Some(child.id()) != excluded {
+                write_element(child, excluded, inclusive_prefixes, &in_scope, out);
+            }
+        } else if child.is_text() {
+            escape_text(child.text().unwrap_or(""), out);
+        } else if let Some(pi) = child.pi() {
+            out.push_str("<?");
+            out.push_str(pi.target);
+            if let Some(value) = pi.value {
+                out.push(' ');
+                out.push_str(value);
+            }
+            out.push_str("?>");
+        }
+    }
+
+    out.push_str("</");
+    out.push_str(qname);
+    out.push('>');
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(xml: &str, inclusive_prefixes: &[&str]) -> String {
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        let element = doc.root_element().first_element_child().unwrap();
+        canonicalize(element, None, inclusive_prefixes)
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        // unused namespaces are dropped, attributes sorted by namespace then name and
+        // empty elements expanded
+        let xml = r#"<root xmlns:a="urn:a" xmlns:b="urn:b" xmlns:unused="urn:unused">
+            <a:item z="1" b:y="2" a="3&amp;&quot;"><b:child/><!-- note -->text &gt; &#13;</a:item>
+        </root>"#;
+        assert_eq!(
+            canonical(xml, &[]),
+            "<a:item xmlns:a=\"urn:a\" xmlns:b=\"urn:b\" a=\"3&amp;&quot;\" z=\"1\" b:y=\"2\"><b:child></b:child>text &gt; &#xD;</a:item>"
+        );
+        assert!(canonical(xml, &["unused"]).starts_with("<a:item xmlns:a=\"urn:a\" xmlns:b=\"urn:b\" xmlns:unused=\"urn:unused\" "));
+    }
+
+    #[test]
+    fn test_canonicalize_default_namespace() {
+        let xml = r#"<root xmlns="urn:default"><item><inner xmlns=""/></item></root>"#;
+        assert_eq!(
+            canonical(xml, &[]),
+            "<item xmlns=\"urn:default\"><inner xmlns=\"\"></inner></item>"
+        );
+        let xml = r#"<root><item><inner/></item></root>"#;
+        assert_eq!(canonical(xml, &[]), "<item><inner></inner></item>");
+    }
+
+    #[test]
+    fn test_canonicalize_excludes_an_element() {
+        let xml = r#"<root><item ID="1"><skip><deep/></skip><keep/></item></root>"#;
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        let item = doc.root_element().first_element_child().unwrap();
+        let skip = item.first_element_child().unwrap();
+        assert_eq!(canonicalize(item, Some(skip.id()), &[]), "<item ID=\"1\"><keep></keep></item>");
+    }
+}
@@ -0,0 +1,22 @@
+//! SAML validators for tests.
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::saml::{SamlAssertion, SamlAssertionValidator, SamlSettings};
+
+
+/// Reads the response as the JSON of the assertion, so a test posts the assertion it wants. The
+/// response `refused` is refused as a response with a bad signature would be.
+pub struct JsonAssertionMock;
+
+impl SamlAssertionValidator for JsonAssertionMock {
+    fn validate_response(_settings: &SamlSettings, saml_response: &str) -> Result<SamlAssertion, NanoServiceError> {
+        match saml_response {
+            "refused" => Err(NanoServiceError::new(
+                "SAML response is not valid: the signature does not match".to_string(),
+                NanoServiceErrorStatus::Unauthorized
+            )),
+            _ => serde_json::from_str(saml_response).map_err(|e| NanoServiceError::new(
+                e.to_string(), NanoServiceErrorStatus::BadRequest
+            ))
+        }
+    }
+}
@@ -0,0 +1,378 @@
+//! Checks SAML responses signed with XML signatures, the way IdPs sign the HTTP-POST binding.
+//!
+//! # Notes
+//! - Signatures are accepted on the assertion, or on the response around it, using exclusive
+//!   canonicalization, RSA with SHA-256 and a SHA-256 digest. Other algorithms, encrypted assertions
+//!   and responses with more than one assertion are refused.
+//! - To stop signature wrapping, the assertion read is the element the signature covers, found by an
+//!   ID no other element in the response repeats.
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Duration, Utc};
+use roxmltree::{Document, Node};
+use rsa::RsaPublicKey;
+use rsa::pkcs1v15::{Signature, VerifyingKey};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::signature::Verifier;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use x509_cert::Certificate;
+use x509_cert::der::{Decode, Encode};
+use crate::saml::{c14n, SamlAssertion, SamlAssertionValidator, SamlSettings, CLOCK_SKEW_SECONDS};
+
+
+const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+
+
+fn invalid(message: &str) -> NanoServiceError {
+    NanoServiceError::new(format!("SAML response is not valid: {}", message), NanoServiceErrorStatus::Unauthorized)
+}
+
+
+/// The first child element with the name.
+fn child<'a, 'input>(node: Node<'a, 'input>, namespace: &str, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.is_element() && child.has_tag_name((namespace, name)))
+}
+
+
+fn children<'a, 'input: 'a>(node: Node<'a, 'input>, namespace: &'a str, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |child| child.is_element() && child.has_tag_name((namespace, name)))
+}
+
+
+/// The text of an element without surrounding whitespace.
+fn text(node: Node) -> String {
+    node.descendants().filter(|node| node.is_text()).filter_map(|node| node.text()).collect::<String>().trim().to_string()
+}
+
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, NanoServiceError> {
+    let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    STANDARD.decode(value).map_err(|_| invalid("bad base64"))
+}
+
+
+/// The IdP's public key, from its certificate or public key.
+fn idp_key(idp_certificate: &str) -> Result<RsaPublicKey, NanoServiceError> {
+    let config_error = |message: String| NanoServiceError::new(
+        format!("SAML_IDP_CERTIFICATE is not valid: {}", message),
+        NanoServiceErrorStatus::Unknown
+    );
+    let (label, der) = match pem::parse(idp_certificate.trim()) {
+        Ok(pem) => (pem.tag().to_string(), pem.into_contents()),
+        Err(_) => ("CERTIFICATE".to_string(), decode_base64(idp_certificate).map_err(|e| config_error(e.message))?)
+    };
+    let public_key_der = match label.as_str() {
+        "PUBLIC KEY" => der,
+        "CERTIFICATE" => Certificate::from_der(&der)
+            .and_then(|certificate| certificate.tbs_certificate.subject_public_key_info.to_der())
+            .map_err(|e| config_error(e.to_string()))?,
+        _ => return Err(config_error(format!("unexpected {}", label)))
+    };
+    RsaPublicKey::from_public_key_der(&public_key_der).map_err(|e| config_error(e.to_string()))
+}
+
+
+/// The `InclusiveNamespaces` prefixes of a canonicalization method or transform.
+fn inclusive_prefixes<'a>(method: Node<'a, '_>) -> Vec<&'a str> {
+    child(method, EXC_C14N, "InclusiveNamespaces")
+        .and_then(|namespaces| namespaces.attribute("PrefixList"))
+        .map(|list| list.split_whitespace().collect())
+        .unwrap_or_default()
+}
+
+
+/// Checks the enveloped signature of an element.
+fn verify_signature(signed: Node, key: &RsaPublicKey) -> Result<(), NanoServiceError> {
+    let signature = child(signed, DSIG_NS, "Signature").ok_or_else(|| invalid("not signed"))?;
+    let signed_info = child(signature, DSIG_NS, "SignedInfo").ok_or_else(|| invalid("no SignedInfo"))?;
+    let canonicalization = child(signed_info, DSIG_NS, "CanonicalizationMethod")
+        .filter(|method| method.attribute("Algorithm") == Some(EXC_C14N))
+        .ok_or_else(|| invalid("unsupported canonicalization"))?;
+    if child(signed_info, DSIG_NS, "SignatureMethod").and_then(|method| method.attribute("Algorithm")) != Some(RSA_SHA256) {
+        return Err(invalid("unsupported signature method"))
+    }
+
+    let mut references = children(signed_info, DSIG_NS, "Reference");
+    let (Some(reference), None) = (references.next(), references.next()) else {
+        return Err(invalid("the signature must have one reference"))
+    };
+    let id = signed.attribute("ID").ok_or_else(|| invalid("the signed element has no ID"))?;
+    if reference.attribute("URI") != Some(format!("#{}", id).as_str()) {
+        return Err(invalid("the signature does not cover the signed element"))
+    }
+    let mut prefixes = Vec::new();
+    if let Some(transforms) = child(reference, DSIG_NS, "Transforms") {
+        for transform in children(transforms, DSIG_NS, "Transform") {
+            match transform.attribute("Algorithm") {
+                Some(ENVELOPED_SIGNATURE) => {},
+                Some(EXC_C14N) => prefixes = inclusive_prefixes(transform),
+                _ => return Err(invalid("unsupported transform"))
+            }
+        }
+    }
+    if child(reference, DSIG_NS, "DigestMethod").and_then(|method| method.attribute("Algorithm")) != Some(SHA256) {
+        return Err(invalid("unsupported digest method"))
+    }
+    let digest_value = child(reference, DSIG_NS, "DigestValue").map(text).ok_or_else(|| invalid("no digest"))?;
+    let digest = Sha256::digest(c14n::canonicalize(signed, Some(signature.id()), &prefixes).as_bytes());
+    if digest.as_slice() != decode_base64(&digest_value)?.as_slice() {
+        return Err(invalid("the digest does not match"))
+    }
+
+    let signature_value = child(signature, DSIG_NS, "SignatureValue").map(text).ok_or_else(|| invalid("no signature value"))?;
+    let signature_value = Signature::try_from(decode_base64(&signature_value)?.as_slice())
+        .map_err(|_| invalid("bad signature value"))?;
+    let canonical_signed_info = c14n::canonicalize(signed_info, None, &inclusive_prefixes(canonicalization));
+    VerifyingKey::<Sha256>::new(key.clone())
+        .verify(canonical_signed_info.as_bytes(), &signature_value)
+        .map_err(|_| invalid("the signature does not match"))
+}
+
+
+/// A SAML timestamp, refused if it cannot be read.
+fn timestamp(node: Node, attribute: &str) -> Result<Option<DateTime<Utc>>, NanoServiceError> {
+    node.attribute(attribute)
+        .map(|value| DateTime::parse_from_rfc3339(value).map(|time| time.with_timezone(&Utc)))
+        .transpose()
+        .map_err(|_| invalid(&format!("bad {}", attribute)))
+}
+
+
+/// Checks the conditions and subject of a signed assertion and reads it.
+fn read_assertion(assertion: Node, settings: &SamlSettings, now: DateTime<Utc>) -> Result<SamlAssertion, NanoServiceError> {
+    let skew = Duration::seconds(CLOCK_SKEW_SECONDS);
+    if child(assertion, ASSERTION_NS, "Issuer").map(text).as_deref() != Some(settings.idp_entity_id.as_str()) {
+        return Err(invalid("the assertion is not from the IdP"))
+    }
+
+    let conditions = child(assertion, ASSERTION_NS, "Conditions").ok_or_else(|| invalid("no conditions"))?;
+    if timestamp(conditions, "NotBefore")?.is_some_and(|not_before| now + skew < not_before) {
+        return Err(invalid("the assertion is not valid yet"))
+    }
+    if timestamp(conditions, "NotOnOrAfter")?.is_some_and(|not_on_or_after| now - skew >= not_on_or_after) {
+        return Err(invalid("the assertion has expired"))
+    }
+    let mut restrictions = children(conditions, ASSERTION_NS, "AudienceRestriction").peekable();
+    if restrictions.peek().is_none() {
+        return Err(invalid("no audience"))
+    }
+    for restriction in restrictions {
+        if !children(restriction, ASSERTION_NS, "Audience").any(|audience| text(audience) == settings.sp_entity_id) {
+            return Err(invalid("the assertion is for another audience"))
+        }
+    }
+
+    let subject = child(assertion, ASSERTION_NS, "Subject").ok_or_else(|| invalid("no subject"))?;
+    let name_id = child(subject, ASSERTION_NS, "NameID").map(text).filter(|name_id| !name_id.is_empty())
+        .ok_or_else(|| invalid("no NameID"))?;
+    let mut confirmed = false;
+    for confirmation in children(subject, ASSERTION_NS, "SubjectConfirmation") {
+        let Some(data) = child(confirmation, ASSERTION_NS, "SubjectConfirmationData") else { continue };
+        confirmed |= confirmation.attribute("Method") == Some(BEARER)
+            && data.attribute("Recipient") == Some(settings.acs_url.as_str())
+            && timestamp(data, "NotOnOrAfter")?.is_some_and(|not_on_or_after| now - skew < not_on_or_after);
+    }
+    if !confirmed {
+        return Err(invalid("the subject is not confirmed for this server"))
+    }
+
+    let mut attributes: HashMap<String, Vec<String>> = HashMap::new();
+    for statement in children(assertion, ASSERTION_NS, "AttributeStatement") {
+        for attribute in children(statement, ASSERTION_NS, "Attribute") {
+            let Some(name) = attribute.attribute("Name") else { continue };
+            attributes.entry(name.to_string()).or_default()
+                .extend(children(attribute, ASSERTION_NS, "AttributeValue").map(text));
+        }
+    }
+    Ok(SamlAssertion { name_id, attributes })
+}
+
+
+/// Checks a decoded response at a point in time.
+fn validate_xml(xml: &str, settings: &SamlSettings, now: DateTime<Utc>) -> Result<SamlAssertion, NanoServiceError> {
+    let doc = Document::parse(xml).map_err(|_| invalid("bad XML"))?;
+    let response = doc.root_element();
+    if !response.has_tag_name((PROTOCOL_NS, "Response")) {
+        return Err(invalid("not a response"))
+    }
+    let status = child(response, PROTOCOL_NS, "Status")
+        .and_then(|status| child(status, PROTOCOL_NS, "StatusCode"))
+        .and_then(|code| code.attribute("Value"));
+    if status != Some(STATUS_SUCCESS) {
+        return Err(invalid("the IdP did not log the user in"))
+    }
+    if doc.descendants().any(|node| node.has_tag_name((ASSERTION_NS, "EncryptedAssertion"))) {
+        return Err(invalid("encrypted assertions are not supported"))
+    }
+    let assertions: Vec<Node> = doc.descendants().filter(|node| node.has_tag_name((ASSERTION_NS, "Assertion"))).collect();
+    let [assertion] = assertions.as_slice() else {
+        return Err(invalid("the response must have one assertion"))
+    };
+    if assertion.parent_element() != Some(response) {
+        return Err(invalid("the assertion is not in the response"))
+    }
+    let mut ids = HashSet::new();
+    if !doc.descendants().filter_map(|node| node.attribute("ID")).all(|id| ids.insert(id)) {
+        return Err(invalid("repeated ID"))
+    }
+
+    let key = idp_key(&settings.idp_certificate)?;
+    match child(*assertion, DSIG_NS, "Signature") {
+        Some(_) => verify_signature(*assertion, &key)?,
+        None => verify_signature(response, &key)?
+    }
+    read_assertion(*assertion, settings, now)
+}
+
+
+/// Checks responses against the IdP's certificate.
+pub struct XmlDsigValidator;
+
+impl SamlAssertionValidator for XmlDsigValidator {
+    fn validate_response(settings: &SamlSettings, saml_response: &str) -> Result<SamlAssertion, NanoServiceError> {
+        let xml = String::from_utf8(decode_base64(saml_response)?).map_err(|_| invalid("bad encoding"))?;
+        validate_xml(&xml, settings, Utc::now())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::UserRole;
+    use rsa::RsaPrivateKey;
+    use rsa::pkcs1v15::SigningKey;
+    use rsa::pkcs8::{EncodePublicKey, LineEnding};
+    use rsa::signature::{SignatureEncoding, Signer};
+    use std::sync::LazyLock;
+
+    static IDP_KEY: LazyLock<RsaPrivateKey> = LazyLock::new(|| {
+        RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap()
+    });
+
+    fn settings() -> SamlSettings {
+        SamlSettings {
+            sp_entity_id: "https://todo.example.com".to_string(),
+            acs_url: "https://todo.example.com/api/auth/v1/auth/saml/acs".to_string(),
+            idp_entity_id: "https://idp.example.com".to_string(),
+            idp_certificate: IDP_KEY.to_public_key().to_public_key_pem(LineEnding::LF).unwrap(),
+            email_attribute: None,
+            first_name_attribute: None,
+            last_name_attribute: None,
+            role_attribute: None,
+            admin_values: Vec::new(),
+            default_role: UserRole::Worker,
+        }
+    }
+
+    /// A response from the IdP with the assertion, the NameID changed to `name_id`.
+    fn response(name_id: &str, audience: &str, not_on_or_after: &str) -> String {
+        format!(r#"<samlp:Response xmlns:samlp="urn:oasis:names:tc:SAML:2.0:protocol" ID="_response" Version="2.0">
+  <saml:Issuer xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion">https://idp.example.com</saml:Issuer>
+  <samlp:Status><samlp:StatusCode Value="urn:oasis:names:tc:SAML:2.0:status:Success"/></samlp:Status>
+  <saml:Assertion xmlns:saml="urn:oasis:names:tc:SAML:2.0:assertion" xmlns:xs="http://www.w3.org/2001/XMLSchema" ID="_assertion" Version="2.0">
+    <saml:Issuer>https://idp.example.com</saml:Issuer>
+    <saml:Subject>
+      <saml:NameID Format="urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress">{name_id}</saml:NameID>
+      <saml:SubjectConfirmation Method="urn:oasis:names:tc:SAML:2.0:cm:bearer">
+        <saml:SubjectConfirmationData NotOnOrAfter="{not_on_or_after}" Recipient="https://todo.example.com/api/auth/v1/auth/saml/acs"/>
+      </saml:SubjectConfirmation>
+    </saml:Subject>
+    <saml:Conditions NotBefore="2025-01-01T11:59:00Z" NotOnOrAfter="{not_on_or_after}">
+      <saml:AudienceRestriction><saml:Audience>{audience}</saml:Audience></saml:AudienceRestriction>
+    </saml:Conditions>
+    <saml:AttributeStatement>
+      <saml:Attribute Name="groups">
+        <saml:AttributeValue xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="xs:string">staff</saml:AttributeValue>
+        <saml:AttributeValue>admins &amp; owners</saml:AttributeValue>
+      </saml:Attribute>
+    </saml:AttributeStatement>
+  </saml:Assertion>
+</samlp:Response>"#)
+    }
+
+    /// Signs the element with the ID in the response, as an IdP would.
+    fn sign(xml: &str, id: &str) -> String {
+        let doc = Document::parse(xml).unwrap();
+        let signed = doc.descendants().find(|node| node.attribute("ID") == Some(id)).unwrap();
+        let digest = STANDARD.encode(Sha256::digest(c14n::canonicalize(signed, None, &["xs"]).as_bytes()));
+        let signed_info = format!(
+            r##"<ds:SignedInfo xmlns:ds="http://www.w3.org/2000/09/xmldsig#"><ds:CanonicalizationMethod Algorithm="{EXC_C14N}"/><ds:SignatureMethod Algorithm="{RSA_SHA256}"/><ds:Reference URI="#{id}"><ds:Transforms><ds:Transform Algorithm="{ENVELOPED_SIGNATURE}"/><ds:Transform Algorithm="{EXC_C14N}"><ec:InclusiveNamespaces xmlns:ec="{EXC_C14N}" PrefixList="xs"/></ds:Transform></ds:Transforms><ds:DigestMethod Algorithm="{SHA256}"/><ds:DigestValue>{digest}</ds:DigestValue></ds:Reference></ds:SignedInfo>"##
+        );
+        let signed_info_doc = Document::parse(&signed_info).unwrap();
+        let canonical = c14n::canonicalize(signed_info_doc.root_element(), None, &[]);
+        let signature = SigningKey::<Sha256>::new(IDP_KEY.clone()).sign(canonical.as_bytes());
+        let signature = format!(
+            r#"<ds:Signature xmlns:ds="http://www.w3.org/2000/09/xmldsig#">{}<ds:SignatureValue>{}</ds:SignatureValue></ds:Signature>"#,
+            signed_info.replace(r#" xmlns:ds="http://www.w3.org/2000/09/xmldsig#""#, ""),
+            STANDARD.encode(signature.to_vec())
+        );
+        // the signature goes after the issuer of the signed element
+        let issuer_end = signed.range().start + xml[signed.range()].find("</saml:Issuer>").unwrap() + "</saml:Issuer>".len();
+        format!("{}{}{}", &xml[..issuer_end], signature, &xml[issuer_end..])
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-01-01T12:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    const EXPIRY: &str = "2025-01-01T12:05:00Z";
+
+    #[test]
+    fn test_signed_assertion() {
+        let xml = sign(&response("ada@example.com", "https://todo.example.com", EXPIRY), "_assertion");
+        let assertion = validate_xml(&xml, &settings(), now()).unwrap();
+        assert_eq!(assertion.name_id, "ada@example.com");
+        assert_eq!(assertion.attributes["groups"], vec!["staff".to_string(), "admins & owners".to_string()]);
+
+        let encoded = STANDARD.encode(&xml);
+        let error = XmlDsigValidator::validate_response(&settings(), &encoded).unwrap_err();
+        assert!(error.message.contains("expired"));
+    }
+
+    #[test]
+    fn test_signed_response() {
+        let xml = sign(&response("ada@example.com", "https://todo.example.com", EXPIRY), "_response");
+        assert_eq!(validate_xml(&xml, &settings(), now()).unwrap().name_id, "ada@example.com");
+    }
+
+    #[test]
+    fn test_refused_responses() {
+        let signed = sign(&response("ada@example.com", "https://todo.example.com", EXPIRY), "_assertion");
+        let check = |xml: &str| validate_xml(xml, &settings(), now()).unwrap_err();
+
+        let unsigned = response("ada@example.com", "https://todo.example.com", EXPIRY);
+        assert!(check(&unsigned).message.contains("not signed"));
+        let tampered = signed.replace(">ada@example.com<", ">eve@example.com<");
+        assert!(check(&tampered).message.contains("digest"));
+        let other_audience = sign(&response("ada@example.com", "https://other.example.com", EXPIRY), "_assertion");
+        assert!(check(&other_audience).message.contains("audience"));
+        let expired = validate_xml(&signed, &settings(), now() + Duration::minutes(10)).unwrap_err();
+        assert!(expired.message.contains("expired"));
+
+        // a second, unsigned assertion wrapped around the signed one is refused
+        let assertion_start = signed.find("<saml:Assertion").unwrap();
+        let wrapped = format!(
+            "{}<saml:Assertion xmlns:saml=\"urn:oasis:names:tc:SAML:2.0:assertion\" ID=\"_evil\"><saml:Subject><saml:NameID>eve@example.com</saml:NameID></saml:Subject></saml:Assertion>{}",
+            &signed[..assertion_start], &signed[assertion_start..]
+        );
+        assert!(check(&wrapped).message.contains("one assertion"));
+
+        // a key other than the IdP's
+        let mut other_key = settings();
+        other_key.idp_certificate = RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap()
+            .to_public_key().to_public_key_pem(LineEnding::LF).unwrap();
+        let error = validate_xml(&signed, &other_key, now()).unwrap_err();
+        assert!(error.message.contains("signature does not match"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Defines the structs and checks for single sign-on with a SAML identity provider, built with the
+//! `saml` feature.
+//!
+//! ## Purpose
+//! - An enterprise deployment can have its users log in at their own identity provider (IdP). This
+//!   server is the service provider (SP): the IdP is set up with the metadata from `sp_metadata`, and
+//!   posts a signed response to the assertion consumer service (ACS) when a user logs in.
+//! - The response is checked by a `SamlAssertionValidator`. The asserted user is linked to a user in
+//!   `federated_identities` under the `saml` provider, as with OpenID Connect logins, and new users are
+//!   provisioned with the role their IdP attributes map to.
+//!
+//! ## Variables
+//! * `SAML_SP_ENTITY_ID` - The entity ID of this server, SAML login is off when it is not set
+//! * `SAML_ACS_URL` - The URL of the ACS endpoint, ending in `/saml/acs`
+//! * `SAML_IDP_ENTITY_ID` - The entity ID of the IdP, the issuer of its assertions
+//! * `SAML_IDP_CERTIFICATE` - The IdP's signing certificate as PEM or base64, or its RSA public key as PEM
+//! * `SAML_EMAIL_ATTRIBUTE` - The attribute holding the user's email, the NameID when it is not set
+//! * `SAML_FIRST_NAME_ATTRIBUTE` - The attribute holding the user's first name, optional
+//! * `SAML_LAST_NAME_ATTRIBUTE` - The attribute holding the user's last name, optional
+//! * `SAML_ROLE_ATTRIBUTE` - The attribute holding the user's groups, optional
+//! * `SAML_ADMIN_VALUES` - Comma separated values of `SAML_ROLE_ATTRIBUTE` that make a user an `Admin`
+//! * `SAML_DEFAULT_ROLE` - The role of other provisioned users, `Worker` or `Admin`, defaults to `Worker`
+mod c14n;
+pub mod engine_xmldsig;
+pub mod engine_mock;
+
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::oidc::FederatedSubject;
+use crate::users::UserRole;
+
+
+/// The provider SAML users are linked under in `federated_identities`.
+pub const SAML_PROVIDER: &str = "saml";
+
+/// How far the IdP's clock can be from this server's when checking an assertion's validity.
+pub const CLOCK_SKEW_SECONDS: i64 = 60;
+
+
+/// The SP and IdP settings for SAML logins.
+///
+/// # Fields
+/// * sp_entity_id - The entity ID of this server, the audience of assertions.
+/// * acs_url - The URL responses are posted to, the recipient of assertions.
+/// * idp_entity_id - The entity ID of the IdP, the issuer of assertions.
+/// * idp_certificate - The IdP's signing certificate or public key.
+/// * email_attribute - The attribute holding the user's email, the NameID if `None`.
+/// * first_name_attribute - The attribute holding the user's first name.
+/// * last_name_attribute - The attribute holding the user's last name.
+/// * role_attribute - The attribute holding the user's groups.
+/// * admin_values - The values of the role attribute that make a user an `Admin`.
+/// * default_role - The role of users without an admin value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SamlSettings {
+    pub sp_entity_id: String,
+    pub acs_url: String,
+    pub idp_entity_id: String,
+    pub idp_certificate: String,
+    pub email_attribute: Option<String>,
+    pub first_name_attribute: Option<String>,
+    pub last_name_attribute: Option<String>,
+    pub role_attribute: Option<String>,
+    pub admin_values: Vec<String>,
+    pub default_role: UserRole,
+}
+
+
+/// A variable that is left blank or unset when it is not used.
+fn optional_variable<Y: GetConfigVariable>(variable: &str) -> Option<String> {
+    Y::get_config_variable(variable.to_string()).ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+
+impl SamlSettings {
+
+    /// Reads the settings from the config.
+    ///
+    /// # Returns
+    /// * `Ok(SamlSettings)` - The settings
+    /// * `Err(NanoServiceError)` - `NotFound` if SAML is not configured, `Unknown` if the ACS URL or
+    ///   IdP is missing or the default role is not `Worker` or `Admin`
+    pub fn from_config<Y: GetConfigVariable>() -> Result<Self, NanoServiceError> {
+        let Some(sp_entity_id) = optional_variable::<Y>("SAML_SP_ENTITY_ID") else {
+            return Err(NanoServiceError::new(
+                "SAML login is not configured".to_string(),
+                NanoServiceErrorStatus::NotFound
+            ))
+        };
+        let default_role = match optional_variable::<Y>("SAML_DEFAULT_ROLE") {
+            Some(role) => UserRole::from_string(&role)?,
+            None => UserRole::Worker
+        };
+        if default_role == UserRole::SuperAdmin {
+            return Err(NanoServiceError::new(
+                "SAML_DEFAULT_ROLE cannot be Super Admin".to_string(),
+                NanoServiceErrorStatus::Unknown
+            ))
+        }
+        Ok(SamlSettings {
+            sp_entity_id,
+            acs_url: Y::get_config_variable("SAML_ACS_URL".to_string())?,
+            idp_entity_id: Y::get_config_variable("SAML_IDP_ENTITY_ID".to_string())?,
+            idp_certificate: Y::get_config_variable("SAML_IDP_CERTIFICATE".to_string())?,
+            email_attribute: optional_variable::<Y>("SAML_EMAIL_ATTRIBUTE"),
+            first_name_attribute: optional_variable::<Y>("SAML_FIRST_NAME_ATTRIBUTE"),
+            last_name_attribute: optional_variable::<Y>("SAML_LAST_NAME_ATTRIBUTE"),
+            role_attribute: optional_variable::<Y>("SAML_ROLE_ATTRIBUTE"),
+            admin_values: optional_variable::<Y>("SAML_ADMIN_VALUES").unwrap_or_default()
+                .split(',')
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .collect(),
+            default_role,
+        })
+    }
+}
+
+
+/// Escapes a value for an XML attribute.
+fn escape_xml(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+
+/// The SP metadata the IdP is set up with.
+pub fn sp_metadata(settings: &SamlSettings) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="{}">
+  <md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+    <md:NameIDFormat>urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress</md:NameIDFormat>
+    <md:AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="{}" index="0" isDefault="true"/>
+  </md:SPSSODescriptor>
+</md:EntityDescriptor>
+"#,
+        escape_xml(&settings.sp_entity_id),
+        escape_xml(&settings.acs_url)
+    )
+}
+
+
+/// The user an IdP has vouched for in a valid assertion.
+///
+/// # Fields
+/// * name_id - The IdP's ID for the user.
+/// * attributes - The values of each attribute in the assertion.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SamlAssertion {
+    pub name_id: String,
+    #[serde(default)]
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+
+impl SamlAssertion {
+
+    /// The first value of an attribute.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name)?.iter().map(|value| value.trim()).find(|value| !value.is_empty())
+    }
+
+    /// The user's email, from `email_attribute` or the NameID.
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The email
+    /// * `Err(NanoServiceError)` - `Unauthorized` if the assertion has no email
+    pub fn email(&self, settings: &SamlSettings) -> Result<String, NanoServiceError> {
+        let email = match &settings.email_attribute {
+            Some(attribute) => self.attribute(attribute).unwrap_or_default(),
+            None => self.name_id.trim()
+        };
+        match email.contains('@') {
+            true => Ok(email.to_string()),
+            false => Err(NanoServiceError::new(
+                "The SAML assertion has no email".to_string(),
+                NanoServiceErrorStatus::Unauthorized
+            ))
+        }
+    }
+
+    /// The role the user's groups map to, never `SuperAdmin`.
+    pub fn role(&self, settings: &SamlSettings) -> UserRole {
+        let is_admin = settings.role_attribute.as_ref()
+            .and_then(|attribute| self.attributes.get(attribute))
+            .is_some_and(|values| values.iter().any(|value| settings.admin_values.contains(&value.trim().to_string())));
+        match is_admin {
+            true => UserRole::Admin,
+            false => settings.default_role.clone()
+        }
+    }
+
+    /// The subject the user is linked by.
+    pub fn subject(&self, settings: &SamlSettings) -> Result<FederatedSubject, NanoServiceError> {
+        Ok(FederatedSubject {
+            provider: SAML_PROVIDER.to_string(),
+            subject: self.name_id.trim().to_string(),
+            email: self.email(settings)?,
+        })
+    }
+}
+
+
+/// Checks the response an IdP posted to the ACS.
+pub trait SamlAssertionValidator {
+
+    /// Checks the response and reads the assertion in it.
+    ///
+    /// # Arguments
+    /// * `settings` - The SP and IdP settings.
+    /// * `saml_response` - The base64 encoded `SAMLResponse` form field.
+    ///
+    /// # Returns
+    /// * `Ok(SamlAssertion)` - The user the IdP has vouched for
+    /// * `Err(NanoServiceError)` - `Unauthorized` if the response is not valid
+    fn validate_response(settings: &SamlSettings, saml_response: &str) -> Result<SamlAssertion, NanoServiceError>;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SAML_SP_ENTITY_ID" => Ok("https://todo.example.com".to_string()),
+                "SAML_EMAIL_ATTRIBUTE" | "SAML_FIRST_NAME_ATTRIBUTE" => Ok(" ".to_string()),
+                "SAML_ROLE_ATTRIBUTE" => Ok("groups".to_string()),
+                "SAML_ADMIN_VALUES" => Ok("admins, owners,".to_string()),
+                "SAML_DEFAULT_ROLE" => Err(NanoServiceError::new("not set".to_string(), NanoServiceErrorStatus::Unknown)),
+                _ => Ok("value".to_string())
+            }
+        }
+    }
+
+    fn settings() -> SamlSettings {
+        SamlSettings::from_config::<MockConfig>().unwrap()
+    }
+
+    fn assertion(name_id: &str, groups: &[&str]) -> SamlAssertion {
+        SamlAssertion {
+            name_id: name_id.to_string(),
+            attributes: HashMap::from([
+                ("groups".to_string(), groups.iter().map(|group| group.to_string()).collect()),
+                ("mail".to_string(), vec!["".to_string(), "ada@example.com".to_string()]),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_settings_from_config() {
+        let settings = settings();
+        assert_eq!(settings.email_attribute, None);
+        assert_eq!(settings.first_name_attribute, None);
+        assert_eq!(settings.role_attribute.as_deref(), Some("groups"));
+        assert_eq!(settings.admin_values, vec!["admins".to_string(), "owners".to_string()]);
+        assert_eq!(settings.default_role, UserRole::Worker);
+    }
+
+    #[test]
+    fn test_assertion_mapping() {
+        let mut settings = settings();
+        let user = assertion("ada@example.com", &["staff", "owners"]);
+        assert_eq!(user.role(&settings), UserRole::Admin);
+        assert_eq!(assertion("ada@example.com", &["staff"]).role(&settings), UserRole::Worker);
+        assert_eq!(user.subject(&settings).unwrap(), FederatedSubject {
+            provider: SAML_PROVIDER.to_string(),
+            subject: "ada@example.com".to_string(),
+            email: "ada@example.com".to_string(),
+        });
+
+        let opaque = assertion("00u1abcd", &[]);
+        assert_eq!(opaque.email(&settings).unwrap_err().status, NanoServiceErrorStatus::Unauthorized);
+        settings.email_attribute = Some("mail".to_string());
+        assert_eq!(opaque.email(&settings).unwrap(), "ada@example.com");
+    }
+
+    #[test]
+    fn test_sp_metadata() {
+        let mut settings = settings();
+        settings.acs_url = "https://todo.example.com/saml/acs?a=1&b=2".to_string();
+        let metadata = sp_metadata(&settings);
+        let doc = roxmltree::Document::parse(&metadata).unwrap();
+        assert_eq!(doc.root_element().attribute("entityID"), Some("https://todo.example.com"));
+        let acs = doc.descendants().find(|node| node.has_tag_name("AssertionConsumerService")).unwrap();
+        assert_eq!(acs.attribute("Location"), Some("https://todo.example.com/saml/acs?a=1&b=2"));
+    }
+}
@@ -0,0 +1,60 @@
+//! Defines the structs for tracking to-do items against their SLA.
+//!
+//! ## Purpose
+//! - Each priority has a target time to finish an item, counted from when it was assigned.
+//! - The assignee is warned `warn_minutes` before the target so they can act before it is breached.
+use serde::{Serialize, Deserialize};
+use chrono::NaiveDateTime;
+use crate::to_do_items::TodoPriority;
+
+
+/// The SLA for a priority.
+///
+/// # Fields
+/// * priority - The priority the SLA applies to.
+/// * target_minutes - How long an item has to be finished after it is assigned.
+/// * warn_minutes - How long before the target the assignee is warned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct SlaPolicy {
+    pub priority: TodoPriority,
+    pub target_minutes: i32,
+    pub warn_minutes: i32,
+}
+
+
+/// A to-do item with its SLA deadline.
+///
+/// # Fields
+/// * todo_id - The ID of the to-do item.
+/// * name - The name of the to-do item.
+/// * assigned_by - The ID of the user who assigned the item.
+/// * assigned_to - The ID of the user the item is assigned to.
+/// * priority - The priority of the item.
+/// * date_assigned - When the item was assigned.
+/// * sla_due - When the item has to be finished by.
+/// * breached - Whether the item was, or still is, unfinished after `sla_due`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct TodoSlaStatus {
+    pub todo_id: i32,
+    pub name: String,
+    pub assigned_by: i32,
+    pub assigned_to: i32,
+    pub priority: TodoPriority,
+    pub date_assigned: NaiveDateTime,
+    pub sla_due: NaiveDateTime,
+    pub breached: bool,
+}
+
+
+/// Counts of to-do items by state.
+///
+/// # Fields
+/// * open_items - Items that are not finished.
+/// * finished_items - Items that are finished.
+/// * pending_review_items - Items waiting for the assigner to review them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, sqlx::FromRow)]
+pub struct ToDoCounts {
+    pub open_items: i64,
+    pub finished_items: i64,
+    pub pending_review_items: i64,
+}
@@ -0,0 +1,236 @@
+//! Defines the structs for the incremental sync API used by offline clients.
+//!
+//! ## Purpose
+//! - A client keeps the cursor from its last sync and only downloads the users and to-do items
+//!   changed since, along with the tombstones of anything deleted.
+//! - Each feed is read in `(updated_at, id)` order and the cursor records how far through each
+//!   feed the client has got, so rows changed in the same instant are never skipped between pages.
+//! - The `updated_at` of a row doubles as its version. Updates take it back in an `If-Match` header
+//!   and are refused with the current state and the conflicting fields if the row has moved on, so
+//!   the client can merge its change into the server's and try again.
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+use chrono::{DateTime, NaiveDateTime};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::users::PublicUser;
+use crate::to_do_items::Todo;
+use crate::tombstones::Tombstone;
+
+
+/// How far through a feed a client has synced.
+///
+/// # Fields
+/// * updated_at - When the last row the client received was changed.
+/// * id - The ID of the last row the client received.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SyncPosition {
+    pub updated_at: NaiveDateTime,
+    pub id: i32,
+}
+
+impl Default for SyncPosition {
+    fn default() -> Self {
+        SyncPosition {
+            updated_at: DateTime::UNIX_EPOCH.naive_utc(),
+            id: 0,
+        }
+    }
+}
+
+
+/// The position of a client in every feed, handed to clients as an opaque string.
+///
+/// # Fields
+/// * users - The position in the users feed.
+/// * todos - The position in the to-do items feed.
+/// * after_deletion_id - The ID of the last tombstone the client received.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct SyncCursor {
+    pub users: SyncPosition,
+    pub todos: SyncPosition,
+    pub after_deletion_id: i32,
+}
+
+impl SyncCursor {
+
+    /// Encodes the cursor as a URL safe string.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Decodes a cursor returned by `encode`.
+    ///
+    /// # Returns
+    /// * `Err(NanoServiceError)` - `BadRequest` if the cursor was not produced by `encode`.
+    pub fn decode(cursor: &str) -> Result<Self, NanoServiceError> {
+        URL_SAFE_NO_PAD.decode(cursor.trim())
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| NanoServiceError::new(
+                "Invalid sync cursor".to_string(),
+                NanoServiceErrorStatus::BadRequest
+            ))
+    }
+}
+
+
+/// A user as it is sent to sync clients, without their `uuid` as the feed lists other users.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct SyncedUser {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub user: PublicUser,
+    pub updated_at: NaiveDateTime,
+}
+
+
+/// A to-do item as it is sent to sync clients.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct SyncedTodo {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub todo: Todo,
+    pub updated_at: NaiveDateTime,
+}
+
+
+/// The changes since a cursor.
+///
+/// # Fields
+/// * users - The users changed since the cursor, oldest change first.
+/// * todos - The to-do items changed since the cursor, oldest change first.
+/// * deletions - The tombstones written since the cursor, oldest first.
+/// * next_cursor - The cursor to pass on the next sync.
+/// * has_more - Whether a feed was cut short, in which case the client should sync again straight away.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SyncChanges {
+    pub users: Vec<SyncedUser>,
+    pub todos: Vec<SyncedTodo>,
+    pub deletions: Vec<Tombstone>,
+    pub next_cursor: String,
+    pub has_more: bool,
+}
+
+
+/// The format of a version, the same as `updated_at` is serialised in.
+const VERSION_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+
+/// The `ETag` for a row changed at `updated_at`.
+pub fn version_tag(updated_at: &NaiveDateTime) -> String {
+    format!("\"{}\"", updated_at.format(VERSION_FORMAT))
+}
+
+
+/// Reads the version a client expects from its `If-Match` header.
+///
+/// # Arguments
+/// * `if_match` - The value of the header, quoted as in `version_tag` or bare as in `updated_at`.
+///
+/// # Returns
+/// * `Ok(None)` - If there is no header or it is `*`, in which case any version can be changed.
+/// * `Ok(Some(NaiveDateTime))` - The version the client expects.
+/// * `Err(NanoServiceError)` - `BadRequest` if the header is not a version.
+pub fn parse_if_match(if_match: Option<&str>) -> Result<Option<NaiveDateTime>, NanoServiceError> {
+    let tag = match if_match.map(str::trim) {
+        None | Some("*") => return Ok(None),
+        Some(tag) => tag,
+    };
+    let version = tag.strip_prefix('"').and_then(|tag| tag.strip_suffix('"')).unwrap_or(tag);
+    NaiveDateTime::parse_from_str(version, VERSION_FORMAT)
+        .map(Some)
+        .map_err(|_| NanoServiceError::new(
+            format!("Invalid If-Match version: {}", tag),
+            NanoServiceErrorStatus::BadRequest
+        ))
+}
+
+
+/// The error returned when an update was made against an old version.
+///
+/// # Arguments
+/// * `current` - The current state of the row, including its `updated_at`.
+/// * `requested` - The change the client asked for, with only the fields it set.
+///
+/// # Returns
+/// A `Conflict` error whose details hold the `current` state and, under `conflicts`, each field of
+/// the change that no longer matches the server as `{"requested": .., "current": ..}`.
+pub fn version_conflict<C: Serialize, R: Serialize>(current: &C, requested: &R) -> NanoServiceError {
+    let current = serde_json::to_value(current).unwrap_or_default();
+    let requested = serde_json::to_value(requested).unwrap_or_default();
+    let conflicts: Map<String, Value> = requested.as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(field, value)| {
+            let current_value = current.get(field).cloned().unwrap_or(Value::Null);
+            (current_value != *value).then(|| {
+                (field.clone(), json!({"requested": value, "current": current_value}))
+            })
+        })
+        .collect();
+    NanoServiceError::new(
+        "The item has been changed since the version given in If-Match".to_string(),
+        NanoServiceErrorStatus::Conflict
+    ).with_details(json!({"current": current, "conflicts": conflicts}))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = SyncCursor {
+            users: SyncPosition {
+                updated_at: DateTime::from_timestamp(1_735_722_000, 0).unwrap().naive_utc(),
+                id: 4,
+            },
+            todos: SyncPosition::default(),
+            after_deletion_id: 12,
+        };
+        assert_eq!(SyncCursor::decode(&cursor.encode()).unwrap(), cursor);
+    }
+
+    #[test]
+    fn test_invalid_cursor() {
+        for cursor in ["not a cursor", "e30", ""] {
+            let error = SyncCursor::decode(cursor).unwrap_err();
+            assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        }
+    }
+
+    #[test]
+    fn test_version_round_trip() {
+        let updated_at = DateTime::from_timestamp(1_735_722_000, 123_456_000).unwrap().naive_utc();
+        let tag = version_tag(&updated_at);
+        assert_eq!(tag, "\"2025-01-01T09:00:00.123456\"");
+        assert_eq!(parse_if_match(Some(&tag)).unwrap(), Some(updated_at));
+        // the bare `updated_at` from the sync feed is accepted too
+        let bare = serde_json::to_value(updated_at).unwrap();
+        assert_eq!(parse_if_match(bare.as_str()).unwrap(), Some(updated_at));
+    }
+
+    #[test]
+    fn test_parse_if_match() {
+        assert_eq!(parse_if_match(None).unwrap(), None);
+        assert_eq!(parse_if_match(Some("*")).unwrap(), None);
+        let error = parse_if_match(Some("\"yesterday\"")).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[test]
+    fn test_version_conflict() {
+        let current = json!({"id": 1, "name": "Server name", "description": null, "updated_at": "2025-01-01T09:00:00"});
+        let requested = json!({"name": "Client name", "description": null});
+        let error = version_conflict(&current, &requested);
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        assert_eq!(error.details.unwrap(), json!({
+            "current": current,
+            "conflicts": {"name": {"requested": "Client name", "current": "Server name"}}
+        }));
+    }
+}
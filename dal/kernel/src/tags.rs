@@ -0,0 +1,42 @@
+//! Defines the structs for tagging to-do items with labels.
+//!
+//! ## Purpose
+//! - Tags are shared labels such as `urgent` or `billing` that any to-do item can carry.
+//! - The `todo_tags` table links a to-do item to each tag attached to it.
+use serde::{Serialize, Deserialize};
+
+
+/// Represents the schema for a new tag.
+///
+/// # Fields
+/// * name - The unique name of the tag.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewTag {
+    pub name: String,
+}
+
+
+/// Represents a tag stored in the system.
+///
+/// # Fields
+/// * id - The unique identifier for the tag.
+/// * name - The unique name of the tag.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+}
+
+
+/// Represents a tag attached to a to-do item.
+///
+/// # Fields
+/// * id - The unique identifier for the link.
+/// * todo_id - The ID of the tagged to-do item.
+/// * tag_id - The ID of the attached tag.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct TodoTag {
+    pub id: i32,
+    pub todo_id: i32,
+    pub tag_id: i32,
+}
@@ -0,0 +1,70 @@
+//! Holds the organization a request is acting in, for the DAL to scope its queries to.
+//!
+//! ## Purpose
+//! - `api_endpoint` runs every handler taking a token inside `with_tenant` with the token's
+//!   `org_id`, so the to-do items of another organization cannot be read or changed from it even if
+//!   the handler forgets to filter by organization.
+//! - Work that is not done for a request on behalf of one organization, such as background jobs and
+//!   the inbound email webhook, has to run inside `without_tenant` to reach every organization.
+//! - The scoped transactions fail closed: `current_tenant` errors outside of both, so a new caller
+//!   that forgets to pick one gets an error rather than every organization's rows.
+//!
+//! ## Notes
+//! Users are shared between organizations through their memberships, so only the rows that belong
+//! to one organization, the to-do items and the rows hanging off them, are scoped.
+use std::future::Future;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+tokio::task_local! {
+    static TENANT: Option<i32>;
+}
+
+
+/// Runs a future acting in an organization.
+///
+/// # Arguments
+/// * `org_id` - The ID of the organization.
+/// * `future` - The work to do, `current_tenant` returns `org_id` while it runs.
+pub async fn with_tenant<F: Future>(org_id: i32, future: F) -> F::Output {
+    TENANT.scope(Some(org_id), future).await
+}
+
+
+/// Runs a future that deliberately reaches the rows of every organization.
+///
+/// # Arguments
+/// * `future` - The work to do, `current_tenant` returns `None` while it runs.
+pub async fn without_tenant<F: Future>(future: F) -> F::Output {
+    TENANT.scope(None, future).await
+}
+
+
+/// The ID of the organization the current task is acting in.
+///
+/// # Returns
+/// * `Ok(Some(i32))` - Inside `with_tenant`, the organization to scope queries to.
+/// * `Ok(None)` - Inside `without_tenant`, where queries are not scoped.
+/// * `Err(NanoServiceError)` - Outside of both, so the caller refuses to run the query.
+pub fn current_tenant() -> Result<Option<i32>, NanoServiceError> {
+    TENANT.try_with(|org_id| *org_id).map_err(|_| NanoServiceError::new(
+        "No tenant is set for a query scoped to an organization".to_string(),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_current_tenant() {
+        assert_eq!(current_tenant().unwrap_err().status, NanoServiceErrorStatus::Unknown);
+        assert_eq!(with_tenant(3, async { current_tenant() }).await.unwrap(), Some(3));
+        assert_eq!(with_tenant(3, with_tenant(4, async { current_tenant() })).await.unwrap(), Some(4));
+        assert_eq!(without_tenant(async { current_tenant() }).await.unwrap(), None);
+        assert_eq!(with_tenant(3, without_tenant(async { current_tenant() })).await.unwrap(), None);
+        assert!(current_tenant().is_err());
+    }
+}
@@ -3,8 +3,140 @@
 //! # Purpose
 //! - Enable database interactions through `Todo` and `NewTodo` structs.
 //! - Support service-level operations and data transfers related to to-do tasks.
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, Deserializer};
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
 use chrono::NaiveDateTime;
+use std::error::Error;
+use std::str::FromStr;
+use validator::Validate;
+use utils::export_stream::{CsvRow, csv_field};
+use utils::locale::iso_datetime;
+use crate::users::validate_name;
+
+
+/// How urgent a to-do item is, used to pick its SLA.
+///
+/// # Variants
+/// * `Low` - The item can wait.
+/// * `Medium` - The default for new items.
+/// * `High` - The item needs doing first.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoPriority {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl TodoPriority {
+
+    /// The value stored in the `priority` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoPriority::Low => "low",
+            TodoPriority::Medium => "medium",
+            TodoPriority::High => "high",
+        }
+    }
+}
+
+impl FromStr for TodoPriority {
+    type Err = String;
+    fn from_str(priority: &str) -> Result<Self, Self::Err> {
+        match priority.trim() {
+            "low" => Ok(TodoPriority::Low),
+            "medium" => Ok(TodoPriority::Medium),
+            "high" => Ok(TodoPriority::High),
+            _ => Err(format!("Invalid to-do priority: {}", priority)),
+        }
+    }
+}
+
+impl Type<Postgres> for TodoPriority {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for TodoPriority {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for TodoPriority {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        TodoPriority::from_str(s).map_err(|e| e.into())
+    }
+}
+
+/// Where a to-do item is in its workflow, moved between statuses as allowed by
+/// `crate::todo_workflow::StatusTransitions`.
+///
+/// # Variants
+/// * `Backlog` - The default for new items, not started yet.
+/// * `InProgress` - The assignee is working on the item.
+/// * `Blocked` - The item cannot go on until something else is done.
+/// * `Done` - The item is finished, an item is in `Done` exactly when `finished` is set.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    #[default]
+    Backlog,
+    InProgress,
+    Blocked,
+    Done,
+}
+
+impl TodoStatus {
+
+    /// Every status in workflow order.
+    pub const ALL: [TodoStatus; 4] = [TodoStatus::Backlog, TodoStatus::InProgress, TodoStatus::Blocked, TodoStatus::Done];
+
+    /// The value stored in the `status` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoStatus::Backlog => "backlog",
+            TodoStatus::InProgress => "in_progress",
+            TodoStatus::Blocked => "blocked",
+            TodoStatus::Done => "done",
+        }
+    }
+}
+
+impl FromStr for TodoStatus {
+    type Err = String;
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.trim() {
+            "backlog" => Ok(TodoStatus::Backlog),
+            "in_progress" => Ok(TodoStatus::InProgress),
+            "blocked" => Ok(TodoStatus::Blocked),
+            "done" => Ok(TodoStatus::Done),
+            _ => Err(format!("Invalid to-do status: {}", status)),
+        }
+    }
+}
+
+impl Type<Postgres> for TodoStatus {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for TodoStatus {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for TodoStatus {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        TodoStatus::from_str(s).map_err(|e| e.into())
+    }
+}
 
 /// Represents the schema for creating a new to-do item.
 ///
@@ -15,14 +147,29 @@ use chrono::NaiveDateTime;
 /// * `assigned_to`: The ID of the user to whom the task is assigned.
 /// * `description`: A detailed description of the task.
 /// * `date_assigned`: The timestamp of when the task was assigned (optional).
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// * `requires_review`: Whether the assigner has to approve the task before it is finished, defaults to false.
+/// * `priority`: How urgent the task is, defaults to medium.
+///
+/// # Validation
+/// * `name` - 1 to 255 characters with no control characters.
+/// * `description` - At most 5000 characters.
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
 pub struct NewTodo {
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        custom(function = "validate_name")
+    )]
     pub name: String,
     pub due_date: Option<NaiveDateTime>,
     pub assigned_by: i32,
     pub assigned_to: i32,
+    #[validate(length(max = 5000, message = "must be at most 5000 characters"))]
     pub description: Option<String>,
     pub date_assigned: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub requires_review: bool,
+    #[serde(default)]
+    pub priority: TodoPriority,
 }
 
 /// Represents a to-do item retrieved from the database.
@@ -37,6 +184,11 @@ pub struct NewTodo {
 /// * `date_assigned`: The timestamp of when the task was assigned.
 /// * `date_finished`: The timestamp of when the task was finished (optional).
 /// * `finished`: Whether the task is marked as finished.
+/// * `requires_review`: Whether the assigner has to approve the task before it is finished.
+/// * `pending_review`: Whether the task has been completed and is waiting for the assigner to review it.
+/// * `review_comment`: The comment left by the assigner when they last rejected the task (optional).
+/// * `priority`: How urgent the task is.
+/// * `status`: Where the task is in its workflow.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
 pub struct Todo {
     pub id: i32,
@@ -48,6 +200,174 @@ pub struct Todo {
     pub date_assigned: NaiveDateTime,
     pub date_finished: Option<NaiveDateTime>,
     pub finished: bool,
+    pub requires_review: bool,
+    pub pending_review: bool,
+    pub review_comment: Option<String>,
+    pub priority: TodoPriority,
+    #[serde(default)]
+    pub status: TodoStatus,
+}
+
+impl CsvRow for Todo {
+    const CSV_HEADER: &'static str = "id,name,due_date,assigned_by,assigned_to,description,date_assigned,date_finished,finished,requires_review,pending_review,review_comment,priority,status";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.id,
+            csv_field(&self.name),
+            self.due_date.map(iso_datetime).unwrap_or_default(),
+            self.assigned_by,
+            self.assigned_to,
+            csv_field(self.description.as_deref().unwrap_or_default()),
+            iso_datetime(self.date_assigned),
+            self.date_finished.map(iso_datetime).unwrap_or_default(),
+            self.finished,
+            self.requires_review,
+            self.pending_review,
+            csv_field(self.review_comment.as_deref().unwrap_or_default()),
+            self.priority.as_str(),
+            self.status.as_str()
+        )
+    }
+}
+
+/// A to-do item as written to the to-do export, with its assignee by email so the export can be
+/// imported again into another system.
+///
+/// # Fields
+/// * `assignee_email`: The email address of the user the task is assigned to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ExportedTodo {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub due_date: Option<NaiveDateTime>,
+    pub assignee_email: String,
+    pub priority: TodoPriority,
+    pub finished: bool,
+    pub date_assigned: NaiveDateTime,
+    pub date_finished: Option<NaiveDateTime>,
+}
+
+impl CsvRow for ExportedTodo {
+    const CSV_HEADER: &'static str = "id,name,description,due_date,assignee_email,priority,finished,date_assigned,date_finished";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            self.id,
+            csv_field(&self.name),
+            csv_field(self.description.as_deref().unwrap_or_default()),
+            self.due_date.map(iso_datetime).unwrap_or_default(),
+            csv_field(&self.assignee_email),
+            self.priority.as_str(),
+            self.finished,
+            iso_datetime(self.date_assigned),
+            self.date_finished.map(iso_datetime).unwrap_or_default()
+        )
+    }
+}
+
+/// The `Todo` fields a client can pick with `?fields=`.
+pub const TODO_FIELDS: [&str; 14] = [
+    "id", "name", "due_date", "assigned_by", "assigned_to", "description", "date_assigned",
+    "date_finished", "finished", "requires_review", "pending_review", "review_comment", "priority",
+    "status",
+];
+
+/// Deserializes a field that is present, so `null` becomes `Some(None)` while a missing field
+/// is left as `None` by `#[serde(default)]`.
+pub(crate) fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// The changes to make to a to-do item, fields that are left out are not changed.
+///
+/// # Fields
+/// * `name`: The new name of the task.
+/// * `description`: The new description, `Some(None)` clears it.
+/// * `due_date`: The new due date, `Some(None)` clears it.
+/// * `priority`: The new priority.
+///
+/// # Validation
+/// * `name` - 1 to 255 characters with no control characters.
+/// * `description` - At most 5000 characters.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, Validate)]
+pub struct ToDoItemPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        custom(function = "validate_name")
+    )]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    #[validate(length(max = 5000, message = "must be at most 5000 characters"))]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<Option<NaiveDateTime>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<TodoPriority>,
+}
+
+impl ToDoItemPatch {
+
+    /// Whether the patch does not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.description.is_none() && self.due_date.is_none() && self.priority.is_none()
+    }
+}
+
+
+/// The field to-do search results are ordered by.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ToDoSortField {
+    DueDate,
+    #[default]
+    DateAssigned,
+    Name,
+    Priority,
+}
+
+/// The direction to-do search results are ordered in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// The parameters of a search over the to-do items assigned to or by a user.
+///
+/// # Fields
+/// * `q`: Text to find in the name or description, case insensitive.
+/// * `finished`: Only finished or only open items.
+/// * `assigned_by`: Only items assigned by this user.
+/// * `due_before`: Only items due before this time.
+/// * `sort`: The field to order by, the date assigned by default.
+/// * `order`: The direction to order in, newest first by default.
+/// * `limit`: The most items to return.
+///
+/// # Validation
+/// * `q` - 1 to 255 characters.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, Validate)]
+pub struct ToDoSearch {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub q: Option<String>,
+    pub finished: Option<bool>,
+    pub assigned_by: Option<i32>,
+    pub due_before: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub sort: ToDoSortField,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub limit: Option<i64>,
 }
 
 #[cfg(test)]
@@ -72,6 +392,8 @@ mod tests {
             assigned_to,
             description: description.clone(),
             date_assigned,
+            requires_review: false,
+            priority: TodoPriority::Medium,
         };
 
         assert_eq!(new_todo.name, name);
@@ -80,6 +402,27 @@ mod tests {
         assert_eq!(new_todo.description, description);
     }
 
+    #[test]
+    fn test_new_todo_validation() {
+        let mut new_todo = NewTodo {
+            name: "Test Task".to_string(),
+            due_date: None,
+            assigned_by: 1,
+            assigned_to: 2,
+            description: None,
+            date_assigned: None,
+            requires_review: false,
+            priority: TodoPriority::Medium,
+        };
+        assert!(new_todo.validate().is_ok());
+
+        new_todo.name = String::new();
+        new_todo.description = Some("a".repeat(5001));
+        let errors = new_todo.validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("name"));
+        assert!(errors.field_errors().contains_key("description"));
+    }
+
     /// Tests creating a `Todo` instance and verifying its contents.
     #[test]
     fn test_todo_struct() {
@@ -94,10 +437,83 @@ mod tests {
             date_assigned: now,
             date_finished: None,
             finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::High,
+            status: TodoStatus::Backlog,
         };
 
         assert_eq!(todo.id, 1);
-        assert_eq!(todo.finished, false);
+        assert!(!todo.finished);
         assert_eq!(todo.name, "Task 1");
     }
+
+    #[test]
+    fn test_todo_priority_round_trip() {
+        for priority in [TodoPriority::Low, TodoPriority::Medium, TodoPriority::High] {
+            assert_eq!(TodoPriority::from_str(priority.as_str()).unwrap(), priority);
+        }
+        assert!(TodoPriority::from_str("urgent").is_err());
+    }
+
+    #[test]
+    fn test_todo_status_round_trip() {
+        for status in TodoStatus::ALL {
+            assert_eq!(TodoStatus::from_str(status.as_str()).unwrap(), status);
+            assert_eq!(serde_json::to_value(status).unwrap(), status.as_str());
+        }
+        assert!(TodoStatus::from_str("cancelled").is_err());
+    }
+
+    #[test]
+    fn test_new_todo_priority_defaults_to_medium() {
+        let new_todo: NewTodo = serde_json::from_str(
+            r#"{"name": "Task", "due_date": null, "assigned_by": 1, "assigned_to": 2, "description": null, "date_assigned": null}"#
+        ).unwrap();
+        assert_eq!(new_todo.priority, TodoPriority::Medium);
+    }
+
+    #[test]
+    fn test_to_do_item_patch_tells_null_from_missing() {
+        let patch: ToDoItemPatch = serde_json::from_str(r#"{"description": null}"#).unwrap();
+        assert_eq!(patch.name, None);
+        assert_eq!(patch.description, Some(None));
+        assert_eq!(patch.due_date, None);
+        assert!(!patch.is_empty());
+
+        assert_eq!(serde_json::to_value(&patch).unwrap(), serde_json::json!({"description": null}));
+
+        let patch: ToDoItemPatch = serde_json::from_str(r#"{"priority": "high"}"#).unwrap();
+        assert_eq!(patch.priority, Some(TodoPriority::High));
+        assert!(!patch.is_empty());
+
+        let patch: ToDoItemPatch = serde_json::from_str("{}").unwrap();
+        assert!(patch.is_empty());
+    }
+
+    #[test]
+    fn test_to_do_item_patch_validation() {
+        let patch = ToDoItemPatch {
+            name: Some(String::new()),
+            description: Some(Some("a".repeat(5001))),
+            due_date: None,
+            priority: None,
+        };
+        let errors = patch.validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("name"));
+        assert!(errors.field_errors().contains_key("description"));
+        assert!(ToDoItemPatch { description: Some(None), ..Default::default() }.validate().is_ok());
+    }
+
+    #[test]
+    fn test_to_do_search_defaults() {
+        let search: ToDoSearch = serde_json::from_str(r#"{"q": "report", "sort": "due_date"}"#).unwrap();
+        assert_eq!(search.sort, ToDoSortField::DueDate);
+        assert_eq!(search.order, SortOrder::Desc);
+        assert!(search.validate().is_ok());
+
+        let search = ToDoSearch { q: Some("a".repeat(256)), ..Default::default() };
+        assert!(search.validate().unwrap_err().field_errors().contains_key("q"));
+    }
 }
@@ -0,0 +1,43 @@
+//! Defines the structs for commenting on to-do items.
+//!
+//! ## Purpose
+//! - The assigner and assignee of a to-do item can discuss it through comments.
+//! - A comment that arrived by replying to an email keeps the email's message ID.
+use serde::{Serialize, Deserialize};
+use chrono::NaiveDateTime;
+
+
+/// Represents the schema for a new comment.
+///
+/// # Fields
+/// * todo_id - The ID of the to-do item commented on.
+/// * author_id - The ID of the user who wrote the comment.
+/// * body - The text of the comment.
+/// * message_id - The message ID of the email the comment arrived in, if it arrived by email.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewToDoComment {
+    pub todo_id: i32,
+    pub author_id: i32,
+    pub body: String,
+    pub message_id: Option<String>,
+}
+
+
+/// Represents a comment stored in the system.
+///
+/// # Fields
+/// * id - The unique identifier for the comment.
+/// * todo_id - The ID of the to-do item commented on.
+/// * author_id - The ID of the user who wrote the comment.
+/// * body - The text of the comment.
+/// * message_id - The message ID of the email the comment arrived in, if it arrived by email.
+/// * date_created - When the comment was added.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ToDoComment {
+    pub id: i32,
+    pub todo_id: i32,
+    pub author_id: i32,
+    pub body: String,
+    pub message_id: Option<String>,
+    pub date_created: NaiveDateTime,
+}
@@ -0,0 +1,251 @@
+//! Defines the structs for the history of changes made to to-do items.
+//!
+//! ## Purpose
+//! - Every change to the state of a to-do item is recorded by the core layer with the user who made
+//!   it, so the users of an item can see who changed what and when.
+//! - The history of an item can be seen by its assigner, its assignee and admins, and is removed
+//!   with the item.
+use std::error::Error;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+use sqlx::{Decode, Encode, Postgres, Type};
+use sqlx::postgres::PgTypeInfo;
+use sqlx::types::Json;
+use chrono::NaiveDateTime;
+use crate::to_do_items::{Todo, TodoStatus};
+use crate::users::UserRole;
+
+
+/// The kinds of change recorded for a to-do item.
+///
+/// # Variants
+/// * `Created` - The item was created.
+/// * `Reassigned` - The item was assigned to another user.
+/// * `Completed` - The assignee finished the item, or asked for it to be reviewed.
+/// * `Approved` - The assigner approved the item after review.
+/// * `Rejected` - The assigner sent the item back after review.
+/// * `Edited` - The name, description, due date or priority of the item was changed.
+/// * `StatusChanged` - The item was moved to another status in its workflow.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoEventKind {
+    Created,
+    Reassigned,
+    Completed,
+    Approved,
+    Rejected,
+    Edited,
+    StatusChanged,
+}
+
+impl TodoEventKind {
+
+    /// The value stored in the `kind` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoEventKind::Created => "created",
+            TodoEventKind::Reassigned => "reassigned",
+            TodoEventKind::Completed => "completed",
+            TodoEventKind::Approved => "approved",
+            TodoEventKind::Rejected => "rejected",
+            TodoEventKind::Edited => "edited",
+            TodoEventKind::StatusChanged => "status_changed",
+        }
+    }
+}
+
+impl FromStr for TodoEventKind {
+    type Err = String;
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "created" => Ok(TodoEventKind::Created),
+            "reassigned" => Ok(TodoEventKind::Reassigned),
+            "completed" => Ok(TodoEventKind::Completed),
+            "approved" => Ok(TodoEventKind::Approved),
+            "rejected" => Ok(TodoEventKind::Rejected),
+            "edited" => Ok(TodoEventKind::Edited),
+            "status_changed" => Ok(TodoEventKind::StatusChanged),
+            _ => Err(format!("Invalid to-do event kind: {}", kind)),
+        }
+    }
+}
+
+impl Type<Postgres> for TodoEventKind {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for TodoEventKind {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for TodoEventKind {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        TodoEventKind::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// Represents the schema for a change to record in the history of a to-do item.
+///
+/// # Fields
+/// * todo_id - The ID of the to-do item that changed.
+/// * actor_id - The ID of the user who made the change.
+/// * kind - What kind of change it was.
+/// * details - What changed, depending on the kind.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewTodoEvent {
+    pub todo_id: i32,
+    pub actor_id: i32,
+    pub kind: TodoEventKind,
+    pub details: Value,
+}
+
+impl NewTodoEvent {
+
+    /// The item was created, with who it was assigned to.
+    pub fn created(todo: &Todo, actor_id: i32) -> Self {
+        NewTodoEvent {
+            todo_id: todo.id,
+            actor_id,
+            kind: TodoEventKind::Created,
+            details: json!({"name": todo.name, "assigned_to": todo.assigned_to}),
+        }
+    }
+
+    /// The item was assigned to `previous_assignee` and is now assigned to `todo.assigned_to`.
+    pub fn reassigned(todo: &Todo, previous_assignee: i32, actor_id: i32) -> Self {
+        NewTodoEvent {
+            todo_id: todo.id,
+            actor_id,
+            kind: TodoEventKind::Reassigned,
+            details: json!({"from": previous_assignee, "to": todo.assigned_to}),
+        }
+    }
+
+    /// The item was finished, or is waiting for the assigner's review.
+    pub fn completed(todo: &Todo, actor_id: i32) -> Self {
+        NewTodoEvent {
+            todo_id: todo.id,
+            actor_id,
+            kind: TodoEventKind::Completed,
+            details: json!({"pending_review": todo.pending_review}),
+        }
+    }
+
+    /// The assigner approved the item.
+    pub fn approved(todo: &Todo, actor_id: i32) -> Self {
+        NewTodoEvent { todo_id: todo.id, actor_id, kind: TodoEventKind::Approved, details: json!({}) }
+    }
+
+    /// The assigner sent the item back with `comment`.
+    pub fn rejected(todo: &Todo, actor_id: i32, comment: &str) -> Self {
+        NewTodoEvent {
+            todo_id: todo.id,
+            actor_id,
+            kind: TodoEventKind::Rejected,
+            details: json!({"comment": comment}),
+        }
+    }
+
+    /// The item was moved from the status `from` to `todo.status`.
+    pub fn status_changed(todo: &Todo, from: TodoStatus, actor_id: i32) -> Self {
+        NewTodoEvent {
+            todo_id: todo.id,
+            actor_id,
+            kind: TodoEventKind::StatusChanged,
+            details: json!({"from": from, "to": todo.status}),
+        }
+    }
+
+    /// The item was edited from `before` with `change`, recording each field that changed as
+    /// `{"from": .., "to": ..}`, or `None` if the change left every field as it was.
+    pub fn edited<B: Serialize, C: Serialize>(todo_id: i32, actor_id: i32, before: &B, change: &C) -> Option<Self> {
+        let before = serde_json::to_value(before).unwrap_or_default();
+        let change = serde_json::to_value(change).unwrap_or_default();
+        let changes: Map<String, Value> = change.as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(field, to)| {
+                let from = before.get(field).cloned().unwrap_or(Value::Null);
+                (from != *to).then(|| (field.clone(), json!({"from": from, "to": to})))
+            })
+            .collect();
+        (!changes.is_empty()).then_some(NewTodoEvent {
+            todo_id,
+            actor_id,
+            kind: TodoEventKind::Edited,
+            details: Value::Object(changes),
+        })
+    }
+}
+
+
+/// Represents a change in the history of a to-do item.
+///
+/// # Fields
+/// * id - The unique identifier for the change.
+/// * todo_id - The ID of the to-do item that changed.
+/// * actor_id - The ID of the user who made the change, `None` if they have since been deleted.
+/// * kind - What kind of change it was.
+/// * details - What changed, depending on the kind.
+/// * date_created - When the change was made.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct TodoEvent {
+    pub id: i32,
+    pub todo_id: i32,
+    pub actor_id: Option<i32>,
+    pub kind: TodoEventKind,
+    pub details: Json<Value>,
+    pub date_created: NaiveDateTime,
+}
+
+
+/// Whether a user can see the history of a to-do item, only its assigner, its assignee and admins can.
+pub fn can_view_history(todo: &Todo, user_id: i32, role: &UserRole) -> bool {
+    todo.assigned_by == user_id
+        || todo.assigned_to == user_id
+        || matches!(role, UserRole::SuperAdmin | UserRole::Admin)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::to_do_items::ToDoItemPatch;
+
+    #[test]
+    fn test_kind_round_trip() {
+        for kind in [
+            TodoEventKind::Created, TodoEventKind::Reassigned, TodoEventKind::Completed,
+            TodoEventKind::Approved, TodoEventKind::Rejected, TodoEventKind::Edited,
+            TodoEventKind::StatusChanged,
+        ] {
+            assert_eq!(TodoEventKind::from_str(kind.as_str()).unwrap(), kind);
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        }
+        assert!(TodoEventKind::from_str("deleted").is_err());
+    }
+
+    #[test]
+    fn test_edited_records_only_changed_fields() {
+        let before = json!({"name": "Write report", "description": "Old", "due_date": null});
+        let patch = ToDoItemPatch {
+            name: Some("Write report".to_string()),
+            description: Some(None),
+            due_date: None,
+            priority: None,
+        };
+        let event = NewTodoEvent::edited(4, 2, &before, &patch).unwrap();
+        assert_eq!(event.kind, TodoEventKind::Edited);
+        assert_eq!(event.details, json!({"description": {"from": "Old", "to": null}}));
+
+        let unchanged = ToDoItemPatch { name: Some("Write report".to_string()), ..Default::default() };
+        assert_eq!(NewTodoEvent::edited(4, 2, &before, &unchanged), None);
+    }
+}
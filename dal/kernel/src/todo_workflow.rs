@@ -0,0 +1,169 @@
+//! Defines which moves between the statuses of a to-do item are allowed.
+//!
+//! ## Purpose
+//! - A to-do item moves between `backlog`, `in_progress`, `blocked` and `done`. The moves allowed are
+//!   read from `TODO_STATUS_TRANSITIONS` so each install can shape its own workflow.
+//! - Moving an item to `done` finishes it and moving it out of `done` reopens it, so `finished`
+//!   always matches the status.
+//!
+//! # Variables
+//! * `TODO_STATUS_TRANSITIONS` - Comma separated `from>to` pairs such as `backlog>in_progress,in_progress>done`,
+//!   unset or invalid uses `DEFAULT_STATUS_TRANSITIONS`
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+use std::str::FromStr;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::to_do_items::TodoStatus;
+
+
+/// The moves allowed when `TODO_STATUS_TRANSITIONS` is not set.
+pub const DEFAULT_STATUS_TRANSITIONS: [(TodoStatus, TodoStatus); 9] = [
+    (TodoStatus::Backlog, TodoStatus::InProgress),
+    (TodoStatus::Backlog, TodoStatus::Blocked),
+    (TodoStatus::Backlog, TodoStatus::Done),
+    (TodoStatus::InProgress, TodoStatus::Backlog),
+    (TodoStatus::InProgress, TodoStatus::Blocked),
+    (TodoStatus::InProgress, TodoStatus::Done),
+    (TodoStatus::Blocked, TodoStatus::Backlog),
+    (TodoStatus::Blocked, TodoStatus::InProgress),
+    (TodoStatus::Done, TodoStatus::InProgress),
+];
+
+
+/// The body of a request to move a to-do item to another status.
+///
+/// # Fields
+/// * `status` - The status to move the item to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusChange {
+    pub status: TodoStatus,
+}
+
+
+/// The statuses an item can be moved to from one status.
+///
+/// # Fields
+/// * `status` - The status the item is in.
+/// * `next` - The statuses it can be moved to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusOptions {
+    pub status: TodoStatus,
+    pub next: Vec<TodoStatus>,
+}
+
+
+/// The moves allowed between statuses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusTransitions(Vec<(TodoStatus, TodoStatus)>);
+
+impl Default for StatusTransitions {
+    fn default() -> Self {
+        StatusTransitions(DEFAULT_STATUS_TRANSITIONS.to_vec())
+    }
+}
+
+impl FromStr for StatusTransitions {
+    type Err = String;
+
+    /// Parses comma separated `from>to` pairs, refusing a pair that does not change the status.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut transitions = Vec::new();
+        for pair in value.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (from, to) = pair.split_once('>')
+                .ok_or_else(|| format!("Invalid status transition: {}", pair))?;
+            let (from, to) = (TodoStatus::from_str(from)?, TodoStatus::from_str(to)?);
+            if from == to {
+                return Err(format!("Invalid status transition: {}", pair))
+            }
+            if !transitions.contains(&(from, to)) {
+                transitions.push((from, to));
+            }
+        }
+        if transitions.is_empty() {
+            return Err("No status transitions given".to_string())
+        }
+        Ok(StatusTransitions(transitions))
+    }
+}
+
+impl StatusTransitions {
+
+    /// Whether an item can be moved from `from` to `to`.
+    pub fn allows(&self, from: TodoStatus, to: TodoStatus) -> bool {
+        self.0.contains(&(from, to))
+    }
+
+    /// The statuses an item in `from` can be moved to.
+    pub fn next(&self, from: TodoStatus) -> Vec<TodoStatus> {
+        self.0.iter().filter(|(status, _)| *status == from).map(|(_, to)| *to).collect()
+    }
+
+    /// The statuses each status can be moved to, in workflow order.
+    pub fn options(&self) -> Vec<StatusOptions> {
+        TodoStatus::ALL.into_iter().map(|status| StatusOptions { status, next: self.next(status) }).collect()
+    }
+
+    /// Refuses a move that is not allowed.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the move is allowed
+    /// * `Err(NanoServiceError)` - `Conflict` with the statuses the item can be moved to instead
+    pub fn check(&self, from: TodoStatus, to: TodoStatus) -> Result<(), NanoServiceError> {
+        if self.allows(from, to) {
+            return Ok(())
+        }
+        Err(NanoServiceError::new(
+            format!("A to-do item cannot be moved from {} to {}", from.as_str(), to.as_str()),
+            NanoServiceErrorStatus::Conflict
+        ).with_details(json!({"from": from, "to": to, "allowed": self.next(from)})))
+    }
+}
+
+
+/// Reads the allowed moves from `TODO_STATUS_TRANSITIONS`, falling back to `DEFAULT_STATUS_TRANSITIONS`.
+pub fn status_transitions<Y: GetConfigVariable>() -> StatusTransitions {
+    Y::get_config_variable("TODO_STATUS_TRANSITIONS".to_string())
+        .ok()
+        .and_then(|value| StatusTransitions::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "TODO_STATUS_TRANSITIONS" => Ok(" backlog>in_progress, in_progress>done ,".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    #[test]
+    fn test_status_transitions_from_config() {
+        let transitions = status_transitions::<MockConfig>();
+        assert!(transitions.allows(TodoStatus::Backlog, TodoStatus::InProgress));
+        assert!(!transitions.allows(TodoStatus::Backlog, TodoStatus::Done));
+        assert_eq!(transitions.next(TodoStatus::Done), vec![]);
+
+        for invalid in ["", "backlog", "backlog>backlog", "backlog>cancelled"] {
+            assert!(StatusTransitions::from_str(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_check_lists_the_allowed_moves() {
+        let transitions = StatusTransitions::default();
+        assert!(transitions.check(TodoStatus::Done, TodoStatus::InProgress).is_ok());
+        let error = transitions.check(TodoStatus::Blocked, TodoStatus::Done).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        assert_eq!(error.details.unwrap()["allowed"], json!(["backlog", "in_progress"]));
+        assert_eq!(transitions.options()[3], StatusOptions { status: TodoStatus::Done, next: vec![TodoStatus::InProgress] });
+    }
+}
@@ -3,8 +3,11 @@
 //! # Notes
 //! The `$match_expr:pat` is used as opposed to `$match_expr:expr` to allow for the use of the `|` operator.
 //! The `$(,)?` is used to allow for the optional trailing comma in the macro.
+//! Role checks run when the token is extracted. Permission checks need the user's effective
+//! permissions so `check_permissions` is run by `api_endpoint` once the session has been loaded.
 use crate::users::UserRole;
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use std::marker::PhantomData;
 
 
 macro_rules! construct_checks {
@@ -16,10 +19,10 @@ macro_rules! construct_checks {
                 fn check_user_role(role: &UserRole) -> Result<(), NanoServiceError> {
                     match role {
                         $match_expr => Ok(()),
-                        _ => Err(NanoServiceError {
-                            status: NanoServiceErrorStatus::Unauthorized,
-                            message: "Role does not have sufficient permissions".to_string()
-                        })
+                        _ => Err(NanoServiceError::new(
+                            "Role does not have sufficient permissions".to_string(),
+                            NanoServiceErrorStatus::Unauthorized
+                        ).with_message_key("auth.insufficient_role", vec![]))
                     }
                 }
             }
@@ -30,6 +33,11 @@ macro_rules! construct_checks {
 
 pub trait CheckUserRole {
     fn check_user_role(role: &UserRole) -> Result<(), NanoServiceError>;
+
+    /// Checks the effective permissions stored in the user's session. Role checks pass by default.
+    fn check_permissions(_permissions: &[String]) -> Result<(), NanoServiceError> {
+        Ok(())
+    }
 }
 
 construct_checks!(
@@ -41,3 +49,82 @@ construct_checks!(
     ExactAdminRoleCheck => UserRole::Admin,
     ExactWorkerRoleCheck => UserRole::Worker
 );
+
+
+macro_rules! construct_permissions {
+    ($( $struct:ident => $name:expr),* $(,)?) => {
+        $(
+            pub struct $struct;
+
+            impl RequiredPermission for $struct {
+                const NAME: &'static str = $name;
+            }
+        )*
+    };
+}
+
+
+/// A named permission that an endpoint can require with `PermissionCheck`.
+pub trait RequiredPermission {
+    const NAME: &'static str;
+}
+
+
+/// Requires the user's effective permissions to contain `T::NAME`, e.g.
+/// `#[api_endpoint(token=PermissionCheck<TodoAssignPermission>)]`.
+pub struct PermissionCheck<T: RequiredPermission> {
+    permission: PhantomData<T>
+}
+
+impl<T: RequiredPermission> CheckUserRole for PermissionCheck<T> {
+    fn check_user_role(role: &UserRole) -> Result<(), NanoServiceError> {
+        match role {
+            UserRole::SuperAdmin | UserRole::Admin | UserRole::Worker => Ok(()),
+            _ => Err(NanoServiceError::new(
+                "Role does not have sufficient permissions".to_string(),
+                NanoServiceErrorStatus::Unauthorized
+            ).with_message_key("auth.insufficient_role", vec![]))
+        }
+    }
+
+    fn check_permissions(permissions: &[String]) -> Result<(), NanoServiceError> {
+        if permissions.iter().any(|permission| permission == T::NAME) {
+            return Ok(())
+        }
+        Err(NanoServiceError::new(
+            format!("Missing required permission: {}", T::NAME),
+            NanoServiceErrorStatus::Forbidden
+        ).with_message_key("auth.missing_permission", vec![("permission", T::NAME.to_string())]))
+    }
+}
+
+construct_permissions!(
+    TodoCreatePermission => "todo:create",
+    TodoAssignPermission => "todo:assign",
+    TodoCompletePermission => "todo:complete",
+    UserBlockPermission => "user:block",
+    UserManageRolesPermission => "user:manage_roles"
+);
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_checks_ignore_permissions() {
+        assert!(AdminRoleCheck::check_permissions(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_permission_check() {
+        type Check = PermissionCheck<TodoAssignPermission>;
+        assert!(Check::check_user_role(&UserRole::Worker).is_ok());
+        assert!(Check::check_user_role(&UserRole::Unreachable).is_err());
+
+        assert!(Check::check_permissions(&["todo:assign".to_string()]).is_ok());
+        let error = Check::check_permissions(&["todo:complete".to_string()]).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        assert_eq!(error.message, "Missing required permission: todo:assign");
+    }
+}
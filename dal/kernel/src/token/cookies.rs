@@ -0,0 +1,281 @@
+//! Carries the token in cookies for browser sessions, with double submit CSRF protection.
+//!
+//! # Overview
+//! With `TOKEN_COOKIE_ENABLED` set, logging in and refreshing also set the token in an `HttpOnly`
+//! cookie the page's scripts cannot read, and a random CSRF token in a cookie they can read.
+//! `HeaderToken` reads the token from the cookie when the request has no `token` header. A request
+//! authenticated by the cookie that can change something, anything but `GET`, `HEAD` and `OPTIONS`,
+//! has to repeat the CSRF cookie in the `X-CSRF-Token` header. The browser sends the cookies with
+//! requests another site's page makes, but that page cannot read the CSRF cookie to repeat it.
+//!
+//! # Variables
+//! * `TOKEN_COOKIE_ENABLED` - Whether tokens are set as cookies and read from them, defaults to false
+//! * `TOKEN_COOKIE_NAME` - The name of the token cookie, defaults to `token`
+//! * `TOKEN_COOKIE_SAME_SITE` - `Strict`, `Lax` or `None`, defaults to `Strict`
+//! * `TOKEN_COOKIE_SECURE` - Whether the cookies are only sent over HTTPS, defaults to true
+//!
+//! # Notes
+//! - The cookies last as long as the browser session, the token's own expiry still applies.
+//! - The cookies are meant for a frontend served from the same site as the API, the ingress does
+//!   not allow cross origin requests to carry credentials.
+//! - Tokens re-issued by `token::sliding` replace the token cookie and keep the CSRF cookie, so
+//!   requests already in flight with the old CSRF token are not refused.
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::http::Method;
+use actix_web::{HttpRequest, HttpResponse};
+use rand::Rng;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::webhooks::constant_time_eq;
+
+
+/// The header tokens are sent in when not sent in a cookie.
+pub const TOKEN_HEADER: &str = "token";
+
+/// The cookie the CSRF token is set in.
+pub const CSRF_COOKIE: &str = "csrf_token";
+
+/// The header a request authenticated by the token cookie repeats the CSRF token in.
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// The length of a generated CSRF token.
+pub const CSRF_TOKEN_LENGTH: usize = 32;
+
+
+/// The token cookie config.
+///
+/// # Fields
+/// * `enabled` - Whether tokens are set as cookies and read from them.
+/// * `name` - The name of the token cookie.
+/// * `same_site` - The `SameSite` attribute of the cookies.
+/// * `secure` - Whether the cookies are only sent over HTTPS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookieSettings {
+    pub enabled: bool,
+    pub name: String,
+    pub same_site: SameSite,
+    pub secure: bool,
+}
+
+impl CookieSettings {
+
+    /// Reads the settings from the config, falling back to the defaults for unset or invalid values.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let name = Y::get_config_variable("TOKEN_COOKIE_NAME".to_string()).unwrap_or_default();
+        let same_site = Y::get_config_variable("TOKEN_COOKIE_SAME_SITE".to_string()).unwrap_or_default();
+        CookieSettings {
+            enabled: Y::get_bool("TOKEN_COOKIE_ENABLED").unwrap_or(false),
+            name: Some(name.trim().to_string()).filter(|name| !name.is_empty()).unwrap_or_else(|| TOKEN_HEADER.to_string()),
+            same_site: match same_site.trim().to_lowercase().as_str() {
+                "lax" => SameSite::Lax,
+                "none" => SameSite::None,
+                _ => SameSite::Strict,
+            },
+            secure: Y::get_bool("TOKEN_COOKIE_SECURE").unwrap_or(true),
+        }
+    }
+
+    /// Builds a cookie with the settings' attributes.
+    fn cookie(&self, name: String, value: String, http_only: bool) -> Cookie<'static> {
+        Cookie::build(name, value)
+            .path("/")
+            .http_only(http_only)
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .finish()
+    }
+}
+
+
+fn cookie_error(e: impl std::fmt::Display) -> NanoServiceError {
+    NanoServiceError::new(format!("Failed to set the token cookie: {}", e), NanoServiceErrorStatus::Unknown)
+}
+
+
+/// Sets the token cookie on a response, if tokens are set as cookies.
+///
+/// # Arguments
+/// * `response` - The response carrying the token.
+/// * `token` - The encoded token.
+///
+/// # Notes
+/// A token cookie the response already sets is kept, so the token a handler issued, such as when
+/// switching organization, is not replaced by the one `token::sliding` re-issued for the request.
+pub fn add_token_cookie<Y: GetConfigVariable>(response: &mut HttpResponse, token: &str) -> Result<(), NanoServiceError> {
+    let settings = CookieSettings::from_config::<Y>();
+    if !settings.enabled || response.cookies().any(|cookie| cookie.name() == settings.name) {
+        return Ok(())
+    }
+    response.add_cookie(&settings.cookie(settings.name.clone(), token.to_string(), true)).map_err(cookie_error)
+}
+
+
+/// Sets the token cookie and a new CSRF cookie on a response starting a session, if tokens are set
+/// as cookies.
+///
+/// # Arguments
+/// * `response` - The response carrying the token.
+/// * `token` - The encoded token.
+pub fn add_session_cookies<Y: GetConfigVariable>(response: &mut HttpResponse, token: &str) -> Result<(), NanoServiceError> {
+    let settings = CookieSettings::from_config::<Y>();
+    if !settings.enabled {
+        return Ok(())
+    }
+    let csrf_token: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(CSRF_TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    response.add_cookie(&settings.cookie(settings.name.clone(), token.to_string(), true)).map_err(cookie_error)?;
+    response.add_cookie(&settings.cookie(CSRF_COOKIE.to_string(), csrf_token, false)).map_err(cookie_error)
+}
+
+
+/// Removes the token and CSRF cookies, if tokens are set as cookies.
+///
+/// # Arguments
+/// * `response` - The response ending the session.
+pub fn clear_session_cookies<Y: GetConfigVariable>(response: &mut HttpResponse) -> Result<(), NanoServiceError> {
+    let settings = CookieSettings::from_config::<Y>();
+    if !settings.enabled {
+        return Ok(())
+    }
+    for (name, http_only) in [(settings.name.clone(), true), (CSRF_COOKIE.to_string(), false)] {
+        let mut cookie = settings.cookie(name, String::new(), http_only);
+        cookie.make_removal();
+        response.add_cookie(&cookie).map_err(cookie_error)?;
+    }
+    Ok(())
+}
+
+
+/// Checks a request authenticated by the token cookie repeats the CSRF cookie in `X-CSRF-Token`.
+fn check_csrf(req: &HttpRequest) -> Result<(), NanoServiceError> {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(())
+    }
+    let cookie = req.cookie(CSRF_COOKIE);
+    let header = req.headers().get(CSRF_HEADER).and_then(|value| value.to_str().ok());
+    match (cookie, header) {
+        (Some(cookie), Some(header)) if !header.is_empty() && constant_time_eq(cookie.value().as_bytes(), header.as_bytes()) => Ok(()),
+        _ => Err(NanoServiceError::new(
+            "CSRF token missing or does not match".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ).with_message_key("auth.csrf_mismatch", vec![]))
+    }
+}
+
+
+/// Reads the token from the `token` header, or from the token cookie if tokens are set as cookies.
+///
+/// # Arguments
+/// * `req` - The request to read the token from.
+///
+/// # Returns
+/// * `Ok(String)` - The encoded token
+/// * `Err(NanoServiceError)` - `Unauthorized` if there is no token, or `Forbidden` if it came from
+///   the cookie and the request does not pass the CSRF check
+pub fn token_from_request<Y: GetConfigVariable>(req: &HttpRequest) -> Result<String, NanoServiceError> {
+    if let Some(raw_data) = req.headers().get(TOKEN_HEADER) {
+        return raw_data.to_str().map(str::to_string).map_err(|_| NanoServiceError::new(
+            "token not a valid string".to_string(),
+            NanoServiceErrorStatus::Unauthorized
+        ))
+    }
+    let settings = CookieSettings::from_config::<Y>();
+    match req.cookie(&settings.name).filter(|_| settings.enabled) {
+        Some(cookie) => {
+            check_csrf(req)?;
+            Ok(cookie.value().to_string())
+        },
+        None => Err(NanoServiceError::new(
+            "token not in header under key 'token'".to_string(),
+            NanoServiceErrorStatus::Unauthorized
+        ).with_message_key("auth.missing_token", vec![]))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    struct CookieConfig;
+
+    impl GetConfigVariable for CookieConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "TOKEN_COOKIE_ENABLED" => Ok("true".to_string()),
+                "TOKEN_COOKIE_SAME_SITE" => Ok("lax".to_string()),
+                _ => Ok("".to_string())
+            }
+        }
+    }
+
+    struct HeaderConfig;
+
+    impl GetConfigVariable for HeaderConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("".to_string())
+        }
+    }
+
+    #[test]
+    fn test_session_cookies() {
+        let mut response = HttpResponse::Ok().finish();
+        add_session_cookies::<CookieConfig>(&mut response, "encoded").unwrap();
+        let cookies: Vec<_> = response.cookies().collect();
+        let token = cookies.iter().find(|cookie| cookie.name() == "token").unwrap();
+        assert_eq!(token.value(), "encoded");
+        assert_eq!((token.http_only(), token.secure(), token.same_site()), (Some(true), Some(true), Some(SameSite::Lax)));
+        let csrf = cookies.iter().find(|cookie| cookie.name() == CSRF_COOKIE).unwrap();
+        assert_eq!((csrf.value().len(), csrf.http_only()), (CSRF_TOKEN_LENGTH, None));
+
+        let mut response = HttpResponse::Ok().finish();
+        add_session_cookies::<HeaderConfig>(&mut response, "encoded").unwrap();
+        assert_eq!(response.cookies().count(), 0);
+    }
+
+    #[test]
+    fn test_token_cookie_set_by_handler_is_kept() {
+        let mut response = HttpResponse::Ok().finish();
+        add_token_cookie::<CookieConfig>(&mut response, "switched").unwrap();
+        add_token_cookie::<CookieConfig>(&mut response, "reissued").unwrap();
+        assert_eq!(response.cookies().map(|cookie| cookie.value().to_string()).collect::<Vec<_>>(), vec!["switched"]);
+
+        let mut response = HttpResponse::Ok().finish();
+        clear_session_cookies::<CookieConfig>(&mut response).unwrap();
+        assert!(response.cookies().all(|cookie| cookie.value().is_empty() && cookie.max_age() == Some(actix_web::cookie::time::Duration::ZERO)));
+    }
+
+    #[test]
+    fn test_token_from_cookie() {
+        let request = TestRequest::get().cookie(Cookie::new("token", "encoded")).to_http_request();
+        assert_eq!(token_from_request::<CookieConfig>(&request).unwrap(), "encoded");
+        // the cookie is ignored unless tokens are set as cookies
+        assert!(token_from_request::<HeaderConfig>(&request).is_err());
+
+        let request = TestRequest::get().insert_header(("token", "from-header"))
+            .cookie(Cookie::new("token", "encoded")).to_http_request();
+        assert_eq!(token_from_request::<CookieConfig>(&request).unwrap(), "from-header");
+    }
+
+    #[test]
+    fn test_csrf_for_mutating_requests() {
+        let request = |csrf_header: Option<&str>| {
+            let request = TestRequest::post()
+                .cookie(Cookie::new("token", "encoded"))
+                .cookie(Cookie::new(CSRF_COOKIE, "csrf-value"));
+            match csrf_header {
+                Some(value) => request.insert_header((CSRF_HEADER, value)).to_http_request(),
+                None => request.to_http_request(),
+            }
+        };
+        assert_eq!(token_from_request::<CookieConfig>(&request(Some("csrf-value"))).unwrap(), "encoded");
+        for csrf_header in [None, Some(""), Some("guessed")] {
+            let error = token_from_request::<CookieConfig>(&request(csrf_header)).unwrap_err();
+            assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        }
+    }
+}
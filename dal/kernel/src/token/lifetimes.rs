@@ -0,0 +1,97 @@
+//! How long tokens are valid for and how far apart clocks can be when checking them.
+//!
+//! # Overview
+//! Tokens carry when they were issued (`iat`) and the time they are valid from (`nbf`) along with
+//! their expiry. `HeaderToken::check_if_expired` refuses a token that expired, or is not valid
+//! yet, by more than the clock skew, so servers whose clocks have drifted a little apart still
+//! accept each other's tokens.
+//!
+//! # Variables
+//! * `TOKEN_ACCESS_TTL` - How long a token issued for a new session is valid for, defaults to 20 minutes
+//! * `TOKEN_REFRESH_TTL` - How long a token issued by the refresh endpoint is valid for, defaults to
+//!   `TOKEN_ACCESS_TTL`
+//! * `TOKEN_CLOCK_SKEW` - How far apart clocks can be, defaults to 30 seconds and `0` allows none
+//!
+//! The durations are read as by `TypedConfig::get_duration`, such as `20m`, `1h` or `30s`.
+use chrono::Duration;
+use utils::config::{GetConfigVariable, TypedConfig};
+
+
+/// How long a token is valid for when `TOKEN_ACCESS_TTL` is not set.
+pub const TOKEN_LIFETIME_MINUTES: i64 = 20;
+
+/// How far apart clocks can be when `TOKEN_CLOCK_SKEW` is not set.
+pub const DEFAULT_CLOCK_SKEW_SECONDS: i64 = 30;
+
+
+/// The token lifetimes config.
+///
+/// # Fields
+/// * `access_ttl` - How long a token issued for a new session is valid for.
+/// * `refresh_ttl` - How long a token issued by the refresh endpoint is valid for.
+/// * `clock_skew` - How far apart clocks can be.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenLifetimes {
+    pub access_ttl: Duration,
+    pub refresh_ttl: Duration,
+    pub clock_skew: Duration,
+}
+
+impl TokenLifetimes {
+
+    /// Reads the lifetimes from the config, falling back to the defaults for unset or invalid values.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let read = |variable: &str| Y::get_duration(variable).ok().and_then(|value| Duration::from_std(value).ok());
+        let access_ttl = read("TOKEN_ACCESS_TTL")
+            .filter(|ttl| *ttl > Duration::zero())
+            .unwrap_or(Duration::minutes(TOKEN_LIFETIME_MINUTES));
+        TokenLifetimes {
+            access_ttl,
+            refresh_ttl: read("TOKEN_REFRESH_TTL").filter(|ttl| *ttl > Duration::zero()).unwrap_or(access_ttl),
+            clock_skew: read("TOKEN_CLOCK_SKEW").unwrap_or(Duration::seconds(DEFAULT_CLOCK_SKEW_SECONDS)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::errors::NanoServiceError;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "TOKEN_ACCESS_TTL" => Ok("5m".to_string()),
+                "TOKEN_REFRESH_TTL" => Ok("0".to_string()),
+                "TOKEN_CLOCK_SKEW" => Ok("0".to_string()),
+                _ => Ok("".to_string())
+            }
+        }
+    }
+
+    struct EmptyConfig;
+
+    impl GetConfigVariable for EmptyConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("".to_string())
+        }
+    }
+
+    #[test]
+    fn test_from_config() {
+        // a refresh lifetime of zero would issue expired tokens so the access lifetime is kept
+        assert_eq!(TokenLifetimes::from_config::<FakeConfig>(), TokenLifetimes {
+            access_ttl: Duration::minutes(5),
+            refresh_ttl: Duration::minutes(5),
+            clock_skew: Duration::zero(),
+        });
+        assert_eq!(TokenLifetimes::from_config::<EmptyConfig>(), TokenLifetimes {
+            access_ttl: Duration::minutes(TOKEN_LIFETIME_MINUTES),
+            refresh_ttl: Duration::minutes(TOKEN_LIFETIME_MINUTES),
+            clock_skew: Duration::seconds(DEFAULT_CLOCK_SKEW_SECONDS),
+        });
+    }
+}
@@ -1,3 +1,9 @@
+#[allow(clippy::module_inception)]
 pub mod token;
 pub mod checks;
+pub mod signing;
+pub mod lifetimes;
 pub mod session_cache;
+pub mod sliding;
+pub mod cookies;
+pub mod session_limit;
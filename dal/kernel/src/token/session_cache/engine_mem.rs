@@ -1,9 +1,15 @@
 use crate::token::session_cache::traits::{GetAuthCacheSession, SetAuthCacheSession};
-use crate::token::session_cache::structs::{AuthCacheSession, IntoAuthCacheKey, IntoAuthCacheSession};
-use utils::errors::NanoServiceError;
+use crate::token::session_cache::structs::{AuthCacheSession, IntoAuthCacheKey, IntoAuthCacheSession, UserAuthCacheSession, UserSessionCount};
+use crate::token::session_cache::traits::{
+    CountAuthCacheSessions, DelDeviceAuthCacheSessions, DelUserAuthCacheSessions, FlushAuthCacheSessions, GetUserAuthCacheSessions,
+    TouchAuthCacheSession
+};
+use chrono::{DateTime, Utc};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::fault_injection::{inject_fault, FaultTarget};
 use std::future::Future;
 use tokio::sync::Mutex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::sync::LazyLock;
 
@@ -23,6 +29,10 @@ impl GetAuthCacheSession for AuthCacheSessionEngineMem {
     -> impl Future<Output = Result<Option<AuthCacheSession>, NanoServiceError>> + Send {
         let key = key.into_auth_cache_key();
         async move {
+            inject_fault(FaultTarget::Cache).await.map_err(|fault| NanoServiceError::new(
+                fault.to_string(),
+                NanoServiceErrorStatus::Unknown
+            ))?;
             let session = SESSION_CACHE.lock().await;
             match session.get(&key.key) {
                 Some(session) => {
@@ -50,6 +60,22 @@ impl SetAuthCacheSession for AuthCacheSessionEngineMem {
 }
 
 
+impl TouchAuthCacheSession for AuthCacheSessionEngineMem {
+    fn touch_auth_cache_session<X: IntoAuthCacheKey + Send>(key: &X, last_seen: DateTime<Utc>, time_expire: DateTime<Utc>)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send {
+        let key = key.into_auth_cache_key();
+        async move {
+            let mut session_cache = SESSION_CACHE.lock().await;
+            if let Some(session) = session_cache.get_mut(&key.key) {
+                session.last_seen = last_seen;
+                session.time_expire = time_expire;
+            }
+            Ok(())
+        }
+    }
+}
+
+
 impl DelAuthCacheSession for AuthCacheSessionEngineMem {
 
     fn del_auth_cache_session<X: IntoAuthCacheKey>(key: X) 
@@ -63,3 +89,72 @@ impl DelAuthCacheSession for AuthCacheSessionEngineMem {
     }
 
 }
+
+
+impl CountAuthCacheSessions for AuthCacheSessionEngineMem {
+
+    async fn count_auth_cache_sessions() -> Result<Vec<UserSessionCount>, NanoServiceError> {
+        let now = Utc::now();
+        let session_cache = SESSION_CACHE.lock().await;
+        let mut counts: BTreeMap<i32, usize> = BTreeMap::new();
+        for session in session_cache.values().filter(|session| session.time_expire > now) {
+            *counts.entry(session.user_id).or_insert(0) += 1;
+        }
+        Ok(counts.into_iter().map(|(user_id, sessions)| UserSessionCount { user_id, sessions }).collect())
+    }
+
+}
+
+
+impl GetUserAuthCacheSessions for AuthCacheSessionEngineMem {
+
+    async fn get_user_auth_cache_sessions(user_id: i32) -> Result<Vec<UserAuthCacheSession>, NanoServiceError> {
+        let now = Utc::now();
+        let session_cache = SESSION_CACHE.lock().await;
+        let mut sessions: Vec<UserAuthCacheSession> = session_cache.iter()
+            .filter(|(_, session)| session.user_id == user_id && session.time_expire > now)
+            .map(|(key, session)| UserAuthCacheSession { key: key.clone(), session: session.clone() })
+            .collect();
+        sessions.sort_by_key(|session| session.session.time_started);
+        Ok(sessions)
+    }
+
+}
+
+
+impl DelUserAuthCacheSessions for AuthCacheSessionEngineMem {
+
+    async fn del_user_auth_cache_sessions(user_id: i32) -> Result<usize, NanoServiceError> {
+        let mut session_cache = SESSION_CACHE.lock().await;
+        let before = session_cache.len();
+        session_cache.retain(|_, session| session.user_id != user_id);
+        Ok(before - session_cache.len())
+    }
+
+}
+
+
+impl DelDeviceAuthCacheSessions for AuthCacheSessionEngineMem {
+
+    async fn del_device_auth_cache_sessions(user_id: i32, fingerprint: String) -> Result<usize, NanoServiceError> {
+        let mut session_cache = SESSION_CACHE.lock().await;
+        let before = session_cache.len();
+        session_cache.retain(|_, session| {
+            session.user_id != user_id || session.device_fingerprint.as_deref() != Some(fingerprint.as_str())
+        });
+        Ok(before - session_cache.len())
+    }
+
+}
+
+
+impl FlushAuthCacheSessions for AuthCacheSessionEngineMem {
+
+    async fn flush_auth_cache_sessions() -> Result<usize, NanoServiceError> {
+        let mut session_cache = SESSION_CACHE.lock().await;
+        let removed = session_cache.len();
+        session_cache.clear();
+        Ok(removed)
+    }
+
+}
@@ -1,5 +1,15 @@
-use crate::token::session_cache::traits::{GetAuthCacheSession, SetAuthCacheSession};
-use crate::token::session_cache::structs::{AuthCacheSession, IntoAuthCacheKey, IntoAuthCacheSession};
+use crate::token::session_cache::traits::{
+    GetAuthCacheSession,
+    SetAuthCacheSession,
+    DelAuthCacheSession,
+    GetUserAuthCacheSessions,
+    TouchAuthCacheSession,
+    CountAuthCacheSessions,
+    DelUserAuthCacheSessions,
+    DelDeviceAuthCacheSessions,
+    FlushAuthCacheSessions
+};
+use crate::token::session_cache::structs::{AuthCacheSession, IntoAuthCacheKey, IntoAuthCacheSession, UserAuthCacheSession, UserSessionCount};
 use utils::errors::NanoServiceError;
 use std::future::Future;
 use tokio::sync::Mutex;
@@ -7,7 +17,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::LazyLock;
 use crate::users::UserRole;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
 
 pub static SESSION_CACHE: LazyLock<Arc<Mutex<HashMap<String, AuthCacheSession>>>> = LazyLock::new(|| {
@@ -28,7 +38,10 @@ impl GetAuthCacheSession for PassAuthSessionCheckMock {
                 role: UserRole::Admin,
                 time_started: Utc::now(),
                 time_expire: Utc::now(),
-                user_agent: "test".to_string()
+                user_agent: "test".to_string(),
+                permissions: Vec::new(),
+                last_seen: Utc::now(),
+                device_fingerprint: None
             }))
         }
     }
@@ -36,6 +49,7 @@ impl GetAuthCacheSession for PassAuthSessionCheckMock {
 
 
 impl SetAuthCacheSession for PassAuthSessionCheckMock {
+    #[allow(clippy::manual_async_fn)]
     fn set_auth_cache_session<X: IntoAuthCacheKey, Y: IntoAuthCacheSession>(_key: &X, _session: &Y) 
     -> impl Future<Output = Result<(), NanoServiceError>> + Send {
         async move {
@@ -45,6 +59,63 @@ impl SetAuthCacheSession for PassAuthSessionCheckMock {
 }
 
 
+impl DelAuthCacheSession for PassAuthSessionCheckMock {
+    #[allow(clippy::manual_async_fn)]
+    fn del_auth_cache_session<X: IntoAuthCacheKey>(_key: X) 
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send {
+        async move {
+            Ok(())
+        }
+    }
+}
+
+
+impl GetUserAuthCacheSessions for PassAuthSessionCheckMock {
+    async fn get_user_auth_cache_sessions(_user_id: i32) -> Result<Vec<UserAuthCacheSession>, NanoServiceError> {
+        Ok(Vec::new())
+    }
+}
+
+
+impl TouchAuthCacheSession for PassAuthSessionCheckMock {
+    #[allow(clippy::manual_async_fn)]
+    fn touch_auth_cache_session<X: IntoAuthCacheKey + Send>(_key: &X, _last_seen: DateTime<Utc>, _time_expire: DateTime<Utc>)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send {
+        async move {
+            Ok(())
+        }
+    }
+}
+
+
+impl CountAuthCacheSessions for PassAuthSessionCheckMock {
+    async fn count_auth_cache_sessions() -> Result<Vec<UserSessionCount>, NanoServiceError> {
+        Ok(vec![UserSessionCount { user_id: 1, sessions: 2 }])
+    }
+}
+
+
+impl DelUserAuthCacheSessions for PassAuthSessionCheckMock {
+    async fn del_user_auth_cache_sessions(_user_id: i32) -> Result<usize, NanoServiceError> {
+        Ok(1)
+    }
+}
+
+
+impl DelDeviceAuthCacheSessions for PassAuthSessionCheckMock {
+    async fn del_device_auth_cache_sessions(_user_id: i32, _fingerprint: String) -> Result<usize, NanoServiceError> {
+        Ok(1)
+    }
+}
+
+
+impl FlushAuthCacheSessions for PassAuthSessionCheckMock {
+    async fn flush_auth_cache_sessions() -> Result<usize, NanoServiceError> {
+        Ok(3)
+    }
+}
+
+
 pub struct FailAuthSessionCheckMock;
 
 
@@ -58,8 +129,22 @@ impl GetAuthCacheSession for FailAuthSessionCheckMock {
                 role: UserRole::Admin,
                 time_started: Utc::now(),
                 time_expire: Utc::now(),
-                user_agent: "test".to_string()
+                user_agent: "test".to_string(),
+                permissions: Vec::new(),
+                last_seen: Utc::now(),
+                device_fingerprint: None
             }))
         }
     }
-}
\ No newline at end of file
+}
+
+
+impl TouchAuthCacheSession for FailAuthSessionCheckMock {
+    #[allow(clippy::manual_async_fn)]
+    fn touch_auth_cache_session<X: IntoAuthCacheKey + Send>(_key: &X, _last_seen: DateTime<Utc>, _time_expire: DateTime<Utc>)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send {
+        async move {
+            Ok(())
+        }
+    }
+}
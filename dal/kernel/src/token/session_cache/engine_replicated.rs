@@ -0,0 +1,364 @@
+//! Session cache engine for multi-replica deployments that still rely on the in-memory cache.
+//!
+//! Every set and delete is applied to the local `SESSION_CACHE` and then pushed to the peers
+//! listed in `SESSION_REPLICATION_PEERS` (comma separated base urls) so a login on one replica
+//! is honoured by the others. Peers receive the event on `/api/auth/v1/auth/replicate_session`
+//! and apply it locally without forwarding it again.
+use crate::token::session_cache::traits::{
+    GetAuthCacheSession,
+    SetAuthCacheSession,
+    TouchAuthCacheSession,
+    DelAuthCacheSession,
+    CountAuthCacheSessions,
+    GetUserAuthCacheSessions,
+    DelUserAuthCacheSessions,
+    DelDeviceAuthCacheSessions,
+    FlushAuthCacheSessions
+};
+use crate::token::session_cache::structs::{
+    AuthCacheSession,
+    IntoAuthCacheKey,
+    IntoAuthCacheSession,
+    SessionReplicationEvent,
+    UserAuthCacheSession,
+    UserSessionCount
+};
+use crate::token::session_cache::engine_mem::{AuthCacheSessionEngineMem, SESSION_CACHE};
+use chrono::{DateTime, Utc};
+use utils::config::GetConfigVariable;
+use utils::log_limited;
+use utils::errors::NanoServiceError;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::LazyLock;
+
+
+/// The path on a peer that accepts replication events.
+pub const REPLICATION_PATH: &str = "/api/auth/v1/auth/replicate_session";
+
+/// The header carrying the shared secret that peers check before applying an event.
+pub const REPLICATION_SECRET_HEADER: &str = "replication-secret";
+
+static REPLICATION_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+
+
+/// The in-memory session cache with write-through replication to peer instances.
+///
+/// # Generics
+/// * `X` - The config used to read `SESSION_REPLICATION_PEERS` and `SESSION_REPLICATION_SECRET`
+pub struct AuthCacheSessionEngineReplicated<X: GetConfigVariable> {
+    config_handle: PhantomData<X>
+}
+
+
+/// The peers and secret needed to push an event to the other instances.
+struct ReplicationTarget {
+    peers: Vec<String>,
+    secret: String
+}
+
+
+impl ReplicationTarget {
+
+    /// Reads the replication config, returning `None` if replication is not configured.
+    fn from_config<X: GetConfigVariable>() -> Option<Self> {
+        let peers: Vec<String> = X::get_config_variable("SESSION_REPLICATION_PEERS".to_string())
+            .ok()?
+            .split(',')
+            .map(|peer| peer.trim().trim_end_matches('/').to_string())
+            .filter(|peer| !peer.is_empty())
+            .collect();
+        if peers.is_empty() {
+            return None
+        }
+        let secret = X::get_config_variable("SESSION_REPLICATION_SECRET".to_string()).ok()?;
+        Some(ReplicationTarget { peers, secret })
+    }
+
+    /// Sends the event to every peer in the background.
+    ///
+    /// # Notes
+    /// A failed push is logged and dropped. The peer will miss the session until the user logs in
+    /// again on that replica, which is no worse than running without replication.
+    fn broadcast(self, event: SessionReplicationEvent) {
+        for peer in self.peers {
+            let event = event.clone();
+            let secret = self.secret.clone();
+            tokio::spawn(async move {
+                let outcome = REPLICATION_CLIENT
+                    .post(format!("{}{}", peer, REPLICATION_PATH))
+                    .header(REPLICATION_SECRET_HEADER, secret)
+                    .json(&event)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(e) = outcome {
+                    log_limited!("sessions", "failed to replicate session event to {}: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+
+impl<X: GetConfigVariable> GetAuthCacheSession for AuthCacheSessionEngineReplicated<X> {
+    fn get_auth_cache_session<Y: IntoAuthCacheKey + Send>(key: &Y)
+    -> impl Future<Output = Result<Option<AuthCacheSession>, NanoServiceError>> + Send {
+        AuthCacheSessionEngineMem::get_auth_cache_session(key)
+    }
+}
+
+
+impl<X: GetConfigVariable> SetAuthCacheSession for AuthCacheSessionEngineReplicated<X> {
+    fn set_auth_cache_session<Y: IntoAuthCacheKey, Z: IntoAuthCacheSession>(key: &Y, session: &Z)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send {
+        let session = session.into_auth_cache_session();
+        let key = key.into_auth_cache_key();
+        let target = ReplicationTarget::from_config::<X>();
+        async move {
+            let mut session_cache = SESSION_CACHE.lock().await;
+            session_cache.insert(key.key.clone(), session.clone());
+            drop(session_cache);
+            if let Some(target) = target {
+                target.broadcast(SessionReplicationEvent::Set { key: key.key, session });
+            }
+            Ok(())
+        }
+    }
+}
+
+
+impl<X: GetConfigVariable> TouchAuthCacheSession for AuthCacheSessionEngineReplicated<X> {
+    fn touch_auth_cache_session<Y: IntoAuthCacheKey + Send>(key: &Y, last_seen: DateTime<Utc>, time_expire: DateTime<Utc>)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send {
+        let key = key.into_auth_cache_key();
+        let target = ReplicationTarget::from_config::<X>();
+        async move {
+            AuthCacheSessionEngineMem::touch_auth_cache_session(&key.key, last_seen, time_expire).await?;
+            if let Some(target) = target {
+                target.broadcast(SessionReplicationEvent::Touch { key: key.key, last_seen, time_expire });
+            }
+            Ok(())
+        }
+    }
+}
+
+
+impl<X: GetConfigVariable> DelAuthCacheSession for AuthCacheSessionEngineReplicated<X> {
+    fn del_auth_cache_session<Y: IntoAuthCacheKey>(key: Y)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send {
+        let key = key.into_auth_cache_key();
+        let target = ReplicationTarget::from_config::<X>();
+        async move {
+            let mut session_cache = SESSION_CACHE.lock().await;
+            session_cache.remove(&key.key);
+            drop(session_cache);
+            if let Some(target) = target {
+                target.broadcast(SessionReplicationEvent::Del { key: key.key });
+            }
+            Ok(())
+        }
+    }
+}
+
+
+impl<X: GetConfigVariable> CountAuthCacheSessions for AuthCacheSessionEngineReplicated<X> {
+    fn count_auth_cache_sessions()
+    -> impl Future<Output = Result<Vec<UserSessionCount>, NanoServiceError>> + Send {
+        AuthCacheSessionEngineMem::count_auth_cache_sessions()
+    }
+}
+
+
+impl<X: GetConfigVariable> GetUserAuthCacheSessions for AuthCacheSessionEngineReplicated<X> {
+    fn get_user_auth_cache_sessions(user_id: i32)
+    -> impl Future<Output = Result<Vec<UserAuthCacheSession>, NanoServiceError>> + Send {
+        AuthCacheSessionEngineMem::get_user_auth_cache_sessions(user_id)
+    }
+}
+
+
+impl<X: GetConfigVariable> DelUserAuthCacheSessions for AuthCacheSessionEngineReplicated<X> {
+    fn del_user_auth_cache_sessions(user_id: i32)
+    -> impl Future<Output = Result<usize, NanoServiceError>> + Send {
+        let target = ReplicationTarget::from_config::<X>();
+        async move {
+            let removed = AuthCacheSessionEngineMem::del_user_auth_cache_sessions(user_id).await?;
+            if let Some(target) = target {
+                target.broadcast(SessionReplicationEvent::DelUser { user_id });
+            }
+            Ok(removed)
+        }
+    }
+}
+
+
+impl<X: GetConfigVariable> DelDeviceAuthCacheSessions for AuthCacheSessionEngineReplicated<X> {
+    fn del_device_auth_cache_sessions(user_id: i32, fingerprint: String)
+    -> impl Future<Output = Result<usize, NanoServiceError>> + Send {
+        let target = ReplicationTarget::from_config::<X>();
+        async move {
+            let removed = AuthCacheSessionEngineMem::del_device_auth_cache_sessions(user_id, fingerprint.clone()).await?;
+            if let Some(target) = target {
+                target.broadcast(SessionReplicationEvent::DelDevice { user_id, fingerprint });
+            }
+            Ok(removed)
+        }
+    }
+}
+
+
+impl<X: GetConfigVariable> FlushAuthCacheSessions for AuthCacheSessionEngineReplicated<X> {
+    fn flush_auth_cache_sessions()
+    -> impl Future<Output = Result<usize, NanoServiceError>> + Send {
+        let target = ReplicationTarget::from_config::<X>();
+        async move {
+            let removed = AuthCacheSessionEngineMem::flush_auth_cache_sessions().await?;
+            if let Some(target) = target {
+                target.broadcast(SessionReplicationEvent::Flush);
+            }
+            Ok(removed)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::UserRole;
+
+    struct NoPeersConfig;
+
+    impl GetConfigVariable for NoPeersConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("".to_string())
+        }
+    }
+
+    struct PeersConfig;
+
+    impl GetConfigVariable for PeersConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SESSION_REPLICATION_PEERS" => Ok("http://10.0.0.2:8001/, ,http://10.0.0.3:8001".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    fn session() -> AuthCacheSession {
+        AuthCacheSession {
+            user_id: 1,
+            role: UserRole::Admin,
+            time_started: Utc::now(),
+            time_expire: Utc::now(),
+            user_agent: "test".to_string(),
+            permissions: Vec::new(),
+            last_seen: Utc::now(),
+            device_fingerprint: None
+        }
+    }
+
+    #[test]
+    fn test_replication_target_from_config() {
+        assert!(ReplicationTarget::from_config::<NoPeersConfig>().is_none());
+
+        let target = ReplicationTarget::from_config::<PeersConfig>().unwrap();
+        assert_eq!(target.peers, vec!["http://10.0.0.2:8001", "http://10.0.0.3:8001"]);
+        assert_eq!(target.secret, "secret");
+    }
+
+    #[test]
+    fn test_replication_event_serialization() {
+        let event = SessionReplicationEvent::Set { key: "key".to_string(), session: session() };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["action"], "Set");
+        assert_eq!(json["key"], "key");
+        assert_eq!(json["session"]["user_id"], 1);
+
+        let event: SessionReplicationEvent = serde_json::from_value(
+            serde_json::json!({"action": "Del", "key": "key"})
+        ).unwrap();
+        match event {
+            SessionReplicationEvent::Del { key } => assert_eq!(key, "key"),
+            _ => panic!("expected a Del event")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_get_del_without_peers() {
+        type Engine = AuthCacheSessionEngineReplicated<NoPeersConfig>;
+        let key = "replicated-test-key".to_string();
+
+        Engine::set_auth_cache_session(&key, &session()).await.unwrap();
+        let outcome = Engine::get_auth_cache_session(&key).await.unwrap();
+        assert_eq!(outcome.unwrap().user_id, 1);
+
+        let last_seen = Utc::now() + chrono::Duration::minutes(1);
+        Engine::touch_auth_cache_session(&key, last_seen, last_seen).await.unwrap();
+        let outcome = Engine::get_auth_cache_session(&key).await.unwrap().unwrap();
+        assert_eq!((outcome.last_seen, outcome.time_expire), (last_seen, last_seen));
+
+        Engine::del_auth_cache_session(key.clone()).await.unwrap();
+        let outcome = Engine::get_auth_cache_session(&key).await.unwrap();
+        assert!(outcome.is_none());
+
+        // a session that has been removed is not brought back
+        Engine::touch_auth_cache_session(&key, last_seen, last_seen).await.unwrap();
+        assert!(Engine::get_auth_cache_session(&key).await.unwrap().is_none());
+    }
+    #[tokio::test]
+    async fn test_count_and_del_user_sessions() {
+        type Engine = AuthCacheSessionEngineReplicated<NoPeersConfig>;
+        let mut user_session = session();
+        user_session.user_id = 9001;
+        user_session.time_expire = Utc::now() + chrono::Duration::minutes(5);
+
+        Engine::set_auth_cache_session(&"count-test-key-one", &user_session).await.unwrap();
+        Engine::set_auth_cache_session(&"count-test-key-two", &user_session).await.unwrap();
+        let counts = Engine::count_auth_cache_sessions().await.unwrap();
+        assert!(counts.contains(&UserSessionCount { user_id: 9001, sessions: 2 }));
+
+        let removed = Engine::del_user_auth_cache_sessions(9001).await.unwrap();
+        assert_eq!(removed, 2);
+        let counts = Engine::count_auth_cache_sessions().await.unwrap();
+        assert!(counts.iter().all(|count| count.user_id != 9001));
+    }
+
+    #[tokio::test]
+    async fn test_del_device_sessions() {
+        type Engine = AuthCacheSessionEngineReplicated<NoPeersConfig>;
+        let mut laptop = session();
+        laptop.user_id = 9002;
+        laptop.device_fingerprint = Some("laptop".to_string());
+        let mut phone = laptop.clone();
+        phone.device_fingerprint = Some("phone".to_string());
+
+        Engine::set_auth_cache_session(&"device-test-laptop", &laptop).await.unwrap();
+        Engine::set_auth_cache_session(&"device-test-phone", &phone).await.unwrap();
+        let removed = Engine::del_device_auth_cache_sessions(9002, "laptop".to_string()).await.unwrap();
+        assert_eq!(removed, 1);
+        assert!(Engine::get_auth_cache_session(&"device-test-laptop").await.unwrap().is_none());
+        assert!(Engine::get_auth_cache_session(&"device-test-phone").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_user_sessions() {
+        type Engine = AuthCacheSessionEngineReplicated<NoPeersConfig>;
+        let mut newest = session();
+        newest.user_id = 9003;
+        newest.time_expire = Utc::now() + chrono::Duration::minutes(20);
+        let mut oldest = newest.clone();
+        oldest.time_started = newest.time_started - chrono::Duration::minutes(5);
+        let mut expired = newest.clone();
+        expired.time_expire = Utc::now() - chrono::Duration::minutes(1);
+
+        Engine::set_auth_cache_session(&"user-test-newest", &newest).await.unwrap();
+        Engine::set_auth_cache_session(&"user-test-oldest", &oldest).await.unwrap();
+        Engine::set_auth_cache_session(&"user-test-expired", &expired).await.unwrap();
+        let keys: Vec<String> = Engine::get_user_auth_cache_sessions(9003).await.unwrap()
+            .into_iter().map(|session| session.key).collect();
+        assert_eq!(keys, vec!["user-test-oldest".to_string(), "user-test-newest".to_string()]);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod engine_mem;
+pub mod engine_replicated;
 pub mod traits;
 pub mod structs;
 pub mod engine_mock;
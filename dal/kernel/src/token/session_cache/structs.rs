@@ -1,14 +1,27 @@
 use crate::users::UserRole;
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 
-#[derive(Debug, Clone)]
+/// A logged in session held in the cache under the token's unique id.
+///
+/// # Notes
+/// `last_seen` is when the session last made a request. The endpoints' session check refreshes it
+/// and times out sessions that have been idle too long, see `token::sliding`. `device_fingerprint`
+/// is the device the session was started on, see `devices`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthCacheSession {
     pub user_id: i32,
     pub role: UserRole,
     pub time_started: DateTime<Utc>,
     pub time_expire: DateTime<Utc>,
     pub user_agent: String,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    #[serde(default = "Utc::now")]
+    pub last_seen: DateTime<Utc>,
+    #[serde(default)]
+    pub device_fingerprint: Option<String>,
 }
 
 
@@ -17,10 +30,56 @@ pub struct AuthCacheKey {
 }
 
 
+/// The number of unexpired sessions held in the cache for a user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserSessionCount {
+    pub user_id: i32,
+    pub sessions: usize
+}
+
+
+/// A user's session with the key it is held under in the cache.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserAuthCacheSession {
+    pub key: String,
+    pub session: AuthCacheSession
+}
+
+
+/// The number of sessions removed by a forced logout or a flush.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemovedSessions {
+    pub removed: usize
+}
+
+
+/// A session cache change that is sent to the other instances so they can mirror it.
+///
+/// # Variants
+/// * `Set` - A session has been inserted under `key`.
+/// * `Touch` - The session under `key` has made a request, and may have a new expiry.
+/// * `Del` - The session under `key` has been removed.
+/// * `DelUser` - Every session belonging to `user_id` has been removed.
+/// * `DelDevice` - Every session `user_id` started on the device with `fingerprint` has been removed.
+/// * `Flush` - Every session has been removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action")]
+pub enum SessionReplicationEvent {
+    Set { key: String, session: AuthCacheSession },
+    Touch { key: String, last_seen: DateTime<Utc>, time_expire: DateTime<Utc> },
+    Del { key: String },
+    DelUser { user_id: i32 },
+    DelDevice { user_id: i32, fingerprint: String },
+    Flush
+}
+
+
+#[allow(clippy::wrong_self_convention)]
 pub trait IntoAuthCacheSession {
     fn into_auth_cache_session(&self) -> AuthCacheSession;
 }
 
+#[allow(clippy::wrong_self_convention)]
 pub trait IntoAuthCacheKey {
     fn into_auth_cache_key(&self) -> AuthCacheKey;
 }
@@ -40,3 +99,9 @@ impl IntoAuthCacheKey for &str {
         }
     }
 }
+
+impl IntoAuthCacheSession for AuthCacheSession {
+    fn into_auth_cache_session(&self) -> AuthCacheSession {
+        self.clone()
+    }
+}
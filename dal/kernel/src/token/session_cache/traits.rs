@@ -1,4 +1,5 @@
-use crate::token::session_cache::structs::{AuthCacheSession, IntoAuthCacheKey, IntoAuthCacheSession};
+use crate::token::session_cache::structs::{AuthCacheSession, IntoAuthCacheKey, IntoAuthCacheSession, UserAuthCacheSession, UserSessionCount};
+use chrono::{DateTime, Utc};
 use utils::errors::NanoServiceError;
 use std::future::Future;
 
@@ -13,7 +14,40 @@ pub trait SetAuthCacheSession {
     -> impl Future<Output = Result<(), NanoServiceError>> + Send;
 }
 
+/// Records a request made with a session, see `token::sliding`. A session that is not in the
+/// cache is left out.
+pub trait TouchAuthCacheSession {
+    fn touch_auth_cache_session<X: IntoAuthCacheKey + Send>(key: &X, last_seen: DateTime<Utc>, time_expire: DateTime<Utc>)
+    -> impl Future<Output = Result<(), NanoServiceError>> + Send;
+}
+
 pub trait DelAuthCacheSession {
     fn del_auth_cache_session<X: IntoAuthCacheKey>(key: X) 
     -> impl Future<Output = Result<(), NanoServiceError>> + Send;
 }
+
+pub trait CountAuthCacheSessions {
+    fn count_auth_cache_sessions() 
+    -> impl Future<Output = Result<Vec<UserSessionCount>, NanoServiceError>> + Send;
+}
+
+/// Returns a user's unexpired sessions, the oldest first, see `token::session_limit`.
+pub trait GetUserAuthCacheSessions {
+    fn get_user_auth_cache_sessions(user_id: i32) 
+    -> impl Future<Output = Result<Vec<UserAuthCacheSession>, NanoServiceError>> + Send;
+}
+
+pub trait DelUserAuthCacheSessions {
+    fn del_user_auth_cache_sessions(user_id: i32) 
+    -> impl Future<Output = Result<usize, NanoServiceError>> + Send;
+}
+
+pub trait DelDeviceAuthCacheSessions {
+    fn del_device_auth_cache_sessions(user_id: i32, fingerprint: String) 
+    -> impl Future<Output = Result<usize, NanoServiceError>> + Send;
+}
+
+pub trait FlushAuthCacheSessions {
+    fn flush_auth_cache_sessions() 
+    -> impl Future<Output = Result<usize, NanoServiceError>> + Send;
+}
@@ -0,0 +1,201 @@
+//! Caps the number of sessions a user can have at once.
+//!
+//! # Overview
+//! Every login runs `enforce_session_limit` before its session is stored. If the user already has
+//! `MAX_SESSIONS_PER_USER` unexpired sessions in the cache the `SESSION_LIMIT_POLICY` decides what
+//! happens:
+//! - `evict_oldest` ends the sessions started the longest time ago to make room for the new one
+//! - `reject` refuses the login with a `Conflict` until the user logs out somewhere else
+//!
+//! # Notes
+//! Refreshing a token replaces its session rather than adding one, so it is not limited.
+//!
+//! # Variables
+//! * `MAX_SESSIONS_PER_USER` - The most sessions a user can have, unset or `0` means no limit
+//! * `SESSION_LIMIT_POLICY` - `evict_oldest` or `reject`, defaults to `evict_oldest`
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::token::session_cache::traits::{DelAuthCacheSession, GetUserAuthCacheSessions};
+
+
+/// What happens to a login that would take a user over their session limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionLimitPolicy {
+    EvictOldest,
+    Reject
+}
+
+
+/// The session limit config.
+///
+/// # Fields
+/// * `max_sessions` - The most sessions a user can have, `None` if there is no limit.
+/// * `policy` - What happens to a login over the limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionLimit {
+    pub max_sessions: Option<usize>,
+    pub policy: SessionLimitPolicy,
+}
+
+impl SessionLimit {
+
+    /// Reads the limit from the config, an unset or invalid limit is no limit and an unknown policy
+    /// evicts the oldest sessions.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let max_sessions = Y::get_int("MAX_SESSIONS_PER_USER").ok()
+            .filter(|max| *max > 0)
+            .map(|max| max as usize);
+        let policy = match Y::get_config_variable("SESSION_LIMIT_POLICY".to_string()) {
+            Ok(policy) if policy.trim().eq_ignore_ascii_case("reject") => SessionLimitPolicy::Reject,
+            _ => SessionLimitPolicy::EvictOldest
+        };
+        SessionLimit { max_sessions, policy }
+    }
+}
+
+
+/// Makes room for a new session for the user, or refuses it.
+///
+/// # Arguments
+/// * `user_id` - The ID of the user logging in.
+///
+/// # Returns
+/// * `Ok(usize)` - The number of the user's sessions ended to make room
+/// * `Err(NanoServiceError)` - `Conflict` if the user is at the limit and the policy is `reject`
+pub async fn enforce_session_limit<Y, Z>(user_id: i32) -> Result<usize, NanoServiceError>
+where
+    Y: GetConfigVariable,
+    Z: GetUserAuthCacheSessions + DelAuthCacheSession,
+{
+    let limit = SessionLimit::from_config::<Y>();
+    let max_sessions = match limit.max_sessions {
+        Some(max_sessions) => max_sessions,
+        None => return Ok(0)
+    };
+    let sessions = Z::get_user_auth_cache_sessions(user_id).await?;
+    if sessions.len() < max_sessions {
+        return Ok(0)
+    }
+    if limit.policy == SessionLimitPolicy::Reject {
+        return Err(NanoServiceError::new(
+            format!("User already has {} sessions", sessions.len()),
+            NanoServiceErrorStatus::Conflict
+        ).with_message_key("auth.session_limit", vec![("max", max_sessions.to_string())]))
+    }
+    let evict = sessions.len() + 1 - max_sessions;
+    for session in sessions.into_iter().take(evict) {
+        Z::del_auth_cache_session(session.key).await?;
+    }
+    Ok(evict)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::session_cache::structs::{AuthCacheSession, IntoAuthCacheKey, UserAuthCacheSession};
+    use crate::users::UserRole;
+    use chrono::{Duration, Utc};
+    use std::future::Future;
+    use std::sync::Mutex;
+
+    /// The keys of the sessions ended.
+    static ENDED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct LimitConfig;
+
+    impl GetConfigVariable for LimitConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "MAX_SESSIONS_PER_USER" => Ok("2".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    struct RejectConfig;
+
+    impl GetConfigVariable for RejectConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "MAX_SESSIONS_PER_USER" => Ok("2".to_string()),
+                "SESSION_LIMIT_POLICY" => Ok("Reject".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    struct UnsetConfig;
+
+    impl GetConfigVariable for UnsetConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    /// User `n` has `n` sessions, keyed `n-0` and so on from the oldest.
+    struct SessionsMock;
+
+    impl GetUserAuthCacheSessions for SessionsMock {
+        async fn get_user_auth_cache_sessions(user_id: i32) -> Result<Vec<UserAuthCacheSession>, NanoServiceError> {
+            Ok((0..user_id).map(|n| UserAuthCacheSession {
+                key: format!("{}-{}", user_id, n),
+                session: AuthCacheSession {
+                    user_id,
+                    role: UserRole::Worker,
+                    time_started: Utc::now() - Duration::minutes((user_id - n) as i64),
+                    time_expire: Utc::now() + Duration::minutes(20),
+                    user_agent: "some-agent".to_string(),
+                    permissions: Vec::new(),
+                    last_seen: Utc::now(),
+                    device_fingerprint: None
+                }
+            }).collect())
+        }
+    }
+
+    impl DelAuthCacheSession for SessionsMock {
+        fn del_auth_cache_session<X: IntoAuthCacheKey>(key: X)
+        -> impl Future<Output = Result<(), NanoServiceError>> + Send {
+            ENDED.lock().unwrap().push(key.into_auth_cache_key().key);
+            std::future::ready(Ok(()))
+        }
+    }
+
+    fn ended(user_id: i32) -> Vec<String> {
+        let prefix = format!("{}-", user_id);
+        ENDED.lock().unwrap().iter().filter(|key| key.starts_with(&prefix)).cloned().collect()
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(SessionLimit::from_config::<RejectConfig>(), SessionLimit {
+            max_sessions: Some(2),
+            policy: SessionLimitPolicy::Reject,
+        });
+        assert_eq!(SessionLimit::from_config::<UnsetConfig>(), SessionLimit {
+            max_sessions: None,
+            policy: SessionLimitPolicy::EvictOldest,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_under_limit() {
+        assert_eq!(enforce_session_limit::<LimitConfig, SessionsMock>(1).await.unwrap(), 0);
+        assert_eq!(enforce_session_limit::<UnsetConfig, SessionsMock>(5).await.unwrap(), 0);
+        assert!(ended(1).is_empty() && ended(5).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_evicts_oldest() {
+        assert_eq!(enforce_session_limit::<LimitConfig, SessionsMock>(3).await.unwrap(), 2);
+        assert_eq!(ended(3), vec!["3-0".to_string(), "3-1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_rejects() {
+        let error = enforce_session_limit::<RejectConfig, SessionsMock>(4).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        assert!(ended(4).is_empty());
+    }
+}
@@ -0,0 +1,341 @@
+//! Picks the keys tokens are signed and verified with.
+//!
+//! # Overview
+//! Tokens are signed with the shared `SECRET_KEY` using HS256 by default. Setting `JWT_ALGORITHM` to
+//! `RS256` or `EdDSA` signs them with the private key in `JWT_PRIVATE_KEY` instead, and the matching
+//! public key is published by `jwks` so other services can verify tokens without holding a secret
+//! that lets them mint tokens too.
+//!
+//! # Variables
+//! * `JWT_ALGORITHM` - `HS256`, `RS256` or `EdDSA`, anything else is read as `HS256`
+//! * `SECRET_KEY` - The HMAC secret used for `HS256`
+//! * `JWT_PRIVATE_KEY` - The PKCS#8 PEM private key used for `RS256` and `EdDSA`, RSA keys can also be
+//!   PKCS#1. Escaped `\n` sequences are read as newlines so the key can sit on one line
+//! * `JWT_KEY_ID` - The `kid` of the key, defaults to a hash of the public key
+//! * `JWT_ISSUER` - The `iss` claim tokens are issued with and have to carry, not checked when not set
+//! * `JWT_AUDIENCE` - The `aud` claim tokens are issued with and have to carry, not checked when not set
+//!
+//! ## Notes
+//! - Changing the algorithm invalidates every token issued before the change.
+//! - Giving each environment and service its own issuer or audience stops a token minted for one
+//!   from being replayed against another that shares its key. Setting either invalidates every
+//!   token issued without it.
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents};
+use serde_json::{json, Value};
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The keys for the configured algorithm.
+///
+/// # Fields
+/// * `algorithm` - The algorithm tokens are signed with.
+/// * `key_id` - The `kid` put in token headers, `None` for `HS256`.
+/// * `encoding` - The key tokens are signed with.
+/// * `decoding` - The key tokens are verified with.
+/// * `jwk` - The public key as a JSON Web Key, `None` for `HS256` as the secret is never published.
+pub struct TokenKeys {
+    pub algorithm: Algorithm,
+    pub key_id: Option<String>,
+    pub encoding: EncodingKey,
+    pub decoding: DecodingKey,
+    pub jwk: Option<Value>,
+}
+
+impl TokenKeys {
+
+    /// The header for new tokens.
+    pub fn header(&self) -> Header {
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.key_id.clone();
+        header
+    }
+
+    /// The validation for incoming tokens, only accepting the configured algorithm.
+    pub fn validation(&self) -> Validation {
+        let mut validation = Validation::new(self.algorithm);
+        validation.required_spec_claims.remove("exp");
+        validation
+    }
+}
+
+
+/// The parsed asymmetric keys, keyed by algorithm, key ID and PEM so a rotated key is parsed again.
+static ASYMMETRIC_KEYS: LazyLock<Mutex<HashMap<String, Arc<TokenKeys>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+
+fn key_error(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::Unknown)
+}
+
+
+/// Reads the PEM from config, turning escaped newlines back into newlines.
+fn read_private_key<X: GetConfigVariable>() -> Result<String, NanoServiceError> {
+    let key = X::get_config_variable("JWT_PRIVATE_KEY".to_string())?;
+    Ok(key.trim().replace("\\n", "\n"))
+}
+
+
+/// The default key ID, a short hash of the public key.
+fn default_key_id(public_key: &[u8]) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, public_key);
+    URL_SAFE_NO_PAD.encode(&digest.as_ref()[..12])
+}
+
+
+/// Builds the RS256 keys from a PKCS#8 or PKCS#1 PEM private key.
+fn rsa_keys(private_pem: &str, key_id: Option<String>) -> Result<TokenKeys, NanoServiceError> {
+    let der = pem::parse(private_pem)
+        .map_err(|e| key_error(format!("JWT_PRIVATE_KEY is not a PEM key: {}", e)))?;
+    let key_pair = RsaKeyPair::from_pkcs8(der.contents())
+        .or_else(|_| RsaKeyPair::from_der(der.contents()))
+        .map_err(|e| key_error(format!("JWT_PRIVATE_KEY is not an RSA private key: {}", e)))?;
+    let components: RsaPublicKeyComponents<Vec<u8>> = key_pair.public().into();
+    let n = URL_SAFE_NO_PAD.encode(&components.n);
+    let e = URL_SAFE_NO_PAD.encode(&components.e);
+    let key_id = key_id.unwrap_or_else(|| default_key_id(key_pair.public_key().as_ref()));
+
+    let encoding = EncodingKey::from_rsa_pem(private_pem.as_bytes())
+        .map_err(|e| key_error(format!("JWT_PRIVATE_KEY is not an RSA private key: {}", e)))?;
+    let decoding = DecodingKey::from_rsa_components(&n, &e)
+        .map_err(|e| key_error(format!("Failed to build the RSA public key: {}", e)))?;
+    Ok(TokenKeys {
+        algorithm: Algorithm::RS256,
+        jwk: Some(json!({"kty": "RSA", "use": "sig", "alg": "RS256", "kid": key_id, "n": n, "e": e})),
+        key_id: Some(key_id),
+        encoding,
+        decoding,
+    })
+}
+
+
+/// Builds the EdDSA keys from a PKCS#8 PEM Ed25519 private key.
+fn ed25519_keys(private_pem: &str, key_id: Option<String>) -> Result<TokenKeys, NanoServiceError> {
+    let der = pem::parse(private_pem)
+        .map_err(|e| key_error(format!("JWT_PRIVATE_KEY is not a PEM key: {}", e)))?;
+    let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der.contents())
+        .map_err(|e| key_error(format!("JWT_PRIVATE_KEY is not an Ed25519 private key: {}", e)))?;
+    let x = URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref());
+    let key_id = key_id.unwrap_or_else(|| default_key_id(key_pair.public_key().as_ref()));
+
+    let encoding = EncodingKey::from_ed_pem(private_pem.as_bytes())
+        .map_err(|e| key_error(format!("JWT_PRIVATE_KEY is not an Ed25519 private key: {}", e)))?;
+    let decoding = DecodingKey::from_ed_components(&x)
+        .map_err(|e| key_error(format!("Failed to build the Ed25519 public key: {}", e)))?;
+    Ok(TokenKeys {
+        algorithm: Algorithm::EdDSA,
+        jwk: Some(json!({"kty": "OKP", "crv": "Ed25519", "use": "sig", "alg": "EdDSA", "kid": key_id, "x": x})),
+        key_id: Some(key_id),
+        encoding,
+        decoding,
+    })
+}
+
+
+/// Reads an optional claim from config, `None` if it is not set or blank.
+fn configured_claim<X: GetConfigVariable>(variable: &str) -> Option<String> {
+    X::get_config_variable(variable.to_string())
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+
+/// The `iss` claim tokens are issued with, from `JWT_ISSUER`.
+pub fn token_issuer<X: GetConfigVariable>() -> Option<String> {
+    configured_claim::<X>("JWT_ISSUER")
+}
+
+
+/// The `aud` claim tokens are issued with, from `JWT_AUDIENCE`.
+pub fn token_audience<X: GetConfigVariable>() -> Option<String> {
+    configured_claim::<X>("JWT_AUDIENCE")
+}
+
+
+/// Gets the keys for the configured algorithm.
+///
+/// # Returns
+/// * `Ok(Arc<TokenKeys>)` - The keys
+/// * `Err(NanoServiceError)` - If the key for the algorithm is missing or invalid
+pub fn token_keys<X: GetConfigVariable>() -> Result<Arc<TokenKeys>, NanoServiceError> {
+    let algorithm = X::get_config_variable("JWT_ALGORITHM".to_string()).unwrap_or_default();
+    let algorithm = match algorithm.trim() {
+        "RS256" => Algorithm::RS256,
+        "EdDSA" => Algorithm::EdDSA,
+        _ => {
+            let secret = X::get_config_variable("SECRET_KEY".to_string())?;
+            return Ok(Arc::new(TokenKeys {
+                algorithm: Algorithm::HS256,
+                key_id: None,
+                encoding: EncodingKey::from_secret(secret.as_ref()),
+                decoding: DecodingKey::from_secret(secret.as_ref()),
+                jwk: None,
+            }))
+        }
+    };
+    let private_pem = read_private_key::<X>()?;
+    let key_id = X::get_config_variable("JWT_KEY_ID".to_string()).ok().filter(|id| !id.trim().is_empty());
+    let cache_key = format!("{:?}:{:?}:{}", algorithm, key_id, private_pem);
+    if let Some(keys) = ASYMMETRIC_KEYS.lock().unwrap().get(&cache_key) {
+        return Ok(keys.clone())
+    }
+    let keys = Arc::new(match algorithm {
+        Algorithm::RS256 => rsa_keys(&private_pem, key_id)?,
+        _ => ed25519_keys(&private_pem, key_id)?,
+    });
+    ASYMMETRIC_KEYS.lock().unwrap().insert(cache_key, keys.clone());
+    Ok(keys)
+}
+
+
+/// Builds the JSON Web Key Set published at `/.well-known/jwks.json`.
+///
+/// # Returns
+/// * `Ok(Value)` - The key set, with no keys when tokens are signed with `HS256`
+/// * `Err(NanoServiceError)` - If the keys could not be read
+pub fn jwks<X: GetConfigVariable>() -> Result<Value, NanoServiceError> {
+    let keys = token_keys::<X>()?;
+    Ok(json!({"keys": keys.jwk.iter().collect::<Vec<_>>()}))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::checks::NoRoleCheck;
+    use crate::token::token::HeaderToken;
+    use crate::users::UserRole;
+
+    static RSA_KEY: &str = include_str!("test_keys/rsa_private.pem");
+    static ED25519_KEY: &str = include_str!("test_keys/ed25519_private.pem");
+
+    struct RsaConfig;
+
+    impl GetConfigVariable for RsaConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "JWT_ALGORITHM" => Ok("RS256".to_string()),
+                "JWT_PRIVATE_KEY" => Ok(RSA_KEY.replace('\n', "\\n")),
+                "SECRET_KEY" => Ok("secret".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    struct Ed25519Config;
+
+    impl GetConfigVariable for Ed25519Config {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "JWT_ALGORITHM" => Ok("EdDSA".to_string()),
+                "JWT_PRIVATE_KEY" => Ok(ED25519_KEY.to_string()),
+                "JWT_KEY_ID" => Ok("ed-key-1".to_string()),
+                "SECRET_KEY" => Ok("secret".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    struct HmacConfig;
+
+    impl GetConfigVariable for HmacConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SECRET_KEY" => Ok("secret".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    fn token<X: GetConfigVariable>() -> HeaderToken<X, NoRoleCheck> {
+        HeaderToken::new("agent".to_string(), 7, UserRole::Admin)
+    }
+
+    #[test]
+    fn test_rs256_round_trip() {
+        let encoded = token::<RsaConfig>().encode().unwrap();
+        let header = jsonwebtoken::decode_header(&encoded).unwrap();
+        assert_eq!(header.alg, Algorithm::RS256);
+        assert!(header.kid.is_some());
+
+        let decoded = HeaderToken::<RsaConfig, NoRoleCheck>::decode(&encoded).unwrap();
+        assert_eq!(decoded.user_id, 7);
+    }
+
+    #[test]
+    fn test_eddsa_round_trip() {
+        let encoded = token::<Ed25519Config>().encode().unwrap();
+        let header = jsonwebtoken::decode_header(&encoded).unwrap();
+        assert_eq!(header.alg, Algorithm::EdDSA);
+        assert_eq!(header.kid, Some("ed-key-1".to_string()));
+
+        let decoded = HeaderToken::<Ed25519Config, NoRoleCheck>::decode(&encoded).unwrap();
+        assert_eq!(decoded.user_id, 7);
+    }
+
+    #[test]
+    fn test_other_algorithm_rejected() {
+        let hmac_token = token::<HmacConfig>().encode().unwrap();
+        assert!(HeaderToken::<RsaConfig, NoRoleCheck>::decode(&hmac_token).is_err());
+
+        let ed_token = token::<Ed25519Config>().encode().unwrap();
+        assert!(HeaderToken::<RsaConfig, NoRoleCheck>::decode(&ed_token).is_err());
+        assert!(HeaderToken::<HmacConfig, NoRoleCheck>::decode(&ed_token).is_err());
+    }
+
+    #[test]
+    fn test_jwks() {
+        let rsa = jwks::<RsaConfig>().unwrap();
+        let key = &rsa["keys"][0];
+        assert_eq!(key["kty"], "RSA");
+        assert_eq!(key["alg"], "RS256");
+        assert_eq!(key["e"], "AQAB");
+        assert!(key["n"].as_str().unwrap().len() > 300);
+
+        let ed = jwks::<Ed25519Config>().unwrap();
+        let key = &ed["keys"][0];
+        assert_eq!(key["kty"], "OKP");
+        assert_eq!(key["crv"], "Ed25519");
+        assert_eq!(key["kid"], "ed-key-1");
+
+        let hmac = jwks::<HmacConfig>().unwrap();
+        assert_eq!(hmac, json!({"keys": []}));
+    }
+
+    #[test]
+    fn test_jwks_verifies_token() {
+        let encoded = token::<RsaConfig>().encode().unwrap();
+        let set = jwks::<RsaConfig>().unwrap();
+        let key = &set["keys"][0];
+        let decoding = DecodingKey::from_rsa_components(
+            key["n"].as_str().unwrap(),
+            key["e"].as_str().unwrap()
+        ).unwrap();
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.required_spec_claims.remove("exp");
+        let decoded = jsonwebtoken::decode::<serde_json::Value>(&encoded, &decoding, &validation).unwrap();
+        assert_eq!(decoded.claims["user_id"], 7);
+    }
+
+    #[test]
+    fn test_unknown_algorithm_uses_hs256() {
+        struct BadConfig;
+        impl GetConfigVariable for BadConfig {
+            fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+                match variable.as_str() {
+                    "JWT_ALGORITHM" => Ok("none".to_string()),
+                    _ => Ok("secret".to_string())
+                }
+            }
+        }
+        let keys = token_keys::<BadConfig>().unwrap();
+        assert_eq!(keys.algorithm, Algorithm::HS256);
+        assert!(keys.jwk.is_none());
+    }
+}
@@ -0,0 +1,259 @@
+//! Sliding expiration for auth sessions.
+//!
+//! # Overview
+//! A token is valid for `TOKEN_ACCESS_TTL` after it is issued, see `token::lifetimes`. Every
+//! request made with it through `api_endpoint` runs `slide_session` after the session is loaded, which:
+//! - refuses the session if it has not been used for `SESSION_IDLE_TIMEOUT_MINUTES`
+//! - re-issues the token when it is within `SESSION_REISSUE_WITHIN_MINUTES` of expiring, with the
+//!   same session and a new expiry `SESSION_IDLE_TIMEOUT_MINUTES` from now
+//! - records the request as the session's `last_seen` in the cache
+//!
+//! The re-issued token is sent back in the `X-Refreshed-Token` header of a successful response and
+//! the client swaps it in for the old one, so a session in use does not expire. Browser sessions
+//! have it set in the token cookie instead, see `token::cookies`.
+//!
+//! # Notes
+//! - `last_seen` is only written once every `TOUCH_INTERVAL_SECONDS` or when a token is re-issued,
+//!   so a busy session does not write to the cache, and broadcast to its peers, on every request.
+//! - Impersonation tokens are never re-issued, the super admin starts a new impersonation instead.
+//! - The old token stays valid until it expires, the session is the same for both.
+//!
+//! # Variables
+//! * `SESSION_IDLE_TIMEOUT_MINUTES` - How long a session can go unused, defaults to 20
+//! * `SESSION_REISSUE_WITHIN_MINUTES` - How close to expiring a token is re-issued, defaults to 5
+//!   and `0` turns re-issuing off
+use actix_web::HttpResponse;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use chrono::{Duration, Utc};
+use std::future::Future;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::token::checks::CheckUserRole;
+use crate::token::session_cache::structs::AuthCacheSession;
+use crate::token::session_cache::traits::TouchAuthCacheSession;
+use crate::token::token::HeaderToken;
+use crate::token::cookies::add_token_cookie;
+use crate::token::lifetimes::TOKEN_LIFETIME_MINUTES;
+
+
+/// The response header a re-issued token is sent in.
+pub const REFRESHED_TOKEN_HEADER: &str = "x-refreshed-token";
+
+/// How close to expiring a token is re-issued when not configured.
+pub const DEFAULT_REISSUE_WITHIN_MINUTES: i64 = 5;
+
+/// How often a session's `last_seen` is written for a session in use.
+pub const TOUCH_INTERVAL_SECONDS: i64 = 60;
+
+
+/// The sliding expiration config.
+///
+/// # Fields
+/// * `idle_timeout` - How long a session can go unused, and how long a re-issued token lasts.
+/// * `reissue_within` - How close to expiring a token is re-issued, `None` if re-issuing is off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlidingSettings {
+    pub idle_timeout: Duration,
+    pub reissue_within: Option<Duration>,
+}
+
+impl SlidingSettings {
+
+    /// Reads the settings from the config, falling back to the defaults for unset or invalid values.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let idle_minutes = Y::get_int("SESSION_IDLE_TIMEOUT_MINUTES").ok()
+            .filter(|minutes| *minutes > 0)
+            .unwrap_or(TOKEN_LIFETIME_MINUTES);
+        let reissue_minutes = Y::get_int("SESSION_REISSUE_WITHIN_MINUTES").ok()
+            .filter(|minutes| *minutes >= 0)
+            .unwrap_or(DEFAULT_REISSUE_WITHIN_MINUTES);
+        SlidingSettings {
+            idle_timeout: Duration::minutes(idle_minutes),
+            reissue_within: (reissue_minutes > 0).then(|| Duration::minutes(reissue_minutes)),
+        }
+    }
+}
+
+
+/// Checks the session is not idle, records the request and re-issues the token if it is close to
+/// expiring.
+///
+/// # Arguments
+/// * `jwt` - The token the request was made with.
+/// * `session` - The token's session from the cache.
+///
+/// # Returns
+/// * `Ok(Some(String))` - The encoded re-issued token
+/// * `Ok(None)` - If the token was not re-issued
+/// * `Err(NanoServiceError)` - `Unauthorized` if the session has been idle for too long
+pub async fn slide_session<Y, R, Z>(jwt: &HeaderToken<Y, R>, session: &AuthCacheSession) -> Result<Option<String>, NanoServiceError>
+where
+    Y: GetConfigVariable,
+    R: CheckUserRole,
+    Z: TouchAuthCacheSession,
+{
+    let settings = SlidingSettings::from_config::<Y>();
+    let now = Utc::now();
+    if now - session.last_seen > settings.idle_timeout {
+        return Err(NanoServiceError::new(
+            "Session has been idle for too long".to_string(),
+            NanoServiceErrorStatus::Unauthorized
+        ).with_message_key("auth.session_idle", vec![]))
+    }
+    let reissued = match settings.reissue_within {
+        Some(within) if jwt.impersonator_id.is_none() && jwt.time_expire - now <= within => {
+            Some(jwt.reissue(settings.idle_timeout))
+        },
+        _ => None
+    };
+    let time_expire = reissued.as_ref()
+        .map(|token| token.time_expire.max(session.time_expire))
+        .unwrap_or(session.time_expire);
+    if reissued.is_some() || now - session.last_seen > Duration::seconds(TOUCH_INTERVAL_SECONDS) {
+        Z::touch_auth_cache_session(&jwt.unique_id, now, time_expire).await?;
+    }
+    reissued.map(|token| token.encode()).transpose()
+}
+
+
+/// Runs the handler and adds the re-issued token, if any, to its response, in the header and in
+/// the token cookie if tokens are set as cookies. Error responses are left as they are, the client
+/// keeps its token and it is re-issued on the next request.
+///
+/// # Arguments
+/// * `refreshed_token` - The token from `slide_session`.
+/// * `handler` - The body of the handler.
+pub async fn with_refreshed_token<Y, F>(refreshed_token: Option<String>, handler: F) -> Result<HttpResponse, NanoServiceError>
+where
+    Y: GetConfigVariable,
+    F: Future<Output = Result<HttpResponse, NanoServiceError>>
+{
+    let mut response = handler.await?;
+    if let Some(token) = refreshed_token {
+        if let Ok(value) = HeaderValue::from_str(&token) {
+            response.headers_mut().insert(HeaderName::from_static(REFRESHED_TOKEN_HEADER), value);
+        }
+        add_token_cookie::<Y>(&mut response, &token)?;
+    }
+    Ok(response)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::checks::NoRoleCheck;
+    use crate::token::session_cache::structs::{IntoAuthCacheKey, IntoAuthCacheSession};
+    use crate::users::UserRole;
+    use chrono::DateTime;
+    use std::sync::Mutex;
+
+    /// The touches recorded, as the key and `time_expire`.
+    static TOUCHES: Mutex<Vec<(String, DateTime<Utc>)>> = Mutex::new(Vec::new());
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SESSION_IDLE_TIMEOUT_MINUTES" => Ok("30".to_string()),
+                "SESSION_REISSUE_WITHIN_MINUTES" => Ok("5".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    struct NoReissueConfig;
+
+    impl GetConfigVariable for NoReissueConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SESSION_REISSUE_WITHIN_MINUTES" => Ok("0".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    struct RecordTouchMock;
+
+    impl TouchAuthCacheSession for RecordTouchMock {
+        fn touch_auth_cache_session<X: IntoAuthCacheKey + Send>(key: &X, _last_seen: DateTime<Utc>, time_expire: DateTime<Utc>)
+        -> impl std::future::Future<Output = Result<(), NanoServiceError>> + Send {
+            TOUCHES.lock().unwrap().push((key.into_auth_cache_key().key, time_expire));
+            std::future::ready(Ok(()))
+        }
+    }
+
+    fn touches(key: &str) -> Vec<DateTime<Utc>> {
+        TOUCHES.lock().unwrap().iter().filter(|(touched, _)| touched == key).map(|(_, time_expire)| *time_expire).collect()
+    }
+
+    /// A token expiring in `minutes` and its session last seen `idle_seconds` ago.
+    fn session_token<Y: GetConfigVariable>(minutes: i64, idle_seconds: i64) -> (HeaderToken<Y, NoRoleCheck>, AuthCacheSession) {
+        let mut token = HeaderToken::<Y, NoRoleCheck>::new("some-agent".to_string(), 2, UserRole::Worker);
+        token.time_expire = Utc::now() + Duration::minutes(minutes);
+        let mut session = token.into_auth_cache_session();
+        session.last_seen = Utc::now() - Duration::seconds(idle_seconds);
+        (token, session)
+    }
+
+    #[test]
+    fn test_settings() {
+        assert_eq!(SlidingSettings::from_config::<MockConfig>(), SlidingSettings {
+            idle_timeout: Duration::minutes(30),
+            reissue_within: Some(Duration::minutes(5)),
+        });
+        assert_eq!(SlidingSettings::from_config::<NoReissueConfig>(), SlidingSettings {
+            idle_timeout: Duration::minutes(TOKEN_LIFETIME_MINUTES),
+            reissue_within: None,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_fresh_token_is_not_reissued() {
+        let (token, session) = session_token::<MockConfig>(15, 5);
+        assert_eq!(slide_session::<_, _, RecordTouchMock>(&token, &session).await.unwrap(), None);
+        assert!(touches(&token.unique_id).is_empty());
+
+        let (token, session) = session_token::<MockConfig>(15, 120);
+        assert_eq!(slide_session::<_, _, RecordTouchMock>(&token, &session).await.unwrap(), None);
+        assert_eq!(touches(&token.unique_id), vec![session.time_expire]);
+    }
+
+    #[tokio::test]
+    async fn test_expiring_token_is_reissued() {
+        let (token, session) = session_token::<MockConfig>(2, 5);
+        let encoded = slide_session::<_, _, RecordTouchMock>(&token, &session).await.unwrap().unwrap();
+        let reissued = HeaderToken::<MockConfig, NoRoleCheck>::decode(&encoded).unwrap();
+        assert_eq!((reissued.unique_id.as_str(), reissued.user_id), (token.unique_id.as_str(), 2));
+        assert!(reissued.time_expire > Utc::now() + Duration::minutes(29));
+        assert_eq!(touches(&token.unique_id), vec![reissued.time_expire]);
+
+        let (token, session) = session_token::<NoReissueConfig>(2, 5);
+        assert_eq!(slide_session::<_, _, RecordTouchMock>(&token, &session).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_token_is_not_reissued() {
+        let (mut token, session) = session_token::<MockConfig>(2, 5);
+        token.impersonator_id = Some(1);
+        assert_eq!(slide_session::<_, _, RecordTouchMock>(&token, &session).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_idle_session_is_refused() {
+        let (token, session) = session_token::<MockConfig>(2, 31 * 60);
+        let error = slide_session::<_, _, RecordTouchMock>(&token, &session).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Unauthorized);
+        assert!(touches(&token.unique_id).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_with_refreshed_token() {
+        let response = with_refreshed_token::<MockConfig, _>(Some("new-token".to_string()), async { Ok(HttpResponse::Ok().finish()) }).await.unwrap();
+        assert_eq!(response.headers().get(REFRESHED_TOKEN_HEADER).unwrap(), "new-token");
+
+        let response = with_refreshed_token::<MockConfig, _>(None, async { Ok(HttpResponse::Ok().finish()) }).await.unwrap();
+        assert!(response.headers().get(REFRESHED_TOKEN_HEADER).is_none());
+    }
+}
@@ -3,21 +3,18 @@
 use actix_web::{dev::Payload, FromRequest, HttpRequest};
 use chrono::{DateTime, Utc};
 use futures::future::{err, ok, Ready};
-use jsonwebtoken::{
-    Algorithm, 
-    DecodingKey, 
-    EncodingKey, 
-    Header, 
-    Validation, 
-    decode, 
-    encode,
-};
+use jsonwebtoken::{decode, encode};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use std::marker::PhantomData;
 
 // Local crate imports
 use crate::token::checks::CheckUserRole;
+use crate::token::signing::{token_keys, token_issuer, token_audience};
+use crate::token::lifetimes::TokenLifetimes;
+use crate::token::cookies::token_from_request;
+use crate::devices::device_fingerprint;
+use crate::organizations::DEFAULT_ORG_ID;
 use crate::users::UserRole;
 use utils::{
     config::GetConfigVariable,
@@ -30,6 +27,11 @@ use crate::token::session_cache::{
 use std::future::Future;
 
 
+fn default_org_id() -> i32 {
+    DEFAULT_ORG_ID
+}
+
+
 /// The auth token extracted from the header for logged in users.
 /// 
 /// # Fields
@@ -39,6 +41,15 @@ use std::future::Future;
 /// * `time_started` - The time the token was created
 /// * `time_expire` - The time the token will expire
 /// * `user_agent` - The device info of the user
+/// * `impersonator_id` - The id of the super admin acting as the user, `None` unless the token was
+///   issued by `HeaderToken::impersonate`
+/// * `org_id` - The id of the organization the user is acting in, tokens issued before
+///   organizations existed act in `DEFAULT_ORG_ID`
+/// * `iat` - When the token was issued, in seconds since the epoch
+/// * `nbf` - When the token is valid from, in seconds since the epoch, tokens issued before these
+///   claims existed are valid from the epoch
+/// * `iss` - The issuer of the token, from `JWT_ISSUER`, see `token::signing`
+/// * `aud` - The audience of the token, from `JWT_AUDIENCE`, see `token::signing`
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HeaderToken<X: GetConfigVariable, Y: CheckUserRole> {
     pub unique_id: String,
@@ -47,6 +58,18 @@ pub struct HeaderToken<X: GetConfigVariable, Y: CheckUserRole> {
     pub time_started: DateTime<Utc>,
     pub time_expire: DateTime<Utc>,
     pub user_agent: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator_id: Option<i32>,
+    #[serde(default = "default_org_id")]
+    pub org_id: i32,
+    #[serde(default)]
+    pub iat: i64,
+    #[serde(default)]
+    pub nbf: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
     pub var_handle: PhantomData<X>,
     pub role_handle: PhantomData<Y>
 }
@@ -59,7 +82,10 @@ impl<X: GetConfigVariable, Y: CheckUserRole> IntoAuthCacheSession for HeaderToke
             role: self.role.clone(),
             time_started: self.time_started,
             time_expire: self.time_expire,
-            user_agent: self.user_agent.clone()
+            user_agent: self.user_agent.clone(),
+            permissions: Vec::new(),
+            last_seen: Utc::now(),
+            device_fingerprint: Some(device_fingerprint(&self.user_agent))
         }
     }
 }
@@ -75,7 +101,7 @@ impl<X: GetConfigVariable, Y: CheckUserRole> IntoAuthCacheKey for HeaderToken<X,
 
 impl <X: GetConfigVariable, Y: CheckUserRole>HeaderToken<X, Y> {
 
-    /// Creates a new token for a user.
+    /// Creates a new token for a user, valid for `TOKEN_ACCESS_TTL`, see `token::lifetimes`.
     /// 
     /// # Arguments
     /// * `user_agent` - The device info of the user
@@ -85,18 +111,93 @@ impl <X: GetConfigVariable, Y: CheckUserRole>HeaderToken<X, Y> {
     /// # Returns
     /// * A new token for the user
     pub fn new(user_agent: String, user_id: i32, user_role: UserRole) -> Self {
+        let now = Utc::now();
         HeaderToken {
             unique_id: Uuid::new_v4().to_string(),
-            user_id: user_id,
+            user_id,
             role: user_role,
-            time_started: Utc::now(),
-            time_expire: Utc::now() + chrono::Duration::minutes(20),
-            user_agent: user_agent,
+            time_started: now,
+            time_expire: now + TokenLifetimes::from_config::<X>().access_ttl,
+            user_agent,
+            impersonator_id: None,
+            org_id: DEFAULT_ORG_ID,
+            iat: now.timestamp(),
+            nbf: now.timestamp(),
+            iss: token_issuer::<X>(),
+            aud: token_audience::<X>(),
             var_handle: PhantomData,
             role_handle: PhantomData
         }
     }
 
+    /// Creates a token for a super admin to act as another user.
+    ///
+    /// # Arguments
+    /// * `user_agent` - The device info of the super admin
+    /// * `user_id` - The id of the user being impersonated
+    /// * `user_role` - The role of the user being impersonated
+    /// * `impersonator_id` - The id of the super admin
+    /// * `lifetime` - How long the token is valid for
+    ///
+    /// # Returns
+    /// * A token for the user carrying the `impersonator_id` claim
+    pub fn impersonate(user_agent: String, user_id: i32, user_role: UserRole, impersonator_id: i32, lifetime: chrono::Duration) -> Self {
+        let mut token = Self::new(user_agent, user_id, user_role).with_lifetime(lifetime);
+        token.impersonator_id = Some(impersonator_id);
+        token
+    }
+
+    /// Re-issues the token for the same session with a new expiry, see `token::sliding`.
+    ///
+    /// # Arguments
+    /// * `lifetime` - How long the new token is valid for from now
+    ///
+    /// # Returns
+    /// * A token with the same session, user, role and device info expiring `lifetime` from now
+    pub fn reissue(&self, lifetime: chrono::Duration) -> Self {
+        let now = Utc::now();
+        HeaderToken {
+            unique_id: self.unique_id.clone(),
+            user_id: self.user_id,
+            role: self.role.clone(),
+            time_started: self.time_started,
+            time_expire: now + lifetime,
+            user_agent: self.user_agent.clone(),
+            impersonator_id: self.impersonator_id,
+            org_id: self.org_id,
+            iat: now.timestamp(),
+            nbf: now.timestamp(),
+            iss: self.iss.clone(),
+            aud: self.aud.clone(),
+            var_handle: PhantomData,
+            role_handle: PhantomData
+        }
+    }
+
+    /// Sets how long the token is valid for from when it was issued.
+    ///
+    /// # Arguments
+    /// * `lifetime` - How long the token is valid for
+    ///
+    /// # Returns
+    /// * The token expiring `lifetime` after `time_started`
+    pub fn with_lifetime(mut self, lifetime: chrono::Duration) -> Self {
+        self.time_expire = self.time_started + lifetime;
+        self
+    }
+
+    /// Sets the organization the token acts in.
+    ///
+    /// # Arguments
+    /// * `org_id` - The id of the organization
+    ///
+    /// # Returns
+    /// * The token acting in the organization
+    pub fn in_org(mut self, org_id: i32) -> Self {
+        self.org_id = org_id;
+        self
+    }
+
     /// Checks the device info in the request to see if it matches the device info in the token.
     /// 
     /// # Arguments
@@ -122,13 +223,14 @@ impl <X: GetConfigVariable, Y: CheckUserRole>HeaderToken<X, Y> {
         Ok(())
     }
 
-    /// Checks if the token has expired.
+    /// Checks if the token has expired or is not valid yet, allowing for `TOKEN_CLOCK_SKEW`.
     /// 
     /// # Returns
-    /// * error if the token has expired
+    /// * error if the token has expired, or was issued or is valid from a time still to come
     pub fn check_if_expired(&self) -> Result<(), NanoServiceError> {
+        let skew = TokenLifetimes::from_config::<X>().clock_skew;
         let now = Utc::now();
-        if now > self.time_expire {
+        if now > self.time_expire + skew {
             return Err(
                 NanoServiceError::new(
                     "Token has expired".to_string(),
@@ -136,17 +238,25 @@ impl <X: GetConfigVariable, Y: CheckUserRole>HeaderToken<X, Y> {
                 )
             )
         }
+        let latest = (now + skew).timestamp();
+        if self.nbf > latest || self.iat > latest {
+            return Err(
+                NanoServiceError::new(
+                    "Token is not valid yet".to_string(),
+                    NanoServiceErrorStatus::Unauthorized
+                )
+            )
+        }
         Ok(())
     }
 
-    /// Encodes the struct into a token.
+    /// Encodes the struct into a token, signed with the algorithm in `JWT_ALGORITHM`.
     ///
     /// # Returns
     /// encoded token with fields of the current struct
     pub fn encode(self) -> Result<String, NanoServiceError> {
-        let key_str = <X>::get_config_variable("SECRET_KEY".to_string())?;
-        let key = EncodingKey::from_secret(key_str.as_ref());
-        return match encode(&Header::default(), &self, &key) {
+        let keys = token_keys::<X>()?;
+        match encode(&keys.header(), &self, &keys.encoding) {
             Ok(token) => Ok(token),
             Err(error) => Err(
                 NanoServiceError::new(
@@ -154,10 +264,10 @@ impl <X: GetConfigVariable, Y: CheckUserRole>HeaderToken<X, Y> {
                     NanoServiceErrorStatus::Unauthorized
                 )
             )
-        };
+        }
     }
 
-    /// Decodes the token into a struct.
+    /// Decodes the token into a struct, checking it carries the configured `iss` and `aud` claims.
     ///
     /// # Arguments
     /// * `token` - The token to be decoded.
@@ -165,20 +275,25 @@ impl <X: GetConfigVariable, Y: CheckUserRole>HeaderToken<X, Y> {
     /// # Returns
     /// decoded token with fields of the current struct
     pub fn decode(token: &str) -> Result<Self, NanoServiceError> {
-        let key_str = <X>::get_config_variable("SECRET_KEY".to_string())?;
-        let key = DecodingKey::from_secret(key_str.as_ref());
-        let mut validation = Validation::new(Algorithm::HS256);
-        validation.required_spec_claims.remove("exp");
-
-        match decode::<Self>(token, &key, &validation) {
-            Ok(token_data) => return Ok(token_data.claims),
-            Err(error) => return Err(
+        let keys = token_keys::<X>()?;
+        let mut validation = keys.validation();
+        if let Some(issuer) = token_issuer::<X>() {
+            validation.set_issuer(&[issuer]);
+            validation.required_spec_claims.insert("iss".to_string());
+        }
+        if let Some(audience) = token_audience::<X>() {
+            validation.set_audience(&[audience]);
+            validation.required_spec_claims.insert("aud".to_string());
+        }
+        match decode::<Self>(token, &keys.decoding, &validation) {
+            Ok(token_data) => Ok(token_data.claims),
+            Err(error) => Err(
                 NanoServiceError::new(
                     error.to_string(),
                     NanoServiceErrorStatus::Unauthorized
                 )
             )
-        };
+        }
     }
 
     /// Gets the session cache via the token's unique id.
@@ -205,33 +320,19 @@ impl<X: GetConfigVariable, Y: CheckUserRole> FromRequest for HeaderToken<X, Y> {
     /// * `req` - The request to extract the token from
     /// 
     /// # Returns
-    /// * The token or an unauthorized error which is directly returned to the user
+    /// * The token or an unauthorized error, or a forbidden error if the token came from a cookie
+    ///   and the request fails the CSRF check, which is directly returned to the user
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        // extract the token from the header
-        let raw_data = match req.headers().get("token") {
-            Some(data) => data,
-            None => {
-                return err(NanoServiceError {
-                    status: NanoServiceErrorStatus::Unauthorized,
-                    message: "token not in header under key 'token'".to_string()
-                })
-            }
-        };
-        // convert the token to a string
-        let message = match raw_data.to_str() {
-            Ok(token) => token.to_string(),
-            Err(_) => {
-                return err(NanoServiceError {
-                    status: NanoServiceErrorStatus::Unauthorized,
-                    message: "token not a valid string".to_string()
-                })
-            }
+        // extract the token from the header, or the cookie for browser sessions
+        let message = match token_from_request::<X>(req) {
+            Ok(message) => message,
+            Err(e) => return err(e)
         };
         // decode the token and perform role and device checks
         let token = match HeaderToken::decode(&message) {
             Ok(token) => {
                 let unwrapped_token = token;
-                match unwrapped_token.check_device_info(&req) {
+                match unwrapped_token.check_device_info(req) {
                     Ok(_) => (),
                     Err(e) => {
                         return err(e)
@@ -243,15 +344,9 @@ impl<X: GetConfigVariable, Y: CheckUserRole> FromRequest for HeaderToken<X, Y> {
                         return err(e)
                     }
                 }
-                // check if the token has expired
-                let now = Utc::now();
-                if now > unwrapped_token.time_expire {
-                    return err(
-                        NanoServiceError::new(
-                            "Token has expired".to_string(),
-                            NanoServiceErrorStatus::Unauthorized
-                        )
-                    )
+                // check if the token has expired or is not valid yet
+                if let Err(e) = unwrapped_token.check_if_expired() {
+                    return err(e)
                 }
                 unwrapped_token
             },
@@ -261,7 +356,7 @@ impl<X: GetConfigVariable, Y: CheckUserRole> FromRequest for HeaderToken<X, Y> {
         };
 
 
-        return ok(token)
+        ok(token)
     }
 }
 
@@ -301,23 +396,23 @@ mod tests {
     }
 
     async fn pass_handle(token: HeaderToken<FakeConfig, NoRoleCheck>, _: HttpRequest) -> HttpResponse {
-        return HttpResponse::Ok().json(json!({"user_id": token.user_id}))
+        HttpResponse::Ok().json(json!({"user_id": token.user_id}))
     }
 
     async fn super_admin_handle(token: HeaderToken<FakeConfig, SuperAdminRoleCheck>, _: HttpRequest) -> HttpResponse {
-        return HttpResponse::Ok().json(json!({"user_id": token.user_id}))
+        HttpResponse::Ok().json(json!({"user_id": token.user_id}))
     }
 
     async fn admin_handle(token: HeaderToken<FakeConfig, AdminRoleCheck>, _: HttpRequest) -> HttpResponse {
-        return HttpResponse::Ok().json(json!({"user_id": token.user_id}))
+        HttpResponse::Ok().json(json!({"user_id": token.user_id}))
     }
 
     async fn worker_handle(token: HeaderToken<FakeConfig, WorkerRoleCheck>, _: HttpRequest) -> HttpResponse {
-        return HttpResponse::Ok().json(json!({"user_id": token.user_id}))
+        HttpResponse::Ok().json(json!({"user_id": token.user_id}))
     }
 
     async fn exact_check_handle(token: HeaderToken<FakeConfig, ExactAdminRoleCheck>, _: HttpRequest) -> HttpResponse {
-        return HttpResponse::Ok().json(json!({"user_id": token.user_id}))
+        HttpResponse::Ok().json(json!({"user_id": token.user_id}))
     }
 
     /// Because it's being constructed to be sent it doesn't matter what role check is used
@@ -336,6 +431,96 @@ mod tests {
         let expected_token = construct_token(UserRole::Admin).encode().unwrap();
         let decoded_token = HeaderToken::<FakeConfig, NoRoleCheck>::decode(&expected_token).unwrap();
         assert_eq!(decoded_token.user_id, 1);
+        assert_eq!(decoded_token.impersonator_id, None);
+    }
+
+    #[test]
+    fn test_impersonate() {
+        let token = HeaderToken::<FakeConfig, NoRoleCheck>::impersonate(
+            USER_AGENT.to_string(), 7, UserRole::Worker, 1, chrono::Duration::minutes(15)
+        );
+        assert_eq!(token.time_expire - token.time_started, chrono::Duration::minutes(15));
+        let decoded_token = HeaderToken::<FakeConfig, NoRoleCheck>::decode(&token.encode().unwrap()).unwrap();
+        assert_eq!((decoded_token.user_id, decoded_token.impersonator_id), (7, Some(1)));
+    }
+
+    #[test]
+    fn test_org_claim() {
+        let token = construct_token(UserRole::Worker).in_org(3);
+        let decoded_token = HeaderToken::<FakeConfig, NoRoleCheck>::decode(&token.encode().unwrap()).unwrap();
+        assert_eq!(decoded_token.org_id, 3);
+        assert_eq!(decoded_token.reissue(chrono::Duration::minutes(5)).org_id, 3);
+
+        let mut claims = serde_json::to_value(construct_token(UserRole::Worker).in_org(3)).unwrap();
+        claims.as_object_mut().unwrap().remove("org_id");
+        let issued_before_orgs: HeaderToken<FakeConfig, NoRoleCheck> = serde_json::from_value(claims).unwrap();
+        assert_eq!(issued_before_orgs.org_id, DEFAULT_ORG_ID);
+    }
+
+    struct ProdConfig;
+
+    impl GetConfigVariable for ProdConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SECRET_KEY" => Ok("secret".to_string()),
+                "JWT_ISSUER" => Ok("https://auth.example.com".to_string()),
+                "JWT_AUDIENCE" => Ok("prod".to_string()),
+                _ => Ok("".to_string())
+            }
+        }
+    }
+
+    struct StagingConfig;
+
+    impl GetConfigVariable for StagingConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "JWT_AUDIENCE" => Ok("staging".to_string()),
+                _ => ProdConfig::get_config_variable(variable)
+            }
+        }
+    }
+
+    #[test]
+    fn test_issuer_and_audience() {
+        let token = HeaderToken::<ProdConfig, NoRoleCheck>::new(USER_AGENT.to_string(), 1, UserRole::Worker);
+        assert_eq!((token.iss.as_deref(), token.aud.as_deref()), (Some("https://auth.example.com"), Some("prod")));
+        let encoded = token.encode().unwrap();
+        let decoded = HeaderToken::<ProdConfig, NoRoleCheck>::decode(&encoded).unwrap();
+        assert_eq!(decoded.aud.as_deref(), Some("prod"));
+
+        // a token for another environment sharing the key is refused
+        let staging = HeaderToken::<StagingConfig, NoRoleCheck>::new(USER_AGENT.to_string(), 1, UserRole::Worker).encode().unwrap();
+        assert!(HeaderToken::<ProdConfig, NoRoleCheck>::decode(&staging).is_err());
+        // as is one without the claims once they are configured
+        let unscoped = construct_token(UserRole::Worker).encode().unwrap();
+        assert!(HeaderToken::<ProdConfig, NoRoleCheck>::decode(&unscoped).is_err());
+        assert!(HeaderToken::<FakeConfig, NoRoleCheck>::decode(&unscoped).is_ok());
+    }
+
+    #[test]
+    fn test_time_claims() {
+        let token = construct_token(UserRole::Worker);
+        assert_eq!(token.time_expire - token.time_started, chrono::Duration::minutes(20));
+        let decoded_token = HeaderToken::<FakeConfig, NoRoleCheck>::decode(&token.encode().unwrap()).unwrap();
+        assert_eq!((decoded_token.iat, decoded_token.nbf), (decoded_token.time_started.timestamp(), decoded_token.time_started.timestamp()));
+        assert!(decoded_token.check_if_expired().is_ok());
+
+        // the clock skew lets a token through just after it expired or just before it is valid
+        let mut token = construct_token(UserRole::Worker);
+        token.time_expire = Utc::now() - chrono::Duration::seconds(10);
+        token.nbf = (Utc::now() + chrono::Duration::seconds(10)).timestamp();
+        assert!(token.check_if_expired().is_ok());
+
+        let mut token = construct_token(UserRole::Worker);
+        token.nbf = (Utc::now() + chrono::Duration::minutes(5)).timestamp();
+        assert_eq!(token.check_if_expired().unwrap_err().message, "Token is not valid yet");
+
+        let mut claims = serde_json::to_value(construct_token(UserRole::Worker)).unwrap();
+        claims.as_object_mut().unwrap().remove("iat");
+        claims.as_object_mut().unwrap().remove("nbf");
+        let issued_before_claims: HeaderToken<FakeConfig, NoRoleCheck> = serde_json::from_value(claims).unwrap();
+        assert!(issued_before_claims.check_if_expired().is_ok());
     }
 
     #[actix_web::test]
@@ -0,0 +1,111 @@
+//! Defines the tombstones left behind when users and to-do items are deleted.
+//!
+//! ## Purpose
+//! - Deletes are hard deletes, so a sync client that missed the delete has no row to compare
+//!   against. A tombstone records what was deleted and when so clients can catch up.
+//! - Tombstones are written in the same statement as the delete, including the to-do items
+//!   removed when the user they belong to is deleted.
+//! - Each tombstone is kept in the organization of what was deleted, and a deleted user leaves one
+//!   in each of their organizations, so clients only learn about deletions in their own organization.
+use serde::{Serialize, Deserialize};
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
+use chrono::NaiveDateTime;
+use std::error::Error;
+use std::str::FromStr;
+
+
+/// The kind of entity a tombstone was left for.
+///
+/// # Variants
+/// * `User` - A deleted user.
+/// * `Todo` - A deleted to-do item.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TombstoneEntity {
+    User,
+    Todo,
+}
+
+impl TombstoneEntity {
+
+    /// The value stored in the `entity_type` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TombstoneEntity::User => "user",
+            TombstoneEntity::Todo => "todo",
+        }
+    }
+}
+
+impl FromStr for TombstoneEntity {
+    type Err = String;
+    fn from_str(entity: &str) -> Result<Self, Self::Err> {
+        match entity.trim() {
+            "user" => Ok(TombstoneEntity::User),
+            "todo" => Ok(TombstoneEntity::Todo),
+            _ => Err(format!("Invalid tombstone entity: {}", entity)),
+        }
+    }
+}
+
+impl Type<Postgres> for TombstoneEntity {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for TombstoneEntity {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for TombstoneEntity {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        TombstoneEntity::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// A record of a deleted entity.
+///
+/// # Fields
+/// * id - The unique identifier for the tombstone, which only ever increases and is used as the page cursor.
+/// * entity_type - The kind of entity that was deleted.
+/// * entity_id - The ID the entity had.
+/// * deleted_at - When the entity was deleted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct Tombstone {
+    pub id: i32,
+    pub entity_type: TombstoneEntity,
+    pub entity_id: i32,
+    pub deleted_at: NaiveDateTime,
+}
+
+
+/// A page of tombstones returned to sync clients.
+///
+/// # Fields
+/// * deletions - The tombstones in the order they were written.
+/// * next_after_id - The cursor for the next page, `None` when there are no more tombstones.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TombstonePage {
+    pub deletions: Vec<Tombstone>,
+    pub next_after_id: Option<i32>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tombstone_entity_round_trip() {
+        for entity in [TombstoneEntity::User, TombstoneEntity::Todo] {
+            assert_eq!(TombstoneEntity::from_str(entity.as_str()).unwrap(), entity);
+            assert_eq!(serde_json::to_value(entity).unwrap(), entity.as_str());
+        }
+        assert!(TombstoneEntity::from_str("tag").is_err());
+    }
+}
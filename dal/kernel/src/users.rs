@@ -14,6 +14,7 @@
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 use serde::{Serialize, Deserialize};
 use chrono::NaiveDateTime;
+use validator::{Validate, ValidationError};
 use argon2::{
     Argon2, 
     PasswordHasher, 
@@ -27,7 +28,11 @@ use sqlx::postgres::PgTypeInfo;
 use sqlx::{Decode, Encode, Postgres, Type};
 use std::str::FromStr;
 use std::error::Error;
+use utils::export_stream::{CsvRow, csv_field};
+use utils::locale::iso_datetime;
+use std::collections::BTreeMap;
 use crate::role_permissions::RolePermission;
+use crate::avatars::avatar_url;
 use rand::Rng;
 
 
@@ -66,15 +71,56 @@ pub fn hash_password(password: String) -> Result<String, NanoServiceError> {
 /// * `first_name` - The first name of the new user.
 /// * `last_name` - The last name of the new user.
 /// * `user_role` - The role assigned to the user.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+///
+/// # Validation
+/// * `username` - 3 to 32 letters, digits, `.`, `_` or `-`.
+/// * `email` - A valid email address.
+/// * `first_name` and `last_name` - 1 to 64 characters with no control characters.
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
 pub struct NewUserSchema {
+    #[validate(
+        length(min = 3, max = 32, message = "must be between 3 and 32 characters"),
+        custom(function = "validate_username")
+    )]
     pub username: String,
+    #[validate(email(message = "must be a valid email address"))]
     pub email: String,
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_name")
+    )]
     pub first_name: String,
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_name")
+    )]
     pub last_name: String,
     pub user_role: UserRole
 }
 
+
+/// Checks a username only uses letters, digits, `.`, `_` and `-`.
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-');
+    match username.chars().all(allowed) {
+        true => Ok(()),
+        false => Err(ValidationError::new("username")
+            .with_message("may only contain letters, digits, '.', '_' and '-'".into()))
+    }
+}
+
+
+/// Checks a name has no control characters and is not only whitespace.
+pub fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.trim().is_empty() {
+        return Err(ValidationError::new("blank").with_message("must not be blank".into()))
+    }
+    match name.chars().any(char::is_control) {
+        true => Err(ValidationError::new("control_characters").with_message("must not contain control characters".into())),
+        false => Ok(())
+    }
+}
+
 impl NewUserSchema {
     /// Converts a `NewUserSchema` into a `NewUser`.
     /// 
@@ -104,7 +150,7 @@ impl NewUserSchema {
 /// # Variants
 /// * `SuperAdmin` - The super administrator role who has full control over the system.
 /// * `Admin` - The administrator role who can oversee and perform actions on workers such as block, invite, delete.
-///             They will also be able to assign tasks to workers and inspect progress.
+///   They will also be able to assign tasks to workers and inspect progress.
 /// * `Worker` - The worker role who can perform tasks assigned by the administrator.
 #[derive(Debug, Clone, PartialEq)]
 pub enum UserRole {
@@ -114,6 +160,19 @@ pub enum UserRole {
     Unreachable
 }
 
+impl UserRole {
+
+    /// The role as it is stored and shown, such as `Super Admin`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::SuperAdmin => "Super Admin",
+            UserRole::Admin => "Admin",
+            UserRole::Worker => "Worker",
+            UserRole::Unreachable => "Unreachable",
+        }
+    }
+}
+
 // Manually implement `sqlx::Type` to match TEXT type
 impl Type<Postgres> for UserRole {
     fn type_info() -> PgTypeInfo {
@@ -124,13 +183,7 @@ impl Type<Postgres> for UserRole {
 // Implement `sqlx::Encode` for inserting into Postgres
 impl Encode<'_, Postgres> for UserRole {
     fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
-        let role_str = match self {
-            UserRole::SuperAdmin => "Super Admin",
-            UserRole::Admin => "Admin",
-            UserRole::Worker => "Worker",
-            UserRole::Unreachable => "Unreachable",
-        };
-        <&str as Encode<Postgres>>::encode(role_str, buf)
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
     }
 }
 
@@ -190,6 +243,7 @@ impl UserRole {
     /// 
     /// # Returns
     /// * `String` - The string representation of the role.
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         match self {
             UserRole::Admin => "Admin".to_string(),
@@ -230,7 +284,7 @@ impl UserRole {
 /// * `last_name` - The last name of the user.
 /// * `user_role` - The role assigned to the user.
 /// * `date_created` - The date and time the user was created.
-/// * `last_logged_in` - The date and time the user last logged in.
+/// * `last_logged_in` - The date and time the user last logged in, or was created if they never have.
 /// * `blocked` - A boolean indicating if the user is blocked.
 /// * `uuid` - A unique identifier for the user.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -291,6 +345,17 @@ impl NewUser {
             uuid: uuid::Uuid::new_v4().to_string(),
         })
     }
+
+    /// The profile the user's emails are personalised with, using the locale and timezone the
+    /// `users` table gives new users, for emails written before the user is stored.
+    pub fn recipient_profile(&self) -> RecipientProfile {
+        RecipientProfile {
+            first_name: self.first_name.clone(),
+            username: self.username.clone(),
+            locale: "en".to_string(),
+            timezone: "UTC".to_string(),
+        }
+    }
 }
 
 
@@ -306,9 +371,10 @@ impl NewUser {
 /// * `last_name` - The last name of the user.
 /// * `user_role` - The role assigned to the user.
 /// * `date_created` - The date and time the user was created.
-/// * `last_logged_in` - The date and time the user last logged in.
+/// * `last_logged_in` - The date and time the user last logged in, or was created if they never have.
 /// * `blocked` - A boolean indicating if the user is blocked.
 /// * `uuid` - A unique identifier for the user.
+/// * `avatar_version` - The version of the user's avatar, `None` if they have not uploaded one.
 #[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
 pub struct User {
     pub id: i32,
@@ -323,6 +389,8 @@ pub struct User {
     pub last_logged_in: NaiveDateTime,
     pub blocked: bool,
     pub uuid: String,
+    #[serde(default)]
+    pub avatar_version: Option<String>,
 }
 
 impl User {
@@ -351,6 +419,40 @@ impl User {
     }
 }
 
+
+/// Checks that the user behind a token may still use the API. Every `#[api_endpoint]` with a token
+/// runs this unless it opts out with `confirmed_user=false`.
+///
+/// # Arguments
+/// * `lookup` - The result of loading the user row for the token's user ID.
+///
+/// # Returns
+/// * `Ok(User)` - The confirmed user who is not blocked.
+/// * `Err(NanoServiceError)` - A 403 if the user is blocked or unconfirmed, or a 401 if the user has been deleted.
+pub fn check_confirmed_user(lookup: Result<User, NanoServiceError>) -> Result<User, NanoServiceError> {
+    let user = match lookup {
+        Ok(user) => user,
+        Err(e) if e.status == NanoServiceErrorStatus::NotFound => return Err(NanoServiceError::new(
+            "User no longer exists".to_string(),
+            NanoServiceErrorStatus::Unauthorized
+        )),
+        Err(e) => return Err(e)
+    };
+    if user.blocked {
+        return Err(NanoServiceError::new(
+            "User is blocked".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    if !user.confirmed {
+        return Err(NanoServiceError::new(
+            "User is not confirmed".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    Ok(user)
+}
+
 /// Represents a lightweight version of a user for data transfer purposes.
 ///
 /// # Fields
@@ -362,10 +464,11 @@ impl User {
 /// * `last_name` - The last name of the user.
 /// * `user_role` - The role assigned to the user.
 /// * `date_created` - The date and time the user was created.
-/// * `last_logged_in` - The date and time the user last logged in.
+/// * `last_logged_in` - The date and time the user last logged in, or was created if they never have.
 /// * `blocked` - A boolean indicating if the user is blocked.
 /// * `uuid` - A unique identifier for the user.
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+/// * `avatar_url` - The path the user's avatar is served from, `None` if they have not uploaded one.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, sqlx::FromRow)]
 pub struct TrimmedUser {
     pub id: i32,
     pub confirmed: bool,
@@ -378,8 +481,16 @@ pub struct TrimmedUser {
     pub last_logged_in: NaiveDateTime,
     pub blocked: bool,
     pub uuid: String,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
 }
 
+/// The `TrimmedUser` fields a client can pick with `?fields=`.
+pub const USER_FIELDS: [&str; 12] = [
+    "id", "confirmed", "username", "email", "first_name", "last_name",
+    "user_role", "date_created", "last_logged_in", "blocked", "uuid", "avatar_url",
+];
+
 impl From<User> for TrimmedUser {
     /// Converts a `User` into a `TrimmedUser`.
     ///
@@ -389,6 +500,7 @@ impl From<User> for TrimmedUser {
     /// # Returns
     /// * `TrimmedUser` - A lightweight version of the user.
     fn from(user: User) -> Self {
+        let avatar_url = user.avatar_version.map(|version| avatar_url(user.id, &version));
         TrimmedUser {
             id: user.id,
             username: user.username,
@@ -401,6 +513,53 @@ impl From<User> for TrimmedUser {
             blocked: user.blocked,
             uuid: user.uuid,
             confirmed: user.confirmed,
+            avatar_url,
+        }
+    }
+}
+
+
+/// A user as other users see them, a `TrimmedUser` without the `uuid`.
+///
+/// # Notes
+/// The `uuid` is what password reset and confirmation links are built from, so it is only ever
+/// returned to the user it belongs to. Feeds and searches that list other users return this instead.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct PublicUser {
+    pub id: i32,
+    pub confirmed: bool,
+    pub username: String,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub user_role: UserRole,
+    pub date_created: NaiveDateTime,
+    pub last_logged_in: NaiveDateTime,
+    pub blocked: bool,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+}
+
+/// The `PublicUser` fields a client can pick with `?fields=`.
+pub const PUBLIC_USER_FIELDS: [&str; 11] = [
+    "id", "confirmed", "username", "email", "first_name", "last_name",
+    "user_role", "date_created", "last_logged_in", "blocked", "avatar_url",
+];
+
+impl From<TrimmedUser> for PublicUser {
+    fn from(user: TrimmedUser) -> Self {
+        PublicUser {
+            id: user.id,
+            confirmed: user.confirmed,
+            username: user.username,
+            email: user.email,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            user_role: user.user_role,
+            date_created: user.date_created,
+            last_logged_in: user.last_logged_in,
+            blocked: user.blocked,
+            avatar_url: user.avatar_url,
         }
     }
 }
@@ -418,11 +577,178 @@ pub struct UserProfile {
 }
 
 
+/// A user's profile as written to the user export, with their roles flattened into one field.
+///
+/// # Fields
+/// * `roles` - The roles granted to the user, written to CSV separated by `;`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ExportedUserProfile {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub user_role: UserRole,
+    pub roles: Vec<String>,
+    pub confirmed: bool,
+    pub blocked: bool,
+    pub date_created: NaiveDateTime,
+    pub last_logged_in: NaiveDateTime,
+}
+
+impl CsvRow for ExportedUserProfile {
+    const CSV_HEADER: &'static str = "id,username,email,first_name,last_name,user_role,roles,confirmed,blocked,date_created,last_logged_in";
+
+    fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            self.id,
+            csv_field(&self.username),
+            csv_field(&self.email),
+            csv_field(&self.first_name),
+            csv_field(&self.last_name),
+            self.user_role.as_str(),
+            csv_field(&self.roles.join(";")),
+            self.confirmed,
+            self.blocked,
+            iso_datetime(self.date_created),
+            iso_datetime(self.last_logged_in)
+        )
+    }
+}
+
+
+/// A page of users found by a search.
+///
+/// # Fields
+/// * `users` - The users on this page, ordered by username.
+/// * `next_offset` - The offset of the next page, `None` on the last page.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct UserSearchPage {
+    pub users: Vec<PublicUser>,
+    pub next_offset: Option<i64>,
+}
+
+
+/// The changes to make to a user's profile, fields that are left out are not changed.
+///
+/// # Fields
+/// * `username` - The new username.
+/// * `email` - The new email address.
+/// * `first_name` - The new first name.
+/// * `last_name` - The new last name.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+pub struct UserProfilePatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+}
+
+
+/// The changes a user makes to their own profile, fields that are left out are not changed.
+///
+/// # Fields
+/// * `username` - The new username.
+/// * `first_name` - The new first name.
+/// * `last_name` - The new last name.
+/// * `refused` - Any other field in the request, such as `user_role`, `blocked` or `confirmed`,
+///   none of which a user can change on themselves.
+///
+/// # Validation
+/// The same rules as `NewUserSchema` for the fields that are given.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default, Validate)]
+pub struct OwnProfileUpdate {
+    #[validate(
+        length(min = 3, max = 32, message = "must be between 3 and 32 characters"),
+        custom(function = "validate_username")
+    )]
+    pub username: Option<String>,
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_name")
+    )]
+    pub first_name: Option<String>,
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_name")
+    )]
+    pub last_name: Option<String>,
+    #[serde(flatten)]
+    pub refused: BTreeMap<String, serde_json::Value>,
+}
+
+impl OwnProfileUpdate {
+
+    /// Whether the update does not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.username.is_none() && self.first_name.is_none() && self.last_name.is_none()
+    }
+
+    /// Converts the allowed fields into a `UserProfilePatch`.
+    pub fn into_patch(self) -> UserProfilePatch {
+        UserProfilePatch {
+            username: self.username,
+            email: None,
+            first_name: self.first_name,
+            last_name: self.last_name,
+        }
+    }
+}
+
+
+/// The details of an email recipient used to personalise templates.
+///
+/// # Fields
+/// * `first_name` - The first name of the recipient.
+/// * `username` - The username of the recipient.
+/// * `locale` - The language the recipient's emails are written in, such as `en` or `fr-CA`.
+/// * `timezone` - The IANA timezone of the recipient, such as `Europe/London`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct RecipientProfile {
+    pub first_name: String,
+    pub username: String,
+    pub locale: String,
+    pub timezone: String,
+}
+
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
 
+    fn new_user_schema(username: &str, email: &str, first_name: &str) -> NewUserSchema {
+        NewUserSchema {
+            username: username.to_string(),
+            email: email.to_string(),
+            first_name: first_name.to_string(),
+            last_name: "Lovelace".to_string(),
+            user_role: UserRole::Worker
+        }
+    }
+
+    #[test]
+    fn test_new_user_schema_validation() {
+        assert!(new_user_schema("ada.lovelace", "ada@example.com", "Ada").validate().is_ok());
+
+        let errors = new_user_schema("a d", "not-an-email", "\u{7}").validate().unwrap_err();
+        let fields = errors.field_errors();
+        let codes = |field: &str| fields[field].iter().map(|error| error.code.to_string()).collect::<Vec<_>>();
+        assert_eq!(codes("username"), vec!["username"]);
+        assert_eq!(codes("email"), vec!["email"]);
+        assert_eq!(codes("first_name"), vec!["control_characters"]);
+        assert!(!fields.contains_key("last_name"));
+
+        let errors = new_user_schema("ad", "ada@example.com", "  ").validate().unwrap_err();
+        assert!(errors.field_errors().contains_key("username"));
+        assert!(errors.field_errors().contains_key("first_name"));
+    }
+
     #[test]
     fn test_hash_password_success() {
         let password = "pasword".to_string();
@@ -505,6 +831,7 @@ mod tests {
             last_logged_in: Utc::now().naive_utc(),
             blocked: new_user.blocked,
             uuid: new_user.uuid.clone(),
+            avatar_version: None,
         };
 
         // Verify the password using the `User::verify_password` method
@@ -521,7 +848,72 @@ mod tests {
             .expect("Password verification failed");
 
         // Assert that the password is invalid
-        assert_eq!(is_invalid, false, "Password verification failed");
+        assert!(!is_invalid, "Password verification failed");
+    }
+
+    #[test]
+    fn test_check_confirmed_user() {
+        let user = User {
+            id: 1,
+            confirmed: true,
+            username: "testuser".to_string(),
+            email: "test@example.com".to_string(),
+            password: "hash".to_string(),
+            first_name: "John".to_string(),
+            last_name: "Doe".to_string(),
+            user_role: UserRole::Worker,
+            date_created: NaiveDateTime::default(),
+            last_logged_in: NaiveDateTime::default(),
+            blocked: false,
+            uuid: "uuid".to_string(),
+            avatar_version: None,
+        };
+        assert!(check_confirmed_user(Ok(user.clone())).is_ok());
+
+        let error = check_confirmed_user(Ok(User { confirmed: false, ..user.clone() })).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        assert_eq!(error.message, "User is not confirmed");
+
+        let error = check_confirmed_user(Ok(User { blocked: true, ..user })).unwrap_err();
+        assert_eq!(error.message, "User is blocked");
+
+        let missing = NanoServiceError::new("User not found".to_string(), NanoServiceErrorStatus::NotFound);
+        assert_eq!(check_confirmed_user(Err(missing)).unwrap_err().status, NanoServiceErrorStatus::Unauthorized);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_own_profile_update() {
+        let update: OwnProfileUpdate = serde_json::from_value(serde_json::json!({
+            "first_name": "Ada", "user_role": "Admin", "blocked": false
+        })).unwrap();
+        assert_eq!(update.first_name.as_deref(), Some("Ada"));
+        assert_eq!(update.refused.keys().collect::<Vec<_>>(), vec!["blocked", "user_role"]);
+
+        let invalid = OwnProfileUpdate { username: Some("a b".to_string()), ..Default::default() };
+        assert!(invalid.validate().is_err());
+        assert!(OwnProfileUpdate::default().is_empty());
+    }
+
+    #[test]
+    fn test_exported_user_profile_csv_row() {
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let profile = ExportedUserProfile {
+            id: 3,
+            username: "ada".to_string(),
+            email: "ada@example.com".to_string(),
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace, Countess".to_string(),
+            user_role: UserRole::SuperAdmin,
+            roles: vec!["Admin".to_string(), "Super Admin".to_string()],
+            confirmed: true,
+            blocked: false,
+            date_created: date,
+            last_logged_in: date,
+        };
+        assert_eq!(
+            profile.csv_row(),
+            "3,ada,ada@example.com,Ada,\"Lovelace, Countess\",Super Admin,Admin;Super Admin,true,false,2025-01-01T00:00:00,2025-01-01T00:00:00"
+        );
+    }
+
+}
@@ -0,0 +1,42 @@
+//! Posts webhook deliveries over HTTP.
+//!
+//! # Overview
+//! Redirects are not followed, a receiver that moved has to be updated by an admin, and a receiver
+//! that does not answer within `TIMEOUT_SECONDS` counts as not reached.
+use std::sync::LazyLock;
+use std::time::Duration;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::webhooks::{SignedWebhook, WebhookSender};
+
+
+/// How long a receiver has to answer a delivery.
+pub const TIMEOUT_SECONDS: u64 = 10;
+
+static WEBHOOK_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(TIMEOUT_SECONDS))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("the webhook client can be built")
+});
+
+
+/// Posts each delivery as JSON with its signature headers.
+pub struct HttpWebhookSender;
+
+impl WebhookSender for HttpWebhookSender {
+    async fn send(webhook: &SignedWebhook) -> Result<u16, NanoServiceError> {
+        let mut request = WEBHOOK_CLIENT
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(webhook.body.clone());
+        for (name, value) in &webhook.headers {
+            request = request.header(*name, value);
+        }
+        let response = request.send().await.map_err(|e| NanoServiceError::new(
+            format!("Failed to reach {}: {}", webhook.url, e),
+            NanoServiceErrorStatus::Unknown
+        ))?;
+        Ok(response.status().as_u16())
+    }
+}
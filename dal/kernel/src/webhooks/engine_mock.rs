@@ -0,0 +1,25 @@
+//! Webhook senders for tests.
+use std::sync::Mutex;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::webhooks::{SignedWebhook, WebhookSender};
+
+
+/// Every delivery posted with `RecordWebhooksMock`, shared by the tests of a crate.
+pub static SENT_WEBHOOKS: Mutex<Vec<SignedWebhook>> = Mutex::new(Vec::new());
+
+
+/// Records every delivery in `SENT_WEBHOOKS` and answers with the status at the end of its URL,
+/// `https://hooks.example.com/500` gets a `500`, or a `200` if the URL does not end in a status.
+/// A URL ending in `/unreachable` is not reached.
+pub struct RecordWebhooksMock;
+
+impl WebhookSender for RecordWebhooksMock {
+    async fn send(webhook: &SignedWebhook) -> Result<u16, NanoServiceError> {
+        SENT_WEBHOOKS.lock().unwrap().push(webhook.clone());
+        let last = webhook.url.rsplit('/').next().unwrap_or_default();
+        if last == "unreachable" {
+            return Err(NanoServiceError::new(format!("Failed to reach {}", webhook.url), NanoServiceErrorStatus::Unknown))
+        }
+        Ok(last.parse().unwrap_or(200))
+    }
+}
@@ -0,0 +1,375 @@
+//! Defines the structs for the webhooks admins register to be told about events.
+//!
+//! ## Purpose
+//! - An admin registers a URL in the `webhooks` table with the events it should receive. When one of
+//!   those events is published a delivery is queued in `webhook_outbox` for every active webhook
+//!   subscribed to it, and a worker posts it, retrying failures with backoff.
+//! - Every delivery is signed with the webhook's secret, see `sign_webhook`, so the receiver can tell
+//!   it came from us. The deliveries are kept so admins can see why a receiver is not getting them.
+//! - Deliveries are posted through a `WebhookSender` so tests can swap out the HTTP client.
+//!
+//! ## Notes
+//! - The `webhook_deliveries` table is the ledger of the webhooks posted *to* us, see `utils::webhooks`.
+pub mod engine_http;
+pub mod engine_mock;
+
+use serde::{Serialize, Deserialize};
+use sqlx::{Decode, Encode, Postgres, Type, postgres::PgTypeInfo};
+use sqlx::types::Json;
+use chrono::NaiveDateTime;
+use rand::Rng;
+use std::error::Error;
+use std::future::Future;
+use std::str::FromStr;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::webhooks::hmac_sha256_hex;
+use crate::events::DomainEvent;
+
+
+/// The header holding the ID of the event, the same for every attempt at a delivery.
+pub const WEBHOOK_ID_HEADER: &str = "X-Webhook-Id";
+
+/// The header holding the name of the event.
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Webhook-Event";
+
+/// The header holding the unix time the delivery was signed at.
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+
+/// The header holding the signature, as `sha256=<hex>`.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// The length of a generated signing secret.
+const SECRET_LENGTH: usize = 32;
+
+
+/// The events a webhook can subscribe to, named as the domain events they are sent for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    UserCreated,
+    TodoCompleted,
+}
+
+impl WebhookEvent {
+
+    /// The name of the event, as in the `event` field of the payload.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::UserCreated => "user_created",
+            WebhookEvent::TodoCompleted => "todo_completed",
+        }
+    }
+
+    /// The webhook event a domain event is sent as, `None` if webhooks cannot subscribe to it.
+    pub fn for_domain_event(event: &DomainEvent) -> Option<Self> {
+        match event {
+            DomainEvent::UserCreated { .. } => Some(WebhookEvent::UserCreated),
+            DomainEvent::TodoCompleted { .. } => Some(WebhookEvent::TodoCompleted),
+            DomainEvent::UserBlocked { .. } => None,
+        }
+    }
+}
+
+
+/// The delivery status of a webhook delivery.
+///
+/// # Variants
+/// * `Pending` - The delivery is waiting to be sent or retried.
+/// * `Delivered` - The receiver answered with a `2xx`.
+/// * `Failed` - The delivery ran out of attempts and will not be retried.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+
+    /// The value stored in the `status` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Delivered => "delivered",
+            WebhookDeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for WebhookDeliveryStatus {
+    type Err = String;
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status.trim() {
+            "pending" => Ok(WebhookDeliveryStatus::Pending),
+            "delivered" => Ok(WebhookDeliveryStatus::Delivered),
+            "failed" => Ok(WebhookDeliveryStatus::Failed),
+            _ => Err(format!("Invalid webhook delivery status: {}", status)),
+        }
+    }
+}
+
+impl Type<Postgres> for WebhookDeliveryStatus {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl Encode<'_, Postgres> for WebhookDeliveryStatus {
+    fn encode_by_ref(&self, buf: &mut <Postgres as sqlx::Database>::ArgumentBuffer<'_>) -> Result<sqlx::encode::IsNull, Box<dyn Error + Sync + Send>> {
+        <&str as Encode<Postgres>>::encode(self.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for WebhookDeliveryStatus {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        WebhookDeliveryStatus::from_str(s).map_err(|e| e.into())
+    }
+}
+
+
+/// Represents the schema for registering a webhook.
+///
+/// # Fields
+/// * url - Where deliveries are posted.
+/// * secret - The secret deliveries are signed with.
+/// * events - The events the webhook receives.
+/// * created_by - The ID of the admin who registered it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub created_by: i32,
+}
+
+
+/// The changes to a webhook, `None` leaves the field as it is.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct WebhookUpdate {
+    pub url: Option<String>,
+    pub events: Option<Vec<WebhookEvent>>,
+    pub active: Option<bool>,
+}
+
+
+/// Represents a registered webhook.
+///
+/// # Fields
+/// * id - The unique identifier for the webhook.
+/// * url - Where deliveries are posted.
+/// * secret - The secret deliveries are signed with, never serialized so it is only shown once.
+/// * events - The events the webhook receives.
+/// * active - Whether deliveries are queued for it, a paused webhook keeps its deliveries.
+/// * created_by - The ID of the admin who registered it.
+/// * date_created - When it was registered.
+/// * date_updated - When it was last changed.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub events: Json<Vec<WebhookEvent>>,
+    pub active: bool,
+    pub created_by: i32,
+    pub date_created: NaiveDateTime,
+    pub date_updated: NaiveDateTime,
+}
+
+
+/// Represents a delivery of an event to a webhook.
+///
+/// # Fields
+/// * id - The unique identifier for the delivery.
+/// * webhook_id - The webhook it is for.
+/// * event_id - The ID of the event, sent in `X-Webhook-Id` so the receiver can spot repeats.
+/// * event - The name of the event.
+/// * payload - The body that is posted.
+/// * status - The delivery status.
+/// * attempts - How many times it has been posted.
+/// * next_attempt_at - When it is next due to be posted.
+/// * last_status_code - The HTTP status of the most recent attempt, `None` if the receiver was not reached.
+/// * last_error - Why the most recent attempt failed.
+/// * date_created - When it was queued.
+/// * date_delivered - When the receiver accepted it.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event_id: String,
+    pub event: String,
+    pub payload: Json<serde_json::Value>,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub date_created: NaiveDateTime,
+    pub date_delivered: Option<NaiveDateTime>,
+}
+
+
+/// A delivery claimed by the worker, with the URL and secret of its webhook.
+#[derive(Debug, Clone, sqlx::FromRow, PartialEq)]
+pub struct DueWebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub url: String,
+    pub secret: String,
+    pub event_id: String,
+    pub event: String,
+    pub payload: Json<serde_json::Value>,
+    pub attempts: i32,
+}
+
+
+/// A delivery ready to post, signed at `timestamp`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedWebhook {
+    pub url: String,
+    pub body: String,
+    pub headers: Vec<(&'static str, String)>,
+}
+
+impl SignedWebhook {
+
+    /// Signs a delivery as it is sent.
+    ///
+    /// # Arguments
+    /// * `delivery` - The claimed delivery.
+    /// * `timestamp` - The unix time it is sent at, signed with the body so it cannot be replayed later.
+    pub fn sign(delivery: &DueWebhookDelivery, timestamp: i64) -> Self {
+        let body = delivery.payload.0.to_string();
+        let signature = sign_webhook(&delivery.secret, timestamp, &body);
+        SignedWebhook {
+            url: delivery.url.clone(),
+            headers: vec![
+                (WEBHOOK_ID_HEADER, delivery.event_id.clone()),
+                (WEBHOOK_EVENT_HEADER, delivery.event.clone()),
+                (WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string()),
+                (WEBHOOK_SIGNATURE_HEADER, format!("sha256={}", signature)),
+            ],
+            body,
+        }
+    }
+}
+
+
+/// The signature of a delivery, the hex HMAC-SHA256 of `<timestamp>.<body>` with the webhook's secret.
+pub fn sign_webhook(secret: &str, timestamp: i64, body: &str) -> String {
+    hmac_sha256_hex(secret.as_bytes(), &[timestamp.to_string().as_bytes(), b".", body.as_bytes()])
+}
+
+
+/// A new random signing secret.
+pub fn generate_webhook_secret() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("whsec_{}", secret)
+}
+
+
+/// Checks a webhook URL is an absolute `http` or `https` URL.
+///
+/// # Returns
+/// * `Ok(())` - If deliveries can be posted to it
+/// * `Err(NanoServiceError)` - `BadRequest` naming what is wrong with it
+pub fn validate_webhook_url(url: &str) -> Result<(), NanoServiceError> {
+    let invalid = |reason: &str| NanoServiceError::new(
+        format!("Invalid webhook URL '{}': {}", url, reason),
+        NanoServiceErrorStatus::BadRequest
+    );
+    let parsed = reqwest::Url::parse(url).map_err(|e| invalid(&e.to_string()))?;
+    match parsed.scheme() {
+        "http" | "https" if parsed.host_str().is_some() => Ok(()),
+        "http" | "https" => Err(invalid("it has no host")),
+        _ => Err(invalid("it must be http or https"))
+    }
+}
+
+
+/// Defines the contract for posting a signed delivery to a webhook.
+pub trait WebhookSender {
+
+    /// Posts a delivery.
+    ///
+    /// # Returns
+    /// * `Ok(u16)` - The HTTP status the receiver answered with, whatever it was
+    /// * `Err(NanoServiceError)` - If the receiver could not be reached or did not answer in time
+    fn send(webhook: &SignedWebhook) -> impl Future<Output = Result<u16, NanoServiceError>> + Send;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::UserRole;
+    use utils::webhooks::verify_hmac_sha256_hex;
+
+    #[test]
+    fn test_delivery_status_round_trip() {
+        for status in [WebhookDeliveryStatus::Pending, WebhookDeliveryStatus::Delivered, WebhookDeliveryStatus::Failed] {
+            assert_eq!(WebhookDeliveryStatus::from_str(status.as_str()).unwrap(), status);
+        }
+        assert!(WebhookDeliveryStatus::from_str("sent").is_err());
+    }
+
+    #[test]
+    fn test_for_domain_event() {
+        let created = DomainEvent::UserCreated { user_id: 1, role: UserRole::Worker };
+        assert_eq!(WebhookEvent::for_domain_event(&created), Some(WebhookEvent::UserCreated));
+        assert_eq!(WebhookEvent::UserCreated.as_str(), created.name());
+        let completed = DomainEvent::TodoCompleted { todo_id: 1, assigned_to: 2, pending_review: false };
+        assert_eq!(WebhookEvent::TodoCompleted.as_str(), completed.name());
+        assert_eq!(WebhookEvent::for_domain_event(&DomainEvent::UserBlocked { user_id: 1, blocked_by: 2 }), None);
+        assert_eq!(serde_json::to_value(WebhookEvent::TodoCompleted).unwrap(), "todo_completed");
+    }
+
+    #[test]
+    fn test_sign() {
+        let delivery = DueWebhookDelivery {
+            id: 1,
+            webhook_id: 2,
+            url: "https://hooks.example.com/in".to_string(),
+            secret: "whsec_test".to_string(),
+            event_id: "b7c5".to_string(),
+            event: "user_created".to_string(),
+            payload: Json(serde_json::json!({"event": "user_created"})),
+            attempts: 0,
+        };
+        let signed = SignedWebhook::sign(&delivery, 1_700_000_000);
+        assert_eq!(signed.body, r#"{"event":"user_created"}"#);
+        let header = |name| signed.headers.iter().find(|(key, _)| *key == name).map(|(_, value)| value.clone()).unwrap();
+        assert_eq!(header(WEBHOOK_ID_HEADER), "b7c5");
+        assert_eq!(header(WEBHOOK_TIMESTAMP_HEADER), "1700000000");
+
+        // the receiver checks the signature over the timestamp and body it was sent
+        let signature = header(WEBHOOK_SIGNATURE_HEADER);
+        let signature = signature.strip_prefix("sha256=").unwrap();
+        assert!(verify_hmac_sha256_hex(b"whsec_test", &[b"1700000000", b".", signed.body.as_bytes()], signature));
+        assert!(!verify_hmac_sha256_hex(b"whsec_test", &[b"1700000001", b".", signed.body.as_bytes()], signature));
+    }
+
+    #[test]
+    fn test_generate_webhook_secret() {
+        let secret = generate_webhook_secret();
+        assert!(secret.starts_with("whsec_"));
+        assert_eq!(secret.len(), 6 + SECRET_LENGTH);
+        assert_ne!(secret, generate_webhook_secret());
+    }
+
+    #[test]
+    fn test_validate_webhook_url() {
+        assert!(validate_webhook_url("https://hooks.example.com/in").is_ok());
+        assert!(validate_webhook_url("http://localhost:8080/hook").is_ok());
+        for url in ["ftp://example.com/hook", "hooks.example.com", "https://", ""] {
+            let error = validate_webhook_url(url).unwrap_err();
+            assert_eq!(error.status, NanoServiceErrorStatus::BadRequest, "{}", url);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+const fs = require('fs');
+const path = require('path');
+const zlib = require('zlib');
+
+
+// The built files the server sends precompressed, next to each one it writes a `.br` and a `.gz`
+const files = ['bundle.js', 'bundle.css', 'tailwind.css'];
+
+
+for (const file of files) {
+    const filePath = path.join(__dirname, 'public', file);
+    if (!fs.existsSync(filePath)) {
+        continue;
+    }
+    const data = fs.readFileSync(filePath);
+    fs.writeFileSync(`${filePath}.gz`, zlib.gzipSync(data, { level: 9 }));
+    fs.writeFileSync(`${filePath}.br`, zlib.brotliCompressSync(data, {
+        params: {
+            [zlib.constants.BROTLI_PARAM_QUALITY]: zlib.constants.BROTLI_MAX_QUALITY,
+            [zlib.constants.BROTLI_PARAM_SIZE_HINT]: data.length,
+        },
+    }));
+    console.log(`Compressed ${file}`);
+}
@@ -0,0 +1,311 @@
+//! Rolls request metrics up into daily availability and reports it against the availability SLO.
+//!
+//! # Overview
+//! The rollup job recomputes yesterday and today from the per-minute request metrics, so minutes
+//! flushed late are still counted, then purges minutes older than the retention period. The SLO
+//! report covers the last 30 days of rollups and shows how much of the error budget, the server
+//! errors the target allows, has been spent and how fast it is burning.
+//!
+//! # Variables
+//! * `AVAILABILITY_SLO_TARGET` - The fraction of requests that should not get a 5xx, defaults to 0.999
+//! * `AVAILABILITY_ROLLUP_SECONDS` - How often the rollup job runs, defaults to 300
+//! * `REQUEST_METRICS_RETENTION_DAYS` - How long per-minute request metrics are kept, defaults to 7
+use actix_web::HttpResponse;
+use dal::request_metrics::tx_definitions::{GetAvailabilityRollups, PurgeRequestMetrics, RollupAvailability};
+use kernel::chrono::{Days, NaiveDate, NaiveDateTime, Utc};
+use kernel::request_metrics::AvailabilityRollup;
+use serde::Serialize;
+use utils::api_endpoint;
+use utils::log_limited;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The number of days the SLO report covers.
+pub const SLO_WINDOW_DAYS: u64 = 30;
+
+/// The availability target used when not configured.
+const DEFAULT_SLO_TARGET: f64 = 0.999;
+
+/// The rollup interval used when not configured.
+const DEFAULT_ROLLUP_SECONDS: i64 = 300;
+
+/// The per-minute metrics retention used when not configured.
+const DEFAULT_RETENTION_DAYS: i64 = 7;
+
+
+/// The availability over the SLO window measured against the target.
+///
+/// # Fields
+/// * `target` - The availability the server should meet.
+/// * `window_days` - The number of days the report covers.
+/// * `total_requests` - Every request served in the window.
+/// * `server_errors` - The requests that got a 5xx response in the window.
+/// * `availability` - The fraction of requests in the window that did not get a 5xx response.
+/// * `error_budget` - The server errors the target allows for the requests served.
+/// * `error_budget_remaining` - The fraction of the error budget left, negative once it is overspent.
+/// * `burn_rate` - The error rate over the window divided by the rate the target allows, above 1 the budget runs out before the window ends.
+/// * `latest_burn_rate` - The burn rate of the most recent day.
+/// * `days` - The daily rollups in the window, oldest first.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SloReport {
+    pub target: f64,
+    pub window_days: u64,
+    pub total_requests: i64,
+    pub server_errors: i64,
+    pub availability: f64,
+    pub error_budget: f64,
+    pub error_budget_remaining: f64,
+    pub burn_rate: f64,
+    pub latest_burn_rate: f64,
+    pub days: Vec<AvailabilityRollup>,
+}
+
+
+/// Reads the availability target, which has to be between 0 and 1.
+pub fn slo_target<Y: GetConfigVariable>() -> Result<f64, NanoServiceError> {
+    let value = match Y::get_config_variable("AVAILABILITY_SLO_TARGET".to_string()) {
+        Ok(value) => value,
+        Err(_) => return Ok(DEFAULT_SLO_TARGET)
+    };
+    match value.trim().parse::<f64>() {
+        Ok(target) if target > 0.0 && target < 1.0 => Ok(target),
+        _ => Err(NanoServiceError::new(
+            format!("AVAILABILITY_SLO_TARGET must be a fraction between 0 and 1, got '{}'", value),
+            NanoServiceErrorStatus::Unknown
+        ))
+    }
+}
+
+
+/// The error rate divided by the error rate the target allows.
+fn burn_rate(server_errors: i64, total_requests: i64, target: f64) -> f64 {
+    if total_requests == 0 {
+        return 0.0
+    }
+    (server_errors as f64 / total_requests as f64) / (1.0 - target)
+}
+
+
+/// Builds the SLO report from the daily rollups in the window.
+///
+/// # Arguments
+/// * `target` - The availability target
+/// * `days` - The rollups in the window, oldest first
+pub fn build_slo_report(target: f64, days: Vec<AvailabilityRollup>) -> SloReport {
+    let total_requests: i64 = days.iter().map(|day| day.total_requests).sum();
+    let server_errors: i64 = days.iter().map(|day| day.server_errors).sum();
+    let availability = match total_requests {
+        0 => 1.0,
+        _ => 1.0 - server_errors as f64 / total_requests as f64
+    };
+    let error_budget = total_requests as f64 * (1.0 - target);
+    let error_budget_remaining = match error_budget > 0.0 {
+        true => 1.0 - server_errors as f64 / error_budget,
+        false => 1.0
+    };
+    let latest_burn_rate = days.last()
+        .map(|day| burn_rate(day.server_errors, day.total_requests, target))
+        .unwrap_or(0.0);
+    SloReport {
+        target,
+        window_days: SLO_WINDOW_DAYS,
+        total_requests,
+        server_errors,
+        availability,
+        error_budget,
+        error_budget_remaining,
+        burn_rate: burn_rate(server_errors, total_requests, target),
+        latest_burn_rate,
+        days,
+    }
+}
+
+
+/// Gets the SLO report for the window ending today.
+pub async fn get_slo_report_core<X, Y>(today: NaiveDate) -> Result<SloReport, NanoServiceError>
+where
+    X: GetAvailabilityRollups,
+    Y: GetConfigVariable,
+{
+    let target = slo_target::<Y>()?;
+    let since = today - Days::new(SLO_WINDOW_DAYS - 1);
+    let days = X::get_availability_rollups(since).await?;
+    Ok(build_slo_report(target, days))
+}
+
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetAvailabilityRollups])]
+pub async fn get_slo_report() {
+    let report = get_slo_report_core::<X, Y>(Utc::now().date_naive()).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+
+/// Rolls up yesterday and today, then purges the per-minute metrics older than the retention.
+///
+/// # Arguments
+/// * `now` - The current time
+/// * `retention_days` - How many days of per-minute metrics to keep
+///
+/// # Returns
+/// * `Ok(Vec<AvailabilityRollup>)` - The days that were rolled up
+/// * `Err(NanoServiceError)` - If the rollup or purge failed
+pub async fn roll_up_availability<X>(now: NaiveDateTime, retention_days: u64) -> Result<Vec<AvailabilityRollup>, NanoServiceError>
+where
+    X: RollupAvailability + PurgeRequestMetrics,
+{
+    let since = now.date() - Days::new(1);
+    let rollups = X::rollup_availability(since).await?;
+    X::purge_request_metrics(now - Days::new(retention_days)).await?;
+    Ok(rollups)
+}
+
+
+/// Runs the rollup job in the background for the life of the runtime.
+///
+/// # Notes
+/// An error in a run is logged and the job carries on.
+pub fn spawn_availability_rollup<X, Y>()
+where
+    X: RollupAvailability + PurgeRequestMetrics + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let interval = std::time::Duration::from_secs(Y::get_positive_int("AVAILABILITY_ROLLUP_SECONDS").unwrap_or(DEFAULT_ROLLUP_SECONDS) as u64);
+    let retention_days = Y::get_positive_int("REQUEST_METRICS_RETENTION_DAYS").unwrap_or(DEFAULT_RETENTION_DAYS) as u64;
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = roll_up_availability::<X>(Utc::now().naive_utc(), retention_days).await {
+                log_limited!("ingress", "availability rollup failed: {}", e.message);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App};
+    use dal_tx_impl::impl_transaction;
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::token::HeaderToken;
+    use kernel::users::UserRole;
+    use std::sync::Mutex;
+
+    static PURGED_BEFORE: Mutex<Option<NaiveDateTime>> = Mutex::new(None);
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "AVAILABILITY_SLO_TARGET" => Ok("0.99".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    struct MockDbHandle;
+
+    test_support::confirmed_users!(MockDbHandle);
+
+    fn rollup(day: &str, total_requests: i64, server_errors: i64) -> AvailabilityRollup {
+        AvailabilityRollup {
+            day: day.parse().unwrap(),
+            total_requests,
+            server_errors,
+            client_errors: 0,
+            availability: 1.0 - server_errors as f64 / total_requests as f64,
+            error_rate: server_errors as f64 / total_requests as f64,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, GetAvailabilityRollups, get_availability_rollups)]
+    async fn get_availability_rollups(since: NaiveDate) -> Result<Vec<AvailabilityRollup>, NanoServiceError> {
+        assert_eq!(since, "2025-02-28".parse::<NaiveDate>().unwrap());
+        Ok(vec![rollup("2025-03-01", 1000, 5), rollup("2025-03-29", 1000, 20)])
+    }
+
+    #[impl_transaction(MockDbHandle, RollupAvailability, rollup_availability)]
+    async fn rollup_availability(since: NaiveDate) -> Result<Vec<AvailabilityRollup>, NanoServiceError> {
+        assert_eq!(since, "2025-03-28".parse::<NaiveDate>().unwrap());
+        Ok(vec![rollup("2025-03-28", 10, 0), rollup("2025-03-29", 10, 1)])
+    }
+
+    #[impl_transaction(MockDbHandle, PurgeRequestMetrics, purge_request_metrics)]
+    async fn purge_request_metrics(before: NaiveDateTime) -> Result<u64, NanoServiceError> {
+        *PURGED_BEFORE.lock().unwrap() = Some(before);
+        Ok(3)
+    }
+
+    #[test]
+    fn test_build_slo_report() {
+        let report = build_slo_report(0.99, vec![rollup("2025-03-01", 1000, 5), rollup("2025-03-02", 1000, 20)]);
+        assert_eq!(report.total_requests, 2000);
+        assert_eq!(report.server_errors, 25);
+        assert!((report.availability - 0.9875).abs() < 1e-9);
+        assert!((report.error_budget - 20.0).abs() < 1e-9);
+        assert!((report.error_budget_remaining + 0.25).abs() < 1e-9);
+        assert!((report.burn_rate - 1.25).abs() < 1e-9);
+        assert!((report.latest_burn_rate - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_empty_slo_report() {
+        let report = build_slo_report(0.999, Vec::new());
+        assert_eq!(report.availability, 1.0);
+        assert_eq!(report.error_budget_remaining, 1.0);
+        assert_eq!(report.burn_rate, 0.0);
+    }
+
+    #[test]
+    fn test_slo_target() {
+        struct BadConfig;
+        impl GetConfigVariable for BadConfig {
+            fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+                Ok("99.9".to_string())
+            }
+        }
+        assert_eq!(slo_target::<MockConfig>().unwrap(), 0.99);
+        assert!(slo_target::<BadConfig>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_roll_up_availability() {
+        let now = NaiveDateTime::parse_from_str("2025-03-29 00:02:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let rollups = roll_up_availability::<MockDbHandle>(now, 7).await.unwrap();
+        assert_eq!(rollups.len(), 2);
+        assert_eq!(
+            *PURGED_BEFORE.lock().unwrap(),
+            Some(NaiveDateTime::parse_from_str("2025-03-22 00:02:00", "%Y-%m-%d %H:%M:%S").unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_slo_report_core() {
+        let report = get_slo_report_core::<MockDbHandle, MockConfig>("2025-03-29".parse().unwrap()).await.unwrap();
+        assert_eq!(report.target, 0.99);
+        assert_eq!(report.days.len(), 2);
+    }
+
+    async fn send_request(role: UserRole) -> actix_web::dev::ServiceResponse {
+        let app = actix_test::init_service(App::new().route("/slo", web::get().to(
+            get_slo_report::<MockDbHandle, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new("some-agent".to_string(), 7, role);
+        let req = actix_test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri("/slo")
+            .to_request();
+        actix_test::call_service(&app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_get_slo_report_requires_admin() {
+        assert_eq!(send_request(UserRole::Worker).await.status(), 401);
+    }
+}
@@ -0,0 +1,126 @@
+//! Serves the avatars users upload for their profiles.
+//!
+//! # Overview
+//! `GET /avatars/{user_id}` reads the user's avatar from the object store at the size picked by
+//! `?size=`, the smallest stored size at least that many pixels across, and the largest by default.
+//! Avatars are served without a token as `<img>` tags cannot send one.
+//!
+//! # Caching
+//! Every avatar is sent with a strong `ETag` of its SHA-256, so a browser revalidating an avatar it
+//! already has gets a `304 Not Modified` back.
+//! - A request with a `?v=` version, as every `avatar_url` has, is cached for a year as `immutable`.
+//!   A new avatar has a new version and so a new URL, so a cached avatar is never stale.
+//! - A request without a version is sent with `no-cache` so the avatar is revalidated every time.
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use actix_web::http::header::{CacheControl, CacheDirective, ETag, EntityTag, IfNoneMatch, X_CONTENT_TYPE_OPTIONS};
+use actix_web::web::{Path, Query};
+use auth_core::avatars::{AvatarSize, AVATAR_CONTENT_TYPE};
+use kernel::object_store::GetObject;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+
+
+/// How long an avatar requested with a version is cached, a year.
+const VERSIONED_AVATAR_MAX_AGE_SECONDS: u32 = 31_536_000;
+
+
+/// The query of an avatar request.
+///
+/// # Fields
+/// * `size` - The width in pixels the avatar is shown at.
+/// * `v` - The version of the avatar, only used to tell versions apart in caches.
+#[derive(Deserialize, Debug)]
+pub struct AvatarQuery {
+    pub size: Option<u32>,
+    pub v: Option<String>,
+}
+
+
+/// Serves a user's avatar from `O`, a `404` if they have none.
+pub async fn get_avatar<O: GetObject, Y: GetConfigVariable>(req: HttpRequest, user_id: Path<i32>, query: Query<AvatarQuery>)
+-> Result<HttpResponse, NanoServiceError> {
+    let size = query.size.map(AvatarSize::fitting).unwrap_or(AvatarSize::Large);
+    let avatar = O::get_object::<Y>(&size.object_key(user_id.into_inner())).await?;
+    let etag = EntityTag::new_strong(format!("{:x}", Sha256::digest(&avatar)));
+    let cache_control = match query.v {
+        Some(_) => CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(VERSIONED_AVATAR_MAX_AGE_SECONDS),
+            CacheDirective::Extension("immutable".to_string(), None),
+        ]),
+        None => CacheControl(vec![CacheDirective::Public, CacheDirective::NoCache])
+    };
+    let not_modified = match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        None => false
+    };
+    if not_modified {
+        return Ok(HttpResponse::NotModified()
+            .insert_header(ETag(etag))
+            .insert_header(cache_control)
+            .finish())
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(AVATAR_CONTENT_TYPE)
+        .insert_header((X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .insert_header(ETag(etag))
+        .insert_header(cache_control)
+        .body(avatar))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App, http::StatusCode};
+    use actix_web::dev::ServiceResponse;
+    use kernel::object_store::engine_mock::{MemoryObjectStoreMock, STORED_OBJECTS};
+    use test_support::FakeConfig;
+
+    async fn get(uri: &str, if_none_match: Option<&str>) -> ServiceResponse {
+        let app = actix_test::init_service(
+            App::new().route("/avatars/{user_id}", web::get().to(get_avatar::<MemoryObjectStoreMock, FakeConfig>))
+        ).await;
+        let mut req = actix_test::TestRequest::get().uri(uri);
+        if let Some(etag) = if_none_match {
+            req = req.insert_header(("If-None-Match", etag));
+        }
+        actix_test::call_service(&app, req.to_request()).await
+    }
+
+    fn header(response: &ServiceResponse, name: &str) -> String {
+        response.headers().get(name).unwrap().to_str().unwrap().to_string()
+    }
+
+    #[actix_web::test]
+    async fn test_get_avatar() {
+        STORED_OBJECTS.lock().unwrap().insert("avatars/31/256.png".to_string(), b"large".to_vec());
+        STORED_OBJECTS.lock().unwrap().insert("avatars/31/64.png".to_string(), b"small".to_vec());
+
+        let response = get("/avatars/31?v=abc", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, "Content-Type"), "image/png");
+        assert_eq!(header(&response, "Cache-Control"), "public, max-age=31536000, immutable");
+        assert_eq!(actix_test::read_body(response).await, "large");
+
+        let response = get("/avatars/31?size=40", None).await;
+        assert_eq!(header(&response, "Cache-Control"), "public, no-cache");
+        assert_eq!(actix_test::read_body(response).await, "small");
+
+        assert_eq!(get("/avatars/32", None).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_not_modified() {
+        STORED_OBJECTS.lock().unwrap().insert("avatars/33/256.png".to_string(), b"large".to_vec());
+        let etag = header(&get("/avatars/33?v=abc", None).await, "ETag");
+
+        let response = get("/avatars/33?v=abc", Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(header(&response, "ETag"), etag);
+        assert_eq!(get("/avatars/33?v=abc", Some("\"other\"")).await.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,195 @@
+//! Lets super admins of a self-hosted install back the database up to the object store, and takes
+//! backups on a schedule.
+//!
+//! # Overview
+//! `POST /api/ops/v1/backups` takes a backup straight away and `GET /api/ops/v1/backups` lists the
+//! backups newest first. When `BACKUP_INTERVAL_HOURS` is set a backup is also taken on that interval,
+//! and after each one only the newest `BACKUP_KEEP` backups are kept. An external scheduler can take
+//! one instead with an API key, `POST /api/service/v1/backups`.
+//!
+//! # Notes
+//! Restoring replaces every row in the database so it is left to the `backups` CLI in the DAL,
+//! `backups restore <id> --yes`, rather than exposed over HTTP.
+//!
+//! # Variables
+//! * `BACKUP_INTERVAL_HOURS` - How often a backup is taken, unset or `0` turns scheduled backups off
+//! * `BACKUP_KEEP` - How many backups are kept, defaults to 7
+use actix_web::HttpResponse;
+use dal::backups::runner::{create_backup, prune_backups};
+use dal::backups::tx_definitions::{DeleteBackupRecord, DumpBackup, ListBackups, RecordBackup};
+use kernel::object_store::ObjectStore;
+use kernel::object_store::engine_s3::S3ObjectStore;
+use utils::api_endpoint;
+use utils::config::{GetConfigVariable, TypedConfig};
+
+
+/// The backups kept when not configured.
+const DEFAULT_KEEP: i64 = 7;
+
+/// The most backups listed.
+const LIST_LIMIT: i64 = 100;
+
+
+/// Takes a backup and prunes the old ones.
+///
+/// # Arguments
+/// * `keep` - How many backups to keep, including the one just taken.
+///
+/// # Returns
+/// * `bool` - Whether the backup was taken, the old backups are only pruned if it was
+pub async fn run_scheduled_backup<X, O, Y>(keep: usize) -> bool
+where
+    X: DumpBackup + RecordBackup + ListBackups + DeleteBackupRecord,
+    O: ObjectStore,
+    Y: GetConfigVariable,
+{
+    match create_backup::<X, O, Y>(None).await {
+        Ok(backup) => println!("backed up {} rows to {}", backup.row_count, backup.object_key),
+        Err(e) => {
+            println!("scheduled backup failed: {}", e.message);
+            return false
+        }
+    }
+    if let Err(e) = prune_backups::<X, O, Y>(keep).await {
+        println!("failed to prune backups: {}", e.message);
+    }
+    true
+}
+
+
+/// Takes a backup every `BACKUP_INTERVAL_HOURS` in the background for the life of the runtime,
+/// doing nothing if it is not set.
+pub fn spawn_backup_schedule<X, O, Y>()
+where
+    X: DumpBackup + RecordBackup + ListBackups + DeleteBackupRecord + 'static,
+    O: ObjectStore + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let Some(hours) = Y::get_int("BACKUP_INTERVAL_HOURS").ok().filter(|hours| *hours > 0) else {
+        return
+    };
+    let keep = Y::get_int("BACKUP_KEEP").ok().filter(|keep| *keep > 0).unwrap_or(DEFAULT_KEEP) as usize;
+    let interval = std::time::Duration::from_secs(hours as u64 * 3600);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            run_scheduled_backup::<X, O, Y>(keep).await;
+        }
+    });
+}
+
+
+/// Takes a backup now and returns it with `201 Created`.
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[DumpBackup, RecordBackup])]
+pub async fn create_backup_now() {
+    let backup = create_backup::<X, S3ObjectStore, Y>(Some(jwt.user_id)).await?;
+    Ok(HttpResponse::Created().json(backup))
+}
+
+
+/// Takes a backup for a scheduler sending an API key, and returns it with `201 Created`.
+///
+/// # Notes
+/// The backup is recorded as not triggered by a user, the same as a scheduled one, and old backups
+/// are not pruned.
+#[api_endpoint(auth=ApiKey, db_traits=[DumpBackup, RecordBackup], env_variable_trait=true)]
+pub async fn create_backup_with_api_key() {
+    let backup = create_backup::<X, S3ObjectStore, Y>(None).await?;
+    Ok(HttpResponse::Created().json(backup))
+}
+
+
+/// Lists the backups newest first.
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[ListBackups])]
+pub async fn list_backups() {
+    Ok(HttpResponse::Ok().json(X::list_backups(LIST_LIMIT).await?))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::backups::{Backup, DatabaseDump, NewBackup};
+    use kernel::chrono::NaiveDateTime;
+    use kernel::object_store::engine_mock::{FailingObjectStoreMock, MemoryObjectStoreMock};
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::users::UserRole;
+    use std::sync::Mutex;
+    use utils::errors::NanoServiceError;
+    use test_support::{call_endpoint, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+
+    static DELETED: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+
+    fn backup(id: i32) -> Backup {
+        Backup {
+            id,
+            object_key: format!("backups/ingress-{}.sql.gz", id),
+            size_bytes: 10,
+            row_count: 2,
+            schema_version: 7,
+            triggered_by: None,
+            date_created: NaiveDateTime::default(),
+        }
+    }
+
+    /// Has 9 backups newest first.
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, DumpBackup, dump_backup)]
+    async fn dump_backup() -> Result<DatabaseDump, NanoServiceError> {
+        Ok(DatabaseDump { sql: "-- backup at schema version 7\n".to_string(), row_count: 0, schema_version: 7 })
+    }
+
+    #[impl_transaction(MockPostgres, RecordBackup, record_backup)]
+    async fn record_backup(new_backup: NewBackup) -> Result<Backup, NanoServiceError> {
+        Ok(Backup { object_key: new_backup.object_key, ..backup(10) })
+    }
+
+    #[impl_transaction(MockPostgres, ListBackups, list_backups)]
+    async fn list_backups(limit: i64) -> Result<Vec<Backup>, NanoServiceError> {
+        Ok((1..=9).rev().take(limit as usize).map(backup).collect())
+    }
+
+    #[impl_transaction(MockPostgres, DeleteBackupRecord, delete_backup_record)]
+    async fn delete_backup_record(id: i32) -> Result<bool, NanoServiceError> {
+        DELETED.lock().unwrap().push(id);
+        Ok(true)
+    }
+
+    #[tokio::test]
+    async fn test_run_scheduled_backup() {
+        assert!(!run_scheduled_backup::<MockPostgres, FailingObjectStoreMock, FakeConfig>(7).await);
+        assert!(DELETED.lock().unwrap().is_empty());
+        assert!(run_scheduled_backup::<MockPostgres, MemoryObjectStoreMock, FakeConfig>(7).await);
+        assert_eq!(*DELETED.lock().unwrap(), vec![2, 1]);
+    }
+
+    async fn send(method: Method, role: UserRole) -> actix_web::dev::ServiceResponse {
+        let req = TokenBuilder::<FakeConfig, SuperAdminRoleCheck>::new()
+            .role(role)
+            .request(TestRequest::default().method(method.clone()).uri("/backups"));
+        match method {
+            Method::GET => call_endpoint(method, "/backups", list_backups::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>, req).await,
+            _ => call_endpoint(method, "/backups", create_backup_now::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>, req).await,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backup_endpoints() {
+        let resp = send(Method::GET, UserRole::SuperAdmin).await;
+        assert_eq!(resp.status(), 200);
+        let body: Vec<Backup> = test::read_body_json(resp).await;
+        assert_eq!(body.len(), 9);
+        assert_eq!(body[0].id, 9);
+
+        assert_eq!(send(Method::GET, UserRole::Admin).await.status(), 401);
+        assert_eq!(send(Method::POST, UserRole::Admin).await.status(), 401);
+        // the fake config has no object store endpoint, so the upload fails before it is attempted
+        assert_eq!(send(Method::POST, UserRole::SuperAdmin).await.status(), 500);
+    }
+}
@@ -0,0 +1,74 @@
+//! Build information embedded at compile time by `build.rs`.
+use actix_web::HttpResponse;
+use serde::Serialize;
+
+
+/// The short git sha the binary was built from.
+pub const GIT_SHA: &str = env!("BUILD_GIT_SHA");
+
+/// The UTC time the binary was built.
+pub const BUILD_TIME: &str = env!("BUILD_TIME");
+
+/// The crate version of the ingress binary.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The response header carrying the build info on every response.
+pub const BUILD_INFO_HEADER: &str = "X-Build-Info";
+
+
+/// The build info returned by the `/version` endpoint.
+#[derive(Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_sha: &'static str,
+    pub build_time: &'static str,
+}
+
+
+/// The value of the `X-Build-Info` header in the form `<version>+<git sha>@<build time>`.
+pub fn build_info_header_value() -> String {
+    format!("{}+{}@{}", VERSION, GIT_SHA, BUILD_TIME)
+}
+
+
+/// Returns the version, git sha, and build time of the running binary.
+pub async fn version() -> HttpResponse {
+    HttpResponse::Ok().json(BuildInfo {
+        version: VERSION,
+        git_sha: GIT_SHA,
+        build_time: BUILD_TIME
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App, middleware::DefaultHeaders};
+    use serde_json::Value;
+
+    #[actix_web::test]
+    async fn test_version_and_header() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(DefaultHeaders::new().add((BUILD_INFO_HEADER, build_info_header_value())))
+                .route("/version", web::get().to(version))
+                .route("/api/v1/ping", web::get().to(|| async { HttpResponse::Ok().finish() }))
+        ).await;
+
+        let req = actix_test::TestRequest::get().uri("/version").to_request();
+        let body: Value = actix_test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body, serde_json::json!({
+            "version": VERSION,
+            "git_sha": GIT_SHA,
+            "build_time": BUILD_TIME
+        }));
+        assert!(!GIT_SHA.is_empty() && !BUILD_TIME.is_empty());
+
+        let req = actix_test::TestRequest::get().uri("/api/v1/ping").to_request();
+        let response = actix_test::call_service(&app, req).await;
+        assert!(response.status().is_success());
+        let header = response.headers().get(BUILD_INFO_HEADER).unwrap().to_str().unwrap();
+        assert_eq!(header, format!("{}+{}@{}", VERSION, GIT_SHA, BUILD_TIME));
+    }
+}
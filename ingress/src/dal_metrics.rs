@@ -0,0 +1,104 @@
+//! Exposes the timings of the DAL transactions and configures when they are logged as slow.
+//!
+//! # Overview
+//! Every transaction is timed by `impl_transaction`, see `utils::transaction_metrics`. Admins read
+//! the counts and latency histograms by trait name to find the transactions worth optimising.
+//!
+//! # Variables
+//! * `DAL_SLOW_TRANSACTION_MS` - How long a transaction can run before it is logged as slow, defaults to 500
+use actix_web::HttpResponse;
+use utils::api_endpoint;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::transaction_metrics::{
+    DEFAULT_SLOW_TRANSACTION_MS, set_slow_transaction_threshold, transaction_metrics
+};
+
+
+/// Sets the slow transaction threshold from the config.
+///
+/// # Returns
+/// * `u64` - The threshold in milliseconds
+pub fn configure_slow_transaction_threshold<Y: GetConfigVariable>() -> u64 {
+    let threshold_ms = Y::get_int("DAL_SLOW_TRANSACTION_MS").ok()
+        .filter(|value| *value > 0)
+        .map(|value| value as u64)
+        .unwrap_or(DEFAULT_SLOW_TRANSACTION_MS);
+    set_slow_transaction_threshold(threshold_ms);
+    threshold_ms
+}
+
+
+#[api_endpoint(token=AdminRoleCheck)]
+pub async fn get_dal_metrics() {
+    Ok(HttpResponse::Ok().json(transaction_metrics()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App};
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::token::HeaderToken;
+    use kernel::users::UserRole;
+    use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+    use utils::transaction_metrics::record_transaction;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "DAL_SLOW_TRANSACTION_MS" => Ok("250".to_string()),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    struct EmptyConfig;
+
+    impl GetConfigVariable for EmptyConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    #[test]
+    fn test_configure_slow_transaction_threshold() {
+        assert_eq!(configure_slow_transaction_threshold::<EmptyConfig>(), DEFAULT_SLOW_TRANSACTION_MS);
+        assert_eq!(configure_slow_transaction_threshold::<MockConfig>(), 250);
+        set_slow_transaction_threshold(DEFAULT_SLOW_TRANSACTION_MS);
+    }
+
+    async fn send_request(role: UserRole) -> actix_web::dev::ServiceResponse {
+        let app = actix_test::init_service(App::new().route("/dal", web::get().to(
+            get_dal_metrics::<MockPostgres, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new("some-agent".to_string(), 7, role);
+        let req = actix_test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri("/dal")
+            .to_request();
+        actix_test::call_service(&app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_get_dal_metrics() {
+        record_transaction("GetDalMetricsTest", std::time::Duration::from_millis(3), true);
+        let response = send_request(UserRole::Admin).await;
+        assert_eq!(response.status(), 200);
+        let body: serde_json::Value = actix_test::read_body_json(response).await;
+        let stats = body.as_array().unwrap().iter()
+            .find(|stats| stats["transaction"] == "GetDalMetricsTest")
+            .unwrap();
+        assert_eq!(stats["count"], 1);
+
+        assert_eq!(send_request(UserRole::Worker).await.status(), 401);
+    }
+}
@@ -0,0 +1,88 @@
+//! Subscribes the features that react to domain events published in the process.
+//!
+//! # Overview
+//! * `user_blocked` - Recorded in the audit log as `user:blocked`, with the super admin who blocked
+//!   the user as the actor, so blocks can be reviewed with the other privileged actions.
+//! * `user_created`, `todo_completed` - Queued for the webhooks subscribed to them, see `webhooks`.
+use std::future::Future;
+use std::pin::Pin;
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use dal::webhooks::tx_definitions::EnqueueWebhookDeliveries;
+use kernel::audit_log::NewAuditEntry;
+use kernel::events::{DomainEvent, EventEnvelope};
+use kernel::events::engine_in_process::subscribe;
+use crate::webhooks::enqueue_webhooks;
+
+
+/// The audit log action a user being blocked is recorded as.
+pub const USER_BLOCKED_ACTION: &str = "user:blocked";
+
+
+/// Records the events that need an audit entry, logging rather than retrying if the entry fails.
+pub fn audit_event<X: CreateAuditEntry>(envelope: EventEnvelope) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        let entry = match envelope.event {
+            DomainEvent::UserBlocked { user_id, blocked_by } => NewAuditEntry {
+                actor_id: blocked_by,
+                action: USER_BLOCKED_ACTION.to_string(),
+                subject_id: Some(user_id),
+                details: serde_json::json!({"event_id": envelope.id}),
+            },
+            _ => return
+        };
+        if let Err(e) = X::create_audit_entry(entry).await {
+            println!("failed to audit {} event {}: {}", envelope.event.name(), envelope.id, e.message);
+        }
+    })
+}
+
+
+/// Subscribes every feature to the events published with the in-process bus.
+pub fn register_event_subscribers<X: CreateAuditEntry + EnqueueWebhookDeliveries + 'static>() {
+    subscribe(audit_event::<X>);
+    subscribe(enqueue_webhooks::<X>);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::audit_log::AuditEntry;
+    use kernel::chrono::NaiveDateTime;
+    use sqlx::types::Json;
+    use std::sync::Mutex;
+    use utils::errors::NanoServiceError;
+
+    static AUDITED: Mutex<Vec<NewAuditEntry>> = Mutex::new(Vec::new());
+
+    struct MockPostgres;
+
+    #[impl_transaction(MockPostgres, CreateAuditEntry, create_audit_entry)]
+    async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        AUDITED.lock().unwrap().push(entry.clone());
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: Json(entry.details),
+            date_created: NaiveDateTime::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_audit_event() {
+        let blocked = EventEnvelope::new(DomainEvent::UserBlocked { user_id: 6, blocked_by: 1 });
+        audit_event::<MockPostgres>(blocked.clone()).await;
+        audit_event::<MockPostgres>(EventEnvelope::new(DomainEvent::TodoCompleted {
+            todo_id: 2, assigned_to: 6, pending_review: false
+        })).await;
+        assert_eq!(*AUDITED.lock().unwrap(), vec![NewAuditEntry {
+            actor_id: 1,
+            action: "user:blocked".to_string(),
+            subject_id: Some(6),
+            details: serde_json::json!({"event_id": blocked.id}),
+        }]);
+    }
+}
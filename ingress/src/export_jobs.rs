@@ -0,0 +1,591 @@
+//! Runs exports too large to stream in a request in the background, for admins to download when done.
+//!
+//! # Overview
+//! `POST /api/ops/v1/exports` queues an export and returns its job straight away. The export worker
+//! claims queued jobs one at a time, writes the export gzipped a page at a time with `export_stream`,
+//! recording the rows written after each page, and puts it in the object store. Polling
+//! `GET /api/ops/v1/exports/{id}` reports how far the job has got and, once it is complete, a
+//! presigned link to download it from the store directly.
+//!
+//! Each run of the worker also deletes the exports that have expired from the store, so a generated
+//! file is only kept for `EXPORT_RETENTION_HOURS` after it is written.
+//!
+//! # Notes
+//! - A job is only visible to the admin who asked for it, anyone else gets a `404`.
+//! - A download link is valid for `DOWNLOAD_LINK_SECONDS`, or until the export expires if sooner,
+//!   polling again gets a fresh link.
+//! - A worker that dies mid-export leaves its job running, it is claimed again and started over once
+//!   it has recorded no progress for ten minutes.
+//!
+//! # Variables
+//! * `EXPORT_POLL_SECONDS` - How often the worker looks for queued and expired exports, defaults to 10
+//! * `EXPORT_RETENTION_HOURS` - How long a written export is kept, defaults to 24
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use actix_web::{HttpResponse, web::{Json, Path}};
+use dal::audit_log::tx_definitions::ListAuditEntries;
+use dal::export_jobs::tx_definitions::{
+    ClaimExportJob,
+    CompleteExportJob,
+    CountExportRows,
+    CreateExportJob,
+    FailExportJob,
+    GetExportJob,
+    ListExpiredExports,
+    ListTodosForExport,
+    ListUsersForExport,
+    MarkExportExpired,
+    RecordExportProgress,
+};
+use kernel::audit_log::AuditEntry;
+use kernel::chrono::{Duration, NaiveDateTime, Utc};
+use kernel::export_jobs::{ExportJob, ExportJobStatus, ExportKind, ExportedUser, NewExportJob};
+use kernel::object_store::ObjectStore;
+use kernel::object_store::engine_s3::S3ObjectStore;
+use kernel::to_do_items::Todo;
+use serde::{Deserialize, Serialize};
+use utils::api_endpoint;
+use utils::log_limited;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::export_stream::{export_stream, Bytes, ExportEncoding, ExportFormat, Stream, DEFAULT_EXPORT_PAGE_SIZE};
+
+
+/// The poll interval used when not configured.
+const DEFAULT_POLL_SECONDS: i64 = 10;
+
+/// The hours an export is kept for when not configured.
+const DEFAULT_RETENTION_HOURS: i64 = 24;
+
+/// The longest a download link is valid for.
+const DOWNLOAD_LINK_SECONDS: i64 = 900;
+
+/// The most expired exports deleted per run of the worker.
+const EXPIRY_BATCH_SIZE: i64 = 100;
+
+
+/// The export to queue.
+///
+/// # Fields
+/// * `kind` - What to export.
+/// * `format` - JSON or CSV, defaults to JSON.
+#[derive(Deserialize, Debug)]
+pub struct CreateExportBody {
+    pub kind: ExportKind,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+
+/// An export job as reported to the admin who asked for it.
+///
+/// # Fields
+/// * `id` - The ID to poll the job with.
+/// * `kind` - What is exported.
+/// * `format` - `json` or `csv`.
+/// * `status` - Where the job has got to.
+/// * `progress_percent` - How far through the export the job is, only `100` once it is complete.
+/// * `exported_rows` - The rows written so far.
+/// * `total_rows` - The rows there are to export, `None` until the job has started.
+/// * `download_url` - A link to download the gzipped export from, only once it is complete.
+/// * `expires_at` - When the export is deleted.
+/// * `error` - Why the job failed, if it did.
+/// * `date_created` - When the export was asked for.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ExportJobView {
+    pub id: i32,
+    pub kind: ExportKind,
+    pub format: String,
+    pub status: ExportJobStatus,
+    pub progress_percent: u8,
+    pub exported_rows: i64,
+    pub total_rows: Option<i64>,
+    pub download_url: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub error: Option<String>,
+    pub date_created: NaiveDateTime,
+}
+
+
+/// Builds the view of a job, presigning a download link if it is complete.
+///
+/// # Arguments
+/// * `job` - The job.
+/// * `now` - The current time, the link is never valid past the export's `expires_at`.
+pub fn export_job_view<O: ObjectStore, Y: GetConfigVariable>(job: ExportJob, now: NaiveDateTime) -> Result<ExportJobView, NanoServiceError> {
+    let download_url = match (job.status, &job.object_key, job.expires_at) {
+        (ExportJobStatus::Complete, Some(object_key), Some(expires_at)) if expires_at > now => {
+            let expires_in = (expires_at - now).num_seconds().clamp(1, DOWNLOAD_LINK_SECONDS);
+            Some(O::presigned_url::<Y>(object_key, expires_in as u64)?)
+        },
+        _ => None
+    };
+    Ok(ExportJobView {
+        id: job.id,
+        kind: job.kind,
+        progress_percent: job.progress_percent(),
+        format: job.format,
+        status: job.status,
+        exported_rows: job.exported_rows,
+        total_rows: job.total_rows,
+        download_url,
+        expires_at: job.expires_at,
+        error: job.error,
+        date_created: job.date_created,
+    })
+}
+
+
+/// Queues an export for the admin and returns its job with `202 Accepted`.
+#[api_endpoint(token=AdminRoleCheck, db_traits=[CreateExportJob])]
+pub async fn create_export(body: Json<CreateExportBody>) {
+    let job = X::create_export_job(NewExportJob {
+        requested_by: jwt.user_id,
+        kind: body.kind,
+        format: body.format.extension().to_string(),
+    }).await?;
+    Ok(HttpResponse::Accepted().json(export_job_view::<S3ObjectStore, Y>(job, Utc::now().naive_utc())?))
+}
+
+
+/// Reports the progress of one of the admin's exports, with a download link once it is complete.
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetExportJob])]
+pub async fn get_export(id: Path<i32>) {
+    let job = match X::get_export_job(id.into_inner()).await? {
+        Some(job) if job.requested_by == jwt.user_id => job,
+        _ => return Err(NanoServiceError::new("Export not found".to_string(), NanoServiceErrorStatus::NotFound))
+    };
+    Ok(HttpResponse::Ok().json(export_job_view::<S3ObjectStore, Y>(job, Utc::now().naive_utc())?))
+}
+
+
+/// Counts the rows written as each page of an export is read, recording them on the job.
+#[derive(Clone)]
+struct ExportProgress {
+    job_id: i32,
+    total_rows: i64,
+    exported_rows: Arc<AtomicI64>,
+}
+
+impl ExportProgress {
+
+    /// Records a page that was read, passing it on to be written.
+    async fn page<X: RecordExportProgress, T>(self, read: impl Future<Output = Result<Vec<T>, NanoServiceError>>) -> Result<Vec<T>, NanoServiceError> {
+        let rows = read.await?;
+        let exported_rows = self.exported_rows.fetch_add(rows.len() as i64, Ordering::SeqCst) + rows.len() as i64;
+        // the count can pass the total when rows are added during the export, the percentage is capped
+        if let Err(e) = X::record_export_progress(self.job_id, self.total_rows, exported_rows).await {
+            println!("failed to record progress of export {}: {}", self.job_id, e.message);
+        }
+        Ok(rows)
+    }
+}
+
+
+/// Reads a whole export into memory.
+async fn collect_export<S: Stream<Item = Result<Bytes, NanoServiceError>>>(stream: S) -> Result<Vec<u8>, NanoServiceError> {
+    let mut stream = std::pin::pin!(stream);
+    let mut body = Vec::new();
+    while let Some(chunk) = std::future::poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        body.extend_from_slice(&chunk?);
+    }
+    Ok(body)
+}
+
+
+/// Writes a job's export gzipped, recording its progress a page at a time.
+pub async fn write_export<X>(job: &ExportJob) -> Result<Vec<u8>, NanoServiceError>
+where
+    X: CountExportRows + RecordExportProgress + ListUsersForExport + ListTodosForExport + ListAuditEntries,
+{
+    let total_rows = X::count_export_rows(job.kind).await?;
+    X::record_export_progress(job.id, total_rows, 0).await?;
+    let progress = ExportProgress { job_id: job.id, total_rows, exported_rows: Arc::new(AtomicI64::new(0)) };
+    let format = job.export_format();
+    let page_size = DEFAULT_EXPORT_PAGE_SIZE;
+    // every cursor is a row ID so it always fits
+    match job.kind {
+        ExportKind::Users => collect_export(export_stream(
+            format, ExportEncoding::Gzip, page_size, |user: &ExportedUser| user.id as i64,
+            |after| progress.clone().page::<X, _>(X::list_users_for_export(after.map(|id| id as i32), page_size as i64))
+        )).await,
+        ExportKind::Todos => collect_export(export_stream(
+            format, ExportEncoding::Gzip, page_size, |todo: &Todo| todo.id as i64,
+            |after| progress.clone().page::<X, _>(X::list_todos_for_export(after.map(|id| id as i32), page_size as i64))
+        )).await,
+        ExportKind::AuditLog => collect_export(export_stream(
+            format, ExportEncoding::Gzip, page_size, |entry: &AuditEntry| entry.id as i64,
+            |after| progress.clone().page::<X, _>(X::list_audit_entries(after.map(|id| id as i32), page_size as i64))
+        )).await,
+    }
+}
+
+
+/// Writes a job's export to the object store and completes it, or records why it failed.
+///
+/// # Arguments
+/// * `job` - The job, claimed by this worker.
+/// * `retention` - How long the export is kept once it is written.
+///
+/// # Returns
+/// * `ExportJobStatus` - `Complete` or `Failed`
+pub async fn run_export_job<X, O, Y>(job: &ExportJob, retention: Duration) -> ExportJobStatus
+where
+    X: CountExportRows + RecordExportProgress + ListUsersForExport + ListTodosForExport + ListAuditEntries
+        + CompleteExportJob + FailExportJob,
+    O: ObjectStore,
+    Y: GetConfigVariable,
+{
+    let object_key = job.storage_key();
+    let outcome = match write_export::<X>(job).await {
+        Ok(body) => O::put_object::<Y>(&object_key, body, "application/gzip").await,
+        Err(e) => Err(e)
+    };
+    let recorded = match outcome {
+        Ok(()) => X::complete_export_job(job.id, object_key, Utc::now().naive_utc() + retention).await
+            .map(|_| ExportJobStatus::Complete),
+        Err(e) => {
+            println!("export {} failed: {}", job.id, e.message);
+            X::fail_export_job(job.id, e.message).await.map(|_| ExportJobStatus::Failed)
+        }
+    };
+    recorded.unwrap_or_else(|e| {
+        // the job is left running and is claimed again once it goes stale
+        println!("failed to record the outcome of export {}: {}", job.id, e.message);
+        ExportJobStatus::Failed
+    })
+}
+
+
+/// Deletes the expired exports from the object store and marks their jobs expired.
+///
+/// # Returns
+/// * `usize` - The exports expired, one whose file could not be deleted is tried again on the next run
+pub async fn expire_exports<X, O, Y>() -> usize
+where
+    X: ListExpiredExports + MarkExportExpired,
+    O: ObjectStore,
+    Y: GetConfigVariable,
+{
+    let jobs = match X::list_expired_exports(EXPIRY_BATCH_SIZE).await {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            println!("failed to list expired exports: {}", e.message);
+            return 0
+        }
+    };
+    let mut expired = 0;
+    for job in jobs {
+        if let Some(object_key) = &job.object_key {
+            if let Err(e) = O::delete_object::<Y>(object_key).await {
+                println!("failed to delete expired export {}: {}", job.id, e.message);
+                continue
+            }
+        }
+        match X::mark_export_expired(job.id).await {
+            Ok(_) => expired += 1,
+            Err(e) => println!("failed to mark export {} as expired: {}", job.id, e.message)
+        }
+    }
+    expired
+}
+
+
+/// Runs the export worker in the background for the life of the runtime.
+pub fn spawn_export_worker<X, O, Y>()
+where
+    X: ClaimExportJob + CountExportRows + RecordExportProgress + ListUsersForExport + ListTodosForExport
+        + ListAuditEntries + CompleteExportJob + FailExportJob + ListExpiredExports + MarkExportExpired + 'static,
+    O: ObjectStore + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let interval = std::time::Duration::from_secs(Y::get_positive_int("EXPORT_POLL_SECONDS").unwrap_or(DEFAULT_POLL_SECONDS) as u64);
+    let retention = Duration::hours(Y::get_positive_int("EXPORT_RETENTION_HOURS").unwrap_or(DEFAULT_RETENTION_HOURS));
+    tokio::spawn(async move {
+        loop {
+            expire_exports::<X, O, Y>().await;
+            loop {
+                match X::claim_export_job().await {
+                    Ok(Some(job)) => { run_export_job::<X, O, Y>(&job, retention).await; },
+                    Ok(None) => break,
+                    Err(e) => {
+                        log_limited!("ingress", "failed to claim an export: {}", e.message);
+                        break
+                    }
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use flate2::read::GzDecoder;
+    use kernel::object_store::engine_mock::{FailingObjectStoreMock, MemoryObjectStoreMock, STORED_OBJECTS};
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::users::UserRole;
+    use std::io::Read;
+    use std::sync::Mutex;
+    use test_support::{call_endpoint, factories, fake_config, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+
+    /// The progress recorded for each job, and the outcome each job ended with.
+    static PROGRESS: Mutex<Vec<(i32, i64, i64)>> = Mutex::new(Vec::new());
+    static OUTCOMES: Mutex<Vec<(i32, ExportJobStatus, Option<String>)>> = Mutex::new(Vec::new());
+    static EXPIRED: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+
+    fake_config!(StoreConfig, {
+        "OBJECT_STORE_ENDPOINT" => "http://minio:9000",
+        "OBJECT_STORE_BUCKET" => "exports",
+        "OBJECT_STORE_REGION" => "eu-west-2",
+        "OBJECT_STORE_PATH_STYLE" => "true",
+    });
+
+    fn at(datetime: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn job(id: i32, kind: ExportKind, status: ExportJobStatus) -> ExportJob {
+        ExportJob {
+            id,
+            requested_by: 1,
+            kind,
+            format: "csv".to_string(),
+            status,
+            total_rows: None,
+            exported_rows: 0,
+            object_key: None,
+            error: None,
+            date_created: at("2025-05-20 09:00:00"),
+            updated_at: at("2025-05-20 09:00:00"),
+            completed_at: None,
+            expires_at: None,
+        }
+    }
+
+    fn complete_job(id: i32, requested_by: i32, expires_at: NaiveDateTime) -> ExportJob {
+        let mut job = job(id, ExportKind::Users, ExportJobStatus::Complete);
+        job.requested_by = requested_by;
+        job.total_rows = Some(3);
+        job.exported_rows = 3;
+        job.object_key = Some(job.storage_key());
+        job.expires_at = Some(expires_at);
+        job
+    }
+
+    /// Serves 2500 users and two expired exports, job `41` has a missing file, and fails to count to-do items.
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, CreateExportJob, create_export_job)]
+    async fn create_export_job(new_job: NewExportJob) -> Result<ExportJob, NanoServiceError> {
+        let mut created = job(9, new_job.kind, ExportJobStatus::Pending);
+        created.requested_by = new_job.requested_by;
+        created.format = new_job.format;
+        Ok(created)
+    }
+
+    /// Job `3` belongs to user 1 and never expires, job `4` belongs to user 2.
+    #[impl_transaction(MockPostgres, GetExportJob, get_export_job)]
+    async fn get_export_job(id: i32) -> Result<Option<ExportJob>, NanoServiceError> {
+        Ok(match id {
+            3 => Some(complete_job(3, 1, at("2999-01-01 00:00:00"))),
+            4 => Some(complete_job(4, 2, at("2999-01-01 00:00:00"))),
+            _ => None
+        })
+    }
+
+    #[impl_transaction(MockPostgres, CountExportRows, count_export_rows)]
+    async fn count_export_rows(kind: ExportKind) -> Result<i64, NanoServiceError> {
+        match kind {
+            ExportKind::Todos => Err(NanoServiceError::new("connection lost".to_string(), NanoServiceErrorStatus::Unknown)),
+            _ => Ok(2500)
+        }
+    }
+
+    #[impl_transaction(MockPostgres, RecordExportProgress, record_export_progress)]
+    async fn record_export_progress(id: i32, total_rows: i64, exported_rows: i64) -> Result<bool, NanoServiceError> {
+        PROGRESS.lock().unwrap().push((id, total_rows, exported_rows));
+        Ok(true)
+    }
+
+    #[impl_transaction(MockPostgres, ListUsersForExport, list_users_for_export)]
+    async fn list_users_for_export(after_id: Option<i32>, limit: i64) -> Result<Vec<ExportedUser>, NanoServiceError> {
+        Ok((1..=2500)
+            .filter(|id| after_id.is_none_or(|after_id| *id > after_id))
+            .take(limit as usize)
+            .map(|id| {
+                let user = factories::user(id);
+                ExportedUser {
+                    id,
+                    username: user.username,
+                    email: user.email,
+                    first_name: user.first_name,
+                    last_name: user.last_name,
+                    user_role: user.user_role,
+                    confirmed: user.confirmed,
+                    blocked: user.blocked,
+                    date_created: at("2025-01-01 00:00:00"),
+                    last_logged_in: at("2025-01-01 00:00:00"),
+                }
+            })
+            .collect())
+    }
+
+    #[impl_transaction(MockPostgres, ListTodosForExport, list_todos_for_export)]
+    async fn list_todos_for_export(_after_id: Option<i32>, _limit: i64) -> Result<Vec<Todo>, NanoServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[impl_transaction(MockPostgres, ListAuditEntries, list_audit_entries)]
+    async fn list_audit_entries(_before_id: Option<i32>, _limit: i64) -> Result<Vec<AuditEntry>, NanoServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[impl_transaction(MockPostgres, CompleteExportJob, complete_export_job)]
+    async fn complete_export_job(id: i32, _object_key: String, _expires_at: NaiveDateTime) -> Result<bool, NanoServiceError> {
+        OUTCOMES.lock().unwrap().push((id, ExportJobStatus::Complete, None));
+        Ok(true)
+    }
+
+    #[impl_transaction(MockPostgres, FailExportJob, fail_export_job)]
+    async fn fail_export_job(id: i32, error: String) -> Result<bool, NanoServiceError> {
+        OUTCOMES.lock().unwrap().push((id, ExportJobStatus::Failed, Some(error)));
+        Ok(true)
+    }
+
+    #[impl_transaction(MockPostgres, ListExpiredExports, list_expired_exports)]
+    async fn list_expired_exports(_limit: i64) -> Result<Vec<ExportJob>, NanoServiceError> {
+        let mut missing = complete_job(41, 1, at("2025-05-19 09:00:00"));
+        missing.object_key = None;
+        Ok(vec![complete_job(40, 1, at("2025-05-19 09:00:00")), missing])
+    }
+
+    #[impl_transaction(MockPostgres, MarkExportExpired, mark_export_expired)]
+    async fn mark_export_expired(id: i32) -> Result<bool, NanoServiceError> {
+        EXPIRED.lock().unwrap().push(id);
+        Ok(true)
+    }
+
+    fn outcome(id: i32) -> Option<(i32, ExportJobStatus, Option<String>)> {
+        OUTCOMES.lock().unwrap().iter().find(|outcome| outcome.0 == id).cloned()
+    }
+
+    #[test]
+    fn test_export_job_view() {
+        let now = at("2025-05-20 12:00:00");
+        let view = export_job_view::<MemoryObjectStoreMock, FakeConfig>(complete_job(3, 1, at("2025-05-20 12:10:00")), now).unwrap();
+        assert_eq!(view.progress_percent, 100);
+        assert_eq!(view.download_url, Some("memory://exports/3/users.csv.gz?expires_in=600".to_string()));
+
+        let view = export_job_view::<MemoryObjectStoreMock, FakeConfig>(complete_job(3, 1, at("2025-05-21 12:00:00")), now).unwrap();
+        assert_eq!(view.download_url, Some(format!("memory://exports/3/users.csv.gz?expires_in={}", DOWNLOAD_LINK_SECONDS)));
+
+        let view = export_job_view::<MemoryObjectStoreMock, FakeConfig>(complete_job(3, 1, at("2025-05-20 11:00:00")), now).unwrap();
+        assert_eq!(view.download_url, None);
+
+        let mut running = job(3, ExportKind::Users, ExportJobStatus::Running);
+        running.total_rows = Some(200);
+        running.exported_rows = 50;
+        let view = export_job_view::<FailingObjectStoreMock, FakeConfig>(running, now).unwrap();
+        assert_eq!((view.progress_percent, view.download_url), (25, None));
+    }
+
+    #[tokio::test]
+    async fn test_run_export_job() {
+        let status = run_export_job::<MockPostgres, MemoryObjectStoreMock, FakeConfig>(
+            &job(1, ExportKind::Users, ExportJobStatus::Running), Duration::hours(24)
+        ).await;
+        assert_eq!(status, ExportJobStatus::Complete);
+        assert_eq!(outcome(1), Some((1, ExportJobStatus::Complete, None)));
+
+        // the total is recorded before the first page, then each page of 1000 rows as it is written
+        let progress: Vec<i64> = PROGRESS.lock().unwrap().iter()
+            .filter(|(id, total, _)| *id == 1 && *total == 2500)
+            .map(|(_, _, exported)| *exported)
+            .collect();
+        assert_eq!(progress, vec![0, 1000, 2000, 2500]);
+
+        let gzipped = STORED_OBJECTS.lock().unwrap().get("exports/1/users.csv.gz").cloned().unwrap();
+        let mut csv = String::new();
+        GzDecoder::new(gzipped.as_slice()).read_to_string(&mut csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2501);
+        assert!(lines[0].starts_with("id,username,email"));
+        assert!(lines[2500].starts_with("2500,"));
+        assert!(!csv.contains("password"));
+    }
+
+    #[tokio::test]
+    async fn test_run_export_job_failures() {
+        let status = run_export_job::<MockPostgres, FailingObjectStoreMock, FakeConfig>(
+            &job(2, ExportKind::AuditLog, ExportJobStatus::Running), Duration::hours(24)
+        ).await;
+        assert_eq!(status, ExportJobStatus::Failed);
+        assert_eq!(outcome(2), Some((2, ExportJobStatus::Failed, Some("failed to store exports/2/audit_log.csv.gz".to_string()))));
+
+        let status = run_export_job::<MockPostgres, MemoryObjectStoreMock, FakeConfig>(
+            &job(5, ExportKind::Todos, ExportJobStatus::Running), Duration::hours(24)
+        ).await;
+        assert_eq!(status, ExportJobStatus::Failed);
+        assert_eq!(outcome(5), Some((5, ExportJobStatus::Failed, Some("connection lost".to_string()))));
+    }
+
+    #[tokio::test]
+    async fn test_expire_exports() {
+        STORED_OBJECTS.lock().unwrap().insert("exports/40/users.csv.gz".to_string(), vec![1, 2, 3]);
+        assert_eq!(expire_exports::<MockPostgres, FailingObjectStoreMock, FakeConfig>().await, 1);
+        assert_eq!(*EXPIRED.lock().unwrap(), vec![41]);
+
+        assert_eq!(expire_exports::<MockPostgres, MemoryObjectStoreMock, FakeConfig>().await, 2);
+        assert_eq!(*EXPIRED.lock().unwrap(), vec![41, 40, 41]);
+        assert!(!STORED_OBJECTS.lock().unwrap().contains_key("exports/40/users.csv.gz"));
+    }
+
+    async fn get(role: UserRole, id: i32) -> actix_web::dev::ServiceResponse {
+        let req = TokenBuilder::<StoreConfig, AdminRoleCheck>::new()
+            .role(role)
+            .request(TestRequest::get().uri(&format!("/exports/{}", id)));
+        call_endpoint(Method::GET, "/exports/{id}", get_export::<MockPostgres, StoreConfig, PassAuthSessionCheckMock>, req).await
+    }
+
+    #[tokio::test]
+    async fn test_get_export() {
+        let resp = get(UserRole::Admin, 3).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "complete");
+        assert_eq!(body["progress_percent"], 100);
+        let url = body["download_url"].as_str().unwrap();
+        assert!(url.starts_with("http://minio:9000/exports/exports/3/users.csv.gz?X-Amz-Algorithm=AWS4-HMAC-SHA256"));
+        assert!(url.contains(&format!("X-Amz-Expires={}&", DOWNLOAD_LINK_SECONDS)));
+
+        // another admin's export is reported as missing
+        assert_eq!(get(UserRole::Admin, 4).await.status(), 404);
+        assert_eq!(get(UserRole::Admin, 5).await.status(), 404);
+        assert_eq!(get(UserRole::Worker, 3).await.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn test_create_export() {
+        let req = TokenBuilder::<FakeConfig, AdminRoleCheck>::new()
+            .role(UserRole::Admin)
+            .request(TestRequest::post().uri("/exports"))
+            .set_json(serde_json::json!({"kind": "audit_log", "format": "csv"}));
+        let resp = call_endpoint(Method::POST, "/exports", create_export::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>, req).await;
+        assert_eq!(resp.status(), 202);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["id"], 9);
+        assert_eq!(body["kind"], "audit_log");
+        assert_eq!(body["format"], "csv");
+        assert_eq!(body["status"], "pending");
+        assert_eq!(body["download_url"], serde_json::Value::Null);
+    }
+}
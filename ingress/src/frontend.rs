@@ -0,0 +1,448 @@
+//! Serves the web and admin frontends embedded in the binary.
+//!
+//! # Overview
+//! Requests under `/admin` are served from the admin bundle in `frontends/admin/public`, every other
+//! request that is not for the API is served from the web bundle in `frontends/web/public`. A request
+//! for a file, such as `/admin/bundle.js`, gets that file from its bundle and any other request gets
+//! the bundle's index so the frontend can route it.
+//!
+//! # Caching
+//! Every file is sent with a strong `ETag` of its SHA-256, so a browser revalidating a file it
+//! already has gets a `304 Not Modified` back. The bundles' file names are not fingerprinted, so:
+//! - An index is sent with `no-cache`, a deploy is picked up on the next page load.
+//! - Any other file is cached for `ASSET_MAX_AGE_SECONDS` before it is revalidated.
+//!
+//! The hashes are worked out when the files are embedded, so a request never hashes a file.
+//!
+//! # Ranges
+//! A request with a single byte `Range` gets a `206 Partial Content` with just that range, so an
+//! interrupted download of a large bundle carries on where it stopped. A range past the end of the
+//! file gets a `416 Range Not Satisfiable`, and a request for several ranges, or with an `If-Range`
+//! that does not match the file's `ETag`, gets the whole file.
+//!
+//! # Precompressed files
+//! A file with a `.br` or `.gz` copy next to it, as `npm run build` writes for the web bundle, is
+//! sent as the copy the request's `Accept-Encoding` prefers, so the bundle is compressed once when
+//! it is built rather than on every request. Each copy has its own `ETag` and the response varies
+//! on `Accept-Encoding`. A copy that does not decompress to the file next to it, left over from an
+//! earlier build, is never sent, the copies are checked once on the first request.
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::Path;
+use std::sync::LazyLock;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{
+    ACCEPT_RANGES, CONTENT_ENCODING, VARY, AcceptEncoding, CacheControl, CacheDirective, ContentEncoding,
+    ContentRange, ContentRangeSpec, ETag, Encoding, EntityTag, IfNoneMatch, IfRange, Range
+};
+use actix_web::web::Bytes;
+use rust_embed::{EmbeddedFile, RustEmbed};
+use sha2::{Digest, Sha256};
+
+
+/// How long a file other than an index is cached before it is revalidated.
+const ASSET_MAX_AGE_SECONDS: u32 = 3600;
+
+/// The encodings a file can have a copy in and the extension of the copy, in the order they are preferred.
+const PRECOMPRESSED: [(ContentEncoding, &str); 2] = [(ContentEncoding::Brotli, "br"), (ContentEncoding::Gzip, "gz")];
+
+/// The precompressed copies, by bundle and name, that decompress to the file next to them.
+static FRESH_COPIES: LazyLock<HashSet<(Bundle, String)>> = LazyLock::new(fresh_copies);
+
+
+/// Embeds the web frontend files into the binary.
+#[derive(RustEmbed)]
+#[folder = "../frontends/web/public"]
+struct WebAssets;
+
+
+/// Embeds the admin frontend files into the binary.
+#[derive(RustEmbed)]
+#[folder = "../frontends/admin/public"]
+struct AdminAssets;
+
+
+/// A frontend embedded in the binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Bundle {
+    Web,
+    Admin,
+}
+
+impl Bundle {
+
+    /// The bundle a request path is served from.
+    fn for_path(path: &str) -> Self {
+        if path == "/admin" || path.starts_with("/admin/") {
+            Bundle::Admin
+        } else {
+            Bundle::Web
+        }
+    }
+
+    /// The file served for any path that is not a file.
+    fn index(&self) -> &'static str {
+        match self {
+            Bundle::Web => "index.html",
+            Bundle::Admin => "index_admin.html",
+        }
+    }
+
+    fn get(&self, file: &str) -> Option<EmbeddedFile> {
+        match self {
+            Bundle::Web => WebAssets::get(file),
+            Bundle::Admin => AdminAssets::get(file),
+        }
+    }
+
+    fn files(&self) -> Vec<Cow<'static, str>> {
+        match self {
+            Bundle::Web => WebAssets::iter().collect(),
+            Bundle::Admin => AdminAssets::iter().collect(),
+        }
+    }
+}
+
+
+/// Finds the precompressed copies in every bundle that decompress to the file next to them.
+fn fresh_copies() -> HashSet<(Bundle, String)> {
+    let mut fresh = HashSet::new();
+    for bundle in [Bundle::Web, Bundle::Admin] {
+        for name in bundle.files() {
+            let copy = PRECOMPRESSED.iter().find_map(|(encoding, extension)| {
+                name.strip_suffix(&format!(".{}", extension)).map(|file| (*encoding, file))
+            });
+            let Some((encoding, file)) = copy else { continue };
+            let (Some(original), Some(compressed)) = (bundle.get(file), bundle.get(&name)) else { continue };
+            let matches = decompress(encoding, &compressed.data)
+                .is_some_and(|data| Sha256::digest(&data).as_slice() == original.metadata.sha256_hash());
+            if matches {
+                fresh.insert((bundle, name.to_string()));
+            } else {
+                log::warn!("not sending {} as it is not a copy of {}", name, file);
+            }
+        }
+    }
+    fresh
+}
+
+
+/// Decompresses a precompressed copy, `None` if it is not valid.
+fn decompress(encoding: ContentEncoding, data: &[u8]) -> Option<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    let read = match encoding {
+        ContentEncoding::Brotli => brotli::Decompressor::new(data, 4096).read_to_end(&mut decompressed),
+        _ => flate2::read::GzDecoder::new(data).read_to_end(&mut decompressed)
+    };
+    read.ok().map(|_| decompressed)
+}
+
+
+/// What is sent for a request for a file.
+#[derive(Debug, PartialEq)]
+struct Representation {
+    /// The file sent, the file itself or a precompressed copy of it.
+    name: String,
+    /// The encoding of the copy, if a copy is sent.
+    encoding: Option<ContentEncoding>,
+    /// Whether the file has copies, so what is sent depends on `Accept-Encoding`.
+    varies: bool,
+}
+
+
+/// Picks the file or the copy of it the request's `Accept-Encoding` prefers.
+fn representation(req: &HttpRequest, bundle: Bundle, file: &str) -> Representation {
+    let copies: Vec<(ContentEncoding, String)> = PRECOMPRESSED.iter()
+        .map(|(encoding, extension)| (*encoding, format!("{}.{}", file, extension)))
+        .filter(|(_, copy)| FRESH_COPIES.contains(&(bundle, copy.clone())))
+        .collect();
+    let varies = !copies.is_empty();
+    let supported: Vec<Encoding> = copies.iter()
+        .map(|(encoding, _)| Encoding::Known(*encoding))
+        .chain([Encoding::identity()])
+        .collect();
+    let preferred = match varies {
+        true => req.get_header::<AcceptEncoding>().and_then(|accept| accept.negotiate(supported.iter())),
+        false => None
+    };
+    match copies.into_iter().find(|(encoding, _)| preferred == Some(Encoding::Known(*encoding))) {
+        Some((encoding, name)) => Representation { name, encoding: Some(encoding), varies },
+        None => Representation { name: file.to_string(), encoding: None, varies }
+    }
+}
+
+
+/// Catches all requests that are not handled by the other routes. If the route does not have "/api/" in it, then
+/// it will check to see if the request is for static files from the frontend or admin frontend. If it is, then it will
+/// serve the file. Otherwise, it will serve the index.html file for the frontend or the index_admin.html file for the
+/// admin frontend.
+///
+/// # Arguments
+/// * `req` - The request that is being handled.
+///
+/// # Returns
+/// bytes of a file
+pub async fn catch_all(req: HttpRequest) -> impl Responder {
+    if req.path().contains("/api/") {
+        return HttpResponse::NotFound().finish()
+    }
+    let bundle = Bundle::for_path(req.path());
+    let file_type = mime_guess::from_path(req.path()).first_raw().unwrap_or("text/html");
+    if req.path().contains("frontend/public") || !file_type.contains("text/html") {
+        return match Path::new(req.path()).file_name().and_then(|file| file.to_str()) {
+            Some(file) => serve_file(&req, bundle, file),
+            None => HttpResponse::BadRequest().body("404 Not Found")
+        }
+    }
+    serve_file(&req, bundle, bundle.index())
+}
+
+
+/// Serves a file, or the requested range of it, from a bundle, or `304 Not Modified` if the request already has it.
+///
+/// # Arguments
+/// * `req` - The request, checked for `If-None-Match`, `Range` and `If-Range` headers.
+/// * `bundle` - The bundle the file is in.
+/// * `file` - The name of the file.
+///
+/// # Returns
+/// a http response with the bytes of the file
+fn serve_file(req: &HttpRequest, bundle: Bundle, file: &str) -> HttpResponse {
+    let representation = representation(req, bundle, file);
+    let content = match bundle.get(&representation.name) {
+        Some(content) => content,
+        None => return HttpResponse::NotFound().body("404 Not Found")
+    };
+    let etag = EntityTag::new_strong(hex_digest(&content.metadata.sha256_hash()));
+    let cache_control = if file == bundle.index() {
+        CacheControl(vec![CacheDirective::NoCache])
+    } else {
+        CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(ASSET_MAX_AGE_SECONDS)])
+    };
+    let not_modified = match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        None => false
+    };
+    if not_modified {
+        let mut response = HttpResponse::NotModified();
+        if representation.varies {
+            response.insert_header((VARY, "Accept-Encoding"));
+        }
+        return response
+            .insert_header(ETag(etag))
+            .insert_header(cache_control)
+            .finish()
+    }
+    let body = match content.data {
+        Cow::Borrowed(data) => Bytes::from_static(data),
+        Cow::Owned(data) => Bytes::from(data)
+    };
+    let full_length = body.len() as u64;
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(mime_guess::from_path(file).first_or_octet_stream().as_ref())
+        .insert_header((ACCEPT_RANGES, "bytes"))
+        .insert_header(ETag(etag.clone()))
+        .insert_header(cache_control);
+    if representation.varies {
+        response.insert_header((VARY, "Accept-Encoding"));
+    }
+    if let Some(encoding) = representation.encoding {
+        response.insert_header((CONTENT_ENCODING, encoding.as_str()));
+    }
+    match requested_range(req, &etag, full_length) {
+        RequestedRange::Full => response.body(body),
+        RequestedRange::Partial(start, end) => response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .insert_header(ContentRange(ContentRangeSpec::Bytes { range: Some((start, end)), instance_length: Some(full_length) }))
+            .body(body.slice(start as usize..=end as usize)),
+        RequestedRange::Unsatisfiable => response
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .insert_header(ContentRange(ContentRangeSpec::Bytes { range: None, instance_length: Some(full_length) }))
+            .finish()
+    }
+}
+
+
+/// The part of a file a request is answered with.
+#[derive(Debug, PartialEq)]
+enum RequestedRange {
+    Full,
+    /// The first and last byte, inclusive.
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+
+/// Works out the part of a file to send from the request's `Range` and `If-Range` headers.
+fn requested_range(req: &HttpRequest, etag: &EntityTag, full_length: u64) -> RequestedRange {
+    let specs = match req.get_header::<Range>() {
+        Some(Range::Bytes(specs)) => specs,
+        _ => return RequestedRange::Full
+    };
+    // there is no `Last-Modified` to compare a date against, so only a matching tag keeps the range
+    match req.get_header::<IfRange>() {
+        Some(IfRange::EntityTag(tag)) if tag.strong_eq(etag) => {},
+        Some(_) => return RequestedRange::Full,
+        None => {}
+    }
+    match specs.as_slice() {
+        [spec] => match spec.to_satisfiable_range(full_length) {
+            Some((start, end)) => RequestedRange::Partial(start, end),
+            None => RequestedRange::Unsatisfiable
+        },
+        _ => RequestedRange::Full
+    }
+}
+
+
+fn hex_digest(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App};
+
+    async fn get_with(path: &str, headers: &[(&str, &str)]) -> actix_web::dev::ServiceResponse {
+        let app = actix_test::init_service(App::new().default_service(web::route().to(catch_all))).await;
+        let mut request = actix_test::TestRequest::get().uri(path);
+        for header in headers {
+            request = request.insert_header(*header);
+        }
+        actix_test::call_service(&app, request.to_request()).await
+    }
+
+    async fn get(path: &str, if_none_match: Option<&str>) -> actix_web::dev::ServiceResponse {
+        match if_none_match {
+            Some(tag) => get_with(path, &[("If-None-Match", tag)]).await,
+            None => get_with(path, &[]).await
+        }
+    }
+
+    fn header(response: &actix_web::dev::ServiceResponse, name: &str) -> String {
+        response.headers().get(name).unwrap().to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_bundle_for_path() {
+        assert_eq!(Bundle::for_path("/admin"), Bundle::Admin);
+        assert_eq!(Bundle::for_path("/admin/users/4"), Bundle::Admin);
+        assert_eq!(Bundle::for_path("/administrators"), Bundle::Web);
+        assert_eq!(Bundle::for_path("/todos"), Bundle::Web);
+    }
+
+    #[actix_web::test]
+    async fn test_indexes() {
+        let response = get("/admin/users", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, "Cache-Control"), "no-cache");
+        let body = actix_test::read_body(response).await;
+        assert!(std::str::from_utf8(&body).unwrap().contains("<base href=\"/admin/\" />"));
+
+        let response = get("/todos", None).await;
+        let body = actix_test::read_body(response).await;
+        assert!(std::str::from_utf8(&body).unwrap().contains("<base href=\"/\" />"));
+
+        assert_eq!(get("/api/v1/missing", None).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(get("/admin/missing.js", None).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_etag_revalidation() {
+        let response = get("/bundle.css", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, "Cache-Control"), format!("public, max-age={}", ASSET_MAX_AGE_SECONDS));
+        let etag = header(&response, "ETag");
+
+        let response = get("/bundle.css", Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(header(&response, "ETag"), etag);
+        assert!(actix_test::read_body(response).await.is_empty());
+
+        assert_eq!(get("/bundle.css", Some(&format!("W/{}", etag))).await.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(get("/bundle.css", Some("\"stale\"")).await.status(), StatusCode::OK);
+
+        let admin_index = get("/admin", None).await;
+        let web_index = get("/", None).await;
+        assert_ne!(header(&admin_index, "ETag"), header(&web_index, "ETag"));
+    }
+
+    #[actix_web::test]
+    async fn test_ranges() {
+        let full = actix_test::read_body(get("/bundle.css", None).await).await;
+        let etag = header(&get("/bundle.css", None).await, "ETag");
+
+        let response = get_with("/bundle.css", &[("Range", "bytes=0-9")]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&response, "Content-Range"), format!("bytes 0-9/{}", full.len()));
+        assert_eq!(actix_test::read_body(response).await, full.slice(0..10));
+
+        let response = get_with("/bundle.css", &[("Range", "bytes=-5"), ("If-Range", &etag)]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(actix_test::read_body(response).await, full.slice(full.len() - 5..));
+
+        let response = get_with("/bundle.css", &[("Range", &format!("bytes={}-", full.len()))]).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(header(&response, "Content-Range"), format!("bytes */{}", full.len()));
+
+        let response = get_with("/bundle.css", &[("Range", "bytes=0-9"), ("If-Range", "\"stale\"")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, "Accept-Ranges"), "bytes");
+        assert_eq!(actix_test::read_body(response).await, full);
+
+        let response = get_with("/bundle.css", &[("Range", "bytes=0-1,4-5")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_fresh_copies() {
+        let mut copies: Vec<&str> = FRESH_COPIES.iter().map(|(_, name)| name.as_str()).collect();
+        copies.sort();
+        assert_eq!(copies, [
+            "bundle.css.br", "bundle.css.gz", "bundle.js.br", "bundle.js.gz", "tailwind.css.br", "tailwind.css.gz"
+        ]);
+        assert_eq!(decompress(ContentEncoding::Gzip, b"not gzip"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_precompressed() {
+        let full = actix_test::read_body(get("/bundle.js", None).await).await;
+
+        let response = get_with("/bundle.js", &[("Accept-Encoding", "gzip, deflate, br")]).await;
+        assert_eq!(header(&response, "Content-Encoding"), "br");
+        assert_eq!(header(&response, "Vary"), "Accept-Encoding");
+        assert_eq!(header(&response, "Content-Type"), "text/javascript");
+        let brotli_etag = header(&response, "ETag");
+        let body = actix_test::read_body(response).await;
+        assert_eq!(decompress(ContentEncoding::Brotli, &body).unwrap(), full);
+
+        let response = get_with("/bundle.js", &[("Accept-Encoding", "br;q=0, gzip")]).await;
+        assert_eq!(header(&response, "Content-Encoding"), "gzip");
+        let gzip_etag = header(&response, "ETag");
+        let body = actix_test::read_body(response).await;
+        assert_eq!(decompress(ContentEncoding::Gzip, &body).unwrap(), full);
+
+        let response = get("/bundle.js", None).await;
+        assert!(response.headers().get("Content-Encoding").is_none());
+        assert_eq!(header(&response, "Vary"), "Accept-Encoding");
+        let identity_etag = header(&response, "ETag");
+        assert_ne!(brotli_etag, gzip_etag);
+        assert_ne!(brotli_etag, identity_etag);
+
+        let response = get_with("/bundle.js", &[("Accept-Encoding", "br"), ("If-None-Match", &brotli_etag)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(header(&response, "Vary"), "Accept-Encoding");
+        let response = get_with("/bundle.js", &[("Accept-Encoding", "gzip"), ("If-None-Match", &brotli_etag)]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = get_with("/index.html", &[("Accept-Encoding", "br")]).await;
+        assert!(response.headers().get("Content-Encoding").is_none());
+        assert!(response.headers().get("Vary").is_none());
+    }
+}
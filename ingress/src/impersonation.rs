@@ -0,0 +1,191 @@
+//! Middleware writing every request made with an impersonation token to the audit log.
+//!
+//! # Overview
+//! A token issued by `POST /api/auth/v1/auth/impersonate/{user_id}` carries the `impersonator_id`
+//! of the super admin acting as the user. Each request sent with one is recorded after it is answered
+//! with the super admin as the actor, the user as the subject and the method, path and status in the
+//! details, including requests that were refused. The token is read the same way `HeaderToken`
+//! reads it, from the `token` header or else the token cookie. Requests without a valid token, or
+//! with a token cookie failing the CSRF check, are not recorded.
+//! A failed write is logged and does not change the response.
+use std::marker::PhantomData;
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error
+};
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use kernel::audit_log::NewAuditEntry;
+use kernel::impersonation::IMPERSONATED_REQUEST_ACTION;
+use kernel::token::checks::NoRoleCheck;
+use kernel::token::cookies::token_from_request;
+use kernel::token::token::HeaderToken;
+use utils::config::GetConfigVariable;
+use utils::log_limited;
+
+
+/// Records the requests made with an impersonation token.
+///
+/// # Arguments
+/// * `_handles` - Picks the database `X` and the config `Y` the token is verified with
+/// * `req` - The incoming request
+/// * `next` - The rest of the middleware chain
+pub async fn audit_impersonated_requests<X, Y, B>(
+    _handles: PhantomData<(X, Y)>,
+    req: ServiceRequest,
+    next: Next<B>
+) -> Result<ServiceResponse<B>, Error>
+where
+    X: CreateAuditEntry,
+    Y: GetConfigVariable,
+    B: MessageBody + 'static,
+{
+    let token = token_from_request::<Y>(req.request()).ok()
+        .and_then(|value| HeaderToken::<Y, NoRoleCheck>::decode(&value).ok())
+        .filter(|token| token.impersonator_id.is_some());
+    let Some(token) = token else {
+        return next.call(req).await
+    };
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let response = next.call(req).await;
+    let status = match &response {
+        Ok(response) => response.status(),
+        Err(error) => error.as_response_error().status_code()
+    };
+    let entry = NewAuditEntry {
+        actor_id: token.impersonator_id.unwrap_or_default(),
+        action: IMPERSONATED_REQUEST_ACTION.to_string(),
+        subject_id: Some(token.user_id),
+        details: serde_json::json!({
+            "method": method,
+            "path": path,
+            "status": status.as_u16(),
+            "session": token.unique_id,
+        }),
+    };
+    if let Err(e) = X::create_audit_entry(entry).await {
+        log_limited!("ingress", "failed to audit impersonated request {} {}: {}", method, path, e.message);
+    }
+    response
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{cookie::Cookie, test as actix_test, web, App, HttpResponse, middleware::from_fn};
+    use dal_tx_impl::impl_transaction;
+    use kernel::audit_log::AuditEntry;
+    use kernel::chrono::Utc;
+    use kernel::token::checks::WorkerRoleCheck;
+    use kernel::token::cookies::{CSRF_COOKIE, CSRF_HEADER};
+    use sqlx::types::Json;
+    use std::sync::Mutex;
+    use test_support::{FakeConfig, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    static AUDITED: Mutex<Vec<NewAuditEntry>> = Mutex::new(Vec::new());
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, CreateAuditEntry, create_audit_entry)]
+    async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        AUDITED.lock().unwrap().push(entry.clone());
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: Json(entry.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_impersonated_requests_are_audited() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(|req, next| audit_impersonated_requests::<MockDbHandle, FakeConfig, _>(PhantomData, req, next)))
+                .route("/todos", web::get().to(HttpResponse::Ok))
+                .route("/todos", web::delete().to(HttpResponse::Forbidden))
+        ).await;
+
+        // the user's own token, a token that cannot be verified and no token are not recorded
+        let own = TokenBuilder::<FakeConfig, WorkerRoleCheck>::new().user_id(4)
+            .request(actix_test::TestRequest::get().uri("/todos"));
+        actix_test::call_service(&app, own.to_request()).await;
+        let forged = actix_test::TestRequest::get().uri("/todos").insert_header(("token", "not-a-token"));
+        actix_test::call_service(&app, forged.to_request()).await;
+        actix_test::call_service(&app, actix_test::TestRequest::get().uri("/todos").to_request()).await;
+        assert!(AUDITED.lock().unwrap().is_empty());
+
+        for req in [actix_test::TestRequest::get(), actix_test::TestRequest::delete()] {
+            let req = TokenBuilder::<FakeConfig, WorkerRoleCheck>::new()
+                .user_id(4)
+                .impersonated_by(1)
+                .request(req.uri("/todos?page=2"));
+            actix_test::call_service(&app, req.to_request()).await;
+        }
+
+        let audited = AUDITED.lock().unwrap();
+        assert_eq!(audited.len(), 2);
+        assert!(audited.iter().all(|entry| entry.actor_id == 1 && entry.subject_id == Some(4)));
+        assert!(audited.iter().all(|entry| entry.action == IMPERSONATED_REQUEST_ACTION));
+        assert_eq!(audited[0].details["method"], "GET");
+        assert_eq!(audited[0].details["path"], "/todos");
+        assert_eq!(audited[0].details["status"], 200);
+        assert_eq!(audited[1].details["method"], "DELETE");
+        assert_eq!(audited[1].details["status"], 403);
+    }
+
+    static COOKIE_AUDITED: Mutex<Vec<NewAuditEntry>> = Mutex::new(Vec::new());
+
+    struct CookieDbHandle;
+
+    #[impl_transaction(CookieDbHandle, CreateAuditEntry, create_audit_entry)]
+    async fn create_cookie_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        COOKIE_AUDITED.lock().unwrap().push(entry.clone());
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: Json(entry.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    test_support::fake_config!(CookieConfig, {
+        "TOKEN_COOKIE_ENABLED" => "true",
+        "TOKEN_COOKIE_NAME" => "token",
+    });
+
+    #[actix_web::test]
+    async fn test_impersonated_requests_in_cookies_are_audited() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(|req, next| audit_impersonated_requests::<CookieDbHandle, CookieConfig, _>(PhantomData, req, next)))
+                .route("/todos", web::delete().to(HttpResponse::Ok))
+        ).await;
+        let token = TokenBuilder::<CookieConfig, WorkerRoleCheck>::new().user_id(4).impersonated_by(1).encode();
+
+        // without the CSRF token the cookie does not authenticate the request
+        let req = actix_test::TestRequest::delete().uri("/todos")
+            .cookie(Cookie::new("token", token.clone()));
+        actix_test::call_service(&app, req.to_request()).await;
+        assert!(COOKIE_AUDITED.lock().unwrap().is_empty());
+
+        let req = actix_test::TestRequest::delete().uri("/todos")
+            .cookie(Cookie::new("token", token))
+            .cookie(Cookie::new(CSRF_COOKIE, "csrf-value"))
+            .insert_header((CSRF_HEADER, "csrf-value"));
+        actix_test::call_service(&app, req.to_request()).await;
+
+        let audited = COOKIE_AUDITED.lock().unwrap();
+        assert_eq!(audited.len(), 1);
+        assert_eq!((audited[0].actor_id, audited[0].subject_id), (1, Some(4)));
+        assert_eq!(audited[0].details["method"], "DELETE");
+    }
+}
@@ -1,95 +1,231 @@
 //! This server is responsible for managing the tagging of objects and the creation of records
 //! for objects in the system.
-use actix_web::{web, App, HttpServer, Responder, HttpResponse, HttpRequest};
-use rust_embed::RustEmbed;
-use std::path::Path;
+mod build_info;
+mod server_config;
+mod tls;
+mod request_limits;
+mod template_check;
+mod jwks;
+mod request_metrics;
+mod request_logging;
+mod availability;
+mod metering;
+mod dal_metrics;
+mod retention;
+mod frontend;
+mod test_fire;
+mod export_jobs;
+mod backups;
+mod event_subscribers;
+mod webhooks;
+mod impersonation;
+mod avatars;
+
+use actix_web::{web, App, HttpServer};
 use actix_cors::Cors;
 use auth_networking::api::views_factory as auth_views_factory;
 use to_do_networking::api::views_factory as to_do_views_factory;
-use dal::migrations::run_migrations;
-use actix_web::middleware::Logger;
-
-
-/// Serves the HTML file for the frontend which will load the bundle.js file. 
-async fn index() -> HttpResponse {
-    HttpResponse::Ok().content_type("text/html").body(include_str!("../../frontends/web/public/index.html"))
-}
-
-
-/// Catches all requests that are not handled by the other routes. If the route does not have "/api/" in it, then
-/// it will check to see if the request is for static files from the frontend or admin frontend. If it is, then it will
-/// serve the file. Otherwise, it will serve the index.html file for the frontend or the index_admin.html file for the
-/// admin frontend.
-/// 
-/// # Arguments
-/// * `req` - The request that is being handled.
-/// 
-/// # Returns
-/// bytes of a file
-async fn catch_all(req: HttpRequest) -> impl Responder {
-    if req.path().contains("/api/") {
-        return HttpResponse::NotFound().finish()
-    }
-    if req.path().contains("frontend/public") {
-        return serve_frontend_asset(req.path().to_string())
-    }
-    let file_type = match mime_guess::from_path(&req.path()).first_raw() {
-        Some(file_type) => file_type,
-        None => "text/html"
-    };
-    if !file_type.contains("text/html") {
-        return serve_frontend_asset(req.path().to_string())
-    }
-    index().await
-}
-
-
-/// Embeds the frontend files into the binary.
-#[derive(RustEmbed)]
-#[folder = "../frontends/web/public"]
-struct FrontendAssets;
-
-
-/// Serves the frontend files from the binary.
-/// 
-/// # Arguments
-/// * `path` - The path from the request.
-/// 
-/// # Returns
-/// a http response with the bytes of the file
-fn serve_frontend_asset(path: String) -> HttpResponse {
-    let file = match Path::new(&path).file_name() {
-        Some(file) => file.to_str().unwrap(),
-        None => return HttpResponse::BadRequest().body("404 Not Found")
-    };
-    match FrontendAssets::get(file) {
-        Some(content) => HttpResponse::Ok()
-            .content_type(mime_guess::from_path(&file).first_or_octet_stream().as_ref())
-            .append_header(("Cache-Control", "public, max-age=604800"))
-            .body(content.data),
-        None => HttpResponse::NotFound().body("404 Not Found")
-    }
-}
+use dal::migrations::{ensure_migrations_applied, run_migrations};
+use dal::schema_compat::check_schema_compatibility;
+use actix_web::middleware::{Compress, Condition, Logger, DefaultHeaders, from_fn};
+use request_limits::{json_config, limit_header_size, limit_requests_per_ip, payload_config, InFlightByIp};
+use build_info::{version, build_info_header_value, BUILD_INFO_HEADER};
+use server_config::ServerConfig;
+use tls::load_rustls_config;
+use jwks::jwks_endpoint;
+use request_logging::{log_requests, RequestLogConfig};
+use request_metrics::{record_request_metrics, spawn_request_metrics_flush, RequestMetrics};
+use availability::{get_slo_report, spawn_availability_rollup};
+use metering::{get_usage_export, get_usage_export_with_api_key, spawn_usage_rollup};
+use dal_metrics::{configure_slow_transaction_threshold, get_dal_metrics};
+use frontend::catch_all;
+use test_fire::{test_fire_email, test_fire_webhook};
+use retention::{get_retention_metrics, spawn_retention_purge, RetentionMetrics};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+use kernel::token::sliding::REFRESHED_TOKEN_HEADER;
+use utils::config::EnvConfig;
+use utils::i18n::localize_errors;
+use utils::log_sampling::{LogLimits, set_log_limits, spawn_suppressed_log_report};
+use utils::secrets::{SecretsBackend, SecretsConfig, load_secrets, spawn_secrets_refresh};
+use actix_web::http::KeepAlive;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use email_core::mailchimp_traits::mc_definitions::MailchimpDescriptor;
+use template_check::spawn_template_check;
+use export_jobs::{create_export, get_export, spawn_export_worker};
+use backups::{create_backup_now, create_backup_with_api_key, list_backups, spawn_backup_schedule};
+use event_subscribers::register_event_subscribers;
+use webhooks::{create_webhook, delete_webhook, list_webhook_deliveries, list_webhooks, spawn_webhook_worker, update_webhook};
+use kernel::webhooks::engine_http::HttpWebhookSender;
+use impersonation::audit_impersonated_requests;
+use avatars::get_avatar;
+use kernel::object_store::engine_configured::ConfiguredObjectStore;
+use kernel::object_store::engine_s3::S3ObjectStore;
+use email_core::outbox::worker::spawn_outbox_worker;
+use email_core::outbox::descriptor::EmailOutbox;
+use to_do_core::api::sla::monitor::spawn_sla_monitor;
+use to_do_core::api::notifications::batching::spawn_notification_batcher;
+use dal::connections::sqlx_postgres::{SqlxPostGresDescriptor, close_pool, init_pool};
 
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
 
     // init_logger();
-    run_migrations().await;
+    // the secrets are loaded first as the database pool reads `DB_URL` through `SecretsConfig`
+    let secrets_backend = SecretsBackend::from_config::<EnvConfig>().unwrap();
+    let secret_count = load_secrets(&secrets_backend).await.unwrap();
+    println!("loaded {} secrets from the {} secrets backend", secret_count, secrets_backend.name());
+    spawn_secrets_refresh::<EnvConfig>(secrets_backend);
+    let pool_config = init_pool::<SecretsConfig>().await.unwrap_or_else(|e| panic!("{}", e.message));
+    println!("connected to the database with {:?}", pool_config);
+
+    // in compatibility mode another build may still be serving from the same database, so the
+    // schema is checked against this build instead of being migrated, and with `AUTO_MIGRATE=false`
+    // the migrations are run by the deploy so the server only checks they were
+    match (std::env::var("SCHEMA_COMPATIBILITY_MODE").as_deref(), std::env::var("AUTO_MIGRATE").as_deref()) {
+        (Ok("true"), _) => check_schema_compatibility().await.unwrap(),
+        (_, Ok("false")) => ensure_migrations_applied().await.unwrap(),
+        _ => run_migrations().await.unwrap()
+    }
 
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    let server_config = ServerConfig::from_config::<SecretsConfig>().unwrap();
+    log::info!("starting server with {:?}", server_config);
+    log::info!("logging transactions slower than {}ms", configure_slow_transaction_threshold::<SecretsConfig>());
+    let log_limits = LogLimits::from_config::<SecretsConfig>().unwrap();
+    log::info!("collapsing repeated log lines with {:?}", log_limits);
+    set_log_limits(log_limits);
+    spawn_suppressed_log_report();
+    register_event_subscribers::<SqlxPostGresDescriptor>();
+    spawn_template_check::<MailchimpDescriptor, SecretsConfig>();
+    spawn_outbox_worker::<SqlxPostGresDescriptor, MailchimpDescriptor, SecretsConfig>();
+    spawn_sla_monitor::<EmailOutbox<SqlxPostGresDescriptor>, SqlxPostGresDescriptor, SecretsConfig>();
+    spawn_notification_batcher::<EmailOutbox<SqlxPostGresDescriptor>, SqlxPostGresDescriptor, SecretsConfig>();
+    spawn_availability_rollup::<SqlxPostGresDescriptor, SecretsConfig>();
+    spawn_usage_rollup::<SqlxPostGresDescriptor, SecretsConfig>();
+    // shared by every worker and flushed as one set of counts
+    let request_metrics = RequestMetrics::default();
+    spawn_request_metrics_flush::<SqlxPostGresDescriptor, SecretsConfig>(request_metrics.clone());
+    let retention_metrics = RetentionMetrics::default();
+    spawn_retention_purge::<SqlxPostGresDescriptor, SecretsConfig>(retention_metrics.clone());
+    spawn_export_worker::<SqlxPostGresDescriptor, S3ObjectStore, SecretsConfig>();
+    spawn_backup_schedule::<SqlxPostGresDescriptor, S3ObjectStore, SecretsConfig>();
+    spawn_webhook_worker::<SqlxPostGresDescriptor, HttpWebhookSender, SecretsConfig>();
 
-    HttpServer::new(|| {
-        let cors = Cors::default().allow_any_origin().allow_any_method().allow_any_header();
+    let max_header_bytes = server_config.max_header_bytes;
+    let max_requests_per_ip = server_config.max_requests_per_ip;
+    let max_json_bytes = server_config.max_json_bytes;
+    let max_payload_bytes = server_config.max_payload_bytes;
+    let compression = server_config.compression;
+    // shared by every worker so the cap applies to the whole server
+    let in_flight_by_ip = InFlightByIp::default();
+    let request_log_config = Arc::new(RequestLogConfig::from_config::<SecretsConfig>());
+    log::info!("logging requests with {:?}", request_log_config);
+
+    let bind_address = (server_config.host.clone(), server_config.port);
+    let server = HttpServer::new(move || {
+        // browsers only let the frontend read the re-issued token if the header is exposed
+        let cors = Cors::default().allow_any_origin().allow_any_method().allow_any_header()
+            .expose_headers([REFRESHED_TOKEN_HEADER]);
         App::new()
+            .app_data(json_config(max_json_bytes))
+            .app_data(payload_config(max_payload_bytes))
+            .app_data(web::Data::new(retention_metrics.clone()))
+            .route("/version", web::get().to(version))
+            .route("/.well-known/jwks.json", web::get().to(jwks_endpoint::<SecretsConfig>))
+            .route("/api/ops/v1/slo", web::get().to(
+                get_slo_report::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/ops/v1/slo.
+            )
+            .route("/api/ops/v1/usage", web::get().to(
+                get_usage_export::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/ops/v1/usage?format=csv&months=12.
+            )
+            .route("/api/ops/v1/dal", web::get().to(
+                get_dal_metrics::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/ops/v1/dal.
+            )
+            .route("/api/ops/v1/retention", web::get().to(
+                get_retention_metrics::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/ops/v1/retention.
+            )
+            .route("/api/ops/v1/test-fire/email", web::post().to(
+                test_fire_email::<MailchimpDescriptor, SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/ops/v1/test-fire/email.
+            )
+            .route("/api/ops/v1/test-fire/webhooks/{id}", web::post().to(
+                test_fire_webhook::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>, HttpWebhookSender>) // POST /api/ops/v1/test-fire/webhooks/{id}.
+            )
+            .route("/api/ops/v1/exports", web::post().to(
+                create_export::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/ops/v1/exports.
+            )
+            .route("/api/ops/v1/exports/{id}", web::get().to(
+                get_export::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/ops/v1/exports/{id}.
+            )
+            .route("/api/ops/v1/backups", web::post().to(
+                create_backup_now::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/ops/v1/backups.
+            )
+            .route("/api/ops/v1/backups", web::get().to(
+                list_backups::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/ops/v1/backups.
+            )
+            .route("/api/ops/v1/webhooks", web::post().to(
+                create_webhook::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/ops/v1/webhooks.
+            )
+            .route("/api/ops/v1/webhooks", web::get().to(
+                list_webhooks::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/ops/v1/webhooks.
+            )
+            .route("/api/ops/v1/webhooks/{id}", web::patch().to(
+                update_webhook::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // PATCH /api/ops/v1/webhooks/{id}.
+            )
+            .route("/api/ops/v1/webhooks/{id}", web::delete().to(
+                delete_webhook::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // DELETE /api/ops/v1/webhooks/{id}.
+            )
+            .route("/api/ops/v1/webhooks/{id}/deliveries", web::get().to(
+                list_webhook_deliveries::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/ops/v1/webhooks/{id}/deliveries.
+            )
+            .route("/api/service/v1/usage", web::get().to(
+                get_usage_export_with_api_key::<SqlxPostGresDescriptor>) // GET /api/service/v1/usage with an API key.
+            )
+            .route("/api/service/v1/backups", web::post().to(
+                create_backup_with_api_key::<SqlxPostGresDescriptor, SecretsConfig>) // POST /api/service/v1/backups with an API key.
+            )
+            .route("/avatars/{user_id}", web::get().to(
+                get_avatar::<ConfiguredObjectStore, SecretsConfig>) // GET /avatars/{user_id}?size=64&v={version}.
+            )
             .configure(auth_views_factory)
             .configure(to_do_views_factory)
+            // inside the limits so a body is only read once the request has been let through
+            .wrap(from_fn({
+                let request_log_config = request_log_config.clone();
+                move |req, next| log_requests(request_log_config.clone(), req, next)
+            }))
+            .wrap(from_fn(|req, next| audit_impersonated_requests::<SqlxPostGresDescriptor, SecretsConfig, _>(PhantomData, req, next)))
+            .wrap(from_fn(localize_errors))
+            // a file sent precompressed already has a `Content-Encoding` so it is passed through as it is
+            .wrap(Condition::new(compression, Compress::default()))
             .wrap(cors)
+            .wrap(DefaultHeaders::new().add((BUILD_INFO_HEADER, build_info_header_value())))
+            .wrap(from_fn(move |req, next| limit_header_size(max_header_bytes, req, next)))
+            .wrap(from_fn({
+                let in_flight_by_ip = in_flight_by_ip.clone();
+                move |req, next| limit_requests_per_ip(in_flight_by_ip.clone(), max_requests_per_ip, req, next)
+            }))
+            .wrap(from_fn({
+                let request_metrics = request_metrics.clone();
+                move |req, next| record_request_metrics(request_metrics.clone(), req, next)
+            }))
             .wrap(Logger::new("%a %{User-Agent}i %r %s %D"))
             .default_service(web::route().to(catch_all))
     })
-        .bind("0.0.0.0:8001")?
-        .run()
-        .await
+        .workers(server_config.workers)
+        .worker_max_blocking_threads(server_config.max_blocking_threads)
+        .keep_alive(server_config.keep_alive.map(KeepAlive::Timeout).unwrap_or(KeepAlive::Disabled))
+        .client_request_timeout(server_config.client_request_timeout)
+        .client_disconnect_timeout(server_config.client_disconnect_timeout)
+        .max_connections(server_config.max_connections)
+        .max_connection_rate(server_config.max_connection_rate);
+    let server = match &server_config.tls {
+        Some(tls_paths) => server.bind_rustls_0_23(bind_address, load_rustls_config(tls_paths).unwrap())?,
+        None => server.bind(bind_address)?
+    };
+    let outcome = server.run().await;
+    // the server has stopped taking requests, so the connections can be closed
+    close_pool().await;
+    outcome
 }
\ No newline at end of file
@@ -0,0 +1,279 @@
+//! Rolls usage counts up into months and exports them for billing.
+//!
+//! # Overview
+//! The rollup job recomputes last month and this month from the usage counters, so a count that
+//! lands just after midnight on the first is still billed to the right month. Finance exports the
+//! monthly rollups as JSON or CSV, and a billing integration can fetch the same export with an API key.
+//!
+//! # Variables
+//! * `USAGE_ROLLUP_SECONDS` - How often the rollup job runs, defaults to 3600
+use actix_web::HttpResponse;
+use actix_web::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use dal::metering::tx_definitions::{GetUsageRollups, RollupUsage};
+use kernel::chrono::{NaiveDate, Utc};
+use kernel::metering::{UsageRollup, month_start, months_before};
+use serde::Deserialize;
+use utils::api_endpoint;
+use utils::log_limited;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::export_stream::ExportFormat;
+use utils::locale::iso_datetime;
+
+
+/// The rollup interval used when not configured.
+const DEFAULT_ROLLUP_SECONDS: i64 = 3600;
+
+/// The number of months exported when not asked for.
+const DEFAULT_EXPORT_MONTHS: u32 = 12;
+
+/// The header row of the CSV export.
+const CSV_HEADER: &str = "month,active_users,emails_sent,storage_bytes,rolled_up_at";
+
+
+/// The query parameters of the usage export.
+///
+/// # Fields
+/// * `format` - JSON or CSV, defaults to JSON
+/// * `months` - How many months to export including the current one, defaults to 12
+#[derive(Deserialize, Debug)]
+pub struct UsageExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    pub months: Option<u32>,
+}
+
+
+/// Writes the rollups as CSV with a header row.
+pub fn usage_csv(rollups: &[UsageRollup]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for rollup in rollups {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            rollup.month,
+            rollup.active_users,
+            rollup.emails_sent,
+            rollup.storage_bytes,
+            iso_datetime(rollup.rolled_up_at)
+        ));
+    }
+    csv
+}
+
+
+/// Gets the rollups for the months being exported, oldest first.
+///
+/// # Arguments
+/// * `today` - The current day
+/// * `months` - How many months to export including the current one
+pub async fn get_usage_export_core<X: GetUsageRollups>(today: NaiveDate, months: u32) -> Result<Vec<UsageRollup>, NanoServiceError> {
+    if months == 0 {
+        return Err(NanoServiceError::new(
+            "months must be at least 1".to_string(),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    }
+    X::get_usage_rollups(months_before(today, months - 1)).await
+}
+
+
+/// Exports the rollups asked for in the query in its format.
+async fn usage_export_response<X: GetUsageRollups>(query: &UsageExportQuery) -> Result<HttpResponse, NanoServiceError> {
+    let months = query.months.unwrap_or(DEFAULT_EXPORT_MONTHS);
+    let rollups = get_usage_export_core::<X>(Utc::now().date_naive(), months).await?;
+    Ok(match query.format {
+        ExportFormat::Json => HttpResponse::Ok().json(rollups),
+        ExportFormat::Csv => HttpResponse::Ok()
+            .insert_header((CONTENT_TYPE, "text/csv"))
+            .insert_header((CONTENT_DISPOSITION, "attachment; filename=\"usage.csv\""))
+            .body(usage_csv(&rollups)),
+    })
+}
+
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetUsageRollups])]
+pub async fn get_usage_export(query: actix_web::web::Query<UsageExportQuery>) {
+    usage_export_response::<X>(&query).await
+}
+
+
+/// The same export for a billing integration sending an API key.
+#[api_endpoint(auth=ApiKey, db_traits=[GetUsageRollups])]
+pub async fn get_usage_export_with_api_key(query: actix_web::web::Query<UsageExportQuery>) {
+    usage_export_response::<X>(&query).await
+}
+
+
+/// Rolls up last month and this month.
+///
+/// # Arguments
+/// * `today` - The current day
+///
+/// # Returns
+/// * `Ok(Vec<UsageRollup>)` - The months that were rolled up, oldest first
+/// * `Err(NanoServiceError)` - If a rollup failed
+pub async fn roll_up_usage<X: RollupUsage>(today: NaiveDate) -> Result<Vec<UsageRollup>, NanoServiceError> {
+    let previous = X::rollup_usage(months_before(today, 1)).await?;
+    let current = X::rollup_usage(month_start(today)).await?;
+    Ok(vec![previous, current])
+}
+
+
+/// Runs the rollup job in the background for the life of the runtime.
+///
+/// # Notes
+/// An error in a run is logged and the job carries on.
+pub fn spawn_usage_rollup<X, Y>()
+where
+    X: RollupUsage + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let seconds = Y::get_int("USAGE_ROLLUP_SECONDS").ok().filter(|value| *value > 0).unwrap_or(DEFAULT_ROLLUP_SECONDS);
+    let interval = std::time::Duration::from_secs(seconds as u64);
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = roll_up_usage::<X>(Utc::now().date_naive()).await {
+                log_limited!("ingress", "usage rollup failed: {}", e.message);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{body::MessageBody, test as actix_test, web, App};
+    use dal::api_keys::tx_definitions::UseApiKey;
+    use dal_tx_impl::impl_transaction;
+    use kernel::api_keys::{hash_api_key, ApiKey, API_KEY_HEADER};
+    use kernel::chrono::NaiveDateTime;
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::token::HeaderToken;
+    use kernel::users::UserRole;
+    use std::sync::Mutex;
+
+    static ROLLED_UP: Mutex<Vec<NaiveDate>> = Mutex::new(Vec::new());
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockDbHandle;
+
+    test_support::confirmed_users!(MockDbHandle);
+
+    fn rollup(month: &str, active_users: i64) -> UsageRollup {
+        UsageRollup {
+            month: month.parse().unwrap(),
+            active_users,
+            emails_sent: 40,
+            storage_bytes: 2048,
+            rolled_up_at: NaiveDateTime::parse_from_str("2025-04-01 00:05:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, RollupUsage, rollup_usage)]
+    async fn rollup_usage(month: NaiveDate) -> Result<UsageRollup, NanoServiceError> {
+        ROLLED_UP.lock().unwrap().push(month);
+        Ok(rollup(&month.to_string(), 3))
+    }
+
+    #[impl_transaction(MockDbHandle, GetUsageRollups, get_usage_rollups)]
+    async fn get_usage_rollups(_since: NaiveDate) -> Result<Vec<UsageRollup>, NanoServiceError> {
+        Ok(vec![rollup("2025-02-01", 3), rollup("2025-03-01", 5)])
+    }
+
+    /// Only `ak_test` is a valid key.
+    #[impl_transaction(MockDbHandle, UseApiKey, use_api_key)]
+    async fn use_api_key(key_hash: String) -> Result<Option<ApiKey>, NanoServiceError> {
+        Ok((key_hash == hash_api_key("ak_test")).then(|| ApiKey {
+            id: 1,
+            name: "billing".to_string(),
+            key_prefix: "ak_test".to_string(),
+            key_hash,
+            created_by: 1,
+            date_created: Utc::now().naive_utc(),
+            last_used_at: None,
+            revoked_at: None,
+        }))
+    }
+
+    #[test]
+    fn test_usage_csv() {
+        let csv = usage_csv(&[rollup("2025-03-01", 5)]);
+        assert_eq!(csv, format!("{}\n2025-03-01,5,40,2048,2025-04-01T00:05:00\n", CSV_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_roll_up_usage() {
+        let rollups = roll_up_usage::<MockDbHandle>("2025-01-02".parse().unwrap()).await.unwrap();
+        assert_eq!(rollups.len(), 2);
+        assert_eq!(
+            *ROLLED_UP.lock().unwrap(),
+            vec!["2024-12-01".parse::<NaiveDate>().unwrap(), "2025-01-01".parse().unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_needs_a_month() {
+        let error = get_usage_export_core::<MockDbHandle>("2025-03-15".parse().unwrap(), 0).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    async fn send_request(role: UserRole, uri: &str) -> actix_web::dev::ServiceResponse {
+        let app = actix_test::init_service(App::new().route("/usage", web::get().to(
+            get_usage_export::<MockDbHandle, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new("some-agent".to_string(), 7, role);
+        let req = actix_test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri(uri)
+            .to_request();
+        actix_test::call_service(&app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_get_usage_export_as_csv() {
+        let response = send_request(UserRole::Admin, "/usage?format=csv&months=2").await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "text/csv");
+        let body = response.into_body().try_into_bytes().unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert_eq!(body.lines().count(), 3);
+        assert!(body.starts_with(CSV_HEADER));
+    }
+
+    #[actix_web::test]
+    async fn test_get_usage_export_requires_admin() {
+        assert_eq!(send_request(UserRole::Worker, "/usage").await.status(), 401);
+    }
+
+    #[actix_web::test]
+    async fn test_get_usage_export_with_api_key() {
+        let app = actix_test::init_service(App::new().route("/usage", web::get().to(
+            get_usage_export_with_api_key::<MockDbHandle>
+        ))).await;
+        let send = |key: Option<&str>| {
+            let mut req = actix_test::TestRequest::get().uri("/usage?months=2");
+            if let Some(key) = key {
+                req = req.insert_header((API_KEY_HEADER, key));
+            }
+            actix_test::call_service(&app, req.to_request())
+        };
+        let response = send(Some("ak_test")).await;
+        assert_eq!(response.status(), 200);
+        let body: Vec<UsageRollup> = actix_test::read_body_json(response).await;
+        assert_eq!(body.len(), 2);
+        assert_eq!(send(Some("ak_revoked")).await.status(), 401);
+        assert_eq!(send(None).await.status(), 401);
+    }
+}
@@ -0,0 +1,326 @@
+//! Middleware guarding the public listener against oversized request heads and greedy clients.
+//!
+//! Actix handles slow clients itself through the request and disconnect timeouts set in `main`,
+//! answering with a 408. This adds the configurable header limit, answered with a 431, and a cap
+//! on in-flight requests per client IP, answered with a 429. Body sizes are capped through the
+//! `JsonConfig` and `PayloadConfig` extractor settings, answered with a 413.
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use actix_web::{
+    body::{BodySize, EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    error::{InternalError, JsonPayloadError},
+    web::{self, Bytes}, Error, HttpResponse
+};
+use actix_web::http::StatusCode;
+
+
+/// Counts the bytes of the request line and headers as they would appear on the wire.
+///
+/// # Arguments
+/// * `req` - The incoming request
+///
+/// # Returns
+/// * `usize` - The approximate size of the request head
+pub fn request_head_bytes(req: &ServiceRequest) -> usize {
+    let request_line = req.method().as_str().len() + req.uri().to_string().len() + 11;
+    req.headers().iter().fold(request_line, |total, (name, value)| {
+        total + name.as_str().len() + value.len() + 4
+    })
+}
+
+
+/// Rejects requests whose head is larger than `max_header_bytes` with a 431.
+///
+/// # Arguments
+/// * `max_header_bytes` - The largest request head accepted
+/// * `req` - The incoming request
+/// * `next` - The rest of the middleware chain
+pub async fn limit_header_size<B: MessageBody + 'static>(
+    max_header_bytes: usize,
+    req: ServiceRequest,
+    next: Next<B>
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    if request_head_bytes(&req) > max_header_bytes {
+        let response = HttpResponse::build(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
+            .body("Request Header Fields Too Large");
+        return Ok(req.into_response(response).map_into_right_body())
+    }
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+
+
+/// Tracks the in-flight requests for each client IP across all workers.
+///
+/// # Notes
+/// The IP is the socket peer address. Behind a load balancer every request shares the balancer's
+/// address, so the cap should be raised or disabled there and enforced at the balancer instead.
+#[derive(Clone, Default)]
+pub struct InFlightByIp {
+    counts: Arc<Mutex<HashMap<IpAddr, usize>>>
+}
+
+
+/// Releases an in-flight slot when dropped, including on error.
+pub struct InFlightGuard {
+    counts: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    ip: IpAddr
+}
+
+
+impl InFlightByIp {
+
+    /// Takes an in-flight slot for the IP if it is under the cap.
+    ///
+    /// # Arguments
+    /// * `ip` - The client IP
+    /// * `max_in_flight` - The most requests the IP may have in flight
+    ///
+    /// # Returns
+    /// * `Option<InFlightGuard>` - The slot, or `None` if the IP is at the cap
+    pub fn try_acquire(&self, ip: IpAddr, max_in_flight: usize) -> Option<InFlightGuard> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(ip).or_insert(0);
+        if *count >= max_in_flight {
+            return None
+        }
+        *count += 1;
+        Some(InFlightGuard { counts: self.counts.clone(), ip })
+    }
+}
+
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock().unwrap();
+        if let Some(count) = counts.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&self.ip);
+            }
+        }
+    }
+}
+
+
+/// A response body holding the in-flight slot of its request until the body has been sent or the
+/// client has gone, so a streamed export counts against the cap for as long as it streams.
+pub struct InFlightBody<B> {
+    body: Pin<Box<B>>,
+    _guard: Option<InFlightGuard>
+}
+
+
+impl<B: MessageBody> MessageBody for InFlightBody<B> {
+    type Error = B::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        self.get_mut().body.as_mut().poll_next(cx)
+    }
+}
+
+
+/// Rejects a request with a 429 if its client IP already has `max_in_flight` requests running.
+///
+/// # Notes
+/// A request holds its slot until its response body has been sent, not just until the handler returns.
+///
+/// # Arguments
+/// * `limiter` - The shared in-flight counts
+/// * `max_in_flight` - The cap per IP, `0` disables the check
+/// * `req` - The incoming request
+/// * `next` - The rest of the middleware chain
+pub async fn limit_requests_per_ip<B: MessageBody + 'static>(
+    limiter: InFlightByIp,
+    max_in_flight: usize,
+    req: ServiceRequest,
+    next: Next<B>
+) -> Result<ServiceResponse<EitherBody<InFlightBody<B>>>, Error> {
+    let guard = match (max_in_flight, req.peer_addr()) {
+        (0, _) | (_, None) => None,
+        (_, Some(addr)) => match limiter.try_acquire(addr.ip(), max_in_flight) {
+            Some(guard) => Some(guard),
+            None => {
+                let response = HttpResponse::TooManyRequests().body("Too many concurrent requests");
+                return Ok(req.into_response(response).map_into_right_body())
+            }
+        }
+    };
+    let response = next.call(req).await?;
+    Ok(response
+        .map_body(|_, body| InFlightBody { body: Box::pin(body), _guard: guard })
+        .map_into_left_body())
+}
+
+
+/// Builds the JSON extractor settings, rejecting bodies over `limit` with a 413.
+///
+/// # Notes
+/// Any other JSON error is answered with a 400 carrying the parse error, in the same shape as the
+/// `NanoServiceError` responses the handlers return.
+///
+/// # Arguments
+/// * `limit` - The largest JSON body accepted in bytes
+pub fn json_config(limit: usize) -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(limit)
+        .error_handler(|err, _req| {
+            let response = match &err {
+                JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => {
+                    HttpResponse::PayloadTooLarge().json("Request body is too large")
+                },
+                _ => HttpResponse::BadRequest().json(err.to_string())
+            };
+            InternalError::from_response(err, response).into()
+        })
+}
+
+
+/// Builds the raw body extractor settings used by `Bytes` and `String` handlers.
+///
+/// # Arguments
+/// * `limit` - The largest raw body accepted in bytes
+pub fn payload_config(limit: usize) -> web::PayloadConfig {
+    web::PayloadConfig::new(limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App, middleware::from_fn};
+
+    fn in_flight(limiter: &InFlightByIp, ip: &IpAddr) -> usize {
+        limiter.counts.lock().unwrap().get(ip).copied().unwrap_or(0)
+    }
+
+    #[actix_web::test]
+    async fn test_limit_header_size() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(|req, next| limit_header_size(256, req, next)))
+                .route("/", web::get().to(HttpResponse::Ok))
+        ).await;
+
+        let req = actix_test::TestRequest::get().uri("/").insert_header(("x-small", "a")).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = actix_test::TestRequest::get().uri("/").insert_header(("x-large", "a".repeat(512))).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn test_json_config_limits_body() {
+        async fn echo(body: web::Json<serde_json::Value>) -> HttpResponse {
+            HttpResponse::Ok().json(body.into_inner())
+        }
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(json_config(64))
+                .route("/", web::post().to(echo))
+        ).await;
+
+        let req = actix_test::TestRequest::post().uri("/").set_json(serde_json::json!({"a": 1})).to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = actix_test::TestRequest::post().uri("/")
+            .set_json(serde_json::json!({"a": "b".repeat(128)})).to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = actix_test::TestRequest::post().uri("/")
+            .insert_header(("content-type", "application/json")).set_payload("{not json").to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_payload_config_limits_body() {
+        async fn length(body: web::Bytes) -> HttpResponse {
+            HttpResponse::Ok().body(body.len().to_string())
+        }
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(payload_config(64))
+                .route("/", web::post().to(length))
+        ).await;
+
+        let req = actix_test::TestRequest::post().uri("/").set_payload("a".repeat(32)).to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = actix_test::TestRequest::post().uri("/").set_payload("a".repeat(128)).to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_in_flight_guard_releases() {
+        let limiter = InFlightByIp::default();
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+
+        let first = limiter.try_acquire(ip, 2).unwrap();
+        let second = limiter.try_acquire(ip, 2).unwrap();
+        assert!(limiter.try_acquire(ip, 2).is_none());
+        assert_eq!(in_flight(&limiter, &ip), 2);
+
+        drop(first);
+        assert_eq!(in_flight(&limiter, &ip), 1);
+        drop(second);
+        assert_eq!(in_flight(&limiter, &ip), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_limit_requests_per_ip() {
+        let limiter = InFlightByIp::default();
+        let middleware_limiter = limiter.clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| limit_requests_per_ip(middleware_limiter.clone(), 1, req, next)))
+                .route("/", web::get().to(HttpResponse::Ok))
+        ).await;
+        let addr: std::net::SocketAddr = "10.0.0.2:4000".parse().unwrap();
+
+        let req = actix_test::TestRequest::get().uri("/").peer_addr(addr).to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        // hold the only slot as if another request from the same IP were still running
+        let _held = limiter.try_acquire(addr.ip(), 1).unwrap();
+        let req = actix_test::TestRequest::get().uri("/").peer_addr(addr).to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let other: std::net::SocketAddr = "10.0.0.3:4000".parse().unwrap();
+        let req = actix_test::TestRequest::get().uri("/").peer_addr(other).to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_limit_requests_per_ip_holds_slot_until_body_is_sent() {
+        let limiter = InFlightByIp::default();
+        let middleware_limiter = limiter.clone();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| limit_requests_per_ip(middleware_limiter.clone(), 1, req, next)))
+                .route("/export", web::get().to(|| async { HttpResponse::Ok().body("a".repeat(1024)) }))
+        ).await;
+        let addr: std::net::SocketAddr = "10.0.0.4:4000".parse().unwrap();
+
+        // the handler has returned but the body has not been sent yet
+        let req = actix_test::TestRequest::get().uri("/export").peer_addr(addr).to_request();
+        let streaming = actix_test::call_service(&app, req).await;
+        assert_eq!(streaming.status(), StatusCode::OK);
+        assert_eq!(in_flight(&limiter, &addr.ip()), 1);
+
+        let req = actix_test::TestRequest::get().uri("/export").peer_addr(addr).to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        assert_eq!(actix_test::read_body(streaming).await.len(), 1024);
+        assert_eq!(in_flight(&limiter, &addr.ip()), 0);
+    }
+}
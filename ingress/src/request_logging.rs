@@ -0,0 +1,315 @@
+//! Middleware logging a line for each request with its body, for debugging production incidents.
+//!
+//! # Overview
+//! Each logged line holds the method, path, status, latency and the JSON body the request was sent
+//! with. The body is redacted before it is logged: the values of keys naming a password, token,
+//! secret, email, user uuid or similar are masked, as is any string that looks like an email
+//! address, and the result is cut to `REQUEST_LOG_BODY_BYTES`. Bodies that are not JSON, such as
+//! uploads, are logged by their size only. The query string is left out as it can carry tokens.
+//!
+//! A share of requests is logged as set by `REQUEST_LOG_SAMPLE_PERCENT`, requests answered with a
+//! server error are always logged. Paths starting with one of `REQUEST_LOG_EXCLUDED_PATHS` are
+//! never logged and their body is not read.
+//!
+//! # Variables
+//! * `REQUEST_LOG_ENABLED` - Whether requests are logged, defaults to `false`
+//! * `REQUEST_LOG_SAMPLE_PERCENT` - The percentage of requests logged, from `0` to `100`, defaults to `100`
+//! * `REQUEST_LOG_BODY_BYTES` - The most bytes of the redacted body logged, defaults to `1024`
+//! * `REQUEST_LOG_EXCLUDED_PATHS` - Comma separated path prefixes that are not logged, such as
+//!   `/version,/api/auth/v1/auth/login`
+use std::sync::Arc;
+use std::time::Instant;
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::{CONTENT_LENGTH, CONTENT_TYPE},
+    middleware::Next,
+    web, Error
+};
+use rand::Rng;
+use serde_json::Value;
+use utils::config::{GetConfigVariable, TypedConfig};
+
+
+/// The share of requests logged when not configured.
+const DEFAULT_SAMPLE_PERCENT: u32 = 100;
+
+/// The most bytes of a body logged when not configured.
+const DEFAULT_BODY_BYTES: usize = 1024;
+
+/// Bodies larger than this are logged by their size only so they are not held twice in memory.
+const MAX_CAPTURED_BODY_BYTES: usize = 64 * 1024;
+
+/// Replaces a redacted value.
+const REDACTED: &str = "[redacted]";
+
+/// Keys whose values are masked, matched against the lower case key.
+const SENSITIVE_KEYS: [&str; 11] = [
+    "password", "token", "secret", "email", "api_key", "authorization", "otp", "captcha", "credential",
+    "unique_id", "uuid"
+];
+
+
+/// How requests are logged, read once at startup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestLogConfig {
+    pub enabled: bool,
+    pub sample_percent: u32,
+    pub body_bytes: usize,
+    pub excluded_paths: Vec<String>,
+}
+
+
+impl RequestLogConfig {
+
+    /// Reads the request logging config, values that are not valid fall back to their defaults.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let excluded_paths = Y::get_config_variable("REQUEST_LOG_EXCLUDED_PATHS".to_string())
+            .map(|paths| paths.split(',')
+                .map(str::trim)
+                .filter(|path| path.starts_with('/'))
+                .map(str::to_string)
+                .collect())
+            .unwrap_or_default();
+        RequestLogConfig {
+            enabled: Y::get_bool("REQUEST_LOG_ENABLED").unwrap_or(false),
+            sample_percent: Y::get_int("REQUEST_LOG_SAMPLE_PERCENT").ok()
+                .filter(|percent| (0..=100).contains(percent))
+                .map(|percent| percent as u32)
+                .unwrap_or(DEFAULT_SAMPLE_PERCENT),
+            body_bytes: Y::get_int("REQUEST_LOG_BODY_BYTES").ok()
+                .filter(|bytes| *bytes >= 0)
+                .map(|bytes| bytes as usize)
+                .unwrap_or(DEFAULT_BODY_BYTES),
+            excluded_paths,
+        }
+    }
+
+    /// Whether a request to `path` is logged at all.
+    fn logs(&self, path: &str) -> bool {
+        self.enabled && !self.excluded_paths.iter().any(|excluded| path.starts_with(excluded.as_str()))
+    }
+}
+
+
+/// Masks the sensitive values in a JSON body.
+///
+/// # Arguments
+/// * `value` - The parsed body, changed in place
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                let key = key.to_lowercase();
+                if SENSITIVE_KEYS.iter().any(|sensitive| key.contains(sensitive)) {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact(field);
+                }
+            }
+        },
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(text) if looks_like_email(text) => *text = REDACTED.to_string(),
+        _ => {}
+    }
+}
+
+
+/// Whether a string has the shape of an email address.
+fn looks_like_email(text: &str) -> bool {
+    match text.split_once('@') {
+        Some((local, domain)) => !local.is_empty() && !text.contains(char::is_whitespace)
+            && domain.split_once('.').is_some_and(|(host, tld)| !host.is_empty() && !tld.is_empty()),
+        None => false
+    }
+}
+
+
+/// Renders a request body for the log.
+///
+/// # Arguments
+/// * `body` - The body the request was sent with
+/// * `max_bytes` - The most bytes of the redacted body returned
+///
+/// # Returns
+/// * `String` - The redacted JSON cut to `max_bytes`, or the size of a body that is not JSON
+pub fn redacted_body(body: &[u8], max_bytes: usize) -> String {
+    let Ok(mut value) = serde_json::from_slice::<Value>(body) else {
+        return format!("[{} bytes]", body.len())
+    };
+    redact(&mut value);
+    let mut rendered = value.to_string();
+    if rendered.len() > max_bytes {
+        let mut cut = max_bytes;
+        while !rendered.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        let total = rendered.len();
+        rendered.truncate(cut);
+        rendered.push_str(&format!("...[{} bytes]", total));
+    }
+    rendered
+}
+
+
+/// Reads a JSON body small enough to log and puts it back for the handler.
+///
+/// # Returns
+/// * `Ok(Some(web::Bytes))` - The body
+/// * `Ok(None)` - If the request has no JSON body of a known size to read
+/// * `Err(Error)` - If the body could not be read
+async fn capture_body(req: &mut ServiceRequest) -> Result<Option<web::Bytes>, Error> {
+    let is_json = req.headers().get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("json"));
+    let length = req.headers().get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if !is_json || !length.is_some_and(|length| length > 0 && length <= MAX_CAPTURED_BODY_BYTES) {
+        return Ok(None)
+    }
+    let body = req.extract::<web::Bytes>().await?;
+    req.set_payload(Payload::from(body.clone()));
+    Ok(Some(body))
+}
+
+
+/// Logs the requests picked by the config once they are answered.
+///
+/// # Arguments
+/// * `config` - How requests are logged
+/// * `req` - The incoming request
+/// * `next` - The rest of the middleware chain
+pub async fn log_requests<B: MessageBody + 'static>(
+    config: Arc<RequestLogConfig>,
+    mut req: ServiceRequest,
+    next: Next<B>
+) -> Result<ServiceResponse<B>, Error> {
+    if !config.logs(req.path()) {
+        return next.call(req).await
+    }
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let body = match capture_body(&mut req).await? {
+        Some(body) => redacted_body(&body, config.body_bytes),
+        None => "-".to_string()
+    };
+    let response = next.call(req).await;
+    let status = match &response {
+        Ok(response) => response.status(),
+        Err(error) => error.as_response_error().status_code()
+    };
+    let sampled = rand::thread_rng().gen_range(0..100) < config.sample_percent;
+    if sampled || status.is_server_error() {
+        log::info!(
+            target: "request_log",
+            "{} {} {} {}ms body={}",
+            method, path, status.as_u16(), started.elapsed().as_millis(), body
+        );
+    }
+    response
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, App, HttpResponse, middleware::from_fn};
+    use utils::errors::NanoServiceError;
+
+    struct LoggingConfig;
+
+    impl GetConfigVariable for LoggingConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            Ok(match variable.as_str() {
+                "REQUEST_LOG_ENABLED" => "true",
+                "REQUEST_LOG_SAMPLE_PERCENT" => "25",
+                "REQUEST_LOG_BODY_BYTES" => "64",
+                "REQUEST_LOG_EXCLUDED_PATHS" => "/version, /api/auth/v1/auth/login,,metrics",
+                _ => ""
+            }.to_string())
+        }
+    }
+
+    struct InvalidConfig;
+
+    impl GetConfigVariable for InvalidConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = RequestLogConfig::from_config::<LoggingConfig>();
+        assert_eq!(config, RequestLogConfig {
+            enabled: true,
+            sample_percent: 25,
+            body_bytes: 64,
+            excluded_paths: vec!["/version".to_string(), "/api/auth/v1/auth/login".to_string()],
+        });
+        assert!(config.logs("/api/v1/todos"));
+        assert!(!config.logs("/api/auth/v1/auth/login"));
+
+        let config = RequestLogConfig::from_config::<InvalidConfig>();
+        assert_eq!(config, RequestLogConfig {
+            enabled: false,
+            sample_percent: DEFAULT_SAMPLE_PERCENT,
+            body_bytes: DEFAULT_BODY_BYTES,
+            excluded_paths: Vec::new(),
+        });
+        assert!(!config.logs("/api/v1/todos"));
+    }
+
+    #[test]
+    fn test_redacted_body() {
+        let body = serde_json::json!({
+            "email": "maxwell@example.com",
+            "new_password": "hunter2",
+            "title": "write the report",
+            "invites": [{"address": "someone@example.com", "role": "Worker"}],
+            "device": {"refresh_token": "abc", "name": "laptop"}
+        });
+        let redacted: Value = serde_json::from_str(&redacted_body(body.to_string().as_bytes(), 1024)).unwrap();
+        assert_eq!(redacted, serde_json::json!({
+            "email": REDACTED,
+            "new_password": REDACTED,
+            "title": "write the report",
+            "invites": [{"address": REDACTED, "role": "Worker"}],
+            "device": {"refresh_token": REDACTED, "name": "laptop"}
+        }));
+
+        assert_eq!(redacted_body(b"not json", 1024), "[8 bytes]");
+        assert_eq!(redacted_body("{\"title\":\"café\"}".as_bytes(), 13), "{\"title\":\"caf...[17 bytes]");
+        assert_eq!(redacted_body("{\"title\":\"café\"}".as_bytes(), 14), "{\"title\":\"caf...[17 bytes]");
+        // the uuid a password is reset or an account confirmed with is as good as a password
+        let body = serde_json::json!({"unique_id": "0b5a7c1e", "new_password": "hunter2", "user_uuid": "0b5a7c1e"});
+        assert_eq!(
+            redacted_body(body.to_string().as_bytes(), 1024),
+            serde_json::json!({"unique_id": REDACTED, "new_password": REDACTED, "user_uuid": REDACTED}).to_string()
+        );
+        assert!(!looks_like_email("@example.com"));
+        assert!(!looks_like_email("meet @ noon.today"));
+    }
+
+    #[actix_web::test]
+    async fn test_body_is_passed_on() {
+        let config = Arc::new(RequestLogConfig::from_config::<LoggingConfig>());
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(move |req, next| log_requests(config.clone(), req, next)))
+                .route("/echo", web::post().to(|body: web::Bytes| async move { HttpResponse::Ok().body(body) }))
+        ).await;
+
+        for uri in ["/echo", "/api/auth/v1/auth/login"] {
+            let req = actix_test::TestRequest::post().uri(uri)
+                .set_json(serde_json::json!({"password": "hunter2"}))
+                .to_request();
+            let response = actix_test::call_service(&app, req).await;
+            if uri == "/echo" {
+                assert_eq!(actix_test::read_body(response).await, r#"{"password":"hunter2"}"#);
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Middleware counting requests per minute, flushed to the `request_metrics` table in the background.
+//!
+//! # Overview
+//! A response with a 5xx status is a server error and counts against availability, a 4xx is a
+//! client error and is counted but does not. The counts are held in memory and each flush adds them
+//! to the minute they were served in, a failed flush keeps them for the next one.
+//!
+//! # Variables
+//! * `REQUEST_METRICS_FLUSH_SECONDS` - How often the counts are written, defaults to 60
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::StatusCode,
+    middleware::Next,
+    Error
+};
+use dal::request_metrics::tx_definitions::RecordRequestMetrics;
+use kernel::chrono::{DurationRound, NaiveDateTime, TimeDelta, Utc};
+use kernel::request_metrics::RequestMetricsBucket;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::log_limited;
+
+
+/// The flush interval used when not configured.
+const DEFAULT_FLUSH_SECONDS: i64 = 60;
+
+
+/// The request counts not yet flushed, shared by every worker.
+#[derive(Clone, Default)]
+pub struct RequestMetrics {
+    buckets: Arc<Mutex<HashMap<NaiveDateTime, RequestMetricsBucket>>>
+}
+
+
+impl RequestMetrics {
+
+    /// Counts a response in the minute of `served_at`.
+    ///
+    /// # Arguments
+    /// * `status` - The status the request was answered with
+    /// * `served_at` - When the request was answered
+    pub fn record(&self, status: StatusCode, served_at: NaiveDateTime) {
+        let bucket_start = served_at.duration_trunc(TimeDelta::minutes(1)).unwrap_or(served_at);
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(bucket_start).or_insert(RequestMetricsBucket {
+            bucket_start,
+            total_requests: 0,
+            server_errors: 0,
+            client_errors: 0,
+        });
+        bucket.total_requests += 1;
+        if status.is_server_error() {
+            bucket.server_errors += 1;
+        } else if status.is_client_error() {
+            bucket.client_errors += 1;
+        }
+    }
+
+    /// Takes every bucket counted so far, oldest first.
+    fn take(&self) -> Vec<RequestMetricsBucket> {
+        let mut buckets: Vec<RequestMetricsBucket> = self.buckets.lock().unwrap().drain().map(|(_, bucket)| bucket).collect();
+        buckets.sort_by_key(|bucket| bucket.bucket_start);
+        buckets
+    }
+
+    /// Adds a bucket that failed to flush back so it is written by the next flush.
+    fn restore(&self, bucket: RequestMetricsBucket) {
+        let mut buckets = self.buckets.lock().unwrap();
+        match buckets.get_mut(&bucket.bucket_start) {
+            Some(existing) => {
+                existing.total_requests += bucket.total_requests;
+                existing.server_errors += bucket.server_errors;
+                existing.client_errors += bucket.client_errors;
+            },
+            None => {
+                buckets.insert(bucket.bucket_start, bucket);
+            }
+        }
+    }
+
+    /// Writes the counted buckets to the database.
+    ///
+    /// # Returns
+    /// * `usize` - The number of buckets written, the rest are kept for the next flush
+    pub async fn flush<X: RecordRequestMetrics>(&self) -> usize {
+        let mut written = 0;
+        for bucket in self.take() {
+            match X::record_request_metrics(bucket.clone()).await {
+                Ok(_) => written += 1,
+                Err(e) => {
+                    log_limited!("ingress", "failed to flush request metrics for {}: {}", bucket.bucket_start, e.message);
+                    self.restore(bucket);
+                }
+            }
+        }
+        written
+    }
+}
+
+
+/// Counts the response to every request, including requests that failed with an error.
+///
+/// # Arguments
+/// * `metrics` - The shared request counts
+/// * `req` - The incoming request
+/// * `next` - The rest of the middleware chain
+pub async fn record_request_metrics<B: MessageBody + 'static>(
+    metrics: RequestMetrics,
+    req: ServiceRequest,
+    next: Next<B>
+) -> Result<ServiceResponse<B>, Error> {
+    let response = next.call(req).await;
+    let status = match &response {
+        Ok(response) => response.status(),
+        Err(error) => error.as_response_error().status_code()
+    };
+    metrics.record(status, Utc::now().naive_utc());
+    response
+}
+
+
+/// Flushes the request counts in the background for the life of the runtime.
+pub fn spawn_request_metrics_flush<X, Y>(metrics: RequestMetrics)
+where
+    X: RecordRequestMetrics + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let seconds = Y::get_int("REQUEST_METRICS_FLUSH_SECONDS").ok().filter(|value| *value > 0).unwrap_or(DEFAULT_FLUSH_SECONDS);
+    let flush_interval = std::time::Duration::from_secs(seconds as u64);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(flush_interval).await;
+            metrics.flush::<X>().await;
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App, HttpResponse, middleware::from_fn};
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static FAIL_FLUSH: AtomicBool = AtomicBool::new(true);
+    static FLUSHED: Mutex<Vec<RequestMetricsBucket>> = Mutex::new(Vec::new());
+
+    struct MockDbHandle;
+
+    /// Fails until `FAIL_FLUSH` is cleared.
+    #[impl_transaction(MockDbHandle, RecordRequestMetrics, record_request_metrics)]
+    async fn record_request_metrics(bucket: RequestMetricsBucket) -> Result<bool, NanoServiceError> {
+        if FAIL_FLUSH.load(Ordering::SeqCst) {
+            return Err(NanoServiceError::new("down".to_string(), NanoServiceErrorStatus::Unknown))
+        }
+        FLUSHED.lock().unwrap().push(bucket);
+        Ok(true)
+    }
+
+    fn minute(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_responses_are_counted() {
+        let metrics = RequestMetrics::default();
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn({
+                    let metrics = metrics.clone();
+                    move |req, next| record_request_metrics(metrics.clone(), req, next)
+                }))
+                .route("/ok", web::get().to(HttpResponse::Ok))
+                .route("/missing", web::get().to(HttpResponse::NotFound))
+                .route("/broken", web::get().to(|| async {
+                    Err::<HttpResponse, NanoServiceError>(NanoServiceError::new("boom".to_string(), NanoServiceErrorStatus::Unknown))
+                }))
+        ).await;
+
+        for uri in ["/ok", "/ok", "/missing", "/broken"] {
+            let req = actix_test::TestRequest::get().uri(uri).to_request();
+            actix_test::call_service(&app, req).await;
+        }
+
+        let buckets = metrics.take();
+        let total: i64 = buckets.iter().map(|bucket| bucket.total_requests).sum();
+        let server_errors: i64 = buckets.iter().map(|bucket| bucket.server_errors).sum();
+        let client_errors: i64 = buckets.iter().map(|bucket| bucket.client_errors).sum();
+        assert_eq!((total, server_errors, client_errors), (4, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_is_kept() {
+        let metrics = RequestMetrics::default();
+        metrics.record(StatusCode::OK, minute("2025-03-01 10:00:05"));
+        metrics.record(StatusCode::BAD_GATEWAY, minute("2025-03-01 10:00:55"));
+        metrics.record(StatusCode::OK, minute("2025-03-01 10:01:00"));
+
+        assert_eq!(metrics.flush::<MockDbHandle>().await, 0);
+        metrics.record(StatusCode::OK, minute("2025-03-01 10:00:30"));
+
+        FAIL_FLUSH.store(false, Ordering::SeqCst);
+        assert_eq!(metrics.flush::<MockDbHandle>().await, 2);
+
+        let flushed = FLUSHED.lock().unwrap();
+        assert_eq!(flushed[0].bucket_start, minute("2025-03-01 10:00:00"));
+        assert_eq!((flushed[0].total_requests, flushed[0].server_errors), (3, 1));
+        assert_eq!(flushed[1].bucket_start, minute("2025-03-01 10:01:00"));
+        assert_eq!(flushed[1].total_requests, 1);
+        assert!(metrics.take().is_empty());
+    }
+}
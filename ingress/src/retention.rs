@@ -0,0 +1,305 @@
+//! Purges the rows of the tables with a retention period once they are older than it.
+//!
+//! # Overview
+//! The purge job deletes the expired rows of each table in `RetentionTable::ALL` a batch at a time,
+//! carrying on until a batch comes back short, so a large backlog is worked through without a long
+//! lock. A table that fails is logged and the job moves on to the next. What each run purged is
+//! kept in memory for admins to read from the ops endpoint.
+//!
+//! # Variables
+//! * `RETENTION_PURGE_SECONDS` - How often the purge job runs, defaults to 3600
+//! * `RETENTION_PURGE_BATCH_SIZE` - The most rows deleted per statement, defaults to 1000
+//! * `AUDIT_LOG_RETENTION_DAYS` - How long audit entries are kept, defaults to 365
+//! * `LOGIN_EVENTS_RETENTION_DAYS` - How long login events are kept, defaults to 90
+//! * `EMAIL_LOG_RETENTION_DAYS` - How long sent and failed emails are kept, defaults to 30
+//! * `NOTIFICATIONS_RETENTION_DAYS` - How long unbatched notifications are kept, defaults to 30
+//! * `WEBHOOK_DELIVERIES_RETENTION_DAYS` - How long processed webhook deliveries are remembered, defaults to 30
+//!
+//! A retention of `0` days keeps the table's rows forever.
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use actix_web::HttpResponse;
+use actix_web::web::Data;
+use dal::retention::tx_definitions::PurgeExpiredRows;
+use kernel::chrono::{Days, NaiveDateTime, Utc};
+use kernel::retention::RetentionTable;
+use serde::Serialize;
+use utils::api_endpoint;
+use utils::config::{GetConfigVariable, TypedConfig};
+
+
+/// The purge interval used when not configured.
+const DEFAULT_PURGE_SECONDS: i64 = 3600;
+
+/// The batch size used when not configured.
+const DEFAULT_BATCH_SIZE: i64 = 1000;
+
+
+/// The days each table's rows are kept, `None` for a table kept forever.
+pub type RetentionPolicy = Vec<(RetentionTable, Option<u64>)>;
+
+
+/// Reads the retention of every table, falling back to the default if it is not set or invalid.
+pub fn retention_policy<Y: GetConfigVariable>() -> RetentionPolicy {
+    RetentionTable::ALL.iter().map(|table| {
+        let days = match Y::get_int(&table.config_variable()) {
+            Ok(0) => None,
+            Ok(days) if days > 0 => Some(days as u64),
+            _ => Some(table.default_days())
+        };
+        (*table, days)
+    }).collect()
+}
+
+
+/// What the purge job has done to a table since the process started.
+///
+/// # Fields
+/// * `table` - The table.
+/// * `retention_days` - The days its rows are kept, `None` if they are kept forever.
+/// * `purged_total` - Every row purged.
+/// * `batches_total` - Every delete statement run.
+/// * `last_purged` - The rows purged by the last run.
+/// * `last_run_at` - When the last run finished.
+/// * `last_error` - Why the last run failed, `None` if it succeeded.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RetentionStats {
+    pub table: RetentionTable,
+    pub retention_days: Option<u64>,
+    pub purged_total: u64,
+    pub batches_total: u64,
+    pub last_purged: u64,
+    pub last_run_at: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+}
+
+
+/// The purge counts of every table, shared by the purge job and the ops endpoint.
+#[derive(Clone, Default)]
+pub struct RetentionMetrics {
+    tables: Arc<Mutex<BTreeMap<RetentionTable, RetentionStats>>>
+}
+
+impl RetentionMetrics {
+
+    fn record(&self, table: RetentionTable, retention_days: Option<u64>, run: &TableRun) {
+        let mut tables = self.tables.lock().unwrap();
+        let stats = tables.entry(table).or_insert(RetentionStats {
+            table,
+            retention_days,
+            purged_total: 0,
+            batches_total: 0,
+            last_purged: 0,
+            last_run_at: None,
+            last_error: None,
+        });
+        stats.retention_days = retention_days;
+        stats.purged_total += run.purged;
+        stats.batches_total += run.batches;
+        stats.last_purged = run.purged;
+        stats.last_run_at = Some(run.finished_at);
+        stats.last_error = run.error.clone();
+    }
+
+    /// The counts of every table the job has run for, in purge order.
+    pub fn snapshot(&self) -> Vec<RetentionStats> {
+        self.tables.lock().unwrap().values().cloned().collect()
+    }
+}
+
+
+struct TableRun {
+    purged: u64,
+    batches: u64,
+    finished_at: NaiveDateTime,
+    error: Option<String>,
+}
+
+
+/// Deletes a table's rows created before `before` a batch at a time.
+async fn purge_table<X: PurgeExpiredRows>(table: RetentionTable, before: NaiveDateTime, batch_size: i64) -> TableRun {
+    let mut run = TableRun { purged: 0, batches: 0, finished_at: before, error: None };
+    loop {
+        match X::purge_expired_rows(table, before, batch_size).await {
+            Ok(purged) => {
+                run.purged += purged;
+                run.batches += 1;
+                if purged < batch_size as u64 {
+                    break
+                }
+            },
+            Err(e) => {
+                run.error = Some(e.message);
+                break
+            }
+        }
+    }
+    run.finished_at = Utc::now().naive_utc();
+    run
+}
+
+
+/// Purges the expired rows of every table with a retention.
+///
+/// # Arguments
+/// * `now` - The current time, rows older than their table's retention before it are purged
+/// * `policy` - The days each table's rows are kept
+/// * `batch_size` - The most rows deleted per statement
+/// * `metrics` - The counts the run is added to
+///
+/// # Returns
+/// * `u64` - The rows purged across every table
+pub async fn purge_expired<X: PurgeExpiredRows>(
+    now: NaiveDateTime,
+    policy: &RetentionPolicy,
+    batch_size: i64,
+    metrics: &RetentionMetrics
+) -> u64 {
+    let mut purged = 0;
+    for (table, retention_days) in policy {
+        let Some(days) = retention_days else {
+            continue
+        };
+        let run = purge_table::<X>(*table, now - Days::new(*days), batch_size).await;
+        if let Some(error) = &run.error {
+            println!("retention purge of {} failed after {} rows: {}", table.as_str(), run.purged, error);
+        }
+        purged += run.purged;
+        metrics.record(*table, *retention_days, &run);
+    }
+    purged
+}
+
+
+/// Runs the purge job in the background for the life of the runtime.
+///
+/// # Arguments
+/// * `metrics` - The counts each run is added to
+pub fn spawn_retention_purge<X, Y>(metrics: RetentionMetrics)
+where
+    X: PurgeExpiredRows + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let interval = std::time::Duration::from_secs(Y::get_positive_int("RETENTION_PURGE_SECONDS").unwrap_or(DEFAULT_PURGE_SECONDS) as u64);
+    let batch_size = Y::get_positive_int("RETENTION_PURGE_BATCH_SIZE").unwrap_or(DEFAULT_BATCH_SIZE);
+    let policy = retention_policy::<Y>();
+    tokio::spawn(async move {
+        loop {
+            purge_expired::<X>(Utc::now().naive_utc(), &policy, batch_size, &metrics).await;
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+
+#[api_endpoint(token=AdminRoleCheck)]
+pub async fn get_retention_metrics(metrics: Data<RetentionMetrics>) {
+    Ok(HttpResponse::Ok().json(metrics.snapshot()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App};
+    use dal_tx_impl::impl_transaction;
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::token::HeaderToken;
+    use kernel::users::UserRole;
+    use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+    static PURGES: Mutex<Vec<(RetentionTable, NaiveDateTime, i64)>> = Mutex::new(Vec::new());
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "AUDIT_LOG_RETENTION_DAYS" => Ok("0".to_string()),
+                "LOGIN_EVENTS_RETENTION_DAYS" => Ok("14".to_string()),
+                "EMAIL_LOG_RETENTION_DAYS" => Ok("-3".to_string()),
+                "NOTIFICATIONS_RETENTION_DAYS" => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound)),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    struct MockDbHandle;
+
+    test_support::confirmed_users!(MockDbHandle);
+
+    /// Login events have 5 expired rows, email logs fail and notifications have none.
+    #[impl_transaction(MockDbHandle, PurgeExpiredRows, purge_expired_rows)]
+    async fn purge_expired_rows(table: RetentionTable, before: NaiveDateTime, limit: i64) -> Result<u64, NanoServiceError> {
+        let mut purges = PURGES.lock().unwrap();
+        purges.push((table, before, limit));
+        match table {
+            RetentionTable::LoginEvents => {
+                let purged_so_far = purges.iter().filter(|(purged, _, _)| *purged == table).count() as i64 - 1;
+                Ok((5 - purged_so_far * limit).clamp(0, limit) as u64)
+            },
+            RetentionTable::EmailLog => Err(NanoServiceError::new("connection lost".to_string(), NanoServiceErrorStatus::Unknown)),
+            _ => Ok(0)
+        }
+    }
+
+    fn at(datetime: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_retention_policy() {
+        assert_eq!(retention_policy::<MockConfig>(), vec![
+            (RetentionTable::AuditLog, None),
+            (RetentionTable::LoginEvents, Some(14)),
+            (RetentionTable::EmailLog, Some(30)),
+            (RetentionTable::Notifications, Some(30)),
+            (RetentionTable::WebhookDeliveries, Some(30)),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired() {
+        let metrics = RetentionMetrics::default();
+        let purged = purge_expired::<MockDbHandle>(at("2025-03-29 12:00:00"), &retention_policy::<MockConfig>(), 2, &metrics).await;
+        assert_eq!(purged, 5);
+
+        // the audit log is kept forever, login events take batches of 2, 2 and a short 1
+        assert_eq!(*PURGES.lock().unwrap(), vec![
+            (RetentionTable::LoginEvents, at("2025-03-15 12:00:00"), 2),
+            (RetentionTable::LoginEvents, at("2025-03-15 12:00:00"), 2),
+            (RetentionTable::LoginEvents, at("2025-03-15 12:00:00"), 2),
+            (RetentionTable::EmailLog, at("2025-02-27 12:00:00"), 2),
+            (RetentionTable::Notifications, at("2025-02-27 12:00:00"), 2),
+            (RetentionTable::WebhookDeliveries, at("2025-02-27 12:00:00"), 2),
+        ]);
+
+        let stats = metrics.snapshot();
+        assert_eq!(stats.len(), 4);
+        assert_eq!((stats[0].table, stats[0].purged_total, stats[0].batches_total), (RetentionTable::LoginEvents, 5, 3));
+        assert_eq!(stats[0].retention_days, Some(14));
+        assert_eq!(stats[1].last_error, Some("connection lost".to_string()));
+        assert_eq!((stats[2].table, stats[2].last_purged, stats[2].batches_total), (RetentionTable::Notifications, 0, 1));
+    }
+
+    async fn send_request(role: UserRole) -> actix_web::dev::ServiceResponse {
+        let app = actix_test::init_service(App::new()
+            .app_data(Data::new(RetentionMetrics::default()))
+            .route("/retention", web::get().to(get_retention_metrics::<MockDbHandle, MockConfig, PassAuthSessionCheckMock>))
+        ).await;
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new("some-agent".to_string(), 7, role);
+        let req = actix_test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri("/retention")
+            .to_request();
+        actix_test::call_service(&app, req).await
+    }
+
+    #[actix_web::test]
+    async fn test_get_retention_metrics() {
+        assert_eq!(send_request(UserRole::Admin).await.status(), 200);
+        assert_eq!(send_request(UserRole::Worker).await.status(), 401);
+    }
+}
@@ -0,0 +1,336 @@
+//! Tuning for the HTTP server and its runtime, read from config with container-aware defaults.
+//!
+//! # Variables
+//! * `INGRESS_HOST` - The address the server listens on, defaults to `0.0.0.0`
+//! * `INGRESS_PORT` - The port the server listens on, defaults to `8001`
+//! * `INGRESS_TLS_CERT_PATH` - The PEM certificate chain to serve over TLS, set together with `INGRESS_TLS_KEY_PATH`
+//! * `INGRESS_TLS_KEY_PATH` - The PEM private key for the certificate, without both paths the server speaks plain HTTP
+//! * `INGRESS_WORKERS` - The number of Actix worker threads
+//! * `INGRESS_MAX_BLOCKING_THREADS` - The size of the blocking thread pool for each worker
+//! * `INGRESS_KEEP_ALIVE_SECONDS` - How long idle keep-alive connections are held open, `0` disables keep-alive
+//! * `INGRESS_CLIENT_REQUEST_TIMEOUT_MS` - How long a client has to send the request head before a 408
+//! * `INGRESS_CLIENT_DISCONNECT_TIMEOUT_MS` - How long a client has to acknowledge a connection shutdown
+//! * `INGRESS_MAX_CONNECTIONS` - The maximum open connections per worker
+//! * `INGRESS_MAX_CONNECTION_RATE` - The maximum concurrent TLS handshakes per worker
+//! * `INGRESS_MAX_HEADER_BYTES` - The maximum size of the request line and headers before a 431
+//! * `INGRESS_MAX_REQUESTS_PER_IP` - The maximum in-flight requests per client IP before a 429, `0` disables the cap
+//! * `INGRESS_MAX_JSON_BYTES` - The largest JSON body accepted before a 413
+//! * `INGRESS_MAX_PAYLOAD_BYTES` - The largest raw body accepted before a 413, for uploads and webhooks
+//! * `INGRESS_COMPRESSION` - Whether responses are compressed for clients that accept it, defaults to `true`,
+//!   frontend files built with a precompressed copy are sent compressed either way
+use std::thread::available_parallelism;
+use std::time::Duration;
+use utils::config::{parse_bool, GetConfigVariable};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The address listened on when not configured.
+const DEFAULT_HOST: &str = "0.0.0.0";
+
+/// The port listened on when not configured.
+const DEFAULT_PORT: u64 = 8001;
+
+/// The total blocking threads shared out between the workers when not configured.
+const DEFAULT_TOTAL_BLOCKING_THREADS: usize = 512;
+
+/// The keep-alive used when not configured.
+const DEFAULT_KEEP_ALIVE_SECONDS: u64 = 5;
+
+/// The time allowed to send the request head when not configured.
+const DEFAULT_CLIENT_REQUEST_TIMEOUT_MS: u64 = 5000;
+
+/// The time allowed to acknowledge a shutdown when not configured.
+const DEFAULT_CLIENT_DISCONNECT_TIMEOUT_MS: u64 = 1000;
+
+/// The open connections per worker when not configured.
+const DEFAULT_MAX_CONNECTIONS: u64 = 10_000;
+
+/// The concurrent TLS handshakes per worker when not configured.
+const DEFAULT_MAX_CONNECTION_RATE: u64 = 256;
+
+/// The request line and header size limit when not configured.
+const DEFAULT_MAX_HEADER_BYTES: u64 = 16 * 1024;
+
+/// The in-flight requests allowed per client IP when not configured.
+const DEFAULT_MAX_REQUESTS_PER_IP: u64 = 32;
+
+/// The JSON body size limit when not configured.
+const DEFAULT_MAX_JSON_BYTES: u64 = 256 * 1024;
+
+/// The raw body size limit when not configured.
+const DEFAULT_MAX_PAYLOAD_BYTES: u64 = 1024 * 1024;
+
+
+/// The files the TLS certificate and key are read from.
+#[derive(Debug, PartialEq)]
+pub struct TlsPaths {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+
+/// The resolved server tuning.
+#[derive(Debug, PartialEq)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: Option<TlsPaths>,
+    pub workers: usize,
+    pub max_blocking_threads: usize,
+    pub keep_alive: Option<Duration>,
+    pub client_request_timeout: Duration,
+    pub client_disconnect_timeout: Duration,
+    pub max_connections: usize,
+    pub max_connection_rate: usize,
+    pub max_header_bytes: usize,
+    pub max_requests_per_ip: usize,
+    pub max_json_bytes: usize,
+    pub max_payload_bytes: usize,
+    pub compression: bool,
+}
+
+
+impl ServerConfig {
+
+    /// Reads the server tuning from config.
+    ///
+    /// # Notes
+    /// The default worker count comes from `available_parallelism` which respects cgroup CPU quotas,
+    /// so a container limited to 2 CPUs gets 2 workers rather than one per host core.
+    ///
+    /// # Returns
+    /// * `Ok(ServerConfig)` - The config with defaults applied for anything not set
+    /// * `Err(NanoServiceError)` - If a variable is set but is not valid, or only one TLS path is set
+    pub fn from_config<X: GetConfigVariable>() -> Result<Self, NanoServiceError> {
+        let workers = match read_number::<X>("INGRESS_WORKERS")? {
+            Some(workers) => workers.max(1) as usize,
+            None => available_parallelism().map(|cpus| cpus.get()).unwrap_or(1)
+        };
+        let max_blocking_threads = match read_number::<X>("INGRESS_MAX_BLOCKING_THREADS")? {
+            Some(threads) => threads.max(1) as usize,
+            None => (DEFAULT_TOTAL_BLOCKING_THREADS / workers).max(1)
+        };
+        let keep_alive = match read_number::<X>("INGRESS_KEEP_ALIVE_SECONDS")?.unwrap_or(DEFAULT_KEEP_ALIVE_SECONDS) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds))
+        };
+        let client_request_timeout = Duration::from_millis(
+            read_number::<X>("INGRESS_CLIENT_REQUEST_TIMEOUT_MS")?.unwrap_or(DEFAULT_CLIENT_REQUEST_TIMEOUT_MS)
+        );
+        let client_disconnect_timeout = Duration::from_millis(
+            read_number::<X>("INGRESS_CLIENT_DISCONNECT_TIMEOUT_MS")?.unwrap_or(DEFAULT_CLIENT_DISCONNECT_TIMEOUT_MS)
+        );
+        let max_connections = read_number::<X>("INGRESS_MAX_CONNECTIONS")?
+            .unwrap_or(DEFAULT_MAX_CONNECTIONS).max(1) as usize;
+        let max_connection_rate = read_number::<X>("INGRESS_MAX_CONNECTION_RATE")?
+            .unwrap_or(DEFAULT_MAX_CONNECTION_RATE).max(1) as usize;
+        let max_header_bytes = read_number::<X>("INGRESS_MAX_HEADER_BYTES")?
+            .unwrap_or(DEFAULT_MAX_HEADER_BYTES) as usize;
+        let max_requests_per_ip = read_number::<X>("INGRESS_MAX_REQUESTS_PER_IP")?
+            .unwrap_or(DEFAULT_MAX_REQUESTS_PER_IP) as usize;
+        let max_json_bytes = read_number::<X>("INGRESS_MAX_JSON_BYTES")?
+            .unwrap_or(DEFAULT_MAX_JSON_BYTES) as usize;
+        let max_payload_bytes = read_number::<X>("INGRESS_MAX_PAYLOAD_BYTES")?
+            .unwrap_or(DEFAULT_MAX_PAYLOAD_BYTES) as usize;
+        let compression = read_flag::<X>("INGRESS_COMPRESSION")?.unwrap_or(true);
+        let host = read_text::<X>("INGRESS_HOST").unwrap_or_else(|| DEFAULT_HOST.to_string());
+        let port = read_number::<X>("INGRESS_PORT")?.unwrap_or(DEFAULT_PORT);
+        let port = u16::try_from(port).map_err(|_| NanoServiceError::new(
+            format!("INGRESS_PORT must be a port number, got '{}'", port),
+            NanoServiceErrorStatus::BadRequest
+        ))?;
+        let tls = match (read_text::<X>("INGRESS_TLS_CERT_PATH"), read_text::<X>("INGRESS_TLS_KEY_PATH")) {
+            (Some(cert_path), Some(key_path)) => Some(TlsPaths { cert_path, key_path }),
+            (None, None) => None,
+            _ => return Err(NanoServiceError::new(
+                "INGRESS_TLS_CERT_PATH and INGRESS_TLS_KEY_PATH must be set together".to_string(),
+                NanoServiceErrorStatus::BadRequest
+            ))
+        };
+        Ok(ServerConfig {
+            host,
+            port,
+            tls,
+            workers,
+            max_blocking_threads,
+            keep_alive,
+            client_request_timeout,
+            client_disconnect_timeout,
+            max_connections,
+            max_connection_rate,
+            max_header_bytes,
+            max_requests_per_ip,
+            max_json_bytes,
+            max_payload_bytes,
+            compression
+        })
+    }
+}
+
+
+/// Reads an optional text config variable, treating a blank value as not set.
+fn read_text<X: GetConfigVariable>(variable: &str) -> Option<String> {
+    X::get_config_variable(variable.to_string()).ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+
+/// Reads an optional numeric config variable, erroring only if it is set but invalid.
+pub fn read_number<X: GetConfigVariable>(variable: &str) -> Result<Option<u64>, NanoServiceError> {
+    match X::get_config_variable(variable.to_string()) {
+        Ok(value) => value.trim().parse::<u64>().map(Some).map_err(|_| {
+            NanoServiceError::new(
+                format!("{} must be a whole number, got '{}'", variable, value),
+                NanoServiceErrorStatus::BadRequest
+            )
+        }),
+        Err(_) => Ok(None)
+    }
+}
+
+
+/// Reads an optional true or false config variable, erroring only if it is set but invalid.
+fn read_flag<X: GetConfigVariable>(variable: &str) -> Result<Option<bool>, NanoServiceError> {
+    match X::get_config_variable(variable.to_string()) {
+        Ok(value) => parse_bool(&value).map(Some).ok_or_else(|| {
+            NanoServiceError::new(
+                format!("{} must be true or false, got '{}'", variable, value),
+                NanoServiceErrorStatus::BadRequest
+            )
+        }),
+        Err(_) => Ok(None)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EmptyConfig;
+
+    impl GetConfigVariable for EmptyConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    struct SetConfig;
+
+    impl GetConfigVariable for SetConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "INGRESS_WORKERS" => Ok("4".to_string()),
+                "INGRESS_MAX_BLOCKING_THREADS" => Ok(" 16 ".to_string()),
+                "INGRESS_KEEP_ALIVE_SECONDS" => Ok("0".to_string()),
+                "INGRESS_HOST" => Ok("127.0.0.1".to_string()),
+                "INGRESS_PORT" => Ok("8443".to_string()),
+                "INGRESS_TLS_CERT_PATH" => Ok("/etc/tls/cert.pem".to_string()),
+                "INGRESS_TLS_KEY_PATH" => Ok("/etc/tls/key.pem".to_string()),
+                "INGRESS_COMPRESSION" => Ok("false".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+            }
+        }
+    }
+
+    struct BadConfig;
+
+    impl GetConfigVariable for BadConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("many".to_string())
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = ServerConfig::from_config::<EmptyConfig>().unwrap();
+        assert_eq!(config.host, "0.0.0.0");
+        assert_eq!(config.port, 8001);
+        assert_eq!(config.tls, None);
+        assert_eq!(config.workers, available_parallelism().unwrap().get());
+        assert_eq!(config.max_blocking_threads, (512 / config.workers).max(1));
+        assert_eq!(config.keep_alive, Some(Duration::from_secs(5)));
+        assert_eq!(config.client_request_timeout, Duration::from_millis(5000));
+        assert_eq!(config.client_disconnect_timeout, Duration::from_millis(1000));
+        assert_eq!(config.max_connections, 10_000);
+        assert_eq!(config.max_connection_rate, 256);
+        assert_eq!(config.max_header_bytes, 16 * 1024);
+        assert_eq!(config.max_requests_per_ip, 32);
+        assert_eq!(config.max_json_bytes, 256 * 1024);
+        assert_eq!(config.max_payload_bytes, 1024 * 1024);
+        assert!(config.compression);
+    }
+
+    #[test]
+    fn test_configured() {
+        let config = ServerConfig::from_config::<SetConfig>().unwrap();
+        assert_eq!(config.workers, 4);
+        assert_eq!(config.max_blocking_threads, 16);
+        assert_eq!(config.keep_alive, None);
+        assert!(!config.compression);
+        assert_eq!(config.host, "127.0.0.1");
+        assert_eq!(config.port, 8443);
+        assert_eq!(config.tls, Some(TlsPaths {
+            cert_path: "/etc/tls/cert.pem".to_string(),
+            key_path: "/etc/tls/key.pem".to_string()
+        }));
+    }
+
+    #[test]
+    fn test_tls_paths_set_together() {
+        struct CertOnlyConfig;
+
+        impl GetConfigVariable for CertOnlyConfig {
+            fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+                match variable.as_str() {
+                    "INGRESS_TLS_CERT_PATH" => Ok("/etc/tls/cert.pem".to_string()),
+                    _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+                }
+            }
+        }
+
+        let error = ServerConfig::from_config::<CertOnlyConfig>().unwrap_err();
+        assert_eq!(error.message, "INGRESS_TLS_CERT_PATH and INGRESS_TLS_KEY_PATH must be set together");
+    }
+
+    #[test]
+    fn test_invalid_port() {
+        struct PortConfig;
+
+        impl GetConfigVariable for PortConfig {
+            fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+                match variable.as_str() {
+                    "INGRESS_PORT" => Ok("70000".to_string()),
+                    _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+                }
+            }
+        }
+
+        let error = ServerConfig::from_config::<PortConfig>().unwrap_err();
+        assert_eq!(error.message, "INGRESS_PORT must be a port number, got '70000'");
+    }
+
+    #[test]
+    fn test_invalid_flag() {
+        struct CompressionConfig;
+
+        impl GetConfigVariable for CompressionConfig {
+            fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+                match variable.as_str() {
+                    "INGRESS_COMPRESSION" => Ok("gzip".to_string()),
+                    _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+                }
+            }
+        }
+
+        let error = ServerConfig::from_config::<CompressionConfig>().unwrap_err();
+        assert_eq!(error.message, "INGRESS_COMPRESSION must be true or false, got 'gzip'");
+    }
+
+    #[test]
+    fn test_invalid_number() {
+        let error = ServerConfig::from_config::<BadConfig>().unwrap_err();
+        assert_eq!(error.message, "INGRESS_WORKERS must be a whole number, got 'many'");
+    }
+}
@@ -0,0 +1,90 @@
+//! Flags email templates missing from the provider so failed sends are not first found by users.
+//!
+//! # Variables
+//! * `MAILCHIMP_TEMPLATE_CHECK_INTERVAL_MINUTES` - How often the check is repeated after startup, unset or `0` only checks at startup
+use email_core::api::mailchimp_emails::template_check::check_required_templates;
+use email_core::mailchimp_traits::mc_definitions::ListTemplates;
+use std::time::Duration;
+use utils::config::GetConfigVariable;
+
+
+/// Reads how often the check should be repeated, returning `None` if it only runs at startup.
+fn check_interval<Y: GetConfigVariable>() -> Option<Duration> {
+    let minutes = Y::get_config_variable("MAILCHIMP_TEMPLATE_CHECK_INTERVAL_MINUTES".to_string())
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    match minutes {
+        0 => None,
+        minutes => Some(Duration::from_secs(minutes * 60))
+    }
+}
+
+
+/// Runs the template check once and logs the outcome.
+///
+/// # Returns
+/// `true` if every required template is present or the check was skipped
+async fn run_template_check<X: ListTemplates, Y: GetConfigVariable>() -> bool {
+    match check_required_templates::<X, Y>().await {
+        Ok(missing) if missing.is_empty() => true,
+        Ok(missing) => {
+            log::error!("email templates missing from the provider, sends using them will fail: {}", missing.join(", "));
+            false
+        },
+        Err(e) => {
+            log::error!("failed to check email templates with the provider: {}", e.message);
+            false
+        }
+    }
+}
+
+
+/// Checks the templates in the background so a slow provider does not hold up startup,
+/// repeating the check on the configured interval.
+pub fn spawn_template_check<X, Y>()
+where
+    X: ListTemplates + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let interval = check_interval::<Y>();
+    tokio::spawn(async move {
+        loop {
+            run_template_check::<X, Y>().await;
+            match interval {
+                Some(interval) => tokio::time::sleep(interval).await,
+                None => break
+            }
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::errors::NanoServiceError;
+
+    struct IntervalConfig;
+
+    impl GetConfigVariable for IntervalConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok(" 15 ".to_string())
+        }
+    }
+
+    struct DisabledConfig;
+
+    impl GetConfigVariable for DisabledConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("0".to_string())
+        }
+    }
+
+    #[test]
+    fn test_check_interval() {
+        assert_eq!(check_interval::<IntervalConfig>(), Some(Duration::from_secs(900)));
+        assert_eq!(check_interval::<DisabledConfig>(), None);
+    }
+}
@@ -0,0 +1,230 @@
+//! Lets super admins send an email template or a webhook delivery with sample values to check how
+//! the provider or the receiver handles it.
+//!
+//! # Notes
+//! A test fired webhook delivery is posted straight away, signed with the webhook's secret as a real
+//! delivery would be, and is not queued, retried or added to the webhook's delivery log. Its payload
+//! is a sample of the webhook's first event with `"test": true` added, so receivers can drop it.
+use actix_web::{HttpRequest, HttpResponse, web::{Json, Path}};
+use dal::users::tx_definitions::GetUser;
+use dal::webhooks::tx_definitions::GetWebhook;
+use email_core::api::mailchimp_emails::test_fire::{test_fire_template, TestFireStatus};
+use email_core::mailchimp_traits::mc_definitions::SendTemplate;
+use kernel::events::{DomainEvent, EventEnvelope};
+use kernel::users::UserRole;
+use kernel::webhooks::{DueWebhookDelivery, WebhookEvent, WebhookSender};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json as SqlxJson;
+use utils::api_endpoint;
+use utils::locale::Locale;
+use crate::webhooks::{attempt_delivery, webhook_not_found};
+
+
+/// The template to send and who to send it to.
+///
+/// # Fields
+/// * `template` - The name of the template, such as `password-reset`.
+/// * `recipient` - Who to send it to, the super admin sending it if not given.
+#[derive(Serialize, Deserialize)]
+pub struct TestFireEmailBody {
+    pub template: String,
+    pub recipient: Option<String>,
+}
+
+
+/// Sends the template with sample values, its copy in the language of the request, and returns
+/// what the provider did with it.
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[GetUser], email_traits=[SendTemplate])]
+pub async fn test_fire_email(req: HttpRequest, body: Json<TestFireEmailBody>) {
+    let body = body.into_inner();
+    let recipient = match body.recipient {
+        Some(recipient) => recipient,
+        None => X::get_user(jwt.user_id).await?.email
+    };
+    let locale = Locale::from_accept_language(req.headers());
+    let result = test_fire_template::<W, Y>(&body.template, recipient, locale).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+
+/// The outcome of a test fired webhook delivery.
+///
+/// # Fields
+/// * `webhook_id` - The webhook it was posted to.
+/// * `event` - The event it was a sample of.
+/// * `status` - `sent` for a `2xx`, `rejected` for any other answer, `failed` if it was not answered.
+/// * `status_code` - The status the receiver answered with, if it did.
+/// * `error` - Why it was not accepted, if it was not.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TestFireWebhookResult {
+    pub webhook_id: i32,
+    pub event: WebhookEvent,
+    pub status: TestFireStatus,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+
+/// A sample of the event a webhook receives, marked as a test.
+fn sample_payload(event: WebhookEvent) -> serde_json::Value {
+    let domain_event = match event {
+        WebhookEvent::UserCreated => DomainEvent::UserCreated { user_id: 0, role: UserRole::Worker },
+        WebhookEvent::TodoCompleted => DomainEvent::TodoCompleted { todo_id: 0, assigned_to: 0, pending_review: false },
+    };
+    let mut payload = serde_json::to_value(EventEnvelope::new(domain_event)).unwrap_or_default();
+    if let Some(fields) = payload.as_object_mut() {
+        fields.insert("test".to_string(), serde_json::Value::Bool(true));
+    }
+    payload
+}
+
+
+/// Posts a sample of a webhook's first event to it and returns how the receiver answered.
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[GetWebhook])]
+pub async fn test_fire_webhook<S: WebhookSender>(id: Path<i32>) {
+    let id = id.into_inner();
+    let webhook = X::get_webhook(id).await?.ok_or_else(|| webhook_not_found(id))?;
+    let event = webhook.events.0.first().copied().unwrap_or(WebhookEvent::UserCreated);
+    let payload = sample_payload(event);
+    let delivery = DueWebhookDelivery {
+        id: 0,
+        webhook_id: webhook.id,
+        url: webhook.url,
+        secret: webhook.secret,
+        event_id: payload["id"].as_str().unwrap_or_default().to_string(),
+        event: event.as_str().to_string(),
+        payload: SqlxJson(payload),
+        attempts: 0,
+    };
+    let (status_code, error) = attempt_delivery::<S>(&delivery).await;
+    let status = match (status_code, &error) {
+        (Some(_), None) => TestFireStatus::Sent,
+        (Some(_), Some(_)) => TestFireStatus::Rejected,
+        (None, _) => TestFireStatus::Failed,
+    };
+    Ok(HttpResponse::Ok().json(TestFireWebhookResult { webhook_id: id, event, status, status_code, error }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use email_core::mailchimp_helpers::mailchimp_template::Template;
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::chrono::NaiveDateTime;
+    use kernel::users::User;
+    use kernel::webhooks::{Webhook, WEBHOOK_EVENT_HEADER};
+    use kernel::webhooks::engine_mock::{RecordWebhooksMock, SENT_WEBHOOKS};
+    use utils::errors::NanoServiceError;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+
+    struct MockPostgres;
+
+    #[impl_transaction(MockPostgres, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        Ok(factories::user(id))
+    }
+
+    /// Has webhooks `1` to `3`, answering with a `200`, a `500` and not at all.
+    #[impl_transaction(MockPostgres, GetWebhook, get_webhook)]
+    async fn get_webhook(id: i32) -> Result<Option<Webhook>, NanoServiceError> {
+        let url = match id {
+            1 => "https://hooks.example.com/test-fire",
+            2 => "https://hooks.example.com/test-fire/500",
+            3 => "https://hooks.example.com/test-fire/unreachable",
+            _ => return Ok(None)
+        };
+        Ok(Some(Webhook {
+            id,
+            url: url.to_string(),
+            secret: "whsec_existing".to_string(),
+            events: SqlxJson(vec![WebhookEvent::TodoCompleted, WebhookEvent::UserCreated]),
+            active: false,
+            created_by: 1,
+            date_created: NaiveDateTime::default(),
+            date_updated: NaiveDateTime::default(),
+        }))
+    }
+
+    struct MockMailchimp;
+
+    #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+    async fn send_template(_template: &Template) -> Result<bool, NanoServiceError> {
+        Ok(true)
+    }
+
+    async fn send(role: UserRole, body: TestFireEmailBody) -> actix_web::dev::ServiceResponse {
+        let req = TokenBuilder::<FakeConfig, SuperAdminRoleCheck>::new()
+            .role(role)
+            .request(TestRequest::post().uri("/test-fire/email"))
+            .set_json(body);
+        call_endpoint(
+            Method::POST,
+            "/test-fire/email",
+            test_fire_email::<MockMailchimp, MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            req
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_test_fire_email() {
+        let resp = send(UserRole::SuperAdmin, TestFireEmailBody { template: "password-reset".to_string(), recipient: None }).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["recipient"], factories::user(1).email);
+        assert_eq!(body["status"], "sent");
+
+        let body = TestFireEmailBody { template: "password-reset".to_string(), recipient: Some("qa@example.com".to_string()) };
+        let body: serde_json::Value = test::read_body_json(send(UserRole::SuperAdmin, body).await).await;
+        assert_eq!(body["recipient"], "qa@example.com");
+
+        let resp = send(UserRole::SuperAdmin, TestFireEmailBody { template: "unknown".to_string(), recipient: None }).await;
+        assert_eq!(resp.status(), 404);
+        let resp = send(UserRole::Admin, TestFireEmailBody { template: "password-reset".to_string(), recipient: None }).await;
+        assert_eq!(resp.status(), 401);
+    }
+
+    async fn fire_webhook(role: UserRole, id: i32) -> actix_web::dev::ServiceResponse {
+        let uri = format!("/test-fire/webhooks/{}", id);
+        let req = TokenBuilder::<FakeConfig, SuperAdminRoleCheck>::new()
+            .role(role)
+            .request(TestRequest::post().uri(&uri));
+        call_endpoint(
+            Method::POST,
+            "/test-fire/webhooks/{id}",
+            test_fire_webhook::<MockPostgres, FakeConfig, PassAuthSessionCheckMock, RecordWebhooksMock>,
+            req
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_test_fire_webhook() {
+        let body: serde_json::Value = test::read_body_json(fire_webhook(UserRole::SuperAdmin, 1).await).await;
+        assert_eq!(body, serde_json::json!({
+            "webhook_id": 1,
+            "event": "todo_completed",
+            "status": "sent",
+            "status_code": 200,
+            "error": null
+        }));
+        let sent = SENT_WEBHOOKS.lock().unwrap().iter()
+            .find(|webhook| webhook.url == "https://hooks.example.com/test-fire")
+            .cloned()
+            .unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&sent.body).unwrap();
+        assert_eq!(payload["test"], true);
+        assert_eq!(payload["event"], "todo_completed");
+        assert!(sent.headers.contains(&(WEBHOOK_EVENT_HEADER, "todo_completed".to_string())));
+
+        let body: serde_json::Value = test::read_body_json(fire_webhook(UserRole::SuperAdmin, 2).await).await;
+        assert_eq!((&body["status"], &body["status_code"]), (&serde_json::json!("rejected"), &serde_json::json!(500)));
+        let body: serde_json::Value = test::read_body_json(fire_webhook(UserRole::SuperAdmin, 3).await).await;
+        assert_eq!((&body["status"], &body["status_code"]), (&serde_json::json!("failed"), &serde_json::Value::Null));
+
+        assert_eq!(fire_webhook(UserRole::SuperAdmin, 9).await.status(), 404);
+        assert_eq!(fire_webhook(UserRole::Admin, 1).await.status(), 401);
+    }
+}
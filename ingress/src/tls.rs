@@ -0,0 +1,75 @@
+//! Loads the certificate and key for terminating TLS in the server itself.
+use std::sync::Arc;
+use rustls::crypto::ring::default_provider;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use rustls_pki_types::pem::PemObject;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::server_config::TlsPaths;
+
+
+fn tls_error(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::Unknown)
+}
+
+
+/// Builds the rustls config from the PEM files in `paths`.
+///
+/// # Arguments
+/// * `paths` - The certificate chain and private key files
+///
+/// # Returns
+/// * `Ok(rustls::ServerConfig)` - The config serving the certificate over HTTP/1.1 and HTTP/2
+/// * `Err(NanoServiceError)` - If a file cannot be read or the key does not match the certificate
+pub fn load_rustls_config(paths: &TlsPaths) -> Result<rustls::ServerConfig, NanoServiceError> {
+    let cert_chain = CertificateDer::pem_file_iter(&paths.cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| tls_error(format!("Failed to read the TLS certificate {}: {}", paths.cert_path, e)))?;
+    if cert_chain.is_empty() {
+        return Err(tls_error(format!("No certificates found in {}", paths.cert_path)))
+    }
+    let key = PrivateKeyDer::from_pem_file(&paths.key_path)
+        .map_err(|e| tls_error(format!("Failed to read the TLS key {}: {}", paths.key_path, e)))?;
+
+    let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| tls_error(format!("Failed to configure TLS: {}", e)))?
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .map_err(|e| tls_error(format!("The TLS key does not match the certificate: {}", e)))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_cert_path(name: &str) -> String {
+        format!("{}/src/test_certs/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn test_load_rustls_config() {
+        let config = load_rustls_config(&TlsPaths {
+            cert_path: test_cert_path("cert.pem"),
+            key_path: test_cert_path("key.pem")
+        }).unwrap();
+        assert_eq!(config.alpn_protocols, vec![b"h2".to_vec(), b"http/1.1".to_vec()]);
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let error = load_rustls_config(&TlsPaths {
+            cert_path: test_cert_path("missing.pem"),
+            key_path: test_cert_path("key.pem")
+        }).unwrap_err();
+        assert!(error.message.starts_with("Failed to read the TLS certificate"));
+
+        let error = load_rustls_config(&TlsPaths {
+            cert_path: test_cert_path("cert.pem"),
+            key_path: test_cert_path("cert.pem")
+        }).unwrap_err();
+        assert!(error.message.starts_with("Failed to read the TLS key"));
+    }
+}
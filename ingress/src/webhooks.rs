@@ -0,0 +1,531 @@
+//! Lets admins register webhooks that are posted signed JSON when users are created or to-do items
+//! are completed, and delivers them in the background.
+//!
+//! # Overview
+//! `POST /api/ops/v1/webhooks` registers a URL for a set of events and returns the secret its
+//! deliveries are signed with, the only time the secret is shown. The webhooks are listed with
+//! `GET`, changed or paused with `PATCH /api/ops/v1/webhooks/{id}` and removed with `DELETE`.
+//!
+//! `enqueue_webhooks` subscribes to the in-process event bus and queues each event for every active
+//! webhook subscribed to it. The worker posts the queued deliveries, signed as described in
+//! `kernel::webhooks`, and retries any that do not get a `2xx` after `backoff(attempts)` until
+//! `MAX_ATTEMPTS` is reached. `GET /api/ops/v1/webhooks/{id}/deliveries` lists a webhook's latest
+//! deliveries with the status code and error of their last attempt, for debugging a receiver.
+//!
+//! # Notes
+//! - A paused webhook is not queued new events, and the deliveries already queued for it wait
+//!   until it is resumed.
+//! - Every attempt at a delivery has the same `X-Webhook-Id` so the receiver can drop repeats.
+//!
+//! # Variables
+//! * `WEBHOOK_POLL_SECONDS` - How often the queued deliveries are polled, defaults to 5
+//! * `WEBHOOK_BATCH_SIZE` - The most deliveries claimed per poll, defaults to 20
+use std::future::Future;
+use std::pin::Pin;
+use actix_web::{HttpResponse, web::{Json, Path}};
+use dal::webhooks::tx_definitions::{
+    ClaimDueWebhookDeliveries,
+    CreateWebhook,
+    DeleteWebhook,
+    EnqueueWebhookDeliveries,
+    GetWebhook,
+    ListWebhookDeliveries,
+    ListWebhooks,
+    MarkWebhookDelivered,
+    RecordWebhookFailure,
+    UpdateWebhook,
+};
+use kernel::chrono::{Duration, Utc};
+use kernel::events::EventEnvelope;
+use kernel::webhooks::{
+    generate_webhook_secret,
+    validate_webhook_url,
+    DueWebhookDelivery,
+    NewWebhook,
+    SignedWebhook,
+    Webhook,
+    WebhookEvent,
+    WebhookSender,
+    WebhookUpdate,
+};
+use serde::{Deserialize, Serialize};
+use utils::api_endpoint;
+use utils::log_limited;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The number of attempts after which a delivery is marked as failed.
+pub const MAX_ATTEMPTS: i32 = 8;
+
+/// The delay before the first retry, doubled for every attempt after.
+const BASE_BACKOFF_SECONDS: i64 = 30;
+
+/// The longest delay between two attempts.
+const MAX_BACKOFF_SECONDS: i64 = 60 * 60;
+
+/// The poll interval used when not configured.
+const DEFAULT_POLL_SECONDS: i64 = 5;
+
+/// The batch size used when not configured.
+const DEFAULT_BATCH_SIZE: i64 = 20;
+
+/// The most deliveries listed in a delivery log.
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+
+/// The webhook to register.
+///
+/// # Fields
+/// * `url` - Where deliveries are posted, an `http` or `https` URL.
+/// * `events` - The events it receives, at least one.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateWebhookBody {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+
+/// A webhook as returned when it is registered, the only response with its secret.
+#[derive(Serialize, Debug)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+
+/// The counts from a single pass over the queued deliveries.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct WebhookRunSummary {
+    pub delivered: usize,
+    pub retrying: usize,
+    pub failed: usize,
+}
+
+
+/// Checks the events of a webhook, dropping any listed twice.
+fn validate_events(mut events: Vec<WebhookEvent>) -> Result<Vec<WebhookEvent>, NanoServiceError> {
+    if events.is_empty() {
+        return Err(NanoServiceError::new(
+            "A webhook must subscribe to at least one event".to_string(),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    }
+    let mut seen = Vec::with_capacity(events.len());
+    events.retain(|event| if seen.contains(event) { false } else { seen.push(*event); true });
+    Ok(events)
+}
+
+
+pub(crate) fn webhook_not_found(id: i32) -> NanoServiceError {
+    NanoServiceError::new(format!("Webhook {} not found", id), NanoServiceErrorStatus::NotFound)
+}
+
+
+/// Registers a webhook and returns it with its secret and `201 Created`.
+#[api_endpoint(token=AdminRoleCheck, db_traits=[CreateWebhook])]
+pub async fn create_webhook(body: Json<CreateWebhookBody>) {
+    let body = body.into_inner();
+    validate_webhook_url(&body.url)?;
+    let webhook = X::create_webhook(NewWebhook {
+        url: body.url,
+        secret: generate_webhook_secret(),
+        events: validate_events(body.events)?,
+        created_by: jwt.user_id,
+    }).await?;
+    let secret = webhook.secret.clone();
+    Ok(HttpResponse::Created().json(CreatedWebhook { webhook, secret }))
+}
+
+
+/// Lists the registered webhooks, without their secrets.
+#[api_endpoint(token=AdminRoleCheck, db_traits=[ListWebhooks])]
+pub async fn list_webhooks() {
+    Ok(HttpResponse::Ok().json(X::list_webhooks().await?))
+}
+
+
+/// Changes the URL or events of a webhook, or pauses or resumes it with `active`.
+#[api_endpoint(token=AdminRoleCheck, db_traits=[UpdateWebhook])]
+pub async fn update_webhook(id: Path<i32>, body: Json<WebhookUpdate>) {
+    let id = id.into_inner();
+    let mut update = body.into_inner();
+    if let Some(url) = &update.url {
+        validate_webhook_url(url)?;
+    }
+    update.events = update.events.map(validate_events).transpose()?;
+    let webhook = X::update_webhook(id, update).await?.ok_or_else(|| webhook_not_found(id))?;
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+
+/// Removes a webhook and its delivery log, returning `204 No Content`.
+#[api_endpoint(token=AdminRoleCheck, db_traits=[DeleteWebhook])]
+pub async fn delete_webhook(id: Path<i32>) {
+    let id = id.into_inner();
+    if !X::delete_webhook(id).await? {
+        return Err(webhook_not_found(id))
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+
+/// Lists a webhook's latest deliveries newest first, with the outcome of their last attempt.
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetWebhook, ListWebhookDeliveries])]
+pub async fn list_webhook_deliveries(id: Path<i32>) {
+    let id = id.into_inner();
+    if X::get_webhook(id).await?.is_none() {
+        return Err(webhook_not_found(id))
+    }
+    Ok(HttpResponse::Ok().json(X::list_webhook_deliveries(id, DELIVERY_LOG_LIMIT).await?))
+}
+
+
+/// Queues the events webhooks can subscribe to, logging rather than retrying if they cannot be queued.
+pub fn enqueue_webhooks<X: EnqueueWebhookDeliveries>(envelope: EventEnvelope) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        let Some(event) = WebhookEvent::for_domain_event(&envelope.event) else {
+            return
+        };
+        let payload = match serde_json::to_value(&envelope) {
+            Ok(payload) => payload,
+            Err(e) => {
+                println!("failed to serialize {} event {}: {}", event.as_str(), envelope.id, e);
+                return
+            }
+        };
+        if let Err(e) = X::enqueue_webhook_deliveries(event, envelope.id.to_string(), payload).await {
+            log_limited!("webhooks", "failed to queue {} event {} for webhooks: {}", event.as_str(), envelope.id, e.message);
+        }
+    })
+}
+
+
+/// The delay before the next attempt once `attempts` attempts have been made.
+///
+/// # Notes
+/// 1 attempt waits 30 seconds, 2 waits a minute, 3 waits two minutes and so on, capped at an hour.
+pub fn backoff(attempts: i32) -> Duration {
+    let exponent = (attempts.max(1) - 1).min(16) as u32;
+    Duration::seconds((BASE_BACKOFF_SECONDS << exponent).min(MAX_BACKOFF_SECONDS))
+}
+
+
+/// Posts a delivery, returning the status it got and why it failed if it did.
+pub(crate) async fn attempt_delivery<S: WebhookSender>(delivery: &DueWebhookDelivery) -> (Option<i32>, Option<String>) {
+    let signed = SignedWebhook::sign(delivery, Utc::now().timestamp());
+    match S::send(&signed).await {
+        Ok(status) if (200..300).contains(&status) => (Some(status as i32), None),
+        Ok(status) => (Some(status as i32), Some(format!("Receiver answered with HTTP {}", status))),
+        Err(e) => (None, Some(e.message))
+    }
+}
+
+
+/// Posts the deliveries that are due, recording the outcome of each.
+///
+/// # Arguments
+/// * `batch_size` - The most deliveries to claim in this pass
+///
+/// # Returns
+/// * `Ok(WebhookRunSummary)` - The counts for the pass
+/// * `Err(NanoServiceError)` - If the deliveries could not be claimed or updated
+pub async fn process_webhook_outbox<X, S>(batch_size: i64) -> Result<WebhookRunSummary, NanoServiceError>
+where
+    X: ClaimDueWebhookDeliveries + MarkWebhookDelivered + RecordWebhookFailure,
+    S: WebhookSender,
+{
+    let deliveries = X::claim_due_webhook_deliveries(batch_size).await?;
+    let mut summary = WebhookRunSummary::default();
+    for delivery in deliveries {
+        let attempts = delivery.attempts + 1;
+        match attempt_delivery::<S>(&delivery).await {
+            (Some(status_code), None) => {
+                X::mark_webhook_delivered(delivery.id, status_code).await?;
+                summary.delivered += 1;
+            },
+            (status_code, Some(error)) if attempts < MAX_ATTEMPTS => {
+                let next_attempt_at = Utc::now().naive_utc() + backoff(attempts);
+                X::record_webhook_failure(delivery.id, status_code, error, Some(next_attempt_at)).await?;
+                summary.retrying += 1;
+            },
+            (status_code, error) => {
+                let error = error.unwrap_or_default();
+                println!("webhook delivery {} to {} permanently failed after {} attempts: {}", delivery.id, delivery.url, attempts, error);
+                X::record_webhook_failure(delivery.id, status_code, error, None).await?;
+                summary.failed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+
+/// Posts the queued deliveries in the background for the life of the runtime.
+///
+/// # Notes
+/// An error in a pass is logged and the worker carries on, the deliveries stay queued.
+pub fn spawn_webhook_worker<X, S, Y>()
+where
+    X: ClaimDueWebhookDeliveries + MarkWebhookDelivered + RecordWebhookFailure + 'static,
+    S: WebhookSender + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let poll_interval = std::time::Duration::from_secs(Y::get_positive_int("WEBHOOK_POLL_SECONDS").unwrap_or(DEFAULT_POLL_SECONDS) as u64);
+    let batch_size = Y::get_positive_int("WEBHOOK_BATCH_SIZE").unwrap_or(DEFAULT_BATCH_SIZE);
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = process_webhook_outbox::<X, S>(batch_size).await {
+                log_limited!("webhooks", "webhook delivery pass failed: {}", e.message);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::NaiveDateTime;
+    use kernel::events::DomainEvent;
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::users::UserRole;
+    use kernel::webhooks::{WebhookDelivery, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER, sign_webhook};
+    use kernel::webhooks::engine_mock::{RecordWebhooksMock, SENT_WEBHOOKS};
+    use sqlx::types::Json as SqlxJson;
+    use std::sync::Mutex;
+    use test_support::{call_endpoint, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+
+    /// The outcome recorded for a delivery as `(id, status_code, error, retried)`.
+    type Recorded = (i32, Option<i32>, Option<String>, bool);
+
+    static RECORDED: Mutex<Vec<Recorded>> = Mutex::new(Vec::new());
+    static QUEUED: Mutex<Vec<(WebhookEvent, String, serde_json::Value)>> = Mutex::new(Vec::new());
+
+    fn webhook(id: i32) -> Webhook {
+        Webhook {
+            id,
+            url: "https://hooks.example.com/in".to_string(),
+            secret: "whsec_existing".to_string(),
+            events: SqlxJson(vec![WebhookEvent::UserCreated]),
+            active: true,
+            created_by: 1,
+            date_created: NaiveDateTime::default(),
+            date_updated: NaiveDateTime::default(),
+        }
+    }
+
+    fn due(id: i32, url: &str, attempts: i32) -> DueWebhookDelivery {
+        DueWebhookDelivery {
+            id,
+            webhook_id: 1,
+            url: url.to_string(),
+            secret: "whsec_existing".to_string(),
+            event_id: format!("event-{}", id),
+            event: "user_created".to_string(),
+            payload: SqlxJson(serde_json::json!({"event": "user_created", "data": {"user_id": id}})),
+            attempts,
+        }
+    }
+
+    /// Has webhook `1`, with one delivery.
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, CreateWebhook, create_webhook)]
+    async fn create_webhook(new_webhook: NewWebhook) -> Result<Webhook, NanoServiceError> {
+        Ok(Webhook {
+            url: new_webhook.url,
+            secret: new_webhook.secret,
+            events: SqlxJson(new_webhook.events),
+            created_by: new_webhook.created_by,
+            ..webhook(2)
+        })
+    }
+
+    #[impl_transaction(MockPostgres, ListWebhooks, list_webhooks)]
+    async fn list_webhooks() -> Result<Vec<Webhook>, NanoServiceError> {
+        Ok(vec![webhook(1)])
+    }
+
+    #[impl_transaction(MockPostgres, GetWebhook, get_webhook)]
+    async fn get_webhook(id: i32) -> Result<Option<Webhook>, NanoServiceError> {
+        Ok((id == 1).then(|| webhook(1)))
+    }
+
+    #[impl_transaction(MockPostgres, UpdateWebhook, update_webhook)]
+    async fn update_webhook(id: i32, update: WebhookUpdate) -> Result<Option<Webhook>, NanoServiceError> {
+        Ok((id == 1).then(|| Webhook {
+            active: update.active.unwrap_or(true),
+            events: SqlxJson(update.events.unwrap_or_default()),
+            ..webhook(1)
+        }))
+    }
+
+    #[impl_transaction(MockPostgres, DeleteWebhook, delete_webhook)]
+    async fn delete_webhook(id: i32) -> Result<bool, NanoServiceError> {
+        Ok(id == 1)
+    }
+
+    #[impl_transaction(MockPostgres, ListWebhookDeliveries, list_webhook_deliveries)]
+    async fn list_webhook_deliveries(webhook_id: i32, limit: i64) -> Result<Vec<WebhookDelivery>, NanoServiceError> {
+        assert_eq!(limit, DELIVERY_LOG_LIMIT);
+        Ok(vec![WebhookDelivery {
+            id: 7,
+            webhook_id,
+            event_id: "event-7".to_string(),
+            event: "user_created".to_string(),
+            payload: SqlxJson(serde_json::json!({})),
+            status: kernel::webhooks::WebhookDeliveryStatus::Pending,
+            attempts: 2,
+            next_attempt_at: NaiveDateTime::default(),
+            last_status_code: Some(503),
+            last_error: Some("Receiver answered with HTTP 503".to_string()),
+            date_created: NaiveDateTime::default(),
+            date_delivered: None,
+        }])
+    }
+
+    #[impl_transaction(MockPostgres, EnqueueWebhookDeliveries, enqueue_webhook_deliveries)]
+    async fn enqueue_webhook_deliveries(event: WebhookEvent, event_id: String, payload: serde_json::Value) -> Result<i64, NanoServiceError> {
+        QUEUED.lock().unwrap().push((event, event_id, payload));
+        Ok(1)
+    }
+
+    #[impl_transaction(MockPostgres, ClaimDueWebhookDeliveries, claim_due_webhook_deliveries)]
+    async fn claim_due_webhook_deliveries(limit: i64) -> Result<Vec<DueWebhookDelivery>, NanoServiceError> {
+        assert_eq!(limit, 10);
+        Ok(vec![
+            due(1, "https://hooks.example.com/in", 0),
+            due(2, "https://hooks.example.com/503", 0),
+            due(3, "https://hooks.example.com/unreachable", MAX_ATTEMPTS - 1),
+        ])
+    }
+
+    #[impl_transaction(MockPostgres, MarkWebhookDelivered, mark_webhook_delivered)]
+    async fn mark_webhook_delivered(id: i32, status_code: i32) -> Result<bool, NanoServiceError> {
+        RECORDED.lock().unwrap().push((id, Some(status_code), None, false));
+        Ok(true)
+    }
+
+    #[impl_transaction(MockPostgres, RecordWebhookFailure, record_webhook_failure)]
+    async fn record_webhook_failure(id: i32, status_code: Option<i32>, error: String, next_attempt_at: Option<NaiveDateTime>) -> Result<bool, NanoServiceError> {
+        RECORDED.lock().unwrap().push((id, status_code, Some(error), next_attempt_at.is_some()));
+        Ok(true)
+    }
+
+    #[test]
+    fn test_validate_events() {
+        let events = vec![WebhookEvent::TodoCompleted, WebhookEvent::UserCreated, WebhookEvent::TodoCompleted];
+        assert_eq!(validate_events(events).unwrap(), vec![WebhookEvent::TodoCompleted, WebhookEvent::UserCreated]);
+        assert_eq!(validate_events(Vec::new()).unwrap_err().status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::seconds(30));
+        assert_eq!(backoff(3), Duration::seconds(120));
+        assert_eq!(backoff(i32::MAX), Duration::seconds(MAX_BACKOFF_SECONDS));
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_webhooks() {
+        let created = EventEnvelope::new(DomainEvent::UserCreated { user_id: 4, role: UserRole::Worker });
+        enqueue_webhooks::<MockPostgres>(created.clone()).await;
+        enqueue_webhooks::<MockPostgres>(EventEnvelope::new(DomainEvent::UserBlocked { user_id: 4, blocked_by: 1 })).await;
+
+        let queued = QUEUED.lock().unwrap();
+        assert_eq!(queued.len(), 1);
+        let (event, event_id, payload) = &queued[0];
+        assert_eq!(*event, WebhookEvent::UserCreated);
+        assert_eq!(*event_id, created.id.to_string());
+        assert_eq!(payload["event"], "user_created");
+        assert_eq!(payload["data"]["user_id"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_process_webhook_outbox() {
+        let summary = process_webhook_outbox::<MockPostgres, RecordWebhooksMock>(10).await.unwrap();
+        assert_eq!(summary, WebhookRunSummary { delivered: 1, retrying: 1, failed: 1 });
+        assert_eq!(*RECORDED.lock().unwrap(), vec![
+            (1, Some(200), None, false),
+            (2, Some(503), Some("Receiver answered with HTTP 503".to_string()), true),
+            (3, None, Some("Failed to reach https://hooks.example.com/unreachable".to_string()), false),
+        ]);
+
+        // the delivery is signed with the webhook's secret over the timestamp it was sent at
+        let sent = SENT_WEBHOOKS.lock().unwrap().iter()
+            .find(|webhook| webhook.url == "https://hooks.example.com/in").cloned().unwrap();
+        let header = |name| sent.headers.iter().find(|(key, _)| *key == name).map(|(_, value)| value.clone()).unwrap();
+        let timestamp: i64 = header(WEBHOOK_TIMESTAMP_HEADER).parse().unwrap();
+        assert_eq!(header(WEBHOOK_SIGNATURE_HEADER), format!("sha256={}", sign_webhook("whsec_existing", timestamp, &sent.body)));
+    }
+
+    async fn send<F, Args>(method: Method, uri: &str, pattern: &str, handler: F, role: UserRole, body: Option<serde_json::Value>) -> actix_web::dev::ServiceResponse
+    where
+        F: actix_web::Handler<Args>,
+        Args: actix_web::FromRequest + 'static,
+        F::Output: actix_web::Responder + 'static,
+    {
+        let mut req = TokenBuilder::<FakeConfig, AdminRoleCheck>::new()
+            .role(role)
+            .request(TestRequest::default().method(method.clone()).uri(uri));
+        if let Some(body) = body {
+            req = req.set_json(body);
+        }
+        call_endpoint(method, pattern, handler, req).await
+    }
+
+    #[tokio::test]
+    async fn test_webhook_endpoints() {
+        let create = create_webhook::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>;
+        let body = serde_json::json!({"url": "https://hooks.example.com/new", "events": ["todo_completed"]});
+        let resp = send(Method::POST, "/webhooks", "/webhooks", create, UserRole::Admin, Some(body)).await;
+        assert_eq!(resp.status(), 201);
+        let created: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(created["url"], "https://hooks.example.com/new");
+        assert_eq!(created["events"], serde_json::json!(["todo_completed"]));
+        assert!(created["secret"].as_str().unwrap().starts_with("whsec_"));
+
+        let body = serde_json::json!({"url": "ftp://hooks.example.com", "events": ["todo_completed"]});
+        assert_eq!(send(Method::POST, "/webhooks", "/webhooks", create, UserRole::Admin, Some(body)).await.status(), 400);
+        let body = serde_json::json!({"url": "https://hooks.example.com/new", "events": []});
+        assert_eq!(send(Method::POST, "/webhooks", "/webhooks", create, UserRole::Admin, Some(body)).await.status(), 400);
+        let body = serde_json::json!({"url": "https://hooks.example.com/new", "events": ["user_created"]});
+        assert_eq!(send(Method::POST, "/webhooks", "/webhooks", create, UserRole::Worker, Some(body)).await.status(), 401);
+
+        // the secret is only shown when the webhook is registered
+        let list = list_webhooks::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>;
+        let listed: serde_json::Value = test::read_body_json(send(Method::GET, "/webhooks", "/webhooks", list, UserRole::Admin, None).await).await;
+        assert_eq!(listed[0]["id"], 1);
+        assert!(listed[0].get("secret").is_none());
+
+        let update = update_webhook::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>;
+        let body = serde_json::json!({"active": false, "events": ["user_created", "user_created"]});
+        let resp = send(Method::PATCH, "/webhooks/1", "/webhooks/{id}", update, UserRole::Admin, Some(body)).await;
+        assert_eq!(resp.status(), 200);
+        let updated: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!((updated["active"].clone(), updated["events"].clone()), (serde_json::json!(false), serde_json::json!(["user_created"])));
+        let body = serde_json::json!({"active": false});
+        assert_eq!(send(Method::PATCH, "/webhooks/9", "/webhooks/{id}", update, UserRole::Admin, Some(body)).await.status(), 404);
+
+        let delete = delete_webhook::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>;
+        assert_eq!(send(Method::DELETE, "/webhooks/1", "/webhooks/{id}", delete, UserRole::Admin, None).await.status(), 204);
+        assert_eq!(send(Method::DELETE, "/webhooks/9", "/webhooks/{id}", delete, UserRole::Admin, None).await.status(), 404);
+
+        let deliveries = list_webhook_deliveries::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>;
+        let pattern = "/webhooks/{id}/deliveries";
+        let resp = send(Method::GET, "/webhooks/1/deliveries", pattern, deliveries, UserRole::Admin, None).await;
+        assert_eq!(resp.status(), 200);
+        let log: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(log[0]["last_status_code"], 503);
+        assert_eq!(log[0]["status"], "pending");
+        assert_eq!(send(Method::GET, "/webhooks/9/deliveries", pattern, deliveries, UserRole::Admin, None).await.status(), 404);
+    }
+}
@@ -0,0 +1,203 @@
+//! Core logic for super admins creating, listing and revoking API keys.
+//!
+//! # Overview
+//! A key can only be created when the organization's plan includes `api_access`. The key itself is
+//! only returned by `create_api_key`, after which only its hash is kept, see `kernel::api_keys`.
+//! Creating and revoking a key are both written to the audit log.
+use dal::api_keys::tx_definitions::{CreateApiKey, ListApiKeys, RevokeApiKey};
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use dal::plans::tx_definitions::GetOrgPlan;
+use kernel::audit_log::NewAuditEntry;
+use kernel::api_keys::{
+    generate_api_key, ApiKey, CreatedApiKey, NewApiKey, API_KEY_CREATED_ACTION, API_KEY_REVOKED_ACTION
+};
+use serde_json::json;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The longest name a key can be given.
+const MAX_NAME_LENGTH: usize = 100;
+
+
+/// Creates an API key.
+///
+/// # Arguments
+/// - `name`: What the key is used for, such as `nightly-backup`.
+/// - `created_by`: The ID of the super admin creating the key.
+///
+/// # Returns
+/// - `Ok(CreatedApiKey)`: The key, the only time it is returned.
+/// - `Err(NanoServiceError)`: `BadRequest` if the name is blank or too long, `UpgradeRequired` if the
+///   plan does not include `api_access`.
+pub async fn create_api_key<X>(name: String, created_by: i32) -> Result<CreatedApiKey, NanoServiceError>
+where
+    X: CreateApiKey + GetOrgPlan + CreateAuditEntry
+{
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(NanoServiceError::new(
+            format!("An API key needs a name of at most {} characters", MAX_NAME_LENGTH),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    }
+    X::get_org_plan().await?.entitlements().check_api_access()?;
+    let key = generate_api_key();
+    let api_key = X::create_api_key(NewApiKey::from_key(name, &key, created_by)).await?;
+    X::create_audit_entry(NewAuditEntry {
+        actor_id: created_by,
+        action: API_KEY_CREATED_ACTION.to_string(),
+        subject_id: Some(api_key.id),
+        details: json!({"name": api_key.name, "key_prefix": api_key.key_prefix}),
+    }).await?;
+    Ok(CreatedApiKey { api_key, key })
+}
+
+
+/// Revokes an API key, it is refused from the next request.
+///
+/// # Arguments
+/// - `id`: The ID of the key.
+/// - `revoked_by`: The ID of the super admin revoking the key.
+///
+/// # Returns
+/// - `Ok(())`: If the key was revoked.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such key or it was already revoked.
+pub async fn revoke_api_key<X>(id: i32, revoked_by: i32) -> Result<(), NanoServiceError>
+where
+    X: RevokeApiKey + CreateAuditEntry
+{
+    if !X::revoke_api_key(id).await? {
+        return Err(NanoServiceError::new(
+            format!("API key {} not found or already revoked", id),
+            NanoServiceErrorStatus::NotFound
+        ))
+    }
+    X::create_audit_entry(NewAuditEntry {
+        actor_id: revoked_by,
+        action: API_KEY_REVOKED_ACTION.to_string(),
+        subject_id: Some(id),
+        details: json!({}),
+    }).await?;
+    Ok(())
+}
+
+
+/// Lists every key, revoked ones included, newest first.
+pub async fn list_api_keys<X: ListApiKeys>() -> Result<Vec<ApiKey>, NanoServiceError> {
+    X::list_api_keys().await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::api_keys::hash_api_key;
+    use kernel::audit_log::AuditEntry;
+    use kernel::chrono::Utc;
+    use kernel::plans::{OrgPlan, Plan};
+    use sqlx::types::Json;
+    use std::sync::Mutex;
+
+    static AUDITED: Mutex<Vec<NewAuditEntry>> = Mutex::new(Vec::new());
+
+    fn audited(action: &str) -> Vec<NewAuditEntry> {
+        AUDITED.lock().unwrap().iter().filter(|entry| entry.action == action).cloned().collect()
+    }
+
+    fn org_plan(plan: Plan) -> OrgPlan {
+        OrgPlan { plan, max_users: None, max_todos: None, mfa: None, api_access: None, date_updated: Utc::now().naive_utc() }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, CreateAuditEntry, create_audit_entry)]
+    async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        AUDITED.lock().unwrap().push(entry.clone());
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: Json(entry.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(org_plan(Plan::Pro))
+    }
+
+    #[impl_transaction(MockDbHandle, CreateApiKey, create_api_key)]
+    async fn create_api_key(api_key: NewApiKey) -> Result<ApiKey, NanoServiceError> {
+        Ok(ApiKey {
+            id: 7,
+            name: api_key.name,
+            key_prefix: api_key.key_prefix,
+            key_hash: api_key.key_hash,
+            created_by: api_key.created_by,
+            date_created: Utc::now().naive_utc(),
+            last_used_at: None,
+            revoked_at: None,
+        })
+    }
+
+    /// Only key 7 can be revoked.
+    #[impl_transaction(MockDbHandle, RevokeApiKey, revoke_api_key)]
+    async fn revoke_api_key(id: i32) -> Result<bool, NanoServiceError> {
+        Ok(id == 7)
+    }
+
+    struct FreePlanHandle;
+
+    #[impl_transaction(FreePlanHandle, GetOrgPlan, get_org_plan)]
+    async fn get_free_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(org_plan(Plan::Free))
+    }
+
+    #[impl_transaction(FreePlanHandle, CreateApiKey, create_api_key)]
+    async fn create_unreachable_api_key(_api_key: NewApiKey) -> Result<ApiKey, NanoServiceError> {
+        panic!("a key is not created without api_access")
+    }
+
+    #[impl_transaction(FreePlanHandle, CreateAuditEntry, create_audit_entry)]
+    async fn create_unreachable_audit_entry(_entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        panic!("nothing is audited without api_access")
+    }
+
+    #[tokio::test]
+    async fn test_create_api_key() {
+        let created = create_api_key::<MockDbHandle>("  nightly-backup ".to_string(), 1).await.unwrap();
+        assert_eq!(created.api_key.name, "nightly-backup");
+        assert_eq!(created.api_key.key_hash, hash_api_key(&created.key));
+        assert!(created.key.starts_with(&created.api_key.key_prefix));
+        assert_eq!(audited(API_KEY_CREATED_ACTION), vec![NewAuditEntry {
+            actor_id: 1,
+            action: API_KEY_CREATED_ACTION.to_string(),
+            subject_id: Some(7),
+            details: json!({"name": "nightly-backup", "key_prefix": created.api_key.key_prefix}),
+        }]);
+
+        let blank = create_api_key::<MockDbHandle>(" ".to_string(), 1).await.unwrap_err();
+        assert_eq!(blank.status, NanoServiceErrorStatus::BadRequest);
+        let too_long = create_api_key::<MockDbHandle>("a".repeat(MAX_NAME_LENGTH + 1), 1).await.unwrap_err();
+        assert_eq!(too_long.status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_create_api_key_needs_api_access() {
+        let error = create_api_key::<FreePlanHandle>("nightly-backup".to_string(), 1).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::UpgradeRequired);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_api_key() {
+        revoke_api_key::<MockDbHandle>(7, 1).await.unwrap();
+        assert_eq!(audited(API_KEY_REVOKED_ACTION).len(), 1);
+
+        let missing = revoke_api_key::<MockDbHandle>(8, 1).await.unwrap_err();
+        assert_eq!(missing.status, NanoServiceErrorStatus::NotFound);
+        assert_eq!(audited(API_KEY_REVOKED_ACTION).len(), 1);
+    }
+}
@@ -0,0 +1,21 @@
+//! Core logic for super admins exporting the whole audit log.
+use dal::audit_log::tx_definitions::ListAuditEntries;
+use kernel::audit_log::AuditEntry;
+use utils::errors::NanoServiceError;
+use utils::export_stream::{export_stream, Bytes, ExportEncoding, ExportFormat, Stream, DEFAULT_EXPORT_PAGE_SIZE};
+
+
+/// Streams the audit log newest first, reading `DEFAULT_EXPORT_PAGE_SIZE` entries at a time.
+///
+/// # Arguments
+/// - `format`: CSV or JSON.
+/// - `encoding`: Whether to gzip the export.
+///
+/// # Returns
+/// - The bytes of the export, read from the database as the client takes them.
+pub fn export_audit_entries<X: ListAuditEntries>(format: ExportFormat, encoding: ExportEncoding) -> impl Stream<Item = Result<Bytes, NanoServiceError>> {
+    export_stream(format, encoding, DEFAULT_EXPORT_PAGE_SIZE, |entry: &AuditEntry| entry.id as i64, |after| {
+        // the cursor only ever holds an entry ID so it always fits
+        X::list_audit_entries(after.map(|id| id as i32), DEFAULT_EXPORT_PAGE_SIZE as i64)
+    })
+}
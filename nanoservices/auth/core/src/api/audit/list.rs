@@ -0,0 +1,58 @@
+//! Core logic for super admins reading the audit log.
+use utils::errors::NanoServiceError;
+use dal::audit_log::tx_definitions::ListAuditEntries;
+use kernel::audit_log::AuditEntry;
+use kernel::pagination::{Page, PageQuery};
+
+
+/// Lists the audit log newest first, a page at a time.
+///
+/// # Arguments
+/// - `page`: The page asked for, the cursor holding the ID of the last entry on the previous page.
+///
+/// # Returns
+/// - `Ok(Page<AuditEntry>)`: The entries on the page and the cursor of the next page.
+/// - `Err(NanoServiceError)`: `BadRequest` if the limit or cursor is invalid.
+pub async fn list_audit_entries<X: ListAuditEntries>(page: &PageQuery) -> Result<Page<AuditEntry>, NanoServiceError> {
+    let limit = page.limit()?;
+    // one more than the page is read to tell whether there is another page
+    let entries = X::list_audit_entries(page.after_id()?, limit + 1).await?;
+    Ok(Page::from_lookahead(entries, limit, |entry| entry.id as i64))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use sqlx::types::Json;
+
+    struct MockDbHandle;
+
+    /// The log holds entries 1 to 5.
+    #[impl_transaction(MockDbHandle, ListAuditEntries, list_audit_entries)]
+    async fn list_audit_entries(before_id: Option<i32>, limit: i64) -> Result<Vec<AuditEntry>, NanoServiceError> {
+        Ok((1..=5).rev()
+            .filter(|id| before_id.is_none_or(|before_id| *id < before_id))
+            .take(limit as usize)
+            .map(|id| AuditEntry {
+                id,
+                actor_id: 1,
+                action: "todo:capacity_override".to_string(),
+                subject_id: Some(id),
+                details: Json(serde_json::json!({})),
+                date_created: chrono::Utc::now().naive_utc(),
+            })
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn test_list_audit_entries() {
+        let first = list_audit_entries::<MockDbHandle>(&PageQuery { cursor: None, limit: Some(3) }).await.unwrap();
+        assert_eq!(first.items.iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![5, 4, 3]);
+        let next = PageQuery { cursor: first.next_cursor, limit: Some(3) };
+        let last = list_audit_entries::<MockDbHandle>(&next).await.unwrap();
+        assert_eq!(last.items.iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(last.next_cursor, None);
+    }
+}
@@ -0,0 +1,230 @@
+//! Core logic for a super admin impersonating another user.
+//!
+//! # Overview
+//! The super admin is issued a token for the user, with the user's role and permissions, that
+//! carries their own ID as the `impersonator_id` claim and expires after `IMPERSONATION_MINUTES`.
+//! Starting the impersonation is written to the audit log, and the requests made with the token are
+//! audited as they are served, see `kernel::impersonation`.
+//!
+//! # Notes
+//! - Super admins cannot be impersonated, so an impersonation token can never start another.
+//! - Blocked and unconfirmed users cannot be impersonated, as they cannot log in themselves.
+//! - An impersonation token cannot be refreshed, the super admin starts a new impersonation instead.
+//!
+//! # Variables
+//! * `IMPERSONATION_MINUTES` - How long an impersonation token lasts, defaults to 15 and is capped at 60
+use dal::users::tx_definitions::GetUser;
+use dal::permissions::tx_definitions::GetEffectivePermissions;
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use kernel::audit_log::NewAuditEntry;
+use kernel::chrono::{DateTime, Duration, Utc};
+use kernel::impersonation::{DEFAULT_IMPERSONATION_MINUTES, IMPERSONATION_STARTED_ACTION, MAX_IMPERSONATION_MINUTES};
+use kernel::token::token::HeaderToken;
+use kernel::token::checks::NoRoleCheck;
+use kernel::token::session_cache::traits::SetAuthCacheSession;
+use kernel::token::session_cache::structs::IntoAuthCacheSession;
+use kernel::users::UserRole;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The token a super admin acts as the user with.
+///
+/// # Fields
+/// * `token` - The signed impersonation token.
+/// * `user_id` - The ID of the user being impersonated.
+/// * `role` - The role of the user, which the token is issued for.
+/// * `expires_at` - When the token expires.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ImpersonationReturnSchema {
+    pub token: String,
+    pub user_id: i32,
+    pub role: UserRole,
+    pub expires_at: DateTime<Utc>,
+}
+
+
+/// How long an impersonation token lasts, from `IMPERSONATION_MINUTES`.
+pub fn impersonation_lifetime<Y: GetConfigVariable>() -> Duration {
+    let minutes = Y::get_int("IMPERSONATION_MINUTES").ok()
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(DEFAULT_IMPERSONATION_MINUTES)
+        .min(MAX_IMPERSONATION_MINUTES);
+    Duration::minutes(minutes)
+}
+
+
+/// Issues a super admin a token to act as another user.
+///
+/// # Arguments
+/// * `impersonator_id` - The ID of the super admin.
+/// * `user_id` - The ID of the user to impersonate.
+/// * `org_id` - The ID of the organization the super admin is acting in, the token acts in it too.
+/// * `user_agent` - The user agent of the super admin, which the token is bound to.
+///
+/// # Returns
+/// * `Ok(ImpersonationReturnSchema)` - The token and when it expires.
+/// * `Err(NanoServiceError)` - `NotFound` if there is no such user, `BadRequest` if the super admin
+///   picked themselves, `Forbidden` if the user is a super admin, blocked or unconfirmed.
+pub async fn impersonate_user<X, Y, Z>(impersonator_id: i32, user_id: i32, org_id: i32, user_agent: String) -> Result<ImpersonationReturnSchema, NanoServiceError>
+where
+    X: GetUser + GetEffectivePermissions + CreateAuditEntry,
+    Y: GetConfigVariable,
+    Z: SetAuthCacheSession,
+{
+    if user_id == impersonator_id {
+        return Err(NanoServiceError::new(
+            "You cannot impersonate yourself".to_string(),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    }
+    let user = X::get_user(user_id).await?;
+    if user.user_role == UserRole::SuperAdmin {
+        return Err(NanoServiceError::new(
+            "Super admins cannot be impersonated".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    if user.blocked || !user.confirmed {
+        return Err(NanoServiceError::new(
+            "Blocked and unconfirmed users cannot be impersonated".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+
+    let token: HeaderToken<Y, NoRoleCheck> = HeaderToken::impersonate(
+        user_agent, user.id, user.user_role.clone(), impersonator_id, impersonation_lifetime::<Y>()
+    ).in_org(org_id);
+    let mut session = token.into_auth_cache_session();
+    session.permissions = X::get_effective_permissions(user.id).await?;
+    Z::set_auth_cache_session(&token, &session).await?;
+
+    // the session is audited by its key rather than the token, which would let anyone reading the log use it
+    let expires_at = token.time_expire;
+    X::create_audit_entry(NewAuditEntry {
+        actor_id: impersonator_id,
+        action: IMPERSONATION_STARTED_ACTION.to_string(),
+        subject_id: Some(user.id),
+        details: json!({"session": token.unique_id, "expires_at": expires_at}),
+    }).await?;
+    Ok(ImpersonationReturnSchema {
+        token: token.encode()?,
+        user_id: user.id,
+        role: user.user_role,
+        expires_at,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::audit_log::AuditEntry;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::users::User;
+    use sqlx::types::Json;
+    use std::sync::Mutex;
+
+    static AUDITED: Mutex<Vec<NewAuditEntry>> = Mutex::new(Vec::new());
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SECRET_KEY" => Ok("secret".to_string()),
+                "IMPERSONATION_MINUTES" => Ok("500".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+            }
+        }
+    }
+
+    /// User `2` is a worker, `3` a super admin, `4` is blocked and any other ID does not exist.
+    struct MockPostgres;
+
+    #[impl_transaction(MockPostgres, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        let role = match id {
+            2 | 4 => UserRole::Worker,
+            3 => UserRole::SuperAdmin,
+            _ => return Err(NanoServiceError::new("User not found".to_string(), NanoServiceErrorStatus::NotFound))
+        };
+        Ok(User {
+            id,
+            confirmed: true,
+            username: format!("user-{}", id),
+            email: format!("user-{}@example.com", id),
+            password: "hash".to_string(),
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            user_role: role,
+            date_created: Default::default(),
+            last_logged_in: Default::default(),
+            blocked: id == 4,
+            uuid: format!("uuid-{}", id),
+            avatar_version: None,
+        })
+    }
+
+    #[impl_transaction(MockPostgres, GetEffectivePermissions, get_effective_permissions)]
+    async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[impl_transaction(MockPostgres, CreateAuditEntry, create_audit_entry)]
+    async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        AUDITED.lock().unwrap().push(entry.clone());
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: Json(entry.details),
+            date_created: Default::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_impersonate_user() {
+        let outcome = impersonate_user::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>(1, 2, 3, "some-agent".to_string()).await.unwrap();
+        assert_eq!((outcome.user_id, &outcome.role), (2, &UserRole::Worker));
+
+        // the token is for the user, carries the super admin and is capped at the longest lifetime
+        let token = HeaderToken::<FakeConfig, NoRoleCheck>::decode(&outcome.token).unwrap();
+        assert_eq!((token.user_id, token.impersonator_id), (2, Some(1)));
+        assert_eq!(token.org_id, 3);
+        assert_eq!(token.time_expire - token.time_started, Duration::minutes(MAX_IMPERSONATION_MINUTES));
+        assert_eq!(outcome.expires_at, token.time_expire);
+
+        let audited = AUDITED.lock().unwrap().clone();
+        assert_eq!(audited.len(), 1);
+        assert_eq!((audited[0].actor_id, audited[0].action.as_str(), audited[0].subject_id), (1, IMPERSONATION_STARTED_ACTION, Some(2)));
+        assert_eq!(audited[0].details["session"], token.unique_id);
+
+        for (user_id, status) in [
+            (1, NanoServiceErrorStatus::BadRequest),
+            (3, NanoServiceErrorStatus::Forbidden),
+            (4, NanoServiceErrorStatus::Forbidden),
+            (9, NanoServiceErrorStatus::NotFound),
+        ] {
+            let error = impersonate_user::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>(1, user_id, 1, "some-agent".to_string()).await.unwrap_err();
+            assert_eq!(error.status, status, "{}", user_id);
+        }
+        assert_eq!(AUDITED.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_impersonation_lifetime() {
+        struct UnsetConfig;
+        impl GetConfigVariable for UnsetConfig {
+            fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+                Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+            }
+        }
+        assert_eq!(impersonation_lifetime::<UnsetConfig>(), Duration::minutes(DEFAULT_IMPERSONATION_MINUTES));
+        assert_eq!(impersonation_lifetime::<FakeConfig>(), Duration::minutes(MAX_IMPERSONATION_MINUTES));
+    }
+}
@@ -9,15 +9,32 @@
 //! * Retrieves user details from the database.
 //! * Verifies user passwords.
 //! * Checks if the user has the required role.
+//! * Ends the user's oldest sessions, or refuses the login, at `MAX_SESSIONS_PER_USER`.
+//! * Stores the user's effective permissions in the session cache.
+//! * Issues the token in the first organization the user belongs to, see `kernel::organizations`.
+//! * Records the device the user logged in from, see `kernel::devices`.
+//! * Records when the user last logged in.
+//! * Counts the user as active for the month's usage metering.
+//! * Sends a `login` event to the analytics sink.
 //! * Generates and returns an authentication token.
-use kernel::users::UserRole;
-use dal::users::tx_definitions::GetUserByEmail;
+use kernel::users::{User, UserRole};
+use kernel::analytics::{AnalyticsEvent, AnalyticsSink, emit};
+use dal::users::tx_definitions::{GetUserByEmail, UpdateLastLoggedIn};
 use dal::role_permissions::tx_definitions::GetRolePermissions;
+use dal::permissions::tx_definitions::GetEffectivePermissions;
+use dal::metering::tx_definitions::RecordActiveUser;
+use dal::devices::tx_definitions::RecordDeviceLogin;
+use dal::organizations::tx_definitions::GetUserOrganizations;
+use kernel::chrono::Utc;
+use kernel::devices::NewDevice;
+use kernel::organizations::DEFAULT_ORG_ID;
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 use utils::config::GetConfigVariable;
 use kernel::token::token::HeaderToken;
 use kernel::token::checks::NoRoleCheck;
-use kernel::token::session_cache::traits::SetAuthCacheSession;
+use kernel::token::session_limit::enforce_session_limit;
+use kernel::token::session_cache::traits::{SetAuthCacheSession, GetUserAuthCacheSessions, DelAuthCacheSession};
+use kernel::token::session_cache::structs::IntoAuthCacheSession;
 use serde::{Deserialize, Serialize};
 
 
@@ -27,10 +44,13 @@ use serde::{Deserialize, Serialize};
 /// # Fields
 /// * `token` - A signed authentication token representing the user's session.
 /// * `role` - The role assigned to the authenticated user.
+/// * `trusted_device` - Whether the user has marked the device logged in from as trusted.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LoginReturnSchema {
     pub token: String,
     pub role: UserRole,
+    #[serde(default)]
+    pub trusted_device: bool,
 }
 
 /// Authenticates a user by verifying credentials and generating an authentication token.
@@ -42,7 +62,7 @@ pub struct LoginReturnSchema {
 /// * `user_agent` - The user agent string from the request.
 ///
 /// # Type Parameters
-/// * `X` - A type that implements `GetUserByEmail` and `GetRolePermissions` for retrieving user data.
+/// * `X` - A type that implements `GetUserByEmail`, `GetRolePermissions`, `GetEffectivePermissions`, `UpdateLastLoggedIn`, `RecordActiveUser` and `RecordDeviceLogin` for user data, metering and devices.
 /// * `Y` - A type that implements `GetConfigVariable` for configuration handling.
 ///
 /// # Returns
@@ -52,11 +72,12 @@ pub struct LoginReturnSchema {
 /// # Errors
 /// * Returns `NanoServiceErrorStatus::Unauthorized` if the password is invalid.
 /// * Returns `NanoServiceErrorStatus::Unauthorized` if the user does not have the required role.
-pub async fn login<X, Y, Z>(email: String, password: String, role: UserRole, user_agent: String) -> Result<LoginReturnSchema, NanoServiceError> 
+pub async fn login<X, Y, Z, A>(email: String, password: String, role: UserRole, user_agent: String) -> Result<LoginReturnSchema, NanoServiceError> 
 where
-    X: GetUserByEmail + GetRolePermissions,
+    X: GetUserByEmail + GetRolePermissions + GetEffectivePermissions + UpdateLastLoggedIn + RecordActiveUser + RecordDeviceLogin + GetUserOrganizations,
     Y: GetConfigVariable,
-    Z: SetAuthCacheSession
+    Z: SetAuthCacheSession + GetUserAuthCacheSessions + DelAuthCacheSession,
+    A: AnalyticsSink
 {
     // Retrieve user information from the database
     let user = X::get_user_by_email(email).await?;
@@ -65,13 +86,13 @@ where
         return Err(NanoServiceError::new(
             "User is blocked".to_string(), 
             NanoServiceErrorStatus::Unauthorized
-        ));
+        ).with_message_key("auth.user_blocked", vec![]));
     }
-    if user.confirmed == false {
+    if !user.confirmed {
         return Err(NanoServiceError::new(
             "User is not confirmed".to_string(), 
             NanoServiceErrorStatus::Unauthorized
-        ));
+        ).with_message_key("auth.user_not_confirmed", vec![]));
     }
     
     // Verify the provided password
@@ -79,7 +100,7 @@ where
         return Err(NanoServiceError::new(
             "Invalid password".to_string(), 
             NanoServiceErrorStatus::Unauthorized
-        ));
+        ).with_message_key("auth.invalid_password", vec![]));
     }
     
     // Retrieve the roles associated with the user
@@ -90,17 +111,60 @@ where
         return Err(NanoServiceError::new(
             "User does not have the required role".to_string(), 
             NanoServiceErrorStatus::Unauthorized
-        ));
+        ).with_message_key("auth.missing_role", vec![("role", format!("{:?}", role))]));
     }
     
+    start_session::<X, Y, Z, A>(&user, role, user_agent).await
+}
+
+
+/// Issues a token for an authenticated user and starts their session.
+///
+/// # Arguments
+/// * `user` - The user, already checked to be able to log in.
+/// * `role` - The role the token is issued for.
+/// * `user_agent` - The user agent string from the request.
+///
+/// # Returns
+/// * `Ok(LoginReturnSchema)` - A signed authentication token and the role.
+/// * `Err(NanoServiceError)` - If the session could not be stored or the token signed, or a
+///   `Conflict` if the user is at their session limit and the policy is to reject, see
+///   `kernel::token::session_limit`.
+///
+/// # Notes
+/// Shared by every way of logging in, such as a password or an OpenID Connect provider. The token
+/// is issued in the organization the user joined first, or `DEFAULT_ORG_ID` if they belong to none,
+/// and the user can switch to another of their organizations afterwards.
+pub async fn start_session<X, Y, Z, A>(user: &User, role: UserRole, user_agent: String) -> Result<LoginReturnSchema, NanoServiceError>
+where
+    X: GetEffectivePermissions + UpdateLastLoggedIn + RecordActiveUser + RecordDeviceLogin + GetUserOrganizations,
+    Y: GetConfigVariable,
+    Z: SetAuthCacheSession + GetUserAuthCacheSessions + DelAuthCacheSession,
+    A: AnalyticsSink
+{
     // Generate authentication token
-    let token: HeaderToken<Y, NoRoleCheck> = HeaderToken::new(user_agent, user.id, role.clone());
+    let org_id = X::get_user_organizations(user.id).await?.first().map_or(DEFAULT_ORG_ID, |org| org.id);
+    let token: HeaderToken<Y, NoRoleCheck> = HeaderToken::new(user_agent, user.id, role.clone()).in_org(org_id);
     
-    // save to the cache session
-    let _ = Z::set_auth_cache_session(&token, &token).await?;
+    // the oldest sessions are ended, or the login refused, if the user is at their session limit
+    enforce_session_limit::<Y, Z>(user.id).await?;
+
+    // save to the cache session with the permissions granted to the user's roles
+    let mut session = token.into_auth_cache_session();
+    session.permissions = X::get_effective_permissions(user.id).await?;
+    Z::set_auth_cache_session(&token, &session).await?;
+
+    // the session is tied to the device through the fingerprint of the user agent
+    let device = X::record_device_login(NewDevice::new(user.id, &token.user_agent)).await?;
+
+    // admins audit dormant accounts by the last login, and this month's active users are billed
+    X::update_last_logged_in(user.id).await?;
+    X::record_active_user(user.id).await?;
+    emit::<A, Y>(AnalyticsEvent::login(user)).await;
     Ok(LoginReturnSchema { 
         token: token.encode()?,
-        role: role
+        role,
+        trusted_device: device.is_trusted(Utc::now().naive_utc())
     })
 }
 
@@ -111,10 +175,14 @@ mod tests {
     use super::*;
     use kernel::users::{User, NewUser};
     use kernel::role_permissions::RolePermission;
+    use kernel::devices::Device;
+    use kernel::organizations::{OrgRole, UserOrganization};
     use dal_tx_impl::impl_transaction;
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::LazyLock;
     use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::analytics::ProductEvent;
+    use kernel::analytics::engine_mock::{RecordAnalyticsMock, RECORDED_EVENTS};
 
     fn generate_user(password: String, user_role: UserRole) -> User {
         let new_user = NewUser::new(
@@ -138,6 +206,7 @@ mod tests {
             last_logged_in: new_user.last_logged_in,
             blocked: new_user.blocked,
             uuid: new_user.uuid,
+            avatar_version: None,
         }
     }
 
@@ -161,18 +230,60 @@ mod tests {
                 role: UserRole::Admin,
             }])
         }
+        #[impl_transaction(MockPostgres, GetEffectivePermissions, get_effective_permissions)]
+        async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+            Ok(vec!["todo:assign".to_string()])
+        }
+        #[impl_transaction(MockPostgres, RecordActiveUser, record_active_user)]
+        async fn record_active_user(user_id: i32) -> Result<(), NanoServiceError> {
+            assert_eq!(user_id, 1);
+            Ok(())
+        }
+
+        #[impl_transaction(MockPostgres, GetUserOrganizations, get_user_organizations)]
+        async fn get_user_organizations(user_id: i32) -> Result<Vec<UserOrganization>, NanoServiceError> {
+            assert_eq!(user_id, 1);
+            Ok(vec![UserOrganization { id: 3, name: "Acme".to_string(), org_role: OrgRole::Member }])
+        }
+        #[impl_transaction(MockPostgres, RecordDeviceLogin, record_device_login)]
+        async fn record_device_login(device: NewDevice) -> Result<Device, NanoServiceError> {
+            let now = Utc::now().naive_utc();
+            Ok(Device {
+                id: 1,
+                user_id: device.user_id,
+                fingerprint: device.fingerprint,
+                name: None,
+                user_agent: device.user_agent,
+                trusted_until: Some(now + kernel::chrono::Duration::days(1)),
+                date_created: now,
+                last_seen_at: now,
+            })
+        }
+        #[impl_transaction(MockPostgres, UpdateLastLoggedIn, update_last_logged_in)]
+        async fn update_last_logged_in(user_id: i32) -> Result<bool, NanoServiceError> {
+            assert_eq!(user_id, 1);
+            Ok(true)
+        }
         impl GetConfigVariable for MockConfig {
             fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
                 Ok("secret".to_string())
             }
         }
 
-        let _ = login::<MockPostgres, MockConfig, PassAuthSessionCheckMock>(
+        let outcome = login::<MockPostgres, MockConfig, PassAuthSessionCheckMock, RecordAnalyticsMock>(
             "test@gmail.com".to_string(),
             "password".to_string(),
             UserRole::Admin,
             "some-agent".to_string()
         ).await.unwrap();
+        assert!(outcome.trusted_device);
+        let token = HeaderToken::<MockConfig, NoRoleCheck>::decode(&outcome.token).unwrap();
+        assert_eq!(token.org_id, 3);
+        let logins: Vec<_> = RECORDED_EVENTS.lock().unwrap().iter()
+            .filter(|event| event.event == ProductEvent::Login)
+            .map(|event| (event.user_id, serde_json::Value::Object(event.properties.clone())))
+            .collect();
+        assert_eq!(logins, vec![(1, serde_json::json!({"role": "Admin"}))]);
     }
 
     #[tokio::test]
@@ -201,13 +312,35 @@ mod tests {
                 role: UserRole::Admin,
             }])
         }
+        #[impl_transaction(MockPostgres, GetEffectivePermissions, get_effective_permissions)]
+        async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+            Ok(vec!["todo:assign".to_string()])
+        }
+        #[impl_transaction(MockPostgres, RecordActiveUser, record_active_user)]
+        async fn record_active_user(user_id: i32) -> Result<(), NanoServiceError> {
+            assert_eq!(user_id, 1);
+            Ok(())
+        }
+        #[impl_transaction(MockPostgres, GetUserOrganizations, get_user_organizations)]
+        async fn get_user_organizations(_user_id: i32) -> Result<Vec<kernel::organizations::UserOrganization>, NanoServiceError> {
+            Ok(vec![])
+        }
+        #[impl_transaction(MockPostgres, RecordDeviceLogin, record_device_login)]
+        async fn record_device_login(_device: NewDevice) -> Result<Device, NanoServiceError> {
+            Err(NanoServiceError::new("the login fails before the device is recorded".to_string(), NanoServiceErrorStatus::Unknown))
+        }
+        #[impl_transaction(MockPostgres, UpdateLastLoggedIn, update_last_logged_in)]
+        async fn update_last_logged_in(user_id: i32) -> Result<bool, NanoServiceError> {
+            assert_eq!(user_id, 1);
+            Ok(true)
+        }
         impl GetConfigVariable for MockConfig {
             fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
                 Ok("secret".to_string())
             }
         }
 
-        let result = login::<MockPostgres, MockConfig, PassAuthSessionCheckMock>(
+        let result = login::<MockPostgres, MockConfig, PassAuthSessionCheckMock, RecordAnalyticsMock>(
             "test@gmail.com".to_string(),
             "password".to_string(),
             UserRole::Admin,
@@ -248,13 +381,35 @@ mod tests {
                 role: UserRole::Worker,
             }])
         }
+        #[impl_transaction(MockPostgres, GetEffectivePermissions, get_effective_permissions)]
+        async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+            Ok(vec!["todo:assign".to_string()])
+        }
+        #[impl_transaction(MockPostgres, RecordActiveUser, record_active_user)]
+        async fn record_active_user(user_id: i32) -> Result<(), NanoServiceError> {
+            assert_eq!(user_id, 1);
+            Ok(())
+        }
+        #[impl_transaction(MockPostgres, GetUserOrganizations, get_user_organizations)]
+        async fn get_user_organizations(_user_id: i32) -> Result<Vec<kernel::organizations::UserOrganization>, NanoServiceError> {
+            Ok(vec![])
+        }
+        #[impl_transaction(MockPostgres, RecordDeviceLogin, record_device_login)]
+        async fn record_device_login(_device: NewDevice) -> Result<Device, NanoServiceError> {
+            Err(NanoServiceError::new("the login fails before the device is recorded".to_string(), NanoServiceErrorStatus::Unknown))
+        }
+        #[impl_transaction(MockPostgres, UpdateLastLoggedIn, update_last_logged_in)]
+        async fn update_last_logged_in(user_id: i32) -> Result<bool, NanoServiceError> {
+            assert_eq!(user_id, 1);
+            Ok(true)
+        }
         impl GetConfigVariable for MockConfig {
             fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
                 Ok("secret".to_string())
             }
         }
 
-        let result = login::<MockPostgres, MockConfig, PassAuthSessionCheckMock>(
+        let result = login::<MockPostgres, MockConfig, PassAuthSessionCheckMock, RecordAnalyticsMock>(
             "test@gmail.com".to_string(),
             "password".to_string(),
             UserRole::Admin,
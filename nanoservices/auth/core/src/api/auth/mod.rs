@@ -2,3 +2,7 @@ pub mod login;
 pub mod request_password_reset;
 pub mod resend_confirmation_email;
 pub mod refresh;
+pub mod impersonate;
+pub mod oidc;
+#[cfg(feature = "saml")]
+pub mod saml;
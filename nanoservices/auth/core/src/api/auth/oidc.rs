@@ -0,0 +1,455 @@
+//! Core logic for logging in with an OpenID Connect provider such as Google.
+//!
+//! # Overview
+//! `start_oidc_login` gives the URL to send the user to, carrying a signed `state`. Once they have
+//! logged in at the provider, `complete_oidc_login` exchanges the code sent back for an ID token and
+//! finds the user the provider's subject is linked to. On the first login the subject is linked to
+//! the user with the same email, if the provider has verified it, or a new confirmed user is
+//! provisioned with `OIDC_DEFAULT_ROLE`, within the plan's user limit. The user is then issued the
+//! same token as a password login, for their own role. See `kernel::oidc` for the config.
+use dal::federated_identities::tx_definitions::{GetFederatedIdentity, LinkFederatedIdentity, ProvisionFederatedUser};
+use dal::users::tx_definitions::{GetUser, GetUserByEmail, UpdateLastLoggedIn, CountUsers};
+use dal::permissions::tx_definitions::GetEffectivePermissions;
+use dal::metering::tx_definitions::RecordActiveUser;
+use dal::devices::tx_definitions::RecordDeviceLogin;
+use dal::organizations::tx_definitions::GetUserOrganizations;
+use dal::plans::tx_definitions::GetOrgPlan;
+use kernel::analytics::AnalyticsSink;
+use kernel::oidc::{issue_state, verify_state, FederatedSubject, IdTokenClaims, OidcClient, OidcProvider, OidcSettings};
+use kernel::token::session_cache::traits::{SetAuthCacheSession, GetUserAuthCacheSessions, DelAuthCacheSession};
+use kernel::users::{NewUser, User, UserRole};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::api::auth::login::{start_session, LoginReturnSchema};
+
+
+/// The longest part of the email kept in a provisioned username, leaving room for the suffix.
+const USERNAME_BASE_LENGTH: usize = 24;
+
+/// The length of the random suffix that keeps provisioned usernames unique.
+const USERNAME_SUFFIX_LENGTH: usize = 6;
+
+/// The length of the random password given to provisioned users, who can reset it to log in with one.
+const PASSWORD_LENGTH: usize = 32;
+
+
+/// Where to send the user to log in at the provider.
+///
+/// # Fields
+/// * `url` - The provider's login page, carrying the signed `state`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OidcRedirect {
+    pub url: String,
+}
+
+
+/// Starts a login with a provider.
+///
+/// # Returns
+/// * `Ok(OidcRedirect)` - Where to send the user
+/// * `Err(NanoServiceError)` - `NotFound` if the provider is not configured
+pub async fn start_oidc_login<Y: GetConfigVariable>(provider: OidcProvider) -> Result<OidcRedirect, NanoServiceError> {
+    let settings = OidcSettings::from_config::<Y>(provider)?;
+    let (state, nonce) = issue_state::<Y>(provider)?;
+    Ok(OidcRedirect { url: settings.authorization_url(&state, &nonce)? })
+}
+
+
+/// A username for a provisioned user, from the start of their email and a random suffix.
+fn provisioned_username(email: &str) -> String {
+    let base: String = email.split('@').next().unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        .take(USERNAME_BASE_LENGTH)
+        .collect();
+    let suffix: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(USERNAME_SUFFIX_LENGTH)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    match base.is_empty() {
+        true => format!("user-{}", suffix),
+        false => format!("{}-{}", base, suffix)
+    }
+}
+
+
+/// A user vouched for by a provider, see `federated_login`.
+///
+/// # Fields
+/// * `subject` - The provider's ID for the user and the email it gave.
+/// * `email_verified` - If the provider has verified the email, needed to link or provision a user.
+/// * `first_name` - The first name a provisioned user is given.
+/// * `last_name` - The last name a provisioned user is given.
+/// * `role` - The role a provisioned user is given.
+pub(crate) struct FederatedProfile {
+    pub subject: FederatedSubject,
+    pub email_verified: bool,
+    pub first_name: String,
+    pub last_name: String,
+    pub role: UserRole,
+}
+
+
+/// The user for a new subject, linking the user with the same email or provisioning one.
+async fn link_or_provision<X>(profile: FederatedProfile) -> Result<User, NanoServiceError>
+where
+    X: GetUserByEmail + LinkFederatedIdentity + ProvisionFederatedUser + GetOrgPlan + CountUsers
+{
+    if !profile.email_verified {
+        return Err(NanoServiceError::new(
+            format!("The email of the {} account is not verified", profile.subject.provider),
+            NanoServiceErrorStatus::Unauthorized
+        ))
+    }
+    let subject = profile.subject;
+    match X::get_user_by_email(subject.email.clone()).await {
+        Ok(user) => {
+            X::link_federated_identity(user.id, subject).await?;
+            Ok(user)
+        },
+        Err(e) if e.status == NanoServiceErrorStatus::NotFound => {
+            X::get_org_plan().await?.entitlements().check_max_users(X::count_users().await?)?;
+            let password: String = rand::thread_rng()
+                .sample_iter(&rand::distributions::Alphanumeric)
+                .take(PASSWORD_LENGTH)
+                .map(char::from)
+                .collect();
+            let mut new_user = NewUser::new(
+                provisioned_username(&subject.email),
+                subject.email.clone(),
+                profile.first_name,
+                profile.last_name,
+                profile.role,
+                password
+            )?;
+            // the provider has verified the email, so there is nothing left to confirm
+            new_user.confirmed = true;
+            X::provision_federated_user(new_user, subject).await
+        },
+        Err(e) => Err(e)
+    }
+}
+
+
+/// Logs in the user linked to the subject a provider has vouched for, linking or provisioning
+/// one on the first login.
+///
+/// # Arguments
+/// * `profile` - The user the provider has vouched for.
+/// * `user_agent` - The user agent string from the request.
+///
+/// # Returns
+/// * `Ok(LoginReturnSchema)` - A signed authentication token and the user's role
+/// * `Err(NanoServiceError)` - `Unauthorized` if the provider has not verified the email of a new
+///   subject or the user is blocked or not confirmed, `UpgradeRequired` if a user would be
+///   provisioned past the plan's user limit
+pub(crate) async fn federated_login<X, Y, Z, A>(profile: FederatedProfile, user_agent: String) -> Result<LoginReturnSchema, NanoServiceError>
+where
+    X: GetFederatedIdentity + LinkFederatedIdentity + ProvisionFederatedUser + GetUser + GetUserByEmail
+        + GetOrgPlan + CountUsers + GetEffectivePermissions + UpdateLastLoggedIn + RecordActiveUser + RecordDeviceLogin + GetUserOrganizations,
+    Y: GetConfigVariable,
+    Z: SetAuthCacheSession + GetUserAuthCacheSessions + DelAuthCacheSession,
+    A: AnalyticsSink,
+{
+    let identity = X::get_federated_identity(profile.subject.provider.clone(), profile.subject.subject.clone()).await?;
+    let user = match identity {
+        Some(identity) => X::get_user(identity.user_id).await?,
+        None => link_or_provision::<X>(profile).await?
+    };
+    if user.blocked {
+        return Err(NanoServiceError::new(
+            "User is blocked".to_string(),
+            NanoServiceErrorStatus::Unauthorized
+        ).with_message_key("auth.user_blocked", vec![]));
+    }
+    if !user.confirmed {
+        return Err(NanoServiceError::new(
+            "User is not confirmed".to_string(),
+            NanoServiceErrorStatus::Unauthorized
+        ).with_message_key("auth.user_not_confirmed", vec![]));
+    }
+    let role = user.user_role.clone();
+    start_session::<X, Y, Z, A>(&user, role, user_agent).await
+}
+
+
+/// Completes a login with a provider.
+///
+/// # Arguments
+/// * `provider` - The provider the user logged in at.
+/// * `code` - The code the provider sent back.
+/// * `state` - The `state` the provider sent back.
+/// * `user_agent` - The user agent string from the request.
+///
+/// # Returns
+/// * `Ok(LoginReturnSchema)` - A signed authentication token and the user's role
+/// * `Err(NanoServiceError)` - `Unauthorized` if the state or ID token is not valid, and as for
+///   `federated_login`
+pub async fn complete_oidc_login<X, Y, Z, A, C>(
+    provider: OidcProvider,
+    code: String,
+    state: String,
+    user_agent: String
+) -> Result<LoginReturnSchema, NanoServiceError>
+where
+    X: GetFederatedIdentity + LinkFederatedIdentity + ProvisionFederatedUser + GetUser + GetUserByEmail
+        + GetOrgPlan + CountUsers + GetEffectivePermissions + UpdateLastLoggedIn + RecordActiveUser + RecordDeviceLogin + GetUserOrganizations,
+    Y: GetConfigVariable,
+    Z: SetAuthCacheSession + GetUserAuthCacheSessions + DelAuthCacheSession,
+    A: AnalyticsSink,
+    C: OidcClient,
+{
+    let settings = OidcSettings::from_config::<Y>(provider)?;
+    let nonce = verify_state::<Y>(&state, provider)?;
+    let id_token = C::exchange_code(&settings, &code).await?;
+    let claims = IdTokenClaims::from_id_token(&id_token, &settings, &nonce)?;
+    let profile = FederatedProfile {
+        subject: FederatedSubject::from_claims(provider, &claims),
+        email_verified: claims.email_verified,
+        first_name: claims.given_name.unwrap_or_default(),
+        last_name: claims.family_name.unwrap_or_default(),
+        role: settings.default_role,
+    };
+    federated_login::<X, Y, Z, A>(profile, user_agent).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::analytics::engine_mock::RecordAnalyticsMock;
+    use kernel::chrono::{Duration, Utc};
+    use kernel::devices::{Device, NewDevice};
+    use kernel::oidc::FederatedIdentity;
+    use kernel::oidc::engine_mock::{unsigned_id_token, CodeIsIdTokenMock};
+    use kernel::plans::{OrgPlan, Plan};
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::token::HeaderToken;
+    use std::sync::Mutex;
+
+    /// The subjects linked and the users provisioned, as `(user_id, subject)`.
+    static LINKED: Mutex<Vec<(i32, FederatedSubject)>> = Mutex::new(Vec::new());
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "GOOGLE_OIDC_CLIENT_ID" => Ok("client-id".to_string()),
+                "OIDC_DEFAULT_ROLE" => Ok("admin".to_string()),
+                "JWT_ALGORITHM" => Err(NanoServiceError::new("not set".to_string(), NanoServiceErrorStatus::Unknown)),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    fn user(id: i32, email: &str) -> User {
+        let now = Utc::now().naive_utc();
+        User {
+            id,
+            confirmed: true,
+            username: format!("user{}", id),
+            email: email.to_string(),
+            password: "password".to_string(),
+            first_name: "Test".to_string(),
+            last_name: "User".to_string(),
+            user_role: UserRole::Worker,
+            date_created: now,
+            last_logged_in: now,
+            blocked: id == 4,
+            uuid: format!("uuid-{}", id),
+            avatar_version: None,
+        }
+    }
+
+    struct MockDbHandle;
+
+    /// `linked-subject` is linked to user 2 and `blocked-subject` to the blocked user 4.
+    #[impl_transaction(MockDbHandle, GetFederatedIdentity, get_federated_identity)]
+    async fn get_federated_identity(provider: String, subject: String) -> Result<Option<FederatedIdentity>, NanoServiceError> {
+        assert_eq!(provider, "google");
+        let user_id = match subject.as_str() {
+            "linked-subject" => 2,
+            "blocked-subject" => 4,
+            _ => return Ok(None)
+        };
+        Ok(Some(FederatedIdentity {
+            id: 1,
+            user_id,
+            provider,
+            subject,
+            email: "linked@example.com".to_string(),
+            date_created: Utc::now().naive_utc(),
+        }))
+    }
+
+    #[impl_transaction(MockDbHandle, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        Ok(user(id, "linked@example.com"))
+    }
+
+    /// Only `existing@example.com` has an account.
+    #[impl_transaction(MockDbHandle, GetUserByEmail, get_user_by_email)]
+    async fn get_user_by_email(email: String) -> Result<User, NanoServiceError> {
+        match email.as_str() {
+            "existing@example.com" => Ok(user(3, &email)),
+            _ => Err(NanoServiceError::new("User not found".to_string(), NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, LinkFederatedIdentity, link_federated_identity)]
+    async fn link_federated_identity(user_id: i32, subject: FederatedSubject) -> Result<FederatedIdentity, NanoServiceError> {
+        LINKED.lock().unwrap().push((user_id, subject.clone()));
+        Ok(FederatedIdentity {
+            id: 2,
+            user_id,
+            provider: subject.provider,
+            subject: subject.subject,
+            email: subject.email,
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, ProvisionFederatedUser, provision_federated_user)]
+    async fn provision_federated_user(new_user: NewUser, subject: FederatedSubject) -> Result<User, NanoServiceError> {
+        assert!(new_user.confirmed);
+        assert_eq!(new_user.user_role, UserRole::Admin);
+        assert!(new_user.username.starts_with("new-"));
+        assert_eq!(new_user.first_name, "Ada");
+        LINKED.lock().unwrap().push((9, subject));
+        Ok(User { user_role: new_user.user_role, ..user(9, &new_user.email) })
+    }
+
+    #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(OrgPlan { plan: Plan::Pro, max_users: None, max_todos: None, mfa: None, api_access: None, date_updated: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, CountUsers, count_users)]
+    async fn count_users() -> Result<i64, NanoServiceError> {
+        Ok(3)
+    }
+
+    #[impl_transaction(MockDbHandle, GetEffectivePermissions, get_effective_permissions)]
+    async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[impl_transaction(MockDbHandle, UpdateLastLoggedIn, update_last_logged_in)]
+    async fn update_last_logged_in(_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(true)
+    }
+
+    #[impl_transaction(MockDbHandle, RecordActiveUser, record_active_user)]
+    async fn record_active_user(_user_id: i32) -> Result<(), NanoServiceError> {
+        Ok(())
+    }
+
+    #[impl_transaction(MockDbHandle, GetUserOrganizations, get_user_organizations)]
+    async fn get_user_organizations(_user_id: i32) -> Result<Vec<kernel::organizations::UserOrganization>, NanoServiceError> {
+        Ok(vec![])
+    }
+    #[impl_transaction(MockDbHandle, RecordDeviceLogin, record_device_login)]
+    async fn record_device_login(device: NewDevice) -> Result<Device, NanoServiceError> {
+        let now = Utc::now().naive_utc();
+        Ok(Device {
+            id: 1,
+            user_id: device.user_id,
+            fingerprint: device.fingerprint,
+            name: None,
+            user_agent: device.user_agent,
+            trusted_until: None,
+            date_created: now,
+            last_seen_at: now,
+        })
+    }
+
+    /// Completes a login for the subject, with the ID token as the code.
+    async fn complete(subject: &str, email: &str, email_verified: bool) -> Result<LoginReturnSchema, NanoServiceError> {
+        let (state, nonce) = issue_state::<MockConfig>(OidcProvider::Google).unwrap();
+        let id_token = unsigned_id_token(&serde_json::json!({
+            "iss": "https://accounts.google.com",
+            "aud": "client-id",
+            "sub": subject,
+            "email": email,
+            "email_verified": email_verified,
+            "given_name": "Ada",
+            "nonce": nonce,
+            "exp": (Utc::now() + Duration::minutes(5)).timestamp(),
+        }));
+        complete_oidc_login::<MockDbHandle, MockConfig, PassAuthSessionCheckMock, RecordAnalyticsMock, CodeIsIdTokenMock>(
+            OidcProvider::Google, id_token, state, "some-agent".to_string()
+        ).await
+    }
+
+    fn linked(subject: &str) -> Vec<i32> {
+        LINKED.lock().unwrap().iter().filter(|(_, linked)| linked.subject == subject).map(|(user_id, _)| *user_id).collect()
+    }
+
+    #[test]
+    fn test_provisioned_username() {
+        let username = provisioned_username("Ada.Lovelace+work@example.com");
+        assert!(username.starts_with("Ada.Lovelacework-"));
+        assert_eq!(username.len(), "Ada.Lovelacework-".len() + USERNAME_SUFFIX_LENGTH);
+        assert!(provisioned_username(&format!("{}@example.com", "a".repeat(60))).len() <= 32);
+        assert!(provisioned_username("@example.com").starts_with("user-"));
+    }
+
+    #[tokio::test]
+    async fn test_start_oidc_login() {
+        let redirect = start_oidc_login::<MockConfig>(OidcProvider::Google).await.unwrap();
+        assert!(redirect.url.starts_with("https://accounts.google.com/o/oauth2/v2/auth?"));
+        assert!(redirect.url.contains("client_id=client-id"));
+    }
+
+    #[tokio::test]
+    async fn test_linked_subject_logs_in() {
+        let outcome = complete("linked-subject", "linked@example.com", true).await.unwrap();
+        let token = HeaderToken::<MockConfig, NoRoleCheck>::decode(&outcome.token).unwrap();
+        assert_eq!(token.user_id, 2);
+        assert_eq!(outcome.role, UserRole::Worker);
+        assert!(linked("linked-subject").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_new_subject_is_linked_to_the_user_with_the_email() {
+        let outcome = complete("existing-subject", "existing@example.com", true).await.unwrap();
+        assert_eq!(HeaderToken::<MockConfig, NoRoleCheck>::decode(&outcome.token).unwrap().user_id, 3);
+        assert_eq!(linked("existing-subject"), vec![3]);
+
+        // an email the provider has not verified could belong to someone else
+        let error = complete("unverified-subject", "existing@example.com", false).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Unauthorized);
+        assert!(linked("unverified-subject").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_new_subject_is_provisioned() {
+        let outcome = complete("new-subject", "new@example.com", true).await.unwrap();
+        assert_eq!(HeaderToken::<MockConfig, NoRoleCheck>::decode(&outcome.token).unwrap().user_id, 9);
+        assert_eq!(outcome.role, UserRole::Admin);
+        assert_eq!(linked("new-subject"), vec![9]);
+    }
+
+    #[tokio::test]
+    async fn test_refused_logins() {
+        let blocked = complete("blocked-subject", "linked@example.com", true).await.unwrap_err();
+        assert_eq!(blocked.status, NanoServiceErrorStatus::Unauthorized);
+
+        let forged = complete_oidc_login::<MockDbHandle, MockConfig, PassAuthSessionCheckMock, RecordAnalyticsMock, CodeIsIdTokenMock>(
+            OidcProvider::Google, "code".to_string(), "forged-state".to_string(), "some-agent".to_string()
+        ).await.unwrap_err();
+        assert_eq!(forged.status, NanoServiceErrorStatus::Unauthorized);
+
+        let (state, _) = issue_state::<MockConfig>(OidcProvider::Google).unwrap();
+        let refused = complete_oidc_login::<MockDbHandle, MockConfig, PassAuthSessionCheckMock, RecordAnalyticsMock, CodeIsIdTokenMock>(
+            OidcProvider::Google, "refused".to_string(), state, "some-agent".to_string()
+        ).await.unwrap_err();
+        assert_eq!(refused.status, NanoServiceErrorStatus::Unauthorized);
+    }
+}
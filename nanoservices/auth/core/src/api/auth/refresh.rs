@@ -1,11 +1,14 @@
 use kernel::users::UserRole;
 use dal::users::tx_definitions::GetUserByUuid;
 use dal::role_permissions::tx_definitions::GetRolePermissions;
+use dal::permissions::tx_definitions::GetEffectivePermissions;
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 use utils::config::GetConfigVariable;
 use kernel::token::token::HeaderToken;
 use kernel::token::checks::NoRoleCheck;
+use kernel::token::lifetimes::TokenLifetimes;
 use kernel::token::session_cache::traits::{SetAuthCacheSession, DelAuthCacheSession};
+use kernel::token::session_cache::structs::IntoAuthCacheSession;
 use serde::{Deserialize, Serialize};
 
 
@@ -17,9 +20,11 @@ pub struct LoginReturnSchema {
 
 
 
-pub async fn refresh_token<X, Y, Z>(uuid: String, role: UserRole, user_agent: String) -> Result<LoginReturnSchema, NanoServiceError> 
+/// Issues a new token for a session, in the organization the old token was acting in and valid for
+/// `TOKEN_REFRESH_TTL`, see `kernel::token::lifetimes`.
+pub async fn refresh_token<X, Y, Z>(uuid: String, role: UserRole, org_id: i32, user_agent: String) -> Result<LoginReturnSchema, NanoServiceError> 
 where
-    X: GetUserByUuid + GetRolePermissions,
+    X: GetUserByUuid + GetRolePermissions + GetEffectivePermissions,
     Y: GetConfigVariable,
     Z: SetAuthCacheSession + DelAuthCacheSession
 {
@@ -32,7 +37,7 @@ where
             NanoServiceErrorStatus::Unauthorized
         ));
     }
-    if user.confirmed == false {
+    if !user.confirmed {
         return Err(NanoServiceError::new(
             "User is not confirmed".to_string(), 
             NanoServiceErrorStatus::Unauthorized
@@ -51,13 +56,17 @@ where
     }
     
     // Generate authentication token
-    let token: HeaderToken<Y, NoRoleCheck> = HeaderToken::new(user_agent, user.id, role.clone());
+    let token: HeaderToken<Y, NoRoleCheck> = HeaderToken::new(user_agent, user.id, role.clone())
+        .in_org(org_id)
+        .with_lifetime(TokenLifetimes::from_config::<Y>().refresh_ttl);
     
     // save to the cache session
-    let _ = Z::del_auth_cache_session(uuid).await?;
-    let _ = Z::set_auth_cache_session(&token, &token).await?;
+    Z::del_auth_cache_session(uuid).await?;
+    let mut session = token.into_auth_cache_session();
+    session.permissions = X::get_effective_permissions(user.id).await?;
+    Z::set_auth_cache_session(&token, &session).await?;
     Ok(LoginReturnSchema { 
         token: token.encode()?,
-        role: role
+        role
     })
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 //! Core logic for resending a confirmation email
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
-use dal::users::tx_definitions::UpdateUuid;
+use dal::users::tx_definitions::{UpdateUuid, GetRecipientProfile};
 use dal::rate_limit_entries::tx_definitions::{
     CreateRateLimitEntry,
     UpdateRateLimitEntry,
@@ -17,14 +17,14 @@ use email_core::api::mailchimp_emails::confirmation_email::send_confirmation_ema
 /// * `email` - The email of the user.
 pub async fn resend_confirmation_email<X, Y, Z>(email: String) -> Result<(), NanoServiceError> 
 where
-    X: CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry + UpdateUuid,
+    X: CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry + GetRecipientProfile + UpdateUuid,
     Y: SendTemplate,
     Z: GetConfigVariable,
 {
     let new_uuid = uuid::Uuid::new_v4().to_string();
     match X::update_uuid(email.clone(), new_uuid.clone()).await {
         Ok(outcome) => {
-            if outcome == false {
+            if !outcome {
                 return Err(NanoServiceError::new("Failed to update users uuid".to_string(), NanoServiceErrorStatus::Unknown));
             }
         },
@@ -35,7 +35,7 @@ where
 
     match send_confirmation_email::<X, Y, Z>(email.clone(), new_uuid.clone()).await {
         Ok(outcome) => {
-            if outcome == false {
+            if !outcome {
                 return Err(NanoServiceError::new("Failed to resend confirmation email due to a rate limit error".to_string(), NanoServiceErrorStatus::Unknown))
             }
             Ok(())
@@ -48,6 +48,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kernel::users::RecipientProfile;
     use dal_tx_impl::impl_transaction;
     use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
     use std::sync::atomic::{AtomicBool, Ordering};
@@ -114,6 +115,11 @@ mod tests {
         })
     }
 
+    #[impl_transaction(MockDbHandleSuccess, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(_email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        Ok(None)
+    }
+
     #[impl_transaction(MockDbHandleSuccess, GetRateLimitEntry, get_rate_limit_entry)]
     async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
         GET_RATE_LIMIT_CALLED.store(true, Ordering::Relaxed);
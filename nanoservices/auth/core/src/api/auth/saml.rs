@@ -0,0 +1,252 @@
+//! Core logic for single sign-on with a SAML identity provider, built with the `saml` feature.
+//!
+//! # Overview
+//! The IdP is set up with the metadata from `saml_metadata` and posts its response to the ACS, which
+//! `complete_saml_login` checks with a `SamlAssertionValidator`. The user is found, linked or
+//! provisioned as for OpenID Connect logins, the IdP vouching for the email, and new users get the
+//! role their groups map to. See `kernel::saml` for the config.
+use dal::federated_identities::tx_definitions::{GetFederatedIdentity, LinkFederatedIdentity, ProvisionFederatedUser};
+use dal::users::tx_definitions::{GetUser, GetUserByEmail, UpdateLastLoggedIn, CountUsers};
+use dal::permissions::tx_definitions::GetEffectivePermissions;
+use dal::metering::tx_definitions::RecordActiveUser;
+use dal::devices::tx_definitions::RecordDeviceLogin;
+use dal::organizations::tx_definitions::GetUserOrganizations;
+use dal::plans::tx_definitions::GetOrgPlan;
+use kernel::analytics::AnalyticsSink;
+use kernel::saml::{sp_metadata, SamlAssertionValidator, SamlSettings};
+use kernel::token::session_cache::traits::{SetAuthCacheSession, GetUserAuthCacheSessions, DelAuthCacheSession};
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::api::auth::login::LoginReturnSchema;
+use crate::api::auth::oidc::{federated_login, FederatedProfile};
+
+
+/// The SP metadata to set the IdP up with.
+///
+/// # Returns
+/// * `Ok(String)` - The metadata XML
+/// * `Err(NanoServiceError)` - `NotFound` if SAML is not configured
+pub async fn saml_metadata<Y: GetConfigVariable>() -> Result<String, NanoServiceError> {
+    Ok(sp_metadata(&SamlSettings::from_config::<Y>()?))
+}
+
+
+/// Completes a login with the response the IdP posted.
+///
+/// # Arguments
+/// * `saml_response` - The base64 encoded `SAMLResponse` form field.
+/// * `user_agent` - The user agent string from the request.
+///
+/// # Returns
+/// * `Ok(LoginReturnSchema)` - A signed authentication token and the user's role
+/// * `Err(NanoServiceError)` - `Unauthorized` if the response is not valid or has no email, or the
+///   user is blocked or not confirmed, `UpgradeRequired` if a user would be provisioned past the
+///   plan's user limit
+pub async fn complete_saml_login<X, Y, Z, A, V>(saml_response: String, user_agent: String) -> Result<LoginReturnSchema, NanoServiceError>
+where
+    X: GetFederatedIdentity + LinkFederatedIdentity + ProvisionFederatedUser + GetUser + GetUserByEmail
+        + GetOrgPlan + CountUsers + GetEffectivePermissions + UpdateLastLoggedIn + RecordActiveUser + RecordDeviceLogin + GetUserOrganizations,
+    Y: GetConfigVariable,
+    Z: SetAuthCacheSession + GetUserAuthCacheSessions + DelAuthCacheSession,
+    A: AnalyticsSink,
+    V: SamlAssertionValidator,
+{
+    let settings = SamlSettings::from_config::<Y>()?;
+    let assertion = V::validate_response(&settings, &saml_response)?;
+    let name = |attribute: &Option<String>| attribute.as_deref()
+        .and_then(|attribute| assertion.attribute(attribute))
+        .unwrap_or_default()
+        .to_string();
+    let profile = FederatedProfile {
+        subject: assertion.subject(&settings)?,
+        email_verified: true,
+        first_name: name(&settings.first_name_attribute),
+        last_name: name(&settings.last_name_attribute),
+        role: assertion.role(&settings),
+    };
+    federated_login::<X, Y, Z, A>(profile, user_agent).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::analytics::engine_mock::RecordAnalyticsMock;
+    use kernel::chrono::Utc;
+    use kernel::devices::{Device, NewDevice};
+    use kernel::oidc::{FederatedIdentity, FederatedSubject};
+    use kernel::plans::{OrgPlan, Plan};
+    use kernel::saml::SAML_PROVIDER;
+    use kernel::saml::engine_mock::JsonAssertionMock;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::token::HeaderToken;
+    use kernel::users::{NewUser, User, UserRole};
+    use std::sync::Mutex;
+    use utils::errors::NanoServiceErrorStatus;
+
+    /// The users provisioned.
+    static PROVISIONED: Mutex<Vec<NewUser>> = Mutex::new(Vec::new());
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "SAML_FIRST_NAME_ATTRIBUTE" => Ok("givenName".to_string()),
+                "SAML_LAST_NAME_ATTRIBUTE" => Ok("sn".to_string()),
+                "SAML_ROLE_ATTRIBUTE" => Ok("groups".to_string()),
+                "SAML_ADMIN_VALUES" => Ok("admins".to_string()),
+                "SAML_EMAIL_ATTRIBUTE" | "SAML_DEFAULT_ROLE" | "JWT_ALGORITHM" => Err(
+                    NanoServiceError::new("not set".to_string(), NanoServiceErrorStatus::Unknown)
+                ),
+                _ => Ok("secret".to_string())
+            }
+        }
+    }
+
+    fn user(id: i32, email: &str, user_role: UserRole) -> User {
+        let now = Utc::now().naive_utc();
+        User {
+            id,
+            confirmed: true,
+            username: format!("user{}", id),
+            email: email.to_string(),
+            password: "password".to_string(),
+            first_name: "Test".to_string(),
+            last_name: "User".to_string(),
+            user_role,
+            date_created: now,
+            last_logged_in: now,
+            blocked: false,
+            uuid: format!("uuid-{}", id),
+            avatar_version: None,
+        }
+    }
+
+    struct MockDbHandle;
+
+    /// `linked@example.com` is linked to user 2.
+    #[impl_transaction(MockDbHandle, GetFederatedIdentity, get_federated_identity)]
+    async fn get_federated_identity(provider: String, subject: String) -> Result<Option<FederatedIdentity>, NanoServiceError> {
+        assert_eq!(provider, SAML_PROVIDER);
+        Ok((subject == "linked@example.com").then(|| FederatedIdentity {
+            id: 1,
+            user_id: 2,
+            provider,
+            subject,
+            email: "linked@example.com".to_string(),
+            date_created: Utc::now().naive_utc(),
+        }))
+    }
+
+    #[impl_transaction(MockDbHandle, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        Ok(user(id, "linked@example.com", UserRole::Worker))
+    }
+
+    #[impl_transaction(MockDbHandle, GetUserByEmail, get_user_by_email)]
+    async fn get_user_by_email(_email: String) -> Result<User, NanoServiceError> {
+        Err(NanoServiceError::new("User not found".to_string(), NanoServiceErrorStatus::NotFound))
+    }
+
+    #[impl_transaction(MockDbHandle, LinkFederatedIdentity, link_federated_identity)]
+    async fn link_federated_identity(_user_id: i32, _subject: FederatedSubject) -> Result<FederatedIdentity, NanoServiceError> {
+        Err(NanoServiceError::new("no user has the email".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockDbHandle, ProvisionFederatedUser, provision_federated_user)]
+    async fn provision_federated_user(new_user: NewUser, _subject: FederatedSubject) -> Result<User, NanoServiceError> {
+        PROVISIONED.lock().unwrap().push(new_user.clone());
+        Ok(user(9, &new_user.email, new_user.user_role))
+    }
+
+    #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(OrgPlan { plan: Plan::Pro, max_users: None, max_todos: None, mfa: None, api_access: None, date_updated: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, CountUsers, count_users)]
+    async fn count_users() -> Result<i64, NanoServiceError> {
+        Ok(3)
+    }
+
+    #[impl_transaction(MockDbHandle, GetEffectivePermissions, get_effective_permissions)]
+    async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[impl_transaction(MockDbHandle, UpdateLastLoggedIn, update_last_logged_in)]
+    async fn update_last_logged_in(_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(true)
+    }
+
+    #[impl_transaction(MockDbHandle, RecordActiveUser, record_active_user)]
+    async fn record_active_user(_user_id: i32) -> Result<(), NanoServiceError> {
+        Ok(())
+    }
+
+    #[impl_transaction(MockDbHandle, GetUserOrganizations, get_user_organizations)]
+    async fn get_user_organizations(_user_id: i32) -> Result<Vec<kernel::organizations::UserOrganization>, NanoServiceError> {
+        Ok(vec![])
+    }
+    #[impl_transaction(MockDbHandle, RecordDeviceLogin, record_device_login)]
+    async fn record_device_login(device: NewDevice) -> Result<Device, NanoServiceError> {
+        let now = Utc::now().naive_utc();
+        Ok(Device {
+            id: 1,
+            user_id: device.user_id,
+            fingerprint: device.fingerprint,
+            name: None,
+            user_agent: device.user_agent,
+            trusted_until: None,
+            date_created: now,
+            last_seen_at: now,
+        })
+    }
+
+    async fn complete(assertion: serde_json::Value) -> Result<LoginReturnSchema, NanoServiceError> {
+        complete_saml_login::<MockDbHandle, MockConfig, PassAuthSessionCheckMock, RecordAnalyticsMock, JsonAssertionMock>(
+            assertion.to_string(), "some-agent".to_string()
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_saml_metadata() {
+        let metadata = saml_metadata::<MockConfig>().await.unwrap();
+        assert!(metadata.contains(r#"entityID="secret""#));
+    }
+
+    #[tokio::test]
+    async fn test_linked_user_logs_in() {
+        let outcome = complete(serde_json::json!({"name_id": "linked@example.com"})).await.unwrap();
+        assert_eq!(HeaderToken::<MockConfig, NoRoleCheck>::decode(&outcome.token).unwrap().user_id, 2);
+        assert_eq!(outcome.role, UserRole::Worker);
+    }
+
+    #[tokio::test]
+    async fn test_new_user_is_provisioned_with_mapped_role() {
+        let outcome = complete(serde_json::json!({
+            "name_id": "grace@example.com",
+            "attributes": {"givenName": ["Grace"], "sn": ["Hopper"], "groups": ["staff", "admins"]}
+        })).await.unwrap();
+        assert_eq!(HeaderToken::<MockConfig, NoRoleCheck>::decode(&outcome.token).unwrap().user_id, 9);
+        assert_eq!(outcome.role, UserRole::Admin);
+        let provisioned = PROVISIONED.lock().unwrap().iter()
+            .find(|new_user| new_user.email == "grace@example.com").cloned().unwrap();
+        assert_eq!((provisioned.first_name.as_str(), provisioned.last_name.as_str()), ("Grace", "Hopper"));
+        assert!(provisioned.confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_refused_logins() {
+        let refused = complete_saml_login::<MockDbHandle, MockConfig, PassAuthSessionCheckMock, RecordAnalyticsMock, JsonAssertionMock>(
+            "refused".to_string(), "some-agent".to_string()
+        ).await.unwrap_err();
+        assert_eq!(refused.status, NanoServiceErrorStatus::Unauthorized);
+
+        let no_email = complete(serde_json::json!({"name_id": "00u1abcd"})).await.unwrap_err();
+        assert_eq!(no_email.status, NanoServiceErrorStatus::Unauthorized);
+    }
+}
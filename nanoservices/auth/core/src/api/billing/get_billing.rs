@@ -0,0 +1,32 @@
+//! Core logic for reading the organization's billing status.
+use serde::Serialize;
+use utils::errors::NanoServiceError;
+use dal::billing::tx_definitions::GetOrgBilling;
+use dal::plans::tx_definitions::GetOrgPlan;
+use kernel::billing::OrgBilling;
+use crate::api::plans::get_plan::{get_plan, PlanSummary};
+
+
+/// The subscription with the plan it pays for.
+///
+/// # Fields
+/// * `billing` - The state of the Stripe subscription.
+/// * `plan` - The plan and the entitlements it grants.
+#[derive(Serialize, Debug)]
+pub struct BillingSummary {
+    #[serde(flatten)]
+    pub billing: OrgBilling,
+    pub plan: PlanSummary,
+}
+
+
+/// Gets the organization's billing status and plan.
+///
+/// # Returns
+/// - `Ok(BillingSummary)`: The billing status and plan.
+/// - `Err(NanoServiceError)`: If either could not be read.
+pub async fn get_billing<X: GetOrgBilling + GetOrgPlan>() -> Result<BillingSummary, NanoServiceError> {
+    let billing = X::get_org_billing().await?;
+    let plan = get_plan::<X>().await?;
+    Ok(BillingSummary { billing, plan })
+}
@@ -0,0 +1,2 @@
+pub mod stripe_webhook;
+pub mod get_billing;
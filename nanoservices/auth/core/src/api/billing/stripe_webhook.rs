@@ -0,0 +1,137 @@
+//! Core logic for applying the Stripe webhooks that move the subscription through its lifecycle.
+//!
+//! # Overview
+//! The webhook is authenticated by its signature rather than a token. Each event is applied once,
+//! a retry of an event already applied is acknowledged without changing anything so Stripe stops
+//! retrying it, and an event that does not affect billing is acknowledged and ignored.
+//!
+//! # Variables
+//! * `STRIPE_WEBHOOK_SECRET` - The signing secret of the webhook endpoint, webhooks are rejected when not set
+use serde::Serialize;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::billing::tx_definitions::ApplyBillingChange;
+use kernel::billing::{StripeEvent, verify_stripe_signature};
+
+
+/// What happened to a webhook's event.
+///
+/// # Variants
+/// * `Applied` - The event changed the billing status.
+/// * `Duplicate` - The event had already been applied.
+/// * `Ignored` - The event does not affect billing.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookOutcome {
+    Applied,
+    Duplicate,
+    Ignored,
+}
+
+
+/// The response to a webhook.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct StripeWebhookResponse {
+    pub event_id: String,
+    pub outcome: WebhookOutcome,
+}
+
+
+/// Verifies a Stripe webhook and applies its event.
+///
+/// # Arguments
+/// - `signature`: The `Stripe-Signature` header, if it was sent.
+/// - `payload`: The raw body of the webhook, the signature is over the exact bytes.
+/// - `now`: The current time as a unix timestamp.
+///
+/// # Returns
+/// - `Ok(StripeWebhookResponse)`: The event and what happened to it.
+/// - `Err(NanoServiceError)`: `Forbidden` if billing is not configured, `Unauthorized` if the
+///   signature is missing or does not match, `BadRequest` if the event cannot be read.
+pub async fn receive_stripe_webhook<X, Y>(signature: Option<&str>, payload: &[u8], now: i64)
+-> Result<StripeWebhookResponse, NanoServiceError>
+where
+    X: ApplyBillingChange,
+    Y: GetConfigVariable
+{
+    let secret = Y::get_config_variable("STRIPE_WEBHOOK_SECRET".to_string()).map_err(|_| {
+        NanoServiceError::new("Billing is not enabled".to_string(), NanoServiceErrorStatus::Forbidden)
+    })?;
+    let signature = signature.ok_or_else(|| NanoServiceError::new(
+        "Billing webhook is not signed".to_string(),
+        NanoServiceErrorStatus::Unauthorized
+    ))?;
+    verify_stripe_signature(&secret, signature, payload, now)?;
+
+    let event = StripeEvent::from_payload(payload)?;
+    let outcome = match event.to_billing_change()? {
+        Some(change) => match X::apply_billing_change(change).await? {
+            true => WebhookOutcome::Applied,
+            false => WebhookOutcome::Duplicate,
+        },
+        None => WebhookOutcome::Ignored
+    };
+    Ok(StripeWebhookResponse { event_id: event.id, outcome })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::billing::{BillingChange, stripe_signature};
+    use kernel::plans::Plan;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    const NOW: i64 = 1_745_000_100;
+
+    static APPLIED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("whsec_test".to_string())
+        }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, ApplyBillingChange, apply_billing_change)]
+    async fn apply_billing_change(change: BillingChange) -> Result<bool, NanoServiceError> {
+        assert_eq!(change.plan, Some(Plan::Pro));
+        let mut applied = APPLIED.lock().unwrap();
+        if applied.contains(&change.event_id) {
+            return Ok(false)
+        }
+        applied.push(change.event_id);
+        Ok(true)
+    }
+
+    fn signed(payload: &[u8]) -> String {
+        format!("t={},v1={}", NOW, stripe_signature("whsec_test", NOW, payload))
+    }
+
+    #[tokio::test]
+    async fn test_receive_stripe_webhook() {
+        let payload = json!({
+            "id": "evt_checkout", "type": "checkout.session.completed", "created": NOW,
+            "data": {"object": {"customer": "cus_1", "subscription": "sub_1", "metadata": {"plan": "pro"}}}
+        }).to_string().into_bytes();
+        let signature = signed(&payload);
+
+        let first = receive_stripe_webhook::<MockDbHandle, MockConfig>(Some(&signature), &payload, NOW).await.unwrap();
+        assert_eq!(first.outcome, WebhookOutcome::Applied);
+        let retry = receive_stripe_webhook::<MockDbHandle, MockConfig>(Some(&signature), &payload, NOW).await.unwrap();
+        assert_eq!(retry.outcome, WebhookOutcome::Duplicate);
+
+        let ignored = json!({"id": "evt_other", "type": "customer.created", "created": NOW, "data": {"object": {}}})
+            .to_string().into_bytes();
+        let response = receive_stripe_webhook::<MockDbHandle, MockConfig>(Some(&signed(&ignored)), &ignored, NOW).await.unwrap();
+        assert_eq!(response.outcome, WebhookOutcome::Ignored);
+
+        let unsigned = receive_stripe_webhook::<MockDbHandle, MockConfig>(None, &payload, NOW).await.unwrap_err();
+        assert_eq!(unsigned.status, NanoServiceErrorStatus::Unauthorized);
+    }
+}
@@ -0,0 +1,14 @@
+//! Core logic for reading the organization's branding.
+use utils::errors::NanoServiceError;
+use dal::branding::tx_definitions::GetOrgBranding;
+use kernel::branding::OrgBranding;
+
+
+/// Gets the branding the frontend and emails are shown with.
+///
+/// # Returns
+/// - `Ok(OrgBranding)`: The current branding.
+/// - `Err(NanoServiceError)`: If the branding could not be read.
+pub async fn get_branding<X: GetOrgBranding>() -> Result<OrgBranding, NanoServiceError> {
+    X::get_org_branding().await
+}
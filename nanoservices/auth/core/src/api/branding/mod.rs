@@ -0,0 +1,2 @@
+pub mod get_branding;
+pub mod update_branding;
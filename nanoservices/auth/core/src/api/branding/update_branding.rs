@@ -0,0 +1,65 @@
+//! Core logic for changing the organization's branding.
+use utils::errors::NanoServiceError;
+use utils::validation::validate_body;
+use dal::branding::tx_definitions::UpdateOrgBranding;
+use kernel::branding::{NewOrgBranding, OrgBranding};
+
+
+/// Replaces the branding the frontend and emails are shown with.
+///
+/// # Arguments
+/// - `branding`: The new branding.
+///
+/// # Returns
+/// - `Ok(OrgBranding)`: The saved branding.
+/// - `Err(NanoServiceError)`: `BadRequest` with the failing fields if the branding is not valid,
+///   or if the branding could not be saved.
+///
+/// # Notes
+/// - Emails already in the outbox are sent with the new branding as it is applied when they are sent.
+pub async fn update_branding<X: UpdateOrgBranding>(branding: NewOrgBranding) -> Result<OrgBranding, NanoServiceError> {
+    validate_body(&branding)?;
+    X::update_org_branding(branding).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceErrorStatus;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, UpdateOrgBranding, update_org_branding)]
+    async fn update_org_branding(branding: NewOrgBranding) -> Result<OrgBranding, NanoServiceError> {
+        Ok(OrgBranding {
+            product_name: branding.product_name,
+            logo_url: branding.logo_url,
+            accent_color: branding.accent_color,
+            date_updated: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_update_branding() {
+        let branding = NewOrgBranding {
+            product_name: "Acme Tasks".to_string(),
+            logo_url: Some("https://cdn.example.com/logo.png".to_string()),
+            accent_color: "#2563eb".to_string(),
+        };
+        let saved = update_branding::<MockDbHandle>(branding).await.unwrap();
+        assert_eq!(saved.product_name, "Acme Tasks");
+
+        let invalid = NewOrgBranding {
+            product_name: " ".to_string(),
+            logo_url: None,
+            accent_color: "blue".to_string(),
+        };
+        let error = update_branding::<MockDbHandle>(invalid).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        let details = error.details.unwrap();
+        assert!(details.get("product_name").is_some());
+        assert!(details.get("accent_color").is_some());
+    }
+}
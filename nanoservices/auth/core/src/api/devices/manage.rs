@@ -0,0 +1,185 @@
+//! Core logic for users managing the devices they have logged in from.
+//!
+//! # Overview
+//! Devices are recorded as users log in, see `kernel::devices`. A user can name their devices to
+//! tell them apart, mark a device as trusted for `TRUSTED_DEVICE_DAYS`, stop trusting it, or revoke
+//! it. Revoking a device forgets it and ends the sessions started on it, so the device has to log in
+//! again, after which it is recorded as a new, untrusted device.
+//!
+//! # Notes
+//! Every function only touches the user's own devices, another user's device is not found.
+use dal::devices::tx_definitions::{ListDevices, RenameDevice, RevokeDevice, TrustDevice};
+use kernel::chrono::Utc;
+use kernel::devices::{trusted_device_lifetime, Device, MAX_DEVICE_NAME_LENGTH};
+use kernel::token::session_cache::traits::DelDeviceAuthCacheSessions;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+fn device_not_found(id: i32) -> NanoServiceError {
+    NanoServiceError::new(format!("Device {} not found", id), NanoServiceErrorStatus::NotFound)
+}
+
+
+/// Lists the user's devices, the most recently used first.
+pub async fn list_devices<X: ListDevices>(user_id: i32) -> Result<Vec<Device>, NanoServiceError> {
+    X::list_devices(user_id).await
+}
+
+
+/// Names one of the user's devices.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user.
+/// - `id`: The ID of the device.
+/// - `name`: The name, a blank name clears it.
+///
+/// # Returns
+/// - `Ok(Device)`: The renamed device.
+/// - `Err(NanoServiceError)`: `BadRequest` if the name is too long, `NotFound` if the user has no
+///   such device.
+pub async fn rename_device<X: RenameDevice>(user_id: i32, id: i32, name: String) -> Result<Device, NanoServiceError> {
+    let name = name.trim().to_string();
+    if name.chars().count() > MAX_DEVICE_NAME_LENGTH {
+        return Err(NanoServiceError::new(
+            format!("A device name can be at most {} characters", MAX_DEVICE_NAME_LENGTH),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    }
+    let name = (!name.is_empty()).then_some(name);
+    X::rename_device(user_id, id, name).await?.ok_or_else(|| device_not_found(id))
+}
+
+
+/// Trusts one of the user's devices for `TRUSTED_DEVICE_DAYS` from now, or stops trusting it.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user.
+/// - `id`: The ID of the device.
+/// - `trusted`: Whether the device is trusted, trusting a trusted device starts the period again.
+///
+/// # Returns
+/// - `Ok(Device)`: The device.
+/// - `Err(NanoServiceError)`: `NotFound` if the user has no such device.
+pub async fn trust_device<X, Y>(user_id: i32, id: i32, trusted: bool) -> Result<Device, NanoServiceError>
+where
+    X: TrustDevice,
+    Y: GetConfigVariable
+{
+    let trusted_until = trusted.then(|| Utc::now().naive_utc() + trusted_device_lifetime::<Y>());
+    X::trust_device(user_id, id, trusted_until).await?.ok_or_else(|| device_not_found(id))
+}
+
+
+/// Revokes one of the user's devices, ending the sessions started on it.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user.
+/// - `id`: The ID of the device.
+///
+/// # Returns
+/// - `Ok(usize)`: The number of sessions ended.
+/// - `Err(NanoServiceError)`: `NotFound` if the user has no such device.
+pub async fn revoke_device<X, Z>(user_id: i32, id: i32) -> Result<usize, NanoServiceError>
+where
+    X: RevokeDevice,
+    Z: DelDeviceAuthCacheSessions
+{
+    let device = X::revoke_device(user_id, id).await?.ok_or_else(|| device_not_found(id))?;
+    Z::del_device_auth_cache_sessions(user_id, device.fingerprint).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::{Duration, NaiveDateTime};
+    use kernel::devices::{device_fingerprint, DEFAULT_TRUSTED_DEVICE_DAYS};
+    use std::sync::Mutex;
+
+    /// The device fingerprints whose sessions were ended, with the user.
+    static ENDED: Mutex<Vec<(i32, String)>> = Mutex::new(Vec::new());
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    /// User 2 has device 1.
+    fn device(user_id: i32, id: i32) -> Option<Device> {
+        let now = Utc::now().naive_utc();
+        (user_id == 2 && id == 1).then(|| Device {
+            id,
+            user_id,
+            fingerprint: device_fingerprint("Mozilla/5.0"),
+            name: None,
+            user_agent: "Mozilla/5.0".to_string(),
+            trusted_until: None,
+            date_created: now,
+            last_seen_at: now,
+        })
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, RenameDevice, rename_device)]
+    async fn rename_device(user_id: i32, id: i32, name: Option<String>) -> Result<Option<Device>, NanoServiceError> {
+        Ok(device(user_id, id).map(|device| Device { name, ..device }))
+    }
+
+    #[impl_transaction(MockDbHandle, TrustDevice, trust_device)]
+    async fn trust_device(user_id: i32, id: i32, trusted_until: Option<NaiveDateTime>) -> Result<Option<Device>, NanoServiceError> {
+        Ok(device(user_id, id).map(|device| Device { trusted_until, ..device }))
+    }
+
+    #[impl_transaction(MockDbHandle, RevokeDevice, revoke_device)]
+    async fn revoke_device(user_id: i32, id: i32) -> Result<Option<Device>, NanoServiceError> {
+        Ok(device(user_id, id))
+    }
+
+    struct RecordEndedSessions;
+
+    impl DelDeviceAuthCacheSessions for RecordEndedSessions {
+        async fn del_device_auth_cache_sessions(user_id: i32, fingerprint: String) -> Result<usize, NanoServiceError> {
+            ENDED.lock().unwrap().push((user_id, fingerprint));
+            Ok(2)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rename_device() {
+        let renamed = rename_device::<MockDbHandle>(2, 1, " Work laptop ".to_string()).await.unwrap();
+        assert_eq!(renamed.name.as_deref(), Some("Work laptop"));
+        let cleared = rename_device::<MockDbHandle>(2, 1, " ".to_string()).await.unwrap();
+        assert_eq!(cleared.name, None);
+
+        let too_long = rename_device::<MockDbHandle>(2, 1, "a".repeat(MAX_DEVICE_NAME_LENGTH + 1)).await.unwrap_err();
+        assert_eq!(too_long.status, NanoServiceErrorStatus::BadRequest);
+        let not_theirs = rename_device::<MockDbHandle>(3, 1, "Work laptop".to_string()).await.unwrap_err();
+        assert_eq!(not_theirs.status, NanoServiceErrorStatus::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_trust_device() {
+        let trusted = trust_device::<MockDbHandle, MockConfig>(2, 1, true).await.unwrap();
+        let expected = Utc::now().naive_utc() + Duration::days(DEFAULT_TRUSTED_DEVICE_DAYS);
+        assert!((expected - trusted.trusted_until.unwrap()).num_seconds().abs() < 5);
+        assert!(trusted.is_trusted(Utc::now().naive_utc()));
+
+        let untrusted = trust_device::<MockDbHandle, MockConfig>(2, 1, false).await.unwrap();
+        assert_eq!(untrusted.trusted_until, None);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_device() {
+        assert_eq!(revoke_device::<MockDbHandle, RecordEndedSessions>(2, 1).await.unwrap(), 2);
+        assert!(ENDED.lock().unwrap().contains(&(2, device_fingerprint("Mozilla/5.0"))));
+
+        let missing = revoke_device::<MockDbHandle, RecordEndedSessions>(2, 5).await.unwrap_err();
+        assert_eq!(missing.status, NanoServiceErrorStatus::NotFound);
+    }
+}
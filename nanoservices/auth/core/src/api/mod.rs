@@ -1,3 +1,11 @@
 pub mod users;
 pub mod role_permissions;
 pub mod auth;
+pub mod branding;
+pub mod plans;
+pub mod billing;
+pub mod audit;
+pub mod onboarding;
+pub mod api_keys;
+pub mod devices;
+pub mod organizations;
@@ -0,0 +1,99 @@
+//! Core logic for accepting the terms.
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::onboarding::tx_definitions::{GetOnboardingStateByUuid, AcceptTerms};
+use kernel::onboarding::{OnboardingStatus, OnboardingStep};
+use super::{current_terms_version, require_previous_steps};
+
+
+/// Records that a user accepted the current terms.
+///
+/// # Arguments
+/// - `uuid`: The unique ID of the user, as the user cannot log in before onboarding.
+/// - `terms_version`: The version of the terms the user was shown.
+///
+/// # Returns
+/// - `Ok(OnboardingStatus)`: The status after accepting the terms.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such user, `Conflict` if the email has not
+///   been confirmed or the user was shown terms that are no longer current.
+pub async fn accept_terms<X, Y>(uuid: &str, terms_version: &str) -> Result<OnboardingStatus, NanoServiceError>
+where
+    X: GetOnboardingStateByUuid + AcceptTerms,
+    Y: GetConfigVariable,
+{
+    let current_version = current_terms_version::<Y>();
+    if terms_version != current_version {
+        return Err(NanoServiceError::new(
+            "The terms have changed and have to be accepted again".to_string(),
+            NanoServiceErrorStatus::Conflict
+        ).with_details(serde_json::json!({"terms_version": current_version})))
+    }
+    let state = X::get_onboarding_state_by_uuid(uuid.to_string()).await?.ok_or_else(|| NanoServiceError::new(
+        "User not found".to_string(),
+        NanoServiceErrorStatus::NotFound
+    ))?;
+    require_previous_steps(&state, OnboardingStep::AcceptTerms, &current_version)?;
+    let state = X::accept_terms(state.user_id, current_version.clone()).await?;
+    Ok(OnboardingStatus::new(&state, &current_version))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::onboarding::OnboardingState;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("2".to_string())
+        }
+    }
+
+    fn state(uuid: &str) -> OnboardingState {
+        OnboardingState {
+            user_id: 1,
+            confirmed: uuid == "confirmed",
+            terms_version: Some("1".to_string()),
+            terms_accepted_at: None,
+            password_set_at: None,
+            profile_completed_at: None,
+        }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetOnboardingStateByUuid, get_onboarding_state_by_uuid)]
+    async fn get_onboarding_state_by_uuid(uuid: String) -> Result<Option<OnboardingState>, NanoServiceError> {
+        Ok(Some(state(&uuid)))
+    }
+
+    #[impl_transaction(MockDbHandle, AcceptTerms, accept_terms)]
+    async fn accept_terms(_user_id: i32, terms_version: String) -> Result<OnboardingState, NanoServiceError> {
+        let mut state = state("confirmed");
+        state.terms_version = Some(terms_version);
+        Ok(state)
+    }
+
+    #[tokio::test]
+    async fn test_accept_terms() {
+        let status = accept_terms::<MockDbHandle, FakeConfig>("confirmed", "2").await.unwrap();
+        assert_eq!(status.next_step, OnboardingStep::SetPassword);
+    }
+
+    #[tokio::test]
+    async fn test_accept_old_terms() {
+        let error = accept_terms::<MockDbHandle, FakeConfig>("confirmed", "1").await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        assert_eq!(error.details.unwrap()["terms_version"], "2");
+    }
+
+    #[tokio::test]
+    async fn test_accept_terms_before_confirming() {
+        let error = accept_terms::<MockDbHandle, FakeConfig>("unconfirmed", "2").await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        assert_eq!(error.details.unwrap()["next_step"], "confirm_email");
+    }
+}
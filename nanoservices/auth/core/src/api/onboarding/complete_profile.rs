@@ -0,0 +1,106 @@
+//! Core logic for completing a user's profile.
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::validation::validate_body;
+use dal::onboarding::tx_definitions::{GetOnboardingState, CompleteOnboardingProfile};
+use kernel::onboarding::{OnboardingStatus, OnboardingStep, OnboardingProfile};
+use super::{current_terms_version, require_previous_steps};
+
+
+/// Saves the profile of a logged in user, which is the last step of onboarding.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user.
+/// - `profile`: The names, locale and timezone of the user.
+///
+/// # Returns
+/// - `Ok(OnboardingStatus)`: The status after saving the profile.
+/// - `Err(NanoServiceError)`: `BadRequest` with the failing fields if the profile is not valid,
+///   `Conflict` if an earlier step has not been completed.
+pub async fn complete_profile<X, Y>(user_id: i32, profile: OnboardingProfile) -> Result<OnboardingStatus, NanoServiceError>
+where
+    X: GetOnboardingState + CompleteOnboardingProfile,
+    Y: GetConfigVariable,
+{
+    validate_body(&profile)?;
+    let terms_version = current_terms_version::<Y>();
+    let state = X::get_onboarding_state(user_id).await?.ok_or_else(|| NanoServiceError::new(
+        "User not found".to_string(),
+        NanoServiceErrorStatus::NotFound
+    ))?;
+    require_previous_steps(&state, OnboardingStep::CompleteProfile, &terms_version)?;
+    let state = X::complete_onboarding_profile(user_id, profile).await?;
+    Ok(OnboardingStatus::new(&state, &terms_version))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::onboarding::OnboardingState;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("1".to_string())
+        }
+    }
+
+    fn state(user_id: i32) -> OnboardingState {
+        let now = chrono::Utc::now().naive_utc();
+        OnboardingState {
+            user_id,
+            confirmed: true,
+            terms_version: Some("1".to_string()),
+            terms_accepted_at: Some(now),
+            // user 2 has not set their password yet
+            password_set_at: (user_id != 2).then_some(now),
+            profile_completed_at: None,
+        }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetOnboardingState, get_onboarding_state)]
+    async fn get_onboarding_state(user_id: i32) -> Result<Option<OnboardingState>, NanoServiceError> {
+        Ok(Some(state(user_id)))
+    }
+
+    #[impl_transaction(MockDbHandle, CompleteOnboardingProfile, complete_onboarding_profile)]
+    async fn complete_onboarding_profile(user_id: i32, _profile: OnboardingProfile) -> Result<OnboardingState, NanoServiceError> {
+        let mut state = state(user_id);
+        state.profile_completed_at = Some(chrono::Utc::now().naive_utc());
+        Ok(state)
+    }
+
+    fn profile(timezone: &str) -> OnboardingProfile {
+        OnboardingProfile {
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            locale: "en-GB".to_string(),
+            timezone: timezone.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_profile() {
+        let status = complete_profile::<MockDbHandle, FakeConfig>(1, profile("Europe/London")).await.unwrap();
+        assert_eq!(status.next_step, OnboardingStep::Complete);
+    }
+
+    #[tokio::test]
+    async fn test_complete_profile_out_of_order() {
+        let error = complete_profile::<MockDbHandle, FakeConfig>(2, profile("Europe/London")).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        assert_eq!(error.details.unwrap()["next_step"], "set_password");
+    }
+
+    #[tokio::test]
+    async fn test_complete_invalid_profile() {
+        let error = complete_profile::<MockDbHandle, FakeConfig>(1, profile("Nowhere")).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        assert!(error.details.unwrap().get("timezone").is_some());
+    }
+}
@@ -0,0 +1,67 @@
+//! Core logic for the onboarding funnel shown to admins.
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use dal::onboarding::tx_definitions::GetOnboardingFunnel;
+use kernel::onboarding::{OnboardingFunnelCount, OnboardingStep};
+use super::current_terms_version;
+
+
+/// Counts the users waiting on each step of onboarding.
+///
+/// # Returns
+/// - `Ok(Vec<OnboardingFunnelCount>)`: Every step in order followed by `complete`, including steps
+///   no users are waiting on.
+/// - `Err(NanoServiceError)`: If the counts could not be read.
+pub async fn get_onboarding_funnel<X, Y>() -> Result<Vec<OnboardingFunnelCount>, NanoServiceError>
+where
+    X: GetOnboardingFunnel,
+    Y: GetConfigVariable,
+{
+    let counts = X::get_onboarding_funnel(current_terms_version::<Y>()).await?;
+    Ok(OnboardingStep::REQUIRED
+        .into_iter()
+        .chain([OnboardingStep::Complete])
+        .map(|step| OnboardingFunnelCount {
+            step: step.as_str().to_string(),
+            users: counts.iter()
+                .find(|count| count.step == step.as_str())
+                .map(|count| count.users)
+                .unwrap_or(0),
+        })
+        .collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("1".to_string())
+        }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetOnboardingFunnel, get_onboarding_funnel)]
+    async fn get_onboarding_funnel(terms_version: String) -> Result<Vec<OnboardingFunnelCount>, NanoServiceError> {
+        assert_eq!(terms_version, "1");
+        Ok(vec![
+            OnboardingFunnelCount { step: "complete".to_string(), users: 7 },
+            OnboardingFunnelCount { step: "accept_terms".to_string(), users: 2 },
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_funnel_is_in_step_order() {
+        let funnel = get_onboarding_funnel::<MockDbHandle, FakeConfig>().await.unwrap();
+        let steps: Vec<(&str, i64)> = funnel.iter().map(|count| (count.step.as_str(), count.users)).collect();
+        assert_eq!(steps, vec![
+            ("confirm_email", 0), ("accept_terms", 2), ("set_password", 0), ("complete_profile", 0), ("complete", 7)
+        ]);
+    }
+}
@@ -0,0 +1,48 @@
+//! Core logic for reporting where a user is in onboarding.
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::onboarding::tx_definitions::{GetOnboardingState, GetOnboardingStateByUuid};
+use kernel::onboarding::OnboardingStatus;
+use super::current_terms_version;
+
+
+/// Gets the onboarding status of a logged in user.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user.
+///
+/// # Returns
+/// - `Ok(OnboardingStatus)`: The next step and the completed steps.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such user.
+pub async fn get_onboarding_status<X, Y>(user_id: i32) -> Result<OnboardingStatus, NanoServiceError>
+where
+    X: GetOnboardingState,
+    Y: GetConfigVariable,
+{
+    let state = X::get_onboarding_state(user_id).await?.ok_or_else(|| NanoServiceError::new(
+        "User not found".to_string(),
+        NanoServiceErrorStatus::NotFound
+    ))?;
+    Ok(OnboardingStatus::new(&state, &current_terms_version::<Y>()))
+}
+
+
+/// Gets the onboarding status of a user who cannot log in yet, using the unique ID from their emails.
+///
+/// # Arguments
+/// - `uuid`: The unique ID of the user.
+///
+/// # Returns
+/// - `Ok(OnboardingStatus)`: The next step and the completed steps.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such user.
+pub async fn get_onboarding_status_by_uuid<X, Y>(uuid: &str) -> Result<OnboardingStatus, NanoServiceError>
+where
+    X: GetOnboardingStateByUuid,
+    Y: GetConfigVariable,
+{
+    let state = X::get_onboarding_state_by_uuid(uuid.to_string()).await?.ok_or_else(|| NanoServiceError::new(
+        "User not found".to_string(),
+        NanoServiceErrorStatus::NotFound
+    ))?;
+    Ok(OnboardingStatus::new(&state, &current_terms_version::<Y>()))
+}
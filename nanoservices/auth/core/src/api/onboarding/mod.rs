@@ -0,0 +1,88 @@
+//! Core logic for moving users through onboarding.
+//!
+//! # Overview
+//! A user confirms their email, accepts the terms, sets a password and completes their profile.
+//! Each step can only be completed once the steps before it are done, so a user cannot skip ahead
+//! by calling the endpoints out of order.
+//!
+//! # Variables
+//! * `TERMS_VERSION` - The version of the terms users have to accept, defaults to `1`. Changing it
+//!   sends every user back to the accept terms step.
+pub mod get_status;
+pub mod accept_terms;
+pub mod complete_profile;
+pub mod get_funnel;
+
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use kernel::onboarding::{OnboardingState, OnboardingStep};
+
+
+/// The terms version used when not configured.
+const DEFAULT_TERMS_VERSION: &str = "1";
+
+
+/// Reads the version of the terms users currently have to accept.
+pub fn current_terms_version<Y: GetConfigVariable>() -> String {
+    Y::get_config_variable("TERMS_VERSION".to_string())
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or(DEFAULT_TERMS_VERSION.to_string())
+}
+
+
+/// Checks every step before `step` has been completed.
+///
+/// # Returns
+/// - `Err(NanoServiceError)`: `Conflict` with the step the user has to complete first under `next_step`.
+pub fn require_previous_steps(
+    state: &OnboardingState,
+    step: OnboardingStep,
+    terms_version: &str
+) -> Result<(), NanoServiceError> {
+    let next_step = state.next_step(terms_version);
+    if next_step >= step {
+        return Ok(())
+    }
+    Err(NanoServiceError::new(
+        format!("The {} step has to be completed first", next_step.as_str()),
+        NanoServiceErrorStatus::Conflict
+    ).with_details(serde_json::json!({"next_step": next_step})))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok(" ".to_string())
+        }
+    }
+
+    #[test]
+    fn test_current_terms_version_defaults() {
+        assert_eq!(current_terms_version::<FakeConfig>(), "1");
+    }
+
+    #[test]
+    fn test_require_previous_steps() {
+        let state = OnboardingState {
+            user_id: 1,
+            confirmed: true,
+            terms_version: None,
+            terms_accepted_at: None,
+            password_set_at: None,
+            profile_completed_at: None,
+        };
+        assert!(require_previous_steps(&state, OnboardingStep::AcceptTerms, "1").is_ok());
+
+        let error = require_previous_steps(&state, OnboardingStep::CompleteProfile, "1").unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        assert_eq!(error.details.unwrap()["next_step"], "accept_terms");
+    }
+}
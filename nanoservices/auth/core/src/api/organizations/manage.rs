@@ -0,0 +1,306 @@
+//! Core logic for creating organizations, switching between them and managing their members.
+//!
+//! # Overview
+//! Organizations are the tenants a deployment is shared between, see `kernel::organizations`. A
+//! token acts in one organization at a time, a member can switch their token to another of their
+//! organizations, and the owner and admins of an organization add and remove its members.
+//!
+//! # Notes
+//! Super admins can act in, and manage, every organization without being a member of it.
+use dal::organizations::tx_definitions::{
+    AddOrgMember, CreateOrganization, GetOrgMembers, GetOrgMembership, GetOrganization, GetUserOrganizations, RemoveOrgMember,
+};
+use dal::users::tx_definitions::GetUser;
+use kernel::organizations::{can_manage_org, NewOrganization, Organization, OrgMembership, OrgRole, UserOrganization};
+use kernel::users::UserRole;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::validation::validate_body;
+
+
+/// Reads an organization and the user's membership of it.
+///
+/// # Returns
+/// - `Ok(Option<OrgMembership>)`: The membership, `None` if the user is not a member.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such organization.
+async fn get_membership<X>(org_id: i32, user_id: i32) -> Result<Option<OrgMembership>, NanoServiceError>
+where
+    X: GetOrganization + GetOrgMembership
+{
+    X::get_organization(org_id).await?;
+    X::get_org_membership(org_id, user_id).await
+}
+
+
+/// Refuses a user who cannot add and remove the members of an organization.
+async fn check_can_manage<X>(org_id: i32, user_id: i32, role: &UserRole) -> Result<(), NanoServiceError>
+where
+    X: GetOrganization + GetOrgMembership
+{
+    let membership = get_membership::<X>(org_id, user_id).await?;
+    match can_manage_org(membership.as_ref(), role) {
+        true => Ok(()),
+        false => Err(NanoServiceError::new(
+            "Only the owner and admins of an organization can manage its members".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+}
+
+
+/// Creates an organization.
+///
+/// # Arguments
+/// - `owner_id`: The ID of the user who owns the organization.
+/// - `org`: The name of the organization.
+///
+/// # Returns
+/// - `Ok(Organization)`: The created organization.
+/// - `Err(NanoServiceError)`: `BadRequest` if the name is invalid, `NotFound` if there is no such owner.
+pub async fn create_organization<X>(owner_id: i32, org: NewOrganization) -> Result<Organization, NanoServiceError>
+where
+    X: CreateOrganization + GetUser
+{
+    let org = NewOrganization { name: org.name.trim().to_string() };
+    validate_body(&org)?;
+    X::get_user(owner_id).await?;
+    X::create_organization(owner_id, org).await
+}
+
+
+/// Lists the organizations the user belongs to, with their role in each.
+pub async fn list_organizations<X: GetUserOrganizations>(user_id: i32) -> Result<Vec<UserOrganization>, NanoServiceError> {
+    X::get_user_organizations(user_id).await
+}
+
+
+/// Checks a user can act in an organization, which they can if they are a member or a super admin.
+///
+/// # Arguments
+/// - `org_id`: The ID of the organization to act in.
+/// - `user_id`: The ID of the user.
+/// - `role`: The user's role across the deployment.
+///
+/// # Returns
+/// - `Ok(())`: If the user can act in the organization.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such organization, `Forbidden` if the user
+///   is not a member of it.
+pub async fn check_can_switch<X>(org_id: i32, user_id: i32, role: &UserRole) -> Result<(), NanoServiceError>
+where
+    X: GetOrganization + GetOrgMembership
+{
+    let membership = get_membership::<X>(org_id, user_id).await?;
+    match membership.is_some() || matches!(role, UserRole::SuperAdmin) {
+        true => Ok(()),
+        false => Err(NanoServiceError::new(
+            format!("You are not a member of organization {}", org_id),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+}
+
+
+/// Lists the members of an organization, for its members and super admins.
+///
+/// # Returns
+/// - `Ok(Vec<OrgMembership>)`: The members of the organization.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such organization, `Forbidden` if the user
+///   is not a member of it.
+pub async fn list_members<X>(org_id: i32, user_id: i32, role: &UserRole) -> Result<Vec<OrgMembership>, NanoServiceError>
+where
+    X: GetOrganization + GetOrgMembership + GetOrgMembers
+{
+    check_can_switch::<X>(org_id, user_id, role).await?;
+    X::get_org_members(org_id).await
+}
+
+
+/// Adds a user to an organization, or changes the role of a member.
+///
+/// # Arguments
+/// - `org_id`: The ID of the organization.
+/// - `actor_id`: The ID of the user making the change.
+/// - `actor_role`: The role of the user making the change across the deployment.
+/// - `user_id`: The ID of the user to add.
+/// - `org_role`: The role of the user in the organization.
+///
+/// # Returns
+/// - `Ok(OrgMembership)`: The membership.
+/// - `Err(NanoServiceError)`: `Forbidden` if the actor cannot manage the organization, `BadRequest`
+///   if the role is owner, `NotFound` if there is no such organization or user, or `Conflict` if
+///   the user is the owner.
+pub async fn add_member<X>(org_id: i32, actor_id: i32, actor_role: &UserRole, user_id: i32, org_role: OrgRole) -> Result<OrgMembership, NanoServiceError>
+where
+    X: GetOrganization + GetOrgMembership + AddOrgMember + GetUser
+{
+    check_can_manage::<X>(org_id, actor_id, actor_role).await?;
+    if org_role == OrgRole::Owner {
+        return Err(NanoServiceError::new(
+            "An organization has one owner, members can be added as an admin or a member".to_string(),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    }
+    X::get_user(user_id).await?;
+    if let Some(OrgMembership { org_role: OrgRole::Owner, .. }) = X::get_org_membership(org_id, user_id).await? {
+        return Err(NanoServiceError::new(
+            "The role of the owner of an organization cannot be changed".to_string(),
+            NanoServiceErrorStatus::Conflict
+        ))
+    }
+    X::add_org_member(org_id, user_id, org_role).await
+}
+
+
+/// Removes a user from an organization, a member can also remove themselves to leave it.
+///
+/// # Arguments
+/// - `org_id`: The ID of the organization.
+/// - `actor_id`: The ID of the user making the change.
+/// - `actor_role`: The role of the user making the change across the deployment.
+/// - `user_id`: The ID of the member to remove.
+///
+/// # Returns
+/// - `Ok(())`: If the member was removed.
+/// - `Err(NanoServiceError)`: `Forbidden` if the actor cannot manage the organization, `NotFound`
+///   if there is no such organization or member, or `Conflict` if the member is the owner.
+pub async fn remove_member<X>(org_id: i32, actor_id: i32, actor_role: &UserRole, user_id: i32) -> Result<(), NanoServiceError>
+where
+    X: GetOrganization + GetOrgMembership + RemoveOrgMember
+{
+    if actor_id != user_id {
+        check_can_manage::<X>(org_id, actor_id, actor_role).await?;
+    }
+    match get_membership::<X>(org_id, user_id).await? {
+        None => return Err(NanoServiceError::new(
+            format!("User {} is not a member of organization {}", user_id, org_id),
+            NanoServiceErrorStatus::NotFound
+        )),
+        Some(OrgMembership { org_role: OrgRole::Owner, .. }) => return Err(NanoServiceError::new(
+            "The owner of an organization cannot be removed from it".to_string(),
+            NanoServiceErrorStatus::Conflict
+        )),
+        Some(_) => {}
+    }
+    X::remove_org_member(org_id, user_id).await?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use kernel::users::User;
+
+    struct MockDbHandle;
+
+    /// Organization 2 exists, user 1 owns it, user 3 is an admin of it and user 4 a member.
+    fn membership(org_id: i32, user_id: i32) -> Option<OrgMembership> {
+        let org_role = match (org_id, user_id) {
+            (2, 1) => OrgRole::Owner,
+            (2, 3) => OrgRole::Admin,
+            (2, 4) => OrgRole::Member,
+            _ => return None
+        };
+        Some(OrgMembership { org_id, user_id, org_role, date_joined: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, GetOrganization, get_organization)]
+    async fn get_organization(id: i32) -> Result<Organization, NanoServiceError> {
+        match id {
+            2 => Ok(Organization { id, name: "Acme".to_string(), date_created: Utc::now().naive_utc() }),
+            _ => Err(NanoServiceError::new(format!("Organization {} not found", id), NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, GetOrgMembership, get_org_membership)]
+    async fn get_org_membership(org_id: i32, user_id: i32) -> Result<Option<OrgMembership>, NanoServiceError> {
+        Ok(membership(org_id, user_id))
+    }
+
+    #[impl_transaction(MockDbHandle, AddOrgMember, add_org_member)]
+    async fn add_org_member(org_id: i32, user_id: i32, org_role: OrgRole) -> Result<OrgMembership, NanoServiceError> {
+        Ok(OrgMembership { org_id, user_id, org_role, date_joined: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, RemoveOrgMember, remove_org_member)]
+    async fn remove_org_member(org_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(membership(org_id, user_id).is_some())
+    }
+
+    #[impl_transaction(MockDbHandle, CreateOrganization, create_organization)]
+    async fn create_organization(_owner_id: i32, org: NewOrganization) -> Result<Organization, NanoServiceError> {
+        Ok(Organization { id: 5, name: org.name, date_created: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        match id {
+            1..=9 => Ok(User {
+                id,
+                confirmed: true,
+                username: format!("user{}", id),
+                email: format!("user{}@example.com", id),
+                password: "hashed".to_string(),
+                first_name: "Ada".to_string(),
+                last_name: "Lovelace".to_string(),
+                user_role: UserRole::Worker,
+                date_created: Utc::now().naive_utc(),
+                last_logged_in: Utc::now().naive_utc(),
+                blocked: false,
+                uuid: id.to_string(),
+                avatar_version: None,
+            }),
+            _ => Err(NanoServiceError::new(format!("User {} not found", id), NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_organization() {
+        let org = create_organization::<MockDbHandle>(1, NewOrganization { name: " Acme ".to_string() }).await.unwrap();
+        assert_eq!(org.name, "Acme");
+
+        let error = create_organization::<MockDbHandle>(1, NewOrganization { name: "  ".to_string() }).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_check_can_switch() {
+        assert!(check_can_switch::<MockDbHandle>(2, 4, &UserRole::Worker).await.is_ok());
+        assert!(check_can_switch::<MockDbHandle>(2, 8, &UserRole::SuperAdmin).await.is_ok());
+
+        let error = check_can_switch::<MockDbHandle>(2, 8, &UserRole::Admin).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        let error = check_can_switch::<MockDbHandle>(9, 1, &UserRole::SuperAdmin).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_add_member() {
+        let membership = add_member::<MockDbHandle>(2, 3, &UserRole::Worker, 8, OrgRole::Admin).await.unwrap();
+        assert_eq!((membership.user_id, membership.org_role), (8, OrgRole::Admin));
+
+        let error = add_member::<MockDbHandle>(2, 4, &UserRole::Worker, 8, OrgRole::Member).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        let error = add_member::<MockDbHandle>(2, 1, &UserRole::Worker, 8, OrgRole::Owner).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        let error = add_member::<MockDbHandle>(2, 3, &UserRole::Worker, 1, OrgRole::Member).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        let error = add_member::<MockDbHandle>(2, 3, &UserRole::Worker, 42, OrgRole::Member).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_remove_member() {
+        assert!(remove_member::<MockDbHandle>(2, 3, &UserRole::Worker, 4).await.is_ok());
+        // a member can leave without being able to manage the organization
+        assert!(remove_member::<MockDbHandle>(2, 4, &UserRole::Worker, 4).await.is_ok());
+
+        let error = remove_member::<MockDbHandle>(2, 4, &UserRole::Worker, 3).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        let error = remove_member::<MockDbHandle>(2, 3, &UserRole::Worker, 1).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        let error = remove_member::<MockDbHandle>(2, 3, &UserRole::Worker, 8).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+    }
+}
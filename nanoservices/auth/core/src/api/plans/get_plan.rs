@@ -0,0 +1,29 @@
+//! Core logic for reading the organization's plan.
+use serde::Serialize;
+use utils::errors::NanoServiceError;
+use dal::plans::tx_definitions::GetOrgPlan;
+use kernel::plans::{Entitlements, OrgPlan};
+
+
+/// The plan with the entitlements it grants once the overrides are applied.
+///
+/// # Fields
+/// * `plan` - The plan and its overrides as they are stored.
+/// * `entitlements` - What the organization is allowed to do.
+#[derive(Serialize, Debug)]
+pub struct PlanSummary {
+    #[serde(flatten)]
+    pub plan: OrgPlan,
+    pub entitlements: Entitlements,
+}
+
+
+/// Gets the organization's plan and the entitlements it grants.
+///
+/// # Returns
+/// - `Ok(PlanSummary)`: The plan and its entitlements.
+/// - `Err(NanoServiceError)`: If the plan could not be read.
+pub async fn get_plan<X: GetOrgPlan>() -> Result<PlanSummary, NanoServiceError> {
+    let plan = X::get_org_plan().await?;
+    Ok(PlanSummary { entitlements: plan.entitlements(), plan })
+}
@@ -0,0 +1,2 @@
+pub mod get_plan;
+pub mod update_plan;
@@ -0,0 +1,63 @@
+//! Core logic for changing the organization's plan.
+use utils::errors::NanoServiceError;
+use utils::validation::validate_body;
+use dal::plans::tx_definitions::UpdateOrgPlan;
+use kernel::plans::NewOrgPlan;
+use crate::api::plans::get_plan::PlanSummary;
+
+
+/// Replaces the plan and its overrides.
+///
+/// # Arguments
+/// - `plan`: The new plan and overrides, overrides left out fall back to the plan's entitlements.
+///
+/// # Returns
+/// - `Ok(PlanSummary)`: The saved plan and the entitlements it grants.
+/// - `Err(NanoServiceError)`: `BadRequest` with the failing fields if an override is negative, or
+///   if the plan could not be saved.
+///
+/// # Notes
+/// - Lowering a limit below the current usage does not remove anything, it only stops new users or
+///   to-do items being added.
+pub async fn update_plan<X: UpdateOrgPlan>(plan: NewOrgPlan) -> Result<PlanSummary, NanoServiceError> {
+    validate_body(&plan)?;
+    let plan = X::update_org_plan(plan).await?;
+    Ok(PlanSummary { entitlements: plan.entitlements(), plan })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::plans::{OrgPlan, Plan};
+    use utils::errors::NanoServiceErrorStatus;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, UpdateOrgPlan, update_org_plan)]
+    async fn update_org_plan(plan: NewOrgPlan) -> Result<OrgPlan, NanoServiceError> {
+        Ok(OrgPlan {
+            plan: plan.plan,
+            max_users: plan.max_users,
+            max_todos: plan.max_todos,
+            mfa: plan.mfa,
+            api_access: plan.api_access,
+            date_updated: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_update_plan() {
+        let plan = NewOrgPlan { plan: Plan::Free, max_users: Some(10), max_todos: None, mfa: None, api_access: Some(true) };
+        let saved = update_plan::<MockDbHandle>(plan).await.unwrap();
+        assert_eq!(saved.entitlements.max_users, Some(10));
+        assert_eq!(saved.entitlements.max_todos, Some(100));
+        assert!(saved.entitlements.api_access);
+
+        let invalid = NewOrgPlan { plan: Plan::Pro, max_users: Some(-1), max_todos: None, mfa: None, api_access: None };
+        let error = update_plan::<MockDbHandle>(invalid).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        assert!(error.details.unwrap().get("max_users").is_some());
+    }
+}
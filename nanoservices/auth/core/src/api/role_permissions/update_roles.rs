@@ -31,7 +31,6 @@ mod tests {
 
     #[tokio::test]
     async fn test_update_role_permissions_ok() {
-        let outcome = update_role_permissions::<MockDbHandle>(10, vec![UserRole::Admin]).await.unwrap();
-        assert_eq!(outcome, ());
+        update_role_permissions::<MockDbHandle>(10, vec![UserRole::Admin]).await.unwrap();
     }
 }
\ No newline at end of file
@@ -0,0 +1,155 @@
+//! Core logic for users uploading and removing their avatar.
+//!
+//! # Overview
+//! An upload is scanned for viruses and processed into every `AvatarSize` by `crate::avatars`, so
+//! only re-encoded pixels are ever stored. Each size replaces the user's last avatar in the object
+//! store before the users row gets the new `avatar_version`, so a URL with the new version is never
+//! handed out before the avatar it points to is stored.
+use dal::users::tx_definitions::SetUserAvatar;
+use kernel::avatars::{avatar_max_bytes, avatar_too_large, avatar_url, avatar_version, UserAvatar};
+use kernel::content_scan::{reject_infected, ScanContent};
+use kernel::object_store::{DeleteObject, StoreObject};
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::avatars::{process_avatar, AvatarSize, AVATAR_CONTENT_TYPE};
+
+
+fn user_not_found(user_id: i32) -> NanoServiceError {
+    NanoServiceError::new(format!("User {} not found", user_id), NanoServiceErrorStatus::NotFound)
+}
+
+
+/// Replaces a user's avatar with an uploaded image.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user.
+/// - `upload`: The uploaded image.
+///
+/// # Returns
+/// - `Ok(UserAvatar)`: The URL of the new avatar.
+/// - `Err(NanoServiceError)`: `PayloadTooLarge` if the upload is over `AVATAR_MAX_BYTES`,
+///   `UnprocessableEntity` if it is infected, `BadRequest` if it is not a supported image,
+///   `NotFound` if there is no such user.
+pub async fn upload_avatar<X, Y, O, S>(user_id: i32, upload: Vec<u8>) -> Result<UserAvatar, NanoServiceError>
+where
+    X: SetUserAvatar,
+    Y: GetConfigVariable,
+    O: StoreObject,
+    S: ScanContent
+{
+    let max_bytes = avatar_max_bytes::<Y>();
+    if upload.len() > max_bytes {
+        return Err(avatar_too_large(max_bytes))
+    }
+    reject_infected::<S, Y>(&upload).await?;
+    let variants = process_avatar(upload).await?;
+
+    // the largest size has the most detail, so any change to the avatar changes its hash
+    let version = variants.iter()
+        .max_by_key(|variant| variant.size)
+        .map(|variant| avatar_version(&variant.bytes))
+        .unwrap_or_default();
+    for variant in variants {
+        O::put_object::<Y>(&variant.size.object_key(user_id), variant.bytes, AVATAR_CONTENT_TYPE).await?;
+    }
+    if !X::set_user_avatar(user_id, Some(version.clone())).await? {
+        return Err(user_not_found(user_id))
+    }
+    Ok(UserAvatar { avatar_url: Some(avatar_url(user_id, &version)) })
+}
+
+
+/// Removes a user's avatar.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user.
+///
+/// # Returns
+/// - `Ok(UserAvatar)`: The user without an avatar.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such user.
+///
+/// # Notes
+/// The row is cleared before the files are deleted, so the user stops having an avatar even if
+/// the store cannot be reached.
+pub async fn delete_avatar<X, Y, O>(user_id: i32) -> Result<UserAvatar, NanoServiceError>
+where
+    X: SetUserAvatar,
+    Y: GetConfigVariable,
+    O: DeleteObject
+{
+    if !X::set_user_avatar(user_id, None).await? {
+        return Err(user_not_found(user_id))
+    }
+    for size in AvatarSize::ALL {
+        O::delete_object::<Y>(&size.object_key(user_id)).await?;
+    }
+    Ok(UserAvatar { avatar_url: None })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use kernel::content_scan::engine_mock::{InfectedScanMock, PassScanMock};
+    use kernel::object_store::engine_mock::{MemoryObjectStoreMock, STORED_OBJECTS};
+    use std::io::Cursor;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "AVATAR_MAX_BYTES" => Ok("4096".to_string()),
+                _ => Err(NanoServiceError::new("not set".to_string(), NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    /// Only users below 100 exist.
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, SetUserAvatar, set_user_avatar)]
+    async fn set_user_avatar(id: i32, _avatar_version: Option<String>) -> Result<bool, NanoServiceError> {
+        Ok(id < 100)
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar() {
+        let avatar = upload_avatar::<MockDbHandle, MockConfig, MemoryObjectStoreMock, PassScanMock>(11, png(30, 20)).await.unwrap();
+        let stored = STORED_OBJECTS.lock().unwrap().get("avatars/11/256.png").cloned().unwrap();
+        assert_eq!(avatar.avatar_url, Some(avatar_url(11, &avatar_version(&stored))));
+        assert!(AvatarSize::ALL.iter().all(|size| STORED_OBJECTS.lock().unwrap().contains_key(&size.object_key(11))));
+
+        let missing = upload_avatar::<MockDbHandle, MockConfig, MemoryObjectStoreMock, PassScanMock>(100, png(30, 20)).await.unwrap_err();
+        assert_eq!(missing.status, NanoServiceErrorStatus::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_upload_refusals() {
+        let refused = |upload: Vec<u8>| upload_avatar::<MockDbHandle, MockConfig, MemoryObjectStoreMock, PassScanMock>(12, upload);
+        assert_eq!(refused(vec![0; 4097]).await.unwrap_err().status, NanoServiceErrorStatus::PayloadTooLarge);
+        assert_eq!(refused(b"<svg/>".to_vec()).await.unwrap_err().status, NanoServiceErrorStatus::BadRequest);
+
+        let infected = upload_avatar::<MockDbHandle, MockConfig, MemoryObjectStoreMock, InfectedScanMock>(12, png(30, 20)).await.unwrap_err();
+        assert_eq!(infected.status, NanoServiceErrorStatus::UnprocessableEntity);
+        assert!(!STORED_OBJECTS.lock().unwrap().contains_key("avatars/12/256.png"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_avatar() {
+        upload_avatar::<MockDbHandle, MockConfig, MemoryObjectStoreMock, PassScanMock>(13, png(8, 8)).await.unwrap();
+        let avatar = delete_avatar::<MockDbHandle, MockConfig, MemoryObjectStoreMock>(13).await.unwrap();
+        assert_eq!(avatar.avatar_url, None);
+        assert!(AvatarSize::ALL.iter().all(|size| !STORED_OBJECTS.lock().unwrap().contains_key(&size.object_key(13))));
+    }
+}
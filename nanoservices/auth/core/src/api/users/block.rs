@@ -1,21 +1,30 @@
 //! Core logic for blocking a user
+use utils::config::GetConfigVariable;
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 use dal::users::tx_definitions::BlockUser;
+use kernel::events::{publish, DomainEvent, EventBus};
 
 
 /// Blocks a user in the database by setting the `blocked` attribute to `true`.
 /// 
 /// # Arguments
 /// * `user_id` - The ID of the user to block.
-pub async fn block_user<X>(user_id: i32) -> Result<(), NanoServiceError> 
+/// * `blocked_by` - The ID of the super admin blocking them.
+///
+/// # Notes
+/// A `user_blocked` event is published with `E` once the user is blocked.
+pub async fn block_user<X, E, Y>(user_id: i32, blocked_by: i32) -> Result<(), NanoServiceError> 
 where
-    X: BlockUser
+    X: BlockUser,
+    E: EventBus,
+    Y: GetConfigVariable,
 {
     match X::block_user(user_id).await {
         Ok(outcome) => {
-            if outcome == false {
+            if !outcome {
                 return Err(NanoServiceError::new("Failed to block user".to_string(), NanoServiceErrorStatus::Unknown));
             }
+            publish::<E, Y>(DomainEvent::UserBlocked { user_id, blocked_by }).await;
             Ok(())
         },
         Err(e) => Err(e)
@@ -27,6 +36,8 @@ where
 mod tests {
     use super::*;
     use dal_tx_impl::impl_transaction;
+    use kernel::events::engine_mock::{RecordEventsMock, PUBLISHED_EVENTS};
+    use test_support::MissingConfig;
 
     #[tokio::test]
     async fn test_pass() {
@@ -38,7 +49,11 @@ mod tests {
             Ok(true)
         }
 
-        let outcome = block_user::<MockPostgres>(1).await.unwrap();
-        assert_eq!(outcome, ());
+        block_user::<MockPostgres, RecordEventsMock, MissingConfig>(1, 4).await.unwrap();
+        let published: Vec<_> = PUBLISHED_EVENTS.lock().unwrap().iter()
+            .map(|envelope| envelope.event.clone())
+            .filter(|event| matches!(event, DomainEvent::UserBlocked { .. }))
+            .collect();
+        assert_eq!(published, vec![DomainEvent::UserBlocked { user_id: 1, blocked_by: 4 }]);
     }
 }
\ No newline at end of file
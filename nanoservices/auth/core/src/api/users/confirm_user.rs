@@ -13,7 +13,7 @@ where
 {
     match X::confirm_user(unique_id.to_string()).await {
         Ok(outcome) => {
-            if outcome == false {
+            if !outcome {
                 return Err(NanoServiceError::new("Failed to confirm user".to_string(), NanoServiceErrorStatus::Unknown));
             }
             Ok(())
@@ -38,7 +38,6 @@ mod tests {
             Ok(true)
         }
 
-        let outcome = confirm_user::<MockPostgres>("test_unique_id").await.unwrap();
-        assert_eq!(outcome, ());
+        confirm_user::<MockPostgres>("test_unique_id").await.unwrap();
     }
 }
\ No newline at end of file
@@ -12,20 +12,20 @@
 //! # Notes
 //! - The `create_user` function is generic, enabling flexibility with different database implementations.
 //! - The tests include a mock database implementation for validation of core logic.
-use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
-use dal::users::tx_definitions::CreateUser;
-use dal::role_permissions::tx_definitions::CreateRolePermission;
+use utils::errors::NanoServiceError;
+use dal::users::tx_definitions::{CreateUserWithConfirmationEmail, CreateUserWithRolePermission, CountUsers};
+use dal::plans::tx_definitions::GetOrgPlan;
 use dal::rate_limit_entries::tx_definitions::{
     CreateRateLimitEntry,
     UpdateRateLimitEntry,
     GetRateLimitEntry,
 };
 use utils::config::GetConfigVariable;
-use email_core::api::mailchimp_emails::confirmation_email::send_confirmation_email;
-use email_core::mailchimp_traits::mc_definitions::SendTemplate;
+use email_core::api::mailchimp_emails::confirmation_email::confirmation_outbox_email;
 use kernel::users::{User, NewUserSchema};
-use kernel::role_permissions::NewRolePermission;
 use kernel::users::UserRole;
+use kernel::analytics::{AnalyticsEvent, AnalyticsSink, emit};
+use kernel::events::{DomainEvent, EventBus, publish};
 
 
 /// Creates a new user by converting the input schema into a `NewUser`
@@ -36,18 +36,25 @@ use kernel::users::UserRole;
 ///
 /// # Returns
 /// - `Ok(User)`: The newly created user if the operation is successful.
-/// - `Err(NanoServiceError)`: If an error occurs during the operation.
+/// - `Err(NanoServiceError)`: `UpgradeRequired` if the plan's user limit has been reached, or if
+///   an error occurs during the operation.
 ///
 /// # Notes
-/// - This function uses the `CreateUser` trait to perform the database operation.
+/// - A `user_created` event is sent to `A`, and published with `E`, once the user exists.
+/// - The confirmation email is queued in the outbox in the same transaction as the user with
+///   `CreateUserWithConfirmationEmail`, and sent by the outbox worker, so the request does not wait
+///   on the email provider and a user is never left without their confirmation email. Outside of
+///   production no email is queued and the user is created with `CreateUserWithRolePermission`.
 /// - Errors during schema conversion or database transactions are propagated as `NanoServiceError`.
-pub async fn create_user<X, Y, Z>(
+pub async fn create_user<X, Z, A, E>(
     new_user_schema: NewUserSchema
 ) -> Result<User, NanoServiceError> 
 where
-    X: CreateUser + CreateRolePermission + CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry,
-    Y: SendTemplate,
+    X: CreateUserWithConfirmationEmail + CreateUserWithRolePermission + CreateRateLimitEntry + UpdateRateLimitEntry
+        + GetRateLimitEntry + GetOrgPlan + CountUsers,
     Z: GetConfigVariable,
+    A: AnalyticsSink,
+    E: EventBus,
 {
     if new_user_schema.user_role == UserRole::SuperAdmin {
         return Err(NanoServiceError::new(
@@ -56,22 +63,16 @@ where
         ))
     }
     let new_user = new_user_schema.to_new_user()?;
+    X::get_org_plan().await?.entitlements().check_max_users(X::count_users().await?)?;
 
-    let user = X::create_user(new_user).await?;
-    let role_permission = NewRolePermission{
-        user_id: user.id,
-        role: user.user_role.clone(),
-    };
-    X::create_role_permission(role_permission).await?;
-
-    match send_confirmation_email::<X, Y, Z>(user.email.clone(), user.uuid.clone()).await {
-        Ok(outcome) => {
-            if outcome == false {
-                return Err(NanoServiceError::new("Failed to send confirmation email due to a rate limit error".to_string(), NanoServiceErrorStatus::Unknown))
-            }
-        },
-        Err(e) => return Err(NanoServiceError::new(e.to_string(), NanoServiceErrorStatus::Unknown))
+    // the user, their role permission and their confirmation email are created in one transaction
+    // so none exists without the others
+    let user = match confirmation_outbox_email::<X, Z>(&new_user).await? {
+        Some(email) => X::create_user_with_confirmation_email(new_user, email).await?,
+        None => X::create_user_with_role_permission(new_user).await?
     };
+    emit::<A, Z>(AnalyticsEvent::user_created(&user)).await;
+    publish::<E, Z>(DomainEvent::user_created(&user)).await;
 
     Ok(user)
 }
@@ -81,13 +82,17 @@ mod tests {
     use super::*;
     use dal_tx_impl::impl_transaction;
     use kernel::users::NewUser;
-    use kernel::role_permissions::RolePermission;
     use kernel::rate_limit_entries::{RateLimitEntry, NewRateLimitEntry};
+    use kernel::plans::{OrgPlan, Plan};
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::LazyLock;
     use chrono::{Utc, Duration};
     use utils::config::GetConfigVariable;
-    use email_core::mailchimp_helpers::mailchimp_template::Template;
+    use email_core::api::mailchimp_emails::template_check::CONFIRMATION_EMAIL_TEMPLATE;
+    use kernel::email_outbox::NewOutboxEmail;
+    use kernel::analytics::ProductEvent;
+    use kernel::analytics::engine_mock::{NoAnalyticsMock, RecordAnalyticsMock, RECORDED_EVENTS};
+    use kernel::events::engine_mock::{NoEventsMock, RecordEventsMock, PUBLISHED_EVENTS};
 
     fn generate_user(user: NewUser) -> User {
         let now = chrono::Utc::now().naive_utc();
@@ -104,6 +109,18 @@ mod tests {
             date_created: now,
             last_logged_in: now,
             blocked: user.blocked,
+            avatar_version: None,
+        }
+    }
+
+    fn org_plan() -> OrgPlan {
+        OrgPlan {
+            plan: Plan::Free,
+            max_users: None,
+            max_todos: None,
+            mfa: None,
+            api_access: None,
+            date_updated: chrono::Utc::now().naive_utc(),
         }
     }
 
@@ -111,24 +128,25 @@ mod tests {
     async fn test_pass() {
         static CREATE_USER_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
         static CREATE_ROLE_PERMISSION_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
-        static SEND_TEMPLATE_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
+        static EMAIL_QUEUED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
 
         struct MockDbHandle;
 
-        #[impl_transaction(MockDbHandle, CreateUser, create_user)]
-        async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
+        #[impl_transaction(MockDbHandle, CreateUserWithRolePermission, create_user_with_role_permission)]
+        async fn create_user_with_role_permission(user: NewUser) -> Result<User, NanoServiceError> {
             CREATE_USER_CALLED.store(true, Ordering::Relaxed);
+            CREATE_ROLE_PERMISSION_CALLED.store(true, Ordering::Relaxed);
             Ok(generate_user(user))
         }
 
-        #[impl_transaction(MockDbHandle, CreateRolePermission, create_role_permission)]
-        async fn create_role_permission(role_permission: NewRolePermission) -> Result<RolePermission, NanoServiceError> {
+        #[impl_transaction(MockDbHandle, CreateUserWithConfirmationEmail, create_user_with_confirmation_email)]
+        async fn create_user_with_confirmation_email(user: NewUser, email: NewOutboxEmail) -> Result<User, NanoServiceError> {
+            assert_eq!(email.template["message"]["to"][0]["email"], user.email);
+            assert_eq!(email.template["template_name"], CONFIRMATION_EMAIL_TEMPLATE);
+            EMAIL_QUEUED.store(true, Ordering::Relaxed);
+            CREATE_USER_CALLED.store(true, Ordering::Relaxed);
             CREATE_ROLE_PERMISSION_CALLED.store(true, Ordering::Relaxed);
-            Ok(RolePermission{
-                id: 1,
-                user_id: role_permission.user_id,
-                role: role_permission.role.clone()
-            })
+            Ok(generate_user(user))
         }
 
         #[impl_transaction(MockDbHandle, CreateRateLimitEntry, create_rate_limit_entry)]
@@ -160,12 +178,14 @@ mod tests {
             Ok(true)
         }
 
-        struct MockMailchimpHandle;
+        #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+        async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+            Ok(org_plan())
+        }
 
-        #[impl_transaction(MockMailchimpHandle, SendTemplate, send_template)]
-        async fn send_template(_template: &Template) -> Result<bool, NanoServiceError> {
-            SEND_TEMPLATE_CALLED.store(true, Ordering::Relaxed);
-            Ok(true)
+        #[impl_transaction(MockDbHandle, CountUsers, count_users)]
+        async fn count_users() -> Result<i64, NanoServiceError> {
+            Ok(4)
         }
 
         struct FakeConfig;
@@ -188,15 +208,25 @@ mod tests {
             user_role: UserRole::Admin
         };
 
-        let result = create_user::<MockDbHandle, MockMailchimpHandle, FakeConfig>(new_user_schema).await;
+        let result = create_user::<MockDbHandle, FakeConfig, RecordAnalyticsMock, RecordEventsMock>(new_user_schema).await;
         match result {
             Ok(_) => {
             },
             _ => panic!("Expected user"),
         }
+        let recorded: Vec<_> = RECORDED_EVENTS.lock().unwrap().iter()
+            .filter(|event| event.event == ProductEvent::UserCreated)
+            .map(|event| serde_json::Value::Object(event.properties.clone()))
+            .collect();
+        assert_eq!(recorded, vec![serde_json::json!({"role": "Admin", "confirmed": false})]);
+        let published: Vec<_> = PUBLISHED_EVENTS.lock().unwrap().iter()
+            .map(|envelope| envelope.event.clone())
+            .filter(|event| matches!(event, DomainEvent::UserCreated { .. }))
+            .collect();
+        assert_eq!(published, vec![DomainEvent::UserCreated { user_id: 1, role: UserRole::Admin }]);
         assert!(CREATE_USER_CALLED.load(Ordering::Relaxed));
         assert!(CREATE_ROLE_PERMISSION_CALLED.load(Ordering::Relaxed));
-        assert!(SEND_TEMPLATE_CALLED.load(Ordering::Relaxed));
+        assert!(EMAIL_QUEUED.load(Ordering::Relaxed));
     }
 
 
@@ -204,24 +234,25 @@ mod tests {
     async fn test_try_create_super_user() {
         static CREATE_USER_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
         static CREATE_ROLE_PERMISSION_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
-        static SEND_TEMPLATE_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
+        static EMAIL_QUEUED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
 
         struct MockDbHandle;
 
-        #[impl_transaction(MockDbHandle, CreateUser, create_user)]
-        async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
+        #[impl_transaction(MockDbHandle, CreateUserWithRolePermission, create_user_with_role_permission)]
+        async fn create_user_with_role_permission(user: NewUser) -> Result<User, NanoServiceError> {
             CREATE_USER_CALLED.store(true, Ordering::Relaxed);
+            CREATE_ROLE_PERMISSION_CALLED.store(true, Ordering::Relaxed);
             Ok(generate_user(user))
         }
 
-        #[impl_transaction(MockDbHandle, CreateRolePermission, create_role_permission)]
-        async fn create_role_permission(role_permission: NewRolePermission) -> Result<RolePermission, NanoServiceError> {
+        #[impl_transaction(MockDbHandle, CreateUserWithConfirmationEmail, create_user_with_confirmation_email)]
+        async fn create_user_with_confirmation_email(user: NewUser, email: NewOutboxEmail) -> Result<User, NanoServiceError> {
+            assert_eq!(email.template["message"]["to"][0]["email"], user.email);
+            assert_eq!(email.template["template_name"], CONFIRMATION_EMAIL_TEMPLATE);
+            EMAIL_QUEUED.store(true, Ordering::Relaxed);
+            CREATE_USER_CALLED.store(true, Ordering::Relaxed);
             CREATE_ROLE_PERMISSION_CALLED.store(true, Ordering::Relaxed);
-            Ok(RolePermission{
-                id: 1,
-                user_id: role_permission.user_id,
-                role: role_permission.role.clone()
-            })
+            Ok(generate_user(user))
         }
 
         #[impl_transaction(MockDbHandle, CreateRateLimitEntry, create_rate_limit_entry)]
@@ -253,12 +284,14 @@ mod tests {
             Ok(true)
         }
 
-        struct MockMailchimpHandle;
+        #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+        async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+            Ok(org_plan())
+        }
 
-        #[impl_transaction(MockMailchimpHandle, SendTemplate, send_template)]
-        async fn send_template(_template: &Template) -> Result<bool, NanoServiceError> {
-            SEND_TEMPLATE_CALLED.store(true, Ordering::Relaxed);
-            Ok(true)
+        #[impl_transaction(MockDbHandle, CountUsers, count_users)]
+        async fn count_users() -> Result<i64, NanoServiceError> {
+            Ok(4)
         }
 
         struct FakeConfig;
@@ -281,7 +314,7 @@ mod tests {
             user_role: UserRole::SuperAdmin,
         };
 
-        let result = create_user::<MockDbHandle, MockMailchimpHandle, FakeConfig>(new_user_schema).await;
+        let result = create_user::<MockDbHandle, FakeConfig, NoAnalyticsMock, NoEventsMock>(new_user_schema).await;
         match result {
             Err(e) => {
                 assert_eq!(e.status, utils::errors::NanoServiceErrorStatus::Unauthorized);
@@ -290,7 +323,7 @@ mod tests {
             _ => panic!("Expected error"),
         }
         assert!(!CREATE_USER_CALLED.load(Ordering::Relaxed));
-        assert!(!SEND_TEMPLATE_CALLED.load(Ordering::Relaxed));
+        assert!(!EMAIL_QUEUED.load(Ordering::Relaxed));
         assert!(!CREATE_ROLE_PERMISSION_CALLED.load(Ordering::Relaxed));
     }
 }
\ No newline at end of file
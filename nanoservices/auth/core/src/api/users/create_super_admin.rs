@@ -4,9 +4,7 @@
 //! of networking and handle the business logic.
 use kernel::users::{NewUser, User, UserRole};
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
-use dal::users::tx_definitions::CreateUser;
-use dal::role_permissions::tx_definitions::CreateRolePermission;
-use kernel::role_permissions::NewRolePermission;
+use dal::users::tx_definitions::{CreateUserWithRolePermission, GetRecipientProfile};
 use dal::rate_limit_entries::tx_definitions::{
     CreateRateLimitEntry,
     UpdateRateLimitEntry,
@@ -15,20 +13,22 @@ use dal::rate_limit_entries::tx_definitions::{
 use utils::config::GetConfigVariable;
 use email_core::api::mailchimp_emails::confirmation_email::send_confirmation_email;
 use email_core::mailchimp_traits::mc_definitions::SendTemplate;
+use crate::password_policy::enforce_password_policy;
+use crate::password_policy::breach::PwnedPasswordRange;
 
 
 /// Creates a super user in the system.
 ///
 /// # Arguments
 /// - `email`: The email address of the super user.
-/// - `password`: The plaintext password for the super user.
+/// - `password`: The plaintext password for the super user, which has to meet the password policy.
 /// - `first_name`: The first name of the super user.
 /// - `last_name`: The last name of the super user.
 ///
 /// # Returns
 /// - `Ok(User)`: The newly created super user if successful.
 /// - `Err(NanoServiceError)`: If an error occurs during user creation.
-pub async fn create_super_user<X, Y, Z>(
+pub async fn create_super_user<X, Y, Z, B>(
     username: String,
     email: String,
     first_name: String,
@@ -36,10 +36,21 @@ pub async fn create_super_user<X, Y, Z>(
     password: String,
 ) -> Result<User, NanoServiceError> 
 where
-    X: CreateUser + CreateRolePermission + CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry,
+    X: CreateUserWithRolePermission + CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry + GetRecipientProfile,
     Y: SendTemplate,
     Z: GetConfigVariable,
+    B: PwnedPasswordRange,
 {
+    let super_admins = ["maxwellflitton@gmail.com", "raf@gmail.com", "zak@gmail.com"];
+
+    if !super_admins.contains(&email.as_str()) {
+        return Err(NanoServiceError::new(
+            format!("email: {} is not allowed to be a super admin", email),
+            NanoServiceErrorStatus::Unauthorized,
+        ));
+    }
+    enforce_password_policy::<Z, B>(&password).await?;
+
     // Create a `NewUser` object with the SuperAdmin role
     let new_user = NewUser::new(
         username,
@@ -49,41 +60,19 @@ where
         UserRole::SuperAdmin,
         password,
     )?;
-
-    let super_admins = ["maxwellflitton@gmail.com", "raf@gmail.com", "zak@gmail.com"];
-
-    if super_admins.contains(&new_user.email.as_str()) == false {
-        return Err(NanoServiceError::new(
-            format!("email: {} is not allowed to be a super admin", new_user.email),
-            NanoServiceErrorStatus::Unauthorized,
-        ));
-    }
     println!("Creating super user: {}", new_user.email);
 
-    // Use the CreateUser trait to insert the user into the database
-    let user = X::create_user(new_user).await.map_err(|e| {
+    // Insert the user along with their super admin role permission in one transaction
+    let user = X::create_user_with_role_permission(new_user).await.map_err(|e| {
         NanoServiceError::new(
             format!("Failed to create super user: {}", e),
             NanoServiceErrorStatus::Unknown,
         )
     })?;
 
-    let new_role_permission = NewRolePermission {
-        user_id: user.id,
-        role: UserRole::SuperAdmin,
-    };
-
-    // Create a role permission for the super admin
-    let _ = X::create_role_permission(new_role_permission).await.map_err(|e| {
-        NanoServiceError::new(
-            format!("Failed to create role permission for super user: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        )
-    })?;
-
     match send_confirmation_email::<X, Y, Z>(user.email.clone(), user.uuid.clone()).await {
         Ok(outcome) => {
-            if outcome == false {
+            if !outcome {
                 return Err(NanoServiceError::new("Failed to send confirmation email due to a rate limit error".to_string(), NanoServiceErrorStatus::Unknown))
             }
         },
@@ -98,8 +87,8 @@ where
 mod tests {
 
     use super::*;
+    use kernel::users::RecipientProfile;
     use dal_tx_impl::impl_transaction;
-    use kernel::role_permissions::RolePermission;
     use kernel::rate_limit_entries::{RateLimitEntry, NewRateLimitEntry};
     use chrono::{Utc, Duration};
     use utils::config::GetConfigVariable;
@@ -107,8 +96,8 @@ mod tests {
 
     struct MockDbHandleOK;
 
-    #[impl_transaction(MockDbHandleOK, CreateUser, create_user)]
-    async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
+    #[impl_transaction(MockDbHandleOK, CreateUserWithRolePermission, create_user_with_role_permission)]
+    async fn create_user_with_role_permission(user: NewUser) -> Result<User, NanoServiceError> {
         Ok(User {
             id: 1, // Mock ID
             confirmed: false,
@@ -122,15 +111,7 @@ mod tests {
             last_logged_in: Utc::now().naive_utc(),
             blocked: user.blocked,
             uuid: user.uuid.clone(),
-        })
-    }
-
-    #[impl_transaction(MockDbHandleOK, CreateRolePermission, create_role_permission)]
-    async fn create_role_permission(role_permission: NewRolePermission) -> Result<RolePermission, NanoServiceError> {
-        Ok(RolePermission {
-            id: 1, // Mock ID
-            user_id: role_permission.user_id,
-            role: role_permission.role.clone(),
+            avatar_version: None,
         })
     }
 
@@ -146,6 +127,11 @@ mod tests {
         })
     }
 
+    #[impl_transaction(MockDbHandleOK, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(_email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        Ok(None)
+    }
+
     #[impl_transaction(MockDbHandleOK, GetRateLimitEntry, get_rate_limit_entry)]
     async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
         Ok(Some(RateLimitEntry {
@@ -170,6 +156,13 @@ mod tests {
         Ok(true)
     }
 
+    struct MockPwned;
+
+    #[impl_transaction(MockPwned, PwnedPasswordRange, pwned_password_range)]
+    async fn pwned_password_range(_prefix: String) -> Result<String, NanoServiceError> {
+        Ok(String::new())
+    }
+
     struct FakeConfig;
 
     impl GetConfigVariable for FakeConfig {
@@ -190,10 +183,10 @@ mod tests {
         let email = "zak@gmail.com".to_string();
         let first_name = "John".to_string();
         let last_name = "Doe".to_string();
-        let password = "securepassword".to_string();
+        let password = "Secure-password-1".to_string();
 
         // Call `create_super_user` with valid input
-        let result = create_super_user::<MockDbHandleOK, MockMailchimpHandle, FakeConfig>(
+        let result = create_super_user::<MockDbHandleOK, MockMailchimpHandle, FakeConfig, MockPwned>(
             username.clone(),
             email.clone(),
             first_name.clone(),
@@ -211,7 +204,7 @@ mod tests {
             assert_eq!(user.first_name, first_name);
             assert_eq!(user.last_name, last_name);
             assert_eq!(user.user_role, UserRole::SuperAdmin);
-            assert_eq!(user.blocked, false);
+            assert!(!user.blocked);
         } else {
             panic!("Expected Ok(User), but got an error");
         }
@@ -224,10 +217,10 @@ mod tests {
         let email = "test@example.com".to_string();
         let first_name = "John".to_string();
         let last_name = "Doe".to_string();
-        let password = "securepassword".to_string();
+        let password = "Secure-password-1".to_string();
 
         // Call `create_super_user` with an unauthorized email
-        let result = create_super_user::<MockDbHandleOK, MockMailchimpHandle, FakeConfig>(
+        let result = create_super_user::<MockDbHandleOK, MockMailchimpHandle, FakeConfig, MockPwned>(
             username.clone(),
             email.clone(),
             first_name.clone(),
@@ -248,4 +241,16 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_create_super_user_weak_password() {
+        let result = create_super_user::<MockDbHandleOK, MockMailchimpHandle, FakeConfig, MockPwned>(
+            "superadmin_user".to_string(),
+            "zak@gmail.com".to_string(),
+            "John".to_string(),
+            "Doe".to_string(),
+            "securepassword".to_string(),
+        ).await;
+        assert_eq!(result.unwrap_err().status, NanoServiceErrorStatus::BadRequest);
+    }
+
 }
@@ -0,0 +1,16 @@
+//! Core logic for super admins exporting every user's profile.
+use dal::users::tx_definitions::StreamUserProfiles;
+use utils::errors::NanoServiceError;
+use utils::export_stream::{export_rows, Bytes, ExportEncoding, ExportFormat, Stream, DEFAULT_EXPORT_PAGE_SIZE};
+
+
+/// Streams every user's profile as CSV, encoding `DEFAULT_EXPORT_PAGE_SIZE` users into each chunk.
+///
+/// # Arguments
+/// - `encoding`: Whether to gzip the export.
+///
+/// # Returns
+/// - The bytes of the export, read from the database as the client takes them.
+pub fn export_user_profiles<X: StreamUserProfiles>(encoding: ExportEncoding) -> impl Stream<Item = Result<Bytes, NanoServiceError>> {
+    export_rows(ExportFormat::Csv, encoding, DEFAULT_EXPORT_PAGE_SIZE, X::stream_user_profiles())
+}
@@ -87,6 +87,7 @@ mod tests {
             last_logged_in: now,
             blocked: false,
             uuid: "mock-uuid".to_string(),
+            avatar_version: None,
         }
     }
 
@@ -1,6 +1,7 @@
 //! Gets all the user profiles.
 use dal::users::tx_definitions::GetAllUserProfiles;
 use kernel::users::UserProfile;
+use kernel::pagination::{Page, PageQuery};
 use utils::errors::NanoServiceError;
 
 
@@ -11,3 +12,16 @@ use utils::errors::NanoServiceError;
 pub async fn get_all_user_profiles<X: GetAllUserProfiles>() -> Result<Vec<UserProfile>, NanoServiceError> {
     X::get_all_user_profiles().await
 }
+
+
+/// Retrieves a page of the user profiles.
+///
+/// # Arguments
+/// - `page`: The page asked for.
+///
+/// # Returns
+/// - `Ok(Page<UserProfile>)`: The profiles on the page and how many there are.
+/// - `Err(NanoServiceError)`: `BadRequest` if the limit or cursor is invalid.
+pub async fn get_user_profiles_page<X: GetAllUserProfiles>(page: &PageQuery) -> Result<Page<UserProfile>, NanoServiceError> {
+    Page::from_items(X::get_all_user_profiles().await?, page)
+}
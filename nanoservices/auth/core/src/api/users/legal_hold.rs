@@ -0,0 +1,164 @@
+//! Core logic for super admins placing and releasing legal holds on users.
+//!
+//! # Overview
+//! A hold stops the user being deleted and the retention purge removing the rows about them, see
+//! `kernel::legal_holds`. Placing and releasing a hold are both written to the audit log so the
+//! time the data was exempt can be shown later.
+use dal::legal_holds::tx_definitions::{PlaceLegalHold, ReleaseLegalHold, GetLegalHolds};
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use kernel::audit_log::NewAuditEntry;
+use kernel::legal_holds::{NewLegalHold, LegalHold, LEGAL_HOLD_PLACED_ACTION, LEGAL_HOLD_RELEASED_ACTION};
+use serde_json::json;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// Places a hold on a user, or replaces the reason of the hold already in place.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user whose data is held.
+/// - `placed_by`: The ID of the super admin placing the hold.
+/// - `reason`: Why the data is held, such as a case reference.
+///
+/// # Returns
+/// - `Ok(LegalHold)`: The hold.
+/// - `Err(NanoServiceError)`: `BadRequest` if the reason is blank, `NotFound` if there is no such user.
+pub async fn place_legal_hold<X>(user_id: i32, placed_by: i32, reason: String) -> Result<LegalHold, NanoServiceError>
+where
+    X: PlaceLegalHold + CreateAuditEntry
+{
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(NanoServiceError::new(
+            "A legal hold needs a reason".to_string(),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    }
+    let hold = X::place_legal_hold(NewLegalHold { user_id, placed_by, reason }).await?.ok_or_else(|| NanoServiceError::new(
+        format!("User {} not found", user_id),
+        NanoServiceErrorStatus::NotFound
+    ))?;
+    X::create_audit_entry(NewAuditEntry {
+        actor_id: placed_by,
+        action: LEGAL_HOLD_PLACED_ACTION.to_string(),
+        subject_id: Some(user_id),
+        details: json!({"reason": hold.reason}),
+    }).await?;
+    Ok(hold)
+}
+
+
+/// Releases the hold on a user, after which they can be deleted and purged as normal.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user whose data was held.
+/// - `released_by`: The ID of the super admin releasing the hold.
+///
+/// # Returns
+/// - `Ok(())`: If the hold was released.
+/// - `Err(NanoServiceError)`: `NotFound` if the user was not under a hold.
+pub async fn release_legal_hold<X>(user_id: i32, released_by: i32) -> Result<(), NanoServiceError>
+where
+    X: ReleaseLegalHold + CreateAuditEntry
+{
+    if !X::release_legal_hold(user_id).await? {
+        return Err(NanoServiceError::new(
+            format!("User {} is not under a legal hold", user_id),
+            NanoServiceErrorStatus::NotFound
+        ))
+    }
+    X::create_audit_entry(NewAuditEntry {
+        actor_id: released_by,
+        action: LEGAL_HOLD_RELEASED_ACTION.to_string(),
+        subject_id: Some(user_id),
+        details: json!({}),
+    }).await?;
+    Ok(())
+}
+
+
+/// Lists every hold in place, oldest first.
+pub async fn get_legal_holds<X: GetLegalHolds>() -> Result<Vec<LegalHold>, NanoServiceError> {
+    X::get_legal_holds().await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::audit_log::AuditEntry;
+    use kernel::chrono::Utc;
+    use sqlx::types::Json;
+    use std::sync::Mutex;
+
+    static AUDITED: Mutex<Vec<NewAuditEntry>> = Mutex::new(Vec::new());
+
+    struct MockDbHandle;
+
+    /// Only user 2 exists.
+    #[impl_transaction(MockDbHandle, PlaceLegalHold, place_legal_hold)]
+    async fn place_legal_hold(hold: NewLegalHold) -> Result<Option<LegalHold>, NanoServiceError> {
+        if hold.user_id != 2 {
+            return Ok(None)
+        }
+        Ok(Some(LegalHold {
+            user_id: hold.user_id,
+            username: "ada".to_string(),
+            email: "ada@example.com".to_string(),
+            placed_by: hold.placed_by,
+            reason: hold.reason,
+            date_created: Utc::now().naive_utc(),
+        }))
+    }
+
+    /// Only user 2 is under a hold.
+    #[impl_transaction(MockDbHandle, ReleaseLegalHold, release_legal_hold)]
+    async fn release_legal_hold(user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(user_id == 2)
+    }
+
+    #[impl_transaction(MockDbHandle, CreateAuditEntry, create_audit_entry)]
+    async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        AUDITED.lock().unwrap().push(entry.clone());
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: Json(entry.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    fn audited(action: &str) -> Vec<NewAuditEntry> {
+        AUDITED.lock().unwrap().iter().filter(|entry| entry.action == action).cloned().collect()
+    }
+
+    #[tokio::test]
+    async fn test_place_legal_hold() {
+        let hold = place_legal_hold::<MockDbHandle>(2, 1, "  case 42  ".to_string()).await.unwrap();
+        assert_eq!(hold.reason, "case 42");
+        assert_eq!(audited(LEGAL_HOLD_PLACED_ACTION), vec![NewAuditEntry {
+            actor_id: 1,
+            action: LEGAL_HOLD_PLACED_ACTION.to_string(),
+            subject_id: Some(2),
+            details: json!({"reason": "case 42"}),
+        }]);
+
+        let blank = place_legal_hold::<MockDbHandle>(2, 1, " ".to_string()).await.unwrap_err();
+        assert_eq!(blank.status, NanoServiceErrorStatus::BadRequest);
+        let missing = place_legal_hold::<MockDbHandle>(9, 1, "case 42".to_string()).await.unwrap_err();
+        assert_eq!(missing.status, NanoServiceErrorStatus::NotFound);
+        assert_eq!(audited(LEGAL_HOLD_PLACED_ACTION).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_release_legal_hold() {
+        release_legal_hold::<MockDbHandle>(2, 1).await.unwrap();
+        assert_eq!(audited(LEGAL_HOLD_RELEASED_ACTION).len(), 1);
+
+        let not_held = release_legal_hold::<MockDbHandle>(3, 1).await.unwrap_err();
+        assert_eq!(not_held.status, NanoServiceErrorStatus::NotFound);
+        assert_eq!(audited(LEGAL_HOLD_RELEASED_ACTION).len(), 1);
+    }
+}
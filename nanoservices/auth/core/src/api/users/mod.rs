@@ -4,7 +4,10 @@ pub mod unblock;
 pub mod block;
 pub mod get;
 pub mod get_all_profiles;
+pub mod export;
 pub mod confirm_user;
 pub mod reset_password;
 pub mod update;
-pub mod delete_user;
\ No newline at end of file
+pub mod delete_user;pub mod search;
+pub mod legal_hold;
+pub mod avatar;
@@ -1,24 +1,35 @@
 //! Core logic for resetting a users password
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 use dal::users::tx_definitions::ResetPassword;
+use dal::onboarding::tx_definitions::RecordPasswordSet;
 use kernel::users::hash_password;
+use utils::config::GetConfigVariable;
+use crate::password_policy::enforce_password_policy;
+use crate::password_policy::breach::PwnedPasswordRange;
 
 
 /// Resets a users password.
 /// 
 /// # Arguments
 /// * `uuid` - The uuid of the user.
-/// * 'new_password' - The new password for the user.
-pub async fn reset_password<X>(uuid: &str, new_password: &str) -> Result<(), NanoServiceError> 
+/// * 'new_password' - The new password for the user, which has to meet the password policy.
+///
+/// # Notes
+/// - Setting the password also completes the set password step of onboarding.
+pub async fn reset_password<X, Y, B>(uuid: &str, new_password: &str) -> Result<(), NanoServiceError> 
 where
-    X: ResetPassword
+    X: ResetPassword + RecordPasswordSet,
+    Y: GetConfigVariable,
+    B: PwnedPasswordRange,
 {
+    enforce_password_policy::<Y, B>(new_password).await?;
     let hashed_password = hash_password(new_password.to_string())?;
     match X::reset_password(uuid.to_string(), hashed_password).await {
         Ok(outcome) => {
-            if outcome == false {
+            if !outcome {
                 return Err(NanoServiceError::new("Failed to reset password".to_string(), NanoServiceErrorStatus::Unknown));
             }
+            X::record_password_set(uuid.to_string()).await?;
             Ok(())
         },
         Err(e) => Err(e)
@@ -31,17 +42,43 @@ mod tests {
     use super::*;
     use dal_tx_impl::impl_transaction;
 
-    #[tokio::test]
-    async fn test_pass() {
-        struct MockPostgres;
+    struct MockPostgres;
+
+    #[impl_transaction(MockPostgres, ResetPassword, reset_password)]
+    async fn reset_password(uuid: String, _new_password: String) -> Result<bool, NanoServiceError> {
+        assert_eq!(uuid, "test_uuid");
+        Ok(true)
+    }
 
-        #[impl_transaction(MockPostgres, ResetPassword, reset_password)]
-        async fn reset_password(uuid: String, _new_password: String) -> Result<bool, NanoServiceError> {
-            assert_eq!(uuid, "test_uuid");
-            Ok(true)
+    #[impl_transaction(MockPostgres, RecordPasswordSet, record_password_set)]
+    async fn record_password_set(uuid: String) -> Result<bool, NanoServiceError> {
+        assert_eq!(uuid, "test_uuid");
+        Ok(true)
+    }
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("".to_string())
         }
+    }
 
-        let outcome = reset_password::<MockPostgres>("test_uuid", "new_password").await.unwrap();
-        assert_eq!(outcome, ());
+    struct MockPwned;
+
+    #[impl_transaction(MockPwned, PwnedPasswordRange, pwned_password_range)]
+    async fn pwned_password_range(_prefix: String) -> Result<String, NanoServiceError> {
+        Ok(String::new())
+    }
+
+    #[tokio::test]
+    async fn test_pass() {
+        reset_password::<MockPostgres, FakeConfig, MockPwned>("test_uuid", "New-password-1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_weak_password_rejected() {
+        let error = reset_password::<MockPostgres, FakeConfig, MockPwned>("test_uuid", "new_password").await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
     }
 }
\ No newline at end of file
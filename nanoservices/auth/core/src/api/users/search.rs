@@ -0,0 +1,136 @@
+//! Core logic for admins finding users by part of their username, email or name.
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::users::tx_definitions::SearchUsers;
+use kernel::users::{PublicUser, UserSearchPage};
+use kernel::pagination::{Page, PageCursor, PageQuery};
+
+
+/// The page size used when the caller does not ask for one.
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+
+/// The largest page a caller can ask for.
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+/// The longest query accepted.
+const MAX_QUERY_LENGTH: usize = 255;
+
+
+/// Searches the members of an organization by part of their username, email or name.
+///
+/// # Arguments
+/// - `org_id`: The ID of the organization to search the members of.
+/// - `query`: The text to find, case insensitive.
+/// - `limit`: The most users to return, `DEFAULT_PAGE_SIZE` if `None`.
+/// - `offset`: The `next_offset` of the previous page, the first page if `None`.
+///
+/// # Returns
+/// - `Ok(UserSearchPage)`: The matching users and the offset of the next page.
+/// - `Err(NanoServiceError)`: `BadRequest` if the query is blank or too long, or the limit or
+///   offset is out of range.
+pub async fn search_users<X: SearchUsers>(org_id: i32, query: String, limit: Option<i64>, offset: Option<i64>) -> Result<UserSearchPage, NanoServiceError> {
+    let query = query.trim().to_string();
+    if query.is_empty() || query.chars().count() > MAX_QUERY_LENGTH {
+        return Err(NanoServiceError::new(
+            format!("q has to be between 1 and {} characters", MAX_QUERY_LENGTH),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    let offset = offset.unwrap_or(0);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) || offset < 0 {
+        return Err(NanoServiceError::new(
+            format!("limit has to be between 1 and {} and offset can not be negative", MAX_PAGE_SIZE),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    // one more than the page is read to tell whether there is another page
+    let mut users = X::search_users(org_id, query, limit + 1, offset).await?;
+    let next_offset = (users.len() as i64 > limit).then_some(offset + limit);
+    users.truncate(limit as usize);
+    Ok(UserSearchPage { users, next_offset })
+}
+
+
+
+/// Searches the members of an organization by part of their username, email or name, a page at a time.
+///
+/// # Arguments
+/// - `org_id`: The ID of the organization to search the members of.
+/// - `query`: The text to find, case insensitive.
+/// - `page`: The page asked for, the cursor holding the offset of the page.
+///
+/// # Returns
+/// - `Ok(Page<PublicUser>)`: The matching users and the cursor of the next page.
+/// - `Err(NanoServiceError)`: `BadRequest` as for `search_users`, or if the cursor is invalid.
+pub async fn search_users_page<X: SearchUsers>(org_id: i32, query: String, page: &PageQuery) -> Result<Page<PublicUser>, NanoServiceError> {
+    let found = search_users::<X>(org_id, query, Some(page.limit()?), page.after()?).await?;
+    Ok(Page {
+        items: found.users,
+        next_cursor: found.next_offset.map(|after| PageCursor { after }.encode()),
+        total: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::{PublicUser, UserRole};
+    use dal_tx_impl::impl_transaction;
+    use chrono::Utc;
+
+    struct MockDbHandle;
+
+    fn user(id: i32) -> PublicUser {
+        let now = Utc::now().naive_utc();
+        PublicUser {
+            id,
+            confirmed: true,
+            username: format!("ada{}", id),
+            email: format!("ada{}@example.com", id),
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            user_role: UserRole::Worker,
+            date_created: now,
+            last_logged_in: now,
+            blocked: false,
+            avatar_url: None,
+        }
+    }
+
+    /// Five users match, the page is cut from them.
+    #[impl_transaction(MockDbHandle, SearchUsers, search_users)]
+    async fn search_users(org_id: i32, query: String, limit: i64, offset: i64) -> Result<Vec<PublicUser>, NanoServiceError> {
+        assert_eq!(org_id, 3);
+        assert_eq!(query, "ada");
+        Ok((1..=5).skip(offset as usize).take(limit as usize).map(user).collect())
+    }
+
+    #[tokio::test]
+    async fn test_search_users_pages() {
+        let page = search_users::<MockDbHandle>(3, " ada ".to_string(), Some(2), None).await.unwrap();
+        assert_eq!(page.users.iter().map(|user| user.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(page.next_offset, Some(2));
+
+        let page = search_users::<MockDbHandle>(3, "ada".to_string(), Some(2), Some(4)).await.unwrap();
+        assert_eq!(page.users.len(), 1);
+        assert_eq!(page.next_offset, None);
+    }
+
+    #[tokio::test]
+    async fn test_search_users_page_cursor() {
+        let first = search_users_page::<MockDbHandle>(3, "ada".to_string(), &PageQuery { cursor: None, limit: Some(3) }).await.unwrap();
+        assert_eq!(first.items.len(), 3);
+        let next = PageQuery { cursor: first.next_cursor, limit: Some(3) };
+        let last = search_users_page::<MockDbHandle>(3, "ada".to_string(), &next).await.unwrap();
+        assert_eq!(last.items.iter().map(|user| user.id).collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_search_users_bad_request() {
+        for (query, limit, offset) in [("  ", None, None), ("ada", Some(101), None), ("ada", None, Some(-1))] {
+            let error = search_users::<MockDbHandle>(3, query.to_string(), limit, offset).await.unwrap_err();
+            assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        }
+    }
+}
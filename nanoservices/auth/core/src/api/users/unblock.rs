@@ -13,7 +13,7 @@ where
 {
     match X::unblock_user(user_id).await {
         Ok(outcome) => {
-            if outcome == false {
+            if !outcome {
                 return Err(NanoServiceError::new("Failed to unblock user".to_string(), NanoServiceErrorStatus::Unknown));
             }
             Ok(())
@@ -38,7 +38,6 @@ mod tests {
             Ok(true)
         }
 
-        let outcome = unblock_user::<MockPostgres>(1).await.unwrap();
-        assert_eq!(outcome, ());
+        unblock_user::<MockPostgres>(1).await.unwrap();
     }
 }
\ No newline at end of file
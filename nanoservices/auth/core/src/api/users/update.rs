@@ -1,50 +1,170 @@
-use utils::errors::NanoServiceError;
-use dal::users::tx_definitions::{
-    UpdateUserUsername,
-    UpdateUserEmail,
-    UpdateUserFirstName,
-    UpdateUserLasttName,
-    GetUser
-};
-use kernel::users::User;
+use serde_json::json;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::validation::validate_body;
+use dal::users::tx_definitions::UpdateUserProfile;
+use dal::sync::tx_definitions::GetSyncedUser;
+use kernel::users::{UserProfilePatch, OwnProfileUpdate};
+use kernel::sync::{SyncedUser, version_conflict};
+use kernel::chrono::NaiveDateTime;
 
 /// Updates a user’s fields if provided.
 ///
 /// # Arguments
 /// - `id`: User ID.
-/// - `username`: Optional username update.
-/// - `email`: Optional email update.
-/// - `first_name`: Optional first name update.
-/// - `last_name`: Optional last name update.
+/// - `patch`: The fields to change, fields left as `None` are not changed.
+/// - `if_match`: The version of the user the change was made against, any version if `None`.
 ///
 /// # Returns
-/// - `Ok(User)`: The updated user.
-/// - `Err(NanoServiceError)`: If an error occurs.
+/// - `Ok(SyncedUser)`: The updated user with their new version.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such user, `Conflict` with the current user
+///   and the clashing fields if they are no longer at `if_match`, or if another error occurs.
 pub async fn update_user_fields<X>(
     id: i32,
-    username: Option<String>,
-    email: Option<String>,
-    first_name: Option<String>,
-    last_name: Option<String>
-) -> Result<User, NanoServiceError> 
+    patch: UserProfilePatch,
+    if_match: Option<NaiveDateTime>
+) -> Result<SyncedUser, NanoServiceError>
 where
-    X: UpdateUserEmail + UpdateUserFirstName + UpdateUserLasttName + UpdateUserUsername + GetUser
+    X: UpdateUserProfile + GetSyncedUser
 {
-    match username {
-        Some(username) => {X::update_user_username(id, username).await?; ()},
-        None => ()
+    match X::update_user_profile(id, patch.clone(), if_match).await? {
+        Some(user) => Ok(user),
+        // no row was updated, either the user does not exist or they have moved on from `if_match`
+        None => Err(version_conflict(&X::get_synced_user(id).await?, &patch)),
     }
-    match email {
-        Some(email) => {X::update_user_email(id, email).await?; ()},
-        None => ()
+}
+
+
+/// Updates the profile of the user making the request.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user, taken from their token.
+/// - `update`: The username and names to change.
+/// - `if_match`: The version of the user the change was made against, any version if `None`.
+///
+/// # Returns
+/// - `Ok(SyncedUser)`: The updated user with their new version.
+/// - `Err(NanoServiceError)`: `Forbidden` with the refused fields if the update tries to change
+///   anything other than the username and names, such as their role or whether they are blocked or
+///   confirmed, `BadRequest` if nothing is changed or a field is not valid, and the errors of
+///   `update_user_fields` otherwise.
+pub async fn update_own_profile<X>(
+    user_id: i32,
+    update: OwnProfileUpdate,
+    if_match: Option<NaiveDateTime>
+) -> Result<SyncedUser, NanoServiceError>
+where
+    X: UpdateUserProfile + GetSyncedUser
+{
+    if !update.refused.is_empty() {
+        let refused: Vec<&String> = update.refused.keys().collect();
+        return Err(NanoServiceError::new(
+            "Only the username, first_name and last_name can be changed on your own profile".to_string(),
+            NanoServiceErrorStatus::Forbidden,
+        ).with_details(json!({"refused": refused})))
+    }
+    if update.is_empty() {
+        return Err(NanoServiceError::new(
+            "At least one of username, first_name or last_name has to be given".to_string(),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    validate_body(&update)?;
+    update_user_fields::<X>(user_id, update.into_patch(), if_match).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::{PublicUser, UserRole};
+    use dal_tx_impl::impl_transaction;
+    use chrono::DateTime;
+
+    struct MockDbHandle;
+
+    fn user(id: i32) -> SyncedUser {
+        let version = DateTime::from_timestamp(1_735_722_000, 0).unwrap().naive_utc();
+        SyncedUser {
+            user: PublicUser {
+                id,
+                confirmed: true,
+                username: "worker".to_string(),
+                email: "worker@example.com".to_string(),
+                first_name: "Worker".to_string(),
+                last_name: "User".to_string(),
+                user_role: UserRole::Worker,
+                date_created: version,
+                last_logged_in: version,
+                blocked: false,
+                avatar_url: None,
+            },
+            updated_at: version,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, UpdateUserProfile, update_user_profile)]
+    async fn update_user_profile(id: i32, patch: UserProfilePatch, if_match: Option<NaiveDateTime>) -> Result<Option<SyncedUser>, NanoServiceError> {
+        if if_match.is_some_and(|version| version != user(id).updated_at) {
+            return Ok(None)
+        }
+        let mut updated = user(id);
+        updated.user.first_name = patch.first_name.unwrap_or(updated.user.first_name);
+        Ok(Some(updated))
+    }
+
+    #[impl_transaction(MockDbHandle, GetSyncedUser, get_synced_user)]
+    async fn get_synced_user(id: i32) -> Result<SyncedUser, NanoServiceError> {
+        Ok(user(id))
     }
-    match first_name {
-        Some(first_name) => {X::update_user_first_name(id, first_name).await?; ()},
-        None => ()
+
+    fn patch() -> UserProfilePatch {
+        UserProfilePatch {
+            first_name: Some("Ada".to_string()),
+            last_name: Some("User".to_string()),
+            ..Default::default()
+        }
     }
-    match last_name {
-        Some(last_name) => {X::update_user_last_name(id, last_name).await?; ()},
-        None => ()
+
+    #[tokio::test]
+    async fn test_update_user_fields() {
+        let updated = update_user_fields::<MockDbHandle>(1, patch(), None).await.unwrap();
+        assert_eq!(updated.user.first_name, "Ada");
+
+        let current = user(1).updated_at;
+        assert!(update_user_fields::<MockDbHandle>(1, patch(), Some(current)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_update_user_fields_stale_version() {
+        let stale = user(1).updated_at - chrono::Duration::seconds(1);
+        let error = update_user_fields::<MockDbHandle>(1, patch(), Some(stale)).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        let details = error.details.unwrap();
+        assert_eq!(details["current"]["first_name"], "Worker");
+        // the last name already matches so it is not a conflict
+        assert_eq!(details["conflicts"], serde_json::json!({
+            "first_name": {"requested": "Ada", "current": "Worker"}
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_update_own_profile() {
+        let update = OwnProfileUpdate { first_name: Some("Ada".to_string()), ..Default::default() };
+        let updated = update_own_profile::<MockDbHandle>(1, update, None).await.unwrap();
+        assert_eq!(updated.user.first_name, "Ada");
+
+        let mut escalation = OwnProfileUpdate { first_name: Some("Ada".to_string()), ..Default::default() };
+        escalation.refused.insert("user_role".to_string(), serde_json::json!("SuperAdmin"));
+        escalation.refused.insert("confirmed".to_string(), serde_json::json!(true));
+        let error = update_own_profile::<MockDbHandle>(1, escalation, None).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        assert_eq!(error.details.unwrap(), serde_json::json!({"refused": ["confirmed", "user_role"]}));
+
+        let error = update_own_profile::<MockDbHandle>(1, OwnProfileUpdate::default(), None).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+
+        let blank = OwnProfileUpdate { last_name: Some("  ".to_string()), ..Default::default() };
+        let error = update_own_profile::<MockDbHandle>(1, blank, None).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
     }
-    X::get_user(id).await
 }
@@ -0,0 +1,237 @@
+//! Processes uploaded avatars into the square sizes they are served at.
+//!
+//! # Overview
+//! An avatar is decoded, turned upright using its EXIF orientation, cropped to a centred square and
+//! resized to each of `AvatarSize::ALL`. The variants are re-encoded as PNG from the pixels alone,
+//! so EXIF data such as where a photo was taken is never stored or served. Decoding and resizing
+//! are CPU bound, so `process_avatar` runs them on the blocking task pool.
+//!
+//! Each variant is stored under `AvatarSize::object_key` and a request for a size in pixels is
+//! served the variant picked by `AvatarSize::fitting`.
+use std::io::Cursor;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use image::imageops::FilterType;
+use serde::{Serialize, Deserialize};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The largest width or height of an uploaded avatar, larger images are refused before they are decoded.
+pub const MAX_AVATAR_DIMENSION: u32 = 8192;
+
+/// The content type every variant is stored and served with.
+pub const AVATAR_CONTENT_TYPE: &str = "image/png";
+
+
+/// A size an avatar is served at, given in pixels as `64`, `128` or `256`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum AvatarSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl AvatarSize {
+
+    /// Every size, smallest first.
+    pub const ALL: [AvatarSize; 3] = [AvatarSize::Small, AvatarSize::Medium, AvatarSize::Large];
+
+    /// The width and height of the size in pixels.
+    pub fn pixels(&self) -> u32 {
+        match self {
+            AvatarSize::Small => 64,
+            AvatarSize::Medium => 128,
+            AvatarSize::Large => 256,
+        }
+    }
+
+    /// The smallest size at least `pixels` wide, the largest size if none is.
+    pub fn fitting(pixels: u32) -> AvatarSize {
+        AvatarSize::ALL
+            .into_iter()
+            .find(|size| size.pixels() >= pixels)
+            .unwrap_or(AvatarSize::Large)
+    }
+
+    /// The key the user's avatar is stored under at this size.
+    pub fn object_key(&self, user_id: i32) -> String {
+        format!("avatars/{}/{}.png", user_id, self.pixels())
+    }
+}
+
+impl TryFrom<u32> for AvatarSize {
+    type Error = String;
+    fn try_from(pixels: u32) -> Result<Self, Self::Error> {
+        AvatarSize::ALL
+            .into_iter()
+            .find(|size| size.pixels() == pixels)
+            .ok_or_else(|| format!("Invalid avatar size: {}, has to be 64, 128 or 256", pixels))
+    }
+}
+
+impl From<AvatarSize> for u32 {
+    fn from(size: AvatarSize) -> Self {
+        size.pixels()
+    }
+}
+
+
+/// An avatar at one of the sizes it is served at.
+///
+/// # Fields
+/// * `size` - The size of the variant.
+/// * `bytes` - The variant encoded as PNG.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvatarVariant {
+    pub size: AvatarSize,
+    pub bytes: Vec<u8>,
+}
+
+
+/// Processes an uploaded avatar into every size on the blocking task pool.
+///
+/// # Arguments
+/// * `upload` - The uploaded file, a PNG, JPEG, GIF or WebP image.
+///
+/// # Returns
+/// * `Ok(Vec<AvatarVariant>)` - A variant for each of `AvatarSize::ALL`
+/// * `Err(NanoServiceError)` - `BadRequest` if the file is not a supported image or is too large
+pub async fn process_avatar(upload: Vec<u8>) -> Result<Vec<AvatarVariant>, NanoServiceError> {
+    tokio::task::spawn_blocking(move || process_avatar_blocking(&upload))
+        .await
+        .map_err(|e| NanoServiceError::new(
+            format!("Failed to process avatar: {}", e),
+            NanoServiceErrorStatus::Unknown
+        ))?
+}
+
+
+/// Processes an uploaded avatar into every size on the current thread, see `process_avatar`.
+pub fn process_avatar_blocking(upload: &[u8]) -> Result<Vec<AvatarVariant>, NanoServiceError> {
+    let image = decode_upright(upload)?;
+    let side = image.width().min(image.height());
+    let square = image.crop_imm((image.width() - side) / 2, (image.height() - side) / 2, side, side);
+    AvatarSize::ALL
+        .into_iter()
+        .map(|size| {
+            let resized = square.resize_exact(size.pixels(), size.pixels(), FilterType::Lanczos3);
+            let mut bytes = Vec::new();
+            resized.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+                .map_err(|e| NanoServiceError::new(
+                    format!("Failed to encode avatar: {}", e),
+                    NanoServiceErrorStatus::Unknown
+                ))?;
+            Ok(AvatarVariant { size, bytes })
+        })
+        .collect()
+}
+
+
+/// Decodes an image and rotates or flips it as its EXIF orientation says.
+fn decode_upright(upload: &[u8]) -> Result<DynamicImage, NanoServiceError> {
+    let invalid = |e: String| NanoServiceError::new(
+        format!("The avatar is not a supported image: {}", e),
+        NanoServiceErrorStatus::BadRequest
+    );
+    let mut reader = ImageReader::new(Cursor::new(upload))
+        .with_guessed_format()
+        .map_err(|e| invalid(e.to_string()))?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_AVATAR_DIMENSION);
+    limits.max_image_height = Some(MAX_AVATAR_DIMENSION);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().map_err(|e| invalid(e.to_string()))?;
+    let orientation = decoder.orientation().map_err(|e| invalid(e.to_string()))?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| invalid(e.to_string()))?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageEncoder, Rgb, RgbImage};
+    use image::codecs::jpeg::JpegEncoder;
+
+    /// An image with a red left half and a blue right half.
+    fn halves(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, _| match x < width / 2 {
+            true => Rgb([255, 0, 0]),
+            false => Rgb([0, 0, 255]),
+        })
+    }
+
+    fn decode(bytes: &[u8]) -> DynamicImage {
+        image::load_from_memory_with_format(bytes, ImageFormat::Png).unwrap()
+    }
+
+    #[test]
+    fn test_variants_are_square() {
+        let mut upload = Vec::new();
+        DynamicImage::ImageRgb8(halves(300, 200))
+            .write_to(&mut Cursor::new(&mut upload), ImageFormat::Png)
+            .unwrap();
+
+        let variants = process_avatar_blocking(&upload).unwrap();
+        assert_eq!(variants.iter().map(|variant| variant.size).collect::<Vec<_>>(), AvatarSize::ALL);
+        for variant in variants {
+            let image = decode(&variant.bytes);
+            assert_eq!((image.width(), image.height()), (variant.size.pixels(), variant.size.pixels()));
+        }
+    }
+
+    #[test]
+    fn test_exif_is_applied_and_stripped() {
+        // orientation 6 means the camera was turned, the image has to be rotated 90 degrees clockwise
+        let exif: Vec<u8> = vec![
+            b'M', b'M', 0, 42, 0, 0, 0, 8,
+            0, 1,
+            0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0,
+            0, 0, 0, 0,
+        ];
+        let image = halves(40, 20);
+        let mut upload = Vec::new();
+        let mut encoder = JpegEncoder::new_with_quality(&mut upload, 95);
+        encoder.set_exif_metadata(exif).unwrap();
+        encoder.write_image(image.as_raw(), 40, 20, image::ExtendedColorType::Rgb8).unwrap();
+
+        let variants = process_avatar_blocking(&upload).unwrap();
+        let small = decode(&variants[0].bytes).to_rgb8();
+        // the red left half is on top once the image is upright
+        assert!(small.get_pixel(32, 4)[0] > 200 && small.get_pixel(32, 4)[2] < 60);
+        assert!(small.get_pixel(32, 60)[2] > 200 && small.get_pixel(32, 60)[0] < 60);
+
+        let mut decoder = ImageReader::new(Cursor::new(&variants[0].bytes))
+            .with_guessed_format()
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        assert_eq!(decoder.exif_metadata().unwrap(), None);
+    }
+
+    #[test]
+    fn test_not_an_image() {
+        let error = process_avatar_blocking(b"not an image").unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[test]
+    fn test_avatar_size() {
+        assert_eq!(AvatarSize::fitting(10), AvatarSize::Small);
+        assert_eq!(AvatarSize::fitting(100), AvatarSize::Medium);
+        assert_eq!(AvatarSize::fitting(1000), AvatarSize::Large);
+        assert_eq!(serde_json::from_str::<AvatarSize>("128").unwrap(), AvatarSize::Medium);
+        assert!(serde_json::from_str::<AvatarSize>("100").is_err());
+        assert_eq!(AvatarSize::Large.object_key(3), "avatars/3/256.png");
+    }
+
+    #[tokio::test]
+    async fn test_process_avatar_on_blocking_pool() {
+        let mut upload = Vec::new();
+        DynamicImage::ImageRgb8(halves(10, 10))
+            .write_to(&mut Cursor::new(&mut upload), ImageFormat::Png)
+            .unwrap();
+        assert_eq!(process_avatar(upload).await.unwrap().len(), 3);
+    }
+}
@@ -1 +1,3 @@
 pub mod api;
+pub mod password_policy;
+pub mod avatars;
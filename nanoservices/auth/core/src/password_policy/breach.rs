@@ -0,0 +1,102 @@
+//! Looks passwords up in the Pwned Passwords range API without sending the password.
+//!
+//! # Overview
+//! Only the first 5 characters of the password's SHA-1 hash are sent. The API answers with the
+//! suffix of every breached hash sharing that prefix, and the match is made locally, so neither
+//! the password nor its full hash leave the server.
+use std::future::Future;
+use dal_tx_impl::impl_transaction;
+use reqwest::Client;
+use sha1::{Digest, Sha1};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// Descriptor for the haveibeenpwned Pwned Passwords API.
+pub struct HaveIBeenPwnedDescriptor;
+
+/// Defines the contract for fetching the breached hash suffixes that share a prefix.
+pub trait PwnedPasswordRange {
+    /// Returns the raw range body, one `SUFFIX:COUNT` line per breached hash.
+    fn pwned_password_range(prefix: String) -> impl Future<Output = Result<String, NanoServiceError>> + Send;
+}
+
+
+#[impl_transaction(HaveIBeenPwnedDescriptor, PwnedPasswordRange, pwned_password_range)]
+async fn pwned_password_range(prefix: String) -> Result<String, NanoServiceError> {
+    let response = Client::new()
+        .get(format!("https://api.pwnedpasswords.com/range/{}", prefix))
+        // padded responses stop the size of the reply giving away the prefix
+        .header("Add-Padding", "true")
+        .send()
+        .await
+        .map_err(|e| NanoServiceError::new(
+            format!("Failed to query Pwned Passwords: {}", e),
+            NanoServiceErrorStatus::Unknown,
+        ))?;
+    if !response.status().is_success() {
+        return Err(NanoServiceError::new(
+            format!("Failed to query Pwned Passwords. HTTP Status: {}", response.status()),
+            NanoServiceErrorStatus::Unknown,
+        ))
+    }
+    response.text().await.map_err(|e| NanoServiceError::new(
+        format!("Failed to read Pwned Passwords response: {}", e),
+        NanoServiceErrorStatus::Unknown,
+    ))
+}
+
+
+/// Splits the uppercase SHA-1 hex digest of a password into the 5 character prefix and the rest.
+pub fn hash_prefix_and_suffix(password: &str) -> (String, String) {
+    let digest = Sha1::digest(password.as_bytes());
+    let hex: String = digest.iter().map(|byte| format!("{:02X}", byte)).collect();
+    let (prefix, suffix) = hex.split_at(5);
+    (prefix.to_string(), suffix.to_string())
+}
+
+
+/// Finds how many times a hash suffix appears in a range body, padding entries have a count of 0.
+pub fn times_breached(range: &str, suffix: &str) -> u64 {
+    range.lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse::<u64>().ok())
+        .unwrap_or(0)
+}
+
+
+/// Checks how many times a password has appeared in known breaches.
+///
+/// # Arguments
+/// * `password` - The plaintext password
+///
+/// # Returns
+/// * `Ok(u64)` - The number of times the password has been seen, 0 if never
+/// * `Err(NanoServiceError)` - If the range could not be fetched
+pub async fn password_breach_count<B: PwnedPasswordRange>(password: &str) -> Result<u64, NanoServiceError> {
+    let (prefix, suffix) = hash_prefix_and_suffix(password);
+    let range = B::pwned_password_range(prefix).await?;
+    Ok(times_breached(&range, &suffix))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_prefix_and_suffix() {
+        // SHA-1 of "password" is 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+        let (prefix, suffix) = hash_prefix_and_suffix("password");
+        assert_eq!(prefix, "5BAA6");
+        assert_eq!(suffix, "1E4C9B93F3F0682250B6CF8331B7EE68FD8");
+    }
+
+    #[test]
+    fn test_times_breached() {
+        let range = "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n1E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365\r\nFFFF0000000000000000000000000000000:0";
+        assert_eq!(times_breached(range, "1E4C9B93F3F0682250B6CF8331B7EE68FD8"), 9659365);
+        assert_eq!(times_breached(range, "FFFF0000000000000000000000000000000"), 0);
+        assert_eq!(times_breached(range, "AAAA0000000000000000000000000000000"), 0);
+    }
+}
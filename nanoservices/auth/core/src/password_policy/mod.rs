@@ -0,0 +1,223 @@
+//! Enforces the password policy wherever a password is set.
+//!
+//! # Overview
+//! A password has to meet the configured length and character class rules, and can optionally be
+//! checked against known breaches through `PwnedPasswordRange`. Every rule the password breaks is
+//! reported under `password` in the error details, so a user can fix them all in one go.
+//!
+//! # Variables
+//! * `PASSWORD_MIN_LENGTH` - The fewest characters a password can have, defaults to 12
+//! * `PASSWORD_REQUIRE_LOWERCASE` - Whether a lowercase letter is required, defaults to `true`
+//! * `PASSWORD_REQUIRE_UPPERCASE` - Whether an uppercase letter is required, defaults to `true`
+//! * `PASSWORD_REQUIRE_DIGIT` - Whether a digit is required, defaults to `true`
+//! * `PASSWORD_REQUIRE_SYMBOL` - Whether a character that is not a letter or digit is required, defaults to `false`
+//! * `PASSWORD_BREACH_CHECK` - Whether passwords are checked against known breaches, defaults to `false`
+pub mod breach;
+
+use utils::config::GetConfigVariable;
+use utils::log_limited;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use breach::{password_breach_count, PwnedPasswordRange};
+
+
+/// The minimum length used when not configured.
+const DEFAULT_MIN_LENGTH: usize = 12;
+
+/// The longest password accepted, so hashing cannot be used to tie up the server.
+pub const MAX_LENGTH: usize = 128;
+
+
+/// The rules a password has to meet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    pub check_breaches: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: DEFAULT_MIN_LENGTH,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: false,
+            check_breaches: false,
+        }
+    }
+}
+
+
+/// Reads a flag from config, falling back to the default if it is not set or not `true`/`false`.
+fn read_flag<Y: GetConfigVariable>(name: &str, default: bool) -> bool {
+    match Y::get_config_variable(name.to_string()).map(|value| value.trim().to_lowercase()) {
+        Ok(value) if value == "true" => true,
+        Ok(value) if value == "false" => false,
+        _ => default
+    }
+}
+
+
+impl PasswordPolicy {
+
+    /// Reads the policy from config, falling back to the default for anything not set or invalid.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let defaults = PasswordPolicy::default();
+        let min_length = Y::get_config_variable("PASSWORD_MIN_LENGTH".to_string())
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .filter(|value| *value > 0 && *value <= MAX_LENGTH)
+            .unwrap_or(defaults.min_length);
+        PasswordPolicy {
+            min_length,
+            require_lowercase: read_flag::<Y>("PASSWORD_REQUIRE_LOWERCASE", defaults.require_lowercase),
+            require_uppercase: read_flag::<Y>("PASSWORD_REQUIRE_UPPERCASE", defaults.require_uppercase),
+            require_digit: read_flag::<Y>("PASSWORD_REQUIRE_DIGIT", defaults.require_digit),
+            require_symbol: read_flag::<Y>("PASSWORD_REQUIRE_SYMBOL", defaults.require_symbol),
+            check_breaches: read_flag::<Y>("PASSWORD_BREACH_CHECK", defaults.check_breaches),
+        }
+    }
+
+    /// Lists the length and character class rules a password breaks, empty if it meets them all.
+    pub fn violations(&self, password: &str) -> Vec<String> {
+        let length = password.chars().count();
+        let rules = [
+            (length < self.min_length, format!("must be at least {} characters", self.min_length)),
+            (length > MAX_LENGTH, format!("must be at most {} characters", MAX_LENGTH)),
+            (self.require_lowercase && !password.chars().any(char::is_lowercase), "must contain a lowercase letter".to_string()),
+            (self.require_uppercase && !password.chars().any(char::is_uppercase), "must contain an uppercase letter".to_string()),
+            (self.require_digit && !password.chars().any(|c| c.is_ascii_digit()), "must contain a digit".to_string()),
+            (self.require_symbol && password.chars().all(char::is_alphanumeric), "must contain a symbol".to_string()),
+        ];
+        rules.into_iter().filter(|(broken, _)| *broken).map(|(_, message)| message).collect()
+    }
+}
+
+
+/// Checks a password against the configured policy.
+///
+/// # Arguments
+/// * `password` - The plaintext password being set
+///
+/// # Returns
+/// * `Ok(())` - If the password meets the policy
+/// * `Err(NanoServiceError)` - `BadRequest` with the broken rules under `password` in the details
+///
+/// # Notes
+/// The breach check is only made once the other rules pass. If the breach API cannot be reached
+/// the password is accepted and the failure logged, so an outage does not stop users setting passwords.
+pub async fn enforce_password_policy<Y, B>(password: &str) -> Result<(), NanoServiceError>
+where
+    Y: GetConfigVariable,
+    B: PwnedPasswordRange,
+{
+    let policy = PasswordPolicy::from_config::<Y>();
+    let mut violations = policy.violations(password);
+    if violations.is_empty() && policy.check_breaches {
+        match password_breach_count::<B>(password).await {
+            Ok(0) => {},
+            Ok(_) => violations.push("has appeared in a data breach, choose another".to_string()),
+            Err(e) => log_limited!("auth", "password breach check skipped: {}", e.message)
+        }
+    }
+    if violations.is_empty() {
+        return Ok(())
+    }
+    Err(NanoServiceError::new(
+        "Password does not meet the password policy".to_string(),
+        NanoServiceErrorStatus::BadRequest,
+    ).with_details(serde_json::json!({ "password": violations })))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+
+    struct EmptyConfig;
+
+    impl GetConfigVariable for EmptyConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("".to_string())
+        }
+    }
+
+    struct BreachCheckConfig;
+
+    impl GetConfigVariable for BreachCheckConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "PASSWORD_MIN_LENGTH" => Ok("8".to_string()),
+                "PASSWORD_REQUIRE_SYMBOL" => Ok("TRUE".to_string()),
+                "PASSWORD_BREACH_CHECK" => Ok("true".to_string()),
+                _ => Err(NanoServiceError::new("not set".to_string(), NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    struct MockPwned;
+
+    #[impl_transaction(MockPwned, PwnedPasswordRange, pwned_password_range)]
+    async fn pwned_password_range(prefix: String) -> Result<String, NanoServiceError> {
+        // only "Password-123" is in the mocked breach data
+        let (breached_prefix, breached_suffix) = breach::hash_prefix_and_suffix("Password-123");
+        if prefix == breached_prefix {
+            return Ok(format!("{}:42\r\n", breached_suffix))
+        }
+        Ok(String::new())
+    }
+
+    struct MockPwnedDown;
+
+    #[impl_transaction(MockPwnedDown, PwnedPasswordRange, pwned_password_range)]
+    async fn pwned_password_range(_prefix: String) -> Result<String, NanoServiceError> {
+        Err(NanoServiceError::new("HTTP Status: 503".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[test]
+    fn test_policy_from_config() {
+        assert_eq!(PasswordPolicy::from_config::<EmptyConfig>(), PasswordPolicy::default());
+        let policy = PasswordPolicy::from_config::<BreachCheckConfig>();
+        assert_eq!(policy.min_length, 8);
+        assert!(policy.require_symbol);
+        assert!(policy.check_breaches);
+        assert!(policy.require_uppercase);
+    }
+
+    #[test]
+    fn test_violations() {
+        let policy = PasswordPolicy::default();
+        assert!(policy.violations("Correct-horse-9").is_empty());
+        assert_eq!(policy.violations("short"), vec![
+            "must be at least 12 characters".to_string(),
+            "must contain an uppercase letter".to_string(),
+            "must contain a digit".to_string(),
+        ]);
+        assert_eq!(policy.violations(&"Aa1".repeat(50)), vec!["must be at most 128 characters".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_enforce_password_policy() {
+        enforce_password_policy::<EmptyConfig, MockPwned>("Correct-horse-9").await.unwrap();
+
+        let error = enforce_password_policy::<EmptyConfig, MockPwned>("password").await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        assert_eq!(error.details.unwrap()["password"].as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_breach_check() {
+        enforce_password_policy::<BreachCheckConfig, MockPwned>("Not-breached-7").await.unwrap();
+
+        let error = enforce_password_policy::<BreachCheckConfig, MockPwned>("Password-123").await.unwrap_err();
+        assert_eq!(error.details.unwrap()["password"][0], "has appeared in a data breach, choose another");
+
+        // an unreachable breach API does not block the password
+        enforce_password_policy::<BreachCheckConfig, MockPwnedDown>("Password-123").await.unwrap();
+    }
+}
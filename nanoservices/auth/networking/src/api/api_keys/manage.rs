@@ -0,0 +1,119 @@
+//! Endpoints for super admins to create, list and revoke API keys.
+use actix_web::{HttpResponse, web::{Json, Path}};
+use auth_core::api::api_keys::manage::{
+    create_api_key as create_api_key_core,
+    revoke_api_key as revoke_api_key_core,
+    list_api_keys as list_api_keys_core,
+};
+use dal::api_keys::tx_definitions::{CreateApiKey, ListApiKeys, RevokeApiKey};
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use dal::plans::tx_definitions::GetOrgPlan;
+use serde::{Deserialize, Serialize};
+use utils::api_endpoint;
+
+
+/// The key to create.
+///
+/// # Fields
+/// * `name` - What the key is used for, such as `nightly-backup`.
+#[derive(Serialize, Deserialize)]
+pub struct CreateApiKeyBody {
+    pub name: String,
+}
+
+
+/// Creates a key and returns it with `201 Created`, the only time the key itself is returned.
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[CreateApiKey, GetOrgPlan, CreateAuditEntry])]
+pub async fn create_api_key(body: Json<CreateApiKeyBody>) {
+    let created = create_api_key_core::<X>(body.into_inner().name, jwt.user_id).await?;
+    Ok(HttpResponse::Created().json(created))
+}
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[ListApiKeys])]
+pub async fn list_api_keys() {
+    Ok(HttpResponse::Ok().json(list_api_keys_core::<X>().await?))
+}
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[RevokeApiKey, CreateAuditEntry])]
+pub async fn revoke_api_key(id: Path<i32>) {
+    revoke_api_key_core::<X>(id.into_inner(), jwt.user_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::api_keys::{ApiKey, NewApiKey};
+    use kernel::audit_log::{AuditEntry, NewAuditEntry};
+    use kernel::plans::{OrgPlan, Plan};
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::users::UserRole;
+    use utils::errors::NanoServiceError;
+    use test_support::{call_endpoint, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(OrgPlan { plan: Plan::Enterprise, max_users: None, max_todos: None, mfa: None, api_access: None, date_updated: chrono::Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockPostgres, CreateApiKey, create_api_key)]
+    async fn create_api_key(api_key: NewApiKey) -> Result<ApiKey, NanoServiceError> {
+        assert_eq!(api_key.created_by, 1);
+        Ok(ApiKey {
+            id: 3,
+            name: api_key.name,
+            key_prefix: api_key.key_prefix,
+            key_hash: api_key.key_hash,
+            created_by: api_key.created_by,
+            date_created: chrono::Utc::now().naive_utc(),
+            last_used_at: None,
+            revoked_at: None,
+        })
+    }
+
+    #[impl_transaction(MockPostgres, CreateAuditEntry, create_audit_entry)]
+    async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: sqlx::types::Json(entry.details),
+            date_created: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    async fn send(role: UserRole) -> actix_web::dev::ServiceResponse {
+        let req = TokenBuilder::<FakeConfig, SuperAdminRoleCheck>::new()
+            .role(role)
+            .request(TestRequest::post().uri("/api-keys"))
+            .set_json(CreateApiKeyBody { name: "nightly-backup".to_string() });
+        call_endpoint(Method::POST, "/api-keys", create_api_key::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>, req).await
+    }
+
+    #[tokio::test]
+    async fn test_create_api_key() {
+        let resp = send(UserRole::SuperAdmin).await;
+        assert_eq!(resp.status(), 201);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["api_key"]["name"], "nightly-backup");
+        assert!(body["api_key"].get("key_hash").is_none());
+        assert!(body["key"].as_str().unwrap().starts_with(body["api_key"]["key_prefix"].as_str().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_create_api_key_as_admin() {
+        let resp = send(UserRole::Admin).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
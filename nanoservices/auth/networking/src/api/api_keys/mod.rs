@@ -0,0 +1,28 @@
+//! Defines the endpoints for managing API keys.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v1/api-keys` and are limited to super admins. The keys they
+//! create are sent by services in the `X-Api-Key` header to the endpoints built with
+//! `api_endpoint(auth=ApiKey)`.
+pub mod manage;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get, delete};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn api_keys_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v1/api-keys") // Namespace for API key routes.
+        .route("", post().to(
+            manage::create_api_key::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/api-keys.
+        )
+        .route("", get().to(
+            manage::list_api_keys::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/api-keys.
+        )
+        .route("{id}", delete().to(
+            manage::revoke_api_key::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // DELETE /api/auth/v1/api-keys/{id}.
+        )
+    );
+}
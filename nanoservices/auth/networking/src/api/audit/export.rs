@@ -0,0 +1,160 @@
+//! Networking layer for super admins exporting the audit log.
+use actix_web::{HttpRequest, web::Query};
+use auth_core::api::audit::export::export_audit_entries as export_audit_entries_core;
+use dal::audit_log::tx_definitions::ListAuditEntries;
+use serde::Deserialize;
+use utils::api_endpoint;
+use utils::export_stream::{export_response, ExportEncoding, ExportFormat};
+
+
+/// The `ListAuditEntries` handles that can be held by a streamed response, which outlives the handler.
+pub trait StreamAuditEntries: ListAuditEntries + 'static {}
+
+impl<T: ListAuditEntries + 'static> StreamAuditEntries for T {}
+
+
+/// The query parameters of the audit log export.
+///
+/// # Fields
+/// * `format` - JSON or CSV, defaults to JSON
+#[derive(Deserialize, Debug)]
+pub struct AuditExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[StreamAuditEntries])]
+pub async fn export_audit_entries(req: HttpRequest, query: Query<AuditExportQuery>) {
+    let encoding = ExportEncoding::negotiate(req.headers());
+    let body = export_audit_entries_core::<X>(query.format, encoding);
+    Ok(export_response(query.format, encoding, "audit-log", body))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::MessageBody;
+    use actix_web::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, USER_AGENT};
+    use actix_web::{test, web, App};
+    use dal::users::tx_definitions::GetUser;
+    use dal_tx_impl::impl_transaction;
+    use flate2::read::GzDecoder;
+    use kernel::audit_log::AuditEntry;
+    use kernel::users::UserRole;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use sqlx::types::Json;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use utils::config::GetConfigVariable;
+    use utils::errors::NanoServiceError;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    /// Every mock serves a log of 2500 entries, counting the pages it is asked for.
+    macro_rules! audit_log_mock {
+        ($handle:ident, $pages:ident) => {
+            static $pages: AtomicUsize = AtomicUsize::new(0);
+
+            struct $handle;
+
+            test_support::confirmed_users!($handle);
+
+            #[impl_transaction($handle, ListAuditEntries, list_audit_entries)]
+            async fn list_audit_entries(before_id: Option<i32>, limit: i64) -> Result<Vec<AuditEntry>, NanoServiceError> {
+                $pages.fetch_add(1, Ordering::SeqCst);
+                Ok((1..=2500).rev()
+                    .filter(|id| before_id.is_none_or(|before_id| *id < before_id))
+                    .take(limit as usize)
+                    .map(|id| AuditEntry {
+                        id,
+                        actor_id: 1,
+                        action: "todo:capacity_override".to_string(),
+                        subject_id: Some(id),
+                        details: Json(serde_json::json!({"limit": 5, "note": "over, by one"})),
+                        date_created: chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+                    })
+                    .collect())
+            }
+        };
+    }
+
+    async fn export<X: StreamAuditEntries + GetUser>(uri: &str, accept_encoding: &str) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(App::new().route(
+            "/export",
+            web::get().to(export_audit_entries::<X, MockConfig, PassAuthSessionCheckMock>)
+        )).await;
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, SuperAdminRoleCheck> = HeaderToken::new(agent.clone(), 1, UserRole::SuperAdmin);
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((USER_AGENT, agent))
+            .insert_header((ACCEPT_ENCODING, accept_encoding))
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_export_csv() {
+        audit_log_mock!(CsvPostgres, CSV_PAGES);
+        let resp = export::<CsvPostgres>("/export?format=csv", "identity").await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "text/csv");
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+
+        let body = test::read_body(resp).await;
+        let csv = std::str::from_utf8(&body).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2501);
+        assert_eq!(lines[0], "id,actor_id,action,subject_id,details,date_created");
+        assert_eq!(lines[1], "2500,1,todo:capacity_override,2500,\"{\"\"limit\"\":5,\"\"note\"\":\"\"over, by one\"\"}\",2025-01-01T00:00:00");
+        assert!(lines[2500].starts_with("1,"));
+        assert_eq!(CSV_PAGES.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_export_gzipped_json() {
+        audit_log_mock!(JsonPostgres, JSON_PAGES);
+        let resp = export::<JsonPostgres>("/export", "gzip, deflate").await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "application/json");
+
+        let body = test::read_body(resp).await;
+        let mut json = String::new();
+        GzDecoder::new(body.as_ref()).read_to_string(&mut json).unwrap();
+        let entries: Vec<AuditEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(entries.len(), 2500);
+        assert_eq!(entries[2499].id, 1);
+        assert!(body.len() < json.len() / 10);
+        assert_eq!(JSON_PAGES.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_export_follows_slow_client() {
+        audit_log_mock!(SlowPostgres, SLOW_PAGES);
+        let resp = export::<SlowPostgres>("/export?format=csv", "identity").await;
+        // no page is read until the client starts reading the body
+        assert_eq!(SLOW_PAGES.load(Ordering::SeqCst), 0);
+
+        let mut body = resp.into_body();
+        let first = std::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx)).await.unwrap().unwrap();
+        assert!(first.starts_with(b"id,actor_id"));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(SLOW_PAGES.load(Ordering::SeqCst), 1);
+
+        // the client goes away after the first page, so the rest of the log is never read
+        drop(body);
+        assert_eq!(SLOW_PAGES.load(Ordering::SeqCst), 1);
+    }
+}
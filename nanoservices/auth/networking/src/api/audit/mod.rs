@@ -0,0 +1,22 @@
+//! Defines the endpoints for the audit log.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v1/audit`. Super admins page through the log with the v2 list
+//! route and download all of it with `export`. The log covers every organization, so the admins of
+//! a single organization cannot read it.
+pub mod export;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, get};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn audit_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v1/audit") // Namespace for audit log routes.
+        .route("export", get().to(
+            export::export_audit_entries::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/audit/export?format=csv.
+        )
+    );
+}
@@ -0,0 +1,82 @@
+//! Networking layer for super admins impersonating another user.
+use actix_web::{HttpResponse, web::Path};
+use auth_core::api::auth::impersonate::impersonate_user;
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use dal::permissions::tx_definitions::GetEffectivePermissions;
+use dal::users::tx_definitions::GetUser;
+use kernel::token::session_cache::traits::SetAuthCacheSession;
+use utils::api_endpoint;
+
+
+/// Issues the super admin a short-lived token for the user in the path, bound to the super admin's
+/// user agent.
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[GetUser, GetEffectivePermissions, CreateAuditEntry], cache_traits=[SetAuthCacheSession])]
+pub async fn impersonate(user_id: Path<i32>) {
+    let outcome = impersonate_user::<X, Y, Z>(jwt.user_id, user_id.into_inner(), jwt.org_id, jwt.user_agent.clone()).await?;
+    Ok(HttpResponse::Ok().json(outcome))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::audit_log::{AuditEntry, NewAuditEntry};
+    use kernel::token::checks::{NoRoleCheck, SuperAdminRoleCheck};
+    use kernel::token::token::HeaderToken;
+    use kernel::users::{User, UserRole};
+    use sqlx::types::Json;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    struct MockPostgres;
+
+    #[impl_transaction(MockPostgres, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        Ok(User { user_role: UserRole::Worker, ..factories::user(id) })
+    }
+
+    #[impl_transaction(MockPostgres, GetEffectivePermissions, get_effective_permissions)]
+    async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[impl_transaction(MockPostgres, CreateAuditEntry, create_audit_entry)]
+    async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: Json(entry.details),
+            date_created: Default::default(),
+        })
+    }
+
+    async fn send(role: UserRole, uri: &str) -> actix_web::dev::ServiceResponse {
+        let req = TokenBuilder::<FakeConfig, SuperAdminRoleCheck>::new()
+            .role(role)
+            .request(TestRequest::post().uri(uri));
+        call_endpoint(
+            Method::POST,
+            "/impersonate/{user_id}",
+            impersonate::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            req
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_impersonate() {
+        let resp = send(UserRole::SuperAdmin, "/impersonate/5").await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let token = HeaderToken::<FakeConfig, NoRoleCheck>::decode(body["token"].as_str().unwrap()).unwrap();
+        assert_eq!((token.user_id, token.impersonator_id), (5, Some(1)));
+        assert_eq!(token.user_agent, test_support::token::TEST_USER_AGENT);
+
+        assert_eq!(send(UserRole::SuperAdmin, "/impersonate/1").await.status(), 400);
+        assert_eq!(send(UserRole::Admin, "/impersonate/5").await.status(), 401);
+    }
+}
@@ -4,10 +4,16 @@ use crate::utils::extract_basic_auth_credentials;
 use auth_core::api::auth::login::login as login_core;
 use kernel::users::UserRole;
 use serde::Deserialize;
-use dal::users::tx_definitions::GetUserByEmail;
+use dal::users::tx_definitions::{GetUserByEmail, UpdateLastLoggedIn};
 use dal::role_permissions::tx_definitions::GetRolePermissions;
+use dal::permissions::tx_definitions::GetEffectivePermissions;
+use dal::metering::tx_definitions::RecordActiveUser;
+use dal::devices::tx_definitions::RecordDeviceLogin;
+use dal::organizations::tx_definitions::GetUserOrganizations;
 use utils::config::GetConfigVariable;
-use kernel::token::session_cache::traits::SetAuthCacheSession;
+use kernel::token::session_cache::traits::{SetAuthCacheSession, GetUserAuthCacheSessions, DelAuthCacheSession};
+use kernel::analytics::engine_configured::ConfiguredAnalyticsSink;
+use kernel::token::cookies::add_session_cookies;
 
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 
@@ -18,12 +24,13 @@ pub struct LoginBody {
 }
 
 
-/// This endpoint logs the user in.
+/// This endpoint logs the user in, also setting the token as a cookie for browser sessions, see
+/// `kernel::token::cookies`.
 pub async fn login<X, Y, Z>(req: HttpRequest, body: Json<LoginBody>) -> Result<HttpResponse, NanoServiceError> 
 where
-    X: GetUserByEmail + GetRolePermissions,
+    X: GetUserByEmail + GetRolePermissions + GetEffectivePermissions + UpdateLastLoggedIn + RecordActiveUser + RecordDeviceLogin + GetUserOrganizations,
     Y: GetConfigVariable,
-    Z: SetAuthCacheSession,
+    Z: SetAuthCacheSession + GetUserAuthCacheSessions + DelAuthCacheSession,
 {
     let (email, password) = extract_basic_auth_credentials(&req)?;
     let agent_value = match req.headers().get("User-Agent") {
@@ -35,13 +42,15 @@ where
     let agent_string = agent_value.to_str().map_err(|e| NanoServiceError::new(
         e.to_string(), NanoServiceErrorStatus::Unauthorized
     ))?.to_string();
-    let login_response = match login_core::<X, Y, Z>(email, password, body.into_inner().role, agent_string).await {
+    let login_response = match login_core::<X, Y, Z, ConfiguredAnalyticsSink>(email, password, body.into_inner().role, agent_string).await {
         Ok(login_response) => login_response,
         Err(e) => {
             return Err(e)
         }
     };
-    Ok(HttpResponse::Ok().json(login_response))
+    let mut response = HttpResponse::Ok().json(&login_response);
+    add_session_cookies::<Y>(&mut response, &login_response.token)?;
+    Ok(response)
 }
 
 
@@ -59,10 +68,12 @@ mod tests {
     use dal_tx_impl::impl_transaction;
     use base64::{Engine as _, engine::general_purpose};
     use kernel::role_permissions::RolePermission;
+    use kernel::devices::{Device, NewDevice};
     use kernel::users::{User, NewUser};
     use serde_json::json;
     use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
     use auth_core::api::auth::login::LoginReturnSchema;
+    use test_support::factories;
 
     fn generate_user(password: String, user_role: UserRole) -> User {
         let new_user = NewUser::new(
@@ -86,6 +97,7 @@ mod tests {
             last_logged_in: new_user.last_logged_in,
             blocked: new_user.blocked,
             uuid: new_user.uuid,
+            avatar_version: None,
         }
     }
 
@@ -110,6 +122,29 @@ mod tests {
                 role: UserRole::Admin,
             }])
         }
+        #[impl_transaction(MockPostgres, GetEffectivePermissions, get_effective_permissions)]
+        async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+            Ok(vec!["todo:assign".to_string()])
+        }
+        #[impl_transaction(MockPostgres, RecordActiveUser, record_active_user)]
+        async fn record_active_user(user_id: i32) -> Result<(), NanoServiceError> {
+            assert_eq!(user_id, 1);
+            Ok(())
+        }
+
+        #[impl_transaction(MockPostgres, GetUserOrganizations, get_user_organizations)]
+        async fn get_user_organizations(_user_id: i32) -> Result<Vec<kernel::organizations::UserOrganization>, NanoServiceError> {
+            Ok(vec![])
+        }
+        #[impl_transaction(MockPostgres, RecordDeviceLogin, record_device_login)]
+        async fn record_device_login(device: NewDevice) -> Result<Device, NanoServiceError> {
+            Ok(Device { user_id: device.user_id, fingerprint: device.fingerprint, user_agent: device.user_agent, ..factories::device(1) })
+        }
+        #[impl_transaction(MockPostgres, UpdateLastLoggedIn, update_last_logged_in)]
+        async fn update_last_logged_in(user_id: i32) -> Result<bool, NanoServiceError> {
+            assert_eq!(user_id, 1);
+            Ok(true)
+        }
         impl GetConfigVariable for MockConfig {
             fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
                 Ok("secret".to_string())
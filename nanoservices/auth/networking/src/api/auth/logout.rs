@@ -4,6 +4,7 @@ use utils::config::GetConfigVariable;
 use kernel::token::session_cache::traits::DelAuthCacheSession;
 use kernel::token::token::HeaderToken;
 use kernel::token::checks::NoRoleCheck;
+use kernel::token::cookies::clear_session_cookies;
 
 use utils::errors::NanoServiceError;
 
@@ -14,6 +15,8 @@ where
     Y: GetConfigVariable
 {
     X::del_auth_cache_session(token.into_auth_cache_key().key).await?;
-    Ok(HttpResponse::Ok().finish())
+    let mut response = HttpResponse::Ok().finish();
+    clear_session_cookies::<Y>(&mut response)?;
+    Ok(response)
 }
 
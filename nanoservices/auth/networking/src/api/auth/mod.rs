@@ -3,31 +3,71 @@ pub mod logout;
 pub mod request_password_reset;
 pub mod refresh;
 pub mod resend_confirmation_email;
+pub mod replicate_session;
+pub mod impersonate;
+pub mod oidc;
+#[cfg(feature = "saml")]
+pub mod saml;
 
 use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
-use utils::config::EnvConfig;
-use email_core::mailchimp_traits::mc_definitions::MailchimpDescriptor;
-use actix_web::web::{ServiceConfig, scope, post};
+use utils::secrets::SecretsConfig;
+use email_core::outbox::descriptor::EmailOutbox;
+use actix_web::web::{ServiceConfig, scope, resource, get, post};
+use kernel::oidc::engine_http::HttpOidcClient;
+use kernel::captcha::engine_http::HttpCaptchaVerifier;
+#[cfg(feature = "saml")]
+use kernel::saml::engine_xmldsig::XmlDsigValidator;
+use actix_web::middleware::from_fn;
 use kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+use crate::rate_limit::{limit_auth_requests, LOGIN_RATE_LIMIT, PASSWORD_RESET_RATE_LIMIT};
 
 
 pub fn auth_factory(app: &mut ServiceConfig) {
-    app.service(
-        scope("/api/auth/v1/auth") // Namespace for user-related API routes.
-        .route("login", post().to(
-            login::login::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/users/login.
+    let auth_scope = scope("/api/auth/v1/auth") // Namespace for user-related API routes.
+        .service(resource("login")
+            .wrap(from_fn(|req, next| limit_auth_requests::<SqlxPostGresDescriptor, SecretsConfig, HttpCaptchaVerifier, _>(LOGIN_RATE_LIMIT, req, next)))
+            .route(post().to(
+                login::login::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/login.
+            )
         )
         .route("refresh", post().to(
-            refresh::refresh::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/users/refresh.
+            refresh::refresh::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/refresh.
         )
         .route("logout", post().to(
-            logout::logout::<AuthCacheSessionEngineMem, EnvConfig>) // POST /api/auth/v1/users/logout.
+            logout::logout::<AuthCacheSessionEngineReplicated<SecretsConfig>, SecretsConfig>) // POST /api/auth/v1/users/logout.
         )
-        .route("request_password_reset", post().to(
-            request_password_reset::request_password_reset::<MailchimpDescriptor, SqlxPostGresDescriptor, EnvConfig>) // POST /api/auth/v1/users/password_reset_request.
+        .service(resource("request_password_reset")
+            .wrap(from_fn(|req, next| limit_auth_requests::<SqlxPostGresDescriptor, SecretsConfig, HttpCaptchaVerifier, _>(PASSWORD_RESET_RATE_LIMIT, req, next)))
+            .route(post().to(
+                request_password_reset::request_password_reset::<EmailOutbox<SqlxPostGresDescriptor>, SqlxPostGresDescriptor, SecretsConfig>) // POST /api/auth/v1/users/password_reset_request.
+            )
         )
         .route("resend_confirmation_email", post().to(
-            resend_confirmation_email::resend_confirmation_email::<MailchimpDescriptor, SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/users/resend_confirmation_email.
+            resend_confirmation_email::resend_confirmation_email::<EmailOutbox<SqlxPostGresDescriptor>, SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/resend_confirmation_email.
         )
-    );
+        .route("impersonate/{user_id}", post().to(
+            impersonate::impersonate::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/auth/impersonate/{user_id}.
+        )
+        .route("oidc/{provider}/start", get().to(
+            oidc::start::<SecretsConfig>) // GET /api/auth/v1/auth/oidc/{provider}/start.
+        )
+        .route("oidc/{provider}/callback", get().to(
+            oidc::callback::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>, HttpOidcClient>) // GET /api/auth/v1/auth/oidc/{provider}/callback.
+        )
+        .route("replicate_session", post().to(
+            replicate_session::replicate_session::<AuthCacheSessionEngineMem, SecretsConfig>) // POST /api/auth/v1/auth/replicate_session.
+        );
+    #[cfg(feature = "saml")]
+    let auth_scope = auth_scope
+        .route("saml/metadata", get().to(
+            saml::metadata::<SecretsConfig>) // GET /api/auth/v1/auth/saml/metadata.
+        )
+        .service(resource("saml/acs")
+            .wrap(from_fn(|req, next| limit_auth_requests::<SqlxPostGresDescriptor, SecretsConfig, HttpCaptchaVerifier, _>(LOGIN_RATE_LIMIT, req, next)))
+            .route(post().to(
+                saml::acs::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>, XmlDsigValidator>) // POST /api/auth/v1/auth/saml/acs.
+            )
+        );
+    app.service(auth_scope);
 }
@@ -0,0 +1,215 @@
+//! Networking layer for logging in with an OpenID Connect provider such as Google.
+use std::str::FromStr;
+use actix_web::{HttpResponse, HttpRequest, http::header, web::{Path, Query}};
+use auth_core::api::auth::oidc::{start_oidc_login, complete_oidc_login};
+use dal::federated_identities::tx_definitions::{GetFederatedIdentity, LinkFederatedIdentity, ProvisionFederatedUser};
+use dal::users::tx_definitions::{GetUser, GetUserByEmail, UpdateLastLoggedIn, CountUsers};
+use dal::permissions::tx_definitions::GetEffectivePermissions;
+use dal::metering::tx_definitions::RecordActiveUser;
+use dal::devices::tx_definitions::RecordDeviceLogin;
+use dal::organizations::tx_definitions::GetUserOrganizations;
+use dal::plans::tx_definitions::GetOrgPlan;
+use kernel::analytics::engine_configured::ConfiguredAnalyticsSink;
+use kernel::oidc::{OidcClient, OidcProvider};
+use kernel::token::session_cache::traits::{SetAuthCacheSession, GetUserAuthCacheSessions, DelAuthCacheSession};
+use serde::Deserialize;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The query string the provider sends back to the callback.
+///
+/// # Fields
+/// * `code` - The code to exchange for an ID token, missing if the login failed.
+/// * `state` - The `state` sent with the user to the provider.
+/// * `error` - Why the login failed at the provider, such as `access_denied`.
+#[derive(Deserialize, Debug)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+
+/// Redirects the user to log in at the provider in the path.
+pub async fn start<Y: GetConfigVariable>(provider: Path<String>) -> Result<HttpResponse, NanoServiceError> {
+    let provider = OidcProvider::from_str(&provider)?;
+    let redirect = start_oidc_login::<Y>(provider).await?;
+    Ok(HttpResponse::Found().insert_header((header::LOCATION, redirect.url)).finish())
+}
+
+
+/// Logs the user in with the code the provider in the path sent back.
+pub async fn callback<X, Y, Z, C>(
+    req: HttpRequest,
+    provider: Path<String>,
+    query: Query<OidcCallbackQuery>
+) -> Result<HttpResponse, NanoServiceError>
+where
+    X: GetFederatedIdentity + LinkFederatedIdentity + ProvisionFederatedUser + GetUser + GetUserByEmail
+        + GetOrgPlan + CountUsers + GetEffectivePermissions + UpdateLastLoggedIn + RecordActiveUser + RecordDeviceLogin + GetUserOrganizations,
+    Y: GetConfigVariable,
+    Z: SetAuthCacheSession + GetUserAuthCacheSessions + DelAuthCacheSession,
+    C: OidcClient,
+{
+    let provider = OidcProvider::from_str(&provider)?;
+    let query = query.into_inner();
+    if let Some(error) = query.error {
+        return Err(NanoServiceError::new(
+            format!("Login at {} failed: {}", provider.as_str(), error),
+            NanoServiceErrorStatus::Unauthorized
+        ))
+    }
+    let (Some(code), Some(state)) = (query.code, query.state) else {
+        return Err(NanoServiceError::new(
+            "The callback needs a code and a state".to_string(),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    };
+    let agent_string = match req.headers().get(header::USER_AGENT).and_then(|value| value.to_str().ok()) {
+        Some(agent) => agent.to_string(),
+        None => return Err(
+            NanoServiceError::new("No User-Agent header found".to_string(), NanoServiceErrorStatus::Unauthorized)
+        )
+    };
+    let outcome = complete_oidc_login::<X, Y, Z, ConfiguredAnalyticsSink, C>(provider, code, state, agent_string).await?;
+    Ok(HttpResponse::Ok().json(outcome))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::devices::{Device, NewDevice};
+    use kernel::chrono::{Duration, Utc};
+    use kernel::oidc::{issue_state, FederatedIdentity, FederatedSubject};
+    use kernel::oidc::engine_mock::{unsigned_id_token, CodeIsIdTokenMock};
+    use kernel::plans::OrgPlan;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::users::{NewUser, User};
+    use test_support::{call_endpoint, factories, PassAuthSessionCheckMock};
+
+    test_support::fake_config!(GoogleConfig, {
+        "GOOGLE_OIDC_CLIENT_ID" => "client-id",
+        "OIDC_DEFAULT_ROLE" => "worker",
+        "ANALYTICS_SINK" => "none",
+    });
+
+    test_support::fake_config!(UnconfiguredConfig, {
+        "GOOGLE_OIDC_CLIENT_ID" => "",
+    });
+
+    struct MockPostgres;
+
+    /// Every subject is linked to user 2.
+    #[impl_transaction(MockPostgres, GetFederatedIdentity, get_federated_identity)]
+    async fn get_federated_identity(provider: String, subject: String) -> Result<Option<FederatedIdentity>, NanoServiceError> {
+        Ok(Some(FederatedIdentity { id: 1, user_id: 2, provider, subject, email: "linked@example.com".to_string(), date_created: Default::default() }))
+    }
+
+    #[impl_transaction(MockPostgres, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        Ok(factories::user(id))
+    }
+
+    #[impl_transaction(MockPostgres, GetUserByEmail, get_user_by_email)]
+    async fn get_user_by_email(_email: String) -> Result<User, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, LinkFederatedIdentity, link_federated_identity)]
+    async fn link_federated_identity(_user_id: i32, _subject: FederatedSubject) -> Result<FederatedIdentity, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, ProvisionFederatedUser, provision_federated_user)]
+    async fn provision_federated_user(_user: NewUser, _subject: FederatedSubject) -> Result<User, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, CountUsers, count_users)]
+    async fn count_users() -> Result<i64, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, GetEffectivePermissions, get_effective_permissions)]
+    async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[impl_transaction(MockPostgres, UpdateLastLoggedIn, update_last_logged_in)]
+    async fn update_last_logged_in(_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(true)
+    }
+
+    #[impl_transaction(MockPostgres, RecordActiveUser, record_active_user)]
+    async fn record_active_user(_user_id: i32) -> Result<(), NanoServiceError> {
+        Ok(())
+    }
+
+    #[impl_transaction(MockPostgres, GetUserOrganizations, get_user_organizations)]
+    async fn get_user_organizations(_user_id: i32) -> Result<Vec<kernel::organizations::UserOrganization>, NanoServiceError> {
+        Ok(vec![])
+    }
+    #[impl_transaction(MockPostgres, RecordDeviceLogin, record_device_login)]
+    async fn record_device_login(device: NewDevice) -> Result<Device, NanoServiceError> {
+        Ok(Device { user_id: device.user_id, fingerprint: device.fingerprint, user_agent: device.user_agent, ..factories::device(1) })
+    }
+
+    async fn send_callback(uri: &str) -> actix_web::dev::ServiceResponse {
+        call_endpoint(
+            Method::GET,
+            "/oidc/{provider}/callback",
+            callback::<MockPostgres, GoogleConfig, PassAuthSessionCheckMock, CodeIsIdTokenMock>,
+            TestRequest::get().uri(uri).insert_header((header::USER_AGENT, "some-agent"))
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_start() {
+        let resp = call_endpoint(Method::GET, "/oidc/{provider}/start", start::<GoogleConfig>, TestRequest::get().uri("/oidc/google/start")).await;
+        assert_eq!(resp.status(), 302);
+        let location = resp.headers().get(header::LOCATION).unwrap().to_str().unwrap();
+        assert!(location.starts_with("https://accounts.google.com/"));
+
+        let unknown = call_endpoint(Method::GET, "/oidc/{provider}/start", start::<GoogleConfig>, TestRequest::get().uri("/oidc/myspace/start")).await;
+        assert_eq!(unknown.status(), 404);
+
+        // a provider without a client ID is not offered
+        let unconfigured = call_endpoint(Method::GET, "/oidc/{provider}/start", start::<UnconfiguredConfig>, TestRequest::get().uri("/oidc/google/start")).await;
+        assert_eq!(unconfigured.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_callback() {
+        let (state, nonce) = issue_state::<GoogleConfig>(OidcProvider::Google).unwrap();
+        let code = unsigned_id_token(&serde_json::json!({
+            "iss": "https://accounts.google.com",
+            "aud": "client-id",
+            "sub": "subject",
+            "email": "linked@example.com",
+            "email_verified": true,
+            "nonce": nonce,
+            "exp": (Utc::now() + Duration::minutes(5)).timestamp(),
+        }));
+        let resp = send_callback(&format!("/oidc/google/callback?code={}&state={}", code, state)).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let token = HeaderToken::<GoogleConfig, NoRoleCheck>::decode(body["token"].as_str().unwrap()).unwrap();
+        assert_eq!(token.user_id, 2);
+        assert_eq!(token.user_agent, "some-agent");
+
+        assert_eq!(send_callback("/oidc/google/callback?error=access_denied").await.status(), 401);
+        assert_eq!(send_callback("/oidc/google/callback?code=code").await.status(), 400);
+        assert_eq!(send_callback("/oidc/google/callback?code=code&state=forged").await.status(), 401);
+    }
+}
@@ -2,27 +2,38 @@
 use actix_web::HttpResponse;
 use auth_core::api::auth::refresh::refresh_token;
 use dal::role_permissions::tx_definitions::GetRolePermissions;
+use dal::permissions::tx_definitions::GetEffectivePermissions;
 use dal::users::tx_definitions::GetUserByUuid;
 use utils::config::GetConfigVariable;
 use kernel::token::session_cache::traits::{SetAuthCacheSession, DelAuthCacheSession};
 use kernel::token::checks::NoRoleCheck;
 use kernel::token::token::HeaderToken;
+use kernel::token::cookies::add_session_cookies;
 
-use utils::errors::NanoServiceError;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
 
 
+/// Issues a new token for the session, an impersonation token has to be started again instead.
 pub async fn refresh<X, Y, Z>(token: HeaderToken<Y, NoRoleCheck>) -> Result<HttpResponse, NanoServiceError> 
 where
-    X: GetUserByUuid + GetRolePermissions,
+    X: GetUserByUuid + GetRolePermissions + GetEffectivePermissions,
     Y: GetConfigVariable,
     Z: SetAuthCacheSession + DelAuthCacheSession,
 {
+    if token.impersonator_id.is_some() {
+        return Err(NanoServiceError::new(
+            "Impersonation tokens cannot be refreshed".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
     let login_response = match refresh_token::<X, Y, Z>(
-        token.unique_id.clone(), token.role, token.user_agent).await {
+        token.unique_id.clone(), token.role, token.org_id, token.user_agent).await {
         Ok(login_response) => login_response,
         Err(e) => {
             return Err(e)
         }
     };
-    Ok(HttpResponse::Ok().json(login_response))
+    let mut response = HttpResponse::Ok().json(&login_response);
+    add_session_cookies::<Y>(&mut response, &login_response.token)?;
+    Ok(response)
 }
\ No newline at end of file
@@ -0,0 +1,150 @@
+use actix_web::{HttpRequest, HttpResponse, web::Json};
+use kernel::token::session_cache::structs::SessionReplicationEvent;
+use kernel::token::session_cache::traits::{
+    SetAuthCacheSession,
+    TouchAuthCacheSession,
+    DelAuthCacheSession,
+    DelUserAuthCacheSessions,
+    DelDeviceAuthCacheSessions,
+    FlushAuthCacheSessions
+};
+use kernel::token::session_cache::engine_replicated::REPLICATION_SECRET_HEADER;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::webhooks::constant_time_eq;
+
+
+/// Applies a session cache change pushed by another instance.
+///
+/// # Notes
+/// `X` should be a local only engine so the event is not forwarded on again.
+pub async fn replicate_session<X, Y>(req: HttpRequest, event: Json<SessionReplicationEvent>)
+-> Result<HttpResponse, NanoServiceError>
+where
+    X: SetAuthCacheSession + TouchAuthCacheSession + DelAuthCacheSession + DelUserAuthCacheSessions + DelDeviceAuthCacheSessions
+        + FlushAuthCacheSessions,
+    Y: GetConfigVariable
+{
+    let secret = Y::get_config_variable("SESSION_REPLICATION_SECRET".to_string()).map_err(|_| {
+        NanoServiceError::new(
+            "Session replication is not enabled".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        )
+    })?;
+    let provided = req.headers()
+        .get(REPLICATION_SECRET_HEADER)
+        .and_then(|header| header.to_str().ok());
+    if !provided.is_some_and(|provided| constant_time_eq(provided.as_bytes(), secret.as_bytes())) {
+        return Err(NanoServiceError::new(
+            "Invalid replication secret".to_string(),
+            NanoServiceErrorStatus::Unauthorized
+        ))
+    }
+    match event.into_inner() {
+        SessionReplicationEvent::Set { key, session } => X::set_auth_cache_session(&key, &session).await?,
+        SessionReplicationEvent::Touch { key, last_seen, time_expire } => {
+            X::touch_auth_cache_session(&key, last_seen, time_expire).await?
+        },
+        SessionReplicationEvent::Del { key } => X::del_auth_cache_session(key).await?,
+        SessionReplicationEvent::DelUser { user_id } => {
+            X::del_user_auth_cache_sessions(user_id).await?;
+        },
+        SessionReplicationEvent::DelDevice { user_id, fingerprint } => {
+            X::del_device_auth_cache_sessions(user_id, fingerprint).await?;
+        },
+        SessionReplicationEvent::Flush => {
+            X::flush_auth_cache_sessions().await?;
+        }
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App, web};
+    use kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem;
+    use kernel::token::session_cache::traits::GetAuthCacheSession;
+    use serde_json::json;
+    use chrono::Utc;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    fn set_event(key: &str) -> serde_json::Value {
+        json!({
+            "action": "Set",
+            "key": key,
+            "session": {
+                "user_id": 3,
+                "role": "Worker",
+                "time_started": Utc::now(),
+                "time_expire": Utc::now(),
+                "user_agent": "test"
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_replicate_session_set_and_del() {
+        let app = test::init_service(
+            App::new().route("/replicate", web::post().to(
+                replicate_session::<AuthCacheSessionEngineMem, FakeConfig>
+            ))
+        ).await;
+
+        let req = test::TestRequest::post()
+            .uri("/replicate")
+            .insert_header((REPLICATION_SECRET_HEADER, "secret"))
+            .set_json(set_event("replicate-endpoint-key"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let session = AuthCacheSessionEngineMem::get_auth_cache_session(&"replicate-endpoint-key").await.unwrap();
+        assert_eq!(session.unwrap().user_id, 3);
+
+        let last_seen = Utc::now();
+        let req = test::TestRequest::post()
+            .uri("/replicate")
+            .insert_header((REPLICATION_SECRET_HEADER, "secret"))
+            .set_json(json!({"action": "Touch", "key": "replicate-endpoint-key", "last_seen": last_seen, "time_expire": last_seen}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let session = AuthCacheSessionEngineMem::get_auth_cache_session(&"replicate-endpoint-key").await.unwrap();
+        assert_eq!(session.unwrap().last_seen, last_seen);
+
+        let req = test::TestRequest::post()
+            .uri("/replicate")
+            .insert_header((REPLICATION_SECRET_HEADER, "secret"))
+            .set_json(json!({"action": "Del", "key": "replicate-endpoint-key"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let session = AuthCacheSessionEngineMem::get_auth_cache_session(&"replicate-endpoint-key").await.unwrap();
+        assert!(session.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replicate_session_wrong_secret() {
+        let app = test::init_service(
+            App::new().route("/replicate", web::post().to(
+                replicate_session::<AuthCacheSessionEngineMem, FakeConfig>
+            ))
+        ).await;
+
+        let req = test::TestRequest::post()
+            .uri("/replicate")
+            .insert_header((REPLICATION_SECRET_HEADER, "wrong"))
+            .set_json(set_event("replicate-endpoint-wrong-key"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -28,7 +28,7 @@ use actix_web::{
     web::Json
 };
 use utils::api_endpoint;
-use dal::users::tx_definitions::UpdateUuid;
+use dal::users::tx_definitions::{UpdateUuid, GetRecipientProfile};
 use dal::rate_limit_entries::tx_definitions::{
     CreateRateLimitEntry,
     UpdateRateLimitEntry,
@@ -55,10 +55,10 @@ pub struct RequestPasswordResetSchema {
 ///   email traits struct, then lastly the env variable trait struct. 
 /// - The way our `api_endpoint` macro defines the traits is W for the email traits, X for the db traits and Y for the env variable
 ///   trait.
-#[api_endpoint(db_traits=[CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry, UpdateUuid], email_traits=[SendTemplate], env_variable_trait=true)]
+#[api_endpoint(db_traits=[CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry, UpdateUuid, GetRecipientProfile], email_traits=[SendTemplate], env_variable_trait=true)]
 pub async fn request_password_reset(body: Json<RequestPasswordResetSchema>) {
     let body = body.into_inner();
-    let _ = request_password_reset_core::<X, W, Y>(body.email.clone()).await?;
+    request_password_reset_core::<X, W, Y>(body.email.clone()).await?;
     Ok(HttpResponse::Ok().finish())
 }
 
@@ -72,6 +72,7 @@ mod tests {
     //! closely mirroring how the `create` endpoint is tested.
 
     use super::*;
+    use kernel::users::RecipientProfile;
     use actix_web::{
         body::MessageBody,
         dev::ServiceResponse,
@@ -122,6 +123,11 @@ mod tests {
         })
     }
 
+    #[impl_transaction(MockDbHandleSuccess, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(_email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        Ok(None)
+    }
+
     #[impl_transaction(MockDbHandleSuccess, GetRateLimitEntry, get_rate_limit_entry)]
     async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
         Ok(Some(RateLimitEntry {
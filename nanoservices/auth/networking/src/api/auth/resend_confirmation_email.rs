@@ -25,7 +25,7 @@ use actix_web::{
     web::Json
 };
 use utils::api_endpoint;
-use dal::users::tx_definitions::UpdateUuid;
+use dal::users::tx_definitions::{UpdateUuid, GetRecipientProfile};
 use dal::rate_limit_entries::tx_definitions::{
     CreateRateLimitEntry,
     UpdateRateLimitEntry,
@@ -52,10 +52,12 @@ pub struct ResendConfirmationEmailSchema {
 ///   email traits struct, then lastly the env variable trait struct. 
 /// - The way our `api_endpoint` macro defines the traits is W for the email traits, X for the db traits and Y for the env variable
 ///   trait.
-#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry, UpdateUuid], email_traits=[SendTemplate])]
+/// - `confirmed_user=false` keeps the confirmed user check off, as the email is resent for a user who
+///   is not confirmed yet.
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry, UpdateUuid, GetRecipientProfile], email_traits=[SendTemplate], confirmed_user=false)]
 pub async fn resend_confirmation_email(body: Json<ResendConfirmationEmailSchema>) {
     let body = body.into_inner();
-    let _ = resend_confirmation_email_core::<X, W, Y>(body.email.clone()).await?;
+    resend_confirmation_email_core::<X, W, Y>(body.email.clone()).await?;
     Ok(HttpResponse::Ok().finish())
 }
 
@@ -69,6 +71,7 @@ mod tests {
     //! closely mirroring how the `create` endpoint is tested.
 
     use super::*;
+    use kernel::users::RecipientProfile;
     use actix_web::{
         body::MessageBody,
         dev::ServiceResponse,
@@ -124,6 +127,11 @@ mod tests {
         })
     }
 
+    #[impl_transaction(MockDbHandleSuccess, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(_email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        Ok(None)
+    }
+
     #[impl_transaction(MockDbHandleSuccess, GetRateLimitEntry, get_rate_limit_entry)]
     async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
         Ok(Some(RateLimitEntry {
@@ -0,0 +1,182 @@
+//! Networking layer for single sign-on with a SAML identity provider, built with the `saml` feature.
+use actix_web::{HttpResponse, HttpRequest, http::header, web::Form};
+use auth_core::api::auth::saml::{saml_metadata, complete_saml_login};
+use dal::federated_identities::tx_definitions::{GetFederatedIdentity, LinkFederatedIdentity, ProvisionFederatedUser};
+use dal::users::tx_definitions::{GetUser, GetUserByEmail, UpdateLastLoggedIn, CountUsers};
+use dal::permissions::tx_definitions::GetEffectivePermissions;
+use dal::metering::tx_definitions::RecordActiveUser;
+use dal::devices::tx_definitions::RecordDeviceLogin;
+use dal::organizations::tx_definitions::GetUserOrganizations;
+use dal::plans::tx_definitions::GetOrgPlan;
+use kernel::analytics::engine_configured::ConfiguredAnalyticsSink;
+use kernel::saml::SamlAssertionValidator;
+use kernel::token::session_cache::traits::{SetAuthCacheSession, GetUserAuthCacheSessions, DelAuthCacheSession};
+use serde::Deserialize;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The form the IdP posts to the ACS.
+///
+/// # Fields
+/// * `saml_response` - The base64 encoded response.
+/// * `relay_state` - The state the login was started with, not used.
+#[derive(Deserialize, Debug)]
+pub struct AcsForm {
+    #[serde(rename = "SAMLResponse")]
+    pub saml_response: String,
+    #[serde(rename = "RelayState")]
+    pub relay_state: Option<String>,
+}
+
+
+/// Returns the SP metadata to set the IdP up with.
+pub async fn metadata<Y: GetConfigVariable>() -> Result<HttpResponse, NanoServiceError> {
+    let metadata = saml_metadata::<Y>().await?;
+    Ok(HttpResponse::Ok().content_type("application/samlmetadata+xml").body(metadata))
+}
+
+
+/// Logs the user in with the response the IdP posted.
+pub async fn acs<X, Y, Z, V>(req: HttpRequest, form: Form<AcsForm>) -> Result<HttpResponse, NanoServiceError>
+where
+    X: GetFederatedIdentity + LinkFederatedIdentity + ProvisionFederatedUser + GetUser + GetUserByEmail
+        + GetOrgPlan + CountUsers + GetEffectivePermissions + UpdateLastLoggedIn + RecordActiveUser + RecordDeviceLogin + GetUserOrganizations,
+    Y: GetConfigVariable,
+    Z: SetAuthCacheSession + GetUserAuthCacheSessions + DelAuthCacheSession,
+    V: SamlAssertionValidator,
+{
+    let agent_string = match req.headers().get(header::USER_AGENT).and_then(|value| value.to_str().ok()) {
+        Some(agent) => agent.to_string(),
+        None => return Err(
+            NanoServiceError::new("No User-Agent header found".to_string(), NanoServiceErrorStatus::Unauthorized)
+        )
+    };
+    let outcome = complete_saml_login::<X, Y, Z, ConfiguredAnalyticsSink, V>(form.into_inner().saml_response, agent_string).await?;
+    Ok(HttpResponse::Ok().json(outcome))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::devices::{Device, NewDevice};
+    use kernel::oidc::{FederatedIdentity, FederatedSubject};
+    use kernel::plans::OrgPlan;
+    use kernel::saml::engine_mock::JsonAssertionMock;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::users::{NewUser, User};
+    use test_support::{call_endpoint, factories, PassAuthSessionCheckMock};
+
+    test_support::fake_config!(SamlConfig, {
+        "SAML_SP_ENTITY_ID" => "https://todo.example.com",
+        "SAML_DEFAULT_ROLE" => "worker",
+        "SAML_EMAIL_ATTRIBUTE" => "",
+        "ANALYTICS_SINK" => "none",
+    });
+
+    test_support::fake_config!(UnconfiguredConfig, {
+        "SAML_SP_ENTITY_ID" => "",
+    });
+
+    struct MockPostgres;
+
+    /// Every subject is linked to user 2.
+    #[impl_transaction(MockPostgres, GetFederatedIdentity, get_federated_identity)]
+    async fn get_federated_identity(provider: String, subject: String) -> Result<Option<FederatedIdentity>, NanoServiceError> {
+        Ok(Some(FederatedIdentity { id: 1, user_id: 2, provider, subject, email: "linked@example.com".to_string(), date_created: Default::default() }))
+    }
+
+    #[impl_transaction(MockPostgres, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        Ok(factories::user(id))
+    }
+
+    #[impl_transaction(MockPostgres, GetUserByEmail, get_user_by_email)]
+    async fn get_user_by_email(_email: String) -> Result<User, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, LinkFederatedIdentity, link_federated_identity)]
+    async fn link_federated_identity(_user_id: i32, _subject: FederatedSubject) -> Result<FederatedIdentity, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, ProvisionFederatedUser, provision_federated_user)]
+    async fn provision_federated_user(_user: NewUser, _subject: FederatedSubject) -> Result<User, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, CountUsers, count_users)]
+    async fn count_users() -> Result<i64, NanoServiceError> {
+        Err(NanoServiceError::new("every subject is linked".to_string(), NanoServiceErrorStatus::Unknown))
+    }
+
+    #[impl_transaction(MockPostgres, GetEffectivePermissions, get_effective_permissions)]
+    async fn get_effective_permissions(_user_id: i32) -> Result<Vec<String>, NanoServiceError> {
+        Ok(Vec::new())
+    }
+
+    #[impl_transaction(MockPostgres, UpdateLastLoggedIn, update_last_logged_in)]
+    async fn update_last_logged_in(_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(true)
+    }
+
+    #[impl_transaction(MockPostgres, RecordActiveUser, record_active_user)]
+    async fn record_active_user(_user_id: i32) -> Result<(), NanoServiceError> {
+        Ok(())
+    }
+
+    #[impl_transaction(MockPostgres, GetUserOrganizations, get_user_organizations)]
+    async fn get_user_organizations(_user_id: i32) -> Result<Vec<kernel::organizations::UserOrganization>, NanoServiceError> {
+        Ok(vec![])
+    }
+    #[impl_transaction(MockPostgres, RecordDeviceLogin, record_device_login)]
+    async fn record_device_login(device: NewDevice) -> Result<Device, NanoServiceError> {
+        Ok(Device { user_id: device.user_id, fingerprint: device.fingerprint, user_agent: device.user_agent, ..factories::device(1) })
+    }
+
+    async fn send_acs(saml_response: &str) -> actix_web::dev::ServiceResponse {
+        call_endpoint(
+            Method::POST,
+            "/saml/acs",
+            acs::<MockPostgres, SamlConfig, PassAuthSessionCheckMock, JsonAssertionMock>,
+            TestRequest::post()
+                .uri("/saml/acs")
+                .insert_header((header::USER_AGENT, "some-agent"))
+                .set_form([("SAMLResponse", saml_response), ("RelayState", "")])
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_metadata() {
+        let resp = call_endpoint(Method::GET, "/saml/metadata", metadata::<SamlConfig>, TestRequest::get().uri("/saml/metadata")).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "application/samlmetadata+xml");
+        let body = test::read_body(resp).await;
+        assert!(String::from_utf8(body.to_vec()).unwrap().contains(r#"entityID="https://todo.example.com""#));
+
+        let unconfigured = call_endpoint(Method::GET, "/saml/metadata", metadata::<UnconfiguredConfig>, TestRequest::get().uri("/saml/metadata")).await;
+        assert_eq!(unconfigured.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_acs() {
+        let resp = send_acs(r#"{"name_id": "linked@example.com"}"#).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let token = HeaderToken::<SamlConfig, NoRoleCheck>::decode(body["token"].as_str().unwrap()).unwrap();
+        assert_eq!((token.user_id, token.user_agent.as_str()), (2, "some-agent"));
+
+        assert_eq!(send_acs("refused").await.status(), 401);
+    }
+}
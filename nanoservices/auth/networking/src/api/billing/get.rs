@@ -0,0 +1,95 @@
+//! Networking layer for reading the organization's billing status
+use dal::billing::tx_definitions::GetOrgBilling;
+use dal::plans::tx_definitions::GetOrgPlan;
+use auth_core::api::billing::get_billing::get_billing as get_billing_core;
+use actix_web::HttpResponse;
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetOrgBilling, GetOrgPlan])]
+pub async fn get_billing() {
+    let billing = get_billing_core::<X>().await?;
+    Ok(HttpResponse::Ok().json(billing))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web, App,
+    };
+    use dal_tx_impl::impl_transaction;
+    use kernel::billing::{BillingStatus, OrgBilling};
+    use kernel::plans::{OrgPlan, Plan};
+    use kernel::users::UserRole;
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use utils::config::GetConfigVariable;
+    use utils::errors::NanoServiceError;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockDbHandle;
+
+    test_support::confirmed_users!(MockDbHandle);
+
+    #[impl_transaction(MockDbHandle, GetOrgBilling, get_org_billing)]
+    async fn get_org_billing() -> Result<OrgBilling, NanoServiceError> {
+        Ok(OrgBilling {
+            status: BillingStatus::PastDue,
+            customer_id: Some("cus_1".to_string()),
+            subscription_id: Some("sub_1".to_string()),
+            current_period_end: None,
+            last_event_at: None,
+            date_updated: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(OrgPlan {
+            plan: Plan::Pro,
+            max_users: None,
+            max_todos: None,
+            mfa: None,
+            api_access: None,
+            date_updated: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    async fn send_as(role: UserRole) -> actix_web::dev::ServiceResponse {
+        let app = init_service(App::new().route(
+            "/billing",
+            web::get().to(get_billing::<MockDbHandle, MockConfig, PassAuthSessionCheckMock>)
+        )).await;
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new("some-agent".to_string(), 1, role);
+        let req = TestRequest::get()
+            .uri("/billing")
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .to_request();
+        call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_get_billing() {
+        let resp = send_as(UserRole::Admin).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["status"], "past_due");
+        assert_eq!(body["customer_id"], "cus_1");
+        assert_eq!(body["plan"]["plan"], "pro");
+        assert_eq!(body["plan"]["entitlements"]["max_users"], 50);
+
+        assert_eq!(send_as(UserRole::Worker).await.status(), 401);
+    }
+}
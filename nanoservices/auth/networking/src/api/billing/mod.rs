@@ -0,0 +1,25 @@
+//! Defines the endpoints for billing.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v1/billing`. Admins can read the billing status, and Stripe
+//! posts its webhooks to `stripe-webhook`, which is authenticated by Stripe's signature.
+pub mod get;
+pub mod stripe_webhook;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn billing_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v1/billing") // Namespace for billing routes.
+        .route("", get().to(
+            get::get_billing::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/billing.
+        )
+        .route("stripe-webhook", post().to(
+            stripe_webhook::receive_stripe_webhook::<SqlxPostGresDescriptor, SecretsConfig>) // POST /api/auth/v1/billing/stripe-webhook.
+        )
+    );
+}
@@ -0,0 +1,81 @@
+//! Networking layer for Stripe's billing webhooks
+use actix_web::{HttpRequest, HttpResponse, web::Bytes};
+use dal::billing::tx_definitions::ApplyBillingChange;
+use auth_core::api::billing::stripe_webhook::receive_stripe_webhook as receive_stripe_webhook_core;
+use kernel::billing::STRIPE_SIGNATURE_HEADER;
+use kernel::chrono::Utc;
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+
+
+/// Applies a webhook posted by Stripe.
+///
+/// # Notes
+/// The webhook is authenticated by Stripe's signature rather than a token, so the raw body is read
+/// as the signature is over the exact bytes Stripe sent.
+pub async fn receive_stripe_webhook<X, Y>(req: HttpRequest, body: Bytes) -> Result<HttpResponse, NanoServiceError>
+where
+    X: ApplyBillingChange,
+    Y: GetConfigVariable
+{
+    let signature = req.headers().get(STRIPE_SIGNATURE_HEADER).and_then(|value| value.to_str().ok());
+    let response = receive_stripe_webhook_core::<X, Y>(signature, &body, Utc::now().timestamp()).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App, web};
+    use dal_tx_impl::impl_transaction;
+    use kernel::billing::{BillingChange, BillingStatus, stripe_signature};
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("whsec_test".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    #[impl_transaction(MockPostgres, ApplyBillingChange, apply_billing_change)]
+    async fn apply_billing_change(change: BillingChange) -> Result<bool, NanoServiceError> {
+        assert_eq!(change.status, BillingStatus::Canceled);
+        Ok(true)
+    }
+
+    #[tokio::test]
+    async fn test_receive_stripe_webhook() {
+        let app = test::init_service(
+            App::new().route("/stripe-webhook", web::post().to(
+                receive_stripe_webhook::<MockPostgres, MockConfig>
+            ))
+        ).await;
+        let now = Utc::now().timestamp();
+        let payload = serde_json::json!({
+            "id": "evt_deleted", "type": "customer.subscription.deleted", "created": now,
+            "data": {"object": {"id": "sub_1", "customer": "cus_1"}}
+        }).to_string();
+        let signature = format!("t={},v1={}", now, stripe_signature("whsec_test", now, payload.as_bytes()));
+
+        let req = test::TestRequest::post()
+            .uri("/stripe-webhook")
+            .insert_header((STRIPE_SIGNATURE_HEADER, signature))
+            .set_payload(payload.clone())
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body, serde_json::json!({"event_id": "evt_deleted", "outcome": "applied"}));
+
+        let req = test::TestRequest::post()
+            .uri("/stripe-webhook")
+            .insert_header((STRIPE_SIGNATURE_HEADER, format!("t={},v1=forged", now)))
+            .set_payload(payload)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+}
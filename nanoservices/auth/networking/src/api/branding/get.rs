@@ -0,0 +1,47 @@
+//! Networking layer for reading the organization's branding
+use dal::branding::tx_definitions::GetOrgBranding;
+use auth_core::api::branding::get_branding::get_branding as get_branding_core;
+use actix_web::HttpResponse;
+use utils::api_endpoint;
+
+
+#[api_endpoint(db_traits=[GetOrgBranding])]
+pub async fn get_branding() {
+    let branding = get_branding_core::<X>().await?;
+    Ok(HttpResponse::Ok().json(branding))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web, App,
+    };
+    use dal_tx_impl::impl_transaction;
+    use kernel::branding::OrgBranding;
+    use utils::errors::NanoServiceError;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetOrgBranding, get_org_branding)]
+    async fn get_org_branding() -> Result<OrgBranding, NanoServiceError> {
+        Ok(OrgBranding {
+            product_name: "Acme Tasks".to_string(),
+            logo_url: None,
+            accent_color: "#2563eb".to_string(),
+            date_updated: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_branding_without_token() {
+        let app = init_service(App::new().route("/branding", web::get().to(get_branding::<MockDbHandle>))).await;
+        let resp = call_service(&app, TestRequest::get().uri("/branding").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["product_name"], "Acme Tasks");
+        assert_eq!(body["accent_color"], "#2563eb");
+    }
+}
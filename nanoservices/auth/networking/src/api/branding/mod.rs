@@ -0,0 +1,25 @@
+//! Defines the endpoints for the organization's branding.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v1/branding`. Reading the branding needs no token as the
+//! frontend shows it on the login page, changing it is limited to super admins.
+pub mod get;
+pub mod update;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn branding_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v1/branding") // Namespace for branding routes.
+        .route("", get().to(
+            get::get_branding::<SqlxPostGresDescriptor>) // GET /api/auth/v1/branding.
+        )
+        .route("update", post().to(
+            update::update_branding::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/branding/update.
+        )
+    );
+}
@@ -0,0 +1,94 @@
+//! Networking layer for changing the organization's branding
+use dal::branding::tx_definitions::UpdateOrgBranding;
+use kernel::branding::NewOrgBranding;
+use auth_core::api::branding::update_branding::update_branding as update_branding_core;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[UpdateOrgBranding])]
+pub async fn update_branding(body: Json<NewOrgBranding>) {
+    let branding = update_branding_core::<X>(body.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(branding))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::UserRole;
+    use kernel::branding::OrgBranding;
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::send_test_request;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, UpdateOrgBranding, update_org_branding)]
+    async fn update_org_branding(branding: NewOrgBranding) -> Result<OrgBranding, NanoServiceError> {
+        Ok(OrgBranding {
+            product_name: branding.product_name,
+            logo_url: branding.logo_url,
+            accent_color: branding.accent_color,
+            date_updated: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_update_branding() {
+        send_test_request!(
+            POST,
+            "/branding",
+            serde_json::json!({"product_name": "Acme Tasks", "logo_url": null, "accent_color": "#2563eb"}),
+            SuperAdminRoleCheck,
+            UserRole::SuperAdmin,
+            1,
+            update_branding,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_update_invalid_branding() {
+        send_test_request!(
+            POST,
+            "/branding",
+            serde_json::json!({"product_name": "Acme Tasks", "logo_url": "http://cdn.example.com/logo.png", "accent_color": "#2563eb"}),
+            SuperAdminRoleCheck,
+            UserRole::SuperAdmin,
+            1,
+            update_branding,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_update_branding_as_admin() {
+        send_test_request!(
+            POST,
+            "/branding",
+            serde_json::json!({"product_name": "Acme Tasks", "logo_url": null, "accent_color": "#2563eb"}),
+            SuperAdminRoleCheck,
+            UserRole::Admin,
+            1,
+            update_branding,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -0,0 +1,200 @@
+//! Endpoints for users to list, name, trust and revoke their devices.
+use actix_web::{HttpResponse, web::{Json, Path}};
+use auth_core::api::devices::manage::{
+    list_devices as list_devices_core,
+    rename_device as rename_device_core,
+    trust_device as trust_device_core,
+    revoke_device as revoke_device_core,
+};
+use dal::devices::tx_definitions::{ListDevices, RenameDevice, RevokeDevice, TrustDevice};
+use kernel::token::session_cache::structs::RemovedSessions;
+use kernel::token::session_cache::traits::DelDeviceAuthCacheSessions;
+use serde::{Deserialize, Serialize};
+use utils::api_endpoint;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The name to give a device.
+///
+/// # Fields
+/// * `name` - The name, such as `Work laptop`, a blank name clears it.
+#[derive(Serialize, Deserialize)]
+pub struct RenameDeviceBody {
+    pub name: String,
+}
+
+
+/// Refuses a change to the devices made with an impersonation token.
+fn refuse_impersonation(impersonator_id: Option<i32>) -> Result<(), NanoServiceError> {
+    match impersonator_id {
+        Some(_) => Err(NanoServiceError::new(
+            "Devices cannot be changed with an impersonation token".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        )),
+        None => Ok(())
+    }
+}
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[ListDevices])]
+pub async fn list_devices() {
+    Ok(HttpResponse::Ok().json(list_devices_core::<X>(jwt.user_id).await?))
+}
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[RenameDevice])]
+pub async fn rename_device(id: Path<i32>, body: Json<RenameDeviceBody>) {
+    refuse_impersonation(jwt.impersonator_id)?;
+    let device = rename_device_core::<X>(jwt.user_id, id.into_inner(), body.into_inner().name).await?;
+    Ok(HttpResponse::Ok().json(device))
+}
+
+
+/// Marks the device as trusted for `TRUSTED_DEVICE_DAYS`.
+#[api_endpoint(token=NoRoleCheck, db_traits=[TrustDevice])]
+pub async fn trust_device(id: Path<i32>) {
+    refuse_impersonation(jwt.impersonator_id)?;
+    let device = trust_device_core::<X, Y>(jwt.user_id, id.into_inner(), true).await?;
+    Ok(HttpResponse::Ok().json(device))
+}
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[TrustDevice])]
+pub async fn untrust_device(id: Path<i32>) {
+    refuse_impersonation(jwt.impersonator_id)?;
+    let device = trust_device_core::<X, Y>(jwt.user_id, id.into_inner(), false).await?;
+    Ok(HttpResponse::Ok().json(device))
+}
+
+
+/// Forgets the device and ends the sessions started on it.
+#[api_endpoint(token=NoRoleCheck, db_traits=[RevokeDevice], cache_traits=[DelDeviceAuthCacheSessions])]
+pub async fn revoke_device(id: Path<i32>) {
+    refuse_impersonation(jwt.impersonator_id)?;
+    let removed = revoke_device_core::<X, Z>(jwt.user_id, id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(RemovedSessions { removed }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::NaiveDateTime;
+    use kernel::devices::Device;
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    /// User 1 has device 4.
+    fn device(user_id: i32, id: i32) -> Option<Device> {
+        (user_id == 1 && id == 4).then(|| Device { id, user_id, ..factories::device(id) })
+    }
+
+    #[impl_transaction(MockPostgres, ListDevices, list_devices)]
+    async fn list_devices(user_id: i32) -> Result<Vec<Device>, NanoServiceError> {
+        Ok(device(user_id, 4).into_iter().collect())
+    }
+
+    #[impl_transaction(MockPostgres, RenameDevice, rename_device)]
+    async fn rename_device(user_id: i32, id: i32, name: Option<String>) -> Result<Option<Device>, NanoServiceError> {
+        Ok(device(user_id, id).map(|device| Device { name, ..device }))
+    }
+
+    #[impl_transaction(MockPostgres, TrustDevice, trust_device)]
+    async fn trust_device(user_id: i32, id: i32, trusted_until: Option<NaiveDateTime>) -> Result<Option<Device>, NanoServiceError> {
+        Ok(device(user_id, id).map(|device| Device { trusted_until, ..device }))
+    }
+
+    #[impl_transaction(MockPostgres, RevokeDevice, revoke_device)]
+    async fn revoke_device(user_id: i32, id: i32) -> Result<Option<Device>, NanoServiceError> {
+        Ok(device(user_id, id))
+    }
+
+    fn request(request: TestRequest) -> TestRequest {
+        TokenBuilder::<FakeConfig, NoRoleCheck>::new().request(request)
+    }
+
+    #[tokio::test]
+    async fn test_list_devices() {
+        let resp = call_endpoint(
+            Method::GET, "/devices",
+            list_devices::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(TestRequest::get().uri("/devices"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[0]["id"], 4);
+        assert!(body[0].get("fingerprint").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rename_device() {
+        let resp = call_endpoint(
+            Method::PUT, "/devices/{id}/name",
+            rename_device::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(TestRequest::put().uri("/devices/4/name")).set_json(RenameDeviceBody { name: "Work laptop".to_string() })
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["name"], "Work laptop");
+    }
+
+    #[tokio::test]
+    async fn test_trust_and_untrust_device() {
+        let resp = call_endpoint(
+            Method::POST, "/devices/{id}/trust",
+            trust_device::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(TestRequest::post().uri("/devices/4/trust"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["trusted_until"].is_string());
+
+        let resp = call_endpoint(
+            Method::DELETE, "/devices/{id}/trust",
+            untrust_device::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(TestRequest::delete().uri("/devices/4/trust"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["trusted_until"].is_null());
+
+        let resp = call_endpoint(
+            Method::POST, "/devices/{id}/trust",
+            trust_device::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(TestRequest::post().uri("/devices/9/trust"))
+        ).await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_cannot_trust_device() {
+        let req = TokenBuilder::<FakeConfig, NoRoleCheck>::new()
+            .impersonated_by(7)
+            .request(TestRequest::post().uri("/devices/4/trust"));
+        let resp = call_endpoint(
+            Method::POST, "/devices/{id}/trust",
+            trust_device::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            req
+        ).await;
+        assert_eq!(resp.status(), 403);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_device() {
+        let resp = call_endpoint(
+            Method::DELETE, "/devices/{id}",
+            revoke_device::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(TestRequest::delete().uri("/devices/4"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: RemovedSessions = test::read_body_json(resp).await;
+        assert_eq!(body.removed, 1);
+    }
+}
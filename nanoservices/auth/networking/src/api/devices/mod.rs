@@ -0,0 +1,34 @@
+//! Defines the endpoints for users managing the devices they have logged in from.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v1/devices` and only reach the caller's own devices. An
+//! impersonation token can list the devices but cannot change them, so a super admin cannot trust a
+//! device on the user's behalf.
+pub mod manage;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get, put, delete};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn devices_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v1/devices") // Namespace for device routes.
+        .route("", get().to(
+            manage::list_devices::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/devices.
+        )
+        .route("{id}/name", put().to(
+            manage::rename_device::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // PUT /api/auth/v1/devices/{id}/name.
+        )
+        .route("{id}/trust", post().to(
+            manage::trust_device::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/devices/{id}/trust.
+        )
+        .route("{id}/trust", delete().to(
+            manage::untrust_device::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // DELETE /api/auth/v1/devices/{id}/trust.
+        )
+        .route("{id}", delete().to(
+            manage::revoke_device::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // DELETE /api/auth/v1/devices/{id}.
+        )
+    );
+}
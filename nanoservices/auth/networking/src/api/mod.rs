@@ -1,6 +1,16 @@
 pub mod users;
 pub mod auth;
 pub mod roles;
+pub mod sessions;
+pub mod branding;
+pub mod plans;
+pub mod billing;
+pub mod v2;
+pub mod onboarding;
+pub mod audit;
+pub mod api_keys;
+pub mod devices;
+pub mod organizations;
 use actix_web::web::ServiceConfig;
 
 
@@ -8,4 +18,14 @@ pub fn views_factory(app: &mut ServiceConfig) {
     users::users_factory(app);
     auth::auth_factory(app);
     roles::roles_factory(app);
+    sessions::sessions_factory(app);
+    branding::branding_factory(app);
+    plans::plans_factory(app);
+    billing::billing_factory(app);
+    v2::v2_factory(app);
+    onboarding::onboarding_factory(app);
+    audit::audit_factory(app);
+    api_keys::api_keys_factory(app);
+    devices::devices_factory(app);
+    organizations::organizations_factory(app);
 }
@@ -0,0 +1,101 @@
+//! Networking layer for accepting the terms
+use dal::onboarding::tx_definitions::{GetOnboardingStateByUuid, AcceptTerms};
+use auth_core::api::onboarding::accept_terms::accept_terms as accept_terms_core;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+use serde::Deserialize;
+use utils::api_endpoint;
+
+
+/// Schema for accepting the terms
+///
+/// # Fields
+/// * `unique_id` - The unique ID of the user from their confirmation email.
+/// * `terms_version` - The version of the terms the user was shown.
+#[derive(Deserialize)]
+pub struct AcceptTermsSchema {
+    pub unique_id: String,
+    pub terms_version: String,
+}
+
+#[api_endpoint(db_traits=[GetOnboardingStateByUuid, AcceptTerms], env_variable_trait=true)]
+pub async fn accept_terms(body: Json<AcceptTermsSchema>) {
+    let status = accept_terms_core::<X, Y>(&body.unique_id, &body.terms_version).await?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        http::header::ContentType,
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web, App,
+    };
+    use dal_tx_impl::impl_transaction;
+    use kernel::onboarding::OnboardingState;
+    use utils::config::GetConfigVariable;
+    use utils::errors::NanoServiceError;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("2024-06".to_string())
+        }
+    }
+
+    fn state(terms_version: Option<String>) -> OnboardingState {
+        OnboardingState {
+            user_id: 1,
+            confirmed: true,
+            terms_version,
+            terms_accepted_at: None,
+            password_set_at: None,
+            profile_completed_at: None,
+        }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetOnboardingStateByUuid, get_onboarding_state_by_uuid)]
+    async fn get_onboarding_state_by_uuid(uuid: String) -> Result<Option<OnboardingState>, NanoServiceError> {
+        assert_eq!(uuid, "unique-123");
+        Ok(Some(state(None)))
+    }
+
+    #[impl_transaction(MockDbHandle, AcceptTerms, accept_terms)]
+    async fn accept_terms(user_id: i32, terms_version: String) -> Result<OnboardingState, NanoServiceError> {
+        assert_eq!(user_id, 1);
+        Ok(state(Some(terms_version)))
+    }
+
+    async fn send(terms_version: &str) -> actix_web::dev::ServiceResponse {
+        let app = init_service(
+            App::new().route("/accept_terms", web::post().to(accept_terms::<MockDbHandle, MockConfig>))
+        ).await;
+        let req = TestRequest::post()
+            .insert_header(ContentType::json())
+            .uri("/accept_terms")
+            .set_json(serde_json::json!({"unique_id": "unique-123", "terms_version": terms_version}))
+            .to_request();
+        call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_accept_terms() {
+        let resp = send("2024-06").await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["next_step"], "set_password");
+    }
+
+    #[tokio::test]
+    async fn test_accept_outdated_terms() {
+        let resp = send("2023-01").await;
+        assert_eq!(resp.status(), 409);
+    }
+}
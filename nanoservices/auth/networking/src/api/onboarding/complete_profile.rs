@@ -0,0 +1,96 @@
+//! Networking layer for completing a user's profile
+use dal::onboarding::tx_definitions::{GetOnboardingState, CompleteOnboardingProfile};
+use kernel::onboarding::OnboardingProfile;
+use auth_core::api::onboarding::complete_profile::complete_profile as complete_profile_core;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetOnboardingState, CompleteOnboardingProfile])]
+pub async fn complete_profile(body: Json<OnboardingProfile>) {
+    let status = complete_profile_core::<X, Y>(jwt.user_id, body.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::UserRole;
+    use kernel::onboarding::OnboardingState;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::send_test_request;
+
+    fn state(user_id: i32) -> OnboardingState {
+        let now = chrono::Utc::now().naive_utc();
+        OnboardingState {
+            user_id,
+            confirmed: true,
+            // the test config returns "secret" for every variable, including `TERMS_VERSION`
+            terms_version: Some("secret".to_string()),
+            terms_accepted_at: Some(now),
+            password_set_at: Some(now),
+            profile_completed_at: None,
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetOnboardingState, get_onboarding_state)]
+    async fn get_onboarding_state(user_id: i32) -> Result<Option<OnboardingState>, NanoServiceError> {
+        Ok(Some(state(user_id)))
+    }
+
+    #[impl_transaction(MockPostgres, CompleteOnboardingProfile, complete_onboarding_profile)]
+    async fn complete_onboarding_profile(user_id: i32, profile: OnboardingProfile) -> Result<OnboardingState, NanoServiceError> {
+        assert_eq!(user_id, 3);
+        assert_eq!(profile.timezone, "America/New_York");
+        let mut state = state(user_id);
+        state.profile_completed_at = Some(chrono::Utc::now().naive_utc());
+        Ok(state)
+    }
+
+    #[tokio::test]
+    async fn test_complete_profile() {
+        send_test_request!(
+            POST,
+            "/complete_profile",
+            serde_json::json!({"first_name": "Ada", "last_name": "Lovelace", "locale": "en-US", "timezone": "America/New_York"}),
+            NoRoleCheck,
+            UserRole::Worker,
+            3,
+            complete_profile,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body["next_step"], "complete");
+    }
+
+    #[tokio::test]
+    async fn test_complete_invalid_profile() {
+        send_test_request!(
+            POST,
+            "/complete_profile",
+            serde_json::json!({"first_name": "", "last_name": "Lovelace", "locale": "en-US", "timezone": "UTC"}),
+            NoRoleCheck,
+            UserRole::Worker,
+            3,
+            complete_profile,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 400);
+    }
+}
@@ -0,0 +1,75 @@
+//! Networking layer for the onboarding funnel shown to admins
+use dal::onboarding::tx_definitions::GetOnboardingFunnel;
+use auth_core::api::onboarding::get_funnel::get_onboarding_funnel;
+use actix_web::HttpResponse;
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetOnboardingFunnel])]
+pub async fn get_funnel() {
+    let funnel = get_onboarding_funnel::<X, Y>().await?;
+    Ok(HttpResponse::Ok().json(funnel))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web, App,
+    };
+    use dal_tx_impl::impl_transaction;
+    use kernel::onboarding::OnboardingFunnelCount;
+    use kernel::users::UserRole;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use utils::config::GetConfigVariable;
+    use utils::errors::NanoServiceError;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockDbHandle;
+
+    test_support::confirmed_users!(MockDbHandle);
+
+    #[impl_transaction(MockDbHandle, GetOnboardingFunnel, get_onboarding_funnel)]
+    async fn get_onboarding_funnel(_terms_version: String) -> Result<Vec<OnboardingFunnelCount>, NanoServiceError> {
+        Ok(vec![OnboardingFunnelCount { step: "confirm_email".to_string(), users: 4 }])
+    }
+
+    async fn send(role: UserRole) -> actix_web::dev::ServiceResponse {
+        let service = get_funnel::<MockDbHandle, MockConfig, PassAuthSessionCheckMock>;
+        let app = init_service(App::new().route("/funnel", web::get().to(service))).await;
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new(agent.clone(), 1, role);
+        let req = TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, agent))
+            .uri("/funnel")
+            .to_request();
+        call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_get_funnel() {
+        let resp = send(UserRole::Admin).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body[0], serde_json::json!({"step": "confirm_email", "users": 4}));
+        assert_eq!(body.as_array().unwrap().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_get_funnel_as_worker() {
+        let resp = send(UserRole::Worker).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -0,0 +1,38 @@
+//! Defines the endpoints for moving users through onboarding.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v1/onboarding`. The frontend reads the next step from the
+//! status routes to decide which screen to show. Users cannot log in until they have confirmed
+//! their email, so the status and terms routes before that point take the unique ID from the
+//! confirmation email rather than a token. Setting the password is recorded by `users/reset_password`.
+pub mod status;
+pub mod accept_terms;
+pub mod complete_profile;
+pub mod funnel;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn onboarding_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v1/onboarding") // Namespace for onboarding routes.
+        .route("status", get().to(
+            status::get_status::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/onboarding/status.
+        )
+        .route("status/{unique_id}", get().to(
+            status::get_status_by_uuid::<SqlxPostGresDescriptor, SecretsConfig>) // GET /api/auth/v1/onboarding/status/{unique_id}.
+        )
+        .route("accept_terms", post().to(
+            accept_terms::accept_terms::<SqlxPostGresDescriptor, SecretsConfig>) // POST /api/auth/v1/onboarding/accept_terms.
+        )
+        .route("complete_profile", post().to(
+            complete_profile::complete_profile::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/onboarding/complete_profile.
+        )
+        .route("funnel", get().to(
+            funnel::get_funnel::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/onboarding/funnel.
+        )
+    );
+}
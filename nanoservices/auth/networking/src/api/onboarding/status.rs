@@ -0,0 +1,71 @@
+//! Networking layer for reporting where a user is in onboarding
+use dal::onboarding::tx_definitions::{GetOnboardingState, GetOnboardingStateByUuid};
+use auth_core::api::onboarding::get_status::{get_onboarding_status, get_onboarding_status_by_uuid};
+use actix_web::{HttpResponse, web};
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetOnboardingState])]
+pub async fn get_status() {
+    let status = get_onboarding_status::<X, Y>(jwt.user_id).await?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+
+#[api_endpoint(db_traits=[GetOnboardingStateByUuid], env_variable_trait=true)]
+pub async fn get_status_by_uuid(path: web::Path<String>) {
+    let status = get_onboarding_status_by_uuid::<X, Y>(&path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, read_body_json, TestRequest},
+        App,
+    };
+    use dal_tx_impl::impl_transaction;
+    use kernel::onboarding::OnboardingState;
+    use utils::config::GetConfigVariable;
+    use utils::errors::NanoServiceError;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("1".to_string())
+        }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetOnboardingStateByUuid, get_onboarding_state_by_uuid)]
+    async fn get_onboarding_state_by_uuid(uuid: String) -> Result<Option<OnboardingState>, NanoServiceError> {
+        Ok((uuid == "unique-123").then_some(OnboardingState {
+            user_id: 1,
+            confirmed: true,
+            terms_version: None,
+            terms_accepted_at: None,
+            password_set_at: None,
+            profile_completed_at: None,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_get_status_by_uuid() {
+        let service = get_status_by_uuid::<MockDbHandle, MockConfig>;
+        let app = init_service(App::new().route("/status/{unique_id}", web::get().to(service))).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/status/unique-123").to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["next_step"], "accept_terms");
+        assert_eq!(body["completed_steps"], serde_json::json!(["confirm_email"]));
+        assert_eq!(body["terms_version"], "1");
+
+        let resp = call_service(&app, TestRequest::get().uri("/status/unknown").to_request()).await;
+        assert_eq!(resp.status(), 404);
+    }
+}
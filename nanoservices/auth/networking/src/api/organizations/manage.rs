@@ -0,0 +1,281 @@
+//! Endpoints for creating organizations, switching between them and managing their members.
+use actix_web::{HttpResponse, web::{Json, Path}};
+use auth_core::api::organizations::manage::{
+    create_organization as create_organization_core,
+    list_organizations as list_organizations_core,
+    check_can_switch,
+    list_members as list_members_core,
+    add_member as add_member_core,
+    remove_member as remove_member_core,
+};
+use dal::organizations::tx_definitions::{
+    AddOrgMember, CreateOrganization, GetOrgMembers, GetOrgMembership, GetOrganization, GetUserOrganizations, RemoveOrgMember,
+};
+use dal::users::tx_definitions::GetUser;
+use kernel::chrono::Utc;
+use kernel::organizations::{NewOrganization, OrgRole};
+use kernel::token::cookies::add_token_cookie;
+use serde::{Deserialize, Serialize};
+use utils::api_endpoint;
+
+
+/// The organization to create.
+///
+/// # Fields
+/// * `org` - The name of the organization, given at the top level of the body.
+/// * `owner_id` - The ID of the user who owns the organization, the caller if not given.
+#[derive(Serialize, Deserialize)]
+pub struct CreateOrganizationBody {
+    #[serde(flatten)]
+    pub org: NewOrganization,
+    pub owner_id: Option<i32>,
+}
+
+
+/// The user to add to an organization.
+///
+/// # Fields
+/// * `user_id` - The ID of the user.
+/// * `org_role` - The role of the user in the organization, `member` if not given.
+#[derive(Serialize, Deserialize)]
+pub struct AddMemberBody {
+    pub user_id: i32,
+    #[serde(default)]
+    pub org_role: OrgRole,
+}
+
+
+/// The token for the session acting in another organization.
+///
+/// # Fields
+/// * `token` - The token, which expires when the token it replaces would have.
+/// * `org_id` - The ID of the organization the token acts in.
+#[derive(Serialize, Deserialize)]
+pub struct SwitchOrganizationResponse {
+    pub token: String,
+    pub org_id: i32,
+}
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[CreateOrganization, GetUser])]
+pub async fn create_organization(body: Json<CreateOrganizationBody>) {
+    let CreateOrganizationBody { org, owner_id } = body.into_inner();
+    let org = create_organization_core::<X>(owner_id.unwrap_or(jwt.user_id), org).await?;
+    Ok(HttpResponse::Created().json(org))
+}
+
+
+/// Lists the organizations the caller belongs to, with their role in each.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetUserOrganizations])]
+pub async fn list_organizations() {
+    Ok(HttpResponse::Ok().json(list_organizations_core::<X>(jwt.user_id).await?))
+}
+
+
+/// Re-issues the caller's token to act in the organization in the path, for the same session.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetOrganization, GetOrgMembership])]
+pub async fn switch_organization(org_id: Path<i32>) {
+    let org_id = org_id.into_inner();
+    check_can_switch::<X>(org_id, jwt.user_id, &jwt.role).await?;
+    let token = jwt.reissue(jwt.time_expire - Utc::now()).in_org(org_id).encode()?;
+    let mut response = HttpResponse::Ok().json(SwitchOrganizationResponse { token: token.clone(), org_id });
+    add_token_cookie::<Y>(&mut response, &token)?;
+    Ok(response)
+}
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetOrganization, GetOrgMembership, GetOrgMembers])]
+pub async fn list_members(org_id: Path<i32>) {
+    let members = list_members_core::<X>(org_id.into_inner(), jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok().json(members))
+}
+
+
+/// Adds a user to the organization, or changes the role of a member.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetOrganization, GetOrgMembership, AddOrgMember, GetUser])]
+pub async fn add_member(org_id: Path<i32>, body: Json<AddMemberBody>) {
+    let AddMemberBody { user_id, org_role } = body.into_inner();
+    let membership = add_member_core::<X>(org_id.into_inner(), jwt.user_id, &jwt.role, user_id, org_role).await?;
+    Ok(HttpResponse::Ok().json(membership))
+}
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetOrganization, GetOrgMembership, RemoveOrgMember])]
+pub async fn remove_member(path: Path<(i32, i32)>) {
+    let (org_id, user_id) = path.into_inner();
+    remove_member_core::<X>(org_id, jwt.user_id, &jwt.role, user_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::organizations::{Organization, OrgMembership, UserOrganization};
+    use kernel::token::checks::{NoRoleCheck, SuperAdminRoleCheck};
+    use kernel::token::token::HeaderToken;
+    use kernel::users::{User, UserRole};
+    use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+
+    struct MockPostgres;
+
+    /// Organization 2 exists, user 1 owns it and user 3 is a member of it.
+    fn membership(org_id: i32, user_id: i32) -> Option<OrgMembership> {
+        let org_role = match (org_id, user_id) {
+            (2, 1) => OrgRole::Owner,
+            (2, 3) => OrgRole::Member,
+            _ => return None
+        };
+        Some(OrgMembership { org_id, user_id, org_role, date_joined: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockPostgres, CreateOrganization, create_organization)]
+    async fn create_organization(owner_id: i32, org: NewOrganization) -> Result<Organization, NanoServiceError> {
+        assert_eq!(owner_id, 3);
+        Ok(Organization { id: 2, name: org.name, date_created: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockPostgres, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        Ok(factories::user(id))
+    }
+
+    #[impl_transaction(MockPostgres, GetUserOrganizations, get_user_organizations)]
+    async fn get_user_organizations(user_id: i32) -> Result<Vec<UserOrganization>, NanoServiceError> {
+        Ok(membership(2, user_id).into_iter()
+            .map(|membership| UserOrganization { id: 2, name: "Acme".to_string(), org_role: membership.org_role })
+            .collect())
+    }
+
+    #[impl_transaction(MockPostgres, GetOrganization, get_organization)]
+    async fn get_organization(id: i32) -> Result<Organization, NanoServiceError> {
+        match id {
+            2 => Ok(Organization { id, name: "Acme".to_string(), date_created: Utc::now().naive_utc() }),
+            _ => Err(NanoServiceError::new(format!("Organization {} not found", id), NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    #[impl_transaction(MockPostgres, GetOrgMembership, get_org_membership)]
+    async fn get_org_membership(org_id: i32, user_id: i32) -> Result<Option<OrgMembership>, NanoServiceError> {
+        Ok(membership(org_id, user_id))
+    }
+
+    #[impl_transaction(MockPostgres, GetOrgMembers, get_org_members)]
+    async fn get_org_members(org_id: i32) -> Result<Vec<OrgMembership>, NanoServiceError> {
+        Ok([1, 3].into_iter().filter_map(|user_id| membership(org_id, user_id)).collect())
+    }
+
+    #[impl_transaction(MockPostgres, AddOrgMember, add_org_member)]
+    async fn add_org_member(org_id: i32, user_id: i32, org_role: OrgRole) -> Result<OrgMembership, NanoServiceError> {
+        Ok(OrgMembership { org_id, user_id, org_role, date_joined: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockPostgres, RemoveOrgMember, remove_org_member)]
+    async fn remove_org_member(org_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(membership(org_id, user_id).is_some())
+    }
+
+    fn request(user_id: i32, request: TestRequest) -> TestRequest {
+        TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(user_id).request(request)
+    }
+
+    #[tokio::test]
+    async fn test_create_organization() {
+        let req = TokenBuilder::<FakeConfig, SuperAdminRoleCheck>::new()
+            .role(UserRole::SuperAdmin)
+            .request(TestRequest::post().uri("/orgs"))
+            .set_json(serde_json::json!({"name": "Acme", "owner_id": 3}));
+        let resp = call_endpoint(
+            Method::POST, "/orgs",
+            create_organization::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            req
+        ).await;
+        assert_eq!(resp.status(), 201);
+        let body: Organization = test::read_body_json(resp).await;
+        assert_eq!((body.id, body.name.as_str()), (2, "Acme"));
+    }
+
+    #[tokio::test]
+    async fn test_list_organizations() {
+        let resp = call_endpoint(
+            Method::GET, "/orgs",
+            list_organizations::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(3, TestRequest::get().uri("/orgs"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body, serde_json::json!([{"id": 2, "name": "Acme", "org_role": "member"}]));
+    }
+
+    #[tokio::test]
+    async fn test_switch_organization() {
+        let resp = call_endpoint(
+            Method::POST, "/orgs/{org_id}/switch",
+            switch_organization::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(3, TestRequest::post().uri("/orgs/2/switch"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: SwitchOrganizationResponse = test::read_body_json(resp).await;
+        let token = HeaderToken::<FakeConfig, NoRoleCheck>::decode(&body.token).unwrap();
+        assert_eq!((token.user_id, token.org_id, body.org_id), (3, 2, 2));
+
+        let resp = call_endpoint(
+            Method::POST, "/orgs/{org_id}/switch",
+            switch_organization::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(4, TestRequest::post().uri("/orgs/2/switch"))
+        ).await;
+        assert_eq!(resp.status(), 403);
+    }
+
+    #[tokio::test]
+    async fn test_list_members() {
+        let resp = call_endpoint(
+            Method::GET, "/orgs/{org_id}/members",
+            list_members::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(3, TestRequest::get().uri("/orgs/2/members"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: Vec<OrgMembership> = test::read_body_json(resp).await;
+        assert_eq!(body.iter().map(|member| member.user_id).collect::<Vec<_>>(), vec![1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_add_member() {
+        let resp = call_endpoint(
+            Method::POST, "/orgs/{org_id}/members",
+            add_member::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(1, TestRequest::post().uri("/orgs/2/members")).set_json(serde_json::json!({"user_id": 5}))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: OrgMembership = test::read_body_json(resp).await;
+        assert_eq!((body.user_id, body.org_role), (5, OrgRole::Member));
+
+        let resp = call_endpoint(
+            Method::POST, "/orgs/{org_id}/members",
+            add_member::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(3, TestRequest::post().uri("/orgs/2/members")).set_json(serde_json::json!({"user_id": 5, "org_role": "admin"}))
+        ).await;
+        assert_eq!(resp.status(), 403);
+    }
+
+    #[tokio::test]
+    async fn test_remove_member() {
+        let resp = call_endpoint(
+            Method::DELETE, "/orgs/{org_id}/members/{user_id}",
+            remove_member::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(1, TestRequest::delete().uri("/orgs/2/members/3"))
+        ).await;
+        assert_eq!(resp.status(), 204);
+
+        let resp = call_endpoint(
+            Method::DELETE, "/orgs/{org_id}/members/{user_id}",
+            remove_member::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request(1, TestRequest::delete().uri("/orgs/2/members/1"))
+        ).await;
+        assert_eq!(resp.status(), 409);
+    }
+}
@@ -0,0 +1,37 @@
+//! Defines the endpoints for organizations and their members.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v1/orgs`. Super admins create organizations, any user can
+//! list their organizations and switch their token to one of them, and the owner and admins of an
+//! organization add and remove its members.
+pub mod manage;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get, delete};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn organizations_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v1/orgs") // Namespace for organization routes.
+        .route("", post().to(
+            manage::create_organization::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/orgs.
+        )
+        .route("", get().to(
+            manage::list_organizations::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/orgs.
+        )
+        .route("{org_id}/switch", post().to(
+            manage::switch_organization::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/orgs/{org_id}/switch.
+        )
+        .route("{org_id}/members", get().to(
+            manage::list_members::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/orgs/{org_id}/members.
+        )
+        .route("{org_id}/members", post().to(
+            manage::add_member::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/orgs/{org_id}/members.
+        )
+        .route("{org_id}/members/{user_id}", delete().to(
+            manage::remove_member::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // DELETE /api/auth/v1/orgs/{org_id}/members/{user_id}.
+        )
+    );
+}
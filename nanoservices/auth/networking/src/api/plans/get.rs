@@ -0,0 +1,74 @@
+//! Networking layer for reading the organization's plan
+use dal::plans::tx_definitions::GetOrgPlan;
+use auth_core::api::plans::get_plan::get_plan as get_plan_core;
+use actix_web::HttpResponse;
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetOrgPlan])]
+pub async fn get_plan() {
+    let plan = get_plan_core::<X>().await?;
+    Ok(HttpResponse::Ok().json(plan))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, read_body_json, TestRequest},
+        web, App,
+    };
+    use dal_tx_impl::impl_transaction;
+    use kernel::plans::{OrgPlan, Plan};
+    use kernel::users::UserRole;
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use utils::config::GetConfigVariable;
+    use utils::errors::NanoServiceError;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockDbHandle;
+
+    test_support::confirmed_users!(MockDbHandle);
+
+    #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(OrgPlan {
+            plan: Plan::Pro,
+            max_users: Some(80),
+            max_todos: None,
+            mfa: None,
+            api_access: None,
+            date_updated: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_plan() {
+        let app = init_service(App::new().route(
+            "/plan",
+            web::get().to(get_plan::<MockDbHandle, MockConfig, PassAuthSessionCheckMock>)
+        )).await;
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new("some-agent".to_string(), 1, UserRole::Admin);
+        let req = TestRequest::get()
+            .uri("/plan")
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["plan"], "pro");
+        assert_eq!(body["entitlements"]["max_users"], 80);
+        assert_eq!(body["entitlements"]["max_todos"], 10_000);
+    }
+}
@@ -0,0 +1,25 @@
+//! Defines the endpoints for the organization's plan.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v1/plan`. Admins can read the plan and its entitlements,
+//! changing it is limited to super admins.
+pub mod get;
+pub mod update;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn plans_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v1/plan") // Namespace for plan routes.
+        .route("", get().to(
+            get::get_plan::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/plan.
+        )
+        .route("update", post().to(
+            update::update_plan::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/plan/update.
+        )
+    );
+}
@@ -0,0 +1,82 @@
+//! Networking layer for changing the organization's plan
+use dal::plans::tx_definitions::UpdateOrgPlan;
+use kernel::plans::NewOrgPlan;
+use auth_core::api::plans::update_plan::update_plan as update_plan_core;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[UpdateOrgPlan])]
+pub async fn update_plan(body: Json<NewOrgPlan>) {
+    let plan = update_plan_core::<X>(body.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(plan))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::UserRole;
+    use kernel::plans::OrgPlan;
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::send_test_request;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, UpdateOrgPlan, update_org_plan)]
+    async fn update_org_plan(plan: NewOrgPlan) -> Result<OrgPlan, NanoServiceError> {
+        Ok(OrgPlan {
+            plan: plan.plan,
+            max_users: plan.max_users,
+            max_todos: plan.max_todos,
+            mfa: plan.mfa,
+            api_access: plan.api_access,
+            date_updated: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_update_plan() {
+        send_test_request!(
+            POST,
+            "/plan",
+            serde_json::json!({"plan": "free", "max_users": 10}),
+            SuperAdminRoleCheck,
+            UserRole::SuperAdmin,
+            1,
+            update_plan,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body["entitlements"]["max_users"], 10);
+        assert_eq!(body["entitlements"]["mfa"], false);
+    }
+
+    #[tokio::test]
+    async fn test_update_plan_as_admin() {
+        send_test_request!(
+            POST,
+            "/plan",
+            serde_json::json!({"plan": "enterprise"}),
+            SuperAdminRoleCheck,
+            UserRole::Admin,
+            1,
+            update_plan,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -29,6 +29,8 @@ mod tests {
     async fn test_assign_role_pass() {
         struct MockPostgres;
 
+        test_support::confirmed_users!(MockPostgres);
+
         #[impl_transaction(MockPostgres, CreateRolePermission, create_role_permission)]
         async fn create_role_permission(_role_permission: NewRolePermission) -> Result<RolePermission, NanoServiceError> {
             Ok(RolePermission {
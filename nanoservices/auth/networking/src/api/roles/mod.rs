@@ -3,22 +3,22 @@ pub mod remove_role;
 pub mod update_roles;
 
 use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
-use utils::config::EnvConfig;
+use utils::secrets::SecretsConfig;
 use actix_web::web::{ServiceConfig, scope, post};
-use kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
 
 
 pub fn roles_factory(app: &mut ServiceConfig) {
     app.service(
         scope("/api/auth/v1/roles") // Namespace for user-related API routes.
         .route("assign_role", post().to(
-            assign_role::assign_role::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/roles/assign_role.
+            assign_role::assign_role::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/roles/assign_role.
         )
         .route("remove_role", post().to(
-            remove_role::remove_role::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/roles/remove_role.
+            remove_role::remove_role::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/roles/remove_role.
         )
         .route("update", post().to(
-            update_roles::update_roles::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/roles/update.
+            update_roles::update_roles::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/roles/update.
         )
     );
 }
\ No newline at end of file
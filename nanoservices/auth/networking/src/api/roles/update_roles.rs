@@ -22,7 +22,7 @@ pub struct UpdateBody {
 #[api_endpoint(token=SuperAdminRoleCheck, db_traits=[UpdateRolePermissions])]
 pub async fn update_roles(body: Json<UpdateBody>) {
     let body = body.into_inner();
-    let _ = update_role_permissions_core::<X>(body.user_id, body.roles).await?;
+    update_role_permissions_core::<X>(body.user_id, body.roles).await?;
     Ok(HttpResponse::Ok().finish())  
 }
 
@@ -51,6 +51,8 @@ mod tests {
     #[tokio::test]
     async fn test_update_roles_pass() {
         struct MockPostgres;
+
+        test_support::confirmed_users!(MockPostgres);
         struct MockConfig;
 
         #[impl_transaction(MockPostgres, UpdateRolePermissions, update_role_permissions)]
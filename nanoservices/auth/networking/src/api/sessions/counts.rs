@@ -0,0 +1,64 @@
+//! Networking layer for counting the active sessions per user
+use kernel::token::session_cache::traits::CountAuthCacheSessions;
+use actix_web::HttpResponse;
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, cache_traits=[CountAuthCacheSessions])]
+pub async fn session_counts() {
+    let counts = Z::count_auth_cache_sessions().await?;
+    Ok(HttpResponse::Ok().json(counts))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, read_body_json, TestRequest},
+        http::header,
+        web, App
+    };
+    use kernel::users::UserRole;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::session_cache::structs::UserSessionCount;
+    use utils::config::GetConfigVariable;
+    use utils::errors::NanoServiceError;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_counts() {
+        let service = session_counts::<MockPostgres, MockConfig, PassAuthSessionCheckMock>;
+        let app = init_service(App::new().route("/counts", web::get().to(service))).await;
+
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, SuperAdminRoleCheck> = HeaderToken::new(
+            agent.clone(),
+            1,
+            UserRole::SuperAdmin,
+        );
+        let req = TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((header::USER_AGENT, agent))
+            .uri("/counts")
+            .to_request();
+
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: Vec<UserSessionCount> = read_body_json(resp).await;
+        assert_eq!(body, vec![UserSessionCount { user_id: 1, sessions: 2 }]);
+    }
+}
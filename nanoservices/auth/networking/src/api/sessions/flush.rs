@@ -0,0 +1,45 @@
+//! Networking layer for flushing every session, e.g. after rotating the secret key
+use kernel::token::session_cache::traits::FlushAuthCacheSessions;
+use kernel::token::session_cache::structs::RemovedSessions;
+use actix_web::HttpResponse;
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, cache_traits=[FlushAuthCacheSessions])]
+pub async fn flush_sessions() {
+    let removed = Z::flush_auth_cache_sessions().await?;
+    Ok(HttpResponse::Ok().json(RemovedSessions { removed }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::UserRole;
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use utils::send_test_request;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[tokio::test]
+    async fn test_flush_sessions() {
+        send_test_request!(
+            POST,
+            "/flush",
+            serde_json::json!({}),
+            SuperAdminRoleCheck,
+            UserRole::SuperAdmin,
+            1,
+            flush_sessions,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+        let body: RemovedSessions = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body.removed, 3);
+    }
+}
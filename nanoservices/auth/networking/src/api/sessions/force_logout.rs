@@ -0,0 +1,60 @@
+//! Networking layer for forcing a user out of all of their sessions
+use kernel::token::session_cache::traits::DelUserAuthCacheSessions;
+use kernel::token::session_cache::structs::RemovedSessions;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+use serde::Deserialize;
+use utils::api_endpoint;
+
+
+/// Schema for forcing a logout
+/// 
+/// # Fields
+/// * `user_id` - The ID of the user whose sessions are removed.
+#[derive(Deserialize)]
+pub struct ForceLogoutSchema {
+    pub user_id: i32
+}
+
+#[api_endpoint(token=SuperAdminRoleCheck, cache_traits=[DelUserAuthCacheSessions])]
+pub async fn force_logout(body: Json<ForceLogoutSchema>) {
+    let removed = Z::del_user_auth_cache_sessions(body.user_id).await?;
+    Ok(HttpResponse::Ok().json(RemovedSessions { removed }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::UserRole;
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use utils::send_test_request;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[tokio::test]
+    async fn test_force_logout() {
+        send_test_request!(
+            POST,
+            "/force_logout",
+            serde_json::json!({
+                "user_id": 2
+            }),
+            SuperAdminRoleCheck,
+            UserRole::SuperAdmin,
+            1,
+            force_logout,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+        let body: RemovedSessions = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body.removed, 1);
+    }
+}
@@ -0,0 +1,30 @@
+//! Defines the super admin endpoints for inspecting and clearing the session cache.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v1/sessions`. Forcing a logout or flushing the cache only
+//! removes sessions, so users holding an affected token get a 401 on their next request and have
+//! to log in again.
+pub mod counts;
+pub mod force_logout;
+pub mod flush;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn sessions_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v1/sessions") // Namespace for session cache admin routes.
+        .route("counts", get().to(
+            counts::session_counts::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/sessions/counts.
+        )
+        .route("force_logout", post().to(
+            force_logout::force_logout::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/sessions/force_logout.
+        )
+        .route("flush", post().to(
+            flush::flush_sessions::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/sessions/flush.
+        )
+    );
+}
@@ -0,0 +1,165 @@
+//! Networking layer for users uploading and removing their avatar.
+use actix_multipart::Multipart;
+use actix_web::HttpResponse;
+use auth_core::api::users::avatar::{upload_avatar as upload_avatar_core, delete_avatar as delete_avatar_core};
+use dal::users::tx_definitions::SetUserAvatar;
+use futures_util::StreamExt;
+use kernel::avatars::{avatar_max_bytes, avatar_too_large};
+use kernel::content_scan::ScanContent;
+use kernel::object_store::{DeleteObject, StoreObject};
+use utils::api_endpoint;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The multipart field the image is sent in.
+pub const AVATAR_FIELD: &str = "avatar";
+
+
+fn bad_upload(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::BadRequest)
+}
+
+
+/// Reads the `avatar` field of a multipart body, refusing it as soon as it is over
+/// `AVATAR_MAX_BYTES` so a large upload is never read whole.
+///
+/// # Returns
+/// - `Ok(Vec<u8>)`: The uploaded image.
+/// - `Err(NanoServiceError)`: `BadRequest` if the body is not multipart or has no `avatar` field,
+///   `PayloadTooLarge` if the image is too large.
+pub async fn read_avatar_upload<Y: GetConfigVariable>(mut payload: Multipart) -> Result<Vec<u8>, NanoServiceError> {
+    let max_bytes = avatar_max_bytes::<Y>();
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| bad_upload(format!("Failed to read the upload: {}", e)))?;
+        if field.name() != Some(AVATAR_FIELD) {
+            continue
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| bad_upload(format!("Failed to read the upload: {}", e)))?;
+            if body.len() + chunk.len() > max_bytes {
+                return Err(avatar_too_large(max_bytes))
+            }
+            body.extend_from_slice(&chunk);
+        }
+        return Ok(body)
+    }
+    Err(bad_upload(format!("The upload has no {} field", AVATAR_FIELD)))
+}
+
+
+/// Replaces the user's avatar with the `avatar` field of a `multipart/form-data` body. The image is
+/// stored with `O` once `S` has found it clean.
+#[api_endpoint(token=NoRoleCheck, db_traits=[SetUserAvatar])]
+pub async fn upload_avatar<O: StoreObject, S: ScanContent>(payload: Multipart) {
+    let upload = read_avatar_upload::<Y>(payload).await?;
+    let avatar = upload_avatar_core::<X, Y, O, S>(jwt.user_id, upload).await?;
+    Ok(HttpResponse::Ok().json(avatar))
+}
+
+
+/// Removes the user's avatar from the users row and from `O`.
+#[api_endpoint(token=NoRoleCheck, db_traits=[SetUserAvatar])]
+pub async fn delete_avatar<O: DeleteObject>() {
+    let avatar = delete_avatar_core::<X, Y, O>(jwt.user_id).await?;
+    Ok(HttpResponse::Ok().json(avatar))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::http::header::CONTENT_TYPE;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::content_scan::engine_mock::{InfectedScanMock, PassScanMock};
+    use kernel::object_store::engine_mock::{MemoryObjectStoreMock, STORED_OBJECTS};
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, PassAuthSessionCheckMock, TokenBuilder};
+
+    test_support::fake_config!(AvatarConfig, {
+        "AVATAR_MAX_BYTES" => "2048",
+    });
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, SetUserAvatar, set_user_avatar)]
+    async fn set_user_avatar(_id: i32, _avatar_version: Option<String>) -> Result<bool, NanoServiceError> {
+        Ok(true)
+    }
+
+    /// A 1x1 PNG.
+    const PIXEL: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+        0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8, 0xcf, 0xc0, 0xf0,
+        0x1f, 0x00, 0x05, 0x00, 0x01, 0xff, 0x89, 0x99, 0x3d, 0x1d, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45,
+        0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    /// A `multipart/form-data` body with one field, sent by user `user_id`.
+    fn multipart(user_id: i32, field: &str, body: &[u8]) -> TestRequest {
+        let mut payload = format!(
+            "--BOUNDARY\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"me.png\"\r\nContent-Type: image/png\r\n\r\n",
+            field
+        ).into_bytes();
+        payload.extend_from_slice(body);
+        payload.extend_from_slice(b"\r\n--BOUNDARY--\r\n");
+        TokenBuilder::<AvatarConfig, NoRoleCheck>::new()
+            .user_id(user_id)
+            .request(TestRequest::post().uri("/me/avatar"))
+            .insert_header((CONTENT_TYPE, "multipart/form-data; boundary=BOUNDARY"))
+            .set_payload(payload)
+    }
+
+    #[tokio::test]
+    async fn test_upload_avatar() {
+        let resp = call_endpoint(
+            Method::POST, "/me/avatar",
+            upload_avatar::<MockPostgres, AvatarConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock, PassScanMock>,
+            multipart(21, "avatar", PIXEL)
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["avatar_url"].as_str().unwrap().starts_with("/avatars/21?v="));
+        assert!(STORED_OBJECTS.lock().unwrap().contains_key("avatars/21/64.png"));
+    }
+
+    #[tokio::test]
+    async fn test_upload_refusals() {
+        let upload = |request: TestRequest| call_endpoint(
+            Method::POST, "/me/avatar",
+            upload_avatar::<MockPostgres, AvatarConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock, PassScanMock>,
+            request
+        );
+        assert_eq!(upload(multipart(22, "avatar", &[0; 2049])).await.status(), 413);
+        assert_eq!(upload(multipart(22, "avatar", b"not an image")).await.status(), 400);
+        assert_eq!(upload(multipart(22, "file", PIXEL)).await.status(), 400);
+
+        let infected = call_endpoint(
+            Method::POST, "/me/avatar",
+            upload_avatar::<MockPostgres, AvatarConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock, InfectedScanMock>,
+            multipart(22, "avatar", PIXEL)
+        ).await;
+        assert_eq!(infected.status(), 422);
+        assert!(!STORED_OBJECTS.lock().unwrap().contains_key("avatars/22/64.png"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_avatar() {
+        STORED_OBJECTS.lock().unwrap().insert("avatars/23/64.png".to_string(), PIXEL.to_vec());
+        let resp = call_endpoint(
+            Method::DELETE, "/me/avatar",
+            delete_avatar::<MockPostgres, AvatarConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock>,
+            TokenBuilder::<AvatarConfig, NoRoleCheck>::new().user_id(23).request(TestRequest::delete().uri("/me/avatar"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["avatar_url"].is_null());
+        assert!(!STORED_OBJECTS.lock().unwrap().contains_key("avatars/23/64.png"));
+    }
+}
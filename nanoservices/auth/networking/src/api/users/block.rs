@@ -7,6 +7,7 @@ use actix_web::{
 };
 use serde::Deserialize;
 use utils::api_endpoint;
+use kernel::events::engine_configured::ConfiguredEventBus;
 
 
 /// Schema for blocking a user
@@ -20,7 +21,7 @@ pub struct BlockSchema {
 
 #[api_endpoint(token=SuperAdminRoleCheck, db_traits=[BlockUser])]
 pub async fn block_user(body: Json<BlockSchema>) {
-    let _ = block_user_core::<X>(body.user_id).await?;
+    block_user_core::<X, ConfiguredEventBus, Y>(body.user_id, jwt.user_id).await?;
     Ok(HttpResponse::Ok().finish())
 }
 
@@ -54,6 +55,8 @@ mod tests {
     #[tokio::test]
     async fn test_pass() {
         struct MockDbHandle;
+
+        test_support::confirmed_users!(MockDbHandle);
         struct MockConfig;
 
         #[impl_transaction(MockDbHandle, BlockUser, block_user)]
@@ -18,9 +18,9 @@ pub struct ConfirmUserSchema {
     pub unique_id: String
 }
 
-#[api_endpoint(db_traits=[ConfirmUser])]
+#[api_endpoint(db_traits=[ConfirmUser], confirmed_user=false)]
 pub async fn confirm_user(body: Json<ConfirmUserSchema>) {
-    let _ = confirm_user_core::<X>(&body.unique_id).await?;
+    confirm_user_core::<X>(&body.unique_id).await?;
     Ok(HttpResponse::Ok().finish())
 }
 
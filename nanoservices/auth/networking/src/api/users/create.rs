@@ -11,7 +11,7 @@
 //!
 //! # Notes
 //! - The function is generic and allows different database implementations to be injected.
-//! - Additional actions, such as sending an email, can be performed after the user is created.
+//! - The user's confirmation email is queued in the outbox together with the user.
 //!
 //! # Arguments
 //! - `body`: A JSON representation of `NewUserSchema` containing the user's details.
@@ -21,17 +21,18 @@
 //! - `Err(NanoServiceError)`: A 500 Internal Server Error response if the operation fails.
 //!
 //! # Notes
-//! - After delegating to the core `create_user` function, additional actions (e.g., sending an email) can be performed.
 //! - This function uses generics to allow the injection of different implementations of the `CreateUser` trait.
-use dal::users::tx_definitions::CreateUser;
+use dal::users::tx_definitions::{CreateUserWithConfirmationEmail, CreateUserWithRolePermission, CountUsers};
+use dal::plans::tx_definitions::GetOrgPlan;
 use dal::rate_limit_entries::tx_definitions::{
     CreateRateLimitEntry,
     UpdateRateLimitEntry,
     GetRateLimitEntry,
 };
-use email_core::mailchimp_traits::mc_definitions::SendTemplate;
-use dal::role_permissions::tx_definitions::CreateRolePermission;
 use kernel::users::NewUserSchema;
+use kernel::analytics::engine_configured::ConfiguredAnalyticsSink;
+use kernel::events::engine_configured::ConfiguredEventBus;
+use utils::validation::validate_body;
 use auth_core::api::users::create::create_user as create_user_core;
 use actix_web::{
     HttpResponse,
@@ -43,17 +44,17 @@ use utils::api_endpoint;
 /// This is our networking method for creating a user
 ///
 /// # Notes
-/// - We call our core method with the traits in the order <X, W, Y> because the core method takes the db traits struct, then the 
-///   email traits struct, then lastly the env variable trait struct. 
-/// - The way our `api_endpoint` macro defines the traits is W for the email traits, X for the db traits and Y for the env variable
-///   trait.
+/// - We call our core method with the traits in the order <X, Y> because the core method takes the db traits struct, then the
+///   env variable trait struct. The confirmation email is queued in the database with the user, so no email traits are needed.
+/// - The way our `api_endpoint` macro defines the traits is X for the db traits and Y for the env variable trait.
 #[api_endpoint(
     token=SuperAdminRoleCheck, 
-    db_traits=[CreateUser, CreateRolePermission, CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry], 
-    email_traits=[SendTemplate])
+    db_traits=[CreateUserWithConfirmationEmail, CreateUserWithRolePermission, CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry,
+        GetOrgPlan, CountUsers])
 ]
 pub async fn create_user(body: Json<NewUserSchema>) {
-    let _ = create_user_core::<X, W, Y>(body.into_inner()).await?;
+    validate_body(&*body)?;
+    let _ = create_user_core::<X, Y, ConfiguredAnalyticsSink, ConfiguredEventBus>(body.into_inner()).await?;
     Ok(HttpResponse::Created().finish())
 }
 
@@ -76,8 +77,8 @@ mod tests {
     use actix_http::Request;
     use kernel::users::{User, NewUser};
     use kernel::rate_limit_entries::{RateLimitEntry, NewRateLimitEntry};
+    use kernel::plans::{OrgPlan, Plan};
     use dal_tx_impl::impl_transaction;
-    use kernel::role_permissions::{RolePermission, NewRolePermission};
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::LazyLock;
     use kernel::users::UserRole;
@@ -86,7 +87,7 @@ mod tests {
     use kernel::token::token::HeaderToken;
     use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
     use utils::config::GetConfigVariable;
-    use email_core::mailchimp_helpers::mailchimp_template::Template;
+    use kernel::email_outbox::NewOutboxEmail;
     use kernel::token::checks::SuperAdminRoleCheck;
     use chrono::{Utc, Duration};
 
@@ -105,34 +106,46 @@ mod tests {
             date_created: now,
             last_logged_in: now,
             blocked: user.blocked,
+            avatar_version: None,
+        }
+    }
+
+    fn org_plan() -> OrgPlan {
+        OrgPlan {
+            plan: Plan::Enterprise,
+            max_users: None,
+            max_todos: None,
+            mfa: None,
+            api_access: None,
+            date_updated: chrono::Utc::now().naive_utc(),
         }
     }
 
     #[tokio::test]
     async fn test_pass() {
 
-        static SEND_TEMPLATE_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
+        static EMAIL_QUEUED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
         static CREATE_USER_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
         static CREATE_ROLE_PERMISSION_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
        
         struct MockDbHandle;
-        struct MockMailchimpHandle;
+
+        test_support::confirmed_users!(MockDbHandle);
         struct MockConfig;
         
-        #[impl_transaction(MockDbHandle, CreateUser, create_user)]
-        async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
+        #[impl_transaction(MockDbHandle, CreateUserWithRolePermission, create_user_with_role_permission)]
+        async fn create_user_with_role_permission(user: NewUser) -> Result<User, NanoServiceError> {
             CREATE_USER_CALLED.store(true, Ordering::Relaxed);
+            CREATE_ROLE_PERMISSION_CALLED.store(true, Ordering::Relaxed);
             Ok(generate_user(user))
         }
 
-        #[impl_transaction(MockDbHandle, CreateRolePermission, create_role_permission)]
-        async fn create_role_permission(role_permission: NewRolePermission) -> Result<RolePermission, NanoServiceError> {
+        #[impl_transaction(MockDbHandle, CreateUserWithConfirmationEmail, create_user_with_confirmation_email)]
+        async fn create_user_with_confirmation_email(user: NewUser, _email: NewOutboxEmail) -> Result<User, NanoServiceError> {
+            EMAIL_QUEUED.store(true, Ordering::Relaxed);
+            CREATE_USER_CALLED.store(true, Ordering::Relaxed);
             CREATE_ROLE_PERMISSION_CALLED.store(true, Ordering::Relaxed);
-            Ok(RolePermission{
-                id: 1,
-                user_id: role_permission.user_id,
-                role: role_permission.role.clone()
-            })
+            Ok(generate_user(user))
         }
 
         #[impl_transaction(MockDbHandle, CreateRateLimitEntry, create_rate_limit_entry)]
@@ -164,10 +177,14 @@ mod tests {
             Ok(true)
         }
 
-        #[impl_transaction(MockMailchimpHandle, SendTemplate, send_template)]
-        async fn send_template(_template: &Template) -> Result<bool, NanoServiceError> {
-            SEND_TEMPLATE_CALLED.store(true, Ordering::Relaxed);
-            Ok(true)
+        #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+        async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+            Ok(org_plan())
+        }
+
+        #[impl_transaction(MockDbHandle, CountUsers, count_users)]
+        async fn count_users() -> Result<i64, NanoServiceError> {
+            Ok(1)
         }
 
         impl GetConfigVariable for MockConfig {
@@ -181,7 +198,7 @@ mod tests {
         }
 
         async fn run_request(req: Request) -> ServiceResponse {
-            let service = create_user::<MockMailchimpHandle, MockDbHandle, MockConfig, PassAuthSessionCheckMock>;
+            let service = create_user::<MockDbHandle, MockConfig, PassAuthSessionCheckMock>;
             let app = init_service(App::new().route("/create", web::post().to(service))).await;
             call_service(&app, req).await
         }
@@ -217,7 +234,7 @@ mod tests {
         let _body_str = std::str::from_utf8(&raw_body).unwrap();
 
         assert!(CREATE_USER_CALLED.load(Ordering::Relaxed));
-        assert!(SEND_TEMPLATE_CALLED.load(Ordering::Relaxed));
+        assert!(EMAIL_QUEUED.load(Ordering::Relaxed));
         assert!(CREATE_ROLE_PERMISSION_CALLED.load(Ordering::Relaxed));
 
         assert_eq!(status, 201);
@@ -226,28 +243,28 @@ mod tests {
     #[tokio::test]
     async fn test_bad_json() {
 
-        static SEND_TEMPLATE_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
+        static EMAIL_QUEUED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
         static CREATE_USER_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
         static CREATE_ROLE_PERMISSION_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
        
         struct MockDbHandle;
-        struct MockMailchimpHandle;
+
+        test_support::confirmed_users!(MockDbHandle);
         struct MockConfig;
         
-        #[impl_transaction(MockDbHandle, CreateUser, create_user)]
-        async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
+        #[impl_transaction(MockDbHandle, CreateUserWithRolePermission, create_user_with_role_permission)]
+        async fn create_user_with_role_permission(user: NewUser) -> Result<User, NanoServiceError> {
             CREATE_USER_CALLED.store(true, Ordering::Relaxed);
+            CREATE_ROLE_PERMISSION_CALLED.store(true, Ordering::Relaxed);
             Ok(generate_user(user))
         }
 
-        #[impl_transaction(MockDbHandle, CreateRolePermission, create_role_permission)]
-        async fn create_role_permission(role_permission: NewRolePermission) -> Result<RolePermission, NanoServiceError> {
+        #[impl_transaction(MockDbHandle, CreateUserWithConfirmationEmail, create_user_with_confirmation_email)]
+        async fn create_user_with_confirmation_email(user: NewUser, _email: NewOutboxEmail) -> Result<User, NanoServiceError> {
+            EMAIL_QUEUED.store(true, Ordering::Relaxed);
+            CREATE_USER_CALLED.store(true, Ordering::Relaxed);
             CREATE_ROLE_PERMISSION_CALLED.store(true, Ordering::Relaxed);
-            Ok(RolePermission{
-                id: 1,
-                user_id: role_permission.user_id,
-                role: role_permission.role.clone()
-            })
+            Ok(generate_user(user))
         }
 
         #[impl_transaction(MockDbHandle, CreateRateLimitEntry, create_rate_limit_entry)]
@@ -279,10 +296,14 @@ mod tests {
             Ok(true)
         }
 
-        #[impl_transaction(MockMailchimpHandle, SendTemplate, send_template)]
-        async fn send_template(_template: &Template) -> Result<bool, NanoServiceError> {
-            SEND_TEMPLATE_CALLED.store(true, Ordering::Relaxed);
-            Ok(true)
+        #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+        async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+            Ok(org_plan())
+        }
+
+        #[impl_transaction(MockDbHandle, CountUsers, count_users)]
+        async fn count_users() -> Result<i64, NanoServiceError> {
+            Ok(1)
         }
 
         impl GetConfigVariable for MockConfig {
@@ -295,7 +316,7 @@ mod tests {
             }
         }
         async fn run_request(req: Request) -> ServiceResponse {
-            let service = create_user::<MockMailchimpHandle, MockDbHandle, MockConfig, PassAuthSessionCheckMock>;
+            let service = create_user::<MockDbHandle, MockConfig, PassAuthSessionCheckMock>;
             let app = init_service(App::new().route("/create", web::post().to(service))).await;
             call_service(&app, req).await
         }
@@ -331,11 +352,89 @@ mod tests {
         let _body_str = std::str::from_utf8(&raw_body).unwrap();
 
 
-        assert!(!SEND_TEMPLATE_CALLED.load(Ordering::Relaxed));
+        assert!(!EMAIL_QUEUED.load(Ordering::Relaxed));
         assert!(!CREATE_USER_CALLED.load(Ordering::Relaxed));
         assert!(!CREATE_ROLE_PERMISSION_CALLED.load(Ordering::Relaxed));
 
         assert_eq!(status, 400);
     }
 
+
+    #[tokio::test]
+    async fn test_invalid_fields() {
+        struct MockDbHandle;
+
+        test_support::confirmed_users!(MockDbHandle);
+        struct MockConfig;
+
+        #[impl_transaction(MockDbHandle, CreateUserWithRolePermission, create_user_with_role_permission)]
+        async fn create_user_with_role_permission(_user: NewUser) -> Result<User, NanoServiceError> {
+            panic!("an invalid user should not be created")
+        }
+
+        #[impl_transaction(MockDbHandle, CreateUserWithConfirmationEmail, create_user_with_confirmation_email)]
+        async fn create_user_with_confirmation_email(_user: NewUser, _email: NewOutboxEmail) -> Result<User, NanoServiceError> {
+            panic!("an invalid user should not be created")
+        }
+
+        #[impl_transaction(MockDbHandle, CreateRateLimitEntry, create_rate_limit_entry)]
+        async fn create_rate_limit_entry(_new_entry: NewRateLimitEntry) -> Result<RateLimitEntry, NanoServiceError> {
+            panic!("no email should be sent")
+        }
+
+        #[impl_transaction(MockDbHandle, GetRateLimitEntry, get_rate_limit_entry)]
+        async fn get_rate_limit_entry(_email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
+            panic!("no email should be sent")
+        }
+
+        #[impl_transaction(MockDbHandle, UpdateRateLimitEntry, update_rate_limit_entry)]
+        async fn update_rate_limit_entry(_updated_entry: RateLimitEntry) -> Result<bool, NanoServiceError> {
+            panic!("no email should be sent")
+        }
+
+        #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+        async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+            Ok(org_plan())
+        }
+
+        #[impl_transaction(MockDbHandle, CountUsers, count_users)]
+        async fn count_users() -> Result<i64, NanoServiceError> {
+            Ok(1)
+        }
+
+        impl GetConfigVariable for MockConfig {
+            fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+                Ok("".to_string())
+            }
+        }
+
+        let body = json!({
+            "email": "not-an-email",
+            "username": "a",
+            "first_name": "zak",
+            "last_name": "",
+            "user_role": "AdMiN",
+        });
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, SuperAdminRoleCheck> = HeaderToken::new(agent.clone(), 1, UserRole::SuperAdmin);
+        let req = TestRequest::post()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((header::USER_AGENT, agent))
+            .uri("/create")
+            .set_json(&body)
+            .to_request();
+        let service = create_user::<MockDbHandle, MockConfig, PassAuthSessionCheckMock>;
+        let app = init_service(App::new().route("/create", web::post().to(service))).await;
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 400);
+
+        let raw_body = resp.into_body().try_into_bytes().unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&raw_body).unwrap();
+        assert_eq!(body["message"], "Request body failed validation");
+        assert_eq!(body["details"], json!({
+            "email": ["must be a valid email address"],
+            "last_name": ["must be between 1 and 64 characters", "must not be blank"],
+            "username": ["must be between 3 and 32 characters"]
+        }));
+    }
 }
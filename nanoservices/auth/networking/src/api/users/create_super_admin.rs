@@ -10,26 +10,42 @@
 //! # Notes
 //! - After delegating to the core `create_user` function, additional actions (e.g., sending an email) can be performed.
 //! - This function uses generics to allow the injection of different implementations of the `CreateUser` trait.
-use dal::users::tx_definitions::CreateUser;
+use dal::users::tx_definitions::{CreateUserWithRolePermission, GetRecipientProfile};
 use dal::rate_limit_entries::tx_definitions::{
     CreateRateLimitEntry,
     UpdateRateLimitEntry,
     GetRateLimitEntry,
 };
 use email_core::mailchimp_traits::mc_definitions::SendTemplate;
-use dal::role_permissions::tx_definitions::CreateRolePermission;
 use auth_core::api::users::create_super_admin::create_super_user as create_super_user_core;
+use auth_core::password_policy::breach::HaveIBeenPwnedDescriptor;
 use actix_web::{web::Json, HttpResponse};
 use serde::Deserialize;
-use kernel::users::UserRole;
+use validator::Validate;
+use kernel::users::{UserRole, validate_username, validate_name};
 use utils::api_endpoint;
+use utils::validation::validate_body;
 
 
-#[derive(Deserialize)]
+/// Follows the same rules as `NewUserSchema`.
+#[derive(Deserialize, Validate)]
 pub struct SuperAdminSchema {
+    #[validate(email(message = "must be a valid email address"))]
     pub email: String,
+    #[validate(
+        length(min = 3, max = 32, message = "must be between 3 and 32 characters"),
+        custom(function = "validate_username")
+    )]
     pub username: String,
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_name")
+    )]
     pub first_name: String,
+    #[validate(
+        length(min = 1, max = 64, message = "must be between 1 and 64 characters"),
+        custom(function = "validate_name")
+    )]
     pub last_name: String,
     pub user_role: UserRole,
     pub password: String
@@ -43,17 +59,19 @@ pub struct SuperAdminSchema {
 ///   email traits struct, then lastly the env variable trait struct. 
 /// - The way our `api_endpoint` macro defines the traits is W for the email traits, X for the db traits and Y for the env variable
 ///   trait.
-#[api_endpoint(db_traits=[CreateUser, CreateRolePermission, CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry], email_traits=[SendTemplate], env_variable_trait=true)]
+/// - Breached passwords are looked up with `HaveIBeenPwnedDescriptor` when `PASSWORD_BREACH_CHECK` is set.
+#[api_endpoint(db_traits=[CreateUserWithRolePermission, CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry, GetRecipientProfile], email_traits=[SendTemplate], env_variable_trait=true)]
 pub async fn create_super_user(body: Json<SuperAdminSchema>) {
+    validate_body(&*body)?;
     let body = body.into_inner();
-    let _ = create_super_user_core::<X, W, Y>(
+    let _ = create_super_user_core::<X, W, Y, HaveIBeenPwnedDescriptor>(
         body.username,
         body.email,
         body.first_name,
         body.last_name,
         body.password
     ).await?;
-    Ok(HttpResponse::Created().json({}))
+    Ok(HttpResponse::Created().json(()))
 }
 
 
@@ -65,13 +83,13 @@ mod tests {
     //! using a mock database implementation.
 
     use super::*;
+    use kernel::users::RecipientProfile;
     use actix_web::{
         dev::ServiceResponse,
         self, body::MessageBody, http::header::ContentType, test::{
             call_service, init_service, TestRequest
         }, web, App
     };
-    use kernel::role_permissions::{RolePermission, NewRolePermission};
     use kernel::rate_limit_entries::{RateLimitEntry, NewRateLimitEntry};
     use actix_http::Request;
     use kernel::users::{User, NewUser};
@@ -84,8 +102,8 @@ mod tests {
 
     struct MockDbHandle;
 
-    #[impl_transaction(MockDbHandle, CreateUser, create_user)]
-    async fn create_user(user: NewUser) -> Result<User, NanoServiceError> {
+    #[impl_transaction(MockDbHandle, CreateUserWithRolePermission, create_user_with_role_permission)]
+    async fn create_user_with_role_permission(user: NewUser) -> Result<User, NanoServiceError> {
         Ok(User {
             id: 1, // Mock ID
             confirmed: false,
@@ -99,15 +117,7 @@ mod tests {
             last_logged_in: Utc::now().naive_utc(),
             blocked: user.blocked,
             uuid: user.uuid.clone(),
-        })
-    }
-
-    #[impl_transaction(MockDbHandle, CreateRolePermission, create_role_permission)]
-    async fn create_role_permission(role_permission: NewRolePermission) -> Result<RolePermission, NanoServiceError> {
-        Ok(RolePermission {
-            id: 1, // Mock ID
-            user_id: role_permission.user_id,
-            role: role_permission.role.clone(),
+            avatar_version: None,
         })
     }
 
@@ -123,6 +133,11 @@ mod tests {
         })
     }
 
+    #[impl_transaction(MockDbHandle, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(_email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        Ok(None)
+    }
+
     #[impl_transaction(MockDbHandle, GetRateLimitEntry, get_rate_limit_entry)]
     async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
         Ok(Some(RateLimitEntry {
@@ -174,7 +189,7 @@ mod tests {
             "first_name": "zak",
             "last_name": "siddiq",
             "user_role": "SuPeR AdMiN",
-            "password": "securepassword"
+            "password": "Secure-password-1"
         });
         let req = TestRequest::post()
             .insert_header(ContentType::json())
@@ -199,7 +214,7 @@ mod tests {
             "first_name": "zak",
             "last_name": "siddiq",
             "user_role": "SuPeR AdMiN",
-            "password": "securepassword"
+            "password": "Secure-password-1"
         });
         let req = TestRequest::post()
             .insert_header(ContentType::json())
@@ -0,0 +1,106 @@
+//! Networking layer for super admins exporting every user's profile as CSV.
+use actix_web::HttpRequest;
+use auth_core::api::users::export::export_user_profiles as export_user_profiles_core;
+use dal::users::tx_definitions::StreamUserProfiles;
+use utils::api_endpoint;
+use utils::export_stream::{export_response, ExportEncoding, ExportFormat};
+
+
+/// The `StreamUserProfiles` handles that can be held by a streamed response, which outlives the handler.
+pub trait StreamUserProfileRows: StreamUserProfiles + 'static {}
+
+impl<T: StreamUserProfiles + 'static> StreamUserProfileRows for T {}
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[StreamUserProfileRows])]
+pub async fn export_user_profiles(req: HttpRequest) {
+    let encoding = ExportEncoding::negotiate(req.headers());
+    let body = export_user_profiles_core::<X>(encoding);
+    Ok(export_response(ExportFormat::Csv, encoding, "users", body))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::http::header::{ACCEPT_ENCODING, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_TYPE};
+    use actix_web::test::{self, TestRequest};
+    use flate2::read::GzDecoder;
+    use futures_util::stream;
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::users::{ExportedUserProfile, UserRole};
+    use std::io::Read;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::export_stream::{CsvRow, RowStream};
+
+    /// Serves 1500 users, every third one an admin as well as a worker.
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    impl StreamUserProfiles for MockPostgres {
+        fn stream_user_profiles() -> RowStream<ExportedUserProfile> {
+            Box::pin(stream::iter((1..=1500).map(|id| {
+                let user = factories::user(id);
+                let roles = match id % 3 {
+                    0 => vec!["Admin".to_string(), "Worker".to_string()],
+                    _ => vec!["Worker".to_string()],
+                };
+                Ok(ExportedUserProfile {
+                    id,
+                    username: user.username,
+                    email: user.email,
+                    first_name: user.first_name,
+                    last_name: user.last_name,
+                    user_role: user.user_role,
+                    roles,
+                    confirmed: user.confirmed,
+                    blocked: user.blocked,
+                    date_created: user.date_created,
+                    last_logged_in: user.last_logged_in,
+                })
+            })))
+        }
+    }
+
+    async fn export(role: UserRole, accept_encoding: &str) -> actix_web::dev::ServiceResponse {
+        let req = TokenBuilder::<FakeConfig, SuperAdminRoleCheck>::new()
+            .role(role)
+            .request(TestRequest::get().uri("/export.csv"))
+            .insert_header((ACCEPT_ENCODING, accept_encoding));
+        call_endpoint(Method::GET, "/export.csv", export_user_profiles::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>, req).await
+    }
+
+    #[tokio::test]
+    async fn test_export_csv() {
+        let resp = export(UserRole::SuperAdmin, "identity").await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "text/csv");
+        assert_eq!(resp.headers().get(CONTENT_DISPOSITION).unwrap(), "attachment; filename=\"users.csv\"");
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+
+        let body = test::read_body(resp).await;
+        let csv = std::str::from_utf8(&body).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1501);
+        assert_eq!(lines[0], ExportedUserProfile::CSV_HEADER);
+        assert!(lines[3].starts_with("3,"));
+        assert!(lines[3].contains(",Admin;Worker,"));
+    }
+
+    #[tokio::test]
+    async fn test_export_gzipped_csv() {
+        let resp = export(UserRole::SuperAdmin, "gzip").await;
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        let body = test::read_body(resp).await;
+        let mut csv = String::new();
+        GzDecoder::new(body.as_ref()).read_to_string(&mut csv).unwrap();
+        assert_eq!(csv.lines().count(), 1501);
+    }
+
+    #[tokio::test]
+    async fn test_export_as_admin() {
+        assert_eq!(export(UserRole::Admin, "identity").await.status(), 401);
+    }
+}
@@ -9,6 +9,9 @@
 //! # Features
 //! - Uses generics to inject database operations via DAL traits.
 //! - Converts errors to proper HTTP responses using `NanoServiceError`.
+//! - Takes `?fields=` to only return the picked `TrimmedUser` fields of the user.
+//! - `get_by_jwt` adds `impersonation` when the token is a super admin's impersonation token, so
+//!   the frontend can show a banner while it is used.
 //!
 //! # Routes
 //! - `GET /api/auth/v1/users/{id}`
@@ -16,7 +19,9 @@
 //! - `GET /api/auth/v1/users/by-uuid/{uuid}`
 
 use actix_web::{web, HttpResponse};
-use kernel::users::{TrimmedUser, UserRole};
+use kernel::users::{TrimmedUser, UserRole, USER_FIELDS};
+use kernel::fields::{FieldSelection, FieldsQuery};
+use kernel::impersonation::Impersonation;
 use auth_core::api::users::get::{get_user, get_user_by_email, get_user_by_uuid};
 use dal::users::tx_definitions::{GetUser, GetUserByEmail, GetUserByUuid};
 use dal::role_permissions::tx_definitions::GetRolePermissions;
@@ -32,46 +37,54 @@ use utils::api_endpoint;
 /// # Fields
 /// - `user`: The user details.
 /// - `roles`: The roles assigned to the user.
+/// - `impersonation`: Who is acting as the user, only for `get_by_jwt` with an impersonation token.
 #[derive(Serialize, Deserialize)]
 struct UserProfile {
     pub user: TrimmedUser,
     pub roles: Vec<UserRole>, 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonation: Option<Impersonation>,
 }
 
 /// gets the roles for the user and returns the profile as a HTTP response.
 macro_rules! return_profile {
-    ($id:expr, $user:ident) => {{
+    ($id:expr, $user:ident, $fields:ident) => {
+        return_profile!($id, $user, $fields, None)
+    };
+    ($id:expr, $user:ident, $fields:ident, $impersonation:expr) => {{
+        let selection = FieldSelection::parse(&$fields, &USER_FIELDS)?;
         let roles = X::get_role_permissions($id).await?;
         let roles: Vec<UserRole> = roles.into_iter().map(|role| role.role).collect();
-        Ok(HttpResponse::Ok().json(UserProfile { user: $user, roles }))
+        let profile = UserProfile { user: $user, roles, impersonation: $impersonation };
+        Ok(HttpResponse::Ok().json(selection.select(&profile, &["user"])?))
     }};
 }
 
 #[api_endpoint(token=SuperAdminRoleCheck, db_traits=[GetUser, GetRolePermissions])]
-pub async fn get_user_by_id(path: web::Path<i32>) {
+pub async fn get_user_by_id(path: web::Path<i32>, fields: web::Query<FieldsQuery>) {
     let id = path.into_inner();
     let user: TrimmedUser = get_user::<X>(id).await?.into();
-    return_profile!(id, user)
+    return_profile!(id, user, fields)
 }
 
 #[api_endpoint(token=SuperAdminRoleCheck, db_traits=[GetUserByEmail, GetRolePermissions])]
-pub async fn get_user_by_email_route(path: web::Path<String>) {
+pub async fn get_user_by_email_route(path: web::Path<String>, fields: web::Query<FieldsQuery>) {
     let email = path.into_inner();
     let user: TrimmedUser = get_user_by_email::<X>(email).await?.into();
-    return_profile!(user.id, user)
+    return_profile!(user.id, user, fields)
 }
 
 #[api_endpoint(db_traits=[GetUserByUuid, GetRolePermissions])]
-pub async fn get_user_by_uuid_route(path: web::Path<String>) {
+pub async fn get_user_by_uuid_route(path: web::Path<String>, fields: web::Query<FieldsQuery>) {
     let uuid = path.into_inner();
     let user: TrimmedUser = get_user_by_uuid::<X>(uuid).await?.into();
-    return_profile!(user.id, user)
+    return_profile!(user.id, user, fields)
 }
 
 #[api_endpoint(token=NoRoleCheck, db_traits=[GetUser, GetRolePermissions])]
-pub async fn get_by_jwt() {
+pub async fn get_by_jwt(fields: web::Query<FieldsQuery>) {
     let user: TrimmedUser = X::get_user(jwt.user_id).await?.into();
-    return_profile!(user.id, user)
+    return_profile!(user.id, user, fields, Impersonation::from_token(&jwt))
 }
 
 
@@ -110,13 +123,13 @@ mod tests {
     fn generate_new_user(email: String, uuid: String) -> NewUser {
         NewUser {
             username: "test".to_string(),
-            email: email,
+            email,
             confirmed: true,
             password: "password".to_string(),
             first_name: "Test".to_string(),
             last_name: "User".to_string(),
             user_role: UserRole::Admin,
-            uuid: uuid,
+            uuid,
             blocked: false,
             last_logged_in: chrono::Utc::now().naive_utc(),
             date_created: chrono::Utc::now().naive_utc(),
@@ -127,8 +140,8 @@ mod tests {
     fn generate_user(user: NewUser, id: i32) -> User {
         let now = chrono::Utc::now().naive_utc();
         User {
-            id: id,
-            confirmed: false,
+            id,
+            confirmed: true,
             username: user.username.clone(),
             email: user.email.clone(),
             first_name: user.first_name.clone(),
@@ -139,6 +152,7 @@ mod tests {
             date_created: now,
             last_logged_in: now,
             blocked: user.blocked,
+            avatar_version: None,
         }
     }
 
@@ -151,12 +165,12 @@ mod tests {
                 Ok(vec![
                     RolePermission {
                         id: 1,
-                        user_id: user_id,
+                        user_id,
                         role: UserRole::Admin,
                     },
                     RolePermission {
                         id: 2,
-                        user_id: user_id,
+                        user_id,
                         role: UserRole::SuperAdmin,
                     }
                 ])
@@ -215,8 +229,8 @@ mod tests {
         assert_eq!(trimmed_user.user.id, 1);
         assert_eq!(trimmed_user.roles.len(), 2);
         assert_eq!(status, 200);
-        assert_eq!(GET_USER_BY_ID.load(Ordering::Relaxed), true);
-        assert_eq!(GET_USER_PERMISSIONS.load(Ordering::Relaxed), true);
+        assert!(GET_USER_BY_ID.load(Ordering::Relaxed));
+        assert!(GET_USER_PERMISSIONS.load(Ordering::Relaxed));
     }
 
     #[tokio::test]
@@ -227,6 +241,8 @@ mod tests {
 
         struct MockDbHandle;
 
+        test_support::confirmed_users!(MockDbHandle);
+
         #[impl_transaction(MockDbHandle, GetUserByEmail, get_user_by_email)]
         async fn get_user_by_email(email: String) -> Result<User, NanoServiceError> {
             GET_USER_BY_EMAIL.store(true, Ordering::Relaxed);
@@ -270,8 +286,8 @@ mod tests {
         assert_eq!(trimmed_user.roles.len(), 2);
         assert_eq!(trimmed_user.user.email, "test@gmail.com".to_string());
         assert_eq!(status, 200);
-        assert_eq!(GET_USER_BY_EMAIL.load(Ordering::Relaxed), true);
-        assert_eq!(GET_USER_PERMISSIONS.load(Ordering::Relaxed), true);
+        assert!(GET_USER_BY_EMAIL.load(Ordering::Relaxed));
+        assert!(GET_USER_PERMISSIONS.load(Ordering::Relaxed));
 
     }
 
@@ -316,8 +332,8 @@ mod tests {
         assert_eq!(trimmed_user.user.uuid, "test-uuid".to_string());
         assert_eq!(trimmed_user.roles.len(), 2);
         assert_eq!(status, 200);
-        assert_eq!(GET_USER_BY_UUID.load(Ordering::Relaxed), true);
-        assert_eq!(GET_USER_PERMISSIONS.load(Ordering::Relaxed), true);
+        assert!(GET_USER_BY_UUID.load(Ordering::Relaxed));
+        assert!(GET_USER_PERMISSIONS.load(Ordering::Relaxed));
     }
 
     #[tokio::test]
@@ -357,7 +373,7 @@ mod tests {
         let req = TestRequest::get()
             .uri("/")
             .insert_header(("token", jwt.encode().unwrap()))
-            .insert_header((header::USER_AGENT, agent))
+            .insert_header((header::USER_AGENT, agent.clone()))
             .to_request();
 
         let resp = run_request(req).await;
@@ -370,9 +386,52 @@ mod tests {
         assert_eq!(trimmed_user.user.id, 20);
         assert_eq!(trimmed_user.user.uuid, "test-uuid".to_string());
         assert_eq!(trimmed_user.roles.len(), 2);
+        assert!(trimmed_user.impersonation.is_none());
         assert_eq!(status, 200);
-        assert_eq!(GET_USER_BY_ID.load(Ordering::Relaxed), true);
-        assert_eq!(GET_USER_PERMISSIONS.load(Ordering::Relaxed), true);
+        assert!(GET_USER_BY_ID.load(Ordering::Relaxed));
+        assert!(GET_USER_PERMISSIONS.load(Ordering::Relaxed));
+
+        // a super admin impersonating the user is reported for the banner
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::impersonate(
+            agent.clone(), 20, UserRole::Worker, 1, kernel::chrono::Duration::minutes(15)
+        );
+        let expires_at = jwt.time_expire;
+        let req = TestRequest::get()
+            .uri("/")
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((header::USER_AGENT, agent))
+            .to_request();
+        let body: serde_json::Value = actix_web::test::read_body_json(run_request(req).await).await;
+        let impersonation: Impersonation = serde_json::from_value(body["impersonation"].clone()).unwrap();
+        assert_eq!(impersonation, Impersonation { impersonator_id: 1, expires_at });
     }
 
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn test_get_user_by_uuid_route_fields() {
+
+        static GET_USER_PERMISSIONS: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
+
+        struct MockDbHandle;
+
+        #[impl_transaction(MockDbHandle, GetUserByUuid, get_user_by_uuid)]
+        async fn get_user_by_uuid(uuid: String) -> Result<User, NanoServiceError> {
+            Ok(generate_user(generate_new_user("test@gmail.com".to_string(), uuid), 3))
+        }
+        impl_roles!(3);
+
+        let app = init_service(App::new().route(
+            "/by-uuid/{uuid}", web::get().to(get_user_by_uuid_route::<MockDbHandle>)
+        )).await;
+
+        let req = TestRequest::get().uri("/by-uuid/test-uuid?fields=id,email").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status().as_u16(), 200);
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        assert_eq!(body["user"], serde_json::json!({"id": 3, "email": "test@gmail.com"}));
+        assert_eq!(body["roles"].as_array().unwrap().len(), 2);
+
+        let req = TestRequest::get().uri("/by-uuid/test-uuid?fields=password").to_request();
+        assert_eq!(call_service(&app, req).await.status().as_u16(), 400);
+    }
+
+}
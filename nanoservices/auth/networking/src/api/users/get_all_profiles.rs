@@ -1,14 +1,17 @@
 //! Endpoint that gets all the user profiles.
-use actix_web::HttpResponse;
+use actix_web::{HttpResponse, web::Query};
 use auth_core::api::users::get_all_profiles::get_all_user_profiles as get_all_user_profiles_core;
 use dal::users::tx_definitions::GetAllUserProfiles;
+use kernel::fields::{FieldSelection, FieldsQuery};
+use kernel::users::USER_FIELDS;
 use utils::api_endpoint;
 
 
 #[api_endpoint(token=SuperAdminRoleCheck, db_traits=[GetAllUserProfiles])]
-pub async fn get_all_user_profiles() {
+pub async fn get_all_user_profiles(fields: Query<FieldsQuery>) {
+    let selection = FieldSelection::parse(&fields, &USER_FIELDS)?;
     let user_profiles = get_all_user_profiles_core::<X>().await?;
-    Ok(HttpResponse::Ok().json(user_profiles))
+    Ok(HttpResponse::Ok().json(selection.select(&user_profiles, &["user"])?))
 }
 
 
@@ -47,13 +50,13 @@ mod tests {
     fn generate_new_user(email: String, uuid: String) -> NewUser {
         NewUser {
             username: "test".to_string(),
-            email: email,
+            email,
             confirmed: true,
             password: "password".to_string(),
             first_name: "Test".to_string(),
             last_name: "User".to_string(),
             user_role: UserRole::Admin,
-            uuid: uuid,
+            uuid,
             blocked: false,
             last_logged_in: chrono::Utc::now().naive_utc(),
             date_created: chrono::Utc::now().naive_utc(),
@@ -64,7 +67,7 @@ mod tests {
     fn generate_user(user: NewUser, id: i32) -> User {
         let now = chrono::Utc::now().naive_utc();
         User {
-            id: id,
+            id,
             confirmed: false,
             username: user.username.clone(),
             email: user.email.clone(),
@@ -76,6 +79,7 @@ mod tests {
             date_created: now,
             last_logged_in: now,
             blocked: user.blocked,
+            avatar_version: None,
         }
     }
 
@@ -83,6 +87,8 @@ mod tests {
     async fn test_get_all_user_profiles_success() {
         struct MockDbHandle;
 
+        test_support::confirmed_users!(MockDbHandle);
+
         #[impl_transaction(MockDbHandle, GetAllUserProfiles, get_all_user_profiles)]
         async fn get_all_user_profiles() -> Result<Vec<UserProfile>, NanoServiceError> {
             Ok(vec![
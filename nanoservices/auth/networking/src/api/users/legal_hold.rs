@@ -0,0 +1,119 @@
+//! Endpoints for super admins to place, release and list legal holds on users.
+use actix_web::{HttpResponse, web::Json};
+use auth_core::api::users::legal_hold::{
+    place_legal_hold as place_legal_hold_core,
+    release_legal_hold as release_legal_hold_core,
+    get_legal_holds as get_legal_holds_core,
+};
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use dal::legal_holds::tx_definitions::{PlaceLegalHold, ReleaseLegalHold, GetLegalHolds};
+use serde::{Deserialize, Serialize};
+use utils::api_endpoint;
+
+
+/// The user to hold and why.
+///
+/// # Fields
+/// * `user_id` - The ID of the user whose data is held.
+/// * `reason` - Why the data is held, such as a case reference.
+#[derive(Serialize, Deserialize)]
+pub struct PlaceLegalHoldBody {
+    pub user_id: i32,
+    pub reason: String,
+}
+
+
+/// The user whose hold is released.
+#[derive(Serialize, Deserialize)]
+pub struct ReleaseLegalHoldBody {
+    pub user_id: i32,
+}
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[PlaceLegalHold, CreateAuditEntry])]
+pub async fn place_legal_hold(body: Json<PlaceLegalHoldBody>) {
+    let body = body.into_inner();
+    let hold = place_legal_hold_core::<X>(body.user_id, jwt.user_id, body.reason).await?;
+    Ok(HttpResponse::Created().json(hold))
+}
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[ReleaseLegalHold, CreateAuditEntry])]
+pub async fn release_legal_hold(body: Json<ReleaseLegalHoldBody>) {
+    release_legal_hold_core::<X>(body.user_id, jwt.user_id).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[GetLegalHolds])]
+pub async fn get_legal_holds() {
+    let holds = get_legal_holds_core::<X>().await?;
+    Ok(HttpResponse::Ok().json(holds))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::audit_log::{AuditEntry, NewAuditEntry};
+    use kernel::legal_holds::{LegalHold, NewLegalHold};
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::users::UserRole;
+    use utils::errors::NanoServiceError;
+    use test_support::{call_endpoint, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, PlaceLegalHold, place_legal_hold)]
+    async fn place_legal_hold(hold: NewLegalHold) -> Result<Option<LegalHold>, NanoServiceError> {
+        assert_eq!(hold.placed_by, 1);
+        Ok(Some(LegalHold {
+            user_id: hold.user_id,
+            username: "ada".to_string(),
+            email: "ada@example.com".to_string(),
+            placed_by: hold.placed_by,
+            reason: hold.reason,
+            date_created: chrono::Utc::now().naive_utc(),
+        }))
+    }
+
+    #[impl_transaction(MockPostgres, CreateAuditEntry, create_audit_entry)]
+    async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: sqlx::types::Json(entry.details),
+            date_created: chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    async fn send(role: UserRole) -> actix_web::dev::ServiceResponse {
+        let req = TokenBuilder::<FakeConfig, SuperAdminRoleCheck>::new()
+            .role(role)
+            .request(TestRequest::post().uri("/legal-hold"))
+            .set_json(PlaceLegalHoldBody { user_id: 3, reason: "case 42".to_string() });
+        call_endpoint(Method::POST, "/legal-hold", place_legal_hold::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>, req).await
+    }
+
+    #[tokio::test]
+    async fn test_place_legal_hold() {
+        let resp = send(UserRole::SuperAdmin).await;
+        assert_eq!(resp.status(), 201);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["user_id"], 3);
+        assert_eq!(body["reason"], "case 42");
+    }
+
+    #[tokio::test]
+    async fn test_place_legal_hold_as_admin() {
+        let resp = send(UserRole::Admin).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -0,0 +1,156 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::http::header::{ETAG, IF_MATCH};
+use auth_core::api::users::update::update_own_profile;
+use utils::api_endpoint;
+use kernel::users::OwnProfileUpdate;
+use kernel::sync::{parse_if_match, version_tag};
+use dal::users::tx_definitions::UpdateUserProfile;
+use dal::sync::tx_definitions::GetSyncedUser;
+
+
+/// Lets any signed in user change their own username, first name and last name. Any other field,
+/// such as `user_role`, `blocked` or `confirmed`, gets a 403 listing the refused fields.
+///
+/// An `If-Match` header makes the update conditional in the same way as `update`. Blocked and
+/// unconfirmed users are refused even if they still hold a token.
+#[api_endpoint(
+    token=NoRoleCheck,
+    db_traits=[UpdateUserProfile, GetSyncedUser]
+)]
+pub async fn update_me(req: HttpRequest, body: web::Json<OwnProfileUpdate>) {
+    let if_match = parse_if_match(
+        req.headers().get(IF_MATCH).map(|value| value.to_str().unwrap_or_default())
+    )?;
+    let updated_user = update_own_profile::<X>(jwt.user_id, body.into_inner(), if_match).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, version_tag(&updated_user.updated_at)))
+        .json(updated_user))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::sync::SyncedUser;
+    use kernel::users::{PublicUser, User, UserRole, UserProfilePatch};
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::chrono::NaiveDateTime;
+    use dal_tx_impl::impl_transaction;
+    use dal::users::tx_definitions::GetUser;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+    use actix_web::{test, App};
+    use chrono::DateTime;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    fn user(id: i32) -> SyncedUser {
+        let version = DateTime::from_timestamp(1_735_722_000, 0).unwrap().naive_utc();
+        SyncedUser {
+            user: PublicUser {
+                id,
+                confirmed: true,
+                username: "worker".to_string(),
+                email: "worker@example.com".to_string(),
+                first_name: "Worker".to_string(),
+                last_name: "User".to_string(),
+                user_role: UserRole::Worker,
+                date_created: version,
+                last_logged_in: version,
+                blocked: false,
+                avatar_url: None,
+            },
+            updated_at: version,
+        }
+    }
+
+    #[impl_transaction(MockPostgres, UpdateUserProfile, update_user_profile)]
+    async fn update_user_profile(id: i32, patch: UserProfilePatch, _if_match: Option<NaiveDateTime>) -> Result<Option<SyncedUser>, NanoServiceError> {
+        assert_eq!(patch.email, None);
+        let mut updated = user(id);
+        updated.user.first_name = patch.first_name.unwrap_or(updated.user.first_name);
+        Ok(Some(updated))
+    }
+
+    #[impl_transaction(MockPostgres, GetSyncedUser, get_synced_user)]
+    async fn get_synced_user(id: i32) -> Result<SyncedUser, NanoServiceError> {
+        Ok(user(id))
+    }
+
+    // user 6 signed up but never confirmed their email
+    #[impl_transaction(MockPostgres, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        let synced = user(id).user;
+        Ok(User {
+            id,
+            confirmed: id != 6,
+            username: synced.username,
+            email: synced.email,
+            password: "hash".to_string(),
+            first_name: synced.first_name,
+            last_name: synced.last_name,
+            user_role: synced.user_role,
+            date_created: synced.date_created,
+            last_logged_in: synced.last_logged_in,
+            blocked: false,
+            uuid: "uuid".to_string(),
+            avatar_version: None,
+        })
+    }
+
+    async fn send(body: serde_json::Value) -> actix_web::dev::ServiceResponse {
+        send_as(5, body).await
+    }
+
+    async fn send_as(user_id: i32, body: serde_json::Value) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(App::new().route(
+            "/me",
+            web::post().to(update_me::<MockPostgres, MockConfig, PassAuthSessionCheckMock>)
+        )).await;
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::new(agent.clone(), user_id, UserRole::Worker);
+        let req = test::TestRequest::post()
+            .uri("/me")
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, agent))
+            .set_json(body)
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_update_me() {
+        let resp = send(serde_json::json!({"first_name": "Ada"})).await;
+        assert_eq!(resp.status(), 200);
+        assert!(resp.headers().get(ETAG).is_some());
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["id"], 5);
+        assert_eq!(body["first_name"], "Ada");
+    }
+
+    #[tokio::test]
+    async fn test_update_me_refuses_privileged_fields() {
+        let resp = send(serde_json::json!({"first_name": "Ada", "user_role": "SuperAdmin", "blocked": false})).await;
+        assert_eq!(resp.status(), 403);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["details"]["refused"], serde_json::json!(["blocked", "user_role"]));
+    }
+
+    #[tokio::test]
+    async fn test_update_me_refuses_unconfirmed_users() {
+        let resp = send_as(6, serde_json::json!({"first_name": "Ada"})).await;
+        assert_eq!(resp.status(), 403);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body, "User is not confirmed");
+    }
+}
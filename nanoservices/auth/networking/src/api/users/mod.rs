@@ -15,16 +15,26 @@ pub mod block;
 pub mod unblock;
 pub mod get;
 pub mod get_all_profiles;
+pub mod export;
 pub mod confirm_user;
 pub mod reset_password;
 pub mod update;
+pub mod me;
 pub mod delete;
+pub mod search;
+pub mod legal_hold;
+pub mod avatar;
 
 use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
-use actix_web::web::{ServiceConfig, scope, post, get};
-use utils::config::EnvConfig;
-use kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem;
-use email_core::mailchimp_traits::mc_definitions::MailchimpDescriptor;
+use actix_web::web::{ServiceConfig, scope, resource, post, get, delete as delete_route};
+use actix_web::middleware::from_fn;
+use utils::secrets::SecretsConfig;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+use kernel::object_store::engine_configured::ConfiguredObjectStore;
+use kernel::content_scan::engine_clamd::ClamdScanner;
+use kernel::captcha::engine_http::HttpCaptchaVerifier;
+use email_core::outbox::descriptor::EmailOutbox;
+use crate::rate_limit::{limit_auth_requests, CREATE_USER_RATE_LIMIT};
 
 /// Configures the API routes for user-related operations.
 ///
@@ -33,6 +43,9 @@ use email_core::mailchimp_traits::mc_definitions::MailchimpDescriptor;
 ///
 /// # Routes
 /// - `POST /api/auth/v1/users/create`: Creates a new user using the `create` module.
+/// - `GET /api/auth/v1/users/export.csv`: Streams every user's profile as CSV, for super admins.
+/// - `POST /api/auth/v1/users/me/avatar`: Replaces the user's avatar with a multipart upload.
+/// - `DELETE /api/auth/v1/users/me/avatar`: Removes the user's avatar.
 ///
 /// # Example
 /// ```rust
@@ -50,43 +63,70 @@ pub fn users_factory(app: &mut ServiceConfig) {
     app.service(
         scope("/api/auth/v1/users") // Namespace for user-related API routes.
         .route("create/superadmin", post().to(
-            create_super_admin::create_super_user::<MailchimpDescriptor, SqlxPostGresDescriptor, EnvConfig>) // POST /api/auth/v1/users/create.
+            create_super_admin::create_super_user::<EmailOutbox<SqlxPostGresDescriptor>, SqlxPostGresDescriptor, SecretsConfig>) // POST /api/auth/v1/users/create.
         )
         .route("update", post().to(
-            update::update::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/users/update.
+            update::update::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/update.
         )
-        .route("create", post().to(
-            create::create_user::<MailchimpDescriptor, SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/users/create.
+        .service(resource("create")
+            .wrap(from_fn(|req, next| limit_auth_requests::<SqlxPostGresDescriptor, SecretsConfig, HttpCaptchaVerifier, _>(CREATE_USER_RATE_LIMIT, req, next)))
+            .route(post().to(
+                create::create_user::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/create.
+            )
+        )
+        .route("me", post().to(
+            me::update_me::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/me.
+        )
+        .route("me/avatar", post().to(
+            avatar::upload_avatar::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>, ConfiguredObjectStore, ClamdScanner>) // POST /api/auth/v1/users/me/avatar.
+        )
+        .route("me/avatar", delete_route().to(
+            avatar::delete_avatar::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>, ConfiguredObjectStore>) // DELETE /api/auth/v1/users/me/avatar.
         )
         .route("delete", post().to(
-            delete::delete_user::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/users/delete.
+            delete::delete_user::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/delete.
         )
         .route("block", post().to(
-            block::block_user::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/users/block.
+            block::block_user::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/block.
         )
         .route("unblock", post().to(
-            unblock::unblock_user::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/auth/v1/users/unblock.
+            unblock::unblock_user::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/unblock.
         )
         .route("get-by-id/{id}", get().to(
-            get::get_user_by_id::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>)
+            get::get_user_by_id::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>)
         )
         .route("/get-by-email/{email}", get().to(
-            get::get_user_by_email_route::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>)
+            get::get_user_by_email_route::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>)
         )
         .route("/get-by-uuid/{uuid}", get().to(
             get::get_user_by_uuid_route::<SqlxPostGresDescriptor>)
         )
         .route("/get-by-jwt", get().to(
-            get::get_by_jwt::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>)
+            get::get_by_jwt::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>)
         )
         .route("/get-all", get().to(
-            get_all_profiles::get_all_user_profiles::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>)
+            get_all_profiles::get_all_user_profiles::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>)
+        )
+        .route("/export.csv", get().to(
+            export::export_user_profiles::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/users/export.csv.
+        )
+        .route("/search", get().to(
+            search::search_users::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/users/search?q={text}.
+        )
+        .route("/legal-hold", post().to(
+            legal_hold::place_legal_hold::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/legal-hold.
+        )
+        .route("/legal-hold/release", post().to(
+            legal_hold::release_legal_hold::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/auth/v1/users/legal-hold/release.
+        )
+        .route("/legal-holds", get().to(
+            legal_hold::get_legal_holds::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v1/users/legal-holds.
         )
         .route("/confirm", post().to(
             confirm_user::confirm_user::<SqlxPostGresDescriptor>)
         )
         .route("/reset-password", post().to(
-            reset_password::reset_password::<SqlxPostGresDescriptor>)
+            reset_password::reset_password::<SqlxPostGresDescriptor, SecretsConfig>)
         )
     );
 }
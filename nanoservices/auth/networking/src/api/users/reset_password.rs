@@ -1,6 +1,8 @@
 //! Networking layer for resetting a users password
 use dal::users::tx_definitions::ResetPassword;
+use dal::onboarding::tx_definitions::RecordPasswordSet;
 use auth_core::api::users::reset_password::reset_password as reset_password_core;
+use auth_core::password_policy::breach::HaveIBeenPwnedDescriptor;
 use actix_web::{
     HttpResponse,
     web::Json
@@ -20,9 +22,13 @@ pub struct ResetPasswordSchema {
     pub new_password: String,
 }
 
-#[api_endpoint(db_traits=[ResetPassword])]
+/// Resets the password once it has been checked against the password policy.
+///
+/// # Notes
+/// - Breached passwords are looked up with `HaveIBeenPwnedDescriptor` when `PASSWORD_BREACH_CHECK` is set.
+#[api_endpoint(db_traits=[ResetPassword, RecordPasswordSet], env_variable_trait=true)]
 pub async fn reset_password(body: Json<ResetPasswordSchema>) {
-    let _ = reset_password_core::<X>(&body.unique_id, &body.new_password).await?;
+    reset_password_core::<X, Y, HaveIBeenPwnedDescriptor>(&body.unique_id, &body.new_password).await?;
     Ok(HttpResponse::Ok().finish())
 }
 
@@ -44,34 +50,68 @@ mod tests {
     use actix_http::Request;
     use dal_tx_impl::impl_transaction;
     use serde_json::json;
+    use utils::config::GetConfigVariable;
     use utils::errors::NanoServiceError;
 
-    #[tokio::test]
-    async fn test_reset_password_success() {
-        // Define our mock database handle.
-        struct MockDbHandle;
-
-        // Provide a mock implementation for the `ResetPassword` transaction.
-        #[impl_transaction(MockDbHandle, ResetPassword, reset_password)]
-        async fn reset_password(uuid: String, _new_password: String) -> Result<bool, NanoServiceError> {
-            // Ensure that the `unique_id` received matches our expectation.
-            assert_eq!(uuid, "unique-123");
-            Ok(true)
-        }
+    // Define our mock database handle.
+    struct MockDbHandle;
+
+    // Provide a mock implementation for the `ResetPassword` transaction.
+    #[impl_transaction(MockDbHandle, ResetPassword, reset_password)]
+    async fn reset_password(uuid: String, _new_password: String) -> Result<bool, NanoServiceError> {
+        // Ensure that the `unique_id` received matches our expectation.
+        assert_eq!(uuid, "unique-123");
+        Ok(true)
+    }
+
+    #[impl_transaction(MockDbHandle, RecordPasswordSet, record_password_set)]
+    async fn record_password_set(uuid: String) -> Result<bool, NanoServiceError> {
+        assert_eq!(uuid, "unique-123");
+        Ok(true)
+    }
 
-        // Helper function to run our test request.
-        async fn run_request(req: Request) -> ServiceResponse {
-            // Instantiate the endpoint with our mock type.
-            let service = reset_password::<MockDbHandle>;
-            let app = init_service(App::new().route("/reset_password", web::post().to(service))).await;
-            call_service(&app, req).await
+    // Leaves the password policy at its defaults with the breach check off.
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("".to_string())
         }
+    }
 
-        // Build the JSON body expected by the endpoint.
+    // Helper function to run our test request.
+    async fn run_request(req: Request) -> ServiceResponse {
+        // Instantiate the endpoint with our mock types.
+        let service = reset_password::<MockDbHandle, FakeConfig>;
+        let app = init_service(App::new().route("/reset_password", web::post().to(service))).await;
+        call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_reset_password_weak_password() {
         let body = json!({
             "unique_id": "unique-123",
             "new_password": "new_password"
         });
+        let req = TestRequest::post()
+            .insert_header(ContentType::json())
+            .uri("/reset_password")
+            .set_json(&body)
+            .to_request();
+
+        let resp = run_request(req).await;
+        assert_eq!(resp.status(), 400);
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        assert!(body["details"]["password"].is_array());
+    }
+
+    #[tokio::test]
+    async fn test_reset_password_success() {
+        // Build the JSON body expected by the endpoint.
+        let body = json!({
+            "unique_id": "unique-123",
+            "new_password": "New-password-1"
+        });
 
         // Construct the test request.
         let req = TestRequest::post()
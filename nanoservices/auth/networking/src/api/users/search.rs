@@ -0,0 +1,79 @@
+//! Endpoint for admins to find users by part of their username, email or name.
+use actix_web::{HttpResponse, web::Query};
+use auth_core::api::users::search::search_users as search_users_core;
+use dal::users::tx_definitions::SearchUsers;
+use serde::Deserialize;
+use utils::api_endpoint;
+
+
+/// The search and the page to return.
+///
+/// # Fields
+/// * `q` - The text to find in the username, email or name.
+/// * `limit` - The most users to return.
+/// * `offset` - The `next_offset` of the previous page.
+#[derive(Deserialize)]
+pub struct UserSearchQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[SearchUsers])]
+pub async fn search_users(query: Query<UserSearchQuery>) {
+    let query = query.into_inner();
+    let page = search_users_core::<X>(jwt.org_id, query.q, query.limit, query.offset).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use kernel::users::{PublicUser, UserRole};
+    use kernel::token::checks::AdminRoleCheck;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, SearchUsers, search_users)]
+    async fn search_users(org_id: i32, query: String, limit: i64, offset: i64) -> Result<Vec<PublicUser>, NanoServiceError> {
+        assert_eq!(org_id, 2);
+        assert_eq!(query, "lovelace");
+        assert_eq!(limit, 11);
+        assert_eq!(offset, 0);
+        Ok(vec![PublicUser { username: "ada".to_string(), ..factories::public_user(3) }])
+    }
+
+    async fn send(role: UserRole) -> actix_web::dev::ServiceResponse {
+        let req = TokenBuilder::<FakeConfig, AdminRoleCheck>::new()
+            .role(role)
+            .org_id(2)
+            .request(TestRequest::get().uri("/search?q=lovelace&limit=10"));
+        call_endpoint(Method::GET, "/search", search_users::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>, req).await
+    }
+
+    #[tokio::test]
+    async fn test_search_users() {
+        let resp = send(UserRole::Admin).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["users"][0]["username"], "ada");
+        assert!(body["users"][0].get("password").is_none());
+        assert!(body["users"][0].get("uuid").is_none());
+        assert!(body["next_offset"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_search_users_as_worker() {
+        let resp = send(UserRole::Worker).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -21,7 +21,7 @@ pub struct UnblockSchema {
 
 #[api_endpoint(token=SuperAdminRoleCheck, db_traits=[UnblockUser])]
 pub async fn unblock_user(body: Json<UnblockSchema>) {
-    let _ = unblock_user_core::<X>(body.user_id).await?;
+    unblock_user_core::<X>(body.user_id).await?;
     Ok(HttpResponse::Ok().finish())
 }
 
@@ -57,6 +57,8 @@ mod tests {
     #[tokio::test]
     async fn test_pass() {
         struct MockDbHandle;
+
+        test_support::confirmed_users!(MockDbHandle);
         struct MockConfig;
 
         #[impl_transaction(MockDbHandle, UnblockUser, unblock_user)]
@@ -1,37 +1,141 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::http::header::{ETAG, IF_MATCH};
 use auth_core::api::users::update::update_user_fields;
 use utils::api_endpoint;
 use serde::{Serialize, Deserialize};
-use dal::users::tx_definitions::{
-    UpdateUserUsername,
-    UpdateUserEmail,
-    UpdateUserFirstName,
-    UpdateUserLasttName,
-    GetUser
-};
+use kernel::users::UserProfilePatch;
+use kernel::sync::{parse_if_match, version_tag};
+use dal::users::tx_definitions::UpdateUserProfile;
+use dal::sync::tx_definitions::GetSyncedUser;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct UpdateUserBody {
-    pub username: Option<String>,
-    pub email: Option<String>,
-    pub first_name: Option<String>,
-    pub last_name: Option<String>,
+    #[serde(flatten)]
+    pub patch: UserProfilePatch,
     pub id: i32
 }
 
 
+/// An `If-Match` header holding the user's `ETag` or `updated_at` makes the update conditional, if
+/// the user has changed since, a 409 is returned with the current user and the clashing fields.
 #[api_endpoint(
-    token=SuperAdminRoleCheck, 
-    db_traits=[UpdateUserUsername, UpdateUserEmail, UpdateUserFirstName, UpdateUserLasttName, GetUser]
+    token=SuperAdminRoleCheck,
+    db_traits=[UpdateUserProfile, GetSyncedUser]
 )]
-pub async fn update(body: web::Json<UpdateUserBody>)  {
+pub async fn update(req: HttpRequest, body: web::Json<UpdateUserBody>)  {
+    let if_match = parse_if_match(
+        req.headers().get(IF_MATCH).map(|value| value.to_str().unwrap_or_default())
+    )?;
     let body: UpdateUserBody = body.into_inner();
-    let updated_user = update_user_fields::<X>(
-        body.id, 
-        body.username, 
-        body.email, 
-        body.first_name, 
-        body.last_name
-    ).await?;
-    Ok(HttpResponse::Ok().json(updated_user))
+    let updated_user = update_user_fields::<X>(body.id, body.patch, if_match).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, version_tag(&updated_user.updated_at)))
+        .json(updated_user))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::sync::SyncedUser;
+    use kernel::users::{PublicUser, UserRole};
+    use kernel::token::checks::SuperAdminRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::chrono::NaiveDateTime;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+    use actix_web::{test, App};
+    use chrono::DateTime;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    fn user(id: i32) -> SyncedUser {
+        let version = DateTime::from_timestamp(1_735_722_000, 0).unwrap().naive_utc();
+        SyncedUser {
+            user: PublicUser {
+                id,
+                confirmed: true,
+                username: "worker".to_string(),
+                email: "worker@example.com".to_string(),
+                first_name: "Worker".to_string(),
+                last_name: "User".to_string(),
+                user_role: UserRole::Worker,
+                date_created: version,
+                last_logged_in: version,
+                blocked: false,
+                avatar_url: None,
+            },
+            updated_at: version,
+        }
+    }
+
+    #[impl_transaction(MockPostgres, UpdateUserProfile, update_user_profile)]
+    async fn update_user_profile(id: i32, patch: UserProfilePatch, if_match: Option<NaiveDateTime>) -> Result<Option<SyncedUser>, NanoServiceError> {
+        if if_match.is_some_and(|version| version != user(id).updated_at) {
+            return Ok(None)
+        }
+        let mut updated = user(id);
+        updated.user.username = patch.username.unwrap_or(updated.user.username);
+        updated.updated_at += chrono::Duration::seconds(1);
+        Ok(Some(updated))
+    }
+
+    #[impl_transaction(MockPostgres, GetSyncedUser, get_synced_user)]
+    async fn get_synced_user(id: i32) -> Result<SyncedUser, NanoServiceError> {
+        Ok(user(id))
+    }
+
+    async fn send(if_match: Option<&str>) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(App::new().route(
+            "/update",
+            web::post().to(update::<MockPostgres, MockConfig, PassAuthSessionCheckMock>)
+        )).await;
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, SuperAdminRoleCheck> = HeaderToken::new(agent.clone(), 1, UserRole::SuperAdmin);
+        let mut req = test::TestRequest::post()
+            .uri("/update")
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, agent))
+            .set_json(serde_json::json!({"id": 3, "username": "renamed"}));
+        if let Some(if_match) = if_match {
+            req = req.insert_header((IF_MATCH, if_match));
+        }
+        test::call_service(&app, req.to_request()).await
+    }
+
+    #[tokio::test]
+    async fn test_update() {
+        let resp = send(None).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(ETAG).unwrap(), "\"2025-01-01T09:00:01\"");
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["username"], "renamed");
+        assert!(body.get("password").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_stale_version() {
+        let resp = send(Some("\"2025-01-01T09:00:00\"")).await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = send(Some("\"2024-12-31T09:00:00\"")).await;
+        assert_eq!(resp.status(), 409);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["details"]["current"]["username"], "worker");
+        assert_eq!(body["details"]["conflicts"]["username"], serde_json::json!({
+            "requested": "renamed", "current": "worker"
+        }));
+    }
 }
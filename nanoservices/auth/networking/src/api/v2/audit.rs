@@ -0,0 +1,76 @@
+//! Networking layer for super admins paging through the audit log.
+use actix_web::{HttpResponse, web::Query};
+use auth_core::api::audit::list::list_audit_entries as list_audit_entries_core;
+use dal::audit_log::tx_definitions::ListAuditEntries;
+use kernel::pagination::PageQuery;
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[ListAuditEntries])]
+pub async fn list_audit_entries(page: Query<PageQuery>) {
+    let entries = list_audit_entries_core::<X>(&page).await?;
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App};
+    use dal_tx_impl::impl_transaction;
+    use kernel::audit_log::AuditEntry;
+    use kernel::users::UserRole;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::checks::{AdminRoleCheck, SuperAdminRoleCheck};
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, ListAuditEntries, list_audit_entries)]
+    async fn list_audit_entries(before_id: Option<i32>, limit: i64) -> Result<Vec<AuditEntry>, NanoServiceError> {
+        assert_eq!(before_id, None);
+        assert_eq!(limit, 21);
+        Ok(vec![])
+    }
+
+    #[tokio::test]
+    async fn test_list_audit_entries() {
+        let app = test::init_service(App::new().route(
+            "/audit",
+            web::get().to(list_audit_entries::<MockPostgres, MockConfig, PassAuthSessionCheckMock>)
+        )).await;
+        let agent = "some-agent".to_string();
+
+        // the admin of one organization cannot read the entries of the others
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new(agent.clone(), 2, UserRole::Admin);
+        let req = test::TestRequest::get()
+            .uri("/audit")
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, agent.clone()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+        let jwt: HeaderToken<MockConfig, SuperAdminRoleCheck> = HeaderToken::new(agent.clone(), 1, UserRole::SuperAdmin);
+        let req = test::TestRequest::get()
+            .uri("/audit")
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, agent))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body, serde_json::json!({"items": [], "next_cursor": null}));
+    }
+}
@@ -0,0 +1,29 @@
+//! Defines the v2 list endpoints of the auth service.
+//!
+//! # Overview
+//! These routes live under `/api/auth/v2`. Every list comes back in the `kernel::pagination::Page`
+//! envelope, `{items, next_cursor, total?}`, and takes `cursor` and `limit` in the query string.
+//! The v1 routes keep returning bare arrays for the clients built against them.
+pub mod users;
+pub mod audit;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, get};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn v2_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/auth/v2") // Namespace for paginated list routes.
+        .route("users", get().to(
+            users::get_users::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v2/users?cursor={cursor}&limit={limit}.
+        )
+        .route("users/search", get().to(
+            users::search_users::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v2/users/search?q={text}&cursor={cursor}&limit={limit}.
+        )
+        .route("audit", get().to(
+            audit::list_audit_entries::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/auth/v2/audit?cursor={cursor}&limit={limit}.
+        )
+    );
+}
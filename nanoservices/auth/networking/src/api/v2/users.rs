@@ -0,0 +1,152 @@
+//! Networking layer for paging through and searching the users.
+use actix_web::{HttpResponse, web::Query};
+use auth_core::api::users::get_all_profiles::get_user_profiles_page;
+use auth_core::api::users::search::search_users_page;
+use dal::users::tx_definitions::{GetAllUserProfiles, SearchUsers};
+use kernel::fields::{FieldSelection, FieldsQuery};
+use kernel::pagination::PageQuery;
+use kernel::users::{PUBLIC_USER_FIELDS, USER_FIELDS};
+use serde::Deserialize;
+use utils::api_endpoint;
+
+
+/// The text to search for, the page is read from the same query string as a `PageQuery`.
+#[derive(Deserialize)]
+pub struct UserSearchText {
+    pub q: String,
+}
+
+
+#[api_endpoint(token=SuperAdminRoleCheck, db_traits=[GetAllUserProfiles])]
+pub async fn get_users(page: Query<PageQuery>, fields: Query<FieldsQuery>) {
+    let selection = FieldSelection::parse(&fields, &USER_FIELDS)?;
+    let profiles = get_user_profiles_page::<X>(&page).await?;
+    Ok(HttpResponse::Ok().json(selection.select(&profiles, &["items", "user"])?))
+}
+
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[SearchUsers])]
+pub async fn search_users(search: Query<UserSearchText>, page: Query<PageQuery>, fields: Query<FieldsQuery>) {
+    let selection = FieldSelection::parse(&fields, &PUBLIC_USER_FIELDS)?;
+    let users = search_users_page::<X>(jwt.org_id, search.into_inner().q, &page).await?;
+    Ok(HttpResponse::Ok().json(selection.select(&users, &["items"])?))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App};
+    use dal_tx_impl::impl_transaction;
+    use kernel::users::{PublicUser, TrimmedUser, UserProfile, UserRole};
+    use kernel::token::token::HeaderToken;
+    use kernel::token::checks::{AdminRoleCheck, SuperAdminRoleCheck};
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    fn user(id: i32) -> TrimmedUser {
+        let now = chrono::Utc::now().naive_utc();
+        TrimmedUser {
+            id,
+            confirmed: true,
+            username: format!("ada{}", id),
+            email: format!("ada{}@example.com", id),
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            user_role: UserRole::Worker,
+            date_created: now,
+            last_logged_in: now,
+            blocked: false,
+            uuid: format!("uuid-{}", id),
+            avatar_url: None,
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetAllUserProfiles, get_all_user_profiles)]
+    async fn get_all_user_profiles() -> Result<Vec<UserProfile>, NanoServiceError> {
+        Ok((1..=3).map(|id| UserProfile { user: user(id), role_permissions: vec![] }).collect())
+    }
+
+    #[impl_transaction(MockPostgres, SearchUsers, search_users)]
+    async fn search_users(_org_id: i32, query: String, limit: i64, offset: i64) -> Result<Vec<PublicUser>, NanoServiceError> {
+        assert_eq!(query, "lovelace");
+        Ok((1..=3).skip(offset as usize).take(limit as usize).map(|id| user(id).into()).collect())
+    }
+
+    async fn get<R>(uri: &str, role: UserRole) -> actix_web::dev::ServiceResponse
+    where
+        R: kernel::token::checks::CheckUserRole
+    {
+        let app = test::init_service(App::new()
+            .route("/users", web::get().to(get_users::<MockPostgres, MockConfig, PassAuthSessionCheckMock>))
+            .route("/users/search", web::get().to(search_users::<MockPostgres, MockConfig, PassAuthSessionCheckMock>))
+        ).await;
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, R> = HeaderToken::new(agent.clone(), 1, role);
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, agent))
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_get_users() {
+        let resp = get::<SuperAdminRoleCheck>("/users?limit=2", UserRole::SuperAdmin).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 2);
+        assert_eq!(body["items"][0]["user"]["username"], "ada1");
+        assert_eq!(body["total"], 3);
+
+        let uri = format!("/users?limit=2&cursor={}", body["next_cursor"].as_str().unwrap());
+        let body: serde_json::Value = test::read_body_json(get::<SuperAdminRoleCheck>(&uri, UserRole::SuperAdmin).await).await;
+        assert_eq!(body["items"][0]["user"]["username"], "ada3");
+        assert!(body["next_cursor"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_get_users_fields() {
+        let resp = get::<SuperAdminRoleCheck>("/users?limit=1&fields=id,username", UserRole::SuperAdmin).await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["items"][0]["user"], serde_json::json!({"id": 1, "username": "ada1"}));
+        assert!(body["items"][0]["role_permissions"].is_array());
+
+        let resp = get::<AdminRoleCheck>("/users/search?q=lovelace&fields=email", UserRole::Admin).await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["items"][0], serde_json::json!({"email": "ada1@example.com"}));
+
+        let resp = get::<SuperAdminRoleCheck>("/users?fields=password", UserRole::SuperAdmin).await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = get::<AdminRoleCheck>("/users/search?q=lovelace&fields=uuid", UserRole::Admin).await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_search_users() {
+        let resp = get::<AdminRoleCheck>("/users/search?q=lovelace&limit=2", UserRole::Admin).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 2);
+        assert!(body["next_cursor"].is_string());
+        assert!(body.get("total").is_none());
+
+        let resp = get::<AdminRoleCheck>("/users/search?q=lovelace", UserRole::Worker).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod api;
 pub mod utils;
+pub mod rate_limit;
@@ -0,0 +1,363 @@
+//! Limits how often the login, password reset and user creation endpoints can be called.
+//!
+//! # Overview
+//! Each limited endpoint has a `RateLimitRule`. Every request is counted against the client IP and
+//! against the account it targets, in fixed windows held by a `HitRateLimit` store. Once either
+//! count passes its limit the request is answered with a 429 and a `Retry-After` header. The
+//! Postgres store shares the counts between servers, `InMemoryRateLimitStore` keeps them in the
+//! process for single server deployments and tests.
+//!
+//! Before an account reaches its limit, a rule can ask for a solved CAPTCHA once the account has
+//! been tried more than `challenge_after` times in the window, checked by a `VerifyHumanChallenge`
+//! verifier, see `kernel::captcha`.
+//!
+//! # Variables
+//! For each rule, where `NAME` is `LOGIN`, `PASSWORD_RESET` or `CREATE_USER`:
+//! * `AUTH_RATE_LIMIT_{NAME}_IP_MAX` - The requests allowed per client IP in a window, `0` disables the check
+//! * `AUTH_RATE_LIMIT_{NAME}_ACCOUNT_MAX` - The requests allowed per account in a window, `0` disables the check
+//! * `AUTH_RATE_LIMIT_{NAME}_WINDOW_SECONDS` - The length of a window
+//! * `AUTH_RATE_LIMIT_{NAME}_CHALLENGE_AFTER` - The requests allowed per account in a window before a
+//!   CAPTCHA has to be solved, `0` disables the challenge
+//!
+//! # Notes
+//! The IP is the socket peer address, so behind a load balancer the IP limits should be raised or
+//! disabled and enforced at the balancer instead.
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::RETRY_AFTER,
+    middleware::Next,
+    web::Bytes,
+    Error, HttpResponse, ResponseError
+};
+use dal::request_rate_limits::tx_definitions::HitRateLimit;
+use dal_tx_impl::impl_transaction;
+use kernel::chrono::{Duration, Utc};
+use kernel::request_rate_limits::RateLimitHit;
+use kernel::captcha::{VerifyHumanChallenge, CAPTCHA_HEADER, require_human};
+use utils::config::GetConfigVariable;
+use utils::log_limited;
+use utils::errors::NanoServiceError;
+use crate::utils::extract_basic_auth_credentials;
+
+
+/// Where the account a request targets is read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccountKey {
+    /// The email in the basic auth header.
+    BasicAuth,
+    /// The `email` field of the JSON body.
+    JsonEmail,
+}
+
+
+/// The limits for one endpoint.
+///
+/// # Fields
+/// * name - The name used in the config variables and the store keys.
+/// * ip_max - The requests allowed per client IP in a window, `0` disables the check.
+/// * account_max - The requests allowed per account in a window, `0` disables the check.
+/// * window_seconds - The length of a window.
+/// * account_key - Where the account is read from.
+/// * challenge_after - The requests allowed per account in a window before a CAPTCHA has to be
+///   solved, `0` disables the challenge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitRule {
+    pub name: &'static str,
+    pub ip_max: i32,
+    pub account_max: i32,
+    pub window_seconds: i64,
+    pub account_key: AccountKey,
+    pub challenge_after: i32,
+}
+
+/// The default limits for logging in, keyed on the basic auth email.
+pub const LOGIN_RATE_LIMIT: RateLimitRule = RateLimitRule {
+    name: "login",
+    ip_max: 20,
+    account_max: 5,
+    window_seconds: 15 * 60,
+    account_key: AccountKey::BasicAuth,
+    challenge_after: 0,
+};
+
+/// The default limits for requesting a password reset.
+pub const PASSWORD_RESET_RATE_LIMIT: RateLimitRule = RateLimitRule {
+    name: "password_reset",
+    ip_max: 10,
+    account_max: 3,
+    window_seconds: 60 * 60,
+    account_key: AccountKey::JsonEmail,
+    challenge_after: 0,
+};
+
+/// The default limits for creating users, keyed on the email of the user being created.
+pub const CREATE_USER_RATE_LIMIT: RateLimitRule = RateLimitRule {
+    name: "create_user",
+    ip_max: 30,
+    account_max: 3,
+    window_seconds: 60 * 60,
+    account_key: AccountKey::JsonEmail,
+    challenge_after: 0,
+};
+
+
+/// Reads a number from config, falling back to the default if it is not set or invalid.
+fn read_setting<Y: GetConfigVariable>(name: String, default: i64) -> i64 {
+    Y::get_config_variable(name)
+        .ok()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .filter(|value| *value >= 0)
+        .unwrap_or(default)
+}
+
+
+impl RateLimitRule {
+
+    /// Applies any limits set in config over the defaults.
+    pub fn from_config<Y: GetConfigVariable>(self) -> Self {
+        let prefix = format!("AUTH_RATE_LIMIT_{}", self.name.to_uppercase());
+        let window_seconds = read_setting::<Y>(format!("{}_WINDOW_SECONDS", prefix), self.window_seconds);
+        RateLimitRule {
+            ip_max: read_setting::<Y>(format!("{}_IP_MAX", prefix), self.ip_max as i64) as i32,
+            account_max: read_setting::<Y>(format!("{}_ACCOUNT_MAX", prefix), self.account_max as i64) as i32,
+            window_seconds: if window_seconds > 0 { window_seconds } else { self.window_seconds },
+            challenge_after: read_setting::<Y>(format!("{}_CHALLENGE_AFTER", prefix), self.challenge_after as i64) as i32,
+            ..self
+        }
+    }
+}
+
+
+/// Keeps the rate limit windows in the process rather than the database.
+pub struct InMemoryRateLimitStore;
+
+/// The open windows by key, along with the length of each window.
+static MEMORY_WINDOWS: LazyLock<Mutex<HashMap<String, (RateLimitHit, i64)>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[impl_transaction(InMemoryRateLimitStore, HitRateLimit, hit_rate_limit)]
+async fn hit_rate_limit(key: String, window_seconds: i64) -> Result<RateLimitHit, NanoServiceError> {
+    let now = Utc::now().naive_utc();
+    let mut windows = MEMORY_WINDOWS.lock().unwrap();
+    // drop the finished windows so keys that stop being used do not build up
+    windows.retain(|_, (hit, window)| hit.window_start > now - Duration::seconds(*window));
+    let (hit, _) = windows.entry(key).or_insert((RateLimitHit { count: 0, window_start: now }, window_seconds));
+    hit.count += 1;
+    Ok(hit.clone())
+}
+
+
+/// Reads the account a request targets, putting the body back for the handler once it is read.
+async fn account_for(account_key: AccountKey, req: &mut ServiceRequest) -> Option<String> {
+    let account = match account_key {
+        AccountKey::BasicAuth => extract_basic_auth_credentials(req.request()).ok().map(|(email, _)| email),
+        AccountKey::JsonEmail => {
+            let body = req.extract::<Bytes>().await.ok()?;
+            req.set_payload(Payload::from(body.clone()));
+            serde_json::from_slice::<serde_json::Value>(&body).ok()?
+                .get("email")?
+                .as_str()
+                .map(str::to_string)
+        }
+    };
+    account.map(|email| email.trim().to_lowercase()).filter(|email| !email.is_empty())
+}
+
+
+/// Rejects a request with a 429 if its client IP or account has used up the rule's limits, or with
+/// a 403 if its account is past the rule's `challenge_after` and it does not carry a solved CAPTCHA.
+///
+/// # Arguments
+/// * `rule` - The default limits, overridden by any set in config
+/// * `req` - The incoming request
+/// * `next` - The rest of the middleware chain
+///
+/// # Notes
+/// If the store cannot be reached the request is let through and the failure logged, so a store
+/// outage does not lock every user out. If the CAPTCHA provider cannot be reached the request is
+/// refused, as only the accounts already being tried too often are challenged.
+pub async fn limit_auth_requests<S, Y, C, B>(
+    rule: RateLimitRule,
+    mut req: ServiceRequest,
+    next: Next<B>
+) -> Result<ServiceResponse<EitherBody<B>>, Error>
+where
+    S: HitRateLimit,
+    Y: GetConfigVariable,
+    C: VerifyHumanChallenge,
+    B: MessageBody + 'static,
+{
+    let rule = rule.from_config::<Y>();
+    let mut keys = Vec::new();
+    if let (true, Some(addr)) = (rule.ip_max > 0, req.peer_addr()) {
+        keys.push((format!("{}:ip:{}", rule.name, addr.ip()), rule.ip_max, 0));
+    }
+    if rule.account_max > 0 {
+        if let Some(account) = account_for(rule.account_key, &mut req).await {
+            keys.push((format!("{}:account:{}", rule.name, account), rule.account_max, rule.challenge_after));
+        }
+    }
+    let mut challenge = false;
+    for (key, max, challenge_after) in keys {
+        match S::hit_rate_limit(key, rule.window_seconds).await {
+            Ok(hit) if hit.count > max => {
+                let reset_at = hit.window_start + Duration::seconds(rule.window_seconds);
+                let retry_after = (reset_at - Utc::now().naive_utc()).num_seconds().max(1);
+                let response = HttpResponse::TooManyRequests()
+                    .insert_header((RETRY_AFTER, retry_after.to_string()))
+                    .json("Too many requests, try again later");
+                return Ok(req.into_response(response).map_into_right_body())
+            },
+            Ok(hit) => challenge |= challenge_after > 0 && hit.count > challenge_after,
+            Err(e) => log_limited!("auth", "{} rate limit check skipped: {}", rule.name, e.message)
+        }
+    }
+    if challenge {
+        let token = req.headers().get(CAPTCHA_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let remote_ip = req.peer_addr().map(|addr| addr.ip().to_string());
+        if let Err(e) = require_human::<C, Y>(token, remote_ip).await {
+            return Ok(req.into_response(e.error_response()).map_into_right_body())
+        }
+    }
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App, middleware::from_fn, http::StatusCode};
+    use base64::{Engine as _, engine::general_purpose};
+    use kernel::captcha::engine_mock::SolvedTokenMock;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "AUTH_RATE_LIMIT_LOGIN_ACCOUNT_MAX" => Ok("2".to_string()),
+                "AUTH_RATE_LIMIT_LOGIN_WINDOW_SECONDS" => Ok("0".to_string()),
+                _ => Ok("".to_string())
+            }
+        }
+    }
+
+    #[test]
+    fn test_rule_from_config() {
+        let rule = LOGIN_RATE_LIMIT.from_config::<FakeConfig>();
+        assert_eq!(rule.account_max, 2);
+        assert_eq!(rule.ip_max, LOGIN_RATE_LIMIT.ip_max);
+        // a zero window would never reset so the default is kept
+        assert_eq!(rule.window_seconds, LOGIN_RATE_LIMIT.window_seconds);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_counts_per_key() {
+        let first = InMemoryRateLimitStore::hit_rate_limit("test:memory:a".to_string(), 60).await.unwrap();
+        let second = InMemoryRateLimitStore::hit_rate_limit("test:memory:a".to_string(), 60).await.unwrap();
+        let other = InMemoryRateLimitStore::hit_rate_limit("test:memory:b".to_string(), 60).await.unwrap();
+        assert_eq!((first.count, second.count, other.count), (1, 2, 1));
+        assert_eq!(first.window_start, second.window_start);
+    }
+
+    fn basic_auth(email: &str) -> String {
+        format!("Basic {}", general_purpose::STANDARD.encode(format!("{}:password", email)))
+    }
+
+    #[actix_web::test]
+    async fn test_limit_by_account() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(|req, next| limit_auth_requests::<InMemoryRateLimitStore, FakeConfig, SolvedTokenMock, _>(LOGIN_RATE_LIMIT, req, next)))
+                .route("/login", web::post().to(HttpResponse::Ok))
+        ).await;
+
+        for _ in 0..2 {
+            let req = actix_test::TestRequest::post().uri("/login")
+                .insert_header(("Authorization", basic_auth("Limited@example.com"))).to_request();
+            assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::OK);
+        }
+        // the account is matched whatever the case of the email
+        let req = actix_test::TestRequest::post().uri("/login")
+            .insert_header(("Authorization", basic_auth("limited@example.com"))).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(resp.headers().get(RETRY_AFTER).is_some());
+
+        let req = actix_test::TestRequest::post().uri("/login")
+            .insert_header(("Authorization", basic_auth("other@example.com"))).to_request();
+        assert_eq!(actix_test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_limit_by_ip_and_json_email() {
+        const RULE: RateLimitRule = RateLimitRule {
+            name: "test_json",
+            ip_max: 3,
+            account_max: 1,
+            window_seconds: 60,
+            account_key: AccountKey::JsonEmail,
+            challenge_after: 0,
+        };
+        async fn echo_email(body: web::Json<serde_json::Value>) -> HttpResponse {
+            HttpResponse::Ok().json(body["email"].clone())
+        }
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(|req, next| limit_auth_requests::<InMemoryRateLimitStore, FakeConfig, SolvedTokenMock, _>(RULE, req, next)))
+                .route("/reset", web::post().to(echo_email))
+        ).await;
+        let addr: std::net::SocketAddr = "10.1.0.1:4000".parse().unwrap();
+        let request = |email: &str| actix_test::TestRequest::post().uri("/reset").peer_addr(addr)
+            .set_json(serde_json::json!({"email": email})).to_request();
+
+        // the handler still reads the body after the middleware has
+        let resp = actix_test::call_service(&app, request("first@example.com")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: String = actix_test::read_body_json(resp).await;
+        assert_eq!(body, "first@example.com");
+
+        assert_eq!(actix_test::call_service(&app, request("first@example.com")).await.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(actix_test::call_service(&app, request("second@example.com")).await.status(), StatusCode::OK);
+        // the fourth request from the IP is over its limit whatever the account
+        assert_eq!(actix_test::call_service(&app, request("third@example.com")).await.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[actix_web::test]
+    async fn test_challenge_after() {
+        const RULE: RateLimitRule = RateLimitRule {
+            name: "test_challenge",
+            ip_max: 0,
+            account_max: 5,
+            window_seconds: 60,
+            account_key: AccountKey::BasicAuth,
+            challenge_after: 1,
+        };
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(|req, next| limit_auth_requests::<InMemoryRateLimitStore, FakeConfig, SolvedTokenMock, _>(RULE, req, next)))
+                .route("/login", web::post().to(HttpResponse::Ok))
+        ).await;
+        let request = |token: Option<&str>| {
+            let req = actix_test::TestRequest::post().uri("/login")
+                .insert_header(("Authorization", basic_auth("challenged@example.com")));
+            match token {
+                Some(token) => req.insert_header((CAPTCHA_HEADER, token)).to_request(),
+                None => req.to_request(),
+            }
+        };
+
+        assert_eq!(actix_test::call_service(&app, request(None)).await.status(), StatusCode::OK);
+        // past the threshold the account has to solve a CAPTCHA
+        let resp = actix_test::call_service(&app, request(None)).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let body: serde_json::Value = actix_test::read_body_json(resp).await;
+        assert_eq!(body["details"]["code"], "captcha_required");
+        assert_eq!(actix_test::call_service(&app, request(Some("guessed"))).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(actix_test::call_service(&app, request(Some("solved"))).await.status(), StatusCode::OK);
+    }
+}
@@ -2,19 +2,26 @@
 //!
 //! # Overview
 //! This file defines the `send_confirmation_email` method, which enforces email rate limits and
-//! sends confirmation emails using Mailchimp templates. It interacts with the data access layer (DAL)
+//! sends confirmation emails using Mailchimp templates, and `confirmation_outbox_email`, which
+//! builds the confirmation email for a new user to be queued in the same transaction as the user. It interacts with the data access layer (DAL)
 //! for rate-limit tracking and delegates email sending to the `SendTemplate` trait.
 
 use utils::{
     config::GetConfigVariable,
-    errors::NanoServiceError,
+    errors::{NanoServiceError, NanoServiceErrorStatus},
 };
 use dal::rate_limit_entries::tx_definitions::{
     CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry,
 };
+use dal::users::tx_definitions::GetRecipientProfile;
 use crate::api::mailchimp_emails::manage_rate_limit::manage_rate_limit;
 use crate::mailchimp_helpers::create_mailchimp_template::create_mailchimp_template;
+use crate::mailchimp_helpers::recipient_merge_vars::{add_recipient_merge_vars, recipient_locale};
 use crate::mailchimp_traits::mc_definitions::SendTemplate;
+use crate::api::mailchimp_emails::template_check::CONFIRMATION_EMAIL_TEMPLATE;
+use crate::outbox::descriptor::outbox_email;
+use kernel::email_outbox::NewOutboxEmail;
+use kernel::users::NewUser;
 
 
 /// Sends a confirmation email if within rate limits.
@@ -30,14 +37,15 @@ use crate::mailchimp_traits::mc_definitions::SendTemplate;
 ///
 /// ## Notes
 /// - Calls `manage_rate_limit` before proceeding with email sending.
-/// - Uses `create_mailchimp_template` to format the email content.
+/// - Uses `create_mailchimp_template` to format the email content in the recipient's stored locale.
+/// - Adds the recipient's first name, username and locale with `add_recipient_merge_vars`.
 /// - Checks the `PRODUCTION` environment variable to determine whether to actually send the email.
 pub async fn send_confirmation_email<X, Y, Z>(
     email: String,
     unique_id: String,
 ) -> Result<bool, NanoServiceError>
 where
-    X: CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry,
+    X: CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry + GetRecipientProfile,
     Y: SendTemplate,
     Z: GetConfigVariable,
 {
@@ -47,8 +55,11 @@ where
     }
 
     let global_merge_var_name = "CONFIRMATION_URL".to_string();
-    let template_name = "confirmation-email".to_string();
-    let template = create_mailchimp_template::<Z>(email, unique_id, global_merge_var_name, template_name)?;
+    let template_name = CONFIRMATION_EMAIL_TEMPLATE.to_string();
+    let profile = X::get_recipient_profile(email.clone()).await?;
+    let locale = recipient_locale(profile.as_ref());
+    let template = create_mailchimp_template::<Z>(email, unique_id, global_merge_var_name, template_name, locale)?;
+    let template = add_recipient_merge_vars(template, profile);
 
     let production = <Z>::get_config_variable("PRODUCTION".to_string())?;
     if production.to_uppercase().trim() == "TRUE" {
@@ -58,16 +69,63 @@ where
     }
 }
 
+/// Builds the confirmation email for a user who is about to be created.
+///
+/// # Arguments
+/// - `user`: The user being created.
+///
+/// # Returns
+/// - `Ok(Some(NewOutboxEmail))`: The email to queue in the same transaction as the user.
+/// - `Ok(None)`: If `PRODUCTION` is not `true`, in which case no email is sent.
+/// - `Err(NanoServiceError)`: If the address is rate limited or the template cannot be built.
+///
+/// ## Notes
+/// - The rate limit is checked before the user is written, so a rate limited address does not
+///   leave a user behind that was never sent a confirmation email.
+/// - The user is not stored yet, so the template is personalised with `NewUser::recipient_profile`
+///   rather than `GetRecipientProfile`.
+pub async fn confirmation_outbox_email<X, Z>(user: &NewUser) -> Result<Option<NewOutboxEmail>, NanoServiceError>
+where
+    X: CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry,
+    Z: GetConfigVariable,
+{
+    let within_limits = manage_rate_limit::<X>(&user.email).await?;
+    if !within_limits {
+        return Err(NanoServiceError::new(
+            "Failed to send confirmation email due to a rate limit error".to_string(),
+            NanoServiceErrorStatus::Unknown
+        ));
+    }
+
+    let profile = user.recipient_profile();
+    let template = create_mailchimp_template::<Z>(
+        user.email.clone(),
+        user.uuid.clone(),
+        "CONFIRMATION_URL".to_string(),
+        CONFIRMATION_EMAIL_TEMPLATE.to_string(),
+        recipient_locale(Some(&profile))
+    )?;
+    let template = add_recipient_merge_vars(template, Some(profile));
+
+    let production = <Z>::get_config_variable("PRODUCTION".to_string())?;
+    if production.to_uppercase().trim() == "TRUE" {
+        outbox_email(&template, None).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kernel::users::RecipientProfile;
     use chrono::{Duration, Utc};
     use dal_tx_impl::impl_transaction;
     use kernel::rate_limit_entries::{NewRateLimitEntry, RateLimitEntry};
     use crate::mailchimp_helpers::mailchimp_template::Template;
     use std::sync::atomic::{AtomicBool, Ordering};
     use std::sync::LazyLock;
-    use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+    use kernel::users::UserRole;
 
     // Atomic flags
     static CREATE_RATE_LIMIT_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
@@ -122,6 +180,11 @@ mod tests {
         })
     }
 
+    #[impl_transaction(MockDbHandleSuccess, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(_email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        Ok(None)
+    }
+
     #[impl_transaction(MockDbHandleSuccess, GetRateLimitEntry, get_rate_limit_entry)]
     async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
         GET_RATE_LIMIT_CALLED.store(true, Ordering::Relaxed);
@@ -153,6 +216,11 @@ mod tests {
         })
     }
 
+    #[impl_transaction(MockDbHandleRateLimited, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(_email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        Ok(None)
+    }
+
     #[impl_transaction(MockDbHandleRateLimited, GetRateLimitEntry, get_rate_limit_entry)]
     async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
         GET_RATE_LIMIT_CALLED.store(true, Ordering::Relaxed);
@@ -216,7 +284,7 @@ mod tests {
         .await;
 
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true);
+        assert!(result.unwrap());
 
         assert!(
             GET_RATE_LIMIT_CALLED.load(Ordering::Relaxed),
@@ -269,7 +337,7 @@ mod tests {
         .await;
 
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), false);
+        assert!(!result.unwrap());
 
         assert!(GET_RATE_LIMIT_CALLED.load(Ordering::Relaxed));
         assert!(UPDATE_RATE_LIMIT_CALLED.load(Ordering::Relaxed));
@@ -311,11 +379,60 @@ mod tests {
         .await;
 
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true);
+        assert!(result.unwrap());
 
         assert!(GET_RATE_LIMIT_CALLED.load(Ordering::Relaxed));
         assert!(UPDATE_RATE_LIMIT_CALLED.load(Ordering::Relaxed));
         assert!(!CREATE_RATE_LIMIT_CALLED.load(Ordering::Relaxed));
         assert!(!SEND_TEMPLATE_CALLED.load(Ordering::Relaxed));
     }
+
+    #[tokio::test]
+    async fn test_confirmation_outbox_email() {
+        struct MockDbHandleNewUser;
+
+        #[impl_transaction(MockDbHandleNewUser, CreateRateLimitEntry, create_rate_limit_entry)]
+        async fn create_rate_limit_entry(new_entry: NewRateLimitEntry) -> Result<RateLimitEntry, NanoServiceError> {
+            Ok(RateLimitEntry {
+                id: 1,
+                email: new_entry.email,
+                rate_limit_period_start: Utc::now().naive_utc(),
+                count: 1,
+            })
+        }
+
+        #[impl_transaction(MockDbHandleNewUser, GetRateLimitEntry, get_rate_limit_entry)]
+        async fn get_rate_limit_entry(_email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
+            Ok(None)
+        }
+
+        #[impl_transaction(MockDbHandleNewUser, UpdateRateLimitEntry, update_rate_limit_entry)]
+        async fn update_rate_limit_entry(_updated_entry: RateLimitEntry) -> Result<bool, NanoServiceError> {
+            Ok(true)
+        }
+
+        let user = NewUser::new(
+            "ada".to_string(),
+            "ada@example.com".to_string(),
+            "Ada".to_string(),
+            "Lovelace".to_string(),
+            UserRole::Worker,
+            "password".to_string()
+        ).unwrap();
+
+        let email = confirmation_outbox_email::<MockDbHandleNewUser, FakeConfigProductionTrue>(&user).await.unwrap().unwrap();
+        assert!(email.send_at.is_none());
+        assert_eq!(email.template["template_name"], CONFIRMATION_EMAIL_TEMPLATE);
+        assert_eq!(email.template["api_key"], "");
+        assert_eq!(email.template["message"]["to"][0]["email"], "ada@example.com");
+        let merge_vars = email.template["message"]["global_merge_vars"].as_array().unwrap();
+        assert!(merge_vars.contains(&serde_json::json!({"name": "CONFIRMATION_URL", "content": user.uuid})));
+        assert!(merge_vars.contains(&serde_json::json!({"name": "FIRST_NAME", "content": "Ada"})));
+
+        let email = confirmation_outbox_email::<MockDbHandleNewUser, FakeConfigProductionFalse>(&user).await.unwrap();
+        assert!(email.is_none());
+
+        let result = confirmation_outbox_email::<MockDbHandleRateLimited, FakeConfigProductionTrue>(&user).await;
+        assert_eq!(result.err().unwrap().message, "Email rate limited");
+    }
 }
@@ -199,14 +199,14 @@ mod tests {
     async fn test_manage_rate_limit_no_entry() {
         let result = manage_rate_limit::<MockDbHandleNoEntry>("test@example.com").await;
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true);
+        assert!(result.unwrap());
     }
 
     #[tokio::test]
     async fn test_manage_rate_limit_outside_rate_limit() {
         let result = manage_rate_limit::<MockDbHandleOutsideRateLimit>("test@example.com").await;
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true); // Should reset the limit and allow the request
+        assert!(result.unwrap()); // Should reset the limit and allow the request
     }
 
     #[tokio::test]
@@ -222,6 +222,6 @@ mod tests {
     async fn test_manage_rate_limit_not_rate_limited() {
         let result = manage_rate_limit::<MockDbHandleNotRateLimited>("test@example.com").await;
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true); // Should allow the request since under the limit
+        assert!(result.unwrap()); // Should allow the request since under the limit
     }
 }
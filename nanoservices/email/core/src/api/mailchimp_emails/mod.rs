@@ -1,3 +1,8 @@
 pub mod confirmation_email;
 pub mod password_reset_email;
-pub mod manage_rate_limit;
\ No newline at end of file
+pub mod manage_rate_limit;
+pub mod template_check;
+pub mod review_request_email;
+pub mod sla_warning_email;
+pub mod notification_summary_email;
+pub mod test_fire;
@@ -0,0 +1,134 @@
+//! Core logic for sending a user a single email summarising a batch of notifications.
+//!
+//! # Overview
+//! The user is sent the `todo-notification-summary` template with these global merge variables:
+//! * `NOTIFICATION_TYPE` - The kind of event, such as `assignment` or `comment`.
+//! * `COUNT` - How many notifications are in the batch.
+//! * `TODO_IDS` - The IDs of the to-do items, comma separated in the order the events happened.
+//! * `TODO_NAMES` - The names of the to-do items, one per line in the same order.
+//!
+//! A batch of one is sent with the same template so it can word a single event differently.
+
+use utils::{
+    config::GetConfigVariable,
+    errors::NanoServiceError,
+};
+use kernel::notifications::{NotificationType, PendingNotification};
+use crate::mailchimp_helpers::mailchimp_template::{
+    ToContent,
+    GlobalMergeVarsContent,
+    MessageContent,
+    Template,
+};
+use crate::mailchimp_traits::mc_definitions::SendTemplate;
+use crate::api::mailchimp_emails::template_check::NOTIFICATION_SUMMARY_TEMPLATE;
+
+
+/// Builds the notification summary template.
+fn notification_summary_template<Z: GetConfigVariable>(
+    email: String,
+    notification_type: NotificationType,
+    notifications: &[PendingNotification]
+) -> Result<Template, NanoServiceError> {
+    let mailchimp_api_key = <Z>::get_config_variable("MAILCHIMP_API_KEY".to_string())?;
+    let todo_ids: Vec<String> = notifications.iter().map(|notification| notification.todo_id.to_string()).collect();
+    let todo_names: Vec<&str> = notifications.iter().map(|notification| notification.todo_name.as_str()).collect();
+    let global_merge_vars = vec![
+        GlobalMergeVarsContent::new("NOTIFICATION_TYPE".to_string(), notification_type.as_str().to_string()),
+        GlobalMergeVarsContent::new("COUNT".to_string(), notifications.len().to_string()),
+        GlobalMergeVarsContent::new("TODO_IDS".to_string(), todo_ids.join(",")),
+        GlobalMergeVarsContent::new("TODO_NAMES".to_string(), todo_names.join("\n")),
+    ];
+    let message_content = MessageContent::new(vec![ToContent::new(email, "to".to_string())], global_merge_vars);
+    Ok(Template::new(mailchimp_api_key, NOTIFICATION_SUMMARY_TEMPLATE.to_string(), message_content))
+}
+
+
+/// Sends a user one email summarising a batch of notifications of the same type.
+///
+/// # Arguments
+/// - `email`: The user's email address.
+/// - `notification_type`: The kind of event every notification in the batch is about.
+/// - `notifications`: The notifications in the batch, oldest first.
+///
+/// # Returns
+/// - `Ok(true)`: If the email was sent, or skipped because `PRODUCTION` is not `TRUE`.
+/// - `Ok(false)`: If the email send operation returned false.
+/// - `Err(NanoServiceError)`: If an error occurs during processing.
+pub async fn send_notification_summary_email<Y, Z>(
+    email: String,
+    notification_type: NotificationType,
+    notifications: &[PendingNotification]
+) -> Result<bool, NanoServiceError>
+where
+    Y: SendTemplate,
+    Z: GetConfigVariable,
+{
+    let template = notification_summary_template::<Z>(email, notification_type, notifications)?;
+
+    let production = <Z>::get_config_variable("PRODUCTION".to_string())?;
+    if production.to_uppercase().trim() == "TRUE" {
+        Y::send_template(&template).await
+    } else {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "MAILCHIMP_API_KEY" => Ok("mock_mailchimp_api_key".to_string()),
+                "PRODUCTION" => Ok("true".to_string()),
+                _ => Ok("".to_string()),
+            }
+        }
+    }
+
+    struct MockMailchimp;
+
+    #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+    async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {
+        assert_eq!(template.template_name, NOTIFICATION_SUMMARY_TEMPLATE);
+        assert_eq!(template.message.to[0].email, "worker@example.com");
+        let vars: Vec<(&str, &str)> = template.message.global_merge_vars.iter()
+            .map(|var| (var.name.as_str(), var.content.as_str()))
+            .collect();
+        assert_eq!(vars, vec![
+            ("NOTIFICATION_TYPE", "assignment"),
+            ("COUNT", "2"),
+            ("TODO_IDS", "4,7"),
+            ("TODO_NAMES", "write report\nfile expenses"),
+        ]);
+        Ok(true)
+    }
+
+    fn notification(todo_id: i32, todo_name: &str) -> PendingNotification {
+        PendingNotification {
+            id: todo_id,
+            user_id: 3,
+            notification_type: NotificationType::Assignment,
+            todo_id,
+            todo_name: todo_name.to_string(),
+            date_created: Utc::now().naive_utc(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_notification_summary_email() {
+        let notifications = vec![notification(4, "write report"), notification(7, "file expenses")];
+        let sent = send_notification_summary_email::<MockMailchimp, FakeConfig>(
+            "worker@example.com".to_string(),
+            NotificationType::Assignment,
+            &notifications
+        ).await.unwrap();
+        assert!(sent);
+    }
+}
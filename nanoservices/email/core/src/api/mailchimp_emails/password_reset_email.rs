@@ -11,9 +11,12 @@ use utils::{
 use dal::rate_limit_entries::tx_definitions::{
     CreateRateLimitEntry, UpdateRateLimitEntry, GetRateLimitEntry,
 };
+use dal::users::tx_definitions::GetRecipientProfile;
 use crate::api::mailchimp_emails::manage_rate_limit::manage_rate_limit;
 use crate::mailchimp_helpers::create_mailchimp_template::create_mailchimp_template;
+use crate::mailchimp_helpers::recipient_merge_vars::{add_recipient_merge_vars, recipient_locale};
 use crate::mailchimp_traits::mc_definitions::SendTemplate;
+use crate::api::mailchimp_emails::template_check::PASSWORD_RESET_TEMPLATE;
 
 
 /// Sends a password reset email if within rate limits.
@@ -29,25 +32,29 @@ use crate::mailchimp_traits::mc_definitions::SendTemplate;
 ///
 /// ## Notes
 /// - Calls `manage_rate_limit` before proceeding with email sending.
-/// - Uses `create_mailchimp_template` to format the email content.
+/// - Uses `create_mailchimp_template` to format the email content in the recipient's stored locale.
+/// - Adds the recipient's first name, username and locale with `add_recipient_merge_vars`.
 pub async fn send_password_reset_email<X, Y, Z>(
     email: String,
     unique_id: String,
 ) -> Result<bool, NanoServiceError>
 where
-    X: CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry,
+    X: CreateRateLimitEntry + UpdateRateLimitEntry + GetRateLimitEntry + GetRecipientProfile,
     Y: SendTemplate,
     Z: GetConfigVariable,
 {
     // TODO => I've now added this check but Sam needs to confirm that this check is correct
     let within_limits = manage_rate_limit::<X>(&email).await?;
-    if within_limits == false {
+    if !within_limits {
         return Ok(false);
     }
 
     let global_merge_var_name = "PASSWORD_RESET_URL".to_string();
-    let template_name = "password-reset".to_string();
-    let template = create_mailchimp_template::<Z>(email, unique_id, global_merge_var_name, template_name)?;
+    let template_name = PASSWORD_RESET_TEMPLATE.to_string();
+    let profile = X::get_recipient_profile(email.clone()).await?;
+    let locale = recipient_locale(profile.as_ref());
+    let template = create_mailchimp_template::<Z>(email, unique_id, global_merge_var_name, template_name, locale)?;
+    let template = add_recipient_merge_vars(template, profile);
     
     let production = <Z>::get_config_variable("PRODUCTION".to_string())?;
 
@@ -62,6 +69,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kernel::users::RecipientProfile;
     use chrono::{Duration, Utc};
     use dal_tx_impl::impl_transaction;
     use kernel::rate_limit_entries::*;
@@ -126,6 +134,11 @@ mod tests {
         })
     }
 
+    #[impl_transaction(MockDbHandleSuccess, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(_email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        Ok(None)
+    }
+
     #[impl_transaction(MockDbHandleSuccess, GetRateLimitEntry, get_rate_limit_entry)]
     async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
         GET_RATE_LIMIT_CALLED.store(true, Ordering::Relaxed);
@@ -157,6 +170,11 @@ mod tests {
         })
     }
 
+    #[impl_transaction(MockDbHandleRateLimited, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(_email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        Ok(None)
+    }
+
     #[impl_transaction(MockDbHandleRateLimited, GetRateLimitEntry, get_rate_limit_entry)]
     async fn get_rate_limit_entry(email: String) -> Result<Option<RateLimitEntry>, NanoServiceError> {
         GET_RATE_LIMIT_CALLED.store(true, Ordering::Relaxed);
@@ -219,7 +237,7 @@ mod tests {
         .await;
 
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true);
+        assert!(result.unwrap());
 
         assert!(
             GET_RATE_LIMIT_CALLED.load(Ordering::Relaxed),
@@ -272,7 +290,7 @@ mod tests {
         .await;
 
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), false);
+        assert!(!result.unwrap());
 
         assert!(GET_RATE_LIMIT_CALLED.load(Ordering::Relaxed));
         assert!(UPDATE_RATE_LIMIT_CALLED.load(Ordering::Relaxed));
@@ -314,7 +332,7 @@ mod tests {
         .await;
 
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true);
+        assert!(result.unwrap());
 
         assert!(GET_RATE_LIMIT_CALLED.load(Ordering::Relaxed));
         assert!(UPDATE_RATE_LIMIT_CALLED.load(Ordering::Relaxed));
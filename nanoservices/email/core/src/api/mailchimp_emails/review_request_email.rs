@@ -0,0 +1,130 @@
+//! Core logic for notifying an assigner that a to-do item is waiting for their review.
+//!
+//! # Overview
+//! When a worker completes a to-do item that requires review, the user who assigned it is sent
+//! the `todo-review-request` template. The template is given the item and the endpoints used to
+//! approve or reject it as global merge variables. Replies go to the item's inbound address when
+//! `INBOUND_EMAIL_ADDRESS` is set, so they are added to it as comments.
+
+use utils::{
+    config::GetConfigVariable,
+    errors::NanoServiceError,
+};
+use crate::mailchimp_helpers::mailchimp_template::{
+    ToContent,
+    GlobalMergeVarsContent,
+    MessageContent,
+    Template,
+};
+use crate::mailchimp_traits::mc_definitions::SendTemplate;
+use crate::inbound::todo_reply_address;
+use crate::api::mailchimp_emails::template_check::REVIEW_REQUEST_TEMPLATE;
+
+
+/// The details of the to-do item given to the review request template.
+///
+/// # Fields
+/// * `todo_id` - The ID of the to-do item.
+/// * `todo_name` - The name of the to-do item.
+/// * `approve_endpoint` - The endpoint the reviewer calls to approve the item.
+/// * `reject_endpoint` - The endpoint the reviewer calls to reject the item.
+pub struct ReviewRequest {
+    pub todo_id: i32,
+    pub todo_name: String,
+    pub approve_endpoint: String,
+    pub reject_endpoint: String,
+}
+
+
+/// Builds the review request template.
+fn review_request_template<Z: GetConfigVariable>(email: String, request: ReviewRequest) -> Result<Template, NanoServiceError> {
+    let mailchimp_api_key = <Z>::get_config_variable("MAILCHIMP_API_KEY".to_string())?;
+    let reply_to = todo_reply_address::<Z>(request.todo_id);
+    let global_merge_vars = vec![
+        GlobalMergeVarsContent::new("TODO_ID".to_string(), request.todo_id.to_string()),
+        GlobalMergeVarsContent::new("TODO_NAME".to_string(), request.todo_name),
+        GlobalMergeVarsContent::new("APPROVE_URL".to_string(), request.approve_endpoint),
+        GlobalMergeVarsContent::new("REJECT_URL".to_string(), request.reject_endpoint),
+    ];
+    let mut message_content = MessageContent::new(vec![ToContent::new(email, "to".to_string())], global_merge_vars);
+    if let Some(reply_to) = reply_to {
+        message_content = message_content.with_reply_to(reply_to);
+    }
+    Ok(Template::new(mailchimp_api_key, REVIEW_REQUEST_TEMPLATE.to_string(), message_content))
+}
+
+
+/// Sends a review request to the assigner of a to-do item.
+///
+/// # Arguments
+/// - `email`: The assigner's email address.
+/// - `request`: The to-do item waiting for review.
+///
+/// # Returns
+/// - `Ok(true)`: If the email was sent, or skipped because `PRODUCTION` is not `TRUE`.
+/// - `Ok(false)`: If the email send operation returned false.
+/// - `Err(NanoServiceError)`: If an error occurs during processing.
+pub async fn send_review_request_email<Y, Z>(email: String, request: ReviewRequest) -> Result<bool, NanoServiceError>
+where
+    Y: SendTemplate,
+    Z: GetConfigVariable,
+{
+    let template = review_request_template::<Z>(email, request)?;
+
+    let production = <Z>::get_config_variable("PRODUCTION".to_string())?;
+    if production.to_uppercase().trim() == "TRUE" {
+        Y::send_template(&template).await
+    } else {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "MAILCHIMP_API_KEY" => Ok("mock_mailchimp_api_key".to_string()),
+                "PRODUCTION" => Ok("true".to_string()),
+                _ => Ok("".to_string()),
+            }
+        }
+    }
+
+    struct MockMailchimp;
+
+    #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+    async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {
+        assert_eq!(template.template_name, REVIEW_REQUEST_TEMPLATE);
+        assert_eq!(template.message.to[0].email, "admin@example.com");
+        let vars: Vec<(&str, &str)> = template.message.global_merge_vars.iter()
+            .map(|var| (var.name.as_str(), var.content.as_str()))
+            .collect();
+        assert_eq!(vars, vec![
+            ("TODO_ID", "4"),
+            ("TODO_NAME", "write report"),
+            ("APPROVE_URL", "/approve"),
+            ("REJECT_URL", "/reject"),
+        ]);
+        Ok(true)
+    }
+
+    #[tokio::test]
+    async fn test_send_review_request_email() {
+        let request = ReviewRequest {
+            todo_id: 4,
+            todo_name: "write report".to_string(),
+            approve_endpoint: "/approve".to_string(),
+            reject_endpoint: "/reject".to_string(),
+        };
+        let sent = send_review_request_email::<MockMailchimp, FakeConfig>("admin@example.com".to_string(), request)
+            .await
+            .unwrap();
+        assert!(sent);
+    }
+}
@@ -0,0 +1,149 @@
+//! Core logic for warning an assignee that a to-do item is close to breaching its SLA.
+//!
+//! # Overview
+//! The assignee is sent the `todo-sla-warning` template with the item, its priority and the time
+//! it is due by as global merge variables, the due time written in the assignee's locale. Replies go to the item's inbound address when
+//! `INBOUND_EMAIL_ADDRESS` is set, so they are added to it as comments.
+
+use utils::{
+    config::GetConfigVariable,
+    errors::NanoServiceError,
+    locale::Locale,
+};
+use kernel::chrono::NaiveDateTime;
+use crate::mailchimp_helpers::mailchimp_template::{
+    ToContent,
+    GlobalMergeVarsContent,
+    MessageContent,
+    Template,
+};
+use crate::mailchimp_traits::mc_definitions::SendTemplate;
+use crate::inbound::todo_reply_address;
+use crate::api::mailchimp_emails::template_check::SLA_WARNING_TEMPLATE;
+
+
+/// The details of the to-do item given to the SLA warning template.
+///
+/// # Fields
+/// * `todo_id` - The ID of the to-do item.
+/// * `todo_name` - The name of the to-do item.
+/// * `priority` - The priority of the to-do item.
+/// * `sla_due` - When the to-do item has to be finished by, in UTC.
+/// * `locale` - How the assignee reads dates.
+pub struct SlaWarning {
+    pub todo_id: i32,
+    pub todo_name: String,
+    pub priority: String,
+    pub sla_due: NaiveDateTime,
+    pub locale: Locale,
+}
+
+
+/// Builds the SLA warning template.
+fn sla_warning_template<Z: GetConfigVariable>(email: String, warning: SlaWarning) -> Result<Template, NanoServiceError> {
+    let mailchimp_api_key = <Z>::get_config_variable("MAILCHIMP_API_KEY".to_string())?;
+    let reply_to = todo_reply_address::<Z>(warning.todo_id);
+    let global_merge_vars = vec![
+        GlobalMergeVarsContent::new("TODO_ID".to_string(), warning.todo_id.to_string()),
+        GlobalMergeVarsContent::new("TODO_NAME".to_string(), warning.todo_name),
+        GlobalMergeVarsContent::new("PRIORITY".to_string(), warning.priority),
+        GlobalMergeVarsContent::new("SLA_DUE".to_string(), format!("{} UTC", warning.locale.format_datetime(warning.sla_due))),
+    ];
+    let mut message_content = MessageContent::new(vec![ToContent::new(email, "to".to_string())], global_merge_vars);
+    if let Some(reply_to) = reply_to {
+        message_content = message_content.with_reply_to(reply_to);
+    }
+    Ok(Template::new(mailchimp_api_key, SLA_WARNING_TEMPLATE.to_string(), message_content))
+}
+
+
+/// Sends an SLA warning to the assignee of a to-do item.
+///
+/// # Arguments
+/// - `email`: The assignee's email address.
+/// - `warning`: The to-do item that is close to breaching its SLA.
+///
+/// # Returns
+/// - `Ok(true)`: If the email was sent, or skipped because `PRODUCTION` is not `TRUE`.
+/// - `Ok(false)`: If the email send operation returned false.
+/// - `Err(NanoServiceError)`: If an error occurs during processing.
+pub async fn send_sla_warning_email<Y, Z>(email: String, warning: SlaWarning) -> Result<bool, NanoServiceError>
+where
+    Y: SendTemplate,
+    Z: GetConfigVariable,
+{
+    let template = sla_warning_template::<Z>(email, warning)?;
+
+    let production = <Z>::get_config_variable("PRODUCTION".to_string())?;
+    if production.to_uppercase().trim() == "TRUE" {
+        Y::send_template(&template).await
+    } else {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::NaiveDate;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "MAILCHIMP_API_KEY" => Ok("mock_mailchimp_api_key".to_string()),
+                "PRODUCTION" => Ok("true".to_string()),
+                _ => Ok("".to_string()),
+            }
+        }
+    }
+
+    struct MockMailchimp;
+
+    #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+    async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {
+        assert_eq!(template.template_name, SLA_WARNING_TEMPLATE);
+        assert_eq!(template.message.to[0].email, "worker@example.com");
+        let vars: Vec<(&str, &str)> = template.message.global_merge_vars.iter()
+            .map(|var| (var.name.as_str(), var.content.as_str()))
+            .collect();
+        assert_eq!(vars, vec![
+            ("TODO_ID", "4"),
+            ("TODO_NAME", "write report"),
+            ("PRIORITY", "high"),
+            ("SLA_DUE", "2025-02-10 17:30 UTC"),
+        ]);
+        Ok(true)
+    }
+
+    #[tokio::test]
+    async fn test_send_sla_warning_email() {
+        let warning = SlaWarning {
+            todo_id: 4,
+            todo_name: "write report".to_string(),
+            priority: "high".to_string(),
+            sla_due: NaiveDate::from_ymd_opt(2025, 2, 10).unwrap().and_hms_opt(17, 30, 0).unwrap(),
+            locale: Locale::Neutral,
+        };
+        let sent = send_sla_warning_email::<MockMailchimp, FakeConfig>("worker@example.com".to_string(), warning)
+            .await
+            .unwrap();
+        assert!(sent);
+    }
+
+    #[test]
+    fn test_sla_due_follows_locale() {
+        let warning = SlaWarning {
+            todo_id: 4,
+            todo_name: "write report".to_string(),
+            priority: "high".to_string(),
+            sla_due: NaiveDate::from_ymd_opt(2025, 2, 10).unwrap().and_hms_opt(17, 30, 0).unwrap(),
+            locale: Locale::EnUs,
+        };
+        let template = sla_warning_template::<FakeConfig>("worker@example.com".to_string(), warning).unwrap();
+        let sla_due = template.message.global_merge_vars.iter().find(|var| var.name == "SLA_DUE").unwrap();
+        assert_eq!(sla_due.content, "02/10/2025 5:30 PM UTC");
+    }
+}
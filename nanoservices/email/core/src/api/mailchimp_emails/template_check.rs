@@ -0,0 +1,193 @@
+//! Checks that the templates the service sends with exist on the provider.
+//!
+//! # Overview
+//! A missing template only shows up as a failed send when a user tries to confirm their account
+//! or reset their password. `check_required_templates` lists the templates held by the provider so
+//! the server can flag any that are missing when it starts rather than when a user hits them.
+//! Templates rendered locally, see `crate::local_templates`, are not needed on the provider.
+
+use utils::{
+    config::GetConfigVariable,
+    errors::NanoServiceError,
+};
+use crate::mailchimp_traits::mc_definitions::ListTemplates;
+use crate::local_templates::LocalTemplates;
+
+
+/// The template used for the account confirmation email.
+pub const CONFIRMATION_EMAIL_TEMPLATE: &str = "confirmation-email";
+
+/// The template used for the password reset email.
+pub const PASSWORD_RESET_TEMPLATE: &str = "password-reset";
+
+/// The template used to ask an assigner to review a completed to-do item.
+pub const REVIEW_REQUEST_TEMPLATE: &str = "todo-review-request";
+
+/// The template used to warn an assignee that a to-do item is close to breaching its SLA.
+pub const SLA_WARNING_TEMPLATE: &str = "todo-sla-warning";
+
+/// The template used to summarise a batch of notifications for a user.
+pub const NOTIFICATION_SUMMARY_TEMPLATE: &str = "todo-notification-summary";
+
+/// The template used to invite someone to join. Nothing sends invites yet so it is not required.
+pub const INVITE_TEMPLATE: &str = "invite";
+
+/// Every template the service sends with. New templates need adding here to be checked.
+pub const REQUIRED_TEMPLATES: [&str; 5] = [
+    CONFIRMATION_EMAIL_TEMPLATE,
+    PASSWORD_RESET_TEMPLATE,
+    REVIEW_REQUEST_TEMPLATE,
+    SLA_WARNING_TEMPLATE,
+    NOTIFICATION_SUMMARY_TEMPLATE,
+];
+
+
+/// Finds the required templates that are not held by the provider.
+///
+/// # Returns
+/// - `Ok(Vec<String>)`: The names of the missing templates, empty if all are present.
+/// - `Err(NanoServiceError)`: If the config is missing or the provider could not be queried.
+///
+/// ## Notes
+/// - Emails are only sent when `PRODUCTION` is `TRUE`, so outside of production the provider is
+///   not queried and nothing is reported as missing.
+/// - Templates with a local copy are not reported when `EMAIL_TEMPLATES` is `local`.
+pub async fn check_required_templates<X, Y>() -> Result<Vec<String>, NanoServiceError>
+where
+    X: ListTemplates,
+    Y: GetConfigVariable,
+{
+    let production = <Y>::get_config_variable("PRODUCTION".to_string())?;
+    if production.to_uppercase().trim() != "TRUE" {
+        return Ok(Vec::new())
+    }
+    let api_key = <Y>::get_config_variable("MAILCHIMP_API_KEY".to_string())?;
+    let local_templates = LocalTemplates::from_config::<Y>();
+    let templates = X::list_templates(&api_key).await?;
+    Ok(REQUIRED_TEMPLATES
+        .iter()
+        .filter(|required| !local_templates.renders(required))
+        .filter(|required| !templates.iter().any(|template| template == *required))
+        .map(|required| required.to_string())
+        .collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::LazyLock;
+    use utils::errors::NanoServiceErrorStatus;
+    use test_support::fake_config;
+
+    static LIST_TEMPLATES_CALLED: LazyLock<AtomicBool> = LazyLock::new(|| AtomicBool::new(false));
+
+    struct FakeConfigProductionTrue;
+
+    impl GetConfigVariable for FakeConfigProductionTrue {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "MAILCHIMP_API_KEY" => Ok("mock_mailchimp_api_key".to_string()),
+                "PRODUCTION" => Ok("true".to_string()),
+                _ => Ok("".to_string()),
+            }
+        }
+    }
+
+    fake_config!(FakeConfigLocalTemplates, {
+        "PRODUCTION" => "true",
+        "EMAIL_TEMPLATES" => "local",
+    });
+
+    struct FakeConfigProductionFalse;
+
+    impl GetConfigVariable for FakeConfigProductionFalse {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "PRODUCTION" => Ok("false".to_string()),
+                _ => Ok("".to_string()),
+            }
+        }
+    }
+
+    struct MockMailchimpAllTemplates;
+
+    #[impl_transaction(MockMailchimpAllTemplates, ListTemplates, list_templates)]
+    async fn list_templates(api_key: &str) -> Result<Vec<String>, NanoServiceError> {
+        assert_eq!(api_key, "mock_mailchimp_api_key");
+        Ok(vec![
+            "Confirmation Email".to_string(),
+            CONFIRMATION_EMAIL_TEMPLATE.to_string(),
+            PASSWORD_RESET_TEMPLATE.to_string(),
+            REVIEW_REQUEST_TEMPLATE.to_string(),
+            SLA_WARNING_TEMPLATE.to_string(),
+            NOTIFICATION_SUMMARY_TEMPLATE.to_string(),
+            "newsletter".to_string(),
+        ])
+    }
+
+    struct MockMailchimpMissingReset;
+
+    #[impl_transaction(MockMailchimpMissingReset, ListTemplates, list_templates)]
+    async fn list_templates(_api_key: &str) -> Result<Vec<String>, NanoServiceError> {
+        LIST_TEMPLATES_CALLED.store(true, Ordering::Relaxed);
+        Ok(vec![
+            CONFIRMATION_EMAIL_TEMPLATE.to_string(),
+            REVIEW_REQUEST_TEMPLATE.to_string(),
+            SLA_WARNING_TEMPLATE.to_string(),
+            NOTIFICATION_SUMMARY_TEMPLATE.to_string(),
+        ])
+    }
+
+    struct MockMailchimpError;
+
+    #[impl_transaction(MockMailchimpError, ListTemplates, list_templates)]
+    async fn list_templates(_api_key: &str) -> Result<Vec<String>, NanoServiceError> {
+        Err(NanoServiceError::new(
+            "Failed to list templates".to_string(),
+            NanoServiceErrorStatus::Unknown,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_all_templates_present() {
+        let missing = check_required_templates::<MockMailchimpAllTemplates, FakeConfigProductionTrue>()
+            .await
+            .unwrap();
+        assert!(missing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_missing_template_reported() {
+        let missing = check_required_templates::<MockMailchimpMissingReset, FakeConfigProductionTrue>()
+            .await
+            .unwrap();
+        assert_eq!(missing, vec![PASSWORD_RESET_TEMPLATE.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_local_template_not_required() {
+        let missing = check_required_templates::<MockMailchimpMissingReset, FakeConfigLocalTemplates>()
+            .await
+            .unwrap();
+        assert!(missing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_provider_error_returned() {
+        let outcome = check_required_templates::<MockMailchimpError, FakeConfigProductionTrue>().await;
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_skipped_outside_production() {
+        LIST_TEMPLATES_CALLED.store(false, Ordering::Relaxed);
+        let missing = check_required_templates::<MockMailchimpMissingReset, FakeConfigProductionFalse>()
+            .await
+            .unwrap();
+        assert!(missing.is_empty());
+        assert!(!LIST_TEMPLATES_CALLED.load(Ordering::Relaxed));
+    }
+}
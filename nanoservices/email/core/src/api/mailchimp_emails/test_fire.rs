@@ -0,0 +1,207 @@
+//! Sends a template with made up values so its setup with the provider can be checked by hand.
+//!
+//! # Overview
+//! A template that is misnamed, or missing a merge variable, only shows up when a real email goes
+//! out wrong. `test_fire_template` sends any of the `REQUIRED_TEMPLATES` with sample values for
+//! the merge variables the service fills in, and reports what the provider did with it rather
+//! than failing, so whoever is debugging sees the outcome straight away.
+//!
+//! ## Notes
+//! - A test send goes out whatever `PRODUCTION` is set to, asking for one is the point.
+//! - Every test send has the `TEST_SEND` merge variable set to `true` so templates can flag it.
+
+use serde::Serialize;
+use utils::{
+    config::GetConfigVariable,
+    errors::{NanoServiceError, NanoServiceErrorStatus},
+    locale::Locale,
+};
+use kernel::chrono::Utc;
+use crate::mailchimp_helpers::create_mailchimp_template::create_mailchimp_template;
+use crate::mailchimp_helpers::mailchimp_template::GlobalMergeVarsContent;
+use crate::mailchimp_traits::mc_definitions::SendTemplate;
+use crate::api::mailchimp_emails::template_check::{
+    CONFIRMATION_EMAIL_TEMPLATE,
+    PASSWORD_RESET_TEMPLATE,
+    REVIEW_REQUEST_TEMPLATE,
+    SLA_WARNING_TEMPLATE,
+    NOTIFICATION_SUMMARY_TEMPLATE,
+    REQUIRED_TEMPLATES,
+};
+
+
+/// What the provider did with a test send.
+///
+/// # Variants
+/// * `Sent` - The provider accepted the email.
+/// * `Rejected` - The provider answered but did not accept the email.
+/// * `Failed` - The provider could not be reached, or the send could not be built.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TestFireStatus {
+    Sent,
+    Rejected,
+    Failed,
+}
+
+
+/// The outcome of a test send.
+///
+/// # Fields
+/// * `template` - The template that was sent.
+/// * `recipient` - Who it was sent to.
+/// * `status` - What the provider did with it.
+/// * `error` - Why it failed, if it did.
+/// * `merge_vars` - The merge variables it was sent with.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TestFireResult {
+    pub template: String,
+    pub recipient: String,
+    pub status: TestFireStatus,
+    pub error: Option<String>,
+    pub merge_vars: Vec<GlobalMergeVarsContent>,
+}
+
+
+/// The sample values a template is sent with, as the service would fill them in.
+///
+/// # Returns
+/// * `Some(Vec<(&str, String)>)` - The merge variables, the first is the one the template is built around.
+/// * `None` - If the service does not send with the template.
+pub fn sample_merge_vars(template_name: &str, locale: Locale) -> Option<Vec<(&'static str, String)>> {
+    let sample = match template_name {
+        CONFIRMATION_EMAIL_TEMPLATE => vec![("CONFIRMATION_URL", "test-fire-confirmation-id".to_string())],
+        PASSWORD_RESET_TEMPLATE => vec![("PASSWORD_RESET_URL", "test-fire-reset-id".to_string())],
+        REVIEW_REQUEST_TEMPLATE => vec![
+            ("TODO_ID", "0".to_string()),
+            ("TODO_NAME", "Sample to-do item".to_string()),
+            ("APPROVE_URL", "/api/todo/v1/review/approve".to_string()),
+            ("REJECT_URL", "/api/todo/v1/review/reject".to_string()),
+        ],
+        SLA_WARNING_TEMPLATE => vec![
+            ("TODO_ID", "0".to_string()),
+            ("TODO_NAME", "Sample to-do item".to_string()),
+            ("PRIORITY", "high".to_string()),
+            ("SLA_DUE", format!("{} UTC", locale.format_datetime(Utc::now().naive_utc()))),
+        ],
+        NOTIFICATION_SUMMARY_TEMPLATE => vec![
+            ("NOTIFICATION_TYPE", "assignment".to_string()),
+            ("COUNT", "2".to_string()),
+            ("TODO_IDS", "0,1".to_string()),
+            ("TODO_NAMES", "Sample to-do item\nAnother sample to-do item".to_string()),
+        ],
+        _ => return None
+    };
+    Some(sample)
+}
+
+
+/// Sends a template to a recipient with sample values and reports what happened.
+///
+/// # Arguments
+/// * `template_name` - The template to send, one of the `REQUIRED_TEMPLATES`.
+/// * `recipient` - Who to send it to.
+/// * `locale` - The locale the template's copy and dates are written in.
+///
+/// # Returns
+/// * `Ok(TestFireResult)` - What the provider did with the send, including failures.
+/// * `Err(NanoServiceError)` - `NotFound` if the service does not send with the template.
+pub async fn test_fire_template<W, Y>(template_name: &str, recipient: String, locale: Locale) -> Result<TestFireResult, NanoServiceError>
+where
+    W: SendTemplate,
+    Y: GetConfigVariable,
+{
+    let Some(mut sample) = sample_merge_vars(template_name, locale) else {
+        return Err(NanoServiceError::new(
+            format!("{} is not one of the templates the service sends: {}", template_name, REQUIRED_TEMPLATES.join(", ")),
+            NanoServiceErrorStatus::NotFound
+        ))
+    };
+    sample.push(("TEST_SEND", "true".to_string()));
+    let (first_name, first_value) = sample.remove(0);
+    let mut result = TestFireResult {
+        template: template_name.to_string(),
+        recipient: recipient.clone(),
+        status: TestFireStatus::Failed,
+        error: None,
+        merge_vars: Vec::new(),
+    };
+    let mut template = match create_mailchimp_template::<Y>(
+        recipient, first_value, first_name.to_string(), template_name.to_string(), locale
+    ) {
+        Ok(template) => template,
+        Err(e) => {
+            result.error = Some(e.message);
+            return Ok(result)
+        }
+    };
+    for (name, value) in sample {
+        template.message.global_merge_vars.push(GlobalMergeVarsContent::new(name.to_string(), value));
+    }
+    match W::send_template(&template).await {
+        Ok(true) => result.status = TestFireStatus::Sent,
+        Ok(false) => result.status = TestFireStatus::Rejected,
+        Err(e) => result.error = Some(e.message)
+    }
+    result.merge_vars = template.message.global_merge_vars;
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailchimp_helpers::mailchimp_template::Template;
+    use dal_tx_impl::impl_transaction;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "MAILCHIMP_API_KEY" => Ok("mock_mailchimp_api_key".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+            }
+        }
+    }
+
+    struct MockMailchimp;
+
+    /// Accepts every email but those sent to `bounce@example.com`, and fails those sent to `down@example.com`.
+    #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+    async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {
+        match template.message.to[0].email.as_str() {
+            "bounce@example.com" => Ok(false),
+            "down@example.com" => Err(NanoServiceError::new("provider unreachable".to_string(), NanoServiceErrorStatus::Unknown)),
+            _ => Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_every_required_template_has_a_sample() {
+        for template in REQUIRED_TEMPLATES {
+            assert!(sample_merge_vars(template, Locale::Neutral).is_some(), "{}", template);
+        }
+        assert_eq!(sample_merge_vars("unknown", Locale::Neutral), None);
+    }
+
+    #[tokio::test]
+    async fn test_test_fire_template() {
+        let result = test_fire_template::<MockMailchimp, FakeConfig>(REVIEW_REQUEST_TEMPLATE, "admin@example.com".to_string(), Locale::Neutral).await.unwrap();
+        assert_eq!(result.status, TestFireStatus::Sent);
+        assert_eq!(result.error, None);
+        let names: Vec<&str> = result.merge_vars.iter().map(|var| var.name.as_str()).collect();
+        assert_eq!(names, vec!["TODO_ID", "TODO_NAME", "APPROVE_URL", "REJECT_URL", "TEST_SEND"]);
+
+        let result = test_fire_template::<MockMailchimp, FakeConfig>(PASSWORD_RESET_TEMPLATE, "bounce@example.com".to_string(), Locale::Fr).await.unwrap();
+        assert_eq!(result.status, TestFireStatus::Rejected);
+        assert!(result.merge_vars.iter().any(|var| var.name == "SUBJECT" && var.content == "Réinitialisez votre mot de passe"));
+
+        let result = test_fire_template::<MockMailchimp, FakeConfig>(SLA_WARNING_TEMPLATE, "down@example.com".to_string(), Locale::Neutral).await.unwrap();
+        assert_eq!(result.status, TestFireStatus::Failed);
+        assert_eq!(result.error, Some("provider unreachable".to_string()));
+
+        let error = test_fire_template::<MockMailchimp, FakeConfig>("unknown", "admin@example.com".to_string(), Locale::Neutral).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+    }
+}
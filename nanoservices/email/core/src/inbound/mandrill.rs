@@ -0,0 +1,236 @@
+//! Implements `ParseInboundEmail` for Mailchimp Transactional (Mandrill) inbound webhooks.
+//!
+//! # Overview
+//! Mandrill posts a form with a `mandrill_events` field holding a JSON array of events, and signs
+//! it in the `X-Mandrill-Signature` header with a base64 HMAC-SHA1, keyed with the webhook key, of
+//! the webhook URL followed by every form field name and value sorted by name.
+//!
+//! # Variables
+//! * `MANDRILL_INBOUND_WEBHOOK_KEY` - The key Mandrill gave the inbound route, webhooks are rejected when not set
+//! * `MANDRILL_INBOUND_WEBHOOK_URL` - The URL registered with Mandrill, defaults to the URL the request
+//!   arrived on, which differs behind a proxy
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::Value;
+use sha1::Sha1;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::webhooks::constant_time_eq;
+use crate::inbound::{InboundEmail, InboundWebhook, ParseInboundEmail};
+use crate::mailchimp_traits::mc_definitions::MailchimpDescriptor;
+
+
+/// The header Mandrill signs webhooks in.
+pub const MANDRILL_SIGNATURE_HEADER: &str = "X-Mandrill-Signature";
+
+
+/// An event in the `mandrill_events` field, only `inbound` events are read.
+#[derive(Deserialize)]
+struct MandrillEvent {
+    event: String,
+    msg: Option<MandrillMessage>,
+}
+
+
+/// The parts of an inbound message that are read.
+#[derive(Deserialize)]
+struct MandrillMessage {
+    from_email: String,
+    email: Option<String>,
+    #[serde(default)]
+    to: Vec<(String, Option<String>)>,
+    #[serde(default)]
+    subject: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    headers: serde_json::Map<String, Value>,
+}
+
+
+impl MandrillMessage {
+
+    /// Gets a header, which Mandrill sends as an array when it appears more than once.
+    fn header(&self, name: &str) -> Vec<String> {
+        let value = self.headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value);
+        match value {
+            Some(Value::String(value)) => vec![value.clone()],
+            Some(Value::Array(values)) => values.iter().filter_map(|value| value.as_str().map(str::to_string)).collect(),
+            _ => Vec::new()
+        }
+    }
+
+    fn into_inbound_email(self) -> InboundEmail {
+        let message_id = self.header("Message-Id").into_iter().next();
+        let references = self.header("In-Reply-To").into_iter()
+            .chain(self.header("References"))
+            .flat_map(|value| value.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+            .collect();
+        let mut to: Vec<String> = self.to.iter().map(|(address, _)| address.clone()).collect();
+        if let Some(address) = &self.email {
+            if !to.contains(address) {
+                to.push(address.clone());
+            }
+        }
+        InboundEmail {
+            message_id,
+            from: self.from_email,
+            to,
+            subject: self.subject,
+            text: self.text,
+            references,
+        }
+    }
+}
+
+
+fn bad_request(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::BadRequest)
+}
+
+
+/// Computes the signature Mandrill sends for a webhook.
+///
+/// # Arguments
+/// * `key` - The webhook key
+/// * `url` - The URL the webhook is registered with
+/// * `fields` - The form fields of the webhook
+pub fn mandrill_signature(key: &str, url: &str, fields: &[(String, String)]) -> String {
+    let mut sorted: Vec<&(String, String)> = fields.iter().collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut mac = Hmac::<Sha1>::new_from_slice(key.as_bytes()).expect("HMAC accepts a key of any length");
+    mac.update(url.as_bytes());
+    for (name, value) in sorted {
+        mac.update(name.as_bytes());
+        mac.update(value.as_bytes());
+    }
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+
+impl ParseInboundEmail for MailchimpDescriptor {
+
+    fn parse_inbound_email<Y: GetConfigVariable>(webhook: &InboundWebhook) -> Result<Vec<InboundEmail>, NanoServiceError> {
+        let key = Y::get_config_variable("MANDRILL_INBOUND_WEBHOOK_KEY".to_string()).map_err(|_| {
+            NanoServiceError::new("Inbound email is not enabled".to_string(), NanoServiceErrorStatus::Forbidden)
+        })?;
+        let url = Y::get_config_variable("MANDRILL_INBOUND_WEBHOOK_URL".to_string())
+            .unwrap_or_else(|_| webhook.url.clone());
+        let fields: Vec<(String, String)> = serde_urlencoded::from_bytes(&webhook.body)
+            .map_err(|e| bad_request(format!("Inbound webhook is not a form: {}", e)))?;
+
+        let expected = mandrill_signature(&key, &url, &fields);
+        let provided = webhook.header(MANDRILL_SIGNATURE_HEADER).unwrap_or_default();
+        if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+            return Err(NanoServiceError::new(
+                "Invalid inbound webhook signature".to_string(),
+                NanoServiceErrorStatus::Unauthorized
+            ))
+        }
+
+        let events = match fields.iter().find(|(name, _)| name == "mandrill_events") {
+            Some((_, events)) => events,
+            None => return Ok(Vec::new())
+        };
+        let events: Vec<MandrillEvent> = serde_json::from_str(events)
+            .map_err(|e| bad_request(format!("Invalid mandrill_events: {}", e)))?;
+        Ok(events.into_iter()
+            .filter(|event| event.event == "inbound")
+            .filter_map(|event| event.msg)
+            .map(MandrillMessage::into_inbound_email)
+            .collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const URL: &str = "https://example.com/api/todo/v1/inbound-email";
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "MANDRILL_INBOUND_WEBHOOK_KEY" => Ok("webhook-key".to_string()),
+                "MANDRILL_INBOUND_WEBHOOK_URL" => Ok(URL.to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+            }
+        }
+    }
+
+    fn webhook(events: Value, signature: Option<String>) -> InboundWebhook {
+        let fields = vec![("mandrill_events".to_string(), events.to_string())];
+        let signature = signature.unwrap_or_else(|| mandrill_signature("webhook-key", URL, &fields));
+        InboundWebhook {
+            url: "http://internal:8001/api/todo/v1/inbound-email".to_string(),
+            headers: vec![(MANDRILL_SIGNATURE_HEADER.to_lowercase(), signature)],
+            body: serde_urlencoded::to_string(&fields).unwrap().into_bytes(),
+        }
+    }
+
+    #[test]
+    fn test_signature_vector() {
+        let fields = vec![
+            ("mandrill_events".to_string(), "[]".to_string()),
+            ("a".to_string(), "1".to_string()),
+        ];
+        let mut mac = Hmac::<Sha1>::new_from_slice(b"webhook-key").unwrap();
+        mac.update(format!("{}a1mandrill_events[]", URL).as_bytes());
+        assert_eq!(mandrill_signature("webhook-key", URL, &fields), STANDARD.encode(mac.finalize().into_bytes()));
+    }
+
+    #[test]
+    fn test_parse_inbound_email() {
+        let events = json!([
+            {"event": "inbound", "msg": {
+                "from_email": "ada@example.com",
+                "email": "reply+todo-12@inbound.example.com",
+                "to": [["reply+todo-12@inbound.example.com", null]],
+                "subject": "Re: Review requested",
+                "text": "Approved\n\nOn Mon wrote:\n> old",
+                "headers": {
+                    "Message-Id": "<abc@mail.example.com>",
+                    "In-Reply-To": "<todo-12.1@example.com>",
+                    "References": "<first@example.com> <todo-12.1@example.com>"
+                }
+            }},
+            {"event": "hard_bounce", "msg": {"from_email": "x@example.com"}}
+        ]);
+        let emails = MailchimpDescriptor::parse_inbound_email::<FakeConfig>(&webhook(events, None)).unwrap();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].from, "ada@example.com");
+        assert_eq!(emails[0].to, vec!["reply+todo-12@inbound.example.com"]);
+        assert_eq!(emails[0].message_id, Some("<abc@mail.example.com>".to_string()));
+        assert_eq!(emails[0].references.len(), 3);
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let error = MailchimpDescriptor::parse_inbound_email::<FakeConfig>(
+            &webhook(json!([]), Some("forged".to_string()))
+        ).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Unauthorized);
+    }
+
+    #[test]
+    fn test_not_enabled() {
+        struct EmptyConfig;
+
+        impl GetConfigVariable for EmptyConfig {
+            fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+                Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+            }
+        }
+
+        let error = MailchimpDescriptor::parse_inbound_email::<EmptyConfig>(&webhook(json!([]), None)).unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+    }
+}
@@ -0,0 +1,199 @@
+//! Receives replies to emails about to-do items so they can be added as comments.
+//!
+//! # Overview
+//! Emails about a to-do item are sent with a `Reply-To` of `INBOUND_EMAIL_ADDRESS` plus-tagged with
+//! the item, `reply+todo-12@inbound.example.com` for item 12, and the provider forwards replies to
+//! a webhook. Each provider posts a different payload, so parsing is behind `ParseInboundEmail`
+//! and the rest of the flow only sees `InboundEmail`.
+//!
+//! # Variables
+//! * `INBOUND_EMAIL_ADDRESS` - The address replies are received on, replies are not requested when not set
+pub mod mandrill;
+
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+
+
+/// An email received by the inbound webhook.
+///
+/// # Fields
+/// * `message_id` - The `Message-Id` of the email, used to add a redelivered email only once.
+/// * `from` - The address the email was sent from.
+/// * `to` - The addresses the email was sent to.
+/// * `subject` - The subject of the email.
+/// * `text` - The plain text body of the email.
+/// * `references` - The message IDs in the `In-Reply-To` and `References` headers.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InboundEmail {
+    pub message_id: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub text: String,
+    pub references: Vec<String>,
+}
+
+
+/// A webhook request as it arrived, before any provider specific parsing.
+///
+/// # Fields
+/// * `url` - The full URL the webhook was posted to.
+/// * `headers` - The request headers as name and value pairs.
+/// * `body` - The raw request body.
+#[derive(Debug, Clone, Default)]
+pub struct InboundWebhook {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl InboundWebhook {
+
+    /// Gets a header by name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+
+/// Defines the contract for checking and parsing an inbound email webhook from a provider.
+pub trait ParseInboundEmail {
+
+    /// Verifies the webhook came from the provider and parses the emails in it.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<InboundEmail>)` - The emails in the webhook, a provider may send several at once
+    /// * `Err(NanoServiceError)` - `Unauthorized` if the webhook could not be verified, `BadRequest` if it could not be parsed
+    fn parse_inbound_email<Y: GetConfigVariable>(webhook: &InboundWebhook) -> Result<Vec<InboundEmail>, NanoServiceError>;
+}
+
+
+/// The address replies about a to-do item should be sent to.
+///
+/// # Arguments
+/// * `todo_id` - The ID of the to-do item the email is about.
+///
+/// # Returns
+/// * `Some(String)` - `INBOUND_EMAIL_ADDRESS` tagged with the item
+/// * `None` - If `INBOUND_EMAIL_ADDRESS` is not set
+pub fn todo_reply_address<Y: GetConfigVariable>(todo_id: i32) -> Option<String> {
+    let address = Y::get_config_variable("INBOUND_EMAIL_ADDRESS".to_string()).ok()?;
+    let (local, domain) = address.trim().split_once('@')?;
+    let local = local.split('+').next().unwrap_or(local);
+    Some(format!("{}+todo-{}@{}", local, todo_id, domain))
+}
+
+
+/// Finds the `todo-<id>` tag in a piece of text such as an address or message ID.
+fn find_todo_tag(text: &str) -> Option<i32> {
+    let lower = text.to_lowercase();
+    let mut search_from = 0;
+    while let Some(offset) = lower[search_from..].find("todo-") {
+        let start = search_from + offset;
+        let tag_boundary = start == 0 || !lower.as_bytes()[start - 1].is_ascii_alphanumeric();
+        let digits: String = lower[start + 5..].chars().take_while(|c| c.is_ascii_digit()).collect();
+        if tag_boundary && !digits.is_empty() {
+            if let Ok(todo_id) = digits.parse() {
+                return Some(todo_id)
+            }
+        }
+        search_from = start + 5;
+    }
+    None
+}
+
+
+/// Finds the to-do item an email is a reply to.
+///
+/// # Notes
+/// The plus-tagged reply address is checked first, then the message IDs the email references.
+///
+/// # Returns
+/// * `Some(i32)` - The ID of the to-do item
+/// * `None` - If the email does not reference a to-do item
+pub fn todo_reference(email: &InboundEmail) -> Option<i32> {
+    let tagged_address = email.to.iter()
+        .filter_map(|address| address.split_once('@').map(|(local, _)| local))
+        .filter_map(|local| local.split_once('+').map(|(_, tag)| tag))
+        .find_map(find_todo_tag);
+    tagged_address.or_else(|| email.references.iter().find_map(|reference| find_todo_tag(reference)))
+}
+
+
+/// Removes the quoted message and signature from a reply so only the new text is kept.
+///
+/// # Notes
+/// The reply is cut at the first `>` quoted line, an `On ... wrote:` attribution line, an
+/// `-----Original Message-----` separator or a `-- ` signature separator.
+pub fn strip_quoted_reply(text: &str) -> String {
+    let mut kept = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let quote_starts = trimmed.starts_with('>')
+            || (trimmed.starts_with("On ") && trimmed.ends_with("wrote:"))
+            || trimmed.eq_ignore_ascii_case("-----Original Message-----")
+            || line == "-- ";
+        if quote_starts {
+            break
+        }
+        kept.push(line);
+    }
+    kept.join("\n").trim().to_string()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::errors::NanoServiceErrorStatus;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "INBOUND_EMAIL_ADDRESS" => Ok("reply@inbound.example.com".to_string()),
+                _ => Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+            }
+        }
+    }
+
+    struct EmptyConfig;
+
+    impl GetConfigVariable for EmptyConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    #[test]
+    fn test_todo_reply_address() {
+        assert_eq!(todo_reply_address::<FakeConfig>(12), Some("reply+todo-12@inbound.example.com".to_string()));
+        assert_eq!(todo_reply_address::<EmptyConfig>(12), None);
+    }
+
+    #[test]
+    fn test_todo_reference() {
+        let mut email = InboundEmail {
+            to: vec!["someone@example.com".to_string(), "Reply+Todo-42@inbound.example.com".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(todo_reference(&email), Some(42));
+
+        email.to = vec!["mytodo-3@example.com".to_string()];
+        assert_eq!(todo_reference(&email), None);
+
+        email.references = vec!["<abc@mail.example.com>".to_string(), "<todo-7.1700000000@example.com>".to_string()];
+        assert_eq!(todo_reference(&email), Some(7));
+    }
+
+    #[test]
+    fn test_strip_quoted_reply() {
+        let text = "Done, see the attached notes.\r\nThanks\r\n\r\nOn Mon, 3 Mar 2025 at 10:00, Todo <reply@example.com> wrote:\r\n> Please review";
+        assert_eq!(strip_quoted_reply(text), "Done, see the attached notes.\nThanks");
+        assert_eq!(strip_quoted_reply("Looks good\n-- \nAda"), "Looks good");
+        assert_eq!(strip_quoted_reply("> only a quote"), "");
+    }
+}
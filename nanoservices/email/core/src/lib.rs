@@ -1,3 +1,6 @@
 pub mod mailchimp_helpers;
 pub mod mailchimp_traits;
 pub mod api;
+pub mod outbox;
+pub mod inbound;
+pub mod local_templates;
@@ -0,0 +1,325 @@
+//! Renders emails from templates embedded in the binary for deployments not using Mailchimp templates.
+//!
+//! # Overview
+//! The templates live in the crate's `templates` folder and are compiled in with `RustEmbed`. Each
+//! email has a `<template>.html.hbs` and a `<template>.txt.hbs`, which fill the shared `layout`
+//! partial, so every email is sent with an HTML body and a plain text part for clients that do not
+//! show HTML. The templates are rendered with handlebars using the same global merge variables a
+//! Mailchimp template is given, so the copy in the recipient's language (`SUBJECT`, `HEADING`,
+//! `BODY` and `ACTION`), the recipient's details and the branding all carry over. Values are HTML
+//! escaped in the HTML body and left as they are in the text part.
+//!
+//! The link in each email is built from `EMAIL_LINK_BASE_URL` and the email's merge variable:
+//! * `confirmation-email` - `/confirm-user/{CONFIRMATION_URL}`
+//! * `password-reset` - `/reset-password/{PASSWORD_RESET_URL}`
+//! * `invite` - `/accept-invite/{INVITE_URL}`
+//!
+//! When turned on, emails with a local template are rendered here and sent as a plain message,
+//! emails without one are still sent with the Mailchimp template of the same name.
+//!
+//! # Variables
+//! * `EMAIL_TEMPLATES` - Set to `local` to render the emails with a local template here, any other
+//!   value sends every email with its Mailchimp template
+//! * `EMAIL_LINK_BASE_URL` - The frontend the links in the emails point to, such as `https://app.example.com`
+//! * `EMAIL_FROM_ADDRESS` - The address locally rendered emails are sent from, required when `local`
+//! * `EMAIL_FROM_NAME` - The name locally rendered emails are sent from, optional
+use crate::mailchimp_helpers::mailchimp_template::{MessageContent, Template, ToContent};
+use handlebars::Handlebars;
+use rust_embed::RustEmbed;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::secrets::SecretsConfig;
+
+
+/// The partial every template renders its content into.
+const LAYOUT: &str = "layout";
+
+/// How locally rendered emails are sent, configured on first use.
+pub static LOCAL_TEMPLATES: LazyLock<LocalTemplates> = LazyLock::new(LocalTemplates::from_config::<SecretsConfig>);
+
+/// The HTML bodies, with every value escaped.
+static HTML_TEMPLATES: LazyLock<Handlebars<'static>> = LazyLock::new(|| registry("html", true));
+
+/// The plain text parts, with values left as they are.
+static TEXT_TEMPLATES: LazyLock<Handlebars<'static>> = LazyLock::new(|| registry("txt", false));
+
+
+/// The templates compiled into the binary.
+#[derive(RustEmbed)]
+#[folder = "templates"]
+struct EmbeddedTemplates;
+
+
+/// Registers every embedded template with the given extension, such as `password-reset.html.hbs`
+/// as `password-reset`.
+///
+/// # Arguments
+/// * `extension` - The kind of template, `html` or `txt`
+/// * `escape` - Whether values are HTML escaped
+fn registry(extension: &str, escape: bool) -> Handlebars<'static> {
+    let mut registry = Handlebars::new();
+    if !escape {
+        registry.register_escape_fn(handlebars::no_escape);
+    }
+    let suffix = format!(".{}.hbs", extension);
+    for file in EmbeddedTemplates::iter() {
+        let Some(name) = file.strip_suffix(suffix.as_str()) else { continue };
+        let embedded = EmbeddedTemplates::get(&file).expect("an embedded template can be read");
+        let source = std::str::from_utf8(&embedded.data).expect("the embedded templates are UTF-8");
+        let registered = match name {
+            LAYOUT => registry.register_partial(name, source),
+            _ => registry.register_template_string(name, source),
+        };
+        registered.expect("the embedded templates are valid");
+    }
+    registry
+}
+
+
+/// Whether the email has a local template to be rendered with.
+pub fn has_local_template(template_name: &str) -> bool {
+    template_name != LAYOUT
+        && HTML_TEMPLATES.has_template(template_name)
+        && TEXT_TEMPLATES.has_template(template_name)
+}
+
+
+/// An email rendered from a local template.
+///
+/// # Fields
+/// * `subject` - The subject line, the `SUBJECT` merge variable.
+/// * `html` - The HTML body.
+/// * `text` - The plain text part.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+
+/// Renders the email a template describes with its local template.
+///
+/// # Arguments
+/// * `template` - The email, whose global merge variables fill the template.
+/// * `link_base_url` - The frontend the link in the email points to.
+///
+/// # Returns
+/// * `Ok(RenderedEmail)` - The subject, HTML body and plain text part.
+/// * `Err(NanoServiceError)` - A `NotFound` if there is no local template of the email's name, or a
+///   `BadRequest` if the email has no `SUBJECT` or could not be rendered.
+pub fn render_email(template: &Template, link_base_url: &str) -> Result<RenderedEmail, NanoServiceError> {
+    let name = template.template_name.as_str();
+    if !has_local_template(name) {
+        return Err(NanoServiceError::new(
+            format!("There is no local template for {}", name),
+            NanoServiceErrorStatus::NotFound
+        ))
+    }
+    let mut data: Map<String, Value> = template.message.global_merge_vars.iter()
+        .map(|var| (var.name.clone(), Value::String(var.content.clone())))
+        .collect();
+    data.insert("LINK_BASE_URL".to_string(), Value::String(link_base_url.trim_end_matches('/').to_string()));
+    let subject = match data.get("SUBJECT") {
+        Some(Value::String(subject)) if !subject.is_empty() => subject.clone(),
+        _ => return Err(NanoServiceError::new(
+            format!("The {} email has no SUBJECT to render", name),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    };
+    let rendering_error = |e: handlebars::RenderError| NanoServiceError::new(
+        format!("Failed to render the {} email: {}", name, e),
+        NanoServiceErrorStatus::BadRequest
+    );
+    Ok(RenderedEmail {
+        subject,
+        html: HTML_TEMPLATES.render(name, &data).map_err(rendering_error)?,
+        text: TEXT_TEMPLATES.render(name, &data).map_err(rendering_error)?,
+    })
+}
+
+
+/// A rendered email as sent with the provider's `messages/send`.
+///
+/// # Fields
+/// * `email` - The subject and bodies.
+/// * `from_email` - The address the email is sent from.
+/// * `from_name` - The name the email is sent from, left out when not configured.
+/// * `to` - The recipients.
+/// * `headers` - Extra headers such as `Reply-To`, left out when empty.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LocalMessage {
+    #[serde(flatten)]
+    pub email: RenderedEmail,
+    pub from_email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_name: Option<String>,
+    pub to: Vec<ToContent>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+
+/// Whether emails are rendered locally and who they are sent from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalTemplates {
+    pub enabled: bool,
+    pub link_base_url: String,
+    pub from_email: Option<String>,
+    pub from_name: Option<String>,
+}
+
+
+impl LocalTemplates {
+
+    /// Reads the local template config, empty values are treated as not set.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let variable = |name: &str| Y::get_config_variable(name.to_string()).ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+        LocalTemplates {
+            enabled: variable("EMAIL_TEMPLATES").is_some_and(|mode| mode.eq_ignore_ascii_case("local")),
+            link_base_url: variable("EMAIL_LINK_BASE_URL").unwrap_or_default(),
+            from_email: variable("EMAIL_FROM_ADDRESS"),
+            from_name: variable("EMAIL_FROM_NAME"),
+        }
+    }
+
+    /// Whether the email is rendered locally rather than sent with its Mailchimp template.
+    pub fn renders(&self, template_name: &str) -> bool {
+        self.enabled && has_local_template(template_name)
+    }
+
+    /// Renders the email into the message sent to the provider.
+    ///
+    /// # Returns
+    /// * `Ok(LocalMessage)` - The rendered email with its sender and recipients.
+    /// * `Err(NanoServiceError)` - A `BadRequest` if `EMAIL_FROM_ADDRESS` is not set, or the error
+    ///   of `render_email`.
+    pub fn message(&self, template: &Template) -> Result<LocalMessage, NanoServiceError> {
+        let from_email = self.from_email.clone().ok_or_else(|| NanoServiceError::new(
+            "EMAIL_FROM_ADDRESS must be set to send locally rendered emails".to_string(),
+            NanoServiceErrorStatus::BadRequest
+        ))?;
+        let MessageContent { to, headers, .. } = template.message.clone();
+        Ok(LocalMessage {
+            email: render_email(template, &self.link_base_url)?,
+            from_email,
+            from_name: self.from_name.clone(),
+            to,
+            headers,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mailchimp_emails::template_check::{
+        CONFIRMATION_EMAIL_TEMPLATE,
+        INVITE_TEMPLATE,
+        PASSWORD_RESET_TEMPLATE,
+        REVIEW_REQUEST_TEMPLATE,
+    };
+    use crate::mailchimp_helpers::create_mailchimp_template::create_mailchimp_template;
+    use crate::mailchimp_helpers::mailchimp_template::GlobalMergeVarsContent;
+    use test_support::{fake_config, FakeConfig};
+    use utils::locale::Locale;
+
+    fake_config!(LocalConfig, {
+        "MAILCHIMP_API_KEY" => "key",
+        "EMAIL_TEMPLATES" => "Local",
+        "EMAIL_LINK_BASE_URL" => "https://app.example.com/",
+        "EMAIL_FROM_ADDRESS" => "noreply@example.com",
+        "EMAIL_FROM_NAME" => "",
+    });
+
+    fn template(template_name: &str, merge_var: &str, locale: Locale) -> Template {
+        create_mailchimp_template::<LocalConfig>(
+            "ada@example.com".to_string(),
+            "1234-abcd".to_string(),
+            merge_var.to_string(),
+            template_name.to_string(),
+            locale,
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_local_templates_registered() {
+        for name in [CONFIRMATION_EMAIL_TEMPLATE, PASSWORD_RESET_TEMPLATE, INVITE_TEMPLATE] {
+            assert!(has_local_template(name), "{} has no local template", name);
+        }
+        assert!(!has_local_template(REVIEW_REQUEST_TEMPLATE));
+        assert!(!has_local_template(LAYOUT));
+    }
+
+    #[test]
+    fn test_render_email() {
+        let mut template = template(PASSWORD_RESET_TEMPLATE, "PASSWORD_RESET_URL", Locale::Neutral);
+        template.message.global_merge_vars.push(GlobalMergeVarsContent::new("FIRST_NAME".to_string(), "Ada <b>".to_string()));
+        let email = render_email(&template, "https://app.example.com/").unwrap();
+
+        assert_eq!(email.subject, "Reset your password");
+        assert!(email.html.contains("href=\"https://app.example.com/reset-password/1234-abcd\""));
+        assert!(email.html.contains("Ada &lt;b&gt;,"));
+        assert!(email.html.contains("background:#2563eb"));
+        assert!(!email.html.contains("<img"));
+        assert!(email.text.starts_with("Reset your password\n\nAda <b>,\n\n"));
+        assert!(email.text.contains(": https://app.example.com/reset-password/1234-abcd"));
+        assert!(!email.text.contains("<p"));
+    }
+
+    #[test]
+    fn test_render_email_in_locale() {
+        let template = template(CONFIRMATION_EMAIL_TEMPLATE, "CONFIRMATION_URL", Locale::from_tag_or_default("de"));
+        let email = render_email(&template, "https://app.example.com").unwrap();
+        let subject = utils::i18n::lookup(Locale::from_tag_or_default("de"), "email.confirmation-email.subject").unwrap();
+        assert_eq!(email.subject, subject);
+        assert!(email.text.contains("https://app.example.com/confirm-user/1234-abcd"));
+    }
+
+    #[test]
+    fn test_render_email_errors() {
+        let error = render_email(&template(REVIEW_REQUEST_TEMPLATE, "TODO_ID", Locale::Neutral), "").unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+
+        let mut template = template(INVITE_TEMPLATE, "INVITE_URL", Locale::Neutral);
+        template.message.global_merge_vars.retain(|var| var.name != "SUBJECT");
+        let error = render_email(&template, "").unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[test]
+    fn test_local_message() {
+        let local = LocalTemplates::from_config::<LocalConfig>();
+        assert_eq!(local, LocalTemplates {
+            enabled: true,
+            link_base_url: "https://app.example.com/".to_string(),
+            from_email: Some("noreply@example.com".to_string()),
+            from_name: None,
+        });
+        assert!(local.renders(INVITE_TEMPLATE));
+        assert!(!local.renders(REVIEW_REQUEST_TEMPLATE));
+
+        let mut template = template(INVITE_TEMPLATE, "INVITE_URL", Locale::Neutral);
+        template.message = template.message.with_reply_to("team@example.com".to_string());
+        let message = serde_json::to_value(local.message(&template).unwrap()).unwrap();
+        assert_eq!(message["subject"], "You have been invited");
+        assert_eq!(message["from_email"], "noreply@example.com");
+        assert!(message.get("from_name").is_none());
+        assert_eq!(message["to"][0]["email"], "ada@example.com");
+        assert_eq!(message["headers"]["Reply-To"], "team@example.com");
+        assert!(message["text"].as_str().unwrap().contains("/accept-invite/1234-abcd"));
+
+        let local = LocalTemplates { from_email: None, ..local };
+        assert_eq!(local.message(&template).unwrap_err().status, NanoServiceErrorStatus::BadRequest);
+        let local = LocalTemplates::from_config::<FakeConfig>();
+        assert!(!local.enabled);
+        assert!(!local.renders(INVITE_TEMPLATE));
+    }
+}
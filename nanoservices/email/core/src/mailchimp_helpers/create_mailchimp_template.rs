@@ -2,7 +2,8 @@
 //!
 //! # Overview
 //! This file contains the core functionality for dynamically generating email 
-//! templates to be sent to mailchimp. 
+//! templates to be sent to mailchimp. The copy of each template is added in the recipient's
+//! language as the `SUBJECT`, `HEADING`, `BODY` and `ACTION` merge variables, see `utils::i18n`.
 
 use crate::mailchimp_helpers::mailchimp_template::{
     ToContent, 
@@ -13,6 +14,8 @@ use crate::mailchimp_helpers::mailchimp_template::{
 use utils::{
     config::GetConfigVariable,
     errors::NanoServiceError,
+    i18n::email_merge_vars,
+    locale::Locale,
 };
 
 
@@ -23,6 +26,7 @@ use utils::{
 /// * `unique_id` - The unique identifier for the action (e.g., confirmation, reset password).
 /// * `global_merge_var_name` - The name of the global merge variable (e.g., "CONFIRMATION_URL").
 /// * `template_name` - The name of the template.
+/// * `locale` - The recipient's locale, the template's copy is written in its language.
 ///
 /// # Returns
 /// * `Ok(Template)` - If the template was successfully created.
@@ -32,6 +36,7 @@ pub fn create_mailchimp_template<X: GetConfigVariable>(
     unique_id: String, 
     global_merge_var_name: String,
     template_name: String,
+    locale: Locale,
 ) -> Result<Template, NanoServiceError> {
     let mailchimp_api_key = <X>::get_config_variable("MAILCHIMP_API_KEY".to_string())?;
 
@@ -39,7 +44,10 @@ pub fn create_mailchimp_template<X: GetConfigVariable>(
     let global_merge_vars_content = GlobalMergeVarsContent::new(global_merge_var_name, unique_id);
 
     let to_vec = vec![to_content];
-    let global_merge_vars_vec = vec![global_merge_vars_content];
+    let mut global_merge_vars_vec = vec![global_merge_vars_content];
+    for (name, copy) in email_merge_vars(&template_name, locale) {
+        global_merge_vars_vec.push(GlobalMergeVarsContent::new(name.to_string(), copy));
+    }
 
     let message_content = MessageContent::new(to_vec, global_merge_vars_vec);
     let template = Template::new(mailchimp_api_key, template_name, message_content);
@@ -72,11 +80,11 @@ mod tests {
     impl GetConfigVariable for FakeConfigNoApiKey {
 
         fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
-            return Err(
+            Err(
                 NanoServiceError::new(
                     "MAILCHIMP_API_KEY not found in environment".to_string(),
                     NanoServiceErrorStatus::Unknown
-                ));
+                ))
         }
     }
 
@@ -92,6 +100,7 @@ mod tests {
             unique_id.clone(),
             global_merge_var_name.clone(),
             template_name.clone(),
+            Locale::Neutral,
         );
 
         assert!(result.is_ok());
@@ -100,6 +109,21 @@ mod tests {
         assert_eq!(template.message.to[0].email, email);
         assert_eq!(template.message.global_merge_vars[0].name, global_merge_var_name);
         assert_eq!(template.message.global_merge_vars[0].content, unique_id);
+        assert_eq!(template.message.global_merge_vars.len(), 1);
+    }
+
+    #[test]
+    fn test_create_mailchimp_template_localized_copy() {
+        let template = create_mailchimp_template::<FakeConfigWithApiKey>(
+            "test@example.com".to_string(),
+            "unique-id".to_string(),
+            "PASSWORD_RESET_URL".to_string(),
+            "password-reset".to_string(),
+            Locale::Fr,
+        ).unwrap();
+        let subject = template.message.global_merge_vars.iter().find(|var| var.name == "SUBJECT").unwrap();
+        assert_eq!(subject.content, "Réinitialisez votre mot de passe");
+        assert_eq!(template.message.global_merge_vars.len(), 5);
     }
 
     #[test]
@@ -109,6 +133,7 @@ mod tests {
             "unique-id".to_string(),
             "CONFIRMATION_URL".to_string(),
             "confirmation-template".to_string(),
+            Locale::Neutral,
         );
 
         assert!(result.is_err());
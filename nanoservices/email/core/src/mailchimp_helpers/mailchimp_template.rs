@@ -7,6 +7,8 @@
 //! and manipulation to simplify working with email-related data structures.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use kernel::branding::OrgBranding;
 
 
 /// Represents the `ToContent` schema for defining recipient information.
@@ -67,10 +69,13 @@ impl GlobalMergeVarsContent {
 /// # Fields
 /// * `to` - A list of recipients.
 /// * `global_merge_vars` - A list of global merge variables for templated content.
+/// * `headers` - Extra headers for the message such as `Reply-To`, left out of the request when empty.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct MessageContent {
     pub to: Vec<ToContent>,
     pub global_merge_vars: Vec<GlobalMergeVarsContent>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
 }
 
 impl MessageContent {
@@ -83,7 +88,40 @@ impl MessageContent {
     /// # Returns
     /// A new `MessageContent` instance.
     pub fn new(to: Vec<ToContent>, global_merge_vars: Vec<GlobalMergeVarsContent>) -> Self {
-        MessageContent { to, global_merge_vars }
+        MessageContent { to, global_merge_vars, headers: BTreeMap::new() }
+    }
+
+    /// Sets the address replies to the message are sent to.
+    ///
+    /// # Arguments
+    /// * `reply_to` - The reply address.
+    ///
+    /// # Returns
+    /// The message with the `Reply-To` header set.
+    pub fn with_reply_to(mut self, reply_to: String) -> Self {
+        self.headers.insert("Reply-To".to_string(), reply_to);
+        self
+    }
+
+    /// Adds the organization's branding as the `PRODUCT_NAME`, `LOGO_URL` and `ACCENT_COLOR`
+    /// global merge variables, replacing any already set.
+    ///
+    /// # Arguments
+    /// * `branding` - The branding to apply, a missing logo is given as an empty `LOGO_URL`.
+    ///
+    /// # Returns
+    /// The message with the branding merge variables set.
+    pub fn with_branding(mut self, branding: &OrgBranding) -> Self {
+        let branding_vars = [
+            ("PRODUCT_NAME", branding.product_name.clone()),
+            ("LOGO_URL", branding.logo_url.clone().unwrap_or_default()),
+            ("ACCENT_COLOR", branding.accent_color.clone()),
+        ];
+        self.global_merge_vars.retain(|var| !branding_vars.iter().any(|(name, _)| *name == var.name));
+        self.global_merge_vars.extend(branding_vars.into_iter().map(|(name, content)| {
+            GlobalMergeVarsContent::new(name.to_string(), content)
+        }));
+        self
     }
 }
 
@@ -163,6 +201,45 @@ mod tests {
         assert_eq!(message_content.global_merge_vars, global_merge_vars);
     }
 
+    #[test]
+    fn test_message_content_headers() {
+        let message_content = MessageContent::new(Vec::new(), Vec::new());
+        let serialized = serde_json::to_value(&message_content).unwrap();
+        assert!(serialized.get("headers").is_none());
+
+        let message_content = message_content.with_reply_to("reply+todo-7@example.com".to_string());
+        let serialized = serde_json::to_value(&message_content).unwrap();
+        assert_eq!(serialized["headers"]["Reply-To"], "reply+todo-7@example.com");
+
+        let stored = serde_json::json!({"to": [], "global_merge_vars": []});
+        let message_content: MessageContent = serde_json::from_value(stored).unwrap();
+        assert!(message_content.headers.is_empty());
+    }
+
+    #[test]
+    fn test_message_content_branding() {
+        let branding = OrgBranding {
+            product_name: "Acme Tasks".to_string(),
+            logo_url: None,
+            accent_color: "#2563eb".to_string(),
+            date_updated: chrono::Utc::now().naive_utc(),
+        };
+        let message_content = MessageContent::new(Vec::new(), vec![
+            GlobalMergeVarsContent::new("TODO_ID".to_string(), "4".to_string()),
+            GlobalMergeVarsContent::new("PRODUCT_NAME".to_string(), "stale".to_string()),
+        ]).with_branding(&branding);
+
+        let vars: Vec<(&str, &str)> = message_content.global_merge_vars.iter()
+            .map(|var| (var.name.as_str(), var.content.as_str()))
+            .collect();
+        assert_eq!(vars, vec![
+            ("TODO_ID", "4"),
+            ("PRODUCT_NAME", "Acme Tasks"),
+            ("LOGO_URL", ""),
+            ("ACCENT_COLOR", "#2563eb"),
+        ]);
+    }
+
     #[test]
     fn test_template_new() {
         let message = MessageContent::new(
@@ -1,2 +1,3 @@
 pub mod mailchimp_template;
 pub mod create_mailchimp_template;
+pub mod recipient_merge_vars;
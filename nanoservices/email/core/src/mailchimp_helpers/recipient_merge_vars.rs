@@ -0,0 +1,99 @@
+//! Adds the recipient's details to a template so emails can greet them personally.
+//!
+//! # Overview
+//! The sender looks the recipient up by their address with `GetRecipientProfile`, which also gives
+//! the locale the template is written in, and if they are a user the template is given these
+//! global merge variables:
+//! * `FIRST_NAME` - The recipient's first name.
+//! * `USERNAME` - The recipient's username.
+//! * `LOCALE` - The language the recipient's emails are written in.
+//!
+//! A merge variable the caller already set is left as it is, and an address that does not belong
+//! to a user leaves the template unchanged.
+
+use kernel::users::RecipientProfile;
+use utils::locale::Locale;
+use crate::mailchimp_helpers::mailchimp_template::{GlobalMergeVarsContent, Template};
+
+
+/// The locale to write a recipient's emails in, `Locale::Neutral` if they are not a user.
+pub fn recipient_locale(profile: Option<&RecipientProfile>) -> Locale {
+    profile.map(|profile| Locale::from_tag_or_default(&profile.locale)).unwrap_or_default()
+}
+
+
+/// Adds the recipient's first name, username and locale to the template's global merge variables.
+///
+/// # Arguments
+/// * `template` - The template to personalise.
+/// * `profile` - The recipient's profile, `None` if they are not a user.
+///
+/// # Returns
+/// * `Template` - The template with the recipient's details added.
+pub fn add_recipient_merge_vars(mut template: Template, profile: Option<RecipientProfile>) -> Template {
+    let profile = match profile {
+        Some(profile) => profile,
+        None => return template
+    };
+    let merge_vars = [
+        ("FIRST_NAME", profile.first_name),
+        ("USERNAME", profile.username),
+        ("LOCALE", profile.locale),
+    ];
+    for (name, content) in merge_vars {
+        let already_set = template.message.global_merge_vars.iter().any(|var| var.name == name);
+        if !already_set {
+            template.message.global_merge_vars.push(GlobalMergeVarsContent::new(name.to_string(), content));
+        }
+    }
+    template
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailchimp_helpers::mailchimp_template::{MessageContent, ToContent};
+
+    fn profile() -> RecipientProfile {
+        RecipientProfile {
+            first_name: "Ada".to_string(),
+            username: "ada".to_string(),
+            locale: "fr-CA".to_string(),
+            timezone: "UTC".to_string(),
+        }
+    }
+
+    fn template(email: &str) -> Template {
+        let message = MessageContent::new(
+            vec![ToContent::new(email.to_string(), "to".to_string())],
+            vec![
+                GlobalMergeVarsContent::new("CONFIRMATION_URL".to_string(), "unique-id".to_string()),
+                GlobalMergeVarsContent::new("USERNAME".to_string(), "set-by-caller".to_string()),
+            ]
+        );
+        Template::new("key".to_string(), "template".to_string(), message)
+    }
+
+    fn merge_var(template: &Template, name: &str) -> Option<String> {
+        template.message.global_merge_vars.iter().find(|var| var.name == name).map(|var| var.content.clone())
+    }
+
+    #[test]
+    fn test_recipient_details_are_added() {
+        let template = add_recipient_merge_vars(template("user@example.com"), Some(profile()));
+        assert_eq!(merge_var(&template, "FIRST_NAME"), Some("Ada".to_string()));
+        assert_eq!(merge_var(&template, "LOCALE"), Some("fr-CA".to_string()));
+        assert_eq!(merge_var(&template, "USERNAME"), Some("set-by-caller".to_string()));
+        assert_eq!(merge_var(&template, "CONFIRMATION_URL"), Some("unique-id".to_string()));
+        assert_eq!(template.message.global_merge_vars.len(), 4);
+    }
+
+    #[test]
+    fn test_unknown_recipient_is_unchanged() {
+        let template = add_recipient_merge_vars(template("stranger@example.com"), None);
+        assert_eq!(template, self::template("stranger@example.com"));
+        assert_eq!(recipient_locale(None), Locale::Neutral);
+        assert_eq!(recipient_locale(Some(&profile())), Locale::Fr);
+    }
+}
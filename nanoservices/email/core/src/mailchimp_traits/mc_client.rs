@@ -0,0 +1,453 @@
+//! The HTTP client the Mailchimp transactional (Mandrill) API is called with.
+//!
+//! # Overview
+//! Every call is a JSON `POST` to a path under the base URL. Failures are translated from the
+//! provider's error body, which names the error whatever the HTTP status, into a `NanoServiceError`:
+//! * `Invalid_Key` - `Unauthorized`
+//! * `Unknown_Template` and other `Unknown_*` names - `NotFound`
+//! * `ValidationError`, `PaymentRequired` and any other client error - `BadRequest`, as sending the
+//!   same request again cannot succeed
+//! * rate limits, server errors and network failures - `Unknown`
+//!
+//! A call failing with `Unknown` is made again after a jittered backoff, up to the configured
+//! attempts, any other failure is returned straight away. An email the provider accepts but rejects
+//! for every recipient, such as a bounced or unsubscribed address, is returned as a `BadRequest`.
+//!
+//! Emails are sent with a Mailchimp template by `send_template`, or already rendered, see
+//! `crate::local_templates`, by `send_message`.
+//!
+//! # Variables
+//! * `MAILCHIMP_BASE_URL` - The API the client calls, defaults to `DEFAULT_MAILCHIMP_BASE_URL`. Point
+//!   it at a sandbox, such as a mock of the Mandrill API, so tests and staging never send real emails
+//! * `MAILCHIMP_TIMEOUT` - How long a single call may take, such as `10s`, defaults to 10 seconds
+//! * `MAILCHIMP_MAX_ATTEMPTS` - How many times a call is made in total, defaults to 3, 1 turns retries off
+//! * `MAILCHIMP_RETRY_BASE_DELAY` - The backoff before the first retry, doubling for each retry
+//!   after it up to 10 seconds, defaults to `500ms`
+use crate::mailchimp_helpers::mailchimp_template::Template;
+use crate::local_templates::LocalMessage;
+use dal::connections::retry::RetryPolicy;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::json;
+use std::sync::LazyLock;
+use std::time::Duration;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::log_limited;
+use utils::secrets::SecretsConfig;
+
+
+/// The API called when `MAILCHIMP_BASE_URL` is not set.
+pub const DEFAULT_MAILCHIMP_BASE_URL: &str = "https://mandrillapp.com/api/1.0";
+
+/// The timeout used when not configured.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The attempts made when not configured.
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// The first backoff used when not configured.
+const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// The most a single backoff can grow to.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// The client `MailchimpDescriptor` calls the API with, configured on first use.
+pub static MAILCHIMP_CLIENT: LazyLock<MailchimpClient> = LazyLock::new(MailchimpClient::from_config::<SecretsConfig>);
+
+
+/// The error body the API answers a failed call with.
+#[derive(Deserialize)]
+struct ProviderError {
+    name: String,
+    message: String,
+}
+
+
+/// The outcome of sending to one recipient.
+#[derive(Deserialize)]
+struct SendResult {
+    email: String,
+    status: String,
+    reject_reason: Option<String>,
+}
+
+
+/// A template as returned by the templates list endpoint, only the identifying fields are kept.
+#[derive(Deserialize)]
+struct ListedTemplate {
+    name: String,
+    slug: String,
+}
+
+
+/// A failed attempt and whether making it again could succeed.
+struct FailedAttempt {
+    error: NanoServiceError,
+    transient: bool,
+}
+
+
+/// Translates a failed response into a `NanoServiceError`.
+///
+/// # Arguments
+/// * `status` - The status the call was answered with
+/// * `body` - The body of the response, the provider's error if it can be read
+fn provider_error(status: StatusCode, body: &str) -> NanoServiceError {
+    let Ok(error) = serde_json::from_str::<ProviderError>(body) else {
+        let error_status = match status {
+            StatusCode::UNAUTHORIZED => NanoServiceErrorStatus::Unauthorized,
+            code if code.is_client_error() && code != StatusCode::TOO_MANY_REQUESTS => NanoServiceErrorStatus::BadRequest,
+            _ => NanoServiceErrorStatus::Unknown
+        };
+        return NanoServiceError::new(format!("Mailchimp request failed. HTTP Status: {}", status), error_status)
+    };
+    let error_status = match error.name.as_str() {
+        "Invalid_Key" => NanoServiceErrorStatus::Unauthorized,
+        name if name.starts_with("Unknown_") => NanoServiceErrorStatus::NotFound,
+        "ValidationError" | "PaymentRequired" => NanoServiceErrorStatus::BadRequest,
+        _ if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS => NanoServiceErrorStatus::BadRequest,
+        _ => NanoServiceErrorStatus::Unknown
+    };
+    NanoServiceError::new(
+        format!("Mailchimp request failed with {} ({}): {}", status, error.name, error.message),
+        error_status
+    )
+}
+
+
+/// Checks an email was accepted for at least one recipient.
+fn accepted(results: Vec<SendResult>) -> Result<bool, NanoServiceError> {
+    if results.iter().any(|result| result.status != "rejected" && result.status != "invalid") {
+        return Ok(true)
+    }
+    let reasons: Vec<String> = results.iter()
+        .map(|result| format!("{} {}", result.email, result.reject_reason.as_deref().unwrap_or(&result.status)))
+        .collect();
+    Err(NanoServiceError::new(
+        format!("Mailchimp rejected the email: {}", reasons.join(", ")),
+        NanoServiceErrorStatus::BadRequest
+    ))
+}
+
+
+/// Calls the Mailchimp transactional API.
+pub struct MailchimpClient {
+    http: reqwest::Client,
+    base_url: String,
+    retry: RetryPolicy,
+}
+
+
+impl MailchimpClient {
+
+    /// Builds the client from config, values that are not valid fall back to their defaults.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let base_url = Y::get_config_variable("MAILCHIMP_BASE_URL".to_string()).ok()
+            .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+            .unwrap_or_else(|| DEFAULT_MAILCHIMP_BASE_URL.to_string());
+        let timeout = Y::get_duration("MAILCHIMP_TIMEOUT").ok()
+            .filter(|timeout| !timeout.is_zero())
+            .unwrap_or(DEFAULT_TIMEOUT);
+        let max_attempts = Y::get_int("MAILCHIMP_MAX_ATTEMPTS").ok()
+            .filter(|attempts| *attempts > 0)
+            .map(|attempts| u32::try_from(attempts).unwrap_or(u32::MAX))
+            .unwrap_or(DEFAULT_MAX_ATTEMPTS);
+        let base_delay = Y::get_duration("MAILCHIMP_RETRY_BASE_DELAY").unwrap_or(DEFAULT_RETRY_BASE_DELAY);
+        MailchimpClient {
+            http: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .expect("the Mailchimp client can be built"),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            retry: RetryPolicy { max_attempts, base_delay, max_delay: MAX_RETRY_DELAY },
+        }
+    }
+
+    /// Makes a single call.
+    async fn attempt<B: Serialize, T: DeserializeOwned>(&self, url: &str, body: &B) -> Result<T, FailedAttempt> {
+        let response = self.http.post(url).json(body).send().await.map_err(|e| FailedAttempt {
+            error: NanoServiceError::new(
+                format!("Failed to send HTTP request to Mailchimp: {}", e),
+                NanoServiceErrorStatus::Unknown,
+            ),
+            transient: e.is_timeout() || e.is_connect() || e.is_request(),
+        })?;
+        let status = response.status();
+        if status.is_success() {
+            return response.json::<T>().await.map_err(|e| FailedAttempt {
+                error: NanoServiceError::new(
+                    format!("Failed to parse Mailchimp response: {}", e),
+                    NanoServiceErrorStatus::Unknown,
+                ),
+                transient: false,
+            })
+        }
+        let body = response.text().await.unwrap_or_default();
+        let error = provider_error(status, &body);
+        let transient = error.status == NanoServiceErrorStatus::Unknown;
+        Err(FailedAttempt { error, transient })
+    }
+
+    /// Posts `body` as JSON to `path` under the base URL, retrying transient failures.
+    ///
+    /// # Arguments
+    /// * `path` - The API method, such as `messages/send-template`
+    /// * `body` - The request, which carries the API key
+    ///
+    /// # Returns
+    /// * `Ok(T)` - The parsed response of the first attempt that succeeded
+    /// * `Err(NanoServiceError)` - The translated error of the first attempt that cannot be retried,
+    ///   or of the last attempt
+    pub async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T, NanoServiceError> {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+        let mut attempt = 1;
+        loop {
+            match self.attempt(&url, body).await {
+                Err(failed) if failed.transient && attempt < self.retry.max_attempts => {
+                    let backoff = self.retry.backoff(attempt);
+                    log_limited!(
+                        "email",
+                        "Mailchimp call to {} attempt {} of {} failed, retrying in {}ms: {}",
+                        path, attempt, self.retry.max_attempts, backoff.as_millis(), failed.error.message
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                },
+                outcome => return outcome.map_err(|failed| failed.error)
+            }
+        }
+    }
+
+    /// Sends a templated email with `messages/send-template`.
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the email was sent or queued for at least one recipient
+    /// * `Err(NanoServiceError)` - A `BadRequest` naming the reason if every recipient was rejected,
+    ///   or the error of the call
+    pub async fn send_template(&self, template: &Template) -> Result<bool, NanoServiceError> {
+        let results: Vec<SendResult> = self.post("messages/send-template", template).await?;
+        accepted(results)
+    }
+
+    /// Sends an email rendered locally with `messages/send`.
+    ///
+    /// # Arguments
+    /// * `api_key` - The Mailchimp API key
+    /// * `message` - The rendered email with its sender and recipients
+    ///
+    /// # Returns
+    /// * `Ok(true)` - If the email was sent or queued for at least one recipient
+    /// * `Err(NanoServiceError)` - A `BadRequest` naming the reason if every recipient was rejected,
+    ///   or the error of the call
+    pub async fn send_message(&self, api_key: &str, message: &LocalMessage) -> Result<bool, NanoServiceError> {
+        let results: Vec<SendResult> = self.post("messages/send", &json!({"key": api_key, "message": message})).await?;
+        accepted(results)
+    }
+
+    /// Lists the name and slug of every template with `templates/list`, as either can be used when sending.
+    pub async fn list_templates(&self, api_key: &str) -> Result<Vec<String>, NanoServiceError> {
+        let templates: Vec<ListedTemplate> = self.post("templates/list", &json!({"key": api_key})).await?;
+        Ok(templates.into_iter().flat_map(|template| [template.name, template.slug]).collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use crate::mailchimp_helpers::mailchimp_template::{MessageContent, ToContent};
+    use crate::local_templates::RenderedEmail;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Runs a fake API answering each connection with the next of `responses`, and returns its
+    /// base URL and the bodies it was sent.
+    async fn fake_mandrill(responses: Vec<(u16, &'static str)>) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/api/1.0", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let bodies = received.clone();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                loop {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, sent)) = text.split_once("\r\n\r\n") {
+                        let length = head.lines()
+                            .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|value| value.trim().parse::<usize>().unwrap()))
+                            .unwrap_or(0);
+                        if sent.len() >= length {
+                            bodies.lock().unwrap().push(serde_json::from_str(sent).unwrap_or(Value::Null));
+                            break
+                        }
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status, body.len(), body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (base_url, received)
+    }
+
+    fn client(base_url: &str) -> MailchimpClient {
+        MailchimpClient {
+            http: reqwest::Client::new(),
+            base_url: base_url.to_string(),
+            retry: RetryPolicy { max_attempts: 3, base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(2) },
+        }
+    }
+
+    struct SandboxConfig;
+
+    impl GetConfigVariable for SandboxConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            Ok(match variable.as_str() {
+                "MAILCHIMP_BASE_URL" => "http://localhost:4010/api/1.0/",
+                "MAILCHIMP_TIMEOUT" => "2s",
+                "MAILCHIMP_MAX_ATTEMPTS" => "5",
+                "MAILCHIMP_RETRY_BASE_DELAY" => "50ms",
+                _ => ""
+            }.to_string())
+        }
+    }
+
+    struct InvalidConfig;
+
+    impl GetConfigVariable for InvalidConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    #[test]
+    fn test_from_config() {
+        let client = MailchimpClient::from_config::<SandboxConfig>();
+        assert_eq!(client.base_url, "http://localhost:4010/api/1.0");
+        assert_eq!(client.retry, RetryPolicy { max_attempts: 5, base_delay: Duration::from_millis(50), max_delay: MAX_RETRY_DELAY });
+
+        let client = MailchimpClient::from_config::<InvalidConfig>();
+        assert_eq!(client.base_url, DEFAULT_MAILCHIMP_BASE_URL);
+        assert_eq!(client.retry.max_attempts, DEFAULT_MAX_ATTEMPTS);
+        assert_eq!(client.retry.base_delay, DEFAULT_RETRY_BASE_DELAY);
+    }
+
+    #[test]
+    fn test_provider_error() {
+        let error = provider_error(StatusCode::INTERNAL_SERVER_ERROR, r#"{"status":"error","code":-1,"name":"Invalid_Key","message":"Invalid API key"}"#);
+        assert_eq!(error.status, NanoServiceErrorStatus::Unauthorized);
+        assert_eq!(error.message, "Mailchimp request failed with 500 Internal Server Error (Invalid_Key): Invalid API key");
+        let error = provider_error(StatusCode::INTERNAL_SERVER_ERROR, r#"{"name":"Unknown_Template","message":"No such template"}"#);
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+        let error = provider_error(StatusCode::BAD_REQUEST, r#"{"name":"ValidationError","message":"to is required"}"#);
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        let error = provider_error(StatusCode::BAD_GATEWAY, "<html>bad gateway</html>");
+        assert_eq!((error.status, error.message.as_str()), (NanoServiceErrorStatus::Unknown, "Mailchimp request failed. HTTP Status: 502 Bad Gateway"));
+        let error = provider_error(StatusCode::TOO_MANY_REQUESTS, "");
+        assert_eq!(error.status, NanoServiceErrorStatus::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_post_retries_server_errors() {
+        let (base_url, received) = fake_mandrill(vec![
+            (503, "unavailable"),
+            (500, r#"{"name":"GeneralError","message":"try again"}"#),
+            (200, r#"{"ok":true}"#),
+        ]).await;
+        let response: Value = client(&base_url).post("users/ping", &json!({"key": "abc"})).await.unwrap();
+        assert_eq!(response, json!({"ok": true}));
+        assert_eq!(*received.lock().unwrap(), vec![json!({"key": "abc"}); 3]);
+    }
+
+    #[tokio::test]
+    async fn test_post_does_not_retry_rejections() {
+        let (base_url, received) = fake_mandrill(vec![
+            (400, r#"{"name":"ValidationError","message":"to is required"}"#),
+        ]).await;
+        let error = client(&base_url).post::<_, Value>("users/ping", &json!({"key": "abc"})).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        // the last failure is returned once the attempts run out
+        let (base_url, received) = fake_mandrill(vec![(503, ""), (503, ""), (503, "")]).await;
+        let error = client(&base_url).post::<_, Value>("users/ping", &json!({})).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Unknown);
+        assert_eq!(received.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_send_template() {
+        let message = MessageContent::new(vec![ToContent::new("ada@example.com".to_string(), "to".to_string())], Vec::new());
+        let template = Template::new("key".to_string(), "confirmation-email".to_string(), message);
+        let (base_url, received) = fake_mandrill(vec![
+            (200, r#"[{"email":"ada@example.com","status":"queued","reject_reason":null,"_id":"1"}]"#),
+            (200, r#"[{"email":"ada@example.com","status":"rejected","reject_reason":"hard-bounce","_id":"2"}]"#),
+            (500, r#"{"status":"error","code":5,"name":"Unknown_Template","message":"No such template"}"#),
+        ]).await;
+        let client = client(&base_url);
+        assert!(client.send_template(&template).await.unwrap());
+        assert_eq!(received.lock().unwrap()[0]["template_name"], "confirmation-email");
+
+        let error = client.send_template(&template).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        assert_eq!(error.message, "Mailchimp rejected the email: ada@example.com hard-bounce");
+
+        let error = client.send_template(&template).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+        assert_eq!(received.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_send_message() {
+        let message = LocalMessage {
+            email: RenderedEmail {
+                subject: "Welcome".to_string(),
+                html: "<p>Welcome</p>".to_string(),
+                text: "Welcome".to_string(),
+            },
+            from_email: "noreply@example.com".to_string(),
+            from_name: Some("Acme".to_string()),
+            to: vec![ToContent::new("ada@example.com".to_string(), "to".to_string())],
+            headers: Default::default(),
+        };
+        let (base_url, received) = fake_mandrill(vec![
+            (200, r#"[{"email":"ada@example.com","status":"sent","reject_reason":null,"_id":"1"}]"#),
+            (200, r#"[{"email":"ada@example.com","status":"invalid","reject_reason":null,"_id":"2"}]"#),
+        ]).await;
+        let client = client(&base_url);
+        assert!(client.send_message("abc", &message).await.unwrap());
+        assert_eq!(received.lock().unwrap()[0], json!({
+            "key": "abc",
+            "message": {
+                "subject": "Welcome",
+                "html": "<p>Welcome</p>",
+                "text": "Welcome",
+                "from_email": "noreply@example.com",
+                "from_name": "Acme",
+                "to": [{"email": "ada@example.com", "type": "to"}]
+            }
+        }));
+
+        let error = client.send_message("abc", &message).await.unwrap_err();
+        assert_eq!((error.status, error.message.as_str()), (NanoServiceErrorStatus::BadRequest, "Mailchimp rejected the email: ada@example.com invalid"));
+    }
+
+    #[tokio::test]
+    async fn test_list_templates() {
+        let (base_url, received) = fake_mandrill(vec![
+            (200, r#"[{"name":"Confirmation Email","slug":"confirmation-email","labels":[]}]"#),
+        ]).await;
+        let templates = client(&base_url).list_templates("abc").await.unwrap();
+        assert_eq!(templates, vec!["Confirmation Email", "confirmation-email"]);
+        assert_eq!(*received.lock().unwrap(), vec![json!({"key": "abc"})]);
+    }
+}
@@ -12,6 +12,7 @@
 //! ## Notes
 //! - `MailchimpDescriptor` handles Mailchimp API interactions.
 //! - `SendTemplate` defines the contract for sending dynamic email templates.
+//! - `ListTemplates` defines the contract for checking which templates exist on the provider.
 //! - `EmailInvite` transactions include creation, filtering, claiming, and deletion.
 
 use crate::mailchimp_helpers::mailchimp_template::Template;
@@ -26,3 +27,8 @@ pub struct MailchimpDescriptor;
 pub trait SendTemplate {
     fn send_template(template: &Template) -> impl Future<Output = Result<bool, NanoServiceError>> + Send;
 }
+
+/// Defines the contract for listing the template names held by the provider.
+pub trait ListTemplates {
+    fn list_templates(api_key: &str) -> impl Future<Output = Result<Vec<String>, NanoServiceError>> + Send;
+}
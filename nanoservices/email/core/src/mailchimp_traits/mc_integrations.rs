@@ -1,37 +1,41 @@
-//! Implements the `SendTemplate` trait for `MailchimpDescriptor`.
+//! Implements the `SendTemplate` and `ListTemplates` traits for `MailchimpDescriptor`.
 //!
 //! # Overview
-//! This file provides the implementation for sending templated emails via Mailchimp.
-//! The `MailchimpDescriptor` handles API interactions, and errors are managed with `NanoServiceError`.
+//! This file provides the implementation for sending templated emails and listing templates via Mailchimp.
+//! The calls are made with `MAILCHIMP_CLIENT`, which handles the timeouts, retries and translating the
+//! provider's errors into `NanoServiceError`. When `EMAIL_TEMPLATES` is `local` an email with a local
+//! template is rendered here and sent as a plain message, see `crate::local_templates`.
 
-use crate::mailchimp_traits::mc_definitions::{MailchimpDescriptor, SendTemplate};
+use crate::mailchimp_traits::mc_definitions::{MailchimpDescriptor, SendTemplate, ListTemplates};
+use crate::mailchimp_traits::mc_client::MAILCHIMP_CLIENT;
+use crate::local_templates::LOCAL_TEMPLATES;
 use crate::mailchimp_helpers::mailchimp_template::Template;
 use dal_tx_impl::impl_transaction;
 use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
-use reqwest::Client;
+use utils::fault_injection::{inject_fault, FaultTarget};
 
 /// Implements the `SendTemplate` trait for `MailchimpDescriptor`.
 /// Sends an email using a Mailchimp template and returns `true` if successful.
+///
+/// A rejected request will be rejected again so it is reported as a bad request, whereas rate
+/// limits and server errors are worth retrying.
 #[impl_transaction(MailchimpDescriptor, SendTemplate, send_template)]
-async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {    
-    let client = Client::new();
-    let response = client
-        .post("https://mandrillapp.com/api/1.0/messages/send-template")
-        .json(template)
-        .send()
-        .await
-        .map_err(|e| NanoServiceError::new(
-            format!("Failed to send HTTP request: {}", e),
-            NanoServiceErrorStatus::Unknown,
-        ))?;
-
-    if response.status() == 200 {
-        Ok(true)
-    } else {
-        Err(NanoServiceError::new(
-            format!("Failed to send email. HTTP Status: {}", response.status()),
-            NanoServiceErrorStatus::Unknown,
-        ))
+async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {
+    inject_fault(FaultTarget::Email).await.map_err(|fault| NanoServiceError::new(
+        fault.to_string(),
+        NanoServiceErrorStatus::Unknown,
+    ))?;
+    if LOCAL_TEMPLATES.renders(&template.template_name) {
+        let message = LOCAL_TEMPLATES.message(template)?;
+        return MAILCHIMP_CLIENT.send_message(&template.api_key, &message).await
     }
+    MAILCHIMP_CLIENT.send_template(template).await
 }
 
+
+/// Implements the `ListTemplates` trait for `MailchimpDescriptor`.
+/// Returns both the name and slug of every template as either can be used when sending.
+#[impl_transaction(MailchimpDescriptor, ListTemplates, list_templates)]
+async fn list_templates(api_key: &str) -> Result<Vec<String>, NanoServiceError> {
+    MAILCHIMP_CLIENT.list_templates(api_key).await
+}
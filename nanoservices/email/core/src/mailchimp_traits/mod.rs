@@ -1,2 +1,3 @@
 pub mod mc_definitions;
-pub mod mc_integrations;
\ No newline at end of file
+pub mod mc_integrations;
+pub mod mc_client;
\ No newline at end of file
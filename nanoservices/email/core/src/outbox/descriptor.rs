@@ -0,0 +1,108 @@
+//! Hands emails off to the outbox instead of sending them inside the request.
+//!
+//! # Overview
+//! `EmailOutbox` implements `SendTemplate` by queueing the template in the `email_outbox` table, so
+//! it can be injected anywhere `MailchimpDescriptor` is. The request only waits on the insert and a
+//! provider outage no longer turns into a failed request, the worker sends the email once the
+//! provider is back.
+use crate::mailchimp_helpers::mailchimp_template::Template;
+use crate::mailchimp_traits::mc_definitions::SendTemplate;
+use dal::email_outbox::tx_definitions::EnqueueEmail;
+use kernel::email_outbox::{NewOutboxEmail, OutboxEmail};
+use kernel::chrono::NaiveDateTime;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use std::future::Future;
+use std::marker::PhantomData;
+
+
+/// Queues templates in the outbox rather than sending them.
+///
+/// # Generics
+/// * `X` - The database handle the outbox is written with
+pub struct EmailOutbox<X: EnqueueEmail> {
+    db_handle: PhantomData<X>
+}
+
+
+/// Builds the outbox record for a template to be sent no earlier than `send_at`.
+///
+/// # Arguments
+/// * `template` - The template to queue
+/// * `send_at` - The earliest time the email may be sent, `None` sends it as soon as possible
+///
+/// # Notes
+/// The API key is stripped before the template is stored, the worker adds it back from config.
+pub fn outbox_email(template: &Template, send_at: Option<NaiveDateTime>) -> Result<NewOutboxEmail, NanoServiceError> {
+    let template = serde_json::to_value(Template { api_key: String::new(), ..template.clone() })
+        .map_err(|e| NanoServiceError::new(
+            format!("Failed to serialize email template: {}", e),
+            NanoServiceErrorStatus::Unknown,
+        ))?;
+    Ok(NewOutboxEmail { template, send_at })
+}
+
+
+/// Queues a template in the outbox to be sent no earlier than `send_at`.
+///
+/// # Arguments
+/// * `template` - The template to queue
+/// * `send_at` - The earliest time the email may be sent, `None` sends it as soon as possible
+pub async fn queue_template<X: EnqueueEmail>(template: &Template, send_at: Option<NaiveDateTime>) -> Result<OutboxEmail, NanoServiceError> {
+    X::enqueue_email(outbox_email(template, send_at)?).await
+}
+
+
+impl<X: EnqueueEmail> SendTemplate for EmailOutbox<X> {
+
+    /// Queues the template to be sent straight away, returning `true` once it is persisted.
+    fn send_template(template: &Template) -> impl Future<Output = Result<bool, NanoServiceError>> + Send {
+        let template = template.clone();
+        async move {
+            queue_template::<X>(&template, None).await?;
+            Ok(true)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailchimp_helpers::mailchimp_template::{MessageContent, ToContent, GlobalMergeVarsContent};
+    use dal_tx_impl::impl_transaction;
+    use kernel::email_outbox::OutboxStatus;
+    use chrono::Utc;
+    use sqlx::types::Json;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, EnqueueEmail, enqueue_email)]
+    async fn enqueue_email(email: NewOutboxEmail) -> Result<OutboxEmail, NanoServiceError> {
+        assert_eq!(email.template["template_name"], "confirmation-email");
+        assert_eq!(email.template["api_key"], "");
+        assert!(email.send_at.is_none());
+        let now = Utc::now().naive_utc();
+        Ok(OutboxEmail {
+            id: 1,
+            template: Json(email.template),
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            date_created: now,
+            date_sent: None,
+            send_at: now,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_send_template_queues_email() {
+        let message = MessageContent::new(
+            vec![ToContent::new("test@example.com".to_string(), "to".to_string())],
+            vec![GlobalMergeVarsContent::new("CONFIRMATION_URL".to_string(), "id".to_string())],
+        );
+        let template = Template::new("key".to_string(), "confirmation-email".to_string(), message);
+        let outcome = EmailOutbox::<MockDbHandle>::send_template(&template).await.unwrap();
+        assert!(outcome);
+    }
+}
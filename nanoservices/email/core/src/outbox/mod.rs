@@ -0,0 +1,3 @@
+pub mod descriptor;
+pub mod worker;
+pub mod schedule;
@@ -0,0 +1,304 @@
+//! Schedules reminder and digest emails for a sensible time in the recipient's timezone.
+//!
+//! # Overview
+//! Emails that are not urgent are queued with a `send_at` inside the send window, 9am to 8pm in
+//! the recipient's timezone by default, so nobody is notified in the middle of the night.
+//! * A reminder is sent straight away if the recipient is inside the window, otherwise when it next opens.
+//! * A digest is sent when the window next opens, so it arrives at the same local time each day.
+//!
+//! The recipient's timezone comes from their profile, falling back to UTC for addresses that are
+//! not users or timezones that cannot be read.
+//!
+//! # Variables
+//! * `EMAIL_SEND_WINDOW_START_HOUR` - The local hour the window opens, defaults to 9
+//! * `EMAIL_SEND_WINDOW_END_HOUR` - The local hour the window closes, defaults to 20
+use crate::mailchimp_helpers::mailchimp_template::Template;
+use crate::outbox::descriptor::queue_template;
+use chrono_tz::Tz;
+use dal::email_outbox::tx_definitions::EnqueueEmail;
+use dal::users::tx_definitions::GetRecipientProfile;
+use kernel::chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+
+
+/// The local hour the window opens when not configured.
+const DEFAULT_WINDOW_START_HOUR: u32 = 9;
+
+/// The local hour the window closes when not configured.
+const DEFAULT_WINDOW_END_HOUR: u32 = 20;
+
+
+/// The hours of the day, in the recipient's timezone, that emails may be sent.
+///
+/// # Fields
+/// * `start_hour` - The hour the window opens.
+/// * `end_hour` - The hour the window closes, emails are not sent from this hour on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SendWindow {
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl Default for SendWindow {
+    fn default() -> Self {
+        SendWindow { start_hour: DEFAULT_WINDOW_START_HOUR, end_hour: DEFAULT_WINDOW_END_HOUR }
+    }
+}
+
+impl SendWindow {
+
+    /// Reads the window from config, falling back to the default if either hour is not set or the
+    /// window would be empty.
+    pub fn from_config<Y: GetConfigVariable>() -> Self {
+        let read_hour = |name: &str| Y::get_config_variable(name.to_string())
+            .ok()
+            .and_then(|value| value.trim().parse::<u32>().ok());
+        let window = SendWindow {
+            start_hour: read_hour("EMAIL_SEND_WINDOW_START_HOUR").unwrap_or(DEFAULT_WINDOW_START_HOUR),
+            end_hour: read_hour("EMAIL_SEND_WINDOW_END_HOUR").unwrap_or(DEFAULT_WINDOW_END_HOUR),
+        };
+        match window.start_hour < window.end_hour && window.end_hour <= 24 {
+            true => window,
+            false => SendWindow::default()
+        }
+    }
+
+    /// The time the window opens on a local date, as a UTC timestamp.
+    ///
+    /// # Notes
+    /// If a daylight saving change skips the opening hour the window opens an hour later.
+    fn opens_on(&self, date: NaiveDate, timezone: Tz) -> NaiveDateTime {
+        let local = date.and_hms_opt(self.start_hour, 0, 0).unwrap();
+        timezone.from_local_datetime(&local)
+            .earliest()
+            .or_else(|| timezone.from_local_datetime(&(local + Duration::hours(1))).earliest())
+            .map(|opens| opens.naive_utc())
+            .unwrap_or(local)
+    }
+
+    /// The next time the window opens after `now`.
+    ///
+    /// # Arguments
+    /// * `now` - The current time
+    /// * `timezone` - The recipient's timezone
+    ///
+    /// # Returns
+    /// * `NaiveDateTime` - The UTC time the window next opens
+    pub fn next_opening(&self, now: DateTime<Utc>, timezone: Tz) -> NaiveDateTime {
+        let today = now.with_timezone(&timezone).date_naive();
+        let opens_today = self.opens_on(today, timezone);
+        match opens_today > now.naive_utc() {
+            true => opens_today,
+            false => self.opens_on(today + Duration::days(1), timezone)
+        }
+    }
+
+    /// The earliest time from `now` that falls inside the window.
+    ///
+    /// # Arguments
+    /// * `now` - The current time
+    /// * `timezone` - The recipient's timezone
+    ///
+    /// # Returns
+    /// * `NaiveDateTime` - `now` if the recipient is inside the window, otherwise the UTC time it next opens
+    pub fn earliest_send(&self, now: DateTime<Utc>, timezone: Tz) -> NaiveDateTime {
+        let local_hour = now.with_timezone(&timezone).hour();
+        match local_hour >= self.start_hour && local_hour < self.end_hour {
+            true => now.naive_utc(),
+            false => self.next_opening(now, timezone)
+        }
+    }
+}
+
+
+/// Looks up the timezone of the template's first recipient.
+///
+/// # Returns
+/// * `Ok(Tz)` - The recipient's timezone, or UTC if it is unknown
+/// * `Err(NanoServiceError)` - If the recipient could not be looked up
+async fn recipient_timezone<X: GetRecipientProfile>(template: &Template) -> Result<Tz, NanoServiceError> {
+    let email = match template.message.to.first() {
+        Some(recipient) => recipient.email.clone(),
+        None => return Ok(Tz::UTC)
+    };
+    Ok(X::get_recipient_profile(email).await?
+        .and_then(|profile| profile.timezone.parse::<Tz>().ok())
+        .unwrap_or(Tz::UTC))
+}
+
+
+/// Queues a reminder to be sent now if the recipient is inside the send window, otherwise when it next opens.
+///
+/// # Arguments
+/// * `template` - The reminder to send
+///
+/// # Returns
+/// * `Ok(NaiveDateTime)` - The UTC time the reminder is scheduled for
+/// * `Err(NanoServiceError)` - If the recipient could not be looked up or the email could not be queued
+pub async fn schedule_reminder_email<X, Y>(template: &Template) -> Result<NaiveDateTime, NanoServiceError>
+where
+    X: EnqueueEmail + GetRecipientProfile,
+    Y: GetConfigVariable
+{
+    let timezone = recipient_timezone::<X>(template).await?;
+    let send_at = SendWindow::from_config::<Y>().earliest_send(Utc::now(), timezone);
+    queue_template::<X>(template, Some(send_at)).await?;
+    Ok(send_at)
+}
+
+
+/// Queues a digest to be sent when the send window next opens for the recipient.
+///
+/// # Arguments
+/// * `template` - The digest to send
+///
+/// # Returns
+/// * `Ok(NaiveDateTime)` - The UTC time the digest is scheduled for
+/// * `Err(NanoServiceError)` - If the recipient could not be looked up or the email could not be queued
+pub async fn schedule_digest_email<X, Y>(template: &Template) -> Result<NaiveDateTime, NanoServiceError>
+where
+    X: EnqueueEmail + GetRecipientProfile,
+    Y: GetConfigVariable
+{
+    let timezone = recipient_timezone::<X>(template).await?;
+    let send_at = SendWindow::from_config::<Y>().next_opening(Utc::now(), timezone);
+    queue_template::<X>(template, Some(send_at)).await?;
+    Ok(send_at)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailchimp_helpers::mailchimp_template::{MessageContent, ToContent};
+    use dal_tx_impl::impl_transaction;
+    use kernel::email_outbox::{NewOutboxEmail, OutboxEmail, OutboxStatus};
+    use kernel::users::RecipientProfile;
+    use sqlx::types::Json;
+    use utils::errors::NanoServiceErrorStatus;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap().and_utc()
+    }
+
+    fn naive(value: &str) -> NaiveDateTime {
+        at(value).naive_utc()
+    }
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            Err(NanoServiceError::new(variable, NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    struct BadWindowConfig;
+
+    impl GetConfigVariable for BadWindowConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "EMAIL_SEND_WINDOW_START_HOUR" => Ok("22".to_string()),
+                _ => Ok("6".to_string())
+            }
+        }
+    }
+
+    #[test]
+    fn test_window_from_config() {
+        assert_eq!(SendWindow::from_config::<FakeConfig>(), SendWindow::default());
+        assert_eq!(SendWindow::from_config::<BadWindowConfig>(), SendWindow::default());
+    }
+
+    #[test]
+    fn test_earliest_send() {
+        let window = SendWindow::default();
+        let new_york: Tz = "America/New_York".parse().unwrap();
+
+        // 15:00 UTC is 10:00 in New York, inside the window
+        assert_eq!(window.earliest_send(at("2025-01-15 15:00"), new_york), naive("2025-01-15 15:00"));
+        // 05:00 UTC is midnight in New York, wait for 9am local
+        assert_eq!(window.earliest_send(at("2025-01-15 05:00"), new_york), naive("2025-01-15 14:00"));
+        // 03:00 UTC is 22:00 the day before in New York, wait for 9am local
+        assert_eq!(window.earliest_send(at("2025-01-16 03:00"), new_york), naive("2025-01-16 14:00"));
+        // 23:00 UTC is 8am the next day in Tokyo
+        assert_eq!(window.earliest_send(at("2025-01-15 23:00"), Tz::Asia__Tokyo), naive("2025-01-16 00:00"));
+    }
+
+    #[test]
+    fn test_next_opening() {
+        let window = SendWindow::default();
+        let london: Tz = "Europe/London".parse().unwrap();
+
+        // British Summer Time, 9am local is 8am UTC
+        assert_eq!(window.next_opening(at("2025-06-10 07:00"), london), naive("2025-06-10 08:00"));
+        assert_eq!(window.next_opening(at("2025-06-10 08:00"), london), naive("2025-06-11 08:00"));
+        // the clocks go back overnight, so the next opening is an hour later in UTC
+        assert_eq!(window.next_opening(at("2025-10-25 12:00"), london), naive("2025-10-26 09:00"));
+    }
+
+    #[test]
+    fn test_opening_skipped_by_daylight_saving() {
+        let window = SendWindow { start_hour: 2, end_hour: 20 };
+        let new_york: Tz = "America/New_York".parse().unwrap();
+
+        // 2am does not exist on 9 March 2025 in New York, the window opens at 3am EDT
+        assert_eq!(window.next_opening(at("2025-03-09 05:00"), new_york), naive("2025-03-09 07:00"));
+    }
+
+    struct MockDbHandle;
+
+    /// `tokyo@example.com` is in Tokyo, `broken@example.com` has an unreadable timezone.
+    #[impl_transaction(MockDbHandle, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        let timezone = match email.as_str() {
+            "tokyo@example.com" => "Asia/Tokyo",
+            "broken@example.com" => "Mars/Olympus_Mons",
+            _ => return Ok(None)
+        };
+        Ok(Some(RecipientProfile {
+            first_name: "Ada".to_string(),
+            username: "ada".to_string(),
+            locale: "en".to_string(),
+            timezone: timezone.to_string(),
+        }))
+    }
+
+    #[impl_transaction(MockDbHandle, EnqueueEmail, enqueue_email)]
+    async fn enqueue_email(email: NewOutboxEmail) -> Result<OutboxEmail, NanoServiceError> {
+        let now = Utc::now().naive_utc();
+        Ok(OutboxEmail {
+            id: 1,
+            template: Json(email.template),
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: email.send_at.unwrap(),
+            last_error: None,
+            date_created: now,
+            date_sent: None,
+            send_at: email.send_at.unwrap(),
+        })
+    }
+
+    fn template(email: &str) -> Template {
+        let message = MessageContent::new(vec![ToContent::new(email.to_string(), "to".to_string())], Vec::new());
+        Template::new(String::new(), "digest".to_string(), message)
+    }
+
+    #[tokio::test]
+    async fn test_schedule_emails() {
+        let window = SendWindow::default();
+        let now = Utc::now();
+
+        let send_at = schedule_digest_email::<MockDbHandle, FakeConfig>(&template("tokyo@example.com")).await.unwrap();
+        assert_eq!(send_at, window.next_opening(now, Tz::Asia__Tokyo));
+
+        let send_at = schedule_digest_email::<MockDbHandle, FakeConfig>(&template("broken@example.com")).await.unwrap();
+        assert_eq!(send_at, window.next_opening(now, Tz::UTC));
+
+        let send_at = schedule_reminder_email::<MockDbHandle, FakeConfig>(&template("stranger@example.com")).await.unwrap();
+        assert!(send_at >= now.naive_utc());
+        assert!(send_at <= window.next_opening(Utc::now(), Tz::UTC));
+    }
+}
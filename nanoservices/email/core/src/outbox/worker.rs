@@ -0,0 +1,289 @@
+//! Sends the emails queued in the outbox, retrying failures with exponential backoff.
+//!
+//! # Overview
+//! The worker polls the outbox, claims the emails that are due and sends each one with the
+//! provider. An email scheduled with a `send_at` in the future is not claimed until that time. A failed send is retried after `backoff(attempts)` until `MAX_ATTEMPTS` is reached.
+//! Emails the provider rejects outright, or that can no longer be read, are marked as failed
+//! straight away as retrying them cannot succeed. The organization's branding is read once per
+//! pass and added to every email as it is sent, so a branding change applies to emails already queued.
+//!
+//! # Variables
+//! * `EMAIL_OUTBOX_POLL_SECONDS` - How often the outbox is polled, defaults to 10
+//! * `EMAIL_OUTBOX_BATCH_SIZE` - The most emails claimed per poll, defaults to 20
+use crate::mailchimp_helpers::mailchimp_template::Template;
+use crate::mailchimp_traits::mc_definitions::SendTemplate;
+use dal::email_outbox::tx_definitions::{ClaimDueEmails, MarkEmailSent, RecordEmailFailure};
+use dal::branding::tx_definitions::GetOrgBranding;
+use kernel::email_outbox::OutboxEmail;
+use kernel::branding::OrgBranding;
+use kernel::chrono::{Duration, Utc};
+use utils::config::GetConfigVariable;
+use utils::log_limited;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use serde::Serialize;
+
+
+/// The number of attempts after which an email is marked as failed.
+pub const MAX_ATTEMPTS: i32 = 8;
+
+/// The delay before the first retry, doubled for every attempt after.
+const BASE_BACKOFF_SECONDS: i64 = 30;
+
+/// The longest delay between two attempts.
+const MAX_BACKOFF_SECONDS: i64 = 60 * 60;
+
+/// The poll interval used when not configured.
+const DEFAULT_POLL_SECONDS: u64 = 10;
+
+/// The batch size used when not configured.
+const DEFAULT_BATCH_SIZE: i64 = 20;
+
+
+/// The counts from a single pass over the outbox.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct OutboxRunSummary {
+    pub sent: usize,
+    pub retrying: usize,
+    pub failed: usize,
+}
+
+
+/// The delay before the next attempt once `attempts` attempts have been made.
+///
+/// # Notes
+/// 1 attempt waits 30 seconds, 2 waits a minute, 3 waits two minutes and so on, capped at an hour.
+pub fn backoff(attempts: i32) -> Duration {
+    let exponent = (attempts.max(1) - 1).min(16) as u32;
+    Duration::seconds((BASE_BACKOFF_SECONDS << exponent).min(MAX_BACKOFF_SECONDS))
+}
+
+
+/// What to do with an email after an attempt.
+enum AttemptOutcome {
+    Sent,
+    Retry(String),
+    Fail(String),
+}
+
+
+/// Sends a single email, adding the API key back as it is not stored in the outbox.
+async fn attempt_send<Y: SendTemplate>(email: &OutboxEmail, api_key: &str, branding: &OrgBranding) -> AttemptOutcome {
+    let mut template: Template = match serde_json::from_value(email.template.0.clone()) {
+        Ok(template) => template,
+        Err(e) => return AttemptOutcome::Fail(format!("Failed to read queued template: {}", e))
+    };
+    template.api_key = api_key.to_string();
+    template.message = template.message.with_branding(branding);
+    match Y::send_template(&template).await {
+        Ok(true) => AttemptOutcome::Sent,
+        Ok(false) => AttemptOutcome::Fail("Provider did not accept the email".to_string()),
+        Err(e) if e.status == NanoServiceErrorStatus::BadRequest => AttemptOutcome::Fail(e.message),
+        Err(e) => AttemptOutcome::Retry(e.message)
+    }
+}
+
+
+/// Sends the emails that are due, recording the outcome of each.
+///
+/// # Arguments
+/// * `batch_size` - The most emails to claim in this pass
+///
+/// # Returns
+/// * `Ok(OutboxRunSummary)` - The counts for the pass
+/// * `Err(NanoServiceError)` - If the config is missing, the branding could not be read or the outbox could not be read or updated
+pub async fn process_outbox<X, Y, Z>(batch_size: i64) -> Result<OutboxRunSummary, NanoServiceError>
+where
+    X: ClaimDueEmails + MarkEmailSent + RecordEmailFailure + GetOrgBranding,
+    Y: SendTemplate,
+    Z: GetConfigVariable,
+{
+    let emails = X::claim_due_emails(batch_size).await?;
+    if emails.is_empty() {
+        return Ok(OutboxRunSummary::default())
+    }
+    let api_key = Z::get_config_variable("MAILCHIMP_API_KEY".to_string())?;
+    let branding = X::get_org_branding().await?;
+    let mut summary = OutboxRunSummary::default();
+    for email in emails {
+        let attempts = email.attempts + 1;
+        match attempt_send::<Y>(&email, &api_key, &branding).await {
+            AttemptOutcome::Sent => {
+                X::mark_email_sent(email.id).await?;
+                summary.sent += 1;
+            },
+            AttemptOutcome::Retry(error) if attempts < MAX_ATTEMPTS => {
+                let next_attempt_at = Utc::now().naive_utc() + backoff(attempts);
+                X::record_email_failure(email.id, error, Some(next_attempt_at)).await?;
+                summary.retrying += 1;
+            },
+            AttemptOutcome::Retry(error) | AttemptOutcome::Fail(error) => {
+                println!("email {} permanently failed after {} attempts: {}", email.id, attempts, error);
+                X::record_email_failure(email.id, error, None).await?;
+                summary.failed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+
+/// Reads a positive number from config, falling back to the default if it is not set or invalid.
+fn read_setting<Z: GetConfigVariable>(name: &str, default: u64) -> u64 {
+    Z::get_config_variable(name.to_string())
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(default)
+}
+
+
+/// Polls the outbox in the background for the life of the runtime.
+///
+/// # Notes
+/// An error in a pass is logged and the worker carries on, the unsent emails stay in the outbox.
+pub fn spawn_outbox_worker<X, Y, Z>()
+where
+    X: ClaimDueEmails + MarkEmailSent + RecordEmailFailure + GetOrgBranding + 'static,
+    Y: SendTemplate + 'static,
+    Z: GetConfigVariable + 'static,
+{
+    let poll_interval = std::time::Duration::from_secs(read_setting::<Z>("EMAIL_OUTBOX_POLL_SECONDS", DEFAULT_POLL_SECONDS));
+    let batch_size = read_setting::<Z>("EMAIL_OUTBOX_BATCH_SIZE", DEFAULT_BATCH_SIZE as u64) as i64;
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = process_outbox::<X, Y, Z>(batch_size).await {
+                log_limited!("email", "email outbox pass failed: {}", e.message);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailchimp_helpers::mailchimp_template::{MessageContent, ToContent};
+    use dal_tx_impl::impl_transaction;
+    use kernel::email_outbox::OutboxStatus;
+    use kernel::chrono::NaiveDateTime;
+    use sqlx::types::Json;
+    use std::sync::{LazyLock, Mutex};
+
+    /// The outcome recorded for an email as `(id, error, next_attempt_at)`, `None` error means sent.
+    type Recorded = (i32, Option<String>, Option<NaiveDateTime>);
+
+    static RECORDED: LazyLock<Mutex<Vec<Recorded>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "MAILCHIMP_API_KEY" => Ok("real-key".to_string()),
+                _ => Ok("".to_string())
+            }
+        }
+    }
+
+    fn queued(id: i32, name: &str, attempts: i32) -> OutboxEmail {
+        let message = MessageContent::new(vec![ToContent::new("test@example.com".to_string(), "to".to_string())], Vec::new());
+        let template = Template::new(String::new(), name.to_string(), message);
+        let now = Utc::now().naive_utc();
+        OutboxEmail {
+            id,
+            template: Json(serde_json::to_value(template).unwrap()),
+            status: OutboxStatus::Pending,
+            attempts,
+            next_attempt_at: now,
+            last_error: None,
+            date_created: now,
+            date_sent: None,
+            send_at: now,
+        }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, ClaimDueEmails, claim_due_emails)]
+    async fn claim_due_emails(limit: i64) -> Result<Vec<OutboxEmail>, NanoServiceError> {
+        assert_eq!(limit, 10);
+        let mut unreadable = queued(5, "unreadable", 0);
+        unreadable.template = Json(serde_json::json!({"unexpected": true}));
+        Ok(vec![
+            queued(1, "sends", 0),
+            queued(2, "outage", 0),
+            queued(3, "outage", MAX_ATTEMPTS - 1),
+            queued(4, "rejected", 0),
+            unreadable,
+        ])
+    }
+
+    #[impl_transaction(MockDbHandle, GetOrgBranding, get_org_branding)]
+    async fn get_org_branding() -> Result<OrgBranding, NanoServiceError> {
+        Ok(OrgBranding {
+            product_name: "Acme Tasks".to_string(),
+            logo_url: Some("https://cdn.example.com/logo.png".to_string()),
+            accent_color: "#2563eb".to_string(),
+            date_updated: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, MarkEmailSent, mark_email_sent)]
+    async fn mark_email_sent(id: i32) -> Result<bool, NanoServiceError> {
+        RECORDED.lock().unwrap().push((id, None, None));
+        Ok(true)
+    }
+
+    #[impl_transaction(MockDbHandle, RecordEmailFailure, record_email_failure)]
+    async fn record_email_failure(id: i32, error: String, next_attempt_at: Option<NaiveDateTime>) -> Result<bool, NanoServiceError> {
+        RECORDED.lock().unwrap().push((id, Some(error), next_attempt_at));
+        Ok(true)
+    }
+
+    struct MockMailchimp;
+
+    #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+    async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {
+        assert_eq!(template.api_key, "real-key");
+        assert!(template.message.global_merge_vars.iter()
+            .any(|var| var.name == "PRODUCT_NAME" && var.content == "Acme Tasks"));
+        match template.template_name.as_str() {
+            "sends" => Ok(true),
+            "rejected" => Err(NanoServiceError::new("HTTP Status: 400".to_string(), NanoServiceErrorStatus::BadRequest)),
+            _ => Err(NanoServiceError::new("HTTP Status: 503".to_string(), NanoServiceErrorStatus::Unknown))
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::seconds(30));
+        assert_eq!(backoff(2), Duration::seconds(60));
+        assert_eq!(backoff(4), Duration::seconds(240));
+        assert_eq!(backoff(20), Duration::seconds(MAX_BACKOFF_SECONDS));
+        assert_eq!(backoff(i32::MAX), Duration::seconds(MAX_BACKOFF_SECONDS));
+    }
+
+    #[tokio::test]
+    async fn test_process_outbox() {
+        let started = Utc::now().naive_utc();
+        let summary = process_outbox::<MockDbHandle, MockMailchimp, FakeConfig>(10).await.unwrap();
+        assert_eq!(summary, OutboxRunSummary { sent: 1, retrying: 1, failed: 3 });
+
+        let recorded = RECORDED.lock().unwrap();
+        assert_eq!(recorded[0], (1, None, None));
+
+        // a transient failure is retried after the backoff
+        let (id, error, next_attempt_at) = &recorded[1];
+        assert_eq!(*id, 2);
+        assert_eq!(error.as_deref(), Some("HTTP Status: 503"));
+        assert!(next_attempt_at.unwrap() >= started + backoff(1));
+
+        // out of attempts, rejected and unreadable emails are not retried
+        assert_eq!(recorded[2].0, 3);
+        assert!(recorded[2].2.is_none());
+        assert_eq!(recorded[3], (4, Some("HTTP Status: 400".to_string()), None));
+        assert_eq!(recorded[4].0, 5);
+        assert!(recorded[4].2.is_none());
+    }
+}
@@ -0,0 +1,124 @@
+//! Core logic for removing a file attached to a to-do item.
+//!
+//! # Notes
+//! The file is deleted from the object store before its row, so a failure part way leaves a row
+//! without a file, which deleting again clears, rather than a file nothing points to.
+use dal::attachments::tx_definitions::{DeleteAttachment, GetAttachment};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use kernel::attachments::Attachment;
+use kernel::object_store::DeleteObject;
+use kernel::users::UserRole;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use crate::api::attachments::{attachment_not_found, get_accessible_to_do_item};
+
+
+/// Removes a file attached to a to-do item.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item.
+/// - `id`: The ID of the attachment.
+/// - `user_id`: The ID of the user removing the file.
+/// - `role`: The role of the user removing the file.
+///
+/// # Returns
+/// - `Ok(Attachment)`: The removed attachment.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such item or attachment, `Forbidden` if the
+///   user neither attached the file nor assigned the item, and is not an admin.
+pub async fn delete_attachment<X, Y, O>(todo_id: i32, id: i32, user_id: i32, role: &UserRole)
+-> Result<Attachment, NanoServiceError>
+where
+    X: GetToDoItem + GetAttachment + DeleteAttachment,
+    Y: GetConfigVariable,
+    O: DeleteObject
+{
+    let todo = get_accessible_to_do_item::<X>(todo_id, user_id, role).await?;
+    let attachment = X::get_attachment(todo_id, id).await?.ok_or_else(|| attachment_not_found(id))?;
+    let is_admin = matches!(role, UserRole::SuperAdmin | UserRole::Admin);
+    if attachment.uploaded_by != user_id && todo.assigned_by != user_id && !is_admin {
+        return Err(NanoServiceError::new(
+            "Only the user who attached the file, the user who assigned the to-do item or an admin can remove it".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    O::delete_object::<Y>(&attachment.storage_key).await?;
+    X::delete_attachment(todo_id, id).await?.ok_or_else(|| attachment_not_found(id))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use kernel::object_store::StoreObject;
+    use kernel::object_store::engine_mock::{MemoryObjectStoreMock, STORED_OBJECTS};
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use test_support::FakeConfig;
+
+    /// Item 1 is assigned by user 2 to user 3, its attachment 5 was attached by user 3.
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(Todo {
+            id: todo_id,
+            name: "write report".to_string(),
+            due_date: None,
+            assigned_by: 2,
+            assigned_to: 3,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        })
+    }
+
+    fn attachment(todo_id: i32, id: i32) -> Option<Attachment> {
+        (todo_id == 1 && id == 5).then(|| Attachment {
+            id,
+            todo_id,
+            uploaded_by: 3,
+            file_name: "report.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            size_bytes: 4,
+            storage_key: "attachments/1/delete-test".to_string(),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, GetAttachment, get_attachment)]
+    async fn get_attachment(todo_id: i32, id: i32) -> Result<Option<Attachment>, NanoServiceError> {
+        Ok(attachment(todo_id, id))
+    }
+
+    #[impl_transaction(MockDbHandle, DeleteAttachment, delete_attachment)]
+    async fn delete_attachment(todo_id: i32, id: i32) -> Result<Option<Attachment>, NanoServiceError> {
+        Ok(attachment(todo_id, id))
+    }
+
+    #[tokio::test]
+    async fn test_delete_attachment() {
+        MemoryObjectStoreMock::put_object::<FakeConfig>("attachments/1/delete-test", b"%PDF".to_vec(), "application/pdf").await.unwrap();
+        let removed = delete_attachment::<MockDbHandle, FakeConfig, MemoryObjectStoreMock>(1, 5, 3, &UserRole::Worker).await.unwrap();
+        assert_eq!(removed.id, 5);
+        assert!(!STORED_OBJECTS.lock().unwrap().contains_key("attachments/1/delete-test"));
+
+        let missing = delete_attachment::<MockDbHandle, FakeConfig, MemoryObjectStoreMock>(1, 6, 3, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(missing.status, NanoServiceErrorStatus::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_only_uploader_assigner_or_admin_can_delete() {
+        // user 4 cannot see the item at all
+        let outsider = delete_attachment::<MockDbHandle, FakeConfig, MemoryObjectStoreMock>(1, 5, 4, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(outsider.status, NanoServiceErrorStatus::Forbidden);
+        assert!(delete_attachment::<MockDbHandle, FakeConfig, MemoryObjectStoreMock>(1, 5, 2, &UserRole::Worker).await.is_ok());
+        assert!(delete_attachment::<MockDbHandle, FakeConfig, MemoryObjectStoreMock>(1, 5, 8, &UserRole::Admin).await.is_ok());
+    }
+}
@@ -0,0 +1,132 @@
+//! Core logic for listing and downloading the files attached to a to-do item.
+use dal::attachments::tx_definitions::{GetAttachment, ListAttachments};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use kernel::attachments::Attachment;
+use kernel::object_store::{GetObject, ObjectStream};
+use kernel::users::UserRole;
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::api::attachments::{attachment_not_found, get_accessible_to_do_item};
+
+
+/// Lists the files attached to a to-do item, the oldest first.
+///
+/// # Returns
+/// - `Ok(Vec<Attachment>)`: The attachments.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such item, `Forbidden` if the user cannot
+///   use its attachments.
+pub async fn list_attachments<X>(todo_id: i32, user_id: i32, role: &UserRole) -> Result<Vec<Attachment>, NanoServiceError>
+where
+    X: GetToDoItem + ListAttachments
+{
+    get_accessible_to_do_item::<X>(todo_id, user_id, role).await?;
+    X::list_attachments(todo_id).await
+}
+
+
+/// Opens a file attached to a to-do item to be streamed to the user.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item.
+/// - `id`: The ID of the attachment.
+/// - `user_id`: The ID of the user downloading the file.
+/// - `role`: The role of the user downloading the file.
+///
+/// # Returns
+/// - `Ok((Attachment, ObjectStream))`: The attachment and the bytes of its file, read from the
+///   object store as they are sent.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such item or attachment, `Forbidden` if the
+///   user cannot use its attachments.
+pub async fn download_attachment<X, Y, O>(todo_id: i32, id: i32, user_id: i32, role: &UserRole)
+-> Result<(Attachment, ObjectStream), NanoServiceError>
+where
+    X: GetToDoItem + GetAttachment,
+    Y: GetConfigVariable,
+    O: GetObject
+{
+    get_accessible_to_do_item::<X>(todo_id, user_id, role).await?;
+    let attachment = X::get_attachment(todo_id, id).await?.ok_or_else(|| attachment_not_found(id))?;
+    let body = O::stream_object::<Y>(&attachment.storage_key).await?;
+    Ok((attachment, body))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use kernel::object_store::StoreObject;
+    use kernel::object_store::engine_mock::MemoryObjectStoreMock;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use test_support::FakeConfig;
+    use std::future::poll_fn;
+    use utils::errors::NanoServiceErrorStatus;
+
+    /// Item 1 is assigned by user 2 to user 3 and has attachment 5.
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(Todo {
+            id: todo_id,
+            name: "write report".to_string(),
+            due_date: None,
+            assigned_by: 2,
+            assigned_to: 3,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        })
+    }
+
+    fn attachment(todo_id: i32, id: i32) -> Option<Attachment> {
+        (todo_id == 1 && id == 5).then(|| Attachment {
+            id,
+            todo_id,
+            uploaded_by: 3,
+            file_name: "report.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            size_bytes: 4,
+            storage_key: "attachments/1/download-test".to_string(),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, ListAttachments, list_attachments)]
+    async fn list_attachments(todo_id: i32) -> Result<Vec<Attachment>, NanoServiceError> {
+        Ok(attachment(todo_id, 5).into_iter().collect())
+    }
+
+    #[impl_transaction(MockDbHandle, GetAttachment, get_attachment)]
+    async fn get_attachment(todo_id: i32, id: i32) -> Result<Option<Attachment>, NanoServiceError> {
+        Ok(attachment(todo_id, id))
+    }
+
+    #[tokio::test]
+    async fn test_list_attachments() {
+        assert_eq!(list_attachments::<MockDbHandle>(1, 2, &UserRole::Worker).await.unwrap().len(), 1);
+        let not_theirs = list_attachments::<MockDbHandle>(1, 4, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(not_theirs.status, NanoServiceErrorStatus::Forbidden);
+    }
+
+    #[tokio::test]
+    async fn test_download_attachment() {
+        MemoryObjectStoreMock::put_object::<FakeConfig>("attachments/1/download-test", b"%PDF".to_vec(), "application/pdf").await.unwrap();
+        let (attachment, mut body) = download_attachment::<MockDbHandle, FakeConfig, MemoryObjectStoreMock>(1, 5, 3, &UserRole::Worker).await.unwrap();
+        assert_eq!(attachment.file_name, "report.pdf");
+        let chunk = poll_fn(|cx| body.as_mut().poll_next(cx)).await.unwrap().unwrap();
+        assert_eq!(chunk.as_ref(), b"%PDF");
+
+        let missing = download_attachment::<MockDbHandle, FakeConfig, MemoryObjectStoreMock>(1, 6, 3, &UserRole::Worker).await.err().unwrap();
+        assert_eq!(missing.status, NanoServiceErrorStatus::NotFound);
+        let not_theirs = download_attachment::<MockDbHandle, FakeConfig, MemoryObjectStoreMock>(1, 5, 4, &UserRole::Worker).await.err().unwrap();
+        assert_eq!(not_theirs.status, NanoServiceErrorStatus::Forbidden);
+    }
+}
@@ -0,0 +1,32 @@
+pub mod upload;
+pub mod download;
+pub mod delete;
+
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use kernel::attachments::can_access_attachments;
+use kernel::to_do_items::Todo;
+use kernel::users::UserRole;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// Reads a to-do item, refusing a user who cannot see its attachments.
+///
+/// # Returns
+/// - `Ok(Todo)`: The to-do item.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such item, `Forbidden` if the user neither
+///   assigned it nor is assigned it, and is not an admin.
+pub async fn get_accessible_to_do_item<X: GetToDoItem>(todo_id: i32, user_id: i32, role: &UserRole) -> Result<Todo, NanoServiceError> {
+    let todo = X::get_to_do_item(todo_id).await?;
+    if !can_access_attachments(&todo, user_id, role) {
+        return Err(NanoServiceError::new(
+            "Only the users the to-do item is assigned by and to, or an admin, can use its attachments".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    Ok(todo)
+}
+
+
+fn attachment_not_found(id: i32) -> NanoServiceError {
+    NanoServiceError::new(format!("Attachment {} not found", id), NanoServiceErrorStatus::NotFound)
+}
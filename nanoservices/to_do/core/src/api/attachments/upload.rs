@@ -0,0 +1,189 @@
+//! Core logic for attaching a file to a to-do item.
+//!
+//! # Overview
+//! The file is checked against the `AttachmentLimits`, scanned for viruses, and put in the object
+//! store under a key of its own before the attachment is recorded. If the attachment cannot be
+//! recorded the file is deleted again, so the store is not left with files nothing points to.
+use dal::attachments::tx_definitions::CreateAttachment;
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use kernel::attachments::{attachment_file_name, attachment_storage_key, Attachment, AttachmentLimits, NewAttachment};
+use kernel::content_scan::{reject_infected, ScanContent};
+use kernel::object_store::{DeleteObject, StoreObject};
+use kernel::users::UserRole;
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use crate::api::attachments::get_accessible_to_do_item;
+
+
+/// A file uploaded to be attached.
+///
+/// # Fields
+/// - `file_name`: The name the client gave the file.
+/// - `content_type`: The content type the client gave the file.
+/// - `body`: The content of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentUpload {
+    pub file_name: String,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+
+/// Attaches a file to a to-do item.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item.
+/// - `user_id`: The ID of the user attaching the file.
+/// - `role`: The role of the user attaching the file.
+/// - `upload`: The file.
+///
+/// # Returns
+/// - `Ok(Attachment)`: The attachment.
+/// - `Err(NanoServiceError)`: `PayloadTooLarge` or `UnsupportedMediaType` if the file is over the
+///   limits, `NotFound` if there is no such item, `Forbidden` if the user cannot use its
+///   attachments, `UnprocessableEntity` if the file is infected.
+pub async fn upload_attachment<X, Y, O, S>(todo_id: i32, user_id: i32, role: &UserRole, upload: AttachmentUpload)
+-> Result<Attachment, NanoServiceError>
+where
+    X: GetToDoItem + CreateAttachment,
+    Y: GetConfigVariable,
+    O: StoreObject + DeleteObject,
+    S: ScanContent
+{
+    AttachmentLimits::from_config::<Y>().check(&upload.content_type, upload.body.len())?;
+    get_accessible_to_do_item::<X>(todo_id, user_id, role).await?;
+    reject_infected::<S, Y>(&upload.body).await?;
+
+    let attachment = NewAttachment {
+        todo_id,
+        uploaded_by: user_id,
+        file_name: attachment_file_name(&upload.file_name),
+        content_type: upload.content_type,
+        size_bytes: upload.body.len() as i64,
+        storage_key: attachment_storage_key(todo_id),
+    };
+    O::put_object::<Y>(&attachment.storage_key, upload.body, &attachment.content_type).await?;
+    let storage_key = attachment.storage_key.clone();
+    match X::create_attachment(attachment).await {
+        Ok(attachment) => Ok(attachment),
+        Err(e) => {
+            // the error recording the attachment is the one worth reporting
+            let _ = O::delete_object::<Y>(&storage_key).await;
+            Err(e)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use kernel::content_scan::engine_mock::{InfectedScanMock, PassScanMock};
+    use kernel::object_store::engine_mock::{FailingObjectStoreMock, MemoryObjectStoreMock, STORED_OBJECTS};
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use test_support::fake_config;
+    use utils::errors::NanoServiceErrorStatus;
+
+    fake_config!(UploadConfig, {
+        "ATTACHMENT_MAX_BYTES" => "16",
+        "ATTACHMENT_ALLOWED_TYPES" => "",
+    });
+
+    /// Item 1 is assigned by user 2 to user 3.
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        if todo_id != 1 {
+            return Err(NanoServiceError::new("not found".to_string(), NanoServiceErrorStatus::NotFound))
+        }
+        Ok(Todo {
+            id: 1,
+            name: "write report".to_string(),
+            due_date: None,
+            assigned_by: 2,
+            assigned_to: 3,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        })
+    }
+
+    /// Refuses files named `unrecorded.txt`.
+    #[impl_transaction(MockDbHandle, CreateAttachment, create_attachment)]
+    async fn create_attachment(attachment: NewAttachment) -> Result<Attachment, NanoServiceError> {
+        if attachment.file_name == "unrecorded.txt" {
+            return Err(NanoServiceError::new("failed to record".to_string(), NanoServiceErrorStatus::Unknown))
+        }
+        Ok(Attachment {
+            id: 5,
+            todo_id: attachment.todo_id,
+            uploaded_by: attachment.uploaded_by,
+            file_name: attachment.file_name,
+            content_type: attachment.content_type,
+            size_bytes: attachment.size_bytes,
+            storage_key: attachment.storage_key,
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    fn upload(file_name: &str, content_type: &str, body: &[u8]) -> AttachmentUpload {
+        AttachmentUpload { file_name: file_name.to_string(), content_type: content_type.to_string(), body: body.to_vec() }
+    }
+
+    #[tokio::test]
+    async fn test_upload_attachment() {
+        let attachment = upload_attachment::<MockDbHandle, UploadConfig, MemoryObjectStoreMock, PassScanMock>(
+            1, 3, &UserRole::Worker, upload("notes/today.txt", "text/plain", b"buy milk")
+        ).await.unwrap();
+        assert_eq!((attachment.file_name.as_str(), attachment.size_bytes, attachment.uploaded_by), ("today.txt", 8, 3));
+        assert!(attachment.storage_key.starts_with("attachments/1/"));
+        assert_eq!(STORED_OBJECTS.lock().unwrap().get(&attachment.storage_key).unwrap(), b"buy milk");
+
+        // an admin can attach to any item
+        assert!(upload_attachment::<MockDbHandle, UploadConfig, MemoryObjectStoreMock, PassScanMock>(
+            1, 9, &UserRole::Admin, upload("admin.txt", "text/plain", b"checked")
+        ).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_upload_refusals() {
+        let refused = |file_name: &'static str, content_type: &'static str, body: &'static [u8], user_id: i32| {
+            upload_attachment::<MockDbHandle, UploadConfig, MemoryObjectStoreMock, PassScanMock>(
+                1, user_id, &UserRole::Worker, upload(file_name, content_type, body)
+            )
+        };
+        let too_large = refused("large.txt", "text/plain", b"more than sixteen bytes", 3).await.unwrap_err();
+        assert_eq!(too_large.status, NanoServiceErrorStatus::PayloadTooLarge);
+        let wrong_type = refused("page.html", "text/html", b"<html>", 3).await.unwrap_err();
+        assert_eq!(wrong_type.status, NanoServiceErrorStatus::UnsupportedMediaType);
+        let not_theirs = refused("notes.txt", "text/plain", b"buy milk", 4).await.unwrap_err();
+        assert_eq!(not_theirs.status, NanoServiceErrorStatus::Forbidden);
+
+        let infected = upload_attachment::<MockDbHandle, UploadConfig, MemoryObjectStoreMock, InfectedScanMock>(
+            1, 3, &UserRole::Worker, upload("virus.txt", "text/plain", b"X5O!P%@AP")
+        ).await.unwrap_err();
+        assert_eq!(infected.status, NanoServiceErrorStatus::UnprocessableEntity);
+        let store_down = upload_attachment::<MockDbHandle, UploadConfig, FailingObjectStoreMock, PassScanMock>(
+            1, 3, &UserRole::Worker, upload("notes.txt", "text/plain", b"buy milk")
+        ).await.unwrap_err();
+        assert_eq!(store_down.status, NanoServiceErrorStatus::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_unrecorded_upload_is_deleted() {
+        let error = upload_attachment::<MockDbHandle, UploadConfig, MemoryObjectStoreMock, PassScanMock>(
+            1, 3, &UserRole::Worker, upload("unrecorded.txt", "text/plain", b"orphan")
+        ).await.unwrap_err();
+        assert_eq!(error.message, "failed to record");
+        assert!(!STORED_OBJECTS.lock().unwrap().values().any(|body| body == b"orphan"));
+    }
+}
@@ -0,0 +1,482 @@
+//! Core logic for limiting the number of open to-do items assigned to a user.
+//!
+//! # Overview
+//! `TODO_MAX_OPEN_PER_USER` caps how many unfinished to-do items a user can be assigned. Assigning
+//! an item to a user at the cap is refused unless the admin sets the override flag, in which case
+//! the item is assigned anyway and the override is written to the audit log. The assignee is
+//! queued an assignment notification unless they assigned the item to themselves. Both are
+//! recorded in the history of the item.
+//!
+//! The description of a new item is moderated before anything else, see `crate::api::moderation::screen`.
+//! A new item is then refused with `UpgradeRequired` if the organization has as many to-do items as
+//! its plan allows.
+//!
+//! # Variables
+//! * `TODO_MAX_OPEN_PER_USER` - The cap, unset, invalid or `0` means there is no cap
+use serde::Serialize;
+use serde_json::json;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use dal::to_do_items::tx_definitions::{CreateToDoItem, CountOpenToDoItemsForUser, CountToDoItems, GetToDoItem, ReAssignToDoItem};
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use dal::plans::tx_definitions::GetOrgPlan;
+use dal::notifications::tx_definitions::QueueNotification;
+use dal::moderation::tx_definitions::RecordModerationDecision;
+use kernel::audit_log::NewAuditEntry;
+use kernel::moderation::{ModerateText, ModeratedContent};
+use kernel::notifications::NotificationType;
+use kernel::todo_events::NewTodoEvent;
+use kernel::to_do_items::{NewTodo, Todo};
+use crate::api::notifications::batching::notify_user;
+use crate::api::moderation::screen::{screen_text, record_flag};
+
+
+/// The action recorded in the audit log when an admin assigns past the cap.
+pub const CAPACITY_OVERRIDE_ACTION: &str = "todo:capacity_override";
+
+
+/// The open item count for an assignee that is at or over the cap.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct CapacityExceeded {
+    pub assigned_to: i32,
+    pub open_items: i64,
+    pub limit: i64,
+}
+
+
+/// Reads the cap from config.
+///
+/// # Returns
+/// * `Some(i64)` - The most open items a user can be assigned
+/// * `None` - If there is no cap
+pub fn max_open_items<Y: GetConfigVariable>() -> Option<i64> {
+    Y::get_config_variable("TODO_MAX_OPEN_PER_USER".to_string())
+        .ok()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .filter(|limit| *limit > 0)
+}
+
+
+/// Checks whether one more item can be assigned to a user.
+///
+/// # Arguments
+/// - `assigned_to`: The ID of the user the item is being assigned to.
+///
+/// # Returns
+/// - `Ok(None)`: If the user is under the cap or there is no cap.
+/// - `Ok(Some(CapacityExceeded))`: If the user is already at the cap.
+/// - `Err(NanoServiceError)`: If the open items could not be counted.
+pub async fn check_capacity<X, Y>(assigned_to: i32) -> Result<Option<CapacityExceeded>, NanoServiceError>
+where
+    X: CountOpenToDoItemsForUser,
+    Y: GetConfigVariable
+{
+    let limit = match max_open_items::<Y>() {
+        Some(limit) => limit,
+        None => return Ok(None)
+    };
+    let open_items = X::count_open_to_do_items_for_user(assigned_to).await?;
+    if open_items < limit {
+        return Ok(None)
+    }
+    Ok(Some(CapacityExceeded { assigned_to, open_items, limit }))
+}
+
+
+/// Refuses the assignment if the user is at the cap and the override flag is not set.
+///
+/// # Returns
+/// - `Ok(Some(CapacityExceeded))`: The assignment is allowed because of the override, so it must be audited.
+/// - `Ok(None)`: The assignment is within the cap.
+/// - `Err(NanoServiceError)`: `Conflict` if the user is at the cap without the override.
+async fn enforce_capacity<X, Y>(assigned_to: i32, override_capacity: bool) -> Result<Option<CapacityExceeded>, NanoServiceError>
+where
+    X: CountOpenToDoItemsForUser,
+    Y: GetConfigVariable
+{
+    match check_capacity::<X, Y>(assigned_to).await? {
+        Some(exceeded) if !override_capacity => Err(NanoServiceError::new(
+            format!(
+                "User {} already has {} open to-do items, the limit is {}; set override_capacity to assign anyway",
+                exceeded.assigned_to, exceeded.open_items, exceeded.limit
+            ),
+            NanoServiceErrorStatus::Conflict,
+        )),
+        outcome => Ok(outcome)
+    }
+}
+
+
+/// Writes the override to the audit log.
+async fn audit_override<X: CreateAuditEntry>(actor_id: i32, todo: &Todo, exceeded: CapacityExceeded) -> Result<(), NanoServiceError> {
+    X::create_audit_entry(NewAuditEntry {
+        actor_id,
+        action: CAPACITY_OVERRIDE_ACTION.to_string(),
+        subject_id: Some(todo.id),
+        details: json!({
+            "assigned_to": exceeded.assigned_to,
+            "open_items": exceeded.open_items,
+            "limit": exceeded.limit,
+        }),
+    }).await?;
+    Ok(())
+}
+
+
+/// Checks the organization's plan allows another to-do item.
+///
+/// # Returns
+/// - `Ok(())`: If another item can be created.
+/// - `Err(NanoServiceError)`: `UpgradeRequired` if the plan's to-do item limit has been reached.
+pub async fn check_plan_todo_limit<X: GetOrgPlan + CountToDoItems>() -> Result<(), NanoServiceError> {
+    X::get_org_plan().await?.entitlements().check_max_todos(X::count_to_do_items().await?)
+}
+
+
+/// Creates a to-do item if the assignee is under the cap.
+///
+/// # Arguments
+/// - `new_todo`: The to-do item to create.
+/// - `org_id`: The ID of the organization the item belongs to.
+/// - `actor_id`: The ID of the user creating the item, recorded in its history and if the cap is overridden.
+/// - `override_capacity`: Whether to create the item even if the assignee is at the cap.
+///
+/// # Returns
+/// - `Ok(Todo)`: The created to-do item.
+/// - `Err(NanoServiceError)`: `UnprocessableEntity` if moderation rejects the description,
+///   `UpgradeRequired` if the plan's to-do item limit has been reached, `Conflict` if the assignee
+///   is at the cap, or if a transaction fails.
+pub async fn create_to_do_item_within_capacity<X, Y, M>(
+    new_todo: NewTodo,
+    org_id: i32,
+    actor_id: i32,
+    override_capacity: bool
+) -> Result<Todo, NanoServiceError>
+where
+    X: CreateToDoItem + CountOpenToDoItemsForUser + CreateAuditEntry + QueueNotification + RecordModerationDecision
+        + GetOrgPlan + CountToDoItems + RecordToDoEvent,
+    Y: GetConfigVariable,
+    M: ModerateText
+{
+    let flag = match new_todo.description.as_deref() {
+        Some(description) => screen_text::<X, M, Y>(
+            ModeratedContent::TodoDescription, None, actor_id, description
+        ).await?,
+        None => None
+    };
+    check_plan_todo_limit::<X>().await?;
+    let exceeded = enforce_capacity::<X, Y>(new_todo.assigned_to, override_capacity).await?;
+    let todo = X::create_to_do_item(new_todo, org_id).await?;
+    X::record_to_do_event(NewTodoEvent::created(&todo, actor_id)).await?;
+    record_flag::<X>(flag, todo.id).await?;
+    if let Some(exceeded) = exceeded {
+        audit_override::<X>(actor_id, &todo, exceeded).await?;
+    }
+    if todo.assigned_to != actor_id {
+        notify_user::<X>(todo.assigned_to, NotificationType::Assignment, &todo).await;
+    }
+    Ok(todo)
+}
+
+
+/// Reassigns a to-do item if the new assignee is under the cap.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item to reassign.
+/// - `new_assigned_to`: The ID of the user to assign the item to.
+/// - `actor_id`: The ID of the user reassigning the item, recorded in its history and if the cap is overridden.
+/// - `override_capacity`: Whether to reassign the item even if the new assignee is at the cap.
+///
+/// # Returns
+/// - `Ok(Todo)`: The reassigned to-do item.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such item, `Conflict` if the new assignee is
+///   at the cap, or if a transaction fails.
+pub async fn re_assign_to_do_item_within_capacity<X, Y>(
+    todo_id: i32,
+    new_assigned_to: i32,
+    actor_id: i32,
+    override_capacity: bool
+) -> Result<Todo, NanoServiceError>
+where
+    X: GetToDoItem + ReAssignToDoItem + CountOpenToDoItemsForUser + CreateAuditEntry + QueueNotification + RecordToDoEvent,
+    Y: GetConfigVariable
+{
+    let previous_assignee = X::get_to_do_item(todo_id).await?.assigned_to;
+    let exceeded = enforce_capacity::<X, Y>(new_assigned_to, override_capacity).await?;
+    let todo = X::re_assign_to_do_item(todo_id, new_assigned_to).await?;
+    X::record_to_do_event(NewTodoEvent::reassigned(&todo, previous_assignee, actor_id)).await?;
+    if let Some(exceeded) = exceeded {
+        audit_override::<X>(actor_id, &todo, exceeded).await?;
+    }
+    if todo.assigned_to != actor_id {
+        notify_user::<X>(todo.assigned_to, NotificationType::Assignment, &todo).await;
+    }
+    Ok(todo)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
+    use dal_tx_impl::impl_transaction;
+    use kernel::audit_log::AuditEntry;
+    use kernel::notifications::{NewNotification, PendingNotification};
+    use kernel::moderation::{NewModerationDecision, ModerationDecision};
+    use kernel::moderation::engine_mock::{AllowTextMock, FlagTextMock, RejectTextMock};
+    use kernel::plans::{OrgPlan, Plan};
+    use kernel::todo_events::{TodoEvent, TodoEventKind};
+    use chrono::Utc;
+    use std::sync::Mutex;
+
+    static MODERATED: Mutex<Vec<NewModerationDecision>> = Mutex::new(Vec::new());
+    static AUDITED: Mutex<Vec<NewAuditEntry>> = Mutex::new(Vec::new());
+    static NOTIFIED: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+    static HISTORY: Mutex<Vec<NewTodoEvent>> = Mutex::new(Vec::new());
+
+    struct CappedConfig;
+
+    impl GetConfigVariable for CappedConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok(" 3 ".to_string())
+        }
+    }
+
+    struct UncappedConfig;
+
+    impl GetConfigVariable for UncappedConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Err(NanoServiceError::new("not set".to_string(), NanoServiceErrorStatus::Unknown))
+        }
+    }
+
+    struct MockDbHandle;
+
+    fn org_plan(plan: Plan) -> OrgPlan {
+        OrgPlan {
+            plan,
+            max_users: None,
+            max_todos: None,
+            mfa: None,
+            api_access: None,
+            date_updated: Utc::now().naive_utc(),
+        }
+    }
+
+    /// User 2 is under the cap, user 3 is at it.
+    #[impl_transaction(MockDbHandle, CountOpenToDoItemsForUser, count_open_to_do_items_for_user)]
+    async fn count_open_to_do_items_for_user(user_id: i32) -> Result<i64, NanoServiceError> {
+        Ok(if user_id == 3 { 3 } else { 2 })
+    }
+
+    fn todo(id: i32, assigned_to: i32) -> Todo {
+        Todo {
+            id,
+            name: "Test Task".to_string(),
+            due_date: None,
+            assigned_by: 1,
+            assigned_to,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, CreateToDoItem, create_to_do_item)]
+    async fn create_to_do_item(new_todo: NewTodo, _org_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(todo(7, new_todo.assigned_to))
+    }
+
+    #[impl_transaction(MockDbHandle, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(todo(todo_id, 2))
+    }
+
+    #[impl_transaction(MockDbHandle, ReAssignToDoItem, re_assign_to_do_item)]
+    async fn re_assign_to_do_item(todo_id: i32, new_assigned_to: i32) -> Result<Todo, NanoServiceError> {
+        Ok(todo(todo_id, new_assigned_to))
+    }
+
+    #[impl_transaction(MockDbHandle, CreateAuditEntry, create_audit_entry)]
+    async fn create_audit_entry(entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+        AUDITED.lock().unwrap().push(entry.clone());
+        Ok(AuditEntry {
+            id: 1,
+            actor_id: entry.actor_id,
+            action: entry.action,
+            subject_id: entry.subject_id,
+            details: sqlx::types::Json(entry.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, QueueNotification, queue_notification)]
+    async fn queue_notification(notification: NewNotification) -> Result<PendingNotification, NanoServiceError> {
+        NOTIFIED.lock().unwrap().push(notification.user_id);
+        Ok(PendingNotification {
+            id: 1,
+            user_id: notification.user_id,
+            notification_type: notification.notification_type,
+            todo_id: notification.todo_id,
+            todo_name: notification.todo_name,
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        HISTORY.lock().unwrap().push(event.clone());
+        Ok(TodoEvent {
+            id: 1,
+            todo_id: event.todo_id,
+            actor_id: Some(event.actor_id),
+            kind: event.kind,
+            details: sqlx::types::Json(event.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(org_plan(Plan::Pro))
+    }
+
+    #[impl_transaction(MockDbHandle, CountToDoItems, count_to_do_items)]
+    async fn count_to_do_items() -> Result<i64, NanoServiceError> {
+        Ok(40)
+    }
+
+    #[impl_transaction(MockDbHandle, RecordModerationDecision, record_moderation_decision)]
+    async fn record_moderation_decision(decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+        MODERATED.lock().unwrap().push(decision.clone());
+        Ok(ModerationDecision {
+            id: 1,
+            content: decision.content,
+            entity_id: decision.entity_id,
+            author_id: decision.author_id,
+            action: decision.action,
+            reasons: decision.reasons,
+            text: decision.text,
+            created_at: Utc::now().naive_utc(),
+            reviewed_by: None,
+            reviewed_at: None,
+            upheld: None,
+        })
+    }
+
+    fn new_todo(assigned_to: i32) -> NewTodo {
+        NewTodo {
+            name: "Test Task".to_string(),
+            due_date: None,
+            assigned_by: 1,
+            assigned_to,
+            description: None,
+            date_assigned: None,
+            requires_review: false,
+            priority: TodoPriority::Medium,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_todo_limit() {
+        struct FullPlanDbHandle;
+
+        #[impl_transaction(FullPlanDbHandle, GetOrgPlan, get_org_plan)]
+        async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+            Ok(OrgPlan { max_todos: Some(40), ..org_plan(Plan::Pro) })
+        }
+
+        #[impl_transaction(FullPlanDbHandle, CountToDoItems, count_to_do_items)]
+        async fn count_to_do_items() -> Result<i64, NanoServiceError> {
+            Ok(40)
+        }
+
+        assert!(check_plan_todo_limit::<MockDbHandle>().await.is_ok());
+        let error = check_plan_todo_limit::<FullPlanDbHandle>().await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::UpgradeRequired);
+        assert_eq!(error.details.unwrap()["entitlement"], "max_todos");
+    }
+
+    #[test]
+    fn test_max_open_items() {
+        assert_eq!(max_open_items::<CappedConfig>(), Some(3));
+        assert_eq!(max_open_items::<UncappedConfig>(), None);
+    }
+
+    #[tokio::test]
+    async fn test_capacity_is_enforced_and_overrides_are_audited() {
+        // under the cap
+        let item = create_to_do_item_within_capacity::<MockDbHandle, CappedConfig, AllowTextMock>(new_todo(2), 1, 1, false).await.unwrap();
+        assert_eq!(item.assigned_to, 2);
+
+        // at the cap without the override
+        let error = create_to_do_item_within_capacity::<MockDbHandle, CappedConfig, AllowTextMock>(new_todo(3), 1, 1, false).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        let error = re_assign_to_do_item_within_capacity::<MockDbHandle, CappedConfig>(5, 3, 1, false).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+
+        // no cap configured
+        let item = create_to_do_item_within_capacity::<MockDbHandle, UncappedConfig, AllowTextMock>(new_todo(3), 1, 1, false).await.unwrap();
+        assert_eq!(item.assigned_to, 3);
+        assert!(AUDITED.lock().unwrap().is_empty());
+
+        // at the cap with the override
+        let item = re_assign_to_do_item_within_capacity::<MockDbHandle, CappedConfig>(5, 3, 1, true).await.unwrap();
+        assert_eq!(item.assigned_to, 3);
+        {
+            let audited = AUDITED.lock().unwrap();
+            assert_eq!(audited.len(), 1);
+            assert_eq!(audited[0], NewAuditEntry {
+                actor_id: 1,
+                action: CAPACITY_OVERRIDE_ACTION.to_string(),
+                subject_id: Some(5),
+                details: json!({"assigned_to": 3, "open_items": 3, "limit": 3}),
+            });
+        }
+
+        // only the items that were assigned, and not to the admin themselves, notify the assignee
+        create_to_do_item_within_capacity::<MockDbHandle, UncappedConfig, AllowTextMock>(new_todo(1), 1, 1, false).await.unwrap();
+        assert_eq!(*NOTIFIED.lock().unwrap(), vec![2, 3, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_recorded_in_the_history() {
+        create_to_do_item_within_capacity::<MockDbHandle, UncappedConfig, AllowTextMock>(new_todo(1), 1, 1, false).await.unwrap();
+        re_assign_to_do_item_within_capacity::<MockDbHandle, UncappedConfig>(8, 1, 1, false).await.unwrap();
+        let history = HISTORY.lock().unwrap();
+        let reassigned = history.iter().find(|event| event.todo_id == 8).unwrap();
+        assert_eq!(reassigned.kind, TodoEventKind::Reassigned);
+        assert_eq!(reassigned.actor_id, 1);
+        assert_eq!(reassigned.details, json!({"from": 2, "to": 1}));
+        assert!(history.iter().any(|event| event.kind == TodoEventKind::Created && event.todo_id == 7));
+    }
+
+    #[tokio::test]
+    async fn test_description_is_moderated() {
+        let mut flagged = new_todo(1);
+        flagged.description = Some("Odd".to_string());
+        let item = create_to_do_item_within_capacity::<MockDbHandle, UncappedConfig, FlagTextMock>(flagged, 1, 1, false).await.unwrap();
+        {
+            let moderated = MODERATED.lock().unwrap();
+            assert_eq!(moderated.len(), 1);
+            assert_eq!(moderated[0].entity_id, Some(item.id));
+            assert_eq!(moderated[0].content, ModeratedContent::TodoDescription);
+        }
+
+        let mut rejected = new_todo(1);
+        rejected.description = Some("Bad".to_string());
+        let error = create_to_do_item_within_capacity::<MockDbHandle, UncappedConfig, RejectTextMock>(rejected, 1, 1, false).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::UnprocessableEntity);
+        assert_eq!(MODERATED.lock().unwrap()[1].entity_id, None);
+
+        // items without a description are not moderated
+        create_to_do_item_within_capacity::<MockDbHandle, UncappedConfig, RejectTextMock>(new_todo(1), 1, 1, false).await.unwrap();
+    }
+}
@@ -29,6 +29,7 @@ pub async fn complete_to_do_item<X: CompleteToDoItem>(todo_id: i32) -> Result<To
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
     use dal_tx_impl::impl_transaction;
     use chrono::Utc;
 
@@ -52,13 +53,18 @@ mod tests {
                 date_assigned: now,
                 date_finished: Some(now),
                 finished: true,
+                requires_review: false,
+                pending_review: false,
+                review_comment: None,
+                priority: TodoPriority::Medium,
+                status: TodoStatus::Done,
             })
         }
 
         let result = complete_to_do_item::<MockDbHandle>(1).await.unwrap();
 
         assert_eq!(result.id, 1);
-        assert_eq!(result.finished, true);
+        assert!(result.finished);
         assert!(result.date_finished.is_some());
     }
 
@@ -17,6 +17,7 @@ use kernel::to_do_items::{NewTodo, Todo};
 ///
 /// # Arguments
 /// - `new_todo`: The input schema containing the details of the to-do item.
+/// - `org_id`: The ID of the organization the item belongs to.
 ///
 /// # Returns
 /// - `Ok(Todo)`: The newly created to-do item if the operation is successful.
@@ -24,13 +25,14 @@ use kernel::to_do_items::{NewTodo, Todo};
 ///
 /// # Notes
 /// - This function uses the `CreateToDoItem` trait to perform the database operation.
-pub async fn create_to_do_item<X: CreateToDoItem>(new_todo: NewTodo) -> Result<Todo, NanoServiceError> {
-    X::create_to_do_item(new_todo).await
+pub async fn create_to_do_item<X: CreateToDoItem>(new_todo: NewTodo, org_id: i32) -> Result<Todo, NanoServiceError> {
+    X::create_to_do_item(new_todo, org_id).await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
     use dal_tx_impl::impl_transaction;
     use chrono::Utc;
 
@@ -40,7 +42,8 @@ mod tests {
         struct MockDbHandle;
 
         #[impl_transaction(MockDbHandle, CreateToDoItem, create_to_do_item)]
-        async fn create_to_do_item(todo: NewTodo) -> Result<Todo, NanoServiceError> {
+        async fn create_to_do_item(todo: NewTodo, org_id: i32) -> Result<Todo, NanoServiceError> {
+            assert_eq!(org_id, 3);
             let now = Utc::now().naive_utc();
             Ok(Todo {
                 id: 1,
@@ -52,6 +55,11 @@ mod tests {
                 date_assigned: todo.date_assigned.unwrap_or(now),
                 date_finished: None,
                 finished: false,
+                requires_review: false,
+                pending_review: false,
+                review_comment: None,
+                priority: TodoPriority::Medium,
+                status: TodoStatus::Backlog,
             })
         }
 
@@ -62,15 +70,17 @@ mod tests {
             assigned_to: 2,
             description: Some("Test description".to_string()),
             date_assigned: Some(Utc::now().naive_utc()),
+            requires_review: false,
+            priority: TodoPriority::Medium,
         };
 
-        let result = create_to_do_item::<MockDbHandle>(new_todo.clone()).await.unwrap();
+        let result = create_to_do_item::<MockDbHandle>(new_todo.clone(), 3).await.unwrap();
 
         assert_eq!(result.name, new_todo.name);
         assert_eq!(result.assigned_by, new_todo.assigned_by);
         assert_eq!(result.assigned_to, new_todo.assigned_to);
         assert_eq!(result.description, new_todo.description);
-        assert_eq!(result.finished, false);
+        assert!(!result.finished);
     }
 
     /// Tests error handling when the DAL returns an error.
@@ -79,7 +89,7 @@ mod tests {
         struct MockDbHandle;
 
         #[impl_transaction(MockDbHandle, CreateToDoItem, create_to_do_item)]
-        async fn create_to_do_item(_todo: NewTodo) -> Result<Todo, NanoServiceError> {
+        async fn create_to_do_item(_todo: NewTodo, _org_id: i32) -> Result<Todo, NanoServiceError> {
             Err(NanoServiceError::new(
                 "Failed to create to-do item".to_string(),
                 utils::errors::NanoServiceErrorStatus::Unknown,
@@ -93,9 +103,11 @@ mod tests {
             assigned_to: 2,
             description: Some("Test description".to_string()),
             date_assigned: Some(Utc::now().naive_utc()),
+            requires_review: false,
+            priority: TodoPriority::Medium,
         };
 
-        let result = create_to_do_item::<MockDbHandle>(new_todo).await;
+        let result = create_to_do_item::<MockDbHandle>(new_todo, 3).await;
 
         assert!(result.is_err());
         let error = result.err().unwrap();
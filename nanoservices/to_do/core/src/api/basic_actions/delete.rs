@@ -38,7 +38,7 @@ mod tests {
         }
 
         let result = delete_to_do_item::<MockDbHandle>(1).await.unwrap();
-        assert_eq!(result, true);
+        assert!(result);
     }
 
     /// Tests the case when a to-do item could not be deleted (returns false).
@@ -52,7 +52,7 @@ mod tests {
         }
 
         let result = delete_to_do_item::<MockDbHandle>(1).await.unwrap();
-        assert_eq!(result, false);
+        assert!(!result);
     }
 
     /// Tests error handling when the DAL returns an error.
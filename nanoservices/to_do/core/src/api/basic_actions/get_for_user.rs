@@ -6,14 +6,18 @@
 //!
 //! # Features
 //! - Delegates the retrieval operation to the data access layer (DAL) using `GetToDoItemsForUser`.
+//! - Optionally filters the items by tag using `GetToDoItemsForUserByTag`.
 use utils::errors::NanoServiceError;
 use dal::to_do_items::tx_definitions::GetToDoItemsForUser;
+use dal::tags::tx_definitions::GetToDoItemsForUserByTag;
 use kernel::to_do_items::Todo;
+use kernel::pagination::{Page, PageQuery};
 
 /// Retrieves all to-do items assigned to a specific user.
 ///
 /// # Arguments
 /// - `user_id`: The unique identifier of the user.
+/// - `org_id`: The ID of the organization the user is acting in.
 ///
 /// # Returns
 /// - `Ok(Vec<Todo>)`: A list of to-do items assigned to the user if the operation is successful.
@@ -21,13 +25,52 @@ use kernel::to_do_items::Todo;
 ///
 /// # Notes
 /// - This function uses the `GetToDoItemsForUser` trait to perform the database operation.
-pub async fn get_to_do_items_for_user<X: GetToDoItemsForUser>(user_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
-    X::get_to_do_items_for_user(user_id).await
+pub async fn get_to_do_items_for_user<X: GetToDoItemsForUser>(user_id: i32, org_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
+    X::get_to_do_items_for_user(user_id, org_id).await
+}
+
+/// Retrieves the to-do items assigned to a specific user, only returning those carrying `tag` if one is given.
+///
+/// # Arguments
+/// - `user_id`: The unique identifier of the user.
+/// - `org_id`: The ID of the organization the user is acting in.
+/// - `tag`: The name of the tag to filter by, all items are returned if `None`.
+///
+/// # Returns
+/// - `Ok(Vec<Todo>)`: A list of to-do items assigned to the user if the operation is successful.
+/// - `Err(NanoServiceError)`: If an error occurs during the database transaction.
+pub async fn get_tagged_to_do_items_for_user<X>(user_id: i32, org_id: i32, tag: Option<String>) -> Result<Vec<Todo>, NanoServiceError>
+where
+    X: GetToDoItemsForUser + GetToDoItemsForUserByTag
+{
+    match tag {
+        Some(tag) => X::get_to_do_items_for_user_by_tag(user_id, org_id, tag.trim().to_lowercase()).await,
+        None => X::get_to_do_items_for_user(user_id, org_id).await
+    }
+}
+
+/// Retrieves a page of the to-do items assigned to a specific user, filtered as in `get_tagged_to_do_items_for_user`.
+///
+/// # Arguments
+/// - `user_id`: The unique identifier of the user.
+/// - `org_id`: The ID of the organization the user is acting in.
+/// - `tag`: The name of the tag to filter by, all items are listed if `None`.
+/// - `page`: The page asked for.
+///
+/// # Returns
+/// - `Ok(Page<Todo>)`: The to-do items on the page and how many there are.
+/// - `Err(NanoServiceError)`: `BadRequest` if the limit or cursor is invalid.
+pub async fn get_to_do_items_page<X>(user_id: i32, org_id: i32, tag: Option<String>, page: &PageQuery) -> Result<Page<Todo>, NanoServiceError>
+where
+    X: GetToDoItemsForUser + GetToDoItemsForUserByTag
+{
+    Page::from_items(get_tagged_to_do_items_for_user::<X>(user_id, org_id, tag).await?, page)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
     use dal_tx_impl::impl_transaction;
     use chrono::Utc;
 
@@ -37,8 +80,9 @@ mod tests {
         struct MockDbHandle;
 
         #[impl_transaction(MockDbHandle, GetToDoItemsForUser, get_to_do_items_for_user)]
-        async fn get_to_do_items_for_user(user_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
+        async fn get_to_do_items_for_user(user_id: i32, org_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
             assert_eq!(user_id, 1);
+            assert_eq!(org_id, 3);
             let now = Utc::now().naive_utc();
             Ok(vec![
                 Todo {
@@ -51,6 +95,11 @@ mod tests {
                     date_assigned: now,
                     date_finished: None,
                     finished: false,
+                    requires_review: false,
+                    pending_review: false,
+                    review_comment: None,
+                    priority: TodoPriority::Medium,
+                    status: TodoStatus::Backlog,
                 },
                 Todo {
                     id: 2,
@@ -62,11 +111,16 @@ mod tests {
                     date_assigned: now,
                     date_finished: None,
                     finished: false,
+                    requires_review: false,
+                    pending_review: false,
+                    review_comment: None,
+                    priority: TodoPriority::Medium,
+                    status: TodoStatus::Backlog,
                 }
             ])
         }
 
-        let result = get_to_do_items_for_user::<MockDbHandle>(1).await.unwrap();
+        let result = get_to_do_items_for_user::<MockDbHandle>(1, 3).await.unwrap();
 
         assert_eq!(result.len(), 2);
         assert_eq!(result[0].name, "Task 1");
@@ -79,18 +133,62 @@ mod tests {
         struct MockDbHandle;
 
         #[impl_transaction(MockDbHandle, GetToDoItemsForUser, get_to_do_items_for_user)]
-        async fn get_to_do_items_for_user(_user_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
+        async fn get_to_do_items_for_user(_user_id: i32, _org_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
             Err(NanoServiceError::new(
                 "Failed to get to-do items".to_string(),
                 utils::errors::NanoServiceErrorStatus::Unknown,
             ))
         }
 
-        let result = get_to_do_items_for_user::<MockDbHandle>(1).await;
+        let result = get_to_do_items_for_user::<MockDbHandle>(1, 3).await;
 
         assert!(result.is_err());
         let error = result.err().unwrap();
         assert_eq!(error.status, utils::errors::NanoServiceErrorStatus::Unknown);
         assert_eq!(error.message, "Failed to get to-do items");
     }
+
+    /// Tests the tag filter is only applied when a tag is given.
+    #[tokio::test]
+    async fn test_get_tagged_to_do_items_for_user() {
+        struct MockDbHandle;
+
+        fn todo(id: i32, user_id: i32) -> Todo {
+            let now = Utc::now().naive_utc();
+            Todo {
+                id,
+                name: format!("Task {}", id),
+                due_date: None,
+                assigned_by: 2,
+                assigned_to: user_id,
+                description: None,
+                date_assigned: now,
+                date_finished: None,
+                finished: false,
+                requires_review: false,
+                pending_review: false,
+                review_comment: None,
+                priority: TodoPriority::Medium,
+                status: TodoStatus::Backlog,
+            }
+        }
+
+        #[impl_transaction(MockDbHandle, GetToDoItemsForUser, get_to_do_items_for_user)]
+        async fn get_to_do_items_for_user(user_id: i32, _org_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
+            Ok(vec![todo(1, user_id), todo(2, user_id)])
+        }
+
+        #[impl_transaction(MockDbHandle, GetToDoItemsForUserByTag, get_to_do_items_for_user_by_tag)]
+        async fn get_to_do_items_for_user_by_tag(user_id: i32, _org_id: i32, tag: String) -> Result<Vec<Todo>, NanoServiceError> {
+            assert_eq!(tag, "urgent");
+            Ok(vec![todo(2, user_id)])
+        }
+
+        let all = get_tagged_to_do_items_for_user::<MockDbHandle>(1, 1, None).await.unwrap();
+        assert_eq!(all.len(), 2);
+
+        let tagged = get_tagged_to_do_items_for_user::<MockDbHandle>(1, 1, Some("Urgent".to_string())).await.unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].id, 2);
+    }
 }
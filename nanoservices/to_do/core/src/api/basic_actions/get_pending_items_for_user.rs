@@ -34,6 +34,7 @@ pub async fn get_pending_to_do_items_for_user<X: GetPendingToDoItemsForUser>(use
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
     use dal_tx_impl::impl_transaction;
     use chrono::Utc;
 
@@ -57,6 +58,11 @@ mod tests {
                     date_assigned: now,
                     date_finished: None,
                     finished: false,
+                    requires_review: false,
+                    pending_review: false,
+                    review_comment: None,
+                    priority: TodoPriority::Medium,
+                    status: TodoStatus::Backlog,
                 },
                 Todo {
                     id: 2,
@@ -68,6 +74,11 @@ mod tests {
                     date_assigned: now,
                     date_finished: None,
                     finished: false,
+                    requires_review: false,
+                    pending_review: false,
+                    review_comment: None,
+                    priority: TodoPriority::Medium,
+                    status: TodoStatus::Backlog,
                 }
             ])
         }
@@ -4,3 +4,6 @@ pub mod get_for_user;
 pub mod get_pending_items_for_user;
 pub mod reassign;
 pub mod complete_to_do_item;
+pub mod capacity;
+pub mod update;
+pub mod search;
@@ -26,6 +26,7 @@ pub async fn re_assign_to_do_item<X: ReAssignToDoItem>(todo_id: i32, new_assigne
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
     use dal_tx_impl::impl_transaction;
     use chrono::Utc;
 
@@ -50,6 +51,11 @@ mod tests {
                 date_assigned: now,
                 date_finished: None,
                 finished: false,
+                requires_review: false,
+                pending_review: false,
+                review_comment: None,
+                priority: TodoPriority::Medium,
+                status: TodoStatus::Backlog,
             })
         }
 
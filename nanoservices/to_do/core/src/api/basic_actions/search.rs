@@ -0,0 +1,62 @@
+//! Core logic for searching the to-do items assigned to or by a user.
+//!
+//! # Overview
+//! The text is matched against the name and description, the filters narrow the results down and
+//! the results come back in the order asked for, at most one page of them.
+use utils::errors::NanoServiceError;
+use utils::validation::validate_body;
+use dal::to_do_items::tx_definitions::SearchToDoItems;
+use kernel::to_do_items::{Todo, ToDoSearch};
+use crate::api::sync::page_size;
+
+
+/// Searches the to-do items assigned to or by a user.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user searching.
+/// - `org_id`: The ID of the organization the user is acting in.
+/// - `search`: The text to find, the filters and the order, blank text is ignored.
+///
+/// # Returns
+/// - `Ok(Vec<Todo>)`: The matching to-do items.
+/// - `Err(NanoServiceError)`: `BadRequest` if the text is too long or the limit is out of range.
+pub async fn search_to_do_items<X: SearchToDoItems>(user_id: i32, org_id: i32, mut search: ToDoSearch) -> Result<Vec<Todo>, NanoServiceError> {
+    search.q = search.q
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty());
+    validate_body(&search)?;
+    let limit = page_size(search.limit)?;
+    X::search_to_do_items(user_id, org_id, search, limit).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceErrorStatus;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, SearchToDoItems, search_to_do_items)]
+    async fn search_to_do_items(user_id: i32, org_id: i32, search: ToDoSearch, limit: i64) -> Result<Vec<Todo>, NanoServiceError> {
+        assert_eq!(user_id, 2);
+        assert_eq!(org_id, 3);
+        assert_eq!(limit, 100);
+        assert_eq!(search.q.as_deref(), Some("report"));
+        Ok(vec![])
+    }
+
+    #[tokio::test]
+    async fn test_search_trims_text() {
+        let search = ToDoSearch { q: Some("  report ".to_string()), ..Default::default() };
+        assert!(search_to_do_items::<MockDbHandle>(2, 3, search).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_rejects_bad_limit() {
+        let search = ToDoSearch { q: Some("report".to_string()), limit: Some(0), ..Default::default() };
+        let error = search_to_do_items::<MockDbHandle>(2, 3, search).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+}
@@ -0,0 +1,266 @@
+//! Core logic for editing a to-do item after it has been created.
+//!
+//! # Overview
+//! Only the fields given in the patch are changed. The user who assigned the item owns its
+//! wording, due date and priority, so only they or an admin can edit it.
+//!
+//! A sync client passes the version of the item it edited, if the item has changed since then the
+//! edit is refused with the current item and the fields that clash so the client can merge them.
+//!
+//! A new description is moderated like the description of a new item.
+//!
+//! The fields an edit changed are recorded in the history of the item with their old and new values.
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::validation::validate_body;
+use dal::to_do_items::tx_definitions::UpdateToDoItem;
+use dal::sync::tx_definitions::GetSyncedToDoItem;
+use dal::moderation::tx_definitions::RecordModerationDecision;
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use kernel::moderation::{ModerateText, ModeratedContent};
+use kernel::todo_events::NewTodoEvent;
+use kernel::to_do_items::ToDoItemPatch;
+use kernel::sync::{SyncedTodo, version_conflict};
+use kernel::users::UserRole;
+use kernel::chrono::NaiveDateTime;
+use crate::api::moderation::screen::{screen_text, record_flag};
+
+
+/// Applies a patch to a to-do item.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item to edit.
+/// - `editor_id`: The ID of the user making the edit.
+/// - `editor_role`: The role of the user making the edit.
+/// - `patch`: The fields to change.
+/// - `if_match`: The version of the item the edit was made against, any version if `None`.
+///
+/// # Returns
+/// - `Ok(SyncedTodo)`: The edited to-do item with its new version.
+/// - `Err(NanoServiceError)`: `BadRequest` if the patch is empty or not valid, `Forbidden` if the
+///   editor neither assigned the item nor is an admin, `NotFound` if there is no such item,
+///   `Conflict` with the current item and the clashing fields if it is no longer at `if_match`,
+///   `UnprocessableEntity` if moderation rejects the new description.
+pub async fn update_to_do_item<X, Y, M>(
+    todo_id: i32,
+    editor_id: i32,
+    editor_role: &UserRole,
+    patch: ToDoItemPatch,
+    if_match: Option<NaiveDateTime>
+) -> Result<SyncedTodo, NanoServiceError>
+where
+    X: GetSyncedToDoItem + UpdateToDoItem + RecordModerationDecision + RecordToDoEvent,
+    Y: GetConfigVariable,
+    M: ModerateText
+{
+    if patch.is_empty() {
+        return Err(NanoServiceError::new(
+            "At least one of name, description, due_date or priority has to be given".to_string(),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    validate_body(&patch)?;
+    let current = X::get_synced_to_do_item(todo_id).await?;
+    let is_admin = matches!(editor_role, UserRole::SuperAdmin | UserRole::Admin);
+    if current.todo.assigned_by != editor_id && !is_admin {
+        return Err(NanoServiceError::new(
+            "Only the user who assigned the to-do item or an admin can edit it".to_string(),
+            NanoServiceErrorStatus::Forbidden,
+        ))
+    }
+    if if_match.is_some_and(|version| version != current.updated_at) {
+        return Err(version_conflict(&current, &patch))
+    }
+    let flag = match patch.description.as_ref().and_then(|description| description.as_deref()) {
+        Some(description) => screen_text::<X, M, Y>(
+            ModeratedContent::TodoDescription, Some(todo_id), editor_id, description
+        ).await?,
+        None => None
+    };
+    // the version is checked again by the update in case the item changed since it was read
+    match X::update_to_do_item(todo_id, patch.clone(), if_match).await? {
+        Some(updated) => {
+            record_flag::<X>(flag, todo_id).await?;
+            if let Some(event) = NewTodoEvent::edited(todo_id, editor_id, &current.todo, &patch) {
+                X::record_to_do_event(event).await?;
+            }
+            Ok(updated)
+        },
+        None => Err(version_conflict(&X::get_synced_to_do_item(todo_id).await?, &patch)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use kernel::moderation::{NewModerationDecision, ModerationDecision, ModerationAction};
+    use kernel::moderation::engine_mock::{AllowTextMock, RejectTextMock};
+    use kernel::todo_events::TodoEvent;
+    use dal_tx_impl::impl_transaction;
+    use chrono::{DateTime, Utc};
+    use std::sync::Mutex;
+
+    static HISTORY: Mutex<Vec<NewTodoEvent>> = Mutex::new(Vec::new());
+
+    struct MockDbHandle;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    fn version() -> NaiveDateTime {
+        DateTime::from_timestamp(1_735_722_000, 0).unwrap().naive_utc()
+    }
+
+    fn todo(todo_id: i32) -> Todo {
+        Todo {
+            id: todo_id,
+            name: "Task".to_string(),
+            due_date: None,
+            assigned_by: 2,
+            assigned_to: 3,
+            description: Some("Old description".to_string()),
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, GetSyncedToDoItem, get_synced_to_do_item)]
+    async fn get_synced_to_do_item(todo_id: i32) -> Result<SyncedTodo, NanoServiceError> {
+        Ok(SyncedTodo { todo: todo(todo_id), updated_at: version() })
+    }
+
+    #[impl_transaction(MockDbHandle, UpdateToDoItem, update_to_do_item)]
+    async fn update_to_do_item(todo_id: i32, patch: ToDoItemPatch, if_match: Option<NaiveDateTime>) -> Result<Option<SyncedTodo>, NanoServiceError> {
+        // the item is changed by someone else between being read and updated
+        if todo_id == 2 {
+            return Ok(None)
+        }
+        assert!(if_match.is_none_or(|version_given| version_given == version()));
+        let mut todo = todo(todo_id);
+        if let Some(name) = patch.name {
+            todo.name = name;
+        }
+        if let Some(description) = patch.description {
+            todo.description = description;
+        }
+        Ok(Some(SyncedTodo { todo, updated_at: Utc::now().naive_utc() }))
+    }
+
+    #[impl_transaction(MockDbHandle, RecordModerationDecision, record_moderation_decision)]
+    async fn record_moderation_decision(decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+        assert_eq!(decision.action, ModerationAction::Rejected);
+        assert_eq!(decision.entity_id, Some(1));
+        Ok(ModerationDecision {
+            id: 1,
+            content: decision.content,
+            entity_id: decision.entity_id,
+            author_id: decision.author_id,
+            action: decision.action,
+            reasons: decision.reasons,
+            text: decision.text,
+            created_at: Utc::now().naive_utc(),
+            reviewed_by: None,
+            reviewed_at: None,
+            upheld: None,
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        HISTORY.lock().unwrap().push(event.clone());
+        Ok(TodoEvent {
+            id: 1,
+            todo_id: event.todo_id,
+            actor_id: Some(event.actor_id),
+            kind: event.kind,
+            details: sqlx::types::Json(event.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    fn patch() -> ToDoItemPatch {
+        ToDoItemPatch {
+            name: Some("Renamed".to_string()),
+            description: Some(None),
+            due_date: None,
+            priority: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_assigner_can_edit() {
+        let updated = update_to_do_item::<MockDbHandle, MockConfig, AllowTextMock>(1, 2, &UserRole::Worker, patch(), None).await.unwrap();
+        assert_eq!(updated.todo.name, "Renamed");
+        assert_eq!(updated.todo.description, None);
+        assert!(HISTORY.lock().unwrap().iter().any(|event| event.actor_id == 2 && event.details == serde_json::json!({
+            "name": {"from": "Task", "to": "Renamed"},
+            "description": {"from": "Old description", "to": null}
+        })));
+    }
+
+    #[tokio::test]
+    async fn test_admin_can_edit() {
+        assert!(update_to_do_item::<MockDbHandle, MockConfig, AllowTextMock>(1, 9, &UserRole::Admin, patch(), Some(version())).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_assignee_cannot_edit() {
+        let error = update_to_do_item::<MockDbHandle, MockConfig, AllowTextMock>(1, 3, &UserRole::Worker, patch(), None).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+    }
+
+    #[tokio::test]
+    async fn test_empty_and_invalid_patches() {
+        let error = update_to_do_item::<MockDbHandle, MockConfig, AllowTextMock>(1, 2, &UserRole::Worker, ToDoItemPatch::default(), None).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+
+        let invalid = ToDoItemPatch { name: Some(String::new()), ..Default::default() };
+        let error = update_to_do_item::<MockDbHandle, MockConfig, AllowTextMock>(1, 2, &UserRole::Worker, invalid, None).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        assert!(error.details.unwrap().get("name").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_stale_version_is_refused_with_merge_hints() {
+        let stale = version() - chrono::Duration::seconds(1);
+        let error = update_to_do_item::<MockDbHandle, MockConfig, AllowTextMock>(1, 2, &UserRole::Worker, patch(), Some(stale)).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        let details = error.details.unwrap();
+        assert_eq!(details["current"]["name"], "Task");
+        assert_eq!(details["current"]["updated_at"], serde_json::to_value(version()).unwrap());
+        assert_eq!(details["conflicts"], serde_json::json!({
+            "name": {"requested": "Renamed", "current": "Task"},
+            "description": {"requested": null, "current": "Old description"}
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_change_between_read_and_update_is_refused() {
+        let error = update_to_do_item::<MockDbHandle, MockConfig, AllowTextMock>(2, 2, &UserRole::Worker, patch(), Some(version())).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Conflict);
+        assert_eq!(error.details.unwrap()["current"]["id"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_new_description_is_moderated() {
+        let rejected = ToDoItemPatch { description: Some(Some("Bad".to_string())), ..Default::default() };
+        let error = update_to_do_item::<MockDbHandle, MockConfig, RejectTextMock>(1, 2, &UserRole::Worker, rejected, None).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::UnprocessableEntity);
+
+        // clearing the description is not moderated
+        assert!(update_to_do_item::<MockDbHandle, MockConfig, RejectTextMock>(1, 2, &UserRole::Worker, patch(), None).await.is_ok());
+    }
+}
@@ -0,0 +1,153 @@
+//! Core logic for the calendar view of a user's to-do items.
+//!
+//! # Overview
+//! The items assigned to a user that are due within a range of dates are returned grouped by the
+//! day they are due, in date order, so the frontend calendar can render each day as it is given.
+//! Days without any items are left out and items without a due date never appear.
+use serde::{Serialize, Deserialize};
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::to_do_items::tx_definitions::GetToDoItemsDueBetween;
+use kernel::to_do_items::Todo;
+use kernel::chrono::{Days, NaiveDate};
+
+
+/// The most days a single calendar request can span.
+pub const MAX_CALENDAR_DAYS: u64 = 366;
+
+
+/// The to-do items due on a single day.
+///
+/// # Fields
+/// * `date` - The day the items are due.
+/// * `items` - The items due that day, in order of due time.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    pub items: Vec<Todo>,
+}
+
+
+/// Gets the to-do items assigned to a user that are due between two dates, grouped by day.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user the items are assigned to.
+/// - `from`: The first day of the range.
+/// - `to`: The last day of the range, inclusive.
+///
+/// # Returns
+/// - `Ok(Vec<CalendarDay>)`: The days in the range that have items due, in date order.
+/// - `Err(NanoServiceError)`: `BadRequest` if `to` is before `from` or the range is longer than
+///   `MAX_CALENDAR_DAYS`, or if the items could not be read.
+pub async fn get_calendar_for_user<X: GetToDoItemsDueBetween>(
+    user_id: i32,
+    from: NaiveDate,
+    to: NaiveDate
+) -> Result<Vec<CalendarDay>, NanoServiceError> {
+    if to < from {
+        return Err(NanoServiceError::new(
+            format!("to ({}) is before from ({})", to, from),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    let end = match to.checked_add_days(Days::new(1)) {
+        Some(end) if (end - from).num_days() as u64 <= MAX_CALENDAR_DAYS => end,
+        _ => return Err(NanoServiceError::new(
+            format!("The calendar range cannot be longer than {} days", MAX_CALENDAR_DAYS),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    };
+    let items = X::get_to_do_items_due_between(
+        user_id,
+        from.and_hms_opt(0, 0, 0).unwrap(),
+        end.and_hms_opt(0, 0, 0).unwrap()
+    ).await?;
+    Ok(group_by_due_date(items))
+}
+
+
+/// Groups items that are already ordered by due date into days.
+fn group_by_due_date(items: Vec<Todo>) -> Vec<CalendarDay> {
+    let mut days: Vec<CalendarDay> = Vec::new();
+    for item in items {
+        let date = match item.due_date {
+            Some(due_date) => due_date.date(),
+            None => continue
+        };
+        match days.last_mut() {
+            Some(day) if day.date == date => day.items.push(item),
+            _ => days.push(CalendarDay { date, items: vec![item] })
+        }
+    }
+    days
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
+    use kernel::chrono::NaiveDateTime;
+    use chrono::Utc;
+
+    struct MockDbHandle;
+
+    fn due(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    fn todo(id: i32, due_date: &str) -> Todo {
+        Todo {
+            id,
+            name: format!("Task {}", id),
+            due_date: Some(due(due_date)),
+            assigned_by: 1,
+            assigned_to: 2,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, GetToDoItemsDueBetween, get_to_do_items_due_between)]
+    async fn get_to_do_items_due_between(user_id: i32, from: NaiveDateTime, to: NaiveDateTime) -> Result<Vec<Todo>, NanoServiceError> {
+        assert_eq!(user_id, 2);
+        assert_eq!(from, due("2025-02-01T00:00:00"));
+        assert_eq!(to, due("2025-03-01T00:00:00"));
+        Ok(vec![
+            todo(1, "2025-02-03T09:00:00"),
+            todo(2, "2025-02-03T17:30:00"),
+            todo(3, "2025-02-28T23:59:59"),
+        ])
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_items_grouped_by_due_date() {
+        let days = get_calendar_for_user::<MockDbHandle>(2, date("2025-02-01"), date("2025-02-28")).await.unwrap();
+        let grouped: Vec<(NaiveDate, Vec<i32>)> = days.iter()
+            .map(|day| (day.date, day.items.iter().map(|item| item.id).collect()))
+            .collect();
+        assert_eq!(grouped, vec![
+            (date("2025-02-03"), vec![1, 2]),
+            (date("2025-02-28"), vec![3]),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_ranges_rejected() {
+        let error = get_calendar_for_user::<MockDbHandle>(2, date("2025-02-02"), date("2025-02-01")).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        let error = get_calendar_for_user::<MockDbHandle>(2, date("2025-01-01"), date("2026-01-02")).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+}
@@ -0,0 +1,310 @@
+//! Core logic for adding replies to to-do item emails as comments.
+//!
+//! # Overview
+//! Each email is matched to a to-do item by its reference, see `email_core::inbound::todo_reference`,
+//! and to a user by the address it was sent from. It is added as a comment from that user once the
+//! quoted message is removed. An email is skipped when it cannot be matched, the sender is neither
+//! the assigner nor the assignee of the item, or nothing is left once the quote is removed, so a
+//! stray email is dropped rather than retried by the provider. The other party on the item is
+//! queued a comment notification for each comment added.
+//!
+//! Comments are moderated, a rejected comment is skipped in the same way so the provider does not
+//! retry it, the rejection is recorded for admins to review.
+use serde::Serialize;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use dal::todo_comments::tx_definitions::CreateToDoComment;
+use dal::users::tx_definitions::GetUserByEmail;
+use dal::notifications::tx_definitions::QueueNotification;
+use dal::moderation::tx_definitions::RecordModerationDecision;
+use email_core::inbound::{InboundEmail, todo_reference, strip_quoted_reply};
+use kernel::notifications::NotificationType;
+use kernel::todo_comments::NewToDoComment;
+use kernel::moderation::{ModerateText, ModeratedContent};
+use crate::api::notifications::batching::notify_user;
+use crate::api::moderation::screen::{screen_text, record_flag};
+
+
+/// What happened to the emails in an inbound webhook.
+///
+/// # Fields
+/// * `added` - The emails added as comments.
+/// * `skipped` - The emails that were not matched, were from an unrelated user, were empty, were
+///   rejected by moderation or were already added.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct InboundEmailOutcome {
+    pub added: usize,
+    pub skipped: usize,
+}
+
+
+/// Treats a `NotFound` error as no match and passes any other error on.
+fn not_found_as_none<T>(result: Result<T, NanoServiceError>) -> Result<Option<T>, NanoServiceError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.status == NanoServiceErrorStatus::NotFound => Ok(None),
+        Err(e) => Err(e)
+    }
+}
+
+
+/// Adds one email as a comment.
+///
+/// # Returns
+/// * `Ok(true)` - If the comment was added
+/// * `Ok(false)` - If the email was skipped
+async fn add_comment_from_email<X, Y, M>(email: InboundEmail) -> Result<bool, NanoServiceError>
+where
+    X: GetUserByEmail + GetToDoItem + CreateToDoComment + QueueNotification + RecordModerationDecision,
+    Y: GetConfigVariable,
+    M: ModerateText
+{
+    let todo_id = match todo_reference(&email) {
+        Some(todo_id) => todo_id,
+        None => return Ok(false)
+    };
+    let body = strip_quoted_reply(&email.text);
+    if body.is_empty() {
+        return Ok(false)
+    }
+    let author = match not_found_as_none(X::get_user_by_email(email.from.trim().to_lowercase()).await)? {
+        Some(author) => author,
+        None => return Ok(false)
+    };
+    let todo = match not_found_as_none(X::get_to_do_item(todo_id).await)? {
+        Some(todo) => todo,
+        None => return Ok(false)
+    };
+    if author.id != todo.assigned_to && author.id != todo.assigned_by {
+        return Ok(false)
+    }
+    let flag = match screen_text::<X, M, Y>(ModeratedContent::Comment, None, author.id, &body).await {
+        Ok(flag) => flag,
+        Err(e) if e.status == NanoServiceErrorStatus::UnprocessableEntity => return Ok(false),
+        Err(e) => return Err(e)
+    };
+    let comment = NewToDoComment {
+        todo_id,
+        author_id: author.id,
+        body,
+        message_id: email.message_id,
+    };
+    let comment = match X::create_to_do_comment(comment).await? {
+        Some(comment) => comment,
+        None => return Ok(false)
+    };
+    record_flag::<X>(flag, comment.id).await?;
+    let other_party = match author.id == todo.assigned_to {
+        true => todo.assigned_by,
+        false => todo.assigned_to
+    };
+    if other_party != author.id {
+        notify_user::<X>(other_party, NotificationType::Comment, &todo).await;
+    }
+    Ok(true)
+}
+
+
+/// Adds the emails from an inbound webhook as comments on the to-do items they reply to.
+///
+/// # Arguments
+/// * `emails` - The emails parsed from the webhook.
+///
+/// # Returns
+/// * `Ok(InboundEmailOutcome)` - How many emails were added and skipped
+/// * `Err(NanoServiceError)` - If a lookup or insert failed, so the provider retries the webhook
+pub async fn add_comments_from_emails<X, Y, M>(emails: Vec<InboundEmail>) -> Result<InboundEmailOutcome, NanoServiceError>
+where
+    X: GetUserByEmail + GetToDoItem + CreateToDoComment + QueueNotification + RecordModerationDecision,
+    Y: GetConfigVariable,
+    M: ModerateText
+{
+    let mut outcome = InboundEmailOutcome::default();
+    for email in emails {
+        match add_comment_from_email::<X, Y, M>(email).await? {
+            true => outcome.added += 1,
+            false => outcome.skipped += 1
+        }
+    }
+    Ok(outcome)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use kernel::todo_comments::ToDoComment;
+    use kernel::notifications::{NewNotification, PendingNotification};
+    use kernel::users::{User, UserRole};
+    use kernel::moderation::{NewModerationDecision, ModerationDecision, ModerationAction};
+    use kernel::moderation::engine_wordlist::WordlistModerator;
+    use chrono::Utc;
+    use std::sync::Mutex;
+
+    static ADDED: Mutex<Vec<NewToDoComment>> = Mutex::new(Vec::new());
+    static NOTIFIED: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+    static MODERATED: Mutex<Vec<NewModerationDecision>> = Mutex::new(Vec::new());
+
+    struct MockDbHandle;
+
+    /// Blocks `spam` and flags `urgent`.
+    struct WordlistConfig;
+
+    impl GetConfigVariable for WordlistConfig {
+        fn get_config_variable(key: String) -> Result<String, NanoServiceError> {
+            match key.as_str() {
+                "MODERATION_BLOCKED_WORDS" => Ok("spam".to_string()),
+                "MODERATION_FLAGGED_WORDS" => Ok("urgent".to_string()),
+                _ => Err(NanoServiceError::new("not set".to_string(), NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    /// User 2 assigned item 12 to user 3, user 4 is unrelated.
+    #[impl_transaction(MockDbHandle, GetUserByEmail, get_user_by_email)]
+    async fn get_user_by_email(email: String) -> Result<User, NanoServiceError> {
+        let id = match email.as_str() {
+            "assigner@example.com" => 2,
+            "assignee@example.com" => 3,
+            "other@example.com" => 4,
+            _ => return Err(NanoServiceError::new("not found".to_string(), NanoServiceErrorStatus::NotFound))
+        };
+        Ok(User {
+            id,
+            confirmed: true,
+            username: format!("user{}", id),
+            email,
+            first_name: "first".to_string(),
+            last_name: "last".to_string(),
+            user_role: UserRole::Worker,
+            password: "password".to_string(),
+            uuid: "uuid".to_string(),
+            date_created: Utc::now().naive_utc(),
+            last_logged_in: Utc::now().naive_utc(),
+            blocked: false,
+            avatar_version: None,
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        if todo_id != 12 {
+            return Err(NanoServiceError::new("not found".to_string(), NanoServiceErrorStatus::NotFound))
+        }
+        Ok(Todo {
+            id: todo_id,
+            name: "Task".to_string(),
+            due_date: None,
+            assigned_by: 2,
+            assigned_to: 3,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        })
+    }
+
+    /// Message `<seen>` was already added.
+    #[impl_transaction(MockDbHandle, CreateToDoComment, create_to_do_comment)]
+    async fn create_to_do_comment(comment: NewToDoComment) -> Result<Option<ToDoComment>, NanoServiceError> {
+        if comment.message_id.as_deref() == Some("<seen>") {
+            return Ok(None)
+        }
+        ADDED.lock().unwrap().push(comment.clone());
+        Ok(Some(ToDoComment {
+            id: 1,
+            todo_id: comment.todo_id,
+            author_id: comment.author_id,
+            body: comment.body,
+            message_id: comment.message_id,
+            date_created: Utc::now().naive_utc(),
+        }))
+    }
+
+    #[impl_transaction(MockDbHandle, QueueNotification, queue_notification)]
+    async fn queue_notification(notification: NewNotification) -> Result<PendingNotification, NanoServiceError> {
+        assert_eq!(notification.notification_type, NotificationType::Comment);
+        NOTIFIED.lock().unwrap().push(notification.user_id);
+        Ok(PendingNotification {
+            id: 1,
+            user_id: notification.user_id,
+            notification_type: notification.notification_type,
+            todo_id: notification.todo_id,
+            todo_name: notification.todo_name,
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, RecordModerationDecision, record_moderation_decision)]
+    async fn record_moderation_decision(decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+        MODERATED.lock().unwrap().push(decision.clone());
+        Ok(ModerationDecision {
+            id: 1,
+            content: decision.content,
+            entity_id: decision.entity_id,
+            author_id: decision.author_id,
+            action: decision.action,
+            reasons: decision.reasons,
+            text: decision.text,
+            created_at: Utc::now().naive_utc(),
+            reviewed_by: None,
+            reviewed_at: None,
+            upheld: None,
+        })
+    }
+
+    fn email(from: &str, to: &str, text: &str, message_id: &str) -> InboundEmail {
+        InboundEmail {
+            message_id: Some(message_id.to_string()),
+            from: from.to_string(),
+            to: vec![to.to_string()],
+            subject: "Re: Task".to_string(),
+            text: text.to_string(),
+            references: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_comments_from_emails() {
+        let emails = vec![
+            email("Assignee@example.com", "reply+todo-12@inbound.example.com", "On it\n\nOn Mon, Bob wrote:\n> Task", "<a>"),
+            email("assigner@example.com", "reply+todo-12@inbound.example.com", "Thanks", "<b>"),
+            email("other@example.com", "reply+todo-12@inbound.example.com", "Not mine", "<c>"),
+            email("stranger@example.com", "reply+todo-12@inbound.example.com", "Who?", "<d>"),
+            email("assignee@example.com", "reply+todo-99@inbound.example.com", "Gone", "<e>"),
+            email("assignee@example.com", "reply@inbound.example.com", "No tag", "<f>"),
+            email("assignee@example.com", "reply+todo-12@inbound.example.com", "> quote only", "<g>"),
+            email("assignee@example.com", "reply+todo-12@inbound.example.com", "Again", "<seen>"),
+            email("assignee@example.com", "reply+todo-12@inbound.example.com", "Buy spam", "<h>"),
+            email("assigner@example.com", "reply+todo-12@inbound.example.com", "Urgent please", "<i>"),
+        ];
+        let outcome = add_comments_from_emails::<MockDbHandle, WordlistConfig, WordlistModerator>(emails).await.unwrap();
+        assert_eq!(outcome, InboundEmailOutcome { added: 3, skipped: 7 });
+
+        let added = ADDED.lock().unwrap();
+        assert_eq!(added[0], NewToDoComment {
+            todo_id: 12,
+            author_id: 3,
+            body: "On it".to_string(),
+            message_id: Some("<a>".to_string()),
+        });
+        assert_eq!(added[1].author_id, 2);
+        assert_eq!(added.len(), 3);
+
+        // the assigner hears about the assignee's comment and the other way round
+        assert_eq!(*NOTIFIED.lock().unwrap(), vec![2, 3, 3]);
+
+        let moderated = MODERATED.lock().unwrap();
+        assert_eq!(moderated.len(), 2);
+        assert_eq!((moderated[0].action, moderated[0].entity_id), (ModerationAction::Rejected, None));
+        assert_eq!((moderated[1].action, moderated[1].entity_id), (ModerationAction::Flagged, Some(1)));
+    }
+}
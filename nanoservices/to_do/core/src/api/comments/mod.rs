@@ -0,0 +1 @@
+pub mod inbound_email;
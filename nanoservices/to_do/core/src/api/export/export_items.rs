@@ -0,0 +1,25 @@
+//! Core logic for admins exporting every to-do item.
+//!
+//! # Notes
+//! The export has the columns the import reads, with the assignee as an email rather than an ID, so
+//! an export can be imported into another system as it is.
+use dal::to_do_items::tx_definitions::ListExportedToDoItems;
+use kernel::to_do_items::ExportedTodo;
+use utils::errors::NanoServiceError;
+use utils::export_stream::{export_stream, Bytes, ExportEncoding, ExportFormat, Stream, DEFAULT_EXPORT_PAGE_SIZE};
+
+
+/// Streams every to-do item oldest first, reading `DEFAULT_EXPORT_PAGE_SIZE` items at a time.
+///
+/// # Arguments
+/// - `format`: CSV or JSON.
+/// - `encoding`: Whether to gzip the export.
+///
+/// # Returns
+/// - The bytes of the export, read from the database as the client takes them.
+pub fn export_to_do_items<X: ListExportedToDoItems>(format: ExportFormat, encoding: ExportEncoding) -> impl Stream<Item = Result<Bytes, NanoServiceError>> {
+    export_stream(format, encoding, DEFAULT_EXPORT_PAGE_SIZE, |todo: &ExportedTodo| todo.id as i64, |after| {
+        // the cursor only ever holds a to-do item ID so it always fits
+        X::list_exported_to_do_items(after.map(|id| id as i32), DEFAULT_EXPORT_PAGE_SIZE as i64)
+    })
+}
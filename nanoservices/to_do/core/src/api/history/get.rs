@@ -0,0 +1,91 @@
+//! Core logic for reading the history of a to-do item.
+//!
+//! # Overview
+//! Every change to an item is recorded as it is made by the core functions that make it, this reads
+//! them back in the order they were made so users can see who changed what and when.
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use dal::todo_events::tx_definitions::ListToDoEvents;
+use kernel::todo_events::{can_view_history, TodoEvent};
+use kernel::users::UserRole;
+
+
+/// Lists the changes made to a to-do item, the oldest first.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item.
+/// - `user_id`: The ID of the user reading the history.
+/// - `role`: The role of the user reading the history.
+///
+/// # Returns
+/// - `Ok(Vec<TodoEvent>)`: The changes made to the item.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such item, `Forbidden` if the user neither
+///   assigned it nor is assigned it, and is not an admin.
+pub async fn get_to_do_item_history<X>(todo_id: i32, user_id: i32, role: &UserRole) -> Result<Vec<TodoEvent>, NanoServiceError>
+where
+    X: GetToDoItem + ListToDoEvents
+{
+    let todo = X::get_to_do_item(todo_id).await?;
+    if !can_view_history(&todo, user_id, role) {
+        return Err(NanoServiceError::new(
+            "Only the users the to-do item is assigned by and to, or an admin, can see its history".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    X::list_to_do_events(todo_id).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use kernel::todo_events::TodoEventKind;
+
+    /// Item 1 is assigned by user 2 to user 3 and was created then completed.
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(Todo {
+            id: todo_id,
+            name: "write report".to_string(),
+            due_date: None,
+            assigned_by: 2,
+            assigned_to: 3,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, ListToDoEvents, list_to_do_events)]
+    async fn list_to_do_events(todo_id: i32) -> Result<Vec<TodoEvent>, NanoServiceError> {
+        Ok([(2, TodoEventKind::Created), (3, TodoEventKind::Completed)].into_iter().zip(1..).map(|((actor_id, kind), id)| TodoEvent {
+            id,
+            todo_id,
+            actor_id: Some(actor_id),
+            kind,
+            details: sqlx::types::Json(serde_json::json!({})),
+            date_created: Utc::now().naive_utc(),
+        }).collect())
+    }
+
+    #[tokio::test]
+    async fn test_get_to_do_item_history() {
+        let history = get_to_do_item_history::<MockDbHandle>(1, 3, &UserRole::Worker).await.unwrap();
+        assert_eq!(history.iter().map(|event| event.kind).collect::<Vec<_>>(), vec![TodoEventKind::Created, TodoEventKind::Completed]);
+        assert!(get_to_do_item_history::<MockDbHandle>(1, 9, &UserRole::Admin).await.is_ok());
+
+        let not_theirs = get_to_do_item_history::<MockDbHandle>(1, 9, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(not_theirs.status, NanoServiceErrorStatus::Forbidden);
+    }
+}
@@ -0,0 +1,639 @@
+//! Core logic for importing to-do items from a CSV or JSON file.
+//!
+//! # Overview
+//! A CSV has a header row with the columns `name`, `description`, `due_date` and `assignee_email`,
+//! and optionally `priority`. A JSON file is an array of objects with the same fields. Both are the
+//! shape written by the to-do export, so items can be moved between systems, and the export's other
+//! columns are ignored.
+//!
+//! Every row is validated and its assignee resolved by email before anything is created. If every
+//! row is valid the items are created together in a single database transaction, otherwise nothing
+//! is created. Either way the outcome of every row is returned in an `ImportReport` so the admin can
+//! fix the failed rows and re-submit the file. A dry run validates the file the same way and reports
+//! the rows that would be created without creating them.
+//!
+//! # Features
+//! - Resolves assignees using `GetUserByEmail`, looking each email up once per import.
+//! - Creates the items using `ImportToDoItems`, recording each in its history as created by the admin.
+//! - Fails rows that would take an assignee past `TODO_MAX_OPEN_PER_USER`, imports cannot override the cap.
+//! - Fails rows whose description is rejected by moderation.
+//! - Fails rows once the organization has as many to-do items as its plan allows.
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::export_stream::ExportFormat;
+use dal::to_do_items::tx_definitions::{ImportToDoItems, CountOpenToDoItemsForUser, CountToDoItems};
+use dal::plans::tx_definitions::GetOrgPlan;
+use dal::users::tx_definitions::GetUserByEmail;
+use dal::moderation::tx_definitions::RecordModerationDecision;
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use kernel::to_do_items::{NewTodo, TodoPriority};
+use kernel::todo_events::NewTodoEvent;
+use kernel::moderation::{ModerateText, ModeratedContent, ModerationVerdict, NewModerationDecision};
+use utils::validation::field_violations;
+use validator::Validate;
+use kernel::chrono::{NaiveDate, NaiveDateTime};
+use std::str::FromStr;
+use crate::api::basic_actions::capacity::max_open_items;
+use crate::api::moderation::screen::{screen_text, record_flag};
+
+
+/// The most rows accepted in a single import.
+pub const MAX_IMPORT_ROWS: usize = 1000;
+
+/// The columns the CSV header must contain.
+const REQUIRED_COLUMNS: [&str; 4] = ["name", "description", "due_date", "assignee_email"];
+
+
+/// A row of the file as it was submitted.
+#[derive(Deserialize, Debug)]
+struct ImportRow {
+    name: String,
+    description: Option<String>,
+    due_date: Option<String>,
+    assignee_email: String,
+    #[serde(default)]
+    priority: Option<String>,
+}
+
+
+/// The outcome of importing a single row.
+///
+/// # Variants
+/// * `Created` - The row was created as the item `todo_id`.
+/// * `Valid` - The row is valid but was not created, as the import was a dry run or another row failed.
+/// * `Failed` - The row was rejected with `error`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ImportRowOutcome {
+    Created { todo_id: i32 },
+    Valid,
+    Failed { error: String },
+}
+
+
+/// The outcome of a row along with where it is in the file. For a CSV this is its line number,
+/// counting the header as line 1, and for JSON its position in the array counting from 1.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ImportRowReport {
+    pub line: usize,
+    #[serde(flatten)]
+    pub outcome: ImportRowOutcome,
+}
+
+
+/// The report returned for an import.
+///
+/// # Fields
+/// * `dry_run` - Whether the import was only validated.
+/// * `created` - The number of items created, `0` for a dry run or if any row failed.
+/// * `failed` - The number of rows that were rejected.
+/// * `rows` - The outcome of every row in the order they were submitted.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ImportReport {
+    #[serde(default)]
+    pub dry_run: bool,
+    pub created: usize,
+    pub failed: usize,
+    pub rows: Vec<ImportRowReport>,
+}
+
+
+/// A row read from the file with its line in a CSV or its position in a JSON array, or why it could not be read.
+type ReadRow = (usize, Result<ImportRow, String>);
+
+
+/// Parses a due date given either as a date or as a date and time.
+fn parse_due_date(due_date: &str) -> Result<NaiveDateTime, String> {
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(due_date, "%Y-%m-%dT%H:%M:%S") {
+        return Ok(date_time)
+    }
+    NaiveDate::parse_from_str(due_date, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap())
+        .map_err(|_| format!("due_date '{}' is not YYYY-MM-DD or YYYY-MM-DDTHH:MM:SS", due_date))
+}
+
+
+/// Reads the rows of a CSV file along with their line numbers.
+fn read_csv_rows(file: &str) -> Result<Vec<ReadRow>, NanoServiceError> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::Headers).from_reader(file.as_bytes());
+    let headers = reader.headers().map_err(|e| NanoServiceError::new(
+        format!("Failed to read CSV header: {}", e),
+        NanoServiceErrorStatus::BadRequest,
+    ))?.clone();
+    for column in REQUIRED_COLUMNS {
+        if !headers.iter().any(|header| header == column) {
+            return Err(NanoServiceError::new(
+                format!("CSV is missing the {} column", column),
+                NanoServiceErrorStatus::BadRequest,
+            ))
+        }
+    }
+    Ok(reader.deserialize::<ImportRow>().enumerate()
+        .map(|(index, record)| (index + 2, record.map_err(|e| format!("malformed row: {}", e))))
+        .collect())
+}
+
+
+/// Reads the rows of a JSON array along with their positions in it.
+fn read_json_rows(file: &str) -> Result<Vec<ReadRow>, NanoServiceError> {
+    let items: Vec<serde_json::Value> = serde_json::from_str(file).map_err(|e| NanoServiceError::new(
+        format!("Failed to read JSON array: {}", e),
+        NanoServiceErrorStatus::BadRequest,
+    ))?;
+    Ok(items.into_iter().enumerate()
+        .map(|(index, item)| (index + 1, serde_json::from_value(item).map_err(|e| format!("malformed row: {}", e))))
+        .collect())
+}
+
+
+/// Converts a row into a `NewTodo`, resolving the assignee by email.
+async fn validate_row<X: GetUserByEmail>(
+    row: ImportRow,
+    assigned_by: i32,
+    assignees: &mut HashMap<String, Result<i32, String>>,
+) -> Result<NewTodo, String> {
+    let name = row.name.trim().to_string();
+    if name.is_empty() {
+        return Err("name is empty".to_string())
+    }
+    let due_date = match row.due_date.as_deref().map(str::trim) {
+        Some("") | None => None,
+        Some(due_date) => Some(parse_due_date(due_date)?)
+    };
+    let priority = match row.priority.as_deref().map(str::trim) {
+        Some("") | None => TodoPriority::Medium,
+        Some(priority) => TodoPriority::from_str(&priority.to_lowercase())
+            .map_err(|_| format!("priority '{}' is not low, medium or high", priority))?
+    };
+    let email = row.assignee_email.trim().to_lowercase();
+    if !assignees.contains_key(&email) {
+        let assignee = match X::get_user_by_email(email.clone()).await {
+            Ok(user) if user.blocked => Err(format!("assignee {} is blocked", email)),
+            Ok(user) => Ok(user.id),
+            Err(_) => Err(format!("assignee {} does not exist", email))
+        };
+        assignees.insert(email.clone(), assignee);
+    }
+    let assigned_to = assignees[&email].clone()?;
+    let new_todo = NewTodo {
+        name,
+        due_date,
+        assigned_by,
+        assigned_to,
+        description: row.description.map(|description| description.trim().to_string()).filter(|d| !d.is_empty()),
+        date_assigned: None,
+        requires_review: false,
+        priority,
+    };
+    if let Err(errors) = new_todo.validate() {
+        let violations: Vec<String> = field_violations(&errors).into_iter()
+            .map(|(field, messages)| format!("{} {}", field, messages.join(", ")))
+            .collect();
+        return Err(violations.join("; "))
+    }
+    Ok(new_todo)
+}
+
+
+/// Checks the cap for a row, counting the assignee's open items the first time they are seen.
+async fn within_capacity<X: CountOpenToDoItemsForUser>(
+    assigned_to: i32,
+    limit: Option<i64>,
+    open_items: &mut HashMap<i32, i64>,
+) -> Result<(), String> {
+    let limit = match limit {
+        Some(limit) => limit,
+        None => return Ok(())
+    };
+    let count = match open_items.get(&assigned_to) {
+        Some(count) => *count,
+        None => {
+            let count = X::count_open_to_do_items_for_user(assigned_to).await.map_err(|e| e.message)?;
+            open_items.insert(assigned_to, count);
+            count
+        }
+    };
+    if count >= limit {
+        return Err(format!("assignee already has {} open to-do items, the limit is {}", count, limit))
+    }
+    Ok(())
+}
+
+
+/// Moderates the description of a validated row, returning the flag to record once it is created.
+///
+/// # Notes
+/// A dry run only asks the moderator, so nothing is recorded for text that is never stored.
+async fn screen_row<X, Y, M>(new_todo: &NewTodo, dry_run: bool) -> Result<Option<NewModerationDecision>, String>
+where
+    X: RecordModerationDecision,
+    Y: GetConfigVariable,
+    M: ModerateText
+{
+    let description = match new_todo.description.as_deref() {
+        Some(description) => description,
+        None => return Ok(None)
+    };
+    if dry_run {
+        return match M::moderate_text::<Y>(description).await {
+            Ok(ModerationVerdict::Reject { .. }) => Err("The text was rejected by moderation".to_string()),
+            _ => Ok(None)
+        }
+    }
+    screen_text::<X, M, Y>(ModeratedContent::TodoDescription, None, new_todo.assigned_by, description)
+        .await
+        .map_err(|e| e.message)
+}
+
+
+/// Imports to-do items from a CSV or JSON file.
+///
+/// # Arguments
+/// - `file`: The file contents, a CSV including the header row or a JSON array.
+/// - `format`: Whether the file is CSV or JSON.
+/// - `dry_run`: Whether to only validate the file.
+/// - `assigned_by`: The ID of the admin running the import.
+/// - `org_id`: The ID of the organization the items belong to.
+///
+/// # Returns
+/// - `Ok(ImportReport)`: The outcome of every row, even if some rows failed.
+/// - `Err(NanoServiceError)`: `BadRequest` if the file cannot be read, is missing a column or has too
+///   many rows, or the error from the database if the items could not be created, in which case none are.
+pub async fn import_to_do_items<X, Y, M>(file: &str, format: ExportFormat, dry_run: bool, assigned_by: i32, org_id: i32) -> Result<ImportReport, NanoServiceError>
+where
+    X: ImportToDoItems + GetUserByEmail + CountOpenToDoItemsForUser + RecordModerationDecision
+        + GetOrgPlan + CountToDoItems + RecordToDoEvent,
+    Y: GetConfigVariable,
+    M: ModerateText
+{
+    let records = match format {
+        ExportFormat::Csv => read_csv_rows(file)?,
+        ExportFormat::Json => read_json_rows(file)?,
+    };
+    if records.len() > MAX_IMPORT_ROWS {
+        return Err(NanoServiceError::new(
+            format!("The file has {} rows, the limit is {}", records.len(), MAX_IMPORT_ROWS),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+
+    let mut assignees = HashMap::new();
+    let mut validated = Vec::with_capacity(records.len());
+    for (line, record) in records {
+        let outcome = match record {
+            Ok(row) => validate_row::<X>(row, assigned_by, &mut assignees).await,
+            Err(error) => Err(error)
+        };
+        validated.push((line, outcome));
+    }
+
+    let limit = max_open_items::<Y>();
+    let entitlements = X::get_org_plan().await?.entitlements();
+    let mut todos = X::count_to_do_items().await?;
+    let mut open_items = HashMap::new();
+    let mut checked = Vec::with_capacity(validated.len());
+    for (line, outcome) in validated {
+        let outcome = match outcome {
+            Ok(new_todo) => match within_capacity::<X>(new_todo.assigned_to, limit, &mut open_items).await
+                .and_then(|_| entitlements.check_max_todos(todos).map_err(|e| e.message)) {
+                Ok(()) => match screen_row::<X, Y, M>(&new_todo, dry_run).await {
+                    Ok(flag) => {
+                        open_items.entry(new_todo.assigned_to).and_modify(|count| *count += 1);
+                        todos += 1;
+                        Ok((new_todo, flag))
+                    },
+                    Err(error) => Err(error)
+                },
+                Err(error) => Err(error)
+            },
+            Err(error) => Err(error)
+        };
+        checked.push((line, outcome));
+    }
+
+    let failed = checked.iter().filter(|(_, outcome)| outcome.is_err()).count();
+    if dry_run || failed > 0 {
+        let rows = checked.into_iter().map(|(line, outcome)| ImportRowReport {
+            line,
+            outcome: match outcome {
+                Ok(_) => ImportRowOutcome::Valid,
+                Err(error) => ImportRowOutcome::Failed { error }
+            }
+        }).collect();
+        return Ok(ImportReport { dry_run, created: 0, failed, rows })
+    }
+
+    let (lines, valid): (Vec<usize>, Vec<(NewTodo, Option<NewModerationDecision>)>) = checked.into_iter()
+        .filter_map(|(line, outcome)| outcome.ok().map(|valid| (line, valid)))
+        .unzip();
+    let (new_todos, flags): (Vec<NewTodo>, Vec<Option<NewModerationDecision>>) = valid.into_iter().unzip();
+    let created = X::import_to_do_items(new_todos, org_id).await?;
+    let mut rows = Vec::with_capacity(created.len());
+    for ((line, flag), todo) in lines.into_iter().zip(flags).zip(created) {
+        X::record_to_do_event(NewTodoEvent::created(&todo, assigned_by)).await?;
+        record_flag::<X>(flag, todo.id).await?;
+        rows.push(ImportRowReport { line, outcome: ImportRowOutcome::Created { todo_id: todo.id } });
+    }
+    Ok(ImportReport { dry_run, created: rows.len(), failed: 0, rows })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::users::{User, UserRole};
+    use kernel::to_do_items::{Todo, TodoStatus};
+    use kernel::moderation::{NewModerationDecision, ModerationDecision};
+    use kernel::moderation::engine_mock::{AllowTextMock, RejectTextMock};
+    use kernel::plans::{OrgPlan, Plan};
+    use kernel::todo_events::TodoEvent;
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+    static RECORDED: AtomicUsize = AtomicUsize::new(0);
+
+    struct UncappedConfig;
+
+    impl GetConfigVariable for UncappedConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("0".to_string())
+        }
+    }
+
+    struct CappedConfig;
+
+    impl GetConfigVariable for CappedConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("2".to_string())
+        }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, CountOpenToDoItemsForUser, count_open_to_do_items_for_user)]
+    async fn count_open_to_do_items_for_user(_user_id: i32) -> Result<i64, NanoServiceError> {
+        Ok(1)
+    }
+
+    #[impl_transaction(MockDbHandle, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(OrgPlan {
+            plan: Plan::Free,
+            max_users: None,
+            max_todos: None,
+            mfa: None,
+            api_access: None,
+            date_updated: Utc::now().naive_utc(),
+        })
+    }
+
+    /// The free plan allows 100 items, so each import can create 3.
+    #[impl_transaction(MockDbHandle, CountToDoItems, count_to_do_items)]
+    async fn count_to_do_items() -> Result<i64, NanoServiceError> {
+        Ok(97)
+    }
+
+    fn user(id: i32, email: String, blocked: bool) -> User {
+        let now = Utc::now().naive_utc();
+        User {
+            id,
+            confirmed: true,
+            username: email.clone(),
+            email,
+            first_name: "first".to_string(),
+            last_name: "last".to_string(),
+            user_role: UserRole::Worker,
+            password: "password".to_string(),
+            uuid: "uuid".to_string(),
+            date_created: now,
+            last_logged_in: now,
+            blocked,
+            avatar_version: None,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, GetUserByEmail, get_user_by_email)]
+    async fn get_user_by_email(email: String) -> Result<User, NanoServiceError> {
+        LOOKUPS.fetch_add(1, Ordering::Relaxed);
+        match email.as_str() {
+            "worker@example.com" => Ok(user(3, email, false)),
+            "blocked@example.com" => Ok(user(4, email, true)),
+            _ => Err(NanoServiceError::new("Failed to retrieve user".to_string(), NanoServiceErrorStatus::NotFound))
+        }
+    }
+
+    /// The items are created as IDs from 10, and none are if any is named `fails to save`.
+    #[impl_transaction(MockDbHandle, ImportToDoItems, import_to_do_items)]
+    async fn import_to_do_items(todos: Vec<NewTodo>, _org_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
+        if todos.iter().any(|todo| todo.name == "fails to save") {
+            return Err(NanoServiceError::new("Failed to import to-do item 'fails to save'".to_string(), NanoServiceErrorStatus::Unknown))
+        }
+        Ok(todos.into_iter().zip(10..).map(|(todo, id)| Todo {
+            id,
+            name: todo.name,
+            due_date: todo.due_date,
+            assigned_by: todo.assigned_by,
+            assigned_to: todo.assigned_to,
+            description: todo.description,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: todo.priority,
+            status: TodoStatus::Backlog,
+        }).collect())
+    }
+
+    #[impl_transaction(MockDbHandle, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        RECORDED.fetch_add(1, Ordering::Relaxed);
+        Ok(TodoEvent {
+            id: 1,
+            todo_id: event.todo_id,
+            actor_id: Some(event.actor_id),
+            kind: event.kind,
+            details: sqlx::types::Json(event.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, RecordModerationDecision, record_moderation_decision)]
+    async fn record_moderation_decision(decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+        Ok(ModerationDecision {
+            id: 1,
+            content: decision.content,
+            entity_id: decision.entity_id,
+            author_id: decision.author_id,
+            action: decision.action,
+            reasons: decision.reasons,
+            text: decision.text,
+            created_at: Utc::now().naive_utc(),
+            reviewed_by: None,
+            reviewed_at: None,
+            upheld: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_every_row() {
+        let csv = "\
+name,description,due_date,assignee_email
+write report,\"quarterly, with charts\",2025-03-01,Worker@example.com
+review report,,2025-03-02T12:30:00,worker@example.com
+,no name,,worker@example.com
+bad date,,03/01/2025,worker@example.com
+blocked,,,blocked@example.com
+missing,,,nobody@example.com
+";
+        LOOKUPS.store(0, Ordering::Relaxed);
+        let report = import_to_do_items::<MockDbHandle, UncappedConfig, AllowTextMock>(csv, ExportFormat::Csv, false, 1, 1).await.unwrap();
+
+        // a failed row stops the whole import, the valid rows are reported so the file can be fixed
+        assert_eq!((report.created, report.failed), (0, 4));
+        assert_eq!(report.rows[0], ImportRowReport { line: 2, outcome: ImportRowOutcome::Valid });
+        assert_eq!(report.rows[2], ImportRowReport {
+            line: 4, outcome: ImportRowOutcome::Failed { error: "name is empty".to_string() }
+        });
+        assert_eq!(report.rows[4], ImportRowReport {
+            line: 6, outcome: ImportRowOutcome::Failed { error: "assignee blocked@example.com is blocked".to_string() }
+        });
+        assert_eq!(report.rows[5], ImportRowReport {
+            line: 7, outcome: ImportRowOutcome::Failed { error: "assignee nobody@example.com does not exist".to_string() }
+        });
+        // the worker is looked up once however many rows they are assigned
+        assert_eq!(LOOKUPS.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_import_creates_every_row() {
+        let csv = "\
+name,description,due_date,assignee_email,priority
+write report,\"quarterly, with charts\",2025-03-01,worker@example.com,high
+review report,,2025-03-02T12:30:00,worker@example.com,
+";
+        let report = import_to_do_items::<MockDbHandle, UncappedConfig, AllowTextMock>(csv, ExportFormat::Csv, false, 1, 1).await.unwrap();
+        assert_eq!((report.dry_run, report.created, report.failed), (false, 2, 0));
+        assert_eq!(report.rows[1], ImportRowReport { line: 3, outcome: ImportRowOutcome::Created { todo_id: 11 } });
+        assert!(RECORDED.load(Ordering::Relaxed) >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_import_json() {
+        let json = r#"[
+            {"id": 4, "name": "write report", "description": null, "due_date": "2025-03-01T00:00:00",
+             "assignee_email": "worker@example.com", "priority": "low", "finished": false},
+            {"name": "review report", "assignee_email": "worker@example.com", "priority": "urgent"},
+            {"name": "no assignee"}
+        ]"#;
+        let report = import_to_do_items::<MockDbHandle, UncappedConfig, AllowTextMock>(json, ExportFormat::Json, false, 1, 1).await.unwrap();
+        assert_eq!((report.created, report.failed), (0, 2));
+        assert_eq!(report.rows[0], ImportRowReport { line: 1, outcome: ImportRowOutcome::Valid });
+        assert_eq!(report.rows[1], ImportRowReport {
+            line: 2, outcome: ImportRowOutcome::Failed { error: "priority 'urgent' is not low, medium or high".to_string() }
+        });
+        assert!(matches!(&report.rows[2].outcome, ImportRowOutcome::Failed { error } if error.starts_with("malformed row")));
+
+        let error = import_to_do_items::<MockDbHandle, UncappedConfig, AllowTextMock>("{}", ExportFormat::Json, false, 1, 1).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_creates_nothing() {
+        // the mock fails to save this row, so a dry run only passes if nothing is saved
+        let csv = "\
+name,description,due_date,assignee_email
+fails to save,,,worker@example.com
+";
+        let report = import_to_do_items::<MockDbHandle, UncappedConfig, AllowTextMock>(csv, ExportFormat::Csv, true, 1, 1).await.unwrap();
+        assert_eq!((report.dry_run, report.created, report.failed), (true, 0, 0));
+        assert_eq!(report.rows[0], ImportRowReport { line: 2, outcome: ImportRowOutcome::Valid });
+    }
+
+    #[tokio::test]
+    async fn test_failed_import_creates_nothing() {
+        let csv = "\
+name,description,due_date,assignee_email
+saved,,,worker@example.com
+fails to save,,,worker@example.com
+";
+        let error = import_to_do_items::<MockDbHandle, UncappedConfig, AllowTextMock>(csv, ExportFormat::Csv, false, 1, 1).await.unwrap_err();
+        assert_eq!(error.message, "Failed to import to-do item 'fails to save'");
+    }
+
+    #[tokio::test]
+    async fn test_import_missing_column() {
+        let error = import_to_do_items::<MockDbHandle, UncappedConfig, AllowTextMock>("name,description\ntask,desc\n", ExportFormat::Csv, false, 1, 1).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        assert_eq!(error.message, "CSV is missing the due_date column");
+    }
+
+    #[tokio::test]
+    async fn test_import_respects_capacity() {
+        let csv = "\
+name,description,due_date,assignee_email
+first,,,worker@example.com
+second,,,worker@example.com
+";
+        let report = import_to_do_items::<MockDbHandle, CappedConfig, AllowTextMock>(csv, ExportFormat::Csv, false, 1, 1).await.unwrap();
+        assert_eq!((report.created, report.failed), (0, 1));
+        assert_eq!(report.rows[1], ImportRowReport {
+            line: 3,
+            outcome: ImportRowOutcome::Failed { error: "assignee already has 2 open to-do items, the limit is 2".to_string() }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_import_respects_plan_limit() {
+        let csv = "\
+name,description,due_date,assignee_email
+first,,,worker@example.com
+second,,,worker@example.com
+third,,,worker@example.com
+fourth,,,worker@example.com
+";
+        let report = import_to_do_items::<MockDbHandle, UncappedConfig, AllowTextMock>(csv, ExportFormat::Csv, false, 1, 1).await.unwrap();
+        assert_eq!((report.created, report.failed), (0, 1));
+        assert_eq!(report.rows[3], ImportRowReport {
+            line: 5,
+            outcome: ImportRowOutcome::Failed { error: "The plan allows at most 100 for max_todos".to_string() }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_import_moderates_descriptions() {
+        let csv = "\
+name,description,due_date,assignee_email
+described,something bad,,worker@example.com
+undescribed,,,worker@example.com
+";
+        for dry_run in [false, true] {
+            let report = import_to_do_items::<MockDbHandle, UncappedConfig, RejectTextMock>(csv, ExportFormat::Csv, dry_run, 1, 1).await.unwrap();
+            assert_eq!((report.created, report.failed), (0, 1));
+            assert_eq!(report.rows[0], ImportRowReport {
+                line: 2, outcome: ImportRowOutcome::Failed { error: "The text was rejected by moderation".to_string() }
+            });
+        }
+    }
+
+    #[test]
+    fn test_parse_due_date() {
+        assert_eq!(parse_due_date("2025-03-01").unwrap().to_string(), "2025-03-01 00:00:00");
+        assert_eq!(parse_due_date("2025-03-01T08:15:00").unwrap().to_string(), "2025-03-01 08:15:00");
+        assert!(parse_due_date("tomorrow").is_err());
+    }
+
+    #[test]
+    fn test_report_serialization() {
+        let row = ImportRowReport { line: 2, outcome: ImportRowOutcome::Created { todo_id: 5 } };
+        assert_eq!(
+            serde_json::to_value(&row).unwrap(),
+            serde_json::json!({"line": 2, "status": "created", "todo_id": 5})
+        );
+    }
+}
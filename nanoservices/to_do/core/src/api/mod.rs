@@ -1 +1,15 @@
 pub mod basic_actions;
+pub mod tags;
+pub mod import;
+pub mod export;
+pub mod attachments;
+pub mod review;
+pub mod sla;
+pub mod calendar;
+pub mod comments;
+pub mod notifications;
+pub mod sync;
+pub mod moderation;
+pub mod history;
+pub mod status;
+pub mod projects;
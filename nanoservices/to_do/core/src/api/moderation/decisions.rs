@@ -0,0 +1,46 @@
+//! Core logic for admins reviewing moderation decisions.
+//!
+//! # Overview
+//! A review records whether the admin agrees with the moderator. Reviewing does not change the
+//! text, a rejected text stays rejected and flagged text stays stored, but overturned decisions
+//! show which words in the wordlist catch text they should not.
+use utils::errors::NanoServiceError;
+use dal::moderation::tx_definitions::{GetModerationDecisions, ReviewModerationDecision};
+use kernel::moderation::ModerationDecision;
+use crate::api::sync::page_size;
+
+
+/// Gets the most recent moderation decisions.
+///
+/// # Arguments
+/// - `pending_only`: Whether to leave out decisions that have been reviewed.
+/// - `limit`: The most decisions to return, see `page_size`.
+///
+/// # Returns
+/// - `Ok(Vec<ModerationDecision>)`: The decisions, newest first.
+/// - `Err(NanoServiceError)`: `BadRequest` if the limit is out of range, or if the query fails.
+pub async fn get_moderation_decisions<X: GetModerationDecisions>(
+    pending_only: bool,
+    limit: Option<i64>
+) -> Result<Vec<ModerationDecision>, NanoServiceError> {
+    X::get_moderation_decisions(pending_only, page_size(limit)?).await
+}
+
+
+/// Records an admin's review of a moderation decision.
+///
+/// # Arguments
+/// - `id`: The ID of the decision.
+/// - `reviewer_id`: The ID of the admin reviewing it.
+/// - `upheld`: Whether the admin agrees with the moderator.
+///
+/// # Returns
+/// - `Ok(ModerationDecision)`: The reviewed decision.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such decision, or if the update fails.
+pub async fn review_moderation_decision<X: ReviewModerationDecision>(
+    id: i32,
+    reviewer_id: i32,
+    upheld: bool
+) -> Result<ModerationDecision, NanoServiceError> {
+    X::review_moderation_decision(id, reviewer_id, upheld).await
+}
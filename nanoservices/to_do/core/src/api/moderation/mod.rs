@@ -0,0 +1,2 @@
+pub mod screen;
+pub mod decisions;
@@ -0,0 +1,155 @@
+//! Core logic for moderating the text users write on to-do items.
+//!
+//! # Overview
+//! Text is passed through the moderator before it is stored. Rejected text is recorded and refused,
+//! flagged text is stored and recorded once the item or comment it belongs to has an ID. If the
+//! moderator cannot be reached the text is let through but flagged, so an outage of an external
+//! moderation API does not stop users working and nothing goes unreviewed.
+use serde_json::json;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::moderation::tx_definitions::RecordModerationDecision;
+use kernel::moderation::{
+    CONTENT_REJECTED_CODE,
+    ModerateText,
+    ModerationVerdict,
+    ModeratedContent,
+    ModerationAction,
+    NewModerationDecision
+};
+
+
+/// Moderates a piece of text and refuses it if it is rejected.
+///
+/// # Arguments
+/// * `content` - The kind of text being moderated.
+/// * `entity_id` - The ID of the item or comment the text belongs to if it is already stored.
+/// * `author_id` - The ID of the user who wrote the text.
+/// * `text` - The text to moderate.
+///
+/// # Returns
+/// * `Ok(None)` - If the text is allowed
+/// * `Ok(Some(NewModerationDecision))` - If the text is flagged, to be passed to `record_flag` once it is stored
+/// * `Err(NanoServiceError)` - `UnprocessableEntity` with the `content_rejected` code and the reasons
+///   if the text is rejected, or if the decision could not be recorded
+pub async fn screen_text<X, M, Y>(
+    content: ModeratedContent,
+    entity_id: Option<i32>,
+    author_id: i32,
+    text: &str
+) -> Result<Option<NewModerationDecision>, NanoServiceError>
+where
+    X: RecordModerationDecision,
+    M: ModerateText,
+    Y: GetConfigVariable
+{
+    let (action, reasons) = match M::moderate_text::<Y>(text).await {
+        Ok(ModerationVerdict::Allow) => return Ok(None),
+        Ok(ModerationVerdict::Flag { reasons }) => (ModerationAction::Flagged, reasons),
+        Ok(ModerationVerdict::Reject { reasons }) => (ModerationAction::Rejected, reasons),
+        Err(e) => (ModerationAction::Flagged, vec![format!("moderation unavailable: {}", e.message)]),
+    };
+    let decision = NewModerationDecision {
+        content,
+        entity_id,
+        author_id,
+        action,
+        reasons: reasons.clone(),
+        text: text.to_string(),
+    };
+    if action == ModerationAction::Flagged {
+        return Ok(Some(decision))
+    }
+    X::record_moderation_decision(decision).await?;
+    Err(NanoServiceError::new(
+        "The text was rejected by moderation".to_string(),
+        NanoServiceErrorStatus::UnprocessableEntity
+    ).with_details(json!({"code": CONTENT_REJECTED_CODE, "reasons": reasons})))
+}
+
+
+/// Records text flagged by `screen_text` against the item or comment it was stored in.
+pub async fn record_flag<X: RecordModerationDecision>(
+    flag: Option<NewModerationDecision>,
+    entity_id: i32
+) -> Result<(), NanoServiceError> {
+    if let Some(mut decision) = flag {
+        decision.entity_id = Some(entity_id);
+        X::record_moderation_decision(decision).await?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::moderation::ModerationDecision;
+    use kernel::moderation::engine_mock::{AllowTextMock, FlagTextMock, RejectTextMock};
+    use chrono::Utc;
+    use std::sync::Mutex;
+
+    static RECORDED: Mutex<Vec<NewModerationDecision>> = Mutex::new(Vec::new());
+
+    struct MockDbHandle;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct UnavailableModerator;
+
+    impl ModerateText for UnavailableModerator {
+        async fn moderate_text<Y: GetConfigVariable>(_text: &str) -> Result<ModerationVerdict, NanoServiceError> {
+            Err(NanoServiceError::new("timed out".to_string(), NanoServiceErrorStatus::Unknown))
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, RecordModerationDecision, record_moderation_decision)]
+    async fn record_moderation_decision(decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+        RECORDED.lock().unwrap().push(decision.clone());
+        Ok(ModerationDecision {
+            id: 1,
+            content: decision.content,
+            entity_id: decision.entity_id,
+            author_id: decision.author_id,
+            action: decision.action,
+            reasons: decision.reasons,
+            text: decision.text,
+            created_at: Utc::now().naive_utc(),
+            reviewed_by: None,
+            reviewed_at: None,
+            upheld: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_screen_text() {
+        let content = ModeratedContent::Comment;
+        let allowed = screen_text::<MockDbHandle, AllowTextMock, MockConfig>(content, None, 2, "fine").await.unwrap();
+        assert_eq!(allowed, None);
+
+        let flag = screen_text::<MockDbHandle, FlagTextMock, MockConfig>(content, None, 2, "odd").await.unwrap().unwrap();
+        assert_eq!(flag.action, ModerationAction::Flagged);
+        assert_eq!(flag.reasons, vec!["test-flag".to_string()]);
+
+        let flag = screen_text::<MockDbHandle, UnavailableModerator, MockConfig>(content, None, 2, "odd").await.unwrap().unwrap();
+        assert_eq!(flag.reasons, vec!["moderation unavailable: timed out".to_string()]);
+        // flags are only recorded once the text is stored
+        assert!(RECORDED.lock().unwrap().is_empty());
+        record_flag::<MockDbHandle>(Some(flag), 7).await.unwrap();
+        assert_eq!(RECORDED.lock().unwrap().pop().unwrap().entity_id, Some(7));
+
+        let error = screen_text::<MockDbHandle, RejectTextMock, MockConfig>(content, Some(3), 2, "bad").await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::UnprocessableEntity);
+        assert_eq!(error.details.unwrap(), json!({"code": "content_rejected", "reasons": ["test-reject"]}));
+        let recorded = RECORDED.lock().unwrap().pop().unwrap();
+        assert_eq!(recorded.action, ModerationAction::Rejected);
+        assert_eq!(recorded.entity_id, Some(3));
+    }
+}
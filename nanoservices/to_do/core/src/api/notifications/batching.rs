@@ -0,0 +1,279 @@
+//! Batches notifications so a burst of events becomes a single email per user.
+//!
+//! # Overview
+//! Events are queued with `queue_notification` instead of being emailed straight away. The batcher
+//! polls for users whose oldest queued notification of a type is older than the type's window and
+//! sends each of them one summary email with everything of that type queued so far, so five
+//! assignments in ten minutes become one email. A window of `0` sends on the next poll. Taking the
+//! notifications removes them, so a failed send is logged and not retried here, pass `EmailOutbox`
+//! as the sender for the send itself to be retried.
+//!
+//! # Variables
+//! * `NOTIFICATION_BATCH_POLL_SECONDS` - How often the batcher polls, defaults to 30
+//! * `NOTIFICATION_BATCH_ASSIGNMENT_SECONDS` - The window for assignments, defaults to 600
+//! * `NOTIFICATION_BATCH_COMMENT_SECONDS` - The window for comments, defaults to 300
+use std::collections::BTreeMap;
+use serde::Serialize;
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::log_limited;
+use utils::errors::NanoServiceError;
+use dal::notifications::tx_definitions::{QueueNotification, TakeDueNotifications};
+use dal::users::tx_definitions::GetUser;
+use email_core::api::mailchimp_emails::notification_summary_email::send_notification_summary_email;
+use email_core::mailchimp_traits::mc_definitions::SendTemplate;
+use kernel::notifications::{NewNotification, NotificationType, PendingNotification};
+use kernel::to_do_items::Todo;
+
+
+/// The poll interval used when not configured.
+const DEFAULT_POLL_SECONDS: i64 = 30;
+
+
+/// The counts from a single pass of the batcher.
+///
+/// # Fields
+/// * `emails` - The summary emails sent.
+/// * `notifications` - The notifications covered by those emails.
+/// * `failed` - The summary emails that could not be sent.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct BatchRunSummary {
+    pub emails: usize,
+    pub notifications: usize,
+    pub failed: usize,
+}
+
+
+/// Reads the batch window for a notification type.
+///
+/// # Returns
+/// * `i64` - The seconds a user's first notification waits for others to join it
+pub fn batch_window<Y: GetConfigVariable>(notification_type: NotificationType) -> i64 {
+    let (name, default) = match notification_type {
+        NotificationType::Assignment => ("NOTIFICATION_BATCH_ASSIGNMENT_SECONDS", 600),
+        NotificationType::Comment => ("NOTIFICATION_BATCH_COMMENT_SECONDS", 300),
+    };
+    Y::get_int(name).ok().filter(|value| *value >= 0).unwrap_or(default)
+}
+
+
+/// Queues a notification about a to-do item for a user.
+///
+/// # Arguments
+/// * `user_id` - The user to notify
+/// * `notification_type` - The kind of event
+/// * `todo` - The to-do item the event happened to
+///
+/// # Notes
+/// A failure to queue is logged rather than returned as the event itself has already happened.
+pub async fn notify_user<X: QueueNotification>(user_id: i32, notification_type: NotificationType, todo: &Todo) {
+    let notification = NewNotification {
+        user_id,
+        notification_type,
+        todo_id: todo.id,
+        todo_name: todo.name.clone(),
+    };
+    if let Err(e) = X::queue_notification(notification).await {
+        println!("failed to queue {} notification for to-do item {}: {}", notification_type.as_str(), todo.id, e.message);
+    }
+}
+
+
+/// Sends a summary email to every user with a batch of notifications whose window has passed.
+///
+/// # Returns
+/// * `Ok(BatchRunSummary)` - The counts for the pass
+/// * `Err(NanoServiceError)` - If the notifications could not be taken
+pub async fn send_batched_notifications<W, X, Y>() -> Result<BatchRunSummary, NanoServiceError>
+where
+    W: SendTemplate,
+    X: TakeDueNotifications + GetUser,
+    Y: GetConfigVariable,
+{
+    let mut summary = BatchRunSummary::default();
+    for notification_type in NotificationType::ALL {
+        let window = batch_window::<Y>(notification_type);
+        let mut batches: BTreeMap<i32, Vec<PendingNotification>> = BTreeMap::new();
+        for notification in X::take_due_notifications(notification_type, window).await? {
+            batches.entry(notification.user_id).or_default().push(notification);
+        }
+        for (user_id, notifications) in batches {
+            let sent = match X::get_user(user_id).await {
+                Ok(user) => send_notification_summary_email::<W, Y>(user.email, notification_type, &notifications).await,
+                Err(e) => Err(e)
+            };
+            match sent {
+                Ok(true) => {
+                    summary.emails += 1;
+                    summary.notifications += notifications.len();
+                },
+                Ok(false) => {
+                    println!("{} summary for user {} was not accepted", notification_type.as_str(), user_id);
+                    summary.failed += 1;
+                },
+                Err(e) => {
+                    println!("failed to send {} summary for user {}: {}", notification_type.as_str(), user_id, e.message);
+                    summary.failed += 1;
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+
+/// Polls for batches that are due in the background for the life of the runtime.
+///
+/// # Notes
+/// An error in a pass is logged and the batcher carries on.
+pub fn spawn_notification_batcher<W, X, Y>()
+where
+    W: SendTemplate + 'static,
+    X: TakeDueNotifications + GetUser + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let poll_seconds = Y::get_int("NOTIFICATION_BATCH_POLL_SECONDS").ok().filter(|value| *value > 0).unwrap_or(DEFAULT_POLL_SECONDS);
+    let poll_interval = std::time::Duration::from_secs(poll_seconds as u64);
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = send_batched_notifications::<W, X, Y>().await {
+                log_limited!("to_do", "notification batcher pass failed: {}", e.message);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use email_core::mailchimp_helpers::mailchimp_template::Template;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
+    use kernel::users::{User, UserRole};
+    use utils::errors::NanoServiceErrorStatus;
+    use chrono::Utc;
+    use std::sync::Mutex;
+
+    static SENT: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+    static QUEUED: Mutex<Vec<NewNotification>> = Mutex::new(Vec::new());
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "NOTIFICATION_BATCH_ASSIGNMENT_SECONDS" => Ok("60".to_string()),
+                "NOTIFICATION_BATCH_COMMENT_SECONDS" => Ok("never".to_string()),
+                "PRODUCTION" => Ok("true".to_string()),
+                _ => Ok("key".to_string())
+            }
+        }
+    }
+
+    fn pending(id: i32, user_id: i32, notification_type: NotificationType) -> PendingNotification {
+        PendingNotification {
+            id,
+            user_id,
+            notification_type,
+            todo_id: id,
+            todo_name: format!("task {}", id),
+            date_created: Utc::now().naive_utc(),
+        }
+    }
+
+    struct MockDbHandle;
+
+    /// Users 3 and 4 have assignments due, user 5 has a comment due but no longer exists.
+    #[impl_transaction(MockDbHandle, TakeDueNotifications, take_due_notifications)]
+    async fn take_due_notifications(notification_type: NotificationType, window_seconds: i64) -> Result<Vec<PendingNotification>, NanoServiceError> {
+        match notification_type {
+            NotificationType::Assignment => {
+                assert_eq!(window_seconds, 60);
+                Ok(vec![
+                    pending(1, 3, notification_type),
+                    pending(2, 4, notification_type),
+                    pending(3, 3, notification_type),
+                ])
+            },
+            NotificationType::Comment => {
+                assert_eq!(window_seconds, 300);
+                Ok(vec![pending(4, 5, notification_type)])
+            }
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        if id == 5 {
+            return Err(NanoServiceError::new("not found".to_string(), NanoServiceErrorStatus::NotFound))
+        }
+        Ok(User {
+            id,
+            confirmed: true,
+            username: format!("user{}", id),
+            email: format!("user{}@example.com", id),
+            first_name: "first".to_string(),
+            last_name: "last".to_string(),
+            user_role: UserRole::Worker,
+            password: "password".to_string(),
+            uuid: "uuid".to_string(),
+            date_created: Utc::now().naive_utc(),
+            last_logged_in: Utc::now().naive_utc(),
+            blocked: false,
+            avatar_version: None,
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, QueueNotification, queue_notification)]
+    async fn queue_notification(notification: NewNotification) -> Result<PendingNotification, NanoServiceError> {
+        QUEUED.lock().unwrap().push(notification.clone());
+        Ok(pending(1, notification.user_id, notification.notification_type))
+    }
+
+    struct MockMailchimp;
+
+    #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+    async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {
+        let count = template.message.global_merge_vars.iter().find(|var| var.name == "COUNT").unwrap();
+        SENT.lock().unwrap().push((template.message.to[0].email.clone(), count.content.clone()));
+        Ok(true)
+    }
+
+    #[tokio::test]
+    async fn test_send_batched_notifications() {
+        let summary = send_batched_notifications::<MockMailchimp, MockDbHandle, FakeConfig>().await.unwrap();
+        assert_eq!(summary, BatchRunSummary { emails: 2, notifications: 3, failed: 1 });
+        assert_eq!(*SENT.lock().unwrap(), vec![
+            ("user3@example.com".to_string(), "2".to_string()),
+            ("user4@example.com".to_string(), "1".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_notify_user() {
+        let todo = Todo {
+            id: 9,
+            name: "Task".to_string(),
+            due_date: None,
+            assigned_by: 1,
+            assigned_to: 3,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        };
+        notify_user::<MockDbHandle>(3, NotificationType::Assignment, &todo).await;
+        assert_eq!(QUEUED.lock().unwrap()[0], NewNotification {
+            user_id: 3,
+            notification_type: NotificationType::Assignment,
+            todo_id: 9,
+            todo_name: "Task".to_string(),
+        });
+    }
+}
@@ -0,0 +1,60 @@
+//! Core logic for a user reading the notifications waiting to be sent to them in their next summary.
+use utils::errors::NanoServiceError;
+use dal::notifications::tx_definitions::ListPendingNotifications;
+use kernel::notifications::PendingNotification;
+use kernel::pagination::{Page, PageQuery};
+
+
+/// Lists a user's pending notifications oldest first, a page at a time.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user.
+/// - `page`: The page asked for, the cursor holding the ID of the last notification on the previous page.
+///
+/// # Returns
+/// - `Ok(Page<PendingNotification>)`: The notifications on the page and the cursor of the next page.
+/// - `Err(NanoServiceError)`: `BadRequest` if the limit or cursor is invalid.
+pub async fn list_pending_notifications<X: ListPendingNotifications>(user_id: i32, page: &PageQuery) -> Result<Page<PendingNotification>, NanoServiceError> {
+    let limit = page.limit()?;
+    // one more than the page is read to tell whether there is another page
+    let notifications = X::list_pending_notifications(user_id, page.after_id()?, limit + 1).await?;
+    Ok(Page::from_lookahead(notifications, limit, |notification| notification.id as i64))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::notifications::NotificationType;
+
+    struct MockDbHandle;
+
+    /// The user has notifications 1 to 3 pending.
+    #[impl_transaction(MockDbHandle, ListPendingNotifications, list_pending_notifications)]
+    async fn list_pending_notifications(user_id: i32, after_id: Option<i32>, limit: i64) -> Result<Vec<PendingNotification>, NanoServiceError> {
+        assert_eq!(user_id, 4);
+        Ok((1..=3)
+            .filter(|id| *id > after_id.unwrap_or(0))
+            .take(limit as usize)
+            .map(|id| PendingNotification {
+                id,
+                user_id,
+                notification_type: NotificationType::Comment,
+                todo_id: 9,
+                todo_name: "Report".to_string(),
+                date_created: chrono::Utc::now().naive_utc(),
+            })
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn test_list_pending_notifications() {
+        let first = list_pending_notifications::<MockDbHandle>(4, &PageQuery { cursor: None, limit: Some(2) }).await.unwrap();
+        assert_eq!(first.items.iter().map(|notification| notification.id).collect::<Vec<_>>(), vec![1, 2]);
+        let next = PageQuery { cursor: first.next_cursor, limit: Some(2) };
+        let last = list_pending_notifications::<MockDbHandle>(4, &next).await.unwrap();
+        assert_eq!(last.items.len(), 1);
+        assert_eq!(last.next_cursor, None);
+    }
+}
@@ -0,0 +1,64 @@
+//! Core logic for creating a project.
+//!
+//! # Features
+//! - Delegates the creation operation to the data access layer (DAL) using `CreateProject`, which
+//!   adds the user creating the project as its owner and first member.
+use utils::errors::NanoServiceError;
+use utils::validation::validate_body;
+use dal::projects::tx_definitions::CreateProject;
+use kernel::projects::{NewProject, Project};
+
+/// Creates a project owned by `owner_id`.
+///
+/// # Arguments
+/// - `owner_id`: The ID of the user creating the project.
+/// - `project`: The name and description of the project.
+///
+/// # Returns
+/// - `Ok(Project)`: The created project.
+/// - `Err(NanoServiceError)`: `BadRequest` if the name or description is invalid, or if an error
+///   occurs during the database transaction.
+pub async fn create_project<X: CreateProject>(owner_id: i32, project: NewProject) -> Result<Project, NanoServiceError> {
+    validate_body(&project)?;
+    let project = NewProject {
+        name: project.name.trim().to_string(),
+        description: project.description.map(|description| description.trim().to_string())
+            .filter(|description| !description.is_empty()),
+    };
+    X::create_project(owner_id, project).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use utils::errors::NanoServiceErrorStatus;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, CreateProject, create_project)]
+    async fn create_project(owner_id: i32, project: NewProject) -> Result<Project, NanoServiceError> {
+        Ok(Project {
+            id: 1,
+            name: project.name,
+            description: project.description,
+            owner_id,
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_create_project() {
+        let project = create_project::<MockDbHandle>(3, NewProject {
+            name: " Launch ".to_string(),
+            description: Some(" ".to_string()),
+        }).await.unwrap();
+        assert_eq!(project.name, "Launch");
+        assert_eq!(project.description, None);
+        assert_eq!(project.owner_id, 3);
+
+        let error = create_project::<MockDbHandle>(3, NewProject { name: "".to_string(), description: None }).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+}
@@ -0,0 +1,72 @@
+//! Core logic for reading projects.
+//!
+//! # Features
+//! - Delegates the retrieval operations to the data access layer (DAL) using `GetProject`,
+//!   `IsProjectMember` and `GetProjectsForUser`.
+use utils::errors::NanoServiceError;
+use dal::projects::tx_definitions::{GetProject, GetProjectsForUser, IsProjectMember};
+use kernel::projects::Project;
+use kernel::users::UserRole;
+use crate::api::projects::get_visible_project;
+
+/// Retrieves a project the user can see.
+///
+/// # Arguments
+/// - `id`: The ID of the project.
+/// - `user_id`: The ID of the user reading the project.
+/// - `role`: The role of the user reading the project.
+///
+/// # Returns
+/// - `Ok(Project)`: The project.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project, `Forbidden` if the user is
+///   not a member of it and is not an admin.
+pub async fn get_project<X: GetProject + IsProjectMember>(id: i32, user_id: i32, role: &UserRole) -> Result<Project, NanoServiceError> {
+    get_visible_project::<X>(id, user_id, role).await
+}
+
+/// Retrieves the projects a user is a member of, ordered by name.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user.
+///
+/// # Returns
+/// - `Ok(Vec<Project>)`: The projects the user is a member of.
+/// - `Err(NanoServiceError)`: If an error occurs during the database transaction.
+pub async fn get_projects_for_user<X: GetProjectsForUser>(user_id: i32) -> Result<Vec<Project>, NanoServiceError> {
+    X::get_projects_for_user(user_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use utils::errors::NanoServiceErrorStatus;
+
+    /// Project 1 is owned by user 2 and user 3 is also a member.
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetProject, get_project)]
+    async fn get_project(id: i32) -> Result<Project, NanoServiceError> {
+        if id != 1 {
+            return Err(NanoServiceError::new(format!("Project {} not found", id), NanoServiceErrorStatus::NotFound))
+        }
+        Ok(Project { id, name: "Launch".to_string(), description: None, owner_id: 2, date_created: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, IsProjectMember, is_project_member)]
+    async fn is_project_member(_project_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(user_id == 2 || user_id == 3)
+    }
+
+    #[tokio::test]
+    async fn test_get_project() {
+        assert_eq!(get_project::<MockDbHandle>(1, 3, &UserRole::Worker).await.unwrap().owner_id, 2);
+        assert!(get_project::<MockDbHandle>(1, 7, &UserRole::Admin).await.is_ok());
+
+        let error = get_project::<MockDbHandle>(1, 7, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        let error = get_project::<MockDbHandle>(4, 3, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+    }
+}
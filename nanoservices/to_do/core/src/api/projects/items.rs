@@ -0,0 +1,201 @@
+//! Core logic for the to-do items in a project.
+//!
+//! # Overview
+//! A member of a project can add the items they assigned or are assigned to it, and an admin can
+//! add any item. Adding an item that is in another project moves it. The owner of the project can
+//! also take out items added by other members.
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::projects::tx_definitions::{
+    AddToDoItemToProject,
+    GetProject,
+    GetToDoItemsForProject,
+    IsProjectMember,
+    RemoveToDoItemFromProject
+};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use kernel::projects::{can_manage_project, ProjectItemFilter, ProjectTodo};
+use kernel::to_do_items::Todo;
+use kernel::users::UserRole;
+use crate::api::projects::get_visible_project;
+
+/// Whether a user can file a to-do item in a project, only its assigner, its assignee and admins can.
+fn can_file_to_do_item(todo: &Todo, user_id: i32, role: &UserRole) -> bool {
+    todo.assigned_by == user_id
+        || todo.assigned_to == user_id
+        || matches!(role, UserRole::SuperAdmin | UserRole::Admin)
+}
+
+/// Adds a to-do item to a project, moving it out of any other project.
+///
+/// # Arguments
+/// - `project_id`: The ID of the project.
+/// - `todo_id`: The ID of the to-do item.
+/// - `user_id`: The ID of the user adding the item.
+/// - `role`: The role of the user adding the item.
+///
+/// # Returns
+/// - `Ok(ProjectTodo)`: The link between the project and the item.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project or item, `Forbidden` if the
+///   user is not a member of the project, or neither assigned the item nor is assigned it, and is
+///   not an admin.
+pub async fn add_to_do_item_to_project<X>(project_id: i32, todo_id: i32, user_id: i32, role: &UserRole) -> Result<ProjectTodo, NanoServiceError>
+where
+    X: GetProject + IsProjectMember + GetToDoItem + AddToDoItemToProject
+{
+    get_visible_project::<X>(project_id, user_id, role).await?;
+    let todo = X::get_to_do_item(todo_id).await?;
+    if !can_file_to_do_item(&todo, user_id, role) {
+        return Err(NanoServiceError::new(
+            "Only the users a to-do item is assigned by and to, or an admin, can add it to a project".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    X::add_to_do_item_to_project(project_id, todo_id).await
+}
+
+/// Takes a to-do item out of a project, the item itself is kept.
+///
+/// # Arguments
+/// - `project_id`: The ID of the project.
+/// - `todo_id`: The ID of the to-do item.
+/// - `user_id`: The ID of the user taking the item out.
+/// - `role`: The role of the user taking the item out.
+///
+/// # Returns
+/// - `Ok(())`: If the item was taken out of the project.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project or item, or the item is not in
+///   the project, `Forbidden` if the user is not a member of the project, or neither owns the
+///   project nor assigned or is assigned the item, and is not an admin.
+pub async fn remove_to_do_item_from_project<X>(project_id: i32, todo_id: i32, user_id: i32, role: &UserRole) -> Result<(), NanoServiceError>
+where
+    X: GetProject + IsProjectMember + GetToDoItem + RemoveToDoItemFromProject
+{
+    let project = get_visible_project::<X>(project_id, user_id, role).await?;
+    let todo = X::get_to_do_item(todo_id).await?;
+    if !can_manage_project(&project, user_id, role) && !can_file_to_do_item(&todo, user_id, role) {
+        return Err(NanoServiceError::new(
+            "Only the owner of a project, or the users a to-do item is assigned by and to, can take it out of the project".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    match X::remove_to_do_item_from_project(project_id, todo_id).await? {
+        true => Ok(()),
+        false => Err(NanoServiceError::new(
+            format!("To-do item {} is not in project {}", todo_id, project_id),
+            NanoServiceErrorStatus::NotFound
+        ))
+    }
+}
+
+/// Retrieves the to-do items in a project the user can see, soonest due first.
+///
+/// # Arguments
+/// - `project_id`: The ID of the project.
+/// - `user_id`: The ID of the user listing the items.
+/// - `role`: The role of the user listing the items.
+/// - `filter`: Only return the items matching every filter given.
+///
+/// # Returns
+/// - `Ok(Vec<Todo>)`: The items in the project.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project, `Forbidden` if the user is
+///   not a member of it and is not an admin.
+pub async fn get_to_do_items_for_project<X>(project_id: i32, user_id: i32, role: &UserRole, filter: ProjectItemFilter) -> Result<Vec<Todo>, NanoServiceError>
+where
+    X: GetProject + IsProjectMember + GetToDoItemsForProject
+{
+    get_visible_project::<X>(project_id, user_id, role).await?;
+    X::get_to_do_items_for_project(project_id, filter).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use kernel::projects::Project;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
+
+    /// Project 1 is owned by user 2 and users 3 and 4 are also members. Item 5 is assigned by user 3
+    /// to user 3 and is in the project.
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetProject, get_project)]
+    async fn get_project(id: i32) -> Result<Project, NanoServiceError> {
+        Ok(Project { id, name: "Launch".to_string(), description: None, owner_id: 2, date_created: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, IsProjectMember, is_project_member)]
+    async fn is_project_member(_project_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok((2..=4).contains(&user_id))
+    }
+
+    fn todo(id: i32, status: TodoStatus) -> Todo {
+        Todo {
+            id,
+            name: "write report".to_string(),
+            due_date: None,
+            assigned_by: 3,
+            assigned_to: 3,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(todo(todo_id, TodoStatus::Backlog))
+    }
+
+    #[impl_transaction(MockDbHandle, AddToDoItemToProject, add_to_do_item_to_project)]
+    async fn add_to_do_item_to_project(project_id: i32, todo_id: i32) -> Result<ProjectTodo, NanoServiceError> {
+        Ok(ProjectTodo { project_id, todo_id, date_added: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, RemoveToDoItemFromProject, remove_to_do_item_from_project)]
+    async fn remove_to_do_item_from_project(_project_id: i32, todo_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(todo_id == 5)
+    }
+
+    #[impl_transaction(MockDbHandle, GetToDoItemsForProject, get_to_do_items_for_project)]
+    async fn get_to_do_items_for_project(_project_id: i32, filter: ProjectItemFilter) -> Result<Vec<Todo>, NanoServiceError> {
+        let items = [todo(5, TodoStatus::Backlog), todo(6, TodoStatus::Blocked)];
+        Ok(items.into_iter().filter(|item| filter.status.is_none_or(|status| item.status == status)).collect())
+    }
+
+    #[tokio::test]
+    async fn test_add_to_do_item_to_project() {
+        assert_eq!(add_to_do_item_to_project::<MockDbHandle>(1, 5, 3, &UserRole::Worker).await.unwrap().todo_id, 5);
+
+        for user_id in [4, 7] {
+            let error = add_to_do_item_to_project::<MockDbHandle>(1, 5, user_id, &UserRole::Worker).await.unwrap_err();
+            assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remove_to_do_item_from_project() {
+        assert!(remove_to_do_item_from_project::<MockDbHandle>(1, 5, 2, &UserRole::Worker).await.is_ok());
+
+        let error = remove_to_do_item_from_project::<MockDbHandle>(1, 5, 4, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        let error = remove_to_do_item_from_project::<MockDbHandle>(1, 6, 3, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_get_to_do_items_for_project() {
+        let filter = ProjectItemFilter { status: Some(TodoStatus::Blocked), ..Default::default() };
+        let items = get_to_do_items_for_project::<MockDbHandle>(1, 4, &UserRole::Worker, filter).await.unwrap();
+        assert_eq!(items.iter().map(|item| item.id).collect::<Vec<_>>(), vec![6]);
+
+        let error = get_to_do_items_for_project::<MockDbHandle>(1, 7, &UserRole::Worker, ProjectItemFilter::default()).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+    }
+}
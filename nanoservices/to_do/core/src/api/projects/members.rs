@@ -0,0 +1,161 @@
+//! Core logic for the members of a project, the users who can see it.
+//!
+//! # Overview
+//! The owner of a project or an admin adds and removes its members, and any member can leave. The
+//! owner stays a member for as long as the project exists.
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::projects::tx_definitions::{AddProjectMember, GetProject, GetProjectMembers, IsProjectMember, RemoveProjectMember};
+use dal::users::tx_definitions::GetUser;
+use kernel::projects::{can_manage_project, ProjectMember};
+use kernel::users::UserRole;
+use crate::api::projects::{get_managed_project, get_visible_project};
+
+/// Lets a user see a project, adding a user who is already a member is not an error.
+///
+/// # Arguments
+/// - `project_id`: The ID of the project.
+/// - `member_id`: The ID of the user to add.
+/// - `user_id`: The ID of the user adding the member.
+/// - `role`: The role of the user adding the member.
+///
+/// # Returns
+/// - `Ok(ProjectMember)`: The membership.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project or user, `Forbidden` if the
+///   user does not own the project and is not an admin.
+pub async fn add_project_member<X>(project_id: i32, member_id: i32, user_id: i32, role: &UserRole) -> Result<ProjectMember, NanoServiceError>
+where
+    X: GetProject + GetUser + AddProjectMember
+{
+    get_managed_project::<X>(project_id, user_id, role).await?;
+    X::get_user(member_id).await?;
+    X::add_project_member(project_id, member_id).await
+}
+
+/// Stops a user seeing a project.
+///
+/// # Arguments
+/// - `project_id`: The ID of the project.
+/// - `member_id`: The ID of the member to remove.
+/// - `user_id`: The ID of the user removing the member.
+/// - `role`: The role of the user removing the member.
+///
+/// # Returns
+/// - `Ok(())`: If the member was removed.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project or member, `Forbidden` if the
+///   user is removing someone else and does not own the project and is not an admin, `Conflict`
+///   if the member is the owner.
+pub async fn remove_project_member<X>(project_id: i32, member_id: i32, user_id: i32, role: &UserRole) -> Result<(), NanoServiceError>
+where
+    X: GetProject + RemoveProjectMember
+{
+    let project = X::get_project(project_id).await?;
+    if member_id != user_id && !can_manage_project(&project, user_id, role) {
+        return Err(NanoServiceError::new(
+            "Only the owner of a project, or an admin, can remove its other members".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    if member_id == project.owner_id {
+        return Err(NanoServiceError::new(
+            "The owner of a project cannot be removed from it".to_string(),
+            NanoServiceErrorStatus::Conflict
+        ))
+    }
+    match X::remove_project_member(project_id, member_id).await? {
+        true => Ok(()),
+        false => Err(NanoServiceError::new(
+            format!("User {} is not a member of project {}", member_id, project_id),
+            NanoServiceErrorStatus::NotFound
+        ))
+    }
+}
+
+/// Retrieves the members of a project the user can see, in the order they were added.
+///
+/// # Arguments
+/// - `project_id`: The ID of the project.
+/// - `user_id`: The ID of the user reading the members.
+/// - `role`: The role of the user reading the members.
+///
+/// # Returns
+/// - `Ok(Vec<ProjectMember>)`: The members of the project.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project, `Forbidden` if the user is
+///   not a member of it and is not an admin.
+pub async fn get_project_members<X>(project_id: i32, user_id: i32, role: &UserRole) -> Result<Vec<ProjectMember>, NanoServiceError>
+where
+    X: GetProject + IsProjectMember + GetProjectMembers
+{
+    get_visible_project::<X>(project_id, user_id, role).await?;
+    X::get_project_members(project_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use kernel::projects::Project;
+    use kernel::users::User;
+
+    /// Project 1 is owned by user 2 and user 3 is also a member, user 9 does not exist.
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetProject, get_project)]
+    async fn get_project(id: i32) -> Result<Project, NanoServiceError> {
+        Ok(Project { id, name: "Launch".to_string(), description: None, owner_id: 2, date_created: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        if id == 9 {
+            return Err(NanoServiceError::new("User not found".to_string(), NanoServiceErrorStatus::NotFound))
+        }
+        Ok(User {
+            id,
+            confirmed: true,
+            username: "worker".to_string(),
+            email: "worker@example.com".to_string(),
+            password: "hash".to_string(),
+            first_name: "Worker".to_string(),
+            last_name: "User".to_string(),
+            user_role: UserRole::Worker,
+            date_created: Utc::now().naive_utc(),
+            last_logged_in: Utc::now().naive_utc(),
+            blocked: false,
+            uuid: "uuid".to_string(),
+            avatar_version: None,
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, AddProjectMember, add_project_member)]
+    async fn add_project_member(project_id: i32, user_id: i32) -> Result<ProjectMember, NanoServiceError> {
+        Ok(ProjectMember { project_id, user_id, date_added: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockDbHandle, RemoveProjectMember, remove_project_member)]
+    async fn remove_project_member(_project_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(user_id == 3)
+    }
+
+    #[tokio::test]
+    async fn test_add_project_member() {
+        assert_eq!(add_project_member::<MockDbHandle>(1, 4, 2, &UserRole::Worker).await.unwrap().user_id, 4);
+
+        let error = add_project_member::<MockDbHandle>(1, 4, 3, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        let error = add_project_member::<MockDbHandle>(1, 9, 2, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_remove_project_member() {
+        assert!(remove_project_member::<MockDbHandle>(1, 3, 3, &UserRole::Worker).await.is_ok());
+        assert!(remove_project_member::<MockDbHandle>(1, 3, 2, &UserRole::Worker).await.is_ok());
+
+        let cases = [(4, 3, NanoServiceErrorStatus::Forbidden), (2, 2, NanoServiceErrorStatus::Conflict), (4, 2, NanoServiceErrorStatus::NotFound)];
+        for (member_id, user_id, status) in cases {
+            let error = remove_project_member::<MockDbHandle>(1, member_id, user_id, &UserRole::Worker).await.unwrap_err();
+            assert_eq!(error.status, status);
+        }
+    }
+}
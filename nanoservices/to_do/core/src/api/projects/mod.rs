@@ -0,0 +1,51 @@
+//! Core logic for projects, the boards that group to-do items.
+//!
+//! # Overview
+//! A project can be seen by its members and admins, and changed by its owner and admins. The
+//! helpers here read a project and refuse the users who cannot see or change it.
+pub mod create;
+pub mod get;
+pub mod update;
+pub mod members;
+pub mod items;
+
+use dal::projects::tx_definitions::{GetProject, IsProjectMember};
+use kernel::projects::{can_manage_project, Project};
+use kernel::users::UserRole;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// Reads a project, refusing a user who is not one of its members.
+///
+/// # Returns
+/// - `Ok(Project)`: The project.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project, `Forbidden` if the user is
+///   not a member of it and is not an admin.
+pub async fn get_visible_project<X: GetProject + IsProjectMember>(id: i32, user_id: i32, role: &UserRole) -> Result<Project, NanoServiceError> {
+    let project = X::get_project(id).await?;
+    if can_manage_project(&project, user_id, role) || X::is_project_member(id, user_id).await? {
+        return Ok(project)
+    }
+    Err(NanoServiceError::new(
+        "Only the members of a project, or an admin, can see it".to_string(),
+        NanoServiceErrorStatus::Forbidden
+    ))
+}
+
+
+/// Reads a project, refusing a user who cannot change it.
+///
+/// # Returns
+/// - `Ok(Project)`: The project.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project, `Forbidden` if the user
+///   does not own it and is not an admin.
+pub async fn get_managed_project<X: GetProject>(id: i32, user_id: i32, role: &UserRole) -> Result<Project, NanoServiceError> {
+    let project = X::get_project(id).await?;
+    if !can_manage_project(&project, user_id, role) {
+        return Err(NanoServiceError::new(
+            "Only the owner of a project, or an admin, can change it".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    Ok(project)
+}
@@ -0,0 +1,108 @@
+//! Core logic for changing and removing projects.
+//!
+//! # Overview
+//! Only the owner of a project or an admin can change or remove it. Removing a project leaves its
+//! to-do items in place, they are only taken out of the project.
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use utils::validation::validate_body;
+use dal::projects::tx_definitions::{DeleteProject, GetProject, UpdateProject};
+use kernel::projects::{Project, ProjectPatch};
+use kernel::users::UserRole;
+use crate::api::projects::get_managed_project;
+
+/// Changes the name or description of a project.
+///
+/// # Arguments
+/// - `id`: The ID of the project.
+/// - `user_id`: The ID of the user changing the project.
+/// - `role`: The role of the user changing the project.
+/// - `patch`: The fields to change.
+///
+/// # Returns
+/// - `Ok(Project)`: The changed project.
+/// - `Err(NanoServiceError)`: `BadRequest` if the patch is empty or invalid, `NotFound` if there is
+///   no such project, `Forbidden` if the user does not own it and is not an admin.
+pub async fn update_project<X: GetProject + UpdateProject>(id: i32, user_id: i32, role: &UserRole, patch: ProjectPatch) -> Result<Project, NanoServiceError> {
+    if patch.is_empty() {
+        return Err(NanoServiceError::new(
+            "At least one of name or description has to be given".to_string(),
+            NanoServiceErrorStatus::BadRequest
+        ))
+    }
+    validate_body(&patch)?;
+    get_managed_project::<X>(id, user_id, role).await?;
+    let patch = ProjectPatch {
+        name: patch.name.map(|name| name.trim().to_string()),
+        description: patch.description,
+    };
+    X::update_project(id, patch).await
+}
+
+/// Removes a project.
+///
+/// # Arguments
+/// - `id`: The ID of the project.
+/// - `user_id`: The ID of the user removing the project.
+/// - `role`: The role of the user removing the project.
+///
+/// # Returns
+/// - `Ok(Project)`: The removed project.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such project, `Forbidden` if the user does
+///   not own it and is not an admin.
+pub async fn delete_project<X: GetProject + DeleteProject>(id: i32, user_id: i32, role: &UserRole) -> Result<Project, NanoServiceError> {
+    let project = get_managed_project::<X>(id, user_id, role).await?;
+    X::delete_project(id).await?;
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+
+    /// Project 1 is owned by user 2.
+    struct MockDbHandle;
+
+    fn project(id: i32) -> Project {
+        Project { id, name: "Launch".to_string(), description: Some("Q3".to_string()), owner_id: 2, date_created: Utc::now().naive_utc() }
+    }
+
+    #[impl_transaction(MockDbHandle, GetProject, get_project)]
+    async fn get_project(id: i32) -> Result<Project, NanoServiceError> {
+        Ok(project(id))
+    }
+
+    #[impl_transaction(MockDbHandle, UpdateProject, update_project)]
+    async fn update_project(id: i32, patch: ProjectPatch) -> Result<Project, NanoServiceError> {
+        let mut project = project(id);
+        project.name = patch.name.unwrap_or(project.name);
+        project.description = patch.description.unwrap_or(project.description);
+        Ok(project)
+    }
+
+    #[impl_transaction(MockDbHandle, DeleteProject, delete_project)]
+    async fn delete_project(_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(true)
+    }
+
+    #[tokio::test]
+    async fn test_update_project() {
+        let patch = ProjectPatch { name: Some(" Relaunch ".to_string()), description: Some(None) };
+        let project = update_project::<MockDbHandle>(1, 2, &UserRole::Worker, patch.clone()).await.unwrap();
+        assert_eq!(project.name, "Relaunch");
+        assert_eq!(project.description, None);
+
+        let error = update_project::<MockDbHandle>(1, 3, &UserRole::Worker, patch).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+        let error = update_project::<MockDbHandle>(1, 2, &UserRole::Worker, ProjectPatch::default()).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_delete_project() {
+        assert_eq!(delete_project::<MockDbHandle>(1, 5, &UserRole::Admin).await.unwrap().id, 1);
+        let error = delete_project::<MockDbHandle>(1, 3, &UserRole::Worker).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+    }
+}
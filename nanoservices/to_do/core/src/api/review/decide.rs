@@ -0,0 +1,167 @@
+//! Core logic for approving or rejecting a to-do item that is pending review.
+//!
+//! # Overview
+//! Only the user who assigned an item can review it. Approving finishes the item, rejecting
+//! reopens it for the assignee along with a comment explaining what needs doing. The decision is
+//! recorded in the history of the item.
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::to_do_items::tx_definitions::{GetToDoItem, ApproveToDoItem, RejectToDoItem};
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use kernel::to_do_items::Todo;
+use kernel::todo_events::NewTodoEvent;
+
+
+/// Checks that the reviewer is the user who assigned the item.
+async fn check_reviewer<X: GetToDoItem>(todo_id: i32, reviewer_id: i32) -> Result<(), NanoServiceError> {
+    let todo = X::get_to_do_item(todo_id).await?;
+    if todo.assigned_by != reviewer_id {
+        return Err(NanoServiceError::new(
+            "Only the user who assigned the to-do item can review it".to_string(),
+            NanoServiceErrorStatus::Forbidden,
+        ))
+    }
+    Ok(())
+}
+
+
+/// Approves a to-do item, finishing it.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item to approve.
+/// - `reviewer_id`: The ID of the user approving the item.
+///
+/// # Returns
+/// - `Ok(Todo)`: The finished to-do item.
+/// - `Err(NanoServiceError)`: `Forbidden` if the reviewer did not assign the item, `NotFound` if it is not pending review.
+pub async fn approve_to_do_item<X>(todo_id: i32, reviewer_id: i32) -> Result<Todo, NanoServiceError>
+where
+    X: GetToDoItem + ApproveToDoItem + RecordToDoEvent
+{
+    check_reviewer::<X>(todo_id, reviewer_id).await?;
+    let todo = X::approve_to_do_item(todo_id).await?;
+    X::record_to_do_event(NewTodoEvent::approved(&todo, reviewer_id)).await?;
+    Ok(todo)
+}
+
+
+/// Rejects a to-do item, reopening it with a comment for the assignee.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item to reject.
+/// - `reviewer_id`: The ID of the user rejecting the item.
+/// - `comment`: Why the item was rejected.
+///
+/// # Returns
+/// - `Ok(Todo)`: The reopened to-do item.
+/// - `Err(NanoServiceError)`: `BadRequest` if the comment is empty, `Forbidden` if the reviewer did not
+///   assign the item, `NotFound` if it is not pending review.
+pub async fn reject_to_do_item<X>(todo_id: i32, reviewer_id: i32, comment: String) -> Result<Todo, NanoServiceError>
+where
+    X: GetToDoItem + RejectToDoItem + RecordToDoEvent
+{
+    let comment = comment.trim().to_string();
+    if comment.is_empty() {
+        return Err(NanoServiceError::new(
+            "A comment is required to reject a to-do item".to_string(),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    check_reviewer::<X>(todo_id, reviewer_id).await?;
+    let todo = X::reject_to_do_item(todo_id, comment.clone()).await?;
+    X::record_to_do_event(NewTodoEvent::rejected(&todo, reviewer_id, &comment)).await?;
+    Ok(todo)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
+    use kernel::todo_events::{TodoEvent, TodoEventKind};
+    use dal_tx_impl::impl_transaction;
+    use chrono::Utc;
+    use std::sync::Mutex;
+
+    static HISTORY: Mutex<Vec<NewTodoEvent>> = Mutex::new(Vec::new());
+
+    struct MockDbHandle;
+
+    fn pending(todo_id: i32) -> Todo {
+        Todo {
+            id: todo_id,
+            name: "Task".to_string(),
+            due_date: None,
+            assigned_by: 5,
+            assigned_to: 2,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: true,
+            pending_review: true,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(pending(todo_id))
+    }
+
+    #[impl_transaction(MockDbHandle, ApproveToDoItem, approve_to_do_item)]
+    async fn approve_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        let mut todo = pending(todo_id);
+        todo.pending_review = false;
+        todo.finished = true;
+        Ok(todo)
+    }
+
+    #[impl_transaction(MockDbHandle, RejectToDoItem, reject_to_do_item)]
+    async fn reject_to_do_item(todo_id: i32, comment: String) -> Result<Todo, NanoServiceError> {
+        let mut todo = pending(todo_id);
+        todo.pending_review = false;
+        todo.review_comment = Some(comment);
+        Ok(todo)
+    }
+
+    #[impl_transaction(MockDbHandle, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        HISTORY.lock().unwrap().push(event.clone());
+        Ok(TodoEvent {
+            id: 1,
+            todo_id: event.todo_id,
+            actor_id: Some(event.actor_id),
+            kind: event.kind,
+            details: sqlx::types::Json(event.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_approve() {
+        let todo = approve_to_do_item::<MockDbHandle>(1, 5).await.unwrap();
+        assert!(todo.finished);
+        assert!(HISTORY.lock().unwrap().contains(&NewTodoEvent::approved(&todo, 5)));
+
+        let error = approve_to_do_item::<MockDbHandle>(1, 6).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+    }
+
+    #[tokio::test]
+    async fn test_reject() {
+        let todo = reject_to_do_item::<MockDbHandle>(1, 5, " add the charts ".to_string()).await.unwrap();
+        assert!(!todo.finished);
+        assert!(!todo.pending_review);
+        assert_eq!(todo.review_comment.as_deref(), Some("add the charts"));
+        let history = HISTORY.lock().unwrap().clone();
+        let rejected = history.iter().find(|event| event.kind == TodoEventKind::Rejected).unwrap();
+        assert_eq!(rejected.details, serde_json::json!({"comment": "add the charts"}));
+
+        let error = reject_to_do_item::<MockDbHandle>(1, 5, "  ".to_string()).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        let error = reject_to_do_item::<MockDbHandle>(1, 6, "no".to_string()).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::Forbidden);
+    }
+}
@@ -0,0 +1,202 @@
+//! Core logic for completing a to-do item that may need review.
+//!
+//! # Overview
+//! Completing an item that requires review moves it to `pending_review` instead of finishing it,
+//! and the user who assigned it is emailed the endpoints to approve or reject it. Items that do
+//! not require review are finished straight away as before. Either way the completion is recorded
+//! in the history of the item.
+use utils::config::GetConfigVariable;
+use utils::log_limited;
+use utils::errors::NanoServiceError;
+use dal::to_do_items::tx_definitions::CompleteToDoItem;
+use dal::users::tx_definitions::GetUser;
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use email_core::api::mailchimp_emails::review_request_email::{send_review_request_email, ReviewRequest};
+use email_core::mailchimp_traits::mc_definitions::SendTemplate;
+use kernel::to_do_items::Todo;
+use kernel::todo_events::NewTodoEvent;
+use kernel::analytics::{emit, AnalyticsEvent, AnalyticsSink};
+use kernel::events::{publish, DomainEvent, EventBus};
+
+
+/// The endpoint the assigner calls to approve a to-do item.
+pub const APPROVE_ENDPOINT: &str = "/api/todo/v1/review/approve";
+
+/// The endpoint the assigner calls to reject a to-do item.
+pub const REJECT_ENDPOINT: &str = "/api/todo/v1/review/reject";
+
+
+/// Completes a to-do item, asking the assigner for a review if the item requires one.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item being completed.
+/// - `actor_id`: The ID of the user completing the item.
+///
+/// # Returns
+/// - `Ok(Todo)`: The item, either finished or pending review.
+/// - `Err(NanoServiceError)`: If the item could not be completed.
+///
+/// # Notes
+/// - A `todo_completed` event is sent to `A` for the assignee, and published with `E`, whether or not a review
+///   is needed.
+/// - A failure to notify the assigner is logged rather than returned as the item has already
+///   moved to `pending_review` and the assigner can still find it there.
+pub async fn complete_to_do_item_with_review<W, X, Y, A, E>(todo_id: i32, actor_id: i32) -> Result<Todo, NanoServiceError>
+where
+    W: SendTemplate,
+    X: CompleteToDoItem + GetUser + RecordToDoEvent,
+    Y: GetConfigVariable,
+    A: AnalyticsSink,
+    E: EventBus,
+{
+    let todo = X::complete_to_do_item(todo_id).await?;
+    X::record_to_do_event(NewTodoEvent::completed(&todo, actor_id)).await?;
+    emit::<A, Y>(AnalyticsEvent::todo_completed(&todo)).await;
+    publish::<E, Y>(DomainEvent::todo_completed(&todo)).await;
+    if !todo.pending_review {
+        return Ok(todo)
+    }
+    let request = ReviewRequest {
+        todo_id: todo.id,
+        todo_name: todo.name.clone(),
+        approve_endpoint: APPROVE_ENDPOINT.to_string(),
+        reject_endpoint: REJECT_ENDPOINT.to_string(),
+    };
+    let notified = match X::get_user(todo.assigned_by).await {
+        Ok(assigner) => send_review_request_email::<W, Y>(assigner.email, request).await,
+        Err(e) => Err(e)
+    };
+    match notified {
+        Ok(true) => {},
+        Ok(false) => println!("review request for to-do item {} was not accepted", todo.id),
+        Err(e) => log_limited!("to_do", "failed to send review request for to-do item {}: {}", todo.id, e.message)
+    }
+    Ok(todo)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
+    use dal_tx_impl::impl_transaction;
+    use email_core::mailchimp_helpers::mailchimp_template::Template;
+    use kernel::users::{User, UserRole};
+    use chrono::Utc;
+    use kernel::analytics::ProductEvent;
+    use kernel::analytics::engine_mock::{RecordAnalyticsMock, RECORDED_EVENTS};
+    use kernel::events::engine_mock::{RecordEventsMock, PUBLISHED_EVENTS};
+    use kernel::todo_events::TodoEvent;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static SENT: AtomicUsize = AtomicUsize::new(0);
+    static HISTORY: Mutex<Vec<NewTodoEvent>> = Mutex::new(Vec::new());
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "PRODUCTION" => Ok("TRUE".to_string()),
+                _ => Ok("key".to_string())
+            }
+        }
+    }
+
+    struct MockMailchimp;
+
+    #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+    async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {
+        assert_eq!(template.message.to[0].email, "assigner@example.com");
+        SENT.fetch_add(1, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    struct MockDbHandle;
+
+    /// Item 1 requires review, any other item does not.
+    #[impl_transaction(MockDbHandle, CompleteToDoItem, complete_to_do_item)]
+    async fn complete_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        let requires_review = todo_id == 1;
+        let now = Utc::now().naive_utc();
+        Ok(Todo {
+            id: todo_id,
+            name: "Task".to_string(),
+            due_date: None,
+            assigned_by: 5,
+            assigned_to: 2,
+            description: None,
+            date_assigned: now,
+            date_finished: if requires_review { None } else { Some(now) },
+            finished: !requires_review,
+            requires_review,
+            pending_review: requires_review,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        assert_eq!(id, 5);
+        let now = Utc::now().naive_utc();
+        Ok(User {
+            id,
+            confirmed: true,
+            username: "assigner".to_string(),
+            email: "assigner@example.com".to_string(),
+            first_name: "first".to_string(),
+            last_name: "last".to_string(),
+            user_role: UserRole::Admin,
+            password: "password".to_string(),
+            uuid: "uuid".to_string(),
+            date_created: now,
+            last_logged_in: now,
+            blocked: false,
+            avatar_version: None,
+        })
+    }
+
+    #[impl_transaction(MockDbHandle, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        HISTORY.lock().unwrap().push(event.clone());
+        Ok(TodoEvent {
+            id: 1,
+            todo_id: event.todo_id,
+            actor_id: Some(event.actor_id),
+            kind: event.kind,
+            details: sqlx::types::Json(event.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_review_requested_only_when_required() {
+        let todo = complete_to_do_item_with_review::<MockMailchimp, MockDbHandle, FakeConfig, RecordAnalyticsMock, RecordEventsMock>(2, 2).await.unwrap();
+        assert!(todo.finished);
+        assert_eq!(SENT.load(Ordering::Relaxed), 0);
+
+        let todo = complete_to_do_item_with_review::<MockMailchimp, MockDbHandle, FakeConfig, RecordAnalyticsMock, RecordEventsMock>(1, 2).await.unwrap();
+        assert!(!todo.finished);
+        assert!(todo.pending_review);
+        assert_eq!(SENT.load(Ordering::Relaxed), 1);
+
+        let completed: Vec<_> = RECORDED_EVENTS.lock().unwrap().iter()
+            .filter(|event| event.event == ProductEvent::TodoCompleted)
+            .map(|event| event.properties["todo_id"].clone())
+            .collect();
+        assert_eq!(completed, vec![serde_json::json!(2), serde_json::json!(1)]);
+        let published: Vec<_> = PUBLISHED_EVENTS.lock().unwrap().iter().map(|envelope| envelope.event.clone()).collect();
+        assert_eq!(published, vec![
+            DomainEvent::TodoCompleted { todo_id: 2, assigned_to: 2, pending_review: false },
+            DomainEvent::TodoCompleted { todo_id: 1, assigned_to: 2, pending_review: true },
+        ]);
+        let history: Vec<_> = HISTORY.lock().unwrap().iter().map(|event| (event.todo_id, event.details.clone())).collect();
+        assert_eq!(history, vec![
+            (2, serde_json::json!({"pending_review": false})),
+            (1, serde_json::json!({"pending_review": true})),
+        ]);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod policies;
+pub mod stats;
+pub mod monitor;
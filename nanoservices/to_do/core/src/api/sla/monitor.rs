@@ -0,0 +1,206 @@
+//! Warns assignees about to-do items that are close to breaching their SLA.
+//!
+//! # Overview
+//! The monitor polls for unfinished items that have reached their warning time, `warn_minutes`
+//! before the SLA is due, and emails each assignee once per item with the due time written in
+//! their locale. Claiming an item records the
+//! warning, so a failed send is logged and not retried here, pass `EmailOutbox` as the sender for
+//! the send itself to be retried.
+//!
+//! # Variables
+//! * `TODO_SLA_POLL_SECONDS` - How often the monitor polls, defaults to 60
+//! * `TODO_SLA_BATCH_SIZE` - The most items warned about per poll, defaults to 50
+use utils::config::{GetConfigVariable, TypedConfig};
+use utils::log_limited;
+use utils::errors::NanoServiceError;
+use utils::locale::Locale;
+use dal::sla::tx_definitions::ClaimSlaWarnings;
+use dal::users::tx_definitions::{GetUser, GetRecipientProfile};
+use email_core::api::mailchimp_emails::sla_warning_email::{send_sla_warning_email, SlaWarning};
+use email_core::mailchimp_traits::mc_definitions::SendTemplate;
+
+
+/// The poll interval used when not configured.
+const DEFAULT_POLL_SECONDS: i64 = 60;
+
+/// The batch size used when not configured.
+const DEFAULT_BATCH_SIZE: i64 = 50;
+
+
+/// Warns the assignees of the items that have reached their warning time.
+///
+/// # Arguments
+/// * `batch_size` - The most items to warn about in this pass
+///
+/// # Returns
+/// * `Ok(usize)` - The number of warnings sent
+/// * `Err(NanoServiceError)` - If the items could not be claimed
+pub async fn notify_approaching_sla<W, X, Y>(batch_size: i64) -> Result<usize, NanoServiceError>
+where
+    W: SendTemplate,
+    X: ClaimSlaWarnings + GetUser + GetRecipientProfile,
+    Y: GetConfigVariable,
+{
+    let mut sent = 0;
+    for item in X::claim_sla_warnings(batch_size).await? {
+        let notified = match X::get_user(item.assigned_to).await {
+            Ok(assignee) => {
+                // a profile that cannot be read only costs the assignee their date format
+                let locale = match X::get_recipient_profile(assignee.email.clone()).await {
+                    Ok(Some(profile)) => Locale::from_tag_or_default(&profile.locale),
+                    _ => Locale::default()
+                };
+                let warning = SlaWarning {
+                    todo_id: item.todo_id,
+                    todo_name: item.name,
+                    priority: item.priority.as_str().to_string(),
+                    sla_due: item.sla_due,
+                    locale,
+                };
+                send_sla_warning_email::<W, Y>(assignee.email, warning).await
+            },
+            Err(e) => Err(e)
+        };
+        match notified {
+            Ok(true) => sent += 1,
+            Ok(false) => println!("SLA warning for to-do item {} was not accepted", item.todo_id),
+            Err(e) => println!("failed to send SLA warning for to-do item {}: {}", item.todo_id, e.message)
+        }
+    }
+    Ok(sent)
+}
+
+
+/// Polls for items approaching their SLA in the background for the life of the runtime.
+///
+/// # Notes
+/// An error in a pass is logged and the monitor carries on.
+pub fn spawn_sla_monitor<W, X, Y>()
+where
+    W: SendTemplate + 'static,
+    X: ClaimSlaWarnings + GetUser + GetRecipientProfile + 'static,
+    Y: GetConfigVariable + 'static,
+{
+    let poll_interval = std::time::Duration::from_secs(Y::get_positive_int("TODO_SLA_POLL_SECONDS").unwrap_or(DEFAULT_POLL_SECONDS) as u64);
+    let batch_size = Y::get_positive_int("TODO_SLA_BATCH_SIZE").unwrap_or(DEFAULT_BATCH_SIZE);
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = notify_approaching_sla::<W, X, Y>(batch_size).await {
+                log_limited!("to_do", "SLA monitor pass failed: {}", e.message);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use email_core::mailchimp_helpers::mailchimp_template::Template;
+    use kernel::sla::TodoSlaStatus;
+    use kernel::to_do_items::TodoPriority;
+    use kernel::users::{RecipientProfile, User, UserRole};
+    use utils::errors::NanoServiceErrorStatus;
+    use chrono::{NaiveDate, Utc};
+    use std::sync::Mutex;
+
+    static SENT_TO: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "PRODUCTION" => Ok("TRUE".to_string()),
+                "TODO_SLA_BATCH_SIZE" => Ok("none".to_string()),
+                _ => Ok("key".to_string())
+            }
+        }
+    }
+
+    struct MockMailchimp;
+
+    #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+    async fn send_template(template: &Template) -> Result<bool, NanoServiceError> {
+        let sla_due = template.message.global_merge_vars.iter().find(|var| var.name == "SLA_DUE").unwrap();
+        SENT_TO.lock().unwrap().push((template.message.to[0].email.clone(), sla_due.content.clone()));
+        Ok(true)
+    }
+
+    struct MockDbHandle;
+
+    fn status(todo_id: i32, assigned_to: i32) -> TodoSlaStatus {
+        let now = Utc::now().naive_utc();
+        let sla_due = NaiveDate::from_ymd_opt(2025, 2, 10).unwrap().and_hms_opt(17, 30, 0).unwrap();
+        TodoSlaStatus {
+            todo_id,
+            name: "Task".to_string(),
+            assigned_by: 1,
+            assigned_to,
+            priority: TodoPriority::High,
+            date_assigned: now,
+            sla_due,
+            breached: false,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, ClaimSlaWarnings, claim_sla_warnings)]
+    async fn claim_sla_warnings(limit: i64) -> Result<Vec<TodoSlaStatus>, NanoServiceError> {
+        assert_eq!(limit, 10);
+        Ok(vec![status(1, 2), status(2, 9), status(3, 3)])
+    }
+
+    /// User 9 does not exist.
+    #[impl_transaction(MockDbHandle, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        if id == 9 {
+            return Err(NanoServiceError::new("not found".to_string(), NanoServiceErrorStatus::NotFound))
+        }
+        Ok(User {
+            id,
+            confirmed: true,
+            username: format!("user{}", id),
+            email: format!("user{}@example.com", id),
+            first_name: "first".to_string(),
+            last_name: "last".to_string(),
+            user_role: UserRole::Worker,
+            password: "password".to_string(),
+            uuid: "uuid".to_string(),
+            date_created: Utc::now().naive_utc(),
+            last_logged_in: Utc::now().naive_utc(),
+            blocked: false,
+            avatar_version: None,
+        })
+    }
+
+    /// User 2 reads German dates and user 3 has no profile.
+    #[impl_transaction(MockDbHandle, GetRecipientProfile, get_recipient_profile)]
+    async fn get_recipient_profile(email: String) -> Result<Option<RecipientProfile>, NanoServiceError> {
+        if email != "user2@example.com" {
+            return Ok(None)
+        }
+        Ok(Some(RecipientProfile {
+            first_name: "first".to_string(),
+            username: "user2".to_string(),
+            locale: "de-AT".to_string(),
+            timezone: "Europe/Vienna".to_string(),
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_assignees_are_warned() {
+        let sent = notify_approaching_sla::<MockMailchimp, MockDbHandle, FakeConfig>(10).await.unwrap();
+        assert_eq!(sent, 2);
+        assert_eq!(*SENT_TO.lock().unwrap(), vec![
+            ("user2@example.com".to_string(), "10.02.2025 17:30 UTC".to_string()),
+            ("user3@example.com".to_string(), "2025-02-10 17:30 UTC".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_batch_size_falls_back_to_default() {
+        assert_eq!(FakeConfig::get_positive_int("TODO_SLA_BATCH_SIZE").unwrap_or(DEFAULT_BATCH_SIZE), DEFAULT_BATCH_SIZE);
+    }
+}
@@ -0,0 +1,70 @@
+//! Core logic for the SLA policies admins set per priority.
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::sla::tx_definitions::{GetSlaPolicies, UpsertSlaPolicy};
+use kernel::sla::SlaPolicy;
+
+
+/// Gets the SLA policy for every priority that has one.
+///
+/// # Returns
+/// - `Ok(Vec<SlaPolicy>)`: The policies, the longest target first.
+/// - `Err(NanoServiceError)`: If the policies could not be read.
+pub async fn get_sla_policies<X: GetSlaPolicies>() -> Result<Vec<SlaPolicy>, NanoServiceError> {
+    X::get_sla_policies().await
+}
+
+
+/// Sets the SLA policy for a priority.
+///
+/// # Arguments
+/// - `policy`: The policy, replacing any existing policy for the same priority.
+///
+/// # Returns
+/// - `Ok(SlaPolicy)`: The saved policy.
+/// - `Err(NanoServiceError)`: `BadRequest` if the target is not positive or the warning does not
+///   fall before the target, or if the policy could not be saved.
+pub async fn set_sla_policy<X: UpsertSlaPolicy>(policy: SlaPolicy) -> Result<SlaPolicy, NanoServiceError> {
+    if policy.target_minutes <= 0 {
+        return Err(NanoServiceError::new(
+            "target_minutes has to be greater than 0".to_string(),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    if policy.warn_minutes < 0 || policy.warn_minutes >= policy.target_minutes {
+        return Err(NanoServiceError::new(
+            "warn_minutes has to be at least 0 and less than target_minutes".to_string(),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    X::upsert_sla_policy(policy).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::to_do_items::TodoPriority;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, UpsertSlaPolicy, upsert_sla_policy)]
+    async fn upsert_sla_policy(policy: SlaPolicy) -> Result<SlaPolicy, NanoServiceError> {
+        Ok(policy)
+    }
+
+    fn policy(target_minutes: i32, warn_minutes: i32) -> SlaPolicy {
+        SlaPolicy { priority: TodoPriority::High, target_minutes, warn_minutes }
+    }
+
+    #[tokio::test]
+    async fn test_set_sla_policy() {
+        let saved = set_sla_policy::<MockDbHandle>(policy(60, 15)).await.unwrap();
+        assert_eq!(saved, policy(60, 15));
+
+        for invalid in [policy(0, 0), policy(60, -1), policy(60, 60)] {
+            let error = set_sla_policy::<MockDbHandle>(invalid).await.unwrap_err();
+            assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        }
+    }
+}
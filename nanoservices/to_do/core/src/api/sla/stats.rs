@@ -0,0 +1,33 @@
+//! Core logic for the to-do item stats, including the items that have breached their SLA.
+use serde::{Serialize, Deserialize};
+use utils::errors::NanoServiceError;
+use dal::sla::tx_definitions::{GetToDoCounts, GetBreachedToDoItems};
+use kernel::sla::{ToDoCounts, TodoSlaStatus};
+
+
+/// The stats for all to-do items.
+///
+/// # Fields
+/// * `counts` - The number of items in each state.
+/// * `breached_items` - The unfinished items past their SLA, the most overdue first.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ToDoStats {
+    #[serde(flatten)]
+    pub counts: ToDoCounts,
+    pub breached_items: Vec<TodoSlaStatus>,
+}
+
+
+/// Gets the stats for all to-do items.
+///
+/// # Returns
+/// - `Ok(ToDoStats)`: The stats.
+/// - `Err(NanoServiceError)`: If the items could not be read.
+pub async fn get_to_do_stats<X>() -> Result<ToDoStats, NanoServiceError>
+where
+    X: GetToDoCounts + GetBreachedToDoItems
+{
+    let counts = X::get_to_do_counts().await?;
+    let breached_items = X::get_breached_to_do_items().await?;
+    Ok(ToDoStats { counts, breached_items })
+}
@@ -0,0 +1,210 @@
+//! Core logic for moving a to-do item between the statuses of its workflow.
+//!
+//! # Overview
+//! The moves allowed are read from `TODO_STATUS_TRANSITIONS`, see `kernel::todo_workflow`. The
+//! users the item is assigned by and to, and admins, can move it. Every move is recorded in the
+//! history of the item.
+//!
+//! Moving an item to `done` finishes it like completing it does, so it is reported to analytics and
+//! published as `todo_completed`. An item that requires review has to be completed instead so the
+//! assigner is asked to review it, and an item waiting for review cannot be moved until it is reviewed.
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::to_do_items::tx_definitions::{GetToDoItem, TransitionToDoItem};
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use kernel::analytics::{emit, AnalyticsEvent, AnalyticsSink};
+use kernel::events::{publish, DomainEvent, EventBus};
+use kernel::to_do_items::{Todo, TodoStatus};
+use kernel::todo_events::NewTodoEvent;
+use kernel::todo_workflow::{status_transitions, StatusOptions};
+use kernel::users::UserRole;
+
+
+/// The statuses each status can be moved to under the configured workflow.
+pub fn get_status_transitions<Y: GetConfigVariable>() -> Vec<StatusOptions> {
+    status_transitions::<Y>().options()
+}
+
+
+/// Moves a to-do item to another status.
+///
+/// # Arguments
+/// - `todo_id`: The ID of the to-do item to move.
+/// - `to`: The status to move the item to.
+/// - `actor_id`: The ID of the user moving the item.
+/// - `role`: The role of the user moving the item.
+///
+/// # Returns
+/// - `Ok(Todo)`: The moved to-do item.
+/// - `Err(NanoServiceError)`: `NotFound` if there is no such item, `Forbidden` if the user neither
+///   assigned it nor is assigned it, and is not an admin, `Conflict` if the move is not allowed, the
+///   item needs or is waiting for review, or it was moved by someone else at the same time.
+pub async fn transition_to_do_item<X, Y, A, E>(todo_id: i32, to: TodoStatus, actor_id: i32, role: &UserRole) -> Result<Todo, NanoServiceError>
+where
+    X: GetToDoItem + TransitionToDoItem + RecordToDoEvent,
+    Y: GetConfigVariable,
+    A: AnalyticsSink,
+    E: EventBus,
+{
+    let current = X::get_to_do_item(todo_id).await?;
+    let is_admin = matches!(role, UserRole::SuperAdmin | UserRole::Admin);
+    if current.assigned_by != actor_id && current.assigned_to != actor_id && !is_admin {
+        return Err(NanoServiceError::new(
+            "Only the users the to-do item is assigned by and to, or an admin, can change its status".to_string(),
+            NanoServiceErrorStatus::Forbidden
+        ))
+    }
+    if current.pending_review {
+        return Err(NanoServiceError::new(
+            format!("To-do item {} is waiting for review", todo_id),
+            NanoServiceErrorStatus::Conflict
+        ))
+    }
+    if to == TodoStatus::Done && current.requires_review {
+        return Err(NanoServiceError::new(
+            format!("To-do item {} requires review, complete it to ask for one", todo_id),
+            NanoServiceErrorStatus::Conflict
+        ))
+    }
+    status_transitions::<Y>().check(current.status, to)?;
+    let todo = X::transition_to_do_item(todo_id, current.status, to).await?.ok_or_else(|| NanoServiceError::new(
+        format!("To-do item {} changed status while it was being moved", todo_id),
+        NanoServiceErrorStatus::Conflict
+    ))?;
+    X::record_to_do_event(NewTodoEvent::status_changed(&todo, current.status, actor_id)).await?;
+    if to == TodoStatus::Done {
+        emit::<A, Y>(AnalyticsEvent::todo_completed(&todo)).await;
+        publish::<E, Y>(DomainEvent::todo_completed(&todo)).await;
+    }
+    Ok(todo)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::analytics::engine_mock::NoAnalyticsMock;
+    use kernel::events::EventEnvelope;
+    use kernel::to_do_items::TodoPriority;
+    use kernel::todo_events::{TodoEvent, TodoEventKind};
+    use chrono::Utc;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static HISTORY: Mutex<Vec<NewTodoEvent>> = Mutex::new(Vec::new());
+    static COMPLETED: AtomicUsize = AtomicUsize::new(0);
+
+    /// Counts the `todo_completed` events published.
+    struct CountCompletedMock;
+
+    impl EventBus for CountCompletedMock {
+        async fn publish<Y: GetConfigVariable>(envelope: &EventEnvelope) -> Result<(), NanoServiceError> {
+            if matches!(envelope.event, DomainEvent::TodoCompleted { .. }) {
+                COMPLETED.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(())
+        }
+    }
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(variable: String) -> Result<String, NanoServiceError> {
+            match variable.as_str() {
+                "PRODUCTION" => Ok("TRUE".to_string()),
+                _ => Err(NanoServiceError::new("not set".to_string(), NanoServiceErrorStatus::Unknown))
+            }
+        }
+    }
+
+    /// Item 1 is in progress, item 2 requires review, item 3 is waiting for review and item 4 is
+    /// moved by someone else between being read and moved. All are assigned by user 5 to user 2.
+    struct MockDbHandle;
+
+    fn todo(todo_id: i32, status: TodoStatus) -> Todo {
+        Todo {
+            id: todo_id,
+            name: "Task".to_string(),
+            due_date: None,
+            assigned_by: 5,
+            assigned_to: 2,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: status == TodoStatus::Done,
+            requires_review: todo_id == 2,
+            pending_review: todo_id == 3,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status,
+        }
+    }
+
+    #[impl_transaction(MockDbHandle, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(todo(todo_id, TodoStatus::InProgress))
+    }
+
+    #[impl_transaction(MockDbHandle, TransitionToDoItem, transition_to_do_item)]
+    async fn transition_to_do_item(todo_id: i32, from: TodoStatus, to: TodoStatus) -> Result<Option<Todo>, NanoServiceError> {
+        assert_eq!(from, TodoStatus::InProgress);
+        Ok((todo_id != 4).then(|| todo(todo_id, to)))
+    }
+
+    #[impl_transaction(MockDbHandle, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        HISTORY.lock().unwrap().push(event.clone());
+        Ok(TodoEvent {
+            id: 1,
+            todo_id: event.todo_id,
+            actor_id: Some(event.actor_id),
+            kind: event.kind,
+            details: sqlx::types::Json(event.details),
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    async fn transition(todo_id: i32, to: &str, actor_id: i32) -> Result<Todo, NanoServiceError> {
+        transition_to_do_item::<MockDbHandle, MockConfig, NoAnalyticsMock, CountCompletedMock>(
+            todo_id, TodoStatus::from_str(to).unwrap(), actor_id, &UserRole::Worker
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_transition_to_do_item() {
+        let todo = transition(1, "blocked", 2).await.unwrap();
+        assert_eq!(todo.status, TodoStatus::Blocked);
+        assert_eq!(COMPLETED.load(Ordering::Relaxed), 0);
+        let todo = transition(1, "done", 5).await.unwrap();
+        assert!(todo.finished);
+        assert_eq!(COMPLETED.load(Ordering::Relaxed), 1);
+
+        let history = HISTORY.lock().unwrap().clone();
+        assert_eq!(history[0].kind, TodoEventKind::StatusChanged);
+        assert_eq!(history[0].details, serde_json::json!({"from": "in_progress", "to": "blocked"}));
+        assert_eq!(history[1].actor_id, 5);
+    }
+
+    #[tokio::test]
+    async fn test_transition_is_refused() {
+        let not_theirs = transition(1, "blocked", 7).await.unwrap_err();
+        assert_eq!(not_theirs.status, NanoServiceErrorStatus::Forbidden);
+        let same_status = transition(1, "in_progress", 2).await.unwrap_err();
+        assert_eq!(same_status.status, NanoServiceErrorStatus::Conflict);
+        let needs_review = transition(2, "done", 2).await.unwrap_err();
+        assert_eq!(needs_review.status, NanoServiceErrorStatus::Conflict);
+        let waiting_for_review = transition(3, "backlog", 2).await.unwrap_err();
+        assert_eq!(waiting_for_review.status, NanoServiceErrorStatus::Conflict);
+        let moved_meanwhile = transition(4, "backlog", 2).await.unwrap_err();
+        assert_eq!(moved_meanwhile.status, NanoServiceErrorStatus::Conflict);
+    }
+
+    #[test]
+    fn test_get_status_transitions() {
+        let options = get_status_transitions::<MockConfig>();
+        assert_eq!(options[0].status, TodoStatus::Backlog);
+        assert_eq!(options[0].next, vec![TodoStatus::InProgress, TodoStatus::Blocked, TodoStatus::Done]);
+    }
+}
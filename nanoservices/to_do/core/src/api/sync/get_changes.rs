@@ -0,0 +1,211 @@
+//! Core logic for telling offline clients what has changed since they last synced.
+//!
+//! # Overview
+//! A client passes the `next_cursor` of its last sync, or nothing on its first sync, and gets back
+//! the users and to-do items changed since along with the deletions. While `has_more` is `true` it
+//! syncs again straight away with the new cursor.
+//!
+//! # Notes
+//! - Only the to-do items assigned to or by the user are synced.
+//! - Super admins get every user, admins get the members of their organization and anyone else
+//!   only gets themselves. Users are synced without their `uuid`.
+//! - Deletions are only synced from the organization of the user, unless they are a super admin.
+use utils::errors::NanoServiceError;
+use dal::sync::tx_definitions::{GetUsersChangedSince, GetToDoItemsChangedSince};
+use dal::tombstones::tx_definitions::GetTombstones;
+use kernel::sync::{SyncChanges, SyncCursor, SyncPosition};
+use kernel::users::UserRole;
+use kernel::chrono::DateTime;
+use super::page_size;
+
+
+/// Gets the changes a user has not synced yet.
+///
+/// # Arguments
+/// - `user_id`: The ID of the user syncing.
+/// - `role`: The role of the user syncing.
+/// - `org_id`: The ID of the organization the user is syncing in.
+/// - `cursor`: The `next_cursor` of the last sync, `None` to sync everything.
+/// - `limit`: The most rows to return from each feed.
+///
+/// # Returns
+/// - `Ok(SyncChanges)`: The changes and the cursor for the next sync.
+/// - `Err(NanoServiceError)`: `BadRequest` if the cursor is not valid or `limit` is out of range,
+///   or if the changes could not be read.
+pub async fn get_changes<X>(
+    user_id: i32,
+    role: UserRole,
+    org_id: i32,
+    cursor: Option<&str>,
+    limit: Option<i64>
+) -> Result<SyncChanges, NanoServiceError>
+where
+    X: GetUsersChangedSince + GetToDoItemsChangedSince + GetTombstones,
+{
+    let limit = page_size(limit)?;
+    let mut cursor = match cursor {
+        Some(cursor) => SyncCursor::decode(cursor)?,
+        None => SyncCursor::default(),
+    };
+    let (user_filter, org_filter) = match role {
+        UserRole::SuperAdmin => (None, None),
+        UserRole::Admin => (None, Some(org_id)),
+        _ => (Some(user_id), None),
+    };
+    let deletion_filter = match role {
+        UserRole::SuperAdmin => None,
+        _ => Some(org_id),
+    };
+
+    // one extra row is read from each feed to know whether it was cut short
+    let mut users = X::get_users_changed_since(user_filter, org_filter, cursor.users, limit + 1).await?;
+    let mut todos = X::get_to_do_items_changed_since(user_id, cursor.todos, limit + 1).await?;
+    let mut deletions = X::get_tombstones(deletion_filter, DateTime::UNIX_EPOCH.naive_utc(), cursor.after_deletion_id, limit + 1).await?;
+    let has_more = [users.len(), todos.len(), deletions.len()].into_iter().any(|len| len as i64 > limit);
+    users.truncate(limit as usize);
+    todos.truncate(limit as usize);
+    deletions.truncate(limit as usize);
+
+    if let Some(user) = users.last() {
+        cursor.users = SyncPosition { updated_at: user.updated_at, id: user.user.id };
+    }
+    if let Some(todo) = todos.last() {
+        cursor.todos = SyncPosition { updated_at: todo.updated_at, id: todo.todo.id };
+    }
+    if let Some(tombstone) = deletions.last() {
+        cursor.after_deletion_id = tombstone.id;
+    }
+    Ok(SyncChanges {
+        users,
+        todos,
+        deletions,
+        next_cursor: cursor.encode(),
+        has_more,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::sync::{SyncedUser, SyncedTodo};
+    use kernel::users::PublicUser;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use kernel::tombstones::{Tombstone, TombstoneEntity};
+    use kernel::chrono::NaiveDateTime;
+    use utils::errors::NanoServiceErrorStatus;
+
+    fn time(seconds: i64) -> NaiveDateTime {
+        DateTime::from_timestamp(seconds, 0).unwrap().naive_utc()
+    }
+
+    fn user(id: i32) -> SyncedUser {
+        SyncedUser {
+            user: PublicUser {
+                id,
+                confirmed: true,
+                username: format!("user{}", id),
+                email: format!("user{}@example.com", id),
+                first_name: "Test".to_string(),
+                last_name: "User".to_string(),
+                user_role: UserRole::Worker,
+                date_created: time(0),
+                last_logged_in: time(0),
+                blocked: false,
+                avatar_url: None,
+            },
+            updated_at: time(100),
+        }
+    }
+
+    fn todo(id: i32) -> SyncedTodo {
+        SyncedTodo {
+            todo: Todo {
+                id,
+                name: "task".to_string(),
+                due_date: None,
+                assigned_by: 1,
+                assigned_to: 2,
+                description: None,
+                date_assigned: time(0),
+                date_finished: None,
+                finished: false,
+                requires_review: false,
+                pending_review: false,
+                review_comment: None,
+                priority: TodoPriority::Medium,
+                status: TodoStatus::Backlog,
+            },
+            // every item changed in the same instant
+            updated_at: time(200),
+        }
+    }
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetUsersChangedSince, get_users_changed_since)]
+    async fn get_users_changed_since(user_id: Option<i32>, org_id: Option<i32>, after: SyncPosition, _limit: i64) -> Result<Vec<SyncedUser>, NanoServiceError> {
+        // users 1 and 2 are in organization 1, user 3 in organization 2
+        let users = match (user_id, org_id) {
+            (Some(user_id), _) => vec![user(user_id)],
+            (None, Some(1)) => vec![user(1), user(2)],
+            (None, Some(_)) => vec![user(3)],
+            (None, None) => vec![user(1), user(2), user(3)],
+        };
+        Ok(users.into_iter().filter(|user| (user.updated_at, user.user.id) > (after.updated_at, after.id)).collect())
+    }
+
+    #[impl_transaction(MockDbHandle, GetToDoItemsChangedSince, get_to_do_items_changed_since)]
+    async fn get_to_do_items_changed_since(_user_id: i32, after: SyncPosition, limit: i64) -> Result<Vec<SyncedTodo>, NanoServiceError> {
+        Ok((1..=3).map(todo)
+            .filter(|todo| (todo.updated_at, todo.todo.id) > (after.updated_at, after.id))
+            .take(limit as usize)
+            .collect())
+    }
+
+    #[impl_transaction(MockDbHandle, GetTombstones, get_tombstones)]
+    async fn get_tombstones(org_id: Option<i32>, _since: NaiveDateTime, after_id: i32, _limit: i64) -> Result<Vec<Tombstone>, NanoServiceError> {
+        // the only tombstone is in organization 1
+        if org_id.is_some_and(|org_id| org_id != 1) {
+            return Ok(vec![])
+        }
+        Ok((after_id + 1..=1).map(|id| Tombstone {
+            id,
+            entity_type: TombstoneEntity::Todo,
+            entity_id: 9,
+            deleted_at: time(300),
+        }).collect())
+    }
+
+    #[tokio::test]
+    async fn test_get_changes_pages_through_feeds() {
+        let first = get_changes::<MockDbHandle>(2, UserRole::Worker, 1, None, Some(2)).await.unwrap();
+        assert_eq!(first.users.iter().map(|u| u.user.id).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(first.todos.iter().map(|t| t.todo.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(first.deletions.len(), 1);
+        assert!(first.has_more);
+
+        let second = get_changes::<MockDbHandle>(2, UserRole::Worker, 1, Some(&first.next_cursor), Some(2)).await.unwrap();
+        assert!(second.users.is_empty());
+        assert_eq!(second.todos.iter().map(|t| t.todo.id).collect::<Vec<_>>(), vec![3]);
+        assert!(second.deletions.is_empty());
+        assert!(!second.has_more);
+    }
+
+    #[tokio::test]
+    async fn test_admins_get_the_users_of_their_org() {
+        let changes = get_changes::<MockDbHandle>(1, UserRole::Admin, 2, None, None).await.unwrap();
+        assert_eq!(changes.users.iter().map(|u| u.user.id).collect::<Vec<_>>(), vec![3]);
+        assert!(changes.deletions.is_empty());
+        let changes = get_changes::<MockDbHandle>(1, UserRole::SuperAdmin, 2, None, None).await.unwrap();
+        assert_eq!(changes.users.len(), 3);
+        assert_eq!(changes.deletions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_cursor() {
+        let error = get_changes::<MockDbHandle>(1, UserRole::Worker, 1, Some("garbage"), None).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+}
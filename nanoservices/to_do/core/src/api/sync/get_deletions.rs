@@ -0,0 +1,95 @@
+//! Core logic for telling sync clients which users and to-do items have been deleted.
+//!
+//! # Overview
+//! A client passes the time it last synced as `since` and pages through the tombstones written
+//! from then on with the `next_after_id` cursor of each page until it comes back as `None`. Only the
+//! tombstones of the organization the client is syncing in are returned.
+use utils::errors::NanoServiceError;
+use dal::tombstones::tx_definitions::GetTombstones;
+use kernel::tombstones::TombstonePage;
+use kernel::users::UserRole;
+use kernel::chrono::{DateTime, NaiveDateTime};
+use super::page_size;
+
+
+/// Gets a page of tombstones written since a point in time.
+///
+/// # Arguments
+/// - `role`: The role of the user syncing, super admins get the tombstones of every organization.
+/// - `org_id`: The ID of the organization the user is syncing in.
+/// - `since`: Only tombstones written at or after this time are returned, all of them if `None`.
+/// - `after_id`: The cursor from the previous page, `None` for the first page.
+/// - `limit`: The most tombstones to return, defaults to `DEFAULT_PAGE_SIZE`.
+///
+/// # Returns
+/// - `Ok(TombstonePage)`: The tombstones oldest first and the cursor for the next page.
+/// - `Err(NanoServiceError)`: `BadRequest` if `limit` is not between 1 and `MAX_PAGE_SIZE`, or if
+///   the tombstones could not be read.
+pub async fn get_deletions<X: GetTombstones>(
+    role: UserRole,
+    org_id: i32,
+    since: Option<NaiveDateTime>,
+    after_id: Option<i32>,
+    limit: Option<i64>
+) -> Result<TombstonePage, NanoServiceError> {
+    let limit = page_size(limit)?;
+    let since = since.unwrap_or(DateTime::UNIX_EPOCH.naive_utc());
+    let org_filter = match role {
+        UserRole::SuperAdmin => None,
+        _ => Some(org_id),
+    };
+
+    // one extra row is read to know whether there is another page
+    let mut deletions = X::get_tombstones(org_filter, since, after_id.unwrap_or(0), limit + 1).await?;
+    let next_after_id = match deletions.len() as i64 > limit {
+        true => {
+            deletions.truncate(limit as usize);
+            deletions.last().map(|tombstone| tombstone.id)
+        },
+        false => None
+    };
+    Ok(TombstonePage { deletions, next_after_id })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+    use kernel::tombstones::{Tombstone, TombstoneEntity};
+    use utils::errors::NanoServiceErrorStatus;
+    use super::super::MAX_PAGE_SIZE;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetTombstones, get_tombstones)]
+    async fn get_tombstones(org_id: Option<i32>, since: NaiveDateTime, after_id: i32, limit: i64) -> Result<Vec<Tombstone>, NanoServiceError> {
+        assert_eq!(org_id, Some(1));
+        assert_eq!(since, DateTime::UNIX_EPOCH.naive_utc());
+        Ok((after_id + 1..=5).take(limit as usize).map(|id| Tombstone {
+            id,
+            entity_type: TombstoneEntity::Todo,
+            entity_id: id * 10,
+            deleted_at: since,
+        }).collect())
+    }
+
+    #[tokio::test]
+    async fn test_get_deletions_pages() {
+        let page = get_deletions::<MockDbHandle>(UserRole::Worker, 1, None, None, Some(3)).await.unwrap();
+        assert_eq!(page.deletions.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(page.next_after_id, Some(3));
+
+        let page = get_deletions::<MockDbHandle>(UserRole::Worker, 1, None, page.next_after_id, Some(3)).await.unwrap();
+        assert_eq!(page.deletions.iter().map(|t| t.id).collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(page.next_after_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_deletions_limit() {
+        for limit in [0, MAX_PAGE_SIZE + 1] {
+            let error = get_deletions::<MockDbHandle>(UserRole::Worker, 1, None, None, Some(limit)).await.unwrap_err();
+            assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+        }
+    }
+}
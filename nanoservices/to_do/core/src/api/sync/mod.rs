@@ -0,0 +1,28 @@
+//! Core logic for the sync API used by offline clients.
+pub mod get_deletions;
+pub mod get_changes;
+
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The page size used when the client does not ask for one.
+pub const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// The largest page a client can ask for.
+pub const MAX_PAGE_SIZE: i64 = 500;
+
+
+/// Checks the page size asked for by a client, falling back to `DEFAULT_PAGE_SIZE`.
+///
+/// # Returns
+/// - `Err(NanoServiceError)`: `BadRequest` if `limit` is not between 1 and `MAX_PAGE_SIZE`.
+pub fn page_size(limit: Option<i64>) -> Result<i64, NanoServiceError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(NanoServiceError::new(
+            format!("limit has to be between 1 and {}", MAX_PAGE_SIZE),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    Ok(limit)
+}
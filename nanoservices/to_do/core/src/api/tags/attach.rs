@@ -0,0 +1,60 @@
+//! Core logic for attaching and detaching tags on to-do items.
+//!
+//! # Features
+//! - Delegates the operations to the data access layer (DAL) using `AttachTag` and `DetachTag`.
+use utils::errors::NanoServiceError;
+use dal::tags::tx_definitions::{AttachTag, DetachTag};
+use kernel::tags::TodoTag;
+
+/// Attaches a tag to a to-do item, attaching a tag that is already attached is not an error.
+///
+/// # Arguments
+/// - `todo_id`: The unique identifier of the to-do item.
+/// - `tag_id`: The unique identifier of the tag.
+///
+/// # Returns
+/// - `Ok(TodoTag)`: The link between the to-do item and the tag.
+/// - `Err(NanoServiceError)`: If an error occurs during the database transaction.
+pub async fn attach_tag<X: AttachTag>(todo_id: i32, tag_id: i32) -> Result<TodoTag, NanoServiceError> {
+    X::attach_tag(todo_id, tag_id).await
+}
+
+/// Detaches a tag from a to-do item.
+///
+/// # Arguments
+/// - `todo_id`: The unique identifier of the to-do item.
+/// - `tag_id`: The unique identifier of the tag.
+///
+/// # Returns
+/// - `Ok(bool)`: `true` if the tag was attached and has been removed.
+/// - `Err(NanoServiceError)`: If an error occurs during the database transaction.
+pub async fn detach_tag<X: DetachTag>(todo_id: i32, tag_id: i32) -> Result<bool, NanoServiceError> {
+    X::detach_tag(todo_id, tag_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, AttachTag, attach_tag)]
+    async fn attach_tag(todo_id: i32, tag_id: i32) -> Result<TodoTag, NanoServiceError> {
+        Ok(TodoTag { id: 1, todo_id, tag_id })
+    }
+
+    #[impl_transaction(MockDbHandle, DetachTag, detach_tag)]
+    async fn detach_tag(todo_id: i32, tag_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(todo_id == 1 && tag_id == 2)
+    }
+
+    /// Tests attaching and detaching a tag using a mock database implementation.
+    #[tokio::test]
+    async fn test_attach_and_detach_tag() {
+        let link = attach_tag::<MockDbHandle>(1, 2).await.unwrap();
+        assert_eq!(link, TodoTag { id: 1, todo_id: 1, tag_id: 2 });
+        assert!(detach_tag::<MockDbHandle>(1, 2).await.unwrap());
+        assert!(!detach_tag::<MockDbHandle>(1, 3).await.unwrap());
+    }
+}
@@ -0,0 +1,57 @@
+//! Core logic for creating a tag.
+//!
+//! # Overview
+//! This file contains the core functionality for creating a tag that can be attached to to-do items.
+//! Tag names are trimmed and lowercased so `Urgent` and `urgent ` do not end up as separate tags.
+//!
+//! # Features
+//! - Delegates the creation operation to the data access layer (DAL) using `CreateTag`.
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+use dal::tags::tx_definitions::CreateTag;
+use kernel::tags::{NewTag, Tag};
+
+/// Creates a new tag.
+///
+/// # Arguments
+/// - `tag`: The `NewTag` holding the name of the tag.
+///
+/// # Returns
+/// - `Ok(Tag)`: The created tag if the operation is successful.
+/// - `Err(NanoServiceError)`: If the name is empty or an error occurs during the database transaction.
+pub async fn create_tag<X: CreateTag>(tag: NewTag) -> Result<Tag, NanoServiceError> {
+    let name = tag.name.trim().to_lowercase();
+    if name.is_empty() {
+        return Err(NanoServiceError::new(
+            "Tag name cannot be empty".to_string(),
+            NanoServiceErrorStatus::BadRequest,
+        ))
+    }
+    X::create_tag(NewTag { name }).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, CreateTag, create_tag)]
+    async fn create_tag(tag: NewTag) -> Result<Tag, NanoServiceError> {
+        Ok(Tag { id: 1, name: tag.name })
+    }
+
+    /// Tests the tag name is normalised before it is stored.
+    #[tokio::test]
+    async fn test_create_tag_ok() {
+        let tag = create_tag::<MockDbHandle>(NewTag { name: " Urgent ".to_string() }).await.unwrap();
+        assert_eq!(tag, Tag { id: 1, name: "urgent".to_string() });
+    }
+
+    /// Tests an empty tag name is rejected.
+    #[tokio::test]
+    async fn test_create_tag_empty_name() {
+        let error = create_tag::<MockDbHandle>(NewTag { name: "  ".to_string() }).await.unwrap_err();
+        assert_eq!(error.status, NanoServiceErrorStatus::BadRequest);
+    }
+}
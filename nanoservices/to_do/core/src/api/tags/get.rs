@@ -0,0 +1,58 @@
+//! Core logic for listing tags.
+//!
+//! # Features
+//! - Delegates the retrieval operations to the data access layer (DAL) using `GetTags` and `GetTagsForToDoItem`.
+use utils::errors::NanoServiceError;
+use dal::tags::tx_definitions::{GetTags, GetTagsForToDoItem};
+use kernel::tags::Tag;
+
+/// Retrieves every tag in the system ordered by name.
+///
+/// # Returns
+/// - `Ok(Vec<Tag>)`: All of the tags.
+/// - `Err(NanoServiceError)`: If an error occurs during the database transaction.
+pub async fn get_tags<X: GetTags>() -> Result<Vec<Tag>, NanoServiceError> {
+    X::get_tags().await
+}
+
+/// Retrieves the tags attached to a to-do item.
+///
+/// # Arguments
+/// - `todo_id`: The unique identifier of the to-do item.
+///
+/// # Returns
+/// - `Ok(Vec<Tag>)`: The tags attached to the to-do item.
+/// - `Err(NanoServiceError)`: If an error occurs during the database transaction.
+pub async fn get_tags_for_to_do_item<X: GetTagsForToDoItem>(todo_id: i32) -> Result<Vec<Tag>, NanoServiceError> {
+    X::get_tags_for_to_do_item(todo_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dal_tx_impl::impl_transaction;
+
+    struct MockDbHandle;
+
+    #[impl_transaction(MockDbHandle, GetTags, get_tags)]
+    async fn get_tags() -> Result<Vec<Tag>, NanoServiceError> {
+        Ok(vec![
+            Tag { id: 1, name: "billing".to_string() },
+            Tag { id: 2, name: "urgent".to_string() },
+        ])
+    }
+
+    #[impl_transaction(MockDbHandle, GetTagsForToDoItem, get_tags_for_to_do_item)]
+    async fn get_tags_for_to_do_item(todo_id: i32) -> Result<Vec<Tag>, NanoServiceError> {
+        assert_eq!(todo_id, 4);
+        Ok(vec![Tag { id: 2, name: "urgent".to_string() }])
+    }
+
+    /// Tests listing all tags and the tags on a to-do item using a mock database implementation.
+    #[tokio::test]
+    async fn test_get_tags() {
+        assert_eq!(get_tags::<MockDbHandle>().await.unwrap().len(), 2);
+        let tags = get_tags_for_to_do_item::<MockDbHandle>(4).await.unwrap();
+        assert_eq!(tags[0].name, "urgent");
+    }
+}
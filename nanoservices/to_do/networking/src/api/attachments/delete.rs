@@ -0,0 +1,81 @@
+//! Networking layer for removing a file attached to a to-do item.
+use actix_web::{HttpResponse, web::Path};
+use dal::attachments::tx_definitions::{DeleteAttachment, GetAttachment};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use kernel::object_store::DeleteObject;
+use to_do_core::api::attachments::delete::delete_attachment as delete_attachment_core;
+use utils::api_endpoint;
+
+
+/// Removes the attachment and deletes its file from `O`.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetToDoItem, GetAttachment, DeleteAttachment])]
+pub async fn delete_attachment<O: DeleteObject>(path: Path<(i32, i32)>) {
+    let (todo_id, id) = path.into_inner();
+    let attachment = delete_attachment_core::<X, Y, O>(todo_id, id, jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok().json(attachment))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::TestRequest;
+    use dal_tx_impl::impl_transaction;
+    use kernel::attachments::Attachment;
+    use kernel::object_store::engine_mock::MemoryObjectStoreMock;
+    use kernel::to_do_items::Todo;
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(factories::todo(todo_id))
+    }
+
+    /// Item 1 has attachment 5, attached by user 2.
+    fn attachment(todo_id: i32, id: i32) -> Option<Attachment> {
+        (todo_id == 1 && id == 5).then(|| Attachment {
+            id,
+            todo_id,
+            uploaded_by: 2,
+            file_name: "report.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            size_bytes: 8,
+            storage_key: "attachments/1/networking-delete".to_string(),
+            date_created: kernel::chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockPostgres, GetAttachment, get_attachment)]
+    async fn get_attachment(todo_id: i32, id: i32) -> Result<Option<Attachment>, NanoServiceError> {
+        Ok(attachment(todo_id, id))
+    }
+
+    #[impl_transaction(MockPostgres, DeleteAttachment, delete_attachment)]
+    async fn delete_attachment(todo_id: i32, id: i32) -> Result<Option<Attachment>, NanoServiceError> {
+        Ok(attachment(todo_id, id))
+    }
+
+    #[tokio::test]
+    async fn test_delete_attachment() {
+        let resp = call_endpoint(
+            Method::DELETE, "/attachments/{todo_id}/{id}",
+            delete_attachment::<MockPostgres, FakeConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(2).request(TestRequest::delete().uri("/attachments/1/5"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+
+        let missing = call_endpoint(
+            Method::DELETE, "/attachments/{todo_id}/{id}",
+            delete_attachment::<MockPostgres, FakeConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(2).request(TestRequest::delete().uri("/attachments/1/9"))
+        ).await;
+        assert_eq!(missing.status(), 404);
+    }
+}
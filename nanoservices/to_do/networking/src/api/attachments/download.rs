@@ -0,0 +1,156 @@
+//! Networking layer for listing and downloading the files attached to a to-do item.
+use actix_web::{HttpResponse, web::Path};
+use actix_web::http::header::{
+    Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS
+};
+use dal::attachments::tx_definitions::{GetAttachment, ListAttachments};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use kernel::object_store::GetObject;
+use to_do_core::api::attachments::download::{
+    download_attachment as download_attachment_core,
+    list_attachments as list_attachments_core,
+};
+use utils::api_endpoint;
+
+
+/// The `Content-Disposition` a file is downloaded with, naming it as it was uploaded. A name that
+/// is not ASCII is also given in the `filename*` form so browsers keep it.
+pub fn attachment_disposition(file_name: &str) -> ContentDisposition {
+    let mut parameters = vec![DispositionParam::Filename(
+        file_name.chars().map(|c| if c.is_ascii() { c } else { '_' }).collect()
+    )];
+    if !file_name.is_ascii() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_string()),
+            language_tag: None,
+            value: file_name.as_bytes().to_vec(),
+        }));
+    }
+    ContentDisposition { disposition: DispositionType::Attachment, parameters }
+}
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetToDoItem, ListAttachments])]
+pub async fn list_attachments(todo_id: Path<i32>) {
+    let attachments = list_attachments_core::<X>(todo_id.into_inner(), jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok().json(attachments))
+}
+
+
+/// Streams the file from `O` with the type and name it was uploaded with. The file is always
+/// downloaded rather than shown, and never sniffed as another type.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetToDoItem, GetAttachment])]
+pub async fn download_attachment<O: GetObject>(path: Path<(i32, i32)>) {
+    let (todo_id, id) = path.into_inner();
+    let (attachment, body) = download_attachment_core::<X, Y, O>(todo_id, id, jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, attachment.content_type.as_str()))
+        .insert_header(attachment_disposition(&attachment.file_name))
+        .insert_header((X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .no_chunking(attachment.size_bytes as u64)
+        .streaming(body))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::http::header::CONTENT_DISPOSITION;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::attachments::Attachment;
+    use kernel::object_store::StoreObject;
+    use kernel::object_store::engine_mock::MemoryObjectStoreMock;
+    use kernel::to_do_items::Todo;
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(factories::todo(todo_id))
+    }
+
+    /// Item 1 has attachment 5.
+    fn attachment(todo_id: i32, id: i32) -> Option<Attachment> {
+        (todo_id == 1 && id == 5).then(|| Attachment {
+            id,
+            todo_id,
+            uploaded_by: 2,
+            file_name: "Résumé.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            size_bytes: 8,
+            storage_key: "attachments/1/networking-download".to_string(),
+            date_created: kernel::chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockPostgres, ListAttachments, list_attachments)]
+    async fn list_attachments(todo_id: i32) -> Result<Vec<Attachment>, NanoServiceError> {
+        Ok(attachment(todo_id, 5).into_iter().collect())
+    }
+
+    #[impl_transaction(MockPostgres, GetAttachment, get_attachment)]
+    async fn get_attachment(todo_id: i32, id: i32) -> Result<Option<Attachment>, NanoServiceError> {
+        Ok(attachment(todo_id, id))
+    }
+
+    fn request(uri: &str, user_id: i32) -> TestRequest {
+        TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(user_id).request(TestRequest::get().uri(uri))
+    }
+
+    #[test]
+    fn test_attachment_disposition() {
+        assert_eq!(attachment_disposition("report.pdf").to_string(), "attachment; filename=\"report.pdf\"");
+        assert_eq!(
+            attachment_disposition("Résumé.pdf").to_string(),
+            "attachment; filename=\"R_sum_.pdf\"; filename*=UTF-8''R%C3%A9sum%C3%A9.pdf"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_attachments() {
+        let resp = call_endpoint(
+            Method::GET, "/attachments/{todo_id}",
+            list_attachments::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request("/attachments/1", 1)
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[0]["id"], 5);
+        assert!(body[0].get("storage_key").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_download_attachment() {
+        MemoryObjectStoreMock::put_object::<FakeConfig>("attachments/1/networking-download", b"%PDF-1.7".to_vec(), "application/pdf").await.unwrap();
+        let resp = call_endpoint(
+            Method::GET, "/attachments/{todo_id}/{id}",
+            download_attachment::<MockPostgres, FakeConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock>,
+            request("/attachments/1/5", 2)
+        ).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "application/pdf");
+        assert!(resp.headers().get(CONTENT_DISPOSITION).unwrap().to_str().unwrap().starts_with("attachment;"));
+        assert_eq!(resp.headers().get(X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+        assert_eq!(test::read_body(resp).await.as_ref(), b"%PDF-1.7");
+
+        let not_theirs = call_endpoint(
+            Method::GET, "/attachments/{todo_id}/{id}",
+            download_attachment::<MockPostgres, FakeConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock>,
+            request("/attachments/1/5", 7)
+        ).await;
+        assert_eq!(not_theirs.status(), 403);
+        let missing = call_endpoint(
+            Method::GET, "/attachments/{todo_id}/{id}",
+            download_attachment::<MockPostgres, FakeConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock>,
+            request("/attachments/1/6", 2)
+        ).await;
+        assert_eq!(missing.status(), 404);
+    }
+}
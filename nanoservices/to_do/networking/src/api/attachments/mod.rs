@@ -0,0 +1,34 @@
+//! Defines the endpoints for the files attached to to-do items.
+//!
+//! # Overview
+//! These routes live under `/api/todo/v1/attachments/{todo_id}`. A file is uploaded as the `file`
+//! field of a `multipart/form-data` body and downloaded as a stream from the object store.
+pub mod upload;
+pub mod download;
+pub mod delete;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get, delete as delete_route};
+use kernel::content_scan::engine_clamd::ClamdScanner;
+use kernel::object_store::engine_configured::ConfiguredObjectStore;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn attachments_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1/attachments") // Namespace for attachment routes.
+        .route("{todo_id}", post().to(
+            upload::upload_attachment::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>, ConfiguredObjectStore, ClamdScanner>) // POST /api/todo/v1/attachments/{todo_id}.
+        )
+        .route("{todo_id}", get().to(
+            download::list_attachments::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/attachments/{todo_id}.
+        )
+        .route("{todo_id}/{id}", get().to(
+            download::download_attachment::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>, ConfiguredObjectStore>) // GET /api/todo/v1/attachments/{todo_id}/{id}.
+        )
+        .route("{todo_id}/{id}", delete_route().to(
+            delete::delete_attachment::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>, ConfiguredObjectStore>) // DELETE /api/todo/v1/attachments/{todo_id}/{id}.
+        )
+    );
+}
@@ -0,0 +1,158 @@
+//! Networking layer for attaching a file to a to-do item.
+use actix_multipart::Multipart;
+use actix_web::{HttpResponse, web::Path};
+use dal::attachments::tx_definitions::CreateAttachment;
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use futures_util::StreamExt;
+use kernel::attachments::AttachmentLimits;
+use kernel::content_scan::ScanContent;
+use kernel::object_store::{DeleteObject, StoreObject};
+use to_do_core::api::attachments::upload::{upload_attachment as upload_attachment_core, AttachmentUpload};
+use utils::api_endpoint;
+use utils::config::GetConfigVariable;
+use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+
+
+/// The multipart field the file is sent in.
+pub const FILE_FIELD: &str = "file";
+
+
+fn bad_upload(message: String) -> NanoServiceError {
+    NanoServiceError::new(message, NanoServiceErrorStatus::BadRequest)
+}
+
+
+/// Reads the `file` field of a multipart body, refusing it as soon as it is over the size limit so
+/// a large upload is never read whole.
+///
+/// # Returns
+/// - `Ok(AttachmentUpload)`: The file, typed `application/octet-stream` if the client gave no type.
+/// - `Err(NanoServiceError)`: `BadRequest` if the body is not multipart or has no `file` field,
+///   `PayloadTooLarge` if the file is over `ATTACHMENT_MAX_BYTES`.
+pub async fn read_upload<Y: GetConfigVariable>(mut payload: Multipart) -> Result<AttachmentUpload, NanoServiceError> {
+    let limits = AttachmentLimits::from_config::<Y>();
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| bad_upload(format!("Failed to read the upload: {}", e)))?;
+        if field.name() != Some(FILE_FIELD) {
+            continue
+        }
+        let file_name = field.content_disposition()
+            .and_then(|disposition| disposition.get_filename())
+            .unwrap_or_default()
+            .to_string();
+        let content_type = field.content_type()
+            .map(|content_type| content_type.to_string())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let mut body = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| bad_upload(format!("Failed to read the upload: {}", e)))?;
+            if body.len() + chunk.len() > limits.max_bytes {
+                return Err(limits.too_large())
+            }
+            body.extend_from_slice(&chunk);
+        }
+        return Ok(AttachmentUpload { file_name, content_type, body })
+    }
+    Err(bad_upload(format!("The upload has no {} field", FILE_FIELD)))
+}
+
+
+/// Attaches the `file` field of a `multipart/form-data` body to the to-do item. The file is stored
+/// with `O` once `S` has found it clean.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetToDoItem, CreateAttachment])]
+pub async fn upload_attachment<O: StoreObject + DeleteObject, S: ScanContent>(todo_id: Path<i32>, payload: Multipart) {
+    let upload = read_upload::<Y>(payload).await?;
+    let attachment = upload_attachment_core::<X, Y, O, S>(todo_id.into_inner(), jwt.user_id, &jwt.role, upload).await?;
+    Ok(HttpResponse::Created().json(attachment))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::http::header::CONTENT_TYPE;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::attachments::{Attachment, NewAttachment};
+    use kernel::content_scan::engine_mock::{InfectedScanMock, PassScanMock};
+    use kernel::object_store::engine_mock::{MemoryObjectStoreMock, STORED_OBJECTS};
+    use kernel::to_do_items::Todo;
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, PassAuthSessionCheckMock, TokenBuilder};
+
+    test_support::fake_config!(AttachmentConfig, {
+        "ATTACHMENT_MAX_BYTES" => "64",
+        "ATTACHMENT_ALLOWED_TYPES" => "text/plain, application/pdf",
+    });
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(factories::todo(todo_id))
+    }
+
+    #[impl_transaction(MockPostgres, CreateAttachment, create_attachment)]
+    async fn create_attachment(attachment: NewAttachment) -> Result<Attachment, NanoServiceError> {
+        Ok(Attachment {
+            id: 5,
+            todo_id: attachment.todo_id,
+            uploaded_by: attachment.uploaded_by,
+            file_name: attachment.file_name,
+            content_type: attachment.content_type,
+            size_bytes: attachment.size_bytes,
+            storage_key: attachment.storage_key,
+            date_created: kernel::chrono::Utc::now().naive_utc(),
+        })
+    }
+
+    /// A `multipart/form-data` body with one field.
+    fn multipart(field: &str, file_name: &str, content_type: &str, body: &str) -> TestRequest {
+        let payload = format!(
+            "--BOUNDARY\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n{}\r\n--BOUNDARY--\r\n",
+            field, file_name, content_type, body
+        );
+        TokenBuilder::<AttachmentConfig, NoRoleCheck>::new()
+            .user_id(2)
+            .request(TestRequest::post().uri("/attachments/1"))
+            .insert_header((CONTENT_TYPE, "multipart/form-data; boundary=BOUNDARY"))
+            .set_payload(payload)
+    }
+
+    #[tokio::test]
+    async fn test_upload_attachment() {
+        let resp = call_endpoint(
+            Method::POST, "/attachments/{todo_id}",
+            upload_attachment::<MockPostgres, AttachmentConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock, PassScanMock>,
+            multipart("file", "notes.txt", "text/plain", "buy milk")
+        ).await;
+        assert_eq!(resp.status(), 201);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["file_name"], "notes.txt");
+        assert_eq!(body["size_bytes"], 8);
+        assert!(body.get("storage_key").is_none());
+        assert!(STORED_OBJECTS.lock().unwrap().values().any(|stored| stored == b"buy milk"));
+    }
+
+    #[tokio::test]
+    async fn test_upload_refusals() {
+        let upload = |request: TestRequest| call_endpoint(
+            Method::POST, "/attachments/{todo_id}",
+            upload_attachment::<MockPostgres, AttachmentConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock, PassScanMock>,
+            request
+        );
+        assert_eq!(upload(multipart("file", "large.txt", "text/plain", &"a".repeat(65))).await.status(), 413);
+        assert_eq!(upload(multipart("file", "page.html", "text/html", "<html>")).await.status(), 415);
+        assert_eq!(upload(multipart("other", "notes.txt", "text/plain", "buy milk")).await.status(), 400);
+
+        let infected = call_endpoint(
+            Method::POST, "/attachments/{todo_id}",
+            upload_attachment::<MockPostgres, AttachmentConfig, PassAuthSessionCheckMock, MemoryObjectStoreMock, InfectedScanMock>,
+            multipart("file", "notes.txt", "text/plain", "buy milk")
+        ).await;
+        assert_eq!(infected.status(), 422);
+    }
+}
@@ -0,0 +1,116 @@
+use dal::to_do_items::tx_definitions::CompleteToDoItem;
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use dal::users::tx_definitions::GetUser;
+use email_core::mailchimp_traits::mc_definitions::SendTemplate;
+use to_do_core::api::review::request::complete_to_do_item_with_review;
+use kernel::analytics::engine_configured::ConfiguredAnalyticsSink;
+use kernel::events::engine_configured::ConfiguredEventBus;
+use kernel::token::checks::TodoCompletePermission;
+use serde::Deserialize;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+
+/// Schema for completing a to-do item
+///
+/// # Fields
+/// * `todo_id` - The ID of the to-do item to complete.
+#[derive(Deserialize)]
+pub struct CompleteToDoItemSchema {
+    pub todo_id: i32
+}
+
+/// Items that require review are moved to `pending_review` and the assigner is asked to review them.
+#[api_endpoint(
+    token=PermissionCheck<TodoCompletePermission>,
+    db_traits=[CompleteToDoItem, GetUser, RecordToDoEvent],
+    email_traits=[SendTemplate],
+    env_variable_trait=true
+)]
+pub async fn complete_to_do_item(body: Json<CompleteToDoItemSchema>) {
+    let item = complete_to_do_item_with_review::<W, X, Y, ConfiguredAnalyticsSink, ConfiguredEventBus>(body.todo_id, jwt.user_id).await?;
+    Ok(HttpResponse::Ok().json(item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::todo_events::{NewTodoEvent, TodoEvent};
+    use test_support::factories;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
+    use kernel::users::UserRole;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use kernel::token::checks::PermissionCheck;
+    use utils::send_test_request;
+    use kernel::to_do_items::Todo;
+    use kernel::users::User;
+    use email_core::mailchimp_helpers::mailchimp_template::Template;
+    use chrono::Utc;
+
+    type CompletePermissionCheck = PermissionCheck<TodoCompletePermission>;
+
+    // a worker session that has been granted `todo:complete`
+    test_support::session_mock!(CompleteSessionMock, 2, UserRole::Worker, ["todo:complete"]);
+
+    #[tokio::test]
+    async fn test_complete_item() {
+        struct MockPostgres;
+
+        #[impl_transaction(MockPostgres, RecordToDoEvent, record_to_do_event)]
+        async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+            Ok(factories::todo_event(1, event))
+        }
+
+        #[impl_transaction(MockPostgres, CompleteToDoItem, complete_to_do_item)]
+        async fn complete_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+            let now = Utc::now().naive_utc();
+            Ok(Todo {
+                id: todo_id,
+                name: "Mock Task".to_string(),
+                due_date: None,
+                assigned_by: 1,
+                assigned_to: 2,
+                description: None,
+                date_assigned: now,
+                date_finished: Some(now),
+                finished: true,
+                requires_review: false,
+                pending_review: false,
+                review_comment: None,
+                priority: TodoPriority::Medium,
+                status: TodoStatus::Done,
+            })
+        }
+
+        #[impl_transaction(MockPostgres, GetUser, get_user)]
+        async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+            // only the caller is looked up, the assigner is only looked up for items that require review
+            assert_eq!(id, 2);
+            Ok(factories::user(id))
+        }
+
+        struct MockMailchimp;
+
+        #[impl_transaction(MockMailchimp, SendTemplate, send_template)]
+        async fn send_template(_template: &Template) -> Result<bool, NanoServiceError> {
+            panic!("no review request should be sent")
+        }
+
+        send_test_request!(
+            POST,
+            "/complete",
+            serde_json::json!({"todo_id": 4}),
+            CompletePermissionCheck,
+            UserRole::Worker,
+            2,
+            complete_to_do_item,
+            MockMailchimp, MockPostgres, MockConfig, CompleteSessionMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+    }
+}
@@ -1,32 +1,53 @@
-use dal::to_do_items::tx_definitions::{CreateToDoItem, GetToDoItemsForUser};
+use dal::to_do_items::tx_definitions::{CreateToDoItem, GetToDoItemsForUser, CountOpenToDoItemsForUser, CountToDoItems};
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use dal::plans::tx_definitions::GetOrgPlan;
+use dal::audit_log::tx_definitions::CreateAuditEntry;
+use dal::notifications::tx_definitions::QueueNotification;
+use dal::moderation::tx_definitions::RecordModerationDecision;
+use to_do_core::api::basic_actions::capacity::create_to_do_item_within_capacity;
 use kernel::to_do_items::NewTodo;
+use kernel::moderation::engine_configured::ConfiguredModerator;
+use serde::Deserialize;
 use utils::api_endpoint;
+use utils::validation::validate_body;
 use actix_web::{
     HttpResponse,
     web::Json
 };
-use serde::{Deserialize, Serialize};
 
-
-#[derive(Serialize, Deserialize)]
-pub struct NewTodoBody {
-    pub item: NewTodo,
-    pub user_id: i32
+/// Schema for creating a to-do item
+///
+/// # Fields
+/// * `new_todo` - The to-do item, given at the top level of the body.
+/// * `override_capacity` - Assign the item even if the assignee is at their open item limit.
+#[derive(Deserialize)]
+pub struct CreateToDoItemSchema {
+    #[serde(flatten)]
+    pub new_todo: NewTodo,
+    #[serde(default)]
+    pub override_capacity: bool
 }
 
-
-#[api_endpoint(token=AdminRoleCheck, db_traits=[CreateToDoItem, GetToDoItemsForUser], env_variable_trait=true)]
-pub async fn create_to_do_item(new_todo: Json<NewTodo>) {
-    let new_item = new_todo.into_inner();
-    let user_id = new_item.assigned_to;
-    let _ = X::create_to_do_item(new_item).await?;
-    let items = X::get_to_do_items_for_user(user_id).await?;
+#[api_endpoint(
+    token=AdminRoleCheck,
+    db_traits=[CreateToDoItem, GetToDoItemsForUser, CountOpenToDoItemsForUser, CreateAuditEntry, QueueNotification, RecordModerationDecision, GetOrgPlan, CountToDoItems, RecordToDoEvent],
+    env_variable_trait=true
+)]
+pub async fn create_to_do_item(body: Json<CreateToDoItemSchema>) {
+    validate_body(&body.new_todo)?;
+    let CreateToDoItemSchema { new_todo, override_capacity } = body.into_inner();
+    let user_id = new_todo.assigned_to;
+    let _ = create_to_do_item_within_capacity::<X, Y, ConfiguredModerator>(new_todo, jwt.org_id, jwt.user_id, override_capacity).await?;
+    let items = X::get_to_do_items_for_user(user_id, jwt.org_id).await?;
     Ok(HttpResponse::Created().json(items))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use kernel::todo_events::{NewTodoEvent, TodoEvent};
+    use test_support::factories;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
     use kernel::users::UserRole;
     use dal_tx_impl::impl_transaction;
     use utils::errors::NanoServiceError;
@@ -34,14 +55,25 @@ mod tests {
     use kernel::token::checks::SuperAdminRoleCheck;
     use utils::send_test_request;
     use kernel::to_do_items::Todo;
+    use kernel::audit_log::{NewAuditEntry, AuditEntry};
+    use kernel::notifications::{NewNotification, PendingNotification, NotificationType};
+    use kernel::moderation::{NewModerationDecision, ModerationDecision};
+    use kernel::plans::{OrgPlan, Plan};
     use chrono::Utc;
 
     #[tokio::test]
     async fn test_create_item() {
         struct MockPostgres;
 
+        test_support::confirmed_users!(MockPostgres);
+
+        #[impl_transaction(MockPostgres, RecordToDoEvent, record_to_do_event)]
+        async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+            Ok(factories::todo_event(1, event))
+        }
+
         #[impl_transaction(MockPostgres, CreateToDoItem, create_to_do_item)]
-        async fn create_to_do_item(todo: NewTodo) -> Result<Todo, NanoServiceError> {
+        async fn create_to_do_item(todo: NewTodo, _org_id: i32) -> Result<Todo, NanoServiceError> {
             let now = Utc::now().naive_utc();
 
             Ok(Todo {
@@ -53,13 +85,18 @@ mod tests {
                 description: todo.description.clone(),// Optional description from input
                 date_assigned: todo.date_assigned.unwrap_or(now), // Use input or current timestamp
                 date_finished: None,                  // Not finished on creation
-                finished: false,                      // Not finished on creation
+                finished: false,                      // Not finished on creation,
+                requires_review: false,
+                pending_review: false,
+                review_comment: None,
+                priority: TodoPriority::Medium,
+                status: TodoStatus::Backlog,
             })
         }
 
 
         #[impl_transaction(MockPostgres, GetToDoItemsForUser, get_to_do_items_for_user)]
-        async fn get_to_do_items_for_user(user_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
+        async fn get_to_do_items_for_user(user_id: i32, _org_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
             let now = Utc::now().naive_utc();
 
             let todos = (1..=5).map(|i| {
@@ -73,12 +110,63 @@ mod tests {
                     date_assigned: now,
                     date_finished: None,
                     finished: false,
+                    requires_review: false,
+                    pending_review: false,
+                    review_comment: None,
+                    priority: TodoPriority::Medium,
+                    status: TodoStatus::Backlog,
                 }
             }).collect();
 
             Ok(todos)
         }
 
+        #[impl_transaction(MockPostgres, CountOpenToDoItemsForUser, count_open_to_do_items_for_user)]
+        async fn count_open_to_do_items_for_user(_user_id: i32) -> Result<i64, NanoServiceError> {
+            Ok(0)
+        }
+
+        #[impl_transaction(MockPostgres, CreateAuditEntry, create_audit_entry)]
+        async fn create_audit_entry(_entry: NewAuditEntry) -> Result<AuditEntry, NanoServiceError> {
+            panic!("no capacity override should be audited")
+        }
+
+        #[impl_transaction(MockPostgres, QueueNotification, queue_notification)]
+        async fn queue_notification(notification: NewNotification) -> Result<PendingNotification, NanoServiceError> {
+            assert_eq!(notification.user_id, 2);
+            assert_eq!(notification.notification_type, NotificationType::Assignment);
+            Ok(PendingNotification {
+                id: 1,
+                user_id: notification.user_id,
+                notification_type: notification.notification_type,
+                todo_id: notification.todo_id,
+                todo_name: notification.todo_name,
+                date_created: Utc::now().naive_utc(),
+            })
+        }
+
+        #[impl_transaction(MockPostgres, GetOrgPlan, get_org_plan)]
+        async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+            Ok(OrgPlan {
+                plan: Plan::Enterprise,
+                max_users: None,
+                max_todos: None,
+                mfa: None,
+                api_access: None,
+                date_updated: Utc::now().naive_utc(),
+            })
+        }
+
+        #[impl_transaction(MockPostgres, CountToDoItems, count_to_do_items)]
+        async fn count_to_do_items() -> Result<i64, NanoServiceError> {
+            Ok(0)
+        }
+
+        #[impl_transaction(MockPostgres, RecordModerationDecision, record_moderation_decision)]
+        async fn record_moderation_decision(_decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+            panic!("the description should be allowed")
+        }
+
         send_test_request!(
             POST, 
             "/create", 
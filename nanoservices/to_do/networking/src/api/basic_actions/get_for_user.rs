@@ -0,0 +1,111 @@
+use dal::to_do_items::tx_definitions::GetToDoItemsForUser;
+use dal::tags::tx_definitions::GetToDoItemsForUserByTag;
+use to_do_core::api::basic_actions::get_for_user::get_tagged_to_do_items_for_user;
+use kernel::fields::{FieldSelection, FieldsQuery};
+use kernel::to_do_items::TODO_FIELDS;
+use serde::Deserialize;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Query
+};
+
+/// The optional filters for listing to-do items.
+///
+/// # Fields
+/// * `tag` - Only return items carrying this tag.
+#[derive(Deserialize)]
+pub struct ToDoItemFilter {
+    pub tag: Option<String>
+}
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetToDoItemsForUser, GetToDoItemsForUserByTag])]
+pub async fn get_to_do_items_for_user(filter: Query<ToDoItemFilter>, fields: Query<FieldsQuery>) {
+    let selection = FieldSelection::parse(&fields, &TODO_FIELDS)?;
+    let items = get_tagged_to_do_items_for_user::<X>(jwt.user_id, jwt.org_id, filter.into_inner().tag).await?;
+    Ok(HttpResponse::Ok().json(selection.select(&items, &[])?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
+    use kernel::users::UserRole;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::to_do_items::Todo;
+    use actix_web::{test, App, web};
+    use chrono::Utc;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    fn todo(id: i32, user_id: i32) -> Todo {
+        Todo {
+            id,
+            name: format!("Mock Task {}", id),
+            due_date: None,
+            assigned_by: 100,
+            assigned_to: user_id,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }
+    }
+
+    #[impl_transaction(MockPostgres, GetToDoItemsForUser, get_to_do_items_for_user)]
+    async fn get_to_do_items_for_user(user_id: i32, org_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
+        assert_eq!(org_id, 4);
+        Ok(vec![todo(1, user_id), todo(2, user_id)])
+    }
+
+    #[impl_transaction(MockPostgres, GetToDoItemsForUserByTag, get_to_do_items_for_user_by_tag)]
+    async fn get_to_do_items_for_user_by_tag(user_id: i32, org_id: i32, tag: String) -> Result<Vec<Todo>, NanoServiceError> {
+        assert_eq!(org_id, 4);
+        assert_eq!(tag, "urgent");
+        Ok(vec![todo(2, user_id)])
+    }
+
+    async fn send_request(uri: &str) -> Vec<Todo> {
+        let app = test::init_service(App::new().route("/get", web::get().to(
+            get_to_do_items_for_user::<MockPostgres, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::new("some-agent".to_string(), 7, UserRole::Worker).in_org(4);
+        let req = test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri(uri)
+            .to_request();
+        test::call_and_read_body_json(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_get_to_do_items_for_user() {
+        let items = send_request("/get").await;
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| item.assigned_to == 7));
+
+        let items = send_request("/get?tag=urgent").await;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, 2);
+    }
+}
@@ -1,15 +1,32 @@
 use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
-use utils::config::EnvConfig;
-use actix_web::web::{ServiceConfig, scope, post};
+use utils::secrets::SecretsConfig;
+use email_core::outbox::descriptor::EmailOutbox;
+use actix_web::web::{ServiceConfig, scope, post, get};
 mod create;
-use kernel::token::session_cache::engine_mem::AuthCacheSessionEngineMem;
+pub mod get_for_user;
+mod complete;
+mod update;
+mod search;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
 
 
 pub fn basic_actions_factory(app: &mut ServiceConfig) {
     app.service(
         scope("/api/todo/v1/basic_actions") // Namespace for user-related API routes.
         .route("create", post().to(
-            create::create_to_do_item::<SqlxPostGresDescriptor, EnvConfig, AuthCacheSessionEngineMem>) // POST /api/todo/v1/basic_actions/create.
+            create::create_to_do_item::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/basic_actions/create.
+        )
+        .route("complete", post().to(
+            complete::complete_to_do_item::<EmailOutbox<SqlxPostGresDescriptor>, SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/basic_actions/complete.
+        )
+        .route("update", post().to(
+            update::update_to_do_item::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/basic_actions/update.
+        )
+        .route("search", get().to(
+            search::search_to_do_items::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/basic_actions/search?q={text}&sort={field}&order={asc|desc}.
+        )
+        .route("get", get().to(
+            get_for_user::get_to_do_items_for_user::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/basic_actions/get?tag={tag}.
         )
     );
 }
@@ -0,0 +1,100 @@
+use dal::to_do_items::tx_definitions::SearchToDoItems;
+use to_do_core::api::basic_actions::search::search_to_do_items as search_to_do_items_core;
+use kernel::to_do_items::ToDoSearch;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Query
+};
+
+/// Searches the items assigned to or by the user, for example
+/// `?q=report&finished=false&due_before=2025-03-01T00:00:00&sort=due_date&order=asc`.
+#[api_endpoint(token=NoRoleCheck, db_traits=[SearchToDoItems])]
+pub async fn search_to_do_items(search: Query<ToDoSearch>) {
+    let items = search_to_do_items_core::<X>(jwt.user_id, jwt.org_id, search.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus, ToDoSortField, SortOrder};
+    use kernel::users::UserRole;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use actix_web::{test, App, web};
+    use chrono::Utc;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, SearchToDoItems, search_to_do_items)]
+    async fn search_to_do_items(user_id: i32, _org_id: i32, search: ToDoSearch, limit: i64) -> Result<Vec<Todo>, NanoServiceError> {
+        assert_eq!(search.q.as_deref(), Some("report"));
+        assert_eq!(search.finished, Some(false));
+        assert_eq!(search.assigned_by, Some(4));
+        assert!(search.due_before.is_some());
+        assert_eq!(search.sort, ToDoSortField::DueDate);
+        assert_eq!(search.order, SortOrder::Asc);
+        assert_eq!(limit, 20);
+        Ok(vec![Todo {
+            id: 1,
+            name: "Write report".to_string(),
+            due_date: None,
+            assigned_by: 4,
+            assigned_to: user_id,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }])
+    }
+
+    async fn send(uri: &str) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(App::new().route("/search", web::get().to(
+            search_to_do_items::<MockPostgres, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::new("some-agent".to_string(), 7, UserRole::Worker);
+        let req = test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri(uri)
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_search_to_do_items() {
+        let resp = send(
+            "/search?q=report&finished=false&assigned_by=4&due_before=2025-03-01T00:00:00&sort=due_date&order=asc&limit=20"
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let items: Vec<Todo> = test::read_body_json(resp).await;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].assigned_to, 7);
+    }
+
+    #[tokio::test]
+    async fn test_search_unknown_sort() {
+        let resp = send("/search?q=report&sort=colour").await;
+        assert_eq!(resp.status(), 400);
+    }
+}
@@ -0,0 +1,216 @@
+use dal::to_do_items::tx_definitions::UpdateToDoItem;
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use dal::sync::tx_definitions::GetSyncedToDoItem;
+use dal::moderation::tx_definitions::RecordModerationDecision;
+use to_do_core::api::basic_actions::update::update_to_do_item as update_to_do_item_core;
+use kernel::to_do_items::ToDoItemPatch;
+use kernel::moderation::engine_configured::ConfiguredModerator;
+use kernel::sync::{parse_if_match, version_tag};
+use serde::Deserialize;
+use utils::api_endpoint;
+use actix_web::{
+    HttpRequest,
+    HttpResponse,
+    http::header::{ETAG, IF_MATCH},
+    web::Json
+};
+
+/// Schema for editing a to-do item
+///
+/// # Fields
+/// * `todo_id` - The ID of the to-do item to edit.
+/// * `patch` - The fields to change, given alongside `todo_id`. A field set to `null` is cleared
+///   and a field that is left out is not changed.
+#[derive(Deserialize)]
+pub struct UpdateToDoItemSchema {
+    pub todo_id: i32,
+    #[serde(flatten)]
+    pub patch: ToDoItemPatch,
+}
+
+/// Only the user who assigned the item or an admin can edit it.
+///
+/// An `If-Match` header holding the item's `ETag` or `updated_at` makes the edit conditional, if
+/// the item has changed since, a 409 is returned with the current item and the clashing fields.
+/// Blocked and unconfirmed users are refused even if they still hold a token.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetSyncedToDoItem, UpdateToDoItem, RecordModerationDecision, RecordToDoEvent])]
+pub async fn update_to_do_item(req: HttpRequest, body: Json<UpdateToDoItemSchema>) {
+    let if_match = parse_if_match(
+        req.headers().get(IF_MATCH).map(|value| value.to_str().unwrap_or_default())
+    )?;
+    let body = body.into_inner();
+    let item = update_to_do_item_core::<X, Y, ConfiguredModerator>(body.todo_id, jwt.user_id, &jwt.role, body.patch, if_match).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, version_tag(&item.updated_at)))
+        .json(item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::todo_events::{NewTodoEvent, TodoEvent};
+    use test_support::factories;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use kernel::sync::SyncedTodo;
+    use kernel::moderation::{NewModerationDecision, ModerationDecision};
+    use kernel::users::{User, UserRole};
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::chrono::NaiveDateTime;
+    use dal_tx_impl::impl_transaction;
+    use dal::users::tx_definitions::GetUser;
+    use utils::errors::NanoServiceError;
+    use utils::send_test_request;
+    use actix_web::{test, App, web};
+    use chrono::{DateTime, Utc};
+
+    struct MockPostgres;
+
+    fn version() -> NaiveDateTime {
+        DateTime::from_timestamp(1_735_722_000, 0).unwrap().naive_utc()
+    }
+
+    fn todo(todo_id: i32) -> Todo {
+        Todo {
+            id: todo_id,
+            name: "Mock Task".to_string(),
+            due_date: Some(Utc::now().naive_utc()),
+            assigned_by: 1,
+            assigned_to: 2,
+            description: Some("Mock description".to_string()),
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }
+    }
+
+    #[impl_transaction(MockPostgres, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        Ok(factories::todo_event(1, event))
+    }
+
+    #[impl_transaction(MockPostgres, GetSyncedToDoItem, get_synced_to_do_item)]
+    async fn get_synced_to_do_item(todo_id: i32) -> Result<SyncedTodo, NanoServiceError> {
+        Ok(SyncedTodo { todo: todo(todo_id), updated_at: version() })
+    }
+
+    #[impl_transaction(MockPostgres, UpdateToDoItem, update_to_do_item)]
+    async fn update_to_do_item(todo_id: i32, patch: ToDoItemPatch, _if_match: Option<NaiveDateTime>) -> Result<Option<SyncedTodo>, NanoServiceError> {
+        assert_eq!(patch.name, None);
+        assert_eq!(patch.description, None);
+        assert_eq!(patch.due_date, Some(None));
+        let mut todo = todo(todo_id);
+        todo.due_date = None;
+        Ok(Some(SyncedTodo { todo, updated_at: version() + chrono::Duration::seconds(1) }))
+    }
+
+    #[impl_transaction(MockPostgres, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        Ok(User {
+            id,
+            confirmed: true,
+            username: "worker".to_string(),
+            email: "worker@example.com".to_string(),
+            password: "hash".to_string(),
+            first_name: "Worker".to_string(),
+            last_name: "User".to_string(),
+            user_role: UserRole::Worker,
+            date_created: version(),
+            last_logged_in: version(),
+            blocked: false,
+            uuid: "uuid".to_string(),
+            avatar_version: None,
+        })
+    }
+
+    #[impl_transaction(MockPostgres, RecordModerationDecision, record_moderation_decision)]
+    async fn record_moderation_decision(_decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+        panic!("the description is not changed")
+    }
+
+    async fn send_with_if_match(if_match: &str) -> actix_web::dev::ServiceResponse {
+        struct MockConfig;
+
+        impl utils::config::GetConfigVariable for MockConfig {
+            fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+                Ok("secret".to_string())
+            }
+        }
+
+        let app = test::init_service(App::new().route(
+            "/update",
+            web::post().to(update_to_do_item::<MockPostgres, MockConfig, PassAuthSessionCheckMock>)
+        )).await;
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::new(agent.clone(), 1, UserRole::Worker);
+        let req = test::TestRequest::post()
+            .uri("/update")
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, agent))
+            .insert_header((IF_MATCH, if_match))
+            .set_json(serde_json::json!({"todo_id": 4, "due_date": null}))
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_update_item() {
+        send_test_request!(
+            POST,
+            "/update",
+            serde_json::json!({"todo_id": 4, "due_date": null}),
+            NoRoleCheck,
+            UserRole::Worker,
+            1,
+            update_to_do_item,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(ETAG).unwrap(), "\"2025-01-01T09:00:01\"");
+        let body: serde_json::Value = actix_web::test::read_body_json(resp).await;
+        assert!(body["due_date"].is_null());
+        assert_eq!(body["description"], "Mock description");
+        assert_eq!(body["updated_at"], "2025-01-01T09:00:01");
+    }
+
+    #[tokio::test]
+    async fn test_update_item_if_match() {
+        let resp = send_with_if_match("\"2025-01-01T09:00:00\"").await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = send_with_if_match("\"2024-12-31T09:00:00\"").await;
+        assert_eq!(resp.status(), 409);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["details"]["current"]["updated_at"], "2025-01-01T09:00:00");
+        assert!(body["details"]["conflicts"]["due_date"]["requested"].is_null());
+        assert!(body["details"]["conflicts"]["due_date"]["current"].is_string());
+
+        let resp = send_with_if_match("yesterday").await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_update_item_as_assignee() {
+        send_test_request!(
+            POST,
+            "/update",
+            serde_json::json!({"todo_id": 4, "due_date": null}),
+            NoRoleCheck,
+            UserRole::Worker,
+            2,
+            update_to_do_item,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 403);
+    }
+}
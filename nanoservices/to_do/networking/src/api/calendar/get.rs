@@ -0,0 +1,103 @@
+use dal::to_do_items::tx_definitions::GetToDoItemsDueBetween;
+use to_do_core::api::calendar::get::get_calendar_for_user;
+use kernel::chrono::NaiveDate;
+use serde::Deserialize;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Query
+};
+
+/// The range of the calendar.
+///
+/// # Fields
+/// * `from` - The first day, as `YYYY-MM-DD`.
+/// * `to` - The last day, as `YYYY-MM-DD`, inclusive.
+#[derive(Deserialize)]
+pub struct CalendarRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate
+}
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetToDoItemsDueBetween])]
+pub async fn get_calendar(range: Query<CalendarRange>) {
+    let range = range.into_inner();
+    let days = get_calendar_for_user::<X>(jwt.user_id, range.from, range.to).await?;
+    Ok(HttpResponse::Ok().json(days))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use kernel::users::UserRole;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::chrono::NaiveDateTime;
+    use actix_web::{test, App, web};
+    use chrono::Utc;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetToDoItemsDueBetween, get_to_do_items_due_between)]
+    async fn get_to_do_items_due_between(user_id: i32, from: NaiveDateTime, _to: NaiveDateTime) -> Result<Vec<Todo>, NanoServiceError> {
+        Ok(vec![Todo {
+            id: 1,
+            name: "Mock Task".to_string(),
+            due_date: Some(from),
+            assigned_by: 100,
+            assigned_to: user_id,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }])
+    }
+
+    async fn send_request(uri: &str) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(App::new().route("/calendar", web::get().to(
+            get_calendar::<MockPostgres, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::new("some-agent".to_string(), 7, UserRole::Worker);
+        let req = test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri(uri)
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_get_calendar() {
+        let resp = send_request("/calendar?from=2025-02-01&to=2025-02-28").await;
+        assert_eq!(resp.status(), 200);
+        let days: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(days[0]["date"], "2025-02-01");
+        assert_eq!(days[0]["items"][0]["assigned_to"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_get_calendar_invalid_range() {
+        let resp = send_request("/calendar?from=2025-02-28&to=2025-02-01").await;
+        assert_eq!(resp.status(), 400);
+    }
+}
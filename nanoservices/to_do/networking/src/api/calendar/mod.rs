@@ -0,0 +1,13 @@
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, get};
+mod get;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+/// Registered as a single route rather than a scope so it does not shadow the other `/api/todo/v1` scopes.
+pub fn calendar_factory(app: &mut ServiceConfig) {
+    app.route("/api/todo/v1/calendar", get().to(
+        get::get_calendar::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/calendar?from={date}&to={date}.
+    );
+}
@@ -0,0 +1,228 @@
+use actix_web::{HttpRequest, HttpResponse, web::Bytes};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use dal::todo_comments::tx_definitions::CreateToDoComment;
+use dal::users::tx_definitions::GetUserByEmail;
+use dal::notifications::tx_definitions::QueueNotification;
+use dal::moderation::tx_definitions::RecordModerationDecision;
+use dal::webhook_deliveries::tx_definitions::{ClaimWebhookDelivery, ReleaseWebhookDelivery};
+use kernel::moderation::ModerateText;
+use kernel::tenancy::without_tenant;
+use email_core::inbound::{InboundWebhook, ParseInboundEmail};
+use to_do_core::api::comments::inbound_email::{add_comments_from_emails, InboundEmailOutcome};
+use utils::config::GetConfigVariable;
+use utils::errors::NanoServiceError;
+use utils::webhooks::{payload_digest, process_once, DeliveryOutcome};
+
+
+/// The source inbound email webhooks are recorded under in the webhook delivery ledger.
+const INBOUND_EMAIL_SOURCE: &str = "inbound_email";
+
+
+/// Adds the replies posted by the email provider's inbound webhook as comments.
+///
+/// # Notes
+/// The webhook is authenticated by the provider's signature rather than a token, so the raw body
+/// and headers are handed to `W` to verify and parse. Comments are moderated with `M`. Providers
+/// do not send a delivery ID, so a verified webhook is processed once per distinct body and a
+/// replay is acknowledged with nothing added. The webhook is not sent for one organization, so it
+/// runs `without_tenant` and only adds a comment from the assignee or assigner of the item.
+pub async fn receive_inbound_email<W, X, Y, M>(req: HttpRequest, body: Bytes)
+-> Result<HttpResponse, NanoServiceError>
+where
+    W: ParseInboundEmail,
+    X: GetUserByEmail + GetToDoItem + CreateToDoComment + QueueNotification + RecordModerationDecision
+        + ClaimWebhookDelivery + ReleaseWebhookDelivery,
+    Y: GetConfigVariable,
+    M: ModerateText
+{
+    let url = {
+        let connection = req.connection_info();
+        format!("{}://{}{}", connection.scheme(), connection.host(), req.uri())
+    };
+    let webhook = InboundWebhook {
+        url,
+        headers: req.headers().iter()
+            .filter_map(|(name, value)| {
+                value.to_str().ok().map(|value| (name.as_str().to_string(), value.to_string()))
+            })
+            .collect(),
+        body: body.to_vec(),
+    };
+    let emails = W::parse_inbound_email::<Y>(&webhook)?;
+    let delivery_id = payload_digest(&webhook.body);
+    let outcome = match process_once::<X, _, _, _>(INBOUND_EMAIL_SOURCE, &delivery_id, || {
+        without_tenant(add_comments_from_emails::<X, Y, M>(emails))
+    }).await? {
+        DeliveryOutcome::Processed(outcome) => outcome,
+        DeliveryOutcome::Replayed => InboundEmailOutcome::default()
+    };
+    Ok(HttpResponse::Ok().json(outcome))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App, web};
+    use dal_tx_impl::impl_transaction;
+    use email_core::inbound::InboundEmail;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use kernel::todo_comments::{NewToDoComment, ToDoComment};
+    use kernel::moderation::engine_mock::AllowTextMock;
+    use kernel::moderation::{NewModerationDecision, ModerationDecision};
+    use kernel::notifications::{NewNotification, PendingNotification};
+    use kernel::users::{User, UserRole};
+    use utils::errors::NanoServiceErrorStatus;
+    use chrono::Utc;
+    use std::sync::Mutex;
+
+    struct FakeConfig;
+
+    impl GetConfigVariable for FakeConfig {
+        fn get_config_variable(_variable: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    /// Takes the body as the reply text and rejects webhooks without a signature header.
+    struct MockParser;
+
+    impl ParseInboundEmail for MockParser {
+        fn parse_inbound_email<Y: GetConfigVariable>(webhook: &InboundWebhook) -> Result<Vec<InboundEmail>, NanoServiceError> {
+            if webhook.header("x-signature").is_none() {
+                return Err(NanoServiceError::new("Invalid signature".to_string(), NanoServiceErrorStatus::Unauthorized))
+            }
+            assert!(webhook.url.ends_with("/inbound-email"));
+            Ok(vec![InboundEmail {
+                message_id: Some("<reply@example.com>".to_string()),
+                from: "assignee@example.com".to_string(),
+                to: vec!["reply+todo-12@inbound.example.com".to_string()],
+                text: String::from_utf8(webhook.body.clone()).unwrap(),
+                ..Default::default()
+            }])
+        }
+    }
+
+    struct MockPostgres;
+
+    #[impl_transaction(MockPostgres, GetUserByEmail, get_user_by_email)]
+    async fn get_user_by_email(email: String) -> Result<User, NanoServiceError> {
+        Ok(User {
+            id: 3,
+            confirmed: true,
+            username: "assignee".to_string(),
+            email,
+            first_name: "first".to_string(),
+            last_name: "last".to_string(),
+            user_role: UserRole::Worker,
+            password: "password".to_string(),
+            uuid: "uuid".to_string(),
+            date_created: Utc::now().naive_utc(),
+            last_logged_in: Utc::now().naive_utc(),
+            blocked: false,
+            avatar_version: None,
+        })
+    }
+
+    #[impl_transaction(MockPostgres, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(Todo {
+            id: todo_id,
+            name: "Mock Task".to_string(),
+            due_date: None,
+            assigned_by: 1,
+            assigned_to: 3,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        })
+    }
+
+    #[impl_transaction(MockPostgres, CreateToDoComment, create_to_do_comment)]
+    async fn create_to_do_comment(comment: NewToDoComment) -> Result<Option<ToDoComment>, NanoServiceError> {
+        Ok(Some(ToDoComment {
+            id: 1,
+            todo_id: comment.todo_id,
+            author_id: comment.author_id,
+            body: comment.body,
+            message_id: comment.message_id,
+            date_created: Utc::now().naive_utc(),
+        }))
+    }
+
+    #[impl_transaction(MockPostgres, QueueNotification, queue_notification)]
+    async fn queue_notification(notification: NewNotification) -> Result<PendingNotification, NanoServiceError> {
+        Ok(PendingNotification {
+            id: 1,
+            user_id: notification.user_id,
+            notification_type: notification.notification_type,
+            todo_id: notification.todo_id,
+            todo_name: notification.todo_name,
+            date_created: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockPostgres, RecordModerationDecision, record_moderation_decision)]
+    async fn record_moderation_decision(_decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+        panic!("the comment should be allowed")
+    }
+
+    static DELIVERIES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    #[impl_transaction(MockPostgres, ClaimWebhookDelivery, claim_webhook_delivery)]
+    async fn claim_webhook_delivery(source: String, delivery_id: String) -> Result<bool, NanoServiceError> {
+        assert_eq!(source, INBOUND_EMAIL_SOURCE);
+        let mut deliveries = DELIVERIES.lock().unwrap();
+        if deliveries.contains(&delivery_id) {
+            return Ok(false)
+        }
+        deliveries.push(delivery_id);
+        Ok(true)
+    }
+
+    #[impl_transaction(MockPostgres, ReleaseWebhookDelivery, release_webhook_delivery)]
+    async fn release_webhook_delivery(_source: String, _delivery_id: String) -> Result<(), NanoServiceError> {
+        panic!("the delivery should be processed")
+    }
+
+    #[tokio::test]
+    async fn test_receive_inbound_email() {
+        let app = test::init_service(
+            App::new().route("/inbound-email", web::post().to(
+                receive_inbound_email::<MockParser, MockPostgres, FakeConfig, AllowTextMock>
+            ))
+        ).await;
+
+        let req = test::TestRequest::post()
+            .uri("/inbound-email")
+            .insert_header(("x-signature", "signed"))
+            .set_payload("Done, see attached")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body, serde_json::json!({"added": 1, "skipped": 0}));
+
+        // the same webhook again is a replay
+        let req = test::TestRequest::post()
+            .uri("/inbound-email")
+            .insert_header(("x-signature", "signed"))
+            .set_payload("Done, see attached")
+            .to_request();
+        let body: serde_json::Value = test::read_body_json(test::call_service(&app, req).await).await;
+        assert_eq!(body, serde_json::json!({"added": 0, "skipped": 0}));
+
+        let req = test::TestRequest::post()
+            .uri("/inbound-email")
+            .set_payload("Done")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
@@ -0,0 +1,20 @@
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use email_core::mailchimp_traits::mc_definitions::MailchimpDescriptor;
+use utils::secrets::SecretsConfig;
+use kernel::moderation::engine_configured::ConfiguredModerator;
+use actix_web::web::{ServiceConfig, scope, post, head, get};
+use actix_web::HttpResponse;
+mod inbound_email;
+
+
+/// Mandrill checks the webhook URL answers a `HEAD` request before saving it, so `HEAD` and `GET` answer 200.
+pub fn comments_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1") // Namespace for comment-related API routes.
+        .route("inbound-email", post().to(
+            inbound_email::receive_inbound_email::<MailchimpDescriptor, SqlxPostGresDescriptor, SecretsConfig, ConfiguredModerator>) // POST /api/todo/v1/inbound-email.
+        )
+        .route("inbound-email", head().to(HttpResponse::Ok)) // HEAD /api/todo/v1/inbound-email.
+        .route("inbound-email", get().to(HttpResponse::Ok)) // GET /api/todo/v1/inbound-email.
+    );
+}
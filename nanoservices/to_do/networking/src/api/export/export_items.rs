@@ -0,0 +1,116 @@
+//! Networking layer for admins exporting every to-do item.
+use actix_web::{HttpRequest, web::Query};
+use dal::to_do_items::tx_definitions::ListExportedToDoItems;
+use serde::Deserialize;
+use to_do_core::api::export::export_items::export_to_do_items as export_to_do_items_core;
+use utils::api_endpoint;
+use utils::export_stream::{export_response, ExportEncoding, ExportFormat};
+
+
+/// The `ListExportedToDoItems` handles that can be held by a streamed response, which outlives the handler.
+pub trait StreamExportedToDoItems: ListExportedToDoItems + 'static {}
+
+impl<T: ListExportedToDoItems + 'static> StreamExportedToDoItems for T {}
+
+
+/// The query parameters of the to-do export.
+///
+/// # Fields
+/// * `format` - JSON or CSV, defaults to JSON
+#[derive(Deserialize, Debug)]
+pub struct ToDoExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[StreamExportedToDoItems])]
+pub async fn export_to_do_items(req: HttpRequest, query: Query<ToDoExportQuery>) {
+    let encoding = ExportEncoding::negotiate(req.headers());
+    let body = export_to_do_items_core::<X>(query.format, encoding);
+    Ok(export_response(query.format, encoding, "todos", body))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::to_do_items::{ExportedTodo, TodoPriority};
+    use kernel::token::checks::{AdminRoleCheck, WorkerRoleCheck};
+    use kernel::users::UserRole;
+    use test_support::{call_endpoint, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    /// Serves 1200 to-do items, every other one finished.
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, ListExportedToDoItems, list_exported_to_do_items)]
+    async fn list_exported_to_do_items(after_id: Option<i32>, limit: i64) -> Result<Vec<ExportedTodo>, NanoServiceError> {
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        Ok((1..=1200)
+            .filter(|id| after_id.is_none_or(|after_id| *id > after_id))
+            .take(limit as usize)
+            .map(|id| ExportedTodo {
+                id,
+                name: format!("task {}", id),
+                description: (id == 1).then(|| "quarterly, with charts".to_string()),
+                due_date: None,
+                assignee_email: "worker@example.com".to_string(),
+                priority: TodoPriority::High,
+                finished: id % 2 == 0,
+                date_assigned: date,
+                date_finished: None,
+            })
+            .collect())
+    }
+
+    async fn export(uri: &str) -> actix_web::dev::ServiceResponse {
+        call_endpoint(
+            Method::GET, "/export",
+            export_to_do_items::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, AdminRoleCheck>::new().role(UserRole::Admin).request(TestRequest::get().uri(uri))
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_export_csv() {
+        let resp = export("/export?format=csv").await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "text/csv");
+        assert!(resp.headers().get(CONTENT_DISPOSITION).unwrap().to_str().unwrap().contains("todos"));
+
+        let body = test::read_body(resp).await;
+        let csv = std::str::from_utf8(&body).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1201);
+        assert_eq!(lines[0], "id,name,description,due_date,assignee_email,priority,finished,date_assigned,date_finished");
+        assert_eq!(lines[1], "1,task 1,\"quarterly, with charts\",,worker@example.com,high,false,2025-01-01T00:00:00,");
+        assert!(lines[1200].starts_with("1200,"));
+    }
+
+    #[tokio::test]
+    async fn test_export_json() {
+        let resp = export("/export").await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "application/json");
+        let todos: Vec<ExportedTodo> = test::read_body_json(resp).await;
+        assert_eq!(todos.len(), 1200);
+        assert_eq!(todos[1199].id, 1200);
+    }
+
+    #[tokio::test]
+    async fn test_workers_cannot_export() {
+        let resp = call_endpoint(
+            Method::GET, "/export",
+            export_to_do_items::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, WorkerRoleCheck>::new().request(TestRequest::get().uri("/export"))
+        ).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
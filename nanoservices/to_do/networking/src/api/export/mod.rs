@@ -0,0 +1,21 @@
+//! Defines the endpoint for exporting to-do items.
+//!
+//! # Overview
+//! Admins download every to-do item with `GET /api/todo/v1/export?format=csv`, in the shape the
+//! import route reads.
+pub mod export_items;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, get};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn export_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1") // Namespace for to-do export routes.
+        .route("export", get().to(
+            export_items::export_to_do_items::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/export?format=csv.
+        )
+    );
+}
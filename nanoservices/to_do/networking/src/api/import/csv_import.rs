@@ -0,0 +1,181 @@
+use dal::to_do_items::tx_definitions::{ImportToDoItems, CountOpenToDoItemsForUser, CountToDoItems};
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use dal::plans::tx_definitions::GetOrgPlan;
+use dal::users::tx_definitions::GetUserByEmail;
+use dal::moderation::tx_definitions::RecordModerationDecision;
+use kernel::moderation::engine_configured::ConfiguredModerator;
+use to_do_core::api::import::csv_import::import_to_do_items as import_to_do_items_core;
+use utils::api_endpoint;
+use utils::export_stream::ExportFormat;
+use actix_web::{HttpResponse, web::Query};
+use serde::Deserialize;
+
+/// The query parameters of the to-do import.
+///
+/// # Fields
+/// * `format` - CSV or JSON, defaults to CSV
+/// * `dry_run` - Validates the file without creating anything, defaults to false
+#[derive(Deserialize, Debug)]
+pub struct ImportQuery {
+    pub format: Option<ExportFormat>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Takes the raw file as the request body and responds with the per-row report.
+#[api_endpoint(token=AdminRoleCheck, db_traits=[ImportToDoItems, GetUserByEmail, CountOpenToDoItemsForUser, RecordModerationDecision, GetOrgPlan, CountToDoItems, RecordToDoEvent], env_variable_trait=true)]
+pub async fn import_to_do_items(body: String, query: Query<ImportQuery>) {
+    let format = query.format.unwrap_or(ExportFormat::Csv);
+    let report = import_to_do_items_core::<X, Y, ConfiguredModerator>(&body, format, query.dry_run, jwt.user_id, jwt.org_id).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::todo_events::{NewTodoEvent, TodoEvent};
+    use test_support::factories;
+    use kernel::users::{User, UserRole};
+    use kernel::to_do_items::{NewTodo, Todo, TodoStatus};
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::{NanoServiceError, NanoServiceErrorStatus};
+    use utils::config::GetConfigVariable;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use to_do_core::api::import::csv_import::ImportReport;
+    use kernel::moderation::{NewModerationDecision, ModerationDecision};
+    use kernel::plans::{OrgPlan, Plan};
+    use actix_web::{test, App, web};
+    use chrono::Utc;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        Ok(factories::todo_event(1, event))
+    }
+
+    #[impl_transaction(MockPostgres, GetOrgPlan, get_org_plan)]
+    async fn get_org_plan() -> Result<OrgPlan, NanoServiceError> {
+        Ok(OrgPlan {
+            plan: Plan::Enterprise,
+            max_users: None,
+            max_todos: None,
+            mfa: None,
+            api_access: None,
+            date_updated: Utc::now().naive_utc(),
+        })
+    }
+
+    #[impl_transaction(MockPostgres, CountToDoItems, count_to_do_items)]
+    async fn count_to_do_items() -> Result<i64, NanoServiceError> {
+        Ok(0)
+    }
+
+    #[impl_transaction(MockPostgres, RecordModerationDecision, record_moderation_decision)]
+    async fn record_moderation_decision(_decision: NewModerationDecision) -> Result<ModerationDecision, NanoServiceError> {
+        panic!("the descriptions should be allowed")
+    }
+
+    #[impl_transaction(MockPostgres, CountOpenToDoItemsForUser, count_open_to_do_items_for_user)]
+    async fn count_open_to_do_items_for_user(_user_id: i32) -> Result<i64, NanoServiceError> {
+        Ok(0)
+    }
+
+    #[impl_transaction(MockPostgres, GetUserByEmail, get_user_by_email)]
+    async fn get_user_by_email(email: String) -> Result<User, NanoServiceError> {
+        if email != "worker@example.com" {
+            return Err(NanoServiceError::new("Failed to retrieve user".to_string(), NanoServiceErrorStatus::NotFound))
+        }
+        let now = Utc::now().naive_utc();
+        Ok(User {
+            id: 3,
+            confirmed: true,
+            username: "worker".to_string(),
+            email,
+            first_name: "first".to_string(),
+            last_name: "last".to_string(),
+            user_role: UserRole::Worker,
+            password: "password".to_string(),
+            uuid: "uuid".to_string(),
+            date_created: now,
+            last_logged_in: now,
+            blocked: false,
+            avatar_version: None,
+        })
+    }
+
+    #[impl_transaction(MockPostgres, ImportToDoItems, import_to_do_items)]
+    async fn import_to_do_items(todos: Vec<NewTodo>, _org_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
+        Ok(todos.into_iter().zip(7..).map(|(todo, id)| {
+            assert_eq!(todo.assigned_by, 1);
+            Todo {
+                id,
+                name: todo.name,
+                due_date: todo.due_date,
+                assigned_by: todo.assigned_by,
+                assigned_to: todo.assigned_to,
+                description: todo.description,
+                date_assigned: Utc::now().naive_utc(),
+                date_finished: None,
+                finished: false,
+                requires_review: false,
+                pending_review: false,
+                review_comment: None,
+                priority: todo.priority,
+                status: TodoStatus::Backlog,
+            }
+        }).collect())
+    }
+
+    async fn import(uri: &str, content_type: &str, payload: &'static str) -> ImportReport {
+        let app = test::init_service(App::new().route("/import", web::post().to(
+            import_to_do_items::<MockPostgres, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new("some-agent".to_string(), 1, UserRole::Admin);
+        let req = test::TestRequest::post()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .insert_header((actix_web::http::header::CONTENT_TYPE, content_type))
+            .uri(uri)
+            .set_payload(payload)
+            .to_request();
+        test::call_and_read_body_json(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_import_to_do_items() {
+        let report = import(
+            "/import", "text/csv",
+            "name,description,due_date,assignee_email\ntask,,2025-03-01,worker@example.com\nother,,,worker@example.com\n"
+        ).await;
+        assert_eq!((report.created, report.failed), (2, 0));
+
+        let report = import(
+            "/import", "text/csv",
+            "name,description,due_date,assignee_email\ntask,,2025-03-01,worker@example.com\nother,,,nobody@example.com\n"
+        ).await;
+        assert_eq!((report.created, report.failed), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_json_import() {
+        let report = import(
+            "/import?format=json&dry_run=true", "application/json",
+            r#"[{"name": "task", "assignee_email": "worker@example.com", "priority": "high"}]"#
+        ).await;
+        assert!(report.dry_run);
+        assert_eq!((report.created, report.failed, report.rows.len()), (0, 0, 1));
+    }
+}
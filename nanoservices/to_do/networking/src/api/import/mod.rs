@@ -0,0 +1,15 @@
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post};
+mod csv_import;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn import_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1") // Namespace for to-do import routes.
+        .route("import", post().to(
+            csv_import::import_to_do_items::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/import.
+        )
+    );
+}
@@ -0,0 +1,73 @@
+//! Networking layer for reading the history of a to-do item.
+use actix_web::{HttpResponse, web::Path};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use dal::todo_events::tx_definitions::ListToDoEvents;
+use to_do_core::api::history::get::get_to_do_item_history as get_to_do_item_history_core;
+use utils::api_endpoint;
+
+
+/// Returns who changed the item, what they changed and when, the oldest change first.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetToDoItem, ListToDoEvents])]
+pub async fn get_to_do_item_history(id: Path<i32>) {
+    let history = get_to_do_item_history_core::<X>(id.into_inner(), jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok().json(history))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::to_do_items::Todo;
+    use kernel::todo_events::{NewTodoEvent, TodoEvent};
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(factories::todo(todo_id))
+    }
+
+    /// The item was created by user 1 and completed by user 2.
+    #[impl_transaction(MockPostgres, ListToDoEvents, list_to_do_events)]
+    async fn list_to_do_events(todo_id: i32) -> Result<Vec<TodoEvent>, NanoServiceError> {
+        let todo = factories::todo(todo_id);
+        Ok(vec![
+            factories::todo_event(1, NewTodoEvent::created(&todo, 1)),
+            factories::todo_event(2, NewTodoEvent::completed(&todo, 2)),
+        ])
+    }
+
+    fn request(uri: &str, user_id: i32) -> TestRequest {
+        TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(user_id).request(TestRequest::get().uri(uri))
+    }
+
+    #[tokio::test]
+    async fn test_get_to_do_item_history() {
+        let resp = call_endpoint(
+            Method::GET, "/items/{id}/history",
+            get_to_do_item_history::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request("/items/4/history", 2)
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[0]["kind"], "created");
+        assert_eq!(body[0]["actor_id"], 1);
+        assert_eq!(body[0]["details"], serde_json::json!({"name": "Task 4", "assigned_to": 2}));
+        assert_eq!(body[1]["kind"], "completed");
+
+        let not_theirs = call_endpoint(
+            Method::GET, "/items/{id}/history",
+            get_to_do_item_history::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request("/items/4/history", 7)
+        ).await;
+        assert_eq!(not_theirs.status(), 403);
+    }
+}
@@ -0,0 +1,24 @@
+//! Defines the endpoints for a single to-do item under `/api/todo/v1/items/{id}`.
+pub mod history;
+pub mod status;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, get, post};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn items_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1/items") // Namespace for single item routes.
+        .route("transitions", get().to(
+            status::get_status_transitions::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/items/transitions.
+        )
+        .route("{id}/history", get().to(
+            history::get_to_do_item_history::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/items/{id}/history.
+        )
+        .route("{id}/status", post().to(
+            status::transition_to_do_item::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/items/{id}/status.
+        )
+    );
+}
@@ -0,0 +1,99 @@
+//! Networking layer for moving a to-do item through its status workflow.
+use actix_web::{HttpResponse, web::{Json, Path}};
+use dal::to_do_items::tx_definitions::{GetToDoItem, TransitionToDoItem};
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use kernel::analytics::engine_configured::ConfiguredAnalyticsSink;
+use kernel::events::engine_configured::ConfiguredEventBus;
+use kernel::todo_workflow::StatusChange;
+use to_do_core::api::status::transition::{
+    get_status_transitions as get_status_transitions_core,
+    transition_to_do_item as transition_to_do_item_core
+};
+use utils::api_endpoint;
+
+
+/// Moves the item to the status in the body. Only its assigner, its assignee and admins can move it,
+/// and a move the workflow does not allow gets a 409 listing the statuses it can be moved to.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetToDoItem, TransitionToDoItem, RecordToDoEvent], env_variable_trait=true)]
+pub async fn transition_to_do_item(id: Path<i32>, body: Json<StatusChange>) {
+    let item = transition_to_do_item_core::<X, Y, ConfiguredAnalyticsSink, ConfiguredEventBus>(
+        id.into_inner(), body.status, jwt.user_id, &jwt.role
+    ).await?;
+    Ok(HttpResponse::Ok().json(item))
+}
+
+
+/// Returns the statuses each status can be moved to, so clients can offer only the allowed moves.
+#[api_endpoint(token=NoRoleCheck)]
+pub async fn get_status_transitions() {
+    Ok(HttpResponse::Ok().json(get_status_transitions_core::<Y>()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::to_do_items::{Todo, TodoStatus};
+    use kernel::todo_events::{NewTodoEvent, TodoEvent};
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(factories::todo(todo_id))
+    }
+
+    #[impl_transaction(MockPostgres, TransitionToDoItem, transition_to_do_item)]
+    async fn transition_to_do_item(todo_id: i32, from: TodoStatus, to: TodoStatus) -> Result<Option<Todo>, NanoServiceError> {
+        assert_eq!(from, TodoStatus::Backlog);
+        let mut todo = factories::todo(todo_id);
+        todo.status = to;
+        Ok(Some(todo))
+    }
+
+    #[impl_transaction(MockPostgres, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        Ok(factories::todo_event(1, event))
+    }
+
+    async fn move_to(user_id: i32, status: &str) -> actix_web::dev::ServiceResponse {
+        call_endpoint(
+            Method::POST, "/items/{id}/status",
+            transition_to_do_item::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(user_id).request(
+                TestRequest::post().uri("/items/4/status").set_json(serde_json::json!({"status": status}))
+            )
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_transition_to_do_item() {
+        let resp = move_to(2, "in_progress").await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "in_progress");
+
+        assert_eq!(move_to(7, "in_progress").await.status(), 403);
+        assert_eq!(move_to(2, "cancelled").await.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_get_status_transitions() {
+        let resp = call_endpoint(
+            Method::GET, "/items/transitions",
+            get_status_transitions::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(2).request(TestRequest::get().uri("/items/transitions"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[0], serde_json::json!({"status": "backlog", "next": ["in_progress", "blocked", "done"]}));
+    }
+}
@@ -1,7 +1,33 @@
 pub mod basic_actions;
+pub mod tags;
+pub mod import;
+pub mod export;
+pub mod attachments;
+pub mod review;
+pub mod sla;
+pub mod calendar;
+pub mod comments;
+pub mod sync;
+pub mod moderation;
+pub mod items;
+pub mod projects;
+pub mod v2;
 use actix_web::web::ServiceConfig;
 
 
 pub fn views_factory(app: &mut ServiceConfig) {
     basic_actions::basic_actions_factory(app);
+    tags::tags_factory(app);
+    import::import_factory(app);
+    export::export_factory(app);
+    attachments::attachments_factory(app);
+    review::review_factory(app);
+    sla::sla_factory(app);
+    calendar::calendar_factory(app);
+    comments::comments_factory(app);
+    sync::sync_factory(app);
+    moderation::moderation_factory(app);
+    items::items_factory(app);
+    projects::projects_factory(app);
+    v2::v2_factory(app);
 }
@@ -0,0 +1,147 @@
+use dal::moderation::tx_definitions::{GetModerationDecisions, ReviewModerationDecision};
+use to_do_core::api::moderation::decisions::{
+    get_moderation_decisions as get_moderation_decisions_core,
+    review_moderation_decision as review_moderation_decision_core
+};
+use serde::Deserialize;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::{Json, Query}
+};
+
+/// Query for listing moderation decisions
+///
+/// # Fields
+/// * `pending` - Only list the decisions not yet reviewed, defaults to true.
+/// * `limit` - The most decisions to return.
+#[derive(Deserialize)]
+pub struct ModerationDecisionsQuery {
+    pub pending: Option<bool>,
+    pub limit: Option<i64>
+}
+
+/// Schema for reviewing a moderation decision
+///
+/// # Fields
+/// * `id` - The ID of the decision.
+/// * `upheld` - Whether the admin agrees with the moderator.
+#[derive(Deserialize)]
+pub struct ReviewModerationDecisionSchema {
+    pub id: i32,
+    pub upheld: bool
+}
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetModerationDecisions])]
+pub async fn get_moderation_decisions(query: Query<ModerationDecisionsQuery>) {
+    let decisions = get_moderation_decisions_core::<X>(query.pending.unwrap_or(true), query.limit).await?;
+    Ok(HttpResponse::Ok().json(decisions))
+}
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[ReviewModerationDecision])]
+pub async fn review_moderation_decision(body: Json<ReviewModerationDecisionSchema>) {
+    let decision = review_moderation_decision_core::<X>(body.id, jwt.user_id, body.upheld).await?;
+    Ok(HttpResponse::Ok().json(decision))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::moderation::{ModerationDecision, ModeratedContent, ModerationAction};
+    use kernel::users::UserRole;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+    use utils::send_test_request;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::AdminRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use actix_web::{test, App, web};
+    use chrono::Utc;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    fn decision(id: i32) -> ModerationDecision {
+        ModerationDecision {
+            id,
+            content: ModeratedContent::Comment,
+            entity_id: None,
+            author_id: 3,
+            action: ModerationAction::Rejected,
+            reasons: vec!["blocked: spam".to_string()],
+            text: "Buy spam".to_string(),
+            created_at: Utc::now().naive_utc(),
+            reviewed_by: None,
+            reviewed_at: None,
+            upheld: None,
+        }
+    }
+
+    #[impl_transaction(MockPostgres, GetModerationDecisions, get_moderation_decisions)]
+    async fn get_moderation_decisions(pending_only: bool, limit: i64) -> Result<Vec<ModerationDecision>, NanoServiceError> {
+        assert!(pending_only);
+        assert_eq!(limit, 100);
+        Ok(vec![decision(1)])
+    }
+
+    #[impl_transaction(MockPostgres, ReviewModerationDecision, review_moderation_decision)]
+    async fn review_moderation_decision(id: i32, reviewer_id: i32, upheld: bool) -> Result<ModerationDecision, NanoServiceError> {
+        let mut decision = decision(id);
+        decision.reviewed_by = Some(reviewer_id);
+        decision.reviewed_at = Some(Utc::now().naive_utc());
+        decision.upheld = Some(upheld);
+        Ok(decision)
+    }
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    async fn list_as(role: UserRole) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(App::new().route("/decisions", web::get().to(
+            get_moderation_decisions::<MockPostgres, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, AdminRoleCheck> = HeaderToken::new("some-agent".to_string(), 1, role);
+        let req = test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri("/decisions")
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_get_moderation_decisions() {
+        let resp = list_as(UserRole::Admin).await;
+        assert_eq!(resp.status(), 200);
+        let decisions: Vec<ModerationDecision> = test::read_body_json(resp).await;
+        assert_eq!(decisions[0].reasons, vec!["blocked: spam".to_string()]);
+
+        assert_eq!(list_as(UserRole::Worker).await.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn test_review_moderation_decision() {
+        send_test_request!(
+            POST,
+            "/review",
+            serde_json::json!({"id": 4, "upheld": false}),
+            AdminRoleCheck,
+            UserRole::Admin,
+            1,
+            review_moderation_decision,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+        let decision: ModerationDecision = test::read_body_json(resp).await;
+        assert_eq!((decision.reviewed_by, decision.upheld), (Some(1), Some(false)));
+    }
+}
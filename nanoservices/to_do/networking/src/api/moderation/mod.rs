@@ -0,0 +1,18 @@
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get};
+mod decisions;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn moderation_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1/moderation") // Namespace for moderation-related API routes.
+        .route("decisions", get().to(
+            decisions::get_moderation_decisions::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/moderation/decisions?pending={bool}&limit={limit}.
+        )
+        .route("review", post().to(
+            decisions::review_moderation_decision::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/moderation/review.
+        )
+    );
+}
@@ -0,0 +1,51 @@
+//! Networking layer for creating a project.
+use actix_web::{HttpResponse, web::Json};
+use dal::projects::tx_definitions::CreateProject;
+use kernel::projects::NewProject;
+use to_do_core::api::projects::create::create_project as create_project_core;
+use utils::api_endpoint;
+
+
+/// Creates a project owned by the caller, who becomes its first member.
+#[api_endpoint(token=NoRoleCheck, db_traits=[CreateProject])]
+pub async fn create_project(body: Json<NewProject>) {
+    let project = create_project_core::<X>(jwt.user_id, body.into_inner()).await?;
+    Ok(HttpResponse::Created().json(project))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::projects::Project;
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, CreateProject, create_project)]
+    async fn create_project(owner_id: i32, project: NewProject) -> Result<Project, NanoServiceError> {
+        Ok(Project { name: project.name, owner_id, ..factories::project(1) })
+    }
+
+    #[tokio::test]
+    async fn test_create_project() {
+        let resp = call_endpoint(
+            Method::POST, "/projects",
+            create_project::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(3).request(
+                TestRequest::post().uri("/projects").set_json(serde_json::json!({"name": "Launch", "description": null}))
+            )
+        ).await;
+        assert_eq!(resp.status(), 201);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["name"], "Launch");
+        assert_eq!(body["owner_id"], 3);
+    }
+}
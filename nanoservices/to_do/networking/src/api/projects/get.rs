@@ -0,0 +1,90 @@
+//! Networking layer for reading projects.
+use actix_web::{HttpResponse, web::Path};
+use dal::projects::tx_definitions::{GetProject, GetProjectsForUser, IsProjectMember};
+use to_do_core::api::projects::get::{
+    get_project as get_project_core,
+    get_projects_for_user
+};
+use utils::api_endpoint;
+
+
+/// Returns the projects the caller is a member of, ordered by name.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProjectsForUser])]
+pub async fn get_projects() {
+    let projects = get_projects_for_user::<X>(jwt.user_id).await?;
+    Ok(HttpResponse::Ok().json(projects))
+}
+
+
+/// Returns a project the caller is a member of.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProject, IsProjectMember])]
+pub async fn get_project(id: Path<i32>) {
+    let project = get_project_core::<X>(id.into_inner(), jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok().json(project))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::projects::Project;
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    /// User 2 is a member of project 1, which is owned by user 1.
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetProjectsForUser, get_projects_for_user)]
+    async fn get_projects_for_user(user_id: i32) -> Result<Vec<Project>, NanoServiceError> {
+        Ok(if user_id == 2 { vec![factories::project(1)] } else { vec![] })
+    }
+
+    #[impl_transaction(MockPostgres, GetProject, get_project)]
+    async fn get_project(id: i32) -> Result<Project, NanoServiceError> {
+        Ok(factories::project(id))
+    }
+
+    #[impl_transaction(MockPostgres, IsProjectMember, is_project_member)]
+    async fn is_project_member(_project_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(user_id == 2)
+    }
+
+    fn request(uri: &str, user_id: i32) -> TestRequest {
+        TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(user_id).request(TestRequest::get().uri(uri))
+    }
+
+    #[tokio::test]
+    async fn test_get_projects() {
+        let resp = call_endpoint(
+            Method::GET, "/projects",
+            get_projects::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request("/projects", 2)
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[0]["name"], "Project 1");
+    }
+
+    #[tokio::test]
+    async fn test_get_project() {
+        let resp = call_endpoint(
+            Method::GET, "/projects/{id}",
+            get_project::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request("/projects/1", 2)
+        ).await;
+        assert_eq!(resp.status(), 200);
+
+        let not_a_member = call_endpoint(
+            Method::GET, "/projects/{id}",
+            get_project::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            request("/projects/1", 7)
+        ).await;
+        assert_eq!(not_a_member.status(), 403);
+    }
+}
@@ -0,0 +1,140 @@
+//! Networking layer for the to-do items in a project.
+use actix_web::{HttpResponse, web::{Json, Path, Query}};
+use dal::projects::tx_definitions::{
+    AddToDoItemToProject,
+    GetProject,
+    GetToDoItemsForProject,
+    IsProjectMember,
+    RemoveToDoItemFromProject
+};
+use dal::to_do_items::tx_definitions::GetToDoItem;
+use kernel::projects::ProjectItemFilter;
+use serde::Deserialize;
+use to_do_core::api::projects::items::{
+    add_to_do_item_to_project as add_to_do_item_to_project_core,
+    get_to_do_items_for_project as get_to_do_items_for_project_core,
+    remove_to_do_item_from_project as remove_to_do_item_from_project_core
+};
+use utils::api_endpoint;
+
+
+/// Schema for adding a to-do item to a project
+///
+/// # Fields
+/// * `todo_id` - The ID of the to-do item to add.
+#[derive(Deserialize)]
+pub struct AddProjectItemSchema {
+    pub todo_id: i32
+}
+
+
+/// Returns the to-do items in a project the caller is a member of, soonest due first. The items
+/// can be narrowed with `?status=`, `?assigned_to=` and `?finished=`.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProject, IsProjectMember, GetToDoItemsForProject])]
+pub async fn get_to_do_items_for_project(id: Path<i32>, filter: Query<ProjectItemFilter>) {
+    let items = get_to_do_items_for_project_core::<X>(id.into_inner(), jwt.user_id, &jwt.role, filter.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(items))
+}
+
+
+/// Adds a to-do item the caller assigned or is assigned to the project, moving it out of any
+/// other project.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProject, IsProjectMember, GetToDoItem, AddToDoItemToProject])]
+pub async fn add_to_do_item_to_project(id: Path<i32>, body: Json<AddProjectItemSchema>) {
+    let link = add_to_do_item_to_project_core::<X>(id.into_inner(), body.todo_id, jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Created().json(link))
+}
+
+
+/// Takes a to-do item out of the project, the item itself is kept.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProject, IsProjectMember, GetToDoItem, RemoveToDoItemFromProject])]
+pub async fn remove_to_do_item_from_project(path: Path<(i32, i32)>) {
+    let (id, todo_id) = path.into_inner();
+    remove_to_do_item_from_project_core::<X>(id, todo_id, jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use kernel::projects::{Project, ProjectTodo};
+    use kernel::to_do_items::{Todo, TodoStatus};
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    /// Project 1 is owned by user 1 and user 2 is also a member, items 4 and 5 are in it.
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetProject, get_project)]
+    async fn get_project(id: i32) -> Result<Project, NanoServiceError> {
+        Ok(factories::project(id))
+    }
+
+    #[impl_transaction(MockPostgres, IsProjectMember, is_project_member)]
+    async fn is_project_member(_project_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(user_id == 2)
+    }
+
+    #[impl_transaction(MockPostgres, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(factories::todo(todo_id))
+    }
+
+    #[impl_transaction(MockPostgres, GetToDoItemsForProject, get_to_do_items_for_project)]
+    async fn get_to_do_items_for_project(_project_id: i32, filter: ProjectItemFilter) -> Result<Vec<Todo>, NanoServiceError> {
+        assert_eq!(filter, ProjectItemFilter { status: Some(TodoStatus::Blocked), assigned_to: Some(2), finished: None });
+        Ok(vec![Todo { status: TodoStatus::Blocked, ..factories::todo(5) }])
+    }
+
+    #[impl_transaction(MockPostgres, AddToDoItemToProject, add_to_do_item_to_project)]
+    async fn add_to_do_item_to_project(project_id: i32, todo_id: i32) -> Result<ProjectTodo, NanoServiceError> {
+        Ok(ProjectTodo { project_id, todo_id, date_added: Utc::now().naive_utc() })
+    }
+
+    #[impl_transaction(MockPostgres, RemoveToDoItemFromProject, remove_to_do_item_from_project)]
+    async fn remove_to_do_item_from_project(_project_id: i32, todo_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(todo_id == 4 || todo_id == 5)
+    }
+
+    #[tokio::test]
+    async fn test_get_to_do_items_for_project() {
+        let resp = call_endpoint(
+            Method::GET, "/projects/{id}/items",
+            get_to_do_items_for_project::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(2).request(
+                TestRequest::get().uri("/projects/1/items?status=blocked&assigned_to=2")
+            )
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[0]["id"], 5);
+        assert_eq!(body[0]["status"], "blocked");
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_to_do_item() {
+        let resp = call_endpoint(
+            Method::POST, "/projects/{id}/items",
+            add_to_do_item_to_project::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(2).request(
+                TestRequest::post().uri("/projects/1/items").set_json(serde_json::json!({"todo_id": 4}))
+            )
+        ).await;
+        assert_eq!(resp.status(), 201);
+
+        let resp = call_endpoint(
+            Method::DELETE, "/projects/{id}/items/{todo_id}",
+            remove_to_do_item_from_project::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(2).request(TestRequest::delete().uri("/projects/1/items/9"))
+        ).await;
+        assert_eq!(resp.status(), 404);
+    }
+}
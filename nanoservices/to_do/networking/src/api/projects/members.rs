@@ -0,0 +1,137 @@
+//! Networking layer for the members of a project.
+use actix_web::{HttpResponse, web::{Json, Path}};
+use dal::projects::tx_definitions::{AddProjectMember, GetProject, GetProjectMembers, IsProjectMember, RemoveProjectMember};
+use dal::users::tx_definitions::GetUser;
+use serde::Deserialize;
+use to_do_core::api::projects::members::{
+    add_project_member as add_project_member_core,
+    get_project_members as get_project_members_core,
+    remove_project_member as remove_project_member_core
+};
+use utils::api_endpoint;
+
+
+/// Schema for adding a member to a project
+///
+/// # Fields
+/// * `user_id` - The ID of the user to add.
+#[derive(Deserialize)]
+pub struct AddProjectMemberSchema {
+    pub user_id: i32
+}
+
+
+/// Returns the members of a project the caller is a member of.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProject, IsProjectMember, GetProjectMembers])]
+pub async fn get_project_members(id: Path<i32>) {
+    let members = get_project_members_core::<X>(id.into_inner(), jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok().json(members))
+}
+
+
+/// Lets a user see the project. Only the owner of the project or an admin can add members.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProject, GetUser, AddProjectMember])]
+pub async fn add_project_member(id: Path<i32>, body: Json<AddProjectMemberSchema>) {
+    let member = add_project_member_core::<X>(id.into_inner(), body.user_id, jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Created().json(member))
+}
+
+
+/// Stops a user seeing the project. Any member can remove themselves, only the owner of the project
+/// or an admin can remove others, and the owner cannot be removed.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProject, RemoveProjectMember])]
+pub async fn remove_project_member(path: Path<(i32, i32)>) {
+    let (id, user_id) = path.into_inner();
+    remove_project_member_core::<X>(id, user_id, jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::chrono::Utc;
+    use kernel::projects::{Project, ProjectMember};
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::users::User;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    /// Project 1 is owned by user 1 and user 2 is also a member.
+    struct MockPostgres;
+
+    fn member(project_id: i32, user_id: i32) -> ProjectMember {
+        ProjectMember { project_id, user_id, date_added: Utc::now().naive_utc() }
+    }
+
+    #[impl_transaction(MockPostgres, GetProject, get_project)]
+    async fn get_project(id: i32) -> Result<Project, NanoServiceError> {
+        Ok(factories::project(id))
+    }
+
+    #[impl_transaction(MockPostgres, IsProjectMember, is_project_member)]
+    async fn is_project_member(_project_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(user_id == 2)
+    }
+
+    #[impl_transaction(MockPostgres, GetProjectMembers, get_project_members)]
+    async fn get_project_members(project_id: i32) -> Result<Vec<ProjectMember>, NanoServiceError> {
+        Ok(vec![member(project_id, 1), member(project_id, 2)])
+    }
+
+    #[impl_transaction(MockPostgres, GetUser, get_user)]
+    async fn get_user(id: i32) -> Result<User, NanoServiceError> {
+        Ok(factories::user(id))
+    }
+
+    #[impl_transaction(MockPostgres, AddProjectMember, add_project_member)]
+    async fn add_project_member(project_id: i32, user_id: i32) -> Result<ProjectMember, NanoServiceError> {
+        Ok(member(project_id, user_id))
+    }
+
+    #[impl_transaction(MockPostgres, RemoveProjectMember, remove_project_member)]
+    async fn remove_project_member(_project_id: i32, user_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(user_id == 2)
+    }
+
+    #[tokio::test]
+    async fn test_get_project_members() {
+        let resp = call_endpoint(
+            Method::GET, "/projects/{id}/members",
+            get_project_members::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(2).request(TestRequest::get().uri("/projects/1/members"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[1]["user_id"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_project_member() {
+        let resp = call_endpoint(
+            Method::POST, "/projects/{id}/members",
+            add_project_member::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(1).request(
+                TestRequest::post().uri("/projects/1/members").set_json(serde_json::json!({"user_id": 4}))
+            )
+        ).await;
+        assert_eq!(resp.status(), 201);
+
+        let leave = call_endpoint(
+            Method::DELETE, "/projects/{id}/members/{user_id}",
+            remove_project_member::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(2).request(TestRequest::delete().uri("/projects/1/members/2"))
+        ).await;
+        assert_eq!(leave.status(), 200);
+
+        let owner = call_endpoint(
+            Method::DELETE, "/projects/{id}/members/{user_id}",
+            remove_project_member::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(1).request(TestRequest::delete().uri("/projects/1/members/1"))
+        ).await;
+        assert_eq!(owner.status(), 409);
+    }
+}
@@ -0,0 +1,55 @@
+//! Defines the endpoints for projects, the boards that group to-do items.
+//!
+//! # Overview
+//! These routes live under `/api/todo/v1/projects`. A project is seen by its members and changed by
+//! its owner, admins can see and change every project.
+pub mod create;
+pub mod get;
+pub mod update;
+pub mod members;
+pub mod items;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get, put, delete};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn projects_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1/projects") // Namespace for project routes.
+        .route("", post().to(
+            create::create_project::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/projects.
+        )
+        .route("", get().to(
+            get::get_projects::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/projects.
+        )
+        .route("{id}", get().to(
+            get::get_project::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/projects/{id}.
+        )
+        .route("{id}", put().to(
+            update::update_project::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // PUT /api/todo/v1/projects/{id}.
+        )
+        .route("{id}", delete().to(
+            update::delete_project::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // DELETE /api/todo/v1/projects/{id}.
+        )
+        .route("{id}/members", get().to(
+            members::get_project_members::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/projects/{id}/members.
+        )
+        .route("{id}/members", post().to(
+            members::add_project_member::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/projects/{id}/members.
+        )
+        .route("{id}/members/{user_id}", delete().to(
+            members::remove_project_member::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // DELETE /api/todo/v1/projects/{id}/members/{user_id}.
+        )
+        .route("{id}/items", get().to(
+            items::get_to_do_items_for_project::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/projects/{id}/items.
+        )
+        .route("{id}/items", post().to(
+            items::add_to_do_item_to_project::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/projects/{id}/items.
+        )
+        .route("{id}/items/{todo_id}", delete().to(
+            items::remove_to_do_item_from_project::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // DELETE /api/todo/v1/projects/{id}/items/{todo_id}.
+        )
+    );
+}
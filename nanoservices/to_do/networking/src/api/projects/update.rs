@@ -0,0 +1,89 @@
+//! Networking layer for changing and removing projects.
+use actix_web::{HttpResponse, web::{Json, Path}};
+use dal::projects::tx_definitions::{DeleteProject, GetProject, UpdateProject};
+use kernel::projects::ProjectPatch;
+use to_do_core::api::projects::update::{
+    delete_project as delete_project_core,
+    update_project as update_project_core
+};
+use utils::api_endpoint;
+
+
+/// Changes the name or description of a project, a `description` of `null` clears it. Only the
+/// owner of the project or an admin can change it.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProject, UpdateProject])]
+pub async fn update_project(id: Path<i32>, body: Json<ProjectPatch>) {
+    let project = update_project_core::<X>(id.into_inner(), jwt.user_id, &jwt.role, body.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(project))
+}
+
+
+/// Removes a project, its to-do items are kept. Only the owner of the project or an admin can remove it.
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetProject, DeleteProject])]
+pub async fn delete_project(id: Path<i32>) {
+    let project = delete_project_core::<X>(id.into_inner(), jwt.user_id, &jwt.role).await?;
+    Ok(HttpResponse::Ok().json(project))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    use actix_web::test::{self, TestRequest};
+    use dal_tx_impl::impl_transaction;
+    use kernel::projects::Project;
+    use kernel::token::checks::NoRoleCheck;
+    use test_support::{call_endpoint, factories, FakeConfig, PassAuthSessionCheckMock, TokenBuilder};
+    use utils::errors::NanoServiceError;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetProject, get_project)]
+    async fn get_project(id: i32) -> Result<Project, NanoServiceError> {
+        Ok(factories::project(id))
+    }
+
+    #[impl_transaction(MockPostgres, UpdateProject, update_project)]
+    async fn update_project(id: i32, patch: ProjectPatch) -> Result<Project, NanoServiceError> {
+        Ok(Project { description: patch.description.flatten(), ..factories::project(id) })
+    }
+
+    #[impl_transaction(MockPostgres, DeleteProject, delete_project)]
+    async fn delete_project(_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(true)
+    }
+
+    #[tokio::test]
+    async fn test_update_project() {
+        let resp = call_endpoint(
+            Method::PUT, "/projects/{id}",
+            update_project::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(1).request(
+                TestRequest::put().uri("/projects/1").set_json(serde_json::json!({"description": "Q3 launch"}))
+            )
+        ).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["description"], "Q3 launch");
+    }
+
+    #[tokio::test]
+    async fn test_delete_project() {
+        let not_the_owner = call_endpoint(
+            Method::DELETE, "/projects/{id}",
+            delete_project::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(2).request(TestRequest::delete().uri("/projects/1"))
+        ).await;
+        assert_eq!(not_the_owner.status(), 403);
+
+        let resp = call_endpoint(
+            Method::DELETE, "/projects/{id}",
+            delete_project::<MockPostgres, FakeConfig, PassAuthSessionCheckMock>,
+            TokenBuilder::<FakeConfig, NoRoleCheck>::new().user_id(1).request(TestRequest::delete().uri("/projects/1"))
+        ).await;
+        assert_eq!(resp.status(), 200);
+    }
+}
@@ -0,0 +1,144 @@
+use dal::to_do_items::tx_definitions::{GetToDoItem, ApproveToDoItem, RejectToDoItem};
+use dal::todo_events::tx_definitions::RecordToDoEvent;
+use to_do_core::api::review::decide::{
+    approve_to_do_item as approve_to_do_item_core,
+    reject_to_do_item as reject_to_do_item_core
+};
+use serde::Deserialize;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+
+/// Schema for approving a to-do item
+///
+/// # Fields
+/// * `todo_id` - The ID of the to-do item pending review.
+#[derive(Deserialize)]
+pub struct ApproveToDoItemSchema {
+    pub todo_id: i32
+}
+
+/// Schema for rejecting a to-do item
+///
+/// # Fields
+/// * `todo_id` - The ID of the to-do item pending review.
+/// * `comment` - Why the item was rejected, shown to the assignee.
+#[derive(Deserialize)]
+pub struct RejectToDoItemSchema {
+    pub todo_id: i32,
+    pub comment: String
+}
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetToDoItem, ApproveToDoItem, RecordToDoEvent])]
+pub async fn approve_to_do_item(body: Json<ApproveToDoItemSchema>) {
+    let item = approve_to_do_item_core::<X>(body.todo_id, jwt.user_id).await?;
+    Ok(HttpResponse::Ok().json(item))
+}
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetToDoItem, RejectToDoItem, RecordToDoEvent])]
+pub async fn reject_to_do_item(body: Json<RejectToDoItemSchema>) {
+    let body = body.into_inner();
+    let item = reject_to_do_item_core::<X>(body.todo_id, jwt.user_id, body.comment).await?;
+    Ok(HttpResponse::Ok().json(item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::todo_events::{NewTodoEvent, TodoEvent};
+    use test_support::factories;
+    use kernel::to_do_items::{TodoPriority, TodoStatus};
+    use kernel::users::UserRole;
+    use kernel::to_do_items::Todo;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::AdminRoleCheck;
+    use utils::send_test_request;
+    use chrono::Utc;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    fn pending(todo_id: i32) -> Todo {
+        Todo {
+            id: todo_id,
+            name: "Mock Task".to_string(),
+            due_date: None,
+            assigned_by: 1,
+            assigned_to: 2,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: true,
+            pending_review: true,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }
+    }
+
+    #[impl_transaction(MockPostgres, RecordToDoEvent, record_to_do_event)]
+    async fn record_to_do_event(event: NewTodoEvent) -> Result<TodoEvent, NanoServiceError> {
+        Ok(factories::todo_event(1, event))
+    }
+
+    #[impl_transaction(MockPostgres, GetToDoItem, get_to_do_item)]
+    async fn get_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        Ok(pending(todo_id))
+    }
+
+    #[impl_transaction(MockPostgres, ApproveToDoItem, approve_to_do_item)]
+    async fn approve_to_do_item(todo_id: i32) -> Result<Todo, NanoServiceError> {
+        let mut todo = pending(todo_id);
+        todo.pending_review = false;
+        todo.finished = true;
+        Ok(todo)
+    }
+
+    #[impl_transaction(MockPostgres, RejectToDoItem, reject_to_do_item)]
+    async fn reject_to_do_item(todo_id: i32, comment: String) -> Result<Todo, NanoServiceError> {
+        let mut todo = pending(todo_id);
+        todo.pending_review = false;
+        todo.review_comment = Some(comment);
+        Ok(todo)
+    }
+
+    #[tokio::test]
+    async fn test_approve_item() {
+        send_test_request!(
+            POST,
+            "/approve",
+            serde_json::json!({"todo_id": 4}),
+            AdminRoleCheck,
+            UserRole::Admin,
+            1,
+            approve_to_do_item,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_reject_item_by_another_admin() {
+        send_test_request!(
+            POST,
+            "/reject",
+            serde_json::json!({"todo_id": 4, "comment": "needs charts"}),
+            AdminRoleCheck,
+            UserRole::Admin,
+            3,
+            reject_to_do_item,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 403);
+    }
+}
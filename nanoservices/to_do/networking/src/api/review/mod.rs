@@ -0,0 +1,19 @@
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post};
+mod decide;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+/// The paths match `APPROVE_ENDPOINT` and `REJECT_ENDPOINT` which are sent in the review request email.
+pub fn review_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1/review") // Namespace for review-related API routes.
+        .route("approve", post().to(
+            decide::approve_to_do_item::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/review/approve.
+        )
+        .route("reject", post().to(
+            decide::reject_to_do_item::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/review/reject.
+        )
+    );
+}
@@ -0,0 +1,22 @@
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get};
+mod policies;
+mod stats;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn sla_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1/sla") // Namespace for SLA-related API routes.
+        .route("policies", get().to(
+            policies::get_sla_policies::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/sla/policies.
+        )
+        .route("policies", post().to(
+            policies::set_sla_policy::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/sla/policies.
+        )
+        .route("stats", get().to(
+            stats::get_to_do_stats::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/sla/stats.
+        )
+    );
+}
@@ -0,0 +1,74 @@
+use dal::sla::tx_definitions::{GetSlaPolicies, UpsertSlaPolicy};
+use kernel::sla::SlaPolicy;
+use to_do_core::api::sla::policies::{get_sla_policies as get_sla_policies_core, set_sla_policy as set_sla_policy_core};
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetSlaPolicies])]
+pub async fn get_sla_policies() {
+    let policies = get_sla_policies_core::<X>().await?;
+    Ok(HttpResponse::Ok().json(policies))
+}
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[UpsertSlaPolicy])]
+pub async fn set_sla_policy(body: Json<SlaPolicy>) {
+    let policy = set_sla_policy_core::<X>(body.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::UserRole;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::AdminRoleCheck;
+    use utils::send_test_request;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, UpsertSlaPolicy, upsert_sla_policy)]
+    async fn upsert_sla_policy(policy: SlaPolicy) -> Result<SlaPolicy, NanoServiceError> {
+        Ok(policy)
+    }
+
+    #[tokio::test]
+    async fn test_set_sla_policy() {
+        send_test_request!(
+            POST,
+            "/policies",
+            serde_json::json!({"priority": "high", "target_minutes": 120, "warn_minutes": 30}),
+            AdminRoleCheck,
+            UserRole::Admin,
+            1,
+            set_sla_policy,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_set_invalid_sla_policy() {
+        send_test_request!(
+            POST,
+            "/policies",
+            serde_json::json!({"priority": "high", "target_minutes": 30, "warn_minutes": 30}),
+            AdminRoleCheck,
+            UserRole::Admin,
+            1,
+            set_sla_policy,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 400);
+    }
+}
@@ -0,0 +1,10 @@
+use dal::sla::tx_definitions::{GetToDoCounts, GetBreachedToDoItems};
+use to_do_core::api::sla::stats::get_to_do_stats as get_to_do_stats_core;
+use utils::api_endpoint;
+use actix_web::HttpResponse;
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[GetToDoCounts, GetBreachedToDoItems])]
+pub async fn get_to_do_stats() {
+    let stats = get_to_do_stats_core::<X>().await?;
+    Ok(HttpResponse::Ok().json(stats))
+}
@@ -0,0 +1,126 @@
+use dal::sync::tx_definitions::{GetUsersChangedSince, GetToDoItemsChangedSince};
+use dal::tombstones::tx_definitions::GetTombstones;
+use to_do_core::api::sync::get_changes::get_changes as get_changes_core;
+use serde::Deserialize;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Query
+};
+
+/// The changes to read.
+///
+/// # Fields
+/// * `since` - The `next_cursor` from the last sync, left out on the first sync.
+/// * `limit` - The most rows to return from each feed.
+#[derive(Deserialize)]
+pub struct ChangesQuery {
+    pub since: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetUsersChangedSince, GetToDoItemsChangedSince, GetTombstones])]
+pub async fn get_changes(query: Query<ChangesQuery>) {
+    let query = query.into_inner();
+    let changes = get_changes_core::<X>(jwt.user_id, jwt.role.clone(), jwt.org_id, query.since.as_deref(), query.limit).await?;
+    Ok(HttpResponse::Ok().json(changes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::sync::{SyncPosition, SyncedUser, SyncedTodo};
+    use kernel::tombstones::Tombstone;
+    use kernel::users::{PublicUser, UserRole};
+    use kernel::chrono::NaiveDateTime;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use actix_web::{test, App, web};
+    use chrono::Utc;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetUsersChangedSince, get_users_changed_since)]
+    async fn get_users_changed_since(user_id: Option<i32>, org_id: Option<i32>, _after: SyncPosition, _limit: i64) -> Result<Vec<SyncedUser>, NanoServiceError> {
+        assert_eq!(user_id, Some(3));
+        assert_eq!(org_id, None);
+        let now = Utc::now().naive_utc();
+        Ok(vec![SyncedUser {
+            user: PublicUser {
+                id: 3,
+                confirmed: true,
+                username: "worker".to_string(),
+                email: "worker@example.com".to_string(),
+                first_name: "Worker".to_string(),
+                last_name: "User".to_string(),
+                user_role: UserRole::Worker,
+                date_created: now,
+                last_logged_in: now,
+                blocked: false,
+                avatar_url: None,
+            },
+            updated_at: now,
+        }])
+    }
+
+    #[impl_transaction(MockPostgres, GetToDoItemsChangedSince, get_to_do_items_changed_since)]
+    async fn get_to_do_items_changed_since(user_id: i32, _after: SyncPosition, limit: i64) -> Result<Vec<SyncedTodo>, NanoServiceError> {
+        assert_eq!(user_id, 3);
+        assert_eq!(limit, 51);
+        Ok(vec![])
+    }
+
+    #[impl_transaction(MockPostgres, GetTombstones, get_tombstones)]
+    async fn get_tombstones(_org_id: Option<i32>, _since: NaiveDateTime, _after_id: i32, _limit: i64) -> Result<Vec<Tombstone>, NanoServiceError> {
+        Ok(vec![])
+    }
+
+    async fn send(uri: &str) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(App::new().route(
+            "/api/sync/changes",
+            web::get().to(get_changes::<MockPostgres, MockConfig, PassAuthSessionCheckMock>)
+        )).await;
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::new(agent.clone(), 3, UserRole::Worker);
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, agent))
+            .to_request();
+        test::call_service(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_get_changes() {
+        let resp = send("/api/sync/changes?limit=50").await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["users"][0]["username"], "worker");
+        assert!(body["users"][0]["updated_at"].is_string());
+        assert!(body["users"][0].get("uuid").is_none());
+        assert_eq!(body["has_more"], false);
+
+        let resp = send(&format!("/api/sync/changes?since={}&limit=50", body["next_cursor"].as_str().unwrap())).await;
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_get_changes_invalid_cursor() {
+        let resp = send("/api/sync/changes?since=garbage").await;
+        assert_eq!(resp.status(), 400);
+    }
+}
@@ -0,0 +1,91 @@
+use dal::tombstones::tx_definitions::GetTombstones;
+use to_do_core::api::sync::get_deletions::get_deletions as get_deletions_core;
+use kernel::chrono::NaiveDateTime;
+use serde::Deserialize;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Query
+};
+
+/// The page of deletions to read.
+///
+/// # Fields
+/// * `since` - Only deletions at or after this time, as `YYYY-MM-DDTHH:MM:SS`.
+/// * `after_id` - The `next_after_id` of the previous page.
+/// * `limit` - The most deletions to return.
+#[derive(Deserialize)]
+pub struct DeletionsQuery {
+    pub since: Option<NaiveDateTime>,
+    pub after_id: Option<i32>,
+    pub limit: Option<i64>,
+}
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetTombstones])]
+pub async fn get_deletions(query: Query<DeletionsQuery>) {
+    let query = query.into_inner();
+    let page = get_deletions_core::<X>(jwt.role.clone(), jwt.org_id, query.since, query.after_id, query.limit).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::tombstones::{Tombstone, TombstoneEntity};
+    use kernel::users::UserRole;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use utils::config::GetConfigVariable;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use actix_web::{test, App, web};
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, GetTombstones, get_tombstones)]
+    async fn get_tombstones(org_id: Option<i32>, since: NaiveDateTime, after_id: i32, limit: i64) -> Result<Vec<Tombstone>, NanoServiceError> {
+        assert_eq!(org_id, Some(1));
+        assert_eq!(since.to_string(), "2025-01-01 09:00:00");
+        assert_eq!(after_id, 7);
+        assert_eq!(limit, 11);
+        Ok(vec![Tombstone {
+            id: 8,
+            entity_type: TombstoneEntity::User,
+            entity_id: 3,
+            deleted_at: since,
+        }])
+    }
+
+    #[tokio::test]
+    async fn test_get_deletions() {
+        let app = test::init_service(App::new().route(
+            "/api/sync/deletions",
+            web::get().to(get_deletions::<MockPostgres, MockConfig, PassAuthSessionCheckMock>)
+        )).await;
+        let agent = "some-agent".to_string();
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::new(agent.clone(), 1, UserRole::Worker);
+        let req = test::TestRequest::get()
+            .uri("/api/sync/deletions?since=2025-01-01T09:00:00&after_id=7&limit=10")
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, agent))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["deletions"][0]["entity_type"], "user");
+        assert_eq!(body["deletions"][0]["entity_id"], 3);
+        assert!(body["next_after_id"].is_null());
+    }
+}
@@ -0,0 +1,17 @@
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, get};
+mod deletions;
+mod changes;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+/// Registered as single routes as sync clients read users as well as to-do items.
+pub fn sync_factory(app: &mut ServiceConfig) {
+    app.route("/api/sync/deletions", get().to(
+        deletions::get_deletions::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/sync/deletions?since={datetime}&after_id={id}&limit={limit}.
+    );
+    app.route("/api/sync/changes", get().to(
+        changes::get_changes::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/sync/changes?since={cursor}&limit={limit}.
+    );
+}
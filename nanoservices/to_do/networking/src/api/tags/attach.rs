@@ -0,0 +1,93 @@
+use dal::tags::tx_definitions::{AttachTag, DetachTag};
+use to_do_core::api::tags::attach::{attach_tag as attach_tag_core, detach_tag as detach_tag_core};
+use serde::Deserialize;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+
+/// Schema for attaching or detaching a tag
+///
+/// # Fields
+/// * `todo_id` - The ID of the to-do item.
+/// * `tag_id` - The ID of the tag.
+#[derive(Deserialize)]
+pub struct TodoTagSchema {
+    pub todo_id: i32,
+    pub tag_id: i32
+}
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[AttachTag])]
+pub async fn attach_tag(body: Json<TodoTagSchema>) {
+    let link = attach_tag_core::<X>(body.todo_id, body.tag_id).await?;
+    Ok(HttpResponse::Created().json(link))
+}
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[DetachTag])]
+pub async fn detach_tag(body: Json<TodoTagSchema>) {
+    match detach_tag_core::<X>(body.todo_id, body.tag_id).await? {
+        true => Ok(HttpResponse::Ok().finish()),
+        false => Ok(HttpResponse::NotFound().finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::UserRole;
+    use kernel::tags::TodoTag;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::AdminRoleCheck;
+    use utils::send_test_request;
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, AttachTag, attach_tag)]
+    async fn attach_tag(todo_id: i32, tag_id: i32) -> Result<TodoTag, NanoServiceError> {
+        Ok(TodoTag { id: 1, todo_id, tag_id })
+    }
+
+    #[impl_transaction(MockPostgres, DetachTag, detach_tag)]
+    async fn detach_tag(_todo_id: i32, tag_id: i32) -> Result<bool, NanoServiceError> {
+        Ok(tag_id == 2)
+    }
+
+    #[tokio::test]
+    async fn test_attach_tag() {
+        send_test_request!(
+            POST,
+            "/attach",
+            serde_json::json!({"todo_id": 1, "tag_id": 2}),
+            AdminRoleCheck,
+            UserRole::Admin,
+            1,
+            attach_tag,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 201);
+    }
+
+    #[tokio::test]
+    async fn test_detach_missing_tag() {
+        send_test_request!(
+            POST,
+            "/detach",
+            serde_json::json!({"todo_id": 1, "tag_id": 3}),
+            AdminRoleCheck,
+            UserRole::Admin,
+            1,
+            detach_tag,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 404);
+    }
+}
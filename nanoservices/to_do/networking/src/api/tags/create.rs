@@ -0,0 +1,53 @@
+use dal::tags::tx_definitions::CreateTag;
+use kernel::tags::NewTag;
+use to_do_core::api::tags::create::create_tag as create_tag_core;
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Json
+};
+
+#[api_endpoint(token=AdminRoleCheck, db_traits=[CreateTag])]
+pub async fn create_tag(new_tag: Json<NewTag>) {
+    let tag = create_tag_core::<X>(new_tag.into_inner()).await?;
+    Ok(HttpResponse::Created().json(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::users::UserRole;
+    use kernel::tags::Tag;
+    use dal_tx_impl::impl_transaction;
+    use utils::errors::NanoServiceError;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use kernel::token::checks::AdminRoleCheck;
+    use utils::send_test_request;
+
+    #[tokio::test]
+    async fn test_create_tag() {
+        struct MockPostgres;
+
+        test_support::confirmed_users!(MockPostgres);
+
+        #[impl_transaction(MockPostgres, CreateTag, create_tag)]
+        async fn create_tag(tag: NewTag) -> Result<Tag, NanoServiceError> {
+            assert_eq!(tag.name, "urgent");
+            Ok(Tag { id: 1, name: tag.name })
+        }
+
+        send_test_request!(
+            POST,
+            "/create",
+            serde_json::json!({"name": "Urgent"}),
+            AdminRoleCheck,
+            UserRole::Admin,
+            1,
+            create_tag,
+            MockPostgres, MockConfig, PassAuthSessionCheckMock
+        );
+
+        let resp = send_request().await;
+        assert_eq!(resp.status(), 201);
+    }
+}
@@ -0,0 +1,19 @@
+use dal::tags::tx_definitions::{GetTags, GetTagsForToDoItem};
+use to_do_core::api::tags::get::{get_tags as get_tags_core, get_tags_for_to_do_item as get_tags_for_to_do_item_core};
+use utils::api_endpoint;
+use actix_web::{
+    HttpResponse,
+    web::Path
+};
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetTags])]
+pub async fn get_tags() {
+    let tags = get_tags_core::<X>().await?;
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetTagsForToDoItem])]
+pub async fn get_tags_for_to_do_item(path: Path<i32>) {
+    let tags = get_tags_for_to_do_item_core::<X>(path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(tags))
+}
@@ -0,0 +1,29 @@
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, post, get};
+mod create;
+mod attach;
+mod get;
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn tags_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v1/tags") // Namespace for tag-related API routes.
+        .route("create", post().to(
+            create::create_tag::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/tags/create.
+        )
+        .route("attach", post().to(
+            attach::attach_tag::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/tags/attach.
+        )
+        .route("detach", post().to(
+            attach::detach_tag::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // POST /api/todo/v1/tags/detach.
+        )
+        .route("get-all", get().to(
+            get::get_tags::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/tags/get-all.
+        )
+        .route("get-for-item/{todo_id}", get().to(
+            get::get_tags_for_to_do_item::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v1/tags/get-for-item/{todo_id}.
+        )
+    );
+}
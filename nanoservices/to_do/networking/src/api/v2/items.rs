@@ -0,0 +1,108 @@
+//! Networking layer for paging through the to-do items assigned to the caller.
+use actix_web::{HttpResponse, web::Query};
+use dal::to_do_items::tx_definitions::GetToDoItemsForUser;
+use dal::tags::tx_definitions::GetToDoItemsForUserByTag;
+use kernel::fields::{FieldSelection, FieldsQuery};
+use kernel::pagination::PageQuery;
+use kernel::to_do_items::TODO_FIELDS;
+use to_do_core::api::basic_actions::get_for_user::get_to_do_items_page;
+use utils::api_endpoint;
+use crate::api::basic_actions::get_for_user::ToDoItemFilter;
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[GetToDoItemsForUser, GetToDoItemsForUserByTag])]
+pub async fn get_to_do_items(filter: Query<ToDoItemFilter>, page: Query<PageQuery>, fields: Query<FieldsQuery>) {
+    let selection = FieldSelection::parse(&fields, &TODO_FIELDS)?;
+    let items = get_to_do_items_page::<X>(jwt.user_id, jwt.org_id, filter.into_inner().tag, &page).await?;
+    Ok(HttpResponse::Ok().json(selection.select(&items, &["items"])?))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App, web};
+    use chrono::Utc;
+    use dal_tx_impl::impl_transaction;
+    use kernel::to_do_items::{Todo, TodoPriority, TodoStatus};
+    use kernel::users::UserRole;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use utils::config::GetConfigVariable;
+    use utils::errors::NanoServiceError;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    fn todo(id: i32, user_id: i32) -> Todo {
+        Todo {
+            id,
+            name: format!("Mock Task {}", id),
+            due_date: None,
+            assigned_by: 100,
+            assigned_to: user_id,
+            description: None,
+            date_assigned: Utc::now().naive_utc(),
+            date_finished: None,
+            finished: false,
+            requires_review: false,
+            pending_review: false,
+            review_comment: None,
+            priority: TodoPriority::Medium,
+            status: TodoStatus::Backlog,
+        }
+    }
+
+    #[impl_transaction(MockPostgres, GetToDoItemsForUser, get_to_do_items_for_user)]
+    async fn get_to_do_items_for_user(user_id: i32, _org_id: i32) -> Result<Vec<Todo>, NanoServiceError> {
+        Ok((1..=3).map(|id| todo(id, user_id)).collect())
+    }
+
+    #[impl_transaction(MockPostgres, GetToDoItemsForUserByTag, get_to_do_items_for_user_by_tag)]
+    async fn get_to_do_items_for_user_by_tag(user_id: i32, _org_id: i32, tag: String) -> Result<Vec<Todo>, NanoServiceError> {
+        assert_eq!(tag, "urgent");
+        Ok(vec![todo(2, user_id)])
+    }
+
+    async fn send_request(uri: &str) -> serde_json::Value {
+        let app = test::init_service(App::new().route("/items", web::get().to(
+            get_to_do_items::<MockPostgres, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::new("some-agent".to_string(), 7, UserRole::Worker);
+        let req = test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri(uri)
+            .to_request();
+        test::call_and_read_body_json(&app, req).await
+    }
+
+    #[tokio::test]
+    async fn test_get_to_do_items() {
+        let page = send_request("/items?limit=2").await;
+        assert_eq!(page["items"].as_array().unwrap().len(), 2);
+        assert_eq!(page["total"], 3);
+        assert!(page["next_cursor"].is_string());
+
+        let page = send_request("/items?tag=urgent").await;
+        assert_eq!(page["items"][0]["id"], 2);
+        assert!(page["next_cursor"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_get_to_do_items_fields() {
+        let page = send_request("/items?limit=2&fields=id,name").await;
+        assert_eq!(page["items"][1], serde_json::json!({"id": 2, "name": "Mock Task 2"}));
+        assert_eq!(page["total"], 3);
+    }
+}
@@ -0,0 +1,27 @@
+//! Defines the v2 list endpoints of the to-do service.
+//!
+//! # Overview
+//! These routes live under `/api/todo/v2`. Every list comes back in the `kernel::pagination::Page`
+//! envelope, `{items, next_cursor, total?}`, and takes `cursor` and `limit` in the query string.
+//! The v1 routes keep returning bare arrays for the clients built against them. Search is left on
+//! v1 as it returns the best matches rather than a list to page through.
+pub mod items;
+pub mod notifications;
+
+use dal::connections::sqlx_postgres::SqlxPostGresDescriptor;
+use utils::secrets::SecretsConfig;
+use actix_web::web::{ServiceConfig, scope, get};
+use kernel::token::session_cache::engine_replicated::AuthCacheSessionEngineReplicated;
+
+
+pub fn v2_factory(app: &mut ServiceConfig) {
+    app.service(
+        scope("/api/todo/v2") // Namespace for paginated list routes.
+        .route("items", get().to(
+            items::get_to_do_items::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v2/items?tag={tag}&cursor={cursor}&limit={limit}.
+        )
+        .route("notifications", get().to(
+            notifications::list_notifications::<SqlxPostGresDescriptor, SecretsConfig, AuthCacheSessionEngineReplicated<SecretsConfig>>) // GET /api/todo/v2/notifications?cursor={cursor}&limit={limit}.
+        )
+    );
+}
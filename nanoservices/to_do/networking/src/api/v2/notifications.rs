@@ -0,0 +1,61 @@
+//! Networking layer for paging through the notifications waiting to be sent to the caller.
+use actix_web::{HttpResponse, web::Query};
+use dal::notifications::tx_definitions::ListPendingNotifications;
+use kernel::pagination::PageQuery;
+use to_do_core::api::notifications::list::list_pending_notifications;
+use utils::api_endpoint;
+
+
+#[api_endpoint(token=NoRoleCheck, db_traits=[ListPendingNotifications])]
+pub async fn list_notifications(page: Query<PageQuery>) {
+    let notifications = list_pending_notifications::<X>(jwt.user_id, &page).await?;
+    Ok(HttpResponse::Ok().json(notifications))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App, web};
+    use dal_tx_impl::impl_transaction;
+    use kernel::notifications::PendingNotification;
+    use kernel::users::UserRole;
+    use kernel::token::checks::NoRoleCheck;
+    use kernel::token::token::HeaderToken;
+    use kernel::token::session_cache::engine_mock::PassAuthSessionCheckMock;
+    use utils::config::GetConfigVariable;
+    use utils::errors::NanoServiceError;
+
+    struct MockConfig;
+
+    impl GetConfigVariable for MockConfig {
+        fn get_config_variable(_key: String) -> Result<String, NanoServiceError> {
+            Ok("secret".to_string())
+        }
+    }
+
+    struct MockPostgres;
+
+    test_support::confirmed_users!(MockPostgres);
+
+    #[impl_transaction(MockPostgres, ListPendingNotifications, list_pending_notifications)]
+    async fn list_pending_notifications(user_id: i32, after_id: Option<i32>, limit: i64) -> Result<Vec<PendingNotification>, NanoServiceError> {
+        assert_eq!((user_id, after_id, limit), (7, None, 6));
+        Ok(vec![])
+    }
+
+    #[tokio::test]
+    async fn test_list_notifications() {
+        let app = test::init_service(App::new().route("/notifications", web::get().to(
+            list_notifications::<MockPostgres, MockConfig, PassAuthSessionCheckMock>
+        ))).await;
+        let jwt: HeaderToken<MockConfig, NoRoleCheck> = HeaderToken::new("some-agent".to_string(), 7, UserRole::Worker);
+        let req = test::TestRequest::get()
+            .insert_header(("token", jwt.encode().unwrap()))
+            .insert_header((actix_web::http::header::USER_AGENT, "some-agent"))
+            .uri("/notifications?limit=5")
+            .to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page, serde_json::json!({"items": [], "next_cursor": null}));
+    }
+}
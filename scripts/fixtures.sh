@@ -0,0 +1,15 @@
+#!/usr/bin/env bash
+# Dumps or restores the integration test dataset.
+#
+# usage:
+#   scripts/fixtures.sh restore                 # loads dal/dal/fixtures/canonical.sql
+#   scripts/fixtures.sh restore <snapshot.sql>  # loads a saved snapshot
+#   scripts/fixtures.sh dump <snapshot.sql>     # saves the current state
+
+# navigate to directory
+SCRIPTPATH="$( cd "$(dirname "$0")" ; pwd -P )"
+cd $SCRIPTPATH
+cd ..
+
+export $(cat .env | xargs)
+cargo run -q -p dal --bin fixtures -- "$@"
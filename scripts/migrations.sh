@@ -0,0 +1,15 @@
+#!/usr/bin/env bash
+# Runs, reverts or reports the database migrations.
+#
+# usage:
+#   scripts/migrations.sh status             # lists every migration and whether it has been applied
+#   scripts/migrations.sh run                # applies the pending migrations
+#   scripts/migrations.sh revert <version>   # reverts the migrations newer than <version>
+
+# navigate to directory
+SCRIPTPATH="$( cd "$(dirname "$0")" ; pwd -P )"
+cd $SCRIPTPATH
+cd ..
+
+export $(cat .env | xargs)
+cargo run -q -p dal --bin migrations -- "$@"